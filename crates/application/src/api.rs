@@ -7,6 +7,7 @@ use std::{
 use async_trait::async_trait;
 use bytes::Bytes;
 use common::{
+    bootstrap_model::schema::SchemaState,
     components::{
         CanonicalizedComponentFunctionPath,
         ComponentId,
@@ -15,11 +16,14 @@ use common::{
     },
     http::ResolvedHostname,
     runtime::Runtime,
+    schemas::DatabaseSchema,
     types::{
         AllowedVisibility,
         ConvexOrigin,
         FunctionCaller,
+        HttpActionRoute,
         RepeatableTimestamp,
+        UdfType,
     },
     RequestId,
 };
@@ -27,12 +31,14 @@ use database::{
     Database,
     LogReader,
     ReadSet,
+    SchemaModel,
     Subscription,
     Token,
 };
 use file_storage::{
     FileRangeStream,
     FileStream,
+    FileValidators,
 };
 use futures::{
     future::BoxFuture,
@@ -45,12 +51,22 @@ use headers::{
 };
 use keybroker::Identity;
 use model::{
+    admin_key_audit_log::types::AdminKeyUse,
     file_storage::FileStorageId,
+    modules::{
+        function_validators::{
+            ArgsValidator,
+            ReturnsValidator,
+        },
+        module_versions::Visibility,
+        ModuleModel,
+    },
     session_requests::types::SessionRequestIdentifier,
 };
 use serde_json::Value as JsonValue;
 use sync_types::{
     AuthenticationToken,
+    CanonicalizedUdfPath,
     SerializedQueryJournal,
     Timestamp,
 };
@@ -61,6 +77,7 @@ use udf::{
 use value::{
     sha256::Sha256Digest,
     DeveloperDocumentId,
+    TableNamespace,
 };
 
 use crate::{
@@ -98,6 +115,13 @@ pub trait ApplicationApi: Send + Sync {
         auth_token: AuthenticationToken,
     ) -> anyhow::Result<Identity>;
 
+    /// Rejects a user identity whose token has been revoked since it was
+    /// authenticated, for sessions that hold on to their identity.
+    fn check_token_revocation(&self, identity: &Identity) -> anyhow::Result<()>;
+
+    /// Appends a use of an admin or deploy key to the audit log.
+    async fn record_admin_key_use(&self, key_use: AdminKeyUse) -> anyhow::Result<()>;
+
     /// Execute a public query on the root app. This method is used by the sync
     /// worker and HTTP API for the majority of traffic as the main entry point
     /// for queries.
@@ -244,6 +268,14 @@ pub trait ApplicationApi: Send + Sync {
         file_storage_id: FileStorageId,
     ) -> anyhow::Result<FileStream>;
 
+    async fn get_file_validators(
+        &self,
+        host: &ResolvedHostname,
+        request_id: RequestId,
+        component: ComponentId,
+        file_storage_id: FileStorageId,
+    ) -> anyhow::Result<FileValidators>;
+
     // Returns a fallible subscription client. The implementation is not required to
     // recover from transient errors with the underlying connection or stream. The
     // client is responsible to Drop the client and create a new one on any system
@@ -255,6 +287,27 @@ pub trait ApplicationApi: Send + Sync {
         &self,
         host: &ResolvedHostname,
     ) -> anyhow::Result<Box<dyn SubscriptionClient>>;
+
+    /// Returns the public functions and the active schema of the root app, for
+    /// describing the deployment's API to clients.
+    async fn public_api_metadata(
+        &self,
+        host: &ResolvedHostname,
+        request_id: RequestId,
+    ) -> anyhow::Result<PublicApiMetadata>;
+}
+
+pub struct PublicApiMetadata {
+    pub functions: Vec<PublicFunctionMetadata>,
+    pub schema: Option<DatabaseSchema>,
+    pub http_routes: Vec<HttpActionRoute>,
+}
+
+pub struct PublicFunctionMetadata {
+    pub path: CanonicalizedUdfPath,
+    pub udf_type: UdfType,
+    pub args: ArgsValidator,
+    pub returns: ReturnsValidator,
 }
 
 // Implements ApplicationApi via Application.
@@ -270,6 +323,17 @@ impl<RT: Runtime> ApplicationApi for Application<RT> {
         self.authenticate(auth_token, validate_time).await
     }
 
+    fn check_token_revocation(&self, identity: &Identity) -> anyhow::Result<()> {
+        match identity {
+            Identity::User(user) => self.app_auth().check_token_revocation(user),
+            _ => Ok(()),
+        }
+    }
+
+    async fn record_admin_key_use(&self, key_use: AdminKeyUse) -> anyhow::Result<()> {
+        self.record_admin_key_use(key_use).await
+    }
+
     async fn execute_public_query(
         &self,
         _host: &ResolvedHostname,
@@ -527,6 +591,16 @@ impl<RT: Runtime> ApplicationApi for Application<RT> {
         self.get_file(component, file_storage_id).await
     }
 
+    async fn get_file_validators(
+        &self,
+        _host: &ResolvedHostname,
+        _request_id: RequestId,
+        component: ComponentId,
+        file_storage_id: FileStorageId,
+    ) -> anyhow::Result<FileValidators> {
+        self.get_file_validators(component, file_storage_id).await
+    }
+
     async fn subscription_client(
         &self,
         _host: &ResolvedHostname,
@@ -535,6 +609,47 @@ impl<RT: Runtime> ApplicationApi for Application<RT> {
             database: self.database.clone(),
         }))
     }
+
+    async fn public_api_metadata(
+        &self,
+        _host: &ResolvedHostname,
+        _request_id: RequestId,
+    ) -> anyhow::Result<PublicApiMetadata> {
+        let mut tx = self.begin(Identity::system()).await?;
+        let modules = ModuleModel::new(&mut tx)
+            .get_application_metadata(ComponentId::Root)
+            .await?;
+        let mut functions = vec![];
+        let mut http_routes = vec![];
+        for module in modules {
+            let Some(analyze_result) = &module.analyze_result else {
+                continue;
+            };
+            if let Some(routes) = &analyze_result.http_routes {
+                http_routes.extend(routes.iter().map(|route| route.route.clone()));
+            }
+            for function in analyze_result.functions.iter() {
+                if function.visibility != Some(Visibility::Public) {
+                    continue;
+                }
+                functions.push(PublicFunctionMetadata {
+                    path: CanonicalizedUdfPath::new(module.path.clone(), function.name.clone()),
+                    udf_type: function.udf_type,
+                    args: function.args()?,
+                    returns: function.returns()?,
+                });
+            }
+        }
+        let schema = SchemaModel::new(&mut tx, TableNamespace::root_component())
+            .get_by_state(SchemaState::Active)
+            .await?
+            .map(|(_id, schema)| schema);
+        Ok(PublicApiMetadata {
+            functions,
+            schema,
+            http_routes,
+        })
+    }
 }
 
 #[async_trait]
@@ -42,6 +42,7 @@ use common::{
         APPLICATION_MAX_CONCURRENT_NODE_ACTIONS,
         APPLICATION_MAX_CONCURRENT_QUERIES,
         APPLICATION_MAX_CONCURRENT_V8_ACTIONS,
+        IDEMPOTENCY_KEY_WINDOW,
         ISOLATE_MAX_USER_HEAP_SIZE,
         UDF_EXECUTOR_OCC_INITIAL_BACKOFF,
         UDF_EXECUTOR_OCC_MAX_BACKOFF,
@@ -67,6 +68,7 @@ use common::{
         FunctionCaller,
         ModuleEnvironment,
         NodeDependency,
+        NodeVersion,
         Timestamp,
         UdfType,
     },
@@ -85,6 +87,7 @@ use errors::{
 };
 use file_storage::TransactionalFileStorage;
 use function_runner::{
+    concurrency_limiter::FunctionConcurrencyLimiter,
     server::{
         FunctionMetadata,
         HttpActionMetadata,
@@ -94,6 +97,7 @@ use function_runner::{
     FunctionWrites,
 };
 use futures::{
+    future::try_join_all,
     select_biased,
     FutureExt,
 };
@@ -109,6 +113,7 @@ use model::{
         module_loader::ModuleLoader,
         types::ModuleConfig,
     },
+    dependency_layers::types::DependencyLayer,
     environment_variables::{
         types::{
             EnvVarName,
@@ -132,7 +137,10 @@ use model::{
         },
         ModuleModel,
     },
-    scheduled_jobs::VirtualSchedulerModel,
+    scheduled_jobs::{
+        types::ScheduleOptions,
+        VirtualSchedulerModel,
+    },
     session_requests::{
         types::{
             SessionRequestIdentifier,
@@ -167,6 +175,7 @@ use udf::{
     },
     ActionOutcome,
     EvaluateAppDefinitionsResult,
+    ExecutionRecording,
     FunctionOutcome,
     FunctionResult,
     HttpActionOutcome,
@@ -297,6 +306,7 @@ impl<RT: Runtime> FunctionRouter<RT> {
                 Some(FunctionMetadata {
                     journal,
                     path_and_args,
+                    replay: None,
                 }),
                 None,
             )
@@ -306,6 +316,66 @@ impl<RT: Runtime> FunctionRouter<RT> {
         Ok((tx, outcome))
     }
 
+    #[fastrace::trace]
+    pub(crate) async fn replay_query_or_mutation(
+        &self,
+        tx: Transaction<RT>,
+        path_and_args: ValidatedPathAndArgs,
+        recording: ExecutionRecording,
+        context: ExecutionContext,
+    ) -> anyhow::Result<UdfOutcome> {
+        let udf_type = recording.udf_type;
+        anyhow::ensure!(udf_type == UdfType::Query || udf_type == UdfType::Mutation);
+        let (_, outcome) = self
+            .function_runner_execute(
+                tx,
+                udf_type,
+                context,
+                None,
+                Some(FunctionMetadata {
+                    journal: QueryJournal::new(),
+                    path_and_args,
+                    replay: Some(recording),
+                }),
+                None,
+            )
+            .await?;
+        let (FunctionOutcome::Query(outcome) | FunctionOutcome::Mutation(outcome)) = outcome else {
+            anyhow::bail!("Replaying a {udf_type} returned an invalid outcome")
+        };
+        Ok(outcome)
+    }
+
+    #[fastrace::trace]
+    pub(crate) async fn replay_action(
+        &self,
+        tx: Transaction<RT>,
+        path_and_args: ValidatedPathAndArgs,
+        recording: ExecutionRecording,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+        context: ExecutionContext,
+    ) -> anyhow::Result<ActionOutcome> {
+        anyhow::ensure!(recording.udf_type == UdfType::Action);
+        let (_, outcome) = self
+            .function_runner_execute(
+                tx,
+                UdfType::Action,
+                context,
+                Some(log_line_sender),
+                Some(FunctionMetadata {
+                    journal: QueryJournal::new(),
+                    path_and_args,
+                    replay: Some(recording),
+                }),
+                None,
+            )
+            .await?;
+        let FunctionOutcome::Action(outcome) = outcome else {
+            anyhow::bail!("Replaying an action returned an invalid outcome")
+        };
+        Ok(outcome)
+    }
+
     #[fastrace::trace]
     pub(crate) async fn execute_action(
         &self,
@@ -323,6 +393,7 @@ impl<RT: Runtime> FunctionRouter<RT> {
                 Some(FunctionMetadata {
                     journal: QueryJournal::new(),
                     path_and_args,
+                    replay: None,
                 }),
                 None,
             )
@@ -579,6 +650,9 @@ pub struct ApplicationFunctionRunner<RT: Runtime> {
     cache_manager: CacheManager<RT>,
     system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
     node_action_limiter: Limiter,
+    // Per-function limits for Node actions. Isolate functions are limited in
+    // the function runner.
+    node_concurrency_limiter: FunctionConcurrencyLimiter<RT>,
 }
 
 impl<RT: Runtime> ApplicationFunctionRunner<RT> {
@@ -608,6 +682,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
             function_log.clone(),
             cache,
         );
+        let node_concurrency_limiter = FunctionConcurrencyLimiter::from_knobs(runtime.clone());
 
         Self {
             runtime,
@@ -626,6 +701,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                 UdfType::Action,
                 *APPLICATION_MAX_CONCURRENT_NODE_ACTIONS,
             ),
+            node_concurrency_limiter,
         }
     }
 
@@ -687,12 +763,14 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
             _ => anyhow::bail!("Received non-query outcome for query"),
         };
         let stats = tx.take_stats();
+        let indexes_read = tx.user_indexes_read();
 
         let result = outcome.result.clone();
         let log_lines = outcome.log_lines.clone();
         self.function_log.log_query(
             &outcome,
             stats,
+            indexes_read,
             false,
             start.elapsed(),
             caller,
@@ -703,6 +781,62 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
         Ok((result, log_lines))
     }
 
+    /// Re-executes a recorded query, mutation or action. Its syscalls are
+    /// answered from the recording, so it doesn't read the database, and any
+    /// writes a replayed mutation makes are discarded. A replayed action's
+    /// fetches and other async operations also complete from the recording
+    /// instead of running.
+    #[fastrace::trace]
+    pub async fn replay_function(
+        &self,
+        request_id: RequestId,
+        mut tx: Transaction<RT>,
+        recording: ExecutionRecording,
+        caller: FunctionCaller,
+    ) -> anyhow::Result<(Result<JsonPackedValue, JsError>, LogLines)> {
+        if !(tx.identity().is_admin() || tx.identity().is_system()) {
+            anyhow::bail!(unauthorized_error("replay_function"));
+        }
+        let validate_result = ValidatedPathAndArgs::new(
+            caller.allowed_visibility(),
+            &mut tx,
+            PublicFunctionPath::Component(recording.path.clone()),
+            recording.arguments.clone(),
+            recording.udf_type,
+        )
+        .await?;
+        let path_and_args = match validate_result {
+            Ok(path_and_args) => path_and_args,
+            Err(js_err) => return Ok((Err(js_err), LogLines::default())),
+        };
+        let context = ExecutionContext::new(request_id, &caller);
+        if recording.udf_type == UdfType::Action {
+            let module = ModuleModel::new(&mut tx)
+                .get_metadata_for_function_by_id(path_and_args.path())
+                .await?
+                .context("Missing a valid module")?;
+            if module.environment != ModuleEnvironment::Isolate {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "CannotReplayNodeAction",
+                    "Only actions that run in the default Convex runtime can be replayed."
+                ));
+            }
+            let (log_line_sender, log_line_receiver) = mpsc::unbounded_channel();
+            let outcome_future = self
+                .isolate_functions
+                .replay_action(tx, path_and_args, recording, log_line_sender, context)
+                .boxed();
+            let (outcome, log_lines) =
+                run_function_and_collect_log_lines(outcome_future, log_line_receiver, |_| {}).await;
+            return Ok((outcome?.result, log_lines));
+        }
+        let outcome = self
+            .isolate_functions
+            .replay_query_or_mutation(tx, path_and_args, recording, context)
+            .await?;
+        Ok((outcome.result, outcome.log_lines))
+    }
+
     /// Runs a mutations and retries on OCC errors.
     #[fastrace::trace]
     pub async fn retry_mutation(
@@ -817,6 +951,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                 .await?;
 
             let stats = tx.take_stats();
+            let indexes_read = tx.user_indexes_read();
             let execution_time = start.elapsed();
             let log_lines = outcome.log_lines.clone();
             let value = match outcome.result {
@@ -828,6 +963,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     self.function_log.log_mutation(
                         outcome.clone(),
                         stats,
+                        indexes_read,
                         execution_time,
                         caller,
                         usage_tracker,
@@ -845,16 +981,21 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
             // Attempt to commit the transaction and log an error if commit failed,
             // even if it was an OCC error. We may decide later to suppress OCC
             // errors from the log.
+            let commit_start = self.runtime.monotonic_now();
             let result = match self
                 .database
                 .commit_with_write_source(tx, udf_path_string.clone())
                 .await
             {
-                Ok(ts) => Ok(MutationReturn {
-                    value,
-                    log_lines,
-                    ts,
-                }),
+                Ok(ts) => {
+                    self.function_log
+                        .log_mutation_commit_time(&outcome.path, commit_start.elapsed());
+                    Ok(MutationReturn {
+                        value,
+                        log_lines,
+                        ts,
+                    })
+                },
                 Err(e) => {
                     if e.is_deterministic_user_error() {
                         let js_error = JsError::from_error(e);
@@ -878,6 +1019,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                             self.function_log.log_mutation_occ_error(
                                 outcome,
                                 stats,
+                                indexes_read,
                                 execution_time,
                                 caller.clone(),
                                 usage_tracker,
@@ -899,6 +1041,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                             self.function_log.log_mutation_occ_error(
                                 outcome,
                                 stats,
+                                indexes_read,
                                 execution_time,
                                 caller,
                                 usage_tracker,
@@ -930,6 +1073,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
             self.function_log.log_mutation(
                 outcome.clone(),
                 stats,
+                indexes_read,
                 execution_time,
                 caller,
                 usage_tracker,
@@ -1276,6 +1420,9 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     }
                     Ok(source_maps)
                 };
+                // Hold the per-function concurrency slot (if any) until the
+                // action finishes executing.
+                let _concurrency_permit = self.node_concurrency_limiter.acquire(&path).await?;
                 let _request_guard = self
                     .node_action_limiter
                     .acquire_permit_with_timeout(&self.runtime)
@@ -1290,6 +1437,24 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     EnvironmentVariablesModel::new(&mut tx).get_all().await?;
                 // Insert special environment variables if not already provided by user
                 environment_variables.extend(self.system_env_vars.clone());
+                // Secrets are decrypted right before the action runs, like in
+                // the function runner.
+                for (name, encrypted_value) in EnvironmentVariablesModel::new(&mut tx)
+                    .get_current_secrets()
+                    .await?
+                {
+                    environment_variables
+                        .insert(name, self.key_broker.decrypt_env_secret(&encrypted_value)?);
+                }
+                let function_path = CanonicalizedComponentFunctionPath {
+                    component: tx.must_component_path(component)?,
+                    udf_path: path.udf_path.clone(),
+                };
+                environment_variables.extend(
+                    EnvironmentVariablesModel::new(&mut tx)
+                        .get_overrides_for_function(&function_path)
+                        .await?,
+                );
 
                 // Fetch source and external_deps presigned URI first
                 let source_uri_future = self
@@ -1320,6 +1485,10 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     (source_uri_future.await?, None)
                 };
 
+                let layers = self
+                    .dependency_layer_packages(source_package.dependency_layers)
+                    .await?;
+
                 let udf_server_version = path_and_args.npm_version().clone();
                 let request = ExecuteRequest {
                     path_and_args,
@@ -1330,6 +1499,8 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                             sha256: source_package.sha256,
                         },
                         external_deps: external_deps_package,
+                        node_version: source_package.node_version.unwrap_or_default(),
+                        layers,
                     },
                     source_package_id,
                     user_identity: tx.user_identity(),
@@ -1435,9 +1606,29 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
     }
 
     #[fastrace::trace]
+    /// Signs URLs for the dependency layers a source package was pushed with.
+    async fn dependency_layer_packages(
+        &self,
+        layers: Vec<DependencyLayer>,
+    ) -> anyhow::Result<Vec<node_executor::Package>> {
+        try_join_all(layers.into_iter().map(|layer| async move {
+            let uri = self
+                .modules_storage
+                .signed_url(layer.storage_key.clone(), Duration::from_secs(60))
+                .await?;
+            anyhow::Ok(node_executor::Package {
+                uri,
+                key: layer.storage_key,
+                sha256: layer.sha256,
+            })
+        }))
+        .await
+    }
+
     pub async fn build_deps(
         &self,
         deps: Vec<NodeDependency>,
+        node_version: Option<NodeVersion>,
     ) -> anyhow::Result<Result<ExternalDepsPackage, JsError>> {
         let (object_key, upload_uri) = self
             .modules_storage
@@ -1446,6 +1637,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
         let request = BuildDepsRequest {
             deps: deps.clone(),
             upload_url: upload_uri,
+            node_version: node_version.unwrap_or_default(),
         };
         let build_deps_res = self.node_actions.build_deps(request).await?;
         Ok(
@@ -1454,6 +1646,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                 sha256: digest,
                 deps,
                 package_size,
+                node_version,
             }),
         )
     }
@@ -1566,6 +1759,10 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     (source_uri_future.await?, None)
                 };
 
+            let layers = self
+                .dependency_layer_packages(source_package.dependency_layers)
+                .await?;
+
             let request = AnalyzeRequest {
                 source_package: node_executor::SourcePackage {
                     bundled_source: node_executor::Package {
@@ -1574,6 +1771,8 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                         sha256: source_package.sha256,
                     },
                     external_deps: external_deps_package,
+                    node_version: source_package.node_version.unwrap_or_default(),
+                    layers,
                 },
                 environment_variables,
             };
@@ -1687,6 +1886,16 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
         self.node_actions.enable()
     }
 
+    /// Forwards log lines posted by a running Node action to the log
+    /// pipeline of that action.
+    pub fn stream_node_action_log_lines(
+        &self,
+        log_stream_id: &str,
+        log_lines: Vec<LogLine>,
+    ) -> anyhow::Result<()> {
+        self.node_actions.stream_log_lines(log_stream_id, log_lines)
+    }
+
     #[fastrace::trace]
     pub async fn run_query_at_ts(
         &self,
@@ -1772,22 +1981,39 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
         let Some(ref identifier) = mutation_identifier else {
             return Ok(None);
         };
-        let mutation_status = SessionRequestModel::new(tx)
+        let Some((ts, record)) = SessionRequestModel::new(tx)
             .get_session_request_record(identifier, Identity::system())
-            .await?;
-        let result = match mutation_status {
-            Some((ts, SessionRequestOutcome::Mutation { result, log_lines })) => {
-                tracing::info!("Mutation already executed so skipping {:?}", identifier);
-                log_mutation_already_committed();
-                Ok(MutationReturn {
-                    value: result,
-                    log_lines,
-                    ts,
-                })
-            },
-            None => return Ok(None),
+            .await?
+        else {
+            return Ok(None);
         };
-        Ok(Some(result))
+        if let Some(ref args_hash) = identifier.args_hash {
+            // Results of mutations called with an `Idempotency-Key` are only
+            // replayed within the window, after which the key can be reused.
+            if let Ok(cutoff) = tx.begin_timestamp().sub(*IDEMPOTENCY_KEY_WINDOW)
+                && ts < *cutoff
+            {
+                SessionRequestModel::new(tx)
+                    .delete_session_request(record.id(), Identity::system())
+                    .await?;
+                return Ok(None);
+            }
+            if record.args_hash.as_ref() != Some(args_hash) {
+                anyhow::bail!(ErrorMetadata::unprocessable_entity(
+                    "IdempotencyKeyReused",
+                    "This Idempotency-Key was already used to call this function with different \
+                     arguments. Use a new key for each distinct request."
+                ));
+            }
+        }
+        let SessionRequestOutcome::Mutation { result, log_lines } = record.into_value().outcome;
+        tracing::info!("Mutation already executed so skipping {:?}", identifier);
+        log_mutation_already_committed();
+        Ok(Some(Ok(MutationReturn {
+            value: result,
+            log_lines,
+            ts,
+        })))
     }
 
     #[fastrace::trace]
@@ -1809,6 +2035,7 @@ impl<RT: Runtime> ApplicationFunctionRunner<RT> {
                     log_lines: outcome.log_lines.clone(),
                 },
                 identity: outcome.identity.clone(),
+                args_hash: identifier.args_hash.clone(),
             };
             SessionRequestModel::new(tx)
                 .record_session_request(record, Identity::system())
@@ -2010,6 +2237,7 @@ impl<RT: Runtime> ActionCallbacks for ApplicationFunctionRunner<RT> {
         udf_args: Vec<JsonValue>,
         scheduled_ts: UnixTimestamp,
         context: ExecutionContext,
+        options: ScheduleOptions,
     ) -> anyhow::Result<DeveloperDocumentId> {
         let (_ts, virtual_id, _stats) = self
             .database
@@ -2021,6 +2249,7 @@ impl<RT: Runtime> ActionCallbacks for ApplicationFunctionRunner<RT> {
                     let path = scheduled_path.clone();
                     let args = udf_args.clone();
                     let context = context.clone();
+                    let options = options.clone();
                     async move {
                         let (path, udf_args) = validate_schedule_args(
                             path,
@@ -2034,7 +2263,7 @@ impl<RT: Runtime> ActionCallbacks for ApplicationFunctionRunner<RT> {
                         .await?;
                         let virtual_id =
                             VirtualSchedulerModel::new(tx, scheduling_component.into())
-                                .schedule(path, udf_args, scheduled_ts, context)
+                                .schedule(path, udf_args, scheduled_ts, context, options)
                                 .await?;
                         Ok(virtual_id)
                     }
@@ -1,6 +1,9 @@
 use std::{
     cmp,
-    collections::BTreeMap,
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
     mem,
     sync::{
         atomic::{
@@ -140,6 +143,9 @@ pub struct RequestedCacheKey {
 impl RequestedCacheKey {
     // In order from most specific to least specific.
     fn _possible_cache_keys(&self) -> Vec<StoredCacheKey> {
+        if !self.shares_results() {
+            return vec![self.precise_cache_key()];
+        }
         vec![
             self.precise_cache_key(),
             StoredCacheKey {
@@ -154,6 +160,12 @@ impl RequestedCacheKey {
         ]
     }
 
+    /// API keys can be limited to some tables, so their results can't be
+    /// shared with other identities even if the query didn't read `ctx.auth`.
+    fn shares_results(&self) -> bool {
+        !matches!(self.identity, IdentityCacheKey::ApiKey(_))
+    }
+
     fn precise_cache_key(&self) -> StoredCacheKey {
         StoredCacheKey {
             instance: self.instance,
@@ -184,7 +196,7 @@ impl RequestedCacheKey {
     }
 
     fn cache_key_after_execution(&self, outcome: &UdfOutcome) -> StoredCacheKey {
-        let identity = if outcome.observed_identity {
+        let identity = if outcome.observed_identity || !self.shares_results() {
             Some(self.identity.clone())
         } else {
             None
@@ -412,7 +424,7 @@ impl<RT: Runtime> CacheManager<RT> {
                 // We are executing ourselves.
                 CacheOp::Go { .. } => false,
             };
-            let (result, table_stats) = match self
+            let (result, table_stats, indexes_read) = match self
                 .perform_cache_op(&requested_key, &stored_key, op, usage_tracker.clone())
                 .await?
             {
@@ -455,6 +467,7 @@ impl<RT: Runtime> CacheManager<RT> {
             self.udf_execution.log_query(
                 &cache_result.outcome,
                 table_stats,
+                indexes_read,
                 is_cache_hit,
                 start.elapsed(),
                 caller,
@@ -484,7 +497,13 @@ impl<RT: Runtime> CacheManager<RT> {
         key: &StoredCacheKey,
         op: CacheOp<'_>,
         usage_tracker: FunctionUsageTracker,
-    ) -> anyhow::Result<Option<(CacheResult, BTreeMap<TableName, TableStats>)>> {
+    ) -> anyhow::Result<
+        Option<(
+            CacheResult,
+            BTreeMap<TableName, TableStats>,
+            BTreeSet<String>,
+        )>,
+    > {
         let pause_client = self.rt.pause_client();
         pause_client.wait("perform_cache_op").await;
         let r = match op {
@@ -495,7 +514,7 @@ impl<RT: Runtime> CacheManager<RT> {
                         key
                     )
                 }
-                (result, BTreeMap::new())
+                (result, BTreeMap::new(), BTreeSet::new())
             },
             CacheOp::Wait {
                 waiting_entry_id,
@@ -528,7 +547,7 @@ impl<RT: Runtime> CacheManager<RT> {
                         key
                     )
                 }
-                (result, BTreeMap::new())
+                (result, BTreeMap::new(), BTreeSet::new())
             },
             CacheOp::Go {
                 waiting_entry_id: _,
@@ -603,6 +622,7 @@ impl<RT: Runtime> CacheManager<RT> {
                 };
                 let ts = tx.begin_timestamp();
                 let table_stats = tx.take_stats();
+                let indexes_read = tx.user_indexes_read();
                 let token = tx.into_token()?;
                 let result = CacheResult {
                     outcome: Arc::new(query_outcome),
@@ -618,7 +638,7 @@ impl<RT: Runtime> CacheManager<RT> {
                     drop(sender);
                 }
                 log_perform_go(result.outcome.result.is_ok());
-                (result, table_stats)
+                (result, table_stats, indexes_read)
             },
         };
         Ok(Some(r))
@@ -60,7 +60,10 @@ use keybroker::Identity;
 use model::{
     backend_state::BackendStateModel,
     cron_jobs::{
-        next_ts::compute_next_ts,
+        next_ts::{
+            compute_jitter,
+            compute_next_ts,
+        },
         types::{
             CronJob,
             CronJobLogLines,
@@ -73,6 +76,13 @@ use model::{
         CRON_JOBS_TABLE,
     },
     modules::ModuleModel,
+    scheduled_jobs::{
+        pauses::{
+            SchedulerPauses,
+            SchedulerPausesModel,
+        },
+        SchedulerModel,
+    },
 };
 use sync_types::Timestamp;
 use tokio::sync::mpsc;
@@ -81,11 +91,13 @@ use value::{
     JsonPackedValue,
     ResolvedDocumentId,
     TableNamespace,
+    TabletId,
 };
 
 use crate::{
     application_function_runner::ApplicationFunctionRunner,
     function_log::FunctionExecutionLog,
+    scheduled_jobs::SchedulerDrain,
 };
 
 mod metrics;
@@ -107,6 +119,7 @@ pub struct CronJobExecutor<RT: Runtime> {
     database: Database<RT>,
     runner: Arc<ApplicationFunctionRunner<RT>>,
     function_log: FunctionExecutionLog<RT>,
+    drain: Arc<SchedulerDrain>,
 }
 
 impl<RT: Runtime> CronJobExecutor<RT> {
@@ -116,6 +129,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
         database: Database<RT>,
         runner: Arc<ApplicationFunctionRunner<RT>>,
         function_log: FunctionExecutionLog<RT>,
+        drain: Arc<SchedulerDrain>,
     ) -> impl Future<Output = ()> + Send {
         let executor = Self {
             rt,
@@ -123,6 +137,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
             database,
             runner,
             function_log,
+            drain,
         };
         async move {
             let mut backoff = Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF);
@@ -152,6 +167,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
             database,
             runner,
             function_log,
+            drain: SchedulerDrain::new(),
         }
     }
 
@@ -165,13 +181,17 @@ impl<RT: Runtime> CronJobExecutor<RT> {
             let mut tx = self.database.begin(Identity::Unknown).await?;
             let backend_state = BackendStateModel::new(&mut tx).get_backend_state().await?;
             let is_backend_stopped = backend_state.is_stopped();
+            let pauses = SchedulerPausesModel::new(&mut tx).load().await?;
 
-            next_job_ready_time = if is_backend_stopped {
+            next_job_ready_time = if is_backend_stopped
+                || pauses.is_all_paused()
+                || self.drain.is_draining()
+            {
                 None
             } else if running_job_ids.len() == *SCHEDULED_JOB_EXECUTION_PARALLELISM {
                 next_job_ready_time
             } else {
-                self.query_and_start_jobs(&mut tx, &mut running_job_ids, &job_finished_tx)
+                self.query_and_start_jobs(&mut tx, &pauses, &mut running_job_ids, &job_finished_tx)
                     .await?
             };
 
@@ -213,16 +233,31 @@ impl<RT: Runtime> CronJobExecutor<RT> {
     async fn query_and_start_jobs(
         &self,
         tx: &mut Transaction<RT>,
+        pauses: &SchedulerPauses,
         running_job_ids: &mut HashSet<ResolvedDocumentId>,
         job_finished_tx: &mpsc::Sender<ResolvedDocumentId>,
     ) -> anyhow::Result<Option<Timestamp>> {
         let now = self.rt.generate_timestamp()?;
+        let component_paths = Self::cron_component_paths(tx);
         let mut job_stream = self.stream_jobs_to_run(tx);
         while let Some(job) = job_stream.try_next().await? {
             let (job_id, job) = job.clone().into_id_and_value();
             if running_job_ids.contains(&job_id) {
                 continue;
             }
+            let is_paused = component_paths
+                .get(&job_id.tablet_id)
+                .is_some_and(|component| {
+                    pauses.is_paused(&CanonicalizedComponentFunctionPath {
+                        component: component.clone(),
+                        udf_path: job.cron_spec.udf_path.clone(),
+                    })
+                });
+            if is_paused {
+                // Don't wait for paused crons. Resuming them wakes us up, and
+                // then they run once to catch up.
+                continue;
+            }
             let next_ts = job.next_ts;
             // If we can't execute the job return the job's target timestamp. If we're
             // caught up, we can sleep until the timestamp. If we're behind and
@@ -231,6 +266,9 @@ impl<RT: Runtime> CronJobExecutor<RT> {
             if next_ts > now || running_job_ids.len() == *SCHEDULED_JOB_EXECUTION_PARALLELISM {
                 return Ok(Some(next_ts));
             }
+            let Some(execution) = self.drain.try_begin_execution() else {
+                return Ok(None);
+            };
             let root = get_sampled_span(
                 &self.instance_name,
                 "crons/execute_job",
@@ -247,6 +285,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
                             tracing::error!("Cron job receiver closed");
                         },
                         result = context.execute_job(job, job_id).fuse() => {
+                            drop(execution);
                             let _ = tx.send(result).await;
                         },
                     }
@@ -258,6 +297,27 @@ impl<RT: Runtime> CronJobExecutor<RT> {
         Ok(None)
     }
 
+    /// The component of each `_cron_jobs` table, whose crons run functions in
+    /// that component.
+    fn cron_component_paths(tx: &mut Transaction<RT>) -> BTreeMap<TabletId, ComponentPath> {
+        let cron_tables: Vec<_> = tx
+            .table_mapping()
+            .iter()
+            .filter(|(_, _, _, name)| **name == *CRON_JOBS_TABLE)
+            .map(|(tablet_id, namespace, ..)| (tablet_id, namespace))
+            .collect();
+        let mut component_paths = BTreeMap::new();
+        for (tablet_id, namespace) in cron_tables {
+            // Components that are still being pushed don't have a path yet.
+            if let Some(component_path) =
+                BootstrapComponentsModel::new(tx).get_component_path(namespace.into())
+            {
+                component_paths.insert(tablet_id, component_path);
+            }
+        }
+        component_paths
+    }
+
     #[try_stream(boxed, ok = ParsedDocument<CronJob>, error = anyhow::Error)]
     async fn stream_jobs_to_run<'a>(&'a self, tx: &'a mut Transaction<RT>) {
         let namespaces: Vec<_> = tx
@@ -470,6 +530,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
             },
         };
         let stats = tx.take_stats();
+        let indexes_read = tx.user_indexes_read();
         let execution_time = start.elapsed();
         let execution_time_f64 = execution_time.as_secs_f64();
         let truncated_log_lines = self.truncate_log_lines(outcome.log_lines.clone());
@@ -487,6 +548,11 @@ impl<RT: Runtime> CronJobExecutor<RT> {
                     execution_time_f64,
                 )
                 .await?;
+            if let Some(max_per_second) = job.cron_spec.dispatch.max_dispatch_per_second {
+                SchedulerModel::new(&mut tx, component.into())
+                    .space_out_new_jobs(max_per_second)
+                    .await?;
+            }
             self.complete_job_run(
                 identity.clone(),
                 &mut tx,
@@ -542,6 +608,7 @@ impl<RT: Runtime> CronJobExecutor<RT> {
         self.function_log.log_mutation(
             outcome,
             stats,
+            indexes_read,
             execution_time,
             caller,
             usage_tracker,
@@ -762,7 +829,9 @@ impl<RT: Runtime> CronJobExecutor<RT> {
         context: ExecutionContext,
     ) -> anyhow::Result<()> {
         let now = self.rt.generate_timestamp()?;
-        let prev_ts = job.next_ts;
+        // Compute the next run from when this one was scheduled, without its
+        // jitter, so jitter doesn't accumulate.
+        let prev_ts = job.scheduled_ts()?;
         let mut next_ts = compute_next_ts(&job.cron_spec, Some(prev_ts), now)?;
         let mut num_skipped = 0;
         let first_skipped_ts = next_ts;
@@ -837,10 +906,12 @@ impl<RT: Runtime> CronJobExecutor<RT> {
                 .await?;
         }
 
+        let jitter = compute_jitter(&job.cron_spec, next_ts, &mut self.rt.rng())?;
         let mut updated_job = job.clone();
         updated_job.state = CronJobState::Pending;
         updated_job.prev_ts = Some(prev_ts);
-        updated_job.next_ts = next_ts;
+        updated_job.next_ts = next_ts.add(jitter)?;
+        updated_job.jitter = jitter;
         model.update_job_state(job_id, updated_job.clone()).await?;
         Ok(())
     }
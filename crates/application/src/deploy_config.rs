@@ -19,12 +19,17 @@ use common::{
     },
     components::{
         ComponentDefinitionPath,
+        ComponentFunctionPath,
         ComponentId,
         ComponentName,
         ComponentPath,
         Resource,
     },
     errors::JsError,
+    knobs::{
+        MAX_WARMUP_FUNCTIONS,
+        WARMUP_MIN_INTERVAL,
+    },
     runtime::{
         Runtime,
         UnixTimestamp,
@@ -34,6 +39,7 @@ use common::{
         EnvVarValue,
         ModuleEnvironment,
         NodeDependency,
+        UdfType,
     },
 };
 use database::{
@@ -98,20 +104,28 @@ use model::{
         upload_download::download_package,
     },
     udf_config::types::UdfConfig,
+    warmup::{
+        types::WarmupFunction,
+        WarmupModel,
+    },
 };
 use rand::Rng;
 use serde::{
     Deserialize,
     Serialize,
 };
+use serde_json::Value as JsonValue;
 use sync_types::{
     CanonicalizedModulePath,
     ModulePath,
+    UdfPath,
 };
 use udf::EvaluateAppDefinitionsResult;
 use usage_tracking::FunctionUsageTracker;
 use value::{
     identifier::Identifier,
+    ConvexArray,
+    ConvexValue,
     DeveloperDocumentId,
     ResolvedDocumentId,
     TableNamespace,
@@ -193,6 +207,8 @@ impl<RT: Runtime> Application<RT> {
         let ctx = TypecheckContext::new(&evaluated_components, &initializer_evaluator);
         let app = ctx.instantiate_root().await?;
 
+        validate_warmup_functions(&config.warmup, &app, &evaluated_components)?;
+
         let schema_change = self
             ._handle_schema_change_in_start_push(&app, &evaluated_components, dry_run)
             .await?;
@@ -220,6 +236,7 @@ impl<RT: Runtime> Application<RT> {
             analysis: evaluated_components,
             app,
             schema_change,
+            warmup_functions: config.warmup.clone(),
         };
         Ok(resp)
     }
@@ -590,6 +607,12 @@ impl<RT: Runtime> Application<RT> {
                         )
                         .await?;
 
+                    // Rewrite the warm-up functions even if they're unchanged so the
+                    // warm-up executor reruns them against the new code.
+                    WarmupModel::new(tx)
+                        .replace(start_push.warmup_functions.clone())
+                        .await?;
+
                     let diffs = PushComponentDiffs {
                         auth_diff: auth_diff.clone(),
                         component_diffs: component_diffs.clone(),
@@ -612,6 +635,69 @@ impl<RT: Runtime> Application<RT> {
     }
 }
 
+/// Checks that each warm-up function exists in the pushed component tree and is
+/// a query. Warm-up runs happen without a user around to see the results, so
+/// functions with side effects aren't allowed.
+fn validate_warmup_functions(
+    warmup: &[WarmupFunction],
+    app: &CheckedComponent,
+    evaluated_components: &BTreeMap<ComponentDefinitionPath, EvaluatedComponentDefinition>,
+) -> anyhow::Result<()> {
+    if warmup.len() > *MAX_WARMUP_FUNCTIONS {
+        anyhow::bail!(ErrorMetadata::bad_request(
+            "TooManyWarmupFunctions",
+            format!(
+                "At most {} warm-up functions can be configured, found {}",
+                *MAX_WARMUP_FUNCTIONS,
+                warmup.len()
+            )
+        ));
+    }
+    for function in warmup {
+        let path = &function.path;
+        let description = format!(
+            "'{}'{}",
+            String::from(path.udf_path.clone()),
+            path.component.in_component_str()
+        );
+        let mut component = app;
+        for name in path.component.iter() {
+            let Some(child) = component.child_components.get(name) else {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "InvalidWarmupFunction",
+                    format!("Warm-up function {description} is in a component that doesn't exist")
+                ));
+            };
+            component = child;
+        }
+        let analyzed_function = evaluated_components
+            .get(&component.definition_path)
+            .and_then(|definition| definition.functions.get(path.udf_path.module()))
+            .and_then(|module| {
+                module
+                    .functions
+                    .iter()
+                    .find(|f| &f.name == path.udf_path.function_name())
+            });
+        let Some(analyzed_function) = analyzed_function else {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidWarmupFunction",
+                format!("Warm-up function {description} doesn't exist")
+            ));
+        };
+        if analyzed_function.udf_type != UdfType::Query {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidWarmupFunction",
+                format!(
+                    "Warm-up function {description} is a {}, but only queries can be warmed up",
+                    analyzed_function.udf_type.to_lowercase_string()
+                )
+            ));
+        }
+    }
+    Ok(())
+}
+
 struct ApplicationInitializerEvaluator<'a, RT: Runtime> {
     application: &'a Application<RT>,
     component_definitions: BTreeMap<ComponentDefinitionPath, ModuleConfig>,
@@ -679,6 +765,15 @@ pub struct StartPushRequest {
     pub component_definitions: Vec<ComponentDefinitionConfigJson>,
 
     pub node_dependencies: Vec<NodeDependencyJson>,
+
+    #[serde(default)]
+    pub node_version: Option<String>,
+
+    #[serde(default)]
+    pub dependency_layers: Vec<String>,
+
+    #[serde(default)]
+    pub warmup: Vec<WarmupFunctionJson>,
 }
 
 impl StartPushRequest {
@@ -699,6 +794,13 @@ impl StartPushRequest {
                 .into_iter()
                 .map(NodeDependency::from)
                 .collect(),
+            node_version: self.node_version.map(|v| v.parse()).transpose()?,
+            dependency_layers: self.dependency_layers,
+            warmup: self
+                .warmup
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<anyhow::Result<_>>()?,
         })
     }
 }
@@ -718,6 +820,8 @@ pub struct StartPushResponse {
     pub app: CheckedComponent,
 
     pub schema_change: SchemaChange,
+
+    pub warmup_functions: Vec<WarmupFunction>,
 }
 
 impl From<NodeDependencyJson> for NodeDependency {
@@ -878,6 +982,61 @@ pub struct NodeDependencyJson {
     version: String,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupFunctionJson {
+    pub function: String,
+    pub component_path: Option<String>,
+    pub args: Option<JsonValue>,
+    pub interval_seconds: Option<u64>,
+}
+
+impl TryFrom<WarmupFunctionJson> for WarmupFunction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: WarmupFunctionJson) -> anyhow::Result<Self> {
+        let udf_path: UdfPath = value.function.parse().map_err(|e: anyhow::Error| {
+            let msg = format!(
+                "{} is not a valid warm-up function path. {e}",
+                value.function
+            );
+            e.context(ErrorMetadata::bad_request("InvalidWarmupFunction", msg))
+        })?;
+        let path = ComponentFunctionPath {
+            component: ComponentPath::deserialize(value.component_path.as_deref())?,
+            udf_path,
+        }
+        .canonicalize();
+        let args = match value.args {
+            Some(args) => ConvexValue::try_from(args)?,
+            None => ConvexValue::Object(Default::default()),
+        };
+        if !matches!(args, ConvexValue::Object(_)) {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidWarmupFunction",
+                format!(
+                    "Arguments for warm-up function {} must be an object",
+                    value.function
+                )
+            ));
+        }
+        let interval = value.interval_seconds.map(Duration::from_secs);
+        if let Some(interval) = interval
+            && interval < *WARMUP_MIN_INTERVAL
+        {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidWarmupFunction",
+                format!(
+                    "Warm-up interval for {} must be at least {} seconds",
+                    value.function,
+                    WARMUP_MIN_INTERVAL.as_secs()
+                )
+            ));
+        }
+        WarmupFunction::new(path, ConvexArray::try_from(vec![args])?, interval)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct FinishPushDiff {
     pub auth_diff: AuthDiff,
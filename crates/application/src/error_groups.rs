@@ -0,0 +1,259 @@
+//! Groups function errors that look alike, so a burst of failures shows up as
+//! a handful of distinct errors with counts instead of a wall of log lines.
+//!
+//! Errors are grouped by a fingerprint of their message, with the parts that
+//! vary between occurrences (numbers, IDs and quoted values) replaced by
+//! placeholders, and the top frames of their stack trace.
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+        VecDeque,
+    },
+    hash::{
+        DefaultHasher,
+        Hash,
+        Hasher,
+    },
+    sync::LazyLock,
+};
+
+use common::{
+    errors::JsError,
+    knobs::MAX_ERROR_GROUPS,
+    runtime::UnixTimestamp,
+};
+use regex::Regex;
+
+/// Quoted values, like the ID in `Document "jd7..." not found`.
+static QUOTED_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""[^"]*"|'[^']*'|`[^`]*`"#).unwrap());
+/// Words with digits in them, like document IDs, hashes, counts or
+/// timestamps.
+static VARYING_WORD_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9_\-]*[0-9][A-Za-z0-9_\-.:]*").unwrap());
+
+/// How many of the innermost stack frames go into the fingerprint.
+const FINGERPRINT_FRAMES: usize = 3;
+/// Recent occurrences are counted per minute, for this many minutes.
+const RECENT_MINUTES: u64 = 60;
+/// A group is spiking if it happened at least `SPIKE_RATIO` times as often in
+/// the last `SPIKE_WINDOW_MINUTES` as in the rest of the last hour, and at
+/// least `SPIKE_MIN_COUNT` times.
+const SPIKE_WINDOW_MINUTES: u64 = 5;
+const SPIKE_RATIO: u64 = 3;
+const SPIKE_MIN_COUNT: u64 = 10;
+
+/// Errors that share a fingerprint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorGroup {
+    pub fingerprint: String,
+    /// The message with its varying parts replaced by placeholders.
+    pub normalized_message: String,
+    /// The message of the latest occurrence.
+    pub latest_message: String,
+    pub count: u64,
+    pub first_seen: UnixTimestamp,
+    pub last_seen: UnixTimestamp,
+    /// Occurrences per function, like `messages:send` or `GET /api/hook`.
+    pub functions: BTreeMap<String, u64>,
+    pub count_last_hour: u64,
+    pub spiking: bool,
+}
+
+struct GroupState {
+    normalized_message: String,
+    latest_message: String,
+    count: u64,
+    first_seen: UnixTimestamp,
+    last_seen: UnixTimestamp,
+    functions: BTreeMap<String, u64>,
+    /// Occurrences per minute since the Unix epoch, for the last
+    /// `RECENT_MINUTES`.
+    recent: VecDeque<(u64, u64)>,
+}
+
+impl GroupState {
+    fn record(&mut self, message: &str, function: String, ts: UnixTimestamp) {
+        self.latest_message = message.to_string();
+        self.count += 1;
+        self.first_seen = self.first_seen.min(ts);
+        self.last_seen = self.last_seen.max(ts);
+        *self.functions.entry(function).or_default() += 1;
+        let minute = ts.as_secs() / 60;
+        match self.recent.back_mut() {
+            Some((last_minute, count)) if *last_minute >= minute => *count += 1,
+            _ => self.recent.push_back((minute, 1)),
+        }
+        while self
+            .recent
+            .front()
+            .is_some_and(|(first_minute, _)| first_minute + RECENT_MINUTES <= minute)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    fn to_group(&self, fingerprint: u64, now: UnixTimestamp) -> ErrorGroup {
+        let now_minute = now.as_secs() / 60;
+        let mut count_last_hour = 0;
+        let mut count_spike_window = 0;
+        for (minute, count) in &self.recent {
+            if minute + RECENT_MINUTES <= now_minute {
+                continue;
+            }
+            count_last_hour += count;
+            if minute + SPIKE_WINDOW_MINUTES > now_minute {
+                count_spike_window += count;
+            }
+        }
+        // Compare rates over the same length of time: the spike window against
+        // the rest of the hour scaled down to its length.
+        let count_before = count_last_hour - count_spike_window;
+        let spiking = count_spike_window >= SPIKE_MIN_COUNT
+            && count_spike_window * (RECENT_MINUTES - SPIKE_WINDOW_MINUTES)
+                >= SPIKE_RATIO * count_before * SPIKE_WINDOW_MINUTES;
+        ErrorGroup {
+            fingerprint: format!("{fingerprint:016x}"),
+            normalized_message: self.normalized_message.clone(),
+            latest_message: self.latest_message.clone(),
+            count: self.count,
+            first_seen: self.first_seen,
+            last_seen: self.last_seen,
+            functions: self.functions.clone(),
+            count_last_hour,
+            spiking,
+        }
+    }
+}
+
+/// The error groups seen since the backend started, up to `MAX_ERROR_GROUPS`
+/// of them. When there are more, the one seen least recently is dropped.
+#[derive(Default)]
+pub struct ErrorGroups {
+    groups: HashMap<u64, GroupState>,
+}
+
+impl ErrorGroups {
+    pub fn record(&mut self, error: &JsError, function: String, ts: UnixTimestamp) {
+        let normalized_message = normalize_message(&error.message);
+        let fingerprint = fingerprint(&normalized_message, error);
+        self.groups
+            .entry(fingerprint)
+            .or_insert_with(|| GroupState {
+                normalized_message,
+                latest_message: String::new(),
+                count: 0,
+                first_seen: ts,
+                last_seen: ts,
+                functions: BTreeMap::new(),
+                recent: VecDeque::new(),
+            })
+            .record(&error.message, function, ts);
+        if self.groups.len() > *MAX_ERROR_GROUPS {
+            let least_recent = self
+                .groups
+                .iter()
+                .min_by_key(|(_, group)| group.last_seen)
+                .map(|(fingerprint, _)| *fingerprint);
+            if let Some(least_recent) = least_recent {
+                self.groups.remove(&least_recent);
+            }
+        }
+    }
+
+    /// The groups last seen at or after `since`, most recently seen first.
+    pub fn groups(&self, since: Option<UnixTimestamp>, now: UnixTimestamp) -> Vec<ErrorGroup> {
+        let mut groups: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| since.is_none_or(|since| group.last_seen >= since))
+            .map(|(fingerprint, group)| group.to_group(*fingerprint, now))
+            .collect();
+        groups.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        groups
+    }
+}
+
+fn normalize_message(message: &str) -> String {
+    let message = QUOTED_PATTERN.replace_all(message, "<value>");
+    VARYING_WORD_PATTERN
+        .replace_all(&message, "<value>")
+        .into_owned()
+}
+
+fn fingerprint(normalized_message: &str, error: &JsError) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    normalized_message.hash(&mut hasher);
+    if let Some(frames) = &error.frames {
+        for frame in frames.0.iter().take(FINGERPRINT_FRAMES) {
+            frame.function_name.hash(&mut hasher);
+            frame.file_name.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        errors::JsError,
+        runtime::UnixTimestamp,
+    };
+
+    use super::{
+        normalize_message,
+        ErrorGroups,
+    };
+
+    fn error(message: &str) -> JsError {
+        JsError {
+            message: message.to_string(),
+            custom_data: None,
+            frames: None,
+        }
+    }
+
+    #[test]
+    fn test_normalize_message() {
+        assert_eq!(
+            normalize_message(r#"Document "jd7f2k9x3q" not found in table 'users' after 3 tries"#),
+            "Document <value> not found in table <value> after <value> tries"
+        );
+        assert_eq!(
+            normalize_message("Uncaught Error: Rate limited"),
+            "Uncaught Error: Rate limited"
+        );
+    }
+
+    #[test]
+    fn test_error_groups() {
+        let mut groups = ErrorGroups::default();
+        let start = UnixTimestamp::from_millis(1_700_000_000_000);
+        for i in 0..20 {
+            let ts = UnixTimestamp::from_millis(1_700_000_000_000 + i * 1000);
+            groups.record(
+                &error(&format!("Document \"{i}\" not found")),
+                "messages:get".to_string(),
+                ts,
+            );
+        }
+        groups.record(&error("Rate limited"), "messages:send".to_string(), start);
+
+        let now = UnixTimestamp::from_millis(1_700_000_060_000);
+        let result = groups.groups(None, now);
+        assert_eq!(result.len(), 2);
+        let not_found = &result[0];
+        assert_eq!(not_found.normalized_message, "Document <value> not found");
+        assert_eq!(not_found.latest_message, "Document \"19\" not found");
+        assert_eq!(not_found.count, 20);
+        assert_eq!(not_found.first_seen, start);
+        assert_eq!(not_found.functions["messages:get"], 20);
+        assert_eq!(not_found.count_last_hour, 20);
+        assert!(not_found.spiking);
+        assert!(!result[1].spiking);
+
+        let since = UnixTimestamp::from_millis(1_700_000_001_000);
+        assert_eq!(groups.groups(Some(since), now).len(), 1);
+    }
+}
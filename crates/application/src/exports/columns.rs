@@ -0,0 +1,282 @@
+//! Columns for the tabular export formats, Parquet and CSV. Each of the
+//! table's top-level fields becomes a column, typed from the table's inferred
+//! shape. Fields without a single scalar type, like objects, arrays, and
+//! fields with values of several types, are written as clean JSON.
+use std::borrow::Cow;
+
+use common::document::{
+    CREATION_TIME_FIELD,
+    ID_FIELD,
+};
+use shape_inference::{
+    CountedShape,
+    ProdConfigWithOptionalFields,
+    ShapeEnum,
+};
+use value::{
+    ConvexObject,
+    ConvexValue,
+};
+
+/// Column with the whole document, for tables whose documents don't share a
+/// set of fields.
+const DOCUMENT_COLUMN: &str = "document";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Int64,
+    Float64,
+    Boolean,
+    String,
+    Bytes,
+    /// Clean JSON of the value.
+    Json,
+}
+
+impl ColumnType {
+    fn of_scalar(shape: &ShapeEnum<ProdConfigWithOptionalFields, u64>) -> Option<Self> {
+        let column_type = match shape {
+            ShapeEnum::Int64 => Self::Int64,
+            ShapeEnum::NegativeInf
+            | ShapeEnum::PositiveInf
+            | ShapeEnum::NegativeZero
+            | ShapeEnum::NaN
+            | ShapeEnum::NormalFloat64
+            | ShapeEnum::Float64 => Self::Float64,
+            ShapeEnum::Boolean => Self::Boolean,
+            ShapeEnum::StringLiteral(_)
+            | ShapeEnum::Id(_)
+            | ShapeEnum::FieldName
+            | ShapeEnum::String => Self::String,
+            ShapeEnum::Bytes => Self::Bytes,
+            _ => return None,
+        };
+        Some(column_type)
+    }
+
+    /// The type of a column for a field with `shape`, and whether the field
+    /// can be null.
+    fn of_field(shape: &CountedShape<ProdConfigWithOptionalFields>) -> (Self, bool) {
+        match shape.variant() {
+            ShapeEnum::Null => (Self::Json, true),
+            ShapeEnum::Union(union) => {
+                let nullable = union
+                    .iter()
+                    .any(|variant| matches!(variant.variant(), ShapeEnum::Null));
+                let mut column_types = union
+                    .iter()
+                    .filter(|variant| !matches!(variant.variant(), ShapeEnum::Null))
+                    .map(|variant| Self::of_scalar(variant.variant()));
+                let first = column_types.next().flatten();
+                let column_type = match first {
+                    Some(column_type) if column_types.all(|t| t == first) => column_type,
+                    _ => Self::Json,
+                };
+                (column_type, nullable)
+            },
+            shape => (Self::of_scalar(shape).unwrap_or(Self::Json), false),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Int64 => "int64",
+            Self::Float64 => "float64",
+            Self::Boolean => "boolean",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+            Self::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+pub struct TableColumns {
+    pub columns: Vec<Column>,
+    /// Set if the whole document is written to the `document` column.
+    document_column: bool,
+}
+
+impl TableColumns {
+    /// The columns for a table with documents of `shape`. The system fields
+    /// come first, followed by the table's fields in order. If the documents
+    /// don't share a set of fields, the columns are the system fields and the
+    /// whole document.
+    pub fn new(shape: &CountedShape<ProdConfigWithOptionalFields>) -> Self {
+        let ShapeEnum::Object(object) = shape.variant() else {
+            let columns = vec![
+                Column {
+                    name: ID_FIELD.to_string(),
+                    column_type: ColumnType::String,
+                    nullable: false,
+                },
+                Column {
+                    name: CREATION_TIME_FIELD.to_string(),
+                    column_type: ColumnType::Float64,
+                    nullable: false,
+                },
+                Column {
+                    name: DOCUMENT_COLUMN.to_string(),
+                    column_type: ColumnType::Json,
+                    nullable: false,
+                },
+            ];
+            return Self {
+                columns,
+                document_column: true,
+            };
+        };
+        let mut columns: Vec<_> = object
+            .fields()
+            .iter()
+            .map(|(field_name, field)| {
+                let (column_type, nullable) = ColumnType::of_field(&field.value_shape);
+                Column {
+                    name: field_name.to_string(),
+                    column_type,
+                    nullable: nullable || field.optional,
+                }
+            })
+            .collect();
+        columns.sort_by_key(|column| {
+            if column.name == **ID_FIELD {
+                0
+            } else if column.name == **CREATION_TIME_FIELD {
+                1
+            } else {
+                2
+            }
+        });
+        Self {
+            columns,
+            document_column: false,
+        }
+    }
+
+    /// Moves the columns named in `field_order` to the front, in that order.
+    /// Names without a column are ignored.
+    pub fn reorder(&mut self, field_order: &[String]) {
+        self.columns.sort_by_key(|column| {
+            field_order
+                .iter()
+                .position(|name| *name == column.name)
+                .unwrap_or(field_order.len())
+        });
+    }
+
+    /// The value of each column for `document`, or `None` if the document
+    /// doesn't have the field.
+    pub fn values<'a>(
+        &'a self,
+        document: &'a ConvexObject,
+    ) -> impl Iterator<Item = (&'a Column, Option<Cow<'a, ConvexValue>>)> {
+        self.columns.iter().map(move |column| {
+            let value = if self.document_column && column.name == DOCUMENT_COLUMN {
+                Some(Cow::Owned(ConvexValue::Object(document.clone())))
+            } else {
+                document.get(&column.name[..]).map(Cow::Borrowed)
+            };
+            (column, value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shape_inference::{
+        CountedShape,
+        ProdConfigWithOptionalFields,
+    };
+    use value::assert_obj;
+
+    use super::{
+        Column,
+        ColumnType,
+        TableColumns,
+    };
+
+    fn column(name: &str, column_type: ColumnType, nullable: bool) -> Column {
+        Column {
+            name: name.to_string(),
+            column_type,
+            nullable,
+        }
+    }
+
+    #[test]
+    fn test_table_columns() {
+        let mut shape = CountedShape::<ProdConfigWithOptionalFields>::empty();
+        for object in [
+            assert_obj!(
+                "_id" => "jd7f2ceqtyk4g5dsfrvxcbp4t56n7h3w",
+                "_creationTime" => 1.0,
+                "name" => "alice",
+                "nickname" => "al",
+                "age" => 30,
+                "tags" => ["a"],
+                "score" => 1.5,
+            ),
+            assert_obj!(
+                "_id" => "jd7ad3fpetdzr0tg5p9v5m6dps6n6n1j",
+                "_creationTime" => 2.0,
+                "name" => "bob",
+                "age" => "unknown",
+                "tags" => ["b"],
+                "score" => null,
+            ),
+        ] {
+            shape = shape.insert(&object);
+        }
+        let mut table_columns = TableColumns::new(&shape);
+        assert!(!table_columns.document_column);
+        assert_eq!(
+            table_columns.columns,
+            vec![
+                column("_id", ColumnType::String, false),
+                column("_creationTime", ColumnType::Float64, false),
+                // Fields with values of several types are written as JSON.
+                column("age", ColumnType::Json, false),
+                column("name", ColumnType::String, false),
+                column("nickname", ColumnType::String, true),
+                column("score", ColumnType::Float64, true),
+                column("tags", ColumnType::Json, false),
+            ]
+        );
+
+        table_columns.reorder(&["name".to_string(), "missing".to_string(), "age".to_string()]);
+        assert_eq!(
+            table_columns
+                .columns
+                .iter()
+                .map(|column| column.name.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "name",
+                "age",
+                "_id",
+                "_creationTime",
+                "nickname",
+                "score",
+                "tags"
+            ]
+        );
+
+        // Empty tables have the whole document in a column.
+        let table_columns = TableColumns::new(&CountedShape::empty());
+        assert!(table_columns.document_column);
+        assert_eq!(
+            table_columns.columns,
+            vec![
+                column("_id", ColumnType::String, false),
+                column("_creationTime", ColumnType::Float64, false),
+                column("document", ColumnType::Json, false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! CSV exports have a CSV file for each table, for spreadsheets and ETL tools
+//! that don't read JSON. Each column in the header is named `<field>:<type>`,
+//! so the values can be parsed back to their types.
+use common::document::ResolvedDocument;
+use serde_json::Value as JsonValue;
+use shape_inference::{
+    CountedShape,
+    ProdConfigWithOptionalFields,
+};
+use value::{
+    export::ValueFormat,
+    ConvexValue,
+};
+
+use crate::exports::{
+    columns::{
+        ColumnType,
+        TableColumns,
+    },
+    zip_uploader::ZipSnapshotCsvUpload,
+};
+
+/// Writes the documents of a table to a CSV file.
+pub struct CsvTableWriter<'a, 'b> {
+    table_columns: TableColumns,
+    upload: ZipSnapshotCsvUpload<'a, 'b>,
+}
+
+impl<'a, 'b> CsvTableWriter<'a, 'b> {
+    pub async fn new(
+        mut upload: ZipSnapshotCsvUpload<'a, 'b>,
+        inferred_type: &CountedShape<ProdConfigWithOptionalFields>,
+        field_order: &[String],
+    ) -> anyhow::Result<Self> {
+        let mut table_columns = TableColumns::new(inferred_type);
+        table_columns.reorder(field_order);
+        let header: Vec<_> = table_columns
+            .columns
+            .iter()
+            .map(|column| format!("{}:{}", column.name, column.column_type.name()))
+            .collect();
+        upload.write_record(&header).await?;
+        Ok(Self {
+            table_columns,
+            upload,
+        })
+    }
+
+    pub async fn write(&mut self, doc: ResolvedDocument) -> anyhow::Result<()> {
+        let document = doc.into_value().0;
+        let record: Vec<_> = self
+            .table_columns
+            .values(&document)
+            .map(|(column, value)| csv_cell(column.column_type, value.as_deref()))
+            .collect();
+        self.upload.write_record(&record).await
+    }
+
+    pub async fn complete(self) -> anyhow::Result<()> {
+        self.upload.complete().await
+    }
+}
+
+/// Formats a value for a column of `column_type`. Missing fields and nulls
+/// are empty cells.
+fn csv_cell(column_type: ColumnType, value: Option<&ConvexValue>) -> String {
+    let Some(value) = value else {
+        return String::new();
+    };
+    if let ConvexValue::Null = value {
+        return String::new();
+    }
+    match (
+        column_type,
+        value.clone().export(ValueFormat::ConvexCleanJSON),
+    ) {
+        (ColumnType::Json, json) => json.to_string(),
+        // Scalars are written without JSON's quotes.
+        (_, JsonValue::String(s)) => s,
+        (_, json) => json.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use value::{
+        assert_val,
+        ConvexValue,
+    };
+
+    use super::csv_cell;
+    use crate::exports::columns::ColumnType;
+
+    #[test]
+    fn test_csv_cell() -> anyhow::Result<()> {
+        assert_eq!(
+            csv_cell(ColumnType::Int64, Some(&ConvexValue::Int64(30))),
+            "30"
+        );
+        assert_eq!(
+            csv_cell(ColumnType::Float64, Some(&ConvexValue::Float64(1.5))),
+            "1.5"
+        );
+        assert_eq!(
+            csv_cell(ColumnType::Float64, Some(&ConvexValue::Float64(f64::NAN))),
+            "NaN"
+        );
+        assert_eq!(
+            csv_cell(ColumnType::Boolean, Some(&ConvexValue::Boolean(true))),
+            "true"
+        );
+        assert_eq!(
+            csv_cell(ColumnType::String, Some(&assert_val!("a, \"b\""))),
+            "a, \"b\""
+        );
+        assert_eq!(
+            csv_cell(
+                ColumnType::Bytes,
+                Some(&ConvexValue::Bytes(vec![0, 16, 131].try_into()?))
+            ),
+            "ABCD"
+        );
+        // JSON columns keep the quotes, so strings can be told apart from
+        // other values.
+        assert_eq!(csv_cell(ColumnType::Json, Some(&assert_val!("a"))), "\"a\"");
+        assert_eq!(
+            csv_cell(ColumnType::Json, Some(&assert_val!(["a", 1.5]))),
+            "[\"a\",1.5]"
+        );
+        assert_eq!(csv_cell(ColumnType::String, Some(&ConvexValue::Null)), "");
+        assert_eq!(csv_cell(ColumnType::String, None), "");
+        Ok(())
+    }
+}
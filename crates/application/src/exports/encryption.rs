@@ -0,0 +1,208 @@
+//! Encrypts exports with the caller's age public keys or passphrase as they're
+//! written, so the export is already encrypted when it lands in the exports
+//! storage or an external destination. Encrypted exports are
+//! [age](https://age-encryption.org) files of the export's zip, which decrypt
+//! with `age --decrypt`.
+use age::{
+    secrecy::SecretString,
+    x25519,
+    Encryptor,
+};
+use anyhow::Context;
+use bytes::Bytes;
+use common::async_compat::TokioAsyncWriteCompatExt;
+use errors::ErrorMetadata;
+use futures::AsyncWriteExt;
+use keybroker::KeyBroker;
+use model::exports::types::ExportEncryption;
+use storage::ChannelWriter;
+use tokio::sync::mpsc;
+
+/// Size of the chunks of the encrypted export passed on to be uploaded.
+const PART_SIZE: usize = 5 << 20;
+
+/// Checks that an export's encryption can be used before it's requested.
+pub fn check_export_encryption(encryption: &ExportEncryption) -> anyhow::Result<()> {
+    match encryption {
+        ExportEncryption::PublicKeys { recipients } => {
+            anyhow::ensure!(
+                !recipients.is_empty(),
+                ErrorMetadata::bad_request(
+                    "InvalidExportEncryption",
+                    "An encrypted export needs at least one public key"
+                )
+            );
+            for recipient in recipients {
+                parse_recipient(recipient)?;
+            }
+        },
+        ExportEncryption::Passphrase { .. } => {},
+    }
+    Ok(())
+}
+
+fn parse_recipient(recipient: &str) -> anyhow::Result<x25519::Recipient> {
+    recipient.parse().map_err(|e| {
+        anyhow::anyhow!("{e}").context(ErrorMetadata::bad_request(
+            "InvalidExportEncryption",
+            format!("{recipient} isn't an age public key like age1..."),
+        ))
+    })
+}
+
+/// The encryptor for an export, which encrypts a random file key to each of
+/// the export's public keys or its passphrase.
+pub fn export_encryptor(
+    encryption: ExportEncryption,
+    key_broker: &KeyBroker,
+) -> anyhow::Result<Encryptor> {
+    let encryptor = match encryption {
+        ExportEncryption::PublicKeys { recipients } => {
+            let recipients: Vec<_> = recipients
+                .iter()
+                .map(|recipient| parse_recipient(recipient))
+                .collect::<anyhow::Result<_>>()?;
+            Encryptor::with_recipients(
+                recipients
+                    .iter()
+                    .map(|recipient| recipient as &dyn age::Recipient),
+            )?
+        },
+        ExportEncryption::Passphrase {
+            encrypted_passphrase,
+        } => {
+            let passphrase = key_broker
+                .decrypt_export_secret(&encrypted_passphrase)
+                .context("Couldn't decrypt the export's passphrase")?;
+            // Deriving the key from the passphrase with scrypt takes about a
+            // second of CPU.
+            common::runtime::block_in_place(|| {
+                Encryptor::with_user_passphrase(SecretString::from(passphrase))
+            })
+        },
+    };
+    Ok(encryptor)
+}
+
+/// Encrypts the export's bytes from `receiver` and sends the encrypted export
+/// on to `sender`.
+pub async fn encrypt_export(
+    encryptor: Encryptor,
+    mut receiver: mpsc::Receiver<Bytes>,
+    sender: mpsc::Sender<Bytes>,
+) -> anyhow::Result<()> {
+    let mut writer = encryptor
+        .wrap_async_output(ChannelWriter::new(sender, PART_SIZE).compat_write())
+        .await?;
+    while let Some(data) = receiver.recv().await {
+        writer.write_all(&data).await?;
+    }
+    // Closing writes the final chunk, without which the export can't be
+    // decrypted.
+    writer.close().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        iter,
+    };
+
+    use age::{
+        secrecy::SecretString,
+        x25519,
+        Decryptor,
+        Identity,
+    };
+    use bytes::Bytes;
+    use keybroker::KeyBroker;
+    use model::exports::types::ExportEncryption;
+    use tokio::sync::mpsc;
+
+    use super::{
+        check_export_encryption,
+        encrypt_export,
+        export_encryptor,
+    };
+
+    async fn encrypt(encryption: ExportEncryption, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let encryptor = export_encryptor(encryption, &KeyBroker::dev())?;
+        let (sender, receiver) = mpsc::channel(1);
+        let (encrypted_sender, mut encrypted_receiver) = mpsc::channel(1);
+        let chunks: Vec<_> = data.chunks(1000).map(Bytes::copy_from_slice).collect();
+        let send = async move {
+            for chunk in chunks {
+                sender.send(chunk).await?;
+            }
+            anyhow::Ok(())
+        };
+        let receive = async move {
+            let mut encrypted = vec![];
+            while let Some(data) = encrypted_receiver.recv().await {
+                encrypted.extend_from_slice(&data);
+            }
+            anyhow::Ok(encrypted)
+        };
+        let ((), (), encrypted) = futures::try_join!(
+            send,
+            encrypt_export(encryptor, receiver, encrypted_sender),
+            receive
+        )?;
+        Ok(encrypted)
+    }
+
+    fn decrypt(encrypted: &[u8], identity: &dyn Identity) -> anyhow::Result<Vec<u8>> {
+        let mut reader = Decryptor::new(encrypted)?.decrypt(iter::once(identity))?;
+        let mut decrypted = vec![];
+        reader.read_to_end(&mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_to_public_keys() -> anyhow::Result<()> {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 256) as u8).collect();
+        let identities = [x25519::Identity::generate(), x25519::Identity::generate()];
+        let encryption = ExportEncryption::PublicKeys {
+            recipients: identities
+                .iter()
+                .map(|identity| identity.to_public().to_string())
+                .collect(),
+        };
+        check_export_encryption(&encryption)?;
+        let encrypted = encrypt(encryption, &data).await?;
+        assert_ne!(encrypted, data);
+        // Either key decrypts the export, but no other key does.
+        for identity in &identities {
+            assert_eq!(decrypt(&encrypted, identity)?, data);
+        }
+        assert!(decrypt(&encrypted, &x25519::Identity::generate()).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_with_passphrase() -> anyhow::Result<()> {
+        let key_broker = KeyBroker::dev();
+        let encryption = ExportEncryption::Passphrase {
+            encrypted_passphrase: key_broker.encrypt_export_secret("hunter2".to_string()),
+        };
+        let encrypted = encrypt(encryption, b"export").await?;
+        let identity = age::scrypt::Identity::new(SecretString::from("hunter2".to_string()));
+        assert_eq!(decrypt(&encrypted, &identity)?, b"export");
+        let identity = age::scrypt::Identity::new(SecretString::from("hunter3".to_string()));
+        assert!(decrypt(&encrypted, &identity).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_export_encryption() {
+        assert!(
+            check_export_encryption(&ExportEncryption::PublicKeys { recipients: vec![] }).is_err()
+        );
+        assert!(check_export_encryption(&ExportEncryption::PublicKeys {
+            recipients: vec!["age1notakey".to_string()]
+        })
+        .is_err());
+    }
+}
@@ -9,6 +9,7 @@ use common::{
     knobs::{
         EXPORT_MAX_INFLIGHT_PREFETCH_BYTES,
         EXPORT_STORAGE_GET_CONCURRENCY,
+        EXPORT_STORAGE_URL_TTL,
     },
     persistence::LatestDocument,
     runtime::Runtime,
@@ -42,7 +43,10 @@ use serde::{
     Serialize,
 };
 use serde_json::json;
-use storage::StorageExt;
+use storage::{
+    Storage,
+    StorageExt,
+};
 use tokio_util::io::StreamReader;
 use usage_tracking::{
     FunctionUsageTracker,
@@ -59,6 +63,10 @@ use crate::exports::{
     zip_uploader::ZipSnapshotUpload,
 };
 
+/// Writes `_storage/documents.jsonl` with the metadata of each file, and then
+/// the files if `include_files` is set. If `include_urls` is set, each file's
+/// metadata has a signed URL to download it, so it can be restored from the
+/// URL when the file isn't in the zip.
 pub async fn write_storage_table<'a, 'b: 'a, RT: Runtime>(
     worker: &ExportWorker<RT>,
     path_prefix: &str,
@@ -70,6 +78,8 @@ pub async fn write_storage_table<'a, 'b: 'a, RT: Runtime>(
     system_tables: &BTreeMap<(TableNamespace, TableName), TabletId>,
     usage: &FunctionUsageTracker,
     requestor: ExportRequestor,
+    include_files: bool,
+    include_urls: bool,
 ) -> anyhow::Result<()> {
     // _storage
     let tablet_id = system_tables
@@ -94,6 +104,18 @@ pub async fn write_storage_table<'a, 'b: 'a, RT: Runtime>(
                 .creation_time()
                 .context("file should have creation time")?,
         );
+        let url = if include_urls {
+            let url = worker
+                .file_storage
+                .signed_url(
+                    file_storage_entry.storage_key.clone(),
+                    *EXPORT_STORAGE_URL_TTL,
+                )
+                .await?;
+            Some(url.to_string())
+        } else {
+            None
+        };
         table_upload
             .write_json_line(json!(FileStorageZipMetadata {
                 id: virtual_storage_id.encode(),
@@ -102,10 +124,14 @@ pub async fn write_storage_table<'a, 'b: 'a, RT: Runtime>(
                 size: Some(file_storage_entry.size),
                 content_type: file_storage_entry.content_type.clone(),
                 internal_id: Some(file_storage_entry.storage_id.to_string()),
+                url,
             }))
             .await?;
     }
     table_upload.complete().await?;
+    if !include_files {
+        return Ok(());
+    }
 
     let table_iterator = worker.database.table_iterator(snapshot_ts, 1000);
     let max_prefetch_bytes = *EXPORT_MAX_INFLIGHT_PREFETCH_BYTES;
@@ -204,4 +230,7 @@ pub struct FileStorageZipMetadata {
     pub size: Option<i64>,
     pub content_type: Option<String>,
     pub internal_id: Option<String>,
+    /// A signed URL to download the file, for exports with storage URLs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
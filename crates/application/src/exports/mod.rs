@@ -1,4 +1,10 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    ops::Bound,
+};
 
 use anyhow::Context;
 use bytes::Bytes;
@@ -10,9 +16,20 @@ use common::{
         ComponentId,
         ComponentPath,
     },
+    document::ResolvedDocument,
     fastrace_helpers::get_sampled_span,
-    persistence::LatestDocument,
-    runtime::Runtime,
+    knobs::INCREMENTAL_EXPORT_ROWS_PER_SECOND,
+    persistence::{
+        DocumentLogEntry,
+        LatestDocument,
+        TimestampRange,
+    },
+    query::Order,
+    runtime::{
+        new_rate_limiter,
+        RateLimiter,
+        Runtime,
+    },
     types::{
         IndexId,
         ObjectKey,
@@ -28,6 +45,7 @@ use database::{
 };
 use fastrace::future::FutureExt;
 use futures::{
+    future::BoxFuture,
     pin_mut,
     try_join,
     AsyncWriteExt,
@@ -35,17 +53,26 @@ use futures::{
     StreamExt,
     TryStreamExt,
 };
+use governor::Quota;
 use itertools::Itertools;
 use keybroker::Identity;
 use maplit::btreemap;
 use model::exports::types::{
+    ExportDestination,
+    ExportEncryption,
+    ExportFilter,
     ExportFormat,
     ExportRequestor,
+    IncrementalExport,
 };
 use serde_json::json;
-use shape_inference::export_context::{
-    ExportContext,
-    GeneratedSchema,
+use shape_inference::{
+    export_context::{
+        ExportContext,
+        GeneratedSchema,
+    },
+    CountedShape,
+    ProdConfigWithOptionalFields,
 };
 use storage::{
     ChannelWriter,
@@ -56,6 +83,10 @@ use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use usage_tracking::FunctionUsageTracker;
 use value::{
+    ConvexValue,
+    DeveloperDocumentId,
+    FieldName,
+    IdentifierFieldName,
     InternalId,
     TableNamespace,
     TableNumber,
@@ -63,24 +94,48 @@ use value::{
 };
 
 use crate::exports::{
+    csv_writer::CsvTableWriter,
+    encryption::{
+        encrypt_export,
+        export_encryptor,
+    },
     export_storage::write_storage_table,
+    parquet_writer::ParquetTableWriter,
+    s3_destination::{
+        upload_to_destination,
+        S3Destination,
+    },
     worker::ExportWorker,
-    zip_uploader::ZipSnapshotUpload,
+    zip_uploader::{
+        ZipSnapshotParquetUpload,
+        ZipSnapshotUpload,
+    },
 };
 
+mod columns;
+mod csv_writer;
+mod encryption;
 mod export_storage;
 mod metrics;
+mod parquet_writer;
+mod s3_destination;
+pub mod schedule_worker;
 #[cfg(test)]
 mod tests;
 pub mod worker;
 mod zip_uploader;
 
+pub use encryption::check_export_encryption;
 pub use export_storage::FileStorageZipMetadata;
 
 async fn export_inner<F, Fut, RT: Runtime>(
     worker: &mut ExportWorker<RT>,
     format: ExportFormat,
     requestor: ExportRequestor,
+    incremental: Option<IncrementalExport>,
+    destination: Option<ExportDestination>,
+    filter: ExportFilter,
+    encryption: Option<ExportEncryption>,
     update_progress: F,
 ) -> anyhow::Result<(Timestamp, ObjectKey, FunctionUsageTracker)>
 where
@@ -123,16 +178,99 @@ where
             system_tables,
         )
     };
-    match format {
-        ExportFormat::Zip { include_storage } => {
-            // Start upload.
-            let mut upload = storage.start_upload().await?;
-            let (sender, receiver) = mpsc::channel::<Bytes>(1);
-            let uploader =
-                upload.try_write_parallel_and_hash(ReceiverStream::new(receiver).map(Ok));
-            let writer = ChannelWriter::new(sender, 5 * (1 << 20));
-            let usage = FunctionUsageTracker::new();
+    // Leave out the components and tables that the filter excludes.
+    let component_ids_to_paths: BTreeMap<_, _> = component_ids_to_paths
+        .into_iter()
+        .filter(|(_, component_path)| filter.includes_component(component_path))
+        .collect();
+    let tables: BTreeMap<_, _> = tables
+        .into_iter()
+        .filter(|(_, (namespace, _, table_name, _))| {
+            component_ids_to_paths
+                .get(&ComponentId::from(*namespace))
+                .is_some_and(|component_path| filter.includes_table(component_path, table_name))
+        })
+        .collect();
+    if incremental.is_some() {
+        anyhow::ensure!(
+            format
+                == ExportFormat::Zip {
+                    include_storage: false,
+                    storage_urls: false
+                },
+            "Incremental exports must be zip exports without file storage"
+        );
+    }
 
+    // Start upload.
+    let mut upload = storage.start_upload().await?;
+    let (sender, receiver) = mpsc::channel::<Bytes>(1);
+    let is_encrypted = encryption.is_some();
+    // Encrypted exports are encrypted as they're written, so only the
+    // encrypted export is uploaded anywhere.
+    let (receiver, encrypt): (_, BoxFuture<'static, anyhow::Result<()>>) = match encryption {
+        Some(encryption) => {
+            let encryptor = export_encryptor(encryption, &worker.key_broker)?;
+            let (encrypted_sender, encrypted_receiver) = mpsc::channel::<Bytes>(1);
+            (
+                encrypted_receiver,
+                Box::pin(encrypt_export(encryptor, receiver, encrypted_sender)),
+            )
+        },
+        None => (receiver, Box::pin(async { Ok(()) })),
+    };
+    // Exports with an external destination are uploaded there as they're
+    // written, and then passed on to the exports storage.
+    let (receiver, destination_upload): (_, BoxFuture<'static, anyhow::Result<()>>) =
+        match destination {
+            Some(destination) => {
+                // This should match the name of the export's download.
+                let mut object_name = format!("snapshot_{}_{}.zip", worker.instance_name, *ts);
+                if is_encrypted {
+                    object_name.push_str(".age");
+                }
+                let destination_upload = S3Destination::new(destination, &worker.key_broker)
+                    .await?
+                    .start_upload(&object_name)
+                    .await?;
+                let (storage_sender, storage_receiver) = mpsc::channel::<Bytes>(1);
+                (
+                    storage_receiver,
+                    Box::pin(upload_to_destination(
+                        destination_upload,
+                        receiver,
+                        storage_sender,
+                    )),
+                )
+            },
+            None => (receiver, Box::pin(async { Ok(()) })),
+        };
+    let uploader = upload.try_write_parallel_and_hash(ReceiverStream::new(receiver).map(Ok));
+    let writer = ChannelWriter::new(sender, 5 * (1 << 20));
+    let usage = FunctionUsageTracker::new();
+
+    match (format, incremental) {
+        (ExportFormat::Zip { .. }, Some(incremental)) => {
+            let zipper = construct_incremental_zip_snapshot(
+                worker,
+                writer,
+                tables.clone(),
+                component_ids_to_paths,
+                ts,
+                incremental,
+                &filter,
+                usage.clone(),
+                update_progress,
+            );
+            let (_, (), (), ()) = try_join!(uploader, zipper, encrypt, destination_upload)?;
+        },
+        (
+            ExportFormat::Zip {
+                include_storage,
+                storage_urls,
+            },
+            None,
+        ) => {
             let zipper = construct_zip_snapshot(
                 worker,
                 writer,
@@ -142,15 +280,32 @@ where
                 by_id_indexes,
                 system_tables,
                 include_storage,
+                storage_urls,
+                &filter,
                 usage.clone(),
                 requestor,
                 update_progress,
             );
-            let (_, ()) = try_join!(uploader, zipper)?;
-            let zip_object_key = upload.complete().await?;
-            Ok((*ts, zip_object_key, usage))
+            let (_, (), (), ()) = try_join!(uploader, zipper, encrypt, destination_upload)?;
+        },
+        (format @ (ExportFormat::Parquet | ExportFormat::Csv { .. }), _) => {
+            let zipper = construct_tabular_snapshot(
+                worker,
+                writer,
+                tables.clone(),
+                component_ids_to_paths,
+                ts,
+                by_id_indexes,
+                &format,
+                &filter,
+                usage.clone(),
+                update_progress,
+            );
+            let (_, (), (), ()) = try_join!(uploader, zipper, encrypt, destination_upload)?;
         },
     }
+    let zip_object_key = upload.complete().await?;
+    Ok((*ts, zip_object_key, usage))
 }
 
 async fn write_tables_table<'a, 'b: 'a>(
@@ -194,6 +349,7 @@ pub async fn write_table<'a, 'b: 'a, RT: Runtime>(
     table_name: TableName,
     table_summary: TableSummary,
     by_id: &InternalId,
+    excluded_fields: Option<&BTreeSet<IdentifierFieldName>>,
     usage: &FunctionUsageTracker,
 ) -> anyhow::Result<()> {
     let mut table_upload = zip_snapshot_upload
@@ -205,9 +361,11 @@ pub async fn write_table<'a, 'b: 'a, RT: Runtime>(
     pin_mut!(stream);
 
     // Write documents from stream to table uploads
-    let mut generated_schema = GeneratedSchema::new(table_summary.inferred_type().into());
-    let is_ambiguous = ExportContext::is_ambiguous(table_summary.inferred_type());
+    let inferred_type = exported_type(&table_summary, excluded_fields);
+    let mut generated_schema = GeneratedSchema::new((&inferred_type).into());
+    let is_ambiguous = ExportContext::is_ambiguous(&inferred_type);
     while let Some(LatestDocument { value: doc, .. }) = stream.try_next().await? {
+        let doc = without_fields(doc, excluded_fields)?;
         if is_ambiguous {
             generated_schema.insert(doc.value(), doc.developer_id());
         }
@@ -236,6 +394,8 @@ async fn construct_zip_snapshot<F, Fut, RT: Runtime>(
     by_id_indexes: BTreeMap<TabletId, IndexId>,
     system_tables: BTreeMap<(TableNamespace, TableName), TabletId>,
     include_storage: bool,
+    storage_urls: bool,
+    filter: &ExportFilter,
     usage: FunctionUsageTracker,
     requestor: ExportRequestor,
     update_progress: F,
@@ -308,6 +468,7 @@ where
             table_name.clone(),
             table_summary.clone(),
             by_id,
+            filter.excluded_fields(table_name),
             &usage,
         )
         .in_span(root)
@@ -315,7 +476,7 @@ where
     }
 
     // Backup the storage tables last - since the upload/download can be slower
-    if include_storage {
+    if include_storage || storage_urls {
         for (component_id, component_path) in component_ids_to_paths {
             let namespace: TableNamespace = component_id.into();
             let path_prefix = get_export_path_prefix(&component_path);
@@ -342,6 +503,8 @@ where
                 &system_tables,
                 &usage,
                 requestor,
+                include_storage,
+                storage_urls,
             )
             .in_span(root)
             .await?;
@@ -354,6 +517,339 @@ where
     Ok(())
 }
 
+/// Writes a Parquet or CSV file for each user table. System tables and file
+/// storage aren't included, since they're only useful for restoring the
+/// deployment.
+async fn construct_tabular_snapshot<F, Fut, RT: Runtime>(
+    worker: &ExportWorker<RT>,
+    mut writer: ChannelWriter,
+    tables: BTreeMap<TabletId, (TableNamespace, TableNumber, TableName, TableSummary)>,
+    component_ids_to_paths: BTreeMap<ComponentId, ComponentPath>,
+    snapshot_ts: RepeatableTimestamp,
+    by_id_indexes: BTreeMap<TabletId, IndexId>,
+    format: &ExportFormat,
+    filter: &ExportFilter,
+    usage: FunctionUsageTracker,
+    update_progress: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(String) -> Fut + Send + Copy,
+    Fut: Future<Output = anyhow::Result<()>> + Send,
+{
+    let mut zip_snapshot_upload = match format {
+        ExportFormat::Parquet => ZipSnapshotUpload::new_parquet(&mut writer).await?,
+        ExportFormat::Csv { .. } => ZipSnapshotUpload::new_csv(&mut writer).await?,
+        ExportFormat::Zip { .. } => anyhow::bail!("Zip snapshots don't have a file per table"),
+    };
+
+    // sort tables small to large, like zip snapshots.
+    let mut sorted_tables: Vec<_> = tables.iter().collect();
+    sorted_tables.sort_by_key(|(_, (_, _, _, table_summary))| table_summary.total_size());
+    for (tablet_id, (namespace, _, table_name, table_summary)) in sorted_tables {
+        let component_id: ComponentId = (*namespace).into();
+        let component_path = component_ids_to_paths
+            .get(&component_id)
+            .context("Component missing")?;
+        let in_component_str = component_path.in_component_str();
+        let path_prefix = get_export_path_prefix(component_path);
+        let by_id = by_id_indexes
+            .get(tablet_id)
+            .ok_or_else(|| anyhow::anyhow!("no by_id index for {} found", tablet_id))?;
+
+        let root = get_sampled_span(
+            &worker.instance_name,
+            "export_worker/write_tabular_table",
+            &mut worker.runtime.rng(),
+            btreemap! {
+                "dev.convex.component_path".to_string() => component_path.to_string(),
+                "dev.convex.table_name".to_string() => table_name.to_string(),
+            },
+        );
+        update_progress(format!("Backing up {table_name}{in_component_str}")).await?;
+        write_tabular_table(
+            worker,
+            &path_prefix,
+            &mut zip_snapshot_upload,
+            snapshot_ts,
+            component_path,
+            tablet_id,
+            table_name.clone(),
+            table_summary,
+            by_id,
+            format,
+            filter.excluded_fields(table_name),
+            &usage,
+        )
+        .in_span(root)
+        .await?;
+    }
+
+    zip_snapshot_upload.complete().await?;
+    writer.compat_write().close().await?;
+    Ok(())
+}
+
+enum TableFileWriter<'a, 'b> {
+    Parquet(ParquetTableWriter<ZipSnapshotParquetUpload<'a, 'b>>),
+    Csv(CsvTableWriter<'a, 'b>),
+}
+
+impl TableFileWriter<'_, '_> {
+    async fn write(&mut self, doc: ResolvedDocument) -> anyhow::Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.write(doc).await,
+            Self::Csv(writer) => writer.write(doc).await,
+        }
+    }
+
+    async fn complete(self) -> anyhow::Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.complete().await,
+            Self::Csv(writer) => writer.complete().await,
+        }
+    }
+}
+
+async fn write_tabular_table<'a, 'b: 'a, RT: Runtime>(
+    worker: &ExportWorker<RT>,
+    path_prefix: &str,
+    zip_snapshot_upload: &'a mut ZipSnapshotUpload<'b>,
+    snapshot_ts: RepeatableTimestamp,
+    component_path: &ComponentPath,
+    tablet_id: &TabletId,
+    table_name: TableName,
+    table_summary: &TableSummary,
+    by_id: &InternalId,
+    format: &ExportFormat,
+    excluded_fields: Option<&BTreeSet<IdentifierFieldName>>,
+    usage: &FunctionUsageTracker,
+) -> anyhow::Result<()> {
+    let inferred_type = &exported_type(table_summary, excluded_fields);
+    let mut table_writer = match format {
+        ExportFormat::Parquet => {
+            let upload = zip_snapshot_upload
+                .start_parquet_table(path_prefix, &table_name)
+                .await?;
+            TableFileWriter::Parquet(ParquetTableWriter::new(upload, inferred_type)?)
+        },
+        ExportFormat::Csv { field_order } => {
+            let upload = zip_snapshot_upload
+                .start_csv_table(path_prefix, &table_name)
+                .await?;
+            TableFileWriter::Csv(CsvTableWriter::new(upload, inferred_type, field_order).await?)
+        },
+        ExportFormat::Zip { .. } => anyhow::bail!("Zip snapshots don't have a file per table"),
+    };
+
+    let table_iterator = worker.database.table_iterator(snapshot_ts, 1000);
+    let stream = table_iterator.stream_documents_in_table(*tablet_id, *by_id, None);
+    pin_mut!(stream);
+    while let Some(LatestDocument { value: doc, .. }) = stream.try_next().await? {
+        usage.track_database_egress_size(
+            component_path.clone(),
+            table_name.to_string(),
+            doc.size() as u64,
+            false,
+        );
+        table_writer
+            .write(without_fields(doc, excluded_fields)?)
+            .await?;
+    }
+    table_writer.complete().await?;
+    Ok(())
+}
+
+/// Writes the documents in each table that changed after the previous export's
+/// snapshot, up to `snapshot_ts`, and the IDs of the ones that were deleted.
+/// Changes are read from the document log, so they're only available while
+/// the previous export's snapshot is within retention.
+async fn construct_incremental_zip_snapshot<F, Fut, RT: Runtime>(
+    worker: &ExportWorker<RT>,
+    mut writer: ChannelWriter,
+    tables: BTreeMap<TabletId, (TableNamespace, TableNumber, TableName, TableSummary)>,
+    component_ids_to_paths: BTreeMap<ComponentId, ComponentPath>,
+    snapshot_ts: RepeatableTimestamp,
+    incremental: IncrementalExport,
+    filter: &ExportFilter,
+    usage: FunctionUsageTracker,
+    update_progress: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(String) -> Fut + Send + Copy,
+    Fut: Future<Output = anyhow::Result<()>> + Send,
+{
+    let manifest = json!({
+        "type": "incremental",
+        "snapshotTs": u64::from(*snapshot_ts),
+        "sinceTs": u64::from(incremental.since_ts),
+        "previousExportId": incremental.previous_export_id.encode(),
+        "baseExportId": incremental.base_export_id.encode(),
+    });
+    let mut zip_snapshot_upload = ZipSnapshotUpload::new_incremental(&mut writer, manifest).await?;
+
+    // List every table, so restores can tell which ones were deleted.
+    for (component_id, component_path) in component_ids_to_paths.iter() {
+        let namespace: TableNamespace = (*component_id).into();
+        let path_prefix = get_export_path_prefix(component_path);
+        let in_component_str = component_path.in_component_str();
+        update_progress(format!("Backing up _tables{in_component_str}")).await?;
+        write_tables_table(&path_prefix, &mut zip_snapshot_upload, namespace, &tables).await?;
+    }
+
+    let rate_limiter = new_rate_limiter(
+        worker.runtime.clone(),
+        Quota::per_second(*INCREMENTAL_EXPORT_ROWS_PER_SECOND),
+    );
+    for (tablet_id, (namespace, table_number, table_name, table_summary)) in tables {
+        let component_id: ComponentId = namespace.into();
+        let component_path = component_ids_to_paths
+            .get(&component_id)
+            .context("Component missing")?;
+        let in_component_str = component_path.in_component_str();
+        let path_prefix = get_export_path_prefix(component_path);
+        let root = get_sampled_span(
+            &worker.instance_name,
+            "export_worker/write_table_changes",
+            &mut worker.runtime.rng(),
+            btreemap! {
+                "dev.convex.component_path".to_string() => component_path.to_string(),
+                "dev.convex.table_name".to_string() => table_name.to_string(),
+            },
+        );
+        update_progress(format!(
+            "Backing up changes to {table_name}{in_component_str}"
+        ))
+        .await?;
+        let excluded_fields = filter.excluded_fields(&table_name);
+        write_table_changes(
+            worker,
+            &path_prefix,
+            &mut zip_snapshot_upload,
+            TimestampRange::new((
+                Bound::Excluded(incremental.since_ts),
+                Bound::Included(*snapshot_ts),
+            ))?,
+            component_path,
+            tablet_id,
+            table_number,
+            table_name,
+            table_summary,
+            excluded_fields,
+            &rate_limiter,
+            &usage,
+        )
+        .in_span(root)
+        .await?;
+    }
+
+    zip_snapshot_upload.complete().await?;
+    writer.compat_write().close().await?;
+    Ok(())
+}
+
+async fn write_table_changes<'a, 'b: 'a, RT: Runtime>(
+    worker: &ExportWorker<RT>,
+    path_prefix: &str,
+    zip_snapshot_upload: &'a mut ZipSnapshotUpload<'b>,
+    range: TimestampRange,
+    component_path: &ComponentPath,
+    tablet_id: TabletId,
+    table_number: TableNumber,
+    table_name: TableName,
+    table_summary: TableSummary,
+    excluded_fields: Option<&BTreeSet<IdentifierFieldName>>,
+    rate_limiter: &RateLimiter<RT>,
+    usage: &FunctionUsageTracker,
+) -> anyhow::Result<()> {
+    // Only the latest revision of each document is exported. Reading the
+    // changes newest first means the first revision we see of a document is
+    // its latest, so we only need to remember which documents we've seen.
+    let stream = worker
+        .database
+        .load_documents_in_table(tablet_id, range, Order::Desc, rate_limiter)
+        .peekable();
+    pin_mut!(stream);
+    if stream.as_mut().peek().await.is_none() {
+        return Ok(());
+    }
+
+    let mut table_upload = zip_snapshot_upload
+        .start_table(path_prefix, table_name.clone())
+        .await?;
+    let inferred_type = exported_type(&table_summary, excluded_fields);
+    let mut generated_schema = GeneratedSchema::new((&inferred_type).into());
+    let is_ambiguous = ExportContext::is_ambiguous(&inferred_type);
+    let mut seen_ids = BTreeSet::new();
+    let mut deleted_ids = vec![];
+    while let Some(DocumentLogEntry { id, value, .. }) = stream.try_next().await? {
+        if !seen_ids.insert(id.internal_id()) {
+            continue;
+        }
+        let Some(doc) = value else {
+            deleted_ids.push(DeveloperDocumentId::new(table_number, id.internal_id()));
+            continue;
+        };
+        let doc = without_fields(doc, excluded_fields)?;
+        if is_ambiguous {
+            generated_schema.insert(doc.value(), doc.developer_id());
+        }
+        usage.track_database_egress_size(
+            component_path.clone(),
+            table_name.to_string(),
+            doc.size() as u64,
+            false,
+        );
+        table_upload.write(doc).await?;
+    }
+    table_upload.complete().await?;
+    zip_snapshot_upload
+        .write_generated_schema(path_prefix, &table_name, generated_schema)
+        .await?;
+
+    if !deleted_ids.is_empty() {
+        let mut deleted_upload = zip_snapshot_upload
+            .start_deleted_ids(path_prefix, &table_name)
+            .await?;
+        for id in deleted_ids {
+            deleted_upload
+                .write_json_line(json!({ "_id": id.encode() }))
+                .await?;
+        }
+        deleted_upload.complete().await?;
+    }
+    Ok(())
+}
+
+/// The shape of a table's exported documents, without the fields the export
+/// drops.
+fn exported_type(
+    table_summary: &TableSummary,
+    excluded_fields: Option<&BTreeSet<IdentifierFieldName>>,
+) -> CountedShape<ProdConfigWithOptionalFields> {
+    match excluded_fields {
+        Some(excluded_fields) => table_summary
+            .inferred_type()
+            .without_fields(excluded_fields),
+        None => table_summary.inferred_type().clone(),
+    }
+}
+
+fn without_fields(
+    doc: ResolvedDocument,
+    excluded_fields: Option<&BTreeSet<IdentifierFieldName>>,
+) -> anyhow::Result<ResolvedDocument> {
+    let Some(excluded_fields) = excluded_fields else {
+        return Ok(doc);
+    };
+    let fields: BTreeMap<FieldName, ConvexValue> = doc.value().0.clone().into();
+    let value = fields
+        .into_iter()
+        .filter(|(field_name, _)| !excluded_fields.contains(&field_name[..]))
+        .collect::<BTreeMap<_, _>>()
+        .try_into()?;
+    doc.replace_value(value)
+}
+
 fn get_export_path_prefix(component_path: &ComponentPath) -> String {
     component_path
         .iter()
@@ -0,0 +1,206 @@
+//! Parquet exports have a Parquet file for each table, so the data can be
+//! loaded straight into tools like DuckDB or Spark.
+use std::sync::Arc;
+
+use arrow_array::{
+    builder::{
+        BinaryBuilder,
+        BooleanBuilder,
+        Float64Builder,
+        Int64Builder,
+        StringBuilder,
+    },
+    ArrayRef,
+    RecordBatch,
+};
+use arrow_schema::{
+    DataType,
+    Field,
+    Schema,
+    SchemaRef,
+};
+use common::document::ResolvedDocument;
+use parquet::{
+    arrow::{
+        async_writer::AsyncFileWriter,
+        AsyncArrowWriter,
+    },
+    basic::{
+        Compression,
+        ZstdLevel,
+    },
+    file::properties::WriterProperties,
+};
+use shape_inference::{
+    CountedShape,
+    ProdConfigWithOptionalFields,
+};
+use value::{
+    export::ValueFormat,
+    ConvexValue,
+};
+
+use crate::exports::columns::{
+    ColumnType,
+    TableColumns,
+};
+
+/// Rows buffered into each record batch before it's handed to the writer.
+const BATCH_ROWS: usize = 8192;
+
+impl ColumnType {
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Int64 => DataType::Int64,
+            Self::Float64 => DataType::Float64,
+            Self::Boolean => DataType::Boolean,
+            Self::String | Self::Json => DataType::Utf8,
+            Self::Bytes => DataType::Binary,
+        }
+    }
+
+    fn builder(self) -> ColumnBuilder {
+        match self {
+            Self::Int64 => ColumnBuilder::Int64(Int64Builder::new()),
+            Self::Float64 => ColumnBuilder::Float64(Float64Builder::new()),
+            Self::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+            Self::String => ColumnBuilder::String(StringBuilder::new()),
+            Self::Bytes => ColumnBuilder::Bytes(BinaryBuilder::new()),
+            Self::Json => ColumnBuilder::Json(StringBuilder::new()),
+        }
+    }
+}
+
+enum ColumnBuilder {
+    Int64(Int64Builder),
+    Float64(Float64Builder),
+    Boolean(BooleanBuilder),
+    String(StringBuilder),
+    Bytes(BinaryBuilder),
+    Json(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn append(&mut self, value: Option<&ConvexValue>) -> anyhow::Result<()> {
+        match (self, value) {
+            (Self::Int64(builder), None | Some(ConvexValue::Null)) => builder.append_null(),
+            (Self::Float64(builder), None | Some(ConvexValue::Null)) => builder.append_null(),
+            (Self::Boolean(builder), None | Some(ConvexValue::Null)) => builder.append_null(),
+            (Self::String(builder) | Self::Json(builder), None | Some(ConvexValue::Null)) => {
+                builder.append_null()
+            },
+            (Self::Bytes(builder), None | Some(ConvexValue::Null)) => builder.append_null(),
+            (Self::Int64(builder), Some(ConvexValue::Int64(value))) => builder.append_value(*value),
+            (Self::Float64(builder), Some(ConvexValue::Float64(value))) => {
+                builder.append_value(*value)
+            },
+            (Self::Boolean(builder), Some(ConvexValue::Boolean(value))) => {
+                builder.append_value(*value)
+            },
+            (Self::String(builder), Some(ConvexValue::String(value))) => {
+                builder.append_value(&value[..])
+            },
+            (Self::Bytes(builder), Some(ConvexValue::Bytes(value))) => {
+                builder.append_value(&value[..])
+            },
+            (Self::Json(builder), Some(value)) => builder.append_value(serde_json::to_string(
+                &value.clone().export(ValueFormat::ConvexCleanJSON),
+            )?),
+            (_, Some(value)) => anyhow::bail!(
+                "{} value doesn't match the table's inferred schema",
+                value.type_name()
+            ),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Int64(builder) => Arc::new(builder.finish()),
+            Self::Float64(builder) => Arc::new(builder.finish()),
+            Self::Boolean(builder) => Arc::new(builder.finish()),
+            Self::String(builder) | Self::Json(builder) => Arc::new(builder.finish()),
+            Self::Bytes(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Writes the documents of a table to a Parquet file.
+pub struct ParquetTableWriter<W: AsyncFileWriter> {
+    table_columns: TableColumns,
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    buffered_rows: usize,
+    writer: AsyncArrowWriter<W>,
+}
+
+impl<W: AsyncFileWriter> ParquetTableWriter<W> {
+    pub fn new(
+        writer: W,
+        inferred_type: &CountedShape<ProdConfigWithOptionalFields>,
+    ) -> anyhow::Result<Self> {
+        let table_columns = TableColumns::new(inferred_type);
+        let schema = Arc::new(Schema::new(
+            table_columns
+                .columns
+                .iter()
+                .map(|column| {
+                    Field::new(
+                        column.name.clone(),
+                        column.column_type.data_type(),
+                        column.nullable,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ));
+        let builders = table_columns
+            .columns
+            .iter()
+            .map(|column| column.column_type.builder())
+            .collect();
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let writer = AsyncArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+        Ok(Self {
+            table_columns,
+            schema,
+            builders,
+            buffered_rows: 0,
+            writer,
+        })
+    }
+
+    pub async fn write(&mut self, doc: ResolvedDocument) -> anyhow::Result<()> {
+        let document = doc.into_value().0;
+        let values = self.table_columns.values(&document);
+        for ((_, value), builder) in values.zip(self.builders.iter_mut()) {
+            builder.append(value.as_deref())?;
+        }
+        self.buffered_rows += 1;
+        if self.buffered_rows >= BATCH_ROWS {
+            self.write_batch().await?;
+        }
+        Ok(())
+    }
+
+    async fn write_batch(&mut self) -> anyhow::Result<()> {
+        let arrays = self
+            .builders
+            .iter_mut()
+            .map(|builder| builder.finish())
+            .collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+        self.writer.write(&batch).await?;
+        self.buffered_rows = 0;
+        Ok(())
+    }
+
+    pub async fn complete(mut self) -> anyhow::Result<()> {
+        if self.buffered_rows > 0 {
+            self.write_batch().await?;
+        }
+        self.writer.close().await?;
+        Ok(())
+    }
+}
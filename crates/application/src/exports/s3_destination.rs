@@ -0,0 +1,417 @@
+//! Uploads exports to an external S3 bucket as they're written, so backups
+//! land off the backend's host without a separate copy step. The export is
+//! streamed to the bucket with a multipart upload, straight over the S3 API
+//! with requests signed with AWS Signature Version 4.
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use bytes::{
+    Bytes,
+    BytesMut,
+};
+use chrono::Utc;
+use common::{
+    aws::{
+        sign_request,
+        AwsCredentials,
+    },
+    sha256::Sha256,
+};
+use keybroker::KeyBroker;
+use model::exports::types::{
+    ExportDestination,
+    S3Credentials,
+};
+use quick_xml::{
+    events::Event,
+    Reader,
+};
+use reqwest::{
+    Method,
+    Url,
+};
+use tokio::sync::mpsc;
+
+/// Size of the parts of the multipart upload. S3 requires every part but the
+/// last to be at least 5 MiB.
+const PART_SIZE: usize = 8 << 20;
+
+const STS_API_VERSION: &str = "2011-06-15";
+
+/// An S3 bucket to upload exports to.
+#[derive(Clone)]
+pub struct S3Destination {
+    client: reqwest::Client,
+    credentials: AwsCredentials,
+    region: String,
+    bucket_url: Url,
+    key_prefix: String,
+}
+
+impl S3Destination {
+    /// Resolves the credentials for `destination`, decrypting its access key
+    /// or assuming its role with the backend's own AWS credentials.
+    pub async fn new(
+        destination: ExportDestination,
+        key_broker: &KeyBroker,
+    ) -> anyhow::Result<Self> {
+        let ExportDestination::S3 {
+            bucket,
+            key_prefix,
+            region,
+            endpoint,
+            credentials,
+        } = destination;
+        let client = reqwest::Client::new();
+        let credentials = match credentials {
+            S3Credentials::AccessKey {
+                access_key_id,
+                encrypted_secret_access_key,
+            } => AwsCredentials {
+                access_key_id,
+                secret_access_key: key_broker
+                    .decrypt_export_secret(&encrypted_secret_access_key)?,
+                session_token: None,
+            },
+            S3Credentials::AssumeRole {
+                role_arn,
+                external_id,
+            } => {
+                let backend_credentials = AwsCredentials::from_env()
+                    .context("The backend needs AWS credentials to assume an export's role")?;
+                assume_role(
+                    &client,
+                    &backend_credentials,
+                    &region,
+                    &role_arn,
+                    external_id.as_deref(),
+                )
+                .await?
+            },
+        };
+        let bucket_url = bucket_url(&bucket, &region, endpoint.as_deref())?;
+        Ok(Self {
+            client,
+            credentials,
+            region,
+            bucket_url,
+            key_prefix,
+        })
+    }
+
+    /// Starts a multipart upload of the object `name` under the destination's
+    /// key prefix.
+    pub async fn start_upload(self, name: &str) -> anyhow::Result<S3MultipartUpload> {
+        let url = self
+            .bucket_url
+            .join(&format!("{}{name}", self.key_prefix))?;
+        let mut create_url = url.clone();
+        create_url.set_query(Some("uploads"));
+        let response = self.send(Method::POST, create_url, vec![], &[]).await?;
+        let upload_id = xml_element_text(&response, "UploadId")?;
+        tracing::info!("Uploading export to {url}");
+        Ok(S3MultipartUpload {
+            destination: self,
+            url,
+            upload_id,
+            buffer: BytesMut::new(),
+            etags: vec![],
+        })
+    }
+
+    /// Sends a signed request to S3 and returns the response's body.
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        body: Vec<u8>,
+        headers: &[(&str, &str)],
+    ) -> anyhow::Result<String> {
+        let (_, body) = send_signed(
+            &self.client,
+            &self.credentials,
+            &self.region,
+            "s3",
+            method,
+            url,
+            body,
+            headers,
+        )
+        .await?;
+        Ok(body)
+    }
+}
+
+/// A multipart upload of an export to S3. Written bytes are buffered into
+/// parts, and the object appears in the bucket once the upload is completed.
+pub struct S3MultipartUpload {
+    destination: S3Destination,
+    url: Url,
+    upload_id: String,
+    buffer: BytesMut,
+    etags: Vec<String>,
+}
+
+impl S3MultipartUpload {
+    pub async fn write(&mut self, data: Bytes) -> anyhow::Result<()> {
+        self.buffer.extend_from_slice(&data);
+        if self.buffer.len() >= PART_SIZE {
+            self.upload_part().await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_part(&mut self) -> anyhow::Result<()> {
+        let part_number = self.etags.len() + 1;
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("partNumber", &part_number.to_string())
+            .append_pair("uploadId", &self.upload_id);
+        let body = self.buffer.split().to_vec();
+        let (headers, _) = send_signed(
+            &self.destination.client,
+            &self.destination.credentials,
+            &self.destination.region,
+            "s3",
+            Method::PUT,
+            url,
+            body,
+            &[],
+        )
+        .await?;
+        let etag = headers
+            .get("etag")
+            .context("S3 didn't return an ETag for an uploaded part")?
+            .to_str()?
+            .to_string();
+        self.etags.push(etag);
+        Ok(())
+    }
+
+    pub async fn complete(mut self) -> anyhow::Result<()> {
+        if !self.buffer.is_empty() || self.etags.is_empty() {
+            self.upload_part().await?;
+        }
+        let parts: String = self
+            .etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    quick_xml::escape::escape(etag.as_str()),
+                )
+            })
+            .collect();
+        let body = format!("<CompleteMultipartUpload>{parts}</CompleteMultipartUpload>");
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("uploadId", &self.upload_id);
+        let response = self
+            .destination
+            .send(
+                Method::POST,
+                url,
+                body.into_bytes(),
+                &[("content-type", "application/xml")],
+            )
+            .await?;
+        // S3 can fail to complete the upload after it's started responding, so
+        // errors come back in the body of a successful response.
+        if let Ok(code) = xml_element_text(&response, "Code") {
+            anyhow::bail!("S3 failed to complete the upload to {}: {code}", self.url);
+        }
+        tracing::info!("Uploaded export to {}", self.url);
+        Ok(())
+    }
+
+    pub async fn abort(self) -> anyhow::Result<()> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("uploadId", &self.upload_id);
+        self.destination
+            .send(Method::DELETE, url, vec![], &[])
+            .await?;
+        Ok(())
+    }
+}
+
+/// Uploads everything received on `receiver` to `upload`, passing it on to
+/// `sender` for the exports storage. The upload is aborted if anything fails.
+pub async fn upload_to_destination(
+    mut upload: S3MultipartUpload,
+    mut receiver: mpsc::Receiver<Bytes>,
+    sender: mpsc::Sender<Bytes>,
+) -> anyhow::Result<()> {
+    let result: anyhow::Result<()> = async {
+        while let Some(data) = receiver.recv().await {
+            upload.write(data.clone()).await?;
+            sender.send(data).await?;
+        }
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        if let Err(abort_error) = upload.abort().await {
+            tracing::warn!("Failed to abort the export's upload to S3: {abort_error:#}");
+        }
+        return Err(e);
+    }
+    drop(sender);
+    upload.complete().await
+}
+
+/// The URL of `bucket`. S3's own endpoints are addressed with the bucket in
+/// the host name, and custom endpoints with the bucket in the path, since
+/// S3-compatible stores don't always support the former.
+fn bucket_url(bucket: &str, region: &str, endpoint: Option<&str>) -> anyhow::Result<Url> {
+    let url = match endpoint {
+        Some(endpoint) => {
+            let endpoint: Url = endpoint
+                .parse()
+                .with_context(|| format!("Invalid S3 endpoint {endpoint}"))?;
+            endpoint.join(&format!("{bucket}/"))?
+        },
+        None => format!("https://{bucket}.s3.{region}.amazonaws.com/").parse()?,
+    };
+    Ok(url)
+}
+
+/// Gets temporary credentials for `role_arn` with AWS STS's `AssumeRole`.
+async fn assume_role(
+    client: &reqwest::Client,
+    credentials: &AwsCredentials,
+    region: &str,
+    role_arn: &str,
+    external_id: Option<&str>,
+) -> anyhow::Result<AwsCredentials> {
+    let mut body = url::form_urlencoded::Serializer::new(String::new());
+    body.append_pair("Action", "AssumeRole")
+        .append_pair("Version", STS_API_VERSION)
+        .append_pair("RoleArn", role_arn)
+        .append_pair("RoleSessionName", "convex-export");
+    if let Some(external_id) = external_id {
+        body.append_pair("ExternalId", external_id);
+    }
+    let (_, response) = send_signed(
+        client,
+        credentials,
+        region,
+        "sts",
+        Method::POST,
+        format!("https://sts.{region}.amazonaws.com/").parse()?,
+        body.finish().into_bytes(),
+        &[("content-type", "application/x-www-form-urlencoded")],
+    )
+    .await
+    .with_context(|| format!("Failed to assume {role_arn}"))?;
+    Ok(AwsCredentials {
+        access_key_id: xml_element_text(&response, "AccessKeyId")?,
+        secret_access_key: xml_element_text(&response, "SecretAccessKey")?,
+        session_token: Some(xml_element_text(&response, "SessionToken")?),
+    })
+}
+
+/// Sends a request signed for `service`, returning the response's headers and
+/// body.
+#[allow(clippy::too_many_arguments)]
+async fn send_signed(
+    client: &reqwest::Client,
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: Method,
+    url: Url,
+    body: Vec<u8>,
+    headers: &[(&str, &str)],
+) -> anyhow::Result<(reqwest::header::HeaderMap, String)> {
+    let mut headers: BTreeMap<String, String> = headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    // S3 requires the hash of the body as a header.
+    headers.insert(
+        "x-amz-content-sha256".to_string(),
+        Sha256::hash(&body).as_hex(),
+    );
+    sign_request(
+        credentials,
+        region,
+        service,
+        &method,
+        &url,
+        &mut headers,
+        &body,
+        Utc::now(),
+    )?;
+    let mut builder = client.request(method, url.clone()).body(body);
+    for (name, value) in &headers {
+        // reqwest sets the host header itself.
+        if name != "host" {
+            builder = builder.header(name, value);
+        }
+    }
+    let response = builder
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {url}"))?;
+    let status = response.status();
+    let response_headers = response.headers().clone();
+    let response_body = response.text().await?;
+    if !status.is_success() {
+        anyhow::bail!("{url} returned {status}: {response_body}");
+    }
+    Ok((response_headers, response_body))
+}
+
+/// The text of the first `<name>` element in `xml`.
+fn xml_element_text(xml: &str, name: &str) -> anyhow::Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut in_element = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) if element.local_name().as_ref() == name.as_bytes() => {
+                in_element = true;
+            },
+            Event::Text(text) if in_element => return Ok(text.unescape()?.into_owned()),
+            Event::End(_) if in_element => return Ok(String::new()),
+            Event::Eof => anyhow::bail!("Response has no {name}: {xml}"),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bucket_url,
+        xml_element_text,
+    };
+
+    #[test]
+    fn test_bucket_url() -> anyhow::Result<()> {
+        assert_eq!(
+            bucket_url("backups", "us-west-2", None)?.as_str(),
+            "https://backups.s3.us-west-2.amazonaws.com/"
+        );
+        assert_eq!(
+            bucket_url("backups", "us-west-2", Some("http://localhost:9000"))?.as_str(),
+            "http://localhost:9000/backups/"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xml_element_text() -> anyhow::Result<()> {
+        let response = r#"<?xml version="1.0" encoding="UTF-8"?>
+<InitiateMultipartUploadResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Bucket>backups</Bucket>
+  <Key>snapshot.zip</Key>
+  <UploadId>VXBsb2FkIElE&amp;1</UploadId>
+</InitiateMultipartUploadResult>"#;
+        assert_eq!(xml_element_text(response, "UploadId")?, "VXBsb2FkIElE&1");
+        assert!(xml_element_text(response, "Code").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use common::{
+    backoff::Backoff,
+    errors::report_error,
+    runtime::Runtime,
+    types::Timestamp,
+};
+use database::Database;
+use futures::{
+    select_biased,
+    Future,
+    FutureExt,
+};
+use keybroker::Identity;
+use model::{
+    cron_jobs::next_ts::compute_next_ts_for_schedule,
+    export_schedules::{
+        types::ExportSchedule,
+        ExportSchedulesModel,
+    },
+    exports::{
+        types::{
+            ExportFilter,
+            ExportRequestor,
+        },
+        ExportsModel,
+    },
+};
+use storage::Storage;
+
+use crate::metrics::log_worker_starting;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(900); // 15 minutes
+
+/// Requests the deployment's scheduled exports when they're due, and deletes
+/// the schedule's completed exports other than the latest `retain`. The
+/// exports themselves are run by the `ExportWorker` like any other export.
+pub struct ExportScheduleWorker<RT: Runtime> {
+    runtime: RT,
+    database: Database<RT>,
+    storage: Arc<dyn Storage>,
+    backoff: Backoff,
+}
+
+impl<RT: Runtime> ExportScheduleWorker<RT> {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        runtime: RT,
+        database: Database<RT>,
+        storage: Arc<dyn Storage>,
+    ) -> impl Future<Output = ()> + Send {
+        let mut worker = Self::new_inner(runtime, database, storage);
+        async move {
+            loop {
+                if let Err(e) = worker.run().await {
+                    report_error(&mut e.context("ExportScheduleWorker died")).await;
+                    let delay = worker.backoff.fail(&mut worker.runtime.rng());
+                    worker.runtime.wait(delay).await;
+                } else {
+                    worker.backoff.reset();
+                }
+            }
+        }
+    }
+
+    fn new_inner(runtime: RT, database: Database<RT>, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            runtime,
+            database,
+            storage,
+            backoff: Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn new_test(runtime: RT, database: Database<RT>, storage: Arc<dyn Storage>) -> Self {
+        Self::new_inner(runtime, database, storage)
+    }
+
+    // Subscribe to the export schedule. Prune the schedule's old exports, and
+    // request an export if one is due and no other export is running.
+    // Otherwise, wait until the next export is due or the schedule changes.
+    pub async fn run(&mut self) -> anyhow::Result<()> {
+        let mut tx = self.database.begin(Identity::system()).await?;
+        let Some(schedule) = ExportSchedulesModel::new(&mut tx).get().await? else {
+            let token = tx.into_token()?;
+            let subscription = self.database.subscribe(token).await?;
+            subscription.wait_for_invalidation().await;
+            return Ok(());
+        };
+        let schedule = schedule.into_value();
+
+        let object_keys_to_del = ExportsModel::new(&mut tx)
+            .prune_completed(ExportRequestor::ScheduledExport, schedule.retain)
+            .await?;
+        if !object_keys_to_del.is_empty() {
+            let num_deleted = object_keys_to_del.len();
+            for object_key in object_keys_to_del {
+                self.storage.delete_object(&object_key).await?;
+            }
+            self.database
+                .commit_with_write_source(tx, "export_schedule_worker_prune")
+                .await?;
+            tracing::info!("Deleted {num_deleted} scheduled exports beyond retention");
+            return Ok(());
+        }
+
+        let now = self.runtime.generate_timestamp()?;
+        if schedule.next_ts <= now {
+            let mut exports_model = ExportsModel::new(&mut tx);
+            // Wait for the running export to finish before requesting the next.
+            if exports_model.latest_requested().await?.is_none()
+                && exports_model.latest_in_progress().await?.is_none()
+            {
+                tracing::info!("Scheduled export due.");
+                let _status = log_worker_starting("ExportScheduleWorker");
+                let expiration_ts = scheduled_export_expiration_ts(&schedule, now)?;
+                let id = exports_model
+                    .insert_requested(
+                        schedule.format.clone(),
+                        schedule.component,
+                        ExportRequestor::ScheduledExport,
+                        Some(expiration_ts),
+                        None,
+                        schedule.destination.clone(),
+                        ExportFilter::default(),
+                        schedule.encryption.clone(),
+                    )
+                    .await?;
+                let next_ts = compute_next_ts_for_schedule(&schedule.schedule, Some(now), now)?;
+                ExportSchedulesModel::new(&mut tx)
+                    .set(ExportSchedule {
+                        next_ts,
+                        last_requested_ts: Some(now),
+                        last_export_id: Some(id.developer_id),
+                        ..schedule
+                    })
+                    .await?;
+                self.database
+                    .commit_with_write_source(tx, "export_schedule_worker_request_export")
+                    .await?;
+                return Ok(());
+            }
+            let token = tx.into_token()?;
+            let subscription = self.database.subscribe(token).await?;
+            subscription.wait_for_invalidation().await;
+            return Ok(());
+        }
+
+        let token = tx.into_token()?;
+        let subscription = self.database.subscribe(token).await?;
+        select_biased! {
+            _ = subscription.wait_for_invalidation().fuse() => {},
+            _ = self.runtime.wait(schedule.next_ts - now) => {},
+        }
+        Ok(())
+    }
+}
+
+/// Scheduled exports are deleted once `retain` newer ones complete, so they
+/// only expire if the schedule stops producing exports. Keep them for an extra
+/// period past that in case the most recent exports failed.
+fn scheduled_export_expiration_ts(
+    schedule: &ExportSchedule,
+    now: Timestamp,
+) -> anyhow::Result<u64> {
+    let mut expiration_ts = now;
+    for _ in 0..=schedule.retain {
+        expiration_ts = compute_next_ts_for_schedule(&schedule.schedule, Some(expiration_ts), now)?;
+    }
+    Ok(expiration_ts.into())
+}
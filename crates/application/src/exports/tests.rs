@@ -6,9 +6,14 @@ use std::{
     io::Cursor,
     str,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::Context;
+use arrow_array::{
+    cast::AsArray,
+    types::Float64Type,
+};
 use async_zip_reader::ZipReader;
 use bytes::Bytes;
 use common::{
@@ -17,8 +22,10 @@ use common::{
         ComponentPath,
     },
     document::ParsedDocument,
+    runtime::Runtime,
     types::{
         ConvexOrigin,
+        ObjectKey,
         TableName,
     },
     value::ConvexObject,
@@ -34,20 +41,39 @@ use file_storage::{
     FileStorage,
     TransactionalFileStorage,
 };
+use futures::StreamExt;
 use headers::ContentType;
 use keybroker::Identity;
-use maplit::btreeset;
+use maplit::{
+    btreemap,
+    btreeset,
+};
 use model::{
-    exports::types::{
-        ExportFormat,
-        ExportRequestor,
+    cron_jobs::types::CronSchedule,
+    export_schedules::{
+        types::ExportSchedule,
+        ExportSchedulesModel,
+    },
+    exports::{
+        types::{
+            Export,
+            ExportFilter,
+            ExportFormat,
+            ExportRequestor,
+            IncrementalExport,
+        },
+        ExportsModel,
     },
     file_storage::types::FileStorageEntry,
     test_helpers::DbFixturesWithModel,
 };
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use pretty_assertions::assert_eq;
 use runtime::testing::TestRuntime;
-use serde_json::json;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
 use storage::{
     LocalDirStorage,
     Storage,
@@ -68,7 +94,13 @@ use crate::{
     exports::{
         export_inner,
         get_export_path_prefix,
-        zip_uploader::README_MD_CONTENTS,
+        schedule_worker::ExportScheduleWorker,
+        zip_uploader::{
+            CSV_README_MD_CONTENTS,
+            INCREMENTAL_README_MD_CONTENTS,
+            PARQUET_README_MD_CONTENTS,
+            README_MD_CONTENTS,
+        },
     },
     test_helpers::ApplicationTestExt,
     tests::components::unmount_component,
@@ -168,8 +200,13 @@ async fn test_export_zip(rt: TestRuntime) -> anyhow::Result<()> {
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: true,
+            storage_urls: false,
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
@@ -282,8 +319,13 @@ async fn test_export_components(rt: TestRuntime) -> anyhow::Result<()> {
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: false,
+            storage_urls: false,
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
@@ -313,6 +355,103 @@ async fn test_export_components(rt: TestRuntime) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_export_filter(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application
+        .load_component_tests_modules("with-schema")
+        .await?;
+    let db = application.database().clone();
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker = ExportWorker::new_test(rt, db.clone(), storage.clone(), file_storage);
+
+    let mut tx = db.begin(Identity::system()).await?;
+    let component_path: ComponentPath = "component".parse()?;
+    let (_, child_component) =
+        BootstrapComponentsModel::new(&mut tx).must_component_path_to_ids(&component_path)?;
+    for (path_prefix, component) in [
+        ("", ComponentId::Root),
+        ("_components/component/", child_component),
+    ] {
+        write_test_data_in_component(&db, component, path_prefix, &mut BTreeMap::new()).await?;
+    }
+
+    // Leave out the root component and the messages' text.
+    let filter = ExportFilter {
+        exclude_components: btreeset! { ComponentPath::root() },
+        exclude_fields: btreemap! { "messages".parse()? => btreeset! { "text".parse()? } },
+        ..ExportFilter::default()
+    };
+    let (_, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        ExportFormat::Zip {
+            include_storage: false,
+            storage_urls: false,
+        },
+        ExportRequestor::SnapshotExport,
+        None,
+        None,
+        filter,
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+    let zip_entries = read_zip_entries(&storage, &zip_object_key).await?;
+    assert_eq!(
+        zip_entries
+            .keys()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>(),
+        btreeset! {
+            "README.md",
+            "_components/component/_tables/documents.jsonl",
+            "_components/component/messages/documents.jsonl",
+            "_components/component/messages/generated_schema.jsonl",
+        }
+    );
+    let document: JsonValue =
+        serde_json::from_slice(&zip_entries["_components/component/messages/documents.jsonl"])?;
+    assert_eq!(document["channel"], json!("c"));
+    assert!(document.get("text").is_none());
+    let generated_schema =
+        str::from_utf8(&zip_entries["_components/component/messages/generated_schema.jsonl"])?;
+    assert!(!generated_schema.contains("text"));
+
+    // Tables that aren't included are left out of every component.
+    let filter = ExportFilter {
+        include_tables: Some(btreeset! { "other".parse()? }),
+        ..ExportFilter::default()
+    };
+    let (_, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        ExportFormat::Zip {
+            include_storage: false,
+            storage_urls: false,
+        },
+        ExportRequestor::SnapshotExport,
+        None,
+        None,
+        filter,
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+    let zip_entries = read_zip_entries(&storage, &zip_object_key).await?;
+    assert_eq!(
+        zip_entries
+            .into_iter()
+            .map(|(filename, contents)| Ok((filename, String::from_utf8(contents)?)))
+            .collect::<anyhow::Result<BTreeMap<_, _>>>()?,
+        btreemap! {
+            "README.md".to_string() => README_MD_CONTENTS.to_string(),
+            "_tables/documents.jsonl".to_string() => "".to_string(),
+            "_components/component/_tables/documents.jsonl".to_string() => "".to_string(),
+        }
+    );
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_export_unmounted_components(rt: TestRuntime) -> anyhow::Result<()> {
     let application = Application::new_for_tests(&rt).await?;
@@ -339,8 +478,13 @@ async fn test_export_unmounted_components(rt: TestRuntime) -> anyhow::Result<()>
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: false,
+            storage_urls: false,
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
@@ -433,8 +577,13 @@ async fn test_export_storage(rt: TestRuntime) -> anyhow::Result<()> {
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: true,
+            storage_urls: false,
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
@@ -465,6 +614,82 @@ async fn test_export_storage(rt: TestRuntime) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_export_storage_urls(rt: TestRuntime) -> anyhow::Result<()> {
+    let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker = ExportWorker::new_test(
+        rt.clone(),
+        db.clone(),
+        storage.clone(),
+        file_storage.clone(),
+    );
+    let file_storage_wrapper = FileStorage {
+        database: db.clone(),
+        transactional_file_storage: TransactionalFileStorage::new(
+            rt,
+            file_storage,
+            ConvexOrigin::from("origin".to_string()),
+        ),
+    };
+    let file_id = file_storage_wrapper
+        .store_file(
+            TableNamespace::test_user(),
+            None,
+            Some(ContentType::jpeg()),
+            futures::stream::iter(vec![Ok(Bytes::from_static(b"abc"))]),
+            None,
+            &FunctionUsageTracker::new(),
+        )
+        .await?;
+
+    let (_, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        ExportFormat::Zip {
+            include_storage: false,
+            storage_urls: true,
+        },
+        ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+
+    let stored_bytes = storage
+        .get(&zip_object_key)
+        .await?
+        .context("object missing from storage")?
+        .collect_as_bytes()
+        .await?;
+    let mut zip_reader = ZipReader::new(Cursor::new(stored_bytes)).await?;
+    let filenames: Vec<_> = zip_reader.file_names().await?;
+    // The file itself isn't in the zip, only its metadata with a URL to
+    // download it.
+    assert!(!filenames.iter().any(
+        |filename| filename.starts_with("_storage/") && filename != "_storage/documents.jsonl"
+    ));
+    let i = filenames
+        .iter()
+        .position(|filename| filename == "_storage/documents.jsonl")
+        .context("_storage/documents.jsonl missing")?;
+    let mut documents = String::new();
+    zip_reader
+        .by_index(i)
+        .await?
+        .read()
+        .read_to_string(&mut documents)
+        .await?;
+    let metadata: JsonValue = serde_json::from_str(documents.trim())?;
+    assert_eq!(metadata["_id"], json!(file_id.encode()));
+    let url = metadata["url"].as_str().context("url missing")?;
+    assert!(!url.is_empty());
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_export_many_storage_files(rt: TestRuntime) -> anyhow::Result<()> {
     let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
@@ -506,8 +731,13 @@ async fn test_export_many_storage_files(rt: TestRuntime) -> anyhow::Result<()> {
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: true,
+            storage_urls: false,
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
@@ -568,14 +798,348 @@ async fn test_export_with_table_delete(rt: TestRuntime) -> anyhow::Result<()> {
         &mut export_worker,
         ExportFormat::Zip {
             include_storage: false,
+            storage_urls: false,
+        },
+        ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_incremental_export(rt: TestRuntime) -> anyhow::Result<()> {
+    let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker = ExportWorker::new_test(rt, db.clone(), storage.clone(), file_storage);
+    let format = ExportFormat::Zip {
+        include_storage: false,
+        storage_urls: false,
+    };
+
+    let mut tx = db.begin(Identity::system()).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+    let changed = model
+        .insert("table_0".parse()?, assert_obj!("foo" => 1))
+        .await?;
+    let deleted = model
+        .insert("table_0".parse()?, assert_obj!("foo" => 2))
+        .await?;
+    model
+        .insert("table_1".parse()?, assert_obj!("foo" => 3))
+        .await?;
+    db.commit(tx).await?;
+    let (since_ts, ..) = export_inner(
+        &mut export_worker,
+        format.clone(),
+        ExportRequestor::CloudBackup,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+
+    let mut tx = db.begin(Identity::system()).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+    model.replace(changed, assert_obj!("foo" => 10)).await?;
+    model.delete(deleted).await?;
+    let created = model
+        .insert("table_0".parse()?, assert_obj!("foo" => 4))
+        .await?;
+    db.commit(tx).await?;
+    let incremental = IncrementalExport {
+        base_export_id: DeveloperDocumentId::MIN,
+        previous_export_id: DeveloperDocumentId::MIN,
+        since_ts,
+    };
+    let (snapshot_ts, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        format,
+        ExportRequestor::CloudBackup,
+        Some(incremental),
+        None,
+        ExportFilter::default(),
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+
+    let stored_bytes = storage
+        .get(&zip_object_key)
+        .await?
+        .context("object missing from storage")?
+        .collect_as_bytes()
+        .await?;
+    let mut zip_reader = ZipReader::new(Cursor::new(stored_bytes)).await?;
+    let mut zip_entries = BTreeMap::new();
+    for (i, filename) in zip_reader.file_names().await?.into_iter().enumerate() {
+        let mut entry_contents = String::new();
+        zip_reader
+            .by_index(i)
+            .await?
+            .read()
+            .read_to_string(&mut entry_contents)
+            .await?;
+        zip_entries.insert(filename, entry_contents);
+    }
+
+    // Tables without changes are left out.
+    assert_eq!(
+        zip_entries
+            .keys()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>(),
+        btreeset! {
+            "README.md",
+            "_tables/documents.jsonl",
+            "manifest.json",
+            "table_0/deleted.jsonl",
+            "table_0/documents.jsonl",
+            "table_0/generated_schema.jsonl",
+        }
+    );
+    assert_eq!(zip_entries["README.md"], INCREMENTAL_README_MD_CONTENTS);
+    let manifest: JsonValue = serde_json::from_str(&zip_entries["manifest.json"])?;
+    assert_eq!(
+        manifest,
+        json!({
+            "type": "incremental",
+            "snapshotTs": u64::from(snapshot_ts),
+            "sinceTs": u64::from(since_ts),
+            "previousExportId": DeveloperDocumentId::MIN.encode(),
+            "baseExportId": DeveloperDocumentId::MIN.encode(),
+        })
+    );
+    let documents = zip_entries["table_0/documents.jsonl"]
+        .lines()
+        .map(|line| {
+            let document: JsonValue = serde_json::from_str(line)?;
+            Ok((document["_id"].clone(), document["foo"].clone()))
+        })
+        .collect::<anyhow::Result<BTreeSet<_>>>()?;
+    assert_eq!(
+        documents,
+        btreeset! {
+            (json!(changed.encode()), json!(10)),
+            (json!(created.encode()), json!(4)),
+        }
+    );
+    assert_eq!(
+        zip_entries["table_0/deleted.jsonl"],
+        format!("{}\n", json!({ "_id": deleted.encode() }))
+    );
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_export_parquet(rt: TestRuntime) -> anyhow::Result<()> {
+    let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker = ExportWorker::new_test(rt, db.clone(), storage.clone(), file_storage);
+
+    let mut tx = db.begin(Identity::system()).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+    let alice_id = model
+        .insert(
+            "messages".parse()?,
+            assert_obj!("author" => "alice", "likes" => 2.0, "tags" => ["a", "b"]),
+        )
+        .await?;
+    let bob_id = model
+        .insert("messages".parse()?, assert_obj!("author" => "bob"))
+        .await?;
+    db.commit(tx).await?;
+
+    let (_, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        ExportFormat::Parquet,
+        ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
+        |_| async { Ok(()) },
+    )
+    .await?;
+
+    let mut zip_entries = read_zip_entries(&storage, &zip_object_key).await?;
+    assert_eq!(
+        zip_entries
+            .keys()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>(),
+        btreeset! { "README.md", "messages/documents.parquet" }
+    );
+    assert_eq!(
+        str::from_utf8(&zip_entries["README.md"])?,
+        PARQUET_README_MD_CONTENTS
+    );
+
+    let parquet_bytes = Bytes::from(zip_entries.remove("messages/documents.parquet").unwrap());
+    let batches = ParquetRecordBatchReaderBuilder::try_new(parquet_bytes)?
+        .build()?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>(),
+        vec!["_id", "_creationTime", "author", "likes", "tags"]
+    );
+    let ids = batch.column_by_name("_id").unwrap().as_string::<i32>();
+    let authors = batch.column_by_name("author").unwrap().as_string::<i32>();
+    let likes = batch
+        .column_by_name("likes")
+        .unwrap()
+        .as_primitive::<Float64Type>();
+    let tags = batch.column_by_name("tags").unwrap().as_string::<i32>();
+    let mut rows = BTreeMap::new();
+    for i in 0..batch.num_rows() {
+        rows.insert(
+            authors.value(i),
+            (
+                ids.value(i).to_string(),
+                likes.is_valid(i).then(|| likes.value(i)),
+                // Arrays are written as JSON.
+                tags.is_valid(i).then(|| tags.value(i)),
+            ),
+        );
+    }
+    assert_eq!(
+        rows,
+        btreemap! {
+            "alice" => (alice_id.encode(), Some(2.0), Some(r#"["a","b"]"#)),
+            "bob" => (bob_id.encode(), None, None),
+        }
+    );
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_export_csv(rt: TestRuntime) -> anyhow::Result<()> {
+    let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker = ExportWorker::new_test(rt, db.clone(), storage.clone(), file_storage);
+
+    let mut tx = db.begin(Identity::system()).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+    let alice_id = model
+        .insert(
+            "messages".parse()?,
+            assert_obj!("author" => "alice", "likes" => 2, "tags" => ["a", "b"]),
+        )
+        .await?;
+    let bob_id = model
+        .insert("messages".parse()?, assert_obj!("author" => "bob, jr."))
+        .await?;
+    db.commit(tx).await?;
+
+    let (_, zip_object_key, _) = export_inner(
+        &mut export_worker,
+        ExportFormat::Csv {
+            field_order: vec!["author".to_string(), "missing".to_string()],
         },
         ExportRequestor::SnapshotExport,
+        None,
+        None,
+        ExportFilter::default(),
+        None,
         |_| async { Ok(()) },
     )
     .await?;
+
+    let zip_entries = read_zip_entries(&storage, &zip_object_key).await?;
+    assert_eq!(
+        zip_entries
+            .keys()
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>(),
+        btreeset! { "README.md", "messages/documents.csv" }
+    );
+    assert_eq!(
+        str::from_utf8(&zip_entries["README.md"])?,
+        CSV_README_MD_CONTENTS
+    );
+
+    let mut reader =
+        csv_async::AsyncReader::from_reader(&zip_entries["messages/documents.csv"][..]);
+    assert_eq!(
+        reader.headers().await?.iter().collect::<Vec<_>>(),
+        vec![
+            "author:string",
+            "_id:string",
+            "_creationTime:float64",
+            "likes:int64",
+            "tags:json",
+        ]
+    );
+    let mut rows = BTreeMap::new();
+    let mut records = reader.records();
+    while let Some(record) = records.next().await {
+        let record = record?;
+        // Skip the creation time, which changes between runs.
+        rows.insert(
+            record[0].to_string(),
+            (
+                record[1].to_string(),
+                record[3].to_string(),
+                record[4].to_string(),
+            ),
+        );
+    }
+    assert_eq!(
+        rows,
+        btreemap! {
+            "alice".to_string() => (
+                alice_id.encode(),
+                "2".to_string(),
+                r#"["a","b"]"#.to_string(),
+            ),
+            // Missing fields are empty.
+            "bob, jr.".to_string() => (bob_id.encode(), "".to_string(), "".to_string()),
+        }
+    );
     Ok(())
 }
 
+async fn read_zip_entries(
+    storage: &Arc<dyn Storage>,
+    zip_object_key: &ObjectKey,
+) -> anyhow::Result<BTreeMap<String, Vec<u8>>> {
+    let stored_bytes = storage
+        .get(zip_object_key)
+        .await?
+        .context("object missing from storage")?
+        .collect_as_bytes()
+        .await?;
+    let mut zip_reader = ZipReader::new(Cursor::new(stored_bytes)).await?;
+    let mut zip_entries = BTreeMap::new();
+    for (i, filename) in zip_reader.file_names().await?.into_iter().enumerate() {
+        let mut entry_contents = vec![];
+        zip_reader
+            .by_index(i)
+            .await?
+            .read()
+            .read_to_end(&mut entry_contents)
+            .await?;
+        zip_entries.insert(filename, entry_contents);
+    }
+    Ok(zip_entries)
+}
+
 #[test]
 fn test_get_export_path_prefix() -> anyhow::Result<()> {
     assert_eq!(get_export_path_prefix(&ComponentPath::root()), "");
@@ -590,3 +1154,71 @@ fn test_get_export_path_prefix() -> anyhow::Result<()> {
     );
     Ok(())
 }
+
+#[convex_macro::test_runtime]
+async fn test_export_schedule_worker(rt: TestRuntime) -> anyhow::Result<()> {
+    let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+    let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let file_storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::new(rt.clone())?);
+    let mut export_worker =
+        ExportWorker::new_test(rt.clone(), db.clone(), storage.clone(), file_storage);
+    let mut schedule_worker =
+        ExportScheduleWorker::new_test(rt.clone(), db.clone(), storage.clone());
+
+    let mut tx = db.begin(Identity::system()).await?;
+    ExportSchedulesModel::new(&mut tx)
+        .set(ExportSchedule {
+            schedule: CronSchedule::Cron {
+                cron_expr: "0 2 * * *".to_string(),
+                timezone: None,
+            },
+            format: ExportFormat::Zip {
+                include_storage: false,
+                storage_urls: false,
+            },
+            component: ComponentId::Root,
+            retain: 1,
+            destination: None,
+            encryption: None,
+            next_ts: rt.generate_timestamp()?,
+            last_requested_ts: None,
+            last_export_id: None,
+        })
+        .await?;
+    db.commit(tx).await?;
+
+    let mut export_ids = vec![];
+    for _ in 0..2 {
+        // The schedule's export is due, so the worker requests it.
+        schedule_worker.run().await?;
+        let mut tx = db.begin(Identity::system()).await?;
+        let schedule = ExportSchedulesModel::new(&mut tx)
+            .get()
+            .await?
+            .context("Export schedule not found")?
+            .into_value();
+        assert!(schedule.next_ts > rt.generate_timestamp()?);
+        let requested = ExportsModel::new(&mut tx)
+            .latest_requested()
+            .await?
+            .context("Export not requested")?;
+        assert_eq!(requested.requestor(), ExportRequestor::ScheduledExport);
+        assert_eq!(schedule.last_export_id, Some(requested.id().developer_id));
+        export_ids.push(requested.id().developer_id);
+
+        export_worker.run().await?;
+        rt.advance_time(Duration::from_days(1)).await;
+    }
+
+    // Only the latest export is kept.
+    schedule_worker.run().await?;
+    let mut tx = db.begin(Identity::system()).await?;
+    let exports = ExportsModel::new(&mut tx).list().await?;
+    assert_eq!(exports.len(), 1);
+    assert_eq!(exports[0].id().developer_id, export_ids[1]);
+    let Export::Completed { zip_object_key, .. } = exports[0].clone().into_value() else {
+        anyhow::bail!("Export must be in completed state");
+    };
+    assert!(storage.get(&zip_object_key).await?.is_some());
+    Ok(())
+}
@@ -23,7 +23,10 @@ use futures::{
     Future,
     FutureExt,
 };
-use keybroker::Identity;
+use keybroker::{
+    Identity,
+    KeyBroker,
+};
 use model::exports::{
     types::{
         Export,
@@ -62,6 +65,7 @@ pub struct ExportWorker<RT: Runtime> {
     pub(super) backoff: Backoff,
     pub(super) usage_tracking: UsageCounter,
     pub(super) instance_name: String,
+    pub(super) key_broker: KeyBroker,
 }
 
 impl<RT: Runtime> ExportWorker<RT> {
@@ -73,6 +77,7 @@ impl<RT: Runtime> ExportWorker<RT> {
         file_storage: Arc<dyn Storage>,
         usage_tracking: UsageCounter,
         instance_name: String,
+        key_broker: KeyBroker,
     ) -> impl Future<Output = ()> + Send {
         let mut worker = Self {
             runtime,
@@ -82,6 +87,7 @@ impl<RT: Runtime> ExportWorker<RT> {
             backoff: Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF),
             usage_tracking,
             instance_name,
+            key_broker,
         };
         async move {
             loop {
@@ -113,6 +119,7 @@ impl<RT: Runtime> ExportWorker<RT> {
             backoff: Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF),
             usage_tracking: UsageCounter::new(Arc::new(NoOpUsageEventLogger)),
             instance_name: "carnitas".to_string(),
+            key_broker: KeyBroker::dev(),
         }
     }
 
@@ -193,6 +200,10 @@ impl<RT: Runtime> ExportWorker<RT> {
         let id = export.id();
         let format = export.format();
         let requestor = export.requestor();
+        let incremental = export.incremental();
+        let destination = export.destination();
+        let filter = export.filter();
+        let encryption = export.encryption();
         drop(export); // Drop this to prevent accidentally using stale state
 
         tracing::info!("Export {id} beginning...");
@@ -201,34 +212,46 @@ impl<RT: Runtime> ExportWorker<RT> {
             let export_future = async {
                 let database_ = self.database.clone();
 
-                export_inner(self, format, requestor, |msg| async {
-                    tracing::info!("Export {id} progress: {msg}");
-                    database_
-                        .execute_with_occ_retries(
-                            Identity::system(),
-                            FunctionUsageTracker::new(),
-                            "export_worker_update_progress",
-                            move |tx| {
-                                let msg = msg.clone();
-                                async move {
-                                    let export: ParsedDocument<Export> =
-                                        tx.get(id).await?.context(ExportCanceled)?.try_into()?;
-                                    let export = export.into_value();
-                                    if let Export::Canceled { .. } = export {
-                                        anyhow::bail!(ExportCanceled);
+                export_inner(
+                    self,
+                    format,
+                    requestor,
+                    incremental,
+                    destination,
+                    filter,
+                    encryption,
+                    |msg| async {
+                        tracing::info!("Export {id} progress: {msg}");
+                        database_
+                            .execute_with_occ_retries(
+                                Identity::system(),
+                                FunctionUsageTracker::new(),
+                                "export_worker_update_progress",
+                                move |tx| {
+                                    let msg = msg.clone();
+                                    async move {
+                                        let export: ParsedDocument<Export> = tx
+                                            .get(id)
+                                            .await?
+                                            .context(ExportCanceled)?
+                                            .try_into()?;
+                                        let export = export.into_value();
+                                        if let Export::Canceled { .. } = export {
+                                            anyhow::bail!(ExportCanceled);
+                                        }
+                                        SystemMetadataModel::new_global(tx)
+                                            .replace(id, export.update_progress(msg)?.try_into()?)
+                                            .await?;
+                                        Ok(())
                                     }
-                                    SystemMetadataModel::new_global(tx)
-                                        .replace(id, export.update_progress(msg)?.try_into()?)
-                                        .await?;
-                                    Ok(())
-                                }
-                                .boxed()
-                                .into()
-                            },
-                        )
-                        .await?;
-                    Ok(())
-                })
+                                    .boxed()
+                                    .into()
+                                },
+                            )
+                            .await?;
+                        Ok(())
+                    },
+                )
                 .await
             };
             tokio::pin!(export_future);
@@ -306,7 +329,7 @@ impl<RT: Runtime> ExportWorker<RT> {
 
         let tag = requestor.usage_tag().to_string();
         let call_type = match requestor {
-            ExportRequestor::SnapshotExport => CallType::Export,
+            ExportRequestor::SnapshotExport | ExportRequestor::ScheduledExport => CallType::Export,
             ExportRequestor::CloudBackup => CallType::CloudBackup,
         };
         // Charge file bandwidth for the upload of the snapshot to exports storage
@@ -13,9 +13,16 @@ use common::{
     document::ResolvedDocument,
     types::TableName,
 };
+use csv_async::AsyncWriter;
 use futures::{
+    future::BoxFuture,
     pin_mut,
     AsyncWriteExt,
+    FutureExt,
+};
+use parquet::{
+    arrow::async_writer::AsyncFileWriter,
+    errors::ParquetError,
 };
 use serde_json::{
     json,
@@ -49,6 +56,49 @@ check out [the docs](https://docs.convex.dev/database/import-export/export) or
 ask us in [Discord](http://convex.dev/community).
 "#;
 
+pub(super) static INCREMENTAL_README_MD_CONTENTS: &str = r#"# Welcome to your Convex incremental snapshot export!
+
+This ZIP file contains the changes to the tables in your Convex deployment
+since an earlier export. manifest.json lists the export it has the changes
+since, and the full export the chain of incremental exports starts from.
+Apply the exports in order, starting from the full export, to restore the
+tables as of this export.
+
+Documents that were created or changed are listed as lines of JSON in
+<table_name>/documents.jsonl files, and the IDs of deleted documents in
+<table_name>/deleted.jsonl files. Tables without changes are left out, and
+_tables/documents.jsonl lists all of the tables as of this export.
+"#;
+
+pub(super) static PARQUET_README_MD_CONTENTS: &str = r#"# Welcome to your Convex Parquet snapshot export!
+
+This ZIP file contains a snapshot of the tables in your Convex deployment.
+
+Documents for each table are in <table_name>/documents.parquet files, with a
+column for each of the table's fields. Fields whose values don't share a single
+type, like objects and arrays, are in columns of JSON strings. Tables whose
+documents don't share a set of fields have each document as JSON in a
+`document` column.
+
+The files can be loaded straight into tools like DuckDB, Spark, or a data
+warehouse. They can't be imported with npx convex import.
+"#;
+
+pub(super) static CSV_README_MD_CONTENTS: &str = r#"# Welcome to your Convex CSV snapshot export!
+
+This ZIP file contains a snapshot of the tables in your Convex deployment.
+
+Documents for each table are in <table_name>/documents.csv files, with a column
+for each of the table's fields. Each column in the header row is named
+<field>:<type>, where the type is one of int64, float64, boolean, string,
+bytes, or json. Bytes are base64 encoded, and json columns have the clean JSON
+of values without a single type, like objects and arrays. Empty cells are
+fields that are missing or null. Tables whose documents don't share a set of
+fields have each document as JSON in a `document` column.
+
+The files can't be imported with npx convex import.
+"#;
+
 // 'a is lifetime of entire zip file writer.
 // 'b is lifetime of entry writer for a single table.
 pub struct ZipSnapshotTableUpload<'a, 'b> {
@@ -58,10 +108,8 @@ pub struct ZipSnapshotTableUpload<'a, 'b> {
 impl<'a, 'b> ZipSnapshotTableUpload<'a, 'b> {
     async fn new(
         zip_writer: &'b mut ZipFileWriter<&'a mut ChannelWriter>,
-        path_prefix: &str,
-        table_name: TableName,
+        source_path: String,
     ) -> anyhow::Result<Self> {
-        let source_path = format!("{path_prefix}{table_name}/documents.jsonl");
         let builder = ZipEntryBuilder::new(source_path.into(), Compression::Deflate)
             .unix_permissions(ZIP_ENTRY_PERMISSIONS);
         let entry_writer = zip_writer.write_entry_stream(builder.build()).await?;
@@ -86,16 +134,97 @@ impl<'a, 'b> ZipSnapshotTableUpload<'a, 'b> {
     }
 }
 
+/// A Parquet file in the zip, written by `ParquetTableWriter`. Completing the
+/// Parquet file completes the zip entry.
+pub struct ZipSnapshotParquetUpload<'a, 'b> {
+    entry_writer: Option<EntryStreamWriter<'b, &'a mut ChannelWriter>>,
+}
+
+impl AsyncFileWriter for ZipSnapshotParquetUpload<'_, '_> {
+    fn write(&mut self, bs: Bytes) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        async move {
+            let entry_writer = self.entry_writer.as_mut().ok_or_else(|| {
+                ParquetError::General("Parquet file is already complete".to_string())
+            })?;
+            entry_writer.write_all(&bs).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn complete(&mut self) -> BoxFuture<'_, parquet::errors::Result<()>> {
+        async move {
+            if let Some(entry_writer) = self.entry_writer.take() {
+                entry_writer
+                    .close()
+                    .await
+                    .map_err(|e| ParquetError::External(Box::new(e)))?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+pub struct ZipSnapshotCsvUpload<'a, 'b> {
+    writer: AsyncWriter<EntryStreamWriter<'b, &'a mut ChannelWriter>>,
+}
+
+impl ZipSnapshotCsvUpload<'_, '_> {
+    pub async fn write_record(&mut self, record: &[String]) -> anyhow::Result<()> {
+        self.writer.write_record(record).await?;
+        Ok(())
+    }
+
+    pub async fn complete(mut self) -> anyhow::Result<()> {
+        self.writer.flush().await?;
+        let entry_writer = self
+            .writer
+            .into_inner()
+            .await
+            .map_err(|_| anyhow::anyhow!("Failed to flush CSV file"))?;
+        entry_writer.close().await?;
+        Ok(())
+    }
+}
+
 pub struct ZipSnapshotUpload<'a> {
     writer: ZipFileWriter<&'a mut ChannelWriter>,
 }
 
 impl<'a> ZipSnapshotUpload<'a> {
     pub async fn new(out: &'a mut ChannelWriter) -> anyhow::Result<Self> {
+        Self::with_readme(out, README_MD_CONTENTS).await
+    }
+
+    pub async fn new_parquet(out: &'a mut ChannelWriter) -> anyhow::Result<Self> {
+        Self::with_readme(out, PARQUET_README_MD_CONTENTS).await
+    }
+
+    pub async fn new_csv(out: &'a mut ChannelWriter) -> anyhow::Result<Self> {
+        Self::with_readme(out, CSV_README_MD_CONTENTS).await
+    }
+
+    /// Starts an incremental export, which has a manifest linking it to the
+    /// exports it's applied on top of.
+    pub async fn new_incremental(
+        out: &'a mut ChannelWriter,
+        manifest: JsonValue,
+    ) -> anyhow::Result<Self> {
+        let mut zip_snapshot_upload =
+            Self::with_readme(out, INCREMENTAL_README_MD_CONTENTS).await?;
+        let manifest = serde_json::to_vec_pretty(&manifest)?;
+        zip_snapshot_upload
+            .stream_full_file("manifest.json".to_owned(), &manifest[..])
+            .await?;
+        Ok(zip_snapshot_upload)
+    }
+
+    async fn with_readme(out: &'a mut ChannelWriter, readme: &str) -> anyhow::Result<Self> {
         let writer = ZipFileWriter::with_tokio(out);
         let mut zip_snapshot_upload = Self { writer };
         zip_snapshot_upload
-            .stream_full_file("README.md".to_owned(), README_MD_CONTENTS.as_bytes())
+            .stream_full_file("README.md".to_owned(), readme.as_bytes())
             .await?;
         Ok(zip_snapshot_upload)
     }
@@ -124,7 +253,48 @@ impl<'a> ZipSnapshotUpload<'a> {
         path_prefix: &str,
         table_name: TableName,
     ) -> anyhow::Result<ZipSnapshotTableUpload<'a, '_>> {
-        ZipSnapshotTableUpload::new(&mut self.writer, path_prefix, table_name).await
+        let source_path = format!("{path_prefix}{table_name}/documents.jsonl");
+        ZipSnapshotTableUpload::new(&mut self.writer, source_path).await
+    }
+
+    pub async fn start_parquet_table(
+        &mut self,
+        path_prefix: &str,
+        table_name: &TableName,
+    ) -> anyhow::Result<ZipSnapshotParquetUpload<'a, '_>> {
+        let source_path = format!("{path_prefix}{table_name}/documents.parquet");
+        // Parquet files are already compressed.
+        let builder = ZipEntryBuilder::new(source_path.into(), Compression::Stored)
+            .unix_permissions(ZIP_ENTRY_PERMISSIONS);
+        let entry_writer = self.writer.write_entry_stream(builder.build()).await?;
+        Ok(ZipSnapshotParquetUpload {
+            entry_writer: Some(entry_writer),
+        })
+    }
+
+    pub async fn start_csv_table(
+        &mut self,
+        path_prefix: &str,
+        table_name: &TableName,
+    ) -> anyhow::Result<ZipSnapshotCsvUpload<'a, '_>> {
+        let source_path = format!("{path_prefix}{table_name}/documents.csv");
+        let builder = ZipEntryBuilder::new(source_path.into(), Compression::Deflate)
+            .unix_permissions(ZIP_ENTRY_PERMISSIONS);
+        let entry_writer = self.writer.write_entry_stream(builder.build()).await?;
+        Ok(ZipSnapshotCsvUpload {
+            writer: AsyncWriter::from_writer(entry_writer),
+        })
+    }
+
+    /// The IDs of the documents in the table deleted since the previous export,
+    /// for incremental exports.
+    pub async fn start_deleted_ids(
+        &mut self,
+        path_prefix: &str,
+        table_name: &TableName,
+    ) -> anyhow::Result<ZipSnapshotTableUpload<'a, '_>> {
+        let source_path = format!("{path_prefix}{table_name}/deleted.jsonl");
+        ZipSnapshotTableUpload::new(&mut self.writer, source_path).await
     }
 
     /// System tables have known shape, so we don't need to serialize it.
@@ -134,7 +304,8 @@ impl<'a> ZipSnapshotUpload<'a> {
         table_name: TableName,
     ) -> anyhow::Result<ZipSnapshotTableUpload<'a, '_>> {
         anyhow::ensure!(table_name.is_system());
-        ZipSnapshotTableUpload::new(&mut self.writer, path_prefix, table_name).await
+        let source_path = format!("{path_prefix}{table_name}/documents.jsonl");
+        ZipSnapshotTableUpload::new(&mut self.writer, source_path).await
     }
 
     pub async fn write_generated_schema<T: ShapeConfig>(
@@ -2,6 +2,7 @@ use std::{
     cell::Cell,
     collections::{
         BTreeMap,
+        BTreeSet,
         HashMap,
         VecDeque,
     },
@@ -22,7 +23,10 @@ use common::{
         report_error_sync,
         JsError,
     },
-    execution_context::ExecutionContext,
+    execution_context::{
+        ExecutionContext,
+        RequestId,
+    },
     identity::InertIdentity,
     knobs,
     log_lines::{
@@ -72,6 +76,7 @@ use udf::{
     HttpActionRequestHead,
     SyscallTrace,
     UdfOutcome,
+    FETCH_SYSCALL_NAME,
 };
 use udf_metrics::{
     CounterBucket,
@@ -88,6 +93,7 @@ use url::Url;
 use usage_tracking::{
     AggregatedFunctionUsageStats,
     CallType,
+    FunctionUsageStats,
     FunctionUsageTracker,
     OccInfo,
     UsageCounter,
@@ -101,6 +107,11 @@ use value::{
     ConvexArray,
 };
 
+use crate::error_groups::{
+    ErrorGroup,
+    ErrorGroups,
+};
+
 /// A function's execution is summarized by this structure and stored in the
 /// UdfExecutionLog
 #[derive(Debug, Clone)]
@@ -476,6 +487,8 @@ impl FromStr for UdfRate {
 pub enum TableRate {
     RowsRead,
     RowsWritten,
+    BytesRead,
+    BytesWritten,
 }
 
 impl FromStr for TableRate {
@@ -485,12 +498,183 @@ impl FromStr for TableRate {
         let table_rate = match r {
             "rowsRead" => TableRate::RowsRead,
             "rowsWritten" => TableRate::RowsWritten,
+            "bytesRead" => TableRate::BytesRead,
+            "bytesWritten" => TableRate::BytesWritten,
             _ => anyhow::bail!("Invalid table rate: {}", r),
         };
         Ok(table_rate)
     }
 }
 
+/// What a table's functions read and wrote over a metrics window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TableActivity {
+    pub rows_read: u64,
+    pub rows_written: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+/// A table's size along with its activity, for seeing which tables dominate
+/// load and storage growth.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableMetrics {
+    pub table_name: TableName,
+    pub document_count: u64,
+    pub total_size: u64,
+    pub activity: TableActivity,
+}
+
+/// Where a function's execution time goes, as profiled across its runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExecutionPhase {
+    /// Waiting on database reads, or for actions, on the queries they run.
+    DatabaseRead,
+    /// Running the function's JavaScript, which is the time not spent waiting
+    /// on reads or fetches.
+    JsExecution,
+    /// Waiting for the responses to an action's fetches.
+    FetchWait,
+    /// Committing a mutation's writes.
+    Commit,
+}
+
+impl ExecutionPhase {
+    pub const ALL: [ExecutionPhase; 4] = [
+        ExecutionPhase::DatabaseRead,
+        ExecutionPhase::JsExecution,
+        ExecutionPhase::FetchWait,
+        ExecutionPhase::Commit,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionPhase::DatabaseRead => "databaseRead",
+            ExecutionPhase::JsExecution => "jsExecution",
+            ExecutionPhase::FetchWait => "fetchWait",
+            ExecutionPhase::Commit => "commit",
+        }
+    }
+}
+
+/// Async syscalls that wait on the database to read documents.
+const DATABASE_READ_SYSCALLS: [&str; 6] = [
+    "1.0/get",
+    "1.0/queryStreamNext",
+    "1.0/queryPage",
+    "1.0/count",
+    "1.0/actions/query",
+    "1.0/actions/vectorSearch",
+];
+
+/// Splits a function's execution time into the phases recorded in its
+/// syscall trace. Phases a run didn't go through are left out, so they don't
+/// drag that phase's percentiles toward zero.
+fn execution_phases(
+    execution_time: Duration,
+    syscall_trace: &SyscallTrace,
+) -> Vec<(ExecutionPhase, Duration)> {
+    let mut database_read: Option<Duration> = None;
+    let mut fetch_wait: Option<Duration> = None;
+    for (name, stats) in &syscall_trace.async_syscalls {
+        let phase = if name == FETCH_SYSCALL_NAME {
+            &mut fetch_wait
+        } else if DATABASE_READ_SYSCALLS.contains(&name.as_str()) {
+            &mut database_read
+        } else {
+            continue;
+        };
+        *phase.get_or_insert_default() += stats.total_duration;
+    }
+    let waiting = database_read.unwrap_or_default() + fetch_wait.unwrap_or_default();
+    let mut phases = vec![(
+        ExecutionPhase::JsExecution,
+        execution_time.saturating_sub(waiting),
+    )];
+    phases.extend(database_read.map(|d| (ExecutionPhase::DatabaseRead, d)));
+    phases.extend(fetch_wait.map(|d| (ExecutionPhase::FetchWait, d)));
+    phases
+}
+
+/// A query or mutation that crossed one of the slow function log's
+/// thresholds.
+#[derive(Debug, Clone)]
+pub struct SlowFunctionExecution {
+    pub identifier: CanonicalizedComponentFunctionPath,
+    pub udf_type: UdfType,
+    /// When the function finished.
+    pub unix_timestamp: UnixTimestamp,
+    pub execution_time: Duration,
+    /// The indexes on user tables that the function read, like
+    /// `messages.by_channel`.
+    pub indexes_read: BTreeSet<String>,
+    pub documents_scanned: u64,
+    pub database_read_bytes: u64,
+    pub request_id: RequestId,
+}
+
+impl SlowFunctionExecution {
+    /// The slow function log record for `execution`, if it crossed any of
+    /// `thresholds`.
+    fn new(
+        execution: &FunctionExecution,
+        indexes_read: BTreeSet<String>,
+        thresholds: &SlowFunctionThresholds,
+    ) -> Option<Self> {
+        let UdfParams::Function { identifier, .. } = &execution.params else {
+            return None;
+        };
+        let execution_time = Duration::from_secs_f64(execution.execution_time);
+        let documents_scanned = execution
+            .tables_touched
+            .values()
+            .map(|stats| stats.rows_read)
+            .sum();
+        let database_read_bytes = execution.usage_stats.database_read_bytes;
+        if !thresholds.exceeded(execution_time, documents_scanned, database_read_bytes) {
+            return None;
+        }
+        Some(Self {
+            identifier: identifier.clone(),
+            udf_type: execution.udf_type,
+            unix_timestamp: execution.unix_timestamp,
+            execution_time,
+            indexes_read,
+            documents_scanned,
+            database_read_bytes,
+            request_id: execution.context.request_id.clone(),
+        })
+    }
+}
+
+/// What makes a query or mutation slow. A zero threshold is never crossed.
+struct SlowFunctionThresholds {
+    execution_time: Duration,
+    documents_scanned: u64,
+    database_read_bytes: u64,
+}
+
+impl SlowFunctionThresholds {
+    fn from_knobs() -> Self {
+        Self {
+            execution_time: *knobs::SLOW_FUNCTION_LOG_EXECUTION_TIME,
+            documents_scanned: *knobs::SLOW_FUNCTION_LOG_DOCUMENTS_SCANNED,
+            database_read_bytes: *knobs::SLOW_FUNCTION_LOG_READ_BYTES,
+        }
+    }
+
+    fn exceeded(
+        &self,
+        execution_time: Duration,
+        documents_scanned: u64,
+        database_read_bytes: u64,
+    ) -> bool {
+        (!self.execution_time.is_zero() && execution_time >= self.execution_time)
+            || (self.documents_scanned > 0 && documents_scanned >= self.documents_scanned)
+            || (self.database_read_bytes > 0 && database_read_bytes >= self.database_read_bytes)
+    }
+}
+
 #[derive(Clone)]
 pub struct FunctionExecutionLog<RT: Runtime> {
     inner: Arc<Mutex<Inner<RT>>>,
@@ -517,6 +701,8 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
                     histogram_significant_figures: *knobs::UDF_METRICS_SIGNIFICANT_FIGURES,
                 },
             ),
+            slow_executions: VecDeque::new(),
+            error_groups: ErrorGroups::default(),
         };
         Self {
             inner: Arc::new(Mutex::new(inner)),
@@ -529,6 +715,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         &self,
         outcome: &UdfOutcome,
         tables_touched: BTreeMap<TableName, TableStats>,
+        indexes_read: BTreeSet<String>,
         was_cached: bool,
         execution_time: Duration,
         caller: FunctionCaller,
@@ -538,6 +725,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self._log_query(
             outcome,
             tables_touched,
+            indexes_read,
             was_cached,
             execution_time,
             caller,
@@ -570,6 +758,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self._log_query(
             &outcome,
             BTreeMap::new(),
+            BTreeSet::new(),
             false,
             start.elapsed(),
             caller,
@@ -583,7 +772,8 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
     fn _log_query(
         &self,
         outcome: &UdfOutcome,
-        tables_touched: BTreeMap<TableName, TableStats>,
+        mut tables_touched: BTreeMap<TableName, TableStats>,
+        indexes_read: BTreeSet<String>,
         was_cached: bool,
         execution_time: Duration,
         caller: FunctionCaller,
@@ -594,6 +784,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
             TrackUsage::Track(usage_tracker) => {
                 let usage_stats = usage_tracker.gather_user_stats();
                 let aggregated = usage_stats.aggregate();
+                add_table_bytes(&mut tables_touched, &usage_stats);
                 self.usage_tracking.track_call(
                     UdfIdentifier::Function(outcome.path.clone()),
                     context.execution_id.clone(),
@@ -637,6 +828,9 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
             identity: outcome.identity.clone(),
             context,
         };
+        if !was_cached {
+            self.log_if_slow(&execution, indexes_read);
+        }
         self.log_execution(execution, true);
     }
 
@@ -644,6 +838,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         &self,
         outcome: ValidatedUdfOutcome,
         tables_touched: BTreeMap<TableName, TableStats>,
+        indexes_read: BTreeSet<String>,
         execution_time: Duration,
         caller: FunctionCaller,
         usage: FunctionUsageTracker,
@@ -652,6 +847,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self._log_mutation(
             outcome,
             tables_touched,
+            indexes_read,
             execution_time,
             caller,
             TrackUsage::Track(usage),
@@ -684,6 +880,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self._log_mutation(
             outcome,
             BTreeMap::new(),
+            BTreeSet::new(),
             start.elapsed(),
             caller,
             TrackUsage::SystemError,
@@ -697,6 +894,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         &self,
         outcome: ValidatedUdfOutcome,
         tables_touched: BTreeMap<TableName, TableStats>,
+        indexes_read: BTreeSet<String>,
         execution_time: Duration,
         caller: FunctionCaller,
         usage: FunctionUsageTracker,
@@ -706,6 +904,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self._log_mutation(
             outcome,
             tables_touched,
+            indexes_read,
             execution_time,
             caller,
             TrackUsage::Track(usage),
@@ -717,7 +916,8 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
     fn _log_mutation(
         &self,
         outcome: ValidatedUdfOutcome,
-        tables_touched: BTreeMap<TableName, TableStats>,
+        mut tables_touched: BTreeMap<TableName, TableStats>,
+        indexes_read: BTreeSet<String>,
         execution_time: Duration,
         caller: FunctionCaller,
         usage: TrackUsage,
@@ -728,6 +928,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
             TrackUsage::Track(usage_tracker) => {
                 let usage_stats = usage_tracker.gather_user_stats();
                 let aggregated = usage_stats.aggregate();
+                add_table_bytes(&mut tables_touched, &usage_stats);
                 self.usage_tracking.track_call(
                     UdfIdentifier::Function(outcome.path.clone()),
                     context.execution_id.clone(),
@@ -767,6 +968,7 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
             identity: outcome.identity,
             context,
         };
+        self.log_if_slow(&execution, indexes_read);
         self.log_execution(execution, true);
     }
 
@@ -1015,6 +1217,60 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         self.log_execution_progress(log_lines, event_source, unix_timestamp)
     }
 
+    fn log_if_slow(&self, execution: &FunctionExecution, indexes_read: BTreeSet<String>) {
+        let Some(slow_execution) = SlowFunctionExecution::new(
+            execution,
+            indexes_read,
+            &SlowFunctionThresholds::from_knobs(),
+        ) else {
+            return;
+        };
+        tracing::warn!(
+            "Slow {}: {} took {:?}, scanned {} documents and read {} bytes using indexes {:?} \
+             (request {})",
+            slow_execution.udf_type,
+            slow_execution.identifier.udf_path,
+            slow_execution.execution_time,
+            slow_execution.documents_scanned,
+            slow_execution.database_read_bytes,
+            slow_execution.indexes_read,
+            slow_execution.request_id,
+        );
+        let mut inner = self.inner.lock();
+        match inner.next_slow_time() {
+            Ok(next_time) => {
+                inner.slow_executions.push_back((next_time, slow_execution));
+                while inner.slow_executions.len() > *knobs::MAX_SLOW_FUNCTION_LOG_RECORDS {
+                    inner.slow_executions.pop_front();
+                }
+            },
+            Err(mut e) => report_error_sync(&mut e),
+        }
+    }
+
+    /// Records how long committing a mutation's writes took in its execution
+    /// profile.
+    pub fn log_mutation_commit_time(
+        &self,
+        path: &CanonicalizedComponentFunctionPath,
+        commit_time: Duration,
+    ) {
+        if path.udf_path.is_system() {
+            return;
+        }
+        let name = udf_phase_metric(
+            &UdfIdentifier::Function(path.clone()),
+            ExecutionPhase::Commit,
+        );
+        let ts = self.rt.system_time();
+        let mut inner = self.inner.lock();
+        for name in [name.as_str(), deployment_commit_time_metric()] {
+            if let Err(e) = inner.metrics.add_histogram(name, ts, commit_time) {
+                Inner::<RT>::log_metrics_error(e);
+            }
+        }
+    }
+
     fn log_execution(&self, execution: FunctionExecution, send_console_events: bool) {
         if let Err(mut e) = self
             .inner
@@ -1224,6 +1480,55 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         Ok(ret)
     }
 
+    /// The percentage of all function calls over `window` that failed, or
+    /// `None` if there weren't any.
+    pub fn deployment_failure_percentage(
+        &self,
+        window: &MetricsWindow,
+    ) -> anyhow::Result<Option<f64>> {
+        let metrics = {
+            let inner = self.inner.lock();
+            inner.metrics.clone()
+        };
+        let total = |name: &str| -> anyhow::Result<Option<f64>> {
+            let buckets = metrics.query_counter(name, window.start..window.end)?;
+            if buckets.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(buckets.iter().map(|bucket| bucket.value as f64).sum()))
+        };
+        Ok(percentage(
+            total(deployment_errors_metric())?,
+            total(deployment_invocations_metric())?,
+        ))
+    }
+
+    /// The `percentile` of how long mutations took to commit over `window`,
+    /// or `None` if none committed.
+    pub fn deployment_commit_time_percentile(
+        &self,
+        percentile: Percentile,
+        window: &MetricsWindow,
+    ) -> anyhow::Result<Option<Duration>> {
+        let metrics = {
+            let inner = self.inner.lock();
+            inner.metrics.clone()
+        };
+        let buckets =
+            metrics.query_histogram(deployment_commit_time_metric(), window.start..window.end)?;
+        let window = MetricsWindow {
+            start: window.start,
+            end: window.end,
+            num_buckets: 1,
+        };
+        let percentiles = window.resample_histograms(&metrics, buckets, &[percentile])?;
+        Ok(percentiles
+            .get(&percentile)
+            .and_then(|timeseries| timeseries.first())
+            .and_then(|(_, value)| *value)
+            .map(Duration::from_secs_f64))
+    }
+
     pub fn latency_percentiles(
         &self,
         identifier: UdfIdentifier,
@@ -1241,6 +1546,31 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         window.resample_histograms(&metrics, buckets, &percentiles)
     }
 
+    /// Percentiles of the time spent in each phase of `identifier`'s
+    /// executions over `window`.
+    pub fn execution_profile(
+        &self,
+        identifier: UdfIdentifier,
+        percentiles: Vec<Percentile>,
+        window: MetricsWindow,
+    ) -> anyhow::Result<BTreeMap<ExecutionPhase, BTreeMap<Percentile, Timeseries>>> {
+        let metrics = {
+            let inner = self.inner.lock();
+            inner.metrics.clone()
+        };
+        ExecutionPhase::ALL
+            .into_iter()
+            .map(|phase| {
+                let buckets = metrics.query_histogram(
+                    &udf_phase_metric(&identifier, phase),
+                    window.start..window.end,
+                )?;
+                let percentiles = window.resample_histograms(&metrics, buckets, &percentiles)?;
+                anyhow::Ok((phase, percentiles))
+            })
+            .collect()
+    }
+
     pub fn table_rate(
         &self,
         table_name: TableName,
@@ -1254,11 +1584,42 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         let name = match metric {
             TableRate::RowsRead => table_rows_read_metric(&table_name),
             TableRate::RowsWritten => table_rows_written_metric(&table_name),
+            TableRate::BytesRead => table_bytes_read_metric(&table_name),
+            TableRate::BytesWritten => table_bytes_written_metric(&table_name),
         };
         let buckets = metrics.query_counter(&name, window.start..window.end)?;
         window.resample_counters(&metrics, buckets, true)
     }
 
+    /// The rows and bytes each table read and wrote in `window`.
+    pub fn table_activity(
+        &self,
+        table_names: impl IntoIterator<Item = TableName>,
+        window: &MetricsWindow,
+    ) -> anyhow::Result<BTreeMap<TableName, TableActivity>> {
+        let metrics = {
+            let inner = self.inner.lock();
+            inner.metrics.clone()
+        };
+        let total = |name: MetricName| -> anyhow::Result<u64> {
+            let buckets = metrics.query_counter(&name, window.start..window.end)?;
+            let total: f64 = buckets.iter().map(|bucket| bucket.value as f64).sum();
+            Ok(total.round() as u64)
+        };
+        table_names
+            .into_iter()
+            .map(|table_name| {
+                let activity = TableActivity {
+                    rows_read: total(table_rows_read_metric(&table_name))?,
+                    rows_written: total(table_rows_written_metric(&table_name))?,
+                    bytes_read: total(table_bytes_read_metric(&table_name))?,
+                    bytes_written: total(table_bytes_written_metric(&table_name))?,
+                };
+                Ok((table_name, activity))
+            })
+            .collect()
+    }
+
     pub fn udf_summary(
         &self,
         cursor: Option<CursorMs>,
@@ -1362,6 +1723,32 @@ impl<RT: Runtime> FunctionExecutionLog<RT> {
         }
     }
 
+    /// The groups of similar errors last seen at or after `since`, most
+    /// recently seen first.
+    pub fn error_groups(&self, since: Option<UnixTimestamp>) -> Vec<ErrorGroup> {
+        let now = self.rt.unix_timestamp();
+        self.inner.lock().error_groups.groups(since, now)
+    }
+
+    /// The slow queries and mutations logged after `cursor`, oldest first,
+    /// and the cursor to pass to get the ones logged after them.
+    pub fn slow_executions(&self, cursor: CursorMs) -> (Vec<SlowFunctionExecution>, CursorMs) {
+        let inner = self.inner.lock();
+        let first_entry_ix = inner
+            .slow_executions
+            .partition_point(|(ts, _)| *ts <= cursor);
+        let entries = inner
+            .slow_executions
+            .range(first_entry_ix..)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        let new_cursor = inner
+            .slow_executions
+            .back()
+            .map_or(cursor, |(ts, _)| ts.max(cursor));
+        (entries, new_cursor)
+    }
+
     pub fn latest_cursor(&self) -> CursorMs {
         let inner = self.inner.lock();
         if let Some((new_cursor, _)) = inner.log.back() {
@@ -1467,6 +1854,8 @@ struct Inner<RT: Runtime> {
     log_waiters: WithHeapSize<Vec<oneshot::Sender<()>>>,
     log_manager: Arc<dyn LogSender>,
     metrics: MetricStore,
+    slow_executions: VecDeque<(CursorMs, SlowFunctionExecution)>,
+    error_groups: ErrorGroups,
 }
 
 impl<RT: Runtime> Inner<RT> {
@@ -1478,6 +1867,17 @@ impl<RT: Runtime> Inner<RT> {
         if let Err(e) = self.log_execution_metrics(&execution) {
             Self::log_metrics_error(e);
         };
+        let error = match &execution.params {
+            UdfParams::Function { error, .. } => error.as_ref(),
+            UdfParams::Http { result, .. } => result.as_ref().err(),
+        };
+        if let Some(error) = error {
+            self.error_groups.record(
+                error,
+                udf_metric_name(&execution.identifier()),
+                execution.unix_timestamp,
+            );
+        }
         let next_time = self.next_time()?;
 
         // Gather log lines
@@ -1544,6 +1944,8 @@ impl<RT: Runtime> Inner<RT> {
 
         let name = udf_invocations_metric(&identifier);
         self.metrics.add_counter(&name, ts, 1.0)?;
+        self.metrics
+            .add_counter(deployment_invocations_metric(), ts, 1.0)?;
 
         let is_err = match &execution.params {
             UdfParams::Function { error, .. } => error.is_some(),
@@ -1552,6 +1954,8 @@ impl<RT: Runtime> Inner<RT> {
         if is_err {
             let name = udf_errors_metric(&identifier);
             self.metrics.add_counter(&name, ts, 1.0)?;
+            self.metrics
+                .add_counter(deployment_errors_metric(), ts, 1.0)?;
         }
         if execution.udf_type == UdfType::Query {
             if execution.cached_result {
@@ -1567,6 +1971,15 @@ impl<RT: Runtime> Inner<RT> {
         self.metrics
             .add_histogram(&name, ts, Duration::from_secs_f64(execution.execution_time))?;
 
+        // Cached queries didn't run, so there's nothing to profile.
+        if !execution.cached_result {
+            let execution_time = Duration::from_secs_f64(execution.execution_time);
+            for (phase, duration) in execution_phases(execution_time, &execution.syscall_trace) {
+                let name = udf_phase_metric(&identifier, phase);
+                self.metrics.add_histogram(&name, ts, duration)?;
+            }
+        }
+
         for (table_name, table_stats) in &execution.tables_touched {
             let name = table_rows_read_metric(table_name);
             self.metrics
@@ -1574,6 +1987,12 @@ impl<RT: Runtime> Inner<RT> {
             let name = table_rows_written_metric(table_name);
             self.metrics
                 .add_counter(&name, ts, table_stats.rows_written as f32)?;
+            let name = table_bytes_read_metric(table_name);
+            self.metrics
+                .add_counter(&name, ts, table_stats.bytes_read as f32)?;
+            let name = table_bytes_written_metric(table_name);
+            self.metrics
+                .add_counter(&name, ts, table_stats.bytes_written as f32)?;
         }
         Ok(())
     }
@@ -1638,6 +2057,18 @@ impl<RT: Runtime> Inner<RT> {
         }
         Ok(next_time)
     }
+
+    fn next_slow_time(&self) -> anyhow::Result<CursorMs> {
+        let since_epoch = self
+            .rt
+            .system_time()
+            .duration_since(SystemTime::UNIX_EPOCH)?;
+        let mut next_time = since_epoch.as_secs_f64() * 1e3;
+        if let Some((last_time, _)) = self.slow_executions.back() {
+            next_time = next_time.max(last_time.next_after(f64::INFINITY));
+        }
+        Ok(next_time)
+    }
 }
 
 /// `t1 - t2` in seconds, possibly negative
@@ -1728,6 +2159,14 @@ fn udf_execution_time_metric(identifier: &UdfIdentifier) -> MetricName {
     format!("udf:{}:execution_time", udf_metric_name(identifier))
 }
 
+fn udf_phase_metric(identifier: &UdfIdentifier, phase: ExecutionPhase) -> MetricName {
+    format!(
+        "udf:{}:phase:{}",
+        udf_metric_name(identifier),
+        phase.as_str()
+    )
+}
+
 // TODO: Thread component path through here.
 fn table_rows_read_metric(table_name: &TableName) -> MetricName {
     format!("table:{}:rows_read", table_name)
@@ -1737,10 +2176,51 @@ fn table_rows_written_metric(table_name: &TableName) -> MetricName {
     format!("table:{}:rows_written", table_name)
 }
 
+fn table_bytes_read_metric(table_name: &TableName) -> MetricName {
+    format!("table:{}:bytes_read", table_name)
+}
+
+fn table_bytes_written_metric(table_name: &TableName) -> MetricName {
+    format!("table:{}:bytes_written", table_name)
+}
+
 fn scheduled_job_next_ts_metric() -> &'static str {
     "scheduled_jobs:next_ts"
 }
 
+// These aren't prefixed with `udf` so the top-k queries over functions skip
+// them.
+fn deployment_invocations_metric() -> &'static str {
+    "deployment:invocations"
+}
+
+fn deployment_errors_metric() -> &'static str {
+    "deployment:errors"
+}
+
+fn deployment_commit_time_metric() -> &'static str {
+    "deployment:commit_time"
+}
+
+/// Adds the bytes each table read and wrote, which usage tracking counts, to
+/// the rows the transaction counted.
+fn add_table_bytes(
+    tables_touched: &mut BTreeMap<TableName, TableStats>,
+    usage_stats: &FunctionUsageStats,
+) {
+    // Usage tracking keys tables by their name as a string.
+    for ((_, table_name), bytes) in usage_stats.database_egress_size.iter() {
+        if let Ok(table_name) = table_name.parse::<TableName>() {
+            tables_touched.entry(table_name).or_default().bytes_read += bytes;
+        }
+    }
+    for ((_, table_name), bytes) in usage_stats.database_ingress_size.iter() {
+        if let Ok(table_name) = table_name.parse::<TableName>() {
+            tables_touched.entry(table_name).or_default().bytes_written += bytes;
+        }
+    }
+}
+
 fn udf_metric_name(identifier: &UdfIdentifier) -> String {
     let (component, id) = identifier.clone().into_component_and_udf_path();
     match component {
@@ -1750,3 +2230,108 @@ fn udf_metric_name(identifier: &UdfIdentifier) -> String {
         None => id,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        time::Duration,
+    };
+
+    use common::{
+        components::ComponentPath,
+        types::TableStats,
+    };
+    use udf::{
+        SyscallTrace,
+        FETCH_SYSCALL_NAME,
+    };
+
+    use usage_tracking::FunctionUsageStats;
+
+    use super::{
+        add_table_bytes,
+        execution_phases,
+        ExecutionPhase,
+        SlowFunctionThresholds,
+    };
+
+    #[test]
+    fn test_slow_function_thresholds() {
+        let thresholds = SlowFunctionThresholds {
+            execution_time: Duration::from_secs(1),
+            documents_scanned: 100,
+            database_read_bytes: 0,
+        };
+        assert!(!thresholds.exceeded(Duration::from_millis(999), 99, u64::MAX));
+        assert!(thresholds.exceeded(Duration::from_secs(1), 0, 0));
+        assert!(thresholds.exceeded(Duration::ZERO, 100, 0));
+        let disabled = SlowFunctionThresholds {
+            execution_time: Duration::ZERO,
+            documents_scanned: 0,
+            database_read_bytes: 0,
+        };
+        assert!(!disabled.exceeded(Duration::from_secs(60), u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_execution_phases() {
+        let mut trace = SyscallTrace::new();
+        trace.log_async_syscall("1.0/get".to_string(), Duration::from_millis(20), true);
+        trace.log_async_syscall(
+            "1.0/queryStreamNext".to_string(),
+            Duration::from_millis(30),
+            true,
+        );
+        trace.log_async_syscall("1.0/insert".to_string(), Duration::from_millis(5), true);
+        assert_eq!(
+            execution_phases(Duration::from_millis(100), &trace),
+            vec![
+                (ExecutionPhase::JsExecution, Duration::from_millis(50)),
+                (ExecutionPhase::DatabaseRead, Duration::from_millis(50)),
+            ]
+        );
+        trace.log_async_syscall(
+            FETCH_SYSCALL_NAME.to_string(),
+            Duration::from_millis(80),
+            true,
+        );
+        assert_eq!(
+            execution_phases(Duration::from_millis(100), &trace),
+            vec![
+                (ExecutionPhase::JsExecution, Duration::ZERO),
+                (ExecutionPhase::DatabaseRead, Duration::from_millis(50)),
+                (ExecutionPhase::FetchWait, Duration::from_millis(80)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_table_bytes() -> anyhow::Result<()> {
+        let mut usage_stats = FunctionUsageStats::default();
+        usage_stats
+            .database_egress_size
+            .insert((ComponentPath::root(), "messages".to_string()), 300);
+        usage_stats
+            .database_ingress_size
+            .insert((ComponentPath::root(), "messages".to_string()), 100);
+        usage_stats
+            .database_ingress_size
+            .insert((ComponentPath::root(), "channels".to_string()), 50);
+        let mut tables_touched = BTreeMap::from([(
+            "messages".parse()?,
+            TableStats {
+                rows_read: 3,
+                rows_written: 1,
+                ..Default::default()
+            },
+        )]);
+        add_table_bytes(&mut tables_touched, &usage_stats);
+        let messages = tables_touched[&"messages".parse()?];
+        assert_eq!(messages.rows_read, 3);
+        assert_eq!(messages.bytes_read, 300);
+        assert_eq!(messages.bytes_written, 100);
+        assert_eq!(tables_touched[&"channels".parse()?].bytes_written, 50);
+        Ok(())
+    }
+}
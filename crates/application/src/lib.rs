@@ -6,6 +6,7 @@
 #![feature(duration_constructors)]
 
 use std::{
+    cmp,
     collections::{
         BTreeMap,
         HashSet,
@@ -21,15 +22,52 @@ use std::{
 
 use anyhow::Context;
 use authentication::{
-    application_auth::ApplicationAuth,
+    anonymous::{
+        anonymous_issuer,
+        anonymous_jwt_provider,
+        is_anonymous,
+        issue_anonymous_token,
+        new_anonymous_subject,
+    },
+    application_auth::{
+        ApplicationAuth,
+        TokenRevocations,
+    },
+    custom_jwt::{
+        custom_jwt_provider_for,
+        validate_custom_jwt,
+        validate_custom_jwt_provider,
+    },
+    saml::{
+        issue_saml_token,
+        saml_jwt_provider,
+        validate_saml_provider,
+        validate_saml_response,
+    },
+    service_account::{
+        validate_client_assertion,
+        validate_service_account_jwk,
+        ClientCredential,
+    },
+    token_exchange::{
+        exchanged_token_identity,
+        issue_exchanged_token,
+        parse_token_identifier,
+        token_exchange_issuer,
+        token_exchange_jwt_provider,
+    },
+    validate_auth_infos,
     validate_id_token,
     Auth0IdToken,
 };
 use bytes::Bytes;
 use common::{
+    access_rules::TableAccessRules,
     auth::{
         AuthConfig,
         AuthInfo,
+        CustomJwtProvider,
+        SamlProvider,
     },
     bootstrap_model::{
         components::handles::FunctionHandle,
@@ -54,22 +92,32 @@ use common::{
         Resource,
     },
     document::{
+        CreationTime,
         DocumentUpdate,
+        ParsedDocument,
         CREATION_TIME_FIELD_PATH,
     },
     errors::{
         report_error,
         JsError,
     },
+    http::fetch::FetchClient,
     knobs::{
+        ANONYMOUS_IDENTITIES_ENABLED,
+        ANONYMOUS_IDENTITY_TOKEN_TTL,
         APPLICATION_MAX_CONCURRENT_UPLOADS,
+        MAX_DEPENDENCY_LAYER_SIZE_BYTES,
         MAX_JOBS_CANCEL_BATCH,
+        SAML_TOKEN_TTL,
+        SERVICE_ACCOUNT_TOKEN_TTL,
         SNAPSHOT_LIST_LIMIT,
+        TOKEN_EXCHANGE_TOKEN_TTL,
     },
     log_lines::LogLines,
     log_streaming::LogSender,
     paths::FieldPath,
     persistence::Persistence,
+    query::Cursor,
     query_journal::QueryJournal,
     runtime::{
         shutdown_and_join,
@@ -81,6 +129,7 @@ use common::{
     types::{
         env_var_limit_met,
         env_var_name_not_unique,
+        AdminKey,
         ConvexOrigin,
         ConvexSite,
         CursorMs,
@@ -90,8 +139,10 @@ use common::{
         FunctionCaller,
         IndexId,
         IndexName,
+        MemberId,
         ModuleEnvironment,
         NodeDependency,
+        NodeVersion,
         ObjectKey,
         RepeatableTimestamp,
         TableName,
@@ -111,6 +162,7 @@ use database::{
     FastForwardIndexWorker,
     IndexModel,
     IndexWorker,
+    OccConflictPair,
     OccRetryStats,
     SearchIndexWorkers,
     Snapshot,
@@ -137,13 +189,19 @@ use file_storage::{
     FileRangeStream,
     FileStorage,
     FileStream,
+    FileValidators,
 };
 use function_log::{
+    ExecutionPhase,
     FunctionExecution,
     FunctionExecutionPart,
+    SlowFunctionExecution,
 };
 use function_runner::FunctionRunner;
-use futures::stream::BoxStream;
+use futures::{
+    stream::BoxStream,
+    StreamExt,
+};
 use headers::{
     ContentLength,
     ContentType,
@@ -152,12 +210,40 @@ use http_client::{
     cached_http_client_for,
     ClientPurpose,
 };
+use humansize::{
+    FormatSize,
+    BINARY,
+};
 use keybroker::{
+    AdminIdentityPrincipal,
+    AdminRole,
+    ApiKeyScopes,
     Identity,
     KeyBroker,
 };
 use maplit::btreemap;
 use model::{
+    access_rules::AccessRulesModel,
+    admin_key_audit_log::{
+        types::AdminKeyUse,
+        AdminKeyAuditLogModel,
+    },
+    admin_roles::AdminRolesModel,
+    alert_rules::{
+        types::{
+            AlertRule,
+            AlertState,
+        },
+        AlertRulesModel,
+    },
+    anonymous_identity_upgrades::{
+        types::AnonymousIdentityUpgrade,
+        AnonymousIdentityUpgradesModel,
+    },
+    api_keys::{
+        types::ApiKeyMetadata,
+        ApiKeysModel,
+    },
     auth::AuthInfoModel,
     backend_state::BackendStateModel,
     components::{
@@ -176,19 +262,47 @@ use model::{
         },
         ConfigModel,
     },
+    cron_jobs::{
+        next_ts::compute_next_ts_for_schedule,
+        types::CronSchedule,
+    },
+    custom_jwt_providers::CustomJwtProvidersModel,
+    dependency_layers::{
+        types::{
+            validate_dependency_layer_name,
+            DependencyLayer,
+        },
+        DependencyLayersModel,
+    },
     deployment_audit_log::{
-        types::DeploymentAuditLogEvent,
+        types::{
+            DeploymentAuditLogEntry,
+            DeploymentAuditLogEvent,
+        },
         DeploymentAuditLogModel,
     },
     environment_variables::{
-        types::EnvironmentVariable,
+        types::{
+            EnvVarScope,
+            EnvironmentSecretVersion,
+            EnvironmentVariable,
+            EnvironmentVariableOverride,
+        },
         EnvironmentVariablesModel,
     },
+    export_schedules::{
+        types::ExportSchedule,
+        ExportSchedulesModel,
+    },
     exports::{
         types::{
             Export,
+            ExportDestination,
+            ExportEncryption,
+            ExportFilter,
             ExportFormat,
             ExportRequestor,
+            IncrementalExport,
         },
         ExportsModel,
     },
@@ -211,7 +325,23 @@ use model::{
         },
         ModuleModel,
     },
-    scheduled_jobs::SchedulerModel,
+    saml_assertions::{
+        types::UsedSamlAssertion,
+        SamlAssertionsModel,
+    },
+    saml_providers::SamlProvidersModel,
+    scheduled_jobs::{
+        types::ScheduledJobFilter,
+        CancelJobsPage,
+        SchedulerModel,
+    },
+    service_accounts::{
+        types::{
+            ServiceAccount,
+            ServiceAccountCredential,
+        },
+        ServiceAccountsModel,
+    },
     session_requests::types::SessionRequestIdentifier,
     snapshot_imports::types::{
         ImportFormat,
@@ -226,15 +356,37 @@ use model::{
         upload_download::upload_package,
         SourcePackageModel,
     },
+    token_revocations::{
+        types::{
+            RevokedTokens,
+            TokenRevocation,
+        },
+        TokenRevocationsModel,
+    },
     udf_config::{
         types::UdfConfig,
         UdfConfigModel,
     },
+    usage_records::{
+        types::{
+            UsageCounts,
+            UsageKey,
+            UsageRecord,
+        },
+        UsageRecordsModel,
+    },
 };
 use node_executor::Actions;
 use parking_lot::Mutex;
-use rand::Rng;
-use scheduled_jobs::ScheduledJobRunner;
+use rand::{
+    distributions::Alphanumeric,
+    Rng,
+};
+use scheduled_jobs::{
+    ScheduledJobExecutorStatus,
+    ScheduledJobRunner,
+    SchedulerDrain,
+};
 use schema_worker::SchemaWorker;
 use search::{
     query::RevisionWithKeys,
@@ -285,6 +437,7 @@ use udf::{
         CONVEX_SITE,
     },
     helpers::parse_udf_args,
+    ExecutionRecording,
     HttpActionRequest,
     HttpActionResponseStreamer,
     HttpActionResult,
@@ -301,7 +454,10 @@ use usage_tracking::{
 };
 use value::{
     id_v6::DeveloperDocumentId,
-    sha256::Sha256Digest,
+    sha256::{
+        Sha256,
+        Sha256Digest,
+    },
     ConvexValue,
     Namespace,
     ResolvedDocumentId,
@@ -315,9 +471,15 @@ use vector::{
 
 use crate::{
     application_function_runner::ApplicationFunctionRunner,
-    exports::worker::ExportWorker,
+    error_groups::ErrorGroup,
+    exports::{
+        check_export_encryption,
+        schedule_worker::ExportScheduleWorker,
+        worker::ExportWorker,
+    },
     function_log::{
         FunctionExecutionLog,
+        TableMetrics,
         TableRate,
         UdfMetricSummary,
         UdfRate,
@@ -329,6 +491,7 @@ use crate::{
         RedactedLogLines,
     },
     snapshot_import::SnapshotImportWorker,
+    warmup::WarmupExecutor,
 };
 
 pub mod api;
@@ -336,6 +499,7 @@ pub mod application_function_runner;
 mod cache;
 pub mod cron_jobs;
 pub mod deploy_config;
+pub mod error_groups;
 mod exports;
 pub mod function_log;
 pub mod log_visibility;
@@ -348,6 +512,7 @@ pub mod snapshot_import;
 mod system_table_cleanup;
 mod table_summary_worker;
 pub mod valid_identifier;
+mod warmup;
 
 #[cfg(any(test, feature = "testing"))]
 pub mod test_helpers;
@@ -363,6 +528,20 @@ use crate::metrics::{
 // The maximum number of user defined modules
 pub const MAX_USER_MODULES: usize = 10000;
 
+// The maximum length of the names of API keys and service accounts
+const MAX_CREDENTIAL_NAME_LEN: usize = 128;
+
+const SERVICE_ACCOUNT_SECRET_LEN: usize = 48;
+
+// Uploaded imports are split into parts of at most this size, so only a few
+// parts of the file are in memory at a time. With 10000 parts, imports can be
+// up to 160 GiB.
+const SNAPSHOT_IMPORT_MAX_PART_SIZE: usize = 16 << 20;
+
+// How many expired SAML assertions each sign-in forgets. More than one, so the
+// table shrinks after a burst of sign-ins.
+const SAML_ASSERTION_DELETE_BATCH_SIZE: usize = 16;
+
 pub struct ConfigMetadataAndSchema {
     pub config_metadata: ConfigMetadata,
     pub schema: Option<DatabaseSchema>,
@@ -466,7 +645,13 @@ pub struct FunctionError {
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum EnvVarChange {
     Unset(EnvVarName),
+    UnsetSecret(EnvVarName),
     Set(EnvironmentVariable),
+    UnsetOverride(EnvVarScope, EnvVarName),
+    SetOverride(EnvironmentVariableOverride),
+    /// Sets a secret, which is stored encrypted and can't be read back through
+    /// the API.
+    SetSecret(EnvironmentVariable),
 }
 
 pub struct Application<RT: Runtime> {
@@ -475,6 +660,7 @@ pub struct Application<RT: Runtime> {
     runner: Arc<ApplicationFunctionRunner<RT>>,
     function_log: FunctionExecutionLog<RT>,
     file_storage: FileStorage<RT>,
+    fetch_client: Arc<dyn FetchClient>,
     files_storage: Arc<dyn Storage>,
     modules_storage: Arc<dyn Storage>,
     search_storage: Arc<dyn Storage>,
@@ -485,6 +671,8 @@ pub struct Application<RT: Runtime> {
     instance_name: String,
     scheduled_job_runner: ScheduledJobRunner,
     cron_job_executor: Arc<Mutex<Box<dyn SpawnHandle>>>,
+    scheduler_drain: Arc<SchedulerDrain>,
+    warmup_executor: Arc<Mutex<Box<dyn SpawnHandle>>>,
     index_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     fast_forward_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     search_worker: Arc<Mutex<SearchIndexWorkers>>,
@@ -493,6 +681,7 @@ pub struct Application<RT: Runtime> {
     schema_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     snapshot_import_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     export_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
+    export_schedule_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     system_table_cleanup_worker: Arc<Mutex<Box<dyn SpawnHandle>>>,
     migration_worker: Arc<Mutex<Option<Box<dyn SpawnHandle>>>>,
     log_sender: Arc<dyn LogSender>,
@@ -510,6 +699,7 @@ impl<RT: Runtime> Clone for Application<RT> {
             runner: self.runner.clone(),
             function_log: self.function_log.clone(),
             file_storage: self.file_storage.clone(),
+            fetch_client: self.fetch_client.clone(),
             files_storage: self.files_storage.clone(),
             modules_storage: self.modules_storage.clone(),
             search_storage: self.search_storage.clone(),
@@ -520,6 +710,8 @@ impl<RT: Runtime> Clone for Application<RT> {
             instance_name: self.instance_name.clone(),
             scheduled_job_runner: self.scheduled_job_runner.clone(),
             cron_job_executor: self.cron_job_executor.clone(),
+            scheduler_drain: self.scheduler_drain.clone(),
+            warmup_executor: self.warmup_executor.clone(),
             index_worker: self.index_worker.clone(),
             fast_forward_worker: self.fast_forward_worker.clone(),
             search_worker: self.search_worker.clone(),
@@ -528,6 +720,7 @@ impl<RT: Runtime> Clone for Application<RT> {
             schema_worker: self.schema_worker.clone(),
             snapshot_import_worker: self.snapshot_import_worker.clone(),
             export_worker: self.export_worker.clone(),
+            export_schedule_worker: self.export_schedule_worker.clone(),
             system_table_cleanup_worker: self.system_table_cleanup_worker.clone(),
             migration_worker: self.migration_worker.clone(),
             log_sender: self.log_sender.clone(),
@@ -553,6 +746,7 @@ impl<RT: Runtime> Application<RT> {
         key_broker: KeyBroker,
         instance_name: String,
         function_runner: Arc<dyn FunctionRunner<RT>>,
+        fetch_client: Arc<dyn FetchClient>,
         convex_origin: ConvexOrigin,
         convex_site: ConvexSite,
         searcher: Arc<dyn Searcher>,
@@ -631,12 +825,14 @@ impl<RT: Runtime> Application<RT> {
         ));
         function_runner.set_action_callbacks(runner.clone());
 
+        let scheduler_drain = SchedulerDrain::new();
         let scheduled_job_runner = ScheduledJobRunner::start(
             runtime.clone(),
             instance_name.clone(),
             database.clone(),
             runner.clone(),
             function_log.clone(),
+            scheduler_drain.clone(),
         );
 
         let cron_job_executor_fut = CronJobExecutor::start(
@@ -645,11 +841,18 @@ impl<RT: Runtime> Application<RT> {
             database.clone(),
             runner.clone(),
             function_log.clone(),
+            scheduler_drain.clone(),
         );
         let cron_job_executor = Arc::new(Mutex::new(
             runtime.spawn("cron_job_executor", cron_job_executor_fut),
         ));
 
+        let warmup_executor_fut =
+            WarmupExecutor::start(runtime.clone(), database.clone(), runner.clone());
+        let warmup_executor = Arc::new(Mutex::new(
+            runtime.spawn("warmup_executor", warmup_executor_fut),
+        ));
+
         let export_worker = ExportWorker::new(
             runtime.clone(),
             database.clone(),
@@ -657,14 +860,22 @@ impl<RT: Runtime> Application<RT> {
             files_storage.clone(),
             database.usage_counter().clone(),
             instance_name.clone(),
+            key_broker.clone(),
         );
         let export_worker = Arc::new(Mutex::new(runtime.spawn("export_worker", export_worker)));
 
+        let export_schedule_worker =
+            ExportScheduleWorker::new(runtime.clone(), database.clone(), exports_storage.clone());
+        let export_schedule_worker = Arc::new(Mutex::new(
+            runtime.spawn("export_schedule_worker", export_schedule_worker),
+        ));
+
         let snapshot_import_worker = SnapshotImportWorker::start(
             runtime.clone(),
             database.clone(),
             snapshot_imports_storage.clone(),
             file_storage.clone(),
+            fetch_client.clone(),
             database.usage_counter().clone(),
         );
         let snapshot_import_worker = Arc::new(Mutex::new(
@@ -681,12 +892,36 @@ impl<RT: Runtime> Application<RT> {
             runtime.spawn("migration_worker", migration_worker.go()),
         )));
 
+        let mut tx = database.begin(Identity::system()).await?;
+        let api_key_ids = ApiKeysModel::new(&mut tx)
+            .list()
+            .await?
+            .into_iter()
+            .map(|api_key| api_key.id().developer_id.encode())
+            .collect();
+        app_auth.set_api_key_ids(api_key_ids);
+        app_auth.set_admin_roles(AdminRolesModel::new(&mut tx).roles().await?);
+        let service_account_roles = ServiceAccountsModel::new(&mut tx)
+            .list()
+            .await?
+            .into_iter()
+            .map(|account| (account.id().developer_id.encode(), account.role))
+            .collect();
+        app_auth.set_service_account_roles(service_account_roles);
+        app_auth.set_token_revocations(token_revocations(
+            TokenRevocationsModel::new(&mut tx).list().await?,
+        ));
+        app_auth.set_anonymous_identity_upgrades(anonymous_identity_upgrades(
+            AnonymousIdentityUpgradesModel::new(&mut tx).list().await?,
+        ));
+
         Ok(Self {
             runtime,
             database,
             runner,
             function_log,
             file_storage,
+            fetch_client,
             files_storage,
             modules_storage,
             search_storage,
@@ -696,6 +931,8 @@ impl<RT: Runtime> Application<RT> {
             key_broker,
             scheduled_job_runner,
             cron_job_executor,
+            scheduler_drain,
+            warmup_executor,
             instance_name,
             index_worker,
             fast_forward_worker,
@@ -704,6 +941,7 @@ impl<RT: Runtime> Application<RT> {
             table_summary_worker,
             schema_worker,
             export_worker,
+            export_schedule_worker,
             snapshot_import_worker,
             system_table_cleanup_worker,
             migration_worker,
@@ -938,6 +1176,7 @@ impl<RT: Runtime> Application<RT> {
         journal: Option<Option<String>>,
         caller: FunctionCaller,
     ) -> anyhow::Result<RedactedQueryReturn> {
+        identity.ensure_can_call_function(&path)?;
         let persistence_version = self.database.persistence_version();
         let block_logging = self
             .log_visibility
@@ -1011,6 +1250,7 @@ impl<RT: Runtime> Application<RT> {
         caller: FunctionCaller,
     ) -> anyhow::Result<Result<RedactedMutationReturn, RedactedMutationError>> {
         identity.ensure_can_run_function(UdfType::Mutation)?;
+        identity.ensure_can_call_function(&path)?;
         let block_logging = self
             .log_visibility
             .should_redact_logs_and_error(
@@ -1073,6 +1313,7 @@ impl<RT: Runtime> Application<RT> {
         caller: FunctionCaller,
     ) -> anyhow::Result<Result<RedactedActionReturn, RedactedActionError>> {
         identity.ensure_can_run_function(UdfType::Action)?;
+        identity.ensure_can_call_function(&name)?;
 
         let block_logging = self
             .log_visibility
@@ -1313,6 +1554,9 @@ impl<RT: Runtime> Application<RT> {
         }
     }
 
+    /// Requests an export. If `previous_export_id` is set, the export is
+    /// incremental, and only has the changes since that export. If
+    /// `encryption` is set, the export is encrypted before it's uploaded.
     pub async fn request_export(
         &self,
         identity: Identity,
@@ -1320,11 +1564,18 @@ impl<RT: Runtime> Application<RT> {
         component: ComponentId,
         requestor: ExportRequestor,
         expiration_ts_ns: Option<u64>,
+        previous_export_id: Option<DeveloperDocumentId>,
+        destination: Option<ExportDestination>,
+        filter: ExportFilter,
+        encryption: Option<ExportEncryption>,
     ) -> anyhow::Result<DeveloperDocumentId> {
         anyhow::ensure!(
             identity.is_admin() || identity.is_system(),
             unauthorized_error("request_export")
         );
+        if let Some(encryption) = &encryption {
+            check_export_encryption(encryption)?;
+        }
         if let Some(expiration_ts_ns) = expiration_ts_ns {
             let now = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -1351,13 +1602,100 @@ impl<RT: Runtime> Application<RT> {
 
         let mut tx = self.begin(identity).await?;
         let mut exports_model = ExportsModel::new(&mut tx);
+        let incremental = match previous_export_id {
+            Some(previous_export_id) => {
+                anyhow::ensure!(
+                    format
+                        == ExportFormat::Zip {
+                            include_storage: false,
+                            storage_urls: false
+                        },
+                    ErrorMetadata::bad_request(
+                        "UnsupportedIncrementalExport",
+                        "Incremental exports must be zip exports without file storage"
+                    )
+                );
+                let previous_export = exports_model.get(previous_export_id).await?.context(
+                    ErrorMetadata::not_found(
+                        "ExportNotFound",
+                        format!("The previous export {previous_export_id} was not found"),
+                    ),
+                )?;
+                let Export::Completed {
+                    start_ts: since_ts,
+                    component: previous_component,
+                    incremental: previous_incremental,
+                    filter: previous_filter,
+                    ..
+                } = previous_export.into_value()
+                else {
+                    anyhow::bail!(ErrorMetadata::bad_request(
+                        "ExportNotComplete",
+                        format!("The previous export {previous_export_id} has not completed"),
+                    ));
+                };
+                anyhow::ensure!(
+                    previous_component == component,
+                    ErrorMetadata::bad_request(
+                        "IncrementalExportComponentMismatch",
+                        "An incremental export must be of the same component as the previous \
+                         export"
+                    )
+                );
+                anyhow::ensure!(
+                    previous_filter == filter,
+                    ErrorMetadata::bad_request(
+                        "IncrementalExportFilterMismatch",
+                        "An incremental export must have the same filter as the previous export"
+                    )
+                );
+                // The changes are read from the document log, which is only kept for
+                // so long.
+                let min_document_snapshot_ts = self
+                    .database
+                    .retention_validator()
+                    .min_document_snapshot_ts()
+                    .await?;
+                anyhow::ensure!(
+                    since_ts >= *min_document_snapshot_ts,
+                    ErrorMetadata::bad_request(
+                        "IncrementalExportTooOld",
+                        format!(
+                            "The previous export {previous_export_id} is too old to export the \
+                             changes since. Request a full export instead."
+                        )
+                    )
+                );
+                Some(IncrementalExport {
+                    base_export_id: previous_incremental
+                        .map_or(previous_export_id, |previous| previous.base_export_id),
+                    previous_export_id,
+                    since_ts,
+                })
+            },
+            None => None,
+        };
         let export_requested = exports_model.latest_requested().await?;
         let export_in_progress = exports_model.latest_in_progress().await?;
 
+        let audit_event = DeploymentAuditLogEvent::RequestExport {
+            format: format.clone(),
+            requestor,
+            incremental: incremental.is_some(),
+        };
         let snapshot_id = match (export_requested, export_in_progress) {
             (None, None) => {
                 exports_model
-                    .insert_requested(format, component, requestor, expiration_ts_ns)
+                    .insert_requested(
+                        format,
+                        component,
+                        requestor,
+                        expiration_ts_ns,
+                        incremental,
+                        destination,
+                        filter,
+                        encryption,
+                    )
                     .await
             },
             _ => Err(
@@ -1368,16 +1706,114 @@ impl<RT: Runtime> Application<RT> {
                     )),
             ),
         }?;
-        self.commit(tx, "request_export").await?;
+        self.commit_with_audit_log_events(tx, vec![audit_event], "request_export").await?;
         Ok(snapshot_id.into())
     }
 
+    /// The deployment's export schedule, if it has one, and the latest export
+    /// the schedule requested.
+    pub async fn get_export_schedule(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Option<(ExportSchedule, Option<Export>)>> {
+        anyhow::ensure!(
+            identity.is_admin() || identity.is_system(),
+            unauthorized_error("get_export_schedule")
+        );
+        let mut tx = self.begin(identity).await?;
+        let Some(schedule) = ExportSchedulesModel::new(&mut tx).get().await? else {
+            return Ok(None);
+        };
+        let schedule = schedule.into_value();
+        let last_export = match schedule.last_export_id {
+            Some(last_export_id) => ExportsModel::new(&mut tx)
+                .get(last_export_id)
+                .await?
+                .map(|export| export.into_value()),
+            None => None,
+        };
+        Ok(Some((schedule, last_export)))
+    }
+
+    /// Sets the deployment's export schedule, replacing any existing one. The
+    /// first export is due at the schedule's next time from now.
+    pub async fn set_export_schedule(
+        &self,
+        identity: Identity,
+        schedule: CronSchedule,
+        format: ExportFormat,
+        component: ComponentId,
+        retain: u64,
+        destination: Option<ExportDestination>,
+        encryption: Option<ExportEncryption>,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            identity.is_admin() || identity.is_system(),
+            unauthorized_error("set_export_schedule")
+        );
+        if let Some(encryption) = &encryption {
+            check_export_encryption(encryption)?;
+        }
+        anyhow::ensure!(
+            retain >= 1,
+            ErrorMetadata::bad_request(
+                "InvalidExportSchedule",
+                "An export schedule must retain at least one export"
+            )
+        );
+        let now = self.runtime.generate_timestamp()?;
+        let next_ts = compute_next_ts_for_schedule(&schedule, None, now).context(
+            ErrorMetadata::bad_request("InvalidExportSchedule", "Invalid export schedule"),
+        )?;
+        let mut tx = self.begin(identity).await?;
+        let mut model = ExportSchedulesModel::new(&mut tx);
+        let (last_requested_ts, last_export_id) = match model.get().await? {
+            Some(existing) => (existing.last_requested_ts, existing.last_export_id),
+            None => (None, None),
+        };
+        model
+            .set(ExportSchedule {
+                schedule,
+                format,
+                component,
+                retain,
+                destination,
+                encryption,
+                next_ts,
+                last_requested_ts,
+                last_export_id,
+            })
+            .await?;
+        self.commit(tx, "set_export_schedule").await?;
+        Ok(())
+    }
+
+    /// Deletes the deployment's export schedule. The exports it already made
+    /// are kept until they expire.
+    pub async fn delete_export_schedule(&self, identity: Identity) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            identity.is_admin() || identity.is_system(),
+            unauthorized_error("delete_export_schedule")
+        );
+        let mut tx = self.begin(identity).await?;
+        let deleted = ExportSchedulesModel::new(&mut tx).delete().await?;
+        anyhow::ensure!(
+            deleted,
+            ErrorMetadata::not_found(
+                "ExportScheduleNotFound",
+                "The deployment has no export schedule"
+            )
+        );
+        self.commit(tx, "delete_export_schedule").await?;
+        Ok(())
+    }
+
     pub async fn get_zip_export(
         &self,
         identity: Identity,
         id: Either<DeveloperDocumentId, Timestamp>,
     ) -> anyhow::Result<(StorageGetStream, String)> {
-        let (object_key, snapshot_ts) = {
+        let (object_key, snapshot_ts, is_encrypted) = {
             let mut tx = self.begin(identity).await?;
             let export = match id {
                 Either::Left(id) => ExportsModel::new(&mut tx).get(id).await?,
@@ -1395,8 +1831,9 @@ impl<RT: Runtime> Application<RT> {
                 Export::Completed {
                     zip_object_key,
                     start_ts,
+                    encryption,
                     ..
-                } => (zip_object_key, start_ts),
+                } => (zip_object_key, start_ts, encryption.is_some()),
                 Export::Failed { .. }
                 | Export::Canceled { .. }
                 | Export::InProgress { .. }
@@ -1417,11 +1854,15 @@ impl<RT: Runtime> Application<RT> {
                     format!("The requested export {snapshot_ts}/{object_key:?} was not found"),
                 ))?;
 
-        let filename = format!(
+        let mut filename = format!(
             // This should match the format in SnapshotExport.tsx.
             "snapshot_{}_{snapshot_ts}.zip",
             self.instance_name
         );
+        // Encrypted exports are age files of the zip.
+        if is_encrypted {
+            filename.push_str(".age");
+        }
         Ok((storage_get_stream, filename))
     }
 
@@ -1430,6 +1871,28 @@ impl<RT: Runtime> Application<RT> {
         self.exports_storage.fully_qualified_key(&zip_export_key)
     }
 
+    /// Uploads a V8 heap snapshot to exports storage, returning the key to
+    /// download it with.
+    pub async fn store_heap_snapshot(&self, snapshot: Vec<u8>) -> anyhow::Result<ObjectKey> {
+        let mut upload = self.exports_storage.start_upload().await?;
+        upload.write(snapshot.into()).await?;
+        upload.complete().await
+    }
+
+    pub async fn get_heap_snapshot(
+        &self,
+        object_key: ObjectKey,
+    ) -> anyhow::Result<(StorageGetStream, String)> {
+        let storage_get_stream = self.exports_storage.get(&object_key).await?.context(
+            ErrorMetadata::not_found(
+                "HeapSnapshotNotFound",
+                format!("The requested heap snapshot {object_key:?} was not found"),
+            ),
+        )?;
+        let filename = format!("{}_{}.heapsnapshot", self.instance_name, &*object_key);
+        Ok((storage_get_stream, filename))
+    }
+
     pub async fn update_environment_variables(
         &self,
         tx: &mut Transaction<RT>,
@@ -1459,13 +1922,70 @@ impl<RT: Runtime> Application<RT> {
                             .push(DeploymentAuditLogEvent::DeleteEnvironmentVariable { name });
                     };
                 },
+                EnvVarChange::SetOverride(env_var_override) => {
+                    let name = env_var_override.env_var.name().clone();
+                    let scope = env_var_override.scope.clone();
+                    if model
+                        .set_override(env_var_override, &self.system_env_var_names)
+                        .await?
+                    {
+                        audit_events.push(
+                            DeploymentAuditLogEvent::UpdateEnvironmentVariableOverride {
+                                name,
+                                scope,
+                            },
+                        );
+                    } else {
+                        audit_events.push(
+                            DeploymentAuditLogEvent::CreateEnvironmentVariableOverride {
+                                name,
+                                scope,
+                            },
+                        );
+                    }
+                },
+                EnvVarChange::UnsetOverride(scope, name) => {
+                    if let Some(_existing) = model.delete_override(&scope, &name).await? {
+                        audit_events.push(
+                            DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride {
+                                name,
+                                scope,
+                            },
+                        );
+                    }
+                },
+                EnvVarChange::SetSecret(EnvironmentVariable { name, value }) => {
+                    let encrypted_value = self.key_broker.encrypt_env_secret(&value);
+                    let version = model
+                        .set_secret(name.clone(), encrypted_value, &self.system_env_var_names)
+                        .await?;
+                    if version > 1 {
+                        audit_events
+                            .push(DeploymentAuditLogEvent::UpdateEnvironmentVariable { name });
+                    } else {
+                        audit_events
+                            .push(DeploymentAuditLogEvent::CreateEnvironmentVariable { name });
+                    }
+                },
+                EnvVarChange::UnsetSecret(name) => {
+                    if model.delete_secret(&name).await? {
+                        audit_events
+                            .push(DeploymentAuditLogEvent::DeleteEnvironmentVariable { name });
+                    }
+                },
             }
         }
 
         let all_env_vars = model.get_all().await?;
+        let all_secrets = model.get_all_secrets().await?;
 
         anyhow::ensure!(
-            all_env_vars.len() as u64 <= (ENV_VAR_LIMIT as u64),
+            all_env_vars.len() as u64 + all_secrets.len() as u64 <= (ENV_VAR_LIMIT as u64),
+            env_var_limit_met(),
+        );
+        let all_overrides = model.get_all_overrides().await?;
+        anyhow::ensure!(
+            all_overrides.len() as u64 <= (ENV_VAR_LIMIT as u64),
             env_var_limit_met(),
         );
 
@@ -1479,9 +1999,13 @@ impl<RT: Runtime> Application<RT> {
         tx: &mut Transaction<RT>,
         environment_variables: Vec<EnvironmentVariable>,
     ) -> anyhow::Result<Vec<DeploymentAuditLogEvent>> {
-        let all_env_vars = EnvironmentVariablesModel::new(tx).get_all().await?;
+        let mut model = EnvironmentVariablesModel::new(tx);
+        let all_env_vars = model.get_all().await?;
+        let all_secrets = model.get_all_secrets().await?;
         anyhow::ensure!(
-            environment_variables.len() as u64 + all_env_vars.len() as u64
+            environment_variables.len() as u64
+                + all_env_vars.len() as u64
+                + all_secrets.len() as u64
                 <= (ENV_VAR_LIMIT as u64),
             env_var_limit_met(),
         );
@@ -1578,6 +2102,18 @@ impl<RT: Runtime> Application<RT> {
         Ok(DeploymentAuditLogEvent::DeleteEnvironmentVariable { name })
     }
 
+    /// The versions of every secret, oldest first. Their values stay
+    /// encrypted, since only functions can read secrets.
+    pub async fn list_environment_secrets(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<BTreeMap<EnvVarName, Vec<ParsedDocument<EnvironmentSecretVersion>>>> {
+        let mut tx = self.begin(identity).await?;
+        EnvironmentVariablesModel::new(&mut tx)
+            .get_all_secrets()
+            .await
+    }
+
     pub async fn analyze(
         &self,
         udf_config: UdfConfig,
@@ -1677,7 +2213,9 @@ impl<RT: Runtime> Application<RT> {
             .await?;
             Ok(auth_config.providers)
         } else {
-            Ok(config.auth_info.clone().unwrap_or_default())
+            let auth_infos = config.auth_info.clone().unwrap_or_default();
+            validate_auth_infos(&auth_infos)?;
+            Ok(auth_infos)
         }
     }
 
@@ -1725,14 +2263,16 @@ impl<RT: Runtime> Application<RT> {
         auth_config_module: ModuleConfig,
         explanation: &str,
     ) -> anyhow::Result<AuthConfig> {
-        runner
+        let auth_config = runner
             .evaluate_auth_config(
                 auth_config_module.source,
                 auth_config_module.source_map,
                 environment_variables,
                 explanation,
             )
-            .await
+            .await?;
+        validate_auth_infos(&auth_config.providers)?;
+        Ok(auth_config)
     }
 
     #[fastrace::trace]
@@ -1854,20 +2394,34 @@ impl<RT: Runtime> Application<RT> {
     )> {
         let upload_limit = Arc::new(Semaphore::new(*APPLICATION_MAX_CONCURRENT_UPLOADS));
 
+        let dependency_layers = if !config.dependency_layers.is_empty() {
+            let mut tx = self.begin(Identity::system()).await?;
+            DependencyLayersModel::new(&mut tx)
+                .resolve(&config.dependency_layers)
+                .await?
+        } else {
+            vec![]
+        };
+
         let root_future = async {
             let permit = upload_limit.acquire().await?;
             let external_deps_id_and_pkg = if !config.node_dependencies.is_empty() {
                 let deps = self
-                    .build_external_node_deps(config.node_dependencies.clone())
+                    .build_external_node_deps(
+                        config.node_dependencies.clone(),
+                        config.node_version,
+                    )
                     .await?;
                 Some(deps)
             } else {
                 None
             };
             let app_modules = config.app_definition.modules().cloned().collect();
-            let app_pkg = self
+            let mut app_pkg = self
                 .upload_package(&app_modules, external_deps_id_and_pkg.clone())
                 .await?;
+            app_pkg.node_version = config.node_version;
+            app_pkg.dependency_layers = dependency_layers;
             drop(permit);
             Ok((external_deps_id_and_pkg, app_pkg))
         };
@@ -1878,9 +2432,11 @@ impl<RT: Runtime> Application<RT> {
             let definition_path = component_def.definition_path.clone();
             let component_modules = component_def.modules().cloned().collect();
             let upload_limit = upload_limit.clone();
+            let node_version = config.node_version;
             let component_pkg_future = async move {
                 let permit = upload_limit.acquire().await?;
-                let component_pkg = app.upload_package(&component_modules, None).await?;
+                let mut component_pkg = app.upload_package(&component_modules, None).await?;
+                component_pkg.node_version = node_version;
                 drop(permit);
                 anyhow::Ok((definition_path, component_pkg))
             };
@@ -2041,11 +2597,15 @@ impl<RT: Runtime> Application<RT> {
         .await
     }
 
+    /// Uploads the file for an import to snapshot-imports storage as it
+    /// arrives, without staging it on local disk or holding all of it in
+    /// memory.
     pub async fn upload_snapshot_import(
         &self,
         body_stream: BoxStream<'_, anyhow::Result<Bytes>>,
     ) -> anyhow::Result<FullyQualifiedObjectKey> {
         let mut upload: Box<BufferedUpload> = self.snapshot_imports_storage.start_upload().await?;
+        upload.limit_part_size(SNAPSHOT_IMPORT_MAX_PART_SIZE);
         // unclear why this reassignment is necessary
         let mut body_stream = body_stream;
         upload.try_write_parallel(&mut body_stream).await?;
@@ -2056,73 +2616,934 @@ impl<RT: Runtime> Application<RT> {
             .fully_qualified_key(&object_key))
     }
 
-    #[fastrace::trace]
-    pub async fn upload_package(
+    /// Uploads a zipped `node_modules` directory as a dependency layer,
+    /// replacing any layer with the same name. Pushes that already use the
+    /// old layer keep running with it.
+    pub async fn upload_dependency_layer(
         &self,
-        modules: &Vec<ModuleConfig>,
-        external_deps_id_and_pkg: Option<(ExternalDepsPackageId, ExternalDepsPackage)>,
-    ) -> anyhow::Result<SourcePackage> {
-        // If there are any node actions, turn on the lambdas.
-        if modules
-            .iter()
-            .any(|m| m.environment == ModuleEnvironment::Node)
-        {
-            self.runner().enable_actions()?;
-        }
-
+        identity: Identity,
+        name: String,
+        body_stream: BoxStream<'_, anyhow::Result<Bytes>>,
+    ) -> anyhow::Result<DependencyLayer> {
+        validate_dependency_layer_name(&name)?;
+        let max_size = *MAX_DEPENDENCY_LAYER_SIZE_BYTES;
+        let mut total_size = 0;
+        let body_stream = body_stream.map(move |chunk| {
+            let chunk = chunk?;
+            total_size += chunk.len();
+            anyhow::ensure!(
+                total_size <= max_size,
+                ErrorMetadata::bad_request(
+                    "DependencyLayerTooLarge",
+                    format!(
+                        "Dependency layer {name:?} exceeded the maximum size of {}",
+                        max_size.format_size(BINARY)
+                    ),
+                )
+            );
+            Ok(chunk)
+        });
+        let mut upload = self.modules_storage.start_upload().await?;
+        let (size_bytes, sha256) = upload.try_write_parallel_and_hash(body_stream).await?;
+        let storage_key = upload.complete().await?;
+        let layer = DependencyLayer {
+            name,
+            storage_key,
+            sha256,
+            size_bytes: size_bytes as u64,
+        };
         tracing::info!(
-            "Uploading package with {} modules to Storage",
-            modules.len()
+            "Uploaded dependency layer {} ({} bytes)",
+            layer.name,
+            layer.size_bytes
         );
 
-        // Canonicalize the modules
-        let package: BTreeMap<_, _> = modules
-            .iter()
-            .map(|m| (m.path.clone().canonicalize(), m))
-            .collect();
+        let mut tx = self.begin(identity).await?;
+        DependencyLayersModel::new(&mut tx)
+            .upsert(layer.clone())
+            .await?;
+        self.commit(tx, "upload_dependency_layer").await?;
+        Ok(layer)
+    }
+
+    pub async fn list_dependency_layers(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<DependencyLayer>> {
+        let mut tx = self.begin(identity).await?;
+        DependencyLayersModel::new(&mut tx).list().await
+    }
+
+    /// Deletes a dependency layer so it can't be used by later pushes. Its
+    /// archive stays in storage since existing pushes may still use it.
+    pub async fn delete_dependency_layer(
+        &self,
+        identity: Identity,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if DependencyLayersModel::new(&mut tx)
+            .delete(name)
+            .await?
+            .is_none()
+        {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "DependencyLayerNotFound",
+                format!("Dependency layer {name:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "delete_dependency_layer").await?;
+        Ok(())
+    }
+
+    /// Creates an API key, returning its id and the key itself. The key can't
+    /// be looked up again later.
+    pub async fn create_api_key(
+        &self,
+        identity: Identity,
+        name: String,
+        scopes: ApiKeyScopes,
+        expiration: Option<SystemTime>,
+    ) -> anyhow::Result<(String, AdminKey)> {
         anyhow::ensure!(
-            modules.len() == package.len(),
+            !name.is_empty() && name.len() <= MAX_CREDENTIAL_NAME_LEN,
             ErrorMetadata::bad_request(
-                "CanonicalizationConflict",
-                "Multiple modules canonicalize to the same name.",
+                "InvalidApiKeyName",
+                format!("API key names must be 1 to {MAX_CREDENTIAL_NAME_LEN} characters long"),
             )
         );
-
-        let (external_deps_package_id, external_deps_pkg) = match external_deps_id_and_pkg {
-            Some((id, pkg)) => (Some(id), Some(pkg)),
-            _ => (None, None),
-        };
-        let (storage_key, sha256, package_size) = upload_package(
-            package,
-            self.modules_storage.clone(),
-            external_deps_pkg.map(|pkg| pkg.storage_key),
-        )
-        .await?;
-
-        tracing::info!("Upload of {storage_key:?} successful");
-        tracing::info!("Source package size: {}", package_size);
-        log_source_package_size_bytes_total(package_size);
-
-        Ok(SourcePackage {
-            storage_key,
-            sha256,
-            external_deps_package_id,
-            package_size,
-        })
+        let principal = identity
+            .instance_admin_principal()
+            .context(unauthorized_error("create_api_key"))?;
+        let mut tx = self.begin(identity).await?;
+        let id = ApiKeysModel::new(&mut tx)
+            .insert(ApiKeyMetadata {
+                name,
+                scopes: scopes.clone(),
+                expiration,
+            })
+            .await?
+            .developer_id
+            .encode();
+        let key = self
+            .key_broker
+            .issue_api_key(&principal, id.clone(), &scopes, expiration)?;
+        self.commit(tx, "create_api_key").await?;
+        self.app_auth.add_api_key_id(id.clone());
+        Ok((id, key))
     }
 
-    // Clear all records for specified tables concurrently, potentially taking
-    // multiple transactions for each. Returns the total number of documents
-    // deleted.
-    pub async fn clear_tables(
+    pub async fn list_api_keys(
         &self,
-        identity: &Identity,
-        table_names: Vec<(ComponentPath, TableName)>,
-    ) -> anyhow::Result<u64> {
-        clear_tables(self, identity, table_names).await
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<ApiKeyMetadata>>> {
+        let mut tx = self.begin(identity).await?;
+        ApiKeysModel::new(&mut tx).list().await
     }
 
-    pub async fn execute_standalone_module(
+    /// Revokes an API key. Requests already authenticated with it aren't
+    /// interrupted.
+    pub async fn revoke_api_key(&self, identity: Identity, id: &str) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if ApiKeysModel::new(&mut tx).delete(id).await?.is_none() {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "ApiKeyNotFound",
+                format!("API key {id:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "revoke_api_key").await?;
+        self.app_auth.remove_api_key_id(id);
+        Ok(())
+    }
+
+    pub async fn list_admin_roles(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<BTreeMap<MemberId, AdminRole>> {
+        let mut tx = self.begin(identity).await?;
+        AdminRolesModel::new(&mut tx).roles().await
+    }
+
+    /// Registers a service account that authenticates with the public key
+    /// `jwk`, or with a generated client secret if it's `None`. Returns the
+    /// account's ID, which is its client ID, and the secret, which can't be
+    /// looked up again later.
+    pub async fn create_service_account(
+        &self,
+        identity: Identity,
+        name: String,
+        role: AdminRole,
+        jwk: Option<String>,
+    ) -> anyhow::Result<(String, Option<String>)> {
+        anyhow::ensure!(
+            !name.is_empty() && name.len() <= MAX_CREDENTIAL_NAME_LEN,
+            ErrorMetadata::bad_request(
+                "InvalidServiceAccountName",
+                format!(
+                    "Service account names must be 1 to {MAX_CREDENTIAL_NAME_LEN} characters long"
+                ),
+            )
+        );
+        let (credential, secret) = match jwk {
+            Some(jwk) => {
+                validate_service_account_jwk(&jwk)?;
+                (ServiceAccountCredential::PublicKey { jwk }, None)
+            },
+            None => {
+                let secret: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(SERVICE_ACCOUNT_SECRET_LEN)
+                    .map(char::from)
+                    .collect();
+                let sha256 = Sha256::hash(secret.as_bytes());
+                (
+                    ServiceAccountCredential::ClientSecret { sha256 },
+                    Some(secret),
+                )
+            },
+        };
+        let mut tx = self.begin(identity).await?;
+        let id = ServiceAccountsModel::new(&mut tx)
+            .insert(ServiceAccount {
+                name,
+                role,
+                credential,
+            })
+            .await?
+            .developer_id
+            .encode();
+        self.commit(tx, "create_service_account").await?;
+        self.app_auth.add_service_account(id.clone(), role);
+        Ok((id, secret))
+    }
+
+    pub async fn list_service_accounts(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<ServiceAccount>>> {
+        let mut tx = self.begin(identity).await?;
+        ServiceAccountsModel::new(&mut tx).list().await
+    }
+
+    /// Deletes a service account. Its tokens stop working right away.
+    pub async fn delete_service_account(&self, identity: Identity, id: &str) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if !ServiceAccountsModel::new(&mut tx).delete(id).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "ServiceAccountNotFound",
+                format!("Service account {id:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "delete_service_account").await?;
+        self.app_auth.remove_service_account(id);
+        Ok(())
+    }
+
+    /// Exchanges a service account's credential for an access token, which
+    /// is returned with how long it's valid for.
+    pub async fn issue_service_account_token(
+        &self,
+        client_id: &str,
+        credential: ClientCredential,
+    ) -> anyhow::Result<(AdminKey, Duration)> {
+        let invalid_credentials = || {
+            ErrorMetadata::unauthenticated(
+                "InvalidClientCredentials",
+                "The service account doesn't exist or the credentials are invalid",
+            )
+        };
+        let mut tx = self.begin(Identity::system()).await?;
+        let account = ServiceAccountsModel::new(&mut tx)
+            .get(client_id)
+            .await?
+            .with_context(invalid_credentials)?;
+        match (&account.credential, credential) {
+            (
+                ServiceAccountCredential::ClientSecret { sha256 },
+                ClientCredential::Secret(secret),
+            ) => {
+                anyhow::ensure!(
+                    Sha256::hash(secret.as_bytes()) == *sha256,
+                    invalid_credentials()
+                );
+            },
+            (
+                ServiceAccountCredential::PublicKey { jwk },
+                ClientCredential::Assertion(assertion),
+            ) => {
+                validate_client_assertion(
+                    &assertion,
+                    jwk,
+                    client_id,
+                    &self.instance_name(),
+                    self.runtime.system_time(),
+                )?;
+            },
+            _ => anyhow::bail!(invalid_credentials()),
+        }
+        let ttl = *SERVICE_ACCOUNT_TOKEN_TTL;
+        let token = self
+            .key_broker
+            .issue_service_account_token(client_id.to_string(), self.runtime.system_time() + ttl)?;
+        Ok((token, ttl))
+    }
+
+    fn token_exchange_jwt_provider(&self) -> CustomJwtProvider {
+        token_exchange_jwt_provider(
+            token_exchange_issuer(&self.instance_name),
+            self.key_broker.token_exchange_secret(),
+        )
+    }
+
+    /// Issues a token that lets the admin or service account of `identity`
+    /// act as the user with `token_identifier`, returning it with how long
+    /// it's valid for. The user must be from one of the deployment's auth
+    /// providers. Each exchange is recorded in the deployment audit log.
+    pub async fn exchange_token(
+        &self,
+        identity: Identity,
+        token_identifier: String,
+    ) -> anyhow::Result<(String, Duration)> {
+        let Identity::InstanceAdmin(admin_identity) = &identity else {
+            anyhow::bail!(ErrorMetadata::forbidden(
+                "TokenExchangeForbidden",
+                "Only admins and service accounts can exchange tokens",
+            ));
+        };
+        let actor = admin_identity.principal().to_string();
+        let (issuer, _) = parse_token_identifier(&token_identifier)?;
+        let mut tx = self.begin(identity).await?;
+        let auth_infos = AuthInfoModel::new(&mut tx).get().await?;
+        let custom_jwt_providers = CustomJwtProvidersModel::new(&mut tx).list().await?;
+        let is_known_issuer = auth_infos
+            .iter()
+            .any(|info| info.domain.trim_end_matches('/') == issuer.trim_end_matches('/'))
+            || custom_jwt_providers
+                .iter()
+                .any(|provider| provider.issuer == issuer);
+        anyhow::ensure!(
+            is_known_issuer,
+            ErrorMetadata::bad_request(
+                "UnknownSubjectIssuer",
+                format!("{issuer:?} isn't the issuer of any of the deployment's auth providers"),
+            )
+        );
+        let ttl = *TOKEN_EXCHANGE_TOKEN_TTL;
+        let token = issue_exchanged_token(
+            &self.token_exchange_jwt_provider(),
+            &token_identifier,
+            actor.clone(),
+            self.runtime.system_time(),
+            ttl,
+        )?;
+        self.commit_with_audit_log_events(
+            tx,
+            vec![DeploymentAuditLogEvent::ExchangeToken {
+                token_identifier,
+                actor,
+            }],
+            "exchange_token",
+        )
+        .await?;
+        Ok((token, ttl))
+    }
+
+    /// Adds a custom JWT provider, returning its ID. Providers for the same
+    /// issuer must have different audiences.
+    pub async fn create_custom_jwt_provider(
+        &self,
+        identity: Identity,
+        provider: CustomJwtProvider,
+    ) -> anyhow::Result<String> {
+        validate_custom_jwt_provider(&provider)?;
+        let mut tx = self.begin(identity).await?;
+        let mut model = CustomJwtProvidersModel::new(&mut tx);
+        // Providers can share an issuer if their audiences differ, so each token
+        // matches one of them.
+        anyhow::ensure!(
+            !model.list().await?.iter().any(|existing| {
+                existing.issuer == provider.issuer && existing.audience == provider.audience
+            }),
+            ErrorMetadata::bad_request(
+                "DuplicateCustomJwtProvider",
+                format!(
+                    "There's already a custom JWT provider for {:?} with the audience {:?}",
+                    provider.issuer, provider.audience
+                ),
+            )
+        );
+        let id = model.insert(provider).await?.developer_id.encode();
+        self.commit(tx, "create_custom_jwt_provider").await?;
+        Ok(id)
+    }
+
+    pub async fn list_custom_jwt_providers(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<CustomJwtProvider>>> {
+        let mut tx = self.begin(identity).await?;
+        CustomJwtProvidersModel::new(&mut tx).list().await
+    }
+
+    /// Deletes a custom JWT provider. Its tokens are rejected from then on,
+    /// though connected clients keep the identity they authenticated with
+    /// until their token expires.
+    pub async fn delete_custom_jwt_provider(
+        &self,
+        identity: Identity,
+        id: &str,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if !CustomJwtProvidersModel::new(&mut tx).delete(id).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "CustomJwtProviderNotFound",
+                format!("Custom JWT provider {id:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "delete_custom_jwt_provider").await?;
+        Ok(())
+    }
+
+    /// Adds a SAML identity provider, returning its ID. Each entity ID can only
+    /// have one provider.
+    pub async fn create_saml_provider(
+        &self,
+        identity: Identity,
+        provider: SamlProvider,
+    ) -> anyhow::Result<String> {
+        validate_saml_provider(&provider)?;
+        let mut tx = self.begin(identity).await?;
+        let mut model = SamlProvidersModel::new(&mut tx);
+        anyhow::ensure!(
+            !model
+                .list()
+                .await?
+                .iter()
+                .any(|existing| existing.entity_id == provider.entity_id),
+            ErrorMetadata::bad_request(
+                "DuplicateSamlProvider",
+                format!(
+                    "There's already a SAML provider for {:?}",
+                    provider.entity_id
+                ),
+            )
+        );
+        let id = model.insert(provider).await?.developer_id.encode();
+        self.commit(tx, "create_saml_provider").await?;
+        Ok(id)
+    }
+
+    pub async fn list_saml_providers(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<SamlProvider>>> {
+        let mut tx = self.begin(identity).await?;
+        SamlProvidersModel::new(&mut tx).list().await
+    }
+
+    /// Deletes a SAML identity provider. The tokens exchanged for its
+    /// assertions are rejected from then on.
+    pub async fn delete_saml_provider(&self, identity: Identity, id: &str) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if !SamlProvidersModel::new(&mut tx).delete(id).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "SamlProviderNotFound",
+                format!("SAML provider {id:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "delete_saml_provider").await?;
+        Ok(())
+    }
+
+    /// Verifies a `SAMLResponse` posted to the assertion consumer service and
+    /// exchanges its assertion for a token users authenticate with, returning
+    /// it with when it expires. Each assertion can only be exchanged once.
+    pub async fn complete_saml_sign_in(
+        &self,
+        saml_response: &str,
+        sp_entity_id: &str,
+        acs_url: &str,
+    ) -> anyhow::Result<(String, SystemTime)> {
+        let mut tx = self.begin(Identity::system()).await?;
+        let providers: Vec<_> = SamlProvidersModel::new(&mut tx)
+            .list()
+            .await?
+            .into_iter()
+            .map(|provider| provider.into_value())
+            .collect();
+        let system_time = self.runtime.system_time();
+        let (provider, assertion) = validate_saml_response(
+            saml_response,
+            &providers,
+            sp_entity_id,
+            acs_url,
+            system_time,
+        )?;
+        let first_use = SamlAssertionsModel::new(&mut tx)
+            .record_use(UsedSamlAssertion {
+                issuer: assertion.issuer.clone(),
+                assertion_id: assertion.id.clone(),
+                expiration: assertion.not_on_or_after,
+            })
+            .await?;
+        anyhow::ensure!(
+            first_use,
+            ErrorMetadata::unauthenticated(
+                "SamlAssertionReplayed",
+                "The SAML assertion was already used",
+            )
+        );
+        self.commit(tx, "complete_saml_sign_in").await?;
+        if let Err(mut e) = self.delete_expired_saml_assertions(system_time).await {
+            report_error(&mut e).await;
+        }
+        let mut expiration = system_time + *SAML_TOKEN_TTL;
+        if let Some(session_expiration) = assertion.session_not_on_or_after {
+            expiration = expiration.min(session_expiration);
+        }
+        let jwt_provider = saml_jwt_provider(provider, self.key_broker.saml_token_secret());
+        let token = issue_saml_token(provider, &jwt_provider, assertion, system_time, expiration)?;
+        Ok((token, expiration))
+    }
+
+    /// Forgets a batch of the used SAML assertions that have expired, since
+    /// they're rejected anyway. It's done in its own transaction so
+    /// concurrent sign-ins don't conflict over the same expired assertions.
+    async fn delete_expired_saml_assertions(&self, now: SystemTime) -> anyhow::Result<()> {
+        let mut tx = self.begin(Identity::system()).await?;
+        let deleted = SamlAssertionsModel::new(&mut tx)
+            .delete_expired(now, SAML_ASSERTION_DELETE_BATCH_SIZE)
+            .await?;
+        if deleted > 0 {
+            self.commit(tx, "delete_expired_saml_assertions").await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_access_rules(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<TableAccessRules>>> {
+        let mut tx = self.begin(identity).await?;
+        AccessRulesModel::new(&mut tx).list().await
+    }
+
+    /// Sets the access rules for a user table, replacing any it had. They
+    /// apply to functions run from then on.
+    pub async fn set_access_rules(
+        &self,
+        identity: Identity,
+        rules: TableAccessRules,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !rules.table.is_system(),
+            ErrorMetadata::bad_request(
+                "InvalidAccessRulesTable",
+                format!("Access rules can't be set on system table {}", rules.table),
+            )
+        );
+        let mut tx = self.begin(identity).await?;
+        AccessRulesModel::new(&mut tx).set(rules).await?;
+        self.commit(tx, "set_access_rules").await?;
+        Ok(())
+    }
+
+    pub async fn delete_access_rules(
+        &self,
+        identity: Identity,
+        table: &TableName,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        if !AccessRulesModel::new(&mut tx).delete(table).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "AccessRulesNotFound",
+                format!("Table {table} doesn't have access rules"),
+            ));
+        }
+        self.commit(tx, "delete_access_rules").await?;
+        Ok(())
+    }
+
+    /// Appends a request made with an admin or deploy key to the audit log.
+    pub async fn record_admin_key_use(&self, key_use: AdminKeyUse) -> anyhow::Result<()> {
+        let mut tx = self.begin(Identity::system()).await?;
+        AdminKeyAuditLogModel::new(&mut tx).insert(key_use).await?;
+        self.commit(tx, "record_admin_key_use").await?;
+        Ok(())
+    }
+
+    /// The most recent `limit` requests made with admin or deploy keys,
+    /// newest first.
+    pub async fn admin_key_audit_log(
+        &self,
+        identity: Identity,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ParsedDocument<AdminKeyUse>>> {
+        let mut tx = self.begin(identity).await?;
+        AdminKeyAuditLogModel::new(&mut tx).list_recent(limit).await
+    }
+
+    /// The most recent `limit` administrative changes to the deployment made
+    /// before `before`, newest first.
+    pub async fn deployment_audit_log(
+        &self,
+        identity: Identity,
+        before: Option<CreationTime>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ParsedDocument<DeploymentAuditLogEntry>>> {
+        let mut tx = self.begin(identity).await?;
+        DeploymentAuditLogModel::new(&mut tx)
+            .list_recent(before, limit)
+            .await
+    }
+
+    /// Adds `usage` to the usage recorded in the hour starting at
+    /// `hour_start`.
+    pub async fn record_usage(
+        &self,
+        hour_start: SystemTime,
+        usage: BTreeMap<UsageKey, UsageCounts>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(Identity::system()).await?;
+        UsageRecordsModel::new(&mut tx)
+            .add(hour_start, usage)
+            .await?;
+        self.commit(tx, "record_usage").await?;
+        Ok(())
+    }
+
+    /// Deletes a batch of the usage recorded before `cutoff`, returning how
+    /// many records were deleted.
+    pub async fn delete_usage_records_before(
+        &self,
+        cutoff: SystemTime,
+        limit: usize,
+    ) -> anyhow::Result<usize> {
+        let mut tx = self.begin(Identity::system()).await?;
+        let deleted = UsageRecordsModel::new(&mut tx)
+            .delete_before(cutoff, limit)
+            .await?;
+        self.commit(tx, "delete_usage_records").await?;
+        Ok(deleted)
+    }
+
+    /// The usage recorded in the hours starting in `[start, end)`.
+    pub async fn usage_records(
+        &self,
+        identity: Identity,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> anyhow::Result<Vec<UsageRecord>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("usage_records"));
+        }
+        let mut tx = self.begin(identity).await?;
+        UsageRecordsModel::new(&mut tx).list(start, end).await
+    }
+
+    /// Revokes a token, or all of a user's tokens issued until now, returning
+    /// the revocation's ID. They're rejected from then on, including by
+    /// connected clients that already authenticated with them.
+    pub async fn revoke_tokens(
+        &self,
+        identity: Identity,
+        revoked: RevokedTokens,
+    ) -> anyhow::Result<String> {
+        let mut tx = self.begin(identity).await?;
+        let mut model = TokenRevocationsModel::new(&mut tx);
+        let id = model
+            .insert(TokenRevocation {
+                revoked,
+                revoked_at: self.runtime.system_time(),
+            })
+            .await?
+            .developer_id
+            .encode();
+        let revocations = token_revocations(model.list().await?);
+        self.commit(tx, "revoke_tokens").await?;
+        self.app_auth.set_token_revocations(revocations);
+        Ok(id)
+    }
+
+    pub async fn list_token_revocations(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<TokenRevocation>>> {
+        let mut tx = self.begin(identity).await?;
+        TokenRevocationsModel::new(&mut tx).list().await
+    }
+
+    /// Deletes a revocation, so the tokens it revoked work again until they
+    /// expire.
+    pub async fn delete_token_revocation(
+        &self,
+        identity: Identity,
+        id: &str,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        let mut model = TokenRevocationsModel::new(&mut tx);
+        if !model.delete(id).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "TokenRevocationNotFound",
+                format!("Token revocation {id:?} doesn't exist"),
+            ));
+        }
+        let revocations = token_revocations(model.list().await?);
+        self.commit(tx, "delete_token_revocation").await?;
+        self.app_auth.set_token_revocations(revocations);
+        Ok(())
+    }
+
+    pub async fn list_alert_rules(
+        &self,
+        identity: Identity,
+    ) -> anyhow::Result<Vec<ParsedDocument<AlertRule>>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("list_alert_rules"));
+        }
+        let mut tx = self.begin(identity).await?;
+        AlertRulesModel::new(&mut tx).list().await
+    }
+
+    /// Adds an alert rule, returning its ID. The alert worker starts
+    /// evaluating it on its next pass.
+    pub async fn create_alert_rule(
+        &self,
+        identity: Identity,
+        rule: AlertRule,
+    ) -> anyhow::Result<String> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("create_alert_rule"));
+        }
+        let mut tx = self.begin(identity).await?;
+        let id = AlertRulesModel::new(&mut tx)
+            .insert(rule)
+            .await?
+            .developer_id
+            .encode();
+        self.commit(tx, "create_alert_rule").await?;
+        Ok(id)
+    }
+
+    pub async fn delete_alert_rule(&self, identity: Identity, id: &str) -> anyhow::Result<()> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("delete_alert_rule"));
+        }
+        let mut tx = self.begin(identity).await?;
+        if !AlertRulesModel::new(&mut tx).delete(id).await? {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "AlertRuleNotFound",
+                format!("Alert rule {id:?} doesn't exist"),
+            ));
+        }
+        self.commit(tx, "delete_alert_rule").await?;
+        Ok(())
+    }
+
+    /// Records whether an alert rule is firing, after the alert worker
+    /// evaluated it.
+    pub async fn set_alert_rule_state(
+        &self,
+        id: ResolvedDocumentId,
+        state: AlertState,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(Identity::system()).await?;
+        AlertRulesModel::new(&mut tx).set_state(id, state).await?;
+        self.commit(tx, "set_alert_rule_state").await?;
+        Ok(())
+    }
+
+    fn anonymous_jwt_provider(&self) -> CustomJwtProvider {
+        anonymous_jwt_provider(
+            anonymous_issuer(&self.instance_name),
+            self.key_broker.anonymous_identity_secret(),
+        )
+    }
+
+    /// Issues a token for an anonymous identity, returning it with the
+    /// identity's subject and how long the token is valid for. Renewing with
+    /// a current token keeps its subject, so users keep the same identity on
+    /// a device.
+    pub async fn issue_anonymous_identity(
+        &self,
+        current_token: Option<String>,
+    ) -> anyhow::Result<(String, String, Duration)> {
+        anyhow::ensure!(
+            *ANONYMOUS_IDENTITIES_ENABLED,
+            ErrorMetadata::forbidden(
+                "AnonymousIdentitiesDisabled",
+                "Anonymous identities aren't enabled for this deployment",
+            )
+        );
+        let provider = self.anonymous_jwt_provider();
+        let system_time = self.runtime.system_time();
+        let subject = match current_token {
+            Some(token) => {
+                let identity = validate_custom_jwt(&token, &provider, system_time)?;
+                // Upgraded anonymous identities are revoked, so they can't be
+                // renewed.
+                self.app_auth.check_token_revocation(&identity)?;
+                identity.subject
+            },
+            None => new_anonymous_subject(),
+        };
+        let ttl = *ANONYMOUS_IDENTITY_TOKEN_TTL;
+        let token = issue_anonymous_token(&provider, subject.clone(), system_time, ttl)?;
+        Ok((token, subject, ttl))
+    }
+
+    /// Upgrades the anonymous identity of `anonymous_token` to the identity
+    /// its user signed up with, returning the anonymous identity's token
+    /// identifier. The anonymous identity's tokens are revoked, and the
+    /// user's identity carries that token identifier in its
+    /// `upgradedFromAnonymous` claim from then on.
+    pub async fn upgrade_anonymous_identity(
+        &self,
+        identity: Identity,
+        anonymous_token: String,
+    ) -> anyhow::Result<String> {
+        let Identity::User(user) = &identity else {
+            anyhow::bail!(ErrorMetadata::unauthenticated(
+                "UserIdentityRequired",
+                "Upgrading an anonymous identity requires the token of the identity to upgrade to",
+            ));
+        };
+        anyhow::ensure!(
+            !is_anonymous(user),
+            ErrorMetadata::bad_request(
+                "InvalidAnonymousIdentityUpgrade",
+                "Anonymous identities can't be upgraded to another anonymous identity",
+            )
+        );
+        let system_time = self.runtime.system_time();
+        let anonymous = validate_custom_jwt(
+            &anonymous_token,
+            &self.anonymous_jwt_provider(),
+            system_time,
+        )?;
+        self.app_auth.check_token_revocation(&anonymous)?;
+        let anonymous_token_identifier = anonymous.attributes.token_identifier.0;
+        let token_identifier = user.attributes.token_identifier.0.clone();
+
+        let mut tx = self.begin(Identity::system()).await?;
+        let mut model = AnonymousIdentityUpgradesModel::new(&mut tx);
+        anyhow::ensure!(
+            !model
+                .list()
+                .await?
+                .iter()
+                .any(|upgrade| upgrade.token_identifier == token_identifier),
+            ErrorMetadata::bad_request(
+                "IdentityAlreadyUpgraded",
+                "This identity was already upgraded from an anonymous identity",
+            )
+        );
+        model
+            .insert(AnonymousIdentityUpgrade {
+                anonymous_token_identifier: anonymous_token_identifier.clone(),
+                token_identifier,
+            })
+            .await?;
+        let upgrades = anonymous_identity_upgrades(model.list().await?);
+        let mut model = TokenRevocationsModel::new(&mut tx);
+        model
+            .insert(TokenRevocation {
+                revoked: RevokedTokens::User(anonymous_token_identifier.clone()),
+                revoked_at: system_time,
+            })
+            .await?;
+        let revocations = token_revocations(model.list().await?);
+        self.commit(tx, "upgrade_anonymous_identity").await?;
+        self.app_auth.set_anonymous_identity_upgrades(upgrades);
+        self.app_auth.set_token_revocations(revocations);
+        Ok(anonymous_token_identifier)
+    }
+
+    /// Sets the role of a team member, or removes it if `role` is `None`.
+    pub async fn set_admin_role(
+        &self,
+        identity: Identity,
+        member_id: MemberId,
+        role: Option<AdminRole>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.begin(identity).await?;
+        let roles = AdminRolesModel::new(&mut tx)
+            .set_role(member_id, role)
+            .await?;
+        self.commit(tx, "set_admin_role").await?;
+        self.app_auth.set_admin_roles(roles);
+        Ok(())
+    }
+
+    #[fastrace::trace]
+    pub async fn upload_package(
+        &self,
+        modules: &Vec<ModuleConfig>,
+        external_deps_id_and_pkg: Option<(ExternalDepsPackageId, ExternalDepsPackage)>,
+    ) -> anyhow::Result<SourcePackage> {
+        // If there are any node actions, turn on the lambdas.
+        if modules
+            .iter()
+            .any(|m| m.environment == ModuleEnvironment::Node)
+        {
+            self.runner().enable_actions()?;
+        }
+
+        tracing::info!(
+            "Uploading package with {} modules to Storage",
+            modules.len()
+        );
+
+        // Canonicalize the modules
+        let package: BTreeMap<_, _> = modules
+            .iter()
+            .map(|m| (m.path.clone().canonicalize(), m))
+            .collect();
+        anyhow::ensure!(
+            modules.len() == package.len(),
+            ErrorMetadata::bad_request(
+                "CanonicalizationConflict",
+                "Multiple modules canonicalize to the same name.",
+            )
+        );
+
+        let (external_deps_package_id, external_deps_pkg) = match external_deps_id_and_pkg {
+            Some((id, pkg)) => (Some(id), Some(pkg)),
+            _ => (None, None),
+        };
+        let (storage_key, sha256, package_size) = upload_package(
+            package,
+            self.modules_storage.clone(),
+            external_deps_pkg.map(|pkg| pkg.storage_key),
+        )
+        .await?;
+
+        tracing::info!("Upload of {storage_key:?} successful");
+        tracing::info!("Source package size: {}", package_size);
+        log_source_package_size_bytes_total(package_size);
+
+        Ok(SourcePackage {
+            storage_key,
+            sha256,
+            external_deps_package_id,
+            package_size,
+            node_version: None,
+            dependency_layers: vec![],
+        })
+    }
+
+    // Clear all records for specified tables concurrently, potentially taking
+    // multiple transactions for each. Returns the total number of documents
+    // deleted.
+    pub async fn clear_tables(
+        &self,
+        identity: &Identity,
+        table_names: Vec<(ComponentPath, TableName)>,
+    ) -> anyhow::Result<u64> {
+        clear_tables(self, identity, table_names).await
+    }
+
+    pub async fn execute_standalone_module(
         &self,
         request_id: RequestId,
         module: ModuleConfig,
@@ -2278,15 +3699,52 @@ impl<RT: Runtime> Application<RT> {
         })
     }
 
+    /// Replays a recording of a query, mutation or action made with
+    /// [`isolate::environment::udf::recording::EXECUTION_RECORDER`].
+    #[fastrace::trace]
+    pub async fn replay_function(
+        &self,
+        request_id: RequestId,
+        identity: Identity,
+        recording: ExecutionRecording,
+        caller: FunctionCaller,
+    ) -> anyhow::Result<Result<FunctionReturn, FunctionError>> {
+        let block_logging = self
+            .log_visibility
+            .should_redact_logs_and_error(
+                &mut self.begin(identity.clone()).await?,
+                identity.clone(),
+                caller.allowed_visibility(),
+            )
+            .await?;
+        let tx = self.begin(identity).await?;
+        let (result, log_lines) = self
+            .runner
+            .replay_function(request_id.clone(), tx, recording, caller)
+            .await?;
+        let log_lines = RedactedLogLines::from_log_lines(log_lines, block_logging);
+        Ok(match result {
+            Ok(value) => Ok(FunctionReturn {
+                value: value.unpack(),
+                log_lines,
+            }),
+            Err(error) => Err(FunctionError {
+                error: RedactedJsError::from_js_error(error, block_logging, request_id),
+                log_lines,
+            }),
+        })
+    }
+
     #[fastrace::trace]
     pub async fn build_external_node_deps(
         &self,
         deps: Vec<NodeDependency>,
+        node_version: Option<NodeVersion>,
     ) -> anyhow::Result<(ExternalDepsPackageId, ExternalDepsPackage)> {
         // Check cache to see if we've built this package recently
         let mut tx = self.begin(Identity::system()).await?;
         let mut model = ExternalPackagesModel::new(&mut tx);
-        let cached_match = model.get_cached_package_match(deps.clone()).await?;
+        let cached_match = model.get_cached_package_match(deps.clone(), node_version).await?;
         if let Some((cached_id, cached_pkg)) = cached_match {
             tracing::info!("Cache hit for external deps package!");
             log_external_deps_package(true);
@@ -2296,7 +3754,7 @@ impl<RT: Runtime> Application<RT> {
             tracing::info!("Cache miss for external deps package, running build_deps...");
         }
 
-        let result = self.runner().build_deps(deps).await?;
+        let result = self.runner().build_deps(deps, node_version).await?;
         let pkg = match result {
             Ok(pkg) => pkg,
             Err(js_error) => {
@@ -2510,6 +3968,28 @@ impl<RT: Runtime> Application<RT> {
             .await
     }
 
+    pub async fn get_file_validators(
+        &self,
+        component: ComponentId,
+        storage_id: FileStorageId,
+    ) -> anyhow::Result<FileValidators> {
+        self.bail_if_not_running().await?;
+        let mut file_storage_tx = self.begin(Identity::system()).await?;
+        let Some(validators) = self
+            .file_storage
+            .transactional_file_storage
+            .get_file_validators(&mut file_storage_tx, component.into(), storage_id.clone())
+            .await?
+        else {
+            return Err(ErrorMetadata::not_found(
+                "FileNotFound",
+                format!("File {storage_id} not found"),
+            )
+            .into());
+        };
+        Ok(validators)
+    }
+
     pub async fn get_file_range(
         &self,
         component: ComponentId,
@@ -2573,6 +4053,13 @@ impl<RT: Runtime> Application<RT> {
                                 "Admin identity returned from check_admin_key was not an admin."
                             );
                         };
+                        anyhow::ensure!(
+                            !matches!(i.principal(), AdminIdentityPrincipal::ServiceAccount(_)),
+                            ErrorMetadata::forbidden(
+                                "ServiceAccountActingAsUser",
+                                "Service accounts can't act as users",
+                            )
+                        );
                         Identity::ActingUser(i, acting_user)
                     },
                     None => admin_identity,
@@ -2580,18 +4067,51 @@ impl<RT: Runtime> Application<RT> {
             },
             AuthenticationToken::User(id_token) => {
                 let mut tx = self.begin(Identity::system()).await?;
-                let auth_infos = AuthInfoModel::new(&mut tx).get().await?;
-
-                let identity = validate_id_token(
-                    Auth0IdToken(id_token),
-                    cached_http_client_for(ClientPurpose::ProviderMetadata),
-                    auth_infos
-                        .into_iter()
-                        .map(|auth_info| auth_info.into_value())
-                        .collect(),
-                    system_time,
-                )
-                .await?;
+                let mut custom_jwt_providers: Vec<_> = CustomJwtProvidersModel::new(&mut tx)
+                    .list()
+                    .await?
+                    .into_iter()
+                    .map(|provider| provider.into_value())
+                    .collect();
+                if *ANONYMOUS_IDENTITIES_ENABLED {
+                    custom_jwt_providers.push(self.anonymous_jwt_provider());
+                }
+                let token_exchange_provider = self.token_exchange_jwt_provider();
+                custom_jwt_providers.push(token_exchange_provider.clone());
+                let saml_token_secret = self.key_broker.saml_token_secret();
+                custom_jwt_providers.extend(
+                    SamlProvidersModel::new(&mut tx)
+                        .list()
+                        .await?
+                        .iter()
+                        .map(|provider| saml_jwt_provider(provider, saml_token_secret.clone())),
+                );
+                let mut identity = match custom_jwt_provider_for(&id_token, &custom_jwt_providers) {
+                    // Exchanged tokens act as the user from another provider.
+                    Some(provider) if *provider == token_exchange_provider => {
+                        exchanged_token_identity(validate_custom_jwt(
+                            &id_token,
+                            provider,
+                            system_time,
+                        )?)?
+                    },
+                    Some(provider) => validate_custom_jwt(&id_token, provider, system_time)?,
+                    None => {
+                        let auth_infos = AuthInfoModel::new(&mut tx).get().await?;
+                        validate_id_token(
+                            Auth0IdToken(id_token),
+                            cached_http_client_for(ClientPurpose::ProviderMetadata),
+                            auth_infos
+                                .into_iter()
+                                .map(|auth_info| auth_info.into_value())
+                                .collect(),
+                            system_time,
+                        )
+                        .await?
+                    },
+                };
+                self.app_auth.check_token_revocation(&identity)?;
+                self.app_auth.annotate_anonymous_identity_upgrade(&mut identity);
                 Identity::user(identity)
             },
             AuthenticationToken::None => Identity::Unknown,
@@ -2661,6 +4181,30 @@ impl<RT: Runtime> Application<RT> {
         self.function_log.cache_hit_percentage(identifier, window)
     }
 
+    pub async fn deployment_failure_percentage(
+        &self,
+        identity: Identity,
+        window: MetricsWindow,
+    ) -> anyhow::Result<Option<f64>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("deployment_failure_percentage"));
+        }
+        self.function_log.deployment_failure_percentage(&window)
+    }
+
+    pub async fn deployment_commit_time_percentile(
+        &self,
+        identity: Identity,
+        percentile: Percentile,
+        window: MetricsWindow,
+    ) -> anyhow::Result<Option<Duration>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("deployment_commit_time_percentile"));
+        }
+        self.function_log
+            .deployment_commit_time_percentile(percentile, &window)
+    }
+
     pub async fn latency_percentiles(
         &self,
         identity: Identity,
@@ -2675,6 +4219,20 @@ impl<RT: Runtime> Application<RT> {
             .latency_percentiles(identifier, percentiles, window)
     }
 
+    pub async fn execution_profile(
+        &self,
+        identity: Identity,
+        identifier: UdfIdentifier,
+        percentiles: Vec<Percentile>,
+        window: MetricsWindow,
+    ) -> anyhow::Result<BTreeMap<ExecutionPhase, BTreeMap<Percentile, Timeseries>>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("execution_profile"));
+        }
+        self.function_log
+            .execution_profile(identifier, percentiles, window)
+    }
+
     pub async fn udf_summary(
         &self,
         identity: Identity,
@@ -2699,6 +4257,46 @@ impl<RT: Runtime> Application<RT> {
         self.function_log.table_rate(name, metric, window)
     }
 
+    /// Every user table's document count and size, along with the rows and
+    /// bytes its functions read and wrote in `window`, busiest tables first.
+    pub fn table_metrics(
+        &self,
+        identity: Identity,
+        window: MetricsWindow,
+    ) -> anyhow::Result<Vec<TableMetrics>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("table_metrics"));
+        }
+        let snapshot = self.latest_snapshot()?;
+        // Table metrics don't distinguish between components yet, so sum up
+        // tables with the same name.
+        let mut sizes: BTreeMap<TableName, (u64, u64)> = BTreeMap::new();
+        for ((_, table_name), summary) in snapshot.iter_table_summaries()? {
+            if table_name.is_system() {
+                continue;
+            }
+            let (document_count, total_size) = sizes.entry(table_name).or_default();
+            *document_count += summary.num_values();
+            *total_size += summary.total_size();
+        }
+        let mut activity = self
+            .function_log
+            .table_activity(sizes.keys().cloned(), &window)?;
+        let mut metrics: Vec<_> = sizes
+            .into_iter()
+            .map(|(table_name, (document_count, total_size))| TableMetrics {
+                activity: activity.remove(&table_name).unwrap_or_default(),
+                table_name,
+                document_count,
+                total_size,
+            })
+            .collect();
+        metrics.sort_by_key(|table| {
+            cmp::Reverse(table.activity.bytes_read + table.activity.bytes_written)
+        });
+        Ok(metrics)
+    }
+
     pub async fn stream_udf_execution(
         &self,
         identity: Identity,
@@ -2721,6 +4319,41 @@ impl<RT: Runtime> Application<RT> {
         Ok(self.function_log.stream_parts(cursor).await)
     }
 
+    /// Streaming function logs from this cursor only returns the ones logged
+    /// after now.
+    pub fn latest_function_log_cursor(&self) -> CursorMs {
+        self.function_log.latest_cursor()
+    }
+
+    pub fn error_groups(
+        &self,
+        identity: Identity,
+        since: Option<UnixTimestamp>,
+    ) -> anyhow::Result<Vec<ErrorGroup>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("error_groups"));
+        }
+        Ok(self.function_log.error_groups(since))
+    }
+
+    pub fn occ_conflicts(&self, identity: Identity) -> anyhow::Result<Vec<OccConflictPair>> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("occ_conflicts"));
+        }
+        Ok(self.database.occ_conflicts())
+    }
+
+    pub fn slow_function_logs(
+        &self,
+        identity: Identity,
+        cursor: CursorMs,
+    ) -> anyhow::Result<(Vec<SlowFunctionExecution>, CursorMs)> {
+        if !(identity.is_admin() || identity.is_system()) {
+            anyhow::bail!(unauthorized_error("slow_function_logs"));
+        }
+        Ok(self.function_log.slow_executions(cursor))
+    }
+
     pub async fn scheduled_job_lag(
         &self,
         identity: Identity,
@@ -2732,14 +4365,19 @@ impl<RT: Runtime> Application<RT> {
         self.function_log.scheduled_job_lag(window)
     }
 
+    /// Cancels every pending job in the component that matches the filter, in
+    /// batches of `MAX_JOBS_CANCEL_BATCH` scanned jobs, and returns how many
+    /// were canceled.
     pub async fn cancel_all_jobs(
         &self,
         component_id: ComponentId,
-        path: Option<CanonicalizedComponentFunctionPath>,
+        filter: ScheduledJobFilter,
         identity: Identity,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<usize> {
+        let mut cursor = None;
+        let mut canceled = 0;
         loop {
-            let count = self
+            let page = self
                 .execute_with_audit_log_events_and_occ_retries(
                     identity.clone(),
                     "application_cancel_all_jobs",
@@ -2747,30 +4385,34 @@ impl<RT: Runtime> Application<RT> {
                         Self::_cancel_all_jobs(
                             tx,
                             component_id,
-                            path.clone(),
+                            &filter,
+                            cursor.clone(),
                             *MAX_JOBS_CANCEL_BATCH,
                         )
                         .into()
                     },
                 )
                 .await?;
-            if count < *MAX_JOBS_CANCEL_BATCH {
+            canceled += page.canceled;
+            cursor = page.cursor;
+            if cursor.is_none() {
                 break;
             }
         }
-        Ok(())
+        Ok(canceled)
     }
 
     async fn _cancel_all_jobs(
         tx: &mut Transaction<RT>,
         component_id: ComponentId,
-        path: Option<CanonicalizedComponentFunctionPath>,
+        filter: &ScheduledJobFilter,
+        cursor: Option<Cursor>,
         max_jobs: usize,
-    ) -> anyhow::Result<(usize, Vec<DeploymentAuditLogEvent>)> {
-        let count = SchedulerModel::new(tx, component_id.into())
-            .cancel_all(path, max_jobs)
+    ) -> anyhow::Result<(CancelJobsPage, Vec<DeploymentAuditLogEvent>)> {
+        let page = SchedulerModel::new(tx, component_id.into())
+            .cancel_all(filter, cursor, max_jobs)
             .await?;
-        Ok((count, vec![]))
+        Ok((page, vec![]))
     }
 
     /// Commit a transaction and send audit log events to the log manager if the
@@ -2965,6 +4607,16 @@ impl<RT: Runtime> Application<RT> {
         self.files_storage.clone()
     }
 
+    pub fn scheduled_job_executor_status(&self) -> ScheduledJobExecutorStatus {
+        self.scheduled_job_runner.executor_status()
+    }
+
+    /// Stops starting scheduled jobs and cron jobs, so the backend can drain
+    /// before it's stopped.
+    pub fn scheduler_drain(&self) -> Arc<SchedulerDrain> {
+        self.scheduler_drain.clone()
+    }
+
     pub async fn shutdown(&self) -> anyhow::Result<()> {
         self.log_sender.shutdown()?;
         self.table_summary_worker.shutdown().await?;
@@ -2975,10 +4627,12 @@ impl<RT: Runtime> Application<RT> {
         self.search_and_vector_bootstrap_worker.lock().shutdown();
         self.fast_forward_worker.lock().shutdown();
         self.export_worker.lock().shutdown();
+        self.export_schedule_worker.lock().shutdown();
         self.snapshot_import_worker.lock().shutdown();
         self.runner.shutdown().await?;
         self.scheduled_job_runner.shutdown();
         self.cron_job_executor.lock().shutdown();
+        self.warmup_executor.lock().shutdown();
         self.database.shutdown().await?;
         let migration_worker = self.migration_worker.lock().take();
         if let Some(migration_worker) = migration_worker {
@@ -2988,3 +4642,39 @@ impl<RT: Runtime> Application<RT> {
         Ok(())
     }
 }
+
+/// The in-memory form of `_token_revocations` that `ApplicationAuth` checks.
+fn token_revocations(revocations: Vec<ParsedDocument<TokenRevocation>>) -> TokenRevocations {
+    let mut result = TokenRevocations::default();
+    for revocation in revocations {
+        let TokenRevocation {
+            revoked,
+            revoked_at,
+        } = revocation.into_value();
+        match revoked {
+            RevokedTokens::TokenId(token_id) => {
+                result.token_ids.insert(token_id);
+            },
+            RevokedTokens::User(token_identifier) => {
+                // The latest revocation of a user covers the earlier ones.
+                let latest = result.users.entry(token_identifier).or_insert(revoked_at);
+                *latest = (*latest).max(revoked_at);
+            },
+        }
+    }
+    result
+}
+
+/// The in-memory form of `_anonymous_identity_upgrades` that
+/// `ApplicationAuth` annotates identities with.
+fn anonymous_identity_upgrades(
+    upgrades: Vec<ParsedDocument<AnonymousIdentityUpgrade>>,
+) -> BTreeMap<String, String> {
+    upgrades
+        .into_iter()
+        .map(|upgrade| {
+            let upgrade = upgrade.into_value();
+            (upgrade.token_identifier, upgrade.anonymous_token_identifier)
+        })
+        .collect()
+}
@@ -11,6 +11,7 @@ use metrics::{
     StaticMetricLabel,
     STATUS_LABEL,
 };
+use model::scheduled_jobs::types::ScheduledJobPriority;
 
 register_convex_counter!(
     SCHEDULED_JOB_RESULT_TOTAL,
@@ -63,3 +64,36 @@ register_convex_gauge!(
 pub fn log_num_running_jobs(num_running: usize) {
     log_gauge(&SCHEDULED_JOB_NUM_RUNNING_TOTAL, num_running as f64);
 }
+
+register_convex_counter!(
+    SCHEDULED_JOB_STARTED_TOTAL,
+    "Count of scheduled jobs started, by priority",
+    &["priority"]
+);
+pub fn log_scheduled_job_started(priority: ScheduledJobPriority) {
+    log_counter_with_labels(
+        &SCHEDULED_JOB_STARTED_TOTAL,
+        1,
+        vec![StaticMetricLabel::new("priority", priority.as_str())],
+    );
+}
+
+register_convex_gauge!(
+    SCHEDULED_JOB_BACKLOG_OUTSTANDING_TOTAL,
+    "Number of scheduled jobs that haven't finished, up to SCHEDULED_JOB_BACKLOG_MAX_COUNT per \
+     component"
+);
+register_convex_gauge!(
+    SCHEDULED_JOB_BACKLOG_DUE_TOTAL,
+    "Number of pending scheduled jobs that are due to run, up to SCHEDULED_JOB_BACKLOG_MAX_COUNT \
+     per component"
+);
+register_convex_gauge!(
+    SCHEDULED_JOB_BACKLOG_LAG_SECONDS,
+    "How long the oldest pending scheduled job has been due"
+);
+pub fn log_scheduled_job_backlog(outstanding: usize, due: usize, lag: Duration) {
+    log_gauge(&SCHEDULED_JOB_BACKLOG_OUTSTANDING_TOTAL, outstanding as f64);
+    log_gauge(&SCHEDULED_JOB_BACKLOG_DUE_TOTAL, due as f64);
+    log_gauge(&SCHEDULED_JOB_BACKLOG_LAG_SECONDS, lag.as_secs_f64());
+}
@@ -2,10 +2,17 @@ use std::{
     cmp,
     collections::{
         BTreeMap,
-        HashSet,
+        HashMap,
     },
     ops::Deref,
-    sync::Arc,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
     time::{
         Duration,
         SystemTime,
@@ -24,8 +31,15 @@ use common::{
         JsError,
     },
     execution_context::ExecutionContext,
-    fastrace_helpers::get_sampled_span,
+    fastrace_helpers::{
+        get_sampled_span,
+        initialize_root_from_parent,
+        EncodedSpan,
+    },
     knobs::{
+        SCHEDULED_JOB_BACKLOG_MAX_COUNT,
+        SCHEDULED_JOB_BACKLOG_METRICS_INTERVAL,
+        SCHEDULED_JOB_DEAD_LETTER_RETENTION,
         SCHEDULED_JOB_EXECUTION_PARALLELISM,
         SCHEDULED_JOB_GARBAGE_COLLECTION_BATCH_SIZE,
         SCHEDULED_JOB_GARBAGE_COLLECTION_DELAY,
@@ -33,6 +47,8 @@ use common::{
         SCHEDULED_JOB_GARBAGE_COLLECTION_MAX_BACKOFF,
         SCHEDULED_JOB_INITIAL_BACKOFF,
         SCHEDULED_JOB_MAX_BACKOFF,
+        SCHEDULED_JOB_MAX_THROTTLED_SCAN,
+        SCHEDULED_JOB_QUEUE_CONCURRENCY,
         SCHEDULED_JOB_RETENTION,
         UDF_EXECUTOR_OCC_MAX_RETRIES,
     },
@@ -56,6 +72,7 @@ use common::{
 };
 use database::{
     Database,
+    IndexModel,
     ResolvedQuery,
     Transaction,
 };
@@ -77,8 +94,19 @@ use model::{
     backend_state::BackendStateModel,
     modules::ModuleModel,
     scheduled_jobs::{
+        dead_letters::{
+            ScheduledJobDeadLettersModel,
+            SCHEDULED_JOB_DEAD_LETTERS_TABLE,
+        },
+        pauses::{
+            SchedulerPauses,
+            SchedulerPausesModel,
+        },
+        ready_jobs_query,
         types::{
             ScheduledJob,
+            ScheduledJobFilter,
+            ScheduledJobPriority,
             ScheduledJobState,
         },
         SchedulerModel,
@@ -86,6 +114,7 @@ use model::{
         NEXT_TS_FIELD,
         SCHEDULED_JOBS_INDEX,
         SCHEDULED_JOBS_INDEX_BY_COMPLETED_TS,
+        SCHEDULED_JOBS_INDEX_BY_PRIORITY,
         SCHEDULED_JOBS_TABLE,
     },
 };
@@ -108,7 +137,69 @@ pub(crate) const SCHEDULED_JOB_COMMITTING: &str = "scheduled_job_committing";
 #[derive(Clone)]
 pub struct ScheduledJobRunner {
     executor: Arc<Mutex<Box<dyn SpawnHandle>>>,
+    executor_status: Arc<Mutex<ScheduledJobExecutorStatus>>,
     garbage_collector: Arc<Mutex<Box<dyn SpawnHandle>>>,
+    backlog_monitor: Arc<Mutex<Box<dyn SpawnHandle>>>,
+}
+
+/// Whether the scheduled job executor is keeping up with its loop, for health
+/// checks.
+#[derive(Clone, Debug, Default)]
+pub struct ScheduledJobExecutorStatus {
+    /// The executor has started and its last pass over the scheduled jobs
+    /// succeeded.
+    pub running: bool,
+    /// The error the executor last failed with, if it hasn't recovered since.
+    pub last_error: Option<String>,
+}
+
+/// Lets the backend drain before it's stopped. Once draining starts, the
+/// scheduled job and cron executors stop starting executions, and the ones
+/// already running are counted until they finish.
+#[derive(Debug, Default)]
+pub struct SchedulerDrain {
+    draining: AtomicBool,
+    running_executions: AtomicUsize,
+}
+
+impl SchedulerDrain {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn start(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Scheduled jobs and cron jobs that are still running.
+    pub fn running_executions(&self) -> usize {
+        self.running_executions.load(Ordering::SeqCst)
+    }
+
+    /// Counts an execution as running until the returned guard is dropped, or
+    /// returns `None` if draining has started.
+    pub(crate) fn try_begin_execution(self: &Arc<Self>) -> Option<RunningExecution> {
+        // Count the execution before checking, so an execution that starts is
+        // always seen by `running_executions`.
+        self.running_executions.fetch_add(1, Ordering::SeqCst);
+        let execution = RunningExecution(self.clone());
+        if self.is_draining() {
+            return None;
+        }
+        Some(execution)
+    }
+}
+
+pub(crate) struct RunningExecution(Arc<SchedulerDrain>);
+
+impl Drop for RunningExecution {
+    fn drop(&mut self) {
+        self.0.running_executions.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl ScheduledJobRunner {
@@ -118,34 +209,53 @@ impl ScheduledJobRunner {
         database: Database<RT>,
         runner: Arc<ApplicationFunctionRunner<RT>>,
         function_log: FunctionExecutionLog<RT>,
+        drain: Arc<SchedulerDrain>,
     ) -> Self {
+        let executor_status = Arc::new(Mutex::new(ScheduledJobExecutorStatus::default()));
         let executor_fut = ScheduledJobExecutor::start(
             rt.clone(),
             instance_name,
             database.clone(),
             runner,
             function_log,
+            executor_status.clone(),
+            drain,
         );
         let executor = Arc::new(Mutex::new(rt.spawn("scheduled_job_executor", executor_fut)));
 
-        let garbage_collector_fut = ScheduledJobGarbageCollector::start(rt.clone(), database);
+        let garbage_collector_fut =
+            ScheduledJobGarbageCollector::start(rt.clone(), database.clone());
         let garbage_collector = Arc::new(Mutex::new(
             rt.spawn("scheduled_job_garbage_collector", garbage_collector_fut),
         ));
+
+        let backlog_monitor_fut = ScheduledJobBacklogMonitor::start(rt.clone(), database);
+        let backlog_monitor = Arc::new(Mutex::new(
+            rt.spawn("scheduled_job_backlog_monitor", backlog_monitor_fut),
+        ));
         Self {
             executor,
+            executor_status,
             garbage_collector,
+            backlog_monitor,
         }
     }
 
+    pub fn executor_status(&self) -> ScheduledJobExecutorStatus {
+        self.executor_status.lock().clone()
+    }
+
     pub fn shutdown(&self) {
         self.executor.lock().shutdown();
         self.garbage_collector.lock().shutdown();
+        self.backlog_monitor.lock().shutdown();
     }
 }
 
 pub struct ScheduledJobExecutor<RT: Runtime> {
     context: ScheduledJobContext<RT>,
+    status: Arc<Mutex<ScheduledJobExecutorStatus>>,
+    drain: Arc<SchedulerDrain>,
 }
 
 impl<RT: Runtime> Deref for ScheduledJobExecutor<RT> {
@@ -178,6 +288,8 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
         database: Database<RT>,
         runner: Arc<ApplicationFunctionRunner<RT>>,
         function_log: FunctionExecutionLog<RT>,
+        status: Arc<Mutex<ScheduledJobExecutorStatus>>,
+        drain: Arc<SchedulerDrain>,
     ) -> impl Future<Output = ()> + Send {
         let mut executor = Self {
             context: ScheduledJobContext {
@@ -187,11 +299,17 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
                 runner,
                 function_log,
             },
+            status,
+            drain,
         };
         async move {
             let mut backoff =
                 Backoff::new(*SCHEDULED_JOB_INITIAL_BACKOFF, *SCHEDULED_JOB_MAX_BACKOFF);
             while let Err(mut e) = executor.run(&mut backoff).await {
+                *executor.status.lock() = ScheduledJobExecutorStatus {
+                    running: false,
+                    last_error: Some(e.to_string()),
+                };
                 let delay = backoff.fail(&mut executor.rt.rng());
                 tracing::error!("Scheduled job executor failed, sleeping {delay:?}");
                 report_error(&mut e).await;
@@ -216,11 +334,13 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
                 runner,
                 function_log,
             },
+            status: Arc::new(Mutex::new(ScheduledJobExecutorStatus::default())),
+            drain: SchedulerDrain::new(),
         }
     }
 
     async fn drain_finished_jobs(
-        running_job_ids: &mut HashSet<ResolvedDocumentId>,
+        running_job_ids: &mut HashMap<ResolvedDocumentId, Option<String>>,
         rx: &mut mpsc::Receiver<ResolvedDocumentId>,
     ) {
         let mut total_drained = 0;
@@ -239,7 +359,8 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
         let pause_client = self.context.rt.pause_client();
         let (job_finished_tx, mut job_finished_rx) =
             mpsc::channel(*SCHEDULED_JOB_EXECUTION_PARALLELISM);
-        let mut running_job_ids = HashSet::new();
+        // The queue of each running job, if it has one.
+        let mut running_job_ids = HashMap::new();
         // Some if there's at least one pending job. May be in the past!
         let mut next_job_ready_time = None;
         loop {
@@ -248,10 +369,16 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
             let mut tx = self.database.begin(Identity::Unknown).await?;
             let backend_state = BackendStateModel::new(&mut tx).get_backend_state().await?;
             let is_backend_stopped = backend_state.is_stopped();
+            let pauses = SchedulerPausesModel::new(&mut tx).load().await?;
 
-            next_job_ready_time = if is_backend_stopped {
-                // If the backend is stopped we shouldn't poll. Our subscription will notify us
-                // when the backend is started again.
+            next_job_ready_time = if is_backend_stopped || pauses.is_all_paused() {
+                // If the backend is stopped or all jobs are paused we shouldn't poll. Our
+                // subscription will notify us when the backend is started or the jobs are
+                // resumed again.
+                None
+            } else if self.drain.is_draining() {
+                // The backend is about to stop, so leave the remaining jobs to the next
+                // one.
                 None
             } else if running_job_ids.len() == *SCHEDULED_JOB_EXECUTION_PARALLELISM {
                 // A scheduled job may have been added, but we can't do anything because we're
@@ -260,7 +387,7 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
             } else {
                 // Great! we have enough remaining concurrency and our backend is running, start
                 // new job(s) if we can and update our next ready time.
-                self.query_and_start_jobs(&mut tx, &mut running_job_ids, &job_finished_tx)
+                self.query_and_start_jobs(&mut tx, &pauses, &mut running_job_ids, &job_finished_tx)
                     .await?
             };
 
@@ -281,6 +408,10 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
 
             let token = tx.into_token()?;
             let subscription = self.database.subscribe(token).await?;
+            *self.status.lock() = ScheduledJobExecutorStatus {
+                running: true,
+                last_error: None,
+            };
 
             select_biased! {
                 job_id = job_finished_rx.recv().fuse() => {
@@ -300,64 +431,191 @@ impl<RT: Runtime> ScheduledJobExecutor<RT> {
         }
     }
 
-    /// Reads through scheduled jobs in timestamp ascending order and starts any
-    /// that are allowed by our concurrency limit and the jobs' scheduled
-    /// time.
+    /// Starts the jobs that are ready to run, highest priority first and then
+    /// in timestamp ascending order, as far as our concurrency limit and the
+    /// jobs' queue limits allow. Jobs of paused functions are skipped.
     ///
-    /// Returns the time at which the next job in the queue will be ready to
-    /// run. If the scheduler is behind, the returned time may be in the
-    /// past. Returns None if all jobs are finished or running.
+    /// Returns the time at which the next job in the queue that isn't running
+    /// or paused will be ready to run. If the scheduler is behind, the
+    /// returned time may be in the past. Returns None if all jobs are
+    /// finished, running or paused.
     async fn query_and_start_jobs(
         &self,
         tx: &mut Transaction<RT>,
-        running_job_ids: &mut HashSet<ResolvedDocumentId>,
+        pauses: &SchedulerPauses,
+        running_job_ids: &mut HashMap<ResolvedDocumentId, Option<String>>,
         job_finished_tx: &mpsc::Sender<ResolvedDocumentId>,
     ) -> anyhow::Result<Option<Timestamp>> {
         let now = self.rt.generate_timestamp()?;
+        let mut num_throttled = 0;
+        let mut job_stream = self.stream_ready_jobs(tx, now);
+        while running_job_ids.len() < *SCHEDULED_JOB_EXECUTION_PARALLELISM {
+            let Some(job) = job_stream.try_next().await? else {
+                break;
+            };
+            let (job_id, job) = job.into_id_and_value();
+            if running_job_ids.contains_key(&job_id) {
+                continue;
+            }
+            if pauses.is_paused(&job.path)
+                || Self::is_queue_full(running_job_ids, job.queue.as_deref())
+            {
+                // Keep looking for jobs from other functions and queues, but don't
+                // read through all of a large paused or throttled backlog on every
+                // pass.
+                num_throttled += 1;
+                if num_throttled >= *SCHEDULED_JOB_MAX_THROTTLED_SCAN {
+                    break;
+                }
+                continue;
+            }
+            let Some(execution) = self.drain.try_begin_execution() else {
+                break;
+            };
+            let queue = job.queue.clone();
+            self.start_job(job, job_id, job_finished_tx, execution);
+            running_job_ids.insert(job_id, queue);
+        }
+        drop(job_stream);
+
+        // If we're caught up, we can sleep until the next job's timestamp. If
+        // we're behind, we can use the timestamp to log how far behind we get.
         let mut job_stream = self.stream_jobs_to_run(tx);
+        let mut num_paused = 0;
         while let Some(job) = job_stream.try_next().await? {
-            let (job_id, job) = job.clone().into_id_and_value();
-            if running_job_ids.contains(&job_id) {
+            if running_job_ids.contains_key(&job.id()) {
                 continue;
             }
             let next_ts = job
                 .next_ts
                 .ok_or_else(|| anyhow::anyhow!("Could not get next_ts to run scheduled job at"))?;
-            // If we can't execute the job return the job's target timestamp. If we're
-            // caught up, we can sleep until the timestamp. If we're behind and
-            // at our concurrency limit, we can use the timestamp to log how far
-            // behind we get.
-            if next_ts > now || running_job_ids.len() == *SCHEDULED_JOB_EXECUTION_PARALLELISM {
-                return Ok(Some(next_ts));
+            if pauses.is_paused(&job.path) {
+                num_paused += 1;
+                if num_paused < *SCHEDULED_JOB_MAX_THROTTLED_SCAN {
+                    continue;
+                }
+                // There may be more jobs behind the paused ones, so check again
+                // once this one is due.
             }
+            return Ok(Some(next_ts));
+        }
+        Ok(None)
+    }
+
+    /// Whether the queue already has as many running jobs as its limit
+    /// allows. Jobs without a queue are only limited by
+    /// `SCHEDULED_JOB_EXECUTION_PARALLELISM`.
+    fn is_queue_full(
+        running_job_ids: &HashMap<ResolvedDocumentId, Option<String>>,
+        queue: Option<&str>,
+    ) -> bool {
+        let Some(queue) = queue else {
+            return false;
+        };
+        let Some(limit) = SCHEDULED_JOB_QUEUE_CONCURRENCY.limit_for(queue) else {
+            return false;
+        };
+        let running = running_job_ids
+            .values()
+            .filter(|running_queue| running_queue.as_deref() == Some(queue))
+            .count();
+        running >= limit
+    }
 
-            let context = self.context.clone();
-            let tx = job_finished_tx.clone();
+    fn start_job(
+        &self,
+        job: ScheduledJob,
+        job_id: ResolvedDocumentId,
+        job_finished_tx: &mpsc::Sender<ResolvedDocumentId>,
+        execution: RunningExecution,
+    ) {
+        let context = self.context.clone();
+        let tx = job_finished_tx.clone();
+        metrics::log_scheduled_job_started(job.priority);
 
-            let root = get_sampled_span(
+        // Jobs scheduled by a traced function continue its trace.
+        let root = match &job.traceparent {
+            Some(traceparent) => initialize_root_from_parent(
+                "scheduler/execute_job",
+                EncodedSpan(Some(traceparent.clone())),
+            ),
+            None => get_sampled_span(
                 &self.instance_name,
                 "scheduler/execute_job",
                 &mut self.rt.rng(),
                 BTreeMap::new(),
-            );
-            self.rt.spawn(
-                "spawn_scheduled_job",
-                async move {
-                    context.execute_job(job, job_id).await;
-                    let _ = tx.send(job_id).await;
-                }
-                .in_span(root),
-            );
-
-            running_job_ids.insert(job_id);
+            ),
+        };
+        self.rt.spawn(
+            "spawn_scheduled_job",
+            async move {
+                context.execute_job(job, job_id).await;
+                drop(execution);
+                let _ = tx.send(job_id).await;
+            }
+            .in_span(root),
+        );
+    }
 
-            // We might have hit the concurrency limit by adding the new job, so
-            // we could check and break immediately if we have.
-            // However we want to know the time the next job in the
-            // queue (if any) is due, so instead we continue the loop one more
-            // time.
+    /// Streams the jobs that are due to run by `now` across all components,
+    /// highest priority first and then in timestamp ascending order.
+    #[try_stream(boxed, ok = ParsedDocument<ScheduledJob>, error = anyhow::Error)]
+    async fn stream_ready_jobs<'a>(&'a self, tx: &'a mut Transaction<RT>, now: Timestamp) {
+        let namespaces: Vec<_> = tx
+            .table_mapping()
+            .iter()
+            .filter(|(_, _, _, name)| **name == *SCHEDULED_JOBS_TABLE)
+            .map(|(_, namespace, ..)| namespace)
+            .collect();
+        // Key is (priority, next_ts, namespace), where priority and next_ts are
+        // for sorting and namespace is for deduping.
+        // Value is (job, query) where job is the job to run and query will get
+        // the next job to run in that namespace with that priority.
+        let mut queries = BTreeMap::new();
+        for namespace in namespaces {
+            let has_priority_index = IndexModel::new(tx)
+                .enabled_index_metadata(namespace, &SCHEDULED_JOBS_INDEX_BY_PRIORITY)?
+                .is_some();
+            let priorities: Vec<_> = if has_priority_index {
+                ScheduledJobPriority::ALL
+                    .into_iter()
+                    .map(|priority| (priority, Some(priority)))
+                    .collect()
+            } else {
+                // The index is still backfilling, or the component's tables were
+                // created before it existed. Run its jobs in timestamp order.
+                vec![(ScheduledJobPriority::Normal, None)]
+            };
+            for (key_priority, query_priority) in priorities {
+                let mut query =
+                    ResolvedQuery::new(tx, namespace, ready_jobs_query(query_priority, now)?)?;
+                if let Some((key, job)) = Self::next_ready_job(tx, &mut query).await? {
+                    queries.insert((key_priority, key, namespace), (job, query));
+                }
+            }
         }
-        Ok(None)
+        while let Some(((priority, _min_next_ts, namespace), (min_job, mut query))) =
+            queries.pop_first()
+        {
+            yield min_job;
+            if let Some((next_ts, job)) = Self::next_ready_job(tx, &mut query).await? {
+                queries.insert((priority, next_ts, namespace), (job, query));
+            }
+        }
+    }
+
+    async fn next_ready_job(
+        tx: &mut Transaction<RT>,
+        query: &mut ResolvedQuery<RT>,
+    ) -> anyhow::Result<Option<(Timestamp, ParsedDocument<ScheduledJob>)>> {
+        let Some(doc) = query.next(tx, None).await? else {
+            return Ok(None);
+        };
+        let job: ParsedDocument<ScheduledJob> = doc.try_into()?;
+        let next_ts = job.next_ts.ok_or_else(|| {
+            anyhow::anyhow!("Could not get next_ts to run scheduled job {}", job.id())
+        })?;
+        Ok(Some((next_ts, job)))
     }
 
     #[try_stream(boxed, ok = ParsedDocument<ScheduledJob>, error = anyhow::Error)]
@@ -524,10 +782,7 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
             Ok(analyzed_function) => analyzed_function.udf_type,
             Err(error) => {
                 SchedulerModel::new(&mut tx, namespace)
-                    .complete(
-                        job_id,
-                        ScheduledJobState::Failed(error.user_facing_message()),
-                    )
+                    .fail(job_id, error.user_facing_message())
                     .await?;
                 self.database
                     .commit_with_write_source(tx, "scheduled_job_analyze_failure")
@@ -572,7 +827,7 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
                     UdfType::Action,
                 );
                 SchedulerModel::new(&mut tx, namespace)
-                    .complete(job_id, ScheduledJobState::Failed(message.clone()))
+                    .fail(job_id, message.clone())
                     .await?;
                 self.database
                     .commit_with_write_source(tx, "scheduled_job_bad_udf")
@@ -663,11 +918,16 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
         };
 
         let stats = tx.take_stats();
+        let indexes_read = tx.user_indexes_read();
         let execution_time = start.elapsed();
 
-        if outcome.result.is_ok() {
+        if let Ok(result) = &outcome.result {
             SchedulerModel::new(&mut tx, namespace)
-                .complete(job_id, ScheduledJobState::Success)
+                .complete_with_result(
+                    job_id,
+                    ScheduledJobState::Success,
+                    Some(result.as_str().to_string()),
+                )
                 .await?;
             if let Fault::Error(e) = pause_client.wait(SCHEDULED_JOB_COMMITTING).await {
                 tracing::info!("Injected error before committing mutation");
@@ -698,10 +958,7 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
                 return Ok(());
             }
             SchedulerModel::new(&mut tx, namespace)
-                .complete(
-                    job_id,
-                    ScheduledJobState::Failed(outcome.result.clone().unwrap_err().to_string()),
-                )
+                .fail(job_id, outcome.result.clone().unwrap_err().to_string())
                 .await?;
             // NOTE: We should not be getting developer errors here.
             self.database
@@ -711,6 +968,7 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
         self.function_log.log_mutation(
             outcome,
             stats,
+            indexes_read,
             execution_time,
             caller,
             usage_tracker,
@@ -758,9 +1016,12 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
                         context.clone(),
                     )
                     .await?;
-                let state = match &completion.outcome.result {
-                    Ok(_) => ScheduledJobState::Success,
-                    Err(e) => ScheduledJobState::Failed(e.to_string()),
+                let (state, result) = match &completion.outcome.result {
+                    Ok(result) => (
+                        ScheduledJobState::Success,
+                        Some(result.as_str().to_string()),
+                    ),
+                    Err(e) => (ScheduledJobState::Failed(e.to_string()), None),
                 };
 
                 // Mark the job as completed. Keep trying until we succeed (or
@@ -769,7 +1030,13 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
                 let mut backoff =
                     Backoff::new(*SCHEDULED_JOB_INITIAL_BACKOFF, *SCHEDULED_JOB_MAX_BACKOFF);
                 while let Err(mut err) = self
-                    .complete_action(job_id, &updated_job, usage_tracker.clone(), state.clone())
+                    .complete_action(
+                        job_id,
+                        &updated_job,
+                        usage_tracker.clone(),
+                        state.clone(),
+                        result.clone(),
+                    )
                     .await
                 {
                     let delay = backoff.fail(&mut self.rt.rng());
@@ -786,7 +1053,7 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
                 // complete this job and log the error.
                 let message = "Transient error while executing action".to_string();
                 SchedulerModel::new(&mut tx, namespace)
-                    .complete(job_id, ScheduledJobState::Failed(message.clone()))
+                    .fail(job_id, message.clone())
                     .await?;
                 self.database
                     .commit_with_write_source(tx, "scheduled_job_action_error")
@@ -839,14 +1106,15 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
         Ok((new_job.as_ref() == Some(expected_state), tx))
     }
 
-    // Completes an action in separate transaction. Returns false if the action
-    // state has changed.
+    // Completes an action in separate transaction, with its return value if it
+    // succeeded. Returns false if the action state has changed.
     async fn complete_action(
         &self,
         job_id: ResolvedDocumentId,
         expected_state: &ScheduledJob,
         usage_tracking: FunctionUsageTracker,
         job_state: ScheduledJobState,
+        result: Option<String>,
     ) -> anyhow::Result<()> {
         let (success, mut tx) = self
             .new_transaction_for_job_state(job_id, expected_state, usage_tracking)
@@ -857,10 +1125,15 @@ impl<RT: Runtime> ScheduledJobContext<RT> {
         }
         let namespace = tx.table_mapping().tablet_namespace(job_id.tablet_id)?;
 
-        // Remove from the scheduled jobs table
-        SchedulerModel::new(&mut tx, namespace)
-            .complete(job_id, job_state)
-            .await?;
+        let mut model = SchedulerModel::new(&mut tx, namespace);
+        match job_state {
+            ScheduledJobState::Failed(error) => model.fail(job_id, error).await?,
+            job_state => {
+                model
+                    .complete_with_result(job_id, job_state, result)
+                    .await?
+            },
+        }
         self.database
             .commit_with_write_source(tx, "scheduled_job_complete_action")
             .await?;
@@ -954,6 +1227,29 @@ impl<RT: Runtime> ScheduledJobGarbageCollector<RT> {
                     deleted_jobs = true;
                 }
             }
+            let dead_letter_namespaces = tx
+                .table_mapping()
+                .namespaces_for_name(&SCHEDULED_JOB_DEAD_LETTERS_TABLE);
+            for namespace in dead_letter_namespaces {
+                let now = self.rt.generate_timestamp()?;
+                let (deleted, next_expiry) = ScheduledJobDeadLettersModel::new(&mut tx, namespace)
+                    .delete_expired(
+                        now,
+                        *SCHEDULED_JOB_DEAD_LETTER_RETENTION,
+                        *SCHEDULED_JOB_GARBAGE_COLLECTION_BATCH_SIZE,
+                    )
+                    .await?;
+                if deleted > 0 {
+                    tracing::debug!("Garbage collecting {deleted} scheduled job dead letters");
+                    deleted_jobs = true;
+                }
+                if let Some(next_expiry) = next_expiry {
+                    next_job_wait = Some(match next_job_wait {
+                        Some(next_job_wait) => cmp::min(next_job_wait, next_expiry),
+                        None => next_expiry,
+                    });
+                }
+            }
             if deleted_jobs {
                 self.database
                     .commit_with_write_source(tx, "scheduled_job_gc")
@@ -976,3 +1272,67 @@ impl<RT: Runtime> ScheduledJobGarbageCollector<RT> {
         }
     }
 }
+
+/// Periodically counts the scheduled jobs that haven't finished and the ones
+/// that are overdue, and how late the oldest overdue job is, so a growing
+/// backlog shows up in metrics before it fills the database.
+pub struct ScheduledJobBacklogMonitor<RT: Runtime> {
+    rt: RT,
+    database: Database<RT>,
+}
+
+impl<RT: Runtime> ScheduledJobBacklogMonitor<RT> {
+    pub fn start(rt: RT, database: Database<RT>) -> impl Future<Output = ()> + Send {
+        let monitor = Self { rt, database };
+        async move {
+            loop {
+                if let Err(mut e) = monitor.log_backlog().await {
+                    tracing::error!("Failed to count the scheduled job backlog");
+                    report_error(&mut e).await;
+                }
+                monitor
+                    .rt
+                    .wait(*SCHEDULED_JOB_BACKLOG_METRICS_INTERVAL)
+                    .await;
+            }
+        }
+    }
+
+    async fn log_backlog(&self) -> anyhow::Result<()> {
+        let mut tx = self.database.begin(Identity::system()).await?;
+        let now = self.rt.generate_timestamp()?;
+        let namespaces = tx
+            .table_mapping()
+            .namespaces_for_name(&SCHEDULED_JOBS_TABLE);
+        let mut outstanding = 0;
+        let mut due = 0;
+        let mut oldest_due_ts: Option<Timestamp> = None;
+        for namespace in namespaces {
+            outstanding += SchedulerModel::new(&mut tx, namespace)
+                .count_outstanding(
+                    &ScheduledJobFilter::default(),
+                    *SCHEDULED_JOB_BACKLOG_MAX_COUNT,
+                )
+                .await?;
+            let query = ready_jobs_query(None, now)?.limit(*SCHEDULED_JOB_BACKLOG_MAX_COUNT);
+            let mut query_stream = ResolvedQuery::new(&mut tx, namespace, query)?;
+            while let Some(doc) = query_stream.next(&mut tx, None).await? {
+                let job: ParsedDocument<ScheduledJob> = doc.try_into()?;
+                // Running jobs are due too, but they aren't waiting to start.
+                if job.state != ScheduledJobState::Pending {
+                    continue;
+                }
+                due += 1;
+                if let Some(next_ts) = job.next_ts {
+                    oldest_due_ts = Some(oldest_due_ts.map_or(next_ts, |ts| ts.min(next_ts)));
+                }
+            }
+        }
+        metrics::log_scheduled_job_backlog(
+            outstanding,
+            due,
+            oldest_due_ts.map_or(Duration::ZERO, |ts| now - ts),
+        );
+        Ok(())
+    }
+}
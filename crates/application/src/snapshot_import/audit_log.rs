@@ -11,7 +11,11 @@ use database::Database;
 use keybroker::Identity;
 use model::{
     deployment_audit_log::types::DeploymentAuditLogEvent,
-    snapshot_imports::types::SnapshotImport,
+    snapshot_imports::types::{
+        ImportFormat,
+        ImportMode,
+        ImportRequestor,
+    },
 };
 use value::{
     TableName,
@@ -23,7 +27,9 @@ use crate::snapshot_import::TableMappingForImport;
 pub async fn make_audit_log_event<RT: Runtime>(
     database: &Database<RT>,
     table_mapping_for_import: &TableMappingForImport,
-    snapshot_import: &SnapshotImport,
+    import_mode: ImportMode,
+    import_format: ImportFormat,
+    requestor: ImportRequestor,
 ) -> anyhow::Result<DeploymentAuditLogEvent> {
     let (table_count, table_names) =
         audit_log_table_names(database, table_mapping_for_import.tables_imported()).await?;
@@ -33,9 +39,9 @@ pub async fn make_audit_log_event<RT: Runtime>(
     Ok(DeploymentAuditLogEvent::SnapshotImport {
         table_names,
         table_count,
-        import_mode: snapshot_import.mode,
-        import_format: snapshot_import.format.clone(),
-        requestor: snapshot_import.requestor.clone(),
+        import_mode,
+        import_format,
+        requestor,
         table_names_deleted,
         table_count_deleted,
     })
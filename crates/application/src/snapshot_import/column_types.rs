@@ -0,0 +1,330 @@
+//! Converting the text cells of CSV files and Postgres dumps to the JSON that
+//! imports parse, using each column's type from the user or inferred from
+//! the file. Int64 and bytes columns are typed by a generated schema, like the
+//! ones in ZIP exports.
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+
+use anyhow::Context;
+use common::types::FieldName;
+use errors::ErrorMetadata;
+use model::snapshot_imports::types::{
+    ImportColumnType,
+    ImportColumnTypes,
+    ImportValueType,
+};
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use shape_inference::{
+    export_context::GeneratedSchema,
+    ProdConfigWithOptionalFields,
+    ShapeEnum,
+    StructuralShape,
+    UnionShape,
+};
+use value::IdentifierFieldName;
+
+use crate::snapshot_import::{
+    import_error::ImportError,
+    parse::parse_csv_cell,
+};
+
+/// Converts rows of cells to objects. Cells are `None` for Postgres's NULL.
+pub struct ColumnConverter {
+    columns: Vec<(FieldName, Option<ImportColumnType>)>,
+}
+
+impl ColumnConverter {
+    pub fn new(column_types: &ImportColumnTypes, columns: Vec<FieldName>) -> anyhow::Result<Self> {
+        let columns = match column_types {
+            ImportColumnTypes::Guess => columns.into_iter().map(|column| (column, None)).collect(),
+            ImportColumnTypes::Explicit(types) => {
+                let names: BTreeSet<_> = columns.iter().collect();
+                if let Some(unknown) = types.keys().find(|column| !names.contains(column)) {
+                    anyhow::bail!(ImportError::UnknownColumn(unknown.to_string()));
+                }
+                columns
+                    .into_iter()
+                    .map(|column| match types.get(&column) {
+                        Some(column_type) => Ok((column, Some(*column_type))),
+                        None => Err(ImportError::ColumnTypeMissing(column.to_string())),
+                    })
+                    .collect::<Result<_, _>>()?
+            },
+            ImportColumnTypes::Infer => {
+                anyhow::bail!("column types must be inferred before converting rows")
+            },
+        };
+        Ok(Self { columns })
+    }
+
+    /// The schema for the shapes that the exported JSON can't express on its
+    /// own, if any columns need one.
+    pub fn generated_schema(
+        &self,
+    ) -> anyhow::Result<Option<GeneratedSchema<ProdConfigWithOptionalFields>>> {
+        let mut fields = BTreeMap::new();
+        for (column, column_type) in &self.columns {
+            let Some(column_type) = column_type else {
+                continue;
+            };
+            let shape = match column_type.value_type {
+                ImportValueType::Int64 => ShapeEnum::Int64,
+                ImportValueType::Bytes => ShapeEnum::Bytes,
+                _ => continue,
+            };
+            let mut shape = StructuralShape::new(shape);
+            if column_type.nullable {
+                shape = StructuralShape::new(ShapeEnum::Union(UnionShape::from_parts(
+                    [shape, StructuralShape::new(ShapeEnum::Null)].into(),
+                )));
+            }
+            let field: IdentifierFieldName = column.parse().with_context(|| {
+                ImportError::ColumnNotIdentifier(column.to_string(), column_type.value_type)
+            })?;
+            fields.insert(field, shape);
+        }
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let shape = StructuralShape::object(fields).with_context(|| {
+            ErrorMetadata::bad_request(
+                "ImportTooManyTypedColumns",
+                "Too many int64 and bytes columns to import",
+            )
+        })?;
+        Ok(Some(GeneratedSchema::new(shape)))
+    }
+
+    /// Converts the `row_number`th row to an object.
+    pub fn convert_row(
+        &self,
+        row_number: usize,
+        cells: &[Option<&str>],
+    ) -> anyhow::Result<JsonValue> {
+        if cells.len() != self.columns.len() {
+            anyhow::bail!(ImportError::CsvRowMissingFields(row_number));
+        }
+        let mut object = serde_json::Map::new();
+        for ((column, column_type), cell) in self.columns.iter().zip(cells.iter().copied()) {
+            let value = match (column_type, cell) {
+                (None, None) => JsonValue::Null,
+                (None, Some(cell)) => parse_csv_cell(cell),
+                (Some(column_type), cell) => convert_cell(column_type, cell).map_err(|e| {
+                    ImportError::InvalidCell(
+                        row_number,
+                        column.to_string(),
+                        column_type.value_type,
+                        e,
+                    )
+                })?,
+            };
+            object.insert(column.to_string(), value);
+        }
+        Ok(JsonValue::Object(object))
+    }
+}
+
+fn convert_cell(column_type: &ImportColumnType, cell: Option<&str>) -> anyhow::Result<JsonValue> {
+    let cell = match cell {
+        Some("") | None if column_type.nullable => return Ok(JsonValue::Null),
+        None => anyhow::bail!("the column isn't nullable"),
+        Some(cell) => cell,
+    };
+    let value = match column_type.value_type {
+        ImportValueType::String => json!(cell),
+        ImportValueType::Float64 => json!(parse_float64(cell)?),
+        // The generated schema imports the string as an int64.
+        ImportValueType::Int64 => json!(cell.trim().parse::<i64>()?.to_string()),
+        ImportValueType::Boolean => json!(parse_boolean(cell).context("expected true or false")?),
+        ImportValueType::Bytes => {
+            let bytes = match cell.strip_prefix("\\x") {
+                Some(hex) => hex::decode(hex)?,
+                None => base64::decode(cell)?,
+            };
+            json!(base64::encode(bytes))
+        },
+        ImportValueType::Json => serde_json::from_str(cell)?,
+    };
+    Ok(value)
+}
+
+fn parse_float64(cell: &str) -> anyhow::Result<f64> {
+    let value: f64 = cell.trim().parse()?;
+    anyhow::ensure!(value.is_finite(), "{value} isn't a finite number");
+    Ok(value)
+}
+
+fn parse_boolean(cell: &str) -> Option<bool> {
+    match &*cell.trim().to_lowercase() {
+        "true" | "t" => Some(true),
+        "false" | "f" => Some(false),
+        _ => None,
+    }
+}
+
+/// Infers each column's type from its cells: booleans if they're all
+/// booleans, float64s if they're all numbers, and strings otherwise. Columns
+/// with empty or NULL cells are nullable.
+pub struct ColumnTypeInference {
+    columns: Vec<(FieldName, Option<ImportValueType>, bool)>,
+}
+
+impl ColumnTypeInference {
+    pub fn new(columns: Vec<FieldName>) -> Self {
+        Self {
+            columns: columns
+                .into_iter()
+                .map(|column| (column, None, false))
+                .collect(),
+        }
+    }
+
+    pub fn add_row(&mut self, row_number: usize, cells: &[Option<&str>]) -> anyhow::Result<()> {
+        if cells.len() != self.columns.len() {
+            anyhow::bail!(ImportError::CsvRowMissingFields(row_number));
+        }
+        for ((_, value_type, nullable), cell) in self.columns.iter_mut().zip(cells.iter().copied())
+        {
+            let cell = match cell {
+                Some("") | None => {
+                    *nullable = true;
+                    continue;
+                },
+                Some(cell) => cell,
+            };
+            let cell_type = if parse_boolean(cell).is_some() {
+                ImportValueType::Boolean
+            } else if parse_float64(cell).is_ok() {
+                ImportValueType::Float64
+            } else {
+                ImportValueType::String
+            };
+            *value_type = match *value_type {
+                None => Some(cell_type),
+                Some(existing) if existing == cell_type => Some(existing),
+                Some(_) => Some(ImportValueType::String),
+            };
+        }
+        Ok(())
+    }
+
+    pub fn into_column_types(self) -> ImportColumnTypes {
+        ImportColumnTypes::Explicit(
+            self.columns
+                .into_iter()
+                .map(|(column, value_type, nullable)| {
+                    let column_type = ImportColumnType {
+                        value_type: value_type.unwrap_or(ImportValueType::String),
+                        nullable,
+                    };
+                    (column, column_type)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+    use model::snapshot_imports::types::{
+        ImportColumnType,
+        ImportColumnTypes,
+        ImportValueType,
+    };
+    use serde_json::json;
+
+    use super::{
+        ColumnConverter,
+        ColumnTypeInference,
+    };
+
+    #[test]
+    fn test_convert_row() -> anyhow::Result<()> {
+        let column_type = |value_type, nullable| ImportColumnType {
+            value_type,
+            nullable,
+        };
+        let column_types = ImportColumnTypes::Explicit(btreemap! {
+            "id".parse()? => column_type(ImportValueType::Int64, false),
+            "name".parse()? => column_type(ImportValueType::String, false),
+            "score".parse()? => column_type(ImportValueType::Float64, true),
+            "active".parse()? => column_type(ImportValueType::Boolean, false),
+            "avatar".parse()? => column_type(ImportValueType::Bytes, true),
+            "tags".parse()? => column_type(ImportValueType::Json, false),
+        });
+        let columns = ["id", "name", "score", "active", "avatar", "tags"]
+            .into_iter()
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let converter = ColumnConverter::new(&column_types, columns.clone())?;
+        let row = [
+            Some("12"),
+            Some(""),
+            None,
+            Some("t"),
+            Some("\\x0102"),
+            Some("[\"a\"]"),
+        ];
+        assert_eq!(
+            converter.convert_row(1, &row)?,
+            json!({
+                "id": "12",
+                "name": "",
+                "score": null,
+                "active": true,
+                "avatar": "AQI=",
+                "tags": ["a"],
+            })
+        );
+        assert_eq!(
+            format!("{}", converter.generated_schema()?.unwrap().inferred_shape),
+            "{\"avatar\": null | bytes, \"id\": int64}"
+        );
+
+        // Errors say which row and column are invalid.
+        let row = [Some("1.5"), Some("a"), None, Some("t"), None, Some("[]")];
+        let err = converter.convert_row(2, &row).unwrap_err();
+        assert!(err.to_string().contains("Row 2"), "{err}");
+        assert!(err.to_string().contains("\"id\""), "{err}");
+        let row = [Some("1"), None, None, Some("t"), None, Some("[]")];
+        assert!(converter.convert_row(3, &row).is_err());
+
+        // Every column needs a type.
+        let mut columns = columns;
+        columns.push("extra".parse()?);
+        assert!(ColumnConverter::new(&column_types, columns).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_column_types() -> anyhow::Result<()> {
+        let columns = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut inference = ColumnTypeInference::new(columns);
+        inference.add_row(1, &[Some("1"), Some("true"), Some("x"), Some("")])?;
+        inference.add_row(2, &[Some("2.5"), Some("F"), Some("3"), None])?;
+        inference.add_row(3, &[Some(""), Some("t"), Some("y"), Some("")])?;
+        let column_type = |value_type, nullable| ImportColumnType {
+            value_type,
+            nullable,
+        };
+        assert_eq!(
+            inference.into_column_types(),
+            ImportColumnTypes::Explicit(btreemap! {
+                "a".parse()? => column_type(ImportValueType::Float64, true),
+                "b".parse()? => column_type(ImportValueType::Boolean, false),
+                "c".parse()? => column_type(ImportValueType::String, false),
+                "d".parse()? => column_type(ImportValueType::String, true),
+            })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Reading the file for an import, either straight from snapshot-imports
+//! storage or from a request body as it arrives. Nothing is copied to local
+//! disk, so imports can be larger than the disk, and only a bounded window of
+//! the file is buffered at a time.
+//!
+//! A request body can only be read once, front to back, so it's only used for
+//! formats that are parsed in a single pass. Formats that read the file more
+//! than once or out of order (ZIP's central directory is at the end, and
+//! inferring column types takes a separate pass) are uploaded first.
+use std::{
+    io::{
+        self,
+        Read,
+        Seek,
+        SeekFrom,
+    },
+    ops::Bound,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use common::types::FullyQualifiedObjectKey;
+use futures::{
+    stream::BoxStream,
+    StreamExt,
+    TryStreamExt,
+};
+use parking_lot::Mutex;
+use storage::{
+    Storage,
+    StorageExt,
+};
+use tokio::{
+    io::{
+        AsyncBufRead,
+        AsyncReadExt,
+    },
+    runtime::Handle,
+};
+use tokio_util::io::StreamReader;
+
+/// Seeking forward by up to this many bytes reads through the open stream
+/// instead of starting a new ranged read.
+const MAX_SEEK_FORWARD_IN_STREAM: u64 = 1 << 20;
+
+pub type ImportStream = BoxStream<'static, io::Result<Bytes>>;
+
+/// The file being imported. An uploaded file can be streamed from the start
+/// any number of times, or from an offset, while a request body can be
+/// streamed once.
+#[derive(Clone)]
+pub struct ImportBody {
+    source: ImportSource,
+}
+
+#[derive(Clone)]
+enum ImportSource {
+    Stored {
+        storage: Arc<dyn Storage>,
+        object_key: FullyQualifiedObjectKey,
+    },
+    /// Taken by the first read.
+    Streamed(Arc<Mutex<Option<ImportStream>>>),
+}
+
+impl ImportBody {
+    pub fn new(storage: Arc<dyn Storage>, object_key: FullyQualifiedObjectKey) -> Self {
+        Self {
+            source: ImportSource::Stored {
+                storage,
+                object_key,
+            },
+        }
+    }
+
+    /// A request body that's parsed as it arrives. Only formats that read
+    /// the file once, front to back, can be imported from it.
+    pub fn streamed(stream: BoxStream<'static, anyhow::Result<Bytes>>) -> Self {
+        let stream = stream.map_err(io::Error::other).boxed();
+        Self {
+            source: ImportSource::Streamed(Arc::new(Mutex::new(Some(stream)))),
+        }
+    }
+
+    pub async fn stream(&self) -> anyhow::Result<ImportStream> {
+        self.stream_from(0).await
+    }
+
+    pub async fn stream_from(&self, offset: u64) -> anyhow::Result<ImportStream> {
+        match &self.source {
+            ImportSource::Stored {
+                storage,
+                object_key,
+            } => Ok(storage
+                .get_fq_object_range(object_key, (Bound::Included(offset), Bound::Unbounded))
+                .await?
+                .with_context(|| format!("Missing import object {object_key:?}"))?
+                .stream),
+            ImportSource::Streamed(stream) => {
+                anyhow::ensure!(offset == 0, "Can't read a streamed import from an offset");
+                stream
+                    .lock()
+                    .take()
+                    .context("Can't read a streamed import more than once")
+            },
+        }
+    }
+
+    /// A blocking reader that supports seeking, for formats like ZIP that
+    /// aren't read front to back. It must be used outside of the async
+    /// runtime, e.g. in `spawn_blocking`.
+    pub async fn seekable_reader(&self) -> anyhow::Result<SeekableImportBody> {
+        let ImportSource::Stored {
+            storage,
+            object_key,
+        } = &self.source
+        else {
+            anyhow::bail!("Can't seek in a streamed import");
+        };
+        let size = storage
+            .get_fq_object_attributes(object_key)
+            .await?
+            .with_context(|| format!("Missing import object {object_key:?}"))?
+            .size;
+        Ok(SeekableImportBody {
+            body: self.clone(),
+            handle: Handle::current(),
+            size,
+            position: 0,
+            stream: None,
+        })
+    }
+}
+
+/// Reads the import from storage with a ranged read from the current position,
+/// which is kept open while reading sequentially and replaced on seeks.
+pub struct SeekableImportBody {
+    body: ImportBody,
+    handle: Handle,
+    size: u64,
+    position: u64,
+    /// The offset of the next byte from the open ranged read, and its reader.
+    stream: Option<(u64, Box<dyn AsyncBufRead + Send + Unpin>)>,
+}
+
+impl Read for SeekableImportBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.size {
+            return Ok(0);
+        }
+        if let Some((offset, _)) = &self.stream
+            && (self.position < *offset || self.position - *offset > MAX_SEEK_FORWARD_IN_STREAM)
+        {
+            self.stream = None;
+        }
+        let (mut offset, mut reader) = match self.stream.take() {
+            Some(stream) => stream,
+            None => {
+                let stream = self
+                    .handle
+                    .block_on(self.body.stream_from(self.position))
+                    .map_err(io::Error::other)?;
+                let reader: Box<dyn AsyncBufRead + Send + Unpin> =
+                    Box::new(StreamReader::new(stream));
+                (self.position, reader)
+            },
+        };
+        let position = self.position;
+        let n = self.handle.block_on(async {
+            if offset < position {
+                let skip = position - offset;
+                let skipped =
+                    tokio::io::copy(&mut (&mut reader).take(skip), &mut tokio::io::sink()).await?;
+                offset += skipped;
+                if skipped < skip {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "import object ended early",
+                    ));
+                }
+            }
+            reader.read(buf).await
+        })?;
+        self.stream = Some((offset + n as u64, reader));
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SeekableImportBody {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{
+            Read,
+            Seek,
+            SeekFrom,
+        },
+        sync::Arc,
+    };
+
+    use bytes::Bytes;
+    use common::runtime::testing::TestRuntime;
+    use storage::{
+        LocalDirStorage,
+        Storage,
+        StorageUseCase,
+        Upload,
+    };
+
+    use super::ImportBody;
+
+    #[convex_macro::test_runtime]
+    async fn test_seekable_reader(rt: TestRuntime) -> anyhow::Result<()> {
+        let storage_dir = tempfile::TempDir::new()?;
+        let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::for_use_case(
+            rt.clone(),
+            &storage_dir.path().to_string_lossy(),
+            StorageUseCase::SnapshotImports,
+        )?);
+        let content: Vec<u8> = (0..3_000_000u32).map(|i| (i % 251) as u8).collect();
+        let mut upload = storage.start_upload().await?;
+        upload.write(Bytes::from(content.clone())).await?;
+        let object_key = storage.fully_qualified_key(&upload.complete().await?);
+        let mut reader = ImportBody::new(storage, object_key)
+            .seekable_reader()
+            .await?;
+        tokio::task::spawn_blocking(move || {
+            let mut buf = [0; 10];
+            // Seeks back, forward within the open stream, and forward past it.
+            for offset in [2_999_990, 5, 1_000, 2_500_000] {
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.read_exact(&mut buf)?;
+                let offset = offset as usize;
+                anyhow::ensure!(buf[..] == content[offset..offset + 10]);
+            }
+            reader.seek(SeekFrom::End(-4))?;
+            let mut rest = vec![];
+            reader.read_to_end(&mut rest)?;
+            anyhow::ensure!(rest[..] == content[content.len() - 4..]);
+            anyhow::ensure!(reader.seek(SeekFrom::Current(-10_000_000)).is_err());
+            Ok(())
+        })
+        .await?
+    }
+}
@@ -9,6 +9,7 @@ use humansize::{
     FormatSize,
     BINARY,
 };
+use model::snapshot_imports::types::ImportValueType;
 use strum::AsRefStr;
 use value::TableName;
 
@@ -60,6 +61,42 @@ pub enum ImportError {
 
     #[error("Not valid JSON: {0}")]
     NotJson(serde_json::Error),
+
+    #[error("Not a valid mongodump file: {0}")]
+    InvalidMongoDump(anyhow::Error),
+
+    #[error("Document {0} wasn't valid BSON: {1}")]
+    InvalidBsonDocument(usize, anyhow::Error),
+
+    #[error("Row {0} has an invalid {2} in column {1:?}: {3}")]
+    InvalidCell(usize, String, ImportValueType, anyhow::Error),
+
+    #[error("Column {0:?} doesn't have a type. Every column needs one when types are given")]
+    ColumnTypeMissing(String),
+
+    #[error("There's a type for column {0:?}, which isn't a column in the file")]
+    UnknownColumn(String),
+
+    #[error("Column {0:?} can't be imported as {1} because it isn't a valid identifier")]
+    ColumnNotIdentifier(String, ImportValueType),
+
+    #[error(
+        "Dump doesn't have a `COPY ... FROM stdin` block for table {0}. Dumps made with --inserts \
+         aren't supported"
+    )]
+    PgDumpTableNotFound(TableName),
+
+    #[error("Postgres column {0:?} isn't a valid field name: {1}")]
+    PgDumpInvalidColumn(String, anyhow::Error),
+
+    #[error("Row {0} of the COPY block isn't valid: {1}")]
+    PgDumpInvalidRow(usize, anyhow::Error),
+
+    #[error(
+        "Couldn't download _storage file {0} from the URL in _storage/documents.jsonl, which may \
+         have expired: {1}"
+    )]
+    StorageFileDownloadFailed(String, anyhow::Error),
 }
 
 impl ImportError {
@@ -5,6 +5,7 @@ use std::{
 };
 
 use anyhow::Context;
+use bytes::Bytes;
 use common::{
     components::ComponentPath,
     document::{
@@ -16,7 +17,19 @@ use common::{
         PeekableExt,
         TryPeekableExt,
     },
-    runtime::Runtime,
+    http::{
+        fetch::FetchClient,
+        HttpRequestStream,
+    },
+    knobs::{
+        IMPORT_STORAGE_FILE_CONNECT_TIMEOUT,
+        IMPORT_STORAGE_FILE_DOWNLOAD_TIMEOUT,
+    },
+    runtime::{
+        Runtime,
+        TimeoutError,
+        WithTimeout,
+    },
     types::StorageUuid,
 };
 use database::{
@@ -27,24 +40,33 @@ use errors::ErrorMetadata;
 use file_storage::FileStorage;
 use futures::{
     stream::{
+        self,
         BoxStream,
         Peekable,
     },
+    Stream,
+    StreamExt,
     TryStreamExt,
 };
 use headers::{
     ContentLength,
     ContentType,
 };
+use http::{
+    HeaderMap,
+    Method,
+};
 use keybroker::Identity;
 use model::{
     file_storage::{
+        types::FileStorageEntry,
         FILE_STORAGE_TABLE,
         FILE_STORAGE_VIRTUAL_TABLE,
     },
     snapshot_imports::types::ImportRequestor,
 };
 use thousands::Separable;
+use url::Url;
 use usage_tracking::{
     FunctionUsageTracker,
     StorageCallTracker,
@@ -72,9 +94,22 @@ use crate::{
     },
 };
 
+/// The metadata of a file from `_storage/documents.jsonl`.
+#[derive(Default)]
+struct StorageFileMetadata {
+    content_length: Option<ContentLength>,
+    content_type: Option<ContentType>,
+    sha256: Option<Sha256Digest>,
+    storage_id: Option<StorageUuid>,
+    creation_time: Option<CreationTime>,
+    /// Where to download the file from if it isn't in the zip.
+    url: Option<Url>,
+}
+
 pub async fn import_storage_table<RT: Runtime>(
     database: &Database<RT>,
     file_storage: &FileStorage<RT>,
+    fetch_client: &dyn FetchClient,
     identity: &Identity,
     table_id: TabletIdAndTableNumber,
     component_path: &ComponentPath,
@@ -133,16 +168,22 @@ pub async fn import_storage_table<RT: Runtime>(
             .map(CreationTime::try_from)
             .transpose()
             .map_err(|e| ImportError::InvalidConvexValue(lineno, e))?;
+        let url = metadata
+            .url
+            .map(|url| parse_storage_url(&url))
+            .transpose()
+            .map_err(|e| ImportError::InvalidConvexValue(lineno, e))?;
 
         storage_metadata.insert(
             id,
-            (
+            StorageFileMetadata {
                 content_length,
                 content_type,
                 sha256,
                 storage_id,
                 creation_time,
-            ),
+                url,
+            },
         );
     }
     let total_num_files = storage_metadata.len();
@@ -152,8 +193,7 @@ pub async fn import_storage_table<RT: Runtime>(
         // The or_default means a storage file with a valid id will be imported
         // even if it has been explicitly removed from _storage/documents.jsonl,
         // to be robust to manual modifications.
-        let (content_length, content_type, expected_sha256, storage_id, creation_time) =
-            storage_metadata.remove(&id).unwrap_or_default();
+        let metadata = storage_metadata.remove(&id).unwrap_or_default();
         let file_chunks = objects
             .as_mut()
             .peeking_take_while(move |unit| match unit {
@@ -167,80 +207,87 @@ pub async fn import_storage_table<RT: Runtime>(
                     _ => Ok(None),
                 }
             });
-        let mut entry = file_storage
-            .transactional_file_storage
-            .upload_file(content_length, content_type, file_chunks, expected_sha256)
-            .await?;
-        if let Some(storage_id) = storage_id {
-            entry.storage_id = storage_id;
+        let creation_time = metadata.creation_time;
+        let entry = upload_storage_file(file_storage, metadata, file_chunks).await?;
+        if num_files < num_to_skip {
+            num_files += 1;
+            continue;
         }
+        insert_storage_file(
+            database,
+            identity,
+            table_id,
+            component_path,
+            usage,
+            &requestor,
+            table_mapping_for_schema,
+            id,
+            entry,
+            creation_time,
+        )
+        .await?;
+        num_files += 1;
+        if let Some(import_id) = import_id {
+            update_storage_progress(
+                database,
+                identity,
+                import_id,
+                component_path,
+                num_files,
+                total_num_files,
+            )
+            .await;
+        }
+    }
+    // Files that aren't in the zip are downloaded from their URLs, for exports
+    // made with storage URLs instead of the files.
+    for (id, mut metadata) in storage_metadata {
+        let Some(url) = metadata.url.take() else {
+            continue;
+        };
         if num_files < num_to_skip {
             num_files += 1;
             continue;
         }
-        let file_size = entry.size as u64;
-        database
-            .execute_with_overloaded_retries(
-                identity.clone(),
-                FunctionUsageTracker::new(),
-                "snapshot_import_storage_table",
-                |tx| {
-                    async {
-                        let mut entry_object_map =
-                            BTreeMap::from(ConvexObject::try_from(entry.clone())?);
-                        entry_object_map.insert(ID_FIELD.clone().into(), val!(id));
-                        if let Some(creation_time) = creation_time {
-                            entry_object_map.insert(
-                                CREATION_TIME_FIELD.clone().into(),
-                                val!(f64::from(creation_time)),
-                            );
-                        }
-                        let entry_object = ConvexObject::try_from(entry_object_map)?;
-                        ImportFacingModel::new(tx)
-                            .insert(
-                                table_id,
-                                &FILE_STORAGE_TABLE,
-                                entry_object,
-                                table_mapping_for_schema,
-                            )
-                            .await?;
-                        Ok(())
-                    }
-                    .into()
-                },
+        tracing::info!("importing storage file {} from its URL", id.encode());
+        let creation_time = metadata.creation_time;
+        let entry = database
+            .runtime()
+            .with_timeout(
+                "import_storage_file_download",
+                *IMPORT_STORAGE_FILE_DOWNLOAD_TIMEOUT,
+                download_storage_file(database, file_storage, fetch_client, id, url, metadata),
             )
-            .await?;
-        let content_type = entry
-            .content_type
-            .as_ref()
-            .map(|ct| ct.parse())
-            .transpose()?;
-        usage.track_storage_call(
-            component_path.clone(),
-            requestor.usage_tag(),
-            entry.storage_id,
-            content_type,
-            entry.sha256,
-        );
-        usage.track_storage_ingress_size(
-            component_path.clone(),
-            requestor.usage_tag().to_string(),
-            file_size,
-        );
+            .await
+            .map_err(|e| {
+                if e.is::<TimeoutError>() {
+                    ImportError::StorageFileDownloadFailed(id.encode(), e).into()
+                } else {
+                    e
+                }
+            })?;
+        insert_storage_file(
+            database,
+            identity,
+            table_id,
+            component_path,
+            usage,
+            &requestor,
+            table_mapping_for_schema,
+            id,
+            entry,
+            creation_time,
+        )
+        .await?;
         num_files += 1;
         if let Some(import_id) = import_id {
-            best_effort_update_progress_message(
+            update_storage_progress(
                 database,
                 identity,
                 import_id,
-                format!(
-                    "Importing \"_storage\" ({}/{} files)",
-                    num_files.separate_with_commas(),
-                    total_num_files.separate_with_commas()
-                ),
                 component_path,
-                &FILE_STORAGE_VIRTUAL_TABLE,
-                num_files as i64,
+                num_files,
+                total_num_files,
             )
             .await;
         }
@@ -263,3 +310,166 @@ pub async fn import_storage_table<RT: Runtime>(
     }
     Ok(())
 }
+
+/// Downloads a file with the deployment's fetch client, which goes through
+/// its proxy and doesn't follow redirects, and uploads it to file storage.
+async fn download_storage_file<RT: Runtime>(
+    database: &Database<RT>,
+    file_storage: &FileStorage<RT>,
+    fetch_client: &dyn FetchClient,
+    id: DeveloperDocumentId,
+    url: Url,
+    metadata: StorageFileMetadata,
+) -> anyhow::Result<FileStorageEntry> {
+    let request = HttpRequestStream {
+        headers: HeaderMap::new(),
+        url,
+        method: Method::GET,
+        body: Box::pin(stream::empty()),
+    };
+    let response = database
+        .runtime()
+        .with_timeout(
+            "import_storage_file_connect",
+            *IMPORT_STORAGE_FILE_CONNECT_TIMEOUT,
+            fetch_client.fetch(request),
+        )
+        .await
+        .map_err(|e| ImportError::StorageFileDownloadFailed(id.encode(), e))?;
+    // Redirects aren't followed, so they fail here too.
+    if !response.status.is_success() {
+        anyhow::bail!(ImportError::StorageFileDownloadFailed(
+            id.encode(),
+            anyhow::anyhow!("Request failed with status {}", response.status),
+        ));
+    }
+    let file_chunks = response
+        .body
+        .unwrap_or_else(|| stream::empty().boxed())
+        .map_err(move |e| {
+            anyhow::Error::from(ImportError::StorageFileDownloadFailed(id.encode(), e))
+        });
+    upload_storage_file(file_storage, metadata, file_chunks).await
+}
+
+/// Only files on the web can be downloaded, and not e.g. `file://` URLs for
+/// files on the backend's machine. Tests download over plain http from local
+/// servers.
+fn parse_storage_url(url: &str) -> anyhow::Result<Url> {
+    let url: Url = url.parse()?;
+    let allowed = if cfg!(any(test, feature = "testing")) {
+        matches!(url.scheme(), "http" | "https")
+    } else {
+        url.scheme() == "https"
+    };
+    anyhow::ensure!(allowed, "_storage file URL {url} must be https");
+    Ok(url)
+}
+
+async fn upload_storage_file<RT: Runtime>(
+    file_storage: &FileStorage<RT>,
+    metadata: StorageFileMetadata,
+    file_chunks: impl Stream<Item = anyhow::Result<Bytes>> + Send,
+) -> anyhow::Result<FileStorageEntry> {
+    let mut entry = file_storage
+        .transactional_file_storage
+        .upload_file(
+            metadata.content_length,
+            metadata.content_type,
+            file_chunks,
+            metadata.sha256,
+        )
+        .await?;
+    if let Some(storage_id) = metadata.storage_id {
+        entry.storage_id = storage_id;
+    }
+    Ok(entry)
+}
+
+async fn insert_storage_file<RT: Runtime>(
+    database: &Database<RT>,
+    identity: &Identity,
+    table_id: TabletIdAndTableNumber,
+    component_path: &ComponentPath,
+    usage: &FunctionUsageTracker,
+    requestor: &ImportRequestor,
+    table_mapping_for_schema: &TableMapping,
+    id: DeveloperDocumentId,
+    entry: FileStorageEntry,
+    creation_time: Option<CreationTime>,
+) -> anyhow::Result<()> {
+    let file_size = entry.size as u64;
+    database
+        .execute_with_overloaded_retries(
+            identity.clone(),
+            FunctionUsageTracker::new(),
+            "snapshot_import_storage_table",
+            |tx| {
+                async {
+                    let mut entry_object_map =
+                        BTreeMap::from(ConvexObject::try_from(entry.clone())?);
+                    entry_object_map.insert(ID_FIELD.clone().into(), val!(id));
+                    if let Some(creation_time) = creation_time {
+                        entry_object_map.insert(
+                            CREATION_TIME_FIELD.clone().into(),
+                            val!(f64::from(creation_time)),
+                        );
+                    }
+                    let entry_object = ConvexObject::try_from(entry_object_map)?;
+                    ImportFacingModel::new(tx)
+                        .insert(
+                            table_id,
+                            &FILE_STORAGE_TABLE,
+                            entry_object,
+                            table_mapping_for_schema,
+                        )
+                        .await?;
+                    Ok(())
+                }
+                .into()
+            },
+        )
+        .await?;
+    let content_type = entry
+        .content_type
+        .as_ref()
+        .map(|ct| ct.parse())
+        .transpose()?;
+    usage.track_storage_call(
+        component_path.clone(),
+        requestor.usage_tag(),
+        entry.storage_id,
+        content_type,
+        entry.sha256,
+    );
+    usage.track_storage_ingress_size(
+        component_path.clone(),
+        requestor.usage_tag().to_string(),
+        file_size,
+    );
+    Ok(())
+}
+
+async fn update_storage_progress<RT: Runtime>(
+    database: &Database<RT>,
+    identity: &Identity,
+    import_id: ResolvedDocumentId,
+    component_path: &ComponentPath,
+    num_files: u64,
+    total_num_files: usize,
+) {
+    best_effort_update_progress_message(
+        database,
+        identity,
+        import_id,
+        format!(
+            "Importing \"_storage\" ({}/{} files)",
+            num_files.separate_with_commas(),
+            total_num_files.separate_with_commas()
+        ),
+        component_path,
+        &FILE_STORAGE_VIRTUAL_TABLE,
+        num_files as i64,
+    )
+    .await;
+}
@@ -28,6 +28,7 @@ use common::{
     errors::report_error,
     execution_context::ExecutionId,
     ext::TryPeekableExt,
+    http::fetch::FetchClient,
     knobs::{
         MAX_IMPORT_AGE,
         TRANSACTION_MAX_NUM_USER_WRITES,
@@ -78,6 +79,7 @@ use model::{
     },
     snapshot_imports::{
         types::{
+            ImportColumnTypes,
             ImportFormat,
             ImportMode,
             ImportRequestor,
@@ -92,10 +94,7 @@ use shape_inference::{
     export_context::GeneratedSchema,
     ProdConfigWithOptionalFields,
 };
-use storage::{
-    Storage,
-    StorageExt,
-};
+use storage::Storage;
 use sync_types::{
     backoff::Backoff,
     Timestamp,
@@ -125,6 +124,7 @@ use crate::{
     snapshot_import::{
         audit_log::make_audit_log_event,
         confirmation::info_message_for_import,
+        import_body::ImportBody,
         import_error::{
             wrap_import_err,
             ImportError,
@@ -150,11 +150,15 @@ use crate::{
 };
 
 mod audit_log;
+mod column_types;
 mod confirmation;
+mod import_body;
 mod import_error;
 mod import_file_storage;
 mod metrics;
+mod mongo;
 mod parse;
+mod pg_dump;
 mod prepare_component;
 mod progress;
 mod schema_constraints;
@@ -170,6 +174,8 @@ struct SnapshotImportExecutor<RT: Runtime> {
     database: Database<RT>,
     snapshot_imports_storage: Arc<dyn Storage>,
     file_storage: FileStorage<RT>,
+    /// Downloads `_storage` files that are imported from their URLs.
+    fetch_client: Arc<dyn FetchClient>,
     usage_tracking: UsageCounter,
     backoff: Backoff,
 }
@@ -332,6 +338,7 @@ impl<RT: Runtime> SnapshotImportExecutor<RT> {
         let (table_mapping_for_import, total_documents_imported) = import_objects(
             &self.database,
             &self.file_storage,
+            self.fetch_client.as_ref(),
             Identity::system(),
             snapshot_import.mode,
             objects,
@@ -341,9 +348,14 @@ impl<RT: Runtime> SnapshotImportExecutor<RT> {
         )
         .await?;
 
-        let audit_log_event =
-            make_audit_log_event(&self.database, &table_mapping_for_import, &snapshot_import)
-                .await?;
+        let audit_log_event = make_audit_log_event(
+            &self.database,
+            &table_mapping_for_import,
+            snapshot_import.mode,
+            snapshot_import.format.clone(),
+            snapshot_import.requestor.clone(),
+        )
+        .await?;
 
         let object_attributes = (match &snapshot_import.object_key {
             Ok(key) => {
@@ -402,17 +414,12 @@ impl<RT: Runtime> SnapshotImportExecutor<RT> {
                 snapshot_import.component_path.clone(),
             )
         };
-        let body_stream = move || {
-            let object_key = object_key.clone();
-            async move {
-                let reader = match object_key.clone() {
-                    Ok(key) => self.snapshot_imports_storage.get_fq_object(&key).await?,
-                    Err(key) => self.snapshot_imports_storage.get(&key).await?,
-                };
-                reader.with_context(|| format!("Missing import object {:?}", object_key))
-            }
+        let object_key = match object_key {
+            Ok(key) => key,
+            Err(key) => self.snapshot_imports_storage.fully_qualified_key(&key),
         };
-        let objects = parse_objects(format.clone(), component_path.clone(), body_stream).boxed();
+        let body = ImportBody::new(self.snapshot_imports_storage.clone(), object_key);
+        let objects = parse_objects(format.clone(), component_path.clone(), body).boxed();
 
         let component_id = prepare_component_for_import(&self.database, &component_path).await?;
         // Remapping could be more extensive here, it's just relatively simple to handle
@@ -422,7 +429,7 @@ impl<RT: Runtime> SnapshotImportExecutor<RT> {
         let mut tx = self.database.begin(Identity::system()).await?;
         let initial_schemas = schemas_for_import(&mut tx).await?;
         let objects = match format {
-            ImportFormat::Csv(table_name) => {
+            ImportFormat::Csv(table_name, _) => {
                 remap_empty_string_by_schema(
                     TableNamespace::from(component_id),
                     table_name,
@@ -646,6 +653,119 @@ pub async fn do_import_from_object_key<RT: Runtime>(
     }
 }
 
+/// Whether an import in `format` is parsed front to back in a single pass, so
+/// it can be imported from a request body as it arrives.
+pub fn can_stream_import(format: &ImportFormat) -> bool {
+    match format {
+        ImportFormat::Csv(_, column_types) | ImportFormat::PgDump(_, column_types) => {
+            *column_types != ImportColumnTypes::Infer
+        },
+        ImportFormat::JsonLines(_) | ImportFormat::JsonArray(_) | ImportFormat::MongoBson(..) => {
+            true
+        },
+        // ZIP's central directory is at the end of the file, and an archive's
+        // collections are read in a separate pass each.
+        ImportFormat::Zip | ImportFormat::MongoArchive(_) => false,
+    }
+}
+
+/// Imports a request body as it's parsed, without uploading it to storage
+/// first, so it can be larger than local disk. Objects are still inserted in
+/// chunked transactions and the tables swapped in at the end. Unlike stored
+/// imports, these aren't tracked in `_snapshot_imports`, so they can't be
+/// confirmed, canceled or resumed after a restart. `format` must be one that
+/// `can_stream_import`.
+pub async fn do_streamed_import<RT: Runtime>(
+    application: &Application<RT>,
+    identity: Identity,
+    format: ImportFormat,
+    mode: ImportMode,
+    component_path: ComponentPath,
+    body_stream: BoxStream<'static, anyhow::Result<Bytes>>,
+) -> anyhow::Result<u64> {
+    if !identity.is_admin() {
+        anyhow::bail!(ImportError::Unauthorized);
+    }
+    anyhow::ensure!(
+        can_stream_import(&format),
+        "{format:?} imports can't be streamed"
+    );
+    let result = async {
+        let component_id =
+            prepare_component_for_import(&application.database, &component_path).await?;
+        let mut tx = application.begin(identity.clone()).await?;
+        let initial_schemas = schemas_for_import(&mut tx).await?;
+        let objects = parse_objects(
+            format.clone(),
+            component_path,
+            ImportBody::streamed(body_stream),
+        )
+        .boxed();
+        let objects = match &format {
+            ImportFormat::Csv(table_name, _) => {
+                remap_empty_string_by_schema(
+                    TableNamespace::from(component_id),
+                    table_name.clone(),
+                    &mut tx,
+                    objects,
+                )
+                .await?
+            },
+            _ => objects,
+        }
+        .peekable();
+        drop(tx);
+
+        let usage = FunctionUsageTracker::new();
+        let (table_mapping_for_import, total_documents_imported) = import_objects(
+            &application.database,
+            &application.file_storage,
+            application.fetch_client.as_ref(),
+            identity.clone(),
+            mode,
+            objects,
+            usage.clone(),
+            None,
+            ImportRequestor::SnapshotImport,
+        )
+        .await?;
+        let audit_log_event = make_audit_log_event(
+            &application.database,
+            &table_mapping_for_import,
+            mode,
+            format.clone(),
+            ImportRequestor::SnapshotImport,
+        )
+        .await?;
+        let pause_client = application.runtime.pause_client();
+        pause_client.wait("before_finalize_import").await;
+        finalize_import(
+            &application.database,
+            &application.usage_tracking,
+            identity.clone(),
+            None,
+            initial_schemas,
+            table_mapping_for_import,
+            usage,
+            audit_log_event,
+            None,
+            ImportRequestor::SnapshotImport,
+        )
+        .await?;
+        Ok(total_documents_imported)
+    }
+    .await;
+    // Fail like a stored import does.
+    result.map_err(|e| {
+        let e = wrap_import_err(e);
+        if e.is_bad_request() {
+            ErrorMetadata::bad_request("ImportFailed", e.user_facing_message()).into()
+        } else {
+            e
+        }
+    })
+}
+
 /// Clears tables atomically.
 /// Returns number of documents deleted.
 /// This is implemented as an import of empty tables in Replace mode.
@@ -670,6 +790,7 @@ pub async fn clear_tables<RT: Runtime>(
     let (table_mapping_for_import, _) = import_objects(
         &application.database,
         &application.file_storage,
+        application.fetch_client.as_ref(),
         identity.clone(),
         ImportMode::Replace,
         objects,
@@ -698,6 +819,7 @@ pub async fn clear_tables<RT: Runtime>(
 async fn import_objects<RT: Runtime>(
     database: &Database<RT>,
     file_storage: &FileStorage<RT>,
+    fetch_client: &dyn FetchClient,
     identity: Identity,
     mode: ImportMode,
     objects: Peekable<BoxStream<'_, anyhow::Result<ImportUnit>>>,
@@ -731,6 +853,7 @@ async fn import_objects<RT: Runtime>(
     while let Some(num_documents) = import_single_table(
         database,
         file_storage,
+        fetch_client,
         &identity,
         mode,
         objects.as_mut(),
@@ -1027,6 +1150,7 @@ async fn import_tables_table<RT: Runtime>(
 async fn import_single_table<RT: Runtime>(
     database: &Database<RT>,
     file_storage: &FileStorage<RT>,
+    fetch_client: &dyn FetchClient,
     identity: &Identity,
     mode: ImportMode,
     mut objects: Pin<&mut Peekable<BoxStream<'_, anyhow::Result<ImportUnit>>>>,
@@ -1136,6 +1260,7 @@ async fn import_single_table<RT: Runtime>(
         import_storage_table(
             database,
             file_storage,
+            fetch_client,
             identity,
             table_id,
             component_path,
@@ -0,0 +1,571 @@
+//! Parsing `mongodump` output: a collection's `.bson` file, or an `--archive`
+//! of a whole database. Documents are converted to the JSON the other import
+//! formats use, so ObjectIds become hex strings, dates become milliseconds
+//! since the epoch, and numbers become float64s.
+use std::{
+    io,
+    str,
+};
+
+use anyhow::Context;
+use async_compression::tokio::bufread::GzipDecoder;
+use model::snapshot_imports::types::MongoIdMapping;
+use serde_json::{
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use tokio::io::{
+    AsyncBufReadExt,
+    AsyncRead,
+    AsyncReadExt,
+};
+use tokio_util::io::StreamReader;
+
+use crate::snapshot_import::{
+    import_body::ImportStream,
+    import_error::ImportError,
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ARCHIVE_MAGIC: u32 = 0x8199e26d;
+/// Ends the prelude of an archive, and each block of a collection's documents.
+const ARCHIVE_TERMINATOR: [u8; 4] = [0xff; 4];
+/// MongoDB's limit on the size of a document, plus some room for the
+/// metadata in archives.
+const MAX_BSON_DOCUMENT_SIZE: usize = (16 << 20) + (16 << 10);
+
+pub type MongoDumpReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Reads a `mongodump` file, decompressing it if it was dumped with `--gzip`.
+pub async fn mongo_dump_reader(stream: ImportStream) -> anyhow::Result<MongoDumpReader> {
+    let mut reader = StreamReader::new(stream);
+    if reader.fill_buf().await?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(GzipDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+fn invalid_mongo_dump(message: impl Into<String>) -> anyhow::Error {
+    ImportError::InvalidMongoDump(anyhow::anyhow!(message.into())).into()
+}
+
+fn map_mongo_dump_io_error(e: io::Error) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::Other {
+        // S3 errors get mapped into ErrorKind::Other
+        e.into()
+    } else {
+        ImportError::InvalidMongoDump(e.into()).into()
+    }
+}
+
+enum BsonItem {
+    Document(Vec<u8>),
+    Terminator,
+}
+
+/// Reads the next BSON document or archive terminator, or `None` at the end
+/// of the file.
+async fn read_bson_item(reader: &mut MongoDumpReader) -> anyhow::Result<Option<BsonItem>> {
+    let mut len_bytes = [0; 4];
+    let mut num_read = 0;
+    while num_read < len_bytes.len() {
+        let n = reader
+            .read(&mut len_bytes[num_read..])
+            .await
+            .map_err(map_mongo_dump_io_error)?;
+        if n == 0 {
+            if num_read == 0 {
+                return Ok(None);
+            }
+            return Err(invalid_mongo_dump("file ends in the middle of a document"));
+        }
+        num_read += n;
+    }
+    if len_bytes == ARCHIVE_TERMINATOR {
+        return Ok(Some(BsonItem::Terminator));
+    }
+    let len = i32::from_le_bytes(len_bytes) as usize;
+    if !(5..=MAX_BSON_DOCUMENT_SIZE).contains(&len) {
+        return Err(invalid_mongo_dump(format!("invalid document length {len}")));
+    }
+    let mut document = vec![0; len];
+    document[..4].copy_from_slice(&len_bytes);
+    reader
+        .read_exact(&mut document[4..])
+        .await
+        .map_err(map_mongo_dump_io_error)?;
+    Ok(Some(BsonItem::Document(document)))
+}
+
+/// Reads the next document in a `.bson` file, or `None` at the end of the
+/// file.
+pub async fn read_bson_document(reader: &mut MongoDumpReader) -> anyhow::Result<Option<Vec<u8>>> {
+    match read_bson_item(reader).await? {
+        Some(BsonItem::Document(document)) => Ok(Some(document)),
+        Some(BsonItem::Terminator) => Err(invalid_mongo_dump(
+            "found an archive terminator in a .bson file",
+        )),
+        None => Ok(None),
+    }
+}
+
+/// A collection in a `mongodump --archive`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MongoNamespace {
+    pub db: String,
+    pub collection: String,
+}
+
+impl MongoNamespace {
+    fn from_document(document: &[u8]) -> anyhow::Result<Self> {
+        let document = BsonCursor(document)
+            .read_object()
+            .map_err(ImportError::InvalidMongoDump)?;
+        let field = |name: &str| {
+            document
+                .get(name)
+                .and_then(JsonValue::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| invalid_mongo_dump(format!("archive metadata has no {name}")))
+        };
+        Ok(Self {
+            db: field("db")?,
+            collection: field("collection")?,
+        })
+    }
+}
+
+/// Reads the documents in a `mongodump --archive`. The archive starts with a
+/// prelude of the collections it has, and then has blocks of each
+/// collection's documents, which are interleaved when collections are dumped
+/// in parallel.
+pub struct MongoArchiveReader {
+    reader: MongoDumpReader,
+    /// The collection of the block being read, if any.
+    namespace: Option<MongoNamespace>,
+}
+
+impl MongoArchiveReader {
+    /// Reads the archive's prelude, returning its collections. Views and
+    /// system collections are left out.
+    pub async fn new(mut reader: MongoDumpReader) -> anyhow::Result<(Self, Vec<MongoNamespace>)> {
+        let mut magic = [0; 4];
+        reader
+            .read_exact(&mut magic)
+            .await
+            .map_err(map_mongo_dump_io_error)?;
+        if u32::from_le_bytes(magic) != ARCHIVE_MAGIC {
+            return Err(invalid_mongo_dump("not a mongodump archive"));
+        }
+        // The prelude has a header followed by each collection's metadata.
+        let mut collections = vec![];
+        loop {
+            match read_bson_item(&mut reader).await? {
+                Some(BsonItem::Document(document)) => {
+                    let metadata = BsonCursor(&document)
+                        .read_object()
+                        .map_err(ImportError::InvalidMongoDump)?;
+                    if !metadata.contains_key("collection")
+                        || metadata.get("type").and_then(JsonValue::as_str) == Some("view")
+                    {
+                        continue;
+                    }
+                    let namespace = MongoNamespace::from_document(&document)?;
+                    if !namespace.collection.starts_with("system.") {
+                        collections.push(namespace);
+                    }
+                },
+                Some(BsonItem::Terminator) => break,
+                None => return Err(invalid_mongo_dump("archive ends in its prelude")),
+            }
+        }
+        let archive_reader = Self {
+            reader,
+            namespace: None,
+        };
+        Ok((archive_reader, collections))
+    }
+
+    /// Reads the next document in the collection `namespace`, skipping the
+    /// other collections' documents.
+    pub async fn next_document(
+        &mut self,
+        namespace: &MongoNamespace,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        loop {
+            match read_bson_item(&mut self.reader).await? {
+                Some(BsonItem::Document(document)) => match &self.namespace {
+                    // Each block starts with the collection's namespace.
+                    None => self.namespace = Some(MongoNamespace::from_document(&document)?),
+                    Some(block_namespace) if block_namespace == namespace => {
+                        return Ok(Some(document));
+                    },
+                    Some(_) => {},
+                },
+                Some(BsonItem::Terminator) => {
+                    if self.namespace.take().is_none() {
+                        return Err(invalid_mongo_dump("archive block has no namespace"));
+                    }
+                },
+                None => {
+                    if self.namespace.is_some() {
+                        return Err(invalid_mongo_dump("archive ends in the middle of a block"));
+                    }
+                    return Ok(None);
+                },
+            }
+        }
+    }
+}
+
+/// Converts a MongoDB document to a JSON object to import, mapping its `_id`
+/// with `id_mapping`.
+pub fn mongo_document_to_json(
+    document: &[u8],
+    id_mapping: &MongoIdMapping,
+) -> anyhow::Result<JsonValue> {
+    let mut object = BsonCursor(document).read_object()?;
+    let id = object.remove("_id");
+    if let MongoIdMapping::Field(field) = id_mapping {
+        let id = id.context("document has no _id")?;
+        anyhow::ensure!(
+            !object.contains_key(&field[..]),
+            "document already has a field named {field}"
+        );
+        object.insert(field.to_string(), id);
+    }
+    Ok(JsonValue::Object(object))
+}
+
+struct BsonCursor<'a>(&'a [u8]);
+
+impl<'a> BsonCursor<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(len <= self.0.len(), "document is truncated");
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn read_i64(&mut self) -> anyhow::Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn read_f64(&mut self) -> anyhow::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.read_i32()?;
+        usize::try_from(len).with_context(|| format!("invalid length {len}"))
+    }
+
+    fn read_cstring(&mut self) -> anyhow::Result<&'a str> {
+        let len = self
+            .0
+            .iter()
+            .position(|b| *b == 0)
+            .context("string has no terminating null byte")?;
+        let s = str::from_utf8(self.take(len)?)?;
+        self.take(1)?;
+        Ok(s)
+    }
+
+    fn read_string(&mut self) -> anyhow::Result<&'a str> {
+        let len = self.read_len()?;
+        let bytes = self.take(len)?;
+        let Some((0, s)) = bytes.split_last() else {
+            anyhow::bail!("string has no terminating null byte");
+        };
+        Ok(str::from_utf8(s)?)
+    }
+
+    /// Reads a document's elements in order, which is the order of an
+    /// array's elements.
+    fn read_elements(&mut self) -> anyhow::Result<Vec<(&'a str, JsonValue)>> {
+        let len = self.read_len()?;
+        anyhow::ensure!(len >= 5, "invalid document length {len}");
+        let mut elements = BsonCursor(self.take(len - 4)?);
+        let mut values = vec![];
+        loop {
+            let element_type = elements.read_u8()?;
+            if element_type == 0 {
+                anyhow::ensure!(elements.0.is_empty(), "document has data after its end");
+                return Ok(values);
+            }
+            let key = elements.read_cstring()?;
+            let value = elements
+                .read_value(element_type)
+                .with_context(|| format!("invalid value for {key}"))?;
+            values.push((key, value));
+        }
+    }
+
+    fn read_object(&mut self) -> anyhow::Result<JsonMap<String, JsonValue>> {
+        Ok(self
+            .read_elements()?
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect())
+    }
+
+    fn read_value(&mut self, element_type: u8) -> anyhow::Result<JsonValue> {
+        let value = match element_type {
+            // Double
+            0x01 => {
+                let value = self.read_f64()?;
+                serde_json::Number::from_f64(value)
+                    .map(JsonValue::Number)
+                    .with_context(|| format!("{value} isn't a finite number"))?
+            },
+            // String, JavaScript code, and symbol
+            0x02 | 0x0D | 0x0E => self.read_string()?.into(),
+            0x03 => self.read_object()?.into(),
+            // Arrays are documents with the keys "0", "1", ...
+            0x04 => self
+                .read_elements()?
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect(),
+            // Binary data, with UUIDs in their usual string form.
+            0x05 => {
+                let len = self.read_len()?;
+                let subtype = self.read_u8()?;
+                let data = self.take(len)?;
+                match subtype {
+                    0x03 | 0x04 if data.len() == 16 => {
+                        let hex = hex::encode(data);
+                        format!(
+                            "{}-{}-{}-{}-{}",
+                            &hex[..8],
+                            &hex[8..12],
+                            &hex[12..16],
+                            &hex[16..20],
+                            &hex[20..]
+                        )
+                        .into()
+                    },
+                    _ => base64::encode(data).into(),
+                }
+            },
+            // Undefined and null
+            0x06 | 0x0A => JsonValue::Null,
+            0x07 => hex::encode(self.take(12)?).into(),
+            0x08 => (self.read_u8()? != 0).into(),
+            // UTC datetime, in milliseconds since the epoch.
+            0x09 => self.read_i64()?.into(),
+            0x0B => {
+                let pattern = self.read_cstring()?;
+                let options = self.read_cstring()?;
+                format!("/{pattern}/{options}").into()
+            },
+            // DBPointer, a namespace and an ObjectId.
+            0x0C => {
+                self.read_string()?;
+                hex::encode(self.take(12)?).into()
+            },
+            // JavaScript code with scope
+            0x0F => {
+                self.read_i32()?;
+                let code = self.read_string()?;
+                self.read_elements()?;
+                code.into()
+            },
+            0x10 => self.read_i32()?.into(),
+            // Internal timestamp, with seconds since the epoch in the high 32 bits.
+            0x11 => ((self.read_i64()? as u64 >> 32) * 1000).into(),
+            0x12 => self.read_i64()?.into(),
+            0x13 => anyhow::bail!("Decimal128 values aren't supported"),
+            0x7F | 0xFF => anyhow::bail!("MinKey and MaxKey values aren't supported"),
+            _ => anyhow::bail!("unknown BSON type {element_type:#04x}"),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::{
+        ARCHIVE_MAGIC,
+        ARCHIVE_TERMINATOR,
+    };
+
+    /// Encodes a BSON document from its elements' types, keys, and encoded
+    /// values.
+    pub fn bson_document(elements: &[(u8, &str, Vec<u8>)]) -> Vec<u8> {
+        let mut body = vec![];
+        for (element_type, key, value) in elements {
+            body.push(*element_type);
+            body.extend_from_slice(key.as_bytes());
+            body.push(0);
+            body.extend_from_slice(value);
+        }
+        body.push(0);
+        let mut document = ((body.len() + 4) as i32).to_le_bytes().to_vec();
+        document.extend(body);
+        document
+    }
+
+    pub fn bson_string(s: &str) -> Vec<u8> {
+        let mut encoded = ((s.len() + 1) as i32).to_le_bytes().to_vec();
+        encoded.extend_from_slice(s.as_bytes());
+        encoded.push(0);
+        encoded
+    }
+
+    fn namespace_document(db: &str, collection: &str) -> Vec<u8> {
+        bson_document(&[
+            (0x02, "db", bson_string(db)),
+            (0x02, "collection", bson_string(collection)),
+        ])
+    }
+
+    /// Encodes a `mongodump --archive` of the collections in `db`, with a
+    /// block for each of the `(collection, documents)` in `blocks`.
+    pub fn mongo_archive(
+        db: &str,
+        collections: &[&str],
+        blocks: &[(&str, Vec<Vec<u8>>)],
+    ) -> Vec<u8> {
+        let mut archive = ARCHIVE_MAGIC.to_le_bytes().to_vec();
+        archive.extend(bson_document(&[(0x02, "version", bson_string("0.1"))]));
+        for collection in collections {
+            archive.extend(namespace_document(db, collection));
+        }
+        archive.extend(ARCHIVE_TERMINATOR);
+        for (collection, documents) in blocks {
+            archive.extend(namespace_document(db, collection));
+            for document in documents {
+                archive.extend(document);
+            }
+            archive.extend(ARCHIVE_TERMINATOR);
+        }
+        archive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use model::snapshot_imports::types::MongoIdMapping;
+    use serde_json::json;
+
+    use super::{
+        mongo_document_to_json,
+        testing::{
+            bson_document,
+            bson_string,
+            mongo_archive,
+        },
+        MongoArchiveReader,
+        MongoNamespace,
+    };
+
+    #[test]
+    fn test_mongo_document_to_json() -> anyhow::Result<()> {
+        let object_id = hex::decode("65a1b2c3d4e5f60718293a4b")?;
+        let array: Vec<_> = (0..11)
+            .map(|i| (0x10, i.to_string(), (i as i32).to_le_bytes().to_vec()))
+            .collect();
+        let array: Vec<_> = array
+            .iter()
+            .map(|(element_type, key, value)| (*element_type, key.as_str(), value.clone()))
+            .collect();
+        let mut uuid = vec![16, 0, 0, 0, 0x04];
+        uuid.extend(hex::decode("0123456789abcdef0123456789abcdef")?);
+        let document = bson_document(&[
+            (0x07, "_id", object_id.clone()),
+            (0x02, "name", bson_string("Ada")),
+            (0x01, "score", 9.5f64.to_le_bytes().to_vec()),
+            (0x12, "visits", 42i64.to_le_bytes().to_vec()),
+            (0x08, "active", vec![1]),
+            (0x09, "createdAt", 1700000000000i64.to_le_bytes().to_vec()),
+            (0x0A, "deletedAt", vec![]),
+            (0x05, "token", uuid),
+            (
+                0x03,
+                "address",
+                bson_document(&[
+                    (0x02, "city", bson_string("London")),
+                    (0x07, "ownerId", object_id),
+                ]),
+            ),
+            (0x04, "ranks", bson_document(&array)),
+        ]);
+        let expected_fields = json!({
+            "name": "Ada",
+            "score": 9.5,
+            "visits": 42,
+            "active": true,
+            "createdAt": 1700000000000i64,
+            "deletedAt": null,
+            "token": "01234567-89ab-cdef-0123-456789abcdef",
+            "address": { "city": "London", "ownerId": "65a1b2c3d4e5f60718293a4b" },
+            "ranks": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+        });
+        assert_eq!(
+            mongo_document_to_json(&document, &MongoIdMapping::Discard)?,
+            expected_fields
+        );
+        let mut expected = expected_fields.clone();
+        expected["mongoId"] = json!("65a1b2c3d4e5f60718293a4b");
+        assert_eq!(
+            mongo_document_to_json(&document, &MongoIdMapping::Field("mongoId".parse()?))?,
+            expected
+        );
+        // The `_id` can't overwrite another field.
+        assert!(
+            mongo_document_to_json(&document, &MongoIdMapping::Field("name".parse()?)).is_err()
+        );
+
+        let decimal = bson_document(&[(0x13, "price", vec![0; 16])]);
+        assert!(mongo_document_to_json(&decimal, &MongoIdMapping::Discard).is_err());
+        assert!(mongo_document_to_json(&document[..10], &MongoIdMapping::Discard).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mongo_archive_reader() -> anyhow::Result<()> {
+        let user = |name: &str| bson_document(&[(0x02, "name", bson_string(name))]);
+        let archive = mongo_archive(
+            "app",
+            &["users", "posts", "system.views"],
+            &[
+                ("users", vec![user("a"), user("b")]),
+                ("posts", vec![user("c")]),
+                ("users", vec![user("d")]),
+                ("users", vec![]),
+                ("posts", vec![]),
+            ],
+        );
+        let namespace = |collection: &str| MongoNamespace {
+            db: "app".to_string(),
+            collection: collection.to_string(),
+        };
+        let (_, collections) =
+            MongoArchiveReader::new(Box::new(Cursor::new(archive.clone()))).await?;
+        assert_eq!(collections, vec![namespace("users"), namespace("posts")]);
+
+        let (mut reader, _) = MongoArchiveReader::new(Box::new(Cursor::new(archive))).await?;
+        let mut users = vec![];
+        while let Some(document) = reader.next_document(&namespace("users")).await? {
+            users.push(document);
+        }
+        assert_eq!(users, vec![user("a"), user("b"), user("d")]);
+
+        assert!(MongoArchiveReader::new(Box::new(Cursor::new(user("a"))))
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -26,14 +26,16 @@ use futures::{
     pin_mut,
     AsyncBufReadExt,
     AsyncReadExt,
-    Future,
     StreamExt,
     TryStreamExt,
 };
 use futures_async_stream::try_stream;
 use model::{
     file_storage::FILE_STORAGE_VIRTUAL_TABLE,
-    snapshot_imports::types::ImportFormat,
+    snapshot_imports::types::{
+        ImportColumnTypes,
+        ImportFormat,
+    },
 };
 use regex::Regex;
 use serde_json::{
@@ -49,14 +51,27 @@ use shape_inference::{
     Shape,
     ShapeConfig,
 };
-use storage::StorageGetStream;
 use tokio::io::AsyncBufReadExt as _;
 use value::{
     id_v6::DeveloperDocumentId,
     TableName,
 };
 
-use crate::snapshot_import::import_error::ImportError;
+use crate::snapshot_import::{
+    column_types::{
+        ColumnConverter,
+        ColumnTypeInference,
+    },
+    import_body::ImportBody,
+    import_error::ImportError,
+    mongo::{
+        mongo_document_to_json,
+        mongo_dump_reader,
+        read_bson_document,
+        MongoArchiveReader,
+    },
+    pg_dump::PgCopyReader,
+};
 
 #[derive(Debug)]
 pub enum ImportUnit {
@@ -120,12 +135,30 @@ fn map_csv_error(e: csv_async::Error) -> anyhow::Error {
     }
 }
 
+async fn parse_csv_header<R: futures::AsyncRead + Unpin + Send>(
+    reader: &mut csv_async::AsyncReader<R>,
+) -> anyhow::Result<Vec<FieldName>> {
+    if !reader.has_headers() {
+        anyhow::bail!(ImportError::CsvMissingHeaders);
+    }
+    let headers = reader.headers().await.map_err(map_csv_error)?;
+    headers
+        .iter()
+        .map(|s| {
+            let trimmed = s.trim_matches(' ');
+            let field_name = FieldName::from_str(trimmed)
+                .map_err(|e| ImportError::CsvInvalidHeader(trimmed.to_string(), e))?;
+            Ok(field_name)
+        })
+        .collect()
+}
+
 /// Parse and stream units from the imported file, starting with a NewTable
 /// for each table and then Objects for each object to import into the table.
-/// stream_body returns the file as streamed bytes. stream_body() can be called
-/// multiple times to read the file multiple times, for cases where the file
-/// must be read out of order, e.g. because the _tables table must be imported
-/// first.
+/// The file is streamed from `body`, which can be read multiple times if it
+/// was uploaded, for cases where the file must be read out of order, e.g.
+/// because the _tables table must be imported first. ZIP files are read with
+/// ranged reads, so the file is never buffered in memory or on local disk.
 /// Objects are yielded with the following guarantees:
 /// 1. When an Object is yielded, it is in the table corresponding to the most
 ///    recently yielded NewTable.
@@ -136,53 +169,46 @@ fn map_csv_error(e: csv_async::Error) -> anyhow::Error {
 /// 4. If a table has a GeneratedSchema, the GeneratedSchema will be yielded
 ///    before any Objects in that table.
 #[try_stream(ok = ImportUnit, error = anyhow::Error)]
-pub async fn parse_objects<'a, Fut>(
-    format: ImportFormat,
-    component_path: ComponentPath,
-    stream_body: impl Fn() -> Fut + 'a,
-) where
-    Fut: Future<Output = anyhow::Result<StorageGetStream>> + 'a,
-{
+pub async fn parse_objects(format: ImportFormat, component_path: ComponentPath, body: ImportBody) {
     match format {
-        ImportFormat::Csv(table_name) => {
-            let reader = stream_body().await?;
-            yield ImportUnit::NewTable(component_path, table_name);
-            let mut reader = csv_async::AsyncReader::from_reader(reader.into_reader());
-            if !reader.has_headers() {
-                anyhow::bail!(ImportError::CsvMissingHeaders);
-            }
-            let field_names = {
-                let headers = reader.headers().await.map_err(map_csv_error)?;
-                headers
-                    .iter()
-                    .map(|s| {
-                        let trimmed = s.trim_matches(' ');
-                        let field_name = FieldName::from_str(trimmed)
-                            .map_err(|e| ImportError::CsvInvalidHeader(trimmed.to_string(), e))?;
-                        Ok(field_name)
-                    })
-                    .collect::<anyhow::Result<Vec<_>>>()?
+        ImportFormat::Csv(table_name, column_types) => {
+            let column_types = match column_types {
+                ImportColumnTypes::Infer => {
+                    // First pass: infer the column types from all of the rows.
+                    let reader = body.stream().await?;
+                    let mut reader = csv_async::AsyncReader::from_reader(reader.into_async_read());
+                    let mut inference =
+                        ColumnTypeInference::new(parse_csv_header(&mut reader).await?);
+                    let mut enumerate_rows = reader.records().enumerate();
+                    while let Some((i, row_r)) = enumerate_rows.next().await {
+                        let row = row_r.map_err(map_csv_error)?;
+                        inference.add_row(i + 1, &row.iter().map(Some).collect::<Vec<_>>())?;
+                    }
+                    inference.into_column_types()
+                },
+                column_types => column_types,
             };
+            let reader = body.stream().await?;
+            let mut reader = csv_async::AsyncReader::from_reader(reader.into_async_read());
+            let converter =
+                ColumnConverter::new(&column_types, parse_csv_header(&mut reader).await?)?;
+            if let Some(generated_schema) = converter.generated_schema()? {
+                yield ImportUnit::GeneratedSchema(
+                    component_path.clone(),
+                    table_name.clone(),
+                    generated_schema,
+                );
+            }
+            yield ImportUnit::NewTable(component_path, table_name);
             let mut enumerate_rows = reader.records().enumerate();
             while let Some((i, row_r)) = enumerate_rows.next().await {
-                let lineno = i + 1;
-                let parsed_row = row_r
-                    .map_err(map_csv_error)?
-                    .iter()
-                    .map(parse_csv_cell)
-                    .collect::<Vec<JsonValue>>();
-                let mut obj = BTreeMap::new();
-                if field_names.len() != parsed_row.len() {
-                    anyhow::bail!(ImportError::CsvRowMissingFields(lineno));
-                }
-                for (field_name, value) in field_names.iter().zip(parsed_row.into_iter()) {
-                    obj.insert(field_name.to_string(), value);
-                }
-                yield ImportUnit::Object(serde_json::to_value(obj)?);
+                let row = row_r.map_err(map_csv_error)?;
+                let cells: Vec<_> = row.iter().map(Some).collect();
+                yield ImportUnit::Object(converter.convert_row(i + 1, &cells)?);
             }
         },
         ImportFormat::JsonLines(table_name) => {
-            let mut reader = stream_body().await?.into_reader();
+            let mut reader = body.stream().await?.into_async_read();
             yield ImportUnit::NewTable(component_path, table_name);
             let mut line = String::new();
             let mut lineno = 1;
@@ -200,11 +226,11 @@ pub async fn parse_objects<'a, Fut>(
             }
         },
         ImportFormat::JsonArray(table_name) => {
-            let reader = stream_body().await?;
+            let reader = body.stream().await?;
             yield ImportUnit::NewTable(component_path, table_name);
             let mut buf = Vec::new();
             let mut truncated_reader = reader
-                .into_reader()
+                .into_async_read()
                 .take((*TRANSACTION_MAX_USER_WRITE_SIZE_BYTES as u64) + 1);
             truncated_reader.read_to_end(&mut buf).await?;
             if buf.len() > *TRANSACTION_MAX_USER_WRITE_SIZE_BYTES {
@@ -217,11 +243,91 @@ pub async fn parse_objects<'a, Fut>(
                 yield ImportUnit::Object(value.clone());
             }
         },
+        ImportFormat::MongoBson(table_name, id_mapping) => {
+            let mut reader = mongo_dump_reader(body.stream().await?).await?;
+            yield ImportUnit::NewTable(component_path, table_name);
+            let mut num_documents = 0;
+            while let Some(document) = read_bson_document(&mut reader).await? {
+                num_documents += 1;
+                let v = mongo_document_to_json(&document, &id_mapping)
+                    .map_err(|e| ImportError::InvalidBsonDocument(num_documents, e))?;
+                yield ImportUnit::Object(v);
+            }
+        },
+        ImportFormat::MongoArchive(id_mapping) => {
+            // First pass: read the collections from the archive's prelude.
+            let reader = mongo_dump_reader(body.stream().await?).await?;
+            let (_, namespaces) = MongoArchiveReader::new(reader).await?;
+            let mut tables = BTreeMap::new();
+            for namespace in &namespaces {
+                let table_name: TableName = namespace
+                    .collection
+                    .parse()
+                    .map_err(|e| ImportError::InvalidName(namespace.collection.clone(), e))?;
+                if tables.insert(table_name, namespace).is_some() {
+                    anyhow::bail!(ErrorMetadata::bad_request(
+                        "DuplicateMongoCollection",
+                        format!(
+                            "Archive has more than one collection named {:?}. Import each \
+                             database's collections separately.",
+                            namespace.collection
+                        ),
+                    ));
+                }
+            }
+            // Second pass for each collection, since an archive's collections
+            // are interleaved.
+            for (table_name, namespace) in tables {
+                let reader = mongo_dump_reader(body.stream().await?).await?;
+                let (mut archive_reader, _) = MongoArchiveReader::new(reader).await?;
+                yield ImportUnit::NewTable(component_path.clone(), table_name);
+                let mut num_documents = 0;
+                while let Some(document) = archive_reader.next_document(namespace).await? {
+                    num_documents += 1;
+                    let v = mongo_document_to_json(&document, &id_mapping)
+                        .map_err(|e| ImportError::InvalidBsonDocument(num_documents, e))?;
+                    yield ImportUnit::Object(v);
+                }
+            }
+        },
+        ImportFormat::PgDump(table_name, column_types) => {
+            let column_types = match column_types {
+                ImportColumnTypes::Infer => {
+                    // First pass: infer the column types from all of the rows.
+                    let (mut rows, columns) =
+                        PgCopyReader::new(body.stream().await?, &table_name).await?;
+                    let mut inference = ColumnTypeInference::new(columns);
+                    let mut row_number = 0;
+                    while let Some(row) = rows.next_row().await? {
+                        row_number += 1;
+                        let cells: Vec<_> = row.iter().map(Option::as_deref).collect();
+                        inference.add_row(row_number, &cells)?;
+                    }
+                    inference.into_column_types()
+                },
+                column_types => column_types,
+            };
+            let (mut rows, columns) = PgCopyReader::new(body.stream().await?, &table_name).await?;
+            let converter = ColumnConverter::new(&column_types, columns)?;
+            if let Some(generated_schema) = converter.generated_schema()? {
+                yield ImportUnit::GeneratedSchema(
+                    component_path.clone(),
+                    table_name.clone(),
+                    generated_schema,
+                );
+            }
+            yield ImportUnit::NewTable(component_path, table_name);
+            let mut row_number = 0;
+            while let Some(row) = rows.next_row().await? {
+                row_number += 1;
+                let cells: Vec<_> = row.iter().map(Option::as_deref).collect();
+                yield ImportUnit::Object(converter.convert_row(row_number, &cells)?);
+            }
+        },
         ImportFormat::Zip => {
             let base_component_path = component_path;
-            let reader = stream_body().await?;
-            let temp_file = copy_to_temp_file(reader).await?;
-            let mut zip_reader = ZipReader::new(std::io::BufReader::new(temp_file))
+            let reader = body.seekable_reader().await?;
+            let mut zip_reader = ZipReader::new(std::io::BufReader::new(reader))
                 .await
                 .map_err(map_zip_error)?;
             let filenames: Vec<_> = zip_reader.file_names().await?;
@@ -344,43 +450,6 @@ pub async fn parse_objects<'a, Fut>(
     }
 }
 
-// Copy an object to disk so that we can more efficiently seek through the file.
-// TODO: write something that can efficiently seek through storage objects
-async fn copy_to_temp_file(reader: StorageGetStream) -> anyhow::Result<std::fs::File> {
-    let size = reader.content_length;
-    let file = common::runtime::block_in_place(|| {
-        let file = tempfile::tempfile().context("Failed to create temp file")?;
-        #[cfg(target_os = "linux")]
-        if size > 0 {
-            unsafe {
-                use std::os::fd::AsRawFd;
-                if libc::fallocate64(
-                    file.as_raw_fd(),
-                    0, /* mode */
-                    0, /* offset */
-                    size,
-                ) < 0
-                {
-                    return Err(anyhow::Error::from(std::io::Error::last_os_error())
-                        .context(format!("Failed to fallocate {size} bytes")));
-                }
-            }
-        }
-        #[cfg(not(target_os = "linux"))]
-        {
-            _ = size;
-        }
-        anyhow::Ok(file)
-    })?;
-    let mut tokio_file = tokio::fs::File::from_std(file);
-    tokio::io::copy_buf(&mut reader.into_tokio_reader(), &mut tokio_file)
-        .await
-        .context("Failed to copy snapshot to temp file")?;
-    // N.B.: it's ok that this file is seeked to the end because the ZipReader is
-    // immediately going to seek it anyway.
-    Ok(tokio_file.into_std().await)
-}
-
 pub fn parse_component_path(
     mut filename: &str,
     base_component_path: &ComponentPath,
@@ -0,0 +1,260 @@
+//! Parsing a table's rows from a `pg_dump` plain-text dump. The dump has a
+//! `COPY ... FROM stdin` statement for each table, followed by its rows in
+//! Postgres's text format and a `\.` line.
+use anyhow::Context;
+use common::types::FieldName;
+use tokio::io::{
+    AsyncBufRead,
+    AsyncBufReadExt,
+};
+use tokio_util::io::StreamReader;
+use value::TableName;
+
+use crate::snapshot_import::{
+    import_body::ImportStream,
+    import_error::ImportError,
+};
+
+const END_OF_COPY: &str = "\\.";
+
+pub struct PgCopyReader {
+    reader: Box<dyn AsyncBufRead + Send + Unpin>,
+    num_columns: usize,
+    row_number: usize,
+}
+
+impl PgCopyReader {
+    /// Skips to the `COPY` block for `table_name`, ignoring its Postgres
+    /// schema, and returns the block's columns.
+    pub async fn new(
+        stream: ImportStream,
+        table_name: &TableName,
+    ) -> anyhow::Result<(Self, Vec<FieldName>)> {
+        let mut reader = StreamReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader
+                .read_line(&mut line)
+                .await
+                .map_err(ImportError::NotUtf8)?
+                == 0
+            {
+                anyhow::bail!(ImportError::PgDumpTableNotFound(table_name.clone()));
+            }
+            let Some((table, columns)) = parse_copy_statement(line.trim_end()) else {
+                continue;
+            };
+            if table != table_name[..] {
+                continue;
+            }
+            let columns: Vec<FieldName> = columns
+                .into_iter()
+                .map(|column| {
+                    column
+                        .parse()
+                        .map_err(|e| ImportError::PgDumpInvalidColumn(column, e))
+                })
+                .try_collect()?;
+            let copy_reader = Self {
+                reader: Box::new(reader),
+                num_columns: columns.len(),
+                row_number: 0,
+            };
+            return Ok((copy_reader, columns));
+        }
+    }
+
+    /// Reads the block's next row, with `None` for NULL cells.
+    pub async fn next_row(&mut self) -> anyhow::Result<Option<Vec<Option<String>>>> {
+        let mut line = String::new();
+        if self
+            .reader
+            .read_line(&mut line)
+            .await
+            .map_err(ImportError::NotUtf8)?
+            == 0
+        {
+            anyhow::bail!(ImportError::PgDumpInvalidRow(
+                self.row_number + 1,
+                anyhow::anyhow!("the dump ends in the middle of the COPY block")
+            ));
+        }
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        if line == END_OF_COPY {
+            return Ok(None);
+        }
+        self.row_number += 1;
+        let row =
+            parse_copy_row(line).map_err(|e| ImportError::PgDumpInvalidRow(self.row_number, e))?;
+        if row.len() != self.num_columns {
+            anyhow::bail!(ImportError::PgDumpInvalidRow(
+                self.row_number,
+                anyhow::anyhow!(
+                    "it has {} cells but the table has {} columns",
+                    row.len(),
+                    self.num_columns
+                )
+            ));
+        }
+        Ok(Some(row))
+    }
+}
+
+/// Parses a statement like `COPY public.users (id, name) FROM stdin;` into
+/// the table's name without its schema, and its columns.
+fn parse_copy_statement(line: &str) -> Option<(String, Vec<String>)> {
+    let mut rest = line.strip_prefix("COPY ")?.strip_suffix(" FROM stdin;")?;
+    let mut table = parse_identifier(&mut rest)?;
+    while let Some(after_dot) = rest.strip_prefix('.') {
+        rest = after_dot;
+        table = parse_identifier(&mut rest)?;
+    }
+    rest = rest.strip_prefix(" (")?;
+    let mut columns = vec![parse_identifier(&mut rest)?];
+    while let Some(after_comma) = rest.strip_prefix(", ") {
+        rest = after_comma;
+        columns.push(parse_identifier(&mut rest)?);
+    }
+    if rest != ")" {
+        return None;
+    }
+    Some((table, columns))
+}
+
+/// Parses an identifier from the start of `s`, which is quoted if it isn't
+/// a lowercase identifier or is a keyword.
+fn parse_identifier(s: &mut &str) -> Option<String> {
+    if let Some(quoted) = s.strip_prefix('"') {
+        let mut identifier = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c != '"' {
+                identifier.push(c);
+            } else if quoted[i + 1..].starts_with('"') {
+                identifier.push('"');
+                chars.next();
+            } else {
+                *s = &quoted[i + 1..];
+                return Some(identifier);
+            }
+        }
+        None
+    } else {
+        let end = s.find(['.', ' ', ',', ')']).unwrap_or(s.len());
+        if end == 0 {
+            return None;
+        }
+        let (identifier, rest) = s.split_at(end);
+        *s = rest;
+        Some(identifier.to_string())
+    }
+}
+
+/// Parses a row in Postgres's text format, where cells are separated by
+/// tabs, `\N` is NULL, and special characters are escaped with backslashes.
+fn parse_copy_row(line: &str) -> anyhow::Result<Vec<Option<String>>> {
+    line.split('\t')
+        .map(|cell| {
+            if cell == "\\N" {
+                return Ok(None);
+            }
+            Ok(Some(unescape_copy_cell(cell)?))
+        })
+        .collect()
+}
+
+fn unescape_copy_cell(cell: &str) -> anyhow::Result<String> {
+    if !cell.contains('\\') {
+        return Ok(cell.to_string());
+    }
+    let input = cell.as_bytes();
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != b'\\' {
+            bytes.push(input[i]);
+            i += 1;
+            continue;
+        }
+        let escaped = *input
+            .get(i + 1)
+            .with_context(|| format!("cell {cell:?} ends with a backslash"))?;
+        i += 2;
+        let unescaped = match escaped {
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            // Up to three octal digits.
+            b'0'..=b'7' => {
+                let start = i - 1;
+                while i < input.len() && i - start < 3 && matches!(input[i], b'0'..=b'7') {
+                    i += 1;
+                }
+                u8::from_str_radix(&cell[start..i], 8)?
+            },
+            // Up to two hex digits.
+            b'x' if input.get(i).is_some_and(u8::is_ascii_hexdigit) => {
+                let start = i;
+                while i < input.len() && i - start < 2 && input[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+                u8::from_str_radix(&cell[start..i], 16)?
+            },
+            other => other,
+        };
+        bytes.push(unescaped);
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_copy_row,
+        parse_copy_statement,
+    };
+
+    #[test]
+    fn test_parse_copy_statement() {
+        assert_eq!(
+            parse_copy_statement("COPY public.users (id, name, \"createdAt\") FROM stdin;"),
+            Some((
+                "users".to_string(),
+                vec![
+                    "id".to_string(),
+                    "name".to_string(),
+                    "createdAt".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            parse_copy_statement("COPY \"My Schema\".\"Quo\"\"ted\" (a) FROM stdin;"),
+            Some(("Quo\"ted".to_string(), vec!["a".to_string()]))
+        );
+        assert_eq!(parse_copy_statement("COPY users TO stdout;"), None);
+        assert_eq!(
+            parse_copy_statement("SELECT pg_catalog.set_config('search_path', '', false);"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_copy_row() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_copy_row("1\t\\N\ta\\tb\\nc\\\\\t\\101\\x42\t")?,
+            vec![
+                Some("1".to_string()),
+                None,
+                Some("a\tb\nc\\".to_string()),
+                Some("AB".to_string()),
+                Some("".to_string()),
+            ]
+        );
+        assert!(parse_copy_row("trailing\\").is_err());
+        Ok(())
+    }
+}
@@ -65,9 +65,16 @@ use keybroker::{
     Identity,
 };
 use maplit::btreemap;
-use model::snapshot_imports::types::{
-    ImportRequestor,
-    ImportState,
+use model::snapshot_imports::{
+    types::{
+        ImportColumnType,
+        ImportColumnTypes,
+        ImportRequestor,
+        ImportState,
+        ImportValueType,
+        MongoIdMapping,
+    },
+    SNAPSHOT_IMPORTS_TABLE,
 };
 use must_let::must_let;
 use runtime::testing::TestRuntime;
@@ -98,7 +105,14 @@ use crate::{
     snapshot_import::{
         do_import,
         do_import_from_object_key,
+        do_streamed_import,
+        import_body::ImportBody,
         import_objects,
+        mongo::testing::{
+            bson_document,
+            bson_string,
+            mongo_archive,
+        },
         parse::{
             parse_objects,
             ImportUnit,
@@ -136,7 +150,7 @@ async fn test_peeking_take_while(_rt: TestRuntime) {
 async fn run_parse_objects<RT: Runtime>(
     rt: RT,
     format: ImportFormat,
-    v: &str,
+    v: impl AsRef<[u8]>,
 ) -> anyhow::Result<Vec<JsonValue>> {
     let storage_dir = tempfile::TempDir::new()?;
     let storage: Arc<dyn Storage> = Arc::new(LocalDirStorage::for_use_case(
@@ -145,10 +159,10 @@ async fn run_parse_objects<RT: Runtime>(
         StorageUseCase::SnapshotImports,
     )?);
     let mut upload = storage.start_upload().await?;
-    upload.write(Bytes::copy_from_slice(v.as_bytes())).await?;
+    upload.write(Bytes::copy_from_slice(v.as_ref())).await?;
     let object_key = upload.complete().await?;
-    let stream = || async { storage.get(&object_key).await?.context("missing object") };
-    parse_objects(format, ComponentPath::root(), stream)
+    let body = ImportBody::new(storage.clone(), storage.fully_qualified_key(&object_key));
+    parse_objects(format, ComponentPath::root(), body)
         .filter_map(|line| async move {
             match line {
                 Ok(super::ImportUnit::Object(object)) => Some(Ok(object)),
@@ -166,6 +180,74 @@ fn stream_from_str(str: &str) -> BoxStream<'static, anyhow::Result<Bytes>> {
     stream::iter(vec![anyhow::Ok(str.to_string().into_bytes().into())]).boxed()
 }
 
+#[convex_macro::test_runtime]
+async fn test_streamed_import(rt: TestRuntime) -> anyhow::Result<()> {
+    let app = Application::new_for_tests(&rt).await?;
+    let table_name = "table1";
+    // Rows are split across chunks of the body.
+    let chunks = ["{\"a\": 1}\n{\"a\"", ": 2}\n", "{\"a\": 3}\n"];
+    let body = stream::iter(chunks.map(|chunk| anyhow::Ok(Bytes::from(chunk)))).boxed();
+    let num_written = do_streamed_import(
+        &app,
+        new_admin_id(),
+        ImportFormat::JsonLines(table_name.parse()?),
+        ImportMode::Replace,
+        ComponentPath::root(),
+        body,
+    )
+    .await?;
+    assert_eq!(num_written, 3);
+    let objects = load_fields_as_maps(&app, table_name, vec!["a"]).await?;
+    assert_eq!(
+        objects,
+        vec![
+            btreemap!("a" => assert_val!(1.)),
+            btreemap!("a" => assert_val!(2.)),
+            btreemap!("a" => assert_val!(3.)),
+        ]
+    );
+    // The body wasn't stored or tracked as a snapshot import.
+    let mut tx = app.begin(new_admin_id()).await?;
+    assert_eq!(
+        tx.must_count(TableNamespace::Global, &SNAPSHOT_IMPORTS_TABLE)
+            .await?,
+        0
+    );
+
+    // Errors fail the import like they do for stored imports.
+    let err = do_streamed_import(
+        &app,
+        new_admin_id(),
+        ImportFormat::JsonLines(table_name.parse()?),
+        ImportMode::Replace,
+        ComponentPath::root(),
+        stream_from_str("{\"a\": 4}\nnot json\n"),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.is_bad_request());
+    assert_eq!(err.short_msg(), "ImportFailed");
+    assert_eq!(
+        load_fields_as_maps(&app, table_name, vec!["a"])
+            .await?
+            .len(),
+        3
+    );
+
+    // Formats that read the file more than once can't be streamed.
+    do_streamed_import(
+        &app,
+        new_admin_id(),
+        ImportFormat::Csv(table_name.parse()?, ImportColumnTypes::Infer),
+        ImportMode::Replace,
+        ComponentPath::root(),
+        stream_from_str("a\n1\n"),
+    )
+    .await
+    .unwrap_err();
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_csv(rt: TestRuntime) -> anyhow::Result<()> {
     let test1 = r#"
@@ -173,7 +255,12 @@ a,b,c
 1,a string i guess,1.2
 5.10,-100,"a string in quotes"
 "#;
-    let objects = run_parse_objects(rt, ImportFormat::Csv("table".parse().unwrap()), test1).await?;
+    let objects = run_parse_objects(
+        rt,
+        ImportFormat::Csv("table".parse().unwrap(), ImportColumnTypes::Guess),
+        test1,
+    )
+    .await?;
     let expected = vec![
         json!({
             "a": 1.,
@@ -218,7 +305,12 @@ async fn test_csv_empty_strings(rt: TestRuntime) -> anyhow::Result<()> {
 a,b,c,d
 "",,"""",""""""
 "#;
-    let objects = run_parse_objects(rt, ImportFormat::Csv("table".parse().unwrap()), test1).await?;
+    let objects = run_parse_objects(
+        rt,
+        ImportFormat::Csv("table".parse().unwrap(), ImportColumnTypes::Guess),
+        test1,
+    )
+    .await?;
     let expected = vec![json!({
         "a": "",
         "b": "",
@@ -229,6 +321,141 @@ a,b,c,d
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_csv_infer_column_types(rt: TestRuntime) -> anyhow::Result<()> {
+    let test1 = r#"
+a,b,c
+1,true,x
+2.5,,3
+"#;
+    let objects = run_parse_objects(
+        rt,
+        ImportFormat::Csv("table".parse()?, ImportColumnTypes::Infer),
+        test1,
+    )
+    .await?;
+    // Unlike guessing each cell's type, "3" stays a string like the rest of
+    // its column.
+    let expected = vec![
+        json!({
+            "a": 1.,
+            "b": true,
+            "c": "x",
+        }),
+        json!({
+            "a": 2.5,
+            "b": null,
+            "c": "3",
+        }),
+    ];
+    assert_eq!(objects, expected);
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn import_pg_dump_with_column_types(rt: TestRuntime) -> anyhow::Result<()> {
+    let app = Application::new_for_tests(&rt).await?;
+    let table_name = "users";
+    let dump = "--\n-- PostgreSQL database dump\n--\n\nCOPY public.posts (id, title) FROM \
+                stdin;\n1\tHello\n\\.\n\nCOPY public.users (id, name, avatar, admin) FROM \
+                stdin;\n9007199254740993\tAda\t\\\\x0102\tt\n2\t\\N\t\\N\tf\n\\.\n";
+    let column_type = |value_type, nullable| ImportColumnType {
+        value_type,
+        nullable,
+    };
+    let column_types = ImportColumnTypes::Explicit(btreemap! {
+        "id".parse()? => column_type(ImportValueType::Int64, false),
+        "name".parse()? => column_type(ImportValueType::String, true),
+        "avatar".parse()? => column_type(ImportValueType::Bytes, true),
+        "admin".parse()? => column_type(ImportValueType::Boolean, false),
+    });
+    do_import(
+        &app,
+        new_admin_id(),
+        ImportFormat::PgDump(table_name.parse()?, column_types),
+        ImportMode::Replace,
+        ComponentPath::root(),
+        stream_from_str(dump),
+    )
+    .await?;
+
+    let objects =
+        load_fields_as_maps(&app, table_name, vec!["id", "name", "avatar", "admin"]).await?;
+    let expected = vec![
+        btreemap!(
+            "id" => ConvexValue::from(9007199254740993i64),
+            "name" => assert_val!("Ada"),
+            "avatar" => ConvexValue::try_from(vec![1u8, 2])?,
+            "admin" => assert_val!(true),
+        ),
+        btreemap!(
+            "id" => ConvexValue::from(2i64),
+            "name" => assert_val!(null),
+            "avatar" => assert_val!(null),
+            "admin" => assert_val!(false),
+        ),
+    ];
+    assert_eq!(objects, expected);
+
+    // Errors say which row and column are invalid.
+    let column_types = ImportColumnTypes::Explicit(btreemap! {
+        "id".parse()? => column_type(ImportValueType::Boolean, false),
+        "title".parse()? => column_type(ImportValueType::String, false),
+    });
+    let err = do_import(
+        &app,
+        new_admin_id(),
+        ImportFormat::PgDump("posts".parse()?, column_types),
+        ImportMode::Replace,
+        ComponentPath::root(),
+        stream_from_str(dump),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.is_bad_request());
+    assert!(
+        err.to_string()
+            .contains("Row 1 has an invalid boolean in column \"id\""),
+        "{err}"
+    );
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_mongo_archive(rt: TestRuntime) -> anyhow::Result<()> {
+    let document = |id: u8, name: &str| {
+        bson_document(&[
+            (0x07, "_id", vec![id; 12]),
+            (0x02, "name", bson_string(name)),
+        ])
+    };
+    let archive = mongo_archive(
+        "app",
+        &["users", "posts"],
+        &[
+            ("users", vec![document(1, "a")]),
+            ("posts", vec![document(2, "b")]),
+            ("users", vec![document(3, "c")]),
+            ("users", vec![]),
+            ("posts", vec![]),
+        ],
+    );
+    let objects = run_parse_objects(
+        rt,
+        ImportFormat::MongoArchive(MongoIdMapping::Field("mongoId".parse()?)),
+        archive,
+    )
+    .await?;
+    // Each collection is imported in turn.
+    let expected = vec![
+        json!({ "mongoId": "020202020202020202020202", "name": "b" }),
+        json!({ "mongoId": "010101010101010101010101", "name": "a" }),
+        json!({ "mongoId": "030303030303030303030303", "name": "c" }),
+    ];
+    assert_eq!(objects, expected);
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 #[ignore]
 async fn import_huge_csv(rt: TestRuntime) -> anyhow::Result<()> {
@@ -434,7 +661,7 @@ a
     let import_id = start_stored_import(
         &app,
         new_admin_id(),
-        ImportFormat::Csv(table_name.parse()?),
+        ImportFormat::Csv(table_name.parse()?, ImportColumnTypes::Guess),
         ImportMode::Replace,
         ComponentPath::root(),
         object_key,
@@ -671,7 +898,7 @@ _id,a
     do_import(
         &app,
         new_admin_id(),
-        ImportFormat::Csv(table_name2.clone()),
+        ImportFormat::Csv(table_name2.clone(), ImportColumnTypes::Guess),
         ImportMode::ReplaceAll,
         ComponentPath::root(),
         stream_from_str(&test_csv),
@@ -747,7 +974,7 @@ _id,a
             let result = do_import(
                 &app,
                 new_admin_id(),
-                ImportFormat::Csv(table_name2.clone()),
+                ImportFormat::Csv(table_name2.clone(), ImportColumnTypes::Guess),
                 mode,
                 ComponentPath::root(),
                 stream_from_str(&test_csv),
@@ -990,6 +1217,51 @@ async fn import_zip_to_empty(rt: TestRuntime) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn import_zip_from_request_body(rt: TestRuntime) -> anyhow::Result<()> {
+    let app = Application::new_for_tests(&rt).await?;
+    let table_name: TableName = "table1".parse()?;
+    let identity = new_admin_id();
+    let mut tx = app.begin(identity.clone()).await?;
+    let mut ufm = UserFacingModel::new_root_for_test(&mut tx);
+    for i in 0..10i64 {
+        ufm.insert(table_name.clone(), assert_obj!("i" => i))
+            .await?;
+    }
+    app.commit_test(tx).await?;
+    let export_object_key = app.export_and_wait().await?;
+    let archive = app
+        .exports_storage()
+        .get_fq_object(&export_object_key)
+        .await?
+        .context("Missing export")?
+        .collect_as_bytes()
+        .await?;
+
+    // The archive is uploaded to storage as it arrives, in small chunks, and
+    // imported from there.
+    let app = Application::new_for_tests(&rt).await?;
+    let chunks: Vec<_> = archive
+        .chunks(64)
+        .map(|chunk| anyhow::Ok(Bytes::copy_from_slice(chunk)))
+        .collect();
+    let num_written = do_import(
+        &app,
+        identity.clone(),
+        ImportFormat::Zip,
+        ImportMode::RequireEmpty,
+        ComponentPath::root(),
+        stream::iter(chunks).boxed(),
+    )
+    .await?;
+    assert_eq!(num_written, 10);
+    assert_eq!(
+        load_fields_as_maps(&app, "table1", vec!["i"]).await?.len(),
+        10
+    );
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn import_zip_to_same_deployment(rt: TestRuntime) -> anyhow::Result<()> {
     for (mode, expect_success) in [
@@ -1107,6 +1379,7 @@ async fn test_import_counts_bandwidth(rt: TestRuntime) -> anyhow::Result<()> {
     import_objects(
         &app.database,
         &app.file_storage,
+        app.fetch_client.as_ref(),
         identity,
         ImportMode::Replace,
         objects,
@@ -1146,6 +1419,7 @@ async fn test_import_file_storage_changing_table_number(rt: TestRuntime) -> anyh
     import_objects(
         &app.database,
         &app.file_storage,
+        app.fetch_client.as_ref(),
         new_admin_id(),
         ImportMode::Replace,
         objects,
@@ -1158,6 +1432,40 @@ async fn test_import_file_storage_changing_table_number(rt: TestRuntime) -> anyh
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_import_file_storage_url_must_be_http(rt: TestRuntime) -> anyhow::Result<()> {
+    let app = Application::new_for_tests(&rt).await?;
+    let storage_id = "kg21pzwemsm55e1fnt2kcsvgjh6h6gtf";
+    let objects = stream::iter(vec![
+        Ok(ImportUnit::NewTable(
+            ComponentPath::root(),
+            "_storage".parse()?,
+        )),
+        Ok(ImportUnit::Object(
+            json!({"_id": storage_id, "url": "file:///etc/passwd"}),
+        )),
+    ])
+    .boxed()
+    .peekable();
+
+    // Files on the backend's machine can't be imported.
+    let err = import_objects(
+        &app.database,
+        &app.file_storage,
+        app.fetch_client.as_ref(),
+        new_admin_id(),
+        ImportMode::Replace,
+        objects,
+        FunctionUsageTracker::new(),
+        None,
+        ImportRequestor::SnapshotImport,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("must be https"), "{err}");
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_import_into_component(rt: TestRuntime) -> anyhow::Result<()> {
     let app = Application::new_for_tests(&rt).await?;
@@ -1171,7 +1479,7 @@ a,b
     do_import(
         &app,
         new_admin_id(),
-        ImportFormat::Csv(table_name.clone()),
+        ImportFormat::Csv(table_name.clone(), ImportColumnTypes::Guess),
         ImportMode::Replace,
         component_path.clone(),
         stream_from_str(test_csv),
@@ -1198,7 +1506,7 @@ a,b
     let num_rows_written = do_import(
         &app,
         new_admin_id(),
-        ImportFormat::Csv(table_name.clone()),
+        ImportFormat::Csv(table_name.clone(), ImportColumnTypes::Guess),
         ImportMode::Replace,
         component_path.clone(),
         stream_from_str(test_csv),
@@ -1285,7 +1593,7 @@ async fn run_csv_import(
     do_import(
         app,
         new_admin_id(),
-        ImportFormat::Csv(table_name.parse()?),
+        ImportFormat::Csv(table_name.parse()?, ImportColumnTypes::Guess),
         ImportMode::Replace,
         ComponentPath::root(),
         stream_from_str(input),
@@ -7,6 +7,7 @@ use std::{
 use common::{
     backoff::Backoff,
     errors::report_error,
+    http::fetch::FetchClient,
     runtime::Runtime,
 };
 use database::Database;
@@ -38,6 +39,7 @@ impl SnapshotImportWorker {
         database: Database<RT>,
         snapshot_imports_storage: Arc<dyn Storage>,
         file_storage: FileStorage<RT>,
+        fetch_client: Arc<dyn FetchClient>,
         usage_tracking: UsageCounter,
     ) -> impl Future<Output = ()> + Send {
         let mut worker = SnapshotImportExecutor {
@@ -45,6 +47,7 @@ impl SnapshotImportWorker {
             database,
             snapshot_imports_storage,
             file_storage,
+            fetch_client,
             usage_tracking,
             backoff: Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF),
         };
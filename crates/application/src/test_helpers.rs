@@ -20,6 +20,7 @@ use cmd_util::env::config_test;
 use common::{
     bootstrap_model::index::database_index::IndexedFields,
     components::ComponentId,
+    concurrency_limits::FunctionConcurrencyLimits,
     db_schema,
     http::fetch::StaticFetchClient,
     knobs::{
@@ -50,6 +51,7 @@ use file_storage::{
     TransactionalFileStorage,
 };
 use function_runner::{
+    concurrency_limiter::FunctionConcurrencyLimiter,
     in_process_function_runner::InProcessFunctionRunner,
     server::InstanceStorage,
 };
@@ -75,6 +77,7 @@ use model::{
     exports::{
         types::{
             Export,
+            ExportFilter,
             ExportFormat,
             ExportRequestor,
         },
@@ -120,6 +123,9 @@ pub static OBJECTS_TABLE_COMPONENT: ComponentId = ComponentId::test_user();
 pub struct ApplicationFixtureArgs {
     pub tp: Option<TestPersistence>,
     pub event_logger: Option<Arc<dyn UsageEventLogger>>,
+    /// Per-function concurrency limits for isolate functions. Executions over
+    /// the limit are rejected without queuing.
+    pub function_concurrency_limits: Option<FunctionConcurrencyLimits>,
 }
 
 impl ApplicationFixtureArgs {
@@ -220,22 +226,26 @@ impl<RT: Runtime> ApplicationTestExt<RT> for Application<RT> {
         )?);
 
         let fetch_client = Arc::new(StaticFetchClient::new());
-        let function_runner = Arc::new(
-            InProcessFunctionRunner::new(
-                DEV_INSTANCE_NAME.into(),
-                DEV_SECRET.try_into()?,
-                convex_origin.clone(),
-                rt.clone(),
-                persistence.reader(),
-                InstanceStorage {
-                    files_storage: files_storage.clone(),
-                    modules_storage: modules_storage.clone(),
-                },
-                database.clone(),
-                fetch_client,
-            )
-            .await?,
-        );
+        let mut function_runner = InProcessFunctionRunner::new(
+            DEV_INSTANCE_NAME.into(),
+            DEV_SECRET.try_into()?,
+            convex_origin.clone(),
+            rt.clone(),
+            persistence.reader(),
+            InstanceStorage {
+                files_storage: files_storage.clone(),
+                modules_storage: modules_storage.clone(),
+            },
+            database.clone(),
+            fetch_client.clone(),
+        )
+        .await?;
+        if let Some(limits) = args.function_concurrency_limits {
+            function_runner = function_runner.with_concurrency_limiter(
+                FunctionConcurrencyLimiter::new(rt.clone(), limits, Duration::ZERO),
+            );
+        }
+        let function_runner = Arc::new(function_runner);
 
         let file_storage = FileStorage {
             transactional_file_storage: TransactionalFileStorage::new(
@@ -268,6 +278,7 @@ impl<RT: Runtime> ApplicationTestExt<RT> for Application<RT> {
             kb.clone(),
             DEV_INSTANCE_NAME.into(),
             function_runner,
+            fetch_client,
             convex_origin,
             convex_site,
             searcher,
@@ -380,10 +391,15 @@ impl<RT: Runtime> ApplicationTestExt<RT> for Application<RT> {
                 Identity::system(),
                 ExportFormat::Zip {
                     include_storage: true,
+                    storage_urls: false,
                 },
                 ComponentId::Root,
                 ExportRequestor::CloudBackup,
                 None,
+                None,
+                None,
+                ExportFilter::default(),
+                None,
             )
             .await?;
         let export_object_key = loop {
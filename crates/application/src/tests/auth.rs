@@ -1,10 +1,40 @@
-use common::runtime::Runtime;
+use std::{
+    collections::BTreeMap,
+    time::Duration,
+};
+
+use authentication::saml::testing::{
+    key_and_certificate,
+    signed_response,
+    ACS_URL,
+    IDP_ENTITY_ID,
+    SP_ENTITY_ID,
+};
+use common::{
+    auth::{
+        CustomJwtKey,
+        CustomJwtProvider,
+        SamlProvider,
+    },
+    runtime::Runtime,
+    testing::TestPersistence,
+    types::MemberId,
+};
 use errors::ErrorMetadataAnyhowExt;
+use keybroker::{
+    AdminIdentity,
+    Identity,
+};
+use model::token_revocations::types::RevokedTokens;
 use runtime::testing::TestRuntime;
 use sync_types::AuthenticationToken;
 
 use crate::{
-    test_helpers::ApplicationTestExt,
+    api::ApplicationApi,
+    test_helpers::{
+        ApplicationFixtureArgs,
+        ApplicationTestExt,
+    },
     Application,
 };
 
@@ -24,3 +54,112 @@ async fn test_auth_with_invalid_admin_key(rt: TestRuntime) -> anyhow::Result<()>
 
     Ok(())
 }
+
+const ISSUER: &str = "https://auth.example.com";
+
+/// The `jti` claim of a JWT.
+fn token_id(token: &str) -> anyhow::Result<String> {
+    let payload = token.split('.').nth(1).unwrap_or_default();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?)?;
+    Ok(claims["jti"].as_str().unwrap().to_string())
+}
+
+#[convex_macro::test_runtime]
+async fn test_revoked_tokens_are_rejected(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    let admin = Identity::InstanceAdmin(AdminIdentity::new_for_test_only(
+        application.instance_name(),
+        MemberId(1),
+    ));
+    application
+        .create_custom_jwt_provider(
+            admin.clone(),
+            CustomJwtProvider {
+                issuer: ISSUER.to_string(),
+                audience: None,
+                algorithm: "HS256".to_string(),
+                key: CustomJwtKey::Secret("secret".to_string()),
+                required_claims: BTreeMap::new(),
+                claim_mapping: BTreeMap::new(),
+            },
+        )
+        .await?;
+    let token_identifier = format!("{ISSUER}|user1");
+    let (token, _) = application
+        .exchange_token(admin.clone(), token_identifier.clone())
+        .await?;
+    let authenticate =
+        || application.authenticate(AuthenticationToken::User(token.clone()), rt.system_time());
+    let identity = authenticate().await?;
+    assert!(matches!(identity, Identity::User(_)));
+
+    for revoked in [
+        RevokedTokens::User(token_identifier.clone()),
+        RevokedTokens::TokenId(token_id(&token)?),
+    ] {
+        let id = application.revoke_tokens(admin.clone(), revoked).await?;
+        let error = authenticate().await.unwrap_err();
+        assert_eq!(error.short_msg(), "TokenRevoked");
+        // Sessions that authenticated before the revocation are cut off too.
+        let error = application.check_token_revocation(&identity).unwrap_err();
+        assert_eq!(error.short_msg(), "TokenRevoked");
+
+        application
+            .delete_token_revocation(admin.clone(), &id)
+            .await?;
+        authenticate().await?;
+        application.check_token_revocation(&identity)?;
+    }
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_saml_assertion_replay_after_restart(rt: TestRuntime) -> anyhow::Result<()> {
+    let tp = TestPersistence::new();
+    let args = || ApplicationFixtureArgs {
+        tp: Some(tp.clone()),
+        ..Default::default()
+    };
+    let application = Application::new_for_tests_with_args(&rt, args()).await?;
+    let admin = Identity::InstanceAdmin(AdminIdentity::new_for_test_only(
+        application.instance_name(),
+        MemberId(1),
+    ));
+    let (key, certificate) = key_and_certificate()?;
+    application
+        .create_saml_provider(
+            admin,
+            SamlProvider {
+                entity_id: IDP_ENTITY_ID.to_string(),
+                certificate,
+                attribute_mapping: BTreeMap::new(),
+            },
+        )
+        .await?;
+    let response = signed_response(
+        &key,
+        rt.system_time(),
+        Some(SP_ENTITY_ID),
+        Duration::from_secs(300),
+        |r| r,
+    )?;
+    application
+        .complete_saml_sign_in(&response, SP_ENTITY_ID, ACS_URL)
+        .await?;
+    let error = application
+        .complete_saml_sign_in(&response, SP_ENTITY_ID, ACS_URL)
+        .await
+        .unwrap_err();
+    assert_eq!(error.short_msg(), "SamlAssertionReplayed");
+    application.shutdown().await?;
+
+    // The used assertion is remembered by the restarted backend.
+    let application = Application::new_for_tests_with_args(&rt, args()).await?;
+    let error = application
+        .complete_saml_sign_in(&response, SP_ENTITY_ID, ACS_URL)
+        .await
+        .unwrap_err();
+    assert_eq!(error.short_msg(), "SamlAssertionReplayed");
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use common::{
     bootstrap_model::components::ComponentState,
     components::{
@@ -22,6 +24,13 @@ use errors::ErrorMetadataAnyhowExt;
 use futures::FutureExt;
 use itertools::Itertools;
 use keybroker::Identity;
+use model::environment_variables::{
+    types::{
+        EnvVarScope,
+        EnvironmentVariableOverride,
+    },
+    EnvironmentVariablesModel,
+};
 use must_let::must_let;
 use runtime::testing::TestRuntime;
 use serde_json::{
@@ -31,6 +40,7 @@ use serde_json::{
 use sync_types::CanonicalizedUdfPath;
 use value::{
     assert_obj,
+    val,
     ConvexObject,
     ConvexValue,
     TableName,
@@ -136,6 +146,38 @@ async fn test_env_vars_not_accessible_in_components(rt: TestRuntime) -> anyhow::
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_env_var_overrides_in_component_calls(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_component_tests_modules("basic").await?;
+    let mut tx = application.begin(Identity::system()).await?;
+    EnvironmentVariablesModel::new(&mut tx)
+        .set_override(
+            EnvironmentVariableOverride {
+                scope: EnvVarScope {
+                    component: "envVars".parse()?,
+                    udf_path: None,
+                },
+                env_var: EnvironmentVariable {
+                    name: "NAME".parse()?,
+                    value: "emma".parse()?,
+                },
+            },
+            &HashSet::new(),
+        )
+        .await?;
+    application.commit_test(tx).await?;
+    // The query is called from a query in the root component, and the action
+    // from an action.
+    let result =
+        run_function(&application, "componentEntry:envVarQuery".parse()?, vec![]).await??;
+    assert_eq!(val!("emma"), result.value);
+    let result =
+        run_function(&application, "componentEntry:envVarAction".parse()?, vec![]).await??;
+    assert_eq!(val!("emma"), result.value);
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_system_env_vars_not_accessible_in_components(rt: TestRuntime) -> anyhow::Result<()> {
     let application = Application::new_for_tests(&rt).await?;
@@ -0,0 +1,78 @@
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+        PublicFunctionPath,
+    },
+    types::FunctionCaller,
+    RequestId,
+};
+use errors::ErrorMetadataAnyhowExt;
+use keybroker::{
+    testing::TestUserIdentity,
+    Identity,
+    UserIdentity,
+};
+use runtime::testing::TestRuntime;
+use serde_json::json;
+
+use crate::{
+    test_helpers::{
+        ApplicationFixtureArgs,
+        ApplicationTestExt,
+    },
+    Application,
+    RedactedActionError,
+    RedactedActionReturn,
+};
+
+async fn run_sleep(
+    application: &Application<TestRuntime>,
+) -> anyhow::Result<Result<RedactedActionReturn, RedactedActionError>> {
+    application
+        .action_udf(
+            RequestId::new(),
+            PublicFunctionPath::Component(CanonicalizedComponentFunctionPath {
+                component: ComponentPath::test_user(),
+                udf_path: "action:sleep".parse()?,
+            }),
+            vec![json!({ "ms": 1000 })],
+            Identity::user(UserIdentity::test()),
+            FunctionCaller::HttpEndpoint,
+        )
+        .await
+}
+
+#[convex_macro::test_runtime]
+async fn test_concurrent_actions_over_limit_are_rejected(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests_with_args(
+        &rt,
+        ApplicationFixtureArgs {
+            function_concurrency_limits: Some("action:sleep=2".parse()?),
+            ..Default::default()
+        },
+    )
+    .await?;
+    application.load_udf_tests_modules().await?;
+
+    let (first, second, third) = futures::join!(
+        run_sleep(&application),
+        run_sleep(&application),
+        run_sleep(&application),
+    );
+    let mut rejected = 0;
+    for result in [first, second, third] {
+        match result {
+            Ok(result) => assert!(result.is_ok()),
+            Err(e) => {
+                assert_eq!(e.short_msg(), "TooManyConcurrentFunctionExecutions");
+                rejected += 1;
+            },
+        }
+    }
+    assert_eq!(rejected, 1);
+
+    // Once the running executions finish, the function can run again.
+    assert!(run_sleep(&application).await?.is_ok());
+    Ok(())
+}
@@ -33,6 +33,7 @@ use model::{
     },
     cron_jobs::{
         types::{
+            CronDispatchOptions,
             CronIdentifier,
             CronJob,
             CronSchedule,
@@ -80,6 +81,7 @@ async fn create_cron_job(
         udf_path: path.udf_path.clone(),
         udf_args: parse_udf_args(&path.udf_path, vec![JsonValue::Object(map)])?,
         cron_schedule: CronSchedule::Interval { seconds: 60 },
+        dispatch: CronDispatchOptions::default(),
     };
     let original_jobs = cron_model.list().await?;
     let name = test_cron_identifier();
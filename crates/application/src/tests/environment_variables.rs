@@ -1,3 +1,12 @@
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+        PublicFunctionPath,
+    },
+    types::FunctionCaller,
+    RequestId,
+};
 use errors::ErrorMetadataAnyhowExt;
 use keybroker::Identity;
 use model::environment_variables::{
@@ -12,6 +21,7 @@ use runtime::{
     prod::ProdRuntime,
     testing::TestRuntime,
 };
+use value::ConvexValue;
 
 use crate::{
     test_helpers::ApplicationTestExt,
@@ -197,3 +207,40 @@ async fn test_env_variable_cannot_set_system_name(rt: ProdRuntime) -> anyhow::Re
 
     Ok(())
 }
+
+#[convex_macro::prod_rt_test]
+async fn test_node_action_reads_secret(rt: ProdRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules_with_node().await?;
+
+    let mut tx = application.begin(Identity::system()).await?;
+    application
+        .update_environment_variables(
+            &mut tx,
+            vec![EnvVarChange::SetSecret(EnvironmentVariable::new(
+                "TEST_NAME".parse()?,
+                "secret value".parse()?,
+            ))],
+        )
+        .await?;
+    application.commit_test(tx).await?;
+
+    let result = application
+        .action_udf(
+            RequestId::new(),
+            PublicFunctionPath::Component(CanonicalizedComponentFunctionPath {
+                component: ComponentPath::test_user(),
+                udf_path: "node_actions:getTestEnvVar".parse()?,
+            }),
+            vec![],
+            Identity::system(),
+            FunctionCaller::HttpEndpoint,
+        )
+        .await??;
+    assert_eq!(
+        result.value,
+        ConvexValue::try_from("secret value".to_string())?
+    );
+
+    Ok(())
+}
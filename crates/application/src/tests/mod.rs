@@ -2,6 +2,7 @@ mod analyze;
 mod auth;
 mod auth_config;
 pub mod components;
+mod concurrency_limits;
 mod cron_jobs;
 mod environment_variables;
 mod mutation;
@@ -12,6 +13,7 @@ mod scheduled_jobs;
 mod schema;
 mod source_package;
 mod storage;
+mod warmup;
 
 const NODE_SOURCE: &str = r#"
 var nodeFunction = () => {};
@@ -3,6 +3,7 @@ use std::{
     time::Duration,
 };
 
+use anyhow::Context;
 use common::{
     components::{
         CanonicalizedComponentFunctionPath,
@@ -21,10 +22,19 @@ use common::{
 };
 use database::{
     BootstrapComponentsModel,
+    IndexModel,
+    ResolvedQuery,
     TableModel,
     Transaction,
 };
-use errors::ErrorMetadata;
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use fastrace::collector::{
+    Config,
+    ConsoleReporter,
+};
 use keybroker::Identity;
 use model::{
     backend_state::{
@@ -32,8 +42,19 @@ use model::{
         BackendStateModel,
     },
     scheduled_jobs::{
-        types::ScheduledJobState,
+        dead_letters::ScheduledJobDeadLettersModel,
+        pauses::SchedulerPausesModel,
+        ready_jobs_query,
+        types::{
+            DuplicateJobBehavior,
+            ScheduleOptions,
+            ScheduledJobFilter,
+            ScheduledJobPriority,
+            ScheduledJobRetryPolicy,
+            ScheduledJobState,
+        },
         SchedulerModel,
+        SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY,
     },
 };
 use runtime::testing::TestRuntime;
@@ -69,6 +90,15 @@ async fn create_scheduled_job<'a>(
     rt: &'a TestRuntime,
     tx: &'a mut Transaction<TestRuntime>,
     path: CanonicalizedComponentFunctionPath,
+) -> anyhow::Result<(ResolvedDocumentId, SchedulerModel<'a, TestRuntime>)> {
+    create_scheduled_job_with_options(rt, tx, path, ScheduleOptions::default()).await
+}
+
+async fn create_scheduled_job_with_options<'a>(
+    rt: &'a TestRuntime,
+    tx: &'a mut Transaction<TestRuntime>,
+    path: CanonicalizedComponentFunctionPath,
+    options: ScheduleOptions,
 ) -> anyhow::Result<(ResolvedDocumentId, SchedulerModel<'a, TestRuntime>)> {
     let mut map = serde_json::Map::new();
     map.insert(
@@ -84,6 +114,7 @@ async fn create_scheduled_job<'a>(
             parse_udf_args(&path.udf_path, vec![JsonValue::Object(map)])?,
             rt.unix_timestamp(),
             ExecutionContext::new_for_test(),
+            options,
         )
         .await?;
     let state = model.check_status(job_id).await?.unwrap();
@@ -147,7 +178,11 @@ async fn test_scheduled_jobs_canceled(rt: TestRuntime) -> anyhow::Result<()> {
     assert!(job.next_ts.is_some());
 
     // Cancel the scheduled job
-    model.cancel_all(Some(path), 1).await?;
+    let filter = ScheduledJobFilter {
+        path: Some(path),
+        ..Default::default()
+    };
+    model.cancel_all(&filter, None, 1).await?;
     let state = model.check_status(job_id).await?.unwrap();
     assert_eq!(state, ScheduledJobState::Canceled);
     application.commit_test(tx).await?;
@@ -155,6 +190,193 @@ async fn test_scheduled_jobs_canceled(rt: TestRuntime) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_scheduled_jobs_cancel_all_filtered(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    // Pause the backend so the executor leaves the jobs pending.
+    let mut tx = application.begin(Identity::system()).await?;
+    BackendStateModel::new(&mut tx)
+        .toggle_backend_state(BackendState::Paused)
+        .await?;
+    let path = insert_object_path();
+    let namespace = TableNamespace::test_user();
+    let mut model = SchedulerModel::new(&mut tx, namespace);
+    let mut job_ids = vec![];
+    for key in ["a", "b", "a", "a"] {
+        let job_id = model
+            .schedule(
+                path.clone(),
+                parse_udf_args(&path.udf_path, vec![serde_json::json!({ "key": key })])?,
+                rt.unix_timestamp(),
+                ExecutionContext::new_for_test(),
+                ScheduleOptions::default(),
+            )
+            .await?;
+        job_ids.push(job_id);
+    }
+
+    // Cancel the jobs with `key: "a"`, scanning one job per batch.
+    let filter = ScheduledJobFilter::new(
+        Some(path),
+        Some(serde_json::json!({ "key": "a" })),
+        None,
+        None,
+    )?;
+    let mut cursor = None;
+    let mut canceled = 0;
+    loop {
+        let page = model.cancel_all(&filter, cursor, 1).await?;
+        canceled += page.canceled;
+        cursor = page.cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(canceled, 3);
+    for (job_id, expected) in job_ids.into_iter().zip([
+        ScheduledJobState::Canceled,
+        ScheduledJobState::Pending,
+        ScheduledJobState::Canceled,
+        ScheduledJobState::Canceled,
+    ]) {
+        assert_eq!(model.check_status(job_id).await?, Some(expected));
+    }
+
+    // Jobs due after the range aren't canceled.
+    let before = rt.unix_timestamp().as_ms_since_epoch()? as f64 - 60_000.0;
+    let filter = ScheduledJobFilter::new(None, None, None, Some(before))?;
+    let page = model.cancel_all(&filter, None, 10).await?;
+    assert_eq!(page.canceled, 0);
+    assert!(page.cursor.is_none());
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_count_outstanding_scheduled_jobs(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    let mut tx = application.begin(Identity::system()).await?;
+    let path = insert_object_path();
+    let other_path = CanonicalizedComponentFunctionPath {
+        component: ComponentPath::test_user(),
+        udf_path: CanonicalizedUdfPath::from_str("basic:simpleMutation")?,
+    };
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    let mut job_ids = vec![];
+    for path in [&path, &path, &path, &other_path] {
+        let job_id = model
+            .schedule(
+                path.clone(),
+                parse_udf_args(&path.udf_path, vec![serde_json::json!({})])?,
+                rt.unix_timestamp(),
+                ExecutionContext::new_for_test(),
+                ScheduleOptions::default(),
+            )
+            .await?;
+        job_ids.push(job_id);
+    }
+    let filter = ScheduledJobFilter {
+        path: Some(path),
+        ..Default::default()
+    };
+    assert_eq!(model.count_outstanding(&filter, 10).await?, 3);
+    assert_eq!(model.count_outstanding(&filter, 2).await?, 2);
+    assert_eq!(
+        model
+            .count_outstanding(&ScheduledJobFilter::default(), 10)
+            .await?,
+        4
+    );
+
+    // Finished jobs don't count.
+    model.cancel(job_ids[0]).await?;
+    assert_eq!(model.count_outstanding(&filter, 10).await?, 2);
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_scheduled_jobs_dedupe_key(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    // Pause the backend so the executor leaves the jobs pending.
+    let mut tx = application.begin(Identity::system()).await?;
+    BackendStateModel::new(&mut tx)
+        .toggle_backend_state(BackendState::Paused)
+        .await?;
+    let path = insert_object_path();
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    let mut schedule = async |key: &str, on_duplicate| {
+        let options = ScheduleOptions {
+            dedupe_key: Some(key.to_string()),
+            on_duplicate,
+            ..Default::default()
+        };
+        model
+            .schedule(
+                path.clone(),
+                parse_udf_args(&path.udf_path, vec![serde_json::json!({})])?,
+                rt.unix_timestamp(),
+                ExecutionContext::new_for_test(),
+                options,
+            )
+            .await
+    };
+    let first = schedule("a", DuplicateJobBehavior::Replace).await?;
+    let other_key = schedule("b", DuplicateJobBehavior::Replace).await?;
+    let replacement = schedule("a", DuplicateJobBehavior::Replace).await?;
+    assert_ne!(first, replacement);
+    let kept = schedule("a", DuplicateJobBehavior::Keep).await?;
+    assert_eq!(kept, replacement);
+
+    assert_eq!(
+        model.check_status(first).await?,
+        Some(ScheduledJobState::Canceled)
+    );
+    for job_id in [other_key, replacement] {
+        assert_eq!(
+            model.check_status(job_id).await?,
+            Some(ScheduledJobState::Pending)
+        );
+    }
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_scheduled_jobs_dedupe_key_without_index(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    // Deployments created before the dedupe key index existed don't have it
+    // enabled until it's backfilled.
+    let mut tx = application.begin(Identity::system()).await?;
+    IndexModel::new(&mut tx)
+        .drop_system_index(
+            TableNamespace::test_user(),
+            SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY.clone(),
+        )
+        .await?;
+    application.commit_test(tx).await?;
+
+    let mut tx = application.begin(Identity::system()).await?;
+    let path = insert_object_path();
+    let options = ScheduleOptions {
+        dedupe_key: Some("a".to_string()),
+        ..Default::default()
+    };
+    let err = create_scheduled_job_with_options(&rt, &mut tx, path.clone(), options)
+        .await
+        .unwrap_err();
+    assert_eq!(err.short_msg(), "DedupeKeyUnavailable");
+
+    // Scheduling without a dedupe key still works.
+    create_scheduled_job(&rt, &mut tx, path).await?;
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_scheduled_jobs_race_condition(rt: TestRuntime) -> anyhow::Result<()> {
     let application = Application::new_for_tests(&rt).await?;
@@ -169,7 +391,11 @@ async fn test_scheduled_jobs_race_condition(rt: TestRuntime) -> anyhow::Result<(
     let (job_id, job) = jobs[0].clone().into_id_and_value();
 
     // Cancel the scheduled job
-    model.cancel_all(Some(path), 1).await?;
+    let filter = ScheduledJobFilter {
+        path: Some(path),
+        ..Default::default()
+    };
+    model.cancel_all(&filter, None, 1).await?;
 
     application.commit_test(tx).await?;
 
@@ -299,6 +525,109 @@ async fn test_scheduled_jobs_helper(
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_pause_scheduled_jobs_of_function(
+    rt: TestRuntime,
+    pause_controller: PauseController,
+) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    let hold_guard = pause_controller.hold(SCHEDULED_JOB_EXECUTED);
+
+    let mut tx = application.begin(Identity::system()).await?;
+    assert!(
+        SchedulerPausesModel::new(&mut tx)
+            .pause(Some(insert_object_path()), Some("flooding".to_string()))
+            .await?
+    );
+    let (paused_job_id, _model) = create_scheduled_job(&rt, &mut tx, insert_object_path()).await?;
+    let other_path = CanonicalizedComponentFunctionPath {
+        component: ComponentPath::test_user(),
+        udf_path: CanonicalizedUdfPath::from_str("basic:simpleMutation")?,
+    };
+    let (other_job_id, _model) = create_scheduled_job(&rt, &mut tx, other_path).await?;
+    application.commit_test(tx).await?;
+
+    // Only the job of the function that isn't paused runs.
+    wait_for_scheduled_job_execution(hold_guard).await;
+    tx = application.begin(Identity::system()).await?;
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    assert_eq!(
+        model.check_status(other_job_id).await?.unwrap(),
+        ScheduledJobState::Success
+    );
+    assert_eq!(
+        model.check_status(paused_job_id).await?.unwrap(),
+        ScheduledJobState::Pending
+    );
+
+    // Resuming the function runs its job.
+    let hold_guard = pause_controller.hold(SCHEDULED_JOB_EXECUTED);
+    assert!(
+        SchedulerPausesModel::new(&mut tx)
+            .resume(Some(insert_object_path()))
+            .await?
+    );
+    application.commit_test(tx).await?;
+    wait_for_scheduled_job_execution(hold_guard).await;
+    tx = application.begin(Identity::system()).await?;
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    assert_eq!(
+        model.check_status(paused_job_id).await?.unwrap(),
+        ScheduledJobState::Success
+    );
+    assert!(
+        !SchedulerPausesModel::new(&mut tx)
+            .resume(Some(insert_object_path()))
+            .await?
+    );
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_draining_stops_scheduled_jobs(
+    rt: TestRuntime,
+    pause_controller: PauseController,
+) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+    let drain = application.scheduler_drain();
+
+    let committing = pause_controller.hold(SCHEDULED_JOB_COMMITTING);
+    let executed = pause_controller.hold(SCHEDULED_JOB_EXECUTED);
+    let mut tx = application.begin(Identity::system()).await?;
+    let (running_job_id, _model) = create_scheduled_job(&rt, &mut tx, insert_object_path()).await?;
+    application.commit_test(tx).await?;
+
+    // Jobs that are already running are counted until they finish.
+    let pause_guard = committing.wait_for_blocked().await.unwrap();
+    assert_eq!(drain.running_executions(), 1);
+    drain.start();
+
+    // Jobs scheduled after draining starts aren't started.
+    let mut tx = application.begin(Identity::system()).await?;
+    let (pending_job_id, _model) = create_scheduled_job(&rt, &mut tx, insert_object_path()).await?;
+    application.commit_test(tx).await?;
+    pause_guard.unpause();
+    wait_for_scheduled_job_execution(executed).await;
+    assert_eq!(drain.running_executions(), 0);
+
+    rt.wait(Duration::from_secs(1)).await;
+    let mut tx = application.begin(Identity::system()).await?;
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    assert_eq!(
+        model.check_status(running_job_id).await?.unwrap(),
+        ScheduledJobState::Success
+    );
+    assert_eq!(
+        model.check_status(pending_job_id).await?.unwrap(),
+        ScheduledJobState::Pending
+    );
+    assert_eq!(drain.running_executions(), 0);
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_cancel_recursively_scheduled_job(rt: TestRuntime) -> anyhow::Result<()> {
     let application = Application::new_for_tests(&rt).await?;
@@ -397,3 +726,202 @@ async fn test_scheduled_job_retry(
     assert_eq!(state, ScheduledJobState::Success);
     Ok(())
 }
+
+#[convex_macro::test_runtime]
+async fn test_scheduled_jobs_ready_by_priority(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    // Pause the backend so the executor leaves the jobs pending.
+    let mut tx = application.begin(Identity::system()).await?;
+    BackendStateModel::new(&mut tx)
+        .toggle_backend_state(BackendState::Paused)
+        .await?;
+    let mut job_ids = vec![];
+    for priority in [
+        ScheduledJobPriority::Low,
+        ScheduledJobPriority::Normal,
+        ScheduledJobPriority::High,
+    ] {
+        let options = ScheduleOptions {
+            priority,
+            queue: Some("backfill".to_string()),
+            ..Default::default()
+        };
+        let (job_id, _model) =
+            create_scheduled_job_with_options(&rt, &mut tx, insert_object_path(), options).await?;
+        job_ids.push((priority, job_id));
+    }
+    application.commit_test(tx).await?;
+
+    let mut tx = application.begin(Identity::system()).await?;
+    let now = rt.generate_timestamp()?;
+    for (priority, job_id) in job_ids {
+        let query = ready_jobs_query(Some(priority), now)?;
+        let mut query = ResolvedQuery::new(&mut tx, TableNamespace::test_user(), query)?;
+        let mut ready = vec![];
+        while let Some(doc) = query.next(&mut tx, None).await? {
+            ready.push(doc.id());
+        }
+        assert_eq!(ready, vec![job_id], "{priority}");
+    }
+    // Jobs that aren't due yet aren't ready.
+    let before = rt.generate_timestamp()?.sub(Duration::from_secs(60))?;
+    let query = ready_jobs_query(None, before)?;
+    let mut query = ResolvedQuery::new(&mut tx, TableNamespace::test_user(), query)?;
+    assert!(query.next(&mut tx, None).await?.is_none());
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_space_out_new_jobs(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    let mut tx = application.begin(Identity::system()).await?;
+    BackendStateModel::new(&mut tx)
+        .toggle_backend_state(BackendState::Paused)
+        .await?;
+    let mut job_ids = vec![];
+    for _ in 0..5 {
+        let (job_id, _model) = create_scheduled_job(&rt, &mut tx, insert_object_path()).await?;
+        job_ids.push(job_id);
+    }
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    model.space_out_new_jobs(2).await?;
+    let mut next_ts = vec![];
+    for job in model.list().await? {
+        if job_ids.contains(&job.id()) {
+            next_ts.push(job.next_ts.unwrap());
+        }
+    }
+    next_ts.sort();
+    assert_eq!(next_ts.len(), 5);
+    for pair in next_ts.windows(2) {
+        assert!(pair[1] - pair[0] >= Duration::from_millis(500));
+    }
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_scheduled_job_retry_policy_and_dead_letters(rt: TestRuntime) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    // Pause the backend so the executor doesn't run the jobs.
+    let mut tx = application.begin(Identity::system()).await?;
+    BackendStateModel::new(&mut tx)
+        .toggle_backend_state(BackendState::Paused)
+        .await?;
+    let options = ScheduleOptions {
+        retry: Some(ScheduledJobRetryPolicy {
+            max_attempts: 2,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 1000,
+        }),
+        ..Default::default()
+    };
+    let (job_id, _model) =
+        create_scheduled_job_with_options(&rt, &mut tx, insert_object_path(), options.clone())
+            .await?;
+    application.commit_test(tx).await?;
+
+    // The first failure is retried after the backoff.
+    let mut tx = application.begin(Identity::system()).await?;
+    let namespace = TableNamespace::test_user();
+    let mut model = SchedulerModel::new(&mut tx, namespace);
+    model.fail(job_id, "first".to_string()).await?;
+    assert_eq!(
+        model.check_status(job_id).await?,
+        Some(ScheduledJobState::Pending)
+    );
+    assert!(ScheduledJobDeadLettersModel::new(&mut tx, namespace)
+        .list()
+        .await?
+        .is_empty());
+
+    // The second one is the last attempt, so the job goes to the dead letters.
+    let mut model = SchedulerModel::new(&mut tx, namespace);
+    model.fail(job_id, "second".to_string()).await?;
+    assert_eq!(
+        model.check_status(job_id).await?,
+        Some(ScheduledJobState::Failed("second".to_string()))
+    );
+    let mut dead_letters_model = ScheduledJobDeadLettersModel::new(&mut tx, namespace);
+    let dead_letters = dead_letters_model.list().await?;
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].job_id, job_id.developer_id);
+    assert_eq!(dead_letters[0].error, "second");
+    assert_eq!(dead_letters[0].attempts, 2);
+    assert_eq!(dead_letters[0].options(), options);
+
+    // Rerunning schedules a new job and removes the dead letter.
+    let new_job_id = dead_letters_model
+        .rerun(dead_letters[0].id(), ExecutionContext::new_for_test())
+        .await?
+        .unwrap();
+    assert!(dead_letters_model.list().await?.is_empty());
+    assert_eq!(
+        SchedulerModel::new(&mut tx, namespace)
+            .check_status(new_job_id)
+            .await?,
+        Some(ScheduledJobState::Pending)
+    );
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_scheduled_job_continues_trace(
+    rt: TestRuntime,
+    pause_controller: PauseController,
+) -> anyhow::Result<()> {
+    // Spans are only recorded once there's a reporter.
+    fastrace::set_reporter(ConsoleReporter, Config::default());
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+    let hold_guard = pause_controller.hold(SCHEDULED_JOB_EXECUTED);
+
+    // Schedule a mutation that schedules another job from a traced function.
+    let path = CanonicalizedComponentFunctionPath {
+        component: ComponentPath::test_user(),
+        udf_path: CanonicalizedUdfPath::from_str("scheduler:scheduleWithArbitraryJson")?,
+    };
+    let mut context = ExecutionContext::new_for_test();
+    context.traceparent =
+        Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string());
+    let mut tx = application.begin(Identity::system()).await?;
+    let job_id = SchedulerModel::new(&mut tx, TableNamespace::test_user())
+        .schedule(
+            path.clone(),
+            parse_udf_args(&path.udf_path, vec![JsonValue::Object(Default::default())])?,
+            rt.unix_timestamp(),
+            context,
+            ScheduleOptions::default(),
+        )
+        .await?;
+    application.commit_test(tx).await?;
+    wait_for_scheduled_job_execution(hold_guard).await;
+
+    // The job ran in the scheduling function's trace, so the job it scheduled
+    // carries the same trace id.
+    let mut tx = application.begin(Identity::system()).await?;
+    let mut model = SchedulerModel::new(&mut tx, TableNamespace::test_user());
+    assert_eq!(
+        model.check_status(job_id).await?,
+        Some(ScheduledJobState::Success)
+    );
+    let jobs = model.list().await?;
+    let scheduled = jobs
+        .iter()
+        .find(|job| job.id() != job_id)
+        .context("The job didn't schedule another job")?;
+    let traceparent = scheduled
+        .traceparent
+        .as_deref()
+        .context("The job's execution wasn't traced")?;
+    assert!(
+        traceparent.starts_with("00-0af7651916cd43dd8448eb211c80319c-"),
+        "{traceparent}"
+    );
+    Ok(())
+}
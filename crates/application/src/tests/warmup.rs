@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    },
+    types::FunctionCaller,
+};
+use keybroker::Identity;
+use model::warmup::{
+    types::WarmupFunction,
+    WarmupModel,
+};
+use runtime::testing::TestRuntime;
+use value::{
+    ConvexArray,
+    ConvexValue,
+};
+
+use crate::{
+    test_helpers::ApplicationTestExt,
+    Application,
+};
+
+fn warmup_function(interval: Option<Duration>) -> anyhow::Result<WarmupFunction> {
+    WarmupFunction::new(
+        CanonicalizedComponentFunctionPath {
+            component: ComponentPath::test_user(),
+            udf_path: "basic:readTimeMs".parse()?,
+        },
+        ConvexArray::try_from(vec![ConvexValue::Object(Default::default())])?,
+        interval,
+    )
+}
+
+async fn replace_warmup_functions(
+    application: &Application<TestRuntime>,
+    functions: Vec<WarmupFunction>,
+) -> anyhow::Result<()> {
+    let mut tx = application.begin(Identity::system()).await?;
+    WarmupModel::new(&mut tx).replace(functions).await?;
+    application.commit_test(tx).await?;
+    Ok(())
+}
+
+async fn num_warmup_executions(application: &Application<TestRuntime>) -> usize {
+    let (function_log, _) = application.function_log().stream(0.0).await;
+    function_log
+        .iter()
+        .filter(|execution| execution.caller == FunctionCaller::Warmup)
+        .count()
+}
+
+#[convex_macro::test_runtime]
+async fn test_warmup_functions_run_after_push_and_on_interval(
+    rt: TestRuntime,
+) -> anyhow::Result<()> {
+    let application = Application::new_for_tests(&rt).await?;
+    application.load_udf_tests_modules().await?;
+
+    replace_warmup_functions(
+        &application,
+        vec![warmup_function(Some(Duration::from_secs(60)))?],
+    )
+    .await?;
+    rt.wait(Duration::from_secs(1)).await;
+    assert_eq!(num_warmup_executions(&application).await, 1);
+
+    // It reruns once its interval has passed.
+    rt.wait(Duration::from_secs(60)).await;
+    assert_eq!(num_warmup_executions(&application).await, 2);
+
+    // Every push rewrites the functions, so they run again right away, and
+    // functions without an interval only run after pushes.
+    replace_warmup_functions(&application, vec![warmup_function(None)?]).await?;
+    rt.wait(Duration::from_secs(1)).await;
+    assert_eq!(num_warmup_executions(&application).await, 3);
+    rt.wait(Duration::from_secs(300)).await;
+    assert_eq!(num_warmup_executions(&application).await, 3);
+
+    replace_warmup_functions(&application, vec![]).await?;
+    rt.wait(Duration::from_secs(300)).await;
+    assert_eq!(num_warmup_executions(&application).await, 3);
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use metrics::{
+    register_convex_histogram,
+    StatusTimer,
+    STATUS_LABEL,
+};
+
+register_convex_histogram!(
+    WARMUP_FUNCTION_SECONDS,
+    "Time to run a configured warm-up function",
+    &STATUS_LABEL
+);
+pub fn warmup_function_timer() -> StatusTimer {
+    StatusTimer::new(&WARMUP_FUNCTION_SECONDS)
+}
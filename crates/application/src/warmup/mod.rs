@@ -0,0 +1,167 @@
+//! Runs the warm-up functions configured at push time. Every push rewrites
+//! `_warmup_functions`, so each function runs once right after a deploy and
+//! then again every `interval` if it has one.
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::Duration,
+};
+
+use common::{
+    backoff::Backoff,
+    components::PublicFunctionPath,
+    document::ParsedDocument,
+    errors::report_error,
+    runtime::Runtime,
+    types::FunctionCaller,
+    RequestId,
+};
+use database::Database;
+use futures::{
+    future::Either,
+    select_biased,
+    Future,
+    FutureExt,
+};
+use keybroker::Identity;
+use model::{
+    backend_state::BackendStateModel,
+    warmup::{
+        types::WarmupFunction,
+        WarmupModel,
+    },
+};
+use serde_json::Value as JsonValue;
+use value::ResolvedDocumentId;
+
+use crate::application_function_runner::ApplicationFunctionRunner;
+
+mod metrics;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+pub struct WarmupExecutor<RT: Runtime> {
+    rt: RT,
+    database: Database<RT>,
+    runner: Arc<ApplicationFunctionRunner<RT>>,
+}
+
+impl<RT: Runtime> WarmupExecutor<RT> {
+    pub fn start(
+        rt: RT,
+        database: Database<RT>,
+        runner: Arc<ApplicationFunctionRunner<RT>>,
+    ) -> impl Future<Output = ()> + Send {
+        let executor = Self {
+            rt,
+            database,
+            runner,
+        };
+        async move {
+            let mut backoff = Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF);
+            while let Err(mut e) = executor.run(&mut backoff).await {
+                report_error(&mut e).await;
+                let delay = backoff.fail(&mut executor.rt.rng());
+                tracing::error!("Warm-up executor failed, sleeping {delay:?}");
+                executor.rt.wait(delay).await;
+            }
+        }
+    }
+
+    async fn run(&self, backoff: &mut Backoff) -> anyhow::Result<()> {
+        tracing::info!("Starting warm-up executor");
+        // When each function should next run. `None` means it has already run
+        // and has no interval. Functions are rewritten with new ids on every
+        // push, so a missing entry means the function should run now.
+        let mut next_runs: BTreeMap<ResolvedDocumentId, Option<tokio::time::Instant>> =
+            BTreeMap::new();
+        loop {
+            let mut tx = self.database.begin(Identity::system()).await?;
+            let backend_state = BackendStateModel::new(&mut tx).get_backend_state().await?;
+            let functions = if backend_state.is_stopped() {
+                vec![]
+            } else {
+                WarmupModel::new(&mut tx).list().await?
+            };
+            let token = tx.into_token()?;
+
+            next_runs.retain(|id, _| functions.iter().any(|f| f.id() == *id));
+            for function in &functions {
+                let now = self.rt.monotonic_now();
+                let due = match next_runs.get(&function.id()) {
+                    None => true,
+                    Some(Some(next_run)) => *next_run <= now,
+                    Some(None) => false,
+                };
+                if due {
+                    self.warm_up(function).await;
+                    let next_run = function.interval.map(|interval| now + interval);
+                    next_runs.insert(function.id(), next_run);
+                }
+            }
+
+            let next_run_future = match next_runs.values().flatten().min() {
+                Some(next_run) => Either::Left(
+                    self.rt
+                        .wait(next_run.saturating_duration_since(self.rt.monotonic_now())),
+                ),
+                None => Either::Right(std::future::pending()),
+            };
+            let subscription = self.database.subscribe(token).await?;
+            select_biased! {
+                _ = next_run_future.fuse() => {},
+                _ = subscription.wait_for_invalidation().fuse() => {},
+            }
+            backoff.reset();
+        }
+    }
+
+    /// Runs a warm-up function, logging rather than propagating failures: a
+    /// broken warm-up function shouldn't affect anything else.
+    async fn warm_up(&self, function: &ParsedDocument<WarmupFunction>) {
+        let timer = metrics::warmup_function_timer();
+        match self.run_query(function).await {
+            Ok(()) => {
+                timer.finish();
+            },
+            Err(e) => {
+                tracing::error!(
+                    "Failed to run warm-up function {:?}{}: {e:#}",
+                    function.path.udf_path,
+                    function.path.component.in_component_str()
+                );
+            },
+        }
+    }
+
+    async fn run_query(&self, function: &WarmupFunction) -> anyhow::Result<()> {
+        let args = function
+            .udf_args()?
+            .into_iter()
+            .map(JsonValue::from)
+            .collect();
+        let ts = self.database.now_ts_for_reads();
+        let query_return = self
+            .runner
+            .run_query_at_ts(
+                RequestId::new(),
+                PublicFunctionPath::Component(function.path.clone()),
+                args,
+                Identity::system(),
+                *ts,
+                None,
+                FunctionCaller::Warmup,
+            )
+            .await?;
+        // The caches are warm even if the query threw, so only log the error.
+        if let Err(e) = query_return.result {
+            tracing::warn!(
+                "Warm-up function {:?}{} threw an error: {e}",
+                function.path.udf_path,
+                function.path.component.in_component_str()
+            );
+        }
+        Ok(())
+    }
+}
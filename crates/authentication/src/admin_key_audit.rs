@@ -0,0 +1,95 @@
+//! Notes the admin and deploy keys that are checked while handling a request,
+//! so the router can record them in `_admin_key_audit_log`.
+//!
+//! Keys can come in a header, a query parameter, a request body or a sync
+//! protocol message, so instead of looking for them, callers hear about
+//! whatever key ends up being checked.
+use std::future::Future;
+
+use keybroker::Identity;
+
+/// The result of checking an admin or deploy key.
+pub enum AdminKeyCheck {
+    Valid {
+        principal: String,
+        api_key_id: Option<String>,
+    },
+    Invalid,
+}
+
+tokio::task_local! {
+    static ON_ADMIN_KEY_CHECK: Box<dyn Fn(AdminKeyCheck) + Send + Sync>;
+}
+
+/// Runs `fut`, calling `on_check` for every admin or deploy key it checks.
+pub async fn with_admin_key_checks<F: Future>(
+    on_check: impl Fn(AdminKeyCheck) + Send + Sync + 'static,
+    fut: F,
+) -> F::Output {
+    ON_ADMIN_KEY_CHECK.scope(Box::new(on_check), fut).await
+}
+
+/// Notes the result of checking an admin or deploy key, where `None` means the
+/// key was rejected. Does nothing outside of `with_admin_key_checks`.
+pub(crate) fn record_admin_key_check(identity: Option<&Identity>) {
+    let check = match identity {
+        Some(Identity::InstanceAdmin(admin_identity))
+        | Some(Identity::ActingUser(admin_identity, _)) => AdminKeyCheck::Valid {
+            principal: admin_identity.principal().to_string(),
+            api_key_id: admin_identity.api_key().map(|api_key| api_key.id.clone()),
+        },
+        Some(Identity::System(_)) => AdminKeyCheck::Valid {
+            principal: "system".to_string(),
+            api_key_id: None,
+        },
+        Some(Identity::User(_)) | Some(Identity::Unknown) | None => AdminKeyCheck::Invalid,
+    };
+    let _ = ON_ADMIN_KEY_CHECK.try_with(|on_check| on_check(check));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::types::MemberId;
+    use keybroker::{
+        AdminIdentity,
+        Identity,
+    };
+    use parking_lot::Mutex;
+
+    use super::{
+        record_admin_key_check,
+        with_admin_key_checks,
+        AdminKeyCheck,
+    };
+
+    #[tokio::test]
+    async fn test_record_admin_key_check() {
+        let checks = Arc::new(Mutex::new(vec![]));
+        let on_check = {
+            let checks = checks.clone();
+            move |check| checks.lock().push(check)
+        };
+        with_admin_key_checks(on_check, async {
+            let identity = Identity::InstanceAdmin(AdminIdentity::new_for_test_only(
+                "carnitas".to_string(),
+                MemberId(7),
+            ));
+            record_admin_key_check(Some(&identity));
+            record_admin_key_check(Some(&Identity::Unknown));
+        })
+        .await;
+        let checks = std::mem::take(&mut *checks.lock());
+        assert!(matches!(
+            &checks[..],
+            [
+                AdminKeyCheck::Valid { principal, api_key_id: None },
+                AdminKeyCheck::Invalid,
+            ] if principal == "member:7"
+        ));
+
+        // Outside of `with_admin_key_checks`, checks aren't recorded anywhere.
+        record_admin_key_check(None);
+    }
+}
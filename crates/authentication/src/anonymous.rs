@@ -0,0 +1,124 @@
+//! Anonymous identities, which let users act before they sign up. The backend
+//! issues them as JWTs signed with a key derived from the instance secret, and
+//! verifies them like tokens from a custom JWT provider. Clients keep the same
+//! subject by renewing their token before it expires, and can later upgrade
+//! the identity to the one they sign up with.
+use std::{
+    collections::BTreeMap,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use common::auth::{
+    CustomJwtKey,
+    CustomJwtProvider,
+};
+use keybroker::UserIdentity;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::custom_jwt::issue_custom_jwt;
+
+/// Claim that anonymous tokens have set to `true`. It's passed through to the
+/// identity's custom claims, so functions can tell anonymous users apart.
+pub const ANONYMOUS_CLAIM: &str = "anonymous";
+
+/// Custom claim of identities that were upgraded from an anonymous identity,
+/// holding the anonymous identity's token identifier.
+pub const UPGRADED_FROM_CLAIM: &str = "upgradedFromAnonymous";
+
+#[derive(Serialize)]
+struct AnonymousClaims {
+    anonymous: bool,
+}
+
+/// Issuer of the deployment's anonymous tokens.
+pub fn anonymous_issuer(instance_name: &str) -> String {
+    format!("convex:anonymous:{instance_name}")
+}
+
+/// The provider that verifies anonymous tokens signed with `secret`.
+pub fn anonymous_jwt_provider(issuer: String, secret: String) -> CustomJwtProvider {
+    CustomJwtProvider {
+        issuer,
+        audience: None,
+        algorithm: "HS256".to_string(),
+        key: CustomJwtKey::Secret(secret),
+        required_claims: BTreeMap::from([(ANONYMOUS_CLAIM.to_string(), "true".to_string())]),
+        claim_mapping: BTreeMap::new(),
+    }
+}
+
+/// A random subject for a new anonymous identity.
+pub fn new_anonymous_subject() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 16]>())
+}
+
+/// Issues a token for the anonymous identity `subject`, valid for `ttl`.
+pub fn issue_anonymous_token(
+    provider: &CustomJwtProvider,
+    subject: String,
+    system_time: SystemTime,
+    ttl: Duration,
+) -> anyhow::Result<String> {
+    issue_custom_jwt(
+        provider,
+        subject,
+        AnonymousClaims { anonymous: true },
+        system_time,
+        system_time + ttl,
+    )
+}
+
+pub fn is_anonymous(identity: &UserIdentity) -> bool {
+    identity
+        .attributes
+        .custom_claims
+        .get(ANONYMOUS_CLAIM)
+        .is_some_and(|value| value == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use super::{
+        anonymous_issuer,
+        anonymous_jwt_provider,
+        is_anonymous,
+        issue_anonymous_token,
+        new_anonymous_subject,
+    };
+    use crate::custom_jwt::{
+        custom_jwt_provider_for,
+        validate_custom_jwt,
+    };
+
+    #[test]
+    fn test_anonymous_tokens() -> anyhow::Result<()> {
+        let provider = anonymous_jwt_provider(anonymous_issuer("carnitas"), "secret".to_string());
+        let now = SystemTime::now();
+        let subject = new_anonymous_subject();
+        let token =
+            issue_anonymous_token(&provider, subject.clone(), now, Duration::from_secs(60))?;
+
+        let providers = [provider];
+        let provider = custom_jwt_provider_for(&token, &providers).unwrap();
+        let identity = validate_custom_jwt(&token, provider, now)?;
+        assert_eq!(identity.subject, subject);
+        assert!(is_anonymous(&identity));
+
+        // Tokens expire.
+        assert!(validate_custom_jwt(&token, provider, now + Duration::from_secs(61)).is_err());
+
+        // Tokens from another deployment aren't accepted.
+        let other = anonymous_jwt_provider(anonymous_issuer("carnitas"), "other".to_string());
+        assert!(validate_custom_jwt(&token, &other, now).is_err());
+        Ok(())
+    }
+}
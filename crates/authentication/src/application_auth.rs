@@ -1,14 +1,30 @@
-use std::sync::Arc;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    sync::Arc,
+    time::SystemTime,
+};
 
 use anyhow::Context;
+use biscuit::JWT;
+use common::types::MemberId;
 use errors::ErrorMetadata;
 use keybroker::{
+    AdminIdentityPrincipal,
+    AdminRole,
     Identity,
     KeyBroker,
+    UserIdentity,
 };
+use parking_lot::RwLock;
+use serde_json::Value as JsonValue;
 
 use crate::{
     access_token_auth::AccessTokenAuth,
+    admin_key_audit::record_admin_key_check,
+    anonymous::UPGRADED_FROM_CLAIM,
     metrics::{
         log_deploy_key_use,
         DeployKeyType,
@@ -18,6 +34,31 @@ use crate::{
 pub struct ApplicationAuth {
     key_broker: KeyBroker,
     access_token_auth: Arc<dyn AccessTokenAuth>,
+    /// IDs of the API keys that haven't been revoked, kept in sync with
+    /// `_api_keys` by the application.
+    api_key_ids: RwLock<BTreeSet<String>>,
+    /// Roles of the members with one, kept in sync with `_admin_roles` by the
+    /// application. When it's empty every member is an owner.
+    admin_roles: RwLock<BTreeMap<MemberId, AdminRole>>,
+    /// Roles of the service accounts by ID, kept in sync with
+    /// `_service_accounts` by the application.
+    service_account_roles: RwLock<BTreeMap<String, AdminRole>>,
+    /// Revoked user tokens, kept in sync with `_token_revocations` by the
+    /// application.
+    token_revocations: RwLock<TokenRevocations>,
+    /// Token identifier of each identity that was upgraded from an anonymous
+    /// identity -> the anonymous identity's, kept in sync with
+    /// `_anonymous_identity_upgrades` by the application.
+    anonymous_identity_upgrades: RwLock<BTreeMap<String, String>>,
+}
+
+#[derive(Default)]
+pub struct TokenRevocations {
+    /// `jti` claims of revoked tokens.
+    pub token_ids: BTreeSet<String>,
+    /// Token identifier of each revoked user -> when they were revoked. Their
+    /// tokens issued before then are rejected.
+    pub users: BTreeMap<String, SystemTime>,
 }
 
 // Encapsulates auth logic supporting both legacy Deploy Keys and new Convex
@@ -27,13 +68,114 @@ impl ApplicationAuth {
         Self {
             key_broker,
             access_token_auth,
+            api_key_ids: RwLock::new(BTreeSet::new()),
+            admin_roles: RwLock::new(BTreeMap::new()),
+            service_account_roles: RwLock::new(BTreeMap::new()),
+            token_revocations: RwLock::new(TokenRevocations::default()),
+            anonymous_identity_upgrades: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set_api_key_ids(&self, ids: BTreeSet<String>) {
+        *self.api_key_ids.write() = ids;
+    }
+
+    pub fn add_api_key_id(&self, id: String) {
+        self.api_key_ids.write().insert(id);
+    }
+
+    pub fn remove_api_key_id(&self, id: &str) {
+        self.api_key_ids.write().remove(id);
+    }
+
+    pub fn set_admin_roles(&self, roles: BTreeMap<MemberId, AdminRole>) {
+        *self.admin_roles.write() = roles;
+    }
+
+    pub fn set_service_account_roles(&self, roles: BTreeMap<String, AdminRole>) {
+        *self.service_account_roles.write() = roles;
+    }
+
+    pub fn add_service_account(&self, id: String, role: AdminRole) {
+        self.service_account_roles.write().insert(id, role);
+    }
+
+    pub fn remove_service_account(&self, id: &str) {
+        self.service_account_roles.write().remove(id);
+    }
+
+    pub fn set_token_revocations(&self, revocations: TokenRevocations) {
+        *self.token_revocations.write() = revocations;
+    }
+
+    /// Rejects `user`'s token if it's been revoked. Tokens of revoked users
+    /// without an `iat` claim are rejected, since they might predate the
+    /// revocation.
+    pub fn check_token_revocation(&self, user: &UserIdentity) -> anyhow::Result<()> {
+        let revocations = self.token_revocations.read();
+        if revocations.token_ids.is_empty() && revocations.users.is_empty() {
+            return Ok(());
+        }
+        // The token has already been verified.
+        let claims = JWT::<biscuit::Empty, biscuit::Empty>::new_encoded(&user.original_token)
+            .unverified_payload()?
+            .registered;
+        let token_revoked = claims
+            .id
+            .is_some_and(|token_id| revocations.token_ids.contains(&token_id));
+        let user_revoked = revocations
+            .users
+            .get(&*user.attributes.token_identifier)
+            .is_some_and(|revoked_at| {
+                claims
+                    .issued_at
+                    .is_none_or(|issued_at| SystemTime::from(*issued_at) <= *revoked_at)
+            });
+        anyhow::ensure!(
+            !token_revoked && !user_revoked,
+            ErrorMetadata::unauthenticated("TokenRevoked", "The provided token has been revoked")
+        );
+        Ok(())
+    }
+
+    pub fn set_anonymous_identity_upgrades(&self, upgrades: BTreeMap<String, String>) {
+        *self.anonymous_identity_upgrades.write() = upgrades;
+    }
+
+    /// Adds the token identifier of the anonymous identity `user` was upgraded
+    /// from to its custom claims, so functions can move the anonymous user's
+    /// data over.
+    pub fn annotate_anonymous_identity_upgrade(&self, user: &mut UserIdentity) {
+        if let Some(anonymous_token_identifier) = self
+            .anonymous_identity_upgrades
+            .read()
+            .get(&*user.attributes.token_identifier)
+        {
+            user.attributes.custom_claims.insert(
+                UPGRADED_FROM_CLAIM.to_string(),
+                JsonValue::String(anonymous_token_identifier.clone()).to_string(),
+            );
         }
     }
 
+    /// Checks an admin key, deploy key or access token, noting the result for
+    /// the admin key audit log.
     pub async fn check_key(
         &self,
         admin_key_or_access_token: String,
         instance_name: String,
+    ) -> anyhow::Result<Identity> {
+        let identity = self
+            .check_key_inner(admin_key_or_access_token, instance_name)
+            .await;
+        record_admin_key_check(identity.as_ref().ok());
+        identity
+    }
+
+    async fn check_key_inner(
+        &self,
+        admin_key_or_access_token: String,
+        instance_name: String,
     ) -> anyhow::Result<Identity> {
         if self
             .key_broker
@@ -41,12 +183,35 @@ impl ApplicationAuth {
         {
             // assume this is a legacy Deploy Key
             log_deploy_key_use(DeployKeyType::Legacy);
-            self.key_broker
+            let mut identity = self
+                .key_broker
                 .check_admin_key(&admin_key_or_access_token)
                 .context(ErrorMetadata::unauthenticated(
                     "BadAdminKey",
                     "The provided admin key was invalid for this instance",
-                ))
+                ))?;
+            if let Some(api_key) = identity.api_key() {
+                anyhow::ensure!(
+                    self.api_key_ids.read().contains(&api_key.id),
+                    ErrorMetadata::unauthenticated(
+                        "ApiKeyRevoked",
+                        "The provided API key has been revoked",
+                    )
+                );
+            }
+            if let Identity::InstanceAdmin(admin_identity) = &mut identity {
+                let role = match admin_identity.principal() {
+                    AdminIdentityPrincipal::Member(member_id) => Some(self.admin_role(*member_id)?),
+                    AdminIdentityPrincipal::ServiceAccount(id) => {
+                        Some(self.service_account_role(id)?)
+                    },
+                    AdminIdentityPrincipal::Team(_) => None,
+                };
+                if let Some(role) = role {
+                    admin_identity.set_role(role);
+                }
+            }
+            Ok(identity)
         } else {
             // assume this is an Access Token
             // Access Tokens are base64 encoded strings
@@ -56,4 +221,29 @@ impl ApplicationAuth {
                 .await
         }
     }
+
+    fn admin_role(&self, member_id: MemberId) -> anyhow::Result<AdminRole> {
+        let admin_roles = self.admin_roles.read();
+        if admin_roles.is_empty() {
+            return Ok(AdminRole::Owner);
+        }
+        admin_roles
+            .get(&member_id)
+            .copied()
+            .context(ErrorMetadata::forbidden(
+                "NoAdminRole",
+                format!("Member {member_id} hasn't been assigned a role in this deployment"),
+            ))
+    }
+
+    fn service_account_role(&self, id: &str) -> anyhow::Result<AdminRole> {
+        self.service_account_roles
+            .read()
+            .get(id)
+            .copied()
+            .context(ErrorMetadata::unauthenticated(
+                "ServiceAccountDeleted",
+                "The service account the provided token was issued to has been deleted",
+            ))
+    }
 }
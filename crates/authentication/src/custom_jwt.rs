@@ -0,0 +1,608 @@
+//! Authentication with JWTs from identity systems that aren't OpenID Connect
+//! providers. Each deployment configures the issuers it accepts as
+//! [`CustomJwtProvider`]s, whose rules say how tokens are verified and how
+//! their claims map onto the user's identity.
+use std::{
+    collections::BTreeMap,
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use biscuit::{
+    jwa::SignatureAlgorithm,
+    jwk::JWKSet,
+    jws::{
+        RegisteredHeader,
+        Secret,
+    },
+    ClaimPresenceOptions,
+    ClaimsSet,
+    Presence,
+    RegisteredClaims,
+    SingleOrMultiple,
+    TemporalOptions,
+    Validation,
+    ValidationOptions,
+    JWT,
+};
+use common::auth::{
+    CustomJwtKey,
+    CustomJwtProvider,
+};
+use errors::ErrorMetadata;
+use keybroker::UserIdentity;
+use rand::Rng;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use sync_types::{
+    UserIdentifier,
+    UserIdentityAttributes,
+};
+
+/// Identity fields that claims can be mapped onto.
+pub const MAPPABLE_IDENTITY_FIELDS: &[&str] = &[
+    "subject",
+    "name",
+    "givenName",
+    "familyName",
+    "nickname",
+    "preferredUsername",
+    "email",
+    "emailVerified",
+    "pictureUrl",
+    "phoneNumber",
+];
+
+/// Claims JWTs define that are never passed through as custom claims.
+const REGISTERED_CLAIMS: &[&str] = &["iss", "sub", "aud", "exp", "nbf", "iat", "jti"];
+
+type Claims = BTreeMap<String, JsonValue>;
+
+/// Checks that tokens can be verified with `provider`'s key and algorithm,
+/// and that its rules are well-formed.
+pub fn validate_custom_jwt_provider(provider: &CustomJwtProvider) -> anyhow::Result<()> {
+    let invalid = |msg: String| ErrorMetadata::bad_request("InvalidCustomJwtProvider", msg);
+    anyhow::ensure!(
+        !provider.issuer.is_empty(),
+        invalid("The issuer can't be empty".to_string())
+    );
+    let algorithm = parse_algorithm(&provider.algorithm)?;
+    let is_hmac = matches!(
+        algorithm,
+        SignatureAlgorithm::HS256 | SignatureAlgorithm::HS384 | SignatureAlgorithm::HS512
+    );
+    match &provider.key {
+        CustomJwtKey::Secret(secret) => {
+            anyhow::ensure!(
+                is_hmac,
+                invalid(format!(
+                    "A secret can only verify HMAC algorithms, not {}",
+                    provider.algorithm
+                ))
+            );
+            anyhow::ensure!(
+                !secret.is_empty(),
+                invalid("The secret can't be empty".to_string())
+            );
+        },
+        CustomJwtKey::Jwks(jwks) => {
+            anyhow::ensure!(
+                !is_hmac && algorithm != SignatureAlgorithm::None,
+                invalid(format!(
+                    "A JWKS can only verify RSA and ECDSA algorithms, not {}",
+                    provider.algorithm
+                ))
+            );
+            let jwks = parse_jwks(jwks)?;
+            anyhow::ensure!(
+                !jwks.keys.is_empty() && jwks.keys.iter().all(|key| key.common.key_id.is_some()),
+                invalid("The JWKS must have at least one key, and every key needs a `kid`".into())
+            );
+        },
+    }
+    for (claim, value) in &provider.required_claims {
+        serde_json::from_str::<JsonValue>(value).context(invalid(format!(
+            "The required value of `{claim}` isn't valid JSON"
+        )))?;
+    }
+    validate_claim_mapping(&provider.claim_mapping, "InvalidCustomJwtProvider")
+}
+
+/// Checks that `claim_mapping` only maps claims onto
+/// [`MAPPABLE_IDENTITY_FIELDS`], failing with `error_code` if it doesn't.
+pub fn validate_claim_mapping(
+    claim_mapping: &BTreeMap<String, String>,
+    error_code: &'static str,
+) -> anyhow::Result<()> {
+    for (field, claim) in claim_mapping {
+        anyhow::ensure!(
+            MAPPABLE_IDENTITY_FIELDS.contains(&field.as_str()),
+            ErrorMetadata::bad_request(
+                error_code,
+                format!(
+                    "Claims can't be mapped onto `{field}`. The fields are: {}",
+                    MAPPABLE_IDENTITY_FIELDS.join(", ")
+                ),
+            )
+        );
+        anyhow::ensure!(
+            !claim.is_empty(),
+            ErrorMetadata::bad_request(
+                error_code,
+                format!("The claim mapped onto `{field}` can't be empty"),
+            )
+        );
+    }
+    Ok(())
+}
+
+/// The provider for `token`'s issuer and audience, if it has one. Several
+/// providers can share an issuer if their audiences differ: the one whose
+/// audience is in the token's `aud` claim is picked, and the one without an
+/// audience otherwise. The claims are read before the token is verified, so
+/// callers must go on to [`validate_custom_jwt`].
+pub fn custom_jwt_provider_for<'a>(
+    token: &str,
+    providers: &'a [CustomJwtProvider],
+) -> Option<&'a CustomJwtProvider> {
+    let claims = JWT::<biscuit::Empty, biscuit::Empty>::new_encoded(token)
+        .unverified_payload()
+        .ok()?;
+    let issuer = claims.registered.issuer?;
+    let audiences = match claims.registered.audience {
+        Some(SingleOrMultiple::Single(audience)) => vec![audience],
+        Some(SingleOrMultiple::Multiple(audiences)) => audiences,
+        None => vec![],
+    };
+    let mut without_audience = None;
+    for provider in providers
+        .iter()
+        .filter(|provider| provider.issuer == issuer)
+    {
+        match &provider.audience {
+            Some(audience) if audiences.contains(audience) => return Some(provider),
+            Some(_) => {},
+            None => {
+                without_audience.get_or_insert(provider);
+            },
+        }
+    }
+    without_audience
+}
+
+/// Reads the identity fields in `claim_mapping` from the claims that weren't
+/// already mapped onto `identity`, for OpenID Connect providers whose tokens
+/// don't use the standard claims. Mapped claims are no longer passed through
+/// as custom claims.
+pub fn apply_claim_mapping(
+    identity: &mut UserIdentity,
+    claim_mapping: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    if claim_mapping.is_empty() {
+        return Ok(());
+    }
+    let attributes = &mut identity.attributes;
+    let mut claims = BTreeMap::new();
+    for (field, claim) in claim_mapping {
+        if let Some(value) = attributes.custom_claims.remove(claim) {
+            claims.insert(field.as_str(), serde_json::from_str::<JsonValue>(&value)?);
+        }
+    }
+    let mut string_claim = |field: &str| match claims.remove(field)? {
+        JsonValue::String(value) => Some(value),
+        value @ JsonValue::Number(_) => Some(value.to_string()),
+        _ => None,
+    };
+    if claim_mapping.contains_key("subject") {
+        let subject = string_claim("subject").context(ErrorMetadata::unauthenticated(
+            "InvalidAuthToken",
+            format!(
+                "The token's `{}` claim must be its subject",
+                claim_mapping["subject"]
+            ),
+        ))?;
+        attributes.token_identifier = UserIdentifier::construct(&identity.issuer, &subject);
+        attributes.subject = Some(subject.clone());
+        identity.subject = subject;
+    }
+    let mut set = |field: &str, attribute: &mut Option<String>| {
+        if let Some(value) = string_claim(field) {
+            *attribute = Some(value);
+        }
+    };
+    set("name", &mut attributes.name);
+    set("givenName", &mut attributes.given_name);
+    set("familyName", &mut attributes.family_name);
+    set("nickname", &mut attributes.nickname);
+    set("preferredUsername", &mut attributes.preferred_username);
+    set("email", &mut attributes.email);
+    set("pictureUrl", &mut attributes.picture_url);
+    set("phoneNumber", &mut attributes.phone_number);
+    if let Some(email_verified) = claims.get("emailVerified").and_then(JsonValue::as_bool) {
+        attributes.email_verified = Some(email_verified);
+    }
+    Ok(())
+}
+
+/// Verifies `token` against `provider`'s rules, returning the identity its
+/// claims map onto.
+pub fn validate_custom_jwt(
+    token: &str,
+    provider: &CustomJwtProvider,
+    system_time: SystemTime,
+) -> anyhow::Result<UserIdentity> {
+    let invalid_token =
+        |msg: &str| ErrorMetadata::unauthenticated("InvalidCustomJwt", msg.to_string());
+    let algorithm = parse_algorithm(&provider.algorithm)?;
+    let encoded = JWT::<Claims, biscuit::Empty>::new_encoded(token);
+    let decoded = match &provider.key {
+        CustomJwtKey::Secret(secret) => {
+            encoded.decode(&Secret::Bytes(secret.as_bytes().to_vec()), algorithm)
+        },
+        CustomJwtKey::Jwks(jwks) => encoded.decode_with_jwks(&parse_jwks(jwks)?, Some(algorithm)),
+    }
+    .context(invalid_token("The token's signature could not be verified"))?;
+    decoded
+        .validate(ValidationOptions {
+            claim_presence_options: ClaimPresenceOptions {
+                issuer: Presence::Required,
+                expiry: Presence::Required,
+                audience: if provider.audience.is_some() {
+                    Presence::Required
+                } else {
+                    Presence::Optional
+                },
+                ..Default::default()
+            },
+            temporal_options: TemporalOptions {
+                epsilon: chrono::Duration::zero(),
+                now: Some(chrono::DateTime::from(system_time)),
+            },
+            issuer: Validation::Validate(provider.issuer.clone()),
+            audience: match &provider.audience {
+                Some(audience) => Validation::Validate(audience.clone()),
+                None => Validation::Ignored,
+            },
+            ..ValidationOptions::default()
+        })
+        .context(ErrorMetadata::unauthenticated(
+            "InvalidCustomJwtClaims",
+            "The token could not be validated",
+        ))?;
+    let claims_set = decoded
+        .payload()
+        .context(invalid_token("The token's claims could not be decoded"))?;
+    let expiration = claims_set
+        .registered
+        .expiry
+        .as_ref()
+        .map(|expiry| SystemTime::from(**expiry))
+        .context(invalid_token("The token must expire"))?;
+    let claims: Claims = serde_json::from_value(serde_json::to_value(claims_set)?)?;
+
+    for (claim, value) in &provider.required_claims {
+        let expected: JsonValue = serde_json::from_str(value)?;
+        let matches = match claims.get(claim) {
+            Some(JsonValue::Array(values)) if !expected.is_array() => values.contains(&expected),
+            Some(value) => *value == expected,
+            None => false,
+        };
+        anyhow::ensure!(
+            matches,
+            ErrorMetadata::unauthenticated(
+                "CustomJwtClaimMismatch",
+                format!("The token's `{claim}` claim doesn't have the required value"),
+            )
+        );
+    }
+
+    let claim_name = |field: &str| provider.claim_mapping.get(field).map(String::as_str);
+    let string_claim = |field: &str| match claims.get(claim_name(field)?)? {
+        JsonValue::String(value) => Some(value.clone()),
+        value @ JsonValue::Number(_) => Some(value.to_string()),
+        _ => None,
+    };
+    let bool_claim = |field: &str| claims.get(claim_name(field)?)?.as_bool();
+    let subject_claim = claim_name("subject").unwrap_or("sub");
+    let subject = match claims.get(subject_claim) {
+        Some(JsonValue::String(subject)) => subject.clone(),
+        Some(subject @ JsonValue::Number(_)) => subject.to_string(),
+        _ => anyhow::bail!(invalid_token(&format!(
+            "The token's `{subject_claim}` claim must be its subject"
+        ))),
+    };
+    let issuer = provider.issuer.clone();
+    let custom_claims = claims
+        .iter()
+        .filter(|(claim, _)| {
+            !REGISTERED_CLAIMS.contains(&claim.as_str())
+                && !provider
+                    .claim_mapping
+                    .values()
+                    .any(|mapped| mapped == *claim)
+        })
+        .map(|(claim, value)| (claim.clone(), value.to_string()))
+        .collect();
+    UserIdentity::from_verified_token(
+        token.to_string(),
+        expiration,
+        UserIdentityAttributes {
+            token_identifier: UserIdentifier::construct(&issuer, &subject),
+            subject: Some(subject),
+            issuer: Some(issuer),
+            name: string_claim("name"),
+            given_name: string_claim("givenName"),
+            family_name: string_claim("familyName"),
+            nickname: string_claim("nickname"),
+            preferred_username: string_claim("preferredUsername"),
+            email: string_claim("email"),
+            email_verified: bool_claim("emailVerified"),
+            picture_url: string_claim("pictureUrl"),
+            phone_number: string_claim("phoneNumber"),
+            custom_claims,
+            ..Default::default()
+        },
+    )
+}
+
+/// Issues a token for `subject` that `provider` accepts, for the providers
+/// whose tokens the deployment signs itself with a secret.
+pub fn issue_custom_jwt<P: Serialize>(
+    provider: &CustomJwtProvider,
+    subject: String,
+    private: P,
+    system_time: SystemTime,
+    expiration: SystemTime,
+) -> anyhow::Result<String> {
+    let CustomJwtKey::Secret(secret) = &provider.key else {
+        anyhow::bail!("Only tokens signed with a secret can be issued");
+    };
+    let claims = ClaimsSet {
+        registered: RegisteredClaims {
+            issuer: Some(provider.issuer.clone()),
+            subject: Some(subject),
+            issued_at: Some(chrono::DateTime::<chrono::Utc>::from(system_time).into()),
+            expiry: Some(chrono::DateTime::<chrono::Utc>::from(expiration).into()),
+            id: Some(hex::encode(rand::thread_rng().gen::<[u8; 16]>())),
+            ..Default::default()
+        },
+        private,
+    };
+    let header = RegisteredHeader {
+        algorithm: parse_algorithm(&provider.algorithm)?,
+        ..Default::default()
+    };
+    let jwt = JWT::new_decoded(header.into(), claims)
+        .into_encoded(&Secret::Bytes(secret.as_bytes().to_vec()))?;
+    Ok(jwt.encoded()?.encode())
+}
+
+fn parse_algorithm(algorithm: &str) -> anyhow::Result<SignatureAlgorithm> {
+    serde_json::from_value(JsonValue::String(algorithm.to_string())).context(
+        ErrorMetadata::bad_request(
+            "InvalidCustomJwtProvider",
+            format!("{algorithm} isn't a JWS algorithm, like RS256 or HS256"),
+        ),
+    )
+}
+
+fn parse_jwks(jwks: &str) -> anyhow::Result<JWKSet<biscuit::Empty>> {
+    serde_json::from_str(jwks).context(ErrorMetadata::bad_request(
+        "InvalidCustomJwtProvider",
+        "The provider's keys must be a JSON Web Key Set",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        time::{
+            Duration,
+            SystemTime,
+        },
+    };
+
+    use biscuit::{
+        jwa::SignatureAlgorithm,
+        jws::{
+            RegisteredHeader,
+            Secret,
+        },
+        ClaimsSet,
+        RegisteredClaims,
+        SingleOrMultiple,
+        JWT,
+    };
+    use common::auth::{
+        CustomJwtKey,
+        CustomJwtProvider,
+    };
+    use keybroker::UserIdentity;
+    use serde_json::json;
+    use sync_types::UserIdentityAttributes;
+
+    use super::{
+        apply_claim_mapping,
+        custom_jwt_provider_for,
+        validate_custom_jwt,
+        validate_custom_jwt_provider,
+        Claims,
+    };
+
+    const SECRET: &str = "an-hmac-secret";
+
+    fn provider() -> CustomJwtProvider {
+        CustomJwtProvider {
+            issuer: "https://auth.example.com".to_string(),
+            audience: Some("my-app".to_string()),
+            algorithm: "HS256".to_string(),
+            key: CustomJwtKey::Secret(SECRET.to_string()),
+            required_claims: BTreeMap::from([("roles".to_string(), "\"member\"".to_string())]),
+            claim_mapping: BTreeMap::from([
+                ("subject".to_string(), "user_id".to_string()),
+                ("email".to_string(), "mail".to_string()),
+            ]),
+        }
+    }
+
+    fn token(audience: &str, expiry: SystemTime, private: Claims) -> anyhow::Result<String> {
+        let claims = ClaimsSet {
+            registered: RegisteredClaims {
+                issuer: Some("https://auth.example.com".to_string()),
+                audience: Some(SingleOrMultiple::Single(audience.to_string())),
+                expiry: Some(chrono::DateTime::<chrono::Utc>::from(expiry).into()),
+                ..Default::default()
+            },
+            private,
+        };
+        let header = RegisteredHeader {
+            algorithm: SignatureAlgorithm::HS256,
+            ..Default::default()
+        };
+        let jwt = JWT::new_decoded(header.into(), claims)
+            .into_encoded(&Secret::Bytes(SECRET.as_bytes().to_vec()))?;
+        Ok(jwt.encoded()?.encode())
+    }
+
+    #[test]
+    fn test_custom_jwt() -> anyhow::Result<()> {
+        let provider = provider();
+        validate_custom_jwt_provider(&provider)?;
+        let now = SystemTime::now();
+        let expiry = now + Duration::from_secs(60);
+        let claims: Claims = serde_json::from_value(json!({
+            "user_id": "user-1",
+            "mail": "user@example.com",
+            "roles": ["admin", "member"],
+            "org": "acme",
+        }))?;
+        let token = token("my-app", expiry, claims.clone())?;
+        let providers = [provider.clone()];
+        assert_eq!(custom_jwt_provider_for(&token, &providers), Some(&provider));
+
+        let identity = validate_custom_jwt(&token, &provider, now)?;
+        assert_eq!(identity.subject, "user-1");
+        assert_eq!(
+            identity.attributes.email.as_deref(),
+            Some("user@example.com")
+        );
+        assert_eq!(
+            identity.attributes.custom_claims.keys().collect::<Vec<_>>(),
+            vec!["org", "roles"]
+        );
+
+        // Expired
+        validate_custom_jwt(&token, &provider, expiry + Duration::from_secs(1)).unwrap_err();
+        // Wrong audience
+        let other_audience = self::token("other-app", expiry, claims.clone())?;
+        validate_custom_jwt(&other_audience, &provider, now).unwrap_err();
+        // Missing a required claim
+        let mut without_role = claims;
+        without_role.remove("roles");
+        let without_role = self::token("my-app", expiry, without_role)?;
+        validate_custom_jwt(&without_role, &provider, now).unwrap_err();
+        // Signed with another secret
+        let other_secret = CustomJwtProvider {
+            key: CustomJwtKey::Secret("another-secret".to_string()),
+            ..provider
+        };
+        validate_custom_jwt(&token, &other_secret, now).unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_custom_jwt_providers() {
+        let with_rsa_secret = CustomJwtProvider {
+            algorithm: "RS256".to_string(),
+            ..provider()
+        };
+        validate_custom_jwt_provider(&with_rsa_secret).unwrap_err();
+        let with_unknown_field = CustomJwtProvider {
+            claim_mapping: BTreeMap::from([("favoriteColor".to_string(), "color".to_string())]),
+            ..provider()
+        };
+        validate_custom_jwt_provider(&with_unknown_field).unwrap_err();
+        let with_invalid_claim = CustomJwtProvider {
+            required_claims: BTreeMap::from([("roles".to_string(), "member".to_string())]),
+            ..provider()
+        };
+        validate_custom_jwt_provider(&with_invalid_claim).unwrap_err();
+    }
+
+    #[test]
+    fn test_providers_sharing_an_issuer() -> anyhow::Result<()> {
+        let expiry = SystemTime::now() + Duration::from_secs(60);
+        let mobile = CustomJwtProvider {
+            audience: Some("mobile-app".to_string()),
+            ..provider()
+        };
+        let any_audience = CustomJwtProvider {
+            audience: None,
+            ..provider()
+        };
+        let providers = [any_audience.clone(), provider(), mobile.clone()];
+        let token = |audience| self::token(audience, expiry, Claims::new());
+        assert_eq!(
+            custom_jwt_provider_for(&token("mobile-app")?, &providers),
+            Some(&mobile)
+        );
+        assert_eq!(
+            custom_jwt_provider_for(&token("my-app")?, &providers),
+            Some(&provider())
+        );
+        assert_eq!(
+            custom_jwt_provider_for(&token("other-app")?, &providers),
+            Some(&any_audience)
+        );
+        assert_eq!(
+            custom_jwt_provider_for(&token("other-app")?, &providers[1..]),
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_claim_mapping() -> anyhow::Result<()> {
+        let custom_claims = BTreeMap::from([
+            ("oid".to_string(), "\"user-1\"".to_string()),
+            ("upn".to_string(), "\"user@example.com\"".to_string()),
+            ("verified".to_string(), "true".to_string()),
+            ("org".to_string(), "\"acme\"".to_string()),
+        ]);
+        let mut identity = UserIdentity::from_verified_token(
+            "token".to_string(),
+            SystemTime::now(),
+            UserIdentityAttributes {
+                issuer: Some("https://login.example.com".to_string()),
+                subject: Some("pairwise-id".to_string()),
+                custom_claims,
+                ..Default::default()
+            },
+        )?;
+        let claim_mapping = BTreeMap::from([
+            ("subject".to_string(), "oid".to_string()),
+            ("email".to_string(), "upn".to_string()),
+            ("emailVerified".to_string(), "verified".to_string()),
+        ]);
+        apply_claim_mapping(&mut identity, &claim_mapping)?;
+        assert_eq!(identity.subject, "user-1");
+        assert_eq!(
+            &*identity.attributes.token_identifier,
+            "https://login.example.com|user-1"
+        );
+        assert_eq!(
+            identity.attributes.email.as_deref(),
+            Some("user@example.com")
+        );
+        assert_eq!(identity.attributes.email_verified, Some(true));
+        assert_eq!(
+            identity.attributes.custom_claims.keys().collect::<Vec<_>>(),
+            vec!["org"]
+        );
+
+        // The subject's claim is missing.
+        apply_claim_mapping(&mut identity, &claim_mapping).unwrap_err();
+        Ok(())
+    }
+}
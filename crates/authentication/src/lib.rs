@@ -37,7 +37,6 @@ use openidconnect::{
     },
     ClaimsVerificationError,
     ClientId,
-    DiscoveryError,
 };
 use serde::{
     Deserialize,
@@ -46,9 +45,18 @@ use serde::{
 use sync_types::AuthenticationToken;
 use url::Url;
 
+use crate::provider_metadata_cache::PROVIDER_METADATA_CACHE;
+
 pub mod access_token_auth;
+pub mod admin_key_audit;
+pub mod anonymous;
 pub mod application_auth;
+pub mod custom_jwt;
 pub mod metrics;
+mod provider_metadata_cache;
+pub mod saml;
+pub mod service_account;
+pub mod token_exchange;
 
 /// Issuer for API access tokens
 pub static CONVEX_AUTH_URL: LazyLock<Url> =
@@ -95,17 +103,38 @@ pub fn token_to_authorization_header(token: AuthenticationToken) -> anyhow::Resu
     }
 }
 
+/// The `<issuer>|<subject>` token identifier a JWT claims, without validating
+/// it. Only for things like counting failed attempts against a user, since
+/// anyone can claim any identifier.
+pub fn unverified_token_identifier(token: &str) -> Option<String> {
+    let claims = JWT::<biscuit::Empty, biscuit::Empty>::new_encoded(token)
+        .unverified_payload()
+        .ok()?;
+    let issuer = claims.registered.issuer?;
+    let subject = claims.registered.subject?;
+    Some(format!("{issuer}|{subject}"))
+}
+
+/// Short codes of the errors for user tokens whose signature was verified but
+/// that were rejected anyway, like because they expired or were revoked. Unlike
+/// other failures, these can be attributed to the user the token is for.
+pub const VERIFIED_TOKEN_ERROR_CODES: &[&str] = &[
+    "InvalidCustomJwtClaims",
+    "CustomJwtClaimMismatch",
+    "TokenRevoked",
+];
+
 /// Validate an OpenID Connect ID token.
 pub async fn validate_id_token<F, E>(
     token_str: Auth0IdToken,
     // The http client is injected here so we can unit test this filter without needing to actually
     // serve an HTTP response from an identity provider.
-    http_client: impl Fn(HttpRequest) -> F + 'static,
+    http_client: impl Fn(HttpRequest) -> F + Clone + Send + Sync + 'static,
     auth_infos: Vec<AuthInfo>,
     system_time: SystemTime,
 ) -> anyhow::Result<UserIdentity>
 where
-    F: Future<Output = Result<HttpResponse, E>>,
+    F: Future<Output = Result<HttpResponse, E>> + Send + 'static,
     E: std::error::Error + 'static + Send + Sync,
 {
     let token = CoreIdToken::from_str(&token_str.0).context(ErrorMetadata::unauthenticated(
@@ -147,46 +176,77 @@ where
             "NoAuthProvider",
             "No auth provider found matching the given token",
         ))?;
+    let issuer = issuer.clone();
     // Use the OpenID Connect Discovery protocol to get the public keys for this
     // provider.
-    // TODO(CX-606): Add an caching layer that respects the HTTP cache headers
-    // in the response.
-    let metadata = CoreProviderMetadata::discover_async(issuer.clone(), http_client)
-        .await
-        .map_err(|e| {
-            let short = "AuthProviderDiscoveryFailed";
-            let long = format!("Auth provider discovery of {} failed", issuer.as_str());
-            match e {
-                DiscoveryError::Response(code, body, _) => {
-                    let long = format!("{long}: {} {}", code, String::from_utf8_lossy(&body));
-                    let Ok(code) = http::StatusCode::from_u16(code.as_u16()) else {
-                        return ErrorMetadata::bad_request(short, long);
-                    };
-                    if let Some(em) =
-                        ErrorMetadata::from_http_status_code(code, short, long.clone())
-                    {
-                        em
-                    } else {
-                        ErrorMetadata::bad_request(short, long)
-                    }
-                },
-                e => {
-                    tracing::error!(
-                        "Error discovering auth provider: {}, {}",
-                        issuer.as_str(),
-                        e
-                    );
-                    ErrorMetadata::bad_request(short, long)
-                },
-            }
-        })?;
+    let cached = PROVIDER_METADATA_CACHE
+        .get(&issuer, http_client.clone())
+        .await?;
+    let mut identity = match verify_id_token(
+        token.clone(),
+        &auth_info.application_id,
+        &cached.metadata,
+        system_time,
+    ) {
+        Ok(identity) => Ok(identity),
+        // The provider may have rotated its keys since we fetched them.
+        Err(e) => match PROVIDER_METADATA_CACHE
+            .refetch(&issuer, http_client, &cached)
+            .await?
+        {
+            Some(refetched) => verify_id_token(
+                token,
+                &auth_info.application_id,
+                &refetched.metadata,
+                system_time,
+            ),
+            None => Err(e),
+        },
+    }?;
+    custom_jwt::apply_claim_mapping(&mut identity, &auth_info.claim_mapping)?;
+    Ok(identity)
+}
+
+/// Checks the providers in a deployment's `auth.config.js`. Several can share
+/// a domain, like while an app migrates between identity systems, as long as
+/// their application IDs differ, so every token has one provider.
+pub fn validate_auth_infos(auth_infos: &[AuthInfo]) -> anyhow::Result<()> {
+    for (i, auth_info) in auth_infos.iter().enumerate() {
+        custom_jwt::validate_claim_mapping(&auth_info.claim_mapping, "InvalidAuthConfig")?;
+        let duplicate = auth_infos[..i].iter().any(|other| {
+            other != auth_info
+                && other.application_id == auth_info.application_id
+                && other.domain.trim_end_matches('/') == auth_info.domain.trim_end_matches('/')
+        });
+        anyhow::ensure!(
+            !duplicate,
+            ErrorMetadata::bad_request(
+                "DuplicateAuthProvider",
+                format!(
+                    "There are several providers for the domain {} and application ID {}. They \
+                     must differ in one of them.",
+                    auth_info.domain.as_str(),
+                    auth_info.application_id
+                ),
+            )
+        );
+    }
+    Ok(())
+}
+
+fn verify_id_token(
+    token: CoreIdToken,
+    application_id: &str,
+    metadata: &CoreProviderMetadata,
+    system_time: SystemTime,
+) -> anyhow::Result<UserIdentity> {
     // Create a verifier for the provider using this metadata. Set the verifier
     // to enforce that the issuer and audience match.
     // Note for posterity: this verifier will reject tokens containing multiple
     // audiences. It's very uncommon for an identity provider to create a token with
     // multiple valid audiences, so we don't handle that case yet.
     let verifier = CoreIdTokenVerifier::new_public_client(
-        ClientId::new(auth_info.application_id),
+        ClientId::new(application_id.to_string()),
         metadata.issuer().clone(),
         metadata.jwks().clone(),
     )
@@ -390,8 +450,16 @@ where
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::BTreeMap,
         convert::Infallible,
         pin::Pin,
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
         time::SystemTime,
     };
 
@@ -399,7 +467,10 @@ mod tests {
         Duration,
         Utc,
     };
-    use common::auth::AuthInfo;
+    use common::{
+        auth::AuthInfo,
+        knobs::OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL,
+    };
     use futures::{
         Future,
         FutureExt,
@@ -439,6 +510,7 @@ mod tests {
         TokenUrl,
         UserInfoUrl,
     };
+    use parking_lot::Mutex;
     use serde::{
         Deserialize,
         Serialize,
@@ -446,6 +518,7 @@ mod tests {
 
     use crate::{
         validate_access_token,
+        validate_auth_infos,
         validate_id_token,
         Auth0AccessToken,
         Auth0IdToken,
@@ -453,11 +526,12 @@ mod tests {
         CONVEX_CONSOLE_API_AUDIENCE,
     };
 
+    type FakeHttpResponse = Pin<Box<dyn Future<Output = Result<HttpResponse, Infallible>> + Send>>;
+
     fn fake_http_client(
         metadata: String,
         jwks: String,
-    ) -> impl Fn(HttpRequest) -> Pin<Box<dyn Future<Output = Result<HttpResponse, Infallible>>>>
-    {
+    ) -> impl Fn(HttpRequest) -> FakeHttpResponse + Clone {
         move |request: HttpRequest| {
             let metadata_ = metadata.clone();
             let jwks_ = jwks.clone();
@@ -478,7 +552,7 @@ mod tests {
                     panic!("unexpected request path {:?}", request.url);
                 }
             }
-            .boxed_local()
+            .boxed()
         }
     }
 
@@ -554,6 +628,7 @@ mod tests {
             vec![AuthInfo {
                 application_id: (*audience).clone(),
                 domain: issuer_url,
+                claim_mapping: BTreeMap::new(),
             }],
             SystemTime::now(),
         )
@@ -562,6 +637,86 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_id_token_key_rotation() -> anyhow::Result<()> {
+        // The metadata cache is shared by all tests, so use an issuer of our own.
+        let issuer_url = IssuerUrl::new("https://rotation.example.com".to_string())?;
+        let audience = Audience::new("client-id-123".to_string());
+        let provider_metadata = serde_json::to_string(&CoreProviderMetadata::new(
+            issuer_url.clone(),
+            None,
+            JsonWebKeySetUrl::new(
+                "https://rotation.example.com/.well-known/jwks.json".to_string(),
+            )?,
+            vec![ResponseTypes::new(vec![CoreResponseType::IdToken])],
+            vec![CoreSubjectIdentifierType::Public],
+            vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+            EmptyAdditionalProviderMetadata {},
+        ))?;
+        let old_jwks = serde_json::to_string(&CoreJsonWebKeySet::new(vec![]))?;
+        let new_jwks = serde_json::to_string(&CoreJsonWebKeySet::new(vec![
+            TEST_SIGNING_KEY.as_verification_key()
+        ]))?;
+        let id_token = CoreIdToken::new(
+            CoreIdTokenClaims::new(
+                issuer_url.clone(),
+                vec![audience.clone()],
+                Utc::now() + Duration::seconds(120),
+                Utc::now(),
+                StandardClaims::new(SubjectIdentifier::new("1234-abcd".to_string())),
+                EmptyAdditionalClaims {},
+            ),
+            &*TEST_SIGNING_KEY,
+            CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+            None,
+            None,
+        )?
+        .to_string();
+
+        let jwks = Arc::new(Mutex::new(old_jwks));
+        let discoveries = Arc::new(AtomicUsize::new(0));
+        let http_client = {
+            let jwks = jwks.clone();
+            let discoveries = discoveries.clone();
+            move |request: HttpRequest| {
+                if request.url.path().ends_with("openid-configuration") {
+                    discoveries.fetch_add(1, Ordering::SeqCst);
+                }
+                fake_http_client(provider_metadata.clone(), jwks.lock().clone())(request)
+            }
+        };
+        let validate = || {
+            validate_id_token(
+                Auth0IdToken(id_token.clone()),
+                http_client.clone(),
+                vec![AuthInfo {
+                    application_id: (*audience).clone(),
+                    domain: issuer_url.clone(),
+                    claim_mapping: BTreeMap::new(),
+                }],
+                SystemTime::now(),
+            )
+        };
+
+        // The token's key isn't published yet.
+        validate().await.unwrap_err();
+        assert_eq!(discoveries.load(Ordering::SeqCst), 1);
+
+        // After the provider rotates its keys, the cached keys are only
+        // refetched once the minimum refetch interval has passed.
+        *jwks.lock() = new_jwks;
+        validate().await.unwrap_err();
+        assert_eq!(discoveries.load(Ordering::SeqCst), 1);
+        tokio::time::advance(*OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL).await;
+        validate().await?;
+        assert_eq!(discoveries.load(Ordering::SeqCst), 2);
+
+        // Later tokens are verified with the cached keys.
+        validate().await?;
+        assert_eq!(discoveries.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_access_token_auth() -> anyhow::Result<()> {
         let issuer_url = IssuerUrl::from_url(CONVEX_AUTH_URL.clone());
@@ -635,4 +790,34 @@ mod tests {
         .unwrap_err();
         Ok(())
     }
+
+    #[test]
+    fn test_validate_auth_infos() -> anyhow::Result<()> {
+        let auth_info = |application_id: &str, domain: &str| -> anyhow::Result<AuthInfo> {
+            Ok(AuthInfo {
+                application_id: application_id.to_string(),
+                domain: IssuerUrl::new(domain.to_string())?,
+                claim_mapping: BTreeMap::new(),
+            })
+        };
+        // Apps migrating between identity systems can have several providers for a
+        // domain.
+        validate_auth_infos(&[
+            auth_info("web", "https://auth.example.com")?,
+            auth_info("mobile", "https://auth.example.com")?,
+            auth_info("web", "https://login.example.com")?,
+        ])?;
+        let remapped = AuthInfo {
+            claim_mapping: BTreeMap::from([("email".to_string(), "upn".to_string())]),
+            ..auth_info("web", "https://auth.example.com/")?
+        };
+        validate_auth_infos(&[auth_info("web", "https://auth.example.com")?, remapped])
+            .unwrap_err();
+        let unknown_field = AuthInfo {
+            claim_mapping: BTreeMap::from([("favoriteColor".to_string(), "color".to_string())]),
+            ..auth_info("web", "https://auth.example.com")?
+        };
+        validate_auth_infos(&[unknown_field]).unwrap_err();
+        Ok(())
+    }
 }
@@ -0,0 +1,187 @@
+//! A cache of OpenID Connect provider metadata, including each provider's
+//! JWKS, so ID tokens can be verified without a round trip to the identity
+//! provider on every request.
+//!
+//! Entries older than `OIDC_PROVIDER_METADATA_REFRESH_INTERVAL` are refreshed
+//! in the background while the cached copy keeps being used. If refreshes keep
+//! failing, entries are used for up to `OIDC_PROVIDER_METADATA_MAX_AGE` before
+//! requests have to wait for a fetch. When a token fails verification against
+//! the cached keys, e.g. because the provider rotated its signing key, the
+//! metadata is refetched right away, at most once every
+//! `OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL` per provider.
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    sync::LazyLock,
+};
+
+use common::{
+    knobs::{
+        OIDC_PROVIDER_METADATA_MAX_AGE,
+        OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL,
+        OIDC_PROVIDER_METADATA_REFRESH_INTERVAL,
+    },
+    runtime::tokio_spawn,
+};
+use errors::ErrorMetadata;
+use futures::Future;
+use oauth2::{
+    HttpRequest,
+    HttpResponse,
+};
+use openidconnect::{
+    core::CoreProviderMetadata,
+    DiscoveryError,
+    IssuerUrl,
+};
+use parking_lot::Mutex;
+use tokio::time::Instant;
+
+pub(crate) static PROVIDER_METADATA_CACHE: LazyLock<ProviderMetadataCache> =
+    LazyLock::new(ProviderMetadataCache::default);
+
+#[derive(Clone)]
+pub(crate) struct CachedMetadata {
+    pub metadata: CoreProviderMetadata,
+    pub fetched_at: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct ProviderMetadataCache {
+    entries: Mutex<BTreeMap<String, Entry>>,
+}
+
+struct Entry {
+    cached: CachedMetadata,
+    refreshing: bool,
+}
+
+impl ProviderMetadataCache {
+    pub async fn get<C, F, E>(
+        &'static self,
+        issuer: &IssuerUrl,
+        http_client: C,
+    ) -> anyhow::Result<CachedMetadata>
+    where
+        C: Fn(HttpRequest) -> F + Clone + Send + Sync + 'static,
+        F: Future<Output = Result<HttpResponse, E>> + Send + 'static,
+        E: Error + Send + Sync + 'static,
+    {
+        let now = Instant::now();
+        let (cached, refresh) = {
+            let mut entries = self.entries.lock();
+            match entries.get_mut(issuer.as_str()) {
+                Some(entry) if now - entry.cached.fetched_at < *OIDC_PROVIDER_METADATA_MAX_AGE => {
+                    let refresh = !entry.refreshing
+                        && now - entry.cached.fetched_at
+                            >= *OIDC_PROVIDER_METADATA_REFRESH_INTERVAL;
+                    entry.refreshing |= refresh;
+                    (Some(entry.cached.clone()), refresh)
+                },
+                _ => (None, false),
+            }
+        };
+        let Some(cached) = cached else {
+            return self.fetch(issuer, http_client).await;
+        };
+        if refresh {
+            let issuer = issuer.clone();
+            tokio_spawn("oidc_provider_metadata_refresh", async move {
+                if let Err(e) = self.fetch(&issuer, http_client).await {
+                    tracing::warn!(
+                        "Failed to refresh auth provider metadata for {}: {e:#}",
+                        issuer.as_str()
+                    );
+                }
+            });
+        }
+        Ok(cached)
+    }
+
+    /// Called when a token didn't verify against `stale`. Returns newer
+    /// metadata to try again with, or `None` if `stale` was fetched too
+    /// recently to fetch it again.
+    pub async fn refetch<C, F, E>(
+        &'static self,
+        issuer: &IssuerUrl,
+        http_client: C,
+        stale: &CachedMetadata,
+    ) -> anyhow::Result<Option<CachedMetadata>>
+    where
+        C: Fn(HttpRequest) -> F + Clone + Send + Sync + 'static,
+        F: Future<Output = Result<HttpResponse, E>> + Send + 'static,
+        E: Error + Send + Sync + 'static,
+    {
+        // Another request may have refetched it already.
+        if let Some(entry) = self.entries.lock().get(issuer.as_str()) {
+            if entry.cached.fetched_at > stale.fetched_at {
+                return Ok(Some(entry.cached.clone()));
+            }
+        }
+        if stale.fetched_at.elapsed() < *OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL {
+            return Ok(None);
+        }
+        self.fetch(issuer, http_client).await.map(Some)
+    }
+
+    async fn fetch<C, F, E>(
+        &self,
+        issuer: &IssuerUrl,
+        http_client: C,
+    ) -> anyhow::Result<CachedMetadata>
+    where
+        C: Fn(HttpRequest) -> F,
+        F: Future<Output = Result<HttpResponse, E>>,
+        E: Error + Send + Sync + 'static,
+    {
+        let result = CoreProviderMetadata::discover_async(issuer.clone(), http_client).await;
+        let mut entries = self.entries.lock();
+        let metadata = match result {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                if let Some(entry) = entries.get_mut(issuer.as_str()) {
+                    entry.refreshing = false;
+                }
+                anyhow::bail!(discovery_error(issuer, e));
+            },
+        };
+        let cached = CachedMetadata {
+            metadata,
+            fetched_at: Instant::now(),
+        };
+        entries.insert(
+            issuer.as_str().to_string(),
+            Entry {
+                cached: cached.clone(),
+                refreshing: false,
+            },
+        );
+        Ok(cached)
+    }
+}
+
+fn discovery_error<E: Error>(issuer: &IssuerUrl, e: DiscoveryError<E>) -> ErrorMetadata {
+    let short = "AuthProviderDiscoveryFailed";
+    let long = format!("Auth provider discovery of {} failed", issuer.as_str());
+    match e {
+        DiscoveryError::Response(code, body, _) => {
+            let long = format!("{long}: {} {}", code, String::from_utf8_lossy(&body));
+            let Ok(code) = http::StatusCode::from_u16(code.as_u16()) else {
+                return ErrorMetadata::bad_request(short, long);
+            };
+            if let Some(em) = ErrorMetadata::from_http_status_code(code, short, long.clone()) {
+                em
+            } else {
+                ErrorMetadata::bad_request(short, long)
+            }
+        },
+        e => {
+            tracing::error!(
+                "Error discovering auth provider: {}, {}",
+                issuer.as_str(),
+                e
+            );
+            ErrorMetadata::bad_request(short, long)
+        },
+    }
+}
@@ -0,0 +1,1048 @@
+//! SAML 2.0 as an identity source, for enterprises that can't use OpenID
+//! Connect. The deployment acts as a service provider: identity providers
+//! post signed responses to its assertion consumer service, which verifies
+//! the assertion and exchanges it for a token the deployment signs. Those
+//! tokens are verified like a custom JWT provider's, with the identity
+//! provider's entity ID as their issuer.
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use anyhow::Context;
+use common::auth::{
+    CustomJwtKey,
+    CustomJwtProvider,
+    SamlProvider,
+};
+use errors::ErrorMetadata;
+use openssl::{
+    hash::{
+        hash,
+        MessageDigest,
+    },
+    sign::Verifier,
+    x509::X509,
+};
+use quick_xml::{
+    escape::{
+        escape,
+        unescape,
+    },
+    events::{
+        BytesStart,
+        Event,
+    },
+    Reader,
+};
+use serde_json::Value as JsonValue;
+
+use crate::custom_jwt::{
+    issue_custom_jwt,
+    MAPPABLE_IDENTITY_FIELDS,
+};
+
+/// Claim that tokens exchanged for SAML assertions have set to `true`.
+pub const SAML_CLAIM: &str = "saml";
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXCLUSIVE_C14N_NS: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER_CONFIRMATION: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const EXCLUSIVE_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+/// How far the identity provider's clock may be from ours.
+const CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+/// The longest an assertion may stay valid for. Used assertions are
+/// remembered until they expire, so this bounds how long that is. Identity
+/// providers usually allow a few minutes.
+const MAX_ASSERTION_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A verified assertion from a SAML response.
+#[derive(Debug)]
+pub struct SamlAssertion {
+    pub id: String,
+    /// The identity provider's entity ID.
+    pub issuer: String,
+    /// The subject's `NameID`.
+    pub name_id: String,
+    /// Attribute name -> its values.
+    pub attributes: BTreeMap<String, Vec<String>>,
+    /// When the assertion stops being valid. It can't be used again before
+    /// then.
+    pub not_on_or_after: SystemTime,
+    /// When the identity provider wants the user's session to end, if it
+    /// says.
+    pub session_not_on_or_after: Option<SystemTime>,
+}
+
+/// Checks that `provider`'s certificate can be parsed and its attribute
+/// mapping is well-formed.
+pub fn validate_saml_provider(provider: &SamlProvider) -> anyhow::Result<()> {
+    let invalid = |msg: String| ErrorMetadata::bad_request("InvalidSamlProvider", msg);
+    anyhow::ensure!(
+        !provider.entity_id.is_empty(),
+        invalid("The entity ID can't be empty".to_string())
+    );
+    X509::from_pem(provider.certificate.as_bytes()).context(invalid(
+        "The certificate must be a PEM-encoded X.509 certificate".to_string(),
+    ))?;
+    for field in provider.attribute_mapping.keys() {
+        anyhow::ensure!(
+            MAPPABLE_IDENTITY_FIELDS.contains(&field.as_str()),
+            invalid(format!(
+                "Attributes can't be mapped onto `{field}`. The fields are: {}",
+                MAPPABLE_IDENTITY_FIELDS.join(", ")
+            ))
+        );
+    }
+    Ok(())
+}
+
+/// Metadata describing the deployment as a service provider, for configuring
+/// identity providers. Assertions must be signed and are posted to
+/// `acs_url`.
+pub fn service_provider_metadata(entity_id: &str, acs_url: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{PROTOCOL_NS}">
+    <md:NameIDFormat>urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="{}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+        escape(entity_id),
+        escape(acs_url),
+    )
+}
+
+/// Verifies a base64-encoded `SAMLResponse` posted to the assertion consumer
+/// service at `acs_url`, returning its assertion and the provider that
+/// issued it. The assertion itself must be signed, and the response may only
+/// have one. It must be restricted to `sp_entity_id` and expire within
+/// `MAX_ASSERTION_LIFETIME`.
+pub fn validate_saml_response<'a>(
+    response: &str,
+    providers: &'a [SamlProvider],
+    sp_entity_id: &str,
+    acs_url: &str,
+    system_time: SystemTime,
+) -> anyhow::Result<(&'a SamlProvider, SamlAssertion)> {
+    let invalid =
+        |msg: &str| ErrorMetadata::unauthenticated("InvalidSamlResponse", msg.to_string());
+    let response: String = response.split_whitespace().collect();
+    let response = base64::decode(response).context(invalid("The response isn't base64"))?;
+    let response = String::from_utf8(response).context(invalid("The response isn't UTF-8"))?;
+    let response = parse_xml(&response).context(invalid("The response isn't valid XML"))?;
+    anyhow::ensure!(
+        response.is(PROTOCOL_NS, "Response"),
+        invalid("Expected a SAML `Response`")
+    );
+    if let Some(destination) = response.attribute("Destination") {
+        anyhow::ensure!(
+            destination == acs_url,
+            invalid("The response is for another destination")
+        );
+    }
+    let status = response
+        .child(PROTOCOL_NS, "Status")
+        .and_then(|status| status.child(PROTOCOL_NS, "StatusCode"))
+        .and_then(|code| code.attribute("Value"))
+        .context(invalid("The response has no status"))?;
+    anyhow::ensure!(
+        status == STATUS_SUCCESS,
+        ErrorMetadata::unauthenticated(
+            "SamlSignInFailed",
+            format!("The identity provider responded with {status}"),
+        )
+    );
+    anyhow::ensure!(
+        response.child(ASSERTION_NS, "EncryptedAssertion").is_none(),
+        invalid("Encrypted assertions aren't supported")
+    );
+    let mut assertions = response.children(ASSERTION_NS, "Assertion");
+    let assertion = assertions
+        .next()
+        .context(invalid("The response has no assertion"))?;
+    anyhow::ensure!(
+        assertions.next().is_none(),
+        invalid("The response must have exactly one assertion")
+    );
+
+    let issuer = assertion
+        .child(ASSERTION_NS, "Issuer")
+        .map(Element::text)
+        .context(invalid("The assertion has no issuer"))?;
+    let provider = providers
+        .iter()
+        .find(|provider| provider.entity_id == issuer)
+        .context(ErrorMetadata::unauthenticated(
+            "UnknownSamlIssuer",
+            format!("{issuer} isn't a SAML identity provider of this deployment"),
+        ))?;
+    let certificate = X509::from_pem(provider.certificate.as_bytes())?;
+    verify_signature(assertion, &certificate)?;
+
+    let id = assertion
+        .attribute("ID")
+        .context(invalid("The assertion has no ID"))?
+        .to_string();
+    let conditions = assertion
+        .child(ASSERTION_NS, "Conditions")
+        .context(invalid("The assertion has no conditions"))?;
+    if let Some(not_before) = conditions.attribute("NotBefore") {
+        anyhow::ensure!(
+            parse_time(not_before)? <= system_time + CLOCK_SKEW,
+            invalid("The assertion isn't valid yet")
+        );
+    }
+    let not_on_or_after = conditions.attribute("NotOnOrAfter").context(invalid(
+        "The assertion's conditions must have `NotOnOrAfter`",
+    ))?;
+    let mut not_on_or_after = parse_time(not_on_or_after)?;
+    // Without an audience restriction, an assertion issued for any service
+    // provider that trusts the same identity provider would be accepted.
+    let mut restrictions = conditions
+        .children(ASSERTION_NS, "AudienceRestriction")
+        .peekable();
+    anyhow::ensure!(
+        restrictions.peek().is_some(),
+        invalid("The assertion isn't restricted to this service provider")
+    );
+    for restriction in restrictions {
+        anyhow::ensure!(
+            restriction
+                .children(ASSERTION_NS, "Audience")
+                .any(|audience| audience.text() == sp_entity_id),
+            invalid("The assertion is for another service provider")
+        );
+    }
+
+    let subject = assertion
+        .child(ASSERTION_NS, "Subject")
+        .context(invalid("The assertion has no subject"))?;
+    let name_id = subject
+        .child(ASSERTION_NS, "NameID")
+        .map(Element::text)
+        .filter(|name_id| !name_id.is_empty())
+        .context(invalid("The assertion's subject has no `NameID`"))?;
+    let mut confirmed = false;
+    for confirmation in subject.children(ASSERTION_NS, "SubjectConfirmation") {
+        if confirmation.attribute("Method") != Some(BEARER_CONFIRMATION) {
+            continue;
+        }
+        let Some(data) = confirmation.child(ASSERTION_NS, "SubjectConfirmationData") else {
+            continue;
+        };
+        if data
+            .attribute("Recipient")
+            .is_some_and(|recipient| recipient != acs_url)
+        {
+            continue;
+        }
+        let Some(expiration) = data.attribute("NotOnOrAfter") else {
+            continue;
+        };
+        let expiration = parse_time(expiration)?;
+        if system_time < expiration + CLOCK_SKEW {
+            confirmed = true;
+            not_on_or_after = not_on_or_after.min(expiration);
+        }
+    }
+    anyhow::ensure!(
+        confirmed,
+        invalid("The assertion's subject has no current bearer confirmation for this service")
+    );
+    anyhow::ensure!(
+        system_time < not_on_or_after + CLOCK_SKEW,
+        invalid("The assertion has expired")
+    );
+    anyhow::ensure!(
+        not_on_or_after <= system_time + MAX_ASSERTION_LIFETIME,
+        invalid("The assertion is valid for too long")
+    );
+
+    let session_not_on_or_after = assertion
+        .children(ASSERTION_NS, "AuthnStatement")
+        .filter_map(|statement| statement.attribute("SessionNotOnOrAfter"))
+        .map(parse_time)
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .min();
+    let mut attributes = BTreeMap::new();
+    for statement in assertion.children(ASSERTION_NS, "AttributeStatement") {
+        for attribute in statement.children(ASSERTION_NS, "Attribute") {
+            let Some(name) = attribute.attribute("Name") else {
+                continue;
+            };
+            attributes
+                .entry(name.to_string())
+                .or_insert_with(Vec::new)
+                .extend(
+                    attribute
+                        .children(ASSERTION_NS, "AttributeValue")
+                        .map(Element::text),
+                );
+        }
+    }
+    Ok((
+        provider,
+        SamlAssertion {
+            id,
+            issuer,
+            name_id,
+            attributes,
+            not_on_or_after: not_on_or_after + CLOCK_SKEW,
+            session_not_on_or_after,
+        },
+    ))
+}
+
+/// The provider that verifies the tokens exchanged for `provider`'s
+/// assertions, which are signed with `secret`.
+pub fn saml_jwt_provider(provider: &SamlProvider, secret: String) -> CustomJwtProvider {
+    CustomJwtProvider {
+        issuer: provider.entity_id.clone(),
+        audience: None,
+        algorithm: "HS256".to_string(),
+        key: CustomJwtKey::Secret(secret),
+        required_claims: BTreeMap::from([(SAML_CLAIM.to_string(), "true".to_string())]),
+        // Mapped attributes are stored under the field's name.
+        claim_mapping: provider
+            .attribute_mapping
+            .keys()
+            .filter(|field| *field != "subject")
+            .map(|field| (field.clone(), field.clone()))
+            .collect(),
+    }
+}
+
+/// Issues a token for the identity in `assertion`, valid until `expiration`,
+/// that `jwt_provider` accepts. Attributes that aren't mapped onto a user
+/// identity field are passed through as custom claims.
+pub fn issue_saml_token(
+    provider: &SamlProvider,
+    jwt_provider: &CustomJwtProvider,
+    assertion: SamlAssertion,
+    system_time: SystemTime,
+    expiration: SystemTime,
+) -> anyhow::Result<String> {
+    let mut claims = BTreeMap::new();
+    let mut subject = assertion.name_id;
+    for (field, attribute) in &provider.attribute_mapping {
+        let Some(value) = assertion
+            .attributes
+            .get(attribute)
+            .and_then(|values| values.first())
+        else {
+            continue;
+        };
+        match field.as_str() {
+            "subject" => subject = value.clone(),
+            "emailVerified" => {
+                claims.insert(field.clone(), JsonValue::Bool(value == "true"));
+            },
+            _ => {
+                claims.insert(field.clone(), JsonValue::String(value.clone()));
+            },
+        }
+    }
+    let mapped: BTreeSet<_> = provider.attribute_mapping.values().collect();
+    for (attribute, mut values) in assertion.attributes {
+        if mapped.contains(&attribute) || claims.contains_key(&attribute) {
+            continue;
+        }
+        let value = if values.len() == 1 {
+            JsonValue::String(values.remove(0))
+        } else {
+            JsonValue::Array(values.into_iter().map(JsonValue::String).collect())
+        };
+        claims.entry(attribute).or_insert(value);
+    }
+    claims.insert(SAML_CLAIM.to_string(), JsonValue::Bool(true));
+    // Registered claims are set from the assertion.
+    for claim in ["iss", "sub", "aud", "exp", "nbf", "iat", "jti"] {
+        claims.remove(claim);
+    }
+    issue_custom_jwt(jwt_provider, subject, claims, system_time, expiration)
+}
+
+fn parse_time(time: &str) -> anyhow::Result<SystemTime> {
+    let time = chrono::DateTime::parse_from_rfc3339(time).context(
+        ErrorMetadata::unauthenticated("InvalidSamlResponse", format!("{time} isn't a valid time")),
+    )?;
+    Ok(time.into())
+}
+
+/// Verifies `element`'s enveloped XML signature with `certificate`. The
+/// signature must be its child and reference the element itself, so what's
+/// verified is what's read.
+fn verify_signature(element: &Element, certificate: &X509) -> anyhow::Result<()> {
+    let invalid =
+        |msg: &str| ErrorMetadata::unauthenticated("InvalidSamlSignature", msg.to_string());
+    let signature = element
+        .child(DSIG_NS, "Signature")
+        .context(invalid("The assertion must be signed"))?;
+    let signed_info = signature
+        .child(DSIG_NS, "SignedInfo")
+        .context(invalid("The signature has no `SignedInfo`"))?;
+    let (canonicalization, signed_info_prefixes) = signed_info
+        .child(DSIG_NS, "CanonicalizationMethod")
+        .map(|method| (method.attribute("Algorithm"), inclusive_prefixes(method)))
+        .context(invalid("The signature has no canonicalization method"))?;
+    anyhow::ensure!(
+        canonicalization == Some(EXCLUSIVE_C14N),
+        invalid("Only exclusive canonicalization is supported")
+    );
+    let signature_digest = signed_info
+        .child(DSIG_NS, "SignatureMethod")
+        .and_then(|method| method.attribute("Algorithm"))
+        .and_then(|algorithm| match algorithm {
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(MessageDigest::sha256()),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(MessageDigest::sha512()),
+            _ => None,
+        })
+        .context(invalid(
+            "The signature must use RSA with SHA-256 or SHA-512",
+        ))?;
+
+    let mut references = signed_info.children(DSIG_NS, "Reference");
+    let reference = references
+        .next()
+        .context(invalid("The signature has no reference"))?;
+    anyhow::ensure!(
+        references.next().is_none(),
+        invalid("The signature must have exactly one reference")
+    );
+    let id = element
+        .attribute("ID")
+        .context(invalid("The signed element has no ID"))?;
+    anyhow::ensure!(
+        reference.attribute("URI") == Some(format!("#{id}").as_str()),
+        invalid("The signature doesn't reference the assertion")
+    );
+    let mut prefixes = vec![];
+    if let Some(transforms) = reference.child(DSIG_NS, "Transforms") {
+        for transform in transforms.children(DSIG_NS, "Transform") {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED_SIGNATURE) => {},
+                Some(EXCLUSIVE_C14N) => prefixes = inclusive_prefixes(transform),
+                _ => anyhow::bail!(invalid("The signature uses an unsupported transform")),
+            }
+        }
+    }
+    let digest_method = reference
+        .child(DSIG_NS, "DigestMethod")
+        .and_then(|method| method.attribute("Algorithm"))
+        .and_then(|algorithm| match algorithm {
+            "http://www.w3.org/2001/04/xmlenc#sha256" => Some(MessageDigest::sha256()),
+            "http://www.w3.org/2001/04/xmlenc#sha512" => Some(MessageDigest::sha512()),
+            _ => None,
+        })
+        .context(invalid("The digest must use SHA-256 or SHA-512"))?;
+    let expected_digest = reference
+        .child(DSIG_NS, "DigestValue")
+        .map(|value| value.text().split_whitespace().collect::<String>())
+        .context(invalid("The reference has no digest"))?;
+    let digest = hash(
+        digest_method,
+        canonicalize(element, Some(signature), &prefixes).as_bytes(),
+    )?;
+    anyhow::ensure!(
+        base64::decode(expected_digest).ok().as_deref() == Some(&*digest),
+        invalid("The assertion doesn't match its signature's digest")
+    );
+
+    let signature_value = signature
+        .child(DSIG_NS, "SignatureValue")
+        .map(|value| value.text().split_whitespace().collect::<String>())
+        .context(invalid("The signature has no value"))?;
+    let signature_value =
+        base64::decode(signature_value).context(invalid("The signature value isn't base64"))?;
+    let public_key = certificate.public_key()?;
+    let mut verifier = Verifier::new(signature_digest, &public_key)?;
+    verifier.update(canonicalize(signed_info, None, &signed_info_prefixes).as_bytes())?;
+    anyhow::ensure!(
+        verifier.verify(&signature_value).unwrap_or(false),
+        invalid("The signature could not be verified with the identity provider's certificate")
+    );
+    Ok(())
+}
+
+/// The `PrefixList` of a canonicalization's `InclusiveNamespaces`.
+fn inclusive_prefixes(method: &Element) -> Vec<String> {
+    method
+        .child(EXCLUSIVE_C14N_NS, "InclusiveNamespaces")
+        .and_then(|namespaces| namespaces.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// An XML element, parsed just far enough to read SAML responses and
+/// canonicalize their signed parts.
+#[derive(Debug)]
+struct Element {
+    /// Qualified name, like `saml:Assertion`.
+    name: String,
+    /// Qualified name -> value of the attributes other than namespace
+    /// declarations, in document order.
+    attributes: Vec<(String, String)>,
+    /// Prefix, or `""` for the default namespace, -> URI of the namespaces in
+    /// scope.
+    namespaces: BTreeMap<String, String>,
+    children: Vec<XmlNode>,
+}
+
+#[derive(Debug)]
+enum XmlNode {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn namespace(&self) -> &str {
+        self.namespaces
+            .get(qname_prefix(&self.name))
+            .map(String::as_str)
+            .unwrap_or("")
+    }
+
+    fn is(&self, namespace: &str, local_name: &str) -> bool {
+        self.namespace() == namespace && qname_local_name(&self.name) == local_name
+    }
+
+    fn children<'a: 'b, 'b>(
+        &'a self,
+        namespace: &'b str,
+        local_name: &'b str,
+    ) -> impl Iterator<Item = &'a Element> + 'b {
+        self.children.iter().filter_map(move |child| match child {
+            XmlNode::Element(element) if element.is(namespace, local_name) => Some(element),
+            _ => None,
+        })
+    }
+
+    fn child(&self, namespace: &str, local_name: &str) -> Option<&Element> {
+        self.children(namespace, local_name).next()
+    }
+
+    /// The value of an unqualified attribute.
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The element's text, without surrounding whitespace.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            if let XmlNode::Text(t) = child {
+                text.push_str(t);
+            }
+        }
+        text.trim().to_string()
+    }
+}
+
+fn qname_prefix(name: &str) -> &str {
+    name.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn qname_local_name(name: &str) -> &str {
+    name.split_once(':')
+        .map_or(name, |(_, local_name)| local_name)
+}
+
+/// Parses a document into its root element. Comments and processing
+/// instructions are dropped, and documents with a DTD are rejected.
+fn parse_xml(xml: &str) -> anyhow::Result<Element> {
+    // XML processors normalize line endings before parsing.
+    let xml = xml.replace("\r\n", "\n").replace('\r', "\n");
+    let mut reader = Reader::from_str(&xml);
+    let mut stack: Vec<Element> = vec![];
+    let mut root = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => {
+                let parent_namespaces = stack.last().map(|parent| &parent.namespaces);
+                stack.push(start_element(&start, parent_namespaces)?);
+            },
+            Event::Empty(start) => {
+                let parent_namespaces = stack.last().map(|parent| &parent.namespaces);
+                let element = start_element(&start, parent_namespaces)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(XmlNode::Element(element)),
+                    None => anyhow::ensure!(root.replace(element).is_none(), "Multiple roots"),
+                }
+            },
+            Event::End(_) => {
+                let element = stack.pop().context("Unbalanced end tag")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(XmlNode::Element(element)),
+                    None => anyhow::ensure!(root.replace(element).is_none(), "Multiple roots"),
+                }
+            },
+            Event::Text(text) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent
+                        .children
+                        .push(XmlNode::Text(text.unescape()?.into_owned()));
+                }
+            },
+            Event::CData(data) => {
+                if let Some(parent) = stack.last_mut() {
+                    let text = String::from_utf8(data.into_inner().into_owned())?;
+                    parent.children.push(XmlNode::Text(text));
+                }
+            },
+            Event::DocType(_) => anyhow::bail!("Documents with a DTD aren't supported"),
+            Event::Decl(_) | Event::PI(_) | Event::Comment(_) => {},
+            Event::Eof => break,
+        }
+    }
+    anyhow::ensure!(stack.is_empty(), "Unclosed element");
+    root.context("No root element")
+}
+
+fn start_element(
+    start: &BytesStart,
+    parent_namespaces: Option<&BTreeMap<String, String>>,
+) -> anyhow::Result<Element> {
+    let mut namespaces = parent_namespaces.cloned().unwrap_or_default();
+    let mut attributes = vec![];
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        let name = std::str::from_utf8(attribute.key.as_ref())?.to_string();
+        // Attribute values have their whitespace normalized, except for
+        // character references.
+        let raw_value = std::str::from_utf8(&attribute.value)?.replace(['\t', '\n'], " ");
+        let value = unescape(&raw_value)?.into_owned();
+        if name == "xmlns" {
+            namespaces.insert(String::new(), value);
+        } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+            namespaces.insert(prefix.to_string(), value);
+        } else {
+            attributes.push((name, value));
+        }
+    }
+    Ok(Element {
+        name: std::str::from_utf8(start.name().as_ref())?.to_string(),
+        attributes,
+        namespaces,
+        children: vec![],
+    })
+}
+
+/// Serializes `element` with Exclusive XML Canonicalization, without
+/// comments, leaving out `excluded`. Namespaces in `inclusive_prefixes` are
+/// rendered whenever they're in scope, like in inclusive canonicalization.
+fn canonicalize(
+    element: &Element,
+    excluded: Option<&Element>,
+    inclusive_prefixes: &[String],
+) -> String {
+    let mut out = String::new();
+    write_canonical(
+        &mut out,
+        element,
+        excluded,
+        inclusive_prefixes,
+        &BTreeMap::new(),
+    );
+    out
+}
+
+fn write_canonical<'a>(
+    out: &mut String,
+    element: &'a Element,
+    excluded: Option<&Element>,
+    inclusive_prefixes: &'a [String],
+    rendered: &BTreeMap<&'a str, &'a str>,
+) {
+    // Namespaces are rendered where they're visibly utilized, unless an
+    // output ancestor already rendered the same declaration.
+    let mut prefixes = BTreeSet::from([qname_prefix(&element.name)]);
+    for (name, _) in &element.attributes {
+        if name.contains(':') {
+            prefixes.insert(qname_prefix(name));
+        }
+    }
+    for inclusive in inclusive_prefixes {
+        let inclusive = if inclusive == "#default" {
+            ""
+        } else {
+            inclusive.as_str()
+        };
+        if element.namespaces.contains_key(inclusive) {
+            prefixes.insert(inclusive);
+        }
+    }
+    let mut rendered = rendered.clone();
+    out.push('<');
+    out.push_str(&element.name);
+    for prefix in prefixes {
+        if prefix == "xml" {
+            continue;
+        }
+        let uri = element
+            .namespaces
+            .get(prefix)
+            .map(String::as_str)
+            .unwrap_or("");
+        if rendered.get(prefix).copied().unwrap_or("") == uri {
+            continue;
+        }
+        rendered.insert(prefix, uri);
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        push_escaped_attribute(out, uri);
+        out.push('"');
+    }
+    // Attributes are sorted by namespace URI, then local name. Unqualified
+    // attributes have no namespace, so they come first.
+    let mut attributes: Vec<_> = element
+        .attributes
+        .iter()
+        .map(|(name, value)| {
+            let namespace = if name.contains(':') {
+                element
+                    .namespaces
+                    .get(qname_prefix(name))
+                    .map(String::as_str)
+                    .unwrap_or("")
+            } else {
+                ""
+            };
+            ((namespace, qname_local_name(name)), name, value)
+        })
+        .collect();
+    attributes.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_, name, value) in attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        push_escaped_attribute(out, value);
+        out.push('"');
+    }
+    out.push('>');
+    for child in &element.children {
+        match child {
+            XmlNode::Element(child) => {
+                if excluded.is_some_and(|excluded| std::ptr::eq(child, excluded)) {
+                    continue;
+                }
+                write_canonical(out, child, excluded, inclusive_prefixes, &rendered);
+            },
+            XmlNode::Text(text) => push_escaped_text(out, text),
+        }
+    }
+    out.push_str("</");
+    out.push_str(&element.name);
+    out.push('>');
+}
+
+fn push_escaped_text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn push_escaped_attribute(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing {
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use openssl::{
+        asn1::Asn1Time,
+        hash::{
+            hash,
+            MessageDigest,
+        },
+        pkey::{
+            PKey,
+            Private,
+        },
+        rsa::Rsa,
+        sign::Signer,
+        x509::{
+            X509NameBuilder,
+            X509,
+        },
+    };
+
+    use super::{
+        canonicalize,
+        parse_xml,
+    };
+
+    pub const SP_ENTITY_ID: &str = "https://carnitas.convex.cloud/api/saml/metadata";
+    pub const ACS_URL: &str = "https://carnitas.convex.cloud/api/saml/acs";
+    pub const IDP_ENTITY_ID: &str = "https://idp.example.com";
+
+    /// A signing key for an identity provider and its PEM-encoded certificate.
+    pub fn key_and_certificate() -> anyhow::Result<(PKey<Private>, String)> {
+        let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "idp.example.com")?;
+        let name = name.build();
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+        builder.set_not_after(&Asn1Time::days_from_now(1)?)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        Ok((key, String::from_utf8(builder.build().to_pem()?)?))
+    }
+
+    fn time(time: SystemTime) -> String {
+        chrono::DateTime::<chrono::Utc>::from(time)
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+    }
+
+    /// A response from `IDP_ENTITY_ID` with an assertion for `alice` signed
+    /// with `key`. The assertion is valid for `lifetime` from `now` and is
+    /// restricted to `audience`, if there is one.
+    pub fn signed_response(
+        key: &PKey<Private>,
+        now: SystemTime,
+        audience: Option<&str>,
+        lifetime: Duration,
+        tamper: impl Fn(String) -> String,
+    ) -> anyhow::Result<String> {
+        let restriction = audience
+            .map(|audience| {
+                format!(
+                    "\n    <saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></\
+                     saml:AudienceRestriction>"
+                )
+            })
+            .unwrap_or_default();
+        let assertion = |signature: &str| {
+            format!(
+                r#"<saml:Assertion xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" xmlns:xs="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" ID="_assertion" IssueInstant="{now}" Version="2.0">
+  <saml:Issuer>{IDP_ENTITY_ID}</saml:Issuer>{signature}
+  <saml:Subject>
+    <saml:NameID>alice@example.com</saml:NameID>
+    <saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">
+      <saml:SubjectConfirmationData NotOnOrAfter="{expiry}" Recipient="{ACS_URL}"/>
+    </saml:SubjectConfirmation>
+  </saml:Subject>
+  <saml:Conditions NotBefore="{now}" NotOnOrAfter="{expiry}">{restriction}
+  </saml:Conditions>
+  <saml:AttributeStatement>
+    <saml:Attribute Name="mail"><saml:AttributeValue xsi:type="xs:string">alice@example.com</saml:AttributeValue></saml:Attribute>
+    <saml:Attribute Name="groups"><saml:AttributeValue>admins</saml:AttributeValue><saml:AttributeValue>engineering</saml:AttributeValue></saml:Attribute>
+  </saml:AttributeStatement>
+</saml:Assertion>"#,
+                now = time(now),
+                expiry = time(now + lifetime),
+            )
+        };
+        let digest = hash(
+            MessageDigest::sha256(),
+            canonicalize(&parse_xml(&assertion(""))?, None, &["xs".to_string()]).as_bytes(),
+        )?;
+        let signed_info = format!(
+            r##"<ds:SignedInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#"><ds:CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/><ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"/><ds:Reference URI="#_assertion"><ds:Transforms><ds:Transform Algorithm="http://www.w3.org/2000/09/xmldsig#enveloped-signature"/><ds:Transform Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"><ec:InclusiveNamespaces xmlns:ec="http://www.w3.org/2001/10/xml-exc-c14n#" PrefixList="xs"/></ds:Transform></ds:Transforms><ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"/><ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>"##,
+            base64::encode(digest),
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), key)?;
+        signer.update(canonicalize(&parse_xml(&signed_info)?, None, &[]).as_bytes())?;
+        let signature = format!(
+            r#"<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{signed_info}<ds:SignatureValue>{}</ds:SignatureValue></ds:Signature>"#,
+            base64::encode(signer.sign_to_vec()?),
+        );
+        let response = format!(
+            r#"<samlp:Response xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_response" Destination="{ACS_URL}" Version="2.0"><samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"/></samlp:Status>{}</samlp:Response>"#,
+            assertion(&signature),
+        );
+        Ok(base64::encode(tamper(response)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        time::{
+            Duration,
+            SystemTime,
+        },
+    };
+
+    use common::auth::SamlProvider;
+
+    use super::{
+        canonicalize,
+        issue_saml_token,
+        parse_xml,
+        saml_jwt_provider,
+        testing::{
+            key_and_certificate,
+            signed_response,
+            ACS_URL,
+            IDP_ENTITY_ID,
+            SP_ENTITY_ID,
+        },
+        validate_saml_response,
+    };
+    use crate::custom_jwt::{
+        custom_jwt_provider_for,
+        validate_custom_jwt,
+    };
+
+    const LIFETIME: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn test_exclusive_canonicalization() -> anyhow::Result<()> {
+        let root = parse_xml(
+            r#"<a:Root xmlns:a="urn:a" xmlns:b="urn:b" xmlns="urn:default"><!-- comment --><Child z="1" b:y="2" a="3">t &amp; &lt;x&gt;</Child><a:Other/></a:Root>"#,
+        )?;
+        assert_eq!(
+            canonicalize(&root, None, &[]),
+            r#"<a:Root xmlns:a="urn:a"><Child xmlns="urn:default" xmlns:b="urn:b" a="3" z="1" b:y="2">t &amp; &lt;x&gt;</Child><a:Other></a:Other></a:Root>"#
+        );
+        assert_eq!(
+            canonicalize(&root, None, &["b".to_string()]),
+            r#"<a:Root xmlns:a="urn:a" xmlns:b="urn:b"><Child xmlns="urn:default" a="3" z="1" b:y="2">t &amp; &lt;x&gt;</Child><a:Other></a:Other></a:Root>"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_saml_response() -> anyhow::Result<()> {
+        let (key, certificate) = key_and_certificate()?;
+        let providers = [SamlProvider {
+            entity_id: IDP_ENTITY_ID.to_string(),
+            certificate,
+            attribute_mapping: BTreeMap::from([("email".to_string(), "mail".to_string())]),
+        }];
+        let now = SystemTime::now();
+        let response =
+            signed_response(&key, now, Some(SP_ENTITY_ID), LIFETIME, |response| response)?;
+        let (provider, assertion) =
+            validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now)?;
+        assert_eq!(assertion.name_id, "alice@example.com");
+        assert_eq!(
+            assertion.attributes["groups"],
+            vec!["admins", "engineering"]
+        );
+
+        // The assertion is exchanged for a token that maps its attributes.
+        let jwt_provider = saml_jwt_provider(provider, "secret".to_string());
+        let token = issue_saml_token(
+            provider,
+            &jwt_provider,
+            assertion,
+            now,
+            now + Duration::from_secs(60),
+        )?;
+        let jwt_providers = [jwt_provider];
+        let jwt_provider = custom_jwt_provider_for(&token, &jwt_providers).unwrap();
+        let identity = validate_custom_jwt(&token, jwt_provider, now)?;
+        assert_eq!(identity.issuer, IDP_ENTITY_ID);
+        assert_eq!(identity.subject, "alice@example.com");
+        assert_eq!(
+            identity.attributes.email.as_deref(),
+            Some("alice@example.com")
+        );
+        assert_eq!(
+            identity.attributes.custom_claims["groups"],
+            r#"["admins","engineering"]"#
+        );
+
+        // Assertions for other service providers are rejected.
+        let response = signed_response(
+            &key,
+            now,
+            Some("https://other.example.com"),
+            LIFETIME,
+            |r| r,
+        )?;
+        assert!(validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now).is_err());
+
+        // As are ones that aren't restricted to any service provider.
+        let response = signed_response(&key, now, None, LIFETIME, |r| r)?;
+        let err =
+            validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The assertion isn't restricted to this service provider"));
+
+        // And ones that are valid for too long to remember.
+        let response = signed_response(
+            &key,
+            now,
+            Some(SP_ENTITY_ID),
+            Duration::from_secs(7 * 24 * 60 * 60),
+            |r| r,
+        )?;
+        let err =
+            validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The assertion is valid for too long"));
+
+        // And expired ones.
+        let response = signed_response(&key, now, Some(SP_ENTITY_ID), LIFETIME, |r| r)?;
+        let later = now + Duration::from_secs(600);
+        assert!(
+            validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, later).is_err()
+        );
+
+        // And ones that were changed after they were signed.
+        let response = signed_response(&key, now, Some(SP_ENTITY_ID), LIFETIME, |response| {
+            response.replace(">alice@example.com<", ">mallory@example.com<")
+        })?;
+        assert!(validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now).is_err());
+
+        // And ones signed with another key.
+        let (other_key, _) = key_and_certificate()?;
+        let response = signed_response(&other_key, now, Some(SP_ENTITY_ID), LIFETIME, |r| r)?;
+        assert!(validate_saml_response(&response, &providers, SP_ENTITY_ID, ACS_URL, now).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Verification of the credentials service accounts exchange for access
+//! tokens, following OAuth's client credentials grant. Accounts authenticate
+//! with either a client secret or, per RFC 7523, a JWT client assertion
+//! signed with their private key.
+use std::time::SystemTime;
+
+use anyhow::Context;
+use biscuit::{
+    jwk::{
+        JWKSet,
+        JWK,
+    },
+    ClaimPresenceOptions,
+    Presence,
+    TemporalOptions,
+    Validation,
+    ValidationOptions,
+    JWT,
+};
+use common::knobs::SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME;
+use errors::ErrorMetadata;
+
+/// The `client_assertion_type` for client assertions signed with a private
+/// key.
+pub const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// What a service account presents to get an access token.
+pub enum ClientCredential {
+    Secret(String),
+    /// A JWT signed with the account's private key.
+    Assertion(String),
+}
+
+/// Checks that `jwk` is a public key that client assertions can be verified
+/// with.
+pub fn validate_service_account_jwk(jwk: &str) -> anyhow::Result<()> {
+    let jwk = parse_jwk(jwk)?;
+    anyhow::ensure!(
+        jwk.common.key_id.is_some(),
+        ErrorMetadata::bad_request(
+            "InvalidServiceAccountKey",
+            "The service account's public key must have a `kid`",
+        )
+    );
+    Ok(())
+}
+
+/// Verifies a client assertion for the service account `client_id`. It must
+/// be signed with the private key matching `jwk` and carry its `kid`, have
+/// `client_id` as its issuer and subject and `audience` as its audience, and
+/// expire within `SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME`.
+pub fn validate_client_assertion(
+    assertion: &str,
+    jwk: &str,
+    client_id: &str,
+    audience: &str,
+    system_time: SystemTime,
+) -> anyhow::Result<()> {
+    let invalid_assertion =
+        |msg: &str| ErrorMetadata::unauthenticated("InvalidClientAssertion", msg.to_string());
+    let jwks = JWKSet {
+        keys: vec![parse_jwk(jwk)?],
+    };
+    let encoded = JWT::<biscuit::Empty, biscuit::Empty>::new_encoded(assertion);
+    let algorithm = encoded
+        .unverified_header()
+        .context(invalid_assertion(
+            "The client assertion could not be decoded",
+        ))?
+        .registered
+        .algorithm;
+    let decoded = encoded
+        .decode_with_jwks(&jwks, Some(algorithm))
+        .context(invalid_assertion(
+            "The client assertion's signature could not be verified",
+        ))?;
+    decoded
+        .validate(ValidationOptions {
+            claim_presence_options: ClaimPresenceOptions {
+                issuer: Presence::Required,
+                audience: Presence::Required,
+                subject: Presence::Required,
+                expiry: Presence::Required,
+                ..Default::default()
+            },
+            temporal_options: TemporalOptions {
+                epsilon: chrono::Duration::zero(),
+                now: Some(chrono::DateTime::from(system_time)),
+            },
+            issuer: Validation::Validate(client_id.to_string()),
+            audience: Validation::Validate(audience.to_string()),
+            ..ValidationOptions::default()
+        })
+        .context(invalid_assertion(
+            "The client assertion could not be validated",
+        ))?;
+    let claims = &decoded
+        .payload()
+        .context(invalid_assertion(
+            "The client assertion could not be decoded",
+        ))?
+        .registered;
+    anyhow::ensure!(
+        claims.subject.as_deref() == Some(client_id),
+        invalid_assertion("The client assertion's subject must be the client id")
+    );
+    let expiry = claims
+        .expiry
+        .as_ref()
+        .context(invalid_assertion("The client assertion must expire"))?;
+    let max_expiry = (system_time + *SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME)
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs();
+    anyhow::ensure!(
+        expiry.timestamp() <= max_expiry as i64,
+        invalid_assertion(&format!(
+            "The client assertion must expire within {} seconds",
+            SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME.as_secs()
+        ))
+    );
+    Ok(())
+}
+
+fn parse_jwk(jwk: &str) -> anyhow::Result<JWK<biscuit::Empty>> {
+    serde_json::from_str(jwk).context(ErrorMetadata::bad_request(
+        "InvalidServiceAccountKey",
+        "The service account's public key must be a JWK",
+    ))
+}
@@ -0,0 +1,186 @@
+//! Token exchange, following RFC 8693, which lets trusted services act as a
+//! user, like to render a page or run a background job for them. A service
+//! authenticates with its service account token or admin key, and exchanges
+//! the user's token identifier for a short-lived token for that user. The
+//! backend signs these tokens with a key derived from the instance secret, and
+//! verifies them like tokens from a custom JWT provider.
+use std::{
+    collections::BTreeMap,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use anyhow::Context;
+use common::auth::{
+    CustomJwtKey,
+    CustomJwtProvider,
+};
+use errors::ErrorMetadata;
+use keybroker::UserIdentity;
+use serde::Serialize;
+use sync_types::UserIdentifier;
+
+use crate::custom_jwt::issue_custom_jwt;
+
+/// The `grant_type` of token exchange requests.
+pub const GRANT_TYPE_TOKEN_EXCHANGE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+
+/// The `subject_token_type` for subject tokens that are the token identifier,
+/// `<issuer>|<subject>`, of the user to act as.
+pub const TOKEN_TYPE_TOKEN_IDENTIFIER: &str = "urn:convex:params:oauth:token-type:token-identifier";
+
+/// The `issued_token_type` of the tokens the exchange issues.
+pub const TOKEN_TYPE_JWT: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+/// Claim holding the issuer of the user the token acts as, since the token's
+/// own issuer is the deployment.
+const SUBJECT_ISSUER_CLAIM: &str = "subjectIssuer";
+
+#[derive(Serialize)]
+struct Actor {
+    sub: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenExchangeClaims {
+    /// Who is acting as the user, per RFC 8693's `act` claim. It's passed
+    /// through to the identity's custom claims, so functions can tell the
+    /// request came from a service.
+    act: Actor,
+    subject_issuer: String,
+}
+
+/// Issuer of the deployment's exchanged tokens.
+pub fn token_exchange_issuer(instance_name: &str) -> String {
+    format!("convex:token-exchange:{instance_name}")
+}
+
+/// The provider that verifies exchanged tokens signed with `secret`.
+pub fn token_exchange_jwt_provider(issuer: String, secret: String) -> CustomJwtProvider {
+    CustomJwtProvider {
+        issuer,
+        audience: None,
+        algorithm: "HS256".to_string(),
+        key: CustomJwtKey::Secret(secret),
+        required_claims: BTreeMap::new(),
+        claim_mapping: BTreeMap::new(),
+    }
+}
+
+/// Splits a subject token into the issuer and subject of the user to act as.
+pub fn parse_token_identifier(token_identifier: &str) -> anyhow::Result<(&str, &str)> {
+    token_identifier
+        .split_once('|')
+        .filter(|(issuer, subject)| !issuer.is_empty() && !subject.is_empty())
+        .context(ErrorMetadata::bad_request(
+            "InvalidSubjectToken",
+            "The subject token must be the token identifier, `<issuer>|<subject>`, of the user to \
+             act as",
+        ))
+}
+
+/// Issues a token, valid for `ttl`, that lets `actor` act as the user with
+/// `token_identifier`.
+pub fn issue_exchanged_token(
+    provider: &CustomJwtProvider,
+    token_identifier: &str,
+    actor: String,
+    system_time: SystemTime,
+    ttl: Duration,
+) -> anyhow::Result<String> {
+    let (issuer, subject) = parse_token_identifier(token_identifier)?;
+    issue_custom_jwt(
+        provider,
+        subject.to_string(),
+        TokenExchangeClaims {
+            act: Actor { sub: actor },
+            subject_issuer: issuer.to_string(),
+        },
+        system_time,
+        system_time + ttl,
+    )
+}
+
+/// Turns the identity verified from an exchanged token into the identity of
+/// the user it acts as, so it has the same token identifier as the user's own
+/// tokens.
+pub fn exchanged_token_identity(mut identity: UserIdentity) -> anyhow::Result<UserIdentity> {
+    let issuer = identity
+        .attributes
+        .custom_claims
+        .remove(SUBJECT_ISSUER_CLAIM)
+        .and_then(|issuer| serde_json::from_str::<String>(&issuer).ok())
+        .context(ErrorMetadata::unauthenticated(
+            "InvalidCustomJwt",
+            "The exchanged token doesn't say which issuer its subject is from",
+        ))?;
+    identity.attributes.token_identifier = UserIdentifier::construct(&issuer, &identity.subject);
+    identity.attributes.issuer = Some(issuer.clone());
+    identity.issuer = issuer;
+    Ok(identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use super::{
+        exchanged_token_identity,
+        issue_exchanged_token,
+        parse_token_identifier,
+        token_exchange_issuer,
+        token_exchange_jwt_provider,
+    };
+    use crate::custom_jwt::{
+        custom_jwt_provider_for,
+        validate_custom_jwt,
+    };
+
+    #[test]
+    fn test_exchanged_tokens() -> anyhow::Result<()> {
+        let provider =
+            token_exchange_jwt_provider(token_exchange_issuer("carnitas"), "secret".to_string());
+        let now = SystemTime::now();
+        let token = issue_exchanged_token(
+            &provider,
+            "https://auth.example.com|auth0|user-1",
+            "serviceAccount:renderer".to_string(),
+            now,
+            Duration::from_secs(60),
+        )?;
+
+        let providers = [provider];
+        let provider = custom_jwt_provider_for(&token, &providers).unwrap();
+        let identity = exchanged_token_identity(validate_custom_jwt(&token, provider, now)?)?;
+        assert_eq!(identity.subject, "auth0|user-1");
+        assert_eq!(identity.issuer, "https://auth.example.com");
+        assert_eq!(
+            &*identity.attributes.token_identifier,
+            "https://auth.example.com|auth0|user-1"
+        );
+        assert_eq!(
+            identity
+                .attributes
+                .custom_claims
+                .get("act")
+                .map(String::as_str),
+            Some(r#"{"sub":"serviceAccount:renderer"}"#)
+        );
+
+        // Tokens expire.
+        assert!(validate_custom_jwt(&token, provider, now + Duration::from_secs(61)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_token_identifier() {
+        assert!(parse_token_identifier("no-subject|").is_err());
+        assert!(parse_token_identifier("just-a-subject").is_err());
+    }
+}
@@ -0,0 +1,516 @@
+//! Declarative access rules for user tables, evaluated by the database for
+//! every function call made by a user or an unauthenticated client. Admins
+//! and the system aren't subject to them.
+//!
+//! Each rule is a JSON predicate over the caller's identity and a document:
+//! - `true` or `false`
+//! - `"authenticated"`, which holds when the caller has a user identity
+//! - `{"and": [rule, ...]}`, `{"or": [rule, ...]}` and `{"not": rule}`
+//! - `{"eq": [a, b]}` and `{"neq": [a, b]}` for operands `a` and `b`
+//! - `{"in": [a, b]}`, which holds when `a` is an element of the array `b`
+//!
+//! Operands are `{"doc": "<field path>"}`, `{"identity": "<field>"}` (e.g.
+//! `subject`, `email` or a custom claim), `{"literal": <value>}`, or any JSON
+//! value that isn't an object. Missing fields evaluate to `null`.
+use std::{
+    fmt,
+    str::FromStr,
+    sync::LazyLock,
+};
+
+use anyhow::Context;
+use errors::ErrorMetadata;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    json,
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::{
+    codegen_convex_serialization,
+    ConvexObject,
+    ConvexValue,
+    FieldPath,
+    TableName,
+};
+
+pub static ACCESS_RULES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_access_rules"
+        .parse()
+        .expect("Invalid built-in access rules table")
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessOperation {
+    Read,
+    Insert,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for AccessOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operation = match self {
+            AccessOperation::Read => "read",
+            AccessOperation::Insert => "insert",
+            AccessOperation::Update => "update",
+            AccessOperation::Delete => "delete",
+        };
+        write!(f, "{operation}")
+    }
+}
+
+impl FromStr for AccessOperation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "read" => Ok(AccessOperation::Read),
+            "insert" => Ok(AccessOperation::Insert),
+            "update" => Ok(AccessOperation::Update),
+            "delete" => Ok(AccessOperation::Delete),
+            _ => anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidAccessOperation",
+                format!("Unknown operation {s:?}, expected read, insert, update or delete"),
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleOperand {
+    Document(FieldPath),
+    Identity(String),
+    Literal(JsonValue),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessRule {
+    Constant(bool),
+    Authenticated,
+    And(Vec<AccessRule>),
+    Or(Vec<AccessRule>),
+    Not(Box<AccessRule>),
+    Eq(RuleOperand, RuleOperand),
+    Neq(RuleOperand, RuleOperand),
+    In(RuleOperand, RuleOperand),
+}
+
+fn invalid_rule(msg: String) -> anyhow::Error {
+    anyhow::anyhow!(ErrorMetadata::bad_request("InvalidAccessRule", msg))
+}
+
+impl TryFrom<JsonValue> for RuleOperand {
+    type Error = anyhow::Error;
+
+    fn try_from(value: JsonValue) -> anyhow::Result<Self> {
+        let JsonValue::Object(object) = value else {
+            return Ok(RuleOperand::Literal(value));
+        };
+        let mut entries = object.into_iter();
+        let (Some((key, value)), None) = (entries.next(), entries.next()) else {
+            return Err(invalid_rule(
+                "Operands must have exactly one of `doc`, `identity` or `literal`".to_string(),
+            ));
+        };
+        match (key.as_str(), value) {
+            ("doc", JsonValue::String(path)) => {
+                Ok(RuleOperand::Document(path.parse().map_err(|e| {
+                    invalid_rule(format!("Invalid field path {path:?}: {e}"))
+                })?))
+            },
+            ("identity", JsonValue::String(field)) => Ok(RuleOperand::Identity(field)),
+            ("literal", value) => Ok(RuleOperand::Literal(value)),
+            (key, _) => Err(invalid_rule(format!("Invalid operand `{key}`"))),
+        }
+    }
+}
+
+impl From<RuleOperand> for JsonValue {
+    fn from(operand: RuleOperand) -> Self {
+        match operand {
+            RuleOperand::Document(path) => json!({ "doc": String::from(path) }),
+            RuleOperand::Identity(field) => json!({ "identity": field }),
+            RuleOperand::Literal(value @ JsonValue::Object(_)) => json!({ "literal": value }),
+            RuleOperand::Literal(value) => value,
+        }
+    }
+}
+
+fn operand_pair(value: JsonValue) -> anyhow::Result<(RuleOperand, RuleOperand)> {
+    let JsonValue::Array(operands) = value else {
+        return Err(invalid_rule(
+            "Comparisons take an array of two operands".to_string(),
+        ));
+    };
+    let Ok([left, right]) = <[JsonValue; 2]>::try_from(operands) else {
+        return Err(invalid_rule(
+            "Comparisons take an array of two operands".to_string(),
+        ));
+    };
+    Ok((left.try_into()?, right.try_into()?))
+}
+
+impl TryFrom<JsonValue> for AccessRule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: JsonValue) -> anyhow::Result<Self> {
+        let object = match value {
+            JsonValue::Bool(b) => return Ok(AccessRule::Constant(b)),
+            JsonValue::String(s) if s == "authenticated" => return Ok(AccessRule::Authenticated),
+            JsonValue::Object(object) => object,
+            value => return Err(invalid_rule(format!("Invalid access rule {value}"))),
+        };
+        let mut entries = object.into_iter();
+        let (Some((key, value)), None) = (entries.next(), entries.next()) else {
+            return Err(invalid_rule(
+                "Access rule objects must have exactly one key".to_string(),
+            ));
+        };
+        let rules = |value: JsonValue| -> anyhow::Result<Vec<AccessRule>> {
+            let JsonValue::Array(rules) = value else {
+                return Err(invalid_rule(format!("`{key}` takes an array of rules")));
+            };
+            rules.into_iter().map(AccessRule::try_from).collect()
+        };
+        let rule = match key.as_str() {
+            "and" => AccessRule::And(rules(value)?),
+            "or" => AccessRule::Or(rules(value)?),
+            "not" => AccessRule::Not(Box::new(value.try_into()?)),
+            "eq" => {
+                let (left, right) = operand_pair(value)?;
+                AccessRule::Eq(left, right)
+            },
+            "neq" => {
+                let (left, right) = operand_pair(value)?;
+                AccessRule::Neq(left, right)
+            },
+            "in" => {
+                let (left, right) = operand_pair(value)?;
+                AccessRule::In(left, right)
+            },
+            key => return Err(invalid_rule(format!("Unknown access rule `{key}`"))),
+        };
+        Ok(rule)
+    }
+}
+
+impl From<AccessRule> for JsonValue {
+    fn from(rule: AccessRule) -> Self {
+        let pair = |left: RuleOperand, right: RuleOperand| {
+            JsonValue::Array(vec![left.into(), right.into()])
+        };
+        match rule {
+            AccessRule::Constant(b) => JsonValue::Bool(b),
+            AccessRule::Authenticated => JsonValue::String("authenticated".to_string()),
+            AccessRule::And(rules) => {
+                json!({ "and": rules.into_iter().map(JsonValue::from).collect::<Vec<_>>() })
+            },
+            AccessRule::Or(rules) => {
+                json!({ "or": rules.into_iter().map(JsonValue::from).collect::<Vec<_>>() })
+            },
+            AccessRule::Not(rule) => json!({ "not": JsonValue::from(*rule) }),
+            AccessRule::Eq(left, right) => json!({ "eq": pair(left, right) }),
+            AccessRule::Neq(left, right) => json!({ "neq": pair(left, right) }),
+            AccessRule::In(left, right) => json!({ "in": pair(left, right) }),
+        }
+    }
+}
+
+impl FromStr for AccessRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let value: JsonValue =
+            serde_json::from_str(s).map_err(|e| invalid_rule(format!("Invalid JSON: {e}")))?;
+        value.try_into()
+    }
+}
+
+impl fmt::Display for AccessRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", JsonValue::from(self.clone()))
+    }
+}
+
+/// Converts a JSON value to the value it compares as. Values that aren't valid
+/// Convex values (e.g. objects with invalid field names in custom claims)
+/// compare as `null`.
+fn convex_value(value: &JsonValue) -> ConvexValue {
+    ConvexValue::try_from(value.clone()).unwrap_or(ConvexValue::Null)
+}
+
+impl RuleOperand {
+    fn evaluate(
+        &self,
+        identity: Option<&JsonMap<String, JsonValue>>,
+        document: &ConvexObject,
+    ) -> ConvexValue {
+        match self {
+            RuleOperand::Document(path) => document
+                .get_path(path)
+                .cloned()
+                .unwrap_or(ConvexValue::Null),
+            RuleOperand::Identity(field) => identity
+                .and_then(|identity| identity.get(field))
+                .map(convex_value)
+                .unwrap_or(ConvexValue::Null),
+            RuleOperand::Literal(value) => convex_value(value),
+        }
+    }
+}
+
+impl AccessRule {
+    /// Whether the rule holds for `document`, given the caller's identity as
+    /// returned by `ctx.auth.getUserIdentity()`.
+    pub fn evaluate(
+        &self,
+        identity: Option<&JsonMap<String, JsonValue>>,
+        document: &ConvexObject,
+    ) -> bool {
+        match self {
+            AccessRule::Constant(b) => *b,
+            AccessRule::Authenticated => identity.is_some(),
+            AccessRule::And(rules) => rules.iter().all(|r| r.evaluate(identity, document)),
+            AccessRule::Or(rules) => rules.iter().any(|r| r.evaluate(identity, document)),
+            AccessRule::Not(rule) => !rule.evaluate(identity, document),
+            AccessRule::Eq(left, right) => {
+                left.evaluate(identity, document) == right.evaluate(identity, document)
+            },
+            AccessRule::Neq(left, right) => {
+                left.evaluate(identity, document) != right.evaluate(identity, document)
+            },
+            AccessRule::In(left, right) => match right.evaluate(identity, document) {
+                ConvexValue::Array(array) => {
+                    let left = left.evaluate(identity, document);
+                    array.iter().any(|element| *element == left)
+                },
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl proptest::arbitrary::Arbitrary for RuleOperand {
+    type Parameters = ();
+
+    type Strategy = impl proptest::strategy::Strategy<Value = RuleOperand>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        prop_oneof![
+            "[a-z]{1,8}(\\.[a-z]{1,8})?"
+                .prop_filter_map("Invalid field path", |path| path.parse().ok())
+                .prop_map(RuleOperand::Document),
+            "[a-zA-Z]{1,16}".prop_map(RuleOperand::Identity),
+            any::<Option<String>>()
+                .prop_map(|s| RuleOperand::Literal(s.map_or(JsonValue::Null, JsonValue::String))),
+            any::<i32>().prop_map(|n| RuleOperand::Literal(n.into())),
+        ]
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl proptest::arbitrary::Arbitrary for AccessRule {
+    type Parameters = ();
+
+    type Strategy = impl proptest::strategy::Strategy<Value = AccessRule>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        use proptest::prelude::*;
+
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(AccessRule::Constant),
+            Just(AccessRule::Authenticated),
+            any::<(RuleOperand, RuleOperand)>().prop_map(|(l, r)| AccessRule::Eq(l, r)),
+            any::<(RuleOperand, RuleOperand)>().prop_map(|(l, r)| AccessRule::Neq(l, r)),
+            any::<(RuleOperand, RuleOperand)>().prop_map(|(l, r)| AccessRule::In(l, r)),
+        ];
+        leaf.prop_recursive(3, 16, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(AccessRule::And),
+                prop::collection::vec(inner.clone(), 0..4).prop_map(AccessRule::Or),
+                inner.prop_map(|rule| AccessRule::Not(Box::new(rule))),
+            ]
+        })
+    }
+}
+
+/// The rules for one table. Operations without a rule are allowed.
+///
+/// Reads hide documents the `read` rule doesn't hold for. Writes the rules
+/// don't allow fail: `insert` is checked against the new document, `update`
+/// against both the existing and the updated document, and `delete` against
+/// the existing document.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct TableAccessRules {
+    pub table: TableName,
+    pub read: Option<AccessRule>,
+    pub insert: Option<AccessRule>,
+    pub update: Option<AccessRule>,
+    pub delete: Option<AccessRule>,
+}
+
+impl TableAccessRules {
+    pub fn rule(&self, operation: AccessOperation) -> Option<&AccessRule> {
+        match operation {
+            AccessOperation::Read => self.read.as_ref(),
+            AccessOperation::Insert => self.insert.as_ref(),
+            AccessOperation::Update => self.update.as_ref(),
+            AccessOperation::Delete => self.delete.as_ref(),
+        }
+    }
+
+    pub fn allows(
+        &self,
+        operation: AccessOperation,
+        identity: Option<&JsonMap<String, JsonValue>>,
+        document: &ConvexObject,
+    ) -> bool {
+        self.rule(operation)
+            .is_none_or(|rule| rule.evaluate(identity, document))
+    }
+}
+
+/// The error for writes that access rules don't allow.
+pub fn access_denied_error(operation: AccessOperation, table: &TableName) -> ErrorMetadata {
+    ErrorMetadata::forbidden(
+        "AccessRuleDenied",
+        format!("The access rules for table {table} don't allow this {operation}"),
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedTableAccessRules {
+    pub table: String,
+    pub read: Option<String>,
+    pub insert: Option<String>,
+    pub update: Option<String>,
+    pub delete: Option<String>,
+}
+
+impl TryFrom<TableAccessRules> for SerializedTableAccessRules {
+    type Error = anyhow::Error;
+
+    fn try_from(rules: TableAccessRules) -> anyhow::Result<Self> {
+        Ok(Self {
+            table: rules.table.to_string(),
+            read: rules.read.map(|rule| rule.to_string()),
+            insert: rules.insert.map(|rule| rule.to_string()),
+            update: rules.update.map(|rule| rule.to_string()),
+            delete: rules.delete.map(|rule| rule.to_string()),
+        })
+    }
+}
+
+impl TryFrom<SerializedTableAccessRules> for TableAccessRules {
+    type Error = anyhow::Error;
+
+    fn try_from(rules: SerializedTableAccessRules) -> anyhow::Result<Self> {
+        let parse = |rule: Option<String>| {
+            rule.map(|rule| rule.parse().context("Invalid stored access rule"))
+                .transpose()
+        };
+        Ok(Self {
+            table: rules.table.parse()?,
+            read: parse(rules.read)?,
+            insert: parse(rules.insert)?,
+            update: parse(rules.update)?,
+            delete: parse(rules.delete)?,
+        })
+    }
+}
+
+codegen_convex_serialization!(TableAccessRules, SerializedTableAccessRules);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{
+        json,
+        Value as JsonValue,
+    };
+    use value::{
+        assert_obj,
+        ConvexObject,
+    };
+
+    use super::AccessRule;
+
+    fn evaluate(rule: JsonValue, identity: Option<JsonValue>, document: ConvexObject) -> bool {
+        let rule = AccessRule::try_from(rule).unwrap();
+        let identity = identity.map(|identity| match identity {
+            JsonValue::Object(identity) => identity,
+            _ => panic!("identity must be an object"),
+        });
+        rule.evaluate(identity.as_ref(), &document)
+    }
+
+    #[test]
+    fn test_evaluate_access_rules() {
+        let owner = json!({ "eq": [{ "doc": "owner" }, { "identity": "subject" }] });
+        let doc = assert_obj!("owner" => "alice", "team" => { "id" => "a" });
+        assert!(evaluate(
+            owner.clone(),
+            Some(json!({ "subject": "alice" })),
+            doc.clone()
+        ));
+        assert!(!evaluate(
+            owner.clone(),
+            Some(json!({ "subject": "bob" })),
+            doc.clone()
+        ));
+        assert!(!evaluate(owner, None, doc.clone()));
+
+        let team = json!({
+            "or": [
+                { "in": [{ "doc": "team.id" }, { "identity": "teams" }] },
+                { "not": "authenticated" },
+            ]
+        });
+        assert!(evaluate(
+            team.clone(),
+            Some(json!({ "teams": ["a", "b"] })),
+            doc.clone()
+        ));
+        assert!(!evaluate(
+            team.clone(),
+            Some(json!({ "teams": ["b"] })),
+            doc.clone()
+        ));
+        assert!(evaluate(team, None, doc.clone()));
+
+        // Numbers compare as floats, like document fields.
+        let doc = assert_obj!("level" => 3.0);
+        assert!(evaluate(
+            json!({ "eq": [{ "doc": "level" }, 3] }),
+            None,
+            doc
+        ));
+    }
+
+    #[test]
+    fn test_access_rule_roundtrips() -> anyhow::Result<()> {
+        let rule = json!({
+            "and": [
+                "authenticated",
+                { "neq": [{ "doc": "status" }, "archived"] },
+                { "eq": [{ "identity": "org" }, { "literal": { "id": 1.0 } }] },
+            ]
+        });
+        let parsed = AccessRule::try_from(rule.clone())?;
+        assert_eq!(JsonValue::from(parsed.clone()), rule);
+        assert_eq!(parsed.to_string().parse::<AccessRule>()?, parsed);
+
+        assert!(AccessRule::try_from(json!({ "eq": [1] })).is_err());
+        assert!(AccessRule::try_from(json!({ "xor": [] })).is_err());
+        assert!(AccessRule::try_from(json!("anonymous")).is_err());
+        Ok(())
+    }
+}
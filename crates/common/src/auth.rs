@@ -1,4 +1,7 @@
-use std::sync::LazyLock;
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+};
 
 use openidconnect::IssuerUrl;
 use regex::Regex;
@@ -15,6 +18,11 @@ pub struct AuthInfo {
     pub application_id: String,
     #[serde(deserialize_with = "deserialize_issuer_url")]
     pub domain: IssuerUrl,
+    /// User identity field, like `email`, -> the claim it's read from, for
+    /// providers whose tokens don't use the standard OpenID Connect claims.
+    /// The subject is read from `sub` unless it's mapped.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub claim_mapping: BTreeMap<String, String>,
 }
 
 static PROTOCOL_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\w+://").unwrap());
@@ -81,6 +89,7 @@ impl AuthInfo {
         Self {
             application_id: "12345".to_string(),
             domain: IssuerUrl::new("https://convex.dev".to_string()).unwrap(),
+            claim_mapping: BTreeMap::new(),
         }
     }
 }
@@ -93,13 +102,19 @@ impl proptest::arbitrary::Arbitrary for AuthInfo {
 
     fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
         use proptest::prelude::*;
-        any::<(String, proptest_http::ArbitraryUri)>().prop_filter_map(
+        any::<(
+            String,
+            proptest_http::ArbitraryUri,
+            BTreeMap<String, String>,
+        )>()
+        .prop_filter_map(
             "String and URI weren't valid AuthInfo",
-            |(s, uri)| {
+            |(s, uri, claim_mapping)| {
                 IssuerUrl::new(format!("{}", uri.0))
                     .map(|domain| Self {
                         application_id: s,
                         domain,
+                        claim_mapping,
                     })
                     .ok()
             },
@@ -117,6 +132,52 @@ pub struct AuthConfig {
     pub providers: Vec<AuthInfo>,
 }
 
+/// Rules for accepting JWTs from an identity system that isn't an OpenID
+/// Connect provider, configured per deployment through
+/// `/api/custom_jwt_providers`. Tokens are matched to a provider by their `iss`
+/// claim, and then by their `aud` claim if several providers share the issuer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct CustomJwtProvider {
+    pub issuer: String,
+    /// If set, tokens must have it as their `aud` claim.
+    pub audience: Option<String>,
+    /// The `alg` tokens must be signed with, like `RS256` or `HS256`.
+    pub algorithm: String,
+    pub key: CustomJwtKey,
+    /// Claim -> the value, as JSON, it must have. A claim that's an array
+    /// matches if it contains the value.
+    pub required_claims: BTreeMap<String, String>,
+    /// User identity field, like `email`, -> the claim it's read from. The
+    /// subject is read from `sub` unless it's mapped.
+    pub claim_mapping: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum CustomJwtKey {
+    /// A shared secret, for the HMAC algorithms.
+    Secret(String),
+    /// A JSON Web Key Set with the public keys for the RSA and ECDSA
+    /// algorithms.
+    Jwks(String),
+}
+
+/// A SAML 2.0 identity provider whose assertions the deployment accepts,
+/// configured per deployment through `/api/saml_providers`. Assertions are
+/// matched to a provider by their `Issuer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct SamlProvider {
+    /// The identity provider's entity ID.
+    pub entity_id: String,
+    /// PEM-encoded X.509 certificate the identity provider signs with.
+    pub certificate: String,
+    /// User identity field, like `email`, -> the attribute it's read from. The
+    /// subject is the assertion's `NameID` unless it's mapped.
+    pub attribute_mapping: BTreeMap<String, String>,
+}
+
 #[cfg(test)]
 mod tests {
     use crate::auth::AuthInfo;
@@ -169,4 +230,22 @@ mod tests {
         )
         .unwrap_err();
     }
+
+    #[test]
+    fn test_auth_info_claim_mapping() {
+        let info: AuthInfo = serde_json::from_str(
+            r#"{"applicationID": "123", "domain": "example.com", "claimMapping": {"email": "upn"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            info.claim_mapping.get("email").map(String::as_str),
+            Some("upn")
+        );
+        let info: AuthInfo =
+            serde_json::from_str(r#"{"applicationID": "123", "domain": "example.com"}"#).unwrap();
+        assert!(info.claim_mapping.is_empty());
+        assert!(!serde_json::to_string(&info)
+            .unwrap()
+            .contains("claimMapping"));
+    }
 }
@@ -244,6 +244,17 @@ impl<RT: Runtime, T> CoDelQueueSender<RT, T> {
     }
 }
 
+impl<RT: Runtime, T> CoDelQueueReceiver<RT, T> {
+    /// Number of items waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.lock().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().queue.is_empty()
+    }
+}
+
 impl<RT: Runtime, T> Stream for CoDelQueueReceiver<RT, T> {
     type Item = (T, Option<ExpiredInQueue>);
 
@@ -25,6 +25,7 @@ pub use self::{
         ExportPath,
         PublicFunctionPath,
         ResolvedComponentFunctionPath,
+        SerializedComponentFunctionPath,
     },
     module_paths::CanonicalizedComponentModulePath,
     reference::Reference,
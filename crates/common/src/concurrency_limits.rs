@@ -0,0 +1,134 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use sync_types::CanonicalizedUdfPath;
+
+use crate::components::{
+    CanonicalizedComponentFunctionPath,
+    ComponentPath,
+};
+
+/// Per-function caps on the number of concurrent executions, configured via
+/// the `FUNCTION_CONCURRENCY_LIMITS` knob.
+///
+/// The knob is a comma separated list of `<function>=<limit>` rules, where
+/// `<function>` is a udf path (e.g. `messages:send`), optionally prefixed by a
+/// component path and `@` (e.g. `waitlist@messages:send`). The special
+/// function `*` sets a default limit for every function without a more
+/// specific rule, which applies to each function separately rather than to
+/// the deployment as a whole. HTTP actions share a single limit, set with the
+/// path of their router (`http`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionConcurrencyLimits {
+    default_limit: Option<usize>,
+    by_function: BTreeMap<CanonicalizedComponentFunctionPath, usize>,
+}
+
+impl FunctionConcurrencyLimits {
+    /// Returns the maximum number of concurrent executions allowed for `path`,
+    /// or `None` if the function is only subject to the deployment-wide
+    /// limits.
+    pub fn limit_for(&self, path: &CanonicalizedComponentFunctionPath) -> Option<usize> {
+        self.by_function.get(path).copied().or(self.default_limit)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default_limit.is_none() && self.by_function.is_empty()
+    }
+}
+
+impl FromStr for FunctionConcurrencyLimits {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut limits = Self::default();
+        for rule in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (function, limit) = rule
+                .rsplit_once('=')
+                .with_context(|| format!("Missing `=<limit>` in concurrency rule {rule}"))?;
+            let limit: usize = limit
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid concurrency limit in rule {rule}"))?;
+            anyhow::ensure!(
+                limit > 0,
+                "Concurrency limit must be positive in rule {rule}"
+            );
+            let function = function.trim();
+            if function == "*" {
+                anyhow::ensure!(
+                    limits.default_limit.is_none(),
+                    "Duplicate default concurrency rule {rule}"
+                );
+                limits.default_limit = Some(limit);
+                continue;
+            }
+            let (component, udf_path) = match function.split_once('@') {
+                Some((component, udf_path)) => (component.parse()?, udf_path),
+                None => (ComponentPath::root(), function),
+            };
+            let udf_path: CanonicalizedUdfPath = udf_path
+                .parse()
+                .with_context(|| format!("Invalid function path in rule {rule}"))?;
+            let path = CanonicalizedComponentFunctionPath {
+                component,
+                udf_path,
+            };
+            anyhow::ensure!(
+                limits.by_function.insert(path, limit).is_none(),
+                "Duplicate concurrency rule for {function}"
+            );
+        }
+        Ok(limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FunctionConcurrencyLimits;
+    use crate::components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    };
+
+    fn path(component: &str, udf_path: &str) -> CanonicalizedComponentFunctionPath {
+        CanonicalizedComponentFunctionPath {
+            component: component.parse::<ComponentPath>().unwrap(),
+            udf_path: udf_path.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_concurrency_limits() -> anyhow::Result<()> {
+        let limits: FunctionConcurrencyLimits =
+            "messages:send=2, waitlist@api/slack.js:post=1,*=8".parse()?;
+        assert_eq!(limits.limit_for(&path("", "messages:send")), Some(2));
+        assert_eq!(limits.limit_for(&path("", "messages.js:send")), Some(2));
+        assert_eq!(
+            limits.limit_for(&path("waitlist", "api/slack:post")),
+            Some(1)
+        );
+        assert_eq!(limits.limit_for(&path("", "api/slack:post")), Some(8));
+
+        let limits: FunctionConcurrencyLimits = "messages:send=2".parse()?;
+        assert_eq!(limits.limit_for(&path("", "messages:list")), None);
+        assert!("".parse::<FunctionConcurrencyLimits>()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_concurrency_limits() {
+        assert!("messages:send"
+            .parse::<FunctionConcurrencyLimits>()
+            .is_err());
+        assert!("messages:send=0"
+            .parse::<FunctionConcurrencyLimits>()
+            .is_err());
+        assert!("messages:send=1,messages:send=2"
+            .parse::<FunctionConcurrencyLimits>()
+            .is_err());
+    }
+}
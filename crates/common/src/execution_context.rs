@@ -7,6 +7,7 @@ use std::{
 };
 
 use anyhow::Context;
+use fastrace::collector::SpanContext;
 use rand::Rng;
 use serde_json::{
     json,
@@ -22,6 +23,7 @@ use value::{
 
 use crate::{
     components::ComponentId,
+    fastrace_helpers::EncodedSpan,
     types::FunctionCaller,
 };
 
@@ -40,6 +42,9 @@ pub struct ExecutionContext {
     /// version of this would be something like parent_execution_id:
     /// Option<ExecutionId>
     is_root: bool,
+    /// The W3C `traceparent` of the span the function was called in, if the
+    /// request is being traced, so its logs can be tied to the trace.
+    pub traceparent: Option<String>,
 }
 
 impl ExecutionContext {
@@ -49,6 +54,7 @@ impl ExecutionContext {
             execution_id: ExecutionId::new(),
             parent_scheduled_job: caller.parent_scheduled_job(),
             is_root: caller.is_root(),
+            traceparent: EncodedSpan::from_parent().0,
         }
     }
 
@@ -57,12 +63,14 @@ impl ExecutionContext {
         execution_id: ExecutionId,
         parent_scheduled_job: Option<(ComponentId, DeveloperDocumentId)>,
         is_root: bool,
+        traceparent: Option<String>,
     ) -> Self {
         Self {
             request_id,
             execution_id,
             parent_scheduled_job,
             is_root,
+            traceparent,
         }
     }
 
@@ -70,6 +78,15 @@ impl ExecutionContext {
         self.is_root
     }
 
+    /// The trace id and span id of `traceparent`, in hex.
+    pub fn trace_ids(&self) -> Option<(String, String)> {
+        let span_context = SpanContext::decode_w3c_traceparent(self.traceparent.as_deref()?)?;
+        Some((
+            format!("{:032x}", span_context.trace_id.0),
+            format!("{:016x}", span_context.span_id.0),
+        ))
+    }
+
     #[cfg(any(test, feature = "testing"))]
     pub fn new_for_test() -> Self {
         Self {
@@ -77,6 +94,7 @@ impl ExecutionContext {
             execution_id: ExecutionId::new(),
             parent_scheduled_job: None,
             is_root: true,
+            traceparent: None,
         }
     }
 }
@@ -89,6 +107,7 @@ impl HeapSize for ExecutionContext {
                 .parent_scheduled_job
                 .map_or(0, |(_, document_id)| document_id.heap_size())
             + self.is_root.heap_size()
+            + self.traceparent.heap_size()
     }
 }
 
@@ -218,6 +237,7 @@ impl From<ExecutionContext> for pb::common::ExecutionContext {
                 .and_then(|id| id.serialize_to_string()),
             parent_scheduled_job: parent_document_id.map(Into::into),
             is_root: Some(value.is_root),
+            traceparent: value.traceparent,
         }
     }
 }
@@ -238,6 +258,7 @@ impl TryFrom<pb::common::ExecutionContext> for ExecutionContext {
             },
             parent_scheduled_job: parent_document_id.map(|id| (parent_component_id, id)),
             is_root: value.is_root.unwrap_or_default(),
+            traceparent: value.traceparent,
         })
     }
 }
@@ -251,6 +272,29 @@ impl From<ExecutionContext> for JsonValue {
             "isRoot": value.is_root,
             "parentScheduledJob": parent_document_id.map(|id| id.to_string()),
             "parentScheduledJobComponentId": parent_component_id.unwrap_or(ComponentId::Root).serialize_to_string(),
+            "traceparent": value.traceparent,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ExecutionContext;
+
+    #[test]
+    fn test_trace_ids() {
+        let mut context = ExecutionContext::new_for_test();
+        assert_eq!(context.trace_ids(), None);
+        context.traceparent =
+            Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string());
+        assert_eq!(
+            context.trace_ids(),
+            Some((
+                "0af7651916cd43dd8448eb211c80319c".to_string(),
+                "b7ad6b7169203331".to_string(),
+            ))
+        );
+        context.traceparent = Some("not a traceparent".to_string());
+        assert_eq!(context.trace_ids(), None);
+    }
+}
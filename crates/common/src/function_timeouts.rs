@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::Context;
+use sync_types::CanonicalizedUdfPath;
+
+use crate::components::{
+    CanonicalizedComponentFunctionPath,
+    ComponentPath,
+};
+
+/// Per-function overrides of the user timeout for queries, mutations and
+/// actions, configured via the `FUNCTION_USER_TIMEOUTS` knob.
+///
+/// The knob is a comma separated list of `<function>=<seconds>` rules, where
+/// `<function>` is a udf path (e.g. `messages:send`), optionally prefixed by a
+/// component path and `@` (e.g. `waitlist@messages:send`), and `<seconds>`
+/// may be fractional (e.g. `0.5`). Functions without a rule use the timeout
+/// for their type.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionTimeouts {
+    by_function: BTreeMap<CanonicalizedComponentFunctionPath, Duration>,
+}
+
+impl FunctionTimeouts {
+    pub fn user_timeout_for(&self, path: &CanonicalizedComponentFunctionPath) -> Option<Duration> {
+        self.by_function.get(path).copied()
+    }
+}
+
+impl FromStr for FunctionTimeouts {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut timeouts = Self::default();
+        for rule in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (function, seconds) = rule
+                .rsplit_once('=')
+                .with_context(|| format!("Missing `=<seconds>` in timeout rule {rule}"))?;
+            let seconds: f64 = seconds
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid timeout in rule {rule}"))?;
+            let timeout = Duration::try_from_secs_f64(seconds)
+                .ok()
+                .filter(|timeout| !timeout.is_zero())
+                .with_context(|| format!("Timeout must be positive in rule {rule}"))?;
+            let function = function.trim();
+            let (component, udf_path) = match function.split_once('@') {
+                Some((component, udf_path)) => (component.parse()?, udf_path),
+                None => (ComponentPath::root(), function),
+            };
+            let udf_path: CanonicalizedUdfPath = udf_path
+                .parse()
+                .with_context(|| format!("Invalid function path in rule {rule}"))?;
+            let path = CanonicalizedComponentFunctionPath {
+                component,
+                udf_path,
+            };
+            anyhow::ensure!(
+                timeouts.by_function.insert(path, timeout).is_none(),
+                "Duplicate timeout rule for {function}"
+            );
+        }
+        Ok(timeouts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::FunctionTimeouts;
+    use crate::components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    };
+
+    fn path(component: &str, udf_path: &str) -> CanonicalizedComponentFunctionPath {
+        CanonicalizedComponentFunctionPath {
+            component: component.parse::<ComponentPath>().unwrap(),
+            udf_path: udf_path.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_function_timeouts() -> anyhow::Result<()> {
+        let timeouts: FunctionTimeouts =
+            "messages:list=0.5, exports@api/run.js:export=900".parse()?;
+        assert_eq!(
+            timeouts.user_timeout_for(&path("", "messages.js:list")),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            timeouts.user_timeout_for(&path("exports", "api/run:export")),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(timeouts.user_timeout_for(&path("", "api/run:export")), None);
+
+        assert!("messages:list".parse::<FunctionTimeouts>().is_err());
+        assert!("messages:list=0".parse::<FunctionTimeouts>().is_err());
+        assert!("messages:list=-1".parse::<FunctionTimeouts>().is_err());
+        assert!("messages:list=1,messages:list=2"
+            .parse::<FunctionTimeouts>()
+            .is_err());
+        Ok(())
+    }
+}
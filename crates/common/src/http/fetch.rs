@@ -3,6 +3,8 @@ use std::{
         BTreeMap,
         HashMap,
     },
+    pin::Pin,
+    str::FromStr,
     sync::{
         atomic::{
             AtomicU64,
@@ -12,23 +14,62 @@ use std::{
     },
 };
 
+use anyhow::Context;
 use async_trait::async_trait;
+use errors::ErrorMetadata;
+use fastrace::{
+    future::FutureExt as _,
+    Span,
+};
 use futures::{
-    future::BoxFuture,
+    future::{
+        self,
+        BoxFuture,
+    },
+    stream::BoxStream,
+    Sink,
+    SinkExt,
     StreamExt,
     TryStreamExt,
 };
-use http::StatusCode;
+use http::{
+    header::{
+        SEC_WEBSOCKET_PROTOCOL,
+        USER_AGENT,
+    },
+    HeaderMap,
+    HeaderValue,
+    StatusCode,
+};
 use reqwest::{
     redirect,
     Body,
     Proxy,
     Url,
 };
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::TcpStream,
+};
+use tungstenite::{
+    client::IntoClientRequest,
+    protocol::{
+        frame::coding::CloseCode,
+        CloseFrame,
+    },
+    Message,
+};
 
-use crate::http::{
-    HttpRequestStream,
-    HttpResponseStream,
+use crate::{
+    fastrace_helpers::EncodedSpan,
+    http::{
+        HttpRequestStream,
+        HttpResponseStream,
+        TRACEPARENT_HEADER,
+    },
 };
 
 /// Http client used for fetch syscall.
@@ -48,19 +89,203 @@ pub trait FetchClient: Send + Sync {
         request: HttpRequestStream,
         purpose: InternalFetchPurpose,
     ) -> anyhow::Result<HttpResponseStream>;
+
+    /// Opens a WebSocket connection on behalf of a UDF. Connections are
+    /// subject to the same restrictions as `fetch`.
+    async fn connect_websocket(
+        &self,
+        _request: WebSocketRequest,
+    ) -> anyhow::Result<WebSocketConnection> {
+        anyhow::bail!(ErrorMetadata::bad_request(
+            "WebSocketUnsupported",
+            "WebSocket connections aren't supported in this environment",
+        ))
+    }
+}
+
+/// The opening handshake for a WebSocket connection.
+#[derive(Clone, Debug)]
+pub struct WebSocketRequest {
+    /// A `ws://` or `wss://` URL.
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// Subprotocols to offer the server, in order of preference.
+    pub protocols: Vec<String>,
+}
+
+/// A data or close message on a WebSocket. Pings and pongs are handled by the
+/// client and never surface here.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WebSocketMessage {
+    Text(String),
+    Binary(bytes::Bytes),
+    Close { code: u16, reason: String },
+}
+
+pub type WebSocketSink = Pin<Box<dyn Sink<WebSocketMessage, Error = anyhow::Error> + Send>>;
+
+pub struct WebSocketConnection {
+    /// The subprotocol selected by the server, if any.
+    pub protocol: Option<String>,
+    pub sink: WebSocketSink,
+    /// Messages received from the server. The stream ends after the server's
+    /// close message or when the connection drops.
+    pub stream: BoxStream<'static, anyhow::Result<WebSocketMessage>>,
+}
+
+impl From<WebSocketMessage> for Message {
+    fn from(message: WebSocketMessage) -> Self {
+        match message {
+            WebSocketMessage::Text(text) => Message::Text(text),
+            WebSocketMessage::Binary(bytes) => Message::Binary(bytes.into()),
+            WebSocketMessage::Close { code, reason } => Message::Close(Some(CloseFrame {
+                code: CloseCode::from(code),
+                reason: reason.into(),
+            })),
+        }
+    }
+}
+
+impl WebSocketMessage {
+    fn from_tungstenite(message: Message) -> Option<Self> {
+        match message {
+            Message::Text(text) => Some(WebSocketMessage::Text(text)),
+            Message::Binary(bytes) => Some(WebSocketMessage::Binary(bytes.into())),
+            Message::Close(frame) => Some(match frame {
+                Some(frame) => WebSocketMessage::Close {
+                    code: frame.code.into(),
+                    reason: frame.reason.into_owned(),
+                },
+                // 1005 is reserved for "no status code present".
+                None => WebSocketMessage::Close {
+                    code: 1005,
+                    reason: String::new(),
+                },
+            }),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => None,
+        }
+    }
 }
 
 pub static INTERNAL_HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
 
+/// Restricts which hosts UDF `fetch` requests may contact. Hosts matching a
+/// denied pattern are always rejected. If any allowed patterns are configured,
+/// all other hosts are rejected too.
+///
+/// The policy is enforced by [`ProxiedFetchClient`], so it only covers `fetch`
+/// in Convex's runtime. Node.js actions make requests from the node executor
+/// and can contact any host it can reach.
+///
+/// Patterns are either an exact host (`api.example.com`), a wildcard matching
+/// any subdomain (`*.example.com`, which doesn't match `example.com` itself),
+/// or `*` to match every host.
+#[derive(Clone, Debug, Default)]
+pub struct FetchHostPolicy {
+    allowed: Vec<HostPattern>,
+    denied: Vec<HostPattern>,
+}
+
+impl FetchHostPolicy {
+    pub fn new(allowed: &[String], denied: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            allowed: allowed.iter().map(|p| p.parse()).try_collect()?,
+            denied: denied.iter().map(|p| p.parse()).try_collect()?,
+        })
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.allowed.is_empty() && self.denied.is_empty()
+    }
+
+    pub fn check(&self, url: &Url) -> anyhow::Result<()> {
+        if self.is_unrestricted() {
+            return Ok(());
+        }
+        let Some(host) = url.host_str() else {
+            anyhow::bail!(ErrorMetadata::forbidden(
+                "FetchHostForbidden",
+                format!("Fetch to {url} is forbidden because it has no host"),
+            ));
+        };
+        let host = normalize_host(host);
+        let allowed = !self.denied.iter().any(|p| p.matches(&host))
+            && (self.allowed.is_empty() || self.allowed.iter().any(|p| p.matches(&host)));
+        if !allowed {
+            anyhow::bail!(ErrorMetadata::forbidden(
+                "FetchHostForbidden",
+                format!("Fetch to {host} is forbidden by this deployment's fetch host policy"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum HostPattern {
+    Any,
+    Exact(String),
+    /// Matches strict subdomains of the domain.
+    Subdomains(String),
+}
+
+impl HostPattern {
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Exact(pattern) => host == pattern,
+            HostPattern::Subdomains(domain) => host
+                .strip_suffix(domain.as_str())
+                .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+        }
+    }
+}
+
+impl FromStr for HostPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let pattern = normalize_host(s.trim());
+        if pattern == "*" {
+            return Ok(HostPattern::Any);
+        }
+        let (domain, wildcard) = match pattern.strip_prefix("*.") {
+            Some(domain) => (domain.to_string(), true),
+            None => (pattern, false),
+        };
+        // Make sure the rest of the pattern is a valid host (and not, say, a URL).
+        Url::parse(&format!("http://{domain}"))
+            .ok()
+            .filter(|url| !domain.contains('*') && url.host_str() == Some(domain.as_str()))
+            .with_context(|| format!("Invalid fetch host pattern: {s:?}"))?;
+        Ok(if wildcard {
+            HostPattern::Subdomains(domain)
+        } else {
+            HostPattern::Exact(domain)
+        })
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
 pub struct ProxiedFetchClient {
     http_client:
         LazyLock<reqwest::Client, Box<dyn FnOnce() -> reqwest::Client + Send + Sync + 'static>>,
     internal_http_client: reqwest::Client,
+    host_policy: FetchHostPolicy,
+    // reqwest doesn't do WebSocket upgrades, so we tunnel WebSocket
+    // connections through the proxy ourselves.
+    proxy_url: Option<Url>,
+    client_id: String,
 }
 
 impl ProxiedFetchClient {
     pub fn new(proxy_url: Option<Url>, client_id: String) -> Self {
         Self {
+            proxy_url: proxy_url.clone(),
+            client_id: client_id.clone(),
             http_client: LazyLock::new(Box::new(move || {
                 let mut builder = reqwest::Client::builder().redirect(redirect::Policy::none());
                 // It's okay to panic on these errors, as they indicate a serious programming
@@ -79,13 +304,20 @@ impl ProxiedFetchClient {
                 builder.build().expect("Failed to build reqwest client")
             })),
             internal_http_client: INTERNAL_HTTP_CLIENT.clone(),
+            host_policy: FetchHostPolicy::default(),
         }
     }
-}
 
-#[async_trait]
-impl FetchClient for ProxiedFetchClient {
-    async fn fetch(&self, request: HttpRequestStream) -> anyhow::Result<HttpResponseStream> {
+    pub fn with_host_policy(mut self, host_policy: FetchHostPolicy) -> Self {
+        self.host_policy = host_policy;
+        self
+    }
+
+    async fn proxied_fetch(
+        &self,
+        request: HttpRequestStream,
+    ) -> anyhow::Result<HttpResponseStream> {
+        self.host_policy.check(&request.url)?;
         let mut request_builder = self
             .http_client
             .request(request.method, request.url.as_str());
@@ -94,6 +326,13 @@ impl FetchClient for ProxiedFetchClient {
         for (name, value) in &request.headers {
             request_builder = request_builder.header(name.as_str(), value.as_bytes());
         }
+        // Continue the function's trace in the service it's calling, unless the
+        // function set its own trace context.
+        if !request.headers.contains_key(TRACEPARENT_HEADER)
+            && let Some(traceparent) = EncodedSpan::from_parent().0
+        {
+            request_builder = request_builder.header(TRACEPARENT_HEADER, traceparent);
+        }
         let raw_request = request_builder.build()?;
         let raw_response = self.http_client.execute(raw_request).await?;
         if raw_response.status() == StatusCode::PROXY_AUTHENTICATION_REQUIRED {
@@ -112,6 +351,21 @@ impl FetchClient for ProxiedFetchClient {
         };
         Ok(response)
     }
+}
+
+#[async_trait]
+impl FetchClient for ProxiedFetchClient {
+    async fn fetch(&self, request: HttpRequestStream) -> anyhow::Result<HttpResponseStream> {
+        let span = Span::enter_with_local_parent("fetch")
+            .with_property(|| ("http.method", request.method.to_string()))
+            .with_property(|| {
+                (
+                    "http.host",
+                    request.url.host_str().unwrap_or_default().to_owned(),
+                )
+            });
+        self.proxied_fetch(request).in_span(span).await
+    }
 
     async fn internal_fetch(
         &self,
@@ -138,6 +392,120 @@ impl FetchClient for ProxiedFetchClient {
         };
         Ok(response)
     }
+
+    async fn connect_websocket(
+        &self,
+        request: WebSocketRequest,
+    ) -> anyhow::Result<WebSocketConnection> {
+        self.host_policy.check(&request.url)?;
+        let (Some(host), Some(port)) =
+            (request.url.host_str(), request.url.port_or_known_default())
+        else {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidWebSocketUrl",
+                format!("{} is not a valid WebSocket URL", request.url),
+            ));
+        };
+        let mut handshake = request.url.as_str().into_client_request()?;
+        let headers = handshake.headers_mut();
+        for (name, value) in &request.headers {
+            headers.append(name, value.clone());
+        }
+        if !request.protocols.is_empty() {
+            headers.insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_str(&request.protocols.join(", "))?,
+            );
+        }
+        headers.insert(USER_AGENT, HeaderValue::from_static("Convex/1.0"));
+
+        let tcp_stream = match &self.proxy_url {
+            Some(proxy_url) => {
+                connect_through_proxy(proxy_url, &self.client_id, host, port).await?
+            },
+            // IPv6 hosts are bracketed in URLs.
+            None => {
+                TcpStream::connect((host.trim_start_matches('[').trim_end_matches(']'), port))
+                    .await?
+            },
+        };
+        let (socket, response) = tokio_tungstenite::client_async_tls(handshake, tcp_stream)
+            .await
+            .map_err(|e| match e {
+                tungstenite::Error::Http(response) => ErrorMetadata::bad_request(
+                    "WebSocketHandshakeFailed",
+                    format!(
+                        "WebSocket handshake with {} failed with status {}",
+                        request.url,
+                        response.status()
+                    ),
+                )
+                .into(),
+                e => anyhow::Error::from(e),
+            })?;
+        let protocol = response
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let (sink, stream) = socket.split();
+        Ok(WebSocketConnection {
+            protocol,
+            sink: Box::pin(sink.sink_map_err(anyhow::Error::from).with(
+                |message: WebSocketMessage| future::ok::<_, anyhow::Error>(Message::from(message)),
+            )),
+            stream: stream
+                .filter_map(|message| {
+                    future::ready(match message {
+                        Ok(message) => WebSocketMessage::from_tungstenite(message).map(Ok),
+                        Err(e) => Some(Err(e.into())),
+                    })
+                })
+                .boxed(),
+        })
+    }
+}
+
+/// Opens a TCP connection to `host:port` through an HTTP `CONNECT` tunnel on
+/// the proxy.
+async fn connect_through_proxy(
+    proxy_url: &Url,
+    client_id: &str,
+    host: &str,
+    port: u16,
+) -> anyhow::Result<TcpStream> {
+    let (Some(proxy_host), Some(proxy_port)) =
+        (proxy_url.host_str(), proxy_url.port_or_known_default())
+    else {
+        anyhow::bail!("Invalid proxy URL {proxy_url}");
+    };
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let connect = format!(
+        "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\nProxy-Authorization: \
+         {client_id}\r\n\r\n"
+    );
+    stream.write_all(connect.as_bytes()).await?;
+    // Read the response head a byte at a time so we don't consume any of the
+    // tunneled stream.
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        anyhow::ensure!(head.len() < 8192, "Proxy CONNECT response too large");
+        let mut byte = [0u8];
+        if stream.read(&mut byte).await? == 0 {
+            anyhow::bail!("Proxy closed the connection during CONNECT");
+        }
+        head.push(byte[0]);
+    }
+    let status = String::from_utf8_lossy(&head)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok());
+    match status {
+        Some(200) => Ok(stream),
+        // SSRF mitigated -- see `fetch` above.
+        Some(407) => anyhow::bail!("Request to {host} forbidden"),
+        _ => anyhow::bail!("Proxy CONNECT to {host}:{port} failed: {status:?}"),
+    }
 }
 
 type HandlerFn = Box<
@@ -211,15 +579,45 @@ pub enum InternalFetchPurpose {
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
     use errors::ErrorMetadataAnyhowExt;
-    use futures::FutureExt;
+    use fastrace::{
+        collector::{
+            Config,
+            ConsoleReporter,
+            SpanContext,
+        },
+        future::FutureExt as _,
+        Span,
+    };
+    use futures::{
+        FutureExt,
+        SinkExt,
+        StreamExt,
+    };
     use http::{
+        header::SEC_WEBSOCKET_PROTOCOL,
         HeaderMap,
+        HeaderValue,
         Method,
         StatusCode,
     };
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+    use tungstenite::handshake::server::{
+        ErrorResponse,
+        Request,
+        Response,
+    };
 
-    use super::ProxiedFetchClient;
+    use super::{
+        FetchHostPolicy,
+        ProxiedFetchClient,
+        WebSocketMessage,
+        WebSocketRequest,
+    };
     use crate::http::{
         categorize_http_response_stream,
         fetch::{
@@ -232,6 +630,7 @@ mod tests {
         HttpResponseStream,
         CONVEX_CLIENT_HEADER,
         CONVEX_CLIENT_HEADER_VALUE,
+        TRACEPARENT_HEADER,
     };
 
     #[tokio::test]
@@ -254,6 +653,202 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_fetch_host_policy() -> anyhow::Result<()> {
+        let policy = FetchHostPolicy::new(
+            &["api.example.com".to_string(), "*.Stripe.com.".to_string()],
+            &["evil.stripe.com".to_string()],
+        )?;
+        let check = |url: &str| policy.check(&url.parse()?);
+        check("https://api.example.com/v1")?;
+        check("https://API.example.com./v1")?;
+        check("https://checkout.stripe.com")?;
+        check("https://a.b.stripe.com")?;
+        for url in [
+            "https://example.com",
+            "https://other.example.com",
+            "https://stripe.com",
+            "https://notstripe.com",
+            "https://evil.stripe.com",
+            "http://127.0.0.1",
+        ] {
+            let err = check(url).unwrap_err();
+            assert!(err.is_forbidden(), "{url}: {err:?}");
+            assert_eq!(err.short_msg(), "FetchHostForbidden");
+        }
+
+        // Denylists alone allow everything else.
+        let policy = FetchHostPolicy::new(&[], &["*.internal".to_string()])?;
+        policy.check(&"https://example.com".parse()?)?;
+        assert!(policy.check(&"https://db.internal".parse()?).is_err());
+
+        assert!(FetchHostPolicy::new(&["https://example.com/".to_string()], &[]).is_err());
+        assert!(FetchHostPolicy::new(&["*example.com".to_string()], &[]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_forbidden_host() -> anyhow::Result<()> {
+        let client = ProxiedFetchClient::new(None, "".to_owned())
+            .with_host_policy(FetchHostPolicy::new(&["example.com".to_string()], &[])?);
+        let request = HttpRequest {
+            headers: Default::default(),
+            url: "http://169.254.169.254/latest/meta-data".parse()?,
+            method: Method::GET,
+            body: None,
+        };
+        let err = client.fetch(request.into()).await.unwrap_err();
+        assert_eq!(err.short_msg(), "FetchHostForbidden");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sends_traceparent() -> anyhow::Result<()> {
+        // Spans are only recorded once there's a reporter.
+        fastrace::set_reporter(ConsoleReporter, Config::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        // Responds to each request with the `traceparent` header it was sent.
+        let server = tokio::spawn(async move {
+            let mut traceparents = vec![];
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await?;
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await?;
+                    anyhow::ensure!(n > 0, "The connection closed before the headers");
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request).into_owned();
+                traceparents.push(request.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case(TRACEPARENT_HEADER)
+                        .then(|| value.trim().to_string())
+                }));
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                    .await?;
+            }
+            anyhow::Ok(traceparents)
+        });
+
+        let client = ProxiedFetchClient::new(None, "".to_owned());
+        let parent = SpanContext::decode_w3c_traceparent(
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        )
+        .unwrap();
+        let url: url::Url = format!("http://{addr}/").parse()?;
+        let request = |headers| HttpRequest {
+            headers,
+            url: url.clone(),
+            method: Method::GET,
+            body: None,
+        };
+        client
+            .fetch(request(HeaderMap::new()).into())
+            .in_span(Span::root("action", parent))
+            .await?;
+        // Functions can set their own trace context.
+        let own = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        client
+            .fetch(
+                request(HeaderMap::from_iter([(
+                    http::HeaderName::from_static(TRACEPARENT_HEADER),
+                    HeaderValue::from_static(own),
+                )]))
+                .into(),
+            )
+            .in_span(Span::root("action", parent))
+            .await?;
+
+        let traceparents = server.await??;
+        let sent =
+            SpanContext::decode_w3c_traceparent(traceparents[0].as_deref().unwrap()).unwrap();
+        assert_eq!(sent.trace_id, parent.trace_id);
+        assert_eq!(traceparents[1].as_deref(), Some(own));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connect_websocket() -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let select_protocol =
+                |request: &Request, mut response: Response| -> Result<Response, ErrorResponse> {
+                    let offered = request.headers().get(SEC_WEBSOCKET_PROTOCOL).cloned();
+                    assert_eq!(offered, Some(HeaderValue::from_static("chat, superchat")));
+                    response
+                        .headers_mut()
+                        .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("chat"));
+                    Ok(response)
+                };
+            let mut socket = tokio_tungstenite::accept_hdr_async(stream, select_protocol).await?;
+            // Echo data messages. Reading on after the client's close message
+            // flushes the close reply, and then the stream ends.
+            while let Some(message) = socket.next().await {
+                let message = message?;
+                if !message.is_close() {
+                    socket.send(message).await?;
+                }
+            }
+            anyhow::Ok(())
+        });
+
+        let client = ProxiedFetchClient::new(None, "".to_owned());
+        let mut connection = client
+            .connect_websocket(WebSocketRequest {
+                url: format!("ws://{addr}/socket").parse()?,
+                headers: HeaderMap::new(),
+                protocols: vec!["chat".to_string(), "superchat".to_string()],
+            })
+            .await?;
+        assert_eq!(connection.protocol.as_deref(), Some("chat"));
+
+        let messages = [
+            WebSocketMessage::Text("hello".to_string()),
+            WebSocketMessage::Binary(Bytes::from_static(&[1, 2, 3])),
+        ];
+        for message in messages {
+            connection.sink.send(message.clone()).await?;
+            assert_eq!(connection.stream.next().await.transpose()?, Some(message));
+        }
+        connection
+            .sink
+            .send(WebSocketMessage::Close {
+                code: 1000,
+                reason: "done".to_string(),
+            })
+            .await?;
+        // The server's close reply surfaces once and then the stream ends.
+        assert!(matches!(
+            connection.stream.next().await.transpose()?,
+            Some(WebSocketMessage::Close { code: 1000, .. })
+        ));
+        assert!(connection.stream.next().await.is_none());
+        server.await?
+    }
+
+    #[tokio::test]
+    async fn test_connect_websocket_forbidden_host() -> anyhow::Result<()> {
+        let client = ProxiedFetchClient::new(None, "".to_owned())
+            .with_host_policy(FetchHostPolicy::new(&["example.com".to_string()], &[])?);
+        let Err(err) = client
+            .connect_websocket(WebSocketRequest {
+                url: "ws://127.0.0.1:1/socket".parse()?,
+                headers: HeaderMap::new(),
+                protocols: vec![],
+            })
+            .await
+        else {
+            panic!("Expected the host policy to reject the connection");
+        };
+        assert_eq!(err.short_msg(), "FetchHostForbidden");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_static_fetch_client() {
         let handler = |request: HttpRequestStream| {
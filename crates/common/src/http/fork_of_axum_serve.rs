@@ -45,17 +45,26 @@ use hyper_util::{
     rt::{
         TokioExecutor,
         TokioIo,
+        TokioTimer,
     },
     server::conn::auto::Builder,
     service::TowerToHyperService,
 };
 use tokio::{
+    io::{
+        AsyncRead,
+        AsyncWrite,
+    },
     net::{
         TcpListener,
         TcpStream,
     },
     sync::watch,
 };
+use tokio_rustls::{
+    server::TlsStream,
+    TlsAcceptor,
+};
 use tower::{
     Service,
     ServiceExt as _,
@@ -65,7 +74,89 @@ use tracing::{
     trace,
 };
 
-use crate::http::MAX_HTTP2_STREAMS;
+use crate::{
+    http::{
+        ClientCertificate,
+        ACME_TLS_ALPN_PROTOCOL,
+        MAX_HTTP2_STREAMS,
+    },
+    knobs::{
+        HTTP2_ADAPTIVE_WINDOW,
+        HTTP2_KEEP_ALIVE_INTERVAL,
+    },
+};
+
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A listener [`serve`] accepts connections from.
+pub trait Listener: Send + 'static {
+    type Io: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Accepts the next connection and the address of its peer. Returns
+    /// `None` if accepting failed and should be tried again.
+    fn accept(&mut self) -> impl Future<Output = Option<(Self::Io, SocketAddr)>> + Send;
+
+    /// Sets the value of the `TCP_NODELAY` option on an accepted connection,
+    /// if it has one.
+    fn set_nodelay(_io: &Self::Io, _nodelay: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Listener for TcpListener {
+    type Io = TcpStream;
+
+    async fn accept(&mut self) -> Option<(TcpStream, SocketAddr)> {
+        match TcpListener::accept(self).await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                handle_accept_error(e).await;
+                None
+            },
+        }
+    }
+
+    fn set_nodelay(io: &TcpStream, nodelay: bool) -> io::Result<()> {
+        io.set_nodelay(nodelay)
+    }
+}
+
+/// A Unix domain socket listener. Unix sockets have no peer address, so
+/// connections are reported as coming from `remote_addr`.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixListener {
+    pub listener: tokio::net::UnixListener,
+    pub remote_addr: SocketAddr,
+}
+
+#[cfg(unix)]
+impl Listener for UnixListener {
+    type Io = tokio::net::UnixStream;
+
+    async fn accept(&mut self) -> Option<(tokio::net::UnixStream, SocketAddr)> {
+        match self.listener.accept().await {
+            Ok((unix_stream, _)) => Some((unix_stream, self.remote_addr)),
+            Err(e) => {
+                handle_accept_error(e).await;
+                None
+            },
+        }
+    }
+}
+
+/// Builds connections that speak HTTP/1, or HTTP/2 if the client starts with
+/// the HTTP/2 connection preface.
+fn connection_builder() -> Builder<TokioExecutor> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder
+        .http2()
+        .max_concurrent_streams(MAX_HTTP2_STREAMS)
+        .adaptive_window(*HTTP2_ADAPTIVE_WINDOW)
+        .keep_alive_interval(*HTTP2_KEEP_ALIVE_INTERVAL)
+        .timer(TokioTimer::new());
+    builder
+}
 
 /// Serve the service with the supplied listener.
 ///
@@ -131,14 +222,15 @@ use crate::http::MAX_HTTP2_STREAMS;
 /// [`Handler`]: crate::handler::Handler
 /// [`HandlerWithoutStateExt::into_make_service_with_connect_info`]: crate::handler::HandlerWithoutStateExt::into_make_service_with_connect_info
 /// [`HandlerService::into_make_service_with_connect_info`]: crate::handler::HandlerService::into_make_service_with_connect_info
-pub fn serve<M, S>(tcp_listener: TcpListener, make_service: M) -> Serve<M, S>
+pub fn serve<L, M, S>(listener: L, make_service: M) -> Serve<L, M, S>
 where
-    M: for<'a> Service<IncomingStream<'a>, Error = Infallible, Response = S>,
+    L: Listener,
+    M: for<'a> Service<IncomingStream<'a, L>, Error = Infallible, Response = S>,
     S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
     S::Future: Send,
 {
     Serve {
-        tcp_listener,
+        listener,
         make_service,
         tcp_nodelay: None,
         _marker: PhantomData,
@@ -147,14 +239,14 @@ where
 
 /// Future returned by [`serve`].
 #[must_use = "futures must be awaited or polled"]
-pub struct Serve<M, S> {
-    tcp_listener: TcpListener,
+pub struct Serve<L, M, S> {
+    listener: L,
     make_service: M,
     tcp_nodelay: Option<bool>,
     _marker: PhantomData<S>,
 }
 
-impl<M, S> Serve<M, S> {
+impl<L, M, S> Serve<L, M, S> {
     /// Prepares a server to handle graceful shutdown when the provided future
     /// completes.
     ///
@@ -177,15 +269,16 @@ impl<M, S> Serve<M, S> {
     ///     // ...
     /// }
     /// ```
-    pub fn with_graceful_shutdown<F>(self, signal: F) -> WithGracefulShutdown<M, S, F>
+    pub fn with_graceful_shutdown<F>(self, signal: F) -> WithGracefulShutdown<L, M, S, F>
     where
         F: Future<Output = ()> + Send + 'static,
     {
         WithGracefulShutdown {
-            tcp_listener: self.tcp_listener,
+            listener: self.listener,
             make_service: self.make_service,
             signal,
             tcp_nodelay: self.tcp_nodelay,
+            tls_acceptor: None,
             _marker: PhantomData,
         }
     }
@@ -217,30 +310,32 @@ impl<M, S> Serve<M, S> {
     }
 }
 
-impl<M, S> Debug for Serve<M, S>
+impl<L, M, S> Debug for Serve<L, M, S>
 where
+    L: Debug,
     M: Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
-            tcp_listener,
+            listener,
             make_service,
             tcp_nodelay,
             _marker: _,
         } = self;
 
         f.debug_struct("Serve")
-            .field("tcp_listener", tcp_listener)
+            .field("listener", listener)
             .field("make_service", make_service)
             .field("tcp_nodelay", tcp_nodelay)
             .finish()
     }
 }
 
-impl<M, S> IntoFuture for Serve<M, S>
+impl<L, M, S> IntoFuture for Serve<L, M, S>
 where
-    M: for<'a> Service<IncomingStream<'a>, Error = Infallible, Response = S> + Send + 'static,
-    for<'a> <M as Service<IncomingStream<'a>>>::Future: Send,
+    L: Listener,
+    M: for<'a> Service<IncomingStream<'a, L>, Error = Infallible, Response = S> + Send + 'static,
+    for<'a> <M as Service<IncomingStream<'a, L>>>::Future: Send,
     S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
     S::Future: Send,
 {
@@ -250,25 +345,25 @@ where
     fn into_future(self) -> Self::IntoFuture {
         private::ServeFuture(Box::pin(async move {
             let Self {
-                tcp_listener,
+                mut listener,
                 mut make_service,
                 tcp_nodelay,
                 _marker: _,
             } = self;
 
             loop {
-                let (tcp_stream, remote_addr) = match tcp_accept(&tcp_listener).await {
+                let (io, remote_addr) = match listener.accept().await {
                     Some(conn) => conn,
                     None => continue,
                 };
 
                 if let Some(nodelay) = tcp_nodelay {
-                    if let Err(err) = tcp_stream.set_nodelay(nodelay) {
+                    if let Err(err) = L::set_nodelay(&io, nodelay) {
                         trace!("failed to set TCP_NODELAY on incoming connection: {err:#}");
                     }
                 }
 
-                let tcp_stream = TokioIo::new(tcp_stream);
+                let io = TokioIo::new(io);
 
                 poll_fn(|cx| make_service.poll_ready(cx))
                     .await
@@ -276,7 +371,7 @@ where
 
                 let tower_service = make_service
                     .call(IncomingStream {
-                        tcp_stream: &tcp_stream,
+                        io: &io,
                         remote_addr,
                     })
                     .await
@@ -285,13 +380,12 @@ where
 
                 let hyper_service = TowerToHyperService::new(tower_service);
 
-                let mut builder = Builder::new(TokioExecutor::new());
-                builder.http2().max_concurrent_streams(MAX_HTTP2_STREAMS);
+                let builder = connection_builder();
 
                 tokio::spawn(async move {
                     match builder
                         // upgrades needed for websockets
-                        .serve_connection_with_upgrades(tcp_stream, hyper_service)
+                        .serve_connection_with_upgrades(io, hyper_service)
                         .await
                     {
                         Ok(()) => {},
@@ -313,15 +407,16 @@ where
 
 /// Serve future with graceful shutdown enabled.
 #[must_use = "futures must be awaited or polled"]
-pub struct WithGracefulShutdown<M, S, F> {
-    tcp_listener: TcpListener,
+pub struct WithGracefulShutdown<L, M, S, F> {
+    listener: L,
     make_service: M,
     signal: F,
     tcp_nodelay: Option<bool>,
+    tls_acceptor: Option<TlsAcceptor>,
     _marker: PhantomData<S>,
 }
 
-impl<M, S, F> WithGracefulShutdown<M, S, F> {
+impl<L, M, S, F> WithGracefulShutdown<L, M, S, F> {
     /// Instructs the server to set the value of the `TCP_NODELAY` option on
     /// every accepted connection.
     ///
@@ -352,36 +447,49 @@ impl<M, S, F> WithGracefulShutdown<M, S, F> {
             ..self
         }
     }
+
+    /// Serves HTTPS instead of HTTP, with the TLS handshake done in each
+    /// connection's task.
+    pub fn tls(self, tls_acceptor: TlsAcceptor) -> Self {
+        Self {
+            tls_acceptor: Some(tls_acceptor),
+            ..self
+        }
+    }
 }
 
-impl<M, S, F> Debug for WithGracefulShutdown<M, S, F>
+impl<L, M, S, F> Debug for WithGracefulShutdown<L, M, S, F>
 where
+    L: Debug,
     M: Debug,
     S: Debug,
     F: Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
-            tcp_listener,
+            listener,
             make_service,
             signal,
             tcp_nodelay,
+            tls_acceptor,
             _marker: _,
         } = self;
 
         f.debug_struct("WithGracefulShutdown")
-            .field("tcp_listener", tcp_listener)
+            .field("listener", listener)
             .field("make_service", make_service)
             .field("signal", signal)
             .field("tcp_nodelay", tcp_nodelay)
+            .field("tls", &tls_acceptor.is_some())
             .finish()
     }
 }
 
-impl<M, S, F> IntoFuture for WithGracefulShutdown<M, S, F>
+impl<L, M, S, F> IntoFuture for WithGracefulShutdown<L, M, S, F>
 where
-    M: for<'a> Service<IncomingStream<'a>, Error = Infallible, Response = S> + Send + 'static,
-    for<'a> <M as Service<IncomingStream<'a>>>::Future: Send,
+    L: Listener,
+    M: for<'a> Service<IncomingStream<'a, L>, Error = Infallible, Response = S> + Send + 'static,
+    for<'a> <M as Service<IncomingStream<'a, L>>>::Future: Send,
     S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
     S::Future: Send,
     F: Future<Output = ()> + Send + 'static,
@@ -391,10 +499,11 @@ where
 
     fn into_future(self) -> Self::IntoFuture {
         let Self {
-            tcp_listener,
+            mut listener,
             mut make_service,
             signal,
             tcp_nodelay,
+            tls_acceptor,
             _marker: _,
         } = self;
 
@@ -410,8 +519,8 @@ where
 
         private::ServeFuture(Box::pin(async move {
             loop {
-                let (tcp_stream, remote_addr) = tokio::select! {
-                    conn = tcp_accept(&tcp_listener) => {
+                let (io, remote_addr) = tokio::select! {
+                    conn = listener.accept() => {
                         match conn {
                             Some(conn) => conn,
                             None => continue,
@@ -424,12 +533,12 @@ where
                 };
 
                 if let Some(nodelay) = tcp_nodelay {
-                    if let Err(err) = tcp_stream.set_nodelay(nodelay) {
+                    if let Err(err) = L::set_nodelay(&io, nodelay) {
                         trace!("failed to set TCP_NODELAY on incoming connection: {err:#}");
                     }
                 }
 
-                let tcp_stream = TokioIo::new(tcp_stream);
+                let io = TokioIo::new(io);
 
                 trace!("connection {remote_addr} accepted");
 
@@ -439,23 +548,48 @@ where
 
                 let tower_service = make_service
                     .call(IncomingStream {
-                        tcp_stream: &tcp_stream,
+                        io: &io,
                         remote_addr,
                     })
                     .await
-                    .unwrap_or_else(|err| match err {})
-                    .map_request(|req: Request<Incoming>| req.map(Body::new));
-
-                let hyper_service = TowerToHyperService::new(tower_service);
+                    .unwrap_or_else(|err| match err {});
 
                 let signal_tx = Arc::clone(&signal_tx);
 
                 let close_rx = close_rx.clone();
 
+                let tls_acceptor = tls_acceptor.clone();
+
                 tokio::spawn(async move {
-                    let mut builder = Builder::new(TokioExecutor::new());
-                    builder.http2().max_concurrent_streams(MAX_HTTP2_STREAMS);
-                    let conn = builder.serve_connection_with_upgrades(tcp_stream, hyper_service);
+                    let (io, client_certificate): (Box<dyn Io>, _) = match tls_acceptor {
+                        Some(tls_acceptor) => {
+                            match tls_accept(&tls_acceptor, io.into_inner()).await {
+                                Some(tls_stream) => {
+                                    let client_certificate = tls_stream
+                                        .get_ref()
+                                        .1
+                                        .peer_certificates()
+                                        .and_then(|certs| certs.first())
+                                        .map(|cert| ClientCertificate(cert.clone()));
+                                    (Box::new(tls_stream), client_certificate)
+                                },
+                                None => return,
+                            }
+                        },
+                        None => (Box::new(io.into_inner()), None),
+                    };
+                    let hyper_service = TowerToHyperService::new(tower_service.map_request(
+                        move |req: Request<Incoming>| {
+                            let mut req = req.map(Body::new);
+                            if let Some(client_certificate) = &client_certificate {
+                                req.extensions_mut().insert(client_certificate.clone());
+                            }
+                            req
+                        },
+                    ));
+                    let builder = connection_builder();
+                    let conn =
+                        builder.serve_connection_with_upgrades(TokioIo::new(io), hyper_service);
                     pin_mut!(conn);
 
                     let signal_closed = signal_tx.closed().fuse();
@@ -483,7 +617,7 @@ where
             }
 
             drop(close_rx);
-            drop(tcp_listener);
+            drop(listener);
 
             trace!(
                 "waiting for {} task(s) to finish",
@@ -496,6 +630,34 @@ where
     }
 }
 
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// Returns `None` if the handshake fails, or if the client is an ACME server
+/// that only needed the handshake to validate the domain.
+async fn tls_accept<I: AsyncRead + AsyncWrite + Unpin>(
+    tls_acceptor: &TlsAcceptor,
+    io: I,
+) -> Option<TlsStream<I>> {
+    let tls_stream =
+        match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls_acceptor.accept(io)).await {
+            Ok(Ok(tls_stream)) => tls_stream,
+            Ok(Err(_err)) => {
+                trace!("TLS handshake failed: {_err:#}");
+                return None;
+            },
+            Err(_) => {
+                trace!("TLS handshake timed out");
+                return None;
+            },
+        };
+    if tls_stream.get_ref().1.alpn_protocol() == Some(ACME_TLS_ALPN_PROTOCOL) {
+        return None;
+    }
+    Some(tls_stream)
+}
+
 fn is_connection_error(e: &io::Error) -> bool {
     matches!(
         e.kind(),
@@ -505,30 +667,24 @@ fn is_connection_error(e: &io::Error) -> bool {
     )
 }
 
-async fn tcp_accept(listener: &TcpListener) -> Option<(TcpStream, SocketAddr)> {
-    match listener.accept().await {
-        Ok(conn) => Some(conn),
-        Err(e) => {
-            if is_connection_error(&e) {
-                return None;
-            }
-
-            // [From `hyper::Server` in 0.14](https://github.com/hyperium/hyper/blob/v0.14.27/src/server/tcp.rs#L186)
-            //
-            // > A possible scenario is that the process has hit the max open files
-            // > allowed, and so trying to accept a new connection will fail with
-            // > `EMFILE`. In some cases, it's preferable to just wait for some time, if
-            // > the application will likely close some files (or connections), and try
-            // > to accept the connection again. If this option is `true`, the error
-            // > will be logged at the `error` level, since it is still a big deal,
-            // > and then the listener will sleep for 1 second.
-            //
-            // hyper allowed customizing this but axum does not.
-            error!("accept error: {e}");
-            tokio::time::sleep(Duration::from_secs(1)).await;
-            None
-        },
+async fn handle_accept_error(e: io::Error) {
+    if is_connection_error(&e) {
+        return;
     }
+
+    // [From `hyper::Server` in 0.14](https://github.com/hyperium/hyper/blob/v0.14.27/src/server/tcp.rs#L186)
+    //
+    // > A possible scenario is that the process has hit the max open files
+    // > allowed, and so trying to accept a new connection will fail with
+    // > `EMFILE`. In some cases, it's preferable to just wait for some time, if
+    // > the application will likely close some files (or connections), and try
+    // > to accept the connection again. If this option is `true`, the error
+    // > will be logged at the `error` level, since it is still a big deal,
+    // > and then the listener will sleep for 1 second.
+    //
+    // hyper allowed customizing this but axum does not.
+    error!("accept error: {e}");
+    tokio::time::sleep(Duration::from_secs(1)).await;
 }
 
 mod private {
@@ -565,16 +721,23 @@ mod private {
 /// Used with [`serve`] and [`IntoMakeServiceWithConnectInfo`].
 ///
 /// [`IntoMakeServiceWithConnectInfo`]: crate::extract::connect_info::IntoMakeServiceWithConnectInfo
-#[derive(Debug)]
-pub struct IncomingStream<'a> {
-    tcp_stream: &'a TokioIo<TcpStream>,
+pub struct IncomingStream<'a, L: Listener = TcpListener> {
+    io: &'a TokioIo<L::Io>,
     remote_addr: SocketAddr,
 }
 
-impl IncomingStream<'_> {
-    /// Returns the local address that this stream is bound to.
-    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
-        self.tcp_stream.inner().local_addr()
+impl<L: Listener> Debug for IncomingStream<'_, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncomingStream")
+            .field("remote_addr", &self.remote_addr)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<L: Listener> IncomingStream<'_, L> {
+    /// Returns the connection that was accepted.
+    pub fn io(&self) -> &L::Io {
+        self.io.inner()
     }
 
     /// Returns the remote address that this stream is bound to.
@@ -583,8 +746,15 @@ impl IncomingStream<'_> {
     }
 }
 
+impl IncomingStream<'_, TcpListener> {
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.io.inner().local_addr()
+    }
+}
+
 const _: () = {
-    impl Service<IncomingStream<'_>> for Router<()> {
+    impl<L: Listener> Service<IncomingStream<'_, L>> for Router<()> {
         type Error = Infallible;
         type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
         type Response = Self;
@@ -593,7 +763,7 @@ const _: () = {
             Poll::Ready(Ok(()))
         }
 
-        fn call(&mut self, _req: IncomingStream<'_>) -> Self::Future {
+        fn call(&mut self, _req: IncomingStream<'_, L>) -> Self::Future {
             // call `Router::with_state` such that everything is turned into `Route` eagerly
             // rather than doing that per request
             std::future::ready(Ok(self.clone().with_state(())))
@@ -602,15 +772,15 @@ const _: () = {
 };
 
 const _: () = {
-    impl Connected<IncomingStream<'_>> for SocketAddr {
-        fn connect_info(target: IncomingStream<'_>) -> Self {
+    impl<L: Listener> Connected<IncomingStream<'_, L>> for SocketAddr {
+        fn connect_info(target: IncomingStream<'_, L>) -> Self {
             target.remote_addr()
         }
     }
 };
 
 const _: () = {
-    impl Service<IncomingStream<'_>> for MethodRouter<()> {
+    impl<L: Listener> Service<IncomingStream<'_, L>> for MethodRouter<()> {
         type Error = Infallible;
         type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
         type Response = Self;
@@ -619,15 +789,16 @@ const _: () = {
             Poll::Ready(Ok(()))
         }
 
-        fn call(&mut self, _req: IncomingStream<'_>) -> Self::Future {
+        fn call(&mut self, _req: IncomingStream<'_, L>) -> Self::Future {
             std::future::ready(Ok(self.clone().with_state(())))
         }
     }
 };
 
 const _: () = {
-    impl<H, T, S> Service<IncomingStream<'_>> for HandlerService<H, T, S>
+    impl<H, T, S, L> Service<IncomingStream<'_, L>> for HandlerService<H, T, S>
     where
+        L: Listener,
         H: Clone,
         S: Clone,
     {
@@ -639,7 +810,7 @@ const _: () = {
             Poll::Ready(Ok(()))
         }
 
-        fn call(&mut self, _req: IncomingStream<'_>) -> Self::Future {
+        fn call(&mut self, _req: IncomingStream<'_, L>) -> Self::Future {
             std::future::ready(Ok(self.clone()))
         }
     }
@@ -41,7 +41,6 @@ use axum::{
     BoxError,
     RequestPartsExt,
     Router,
-    ServiceExt,
 };
 use errors::{
     ErrorMetadata,
@@ -86,7 +85,14 @@ use serde::{
     Deserialize,
     Serialize,
 };
-use tokio::net::TcpSocket;
+use tokio::net::{
+    TcpListener,
+    TcpSocket,
+};
+use tokio_rustls::{
+    rustls::pki_types::CertificateDer,
+    TlsAcceptor,
+};
 use tower::{
     limit::GlobalConcurrencyLimitLayer,
     timeout::TimeoutLayer,
@@ -120,6 +126,17 @@ pub mod fork_of_axum_serve;
 
 const MAX_HTTP2_STREAMS: u32 = 1024;
 
+/// ALPN protocol ACME servers negotiate to validate a domain with the
+/// `tls-alpn-01` challenge. These connections end after the TLS handshake.
+pub const ACME_TLS_ALPN_PROTOCOL: &[u8] = b"acme-tls/1";
+
+/// The leaf certificate a client presented in the TLS handshake, added to the
+/// extensions of each request on that connection. It was verified by the
+/// listener's client certificate verifier, so requests on listeners without
+/// one never have it.
+#[derive(Clone, Debug)]
+pub struct ClientCertificate(pub CertificateDer<'static>);
+
 pub use sync_types::headers::{
     DEPRECATION_MSG_HEADER_NAME,
     DEPRECATION_STATE_HEADER_NAME,
@@ -426,6 +443,11 @@ fn status_code_strategy() -> impl Strategy<Value = StatusCode> {
     proptest_http::ArbitraryStatusCode::arbitrary().prop_map(|v| v.0)
 }
 
+/// The `code` of an error response, attached to it as an extension so
+/// middleware can tell errors apart without parsing the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseErrorCode(pub Cow<'static, str>);
+
 /// `HttpError` is used as a vehicle for getting client facing error messages
 /// to clients on the HTTP protocol. Errors that are tagged with ErrorMetadata
 /// can be used to build these.
@@ -473,14 +495,17 @@ impl HttpError {
         if self.msg.is_empty() && self.error_code.is_empty() {
             self.status_code.into_response()
         } else {
-            (
+            let error_code = ResponseErrorCode(self.error_code.clone());
+            let mut response = (
                 self.status_code,
                 extract::Json(ResponseErrorMessage {
                     code: self.error_code,
                     message: self.msg,
                 }),
             )
-                .into_response()
+                .into_response();
+            response.extensions_mut().insert(error_code);
+            response
         }
     }
 
@@ -584,6 +609,7 @@ impl RouteMapper for NoopRouteMapper {
 pub struct ConvexHttpService {
     router: Router,
     meta_routes_enabled: bool,
+    metrics_route_enabled: bool,
     version: String,
     service_name: &'static str,
     _concurrency_gauge: Option<PullingGauge>,
@@ -642,6 +668,7 @@ impl ConvexHttpService {
             _concurrency_gauge: Some(concurrency_gauge),
             service_name,
             meta_routes_enabled: true,
+            metrics_route_enabled: true,
         }
     }
 
@@ -649,12 +676,21 @@ impl ConvexHttpService {
         self.meta_routes_enabled = enabled;
     }
 
+    /// Whether the meta routes include `/metrics`, for services that shouldn't
+    /// expose the process's metrics to whoever can reach them.
+    pub fn set_metrics_route_enabled(&mut self, enabled: bool) {
+        self.metrics_route_enabled = enabled;
+    }
+
     /// Routes not handled by the passed-in router.
     fn meta_routes(&self) -> Router {
         let version = self.version.clone();
-        Router::new()
-            .route("/version", get(move || async move { version }))
-            .route("/metrics", get(metrics))
+        let router = Router::new().route("/version", get(move || async move { version }));
+        if self.metrics_route_enabled {
+            router.route("/metrics", get(metrics))
+        } else {
+            router
+        }
     }
 
     pub async fn serve<F: Future<Output = ()> + Send + 'static>(
@@ -672,6 +708,23 @@ impl ConvexHttpService {
         serve_http(make_svc, addr, shutdown).await
     }
 
+    /// Like [`Self::serve`], but serving HTTPS with `tls_acceptor`.
+    pub async fn serve_https<F: Future<Output = ()> + Send + 'static>(
+        self,
+        addr: SocketAddr,
+        tls_acceptor: TlsAcceptor,
+        shutdown: F,
+    ) -> anyhow::Result<()> {
+        let extra = self.meta_routes();
+        let mut router = self.router;
+        if self.meta_routes_enabled {
+            router = router.merge(extra);
+        }
+        let make_svc = router.into_make_service_with_connect_info::<SocketAddr>();
+        tracing::info!("{} listening for HTTPS on {addr}", self.service_name);
+        serve_https(make_svc, addr, tls_acceptor, shutdown).await
+    }
+
     /// Apply `middleware_fn` to incoming requests *before* passing them to
     /// the router. Because the middleware is applied before routing, it is
     /// allowed to change the request URI and affect which route will be
@@ -686,31 +739,36 @@ impl ConvexHttpService {
         F: Future<Output = ()> + Send + 'static,
         Fut: Future<Output = Result<http::Request<Body>, Rejection>> + Send + 'static,
         Rejection: IntoResponse + Send + 'static,
+    {
+        tracing::info!("{} listening on {addr}", self.service_name);
+        let router = self.into_router_with_middleware(middleware_fn);
+        serve_http(
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+            addr,
+            shutdown,
+        )
+        .await
+    }
+
+    /// The router [`Self::serve_with_middleware`] serves, for serving it on
+    /// more than one listener.
+    pub fn into_router_with_middleware<Fut, Rejection>(
+        self,
+        middleware_fn: impl FnMut(http::Request<Body>) -> Fut + Clone + Send + 'static,
+    ) -> Router
+    where
+        Fut: Future<Output = Result<http::Request<Body>, Rejection>> + Send + 'static,
+        Rejection: IntoResponse + Send + 'static,
     {
         let middleware = axum::middleware::map_request(middleware_fn);
         let meta_router = self.meta_routes();
         let wrapped_svc = middleware.layer(self.router);
-
-        tracing::info!("{} listening on {addr}", self.service_name);
         if self.meta_routes_enabled {
             // Fall back to the middleware-wrapped service if the request doesn't match the
             // meta router.
-            serve_http(
-                meta_router
-                    .fallback_service(wrapped_svc)
-                    .into_make_service_with_connect_info::<SocketAddr>(),
-                addr,
-                shutdown,
-            )
-            .await
+            meta_router.fallback_service(wrapped_svc)
         } else {
-            // If we're not serving meta routes, simply serve the middleware-wrapped service
-            serve_http(
-                wrapped_svc.into_make_service_with_connect_info::<SocketAddr>(),
-                addr,
-                shutdown,
-            )
-            .await
+            Router::new().fallback_service(wrapped_svc)
         }
     }
 
@@ -720,6 +778,7 @@ impl ConvexHttpService {
             router,
             version: String::new(),
             meta_routes_enabled: true,
+            metrics_route_enabled: true,
             service_name: "test-service",
             _concurrency_gauge: None,
         }
@@ -745,6 +804,99 @@ where
     <R as Service<http::Request<Body>>>::Future: Send,
     F: Future<Output = ()> + Send + 'static,
 {
+    serve_listener(make_service, listen(addr)?, shutdown).await
+}
+
+/// Like `serve_http`, but on a listener that's already bound, e.g. to port 0.
+pub async fn serve_listener<F, R>(
+    make_service: IntoMakeServiceWithConnectInfo<R, SocketAddr>,
+    listener: TcpListener,
+    shutdown: F,
+) -> anyhow::Result<()>
+where
+    R: Service<http::Request<Body>, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    <R as Service<http::Request<Body>>>::Future: Send,
+    F: Future<Output = ()> + Send + 'static,
+{
+    fork_of_axum_serve::serve(listener, make_service)
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
+
+/// Serves an HTTPS server using the given service, terminating TLS with
+/// `tls_acceptor`.
+pub async fn serve_https<F, R>(
+    make_service: IntoMakeServiceWithConnectInfo<R, SocketAddr>,
+    addr: SocketAddr,
+    tls_acceptor: TlsAcceptor,
+    shutdown: F,
+) -> anyhow::Result<()>
+where
+    R: Service<http::Request<Body>, Response = Response, Error = Infallible>
+        + Send
+        + Clone
+        + 'static,
+    <R as Service<http::Request<Body>>>::Future: Send,
+    F: Future<Output = ()> + Send + 'static,
+{
+    fork_of_axum_serve::serve(listen(addr)?, make_service)
+        .with_graceful_shutdown(shutdown)
+        .tls(tls_acceptor)
+        .await?;
+    Ok(())
+}
+
+/// Serves HTTP on a Unix domain socket at `path`, for reverse proxies on the
+/// same machine, with the socket's permissions set to `mode`. Unix sockets
+/// have no peer address, so requests have a loopback `ConnectInfo`.
+#[cfg(unix)]
+pub async fn serve_unix<F>(
+    router: Router,
+    path: &std::path::Path,
+    mode: u32,
+    shutdown: F,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    use std::{
+        fs,
+        io,
+        os::unix::fs::{
+            FileTypeExt,
+            PermissionsExt,
+        },
+    };
+
+    // Remove the socket a previous run left behind, which would fail the bind.
+    // Anything else at `path` is left alone.
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => anyhow::bail!("{} exists and isn't a Unix socket", path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e.into()),
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind Unix socket at {}", path.display()))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    let listener = fork_of_axum_serve::UnixListener {
+        listener,
+        remote_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+    };
+    fork_of_axum_serve::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await?;
+    Ok(())
+}
+
+fn listen(addr: SocketAddr) -> anyhow::Result<TcpListener> {
     // Set SO_REUSEADDR and a bounded TCP accept backlog for our server's listening
     // socket.
     let socket = TcpSocket::new_v4()?;
@@ -752,12 +904,7 @@ where
     // Set TCP_NODELAY on accepted connections.
     socket.set_nodelay(true)?;
     socket.bind(addr)?;
-    let listener = socket.listen(*HTTP_SERVER_TCP_BACKLOG)?;
-
-    fork_of_axum_serve::serve(listener, make_service)
-        .with_graceful_shutdown(shutdown)
-        .await?;
-    Ok(())
+    Ok(socket.listen(*HTTP_SERVER_TCP_BACKLOG)?)
 }
 
 async fn client_version_state_middleware(
@@ -1231,17 +1378,75 @@ where
 
 #[cfg(test)]
 mod tests {
-    use axum::response::IntoResponse;
+    use std::{
+        collections::BTreeMap,
+        convert::Infallible,
+        net::SocketAddr,
+        time::Duration,
+    };
+
+    use axum::{
+        body::Body,
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
     use errors::{
         ErrorMetadata,
         INTERNAL_SERVER_ERROR,
         INTERNAL_SERVER_ERROR_MSG,
     };
-    use http::StatusCode;
-
-    use super::HttpResponseError;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use tokio::{
+        io::{
+            AsyncReadExt,
+            AsyncWriteExt,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+        },
+        sync::oneshot,
+    };
+    use tower::ServiceExt;
+
+    #[cfg(unix)]
+    use super::serve_unix;
+    use super::{
+        serve_listener,
+        ConvexHttpService,
+        HttpResponseError,
+        MAX_HTTP2_STREAMS,
+    };
     use crate::http::HttpError;
 
+    async fn meta_route_status(
+        metrics_route_enabled: bool,
+        path: &str,
+    ) -> anyhow::Result<StatusCode> {
+        let mut service = ConvexHttpService::new_for_test(Router::new());
+        service.set_metrics_route_enabled(metrics_route_enabled);
+        let router = service.into_router_with_middleware(|req| async { Ok::<_, Infallible>(req) });
+        let response = router
+            .oneshot(Request::builder().uri(path).body(Body::empty())?)
+            .await?;
+        Ok(response.status())
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route() -> anyhow::Result<()> {
+        assert_eq!(meta_route_status(true, "/metrics").await?, StatusCode::OK);
+        assert_eq!(
+            meta_route_status(false, "/metrics").await?,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(meta_route_status(false, "/version").await?, StatusCode::OK);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_http_response_error_internal_server_error() -> anyhow::Result<()> {
         let err_text = "some random error";
@@ -1306,4 +1511,178 @@ mod tests {
         assert_eq!(error.message(), msg);
         Ok(())
     }
+
+    const DATA: u8 = 0x0;
+    const HEADERS: u8 = 0x1;
+    const SETTINGS: u8 = 0x4;
+    const PING: u8 = 0x6;
+    const END_STREAM: u8 = 0x1;
+    const ACK: u8 = 0x1;
+    const END_HEADERS: u8 = 0x4;
+    const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+
+    fn h2_frame(frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend([frame_type, flags]);
+        frame.extend(stream_id.to_be_bytes());
+        frame.extend(payload);
+        frame
+    }
+
+    async fn read_h2_frame(stream: &mut TcpStream) -> anyhow::Result<(u8, u8, u32, Vec<u8>)> {
+        let mut header = [0; 9];
+        stream.read_exact(&mut header).await?;
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let stream_id =
+            u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7fff_ffff;
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).await?;
+        Ok((header[3], header[4], stream_id, payload))
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_serve_unix() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        use axum::extract::ConnectInfo;
+        use tokio::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("convex-test-{}.sock", std::process::id()));
+        let router = Router::new().route(
+            "/",
+            get(|ConnectInfo(addr): ConnectInfo<SocketAddr>| async move { addr.ip().to_string() }),
+        );
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn({
+            let path = path.clone();
+            async move {
+                serve_unix(router, &path, 0o600, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+            }
+        });
+
+        let mut stream = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match UnixStream::connect(&path).await {
+                    Ok(stream) => return stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await?;
+        assert_eq!(
+            std::fs::metadata(&path)?.permissions().mode() & 0o777,
+            0o600
+        );
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.ends_with("\r\n\r\n127.0.0.1"), "{response}");
+
+        let _ = shutdown_tx.send(());
+        server.await??;
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    /// Clients may speak HTTP/2 without TLS by starting with the HTTP/2
+    /// preface ("prior knowledge"), which the server must accept with the
+    /// HTTP/2 knobs applied.
+    #[tokio::test]
+    async fn test_h2c_prior_knowledge() -> anyhow::Result<()> {
+        std::env::set_var("HTTP2_KEEP_ALIVE_INTERVAL_SECS", "1");
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let router = Router::new().route("/", get(|| async { "ok" }));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_listener(
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+            listener,
+            async {
+                let _ = shutdown_rx.await;
+            },
+        ));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        stream.write_all(&h2_frame(SETTINGS, 0, 0, &[])).await?;
+        // GET http://localhost/, HPACK encoded with the static table.
+        let mut header_block = vec![0x82, 0x86, 0x84, 0x41, 9];
+        header_block.extend(b"localhost");
+        stream
+            .write_all(&h2_frame(
+                HEADERS,
+                END_HEADERS | END_STREAM,
+                1,
+                &header_block,
+            ))
+            .await?;
+
+        let mut settings = None;
+        let mut status = None;
+        let mut body = vec![];
+        let mut body_done = false;
+        let mut pinged = false;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while settings.is_none() || !body_done || !pinged {
+                let (frame_type, flags, stream_id, payload) = read_h2_frame(&mut stream).await?;
+                match frame_type {
+                    SETTINGS if flags & ACK == 0 => {
+                        let values: BTreeMap<u16, u32> = payload
+                            .chunks(6)
+                            .map(|setting| {
+                                (
+                                    u16::from_be_bytes([setting[0], setting[1]]),
+                                    u32::from_be_bytes([
+                                        setting[2], setting[3], setting[4], setting[5],
+                                    ]),
+                                )
+                            })
+                            .collect();
+                        settings = Some(values);
+                        stream.write_all(&h2_frame(SETTINGS, ACK, 0, &[])).await?;
+                    },
+                    HEADERS if stream_id == 1 => status = payload.first().copied(),
+                    DATA if stream_id == 1 => {
+                        body.extend(payload);
+                        body_done = flags & END_STREAM != 0;
+                    },
+                    // Keep-alive pings start once the connection is idle.
+                    PING if flags & ACK == 0 => {
+                        pinged = true;
+                        stream.write_all(&h2_frame(PING, ACK, 0, &payload)).await?;
+                    },
+                    _ => {},
+                }
+            }
+            anyhow::Ok(())
+        })
+        .await??;
+
+        // 0x88 is `:status: 200` from the static table.
+        assert_eq!(status, Some(0x88));
+        assert_eq!(body, b"ok");
+        let settings = settings.unwrap();
+        assert_eq!(
+            settings.get(&SETTINGS_MAX_CONCURRENT_STREAMS),
+            Some(&MAX_HTTP2_STREAMS)
+        );
+        // With an adaptive window, the windows start at the spec's default
+        // size and grow from there.
+        assert_eq!(settings.get(&SETTINGS_INITIAL_WINDOW_SIZE), Some(&65_535));
+
+        drop(stream);
+        let _ = shutdown_tx.send(());
+        server.await??;
+        Ok(())
+    }
 }
@@ -27,6 +27,8 @@ use crate::types::MemberId;
 pub enum InertIdentity {
     /// Admin for an instance.
     InstanceAdmin(String),
+    /// API key, by its id.
+    ApiKey(String),
     /// System admin.
     System,
     /// Unknown.
@@ -66,6 +68,8 @@ impl HeapSize for InertIdentity {
 pub enum IdentityCacheKey {
     /// Admin for an instance.
     InstanceAdmin(String),
+    /// API key, by its id.
+    ApiKey(String),
     /// System admin.
     System,
     /// Unknown.
@@ -78,6 +82,7 @@ impl HeapSize for IdentityCacheKey {
     fn heap_size(&self) -> usize {
         match self {
             IdentityCacheKey::InstanceAdmin(s) => s.heap_size(),
+            IdentityCacheKey::ApiKey(s) => s.heap_size(),
             IdentityCacheKey::System => 0,
             IdentityCacheKey::Unknown => 0,
             IdentityCacheKey::User(u) => u.heap_size(),
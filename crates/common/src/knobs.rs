@@ -20,7 +20,15 @@ use std::{
 
 use cmd_util::env::env_config;
 
-use crate::fastrace_helpers::SamplingConfig;
+use crate::{
+    concurrency_limits::FunctionConcurrencyLimits,
+    fastrace_helpers::SamplingConfig,
+    function_timeouts::FunctionTimeouts,
+    scheduled_job_queues::{
+        ScheduledJobOutstandingLimits,
+        ScheduledJobQueueLimits,
+    },
+};
 
 /// This exists solely to allow knobs to have separate defaults for local
 /// execution and prod (running in Nomad). Don't export this outside of
@@ -91,6 +99,31 @@ pub static UDF_METRICS_MAX_DURATION: LazyLock<Duration> = LazyLock::new(|| {
 pub static UDF_METRICS_SIGNIFICANT_FIGURES: LazyLock<u8> =
     LazyLock::new(|| env_config("UDF_METRICS_SIGNIFICANT_FIGURES", 2));
 
+/// Queries and mutations that take at least this long are recorded in the
+/// slow function log. 0 turns this threshold off.
+pub static SLOW_FUNCTION_LOG_EXECUTION_TIME: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_millis(env_config("SLOW_FUNCTION_LOG_EXECUTION_TIME_MS", 1000))
+});
+
+/// Queries and mutations that scan at least this many documents are recorded
+/// in the slow function log. 0 turns this threshold off.
+pub static SLOW_FUNCTION_LOG_DOCUMENTS_SCANNED: LazyLock<u64> =
+    LazyLock::new(|| env_config("SLOW_FUNCTION_LOG_DOCUMENTS_SCANNED", 10_000));
+
+/// Queries and mutations that read at least this many bytes from the database
+/// are recorded in the slow function log. 0 turns this threshold off.
+pub static SLOW_FUNCTION_LOG_READ_BYTES: LazyLock<u64> =
+    LazyLock::new(|| env_config("SLOW_FUNCTION_LOG_READ_BYTES", 8 << 20));
+
+/// How many slow function log records to keep in memory.
+pub static MAX_SLOW_FUNCTION_LOG_RECORDS: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_SLOW_FUNCTION_LOG_RECORDS", 1000));
+
+/// How many groups of similar function errors to keep in memory. When there
+/// are more, the group seen least recently is dropped.
+pub static MAX_ERROR_GROUPS: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_ERROR_GROUPS", 1000));
+
 /// How often to flush function activity reports to analytics (in seconds).
 pub static UDF_ANALYTICS_POLL_TIME: LazyLock<u64> =
     LazyLock::new(|| env_config("UDF_ANALYTICS_POLL_TIME", 60));
@@ -148,6 +181,11 @@ pub static UDF_EXECUTOR_OCC_INITIAL_BACKOFF: LazyLock<Duration> =
 pub static UDF_EXECUTOR_OCC_MAX_BACKOFF: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_millis(env_config("UDF_EXECUTOR_OCC_MAX_BACKOFF_MS", 2000)));
 
+/// How many pairs of conflicting functions to keep OCC conflict counts for.
+/// When there are more, the pair seen least recently is dropped.
+pub static MAX_OCC_CONFLICT_PAIRS: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_OCC_CONFLICT_PAIRS", 1000));
+
 /// The time for which a backend will stay around, after getting preempted,
 /// answering health checks but not serving traffic.
 ///
@@ -203,6 +241,22 @@ pub static DOCUMENTS_IN_MEMORY: LazyLock<usize> =
 pub static HTTP_SERVER_TCP_BACKLOG: LazyLock<u32> =
     LazyLock::new(|| env_config("HTTP_SERVER_TCP_BACKLOG", 256));
 
+/// How often the HTTP server pings idle HTTP/2 connections, so proxies and
+/// load balancers don't close long-lived ones. 0 disables the pings.
+pub static HTTP2_KEEP_ALIVE_INTERVAL: LazyLock<Option<Duration>> = LazyLock::new(|| {
+    Some(Duration::from_secs(env_config(
+        "HTTP2_KEEP_ALIVE_INTERVAL_SECS",
+        20,
+    )))
+    .filter(|interval| !interval.is_zero())
+});
+
+/// Whether the HTTP server sizes HTTP/2 flow control windows from each
+/// connection's measured bandwidth, instead of using fixed windows that limit
+/// throughput on high latency connections.
+pub static HTTP2_ADAPTIVE_WINDOW: LazyLock<bool> =
+    LazyLock::new(|| env_config("HTTP2_ADAPTIVE_WINDOW", true));
+
 /// The max concurrent of concurrent HTTP requests. This also limits Node.js
 /// action callbacks concurrency since those go over http.
 pub static HTTP_SERVER_MAX_CONCURRENT_REQUESTS: LazyLock<usize> =
@@ -277,6 +331,51 @@ pub static TRANSACTION_MAX_SCHEDULED_TOTAL_ARGUMENT_SIZE_BYTES: LazyLock<usize>
 pub static SCHEDULED_JOB_EXECUTION_PARALLELISM: LazyLock<usize> =
     LazyLock::new(|| env_config("SCHEDULED_JOB_EXECUTION_PARALLELISM", 10));
 
+/// Per-queue limits on how many scheduled jobs can execute in parallel, e.g.
+/// `backfill=2,emails=4`. See [`ScheduledJobQueueLimits`] for the format.
+///
+/// Jobs from a queue at its limit wait while jobs from other queues run, so a
+/// large backlog in one queue can't take up all of
+/// `SCHEDULED_JOB_EXECUTION_PARALLELISM`. Empty by default.
+pub static SCHEDULED_JOB_QUEUE_CONCURRENCY: LazyLock<ScheduledJobQueueLimits> =
+    LazyLock::new(|| {
+        env_config(
+            "SCHEDULED_JOB_QUEUE_CONCURRENCY",
+            ScheduledJobQueueLimits::default(),
+        )
+    });
+
+/// How many ready scheduled jobs the executor skips over because their queue
+/// is at its limit before it stops looking for other jobs to run. Bounds how
+/// much of a throttled backlog the executor reads on every pass.
+pub static SCHEDULED_JOB_MAX_THROTTLED_SCAN: LazyLock<usize> =
+    LazyLock::new(|| env_config("SCHEDULED_JOB_MAX_THROTTLED_SCAN", 1000));
+
+/// Per-function caps on outstanding scheduled runs, e.g. `emails:send=10000`.
+/// See [`ScheduledJobOutstandingLimits`] for the format.
+///
+/// Runs are counted per component that schedules them, and scheduling a
+/// function at its cap fails, so a scheduling loop can't fill the database.
+/// Counting the runs makes concurrent mutations that schedule a capped
+/// function conflict with each other, so only cap functions that need it.
+/// Empty by default.
+pub static SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION: LazyLock<ScheduledJobOutstandingLimits> =
+    LazyLock::new(|| {
+        env_config(
+            "SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION",
+            ScheduledJobOutstandingLimits::default(),
+        )
+    });
+
+/// How often to count the scheduled job backlog for the backlog metrics.
+pub static SCHEDULED_JOB_BACKLOG_METRICS_INTERVAL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("SCHEDULED_JOB_BACKLOG_METRICS_INTERVAL", 30)));
+
+/// The most scheduled jobs to read when counting the backlog. Larger backlogs
+/// are reported as this many jobs.
+pub static SCHEDULED_JOB_BACKLOG_MAX_COUNT: LazyLock<usize> =
+    LazyLock::new(|| env_config("SCHEDULED_JOB_BACKLOG_MAX_COUNT", 10000));
+
 /// Initial backoff in milliseconds on a system error from a scheduled job.
 pub static SCHEDULED_JOB_INITIAL_BACKOFF: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_millis(env_config("SCHEDULED_JOB_INITIAL_BACKOFF_MS", 10)));
@@ -315,6 +414,15 @@ pub static SCHEDULED_JOB_RETENTION: LazyLock<Duration> = LazyLock::new(|| {
     ))
 });
 
+/// How long scheduled jobs that failed on their last attempt are kept in the
+/// dead letter table before getting garbage collected.
+pub static SCHEDULED_JOB_DEAD_LETTER_RETENTION: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "SCHEDULED_JOB_DEAD_LETTER_RETENTION",
+        60 * 60 * 24 * 30, // 30 days
+    ))
+});
+
 /// Maximum number of scheduled jobs to garbage collect in a single transaction
 pub static SCHEDULED_JOB_GARBAGE_COLLECTION_BATCH_SIZE: LazyLock<usize> =
     LazyLock::new(|| env_config("SCHEDULED_JOB_GARBAGE_COLLECTION_BATCH_SIZE", 1000));
@@ -326,6 +434,18 @@ pub static SCHEDULED_JOB_GARBAGE_COLLECTION_BATCH_SIZE: LazyLock<usize> =
 pub static SCHEDULED_JOB_GARBAGE_COLLECTION_DELAY: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_secs(env_config("SCHEDULED_JOB_GARBAGE_COLLECTION_DELAY", 10)));
 
+/// Maximum number of functions a push can configure to be warmed up.
+pub static MAX_WARMUP_FUNCTIONS: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_WARMUP_FUNCTIONS", 32));
+
+/// Shortest interval allowed between periodic warm-up runs of a function.
+pub static WARMUP_MIN_INTERVAL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("WARMUP_MIN_INTERVAL_SECS", 60)));
+
+/// Maximum size of an uploaded dependency layer archive for Node actions.
+pub static MAX_DEPENDENCY_LAYER_SIZE_BYTES: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_DEPENDENCY_LAYER_SIZE_BYTES", 250 << 20)); // 250 MiB
+
 /// Maximum number of syscalls that can run in a batch together when
 /// awaited in parallel. Higher values improve latency, while lower ones
 /// protect one isolate from hogging database connections.
@@ -593,6 +713,13 @@ pub static MAX_SESSION_CLEANUP_DURATION: LazyLock<Option<Duration>> = LazyLock::
     }
 });
 
+/// How long the result of a mutation called over HTTP with an
+/// `Idempotency-Key` is replayed for. After this, reusing the key runs the
+/// mutation again. Records are also deleted by session request cleanup, so
+/// the effective window is capped by `MAX_SESSION_CLEANUP_DURATION_HOURS`.
+pub static IDEMPOTENCY_KEY_WINDOW: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(60 * 60 * env_config("IDEMPOTENCY_KEY_WINDOW_HOURS", 24)));
+
 /// Snapshots that expired more than this number of days ago are purged
 /// from storage.
 pub static MAX_EXPIRED_SNAPSHOT_AGE: LazyLock<Duration> = LazyLock::new(|| {
@@ -665,6 +792,16 @@ pub static DATABASE_UDF_USER_TIMEOUT: LazyLock<Duration> =
 pub static DATABASE_UDF_SYSTEM_TIMEOUT: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_secs(env_config("DATABASE_UDF_SYSTEM_TIMEOUT_SECONDS", 15)));
 
+/// Per-function user timeouts that replace `DATABASE_UDF_USER_TIMEOUT` and
+/// `ACTION_USER_TIMEOUT` for the functions they name, e.g.
+/// `messages:list=0.5,exports@api/run:export=900`. See [`FunctionTimeouts`]
+/// for the format. Queries and mutations are still bounded by
+/// `DATABASE_UDF_SYSTEM_TIMEOUT`, and Node actions by the Node process
+/// timeout, so raising their timeout past those has no effect. Empty by
+/// default.
+pub static FUNCTION_USER_TIMEOUTS: LazyLock<FunctionTimeouts> =
+    LazyLock::new(|| env_config("FUNCTION_USER_TIMEOUTS", FunctionTimeouts::default()));
+
 /// Timeout on the time it takes to analyze code during a push.
 pub static ISOLATE_ANALYZE_USER_TIMEOUT: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_secs(env_config("ISOLATE_ANALYZE_USER_TIMEOUT_SECONDS", 2)));
@@ -677,6 +814,27 @@ pub static ISOLATE_ANALYZE_USER_TIMEOUT: LazyLock<Duration> =
 pub static ISOLATE_QUEUE_SIZE: LazyLock<usize> =
     LazyLock::new(|| env_config("ISOLATE_QUEUE_SIZE", 2000));
 
+/// Number of isolate worker threads the function runner keeps around even when
+/// idle. The pool grows from here up to its maximum as requests queue up.
+pub static ISOLATE_POOL_MIN_WORKERS: LazyLock<usize> =
+    LazyLock::new(|| env_config("ISOLATE_POOL_MIN_WORKERS", 4));
+
+/// How often the isolate pool re-evaluates how many worker threads it needs.
+pub static ISOLATE_POOL_SCALE_INTERVAL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_millis(env_config("ISOLATE_POOL_SCALE_INTERVAL_MS", 500)));
+
+/// Percentage of the machine's cores that isolate workers can keep busy before
+/// the pool stops adding threads, since more threads would just contend for
+/// the same CPU.
+pub static ISOLATE_POOL_TARGET_CPU_PERCENT: LazyLock<usize> =
+    LazyLock::new(|| env_config("ISOLATE_POOL_TARGET_CPU_PERCENT", 80));
+
+/// Once the isolate pool can't grow any further, reject new requests with an
+/// overloaded error while at least this many requests are queued, rather than
+/// letting them wait in the queue until they expire.
+pub static ISOLATE_POOL_ADMISSION_QUEUE_DEPTH: LazyLock<usize> =
+    LazyLock::new(|| env_config("ISOLATE_POOL_ADMISSION_QUEUE_DEPTH", 200));
+
 /// The size of the pending commits in the committer queue. This is a FIFO
 /// queue, so if the queue is too large, we run into a risk of all requests
 /// waiting too long and no requests going through during overload. The size of
@@ -781,6 +939,31 @@ pub static APPLICATION_MAX_CONCURRENT_HTTP_ACTIONS: LazyLock<usize> = LazyLock::
     )
 });
 
+/// Per-function limits on concurrent executions, e.g.
+/// `messages:send=2,waitlist@api/slack:post=1,*=8`. See
+/// [`FunctionConcurrencyLimits`] for the format.
+///
+/// These are enforced by the function runner, and for Node actions by the
+/// application, in addition to the per-backend limits above. They are useful
+/// for protecting downstream APIs called from actions. Empty by default,
+/// meaning no per-function limits.
+pub static FUNCTION_CONCURRENCY_LIMITS: LazyLock<FunctionConcurrencyLimits> = LazyLock::new(|| {
+    env_config(
+        "FUNCTION_CONCURRENCY_LIMITS",
+        FunctionConcurrencyLimits::default(),
+    )
+});
+
+/// How long a function execution waits in the queue for a per-function
+/// concurrency slot before being rejected. Setting this to 0 rejects excess
+/// executions immediately instead of queueing them.
+pub static FUNCTION_CONCURRENCY_LIMIT_QUEUE_TIMEOUT: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_millis(env_config(
+        "FUNCTION_CONCURRENCY_LIMIT_QUEUE_TIMEOUT_MS",
+        5000,
+    ))
+});
+
 /// The maximum number of concurrent package uploads during
 /// `/api/deploy2/start_push`.
 pub static APPLICATION_MAX_CONCURRENT_UPLOADS: LazyLock<usize> =
@@ -795,6 +978,16 @@ pub static ISOLATE_MAX_USER_HEAP_SIZE: LazyLock<usize> =
 pub static ISOLATE_MAX_HEAP_EXTRA_SIZE: LazyLock<usize> =
     LazyLock::new(|| env_config("ISOLATE_MAX_HEAP_EXTRA_SIZE", 1 << 25));
 
+/// Whether to cache V8's compiled code for user modules so that loading the
+/// same module bundle in a new isolate skips parsing and compilation.
+pub static ISOLATE_CODE_CACHE_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("ISOLATE_CODE_CACHE_ENABLED", true));
+
+/// Maximum total size of the process-wide V8 code cache for compiled modules.
+/// Default 64MiB.
+pub static ISOLATE_CODE_CACHE_MAX_SIZE: LazyLock<usize> =
+    LazyLock::new(|| env_config("ISOLATE_CODE_CACHE_MAX_SIZE", 1 << 26));
+
 /// Chunk sizes: 1, 2, 3, ..., MAX_DYNAMIC_SMART_CHUNK_SIZE incrementing by 1.
 /// These chunk sizes allow small (common) batches to be handled in a single
 /// chunk, while limiting the size of a chunk (don't overload the db), and
@@ -854,12 +1047,26 @@ pub static MYSQL_CHUNK_SIZE: LazyLock<usize> =
 pub static MAX_CONCURRENT_ACTION_OPS: LazyLock<usize> =
     LazyLock::new(|| env_config("MAX_CONCURRENT_ACTION_OPS", 8));
 
+/// How many WebSocket connections an action can have open at once.
+pub static MAX_ACTION_WEBSOCKETS: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_ACTION_WEBSOCKETS", 16));
+
 /// Maximum count of transitions within the web socket server message buffer.
 /// When this limit is reached, the web socket worker will temporary stop
 /// computing and sending transition messages to the client.
 pub static SYNC_MAX_SEND_TRANSITION_COUNT: LazyLock<usize> =
     LazyLock::new(|| env_config("SYNC_MAX_SEND_TRANSITION_COUNT", 2));
 
+/// How long a disconnected sync session's queries and subscriptions are kept
+/// for its client to resume them.
+pub static SYNC_SESSION_RESUME_WINDOW: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("SYNC_SESSION_RESUME_WINDOW_SECS", 60)));
+
+/// Maximum number of disconnected sync sessions kept for resumption. The
+/// oldest are dropped first.
+pub static SYNC_MAX_RESUMABLE_SESSIONS: LazyLock<usize> =
+    LazyLock::new(|| env_config("SYNC_MAX_RESUMABLE_SESSIONS", 1000));
+
 /// Max Axiom sink attributes. This is a knob just in case a user actually hits
 /// the limit but has an Enterprise Axiom plan that lets them use more than the
 /// limit we've configured.
@@ -941,6 +1148,20 @@ pub static FUNRUN_MODULE_MAX_CONCURRENCY: LazyLock<usize> =
 pub static FUNRUN_FETCH_CLIENT_CACHE_SIZE: LazyLock<usize> =
     LazyLock::new(|| env_config("FUNRUN_FETCH_CLIENT_CACHE_SIZE", 100));
 
+/// Cache responses to `GET` requests made by action `fetch` calls according to
+/// their `Cache-Control` headers, sharing them across executions of the same
+/// deployment.
+pub static FUNRUN_FETCH_RESPONSE_CACHE_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("FUNRUN_FETCH_RESPONSE_CACHE_ENABLED", false));
+
+/// The maximum total size of cached fetch response bodies in Funrun in bytes.
+pub static FUNRUN_FETCH_RESPONSE_CACHE_SIZE: LazyLock<usize> =
+    LazyLock::new(|| env_config("FUNRUN_FETCH_RESPONSE_CACHE_SIZE", 64 << 20));
+
+/// Fetch responses with bodies larger than this many bytes aren't cached.
+pub static FUNRUN_FETCH_RESPONSE_CACHE_MAX_ENTRY_SIZE: LazyLock<usize> =
+    LazyLock::new(|| env_config("FUNRUN_FETCH_RESPONSE_CACHE_MAX_ENTRY_SIZE", 1 << 20));
+
 /// The maximum number of concurrent requests a single client can make to a
 /// single Funrun server.
 /// NOTE: When changing this value, ensure that the following parameters
@@ -1096,6 +1317,37 @@ pub static MAX_BACKEND_RPC_HTTP_CHUNK_SIZE: LazyLock<usize> =
 pub static MAX_BACKEND_PUBLIC_API_REQUEST_SIZE: LazyLock<usize> =
     LazyLock::new(|| env_config("MAX_BACKEND_PUBLIC_API_REQUEST_SIZE", (1 << 23) + 2000)); // 8 MiB
 
+/// Whether to compress API responses for clients that accept brotli, zstd, or
+/// gzip, and sync protocol messages for clients that offer
+/// permessage-deflate.
+pub static RESPONSE_COMPRESSION_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("RESPONSE_COMPRESSION_ENABLED", true));
+
+/// Responses smaller than this many bytes aren't compressed, since it saves
+/// little and costs CPU time. Responses of unknown size are always compressed.
+/// Sync protocol messages are compressed at the same size.
+pub static RESPONSE_COMPRESSION_MIN_BYTES: LazyLock<u16> =
+    LazyLock::new(|| env_config("RESPONSE_COMPRESSION_MIN_BYTES", 1024));
+
+/// The most queries a single `/api/query_batch` request can run.
+pub static MAX_QUERY_BATCH_SIZE: LazyLock<usize> =
+    LazyLock::new(|| env_config("MAX_QUERY_BATCH_SIZE", 100));
+
+/// Whether to serve a GraphQL API over the public functions and schema-defined
+/// tables at `/api/graphql`.
+pub static GRAPHQL_API_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("GRAPHQL_API_ENABLED", false));
+
+/// Whether to serve a REST API over the documents in the app's tables at
+/// `/api/rest/:table`.
+pub static REST_API_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("REST_API_ENABLED", false));
+
+/// Whether to serve the gRPC API for public functions (`convex_api.ConvexApi`)
+/// alongside the HTTP API.
+pub static GRPC_API_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("GRPC_API_ENABLED", false));
+
 /// Background database workers wake up periodically, check to see if something
 /// has changed, then either go back to sleep or do work. Most workers determine
 /// if something has changed at least in part by comparing the number of commits
@@ -1139,6 +1391,10 @@ pub static MAX_PUSH_BYTES: LazyLock<usize> =
 pub static MAX_ECHO_BYTES: LazyLock<usize> =
     LazyLock::new(|| env_config("MAX_ECHO_BYTES", 128 * 1024 * 1024));
 
+/// How long each probe of `/healthz/ready` gets before it counts as failing.
+pub static HEALTH_CHECK_PROBE_TIMEOUT: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("HEALTH_CHECK_PROBE_TIMEOUT_SECS", 5)));
+
 /// Percentage of request traces that should sampled.
 ///
 /// Sampling config is a JSON object with the following format:
@@ -1188,6 +1444,35 @@ pub static AUTH_CACHE_SIZE: LazyLock<usize> = LazyLock::new(|| env_config("AUTH_
 pub static AUTH_CACHE_TTL_SECONDS: LazyLock<u64> =
     LazyLock::new(|| env_config("AUTH_CACHE_TTL_SECONDS", 60));
 
+/// How old an auth provider's cached OIDC discovery document and JWKS can get
+/// before they're refetched in the background.
+pub static OIDC_PROVIDER_METADATA_REFRESH_INTERVAL: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "OIDC_PROVIDER_METADATA_REFRESH_INTERVAL_SECONDS",
+        60 * 60,
+    ))
+});
+
+/// How long an auth provider's cached OIDC discovery document and JWKS are
+/// used for if refreshing them keeps failing. Should be at least
+/// `OIDC_PROVIDER_METADATA_REFRESH_INTERVAL`.
+pub static OIDC_PROVIDER_METADATA_MAX_AGE: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "OIDC_PROVIDER_METADATA_MAX_AGE_SECONDS",
+        24 * 60 * 60,
+    ))
+});
+
+/// Minimum time between refetches of an auth provider's JWKS because a token
+/// was signed with a key that isn't in it. Keeps tokens with bogus keys from
+/// hammering the provider.
+pub static OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "OIDC_PROVIDER_METADATA_MIN_REFETCH_INTERVAL_SECONDS",
+        30,
+    ))
+});
+
 /// Request body limit for airbyte streaming import requests
 pub static AIRBYTE_STREAMING_IMPORT_REQUEST_SIZE_LIMIT: LazyLock<usize> = LazyLock::new(|| {
     env_config(
@@ -1215,6 +1500,24 @@ pub static MIGRATION_REWRITE_BATCH_SIZE: LazyLock<usize> =
 pub static MAX_IMPORT_AGE: LazyLock<Duration> =
     LazyLock::new(|| Duration::from_secs(env_config("MAX_IMPORT_AGE_SECONDS", 7 * 24 * 60 * 60)));
 
+/// How long to wait for a response when an import downloads a `_storage` file
+/// from its URL.
+pub static IMPORT_STORAGE_FILE_CONNECT_TIMEOUT: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "IMPORT_STORAGE_FILE_CONNECT_TIMEOUT_SECONDS",
+        30,
+    ))
+});
+
+/// How long an import may spend downloading and storing a single `_storage`
+/// file from its URL.
+pub static IMPORT_STORAGE_FILE_DOWNLOAD_TIMEOUT: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "IMPORT_STORAGE_FILE_DOWNLOAD_TIMEOUT_SECONDS",
+        10 * 60,
+    ))
+});
+
 /// Max staleness in seconds of a partition loader result before we allow
 /// refreshing. If a request tries to update the partition loader and this
 /// duration has not passed since the last refresh, a stale value will be used.
@@ -1260,6 +1563,15 @@ pub static INSTANCE_LOADER_CONCURRENCY: LazyLock<usize> =
 pub static EXPORT_STORAGE_GET_CONCURRENCY: LazyLock<usize> =
     LazyLock::new(|| env_config("EXPORT_STORAGE_GET_CONCURRENCY", 128).max(1));
 
+/// How long the signed URLs for downloading files in exports with storage
+/// URLs are valid for. S3 caps signed URLs at 7 days.
+pub static EXPORT_STORAGE_URL_TTL: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "EXPORT_STORAGE_URL_TTL_SECS",
+        7 * 24 * 60 * 60, // 7 days
+    ))
+});
+
 /// The max number of bytes that can be prefetched concurrently from storage
 /// files during export.
 ///
@@ -1271,3 +1583,68 @@ pub static EXPORT_MAX_INFLIGHT_PREFETCH_BYTES: LazyLock<usize> = LazyLock::new(|
     )
     .clamp(1, u32::MAX as usize)
 });
+
+/// How many entries of the document log incremental exports read per second.
+pub static INCREMENTAL_EXPORT_ROWS_PER_SECOND: LazyLock<NonZeroU32> = LazyLock::new(|| {
+    env_config(
+        "INCREMENTAL_EXPORT_ROWS_PER_SECOND",
+        NonZeroU32::new(10_000).unwrap(),
+    )
+});
+
+/// How long the access tokens issued to service accounts are valid for.
+pub static SERVICE_ACCOUNT_TOKEN_TTL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("SERVICE_ACCOUNT_TOKEN_TTL_SECONDS", 3600)));
+
+/// Longest a service account's signed client assertion can be valid for, so
+/// assertions that leak can't be exchanged for tokens for long.
+pub static SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "SERVICE_ACCOUNT_MAX_ASSERTION_LIFETIME_SECONDS",
+        300,
+    ))
+});
+
+/// Whether clients can get anonymous identities from `/api/auth/anonymous`,
+/// so users can act before they sign up. Off by default, since apps may treat
+/// any authenticated user as trusted.
+pub static ANONYMOUS_IDENTITIES_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("ANONYMOUS_IDENTITIES_ENABLED", false));
+
+/// How long anonymous identity tokens are valid for. Clients renew them with
+/// their current token to keep the same subject.
+pub static ANONYMOUS_IDENTITY_TOKEN_TTL: LazyLock<Duration> = LazyLock::new(|| {
+    Duration::from_secs(env_config(
+        "ANONYMOUS_IDENTITY_TOKEN_TTL_SECONDS",
+        24 * 60 * 60,
+    ))
+});
+
+/// Longest the tokens exchanged for SAML assertions are valid for. They
+/// expire sooner if the identity provider ends the session sooner.
+pub static SAML_TOKEN_TTL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("SAML_TOKEN_TTL_SECONDS", 3600)));
+
+/// How long the tokens services get from `/api/token_exchange` to act as a
+/// user are valid for. They're meant for a single render or job, so they're
+/// short-lived.
+pub static TOKEN_EXCHANGE_TOKEN_TTL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("TOKEN_EXCHANGE_TOKEN_TTL_SECONDS", 300)));
+
+/// How many versions of each secret environment variable are kept, including
+/// the current one. Older versions are deleted when a secret is rotated.
+pub static ENV_SECRET_MAX_VERSIONS: LazyLock<usize> =
+    LazyLock::new(|| env_config("ENV_SECRET_MAX_VERSIONS", 10));
+
+/// Whether requests made with admin or deploy keys, including function calls
+/// and sync sockets, are recorded in `_admin_key_audit_log`.
+pub static ADMIN_KEY_AUDIT_LOG_ENABLED: LazyLock<bool> =
+    LazyLock::new(|| env_config("ADMIN_KEY_AUDIT_LOG_ENABLED", true));
+
+/// How long the hourly usage records in `_usage_records` are kept.
+pub static USAGE_RECORD_RETENTION: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_days(env_config("USAGE_RECORD_RETENTION_DAYS", 90)));
+
+/// How often the alert worker evaluates the rules in `_alert_rules`.
+pub static ALERT_EVALUATION_INTERVAL: LazyLock<Duration> =
+    LazyLock::new(|| Duration::from_secs(env_config("ALERT_EVALUATION_INTERVAL_SECS", 60)));
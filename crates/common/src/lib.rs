@@ -20,8 +20,10 @@
 #![feature(str_split_remainder)]
 #![feature(duration_constructors)]
 
+pub mod access_rules;
 pub mod async_compat;
 pub mod auth;
+pub mod aws;
 pub mod backoff;
 pub mod bootstrap_model;
 pub mod bounds;
@@ -29,6 +31,7 @@ pub mod client_pool;
 pub mod codel_queue;
 pub mod comparators;
 pub mod components;
+pub mod concurrency_limits;
 pub mod deleted_bitset;
 pub mod document;
 pub mod errors;
@@ -36,6 +39,7 @@ pub mod execution_context;
 pub mod ext;
 pub mod fastrace_helpers;
 pub mod floating_point;
+pub mod function_timeouts;
 pub mod grpc;
 pub mod heap_size;
 pub mod http;
@@ -52,16 +56,19 @@ pub mod log_lines;
 pub mod log_streaming;
 pub mod metrics;
 pub mod numeric;
+pub mod otlp;
 pub mod paths;
 pub mod pause;
 pub mod persistence;
 pub mod persistence_helpers;
 pub mod pii;
 pub mod pool_stats;
+pub mod projection;
 pub mod query;
 pub mod query_journal;
 pub mod retriable_stream;
 pub mod runtime;
+pub mod scheduled_job_queues;
 pub mod schemas;
 pub mod sha256;
 pub mod shapes;
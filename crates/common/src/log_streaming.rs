@@ -2,6 +2,7 @@ use std::{
     fmt,
     fmt::Display,
     str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 
@@ -43,6 +44,24 @@ impl LogSender for NoopLogSender {
     }
 }
 
+/// Forwards every batch of logs to each of the wrapped senders.
+pub struct FanOutLogSender(pub Vec<Arc<dyn LogSender>>);
+
+impl LogSender for FanOutLogSender {
+    fn send_logs(&self, logs: Vec<LogEvent>) {
+        for sender in &self.0 {
+            sender.send_logs(logs.clone());
+        }
+    }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        for sender in &self.0 {
+            sender.shutdown()?;
+        }
+        Ok(())
+    }
+}
+
 /// Structured log
 #[derive(Debug, Clone)]
 pub struct LogEvent {
@@ -447,6 +466,10 @@ impl FunctionEventSource {
                 JsonValue::String(component_path_str),
             );
         }
+        if let Some((trace_id, span_id)) = self.context.trace_ids() {
+            fields.insert("trace_id".to_string(), JsonValue::String(trace_id));
+            fields.insert("span_id".to_string(), JsonValue::String(span_id));
+        }
         fields
     }
 }
@@ -174,3 +174,11 @@ register_convex_histogram!(ID_TRACKER_SIZE_BYTES, "IdTracker file size");
 pub fn log_id_tracker_size(size: usize) {
     log_distribution(&ID_TRACKER_SIZE_BYTES, size as f64);
 }
+
+register_convex_counter!(
+    COMMON_OTLP_SPANS_DROPPED_TOTAL,
+    "Number of spans that couldn't be exported to the OpenTelemetry collector"
+);
+pub fn log_otlp_spans_dropped(num_spans: usize) {
+    log_counter(&COMMON_OTLP_SPANS_DROPPED_TOTAL, num_spans as u64);
+}
@@ -0,0 +1,313 @@
+//! Exports the spans recorded with fastrace to an OpenTelemetry collector, with
+//! OTLP over HTTP in its JSON encoding.
+//!
+//! fastrace hands finished spans to [`OtlpReporter`] on its collector thread,
+//! which queues them for a task that posts them to the collector in batches.
+//! Spans are dropped rather than slowing down the backend if the collector
+//! can't keep up.
+use std::time::Duration;
+
+use fastrace::collector::{
+    Config,
+    Reporter,
+    SpanId,
+    SpanRecord,
+};
+use http::{
+    HeaderMap,
+    HeaderName,
+    HeaderValue,
+};
+use reqwest::Url;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use tokio::{
+    sync::{
+        mpsc,
+        oneshot,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    metrics::log_otlp_spans_dropped,
+    runtime::tokio_spawn,
+};
+
+/// Spans queued to be exported. Spans reported while it's full are dropped.
+const EXPORT_QUEUE_SIZE: usize = 16384;
+
+/// Most spans to post to the collector at once.
+const MAX_SPANS_PER_EXPORT: usize = 1024;
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP's `SPAN_KIND_INTERNAL`.
+const SPAN_KIND_INTERNAL: u32 = 1;
+
+pub struct OtlpTraceExportConfig {
+    /// The collector's OTLP/HTTP endpoint, like `http://localhost:4318`.
+    /// Spans are posted to `<endpoint>/v1/traces`.
+    pub endpoint: Url,
+    /// Headers to send with each export, e.g. the collector's API key.
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    /// The `service.name` of the exported spans.
+    pub service_name: String,
+    /// The `service.instance.id` of the exported spans.
+    pub instance_name: String,
+}
+
+/// Exports spans until it's shut down.
+pub struct OtlpTraceExporter {
+    shutdown_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl OtlpTraceExporter {
+    /// Starts exporting the spans that fastrace records. Must be called at
+    /// most once, from within the tokio runtime.
+    pub fn start(config: OtlpTraceExportConfig) -> anyhow::Result<Self> {
+        let url = traces_url(&config.endpoint)?;
+        let client = reqwest::Client::builder()
+            .timeout(EXPORT_TIMEOUT)
+            .default_headers(HeaderMap::from_iter(config.headers))
+            .build()?;
+        let resource = resource_json(&config.service_name, &config.instance_name);
+        let (sender, receiver) = mpsc::channel(EXPORT_QUEUE_SIZE);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let handle = tokio_spawn(
+            "otlp_trace_export",
+            export_spans(client, url.clone(), resource, receiver, shutdown_rx),
+        );
+        fastrace::set_reporter(OtlpReporter { sender }, Config::default());
+        tracing::info!("Exporting traces to {url}");
+        Ok(Self {
+            shutdown_tx,
+            handle,
+        })
+    }
+
+    /// Exports the spans that have finished so far, and stops exporting.
+    pub async fn shutdown(self) {
+        fastrace::flush();
+        let _ = self.shutdown_tx.send(());
+        if let Err(e) = self.handle.await {
+            tracing::warn!("OTLP trace exporter failed: {e}");
+        }
+    }
+}
+
+struct OtlpReporter {
+    sender: mpsc::Sender<SpanRecord>,
+}
+
+impl Reporter for OtlpReporter {
+    fn report(&mut self, spans: Vec<SpanRecord>) {
+        let mut num_dropped = 0;
+        for span in spans {
+            if self.sender.try_send(span).is_err() {
+                num_dropped += 1;
+            }
+        }
+        if num_dropped > 0 {
+            log_otlp_spans_dropped(num_dropped);
+        }
+    }
+}
+
+fn traces_url(endpoint: &Url) -> anyhow::Result<Url> {
+    let base = endpoint.as_str().trim_end_matches('/');
+    Ok(format!("{base}/v1/traces").parse()?)
+}
+
+async fn export_spans(
+    client: reqwest::Client,
+    url: Url,
+    resource: JsonValue,
+    mut receiver: mpsc::Receiver<SpanRecord>,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    let mut spans = Vec::with_capacity(MAX_SPANS_PER_EXPORT);
+    loop {
+        tokio::select! {
+            num_received = receiver.recv_many(&mut spans, MAX_SPANS_PER_EXPORT) => {
+                if num_received == 0 {
+                    return;
+                }
+            },
+            _ = &mut shutdown_rx => break,
+        }
+        post_spans(&client, &url, &resource, &spans).await;
+        spans.clear();
+    }
+    // Export whatever was queued before shutting down.
+    receiver.close();
+    while receiver.recv_many(&mut spans, MAX_SPANS_PER_EXPORT).await > 0 {
+        post_spans(&client, &url, &resource, &spans).await;
+        spans.clear();
+    }
+}
+
+async fn post_spans(
+    client: &reqwest::Client,
+    url: &Url,
+    resource: &JsonValue,
+    spans: &[SpanRecord],
+) {
+    let request = export_request_json(resource, spans);
+    let result = client
+        .post(url.clone())
+        .json(&request)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        tracing::warn!("Failed to export {} spans to {url}: {e}", spans.len());
+        log_otlp_spans_dropped(spans.len());
+    }
+}
+
+fn resource_json(service_name: &str, instance_name: &str) -> JsonValue {
+    json!({
+        "attributes": [
+            attribute_json("service.name", service_name),
+            attribute_json("service.instance.id", instance_name),
+        ],
+    })
+}
+
+/// An `ExportTraceServiceRequest` with `spans`.
+fn export_request_json(resource: &JsonValue, spans: &[SpanRecord]) -> JsonValue {
+    json!({
+        "resourceSpans": [{
+            "resource": resource,
+            "scopeSpans": [{
+                "scope": { "name": "convex" },
+                "spans": spans.iter().map(span_json).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+fn span_json(span: &SpanRecord) -> JsonValue {
+    // Root spans have a zero parent ID, which OTLP leaves empty.
+    let parent_span_id = if span.parent_id == SpanId::default() {
+        String::new()
+    } else {
+        format!("{:016x}", span.parent_id.0)
+    };
+    let events: Vec<_> = span
+        .events
+        .iter()
+        .map(|event| {
+            json!({
+                "timeUnixNano": event.timestamp_unix_ns.to_string(),
+                "name": event.name,
+                "attributes": event
+                    .properties
+                    .iter()
+                    .map(|(key, value)| attribute_json(key, value))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json!({
+        "traceId": format!("{:032x}", span.trace_id.0),
+        "spanId": format!("{:016x}", span.span_id.0),
+        "parentSpanId": parent_span_id,
+        "name": span.name,
+        "kind": SPAN_KIND_INTERNAL,
+        // OTLP's JSON encoding has 64-bit integers as strings.
+        "startTimeUnixNano": span.begin_time_unix_ns.to_string(),
+        "endTimeUnixNano": (span.begin_time_unix_ns + span.duration_ns).to_string(),
+        "attributes": span
+            .properties
+            .iter()
+            .map(|(key, value)| attribute_json(key, value))
+            .collect::<Vec<_>>(),
+        "events": events,
+    })
+}
+
+fn attribute_json(key: &str, value: &str) -> JsonValue {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+#[cfg(test)]
+mod tests {
+    use fastrace::collector::{
+        EventRecord,
+        SpanId,
+        SpanRecord,
+        TraceId,
+    };
+    use serde_json::json;
+
+    use super::{
+        export_request_json,
+        resource_json,
+        traces_url,
+    };
+
+    #[test]
+    fn test_export_request_json() {
+        let root = SpanRecord {
+            trace_id: TraceId(0xabc),
+            span_id: SpanId(1),
+            parent_id: SpanId::default(),
+            begin_time_unix_ns: 1_000,
+            duration_ns: 500,
+            name: "commit".into(),
+            properties: vec![("num_writes".into(), "3".into())],
+            events: vec![EventRecord {
+                name: "retry".into(),
+                timestamp_unix_ns: 1_200,
+                properties: vec![],
+            }],
+        };
+        let child = SpanRecord {
+            trace_id: TraceId(0xabc),
+            span_id: SpanId(2),
+            parent_id: SpanId(1),
+            name: "write_to_persistence".into(),
+            ..Default::default()
+        };
+        let resource = resource_json("convex-backend", "carnitas");
+        let request = export_request_json(&resource, &[root, child]);
+        let spans = &request["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(
+            spans[0],
+            json!({
+                "traceId": "00000000000000000000000000000abc",
+                "spanId": "0000000000000001",
+                "parentSpanId": "",
+                "name": "commit",
+                "kind": 1,
+                "startTimeUnixNano": "1000",
+                "endTimeUnixNano": "1500",
+                "attributes": [{ "key": "num_writes", "value": { "stringValue": "3" } }],
+                "events": [{ "timeUnixNano": "1200", "name": "retry", "attributes": [] }],
+            })
+        );
+        assert_eq!(spans[1]["parentSpanId"], "0000000000000001");
+        assert_eq!(
+            request["resourceSpans"][0]["resource"]["attributes"][0],
+            json!({ "key": "service.name", "value": { "stringValue": "convex-backend" } })
+        );
+    }
+
+    #[test]
+    fn test_traces_url() -> anyhow::Result<()> {
+        assert_eq!(
+            traces_url(&"http://localhost:4318".parse()?)?.as_str(),
+            "http://localhost:4318/v1/traces"
+        );
+        assert_eq!(
+            traces_url(&"https://collector.example.com/otlp/".parse()?)?.as_str(),
+            "https://collector.example.com/otlp/v1/traces"
+        );
+        Ok(())
+    }
+}
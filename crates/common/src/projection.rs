@@ -0,0 +1,107 @@
+//! Field projection for query results, so clients showing a few fields of
+//! wide documents don't have to download the rest.
+use std::collections::BTreeMap;
+
+use value::{
+    ConvexArray,
+    ConvexObject,
+    ConvexValue,
+};
+
+use crate::document::ID_FIELD;
+
+/// Keeps only `fields` and `_id` of the documents in `value`, wherever they
+/// are in it, e.g. in an array or a paginated result's `page`. Any object with
+/// an `_id` field counts as a document. Fields are top-level field names, and
+/// ones that documents don't have are skipped.
+pub fn project_documents(value: ConvexValue, fields: &[String]) -> ConvexValue {
+    match value {
+        ConvexValue::Object(object) if object.get::<str>(&ID_FIELD).is_some() => {
+            let projected: BTreeMap<_, _> = object
+                .into_iter()
+                .filter(|(name, _)| {
+                    **name == **ID_FIELD || fields.iter().any(|field| **field == **name)
+                })
+                .collect();
+            ConvexValue::Object(
+                ConvexObject::try_from(projected)
+                    .expect("Projecting an object should always produce a smaller, valid object"),
+            )
+        },
+        ConvexValue::Object(object) => {
+            let projected: BTreeMap<_, _> = object
+                .into_iter()
+                .map(|(name, value)| (name, project_documents(value, fields)))
+                .collect();
+            ConvexValue::Object(
+                ConvexObject::try_from(projected)
+                    .expect("Projecting an object should always produce a smaller, valid object"),
+            )
+        },
+        ConvexValue::Array(array) => {
+            let projected: Vec<_> = Vec::from(array)
+                .into_iter()
+                .map(|value| project_documents(value, fields))
+                .collect();
+            ConvexValue::Array(
+                ConvexArray::try_from(projected)
+                    .expect("Projecting an array should always produce a smaller, valid array"),
+            )
+        },
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use value::{
+        assert_val,
+        ConvexValue,
+    };
+
+    use super::project_documents;
+    use crate::assert_obj;
+
+    #[test]
+    fn test_project_documents() {
+        let message = |id: &str| {
+            assert_obj!(
+                "_id" => id,
+                "_creationTime" => 1.0,
+                "author" => "orinoco",
+                "body" => "a long message",
+                "channel" => assert_obj!("name" => "general"),
+            )
+        };
+        let fields = ["author".to_string(), "channel".to_string()];
+        let projected = |id: &str| {
+            assert_obj!(
+                "_id" => id,
+                "author" => "orinoco",
+                "channel" => assert_obj!("name" => "general"),
+            )
+        };
+
+        assert_eq!(
+            project_documents(ConvexValue::Object(message("m1")), &fields),
+            ConvexValue::Object(projected("m1")),
+        );
+        // Documents in paginated results are projected.
+        let page = assert_val!({
+            "page" => [message("m1"), message("m2")],
+            "isDone" => false,
+            "continueCursor" => "cursor",
+        });
+        assert_eq!(
+            project_documents(page, &fields),
+            assert_val!({
+                "page" => [projected("m1"), projected("m2")],
+                "isDone" => false,
+                "continueCursor" => "cursor",
+            }),
+        );
+        // Values that aren't documents are left alone.
+        let counts = assert_val!({ "author" => 1.0, "body" => 2.0 });
+        assert_eq!(project_documents(counts.clone(), &fields), counts);
+    }
+}
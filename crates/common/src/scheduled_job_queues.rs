@@ -0,0 +1,146 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use sync_types::CanonicalizedUdfPath;
+
+/// Limits on how many jobs from each named scheduled job queue can run at
+/// once, configured via the `SCHEDULED_JOB_QUEUE_CONCURRENCY` knob.
+///
+/// The knob is a comma separated list of `<queue>=<limit>` rules, e.g.
+/// `backfill=2,emails=4`. Jobs in queues without a rule, and jobs that aren't
+/// in a queue, are only bounded by `SCHEDULED_JOB_EXECUTION_PARALLELISM`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduledJobQueueLimits {
+    by_queue: BTreeMap<String, usize>,
+}
+
+impl ScheduledJobQueueLimits {
+    pub fn limit_for(&self, queue: &str) -> Option<usize> {
+        self.by_queue.get(queue).copied()
+    }
+}
+
+impl FromStr for ScheduledJobQueueLimits {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            by_queue: parse_limits(s, "queue")?,
+        })
+    }
+}
+
+/// Caps on how many scheduled runs of each function can be outstanding, i.e.
+/// pending or in progress, configured via the
+/// `SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION` knob.
+///
+/// The knob is a comma separated list of `<udf path>=<limit>` rules, e.g.
+/// `emails:send=10000`. Scheduling a function that's at its cap fails.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduledJobOutstandingLimits {
+    by_function: BTreeMap<CanonicalizedUdfPath, usize>,
+}
+
+impl ScheduledJobOutstandingLimits {
+    pub fn limit_for(&self, udf_path: &CanonicalizedUdfPath) -> Option<usize> {
+        self.by_function.get(udf_path).copied()
+    }
+}
+
+impl FromStr for ScheduledJobOutstandingLimits {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut by_function = BTreeMap::new();
+        for (udf_path, limit) in parse_limits(s, "function")? {
+            let udf_path: CanonicalizedUdfPath = udf_path
+                .parse()
+                .with_context(|| format!("Invalid function {udf_path}"))?;
+            anyhow::ensure!(
+                by_function.insert(udf_path.clone(), limit).is_none(),
+                "Duplicate limit for function {udf_path}"
+            );
+        }
+        Ok(Self { by_function })
+    }
+}
+
+/// Parses a comma separated list of `<name>=<limit>` rules with positive
+/// limits.
+fn parse_limits(s: &str, kind: &str) -> anyhow::Result<BTreeMap<String, usize>> {
+    let mut limits = BTreeMap::new();
+    for rule in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let (name, limit) = rule
+            .rsplit_once('=')
+            .with_context(|| format!("Missing `=<limit>` in {kind} rule {rule}"))?;
+        let limit: usize = limit
+            .trim()
+            .parse()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .with_context(|| format!("Limit must be a positive integer in rule {rule}"))?;
+        let name = name.trim();
+        anyhow::ensure!(!name.is_empty(), "Missing {kind} name in rule {rule}");
+        anyhow::ensure!(
+            limits.insert(name.to_string(), limit).is_none(),
+            "Duplicate limit for {kind} {name}"
+        );
+    }
+    Ok(limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sync_types::CanonicalizedUdfPath;
+
+    use super::{
+        ScheduledJobOutstandingLimits,
+        ScheduledJobQueueLimits,
+    };
+
+    #[test]
+    fn test_parse_scheduled_job_queue_limits() -> anyhow::Result<()> {
+        let limits: ScheduledJobQueueLimits = "backfill=2, emails = 4".parse()?;
+        assert_eq!(limits.limit_for("backfill"), Some(2));
+        assert_eq!(limits.limit_for("emails"), Some(4));
+        assert_eq!(limits.limit_for("other"), None);
+
+        assert!("backfill".parse::<ScheduledJobQueueLimits>().is_err());
+        assert!("backfill=0".parse::<ScheduledJobQueueLimits>().is_err());
+        assert!("backfill=-1".parse::<ScheduledJobQueueLimits>().is_err());
+        assert!("=2".parse::<ScheduledJobQueueLimits>().is_err());
+        assert!("backfill=1,backfill=2"
+            .parse::<ScheduledJobQueueLimits>()
+            .is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_parse_scheduled_job_outstanding_limits() -> anyhow::Result<()> {
+        let limits: ScheduledJobOutstandingLimits = "emails:send=100, jobs.js:run=5".parse()?;
+        assert_eq!(
+            limits.limit_for(&CanonicalizedUdfPath::from_str("emails.js:send")?),
+            Some(100)
+        );
+        assert_eq!(
+            limits.limit_for(&CanonicalizedUdfPath::from_str("jobs:run")?),
+            Some(5)
+        );
+        assert_eq!(
+            limits.limit_for(&CanonicalizedUdfPath::from_str("emails:receive")?),
+            None
+        );
+
+        assert!("emails:send=0"
+            .parse::<ScheduledJobOutstandingLimits>()
+            .is_err());
+        assert!("emails:send=1,emails.js:send=2"
+            .parse::<ScheduledJobOutstandingLimits>()
+            .is_err());
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use std::{
     str::FromStr,
 };
 
+use errors::ErrorMetadata;
 use serde::{
     Deserialize,
     Serialize,
@@ -73,6 +74,53 @@ impl From<NodeDependency> for JsonValue {
     }
 }
 
+/// Major version of Node.js that "use node" actions run on. Deployments that
+/// don't pick one run on Node.js 18.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum NodeVersion {
+    #[default]
+    V18,
+    V20,
+    V22,
+}
+
+impl NodeVersion {
+    pub const ALL: [NodeVersion; 3] = [NodeVersion::V18, NodeVersion::V20, NodeVersion::V22];
+
+    pub fn major(&self) -> u32 {
+        match self {
+            NodeVersion::V18 => 18,
+            NodeVersion::V20 => 20,
+            NodeVersion::V22 => 22,
+        }
+    }
+}
+
+impl FromStr for NodeVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "18" => Ok(Self::V18),
+            "20" => Ok(Self::V20),
+            "22" => Ok(Self::V22),
+            _ => anyhow::bail!(ErrorMetadata::bad_request(
+                "UnsupportedNodeVersion",
+                format!(
+                    "Unsupported Node.js version {s:?}. Supported versions are 18, 20, and 22."
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.major())
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub enum RoutableMethod {
@@ -211,7 +259,10 @@ impl TryFrom<SerializedHttpActionRoute> for HttpActionRoute {
 mod tests {
     use value::assert_obj;
 
-    use super::NodeDependency;
+    use super::{
+        NodeDependency,
+        NodeVersion,
+    };
 
     #[test]
     fn test_backwards_compatibility() {
@@ -228,4 +279,14 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_node_version_roundtrips() -> anyhow::Result<()> {
+        for version in NodeVersion::ALL {
+            assert_eq!(version.to_string().parse::<NodeVersion>()?, version);
+        }
+        assert!("16".parse::<NodeVersion>().is_err());
+        assert!("v20".parse::<NodeVersion>().is_err());
+        Ok(())
+    }
 }
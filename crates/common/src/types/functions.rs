@@ -160,6 +160,8 @@ pub enum FunctionCaller {
     Action {
         parent_scheduled_job: Option<(ComponentId, DeveloperDocumentId)>,
     },
+    /// Warm-up invocations configured at deploy time.
+    Warmup,
     #[cfg(any(test, feature = "testing"))]
     #[proptest(weight = 0)]
     Test,
@@ -174,7 +176,8 @@ impl FunctionCaller {
             FunctionCaller::HttpEndpoint
             | FunctionCaller::Cron
             | FunctionCaller::Scheduler { .. }
-            | FunctionCaller::Action { .. } => None,
+            | FunctionCaller::Action { .. }
+            | FunctionCaller::Warmup => None,
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => None,
         }
@@ -187,7 +190,8 @@ impl FunctionCaller {
             | FunctionCaller::HttpApi(_)
             | FunctionCaller::Tester(_)
             | FunctionCaller::HttpEndpoint
-            | FunctionCaller::Cron => None,
+            | FunctionCaller::Cron
+            | FunctionCaller::Warmup => None,
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => None,
             FunctionCaller::Scheduler {
@@ -207,7 +211,8 @@ impl FunctionCaller {
             | FunctionCaller::Tester(_)
             | FunctionCaller::HttpEndpoint
             | FunctionCaller::Cron
-            | FunctionCaller::Scheduler { .. } => true,
+            | FunctionCaller::Scheduler { .. }
+            | FunctionCaller::Warmup => true,
             FunctionCaller::Action { .. } => false,
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => true,
@@ -225,7 +230,8 @@ impl FunctionCaller {
             | FunctionCaller::Tester(_) => true,
             FunctionCaller::Cron
             | FunctionCaller::Scheduler { .. }
-            | FunctionCaller::Action { .. } => false,
+            | FunctionCaller::Action { .. }
+            | FunctionCaller::Warmup => false,
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => true,
         }
@@ -243,7 +249,8 @@ impl FunctionCaller {
             FunctionCaller::Tester(_)
             | FunctionCaller::Cron
             | FunctionCaller::Scheduler { .. }
-            | FunctionCaller::Action { .. } => AllowedVisibility::All,
+            | FunctionCaller::Action { .. }
+            | FunctionCaller::Warmup => AllowedVisibility::All,
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => AllowedVisibility::PublicOnly,
         }
@@ -260,6 +267,7 @@ impl fmt::Display for FunctionCaller {
             FunctionCaller::Cron => "Cron",
             FunctionCaller::Scheduler { .. } => "Scheduler",
             FunctionCaller::Action { .. } => "Action",
+            FunctionCaller::Warmup => "Warmup",
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => "Test",
         };
@@ -303,6 +311,7 @@ impl From<FunctionCaller> for pb::common::FunctionCaller {
                 };
                 pb::common::function_caller::Caller::Action(caller)
             },
+            FunctionCaller::Warmup => pb::common::function_caller::Caller::Warmup(()),
             #[cfg(any(test, feature = "testing"))]
             FunctionCaller::Test => panic!("Can't use test function caller"),
         };
@@ -355,6 +364,7 @@ impl TryFrom<pb::common::FunctionCaller> for FunctionCaller {
                     parent_scheduled_job: parent_scheduled_job.map(|job_id| (component_id, job_id)),
                 }
             },
+            Some(pb::common::function_caller::Caller::Warmup(())) => FunctionCaller::Warmup,
             None => anyhow::bail!("Missing `caller` field"),
         };
         Ok(caller)
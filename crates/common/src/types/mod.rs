@@ -30,6 +30,7 @@ pub use actions::{
     ActionCallbackToken,
     HttpActionRoute,
     NodeDependency,
+    NodeVersion,
     RoutableMethod,
     SerializedHttpActionRoute,
 };
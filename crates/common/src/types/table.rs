@@ -7,6 +7,10 @@ pub struct TableStats {
     pub rows_written: u64,
     pub rows_created: u64,
     pub rows_deleted: u64,
+    /// Bytes read and written, which are filled in from usage tracking after
+    /// the function finishes rather than by the transaction.
+    pub bytes_read: u64,
+    pub bytes_written: u64,
 }
 
 impl HeapSize for TableStats {
@@ -135,6 +135,7 @@ impl LocalSyncState {
             args: vec![Value::Object(args.clone()).into()],
             journal: None,
             component_path: None,
+            fields: None,
         });
         let message = ClientMessage::ModifyQuerySet {
             base_version,
@@ -234,6 +235,7 @@ impl LocalSyncState {
                 args: vec![Value::Object(local_query.args.clone()).into()],
                 journal: None,
                 component_path: None,
+                fields: None,
             });
             modifications.push(add)
         }
@@ -657,6 +659,10 @@ impl BaseConvexClient {
             ServerMessage::Ping => {
                 // Do nothing
             },
+            ServerMessage::SessionResumeToken { .. } => {
+                // Only sent to clients that connect with `resumable`, which
+                // this one doesn't.
+            },
         }
         Ok(None)
     }
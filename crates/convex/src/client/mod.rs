@@ -719,6 +719,8 @@ pub mod tests {
                     connection_count: 0,
                     last_close_reason: "InitialConnect".to_string(),
                     max_observed_timestamp: None,
+                    resumable: false,
+                    resume_from: None,
                 },
                 ClientMessage::ModifyQuerySet {
                     base_version: 0,
@@ -729,6 +731,7 @@ pub mod tests {
                         args: vec![json!({})],
                         journal: None,
                         component_path: None,
+                        fields: None,
                     })]
                 },
             ]
@@ -852,6 +855,8 @@ pub mod tests {
                     connection_count: 0,
                     last_close_reason: "InitialConnect".to_string(),
                     max_observed_timestamp: None,
+                    resumable: false,
+                    resume_from: None,
                 },
                 ClientMessage::ModifyQuerySet {
                     base_version: 0,
@@ -862,6 +867,7 @@ pub mod tests {
                         args: vec![json!({})],
                         journal: None,
                         component_path: None,
+                        fields: None,
                     })]
                 },
                 ClientMessage::ModifyQuerySet {
@@ -873,6 +879,7 @@ pub mod tests {
                         args: vec![json!({})],
                         journal: None,
                         component_path: None,
+                        fields: None,
                     })]
                 },
                 ClientMessage::ModifyQuerySet {
@@ -884,6 +891,7 @@ pub mod tests {
                         args: vec![json!({"hello": "world"})],
                         journal: None,
                         component_path: None,
+                        fields: None,
                     })]
                 },
             ]
@@ -912,6 +920,8 @@ pub mod tests {
                     connection_count: 0,
                     last_close_reason: "InitialConnect".to_string(),
                     max_observed_timestamp: None,
+                    resumable: false,
+                    resume_from: None,
                 },
                 ClientMessage::ModifyQuerySet {
                     base_version: 0,
@@ -922,6 +932,7 @@ pub mod tests {
                         args: vec![json!({})],
                         journal: None,
                         component_path: None,
+                        fields: None,
                     })]
                 },
             ]
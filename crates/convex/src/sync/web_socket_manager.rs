@@ -342,6 +342,8 @@ impl WebSocketInternal {
             connection_count,
             last_close_reason,
             max_observed_timestamp,
+            resumable: false,
+            resume_from: None,
         };
         let msg = Message::Text(
             serde_json::Value::try_from(message)
@@ -26,6 +26,7 @@ use crate::{
     Query,
     QueryId,
     QuerySetModification,
+    ResumeFrom,
     SerializedQueryJournal,
     ServerMessage,
     SessionRequestSeqNumber,
@@ -82,6 +83,9 @@ struct QueryJson {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     component_path: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -106,6 +110,7 @@ impl TryFrom<QuerySetModification> for JsonValue {
                     args: JsonValue::from(q.args),
                     journal: q.journal,
                     component_path: q.component_path,
+                    fields: q.fields,
                 };
                 QuerySetModificationJson::Add(query_json)
             },
@@ -132,6 +137,7 @@ impl TryFrom<JsonValue> for QuerySetModification {
                     args,
                     journal: q.journal,
                     component_path: q.component_path,
+                    fields: q.fields,
                 };
                 QuerySetModification::Add(query)
             },
@@ -158,6 +164,12 @@ enum AuthenticationTokenJson {
     None,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+struct ResumeFromJson {
+    token: String,
+    version: JsonValue,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(tag = "type")]
 enum ClientMessageJson {
@@ -173,6 +185,14 @@ enum ClientMessageJson {
         #[serde(default)]
         #[serde(skip_serializing_if = "Option::is_none")]
         max_observed_timestamp: Option<String>,
+
+        #[serde(default)]
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        resumable: bool,
+
+        #[serde(default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        resume_from: Option<ResumeFromJson>,
     },
     #[serde(rename_all = "camelCase")]
     ModifyQuerySet {
@@ -219,11 +239,18 @@ impl TryFrom<ClientMessage> for JsonValue {
                 connection_count,
                 last_close_reason,
                 max_observed_timestamp,
+                resumable,
+                resume_from,
             } => ClientMessageJson::Connect {
                 session_id: format!("{}", session_id.as_hyphenated()),
                 connection_count,
                 last_close_reason: Some(last_close_reason),
                 max_observed_timestamp: max_observed_timestamp.map(|ts| u64_to_string(ts.into())),
+                resumable,
+                resume_from: resume_from.map(|ResumeFrom { token, version }| ResumeFromJson {
+                    token,
+                    version: version.into(),
+                }),
             },
             ClientMessage::ModifyQuerySet {
                 base_version,
@@ -303,6 +330,8 @@ impl TryFrom<JsonValue> for ClientMessage {
                 connection_count,
                 last_close_reason,
                 max_observed_timestamp,
+                resumable,
+                resume_from,
             } => ClientMessage::Connect {
                 session_id: session_id.parse()?,
                 connection_count,
@@ -312,6 +341,15 @@ impl TryFrom<JsonValue> for ClientMessage {
                     .transpose()?
                     .map(Timestamp::try_from)
                     .transpose()?,
+                resumable,
+                resume_from: resume_from
+                    .map(|ResumeFromJson { token, version }| {
+                        anyhow::Ok(ResumeFrom {
+                            token,
+                            version: version.try_into()?,
+                        })
+                    })
+                    .transpose()?,
             },
             ClientMessageJson::ModifyQuerySet {
                 base_version,
@@ -610,6 +648,11 @@ impl<V: Into<JsonValue>> From<ServerMessage<V>> for JsonValue {
             ServerMessage::Ping {} => json!({
                 "type": "Ping"
             }),
+            ServerMessage::SessionResumeToken { token, resumed } => json!({
+                "type": "SessionResumeToken",
+                "token": token,
+                "resumed": resumed,
+            }),
         }
     }
 }
@@ -655,6 +698,8 @@ impl<V: TryFrom<JsonValue, Error = anyhow::Error>> TryFrom<JsonValue> for Server
             },
             #[serde(rename_all = "camelCase")]
             Ping {},
+            #[serde(rename_all = "camelCase")]
+            SessionResumeToken { token: String, resumed: bool },
         }
         let s: ServerMessageJson = serde_json::from_value(value)?;
         let result = match s {
@@ -739,6 +784,9 @@ impl<V: TryFrom<JsonValue, Error = anyhow::Error>> TryFrom<JsonValue> for Server
                 base_version,
             },
             ServerMessageJson::Ping {} => ServerMessage::Ping {},
+            ServerMessageJson::SessionResumeToken { token, resumed } => {
+                ServerMessage::SessionResumeToken { token, resumed }
+            },
         };
         Ok(result)
     }
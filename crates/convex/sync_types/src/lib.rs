@@ -28,6 +28,7 @@ pub use crate::{
         QueryId,
         QuerySetModification,
         QuerySetVersion,
+        ResumeFrom,
         SerializedQueryJournal,
         ServerMessage,
         SessionId,
@@ -91,6 +91,10 @@ pub struct Query {
     /// For internal use by Convex dashboard. Only works with admin auth.
     /// Allows calling a query within a component directly.
     pub component_path: Option<String>,
+
+    /// Only send these top-level fields of the documents in the query's
+    /// result, along with their `_id`s. Sends whole documents if unset.
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -100,6 +104,16 @@ pub enum QuerySetModification {
     Remove { query_id: QueryId },
 }
 
+/// Where a reconnecting client left off.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ResumeFrom {
+    /// The token sent on the session's last connection.
+    pub token: String,
+    /// The version of the last transition the client received.
+    pub version: StateVersion,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub enum ClientMessage {
@@ -108,6 +122,11 @@ pub enum ClientMessage {
         connection_count: u32,
         last_close_reason: String,
         max_observed_timestamp: Option<Timestamp>,
+        /// Set by clients that can resume their session after reconnecting,
+        /// which are sent a `ServerMessage::SessionResumeToken` in reply.
+        resumable: bool,
+        /// The session to resume, if the server still has it.
+        resume_from: Option<ResumeFrom>,
     },
     ModifyQuerySet {
         base_version: QuerySetVersion,
@@ -328,6 +347,14 @@ pub enum ServerMessage<V: 'static> {
         error_message: String,
     },
     Ping,
+    /// Sent before any transition to clients that connect with `resumable`.
+    /// If the session was `resumed`, its queries and identity are as they
+    /// were at the client's `ResumeFrom::version`, and the next transition
+    /// starts there. Otherwise the client must set them up from scratch.
+    SessionResumeToken {
+        token: String,
+        resumed: bool,
+    },
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -0,0 +1,146 @@
+//! Enforces the access rules users configure per table (see
+//! `common::access_rules`) on transactions run by users and unauthenticated
+//! clients. The rules are read from `_access_rules` at most once per
+//! transaction, so changing them invalidates subscriptions that depend on
+//! them.
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+};
+
+use common::{
+    access_rules::{
+        access_denied_error,
+        AccessOperation,
+        TableAccessRules,
+        ACCESS_RULES_TABLE,
+    },
+    document::{
+        DeveloperDocument,
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use keybroker::Identity;
+use serde_json::{
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::{
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    ResolvedQuery,
+    TableModel,
+    Transaction,
+};
+
+pub(crate) type AccessRulesByTable = BTreeMap<TableName, Arc<TableAccessRules>>;
+
+impl<RT: Runtime> Transaction<RT> {
+    /// Admins and the system aren't subject to access rules.
+    pub(crate) fn is_subject_to_access_rules(&self) -> bool {
+        matches!(
+            self.identity,
+            Identity::User(_) | Identity::ActingUser(..) | Identity::Unknown
+        )
+    }
+
+    /// The rules for a user table in the root component, if the transaction's
+    /// identity is subject to them.
+    async fn table_access_rules(
+        &mut self,
+        namespace: TableNamespace,
+        table: &TableName,
+    ) -> anyhow::Result<Option<Arc<TableAccessRules>>> {
+        if namespace != TableNamespace::Global
+            || table.is_system()
+            || !self.is_subject_to_access_rules()
+        {
+            return Ok(None);
+        }
+        if self.access_rules.is_none() {
+            let mut rules = BTreeMap::new();
+            if TableModel::new(self).table_exists(TableNamespace::Global, &ACCESS_RULES_TABLE) {
+                let query = Query::full_table_scan(ACCESS_RULES_TABLE.clone(), Order::Asc);
+                let mut query_stream = ResolvedQuery::new(self, TableNamespace::Global, query)?;
+                while let Some(doc) = query_stream.next(self, None).await? {
+                    let table_rules: ParsedDocument<TableAccessRules> = doc.try_into()?;
+                    let table_rules = table_rules.into_value();
+                    rules.insert(table_rules.table.clone(), Arc::new(table_rules));
+                }
+            }
+            self.access_rules = Some(rules);
+        }
+        Ok(self
+            .access_rules
+            .as_ref()
+            .and_then(|rules| rules.get(table))
+            .cloned())
+    }
+
+    /// The identity rules see, as returned by `ctx.auth.getUserIdentity()`.
+    fn access_rule_identity(&self) -> anyhow::Result<Option<JsonMap<String, JsonValue>>> {
+        let Some(attributes) = self.user_identity() else {
+            return Ok(None);
+        };
+        match JsonValue::try_from(attributes)? {
+            JsonValue::Object(identity) => Ok(Some(identity)),
+            _ => anyhow::bail!("User identity isn't a JSON object"),
+        }
+    }
+
+    /// Whether the access rules let the transaction read `document` from
+    /// `table`. Documents they don't are left out of query results.
+    pub(crate) async fn allows_read(
+        &mut self,
+        namespace: TableNamespace,
+        table: &TableName,
+        document: &DeveloperDocument,
+    ) -> anyhow::Result<bool> {
+        let Some(rules) = self.table_access_rules(namespace, table).await? else {
+            return Ok(true);
+        };
+        let identity = self.access_rule_identity()?;
+        Ok(rules.allows(AccessOperation::Read, identity.as_ref(), document.value()))
+    }
+
+    /// Fails if the access rules don't allow replacing `old_document` with
+    /// `new_document`, where a missing document is an insert or a delete.
+    pub(crate) async fn enforce_write_access(
+        &mut self,
+        old_document: Option<&ResolvedDocument>,
+        new_document: Option<&ResolvedDocument>,
+    ) -> anyhow::Result<()> {
+        if !self.is_subject_to_access_rules() {
+            return Ok(());
+        }
+        let (operation, documents) = match (old_document, new_document) {
+            (None, Some(new)) => (AccessOperation::Insert, vec![new]),
+            (Some(old), Some(new)) => (AccessOperation::Update, vec![old, new]),
+            (Some(old), None) => (AccessOperation::Delete, vec![old]),
+            (None, None) => return Ok(()),
+        };
+        let tablet_id = documents[0].id().tablet_id;
+        let table = self.table_mapping().tablet_name(tablet_id)?;
+        let namespace = self.table_mapping().tablet_namespace(tablet_id)?;
+        let Some(rules) = self.table_access_rules(namespace, &table).await? else {
+            return Ok(());
+        };
+        let identity = self.access_rule_identity()?;
+        for document in documents {
+            anyhow::ensure!(
+                rules.allows(operation, identity.as_ref(), document.value()),
+                access_denied_error(operation, &table)
+            );
+        }
+        Ok(())
+    }
+}
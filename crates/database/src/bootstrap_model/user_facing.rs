@@ -32,6 +32,7 @@ use value::{
     Size,
     TableName,
     TableNamespace,
+    TableNumber,
 };
 
 use crate::{
@@ -103,6 +104,26 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         &mut self,
         id: DeveloperDocumentId,
         version: Option<Version>,
+    ) -> anyhow::Result<Option<(DeveloperDocument, WriteTimestamp)>> {
+        self.get_with_ts_inner(id, version, true).await
+    }
+
+    /// Like `get_with_ts`, but returns documents the table's access rules
+    /// hide. For search queries, whose results are filtered by `AccessFilter`
+    /// instead.
+    pub(crate) async fn get_with_ts_ignoring_access_rules(
+        &mut self,
+        id: DeveloperDocumentId,
+        version: Option<Version>,
+    ) -> anyhow::Result<Option<(DeveloperDocument, WriteTimestamp)>> {
+        self.get_with_ts_inner(id, version, false).await
+    }
+
+    async fn get_with_ts_inner(
+        &mut self,
+        id: DeveloperDocumentId,
+        version: Option<Version>,
+        apply_access_rules: bool,
     ) -> anyhow::Result<Option<(DeveloperDocument, WriteTimestamp)>> {
         if !self
             .tx
@@ -112,6 +133,7 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         {
             return Ok(None);
         }
+        self.require_table_access(id.table())?;
         let id_ = id.to_resolved(
             self.tx
                 .table_mapping()
@@ -148,9 +170,42 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
             result
         } else {
             let table_name = self.tx.table_mapping().tablet_name(id_.tablet_id)?;
-            let result = self.tx.get_inner(id_, table_name).await?;
-            Ok(result.map(|(doc, ts)| (doc.to_developer(), ts)))
+            let Some((document, ts)) = self.tx.get_inner(id_, table_name.clone()).await? else {
+                return Ok(None);
+            };
+            let document = document.to_developer();
+            // Documents the table's access rules don't let the caller read
+            // look like they don't exist, the same as in query results.
+            if apply_access_rules
+                && !self
+                    .tx
+                    .allows_read(self.namespace, &table_name, &document)
+                    .await?
+            {
+                return Ok(None);
+            }
+            Ok(Some((document, ts)))
+        }
+    }
+
+    /// Returns an error if the identity is an API key that isn't allowed to
+    /// access the user table `table_number`.
+    fn require_table_access(&mut self, table_number: TableNumber) -> anyhow::Result<()> {
+        if self.tx.identity.api_key().is_none() {
+            return Ok(());
         }
+        let table_name = self
+            .tx
+            .table_mapping()
+            .namespace(self.namespace)
+            .name_by_number_if_exists(table_number)
+            .cloned();
+        if let Some(table_name) = table_name
+            && !table_name.is_system()
+        {
+            self.tx.identity.ensure_can_access_table(&table_name)?;
+        }
+        Ok(())
     }
 
     /// Returns an error if the component associated with the current namespace
@@ -187,6 +242,7 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         table: TableName,
         value: ConvexObject,
     ) -> anyhow::Result<DeveloperDocumentId> {
+        self.tx.identity.ensure_can_access_table(&table)?;
         self.require_active_component().await?;
         if self.tx.virtual_system_mapping().is_virtual_table(&table) {
             anyhow::bail!(ErrorMetadata::bad_request(
@@ -255,6 +311,7 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         {
             anyhow::bail!(unauthorized_error("patch"))
         }
+        self.require_table_access(id.table())?;
         self.require_active_component().await?;
         self.tx.retention_validator.fail_if_falling_behind()?;
 
@@ -289,6 +346,7 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         {
             anyhow::bail!(unauthorized_error("replace"))
         }
+        self.require_table_access(id.table())?;
         self.require_active_component().await?;
         if !self.tx.is_system(self.namespace, id.table()) {
             check_user_size(value.size())?;
@@ -316,6 +374,7 @@ impl<'a, RT: Runtime> UserFacingModel<'a, RT> {
         {
             anyhow::bail!(unauthorized_error("delete"))
         }
+        self.require_table_access(id.table())?;
         self.require_active_component().await?;
         self.tx.retention_validator.fail_if_falling_behind()?;
 
@@ -109,6 +109,7 @@ use crate::{
         next_commit_ts_seconds,
         table_summary_finish_bootstrap_timer,
     },
+    occ_conflicts::OccConflicts,
     reads::ReadSet,
     search_index_bootstrap::{
         stream_revision_pairs_for_indexes,
@@ -176,6 +177,8 @@ pub struct Committer<RT: Runtime> {
     persistence_writes: FuturesOrdered<BoxFuture<'static, anyhow::Result<PersistenceWrite>>>,
 
     retention_validator: Arc<dyn RetentionValidator>,
+
+    occ_conflicts: Arc<OccConflicts>,
 }
 
 impl<RT: Runtime> Committer<RT> {
@@ -186,6 +189,7 @@ impl<RT: Runtime> Committer<RT> {
         runtime: RT,
         retention_validator: Arc<dyn RetentionValidator>,
         shutdown: ShutdownSignal,
+        occ_conflicts: Arc<OccConflicts>,
     ) -> CommitterClient {
         let persistence_reader = persistence.reader();
         let conflict_checker = PendingWrites::new(persistence_reader.version());
@@ -201,6 +205,7 @@ impl<RT: Runtime> Committer<RT> {
             persistence_writes: FuturesOrdered::new(),
             shutdown,
             retention_validator: retention_validator.clone(),
+            occ_conflicts,
         };
         let handle = runtime.spawn("committer", committer.go(rx));
         CommitterClient {
@@ -627,6 +632,12 @@ impl<RT: Runtime> Committer<RT> {
             *transaction.begin_timestamp,
             commit_ts,
         )? {
+            self.occ_conflicts.record(
+                &conflicting_read,
+                &transaction.table_mapping,
+                &write_source,
+                self.runtime.unix_timestamp(),
+            );
             anyhow::bail!(conflicting_read.into_error(&transaction.table_mapping, &write_source));
         }
         timer.finish();
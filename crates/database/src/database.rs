@@ -169,6 +169,10 @@ use crate::{
         vector::vector_search_with_retries_timer,
         verify_invariants_timer,
     },
+    occ_conflicts::{
+        OccConflictPair,
+        OccConflicts,
+    },
     retention::LeaderRetentionManager,
     schema_registry::SchemaRegistry,
     search_index_bootstrap::SearchIndexBootstrapWorker,
@@ -263,6 +267,7 @@ pub struct Database<RT: Runtime> {
     usage_counter: UsageCounter,
     virtual_system_mapping: VirtualSystemMapping,
     pub bootstrap_metadata: BootstrapMetadata,
+    occ_conflicts: Arc<OccConflicts>,
     // Caches of snapshot TableMapping and by_id index ids, which are used repeatedly by
     // /api/list_snapshot.
     table_mapping_snapshot_cache: AsyncLru<RT, Timestamp, TableMapping>,
@@ -820,6 +825,7 @@ impl<RT: Runtime> Database<RT> {
         let subscriptions =
             SubscriptionsWorker::start(log_owner, runtime.clone(), persistence_reader.version());
         let usage_counter = UsageCounter::new(usage_events);
+        let occ_conflicts = Arc::new(OccConflicts::default());
         let committer = Committer::start(
             log_writer,
             snapshot_writer,
@@ -827,6 +833,7 @@ impl<RT: Runtime> Database<RT> {
             runtime.clone(),
             Arc::new(retention_manager.clone()),
             shutdown,
+            occ_conflicts.clone(),
         );
         let table_mapping_snapshot_cache =
             AsyncLru::new(runtime.clone(), 10, 2, "table_mapping_snapshot");
@@ -849,6 +856,7 @@ impl<RT: Runtime> Database<RT> {
             usage_counter,
             virtual_system_mapping,
             bootstrap_metadata,
+            occ_conflicts,
             table_mapping_snapshot_cache,
             by_id_indexes_snapshot_cache,
             component_paths_snapshot_cache,
@@ -2007,6 +2015,12 @@ impl<RT: Runtime> Database<RT> {
         self.usage_counter.clone()
     }
 
+    /// Commits that failed OCC, by the function committing and the function
+    /// whose write it conflicted with.
+    pub fn occ_conflicts(&self) -> Vec<OccConflictPair> {
+        self.occ_conflicts.pairs()
+    }
+
     pub fn search_storage(&self) -> Arc<dyn Storage> {
         self.search_storage
             .get()
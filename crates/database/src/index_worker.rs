@@ -74,6 +74,7 @@ use common::{
         TabletId,
     },
 };
+use fastrace::prelude::*;
 use futures::{
     future,
     pin_mut,
@@ -336,12 +337,16 @@ impl<RT: Runtime> IndexWorker<RT> {
             for (tablet_id, index_ids) in to_backfill_by_tablet {
                 log_num_indexes_to_backfill(num_to_backfill - num_backfilled);
                 num_backfilled += index_ids.len();
+                let root = Span::root("index_backfill", SpanContext::random())
+                    .with_property(|| ("tablet_id", tablet_id.to_string()))
+                    .with_property(|| ("num_indexes", index_ids.len().to_string()));
                 self.backfill_tablet(
                     tablet_id,
                     index_ids,
                     tx.table_mapping(),
                     index_documents.clone(),
                 )
+                .in_span(root)
                 .await?;
             }
             if num_to_backfill > 0 {
@@ -644,6 +649,7 @@ impl<RT: Runtime> IndexWriter<RT> {
     ///    writes, assuming `snapshot_ts` is after the index was created. If
     ///    there are no active writes, then `backfill_forwards` must be called
     ///    with a timestamp <= `snapshot_ts`.
+    #[fastrace::trace]
     pub async fn perform_backfill(
         &self,
         snapshot_ts: RepeatableTimestamp,
@@ -1003,6 +1009,7 @@ impl<RT: Runtime> IndexWriter<RT> {
         Ok(())
     }
 
+    #[fastrace::trace]
     async fn run_retention(
         &self,
         backfill_begin_ts: RepeatableTimestamp,
@@ -12,6 +12,7 @@
 #![feature(cow_is_borrowed)]
 #![feature(try_find)]
 
+mod access_rules;
 mod bootstrap_model;
 mod committer;
 mod database;
@@ -19,6 +20,7 @@ mod execution_size;
 mod index_worker;
 mod index_workers;
 mod metrics;
+mod occ_conflicts;
 pub mod patch;
 pub mod persistence_helpers;
 mod preloaded;
@@ -151,6 +153,7 @@ pub use self::{
         IndexSelector,
         IndexWriter,
     },
+    occ_conflicts::OccConflictPair,
     query::{
         soft_data_limit,
         DeveloperQuery,
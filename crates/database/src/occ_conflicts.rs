@@ -0,0 +1,166 @@
+//! Attribution for commits that fail optimistic concurrency control, so
+//! contention can be traced to the functions involved instead of guessed at.
+//!
+//! When a commit conflicts, the committer knows which function is committing,
+//! which of its reads conflicted and which function's write it conflicted
+//! with. Conflicts are aggregated by that pair of functions.
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+
+use common::{
+    knobs::MAX_OCC_CONFLICT_PAIRS,
+    runtime::UnixTimestamp,
+};
+use parking_lot::Mutex;
+use value::TableMapping;
+
+use crate::{
+    database::ConflictingReadWithWriteSource,
+    write_log::WriteSource,
+};
+
+/// Conflicts between commits from `writer` and earlier writes from
+/// `conflicting_writer`. A writer is `None` if it isn't known, like for
+/// system writes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OccConflictPair {
+    pub writer: Option<String>,
+    pub conflicting_writer: Option<String>,
+    pub count: u64,
+    pub first_seen: UnixTimestamp,
+    pub last_seen: UnixTimestamp,
+    /// Conflicts per index read by `writer`, like `messages.by_channel`.
+    pub indexes: BTreeMap<String, u64>,
+    /// The document that conflicted most recently.
+    pub latest_document_id: String,
+}
+
+/// The conflicting function pairs seen since the backend started, up to
+/// `MAX_OCC_CONFLICT_PAIRS` of them. When there are more, the pair seen least
+/// recently is dropped.
+#[derive(Default)]
+pub struct OccConflicts {
+    pairs: Mutex<HashMap<(Option<String>, Option<String>), OccConflictPair>>,
+}
+
+impl OccConflicts {
+    pub(crate) fn record(
+        &self,
+        conflict: &ConflictingReadWithWriteSource,
+        mapping: &TableMapping,
+        current_writer: &WriteSource,
+        ts: UnixTimestamp,
+    ) {
+        let Ok(table_name) = mapping.tablet_name(*conflict.read.index.table()) else {
+            return;
+        };
+        let index = format!("{table_name}.{}", conflict.read.index.descriptor());
+        let document_id = conflict.read.id.developer_id.encode();
+        let writer = current_writer.0.as_ref().map(|s| s.to_string());
+        let conflicting_writer = conflict.write_source.0.as_ref().map(|s| s.to_string());
+        tracing::info!(
+            "OCC conflict: {:?} read {document_id} from {index}, which {:?} changed",
+            writer.as_deref().unwrap_or("unknown writer"),
+            conflicting_writer.as_deref().unwrap_or("unknown writer"),
+        );
+        self.record_pair(writer, conflicting_writer, index, document_id, ts);
+    }
+
+    fn record_pair(
+        &self,
+        writer: Option<String>,
+        conflicting_writer: Option<String>,
+        index: String,
+        document_id: String,
+        ts: UnixTimestamp,
+    ) {
+        let mut pairs = self.pairs.lock();
+        let pair = pairs
+            .entry((writer.clone(), conflicting_writer.clone()))
+            .or_insert_with(|| OccConflictPair {
+                writer,
+                conflicting_writer,
+                count: 0,
+                first_seen: ts,
+                last_seen: ts,
+                indexes: BTreeMap::new(),
+                latest_document_id: String::new(),
+            });
+        pair.count += 1;
+        pair.first_seen = pair.first_seen.min(ts);
+        pair.last_seen = pair.last_seen.max(ts);
+        *pair.indexes.entry(index).or_default() += 1;
+        pair.latest_document_id = document_id;
+        if pairs.len() > *MAX_OCC_CONFLICT_PAIRS {
+            let least_recent = pairs
+                .iter()
+                .min_by_key(|(_, pair)| pair.last_seen)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recent) = least_recent {
+                pairs.remove(&least_recent);
+            }
+        }
+    }
+
+    /// The conflicting pairs, most conflicts first.
+    pub fn pairs(&self) -> Vec<OccConflictPair> {
+        let mut pairs: Vec<_> = self.pairs.lock().values().cloned().collect();
+        pairs.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_seen.cmp(&a.last_seen))
+        });
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::runtime::UnixTimestamp;
+
+    use super::OccConflicts;
+
+    #[test]
+    fn test_occ_conflicts_aggregate_by_function_pair() {
+        let conflicts = OccConflicts::default();
+        let send = Some("messages.js:send".to_string());
+        let react = Some("messages.js:react".to_string());
+        for i in 0..3 {
+            conflicts.record_pair(
+                send.clone(),
+                send.clone(),
+                "messages.by_channel".to_string(),
+                format!("doc{i}"),
+                UnixTimestamp::from_millis(1_000 + i),
+            );
+        }
+        conflicts.record_pair(
+            send.clone(),
+            react.clone(),
+            "messages.by_id".to_string(),
+            "doc3".to_string(),
+            UnixTimestamp::from_millis(2_000),
+        );
+        conflicts.record_pair(
+            send.clone(),
+            send.clone(),
+            "channels.by_id".to_string(),
+            "doc4".to_string(),
+            UnixTimestamp::from_millis(3_000),
+        );
+
+        let pairs = conflicts.pairs();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].writer, send);
+        assert_eq!(pairs[0].conflicting_writer, send);
+        assert_eq!(pairs[0].count, 4);
+        assert_eq!(pairs[0].indexes["messages.by_channel"], 3);
+        assert_eq!(pairs[0].indexes["channels.by_id"], 1);
+        assert_eq!(pairs[0].latest_document_id, "doc4");
+        assert_eq!(pairs[0].first_seen, UnixTimestamp::from_millis(1_000));
+        assert_eq!(pairs[1].conflicting_writer, react);
+        assert_eq!(pairs[1].count, 1);
+    }
+}
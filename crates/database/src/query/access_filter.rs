@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use common::{
+    query::CursorPosition,
+    runtime::Runtime,
+    types::{
+        IndexName,
+        TabletIndexName,
+    },
+};
+use value::TableNamespace;
+
+use super::{
+    DeveloperIndexRangeResponse,
+    QueryNode,
+    QueryStream,
+    QueryStreamNext,
+};
+use crate::Transaction;
+
+/// Leaves out the documents the table's access rules don't let the
+/// transaction read. Sits directly above the query's source, so `.filter()`
+/// and `.take()` only see readable documents.
+pub(super) struct AccessFilter {
+    inner: QueryNode,
+    namespace: TableNamespace,
+}
+
+impl AccessFilter {
+    pub fn new(inner: QueryNode, namespace: TableNamespace) -> Self {
+        Self { inner, namespace }
+    }
+}
+
+#[async_trait]
+impl QueryStream for AccessFilter {
+    fn cursor_position(&self) -> &Option<CursorPosition> {
+        self.inner.cursor_position()
+    }
+
+    fn split_cursor_position(&self) -> Option<&CursorPosition> {
+        self.inner.split_cursor_position()
+    }
+
+    fn is_approaching_data_limit(&self) -> bool {
+        self.inner.is_approaching_data_limit()
+    }
+
+    async fn next<RT: Runtime>(
+        &mut self,
+        tx: &mut Transaction<RT>,
+        prefetch_hint: Option<usize>,
+    ) -> anyhow::Result<QueryStreamNext> {
+        loop {
+            let (document, write_timestamp) = match self.inner.next(tx, prefetch_hint).await? {
+                QueryStreamNext::Ready(Some(v)) => v,
+                QueryStreamNext::Ready(None) => return Ok(QueryStreamNext::Ready(None)),
+                QueryStreamNext::WaitingOn(request) => {
+                    return Ok(QueryStreamNext::WaitingOn(request))
+                },
+            };
+            let table = self.inner.printable_index_name().table().clone();
+            if tx.allows_read(self.namespace, &table, &document).await? {
+                return Ok(QueryStreamNext::Ready(Some((document, write_timestamp))));
+            }
+        }
+    }
+
+    fn feed(&mut self, index_range_response: DeveloperIndexRangeResponse) -> anyhow::Result<()> {
+        self.inner.feed(index_range_response)
+    }
+
+    fn tablet_index_name(&self) -> Option<&TabletIndexName> {
+        self.inner.tablet_index_name()
+    }
+
+    fn printable_index_name(&self) -> &IndexName {
+        self.inner.printable_index_name()
+    }
+}
@@ -47,6 +47,7 @@ use value::{
 };
 
 use self::{
+    access_filter::AccessFilter,
     filter::Filter,
     index_range::{
         CursorInterval,
@@ -62,6 +63,7 @@ use crate::{
     Transaction,
 };
 
+mod access_filter;
 mod filter;
 mod index_range;
 mod limit;
@@ -319,6 +321,10 @@ impl<RT: Runtime> DeveloperQuery<RT> {
             QuerySource::IndexRange(ref index_range) => index_range.index_name.clone(),
             QuerySource::Search(ref search) => search.index_name.clone(),
         };
+        if !index_name.table().is_system() {
+            tx.identity.ensure_can_access_table(index_name.table())?;
+        }
+        let apply_access_rules = !index_name.table().is_system() && tx.is_subject_to_access_rules();
         let stable_index_name =
             IndexModel::new(tx).stable_index_name(namespace, &index_name, table_filter)?;
         let indexed_fields = match query.source {
@@ -453,6 +459,9 @@ impl<RT: Runtime> DeveloperQuery<RT> {
                 version,
             )),
         };
+        if apply_access_rules {
+            cur_node = QueryNode::AccessFilter(Box::new(AccessFilter::new(cur_node, namespace)));
+        }
         for operator in query.operators {
             let next_node = match operator {
                 QueryOperator::Filter(expr) => {
@@ -699,6 +708,7 @@ pub async fn resolved_query_batch_next<RT: Runtime>(
 enum QueryNode {
     IndexRange(IndexRange),
     Search(SearchQuery),
+    AccessFilter(Box<AccessFilter>),
     Filter(Box<Filter>),
     Limit(Box<Limit>),
 }
@@ -709,6 +719,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.cursor_position(),
             QueryNode::Search(r) => r.cursor_position(),
+            QueryNode::AccessFilter(r) => r.cursor_position(),
             QueryNode::Filter(r) => r.cursor_position(),
             QueryNode::Limit(r) => r.cursor_position(),
         }
@@ -718,6 +729,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.split_cursor_position(),
             QueryNode::Search(r) => r.split_cursor_position(),
+            QueryNode::AccessFilter(r) => r.split_cursor_position(),
             QueryNode::Filter(r) => r.split_cursor_position(),
             QueryNode::Limit(r) => r.split_cursor_position(),
         }
@@ -727,6 +739,7 @@ impl QueryStream for QueryNode {
         match self {
             Self::IndexRange(r) => r.is_approaching_data_limit(),
             Self::Search(r) => r.is_approaching_data_limit(),
+            Self::AccessFilter(r) => r.is_approaching_data_limit(),
             Self::Filter(r) => r.is_approaching_data_limit(),
             Self::Limit(r) => r.is_approaching_data_limit(),
         }
@@ -740,6 +753,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.next(tx, prefetch_hint).await,
             QueryNode::Search(r) => r.next(tx, prefetch_hint).await,
+            QueryNode::AccessFilter(r) => r.next(tx, prefetch_hint).await,
             QueryNode::Filter(r) => r.next(tx, prefetch_hint).await,
             QueryNode::Limit(r) => r.next(tx, prefetch_hint).await,
         }
@@ -749,6 +763,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.feed(index_range_response),
             QueryNode::Search(r) => r.feed(index_range_response),
+            QueryNode::AccessFilter(r) => r.feed(index_range_response),
             QueryNode::Filter(r) => r.feed(index_range_response),
             QueryNode::Limit(r) => r.feed(index_range_response),
         }
@@ -758,6 +773,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.tablet_index_name(),
             QueryNode::Search(r) => r.tablet_index_name(),
+            QueryNode::AccessFilter(r) => r.tablet_index_name(),
             QueryNode::Filter(r) => r.tablet_index_name(),
             QueryNode::Limit(r) => r.tablet_index_name(),
         }
@@ -767,6 +783,7 @@ impl QueryStream for QueryNode {
         match self {
             QueryNode::IndexRange(r) => r.printable_index_name(),
             QueryNode::Search(r) => r.printable_index_name(),
+            QueryNode::AccessFilter(r) => r.printable_index_name(),
             QueryNode::Filter(r) => r.printable_index_name(),
             QueryNode::Limit(r) => r.printable_index_name(),
         }
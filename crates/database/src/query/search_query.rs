@@ -251,7 +251,7 @@ impl SearchResultIterator {
 
         let id = DeveloperDocumentId::new(self.table_number, candidate.id);
         let (document, existing_doc_ts) = UserFacingModel::new(tx, self.namespace)
-            .get_with_ts(id, self.version.clone())
+            .get_with_ts_ignoring_access_rules(id, self.version.clone())
             .await?
             .ok_or_else(|| {
                 anyhow::anyhow!("Unable to load search result {id}@{:?}", candidate.ts)
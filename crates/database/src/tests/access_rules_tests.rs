@@ -0,0 +1,162 @@
+use common::{
+    access_rules::{
+        AccessRule,
+        TableAccessRules,
+        ACCESS_RULES_TABLE,
+    },
+    assert_obj,
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use errors::ErrorMetadataAnyhowExt;
+use keybroker::{
+    testing::TestUserIdentity,
+    Identity,
+    UserIdentity,
+};
+use runtime::testing::TestRuntime;
+use value::{
+    id_v6::DeveloperDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    query::{
+        DeveloperQuery,
+        TableFilter,
+    },
+    test_helpers::new_test_database,
+    Database,
+    PatchValue,
+    SystemMetadataModel,
+    Transaction,
+    UserFacingModel,
+};
+
+/// The subject of `UserIdentity::test()`.
+const OWNER: &str = "testauth|123";
+
+fn messages() -> TableName {
+    "messages".parse().unwrap()
+}
+
+/// Creates a `messages` table that only a document's owner can read or write,
+/// with one message owned by the test user and one owned by someone else.
+async fn setup(
+    rt: TestRuntime,
+) -> anyhow::Result<(
+    Database<TestRuntime>,
+    DeveloperDocumentId,
+    DeveloperDocumentId,
+)> {
+    let database = new_test_database(rt).await;
+    let mut tx = database.begin(Identity::system()).await?;
+    tx.create_system_table_testing(TableNamespace::Global, &ACCESS_RULES_TABLE, None)
+        .await?;
+    let owner_only: AccessRule =
+        r#"{"eq": [{"doc": "owner"}, {"identity": "subject"}]}"#.parse()?;
+    let rules = TableAccessRules {
+        table: messages(),
+        read: Some(owner_only.clone()),
+        insert: Some(owner_only.clone()),
+        update: Some(owner_only.clone()),
+        delete: Some(owner_only),
+    };
+    SystemMetadataModel::new_global(&mut tx)
+        .insert(&ACCESS_RULES_TABLE, rules.try_into()?)
+        .await?;
+    let mine = UserFacingModel::new_root_for_test(&mut tx)
+        .insert(messages(), assert_obj!("owner" => OWNER))
+        .await?;
+    let theirs = UserFacingModel::new_root_for_test(&mut tx)
+        .insert(messages(), assert_obj!("owner" => "someone else"))
+        .await?;
+    database.commit(tx).await?;
+    Ok((database, mine, theirs))
+}
+
+async fn query_ids<RT: Runtime>(
+    tx: &mut Transaction<RT>,
+) -> anyhow::Result<Vec<DeveloperDocumentId>> {
+    let query = Query::full_table_scan(messages(), Order::Asc);
+    let mut query_stream = DeveloperQuery::new(
+        tx,
+        TableNamespace::test_user(),
+        query,
+        TableFilter::ExcludePrivateSystemTables,
+    )?;
+    let mut ids = vec![];
+    while let Some(document) = query_stream.next(tx, None).await? {
+        ids.push(document.id());
+    }
+    Ok(ids)
+}
+
+#[convex_macro::test_runtime]
+async fn test_access_rules_hide_unreadable_documents(rt: TestRuntime) -> anyhow::Result<()> {
+    let (database, mine, theirs) = setup(rt).await?;
+
+    let mut tx = database.begin(Identity::user(UserIdentity::test())).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+    assert!(model.get(mine, None).await?.is_some());
+    assert!(model.get(theirs, None).await?.is_none());
+    assert_eq!(query_ids(&mut tx).await?, vec![mine]);
+
+    // Unauthenticated clients don't own anything.
+    let mut tx = database.begin(Identity::Unknown).await?;
+    assert!(UserFacingModel::new_root_for_test(&mut tx)
+        .get(mine, None)
+        .await?
+        .is_none());
+    assert_eq!(query_ids(&mut tx).await?, vec![]);
+
+    // The system isn't subject to access rules.
+    let mut tx = database.begin(Identity::system()).await?;
+    assert!(UserFacingModel::new_root_for_test(&mut tx)
+        .get(theirs, None)
+        .await?
+        .is_some());
+    assert_eq!(query_ids(&mut tx).await?.len(), 2);
+    Ok(())
+}
+
+#[convex_macro::test_runtime]
+async fn test_access_rules_deny_writes(rt: TestRuntime) -> anyhow::Result<()> {
+    let (database, mine, theirs) = setup(rt).await?;
+    let mut tx = database.begin(Identity::user(UserIdentity::test())).await?;
+    let mut model = UserFacingModel::new_root_for_test(&mut tx);
+
+    let err = model
+        .insert(messages(), assert_obj!("owner" => "someone else"))
+        .await
+        .unwrap_err();
+    assert!(err.is_forbidden(), "{err:?}");
+    model
+        .insert(messages(), assert_obj!("owner" => OWNER))
+        .await?;
+
+    // Updates are checked against both the existing and the new document.
+    let err = model
+        .patch(theirs, PatchValue::from(assert_obj!("owner" => OWNER)))
+        .await
+        .unwrap_err();
+    assert!(err.is_forbidden(), "{err:?}");
+    let err = model
+        .replace(mine, assert_obj!("owner" => "someone else"))
+        .await
+        .unwrap_err();
+    assert!(err.is_forbidden(), "{err:?}");
+    model
+        .patch(mine, PatchValue::from(assert_obj!("text" => "hello")))
+        .await?;
+
+    let err = model.delete(theirs).await.unwrap_err();
+    assert!(err.is_forbidden(), "{err:?}");
+    model.delete(mine).await?;
+    database.commit(tx).await?;
+    Ok(())
+}
@@ -98,6 +98,7 @@ use value::{
 };
 
 use crate::{
+    access_rules::AccessRulesByTable,
     bootstrap_model::{
         defaults::BootstrapTableIds,
         table::{
@@ -176,6 +177,8 @@ pub struct Transaction<RT: Runtime> {
 
     pub usage_tracker: FunctionUsageTracker,
     pub(crate) virtual_system_mapping: VirtualSystemMapping,
+    /// Access rules by table, loaded on first use.
+    pub(crate) access_rules: Option<AccessRulesByTable>,
 
     #[cfg(any(test, feature = "testing"))]
     index_size_override: Option<usize>,
@@ -236,6 +239,7 @@ impl<RT: Runtime> Transaction<RT> {
             retention_validator,
             usage_tracker,
             virtual_system_mapping,
+            access_rules: None,
             #[cfg(any(test, feature = "testing"))]
             index_size_override: None,
         }
@@ -561,6 +565,8 @@ impl<RT: Runtime> Transaction<RT> {
         SchemaModel::new(self, namespace)
             .enforce(&new_document)
             .await?;
+        self.enforce_write_access(Some(&old_document), Some(&new_document))
+            .await?;
 
         self.apply_validated_write(id, Some((old_document, old_ts)), Some(new_document.clone()))?;
         Ok(new_document)
@@ -599,6 +605,8 @@ impl<RT: Runtime> Transaction<RT> {
         SchemaModel::new(self, namespace)
             .enforce(&new_document)
             .await?;
+        self.enforce_write_access(Some(&old_document), Some(&new_document))
+            .await?;
 
         self.apply_validated_write(
             new_document.id(),
@@ -624,6 +632,7 @@ impl<RT: Runtime> Transaction<RT> {
                     format!("Delete on nonexistent document ID {id}"),
                 ))?;
 
+        self.enforce_write_access(Some(&document), None).await?;
         self.apply_validated_write(document.id(), Some((document.clone(), ts)), None)?;
         Ok(document)
     }
@@ -689,6 +698,22 @@ impl<RT: Runtime> Transaction<RT> {
         &self.stats
     }
 
+    /// The indexes on user tables that this transaction has read, like
+    /// `messages.by_channel`.
+    pub fn user_indexes_read(&self) -> BTreeSet<String> {
+        let read_set = self.reads.read_set();
+        read_set
+            .iter_indexed()
+            .map(|(index_name, _)| index_name)
+            .chain(read_set.iter_search().map(|(index_name, _)| index_name))
+            .filter_map(|index_name| {
+                let table_name = self.table_mapping().tablet_name(*index_name.table()).ok()?;
+                (!table_name.is_system())
+                    .then(|| format!("{table_name}.{}", index_name.descriptor()))
+            })
+            .collect()
+    }
+
     fn take_table_mapping_dep(&mut self) {
         let tables_by_id = TabletIndexName::by_id(
             self.metadata
@@ -1030,6 +1055,7 @@ impl<RT: Runtime> Transaction<RT> {
             .table_mapping()
             .tablet_namespace(document_id.tablet_id)?;
         SchemaModel::new(self, namespace).enforce(&document).await?;
+        self.enforce_write_access(None, Some(&document)).await?;
         self.apply_validated_write(document_id, None, Some(document))?;
         Ok(document_id)
     }
@@ -47,6 +47,7 @@ pub enum ErrorCode {
     NotFound,
     ClientDisconnect,
     RateLimited,
+    UnprocessableEntity,
 
     Overloaded,
     RejectedBeforeExecution,
@@ -143,6 +144,24 @@ impl ErrorMetadata {
         }
     }
 
+    /// Unprocessable Entity. Maps to 422 in HTTP. For well-formed requests
+    /// that conflict with what the server has already recorded, e.g. reusing
+    /// an idempotency key for a different request.
+    ///
+    /// The short_msg should be a CapitalCamelCased describing the error (eg
+    /// IdempotencyKeyReused). The msg should be a descriptive message
+    /// targeted toward the developer.
+    pub fn unprocessable_entity(
+        short_msg: impl Into<Cow<'static, str>>,
+        msg: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self {
+            code: ErrorCode::UnprocessableEntity,
+            short_msg: short_msg.into(),
+            msg: msg.into(),
+        }
+    }
+
     /// Client disconnected the connection.
     pub fn client_disconnect() -> Self {
         Self {
@@ -392,7 +411,8 @@ impl ErrorMetadata {
             ErrorCode::BadRequest
             | ErrorCode::PaginationLimit
             | ErrorCode::Unauthenticated
-            | ErrorCode::Forbidden => true,
+            | ErrorCode::Forbidden
+            | ErrorCode::UnprocessableEntity => true,
             ErrorCode::OperationalInternalServerError
             | ErrorCode::ClientDisconnect
             | ErrorCode::NotFound
@@ -425,6 +445,7 @@ impl ErrorMetadata {
             | ErrorCode::PaginationLimit
             | ErrorCode::Unauthenticated
             | ErrorCode::Forbidden
+            | ErrorCode::UnprocessableEntity
             | ErrorCode::MisdirectedRequest => Some((sentry::Level::Info, None)),
             ErrorCode::OutOfRetention
             | ErrorCode::RejectedBeforeExecution
@@ -450,6 +471,7 @@ impl ErrorMetadata {
             | ErrorCode::PaginationLimit
             | ErrorCode::Unauthenticated
             | ErrorCode::Forbidden
+            | ErrorCode::UnprocessableEntity
             | ErrorCode::ClientDisconnect
             | ErrorCode::MisdirectedRequest
             | ErrorCode::RateLimited => None,
@@ -481,6 +503,7 @@ impl ErrorMetadata {
             ErrorCode::Overloaded => None,
             ErrorCode::RejectedBeforeExecution => None,
             ErrorCode::OperationalInternalServerError => None,
+            ErrorCode::UnprocessableEntity => None,
             ErrorCode::MisdirectedRequest => None,
         }
     }
@@ -500,7 +523,9 @@ impl ErrorMetadata {
             ErrorCode::OperationalInternalServerError => Some(CloseCode::Error),
             // These ones are client errors - so no close code - the client
             // will handle and close the connection instead.
-            ErrorCode::BadRequest | ErrorCode::Unauthenticated => None,
+            ErrorCode::BadRequest | ErrorCode::Unauthenticated | ErrorCode::UnprocessableEntity => {
+                None
+            },
         }?;
         // According to the WebSocket protocol specification (RFC 6455), the reason
         // string (if present) is limited to 123 bytes. This is because the
@@ -526,6 +551,7 @@ impl ErrorCode {
             ErrorCode::Forbidden => StatusCode::FORBIDDEN,
             ErrorCode::NotFound => StatusCode::NOT_FOUND,
             ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::UnprocessableEntity => StatusCode::UNPROCESSABLE_ENTITY,
             ErrorCode::OperationalInternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             ErrorCode::OCC { .. }
             | ErrorCode::OutOfRetention
@@ -541,6 +567,7 @@ impl ErrorCode {
             ErrorCode::BadRequest => tonic::Code::InvalidArgument,
             ErrorCode::Unauthenticated => tonic::Code::Unauthenticated,
             ErrorCode::Forbidden => tonic::Code::FailedPrecondition,
+            ErrorCode::UnprocessableEntity => tonic::Code::FailedPrecondition,
             ErrorCode::NotFound => tonic::Code::NotFound,
             ErrorCode::ClientDisconnect => tonic::Code::Aborted,
             ErrorCode::Overloaded | ErrorCode::RejectedBeforeExecution | ErrorCode::RateLimited => {
@@ -560,6 +587,7 @@ impl ErrorCode {
             StatusCode::FORBIDDEN => Some(ErrorCode::Forbidden),
             StatusCode::NOT_FOUND => Some(ErrorCode::NotFound),
             StatusCode::TOO_MANY_REQUESTS => Some(ErrorCode::RateLimited),
+            StatusCode::UNPROCESSABLE_ENTITY => Some(ErrorCode::UnprocessableEntity),
             StatusCode::MISDIRECTED_REQUEST => Some(ErrorCode::MisdirectedRequest),
             // Tries to categorize in one of the above more specific 4xx codes first,
             // otherwise categorizes as a general 4xx via BadRequest
@@ -871,6 +899,9 @@ mod proptest {
                 ErrorCode::Unauthenticated => ErrorMetadata::unauthenticated("un", "auth"),
                 ErrorCode::Forbidden => ErrorMetadata::forbidden("for", "bidden"),
                 ErrorCode::RateLimited => ErrorMetadata::rate_limited("too", "many requests"),
+                ErrorCode::UnprocessableEntity => {
+                    ErrorMetadata::unprocessable_entity("unprocessable", "entity")
+                },
                 ErrorCode::Overloaded => ErrorMetadata::overloaded("overloaded", "error"),
                 ErrorCode::RejectedBeforeExecution => {
                     ErrorMetadata::rejected_before_execution("rejected_before_execution", "error")
@@ -71,6 +71,7 @@ use crate::{
     FileRangeStream,
     FileStorage,
     FileStream,
+    FileValidators,
     TransactionalFileStorage,
 };
 
@@ -169,6 +170,21 @@ impl<RT: Runtime> TransactionalFileStorage<RT> {
             .context("batch_key missing")?
     }
 
+    pub async fn get_file_validators(
+        &self,
+        tx: &mut Transaction<RT>,
+        namespace: TableNamespace,
+        storage_id: FileStorageId,
+    ) -> anyhow::Result<Option<FileValidators>> {
+        let file = FileStorageModel::new(tx, namespace)
+            .get_file(storage_id)
+            .await?;
+        Ok(file.map(|file| FileValidators {
+            creation_time: file.creation_time(),
+            sha256: file.into_value().sha256,
+        }))
+    }
+
     pub async fn get_file_entry_batch(
         &self,
         tx: &mut Transaction<RT>,
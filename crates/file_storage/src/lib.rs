@@ -2,6 +2,7 @@
 use std::sync::Arc;
 
 use common::{
+    document::CreationTime,
     runtime::Runtime,
     sha256::Sha256Digest,
     types::ConvexOrigin,
@@ -36,6 +37,13 @@ pub struct FileRangeStream {
     pub stream: BoxStream<'static, futures::io::Result<bytes::Bytes>>,
 }
 
+/// What conditional GETs of a stored file are checked against. Stored files
+/// never change, so these stay valid for as long as the file exists.
+pub struct FileValidators {
+    pub sha256: Sha256Digest,
+    pub creation_time: Option<CreationTime>,
+}
+
 #[derive(Clone)]
 pub struct FileStorage<RT: Runtime> {
     pub database: Database<RT>,
@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::Duration,
+};
+
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ResolvedComponentFunctionPath,
+    },
+    concurrency_limits::FunctionConcurrencyLimits,
+    knobs::{
+        FUNCTION_CONCURRENCY_LIMITS,
+        FUNCTION_CONCURRENCY_LIMIT_QUEUE_TIMEOUT,
+    },
+    runtime::Runtime,
+};
+use errors::ErrorMetadata;
+use futures::{
+    select_biased,
+    FutureExt,
+};
+use parking_lot::Mutex;
+use tokio::sync::{
+    OwnedSemaphorePermit,
+    Semaphore,
+};
+
+use crate::metrics::{
+    function_concurrency_wait_timer,
+    log_function_concurrency_limit_rejected,
+};
+
+/// Enforces the per-function limits from [`FunctionConcurrencyLimits`].
+///
+/// Each limited function gets its own semaphore while it's executing or
+/// waiting to execute, so a `*` default limit doesn't keep a semaphore around
+/// for every function that ever ran. Executions over the limit wait up to
+/// `queue_timeout` for a slot before being rejected with a rate limited error.
+///
+/// Isolate functions are limited in the function runner and Node actions in
+/// the application, each with their own limiter. Limits on the deployment as a
+/// whole are out of scope here; they're the `APPLICATION_MAX_CONCURRENT_*`
+/// limits applied before the function runner.
+pub struct FunctionConcurrencyLimiter<RT: Runtime> {
+    rt: RT,
+    limits: FunctionConcurrencyLimits,
+    queue_timeout: Duration,
+    semaphores: Arc<Semaphores>,
+}
+
+type Semaphores = Mutex<HashMap<CanonicalizedComponentFunctionPath, Arc<Semaphore>>>;
+
+/// A slot for executing a function, released when it's dropped.
+pub struct FunctionConcurrencyPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    semaphore: Arc<Semaphore>,
+    path: CanonicalizedComponentFunctionPath,
+    semaphores: Arc<Semaphores>,
+}
+
+impl Drop for FunctionConcurrencyPermit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        // Every execution of the function holds a reference to its semaphore
+        // while it runs or waits, so if only the map and this permit hold one,
+        // the function is idle. Executions take their reference under the
+        // lock, so they can't race with removing it.
+        let mut semaphores = self.semaphores.lock();
+        if semaphores.get(&self.path).is_some_and(|semaphore| {
+            Arc::ptr_eq(semaphore, &self.semaphore) && Arc::strong_count(semaphore) == 2
+        }) {
+            semaphores.remove(&self.path);
+        }
+    }
+}
+
+impl<RT: Runtime> FunctionConcurrencyLimiter<RT> {
+    pub fn new(rt: RT, limits: FunctionConcurrencyLimits, queue_timeout: Duration) -> Self {
+        Self {
+            rt,
+            limits,
+            queue_timeout,
+            semaphores: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates a limiter for the limits configured with
+    /// `FUNCTION_CONCURRENCY_LIMITS`.
+    pub fn from_knobs(rt: RT) -> Self {
+        Self::new(
+            rt,
+            FUNCTION_CONCURRENCY_LIMITS.clone(),
+            *FUNCTION_CONCURRENCY_LIMIT_QUEUE_TIMEOUT,
+        )
+    }
+
+    /// Acquires a slot for executing `path`. Returns `None` if the function
+    /// has no per-function limit. The slot is released when the returned
+    /// permit is dropped.
+    pub async fn acquire(
+        &self,
+        path: &ResolvedComponentFunctionPath,
+    ) -> anyhow::Result<Option<FunctionConcurrencyPermit>> {
+        if self.limits.is_empty() {
+            return Ok(None);
+        }
+        let path = path.clone().for_logging();
+        let Some(limit) = self.limits.limit_for(&path) else {
+            return Ok(None);
+        };
+        let semaphore = self
+            .semaphores
+            .lock()
+            .entry(path.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+        // The permit evicts the semaphore if we're rejected and nothing else
+        // is using it.
+        let mut permit = FunctionConcurrencyPermit {
+            permit: None,
+            semaphore,
+            path: path.clone(),
+            semaphores: self.semaphores.clone(),
+        };
+        if let Ok(semaphore_permit) = permit.semaphore.clone().try_acquire_owned() {
+            permit.permit = Some(semaphore_permit);
+            return Ok(Some(permit));
+        }
+        if !self.queue_timeout.is_zero() {
+            let timer = function_concurrency_wait_timer();
+            select_biased! {
+                semaphore_permit = permit.semaphore.clone().acquire_owned().fuse() => {
+                    timer.finish();
+                    permit.permit = Some(semaphore_permit?);
+                    return Ok(Some(permit));
+                },
+                _ = self.rt.wait(self.queue_timeout) => {},
+            }
+        }
+        log_function_concurrency_limit_rejected();
+        anyhow::bail!(ErrorMetadata::rate_limited(
+            "TooManyConcurrentFunctionExecutions",
+            format!(
+                "Too many concurrent executions of {}. This function is limited to {limit} \
+                 concurrent executions.",
+                path.debug_str(),
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::components::{
+        ComponentId,
+        ResolvedComponentFunctionPath,
+    };
+    use errors::ErrorMetadataAnyhowExt;
+    use runtime::testing::TestRuntime;
+
+    use super::FunctionConcurrencyLimiter;
+
+    fn path(udf_path: &str) -> ResolvedComponentFunctionPath {
+        ResolvedComponentFunctionPath {
+            component: ComponentId::Root,
+            udf_path: udf_path.parse().unwrap(),
+            component_path: None,
+        }
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_rejects_over_limit(rt: TestRuntime) -> anyhow::Result<()> {
+        let limiter = FunctionConcurrencyLimiter::new(rt, "slack:post=1".parse()?, Duration::ZERO);
+        let permit = limiter.acquire(&path("slack:post")).await?;
+        assert!(permit.is_some());
+        let err = limiter.acquire(&path("slack:post")).await.unwrap_err();
+        assert_eq!(err.short_msg(), "TooManyConcurrentFunctionExecutions");
+        // Other functions are not affected.
+        assert!(limiter.acquire(&path("slack:list")).await?.is_none());
+        drop(permit);
+        assert!(limiter.acquire(&path("slack:post")).await?.is_some());
+        Ok(())
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_evicts_idle_semaphores(rt: TestRuntime) -> anyhow::Result<()> {
+        let limiter = FunctionConcurrencyLimiter::new(rt, "*=1".parse()?, Duration::ZERO);
+        let permit = limiter.acquire(&path("slack:post")).await?;
+        limiter.acquire(&path("slack:post")).await.unwrap_err();
+        assert_eq!(limiter.semaphores.lock().len(), 1);
+        drop(permit);
+        assert!(limiter.semaphores.lock().is_empty());
+        Ok(())
+    }
+}
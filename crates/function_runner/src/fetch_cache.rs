@@ -0,0 +1,462 @@
+//! Opt-in cache for responses to action `fetch` calls.
+//!
+//! Actions frequently hit the same third-party endpoints over and over, which
+//! costs latency and rate limit budget. When enabled with
+//! `FUNRUN_FETCH_RESPONSE_CACHE_ENABLED`, `GET` responses are cached in memory
+//! for as long as their `Cache-Control` header allows and shared across
+//! executions of the same deployment. Entries are keyed by the instance, URL
+//! and every request header, so responses that `Vary` on a header are never
+//! shared between requests that differ in it.
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use common::{
+    http::{
+        fetch::{
+            FetchClient,
+            InternalFetchPurpose,
+            WebSocketConnection,
+            WebSocketRequest,
+        },
+        HttpRequestStream,
+        HttpResponseStream,
+    },
+    knobs::{
+        FUNRUN_FETCH_RESPONSE_CACHE_MAX_ENTRY_SIZE,
+        FUNRUN_FETCH_RESPONSE_CACHE_SIZE,
+    },
+    runtime::Runtime,
+};
+use futures::{
+    stream::{
+        self,
+        BoxStream,
+    },
+    StreamExt,
+};
+use http::{
+    header::{
+        AGE,
+        CACHE_CONTROL,
+        VARY,
+    },
+    HeaderMap,
+    HeaderValue,
+    Method,
+    StatusCode,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+use url::Url;
+
+use crate::metrics::{
+    log_fetch_response_cache_get,
+    log_fetch_response_cache_size,
+};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct CacheKey {
+    instance_name: String,
+    url: Url,
+    headers: Vec<(String, Vec<u8>)>,
+}
+
+impl CacheKey {
+    fn new(instance_name: &str, request: &HttpRequestStream) -> Self {
+        let mut headers: Vec<_> = request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+            .collect();
+        headers.sort();
+        Self {
+            instance_name: instance_name.to_string(),
+            url: request.url.clone(),
+            headers,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.url.as_str().len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    url: Option<Url>,
+    body: Bytes,
+    stored_at: tokio::time::Instant,
+    expires_at: tokio::time::Instant,
+}
+
+impl CachedResponse {
+    fn size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>()
+    }
+}
+
+pub struct FetchResponseCache<RT: Runtime> {
+    rt: RT,
+    inner: Mutex<Inner>,
+    max_entry_size: usize,
+}
+
+struct Inner {
+    cache: LruCache<CacheKey, CachedResponse>,
+    size: usize,
+    size_limit: usize,
+}
+
+impl<RT: Runtime> FetchResponseCache<RT> {
+    pub fn new(rt: RT) -> Self {
+        Self::new_with_limits(
+            rt,
+            *FUNRUN_FETCH_RESPONSE_CACHE_SIZE,
+            *FUNRUN_FETCH_RESPONSE_CACHE_MAX_ENTRY_SIZE,
+        )
+    }
+
+    fn new_with_limits(rt: RT, size_limit: usize, max_entry_size: usize) -> Self {
+        Self {
+            rt,
+            inner: Mutex::new(Inner {
+                cache: LruCache::unbounded(),
+                size: 0,
+                size_limit,
+            }),
+            max_entry_size,
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        let mut inner = self.inner.lock();
+        let result = match inner.cache.get(key) {
+            Some(response) if response.expires_at > self.rt.monotonic_now() => {
+                Some(response.clone())
+            },
+            Some(_) => {
+                let (key, expired) = inner.cache.pop_entry(key).expect("Entry disappeared");
+                inner.size -= key.size() + expired.size();
+                None
+            },
+            None => None,
+        };
+        log_fetch_response_cache_get(result.is_some());
+        log_fetch_response_cache_size(inner.size);
+        result
+    }
+
+    fn insert(&self, key: CacheKey, response: CachedResponse) {
+        let mut inner = self.inner.lock();
+        let size = key.size() + response.size();
+        if size > inner.size_limit {
+            return;
+        }
+        inner.size += size;
+        if let Some((previous_key, previous)) = inner.cache.push(key, response) {
+            inner.size -= previous_key.size() + previous.size();
+        }
+        while inner.size > inner.size_limit {
+            let Some((key, evicted)) = inner.cache.pop_lru() else {
+                break;
+            };
+            inner.size -= key.size() + evicted.size();
+        }
+        log_fetch_response_cache_size(inner.size);
+    }
+}
+
+/// Wraps a deployment's [`FetchClient`] to serve `GET` requests from a
+/// [`FetchResponseCache`] when possible.
+pub struct CachingFetchClient<RT: Runtime> {
+    inner: Arc<dyn FetchClient>,
+    cache: Arc<FetchResponseCache<RT>>,
+    instance_name: String,
+}
+
+impl<RT: Runtime> CachingFetchClient<RT> {
+    pub fn new(
+        inner: Arc<dyn FetchClient>,
+        cache: Arc<FetchResponseCache<RT>>,
+        instance_name: String,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            instance_name,
+        }
+    }
+}
+
+#[async_trait]
+impl<RT: Runtime> FetchClient for CachingFetchClient<RT> {
+    async fn fetch(&self, request: HttpRequestStream) -> anyhow::Result<HttpResponseStream> {
+        // `GET` requests don't have meaningful bodies, so the URL and headers
+        // identify the response.
+        let request_directives = CacheDirectives::parse(&request.headers);
+        if request.method != Method::GET || request_directives.no_store {
+            return self.inner.fetch(request).await;
+        }
+        let key = CacheKey::new(&self.instance_name, &request);
+        let cached = if request_directives.no_cache {
+            None
+        } else {
+            self.cache.get(&key)
+        };
+        if let Some(cached) = cached {
+            let mut headers = cached.headers;
+            let age = (self.cache.rt.monotonic_now() - cached.stored_at).as_secs();
+            headers.insert(AGE, HeaderValue::from(age));
+            return Ok(HttpResponseStream {
+                status: cached.status,
+                headers,
+                url: cached.url,
+                body: Some(stream::once(async move { Ok(cached.body) }).boxed()),
+            });
+        }
+
+        let mut response = self.inner.fetch(request).await?;
+        let Some(ttl) = cacheable_ttl(&response) else {
+            return Ok(response);
+        };
+        let Some(body) = response.body.take() else {
+            return Ok(response);
+        };
+        match buffer_body(body, self.cache.max_entry_size).await {
+            Ok(body) => {
+                let now = self.cache.rt.monotonic_now();
+                self.cache.insert(
+                    key,
+                    CachedResponse {
+                        status: response.status,
+                        headers: response.headers.clone(),
+                        url: response.url.clone(),
+                        body: body.clone(),
+                        stored_at: now,
+                        expires_at: now + ttl,
+                    },
+                );
+                response.body = Some(stream::once(async move { Ok(body) }).boxed());
+            },
+            Err(body) => response.body = Some(body),
+        }
+        Ok(response)
+    }
+
+    async fn internal_fetch(
+        &self,
+        request: HttpRequestStream,
+        purpose: InternalFetchPurpose,
+    ) -> anyhow::Result<HttpResponseStream> {
+        self.inner.internal_fetch(request, purpose).await
+    }
+
+    async fn connect_websocket(
+        &self,
+        request: WebSocketRequest,
+    ) -> anyhow::Result<WebSocketConnection> {
+        self.inner.connect_websocket(request).await
+    }
+}
+
+#[derive(Default)]
+struct CacheDirectives {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+}
+
+impl CacheDirectives {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = Self::default();
+        for value in headers.get_all(CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for directive in value.split(',') {
+                let (name, argument) = match directive.split_once('=') {
+                    Some((name, argument)) => (name, Some(argument.trim().trim_matches('"'))),
+                    None => (directive, None),
+                };
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "no-store" => directives.no_store = true,
+                    "no-cache" => directives.no_cache = true,
+                    "private" => directives.private = true,
+                    "max-age" => directives.max_age = argument.and_then(|a| a.parse().ok()),
+                    "s-maxage" => directives.s_maxage = argument.and_then(|a| a.parse().ok()),
+                    _ => {},
+                }
+            }
+        }
+        directives
+    }
+}
+
+/// Returns how long a response may be cached for, if at all. The cache is
+/// shared between executions, so it follows the rules for shared caches.
+fn cacheable_ttl(response: &HttpResponseStream) -> Option<Duration> {
+    if response.status != StatusCode::OK
+        || response
+            .headers
+            .get_all(VARY)
+            .iter()
+            .any(|value| value.as_bytes().trim_ascii() == b"*")
+    {
+        return None;
+    }
+    let directives = CacheDirectives::parse(&response.headers);
+    if directives.no_store || directives.no_cache || directives.private {
+        return None;
+    }
+    let ttl = directives.s_maxage.or(directives.max_age)?;
+    (ttl > 0).then(|| Duration::from_secs(ttl))
+}
+
+/// Reads `body` into memory if it's at most `max_size` bytes. Otherwise, or if
+/// the body fails partway through, returns a stream equivalent to the
+/// original.
+async fn buffer_body(
+    mut body: BoxStream<'static, anyhow::Result<Bytes>>,
+    max_size: usize,
+) -> Result<Bytes, BoxStream<'static, anyhow::Result<Bytes>>> {
+    let mut chunks = vec![];
+    let mut size = 0;
+    while let Some(chunk) = body.next().await {
+        match chunk {
+            Ok(chunk) => {
+                size += chunk.len();
+                chunks.push(chunk);
+                if size > max_size {
+                    return Err(stream::iter(chunks.into_iter().map(Ok)).chain(body).boxed());
+                }
+            },
+            Err(e) => {
+                return Err(stream::iter(chunks.into_iter().map(Ok))
+                    .chain(stream::once(async move { Err(e) }))
+                    .boxed());
+            },
+        }
+    }
+    Ok(chunks.concat().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::Duration,
+    };
+
+    use common::http::{
+        fetch::{
+            FetchClient,
+            StaticFetchClient,
+        },
+        HttpRequest,
+        HttpResponse,
+        HttpResponseStream,
+    };
+    use futures::FutureExt;
+    use http::{
+        header::{
+            AGE,
+            CACHE_CONTROL,
+        },
+        HeaderMap,
+        HeaderValue,
+        Method,
+        StatusCode,
+    };
+    use runtime::testing::TestRuntime;
+
+    use super::{
+        CachingFetchClient,
+        FetchResponseCache,
+    };
+
+    fn get(url: &url::Url) -> HttpRequest {
+        HttpRequest {
+            headers: HeaderMap::new(),
+            url: url.clone(),
+            method: Method::GET,
+            body: None,
+        }
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_fetch_response_cache(rt: TestRuntime) -> anyhow::Result<()> {
+        let mut static_client = StaticFetchClient::new();
+        let cached_url: url::Url = "https://api.example.com/rates".parse()?;
+        let uncached_url: url::Url = "https://api.example.com/now".parse()?;
+        for (url, cache_control) in [
+            (cached_url.clone(), "public, max-age=60"),
+            (uncached_url.clone(), "no-store"),
+        ] {
+            static_client.register_http_route(url, Method::GET, move |_| {
+                async move {
+                    let headers = HeaderMap::from_iter([(
+                        CACHE_CONTROL,
+                        HeaderValue::from_static(cache_control),
+                    )]);
+                    let response =
+                        HttpResponse::new(StatusCode::OK, headers, Some(b"42".to_vec()), None);
+                    Ok(HttpResponseStream::from(response))
+                }
+                .boxed()
+            });
+        }
+        let static_client = Arc::new(static_client);
+        let cache = Arc::new(FetchResponseCache::new_with_limits(
+            rt.clone(),
+            1 << 20,
+            1024,
+        ));
+        let client = CachingFetchClient::new(static_client.clone(), cache.clone(), "a".into());
+
+        for _ in 0..3 {
+            let response = client.fetch(get(&cached_url).into()).await?;
+            let response = response.into_http_response().await?;
+            assert_eq!(response.body, Some(b"42".to_vec()));
+        }
+        assert_eq!(static_client.num_calls(), 1);
+
+        // Responses aren't shared across deployments.
+        let other_client =
+            CachingFetchClient::new(static_client.clone(), cache.clone(), "b".into());
+        other_client.fetch(get(&cached_url).into()).await?;
+        assert_eq!(static_client.num_calls(), 2);
+
+        // Responses that forbid caching are always fetched.
+        client.fetch(get(&uncached_url).into()).await?;
+        client.fetch(get(&uncached_url).into()).await?;
+        assert_eq!(static_client.num_calls(), 4);
+
+        // Entries expire after their max-age.
+        rt.advance_time(Duration::from_secs(30)).await;
+        let response = client.fetch(get(&cached_url).into()).await?;
+        assert_eq!(response.headers.get(AGE), Some(&HeaderValue::from(30)));
+        assert_eq!(static_client.num_calls(), 4);
+        rt.advance_time(Duration::from_secs(31)).await;
+        client.fetch(get(&cached_url).into()).await?;
+        assert_eq!(static_client.num_calls(), 5);
+        Ok(())
+    }
+}
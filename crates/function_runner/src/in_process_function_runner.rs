@@ -74,6 +74,7 @@ use value::identifier::Identifier;
 
 use super::FunctionRunner;
 use crate::{
+    concurrency_limiter::FunctionConcurrencyLimiter,
     server::{
         validate_run_function_result,
         FunctionMetadata,
@@ -126,6 +127,13 @@ impl<RT: Runtime> InProcessFunctionRunner<RT> {
             fetch_client,
         })
     }
+
+    /// Replaces the per-function concurrency limits from the knobs.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn with_concurrency_limiter(mut self, limiter: FunctionConcurrencyLimiter<RT>) -> Self {
+        self.server.set_concurrency_limiter(limiter);
+        self
+    }
 }
 
 #[async_trait]
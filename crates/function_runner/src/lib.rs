@@ -78,6 +78,8 @@ use value::{
     TabletId,
 };
 
+pub mod concurrency_limiter;
+pub mod fetch_cache;
 mod in_memory_indexes;
 pub mod in_process_function_runner;
 mod metrics;
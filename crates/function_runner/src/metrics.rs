@@ -1,8 +1,12 @@
 use metrics::{
+    log_counter,
     log_counter_with_labels,
     log_distribution_with_labels,
+    log_gauge,
     register_convex_counter,
+    register_convex_gauge,
     register_convex_histogram,
+    IntoLabel,
     MetricLabel,
     StaticMetricLabel,
     Timer,
@@ -67,3 +71,43 @@ register_convex_histogram!(
 pub fn begin_tx_timer() -> Timer<VMHistogram> {
     Timer::new(&FUNCTION_RUNNER_BEGIN_TX_SECONDS)
 }
+
+register_convex_histogram!(
+    FUNCTION_RUNNER_CONCURRENCY_LIMIT_WAIT_SECONDS,
+    "Time spent waiting for a per-function concurrency slot",
+);
+pub fn function_concurrency_wait_timer() -> Timer<VMHistogram> {
+    Timer::new(&FUNCTION_RUNNER_CONCURRENCY_LIMIT_WAIT_SECONDS)
+}
+
+register_convex_counter!(
+    FUNCTION_RUNNER_CONCURRENCY_LIMIT_REJECTED_TOTAL,
+    "Number of function executions rejected by per-function concurrency limits",
+);
+pub fn log_function_concurrency_limit_rejected() {
+    log_counter(&FUNCTION_RUNNER_CONCURRENCY_LIMIT_REJECTED_TOTAL, 1);
+}
+
+register_convex_counter!(
+    FUNCTION_RUNNER_FETCH_RESPONSE_CACHE_GET_TOTAL,
+    "Number of action fetch response cache lookups",
+    &["hit"]
+);
+pub fn log_fetch_response_cache_get(hit: bool) {
+    log_counter_with_labels(
+        &FUNCTION_RUNNER_FETCH_RESPONSE_CACHE_GET_TOTAL,
+        1,
+        vec![StaticMetricLabel::new("hit", hit.as_label())],
+    );
+}
+
+register_convex_gauge!(
+    FUNCTION_RUNNER_FETCH_RESPONSE_CACHE_SIZE_BYTES,
+    "Total size of cached action fetch responses"
+);
+pub fn log_fetch_response_cache_size(size: usize) {
+    log_gauge(
+        &FUNCTION_RUNNER_FETCH_RESPONSE_CACHE_SIZE_BYTES,
+        size as f64,
+    );
+}
@@ -13,6 +13,7 @@ use common::{
     auth::AuthConfig,
     bootstrap_model::components::definition::ComponentDefinitionMetadata,
     components::{
+        CanonicalizedComponentFunctionPath,
         ComponentDefinitionPath,
         ComponentName,
         Resource,
@@ -23,6 +24,7 @@ use common::{
         fetch::FetchClient,
         RoutedHttpPath,
     },
+    knobs::FUNRUN_FETCH_RESPONSE_CACHE_ENABLED,
     log_lines::LogLine,
     persistence::{
         NoopRetentionValidator,
@@ -65,9 +67,12 @@ use keybroker::{
 };
 use model::{
     config::types::ModuleConfig,
-    environment_variables::types::{
-        EnvVarName,
-        EnvVarValue,
+    environment_variables::{
+        types::{
+            EnvVarName,
+            EnvVarValue,
+        },
+        EnvironmentVariablesModel,
     },
     modules::module_versions::{
         AnalyzedModule,
@@ -91,6 +96,7 @@ use udf::{
         ValidatedPathAndArgs,
     },
     EvaluateAppDefinitionsResult,
+    ExecutionRecording,
     FunctionOutcome,
     HttpActionRequest as HttpActionRequestInner,
     HttpActionResponseStreamer,
@@ -103,6 +109,11 @@ use value::identifier::Identifier;
 
 use super::in_memory_indexes::InMemoryIndexCache;
 use crate::{
+    concurrency_limiter::FunctionConcurrencyLimiter,
+    fetch_cache::{
+        CachingFetchClient,
+        FetchResponseCache,
+    },
     module_cache::{
         FunctionRunnerModuleLoader,
         ModuleCache,
@@ -111,6 +122,8 @@ use crate::{
     FunctionWrites,
 };
 
+/// Upper bound on isolate worker threads. The pool scales between
+/// `ISOLATE_POOL_MIN_WORKERS` and this based on load.
 const MAX_ISOLATE_WORKERS: usize = 128;
 
 pub struct RunRequestArgs {
@@ -137,6 +150,9 @@ pub struct RunRequestArgs {
 pub struct FunctionMetadata {
     pub path_and_args: ValidatedPathAndArgs,
     pub journal: QueryJournal,
+    /// For queries, mutations and actions, a recording to replay instead of
+    /// executing the function against the database and network.
+    pub replay: Option<ExecutionRecording>,
 }
 
 pub struct HttpActionMetadata {
@@ -185,6 +201,9 @@ pub struct FunctionRunnerCore<RT: Runtime, S: StorageForInstance<RT>> {
     index_cache: InMemoryIndexCache<RT>,
     module_cache: ModuleCache<RT>,
     isolate_client: IsolateClient<RT>,
+    concurrency_limiter: Arc<FunctionConcurrencyLimiter<RT>>,
+    /// Shared cache for action `fetch` responses, if enabled.
+    fetch_response_cache: Option<Arc<FetchResponseCache<RT>>>,
 }
 
 impl<RT: Runtime, S: StorageForInstance<RT>> Clone for FunctionRunnerCore<RT, S> {
@@ -195,6 +214,8 @@ impl<RT: Runtime, S: StorageForInstance<RT>> Clone for FunctionRunnerCore<RT, S>
             index_cache: self.index_cache.clone(),
             module_cache: self.module_cache.clone(),
             isolate_client: self.isolate_client.clone(),
+            concurrency_limiter: self.concurrency_limiter.clone(),
+            fetch_response_cache: self.fetch_response_cache.clone(),
         }
     }
 }
@@ -238,6 +259,9 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
         )?;
         let index_cache = InMemoryIndexCache::new(rt.clone());
         let module_cache = ModuleCache::new(rt.clone());
+        let concurrency_limiter = Arc::new(FunctionConcurrencyLimiter::from_knobs(rt.clone()));
+        let fetch_response_cache = (*FUNRUN_FETCH_RESPONSE_CACHE_ENABLED)
+            .then(|| Arc::new(FetchResponseCache::new(rt.clone())));
 
         Ok(Self {
             rt,
@@ -245,6 +269,8 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
             index_cache,
             module_cache,
             isolate_client,
+            concurrency_limiter,
+            fetch_response_cache,
         })
     }
 
@@ -252,6 +278,11 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
         self.isolate_client.shutdown().await
     }
 
+    #[cfg(any(test, feature = "testing"))]
+    pub fn set_concurrency_limiter(&mut self, limiter: FunctionConcurrencyLimiter<RT>) {
+        self.concurrency_limiter = Arc::new(limiter);
+    }
+
     pub async fn begin_tx(
         &self,
         identity: Identity,
@@ -328,7 +359,7 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
             identity,
             ts,
             existing_writes,
-            system_env_vars,
+            mut system_env_vars,
             in_memory_index_last_modified,
             context,
         }: RunRequestArgs,
@@ -339,6 +370,27 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
         FunctionOutcome,
         FunctionUsageStats,
     )> {
+        // Hold the per-function concurrency slot (if any) until the function
+        // finishes executing. HTTP actions are all limited together under the
+        // path of the `http.js` router, and get the environment variable
+        // overrides scoped to it.
+        let function_path = match (&function_metadata, &http_action_metadata) {
+            (Some(metadata), _) => Some(metadata.path_and_args.path().clone()),
+            (None, Some(metadata)) => Some(metadata.http_module_path.path().clone()),
+            (None, None) => None,
+        };
+        let _concurrency_permit = match &function_path {
+            Some(path) => self.concurrency_limiter.acquire(path).await?,
+            None => None,
+        };
+        let fetch_client: Arc<dyn FetchClient> = match &self.fetch_response_cache {
+            Some(cache) => Arc::new(CachingFetchClient::new(
+                fetch_client,
+                cache.clone(),
+                instance_name.clone(),
+            )),
+            None => fetch_client,
+        };
         let usage_tracker = FunctionUsageTracker::new();
         let retention_validator: Arc<dyn RetentionValidator> = match udf_type {
             // Since queries and mutations are ready only, we can check the retention
@@ -380,10 +432,33 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
             .storage_for_instance(&mut transaction, StorageUseCase::Modules)
             .await?;
 
+        let env_var_overrides = match function_path {
+            Some(path) => {
+                let path = CanonicalizedComponentFunctionPath {
+                    component: transaction.must_component_path(path.component)?,
+                    udf_path: path.udf_path,
+                };
+                EnvironmentVariablesModel::new(&mut transaction)
+                    .get_overrides_for_function(&path)
+                    .await?
+            },
+            None => BTreeMap::new(),
+        };
+
         let key_broker = KeyBroker::new(&instance_name, instance_secret)?;
+        // Secrets are only decrypted here, right before functions run. Like
+        // system environment variables, they're only visible to the root
+        // component, and aren't available when analyzing pushed code.
+        for (name, encrypted_value) in EnvironmentVariablesModel::new(&mut transaction)
+            .get_current_secrets()
+            .await?
+        {
+            system_env_vars.insert(name, key_broker.decrypt_env_secret(&encrypted_value)?);
+        }
         let environment_data = EnvironmentData {
             key_broker,
             system_env_vars,
+            env_var_overrides,
             file_storage,
             module_loader: Arc::new(FunctionRunnerModuleLoader {
                 instance_name: instance_name.clone(),
@@ -397,20 +472,37 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
                 let FunctionMetadata {
                     path_and_args,
                     journal,
+                    replay,
                 } = function_metadata.context("Missing function metadata for query or mutation")?;
-                let (tx, outcome) = self
-                    .isolate_client
-                    .execute_udf(
-                        udf_type,
-                        path_and_args,
-                        transaction,
-                        journal,
-                        context,
-                        environment_data,
-                        0,
-                        instance_name,
-                    )
-                    .await?;
+                let (tx, outcome) = match replay {
+                    Some(recording) => {
+                        anyhow::ensure!(recording.udf_type == udf_type);
+                        self.isolate_client
+                            .replay_udf(
+                                path_and_args,
+                                transaction,
+                                context,
+                                environment_data,
+                                instance_name,
+                                recording,
+                            )
+                            .await?
+                    },
+                    None => {
+                        self.isolate_client
+                            .execute_udf(
+                                udf_type,
+                                path_and_args,
+                                transaction,
+                                journal,
+                                context,
+                                environment_data,
+                                0,
+                                instance_name,
+                            )
+                            .await?
+                    },
+                };
                 Ok((
                     Some(tx.try_into()?),
                     outcome,
@@ -418,23 +510,45 @@ impl<RT: Runtime, S: StorageForInstance<RT>> FunctionRunnerCore<RT, S> {
                 ))
             },
             UdfType::Action => {
-                let FunctionMetadata { path_and_args, .. } =
-                    function_metadata.context("Missing function metadata for action")?;
+                let FunctionMetadata {
+                    path_and_args,
+                    replay,
+                    ..
+                } = function_metadata.context("Missing function metadata for action")?;
                 let log_line_sender =
                     log_line_sender.context("Missing log line sender for action")?;
-                let outcome = self
-                    .isolate_client
-                    .execute_action(
-                        path_and_args,
-                        transaction,
-                        action_callbacks,
-                        fetch_client,
-                        log_line_sender,
-                        context,
-                        environment_data,
-                        instance_name,
-                    )
-                    .await?;
+                let outcome = match replay {
+                    Some(recording) => {
+                        anyhow::ensure!(recording.udf_type == udf_type);
+                        self.isolate_client
+                            .replay_action(
+                                path_and_args,
+                                transaction,
+                                action_callbacks,
+                                fetch_client,
+                                log_line_sender,
+                                context,
+                                environment_data,
+                                instance_name,
+                                recording,
+                            )
+                            .await?
+                    },
+                    None => {
+                        self.isolate_client
+                            .execute_action(
+                                path_and_args,
+                                transaction,
+                                action_callbacks,
+                                fetch_client,
+                                log_line_sender,
+                                context,
+                                environment_data,
+                                instance_name,
+                            )
+                            .await?
+                    },
+                };
                 Ok((
                     None,
                     FunctionOutcome::Action(outcome),
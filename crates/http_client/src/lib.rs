@@ -47,7 +47,8 @@ pub enum ClientPurpose {
 
 pub fn cached_http_client_for(
     purpose: ClientPurpose,
-) -> impl Fn(HttpRequest) -> (impl Future<Output = Result<HttpResponse, AsStdError>> + 'static) {
+) -> impl Fn(HttpRequest) -> (impl Future<Output = Result<HttpResponse, AsStdError>> + 'static) + Clone
+{
     move |request: HttpRequest| cached_http_client_inner(request, purpose)
 }
 
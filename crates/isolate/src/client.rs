@@ -5,7 +5,12 @@ use std::{
         HashMap,
         VecDeque,
     },
+    pin::Pin,
     sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
         Arc,
         Once,
     },
@@ -53,6 +58,7 @@ use common::{
         HEAP_WORKER_REPORT_INTERVAL_SECONDS,
         ISOLATE_IDLE_TIMEOUT,
         ISOLATE_MAX_LIFETIME,
+        ISOLATE_POOL_SCALE_INTERVAL,
         ISOLATE_QUEUE_SIZE,
         REUSE_ISOLATES,
         V8_THREADS,
@@ -121,6 +127,7 @@ use model::{
         ModuleSource,
         SourceMap,
     },
+    scheduled_jobs::types::ScheduleOptions,
     udf_config::types::UdfConfig,
 };
 use parking_lot::Mutex;
@@ -138,6 +145,7 @@ use udf::{
     },
     ActionOutcome,
     EvaluateAppDefinitionsResult,
+    ExecutionRecording,
     FunctionOutcome,
     FunctionResult,
     HttpActionOutcome,
@@ -160,11 +168,21 @@ use crate::{
     metrics::{
         self,
         log_aggregated_heap_stats,
+        log_pool_allocated_count,
+        log_pool_cpu_percent,
         log_pool_max,
+        log_pool_queue_depth,
         log_pool_running_count,
+        log_pool_target_count,
+        log_pool_worker_retired,
         log_worker_stolen,
+        pool_saturated_error,
         queue_timer,
     },
+    pool_autoscaler::{
+        PoolAutoscaler,
+        PoolSample,
+    },
 };
 
 // We gather prometheus stats every 30 seconds, so we should make sure we log
@@ -288,6 +306,7 @@ pub trait ActionCallbacks: Send + Sync {
         udf_args: Vec<JsonValue>,
         scheduled_ts: UnixTimestamp,
         context: ExecutionContext,
+        options: ScheduleOptions,
     ) -> anyhow::Result<DeveloperDocumentId>;
 
     async fn cancel_job(
@@ -323,6 +342,8 @@ pub struct UdfRequest<RT: Runtime> {
     pub transaction: Transaction<RT>,
     pub journal: QueryJournal,
     pub context: ExecutionContext,
+    /// If set, replay this recording instead of running syscalls.
+    pub replay: Option<ExecutionRecording>,
 }
 
 pub struct HttpActionRequest<RT: Runtime> {
@@ -339,6 +360,8 @@ pub struct ActionRequest<RT: Runtime> {
     pub transaction: Transaction<RT>,
     pub identity: Identity,
     pub context: ExecutionContext,
+    /// If set, replay this recording instead of running the action's tasks.
+    pub replay: Option<ExecutionRecording>,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -351,6 +374,9 @@ pub struct ActionRequestParams {
 pub struct EnvironmentData<RT: Runtime> {
     pub key_broker: KeyBroker,
     pub system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
+    /// Environment variable overrides scoped to the function being executed.
+    /// These take precedence over both system and user environment variables.
+    pub env_var_overrides: BTreeMap<EnvVarName, EnvVarValue>,
     pub file_storage: TransactionalFileStorage<RT>,
     pub module_loader: Arc<dyn ModuleLoader<RT>>,
 }
@@ -527,6 +553,7 @@ impl<RT: Runtime> Clone for IsolateClient<RT> {
             handles: self.handles.clone(),
             scheduler: self.scheduler.clone(),
             sender: self.sender.clone(),
+            saturated: self.saturated.clone(),
             concurrency_logger: self.concurrency_logger.clone(),
         }
     }
@@ -599,6 +626,8 @@ pub struct IsolateClient<RT: Runtime> {
     handles: Arc<Mutex<Vec<IsolateWorkerHandle>>>,
     scheduler: Arc<Mutex<Option<Box<dyn SpawnHandle>>>>,
     sender: CoDelQueueSender<RT, Request<RT>>,
+    /// Set by the scheduler while the pool can't grow to serve its queue.
+    saturated: Arc<AtomicBool>,
     concurrency_logger: Arc<Mutex<Option<Box<dyn SpawnHandle>>>>,
 }
 
@@ -628,25 +657,23 @@ impl<RT: Runtime> IsolateClient<RT> {
         let (sender, receiver) =
             new_codel_queue_async::<_, Request<_>>(rt.clone(), *ISOLATE_QUEUE_SIZE);
         let handles = Arc::new(Mutex::new(Vec::new()));
-        let handles_clone = handles.clone();
-        let rt_clone = rt.clone();
-        let scheduler = rt.spawn("shared_isolate_scheduler", async move {
-            // The scheduler thread pops a worker from available_workers and
-            // pops a request from the CoDelQueueReceiver. Then it sends the request
-            // to the worker.
-            let isolate_worker = FunctionRunnerIsolateWorker::new(rt_clone.clone(), isolate_config);
-            let scheduler = SharedIsolateScheduler::new(
-                rt_clone,
-                isolate_worker,
-                max_isolate_workers,
-                handles_clone,
-                max_percent_per_client,
-            );
-            scheduler.run(receiver).await
-        });
+        // The scheduler thread pops a worker from available_workers and
+        // pops a request from the CoDelQueueReceiver. Then it sends the request
+        // to the worker.
+        let isolate_worker = FunctionRunnerIsolateWorker::new(rt.clone(), isolate_config);
+        let scheduler = SharedIsolateScheduler::new(
+            rt.clone(),
+            isolate_worker,
+            max_isolate_workers,
+            handles.clone(),
+            max_percent_per_client,
+        );
+        let saturated = scheduler.saturated();
+        let scheduler = rt.spawn("shared_isolate_scheduler", scheduler.run(receiver));
         Ok(Self {
             rt,
             sender,
+            saturated,
             scheduler: Arc::new(Mutex::new(Some(scheduler))),
             concurrency_logger: Arc::new(Mutex::new(Some(concurrency_logger))),
             handles,
@@ -672,6 +699,59 @@ impl<RT: Runtime> IsolateClient<RT> {
         environment_data: EnvironmentData<RT>,
         reactor_depth: usize,
         instance_name: String,
+    ) -> anyhow::Result<(Transaction<RT>, FunctionOutcome)> {
+        self.execute_udf_inner(
+            udf_type,
+            path_and_args,
+            transaction,
+            journal,
+            context,
+            environment_data,
+            reactor_depth,
+            instance_name,
+            None,
+        )
+        .await
+    }
+
+    /// Re-executes a recorded query or mutation, answering its syscalls from
+    /// the recording rather than `transaction`. The transaction is only used
+    /// to load the function's modules and configuration.
+    #[fastrace::trace]
+    pub async fn replay_udf(
+        &self,
+        path_and_args: ValidatedPathAndArgs,
+        transaction: Transaction<RT>,
+        context: ExecutionContext,
+        environment_data: EnvironmentData<RT>,
+        instance_name: String,
+        recording: ExecutionRecording,
+    ) -> anyhow::Result<(Transaction<RT>, FunctionOutcome)> {
+        self.execute_udf_inner(
+            recording.udf_type,
+            path_and_args,
+            transaction,
+            QueryJournal::new(),
+            context,
+            environment_data,
+            0,
+            instance_name,
+            Some(recording),
+        )
+        .await
+    }
+
+    async fn execute_udf_inner(
+        &self,
+        udf_type: UdfType,
+        path_and_args: ValidatedPathAndArgs,
+        transaction: Transaction<RT>,
+        journal: QueryJournal,
+        context: ExecutionContext,
+        environment_data: EnvironmentData<RT>,
+        reactor_depth: usize,
+        instance_name: String,
+        replay: Option<ExecutionRecording>,
     ) -> anyhow::Result<(Transaction<RT>, FunctionOutcome)> {
         let (tx, rx) = oneshot::channel();
         let request = RequestType::Udf {
@@ -682,6 +762,7 @@ impl<RT: Runtime> IsolateClient<RT> {
                 transaction,
                 journal,
                 context,
+                replay,
             },
             environment_data,
             response: tx,
@@ -710,6 +791,61 @@ impl<RT: Runtime> IsolateClient<RT> {
         context: ExecutionContext,
         environment_data: EnvironmentData<RT>,
         instance_name: String,
+    ) -> anyhow::Result<ActionOutcome> {
+        self.execute_action_inner(
+            path_and_args,
+            transaction,
+            action_callbacks,
+            fetch_client,
+            log_line_sender,
+            context,
+            environment_data,
+            instance_name,
+            None,
+        )
+        .await
+    }
+
+    /// Re-executes a recorded action, completing its syscalls, fetches and
+    /// other async operations from the recording instead of running them.
+    #[fastrace::trace]
+    pub async fn replay_action(
+        &self,
+        path_and_args: ValidatedPathAndArgs,
+        transaction: Transaction<RT>,
+        action_callbacks: Arc<dyn ActionCallbacks>,
+        fetch_client: Arc<dyn FetchClient>,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+        context: ExecutionContext,
+        environment_data: EnvironmentData<RT>,
+        instance_name: String,
+        recording: ExecutionRecording,
+    ) -> anyhow::Result<ActionOutcome> {
+        self.execute_action_inner(
+            path_and_args,
+            transaction,
+            action_callbacks,
+            fetch_client,
+            log_line_sender,
+            context,
+            environment_data,
+            instance_name,
+            Some(recording),
+        )
+        .await
+    }
+
+    async fn execute_action_inner(
+        &self,
+        path_and_args: ValidatedPathAndArgs,
+        transaction: Transaction<RT>,
+        action_callbacks: Arc<dyn ActionCallbacks>,
+        fetch_client: Arc<dyn FetchClient>,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+        context: ExecutionContext,
+        environment_data: EnvironmentData<RT>,
+        instance_name: String,
+        replay: Option<ExecutionRecording>,
     ) -> anyhow::Result<ActionOutcome> {
         let (tx, rx) = oneshot::channel();
         let request = RequestType::Action {
@@ -718,6 +854,7 @@ impl<RT: Runtime> IsolateClient<RT> {
                 identity: transaction.identity().clone(),
                 transaction,
                 context,
+                replay,
             },
             response: tx,
             queue_timer: queue_timer(),
@@ -1019,6 +1156,9 @@ impl<RT: Runtime> IsolateClient<RT> {
     }
 
     fn send_request(&self, request: Request<RT>) -> anyhow::Result<()> {
+        if self.saturated.load(Ordering::Relaxed) {
+            anyhow::bail!(pool_saturated_error());
+        }
         self.sender
             .try_send(request)
             .map_err(|_| metrics::execute_full_error())?;
@@ -1062,14 +1202,16 @@ impl<RT: Runtime> UdfCallback<RT> for IsolateClient<RT> {
 pub struct SharedIsolateScheduler<RT: Runtime, W: IsolateWorker<RT>> {
     rt: RT,
     worker: W,
-    /// Vec of channels for sending work to individual workers.
-    worker_senders: Vec<
+    /// Channels for sending work to individual workers, keyed by worker id.
+    worker_senders: BTreeMap<
+        usize,
         mpsc::Sender<(
             Request<RT>,
             oneshot::Sender<ActiveWorkerState>,
             ActiveWorkerState,
         )>,
     >,
+    next_worker_id: usize,
     /// Map from client_id to stack of workers (implemented with a deque). The
     /// most recently used worker for a given client is at the front of the
     /// deque. These workers were previously used by this client, but may
@@ -1083,8 +1225,16 @@ pub struct SharedIsolateScheduler<RT: Runtime, W: IsolateWorker<RT>> {
     /// Counts the number of active workers per client. Should only contain a
     /// key if the value is greater than 0.
     in_progress_count: HashMap<String, usize>,
+    /// When each active worker started its current request, keyed by worker
+    /// id.
+    in_progress_started: BTreeMap<usize, tokio::time::Instant>,
     /// The max number of workers this scheduler is permitted to create.
     max_workers: usize,
+    /// Decides how many of the `max_workers` workers to keep running.
+    autoscaler: PoolAutoscaler,
+    /// Time workers spent running requests since `last_scaled`.
+    busy_time: Duration,
+    last_scaled: tokio::time::Instant,
     handles: Arc<Mutex<Vec<IsolateWorkerHandle>>>,
     max_percent_per_client: usize,
 }
@@ -1106,19 +1256,29 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
         handles: Arc<Mutex<Vec<IsolateWorkerHandle>>>,
         max_percent_per_client: usize,
     ) -> Self {
+        let last_scaled = rt.monotonic_now();
         Self {
             rt,
             worker,
-            worker_senders: Vec::new(),
+            worker_senders: BTreeMap::new(),
+            next_worker_id: 0,
             in_progress_workers: FuturesUnordered::new(),
             in_progress_count: HashMap::new(),
+            in_progress_started: BTreeMap::new(),
             available_workers: HashMap::new(),
             max_workers,
+            autoscaler: PoolAutoscaler::new(max_workers),
+            busy_time: Duration::ZERO,
+            last_scaled,
             handles,
             max_percent_per_client,
         }
     }
 
+    pub(crate) fn saturated(&self) -> Arc<AtomicBool> {
+        self.autoscaler.saturated()
+    }
+
     fn handle_completed_worker(&mut self, completed_worker: ActiveWorkerState) {
         let new_count = match self
             .in_progress_count
@@ -1143,6 +1303,9 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
             new_count,
             &completed_worker.client_id,
         );
+        if let Some(started) = self.in_progress_started.remove(&completed_worker.worker_id) {
+            self.busy_time += self.rt.monotonic_now() - started.max(self.last_scaled);
+        }
 
         self.available_workers
             .entry(completed_worker.client_id)
@@ -1155,9 +1318,33 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
 
     pub async fn run(mut self, receiver: CoDelQueueReceiver<RT, Request<RT>>) {
         log_pool_max(self.worker.config().name, self.max_workers);
-        let mut receiver = receiver.fuse();
+        // Used to observe the queue depth while `receiver` is borrowed below.
+        let queue = receiver.clone();
+        let mut receiver = receiver.fuse().peekable();
         let mut report_stats = self.rt.wait(*HEAP_WORKER_REPORT_INTERVAL_SECONDS);
+        let mut scale = self.rt.wait(*ISOLATE_POOL_SCALE_INTERVAL);
+        // Whether a request has been peeked from the queue and is waiting for
+        // a worker.
+        let mut request_waiting = false;
         loop {
+            // Leave requests in the queue while every worker is busy and the
+            // pool is still allowed to grow, so the autoscaler sees the backlog.
+            // Once we're at `max_workers`, requests are rejected right away.
+            let accept_requests = !self.available_workers.is_empty()
+                || self.worker_senders.len() < self.autoscaler.target_workers()
+                || self.worker_senders.len() >= self.max_workers;
+            // Wake up as soon as a request has to wait for a worker.
+            let peek_request = !accept_requests && !request_waiting;
+            let next_request = async {
+                if accept_requests {
+                    Some(receiver.next().await)
+                } else if peek_request {
+                    Pin::new(&mut receiver).peek().await;
+                    None
+                } else {
+                    futures::future::pending().await
+                }
+            };
             select_biased! {
                 completed_worker = self.in_progress_workers.select_next_some() => {
                     let Ok(completed_worker): Result<ActiveWorkerState, _> = completed_worker else {
@@ -1166,7 +1353,15 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
                     };
                     self.handle_completed_worker(completed_worker);
                 }
-                request = receiver.next() => {
+                request = next_request.fuse() => {
+                    let Some(request) = request else {
+                        // Scale up now rather than leaving the request waiting
+                        // for the next scaling interval.
+                        request_waiting = true;
+                        self.scale(queue.len() + 1);
+                        continue;
+                    };
+                    request_waiting = false;
                     let Some((request, expired)) = request else {
                         tracing::warn!("Request sender went away; {} scheduler shutting down", self.worker.config().name);
                         return
@@ -1191,8 +1386,9 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
                         *entry,
                         &request.client_id,
                     );
+                    self.in_progress_started.insert(worker_id, self.rt.monotonic_now());
                     let client_id = request.client_id.clone();
-                    if self.worker_senders[worker_id]
+                    if self.worker_senders[&worker_id]
                         .try_send((
                             request,
                             done_sender,
@@ -1217,10 +1413,52 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
                     log_aggregated_heap_stats(&heap_stats);
                     report_stats = self.rt.wait(*HEAP_WORKER_REPORT_INTERVAL_SECONDS);
                 },
+                _ = scale => {
+                    self.scale(queue.len() + usize::from(request_waiting));
+                    scale = self.rt.wait(*ISOLATE_POOL_SCALE_INTERVAL);
+                },
             }
         }
     }
 
+    /// Resizes the pool based on the queue depth and how busy the workers
+    /// were since the last call, shutting down idle workers if the pool
+    /// shrank.
+    fn scale(&mut self, queue_depth: usize) {
+        let now = self.rt.monotonic_now();
+        for started in self.in_progress_started.values() {
+            self.busy_time += now - (*started).max(self.last_scaled);
+        }
+        let sample = PoolSample {
+            queue_depth,
+            active_workers: self.in_progress_started.len(),
+            busy_time: std::mem::take(&mut self.busy_time),
+            interval: now - self.last_scaled,
+        };
+        self.last_scaled = now;
+
+        let name = self.worker.config().name;
+        let target = self.autoscaler.update(sample);
+        log_pool_queue_depth(name, queue_depth);
+        log_pool_cpu_percent(name, self.autoscaler.cpu_percent(&sample));
+        log_pool_target_count(name, target);
+
+        while self.worker_senders.len() > target {
+            let Some(worker) = self.pop_least_recently_used_worker() else {
+                // The remaining workers are busy; retire them once they're idle.
+                break;
+            };
+            // The worker thread exits once its channel is closed.
+            self.worker_senders.remove(&worker.worker_id);
+            self.handles
+                .lock()
+                .retain(|handle| handle.worker_id != worker.worker_id);
+            log_pool_worker_retired(name);
+            tracing::info!("Retired {name} isolate worker {}", worker.worker_id);
+        }
+        log_pool_allocated_count(name, self.worker_senders.len());
+    }
+
     /// Find a worker for the given `client_id`.`
     /// Returns `None` if no worker could be allocated for this client (i.e.
     /// this client has reached it's capacity with the scheduler).
@@ -1254,9 +1492,9 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
             }
             return Some(worker.worker_id);
         }
-        // If we've recently started up and haven't yet created `max_workers` threads,
-        // create a new worker instead of "stealing" some other client's worker.
-        if self.worker_senders.len() < self.max_workers {
+        // If the pool hasn't grown to its target size yet, create a new worker
+        // instead of "stealing" some other client's worker.
+        if self.worker_senders.len() < self.autoscaler.target_workers() {
             let new_worker = self.worker.clone();
             let heap_stats = SharedIsolateHeapStats::new();
             let heap_stats_ = heap_stats.clone();
@@ -1264,21 +1502,34 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
             let handle = self
                 .rt
                 .spawn_thread(move || new_worker.service_requests(work_receiver, heap_stats_));
-            self.worker_senders.push(work_sender);
-            self.handles
-                .lock()
-                .push(IsolateWorkerHandle { handle, heap_stats });
+            let worker_id = self.next_worker_id;
+            self.next_worker_id += 1;
+            self.worker_senders.insert(worker_id, work_sender);
+            self.handles.lock().push(IsolateWorkerHandle {
+                handle,
+                heap_stats,
+                worker_id,
+            });
+            log_pool_allocated_count(self.worker.config().name, self.worker_senders.len());
             tracing::info!(
                 "Created {} isolate worker {}",
                 self.worker.config().name,
-                self.worker_senders.len() - 1
+                worker_id
             );
-            return Some(self.worker_senders.len() - 1);
+            return Some(worker_id);
         }
-        // No existing worker for this client and we've already started the max number
-        // of workers -- just grab the least recently used worker. This worker is least
+        // No existing worker for this client and the pool is at its target size
+        // -- just grab the least recently used worker. This worker is least
         // likely to be reused by its' previous client.
-        let Some((key, workers)) =
+        let worker = self.pop_least_recently_used_worker()?;
+        log_worker_stolen(worker.last_used_ts.elapsed());
+        Some(worker.worker_id)
+    }
+
+    /// Removes the idle worker that has gone unused the longest from
+    /// `self.available_workers`.
+    fn pop_least_recently_used_worker(&mut self) -> Option<IdleWorkerState> {
+        let (key, workers) =
             self.available_workers
                 .iter_mut()
                 .min_by(|(_, workers1), (_, workers2)| {
@@ -1292,19 +1543,8 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
                                 .expect("Available worker map should never contain an empty list")
                                 .last_used_ts,
                         )
-                })
-        else {
-            // No available workers.
-            return None;
-        };
-        log_worker_stolen(
-            workers
-                .back()
-                .expect("Available worker map should never contain an empty list")
-                .last_used_ts
-                .elapsed(),
-        );
-        let worker_id = workers
+                })?;
+        let worker = workers
             .pop_back()
             .expect("Available worker map should never contain an empty list");
         if workers.is_empty() {
@@ -1313,7 +1553,7 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
             let key = key.clone();
             self.available_workers.remove(&key);
         }
-        Some(worker_id.worker_id)
+        Some(worker)
     }
 
     fn aggregate_heap_stats(&self) -> IsolateHeapStats {
@@ -1328,6 +1568,7 @@ impl<RT: Runtime, W: IsolateWorker<RT>> SharedIsolateScheduler<RT, W> {
 pub struct IsolateWorkerHandle {
     pub handle: Box<dyn SpawnHandle>,
     heap_stats: SharedIsolateHeapStats,
+    worker_id: usize,
 }
 
 #[derive(Clone)]
@@ -0,0 +1,207 @@
+//! Process-wide cache of V8 code caches for compiled modules.
+//!
+//! Every request runs in a fresh context, so each module in a bundle is
+//! recompiled from source for every isolate that loads it. After a module is
+//! first compiled we serialize V8's code cache for it and reuse it when the
+//! same source is compiled again, letting V8 skip parsing and eager
+//! compilation. This is keyed by a hash of the module's URL and source, so a
+//! new deploy naturally misses the cache and repopulates it.
+//!
+//! This caches compilation only: module evaluation still runs for every
+//! request. We don't snapshot the evaluated bundle with `v8::SnapshotCreator`
+//! because an isolate can only be created from a snapshot, while isolates
+//! here are pooled and reused across bundles and get a fresh context per
+//! request. A per-bundle snapshot would also need every op registered as an
+//! external reference.
+use std::sync::{
+    Arc,
+    LazyLock,
+};
+
+use common::{
+    knobs::{
+        ISOLATE_CODE_CACHE_ENABLED,
+        ISOLATE_CODE_CACHE_MAX_SIZE,
+    },
+    sha256::{
+        Sha256,
+        Sha256Digest,
+    },
+};
+use deno_core::{
+    v8,
+    ModuleSpecifier,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+
+use crate::metrics::{
+    log_code_cache_get,
+    log_code_cache_rejected,
+    log_code_cache_size,
+};
+
+static CODE_CACHE: LazyLock<CodeCache> =
+    LazyLock::new(|| CodeCache::new(*ISOLATE_CODE_CACHE_MAX_SIZE));
+
+struct CodeCache {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    cache: LruCache<Sha256Digest, Arc<[u8]>>,
+    size: usize,
+    size_limit: usize,
+}
+
+impl CodeCache {
+    fn new(size_limit: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                cache: LruCache::unbounded(),
+                size: 0,
+                size_limit,
+            }),
+        }
+    }
+
+    fn get(&self, key: &Sha256Digest) -> Option<Arc<[u8]>> {
+        let result = self.inner.lock().cache.get(key).cloned();
+        log_code_cache_get(result.is_some());
+        result
+    }
+
+    fn insert(&self, key: Sha256Digest, data: Arc<[u8]>) {
+        let mut inner = self.inner.lock();
+        if data.len() > inner.size_limit {
+            return;
+        }
+        inner.size += data.len();
+        if let Some(previous) = inner.cache.put(key, data) {
+            inner.size -= previous.len();
+        }
+        while inner.size > inner.size_limit {
+            let Some((_, evicted)) = inner.cache.pop_lru() else {
+                break;
+            };
+            inner.size -= evicted.len();
+        }
+        log_code_cache_size(inner.size);
+    }
+
+    fn remove(&self, key: &Sha256Digest) {
+        let mut inner = self.inner.lock();
+        if let Some(removed) = inner.cache.pop(key) {
+            inner.size -= removed.len();
+        }
+        log_code_cache_size(inner.size);
+    }
+}
+
+fn cache_key(name: &ModuleSpecifier, source: &str) -> Sha256Digest {
+    let mut hasher = Sha256::new();
+    hasher.update(name.as_str().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(source.as_bytes());
+    hasher.finalize()
+}
+
+/// A lookup of a module's code cache entry, used to compile a module and then
+/// populate or invalidate the cache depending on the outcome.
+pub struct ModuleCodeCache {
+    key: Option<Sha256Digest>,
+    cached: Option<Arc<[u8]>>,
+}
+
+impl ModuleCodeCache {
+    pub fn lookup(name: &ModuleSpecifier, source: &str) -> Self {
+        if !*ISOLATE_CODE_CACHE_ENABLED {
+            return Self {
+                key: None,
+                cached: None,
+            };
+        }
+        let key = cache_key(name, source);
+        let cached = CODE_CACHE.get(&key);
+        Self {
+            key: Some(key),
+            cached,
+        }
+    }
+
+    /// Returns the source to compile along with the compile options to pass
+    /// to V8, consuming the cached code if there is any.
+    pub fn source(
+        &self,
+        source: v8::Local<v8::String>,
+        origin: &v8::ScriptOrigin,
+    ) -> (
+        v8::script_compiler::Source,
+        v8::script_compiler::CompileOptions,
+    ) {
+        match &self.cached {
+            Some(data) => (
+                v8::script_compiler::Source::new_with_cached_data(
+                    source,
+                    Some(origin),
+                    v8::script_compiler::CachedData::new(data),
+                ),
+                v8::script_compiler::CompileOptions::ConsumeCodeCache,
+            ),
+            None => (
+                v8::script_compiler::Source::new(source, Some(origin)),
+                v8::script_compiler::CompileOptions::NoCompileOptions,
+            ),
+        }
+    }
+
+    /// Updates the cache after `module` was successfully compiled from
+    /// `source`.
+    pub fn finish(
+        self,
+        scope: &mut v8::HandleScope,
+        source: &v8::script_compiler::Source,
+        module: v8::Local<v8::Module>,
+    ) {
+        let Some(key) = self.key else {
+            return;
+        };
+        if self.cached.is_some() {
+            // V8 rejects code caches produced with different flags or by a
+            // different V8 version. Drop it so the next compile regenerates it.
+            if source.get_cached_data().is_some_and(|data| data.rejected()) {
+                log_code_cache_rejected();
+                CODE_CACHE.remove(&key);
+            }
+        } else if let Some(data) = module.get_unbound_module_script(scope).create_code_cache() {
+            CODE_CACHE.insert(key, Arc::from(&data[..]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::sha256::Sha256;
+
+    use super::CodeCache;
+
+    #[test]
+    fn test_code_cache_evicts_to_size_limit() {
+        let cache = CodeCache::new(10);
+        let (a, b, c) = (Sha256::hash(b"a"), Sha256::hash(b"b"), Sha256::hash(b"c"));
+        cache.insert(a.clone(), Arc::from(&[0u8; 4][..]));
+        cache.insert(b.clone(), Arc::from(&[0u8; 4][..]));
+        assert!(cache.get(&a).is_some());
+        // Inserting `c` must evict the least recently used entry, `b`.
+        cache.insert(c.clone(), Arc::from(&[0u8; 4][..]));
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+        // Entries larger than the whole cache are never stored.
+        cache.insert(b.clone(), Arc::from(&[0u8; 11][..]));
+        assert!(cache.get(&b).is_none());
+        assert_eq!(cache.inner.lock().size, 8);
+    }
+}
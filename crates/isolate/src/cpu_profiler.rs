@@ -0,0 +1,186 @@
+//! On-demand V8 CPU profiling of UDF executions.
+//!
+//! An admin requests profiles for the next N executions of a function through
+//! [`CPU_PROFILER`], which each isolate checks before running a function. If
+//! that function is being profiled, the isolate attaches a V8 inspector
+//! session to the request's context and runs the `Profiler` domain for the
+//! duration of the execution. The resulting profiles are in the `.cpuprofile`
+//! format understood by Chrome DevTools and speedscope.
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        LazyLock,
+    },
+};
+
+use common::components::CanonicalizedComponentFunctionPath;
+use deno_core::v8::{
+    self,
+    inspector::{
+        ChannelBase,
+        ChannelImpl,
+        StringBuffer,
+        StringView,
+        V8Inspector,
+        V8InspectorClientBase,
+        V8InspectorClientImpl,
+        V8InspectorClientTrustLevel,
+        V8InspectorSession,
+    },
+};
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+
+use crate::execution_capture::{
+    CaptureClaim,
+    ExecutionCaptures,
+};
+
+pub static CPU_PROFILER: LazyLock<ExecutionCaptures<JsonValue>> =
+    LazyLock::new(ExecutionCaptures::new);
+
+/// Interval between CPU samples. V8 defaults to 1ms, which is too coarse for
+/// functions that only run for a few milliseconds.
+const SAMPLING_INTERVAL_MICROS: u32 = 100;
+
+const CONTEXT_GROUP_ID: i32 = 1;
+
+struct ProfilerClient {
+    base: V8InspectorClientBase,
+}
+
+impl V8InspectorClientImpl for ProfilerClient {
+    fn base(&self) -> &V8InspectorClientBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase {
+        std::ptr::addr_of!((*this).base)
+    }
+}
+
+struct ProfilerChannel {
+    base: ChannelBase,
+    responses: BTreeMap<i32, String>,
+}
+
+impl ChannelImpl for ProfilerChannel {
+    fn base(&self) -> &ChannelBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut ChannelBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const ChannelBase {
+        std::ptr::addr_of!((*this).base)
+    }
+
+    fn send_response(&mut self, call_id: i32, message: v8::UniquePtr<StringBuffer>) {
+        let message = message.unwrap().string().to_string();
+        self.responses.insert(call_id, message);
+    }
+
+    fn send_notification(&mut self, _message: v8::UniquePtr<StringBuffer>) {}
+
+    fn flush_protocol_notifications(&mut self) {}
+}
+
+/// A V8 inspector session running the CPU profiler for a single execution.
+/// If it's dropped without finishing, e.g. because the execution failed to
+/// start, another execution is profiled instead.
+pub(crate) struct CpuProfilingSession {
+    claim: CaptureClaim<'static, JsonValue>,
+    path: CanonicalizedComponentFunctionPath,
+    next_call_id: i32,
+    // NB: Fields are dropped in declaration order. The session and inspector
+    // hold pointers to the channel and client, so they must be dropped first.
+    session: v8::UniqueRef<V8InspectorSession>,
+    _inspector: v8::UniqueRef<V8Inspector>,
+    channel: Box<ProfilerChannel>,
+    _client: Box<ProfilerClient>,
+}
+
+impl CpuProfilingSession {
+    /// Starts profiling `context` if an admin has requested profiles for
+    /// `path` in the deployment `instance_name`.
+    pub(crate) fn start_if_requested(
+        scope: &mut v8::HandleScope,
+        context: v8::Local<v8::Context>,
+        instance_name: Arc<String>,
+        path: CanonicalizedComponentFunctionPath,
+    ) -> Option<Self> {
+        let claim = CPU_PROFILER.claim(&instance_name, &path)?;
+        let mut client = Box::new(ProfilerClient {
+            base: V8InspectorClientBase::new::<ProfilerClient>(),
+        });
+        let mut channel = Box::new(ProfilerChannel {
+            base: ChannelBase::new::<ProfilerChannel>(),
+            responses: BTreeMap::new(),
+        });
+        let mut inspector = V8Inspector::create(scope, &mut *client);
+        inspector.context_created(
+            context,
+            CONTEXT_GROUP_ID,
+            StringView::empty(),
+            StringView::empty(),
+        );
+        let session = inspector.connect(
+            CONTEXT_GROUP_ID,
+            &mut *channel,
+            StringView::empty(),
+            V8InspectorClientTrustLevel::FullyTrusted,
+        );
+        let mut profiling_session = Self {
+            claim,
+            path,
+            next_call_id: 0,
+            session,
+            _inspector: inspector,
+            channel,
+            _client: client,
+        };
+        profiling_session.call("Profiler.enable", json!({}));
+        profiling_session.call(
+            "Profiler.setSamplingInterval",
+            json!({ "interval": SAMPLING_INTERVAL_MICROS }),
+        );
+        profiling_session.call("Profiler.start", json!({}));
+        Some(profiling_session)
+    }
+
+    /// Stops the profiler and hands the profile to [`CPU_PROFILER`].
+    pub(crate) fn finish(mut self) {
+        let profile = self.call("Profiler.stop", json!({})).and_then(|response| {
+            let mut response: JsonValue = serde_json::from_str(&response).ok()?;
+            Some(response.get_mut("result")?.get_mut("profile")?.take())
+        });
+        match profile {
+            Some(profile) => self.claim.record(profile),
+            None => tracing::warn!(
+                "Failed to collect CPU profile for {}",
+                self.path.debug_str()
+            ),
+        }
+    }
+
+    /// Dispatches an inspector protocol call, returning its response. V8
+    /// handles `Profiler` methods synchronously, so the response is available
+    /// as soon as dispatch returns.
+    fn call(&mut self, method: &str, params: JsonValue) -> Option<String> {
+        self.next_call_id += 1;
+        let call_id = self.next_call_id;
+        let message = json!({ "id": call_id, "method": method, "params": params }).to_string();
+        self.session
+            .dispatch_protocol_message(StringView::from(message.as_bytes()));
+        self.channel.responses.remove(&call_id)
+    }
+}
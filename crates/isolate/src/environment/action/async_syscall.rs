@@ -22,6 +22,10 @@ use model::{
         handles::function_handle_not_found,
     },
     file_storage::FileStorageId,
+    scheduled_jobs::types::{
+        ScheduleOptions,
+        ScheduleOptionsJson,
+    },
 };
 use serde::{
     Deserialize,
@@ -241,6 +245,8 @@ impl<RT: Runtime> TaskExecutor<RT> {
             function_handle: Option<String>,
             ts: f64,
             args: UdfArgsJson,
+            #[serde(default)]
+            options: ScheduleOptionsJson,
         }
 
         let ScheduleArgs {
@@ -249,7 +255,9 @@ impl<RT: Runtime> TaskExecutor<RT> {
             function_handle,
             ts,
             args,
+            options,
         }: ScheduleArgs = with_argument_error("scheduler", || Ok(serde_json::from_value(args)?))?;
+        let options = ScheduleOptions::try_from(options)?;
         let path = match function_handle {
             Some(h) => {
                 let handle: FunctionHandle = with_argument_error("scheduler", || h.parse())?;
@@ -272,6 +280,7 @@ impl<RT: Runtime> TaskExecutor<RT> {
                 args.into_arg_vec(),
                 scheduled_ts,
                 self.context.clone(),
+                options,
             )
             .await?;
 
@@ -8,7 +8,11 @@ use common::{
     },
     runtime::Runtime,
 };
-use errors::ErrorMetadata;
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use udf::FETCH_SYSCALL_NAME;
 
 use super::task_executor::TaskExecutor;
 use crate::{
@@ -40,14 +44,18 @@ impl<RT: Runtime> TaskExecutor<RT> {
             Ok(parts) => parts,
             Err(e) => {
                 // All fetch errors are treated as developer errors since we have little
-                // control of what they request.
-                _ =
-                    self.task_retval_sender.send(TaskResponse::TaskDone {
-                        task_id,
-                        variant: Err(
-                            ErrorMetadata::bad_request("FetchFailed", format!("{e:#}")).into()
-                        ),
-                    });
+                // control of what they request. Errors that are already categorized, like
+                // requests blocked by the deployment's fetch host policy, are passed through.
+                let error = if e.is_deterministic_user_error() {
+                    e
+                } else {
+                    ErrorMetadata::bad_request("FetchFailed", format!("{e:#}")).into()
+                };
+                _ = self.task_retval_sender.send(TaskResponse::TaskDone {
+                    task_id,
+                    variant: Err(error),
+                });
+                self.log_fetch_wait(initial_response_time, false);
                 Self::log_fetch_request(t, origin, Err(()), initial_response_time);
                 return;
             },
@@ -58,6 +66,7 @@ impl<RT: Runtime> TaskExecutor<RT> {
         });
         // After sending status and headers, send the body one chunk at a time.
         let stream_result = self.send_stream(stream_id, body).await;
+        self.log_fetch_wait(initial_response_time, stream_result.is_ok());
         Self::log_fetch_request(t, origin, stream_result, initial_response_time);
     }
 
@@ -69,6 +78,16 @@ impl<RT: Runtime> TaskExecutor<RT> {
         self.fetch_client.fetch(request).await
     }
 
+    /// Records the time until the response's headers arrived in the action's
+    /// syscall trace, which profiles how long actions wait on fetches.
+    fn log_fetch_wait(&self, initial_response_time: Duration, is_success: bool) {
+        self.syscall_trace.lock().log_async_syscall(
+            FETCH_SYSCALL_NAME.to_string(),
+            initial_response_time,
+            is_success,
+        );
+    }
+
     fn log_fetch_request(
         t: StatusTimer,
         origin: String,
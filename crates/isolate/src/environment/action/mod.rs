@@ -1,17 +1,20 @@
 mod async_syscall;
 mod fetch;
 mod phase;
+mod recording;
 mod storage;
 mod stream;
 mod syscall;
 mod task;
 mod task_executor;
 mod task_order;
+mod websocket;
 
 use std::{
     cmp::Ordering,
     collections::BTreeMap,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::anyhow;
@@ -28,6 +31,7 @@ use common::{
         ACTION_USER_TIMEOUT,
         FUNCTION_MAX_ARGS_SIZE,
         FUNCTION_MAX_RESULT_SIZE,
+        FUNCTION_USER_TIMEOUTS,
         V8_ACTION_SYSTEM_TIMEOUT,
     },
     log_lines::{
@@ -76,6 +80,7 @@ use model::{
     },
 };
 use parking_lot::Mutex;
+use rand::Rng;
 use rand_chacha::ChaCha12Rng;
 use serde_json::Value as JsonValue;
 use sync_types::{
@@ -87,6 +92,7 @@ use udf::{
     helpers::serialize_udf_args,
     validation::ValidatedHttpPath,
     ActionOutcome,
+    ExecutionRecording,
     HttpActionOutcome,
     HttpActionRequest,
     HttpActionRequestHead,
@@ -94,6 +100,7 @@ use udf::{
     HttpActionResponsePart,
     HttpActionResponseStreamer,
     HttpActionResult,
+    HttpActionWebSocket,
     SyscallTrace,
     HTTP_ACTION_BODY_LIMIT,
 };
@@ -115,6 +122,7 @@ pub use self::{
 };
 use self::{
     phase::ActionPhase,
+    recording::ActionRecorder,
     task::{
         TaskId,
         TaskRequest,
@@ -122,6 +130,7 @@ use self::{
         TaskType,
     },
     task_executor::TaskExecutor,
+    websocket::ActionWebSocket,
 };
 use super::warnings::{
     approaching_duration_limit_warning,
@@ -135,6 +144,7 @@ use crate::{
         SharedIsolateHeapStats,
     },
     concurrency_limiter::ConcurrencyPermit,
+    cpu_profiler::CpuProfilingSession,
     environment::{
         helpers::{
             module_loader::module_specifier_from_path,
@@ -142,10 +152,12 @@ use crate::{
             resolve_promise_allow_all_errors,
             MAX_LOG_LINES,
         },
+        udf::recording::EXECUTION_RECORDER,
         AsyncOpRequest,
         IsolateEnvironment,
     },
     execution_scope::ExecutionScope,
+    heap_snapshot::take_heap_snapshot_if_requested,
     helpers::{
         self,
         deserialize_udf_result,
@@ -220,9 +232,17 @@ pub struct ActionEnvironment<RT: Runtime> {
     // TaskRequests will be executed in parallel, but PromiseResolvers are not Send.
     task_promise_resolvers: BTreeMap<TaskId, (v8::Global<v8::PromiseResolver>, TaskType)>,
     task_responses: mpsc::UnboundedReceiver<TaskResponse>,
+    // Shared with the `TaskExecutor` so HTTP actions can register the
+    // WebSocket their request was upgraded to.
+    websockets: Arc<Mutex<BTreeMap<uuid::Uuid, ActionWebSocket>>>,
     phase: ActionPhase<RT>,
     syscall_trace: Arc<Mutex<SyscallTrace>>,
     heap_stats: SharedIsolateHeapStats,
+    user_timeout: Duration,
+
+    rng_seed: [u8; 32],
+    // Behind a lock since `unix_timestamp` only has a shared reference.
+    recorder: Mutex<ActionRecorder>,
 }
 
 impl<RT: Runtime> ActionEnvironment<RT> {
@@ -232,6 +252,7 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         EnvironmentData {
             key_broker,
             system_env_vars,
+            env_var_overrides,
             file_storage,
             module_loader,
         }: EnvironmentData<RT>,
@@ -248,6 +269,7 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let (task_retval_sender, task_responses) = mpsc::unbounded_channel();
         let resources = Arc::new(Mutex::new(BTreeMap::new()));
         let function_handles = Arc::new(Mutex::new(BTreeMap::new()));
+        let websockets = Arc::new(Mutex::new(BTreeMap::new()));
         let task_executor = TaskExecutor {
             rt: rt.clone(),
             identity: identity.clone(),
@@ -264,6 +286,7 @@ impl<RT: Runtime> ActionEnvironment<RT> {
             resources: resources.clone(),
             component_id: component,
             function_handles: function_handles.clone(),
+            websockets: websockets.clone(),
         };
         let (pending_task_sender, pending_task_receiver) = spsc::unbounded_channel();
         let running_tasks = rt.spawn("task_executor", task_executor.go(pending_task_receiver));
@@ -279,17 +302,23 @@ impl<RT: Runtime> ActionEnvironment<RT> {
             task_responses,
             running_tasks: Some(running_tasks),
             task_promise_resolvers: BTreeMap::new(),
+            websockets,
             phase: ActionPhase::new(
                 rt.clone(),
                 component,
                 transaction,
                 module_loader,
                 system_env_vars,
+                env_var_overrides,
                 resources,
                 function_handles,
             ),
             syscall_trace,
             heap_stats,
+            user_timeout: *ACTION_USER_TIMEOUT,
+
+            rng_seed: rt.rng().gen(),
+            recorder: Mutex::new(ActionRecorder::Disabled),
         }
     }
 
@@ -322,6 +351,13 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let mut handle_scope = isolate.handle_scope();
         let v8_context = v8::Context::new(&mut handle_scope);
         let mut context_scope = v8::ContextScope::new(&mut handle_scope, v8_context);
+        let logging_path = component_function_path.clone().for_logging();
+        let cpu_profiling_session = CpuProfilingSession::start_if_requested(
+            &mut context_scope,
+            v8_context,
+            client_id.clone(),
+            logging_path.clone(),
+        );
 
         let mut isolate_context =
             RequestScope::new(&mut context_scope, handle.clone(), state, true).await?;
@@ -329,7 +365,7 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let request_head = request.head.clone();
 
         let mut result = Self::run_http_action_inner(
-            client_id,
+            client_id.clone(),
             &mut isolate_context,
             udf_path,
             routed_path,
@@ -346,6 +382,10 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         // leak to a subsequent one on isolate reuse.
         isolate_context.scope.perform_microtask_checkpoint();
         *isolate_clean = true;
+        if let Some(cpu_profiling_session) = cpu_profiling_session {
+            cpu_profiling_session.finish();
+        }
+        take_heap_snapshot_if_requested(isolate_context.scope, &client_id, &logging_path);
 
         let execution_time;
         (self, execution_time) = isolate_context.take_environment();
@@ -481,9 +521,16 @@ impl<RT: Runtime> ActionEnvironment<RT> {
             },
             None => None,
         };
-        let request_str =
-            serde_json::to_value(HttpRequestV8::from_request(http_request.head, stream_id)?)?
-                .to_string();
+        let websocket_id = match http_request.websocket {
+            Some(websocket) => Some(scope.state_mut()?.environment.register_websocket(websocket)),
+            None => None,
+        };
+        let request_str = serde_json::to_value(HttpRequestV8::from_request(
+            http_request.head,
+            stream_id,
+            websocket_id,
+        )?)?
+        .to_string();
         metrics::log_argument_length(&request_str);
         let args_v8_str = v8::String::new(&mut scope, &request_str)
             .ok_or_else(|| anyhow!("Failed to create argument string"))?;
@@ -587,6 +634,17 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         Ok(())
     }
 
+    /// Makes the WebSocket the client asked to upgrade the request to
+    /// available to JS, which uses the same ops as for WebSockets it opens.
+    fn register_websocket(&mut self, websocket: HttpActionWebSocket) -> uuid::Uuid {
+        let socket_id = self.rt.new_uuid_v4();
+        self.websockets.lock().insert(
+            socket_id,
+            ActionWebSocket::new(websocket.sink, websocket.stream),
+        );
+        socket_id
+    }
+
     fn send_stream(
         &mut self,
         stream_id: uuid::Uuid,
@@ -609,11 +667,35 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         isolate: &mut Isolate<RT>,
         isolate_clean: &mut bool,
         request_params: ActionRequestParams,
+        replay: Option<ExecutionRecording>,
         cancellation: BoxFuture<'_, ()>,
     ) -> anyhow::Result<ActionOutcome> {
         let client_id = Arc::new(client_id);
-        let start_unix_timestamp = self.rt.unix_timestamp();
+        let logging_path = request_params.path_and_args.path().clone().for_logging();
+        // If the action fails or abandons its recording, dropping the claim
+        // lets a later execution be recorded instead.
+        let mut recording_claim = None;
+        let start_unix_timestamp = match replay {
+            Some(recording) => {
+                self.rng_seed = recording.rng_seed;
+                *self.recorder.get_mut() =
+                    ActionRecorder::Replaying(recording.action_events.into());
+                recording.unix_timestamp
+            },
+            None => {
+                recording_claim = EXECUTION_RECORDER.claim(&client_id, &logging_path);
+                if recording_claim.is_some() {
+                    *self.recorder.get_mut() = ActionRecorder::Recording(vec![]);
+                }
+                self.rt.unix_timestamp()
+            },
+        };
         let heap_stats = self.heap_stats.clone();
+        if let Some(user_timeout) = FUNCTION_USER_TIMEOUTS
+            .user_timeout_for(&request_params.path_and_args.path().clone().for_logging())
+        {
+            self.user_timeout = user_timeout;
+        }
 
         // See Isolate::with_context for an explanation of this setup code. We can't use
         // that method directly since we want an `await` below, and passing in a
@@ -623,12 +705,18 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let mut handle_scope = isolate.handle_scope();
         let v8_context = v8::Context::new(&mut handle_scope);
         let mut context_scope = v8::ContextScope::new(&mut handle_scope, v8_context);
+        let cpu_profiling_session = CpuProfilingSession::start_if_requested(
+            &mut context_scope,
+            v8_context,
+            client_id.clone(),
+            logging_path.clone(),
+        );
 
         let mut isolate_context =
             RequestScope::new(&mut context_scope, handle.clone(), state, true).await?;
 
         let mut result = Self::run_action_inner(
-            client_id,
+            client_id.clone(),
             &mut isolate_context,
             request_params.clone(),
             cancellation,
@@ -640,6 +728,10 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         // leak to a subsequent one on isolate reuse.
         isolate_context.scope.perform_microtask_checkpoint();
         *isolate_clean = true;
+        if let Some(cpu_profiling_session) = cpu_profiling_session {
+            cpu_profiling_session.finish();
+        }
+        take_heap_snapshot_if_requested(isolate_context.scope, &client_id, &logging_path);
 
         match handle.take_termination_error(
             Some(heap_stats.get()),
@@ -659,6 +751,21 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let execution_time;
         (self, execution_time) = isolate_context.take_environment();
         let (path, arguments, udf_server_version) = request_params.path_and_args.consume();
+        let recorder = std::mem::replace(self.recorder.get_mut(), ActionRecorder::Disabled);
+        if let Some(recording_claim) = recording_claim
+            && let Some(action_events) = recorder.into_recorded()
+            && result.is_ok()
+        {
+            recording_claim.record(ExecutionRecording {
+                udf_type: UdfType::Action,
+                path: logging_path.clone(),
+                arguments: arguments.clone(),
+                rng_seed: self.rng_seed,
+                unix_timestamp: start_unix_timestamp,
+                syscalls: vec![],
+                action_events,
+            });
+        }
         self.add_warnings_to_log_lines_action(
             execution_time,
             &arguments,
@@ -950,7 +1057,8 @@ impl<RT: Runtime> ActionEnvironment<RT> {
             // This enforces on database access in the router.
             // We might relax this to e.g. implement a JavaScript router with
             // auth middleware which affected the matched route.
-            state.environment.phase.begin_execution()?;
+            let environment = &mut state.environment;
+            environment.phase.begin_execution(environment.rng_seed)?;
         }
         let global = scope.get_current_context().global(scope);
         let promise_r = scope.with_try_catch(|s| v8_function.call(s, global.into(), v8_args));
@@ -1086,11 +1194,8 @@ impl<RT: Runtime> ActionEnvironment<RT> {
                 // However, actions can call queries, mutations, and other actions
                 // as syscalls, so these should still count towards the user-code
                 // timeout.
-                task_response = environment.task_responses.recv().fuse() => {
-                    let Some(task_response) = task_response else {
-                        anyhow::bail!("Task executor went away?");
-                    };
-                    match task_response {
+                task_response = environment.next_task_response().fuse() => {
+                    match task_response? {
                         TaskResponse::StreamExtend { stream_id, chunk } => {
                             match chunk {
                                 Ok(chunk) => {
@@ -1261,6 +1366,10 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         let task_id = self.next_task_id.increment();
         self.task_promise_resolvers
             .insert(task_id, (resolver, request.to_type()));
+        // Replayed tasks complete from the recording instead.
+        if self.recorder.get_mut().is_replaying() {
+            return Ok(());
+        }
         self.pending_task_sender
             .send(TaskRequest {
                 task_id,
@@ -1271,6 +1380,25 @@ impl<RT: Runtime> ActionEnvironment<RT> {
         Ok(())
     }
 
+    /// Waits for the next response from the task executor, or takes it from
+    /// the recording when replaying.
+    async fn next_task_response(&mut self) -> anyhow::Result<TaskResponse> {
+        let task_name = |task_id| {
+            self.task_promise_resolvers
+                .get(&task_id)
+                .map(|(_, task_type)| task_type.name_when_dangling())
+        };
+        let recorder = self.recorder.get_mut();
+        if recorder.is_replaying() {
+            return recorder.replay_task_response(task_name);
+        }
+        let Some(task_response) = self.task_responses.recv().await else {
+            anyhow::bail!("Task executor went away?");
+        };
+        recorder.record_task_response(&task_response, task_name);
+        Ok(task_response)
+    }
+
     fn trace_system(&mut self, warning: SystemWarning) -> anyhow::Result<()> {
         self.log_line_sender.send(LogLine::new_system_log_line(
             warning.level,
@@ -1316,7 +1444,13 @@ impl<RT: Runtime> IsolateEnvironment<RT> for ActionEnvironment<RT> {
     }
 
     fn unix_timestamp(&self) -> anyhow::Result<UnixTimestamp> {
-        self.phase.unix_timestamp()
+        // The import-time timestamp is fixed, so only record the current time.
+        if !self.phase.is_executing() {
+            return self.phase.unix_timestamp();
+        }
+        self.recorder
+            .lock()
+            .unix_timestamp(|| self.phase.unix_timestamp())
     }
 
     fn get_environment_variable(
@@ -1375,7 +1509,7 @@ impl<RT: Runtime> IsolateEnvironment<RT> for ActionEnvironment<RT> {
     }
 
     fn user_timeout(&self) -> std::time::Duration {
-        *ACTION_USER_TIMEOUT
+        self.user_timeout
     }
 
     fn system_timeout(&self) -> std::time::Duration {
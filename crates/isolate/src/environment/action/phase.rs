@@ -46,10 +46,7 @@ use model::{
     udf_config::UdfConfigModel,
 };
 use parking_lot::Mutex;
-use rand::{
-    Rng,
-    SeedableRng,
-};
+use rand::SeedableRng;
 use rand_chacha::ChaCha12Rng;
 use sync_types::{
     CanonicalizedModulePath,
@@ -91,6 +88,7 @@ enum ActionPreloaded<RT: Runtime> {
         tx: Transaction<RT>,
         module_loader: Arc<dyn ModuleLoader<RT>>,
         system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
+        env_var_overrides: BTreeMap<EnvVarName, EnvVarValue>,
         resources: Arc<Mutex<BTreeMap<Reference, Resource>>>,
         function_handles: Arc<Mutex<BTreeMap<CanonicalizedComponentFunctionPath, FunctionHandle>>>,
     },
@@ -111,6 +109,7 @@ impl<RT: Runtime> ActionPhase<RT> {
         tx: Transaction<RT>,
         module_loader: Arc<dyn ModuleLoader<RT>>,
         system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
+        env_var_overrides: BTreeMap<EnvVarName, EnvVarValue>,
         resources: Arc<Mutex<BTreeMap<Reference, Resource>>>,
         function_handles: Arc<Mutex<BTreeMap<CanonicalizedComponentFunctionPath, FunctionHandle>>>,
     ) -> Self {
@@ -122,6 +121,7 @@ impl<RT: Runtime> ActionPhase<RT> {
                 tx,
                 module_loader,
                 system_env_vars,
+                env_var_overrides,
                 resources,
                 function_handles,
             },
@@ -141,6 +141,7 @@ impl<RT: Runtime> ActionPhase<RT> {
             mut tx,
             module_loader,
             system_env_vars,
+            env_var_overrides,
             resources,
             function_handles,
         } = preloaded
@@ -199,8 +200,9 @@ impl<RT: Runtime> ActionPhase<RT> {
         })
         .await?;
 
-        // Environment variables are not accessible in component functions.
-        let env_vars = if self.component.is_root() {
+        // Deployment-wide environment variables are not accessible in component
+        // functions, but overrides scoped to the component are.
+        let mut env_vars = if self.component.is_root() {
             let mut env_vars = system_env_vars;
             let user_env_vars = with_release_permit(
                 timeout,
@@ -213,6 +215,7 @@ impl<RT: Runtime> ActionPhase<RT> {
         } else {
             BTreeMap::new()
         };
+        env_vars.extend(env_var_overrides);
 
         let component_arguments = if self.component.is_root() {
             None
@@ -277,7 +280,7 @@ impl<RT: Runtime> ActionPhase<RT> {
         Ok(module.map(|(_, source)| source))
     }
 
-    pub fn begin_execution(&mut self) -> anyhow::Result<()> {
+    pub fn begin_execution(&mut self, rng_seed: [u8; 32]) -> anyhow::Result<()> {
         if self.phase != Phase::Importing {
             anyhow::bail!("Phase was already {:?}", self.phase)
         }
@@ -285,11 +288,14 @@ impl<RT: Runtime> ActionPhase<RT> {
             anyhow::bail!("Phase not initialized");
         };
         self.phase = Phase::Executing;
-        let rng_seed = self.rt.rng().gen();
         *rng = Some(ChaCha12Rng::from_seed(rng_seed));
         Ok(())
     }
 
+    pub fn is_executing(&self) -> bool {
+        self.phase == Phase::Executing
+    }
+
     pub fn get_environment_variable(
         &mut self,
         name: EnvVarName,
@@ -0,0 +1,279 @@
+use std::collections::VecDeque;
+
+use common::{
+    errors::JsError,
+    runtime::UnixTimestamp,
+};
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use serde_json::Value as JsonValue;
+use udf::{
+    RecordedActionEvent,
+    RecordedTaskError,
+};
+use value::ConvexValue;
+
+use super::task::{
+    TaskId,
+    TaskResponse,
+    TaskResponseEnum,
+    WebSocketMessageV8,
+};
+
+/// Records what an action observes from outside its JavaScript execution, or
+/// answers it from a previous recording when replaying it.
+pub enum ActionRecorder {
+    Disabled,
+    Recording(Vec<RecordedActionEvent>),
+    /// The action observed something we can't faithfully replay, like binary
+    /// data that's passed to JavaScript as a buffer, so this execution won't
+    /// produce a recording.
+    Abandoned,
+    Replaying(VecDeque<RecordedActionEvent>),
+}
+
+impl ActionRecorder {
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replaying(_))
+    }
+
+    /// Returns the current time, either from `now` or from the recording.
+    pub fn unix_timestamp(
+        &mut self,
+        now: impl FnOnce() -> anyhow::Result<UnixTimestamp>,
+    ) -> anyhow::Result<UnixTimestamp> {
+        match self {
+            Self::Replaying(events) => match events.pop_front() {
+                Some(RecordedActionEvent::UnixTimestamp(unix_timestamp)) => Ok(unix_timestamp),
+                event => anyhow::bail!(replay_diverged(
+                    "read the current time",
+                    &expected(event.as_ref())
+                )),
+            },
+            Self::Recording(events) => {
+                let unix_timestamp = now()?;
+                events.push(RecordedActionEvent::UnixTimestamp(unix_timestamp));
+                Ok(unix_timestamp)
+            },
+            Self::Disabled | Self::Abandoned => now(),
+        }
+    }
+
+    /// Records a response from the task executor. `task_name` looks up the
+    /// name of a pending task.
+    pub fn record_task_response(
+        &mut self,
+        response: &TaskResponse,
+        task_name: impl FnOnce(TaskId) -> Option<String>,
+    ) {
+        let Self::Recording(events) = self else {
+            return;
+        };
+        let event = match response {
+            TaskResponse::TaskDone { task_id, variant } => {
+                let Some(name) = task_name(*task_id) else {
+                    *self = Self::Abandoned;
+                    return;
+                };
+                let result = match variant {
+                    Ok(response) => match recorded_task_result(response) {
+                        Some(result) => Ok(result),
+                        None => {
+                            *self = Self::Abandoned;
+                            return;
+                        },
+                    },
+                    // All task errors are thrown into JavaScript, so we only
+                    // need the message and `ConvexError` data it sees.
+                    Err(e) => Err(RecordedTaskError {
+                        message: e.user_facing_message(),
+                        data: e
+                            .downcast_ref::<JsError>()
+                            .and_then(|js_error| js_error.custom_data.clone())
+                            .map(JsonValue::from),
+                    }),
+                };
+                RecordedActionEvent::TaskDone {
+                    task_id: task_id.0,
+                    name,
+                    result,
+                }
+            },
+            TaskResponse::StreamExtend { stream_id, chunk } => {
+                let chunk = match chunk {
+                    Ok(chunk) => Ok(chunk.clone()),
+                    // Other errors fail the execution when JavaScript reads
+                    // the stream.
+                    Err(e) if e.is_deterministic_user_error() => Err(ErrorMetadata::bad_request(
+                        e.short_msg().to_string(),
+                        e.msg().to_string(),
+                    )),
+                    Err(_) => {
+                        *self = Self::Abandoned;
+                        return;
+                    },
+                };
+                RecordedActionEvent::StreamExtend {
+                    stream_id: stream_id.to_string(),
+                    chunk,
+                }
+            },
+        };
+        events.push(event);
+    }
+
+    /// Returns the next recorded response from the task executor, checking
+    /// that the action is waiting on the task it completes.
+    pub fn replay_task_response(
+        &mut self,
+        task_name: impl FnOnce(TaskId) -> Option<String>,
+    ) -> anyhow::Result<TaskResponse> {
+        let Self::Replaying(events) = self else {
+            anyhow::bail!("Not replaying a recording");
+        };
+        let response = match events.pop_front() {
+            Some(RecordedActionEvent::TaskDone {
+                task_id,
+                name,
+                result,
+            }) => {
+                let task_id = TaskId(task_id);
+                if task_name(task_id).as_ref() != Some(&name) {
+                    anyhow::bail!(replay_diverged(
+                        "wasn't waiting on the task",
+                        &format!("{name} to complete")
+                    ));
+                }
+                let variant = match result {
+                    Ok(value) => Ok(TaskResponseEnum::Replayed(value)),
+                    Err(RecordedTaskError { message, data }) => {
+                        let error =
+                            anyhow::anyhow!(ErrorMetadata::bad_request("Error", message.clone()));
+                        match data {
+                            Some(data) => Err(error.context(JsError {
+                                message,
+                                custom_data: Some(ConvexValue::try_from(data)?),
+                                frames: None,
+                            })),
+                            None => Err(error),
+                        }
+                    },
+                };
+                TaskResponse::TaskDone { task_id, variant }
+            },
+            Some(RecordedActionEvent::StreamExtend { stream_id, chunk }) => {
+                TaskResponse::StreamExtend {
+                    stream_id: stream_id.parse()?,
+                    chunk: chunk.map_err(anyhow::Error::from),
+                }
+            },
+            event => anyhow::bail!(replay_diverged(
+                "waited on a task",
+                &expected(event.as_ref())
+            )),
+        };
+        Ok(response)
+    }
+
+    /// Returns the recorded events if this execution was being recorded.
+    pub fn into_recorded(self) -> Option<Vec<RecordedActionEvent>> {
+        match self {
+            Self::Recording(events) => Some(events),
+            Self::Disabled | Self::Abandoned | Self::Replaying(_) => None,
+        }
+    }
+}
+
+/// The value passed to JavaScript for a completed task, if it's plain JSON.
+fn recorded_task_result(response: &TaskResponseEnum) -> Option<JsonValue> {
+    let value = match response {
+        TaskResponseEnum::Syscall(result) => JsonValue::from(result.clone()),
+        TaskResponseEnum::Fetch(response) => serde_json::to_value(response).ok()?,
+        TaskResponseEnum::Sleep(_) | TaskResponseEnum::WebSocketSend => JsonValue::Null,
+        TaskResponseEnum::StorageStore(storage_id) => JsonValue::from(storage_id.to_string()),
+        TaskResponseEnum::StorageGet(file_response) => serde_json::to_value(file_response).ok()?,
+        TaskResponseEnum::WebSocketConnect(opened) => serde_json::to_value(opened).ok()?,
+        TaskResponseEnum::WebSocketReceive(
+            message @ (WebSocketMessageV8::Text { .. } | WebSocketMessageV8::Close { .. }),
+        ) => serde_json::to_value(message).ok()?,
+        TaskResponseEnum::Replayed(value) => value.clone(),
+        TaskResponseEnum::ParseMultiPart(_)
+        | TaskResponseEnum::WebSocketReceive(WebSocketMessageV8::Binary { .. }) => return None,
+    };
+    Some(value)
+}
+
+fn expected(event: Option<&RecordedActionEvent>) -> String {
+    match event {
+        None => "the end of the recording".to_string(),
+        Some(RecordedActionEvent::UnixTimestamp(_)) => "a read of the current time".to_string(),
+        Some(RecordedActionEvent::TaskDone { name, .. }) => format!("{name} to complete"),
+        Some(RecordedActionEvent::StreamExtend { .. }) => "more of a response body".to_string(),
+    }
+}
+
+fn replay_diverged(action: &str, expected: &str) -> ErrorMetadata {
+    ErrorMetadata::bad_request(
+        "ReplayDiverged",
+        format!(
+            "The action {action} where the recording expected {expected}. Has its code changed \
+             since it was recorded?"
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use common::runtime::UnixTimestamp;
+    use errors::ErrorMetadataAnyhowExt;
+    use serde_json::json;
+
+    use super::ActionRecorder;
+    use crate::environment::action::task::{
+        TaskId,
+        TaskResponse,
+        TaskResponseEnum,
+    };
+
+    #[test]
+    fn test_record_and_replay() -> anyhow::Result<()> {
+        let mut recorder = ActionRecorder::Recording(vec![]);
+        let now = recorder.unix_timestamp(|| Ok(UnixTimestamp::from_nanos(5)))?;
+        recorder.record_task_response(
+            &TaskResponse::TaskDone {
+                task_id: TaskId(0),
+                variant: Ok(TaskResponseEnum::Syscall("{}".to_string())),
+            },
+            |_| Some("runQuery".to_string()),
+        );
+        let events = recorder.into_recorded().unwrap();
+        assert_eq!(events.len(), 2);
+
+        let mut replayer = ActionRecorder::Replaying(events.clone().into());
+        assert_eq!(
+            replayer.unix_timestamp(|| anyhow::bail!("should be replayed"))?,
+            now
+        );
+        let TaskResponse::TaskDone { task_id, variant } =
+            replayer.replay_task_response(|_| Some("runQuery".to_string()))?
+        else {
+            panic!("Expected a task to complete");
+        };
+        assert_eq!(task_id, TaskId(0));
+        assert!(matches!(
+            variant?,
+            TaskResponseEnum::Replayed(value) if value == json!("{}")
+        ));
+
+        // Waiting on a different task than the recording diverges.
+        let mut replayer = ActionRecorder::Replaying(events.into());
+        replayer.unix_timestamp(|| anyhow::bail!("should be replayed"))?;
+        let err = replayer
+            .replay_task_response(|_| Some("fetch".to_string()))
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "ReplayDiverged");
+        Ok(())
+    }
+}
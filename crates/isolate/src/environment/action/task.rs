@@ -3,6 +3,7 @@
 
 use common::{
     fastrace_helpers::EncodedSpan,
+    http::fetch::WebSocketMessage,
     runtime::UnixTimestamp,
 };
 use deno_core::{
@@ -59,6 +60,11 @@ impl TaskRequestEnum {
             TaskRequestEnum::AsyncOp(AsyncOpRequest::StorageStore { .. }) => TaskType::StorageStore,
             TaskRequestEnum::AsyncOp(AsyncOpRequest::StorageGet { .. }) => TaskType::StorageGet,
             TaskRequestEnum::AsyncOp(AsyncOpRequest::SendStream { .. }) => TaskType::SendStream,
+            TaskRequestEnum::AsyncOp(
+                AsyncOpRequest::WebSocketConnect { .. }
+                | AsyncOpRequest::WebSocketSend { .. }
+                | AsyncOpRequest::WebSocketReceive { .. },
+            ) => TaskType::WebSocket,
         }
     }
 
@@ -85,6 +91,7 @@ pub enum TaskType {
     StorageStore,
     StorageGet,
     SendStream,
+    WebSocket,
 }
 
 fn syscall_display_name(syscall: &str) -> String {
@@ -105,6 +112,7 @@ impl TaskType {
             TaskType::StorageStore => "storage.store".to_string(),
             TaskType::StorageGet => "storage.get".to_string(),
             TaskType::SendStream => "ReadableStream".to_string(),
+            TaskType::WebSocket => "WebSocket".to_string(),
             // Sleeps cannot actually be dangling, but we handle it just in case.
             TaskType::Sleep => "setTimeout".to_string(),
         }
@@ -130,6 +138,11 @@ pub enum TaskResponseEnum {
     Sleep(UnixTimestamp),
     StorageStore(DeveloperDocumentId),
     StorageGet(Option<FileResponse>),
+    WebSocketConnect(WebSocketOpened),
+    WebSocketSend,
+    WebSocketReceive(WebSocketMessageV8),
+    /// The value a task passed to JavaScript in a recorded execution.
+    Replayed(JsonValue),
 }
 
 impl TaskResponseEnum {
@@ -144,6 +157,10 @@ impl TaskResponseEnum {
             Self::Sleep(_) => serde_v8::to_v8(scope, ())?,
             Self::StorageStore(storage_id) => serde_v8::to_v8(scope, storage_id.to_string())?,
             Self::StorageGet(file_response) => serde_v8::to_v8(scope, file_response)?,
+            Self::WebSocketConnect(opened) => serde_v8::to_v8(scope, opened)?,
+            Self::WebSocketSend => serde_v8::to_v8(scope, ())?,
+            Self::WebSocketReceive(message) => serde_v8::to_v8(scope, message)?,
+            Self::Replayed(value) => serde_v8::to_v8(scope, value)?,
         };
         Ok(value_v8)
     }
@@ -172,3 +189,30 @@ pub struct FormPartFile {
     pub data: ToJsBuffer,
     pub file_name: Option<String>,
 }
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketOpened {
+    pub socket_id: uuid::Uuid,
+    pub protocol: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WebSocketMessageV8 {
+    Text { data: String },
+    Binary { data: ToJsBuffer },
+    Close { code: u16, reason: String },
+}
+
+impl From<WebSocketMessage> for WebSocketMessageV8 {
+    fn from(message: WebSocketMessage) -> Self {
+        match message {
+            WebSocketMessage::Text(data) => Self::Text { data },
+            WebSocketMessage::Binary(data) => Self::Binary {
+                data: data.to_vec().into(),
+            },
+            WebSocketMessage::Close { code, reason } => Self::Close { code, reason },
+        }
+    }
+}
@@ -53,6 +53,7 @@ use crate::{
                 TaskResponseEnum,
             },
             task_order::TaskOrder,
+            websocket::ActionWebSocket,
         },
         AsyncOpRequest,
     },
@@ -79,6 +80,7 @@ pub struct TaskExecutor<RT: Runtime> {
     pub resources: Arc<Mutex<BTreeMap<Reference, Resource>>>,
     pub component_id: ComponentId,
     pub function_handles: Arc<Mutex<BTreeMap<CanonicalizedComponentFunctionPath, FunctionHandle>>>,
+    pub websockets: Arc<Mutex<BTreeMap<uuid::Uuid, ActionWebSocket>>>,
 }
 
 impl<RT: Runtime> TaskExecutor<RT> {
@@ -163,6 +165,18 @@ impl<RT: Runtime> TaskExecutor<RT> {
                 self.run_storage_get(task_id, storage_id, stream_id).await;
                 return task_id;
             },
+            TaskRequestEnum::AsyncOp(AsyncOpRequest::WebSocketConnect { request }) => self
+                .run_websocket_connect(request)
+                .await
+                .map(TaskResponseEnum::WebSocketConnect),
+            TaskRequestEnum::AsyncOp(AsyncOpRequest::WebSocketSend { socket_id, message }) => self
+                .run_websocket_send(socket_id, message)
+                .await
+                .map(|()| TaskResponseEnum::WebSocketSend),
+            TaskRequestEnum::AsyncOp(AsyncOpRequest::WebSocketReceive { socket_id }) => self
+                .run_websocket_receive(socket_id)
+                .await
+                .map(TaskResponseEnum::WebSocketReceive),
         };
         let _ = self
             .task_retval_sender
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use common::{
+    http::fetch::{
+        WebSocketMessage,
+        WebSocketRequest,
+        WebSocketSink,
+    },
+    knobs::MAX_ACTION_WEBSOCKETS,
+    runtime::Runtime,
+};
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use futures::{
+    stream::BoxStream,
+    SinkExt,
+    StreamExt,
+};
+
+use super::task_executor::TaskExecutor;
+use crate::{
+    environment::action::task::{
+        WebSocketMessageV8,
+        WebSocketOpened,
+    },
+    metrics,
+};
+
+/// Close code for connections that dropped without a close message.
+const ABNORMAL_CLOSURE: u16 = 1006;
+
+/// A WebSocket connection opened by an action. Sending and receiving happen in
+/// separate tasks, so each half has its own lock.
+#[derive(Clone)]
+pub struct ActionWebSocket {
+    sink: Arc<tokio::sync::Mutex<WebSocketSink>>,
+    stream: Arc<tokio::sync::Mutex<BoxStream<'static, anyhow::Result<WebSocketMessage>>>>,
+}
+
+impl ActionWebSocket {
+    pub fn new(
+        sink: WebSocketSink,
+        stream: BoxStream<'static, anyhow::Result<WebSocketMessage>>,
+    ) -> Self {
+        Self {
+            sink: Arc::new(tokio::sync::Mutex::new(sink)),
+            stream: Arc::new(tokio::sync::Mutex::new(stream)),
+        }
+    }
+}
+
+impl<RT: Runtime> TaskExecutor<RT> {
+    pub async fn run_websocket_connect(
+        &self,
+        request: WebSocketRequest,
+    ) -> anyhow::Result<WebSocketOpened> {
+        if self.websockets.lock().len() >= *MAX_ACTION_WEBSOCKETS {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "TooManyWebSockets",
+                format!(
+                    "An action can have at most {} open WebSocket connections",
+                    *MAX_ACTION_WEBSOCKETS
+                ),
+            ));
+        }
+        let timer = metrics::udf_websocket_connect_timer();
+        // Only log origin because query params might contain some PII.
+        let origin = request.url.origin().unicode_serialization();
+        let result = self.fetch_client.connect_websocket(request).await;
+        tracing::info!(
+            "WebSocket to origin: {origin}, success: {}, connect_time: {:?}",
+            result.is_ok(),
+            timer.elapsed(),
+        );
+        let connection = result.map_err(websocket_error)?;
+        timer.finish();
+        let socket_id = self.rt.new_uuid_v4();
+        self.websockets.lock().insert(
+            socket_id,
+            ActionWebSocket::new(connection.sink, connection.stream),
+        );
+        Ok(WebSocketOpened {
+            socket_id,
+            protocol: connection.protocol,
+        })
+    }
+
+    pub async fn run_websocket_send(
+        &self,
+        socket_id: uuid::Uuid,
+        message: WebSocketMessage,
+    ) -> anyhow::Result<()> {
+        let socket = self.websocket(socket_id)?;
+        metrics::log_udf_websocket_message(true, message_size(&message));
+        let mut sink = socket.sink.lock().await;
+        sink.send(message).await.map_err(websocket_error)
+    }
+
+    /// Waits for the next message from the server. Once the connection is
+    /// closed, this returns a close message and forgets the socket.
+    pub async fn run_websocket_receive(
+        &self,
+        socket_id: uuid::Uuid,
+    ) -> anyhow::Result<WebSocketMessageV8> {
+        let socket = self.websocket(socket_id)?;
+        let next = socket.stream.lock().await.next().await;
+        let message = match next {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => {
+                self.websockets.lock().remove(&socket_id);
+                return Err(websocket_error(e));
+            },
+            None => WebSocketMessage::Close {
+                code: ABNORMAL_CLOSURE,
+                reason: String::new(),
+            },
+        };
+        if let WebSocketMessage::Close { .. } = message {
+            self.websockets.lock().remove(&socket_id);
+        }
+        metrics::log_udf_websocket_message(false, message_size(&message));
+        Ok(message.into())
+    }
+
+    fn websocket(&self, socket_id: uuid::Uuid) -> anyhow::Result<ActionWebSocket> {
+        self.websockets
+            .lock()
+            .get(&socket_id)
+            .cloned()
+            .ok_or_else(|| {
+                ErrorMetadata::bad_request("WebSocketClosed", "WebSocket is already closed").into()
+            })
+    }
+}
+
+fn message_size(message: &WebSocketMessage) -> usize {
+    match message {
+        WebSocketMessage::Text(text) => text.len(),
+        WebSocketMessage::Binary(bytes) => bytes.len(),
+        WebSocketMessage::Close { reason, .. } => reason.len(),
+    }
+}
+
+/// Like fetch errors, connection errors are treated as developer errors since
+/// we have little control over what they connect to. Errors that are already
+/// categorized, like hosts blocked by the fetch host policy, are passed
+/// through.
+fn websocket_error(e: anyhow::Error) -> anyhow::Error {
+    if e.is_deterministic_user_error() {
+        e
+    } else {
+        ErrorMetadata::bad_request("WebSocketFailed", format!("{e:#}")).into()
+    }
+}
@@ -1,7 +1,13 @@
 use std::fmt;
 
 use common::{
-    http::HttpRequestStream,
+    http::{
+        fetch::{
+            WebSocketMessage,
+            WebSocketRequest,
+        },
+        HttpRequestStream,
+    },
     runtime::UnixTimestamp,
     sync::spsc,
 };
@@ -34,6 +40,16 @@ pub enum AsyncOpRequest {
         stream: Option<BoxStream<'static, anyhow::Result<bytes::Bytes>>>,
         stream_id: uuid::Uuid,
     },
+    WebSocketConnect {
+        request: WebSocketRequest,
+    },
+    WebSocketSend {
+        socket_id: uuid::Uuid,
+        message: WebSocketMessage,
+    },
+    WebSocketReceive {
+        socket_id: uuid::Uuid,
+    },
 }
 
 impl AsyncOpRequest {
@@ -44,6 +60,9 @@ impl AsyncOpRequest {
             Self::Sleep { .. } => "Sleep",
             Self::StorageStore { .. } | Self::StorageGet { .. } => "Storage",
             Self::SendStream { .. } => "Stream",
+            Self::WebSocketConnect { .. }
+            | Self::WebSocketSend { .. }
+            | Self::WebSocketReceive { .. } => "WebSocket",
         }
     }
 
@@ -55,6 +74,13 @@ impl AsyncOpRequest {
             Self::StorageStore { .. } => "storage.store()".to_string(),
             Self::StorageGet { .. } => "storage.get()".to_string(),
             Self::SendStream { .. } => "stream".to_string(),
+            Self::WebSocketConnect { .. } => "new WebSocket()".to_string(),
+            Self::WebSocketSend {
+                message: WebSocketMessage::Close { .. },
+                ..
+            } => "WebSocket.close()".to_string(),
+            Self::WebSocketSend { .. } => "WebSocket.send()".to_string(),
+            Self::WebSocketReceive { .. } => "WebSocket".to_string(),
         }
     }
 }
@@ -19,6 +19,7 @@ use common::{
     document::DeveloperDocument,
     execution_context::ExecutionContext,
     knobs::{
+        MAX_JOBS_CANCEL_BATCH,
         MAX_REACTOR_CALL_DEPTH,
         MAX_SYSCALL_BATCH_SIZE,
     },
@@ -66,13 +67,34 @@ use model::{
         handles::FunctionHandlesModel,
         ComponentsModel,
     },
+    environment_variables::EnvironmentVariablesModel,
     file_storage::{
         types::FileStorageEntry,
         BatchKey,
         FileStorageId,
     },
-    scheduled_jobs::VirtualSchedulerModel,
+    scheduled_jobs::{
+        types::{
+            RetryPolicyJson,
+            ScheduleOptions,
+            ScheduleOptionsJson,
+            ScheduledJobFilter,
+            ScheduledJobRetryPolicy,
+        },
+        SchedulerModel,
+        VirtualSchedulerModel,
+    },
     virtual_system_mapping,
+    workflows::{
+        types::{
+            WorkflowState,
+            WorkflowStepKind,
+            WorkflowStepState,
+        },
+        WorkflowJournal,
+        WorkflowModel,
+        MAX_WORKFLOW_SLEEP,
+    },
 };
 use serde::{
     Deserialize,
@@ -526,6 +548,17 @@ impl<RT: Runtime> AsyncSyscallProvider<RT> for DatabaseUdfEnvironment<RT> {
             0
         };
 
+        // Overrides scoped to the called function or its component apply to
+        // it like they would if it were called directly.
+        let tx = self.phase.tx()?;
+        let called_function_path = CanonicalizedComponentFunctionPath {
+            component: tx.must_component_path(called_component_id)?,
+            udf_path: path.udf_path.clone(),
+        };
+        let env_var_overrides = EnvironmentVariablesModel::new(tx)
+            .get_overrides_for_function(&called_function_path)
+            .await?;
+
         let mut tx = self.phase.take_tx()?;
         let tokens = tx.begin_subtransaction();
 
@@ -543,6 +576,7 @@ impl<RT: Runtime> AsyncSyscallProvider<RT> for DatabaseUdfEnvironment<RT> {
                 EnvironmentData {
                     key_broker: self.key_broker.clone(),
                     system_env_vars: BTreeMap::new(),
+                    env_var_overrides,
                     file_storage: self.file_storage.clone(),
                     module_loader: self.phase.module_loader().clone(),
                 },
@@ -675,6 +709,14 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsV1<RT, P> {
                     // Scheduling
                     "1.0/schedule" => Box::pin(Self::schedule(provider, args)).await,
                     "1.0/cancel_job" => Box::pin(Self::cancel_job(provider, args)).await,
+                    "1.0/cancel_jobs" => Box::pin(Self::cancel_jobs(provider, args)).await,
+                    // Workflows
+                    "1.0/workflow/start" => Box::pin(Self::start_workflow(provider, args)).await,
+                    "1.0/workflow/get" => Box::pin(Self::get_workflow(provider, args)).await,
+                    "1.0/workflow/resume" => Box::pin(Self::resume_workflow(provider, args)).await,
+                    "1.0/workflow/step" => Box::pin(Self::workflow_step(provider, args)).await,
+                    "1.0/workflow/finish" => Box::pin(Self::finish_workflow(provider, args)).await,
+                    "1.0/workflow/cancel" => Box::pin(Self::cancel_workflow(provider, args)).await,
 
                     // Components
                     "1.0/runUdf" => Box::pin(Self::run_udf(provider, args)).await,
@@ -867,6 +909,8 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsV1<RT, P> {
             function_handle: Option<String>,
             ts: f64,
             args: UdfArgsJson,
+            #[serde(default)]
+            options: ScheduleOptionsJson,
         }
 
         let ScheduleArgs {
@@ -875,8 +919,35 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsV1<RT, P> {
             function_handle,
             ts,
             args,
+            options,
         }: ScheduleArgs = with_argument_error("scheduler", || Ok(serde_json::from_value(args)?))?;
+        let options = ScheduleOptions::try_from(options)?;
 
+        let path =
+            Self::resolve_scheduled_function(provider, name, reference, function_handle).await?;
+
+        let scheduling_component = provider.component()?;
+
+        let scheduled_ts = UnixTimestamp::from_secs_f64(ts);
+        let (path, udf_args) = provider
+            .validate_schedule_args(path, args.into_arg_vec(), scheduled_ts)
+            .await?;
+
+        let context = provider.context().clone();
+        let tx = provider.tx()?;
+        let virtual_id = VirtualSchedulerModel::new(tx, scheduling_component.into())
+            .schedule(path, udf_args, scheduled_ts, context, options)
+            .await?;
+
+        Ok(JsonValue::from(virtual_id))
+    }
+
+    async fn resolve_scheduled_function(
+        provider: &mut P,
+        name: Option<String>,
+        reference: Option<String>,
+        function_handle: Option<String>,
+    ) -> anyhow::Result<CanonicalizedComponentFunctionPath> {
         let path = match function_handle {
             Some(h) => {
                 let handle: FunctionHandle = with_argument_error("scheduler", || h.parse())?;
@@ -901,21 +972,7 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsV1<RT, P> {
                 }
             },
         };
-
-        let scheduling_component = provider.component()?;
-
-        let scheduled_ts = UnixTimestamp::from_secs_f64(ts);
-        let (path, udf_args) = provider
-            .validate_schedule_args(path, args.into_arg_vec(), scheduled_ts)
-            .await?;
-
-        let context = provider.context().clone();
-        let tx = provider.tx()?;
-        let virtual_id = VirtualSchedulerModel::new(tx, scheduling_component.into())
-            .schedule(path, udf_args, scheduled_ts, context)
-            .await?;
-
-        Ok(JsonValue::from(virtual_id))
+        Ok(path)
     }
 
     #[convex_macro::instrument_future]
@@ -941,6 +998,292 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsV1<RT, P> {
         Ok(JsonValue::Null)
     }
 
+    /// Cancels a batch of the pending jobs that match a filter, returning a
+    /// cursor to cancel the next batch with.
+    #[convex_macro::instrument_future]
+    async fn cancel_jobs(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct CancelJobsArgs {
+            name: Option<String>,
+            reference: Option<String>,
+            function_handle: Option<String>,
+            args_match: Option<JsonValue>,
+            scheduled_after_ms: Option<f64>,
+            scheduled_before_ms: Option<f64>,
+            cursor: Option<String>,
+        }
+        let CancelJobsArgs {
+            name,
+            reference,
+            function_handle,
+            args_match,
+            scheduled_after_ms,
+            scheduled_before_ms,
+            cursor,
+        }: CancelJobsArgs =
+            with_argument_error("scheduler.cancelAll", || Ok(serde_json::from_value(args)?))?;
+        let path = if name.is_some() || reference.is_some() || function_handle.is_some() {
+            Some(
+                Self::resolve_scheduled_function(provider, name, reference, function_handle)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        let filter =
+            ScheduledJobFilter::new(path, args_match, scheduled_after_ms, scheduled_before_ms)?;
+        let cursor = cursor
+            .map(|c| {
+                provider
+                    .key_broker()
+                    .decrypt_cursor(c, provider.persistence_version())
+            })
+            .transpose()?;
+
+        let component = provider.component()?;
+        let tx = provider.tx()?;
+        let page = SchedulerModel::new(tx, component.into())
+            .cancel_all(&filter, cursor, *MAX_JOBS_CANCEL_BATCH)
+            .await?;
+        let continue_cursor = page.cursor.as_ref().map(|cursor| {
+            provider
+                .key_broker()
+                .encrypt_cursor(cursor, provider.persistence_version())
+        });
+        Ok(json!({
+            "canceled": page.canceled,
+            "isDone": continue_cursor.is_none(),
+            "continueCursor": continue_cursor,
+        }))
+    }
+
+    /// Starts a workflow defined by a mutation in the calling component,
+    /// returning the workflow's id.
+    #[convex_macro::instrument_future]
+    async fn start_workflow(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct StartWorkflowArgs {
+            name: Option<String>,
+            reference: Option<String>,
+            function_handle: Option<String>,
+            args: JsonValue,
+        }
+        let StartWorkflowArgs {
+            name,
+            reference,
+            function_handle,
+            args,
+        }: StartWorkflowArgs =
+            with_argument_error("startWorkflow", || Ok(serde_json::from_value(args)?))?;
+        let args: ConvexObject = with_argument_error("startWorkflow", || {
+            ConvexValue::try_from(args)
+                .context(ArgName("args"))?
+                .try_into()
+                .context(ArgName("args"))
+        })?;
+        let path =
+            Self::resolve_scheduled_function(provider, name, reference, function_handle).await?;
+        // Check that the workflow's mutation exists. Its id isn't known yet,
+        // but any string passes the workflow mutation's validator.
+        let now = provider.unix_timestamp()?;
+        let (path, _) = provider
+            .validate_schedule_args(path, vec![json!({ "workflowId": "" })], now)
+            .await?;
+
+        let component = provider.component()?;
+        let context = provider.context().clone();
+        let tx = provider.tx()?;
+        let (_, workflow_component) =
+            BootstrapComponentsModel::new(tx).must_component_path_to_ids(&path.component)?;
+        anyhow::ensure!(
+            workflow_component == component,
+            ErrorMetadata::bad_request(
+                "InvalidWorkflow",
+                "Workflows can only be started from the component that defines them",
+            )
+        );
+        let id = WorkflowModel::new(tx, component.into())
+            .start(path, args, context)
+            .await?;
+        Ok(JsonValue::from(id.developer_id))
+    }
+
+    /// Returns a workflow's state and steps, or null if it doesn't exist.
+    #[convex_macro::instrument_future]
+    async fn get_workflow(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        let workflow_id = parse_workflow_id("getWorkflow", args)?;
+        let component = provider.component()?;
+        let tx = provider.tx()?;
+        let journal = WorkflowModel::new(tx, component.into())
+            .load(workflow_id)
+            .await?;
+        journal.map_or(Ok(JsonValue::Null), workflow_journal_to_json)
+    }
+
+    /// Loads a workflow for its mutation to replay, finishing its last step if
+    /// it's a sleep that's over.
+    #[convex_macro::instrument_future]
+    async fn resume_workflow(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        let workflow_id = parse_workflow_id("workflow", args)?;
+        let component = provider.component()?;
+        let tx = provider.tx()?;
+        let journal = WorkflowModel::new(tx, component.into())
+            .resume(workflow_id)
+            .await?;
+        journal.map_or(Ok(JsonValue::Null), workflow_journal_to_json)
+    }
+
+    /// Adds the next step to a workflow's journal. Queries have already run
+    /// and are journaled with their result, mutations and actions are
+    /// scheduled to run now, and sleeps schedule the workflow to run again
+    /// later.
+    #[convex_macro::instrument_future]
+    async fn workflow_step(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct WorkflowStepArgs {
+            workflow_id: String,
+            step_number: u32,
+            kind: String,
+            name: Option<String>,
+            reference: Option<String>,
+            function_handle: Option<String>,
+            args: Option<UdfArgsJson>,
+            result: Option<JsonValue>,
+            error: Option<String>,
+            delay_ms: Option<f64>,
+            retry: Option<RetryPolicyJson>,
+        }
+        let WorkflowStepArgs {
+            workflow_id,
+            step_number,
+            kind,
+            name,
+            reference,
+            function_handle,
+            args,
+            result,
+            error,
+            delay_ms,
+            retry,
+        }: WorkflowStepArgs =
+            with_argument_error("workflow", || Ok(serde_json::from_value(args)?))?;
+        let (workflow_id, kind) = with_argument_error("workflow", || {
+            let workflow_id =
+                DeveloperDocumentId::decode(&workflow_id).context(ArgName("workflowId"))?;
+            let kind: WorkflowStepKind = kind.parse().context(ArgName("kind"))?;
+            Ok((workflow_id, kind))
+        })?;
+        let component = provider.component()?;
+        match kind {
+            WorkflowStepKind::Query => {
+                let outcome = match (result, error) {
+                    (Some(result), None) => Ok(serde_json::to_string(&result)?),
+                    (None, Some(error)) => Err(error),
+                    _ => anyhow::bail!(ErrorMetadata::bad_request(
+                        "InvalidWorkflowStep",
+                        "Query steps must have either a result or an error",
+                    )),
+                };
+                let path =
+                    Self::resolve_scheduled_function(provider, name, reference, function_handle)
+                        .await?;
+                let tx = provider.tx()?;
+                WorkflowModel::new(tx, component.into())
+                    .record_query_step(workflow_id, step_number, path, outcome)
+                    .await?;
+            },
+            WorkflowStepKind::Mutation | WorkflowStepKind::Action => {
+                let path =
+                    Self::resolve_scheduled_function(provider, name, reference, function_handle)
+                        .await?;
+                let args = args.context(ErrorMetadata::bad_request(
+                    "InvalidWorkflowStep",
+                    "Mutation and action steps must have arguments",
+                ))?;
+                let retry = retry.map(ScheduledJobRetryPolicy::try_from).transpose()?;
+                let now = provider.unix_timestamp()?;
+                let (path, udf_args) = provider
+                    .validate_schedule_args(path, args.into_arg_vec(), now)
+                    .await?;
+                let context = provider.context().clone();
+                let tx = provider.tx()?;
+                WorkflowModel::new(tx, component.into())
+                    .start_job_step(
+                        workflow_id,
+                        step_number,
+                        kind,
+                        path,
+                        udf_args,
+                        retry,
+                        context,
+                    )
+                    .await?;
+            },
+            WorkflowStepKind::Sleep => {
+                let duration = delay_ms
+                    .filter(|ms| (0.0..=MAX_WORKFLOW_SLEEP.as_millis() as f64).contains(ms))
+                    .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+                    .context(ErrorMetadata::bad_request(
+                        "InvalidWorkflowSleep",
+                        format!(
+                            "Workflows can sleep for between 0 and {} days",
+                            MAX_WORKFLOW_SLEEP.as_secs() / (24 * 60 * 60)
+                        ),
+                    ))?;
+                let context = provider.context().clone();
+                let tx = provider.tx()?;
+                WorkflowModel::new(tx, component.into())
+                    .sleep(workflow_id, step_number, duration, context)
+                    .await?;
+            },
+        }
+        Ok(JsonValue::Null)
+    }
+
+    /// Finishes a workflow with the value its definition returned or the
+    /// error it threw.
+    #[convex_macro::instrument_future]
+    async fn finish_workflow(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct FinishWorkflowArgs {
+            workflow_id: String,
+            result: Option<JsonValue>,
+            error: Option<String>,
+        }
+        let (workflow_id, outcome) = with_argument_error("workflow", || {
+            let args: FinishWorkflowArgs = serde_json::from_value(args)?;
+            let workflow_id =
+                DeveloperDocumentId::decode(&args.workflow_id).context(ArgName("workflowId"))?;
+            let outcome = match (args.result, args.error) {
+                (Some(result), None) => Ok(serde_json::to_string(&result)?),
+                (None, Some(error)) => Err(error),
+                _ => anyhow::bail!("Workflows must finish with either a result or an error"),
+            };
+            Ok((workflow_id, outcome))
+        })?;
+        let component = provider.component()?;
+        let tx = provider.tx()?;
+        WorkflowModel::new(tx, component.into())
+            .finish(workflow_id, outcome)
+            .await?;
+        Ok(JsonValue::Null)
+    }
+
+    #[convex_macro::instrument_future]
+    async fn cancel_workflow(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
+        let workflow_id = parse_workflow_id("cancelWorkflow", args)?;
+        let component = provider.component()?;
+        let tx = provider.tx()?;
+        WorkflowModel::new(tx, component.into())
+            .cancel(workflow_id)
+            .await?;
+        Ok(JsonValue::Null)
+    }
+
     #[fastrace::trace]
     #[convex_macro::instrument_future]
     async fn insert(provider: &mut P, args: JsonValue) -> anyhow::Result<JsonValue> {
@@ -1582,3 +1925,63 @@ impl<RT: Runtime, P: AsyncSyscallProvider<RT>> DatabaseSyscallsShared<RT, P> {
         Ok(serde_json::to_value(result)?)
     }
 }
+
+fn parse_workflow_id(syscall: &str, args: JsonValue) -> anyhow::Result<DeveloperDocumentId> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct WorkflowIdArgs {
+        workflow_id: String,
+    }
+    with_argument_error(syscall, || {
+        let args: WorkflowIdArgs = serde_json::from_value(args)?;
+        DeveloperDocumentId::decode(&args.workflow_id).context(ArgName("workflowId"))
+    })
+}
+
+/// The workflow and its journal as returned to JavaScript, with results in
+/// Convex JSON and times in milliseconds since the Unix epoch.
+fn workflow_journal_to_json(journal: WorkflowJournal) -> anyhow::Result<JsonValue> {
+    let to_ms = |ts: common::types::Timestamp| i64::from(ts) / 1_000_000;
+    let workflow = journal.workflow;
+    let state = match &workflow.state {
+        WorkflowState::Running => json!({ "kind": "running" }),
+        WorkflowState::Completed { result } => json!({
+            "kind": "completed",
+            "result": serde_json::from_str::<JsonValue>(result)?,
+        }),
+        WorkflowState::Failed { error } => json!({ "kind": "failed", "error": error }),
+        WorkflowState::Canceled => json!({ "kind": "canceled" }),
+    };
+    let steps = journal
+        .steps
+        .iter()
+        .map(|step| {
+            let state = match &step.state {
+                WorkflowStepState::InProgress => json!({ "kind": "inProgress" }),
+                WorkflowStepState::Succeeded { result } => json!({
+                    "kind": "succeeded",
+                    "result": serde_json::from_str::<JsonValue>(result)?,
+                }),
+                WorkflowStepState::Failed { error } => {
+                    json!({ "kind": "failed", "error": error })
+                },
+            };
+            anyhow::Ok(json!({
+                "kind": step.kind.as_str(),
+                "name": step.path.as_ref().map(|path| String::from(path.udf_path.clone())),
+                "state": state,
+                "startedAt": to_ms(step.start_ts),
+                "completedAt": step.completed_ts.map(to_ms),
+            }))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(json!({
+        "id": workflow.id().developer_id.encode(),
+        "name": String::from(workflow.path.udf_path.clone()),
+        "args": JsonValue::from(workflow.args()?),
+        "state": state,
+        "steps": steps,
+        "startedAt": to_ms(workflow.start_ts),
+        "completedAt": workflow.completed_ts.map(to_ms),
+    }))
+}
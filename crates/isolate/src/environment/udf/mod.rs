@@ -23,12 +23,14 @@ use model::{
 };
 use udf::{
     helpers::serialize_udf_args,
+    ExecutionRecording,
     FunctionOutcome,
     SyscallTrace,
 };
 pub mod async_syscall;
 
 mod phase;
+pub mod recording;
 pub mod syscall;
 use std::{
     cmp::Ordering,
@@ -45,6 +47,7 @@ use common::{
         DATABASE_UDF_USER_TIMEOUT,
         FUNCTION_MAX_ARGS_SIZE,
         FUNCTION_MAX_RESULT_SIZE,
+        FUNCTION_USER_TIMEOUTS,
         TRANSACTION_MAX_NUM_SCHEDULED,
         TRANSACTION_MAX_NUM_USER_WRITES,
         TRANSACTION_MAX_READ_SET_INTERVALS,
@@ -128,16 +131,24 @@ use crate::{
         UdfRequest,
     },
     concurrency_limiter::ConcurrencyPermit,
+    cpu_profiler::CpuProfilingSession,
     environment::{
         helpers::{
             module_loader::module_specifier_from_path,
             resolve_promise,
             MAX_LOG_LINES,
         },
-        udf::async_syscall::DatabaseSyscallsV1,
+        udf::{
+            async_syscall::DatabaseSyscallsV1,
+            recording::{
+                SyscallRecorder,
+                EXECUTION_RECORDER,
+            },
+        },
         AsyncOpRequest,
         IsolateEnvironment,
     },
+    heap_snapshot::take_heap_snapshot_if_requested,
     helpers::{
         self,
         deserialize_udf_result,
@@ -189,6 +200,10 @@ pub struct DatabaseUdfEnvironment<RT: Runtime> {
 
     syscall_trace: SyscallTrace,
 
+    /// Recording to replay instead of running syscalls, if any.
+    replay: Option<ExecutionRecording>,
+    syscall_recorder: SyscallRecorder,
+
     heap_stats: SharedIsolateHeapStats,
 
     context: ExecutionContext,
@@ -250,7 +265,15 @@ impl<RT: Runtime> IsolateEnvironment<RT> for DatabaseUdfEnvironment<RT> {
     }
 
     fn syscall(&mut self, name: &str, args: JsonValue) -> anyhow::Result<JsonValue> {
-        syscall_impl(self, name, args)
+        if self.syscall_recorder.is_replaying() {
+            return self.syscall_recorder.replay(name, &args);
+        }
+        let recorded_args = self.syscall_recorder.is_recording().then(|| args.clone());
+        let result = syscall_impl(self, name, args);
+        if let Some(args) = recorded_args {
+            self.syscall_recorder.record(name, args, result.as_ref());
+        }
+        result
     }
 
     fn start_async_syscall(
@@ -289,7 +312,9 @@ impl<RT: Runtime> IsolateEnvironment<RT> for DatabaseUdfEnvironment<RT> {
     }
 
     fn user_timeout(&self) -> std::time::Duration {
-        *DATABASE_UDF_USER_TIMEOUT
+        FUNCTION_USER_TIMEOUTS
+            .user_timeout_for(&self.path.clone().for_logging())
+            .unwrap_or(*DATABASE_UDF_USER_TIMEOUT)
     }
 
     fn system_timeout(&self) -> std::time::Duration {
@@ -304,6 +329,7 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
         EnvironmentData {
             key_broker,
             system_env_vars,
+            env_var_overrides,
             file_storage,
             module_loader,
         }: EnvironmentData<RT>,
@@ -315,6 +341,7 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
             transaction,
             journal,
             context,
+            replay,
         }: UdfRequest<RT>,
         reactor_depth: usize,
         udf_callback: Box<dyn UdfCallback<RT>>,
@@ -336,6 +363,7 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
                 rt,
                 module_loader.clone(),
                 system_env_vars,
+                env_var_overrides,
                 component,
             ),
             file_storage,
@@ -350,6 +378,8 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
 
             pending_syscalls: WithHeapSize::default(),
             syscall_trace: SyscallTrace::new(),
+            replay,
+            syscall_recorder: SyscallRecorder::Disabled,
             heap_stats,
             context,
 
@@ -370,8 +400,23 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
         // Initialize the UDF's RNG from some high-quality entropy. As with
         // `unix_timestamp` below, the UDF is only deterministic modulo this
         // system-generated input.
-        let rng_seed = self.rt.rng().gen();
-        let unix_timestamp = self.rt.unix_timestamp();
+        // If the execution fails or abandons its recording, dropping the
+        // claim lets a later execution be recorded instead.
+        let mut recording_claim = None;
+        let (rng_seed, unix_timestamp) = match self.replay.take() {
+            Some(recording) => {
+                self.syscall_recorder = SyscallRecorder::Replaying(recording.syscalls.into());
+                (recording.rng_seed, recording.unix_timestamp)
+            },
+            None => {
+                recording_claim =
+                    EXECUTION_RECORDER.claim(&client_id, &self.path.clone().for_logging());
+                if recording_claim.is_some() {
+                    self.syscall_recorder = SyscallRecorder::Recording(vec![]);
+                }
+                (self.rt.rng().gen(), self.rt.unix_timestamp())
+            },
+        };
         let heap_stats = self.heap_stats.clone();
 
         // See Isolate::with_context for an explanation of this setup code. We can't use
@@ -379,10 +424,17 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
         // generic async closure to `Isolate` is currently difficult.
         let client_id = Arc::new(client_id);
         let path = self.path.clone();
-        let (handle, state) = isolate.start_request(client_id, self).await?;
+        let (handle, state) = isolate.start_request(client_id.clone(), self).await?;
         let mut handle_scope = isolate.handle_scope();
         let v8_context = v8::Context::new(&mut handle_scope);
         let mut context_scope = v8::ContextScope::new(&mut handle_scope, v8_context);
+        let logging_path = path.clone().for_logging();
+        let cpu_profiling_session = CpuProfilingSession::start_if_requested(
+            &mut context_scope,
+            v8_context,
+            client_id.clone(),
+            logging_path.clone(),
+        );
 
         let mut isolate_context =
             RequestScope::new(&mut context_scope, handle.clone(), state, false).await?;
@@ -394,6 +446,10 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
         // leak to a subsequent one on isolate reuse.
         isolate_context.scope.perform_microtask_checkpoint();
         *isolate_clean = true;
+        if let Some(cpu_profiling_session) = cpu_profiling_session {
+            cpu_profiling_session.finish();
+        }
+        take_heap_snapshot_if_requested(isolate_context.scope, &client_id, &logging_path);
 
         // Override the returned result if we hit a termination error.
         let termination_error = handle
@@ -415,6 +471,21 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
 
         let execution_time;
         (self, execution_time) = isolate_context.take_environment();
+        let syscall_recorder =
+            std::mem::replace(&mut self.syscall_recorder, SyscallRecorder::Disabled);
+        if let Some(recording_claim) = recording_claim
+            && let Some(syscalls) = syscall_recorder.into_recorded()
+        {
+            recording_claim.record(ExecutionRecording {
+                udf_type: self.udf_type,
+                path: self.path.clone().for_logging(),
+                arguments: self.arguments.clone(),
+                rng_seed,
+                unix_timestamp,
+                syscalls,
+                action_events: vec![],
+            });
+        }
         let success_result_value = match result.as_ref() {
             Ok(v) => Some(v),
             _ => None,
@@ -688,6 +759,13 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
                     // No syscalls or javascript to run, so we're done.
                     break;
                 };
+                // Keep each call's arguments around if we need to record or replay it.
+                let track_calls = state.environment.syscall_recorder.is_recording()
+                    || state.environment.syscall_recorder.is_replaying();
+                let mut calls = vec![];
+                if track_calls {
+                    calls.push((p.name.clone(), p.args.clone()));
+                }
                 let mut batch = AsyncSyscallBatch::new(p.name, p.args);
                 let mut resolvers = vec![p.resolver];
                 while let Some(p) = state.environment.pending_syscalls.front()
@@ -698,34 +776,54 @@ impl<RT: Runtime> DatabaseUdfEnvironment<RT> {
                         .pending_syscalls
                         .pop_front()
                         .expect("should have a syscall");
+                    if track_calls {
+                        calls.push((p.name.clone(), p.args.clone()));
+                    }
                     batch.push(p.name, p.args)?;
                     resolvers.push(p.resolver);
                 }
-                // Pause the user-code UDF timeout for the duration of the syscall.
-                // This works because we know that the user is blocked on some syscall,
-                // so running the syscall is on us and we shouldn't count this time
-                // towards the user timeout. When we allow more concurrency, we
-                // may have to rework this.
-                // NOTE: Even though we release the permit, the syscall does in v8.
-                // It is better if we run it in tokio to avoid oversubscribing the CPU.
-                // TODO: Consider running the async call from a tokio thread.
-                // Even though the future would be blocking on the database most of the
-                // time it still does some processing that might result in oversubscribing
-                // the CPU threads dedicated to v8.
-                let results = select_biased! {
-                    _ = cancellation => {
-                        log_isolate_request_cancelled();
-                        anyhow::bail!("Cancelled");
-                    },
-                    results = with_release_permit(
-                        &mut state.timeout,
-                        &mut state.permit,
-                        DatabaseSyscallsV1::run_async_syscall_batch(
-                            &mut state.environment, batch,
-                        ).map(Ok),
-                    ).fuse() => results?,
-                };
-                (resolvers, results)
+                if state.environment.syscall_recorder.is_replaying() {
+                    let results = calls
+                        .into_iter()
+                        .map(|(name, args)| {
+                            let result = state.environment.syscall_recorder.replay(&name, &args)?;
+                            Ok(result.to_string())
+                        })
+                        .collect();
+                    (resolvers, results)
+                } else {
+                    // Pause the user-code UDF timeout for the duration of the syscall.
+                    // This works because we know that the user is blocked on some syscall,
+                    // so running the syscall is on us and we shouldn't count this time
+                    // towards the user timeout. When we allow more concurrency, we
+                    // may have to rework this.
+                    // NOTE: Even though we release the permit, the syscall does in v8.
+                    // It is better if we run it in tokio to avoid oversubscribing the CPU.
+                    // TODO: Consider running the async call from a tokio thread.
+                    // Even though the future would be blocking on the database most of the
+                    // time it still does some processing that might result in oversubscribing
+                    // the CPU threads dedicated to v8.
+                    let results = select_biased! {
+                        _ = cancellation => {
+                            log_isolate_request_cancelled();
+                            anyhow::bail!("Cancelled");
+                        },
+                        results = with_release_permit(
+                            &mut state.timeout,
+                            &mut state.permit,
+                            DatabaseSyscallsV1::run_async_syscall_batch(
+                                &mut state.environment, batch,
+                            ).map(Ok),
+                        ).fuse() => results?,
+                    };
+                    for ((name, args), result) in calls.into_iter().zip(&results) {
+                        state
+                            .environment
+                            .syscall_recorder
+                            .record_async(&name, args, result);
+                    }
+                    (resolvers, results)
+                }
             };
             // Every syscall must have a result (which could be an error or None).
             assert_eq!(resolvers.len(), results.len());
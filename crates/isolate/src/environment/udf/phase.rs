@@ -83,6 +83,7 @@ pub struct UdfPhase<RT: Runtime> {
     pub rt: RT,
     module_loader: Arc<dyn ModuleLoader<RT>>,
     system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
+    env_var_overrides: BTreeMap<EnvVarName, EnvVarValue>,
     preloaded: UdfPreloaded,
     component: ComponentId,
 }
@@ -107,6 +108,7 @@ impl<RT: Runtime> UdfPhase<RT> {
         rt: RT,
         module_loader: Arc<dyn ModuleLoader<RT>>,
         system_env_vars: BTreeMap<EnvVarName, EnvVarValue>,
+        env_var_overrides: BTreeMap<EnvVarName, EnvVarValue>,
         component: ComponentId,
     ) -> Self {
         Self {
@@ -115,6 +117,7 @@ impl<RT: Runtime> UdfPhase<RT> {
             rt,
             module_loader,
             system_env_vars,
+            env_var_overrides,
             preloaded: UdfPreloaded::Created,
             component,
         }
@@ -350,6 +353,9 @@ impl<RT: Runtime> UdfPhase<RT> {
         let UdfPreloaded::Ready { ref env_vars, .. } = self.preloaded else {
             anyhow::bail!("Phase not initialized");
         };
+        if let Some(var) = self.env_var_overrides.get(&name) {
+            return Ok(Some(var.clone()));
+        }
         let tx = self
             .tx
             .as_mut()
@@ -0,0 +1,165 @@
+use std::{
+    collections::VecDeque,
+    sync::LazyLock,
+};
+
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use serde_json::Value as JsonValue;
+use udf::{
+    ExecutionRecording,
+    RecordedSyscall,
+};
+
+use crate::execution_capture::ExecutionCaptures;
+
+/// Recordings of query, mutation and action executions requested by admins.
+pub static EXECUTION_RECORDER: LazyLock<ExecutionCaptures<ExecutionRecording>> =
+    LazyLock::new(ExecutionCaptures::new);
+
+/// Records the syscalls made by a query or mutation, or answers them from a
+/// previous [`ExecutionRecording`] when replaying it.
+pub enum SyscallRecorder {
+    Disabled,
+    Recording(Vec<RecordedSyscall>),
+    /// A syscall failed with an error we can't faithfully replay, so this
+    /// execution won't produce a recording.
+    Abandoned,
+    Replaying(VecDeque<RecordedSyscall>),
+}
+
+impl SyscallRecorder {
+    pub fn is_recording(&self) -> bool {
+        matches!(self, Self::Recording(_))
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replaying(_))
+    }
+
+    pub fn record(
+        &mut self,
+        name: &str,
+        args: JsonValue,
+        result: Result<&JsonValue, &anyhow::Error>,
+    ) {
+        let Self::Recording(syscalls) = self else {
+            return;
+        };
+        let result = match result {
+            Ok(value) => Ok(value.clone()),
+            // Deterministic user errors are thrown into JavaScript, so replay
+            // them with the same message. Anything else fails the execution.
+            Err(e) if e.is_deterministic_user_error() => Err(ErrorMetadata::bad_request(
+                e.short_msg().to_string(),
+                e.msg().to_string(),
+            )),
+            Err(_) => {
+                *self = Self::Abandoned;
+                return;
+            },
+        };
+        syscalls.push(RecordedSyscall {
+            name: name.to_string(),
+            args,
+            result,
+        });
+    }
+
+    /// Records the result of an async syscall, which is returned to
+    /// JavaScript as a serialized JSON string.
+    pub fn record_async(&mut self, name: &str, args: JsonValue, result: &anyhow::Result<String>) {
+        match result {
+            Ok(value) => match serde_json::from_str(value) {
+                Ok(value) => self.record(name, args, Ok(&value)),
+                Err(_) => *self = Self::Abandoned,
+            },
+            Err(e) => self.record(name, args, Err(e)),
+        }
+    }
+
+    /// Returns the recorded result for the next syscall, checking that the
+    /// function made the same call as when it was recorded.
+    pub fn replay(&mut self, name: &str, args: &JsonValue) -> anyhow::Result<JsonValue> {
+        let Self::Replaying(syscalls) = self else {
+            anyhow::bail!("Not replaying a recording");
+        };
+        let Some(syscall) = syscalls.pop_front() else {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "ReplayDiverged",
+                format!(
+                    "The function called {name} after making all of the syscalls in the \
+                     recording. Has its code changed since it was recorded?"
+                ),
+            ));
+        };
+        if syscall.name != name || &syscall.args != args {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "ReplayDiverged",
+                format!(
+                    "The function called {name} where the recording expected {}. Has its code \
+                     changed since it was recorded?",
+                    syscall.name
+                ),
+            ));
+        }
+        syscall.result.map_err(anyhow::Error::from)
+    }
+
+    /// Returns the recorded syscalls if this execution was being recorded.
+    pub fn into_recorded(self) -> Option<Vec<RecordedSyscall>> {
+        match self {
+            Self::Recording(syscalls) => Some(syscalls),
+            Self::Disabled | Self::Abandoned | Self::Replaying(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use errors::{
+        ErrorMetadata,
+        ErrorMetadataAnyhowExt,
+    };
+    use serde_json::json;
+
+    use super::SyscallRecorder;
+
+    #[test]
+    fn test_record_and_replay() -> anyhow::Result<()> {
+        let mut recorder = SyscallRecorder::Recording(vec![]);
+        recorder.record(
+            "1.0/queryStream",
+            json!({ "table": "messages" }),
+            Ok(&json!(0)),
+        );
+        recorder.record_async(
+            "1.0/get",
+            json!({ "id": "abc" }),
+            &Err(ErrorMetadata::bad_request("InvalidId", "Invalid id").into()),
+        );
+        let syscalls = recorder.into_recorded().unwrap();
+        assert_eq!(syscalls.len(), 2);
+
+        let mut replayer = SyscallRecorder::Replaying(syscalls.into());
+        assert_eq!(
+            replayer.replay("1.0/queryStream", &json!({ "table": "messages" }))?,
+            json!(0)
+        );
+        // Diverging from the recording fails.
+        let err = replayer
+            .replay("1.0/get", &json!({ "id": "def" }))
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "ReplayDiverged");
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_errors_abandon_recording() {
+        let mut recorder = SyscallRecorder::Recording(vec![]);
+        recorder.record_async("1.0/get", json!({}), &Err(anyhow::anyhow!("boom")));
+        assert!(recorder.into_recorded().is_none());
+    }
+}
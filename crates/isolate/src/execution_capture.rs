@@ -0,0 +1,265 @@
+//! Process-wide registry for capturing data (e.g. CPU profiles or recordings)
+//! from the next N executions of a function.
+//!
+//! Captures are process-local, so they only observe executions that happen in
+//! the same process as the caller.
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+};
+
+use common::components::CanonicalizedComponentFunctionPath;
+use errors::ErrorMetadata;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+struct CaptureRequest<T> {
+    /// Distinguishes this request from later ones for the same function, so
+    /// claims outliving it don't count towards them.
+    id: u64,
+    num_executions: usize,
+    /// Number of executions that have started capturing.
+    claimed: usize,
+    captures: Vec<T>,
+    done: oneshot::Sender<Vec<T>>,
+}
+
+pub struct ExecutionCaptures<T> {
+    /// Requests keyed by instance name and then by function, since a process
+    /// may run functions for more than one deployment.
+    requests:
+        Mutex<BTreeMap<String, BTreeMap<CanonicalizedComponentFunctionPath, CaptureRequest<T>>>>,
+    next_request_id: AtomicU64,
+}
+
+/// An execution that's being captured. If it's dropped without recording a
+/// capture, because the execution failed or couldn't be captured, it's handed
+/// back so that a later execution is captured instead.
+#[must_use]
+pub(crate) struct CaptureClaim<'a, T> {
+    captures: &'a ExecutionCaptures<T>,
+    instance_name: String,
+    path: CanonicalizedComponentFunctionPath,
+    request_id: u64,
+    recorded: bool,
+}
+
+impl<T> CaptureClaim<'_, T> {
+    pub(crate) fn record(mut self, capture: T) {
+        self.recorded = true;
+        self.captures
+            .record(&self.instance_name, &self.path, self.request_id, capture);
+    }
+}
+
+impl<T> Drop for CaptureClaim<'_, T> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.captures
+                .unclaim(&self.instance_name, &self.path, self.request_id);
+        }
+    }
+}
+
+impl<T> ExecutionCaptures<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            requests: Mutex::new(BTreeMap::new()),
+            next_request_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Capture the next `num_executions` executions of `path` in the
+    /// deployment `instance_name`. The returned receiver resolves once all of
+    /// them have finished. Use [`ExecutionCaptures::stop`] to give up early
+    /// and collect the captures gathered so far.
+    pub fn start(
+        &self,
+        instance_name: String,
+        path: CanonicalizedComponentFunctionPath,
+        num_executions: usize,
+    ) -> anyhow::Result<oneshot::Receiver<Vec<T>>> {
+        anyhow::ensure!(num_executions > 0, "Must capture at least one execution");
+        let mut requests = self.requests.lock();
+        let instance_requests = requests.entry(instance_name).or_default();
+        if instance_requests.contains_key(&path) {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "CaptureAlreadyInProgress",
+                format!("{} is already being captured", path.debug_str()),
+            ));
+        }
+        let (done, receiver) = oneshot::channel();
+        instance_requests.insert(
+            path,
+            CaptureRequest {
+                id: self.next_request_id.fetch_add(1, Ordering::Relaxed),
+                num_executions,
+                claimed: 0,
+                captures: vec![],
+                done,
+            },
+        );
+        Ok(receiver)
+    }
+
+    /// Stops capturing `path` in `instance_name`, returning the captures
+    /// collected so far.
+    pub fn stop(&self, instance_name: &str, path: &CanonicalizedComponentFunctionPath) -> Vec<T> {
+        Self::remove(&mut self.requests.lock(), instance_name, path)
+            .map(|request| request.captures)
+            .unwrap_or_default()
+    }
+
+    /// Returns a claim if the execution about to start should be captured,
+    /// counting it towards the requested number of executions.
+    pub(crate) fn claim(
+        &self,
+        instance_name: &str,
+        path: &CanonicalizedComponentFunctionPath,
+    ) -> Option<CaptureClaim<'_, T>> {
+        let mut requests = self.requests.lock();
+        let request = requests
+            .get_mut(instance_name)
+            .and_then(|instance_requests| instance_requests.get_mut(path))?;
+        if request.claimed >= request.num_executions {
+            return None;
+        }
+        request.claimed += 1;
+        Some(CaptureClaim {
+            captures: self,
+            instance_name: instance_name.to_string(),
+            path: path.clone(),
+            request_id: request.id,
+            recorded: false,
+        })
+    }
+
+    fn unclaim(&self, instance_name: &str, path: &CanonicalizedComponentFunctionPath, id: u64) {
+        let mut requests = self.requests.lock();
+        if let Some(request) = requests
+            .get_mut(instance_name)
+            .and_then(|instance_requests| instance_requests.get_mut(path))
+            && request.id == id
+        {
+            request.claimed -= 1;
+        }
+    }
+
+    fn record(
+        &self,
+        instance_name: &str,
+        path: &CanonicalizedComponentFunctionPath,
+        id: u64,
+        capture: T,
+    ) {
+        let mut requests = self.requests.lock();
+        let Some(request) = requests
+            .get_mut(instance_name)
+            .and_then(|instance_requests| instance_requests.get_mut(path))
+            .filter(|request| request.id == id)
+        else {
+            return;
+        };
+        request.captures.push(capture);
+        if request.captures.len() >= request.num_executions {
+            let request =
+                Self::remove(&mut requests, instance_name, path).expect("request disappeared");
+            // The caller may have stopped waiting.
+            let _ = request.done.send(request.captures);
+        }
+    }
+
+    fn remove(
+        requests: &mut BTreeMap<
+            String,
+            BTreeMap<CanonicalizedComponentFunctionPath, CaptureRequest<T>>,
+        >,
+        instance_name: &str,
+        path: &CanonicalizedComponentFunctionPath,
+    ) -> Option<CaptureRequest<T>> {
+        let instance_requests = requests.get_mut(instance_name)?;
+        let request = instance_requests.remove(path);
+        if instance_requests.is_empty() {
+            requests.remove(instance_name);
+        }
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    };
+
+    use super::ExecutionCaptures;
+
+    #[test]
+    fn test_collects_requested_executions() -> anyhow::Result<()> {
+        let captures = ExecutionCaptures::new();
+        let instance_name = "carnitas";
+        let path = CanonicalizedComponentFunctionPath {
+            component: ComponentPath::root(),
+            udf_path: "messages:send".parse()?,
+        };
+        let mut receiver = captures.start(instance_name.to_string(), path.clone(), 2)?;
+        assert!(captures
+            .start(instance_name.to_string(), path.clone(), 1)
+            .is_err());
+
+        // Executions of the same function in another deployment aren't
+        // captured.
+        assert!(captures.claim("alpastor", &path).is_none());
+
+        let first = captures.claim(instance_name, &path).unwrap();
+        let second = captures.claim(instance_name, &path).unwrap();
+        // Only the requested number of executions are captured.
+        assert!(captures.claim(instance_name, &path).is_none());
+
+        first.record(1);
+        assert!(receiver.try_recv().is_err());
+        second.record(2);
+        assert_eq!(receiver.try_recv()?, vec![1, 2]);
+
+        // Capturing is done, so the function isn't captured anymore.
+        assert!(captures.claim(instance_name, &path).is_none());
+        assert!(captures.stop(instance_name, &path).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dropped_claims_are_handed_back() -> anyhow::Result<()> {
+        let captures = ExecutionCaptures::new();
+        let instance_name = "carnitas";
+        let path = CanonicalizedComponentFunctionPath {
+            component: ComponentPath::root(),
+            udf_path: "messages:send".parse()?,
+        };
+        let mut receiver = captures.start(instance_name.to_string(), path.clone(), 1)?;
+
+        // An execution that fails before it's captured lets the next one be
+        // captured instead.
+        let failed = captures.claim(instance_name, &path).unwrap();
+        assert!(captures.claim(instance_name, &path).is_none());
+        drop(failed);
+        captures.claim(instance_name, &path).unwrap().record(1);
+        assert_eq!(receiver.try_recv()?, vec![1]);
+
+        // Claims on a request that's been stopped don't count towards a new
+        // one.
+        captures.start(instance_name.to_string(), path.clone(), 1)?;
+        let stale = captures.claim(instance_name, &path).unwrap();
+        assert!(captures.stop(instance_name, &path).is_empty());
+        let mut receiver = captures.start(instance_name.to_string(), path.clone(), 1)?;
+        drop(stale);
+        let claim = captures.claim(instance_name, &path).unwrap();
+        assert!(captures.claim(instance_name, &path).is_none());
+        claim.record(2);
+        assert_eq!(receiver.try_recv()?, vec![2]);
+        Ok(())
+    }
+}
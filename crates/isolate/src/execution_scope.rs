@@ -39,6 +39,7 @@ use value::heap_size::HeapSize;
 
 use crate::{
     bundled_js::system_udf_file,
+    code_cache::ModuleCodeCache,
     environment::IsolateEnvironment,
     helpers::{
         self,
@@ -334,11 +335,20 @@ impl<'a, 'b: 'a, RT: Runtime, E: IsolateEnvironment<RT>> ExecutionScope<'a, 'b,
                 .ok_or_else(|| anyhow!("Failed to create source string"))?;
 
             let origin = helpers::module_origin(self, name_str);
-            let v8_source = v8::script_compiler::Source::new(source_str, Some(&origin));
+            let code_cache = ModuleCodeCache::lookup(name, &source);
+            let (mut v8_source, options) = code_cache.source(source_str, &origin);
 
             let module = self
-                .with_try_catch(|s| v8::script_compiler::compile_module(s, v8_source))??
+                .with_try_catch(|s| {
+                    v8::script_compiler::compile_module2(
+                        s,
+                        &mut v8_source,
+                        options,
+                        v8::script_compiler::NoCacheReason::NoReason,
+                    )
+                })??
                 .ok_or_else(|| anyhow!("Unexpected module compilation error"))?;
+            code_cache.finish(self, &v8_source, module);
 
             assert_eq!(module.get_status(), v8::ModuleStatus::Uninstantiated);
             let mut import_specifiers = vec![];
@@ -0,0 +1,43 @@
+//! On-demand V8 heap snapshots of the isolates running a function.
+//!
+//! Isolates are reused across requests, so memory retained by one execution
+//! (e.g. a module-level cache that keeps growing) only shows up as the isolate
+//! eventually being recycled for running out of heap. An admin can request
+//! snapshots for the next N executions of a function through
+//! [`HEAP_SNAPSHOTTER`], and the isolate snapshots its whole heap right after
+//! each of those executions finishes. Snapshots are in the `.heapsnapshot`
+//! format understood by the Memory tab of Chrome DevTools.
+use std::sync::LazyLock;
+
+use common::components::CanonicalizedComponentFunctionPath;
+use deno_core::v8;
+
+use crate::execution_capture::ExecutionCaptures;
+
+pub static HEAP_SNAPSHOTTER: LazyLock<ExecutionCaptures<Vec<u8>>> =
+    LazyLock::new(ExecutionCaptures::new);
+
+/// Snapshots the isolate's heap if an admin has requested snapshots for
+/// `path` in the deployment `instance_name`. Call this after the execution has
+/// finished but before its context is dropped so the snapshot includes the
+/// function's module state.
+pub(crate) fn take_heap_snapshot_if_requested(
+    isolate: &mut v8::Isolate,
+    instance_name: &str,
+    path: &CanonicalizedComponentFunctionPath,
+) {
+    let Some(claim) = HEAP_SNAPSHOTTER.claim(instance_name, path) else {
+        return;
+    };
+    let mut snapshot = Vec::new();
+    isolate.take_heap_snapshot(|chunk| {
+        snapshot.extend_from_slice(chunk);
+        true
+    });
+    tracing::info!(
+        "Took {} byte heap snapshot for {}",
+        snapshot.len(),
+        path.debug_str()
+    );
+    claim.record(snapshot);
+}
@@ -36,6 +36,10 @@ pub struct HttpRequestV8 {
     pub url: String,
     pub method: String,
     pub stream_id: Option<uuid::Uuid>,
+    /// Set on an HTTP action's request if the client asked to upgrade it to a
+    /// WebSocket, which the action can accept with `upgradeWebSocket`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websocket_id: Option<uuid::Uuid>,
 }
 
 impl HttpRequestV8 {
@@ -66,6 +70,7 @@ impl HttpRequestV8 {
     pub fn from_request(
         request: HttpActionRequestHead,
         stream_id: Option<uuid::Uuid>,
+        websocket_id: Option<uuid::Uuid>,
     ) -> anyhow::Result<Self> {
         let mut header_pairs: Vec<(String, String)> = vec![];
 
@@ -83,6 +88,7 @@ impl HttpRequestV8 {
             url: request.url.to_string(),
             method: request.method.to_string(),
             stream_id,
+            websocket_id,
         })
     }
 }
@@ -33,6 +33,7 @@ use super::{
 };
 use crate::{
     bundled_js::system_udf_file,
+    code_cache::ModuleCodeCache,
     deserialize_udf_result,
     environment::helpers::{
         module_loader::module_specifier_from_path,
@@ -162,11 +163,20 @@ impl<'enter, 'scope: 'enter> EnteredContext<'enter, 'scope> {
             .ok_or_else(|| anyhow!("Failed to create source string"))?;
 
         let origin = helpers::module_origin(self.scope, name_str);
-        let v8_source = v8::script_compiler::Source::new(source_str, Some(&origin));
+        let code_cache = ModuleCodeCache::lookup(url, source);
+        let (mut v8_source, options) = code_cache.source(source_str, &origin);
 
         let module = self
-            .execute_user_code(|s| v8::script_compiler::compile_module(s, v8_source))?
+            .execute_user_code(|s| {
+                v8::script_compiler::compile_module2(
+                    s,
+                    &mut v8_source,
+                    options,
+                    v8::script_compiler::NoCacheReason::NoReason,
+                )
+            })?
             .ok_or_else(|| anyhow!("Unexpected module compilation error"))?;
+        code_cache.finish(self.scope, &v8_source, module);
 
         anyhow::ensure!(module.get_status() == v8::ModuleStatus::Uninstantiated);
         let mut import_specifiers: Vec<ModuleSpecifier> = vec![];
@@ -136,6 +136,7 @@ impl<RT: Runtime> FunctionRunnerIsolateWorker<RT> {
                         isolate,
                         isolate_clean,
                         request.params.clone(),
+                        request.replay,
                         oneshot_receiver_closed(&mut response).boxed(),
                     )
                     .await;
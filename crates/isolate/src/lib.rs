@@ -10,10 +10,14 @@
 
 pub mod bundled_js;
 pub mod client;
+mod code_cache;
 mod concurrency_limiter;
+pub mod cpu_profiler;
 pub mod environment;
 pub mod error;
+pub mod execution_capture;
 mod execution_scope;
+pub mod heap_snapshot;
 pub mod helpers;
 mod http;
 mod is_instance_of_error;
@@ -23,6 +27,7 @@ pub mod isolate_worker;
 pub mod metrics;
 pub mod module_map;
 mod ops;
+mod pool_autoscaler;
 mod request_scope;
 pub mod strings;
 mod termination;
@@ -97,6 +97,70 @@ pub fn log_pool_allocated_count(name: &'static str, count: usize) {
     );
 }
 
+register_convex_gauge!(
+    ISOLATE_POOL_TARGET_COUNT_INFO,
+    "How many isolate workers the pool is currently scaled to",
+    &["pool_name"]
+);
+pub fn log_pool_target_count(name: &'static str, count: usize) {
+    log_gauge_with_labels(
+        &ISOLATE_POOL_TARGET_COUNT_INFO,
+        count as f64,
+        vec![StaticMetricLabel::new("pool_name", name)],
+    );
+}
+
+register_convex_gauge!(
+    ISOLATE_POOL_QUEUE_DEPTH_INFO,
+    "How many requests are waiting for an isolate worker",
+    &["pool_name"]
+);
+pub fn log_pool_queue_depth(name: &'static str, depth: usize) {
+    log_gauge_with_labels(
+        &ISOLATE_POOL_QUEUE_DEPTH_INFO,
+        depth as f64,
+        vec![StaticMetricLabel::new("pool_name", name)],
+    );
+}
+
+register_convex_gauge!(
+    ISOLATE_POOL_CPU_PERCENT_INFO,
+    "Percentage of the machine's cores kept busy by isolate workers",
+    &["pool_name"]
+);
+pub fn log_pool_cpu_percent(name: &'static str, percent: usize) {
+    log_gauge_with_labels(
+        &ISOLATE_POOL_CPU_PERCENT_INFO,
+        percent as f64,
+        vec![StaticMetricLabel::new("pool_name", name)],
+    );
+}
+
+register_convex_counter!(
+    ISOLATE_POOL_WORKER_RETIRED_TOTAL,
+    "Number of idle isolate workers shut down when scaling the pool down",
+    &["pool_name"]
+);
+pub fn log_pool_worker_retired(name: &'static str) {
+    log_counter_with_labels(
+        &ISOLATE_POOL_WORKER_RETIRED_TOTAL,
+        1,
+        vec![StaticMetricLabel::new("pool_name", name)],
+    );
+}
+
+register_convex_counter!(
+    ISOLATE_POOL_ADMISSION_REJECTED_TOTAL,
+    "Number of requests rejected because the isolate pool was saturated"
+);
+pub fn pool_saturated_error() -> ErrorMetadata {
+    log_counter(&ISOLATE_POOL_ADMISSION_REJECTED_TOTAL, 1);
+    ErrorMetadata::overloaded(
+        "IsolatePoolSaturated",
+        "Too many concurrent requests, backoff and try again.",
+    )
+}
+
 pub fn is_developer_ok(outcome: &FunctionOutcome) -> bool {
     match &outcome {
         FunctionOutcome::Query(UdfOutcome { result, .. }) => result.is_ok(),
@@ -232,6 +296,35 @@ pub fn compile_module_timer() -> StatusTimer {
     StatusTimer::new(&UDF_ISOLATE_COMPILE_MODULE_SECONDS)
 }
 
+register_convex_counter!(
+    UDF_ISOLATE_CODE_CACHE_GET_TOTAL,
+    "Number of module code cache lookups",
+    &["hit"],
+);
+pub fn log_code_cache_get(hit: bool) {
+    log_counter_with_labels(
+        &UDF_ISOLATE_CODE_CACHE_GET_TOTAL,
+        1,
+        vec![StaticMetricLabel::new("hit", hit.as_label())],
+    );
+}
+
+register_convex_counter!(
+    UDF_ISOLATE_CODE_CACHE_REJECTED_TOTAL,
+    "Number of module code caches rejected by V8",
+);
+pub fn log_code_cache_rejected() {
+    log_counter(&UDF_ISOLATE_CODE_CACHE_REJECTED_TOTAL, 1);
+}
+
+register_convex_gauge!(
+    UDF_ISOLATE_CODE_CACHE_SIZE_BYTES,
+    "Total size of the module code cache in bytes",
+);
+pub fn log_code_cache_size(size: usize) {
+    log_gauge(&UDF_ISOLATE_CODE_CACHE_SIZE_BYTES, size as f64);
+}
+
 register_convex_histogram!(
     UDF_ISOLATE_INSTANTIATE_MODULE_SECONDS,
     "Time to instantiate the top-level module",
@@ -591,6 +684,29 @@ pub fn finish_udf_fetch_timer(t: StatusTimer, success: Result<usize, ()>) {
     log_counter_with_labels(&UDF_FETCH_TOTAL, 1, vec![status_label]);
 }
 
+register_convex_histogram!(
+    UDF_WEBSOCKET_CONNECT_SECONDS,
+    "Time to open a WebSocket connection from a UDF",
+    &STATUS_LABEL
+);
+pub fn udf_websocket_connect_timer() -> StatusTimer {
+    StatusTimer::new(&UDF_WEBSOCKET_CONNECT_SECONDS)
+}
+
+register_convex_counter!(
+    UDF_WEBSOCKET_MESSAGE_BYTES_TOTAL,
+    "Number of bytes sent and received over WebSockets in UDFs",
+    &["direction"]
+);
+pub fn log_udf_websocket_message(sent: bool, size: usize) {
+    let direction = if sent { "sent" } else { "received" };
+    log_counter_with_labels(
+        &UDF_WEBSOCKET_MESSAGE_BYTES_TOTAL,
+        size as u64,
+        vec![StaticMetricLabel::new("direction", direction)],
+    );
+}
+
 // Analyze counters
 register_convex_counter!(
     SOURCE_MAP_MISSING_TOTAL,
@@ -0,0 +1,134 @@
+//! AES Key Wrap (RFC 3394), used by `wrapKey` and `unwrapKey` with `AES-KW`.
+
+use aes::{
+    cipher::{
+        generic_array::GenericArray,
+        BlockDecrypt,
+        BlockEncrypt,
+        KeyInit,
+    },
+    Aes128,
+    Aes192,
+    Aes256,
+};
+use deno_core::ToJsBuffer;
+
+use super::{
+    shared::{
+        data_error,
+        operation_error,
+        AnyError,
+    },
+    CryptoOps,
+};
+
+/// The default initial value from RFC 3394 section 2.2.3.1.
+const DEFAULT_IV: [u8; 8] = [0xA6; 8];
+
+/// Number of wrapping rounds from RFC 3394 section 2.2.1.
+const ROUNDS: usize = 6;
+
+/// The key-encryption key.
+enum Kek {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+impl Kek {
+    fn new(key: &[u8]) -> Result<Self, AnyError> {
+        let kek = match key.len() {
+            16 => Kek::Aes128(Aes128::new(GenericArray::from_slice(key))),
+            24 => Kek::Aes192(Aes192::new(GenericArray::from_slice(key))),
+            32 => Kek::Aes256(Aes256::new(GenericArray::from_slice(key))),
+            _ => return Err(data_error("invalid AES-KW key length")),
+        };
+        Ok(kek)
+    }
+
+    fn encrypt_block(&self, block: &mut [u8; 16]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Kek::Aes128(cipher) => cipher.encrypt_block(block),
+            Kek::Aes192(cipher) => cipher.encrypt_block(block),
+            Kek::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8; 16]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Kek::Aes128(cipher) => cipher.decrypt_block(block),
+            Kek::Aes192(cipher) => cipher.decrypt_block(block),
+            Kek::Aes256(cipher) => cipher.decrypt_block(block),
+        }
+    }
+}
+
+fn xor_counter(a: &mut [u8; 8], t: usize) {
+    for (a, t) in a.iter_mut().zip((t as u64).to_be_bytes()) {
+        *a ^= t;
+    }
+}
+
+impl CryptoOps {
+    pub fn wrap_key_aes_kw(key: &[u8], data: &[u8]) -> Result<ToJsBuffer, AnyError> {
+        if data.len() < 16 || data.len() % 8 != 0 {
+            return Err(operation_error(
+                "AES-KW can only wrap keys that are a multiple of 64 bits and at least 128 bits",
+            ));
+        }
+        let kek = Kek::new(key)?;
+        let n = data.len() / 8;
+        let mut a = DEFAULT_IV;
+        let mut r: Vec<[u8; 8]> = data
+            .chunks_exact(8)
+            .map(|chunk| chunk.try_into().expect("chunks are 8 bytes"))
+            .collect();
+        let mut block = [0; 16];
+        for j in 0..ROUNDS {
+            for (i, r_i) in r.iter_mut().enumerate() {
+                block[..8].copy_from_slice(&a);
+                block[8..].copy_from_slice(r_i);
+                kek.encrypt_block(&mut block);
+                a.copy_from_slice(&block[..8]);
+                xor_counter(&mut a, n * j + i + 1);
+                r_i.copy_from_slice(&block[8..]);
+            }
+        }
+        let mut out = Vec::with_capacity(data.len() + 8);
+        out.extend_from_slice(&a);
+        for r_i in r {
+            out.extend_from_slice(&r_i);
+        }
+        Ok(out.into())
+    }
+
+    pub fn unwrap_key_aes_kw(key: &[u8], data: &[u8]) -> Result<ToJsBuffer, AnyError> {
+        if data.len() < 24 || data.len() % 8 != 0 {
+            return Err(operation_error("invalid AES-KW wrapped key length"));
+        }
+        let kek = Kek::new(key)?;
+        let n = data.len() / 8 - 1;
+        let mut a: [u8; 8] = data[..8].try_into().expect("checked length above");
+        let mut r: Vec<[u8; 8]> = data[8..]
+            .chunks_exact(8)
+            .map(|chunk| chunk.try_into().expect("chunks are 8 bytes"))
+            .collect();
+        let mut block = [0; 16];
+        for j in (0..ROUNDS).rev() {
+            for (i, r_i) in r.iter_mut().enumerate().rev() {
+                xor_counter(&mut a, n * j + i + 1);
+                block[..8].copy_from_slice(&a);
+                block[8..].copy_from_slice(r_i);
+                kek.decrypt_block(&mut block);
+                a.copy_from_slice(&block[..8]);
+                r_i.copy_from_slice(&block[8..]);
+            }
+        }
+        if a != DEFAULT_IV {
+            return Err(operation_error("AES-KW integrity check failed"));
+        }
+        Ok(r.concat().into())
+    }
+}
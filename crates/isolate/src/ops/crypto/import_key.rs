@@ -2,9 +2,14 @@
 // https://github.com/denoland/deno/blob/main/ext/crypto/import_key.rs
 
 use deno_core::ToJsBuffer;
-use elliptic_curve::pkcs8::PrivateKeyInfo;
-use p256::pkcs8::EncodePrivateKey;
-use ring::signature::EcdsaKeyPair;
+use elliptic_curve::{
+    pkcs8::PrivateKeyInfo,
+    sec1::ToEncodedPoint,
+};
+use p256::pkcs8::{
+    DecodePrivateKey,
+    EncodePrivateKey,
+};
 use rsa::{
     pkcs1::UintRef,
     pkcs8::der::Decode as RsaDecode,
@@ -32,10 +37,8 @@ use super::{
         ID_SECP521R1_OID,
         RSA_ENCRYPTION_OID,
     },
-    CryptoNamedCurve,
     CryptoOps,
 };
-use crate::ops::crypto::shared::secure_rng_unavailable;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -514,37 +517,33 @@ fn import_key_ec_jwk(
             })
         },
         KeyData::JwkPrivateEc { d, x, y } => {
-            jwt_b64_int_or_err!(private_d, &d, "invalid JWK private key");
             let point_bytes = import_key_ec_jwk_to_point(x, y, named_curve)?;
-            let pkcs8_der = match named_curve {
+            // Validate that the private key matches the public point. This
+            // doesn't use ring since its key pairs require a SecureRandom.
+            let (pkcs8_der, public_point) = match named_curve {
                 EcNamedCurve::P256 => {
                     let d = decode_b64url_to_field_bytes::<p256::NistP256>(&d)?;
-                    let pk = p256::SecretKey::from_bytes(&d)?;
-
-                    pk.to_pkcs8_der().map_err(|e| anyhow::anyhow!(e))?
+                    let pk = p256::SecretKey::from_bytes(&d)
+                        .map_err(|_| data_error("invalid JWK private key"))?;
+                    (
+                        pk.to_pkcs8_der().map_err(|e| anyhow::anyhow!(e))?,
+                        pk.public_key().to_encoded_point(false).as_bytes().to_vec(),
+                    )
                 },
                 EcNamedCurve::P384 => {
                     let d = decode_b64url_to_field_bytes::<p384::NistP384>(&d)?;
-                    let pk = p384::SecretKey::from_bytes(&d)?;
-
-                    pk.to_pkcs8_der().map_err(|e| anyhow::anyhow!(e))?
+                    let pk = p384::SecretKey::from_bytes(&d)
+                        .map_err(|_| data_error("invalid JWK private key"))?;
+                    (
+                        pk.to_pkcs8_der().map_err(|e| anyhow::anyhow!(e))?,
+                        pk.public_key().to_encoded_point(false).as_bytes().to_vec(),
+                    )
                 },
                 EcNamedCurve::P521 => return Err(data_error("Unsupported named curve")),
             };
-
-            // Import using ring, to validate key
-            let key_alg = match named_curve {
-                EcNamedCurve::P256 => CryptoNamedCurve::P256.into(),
-                EcNamedCurve::P384 => CryptoNamedCurve::P256.into(),
-                EcNamedCurve::P521 => return Err(data_error("Unsupported named curve")),
-            };
-
-            let _key_pair = EcdsaKeyPair::from_private_key_and_public_key(
-                key_alg,
-                private_d.as_bytes(),
-                point_bytes.as_ref(),
-                secure_rng_unavailable()?,
-            );
+            if public_point != point_bytes {
+                return Err(data_error("JWK private key does not match public key"));
+            }
 
             Ok(ImportKeyResult::Ec {
                 raw_data: RustRawKeyData::Private(pkcs8_der.as_bytes().to_vec().into()),
@@ -626,16 +625,16 @@ fn import_key_ec(
 
             // 10.
             if let Some(pk_named_curve) = pk_named_curve {
-                let signing_alg = match pk_named_curve {
-                    EcNamedCurve::P256 => CryptoNamedCurve::P256.into(),
-                    EcNamedCurve::P384 => CryptoNamedCurve::P384.into(),
+                // Deserialize the key to validate it. This doesn't use ring
+                // since its key pairs require a SecureRandom.
+                let valid = match pk_named_curve {
+                    EcNamedCurve::P256 => p256::SecretKey::from_pkcs8_der(&data).is_ok(),
+                    EcNamedCurve::P384 => p384::SecretKey::from_pkcs8_der(&data).is_ok(),
                     EcNamedCurve::P521 => return Err(data_error("Unsupported named curve")),
                 };
-
-                // deserialize pkcs8 using ring crate, to VALIDATE public key
-                let _private_key =
-                    EcdsaKeyPair::from_pkcs8(signing_alg, &data, secure_rng_unavailable()?)
-                        .map_err(|e| anyhow::anyhow!(e))?;
+                if !valid {
+                    return Err(data_error("invalid PKCS#8 private key"));
+                }
 
                 // 11.
                 if named_curve != pk_named_curve {
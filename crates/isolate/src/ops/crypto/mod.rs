@@ -1,6 +1,7 @@
 // Copyright 2018-2023 the Deno authors. All rights reserved. MIT license.
 // https://github.com/denoland/deno/blob/main/ext/crypto/key.rs
 
+mod aes_kw;
 mod ed25519;
 mod export_key;
 mod import_key;
@@ -11,6 +12,7 @@ use std::num::NonZeroU32;
 
 use anyhow::Context;
 use deno_core::ToJsBuffer;
+use p256::pkcs8::DecodePrivateKey;
 use rand::Rng;
 use ring::{
     agreement::Algorithm as RingAlgorithm,
@@ -150,6 +152,30 @@ pub fn op_crypto_derive_bits<'b, P: OpProvider<'b>>(
     CryptoOps::derive_bits(arg, salt.map(|b| b.into_vec()))
 }
 
+#[convex_macro::v8_op]
+pub fn op_crypto_wrap_key<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    arg: WrapUnwrapKeyArg,
+    data: ByteBuf,
+) -> anyhow::Result<ToJsBuffer> {
+    match arg.algorithm {
+        Algorithm::AesKw => CryptoOps::wrap_key_aes_kw(&arg.key.data, &data),
+        _ => Err(type_error("Unsupported algorithm")),
+    }
+}
+
+#[convex_macro::v8_op]
+pub fn op_crypto_unwrap_key<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    arg: WrapUnwrapKeyArg,
+    data: ByteBuf,
+) -> anyhow::Result<ToJsBuffer> {
+    match arg.algorithm {
+        Algorithm::AesKw => CryptoOps::unwrap_key_aes_kw(&arg.key.data, &data),
+        _ => Err(type_error("Unsupported algorithm")),
+    }
+}
+
 #[convex_macro::v8_op]
 pub fn op_crypto_digest<'b, P: OpProvider<'b>>(
     provider: &mut P,
@@ -380,12 +406,19 @@ pub struct DeriveKeyArg {
     length: usize,
     iterations: Option<u32>,
     // ECDH
-    // public_key: Option<KeyData>,
-    // named_curve: Option<CryptoNamedCurve>,
+    public_key: Option<KeyData>,
+    named_curve: Option<CryptoNamedCurve>,
     // HKDF
     // info: Option<ByteBuf>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WrapUnwrapKeyArg {
+    key: KeyData,
+    algorithm: Algorithm,
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub enum Algorithm {
     #[serde(rename = "RSASSA-PKCS1-v1_5")]
@@ -671,7 +704,56 @@ impl CryptoOps {
                 pbkdf2::derive(algorithm, iterations, &salt, &secret, &mut out);
                 Ok(out.into())
             },
-            Algorithm::Ecdh | Algorithm::Hkdf => anyhow::bail!("Signing algorithm not implemented"),
+            Algorithm::Ecdh => {
+                let public_key = args
+                    .public_key
+                    .ok_or_else(|| type_error("Missing public key"))?;
+                let secret = match args.named_curve.ok_or_else(not_supported)? {
+                    CryptoNamedCurve::P256 => {
+                        let secret_key = p256::SecretKey::from_pkcs8_der(&args.key.data)
+                            .map_err(|_| type_error("Unexpected error decoding private key"))?;
+                        let public_key = match public_key.r#type {
+                            KeyType::Private => p256::SecretKey::from_pkcs8_der(&public_key.data)
+                                .map_err(|_| type_error("Unexpected error decoding private key"))?
+                                .public_key(),
+                            KeyType::Public => p256::PublicKey::from_sec1_bytes(&public_key.data)
+                                .map_err(|_| {
+                                type_error("Unexpected error decoding public key")
+                            })?,
+                            KeyType::Secret => unreachable!("unexpected KeyType::Secret"),
+                        };
+                        p256::ecdh::diffie_hellman(
+                            secret_key.to_nonzero_scalar(),
+                            public_key.as_affine(),
+                        )
+                        .raw_secret_bytes()
+                        .to_vec()
+                    },
+                    CryptoNamedCurve::P384 => {
+                        let secret_key = p384::SecretKey::from_pkcs8_der(&args.key.data)
+                            .map_err(|_| type_error("Unexpected error decoding private key"))?;
+                        let public_key = match public_key.r#type {
+                            KeyType::Private => p384::SecretKey::from_pkcs8_der(&public_key.data)
+                                .map_err(|_| type_error("Unexpected error decoding private key"))?
+                                .public_key(),
+                            KeyType::Public => p384::PublicKey::from_sec1_bytes(&public_key.data)
+                                .map_err(|_| {
+                                type_error("Unexpected error decoding public key")
+                            })?,
+                            KeyType::Secret => unreachable!("unexpected KeyType::Secret"),
+                        };
+                        p384::ecdh::diffie_hellman(
+                            secret_key.to_nonzero_scalar(),
+                            public_key.as_affine(),
+                        )
+                        .raw_secret_bytes()
+                        .to_vec()
+                    },
+                };
+                // The caller truncates the secret to the requested length.
+                Ok(secret.into())
+            },
+            Algorithm::Hkdf => anyhow::bail!("Signing algorithm not implemented"),
             _ => Err(anyhow::anyhow!("Unsupported algorithm".to_string())),
         }
     }
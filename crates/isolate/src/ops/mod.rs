@@ -18,6 +18,7 @@ mod text;
 mod time;
 mod validate_args;
 mod validate_returns;
+mod websocket;
 
 use std::{
     collections::BTreeMap,
@@ -86,8 +87,10 @@ use self::{
         op_crypto_random_uuid,
         op_crypto_sign,
         op_crypto_sign_ed25519,
+        op_crypto_unwrap_key,
         op_crypto_verify,
         op_crypto_verify_ed25519,
+        op_crypto_wrap_key,
     },
     database::op_get_table_mapping_without_system_tables,
     environment_variables::op_environment_variables_get,
@@ -130,6 +133,12 @@ use self::{
         op_now,
     },
     validate_args::op_validate_args,
+    websocket::{
+        async_op_websocket_close,
+        async_op_websocket_connect,
+        async_op_websocket_receive,
+        async_op_websocket_send,
+    },
 };
 pub use self::{
     crypto::CryptoOps,
@@ -406,6 +415,8 @@ pub fn run_op<'b, P: OpProvider<'b>>(
         "crypto/verify" => op_crypto_verify(provider, args, rv)?,
         "crypto/verifyEd25519" => op_crypto_verify_ed25519(provider, args, rv)?,
         "crypto/deriveBits" => op_crypto_derive_bits(provider, args, rv)?,
+        "crypto/wrapKey" => op_crypto_wrap_key(provider, args, rv)?,
+        "crypto/unwrapKey" => op_crypto_unwrap_key(provider, args, rv)?,
         "crypto/digest" => op_crypto_digest(provider, args, rv)?,
         "crypto/importKey" => op_crypto_import_key(provider, args, rv)?,
         "crypto/importSpkiEd25519" => op_crypto_import_spki_ed25519(provider, args, rv)?,
@@ -454,6 +465,10 @@ pub fn start_async_op<'b, P: OpProvider<'b>>(
         "storage/store" => async_op_storage_store(provider, args, resolver)?,
         "storage/get" => async_op_storage_get(provider, args, resolver)?,
         "stream/readPart" => async_op_stream_read_part(provider, args, resolver)?,
+        "websocket/connect" => async_op_websocket_connect(provider, args, resolver)?,
+        "websocket/send" => async_op_websocket_send(provider, args, resolver)?,
+        "websocket/close" => async_op_websocket_close(provider, args, resolver)?,
+        "websocket/receive" => async_op_websocket_receive(provider, args, resolver)?,
         _ => {
             anyhow::bail!(ErrorMetadata::bad_request(
                 "UnknownAsyncOperation",
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use common::http::fetch::{
+    WebSocketMessage,
+    WebSocketRequest,
+};
+use deno_core::{
+    serde_v8,
+    v8,
+};
+use headers::HeaderName;
+use http::HeaderMap;
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+use url::Url;
+
+use super::OpProvider;
+use crate::environment::{
+    helpers::with_argument_error,
+    AsyncOpRequest,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebSocketRequestV8 {
+    url: String,
+    protocols: Vec<String>,
+    header_pairs: Vec<(String, String)>,
+}
+
+impl TryFrom<WebSocketRequestV8> for WebSocketRequest {
+    type Error = anyhow::Error;
+
+    fn try_from(request: WebSocketRequestV8) -> anyhow::Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &request.header_pairs {
+            headers.append(HeaderName::from_str(name.as_str())?, value.parse()?);
+        }
+        Ok(Self {
+            url: Url::parse(&request.url)?,
+            headers,
+            protocols: request.protocols,
+        })
+    }
+}
+
+pub fn async_op_websocket_connect<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    args: v8::FunctionCallbackArguments,
+    resolver: v8::Global<v8::PromiseResolver>,
+) -> anyhow::Result<()> {
+    let arg: WebSocketRequestV8 = serde_v8::from_v8(provider.scope(), args.get(1))?;
+    let request = with_argument_error("WebSocket", || arg.try_into())?;
+    provider.start_async_op(AsyncOpRequest::WebSocketConnect { request }, resolver)
+}
+
+pub fn async_op_websocket_send<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    args: v8::FunctionCallbackArguments,
+    resolver: v8::Global<v8::PromiseResolver>,
+) -> anyhow::Result<()> {
+    let socket_id: uuid::Uuid = serde_v8::from_v8(provider.scope(), args.get(1))?;
+    let data = args.get(2);
+    let message = if data.is_string() {
+        WebSocketMessage::Text(serde_v8::from_v8(provider.scope(), data)?)
+    } else {
+        let bytes: ByteBuf = serde_v8::from_v8(provider.scope(), data)?;
+        WebSocketMessage::Binary(bytes.into_vec().into())
+    };
+    provider.start_async_op(
+        AsyncOpRequest::WebSocketSend { socket_id, message },
+        resolver,
+    )
+}
+
+pub fn async_op_websocket_close<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    args: v8::FunctionCallbackArguments,
+    resolver: v8::Global<v8::PromiseResolver>,
+) -> anyhow::Result<()> {
+    let socket_id: uuid::Uuid = serde_v8::from_v8(provider.scope(), args.get(1))?;
+    let code: u16 = serde_v8::from_v8(provider.scope(), args.get(2))?;
+    let reason: String = serde_v8::from_v8(provider.scope(), args.get(3))?;
+    provider.start_async_op(
+        AsyncOpRequest::WebSocketSend {
+            socket_id,
+            message: WebSocketMessage::Close { code, reason },
+        },
+        resolver,
+    )
+}
+
+pub fn async_op_websocket_receive<'b, P: OpProvider<'b>>(
+    provider: &mut P,
+    args: v8::FunctionCallbackArguments,
+    resolver: v8::Global<v8::PromiseResolver>,
+) -> anyhow::Result<()> {
+    let socket_id: uuid::Uuid = serde_v8::from_v8(provider.scope(), args.get(1))?;
+    provider.start_async_op(AsyncOpRequest::WebSocketReceive { socket_id }, resolver)
+}
@@ -0,0 +1,195 @@
+//! Sizing policy for the shared isolate worker pool.
+//!
+//! Rather than eagerly growing to its maximum size, the pool keeps a target
+//! number of worker threads that it adjusts every
+//! [`ISOLATE_POOL_SCALE_INTERVAL`] based on how many requests are waiting and
+//! how busy the workers were. The scheduler also adjusts it as soon as a
+//! request finds every worker busy, so a burst doesn't have to wait for the
+//! next interval. Adding workers only helps while there are idle
+//! cores, so the pool stops growing once the workers' busy time approaches
+//! [`ISOLATE_POOL_TARGET_CPU_PERCENT`] of the machine's cores, and reports
+//! itself as saturated so new requests can be rejected up front instead of
+//! timing out in the queue.
+use std::{
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
+};
+
+use common::knobs::{
+    ISOLATE_POOL_ADMISSION_QUEUE_DEPTH,
+    ISOLATE_POOL_MIN_WORKERS,
+    ISOLATE_POOL_TARGET_CPU_PERCENT,
+};
+
+/// What the scheduler observed over the last scaling interval.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolSample {
+    /// Requests waiting for a worker at the end of the interval.
+    pub queue_depth: usize,
+    /// Workers running a request at the end of the interval.
+    pub active_workers: usize,
+    /// Total time workers spent running requests during the interval.
+    pub busy_time: Duration,
+    pub interval: Duration,
+}
+
+pub struct PoolAutoscaler {
+    min_workers: usize,
+    max_workers: usize,
+    target_workers: usize,
+    cores: usize,
+    target_cpu_percent: usize,
+    admission_queue_depth: usize,
+    saturated: Arc<AtomicBool>,
+}
+
+impl PoolAutoscaler {
+    pub fn new(max_workers: usize) -> Self {
+        let cores = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self::new_with_config(
+            (*ISOLATE_POOL_MIN_WORKERS).min(max_workers),
+            max_workers,
+            cores,
+            *ISOLATE_POOL_TARGET_CPU_PERCENT,
+            *ISOLATE_POOL_ADMISSION_QUEUE_DEPTH,
+        )
+    }
+
+    fn new_with_config(
+        min_workers: usize,
+        max_workers: usize,
+        cores: usize,
+        target_cpu_percent: usize,
+        admission_queue_depth: usize,
+    ) -> Self {
+        assert!(min_workers <= max_workers);
+        Self {
+            min_workers,
+            max_workers,
+            target_workers: min_workers.max(1).min(max_workers),
+            cores,
+            target_cpu_percent,
+            admission_queue_depth,
+            saturated: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn target_workers(&self) -> usize {
+        self.target_workers
+    }
+
+    /// Shared flag that's set while the pool can't take on more work. Checked
+    /// by [`crate::client::IsolateClient`] before enqueueing requests.
+    pub fn saturated(&self) -> Arc<AtomicBool> {
+        self.saturated.clone()
+    }
+
+    /// Percentage of the machine's cores the workers kept busy during the
+    /// sample's interval.
+    pub fn cpu_percent(&self, sample: &PoolSample) -> usize {
+        if sample.interval.is_zero() {
+            return 0;
+        }
+        let capacity = sample.interval.as_secs_f64() * self.cores as f64;
+        ((sample.busy_time.as_secs_f64() / capacity) * 100.0) as usize
+    }
+
+    /// Updates the target pool size from the last interval, returning the new
+    /// target.
+    pub fn update(&mut self, sample: PoolSample) -> usize {
+        let cpu_bound = self.cpu_percent(&sample) >= self.target_cpu_percent;
+        let all_busy = sample.active_workers >= self.target_workers;
+        if sample.queue_depth > 0 && all_busy {
+            if !cpu_bound {
+                // Grow by the number of waiting requests, but at most double
+                // the pool each interval so a burst doesn't spawn threads we
+                // won't need.
+                let step = sample.queue_depth.min(self.target_workers.max(1));
+                self.target_workers = (self.target_workers + step).min(self.max_workers);
+            }
+        } else if sample.queue_depth == 0 && sample.active_workers * 2 < self.target_workers {
+            // Shed half of the idle workers each interval.
+            let idle = self.target_workers - sample.active_workers;
+            self.target_workers = (self.target_workers - idle.div_ceil(2)).max(self.min_workers);
+        }
+        let can_grow = self.target_workers < self.max_workers && !cpu_bound;
+        let saturated = !can_grow && sample.queue_depth >= self.admission_queue_depth;
+        self.saturated.store(saturated, Ordering::Relaxed);
+        self.target_workers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::Ordering,
+        time::Duration,
+    };
+
+    use super::{
+        PoolAutoscaler,
+        PoolSample,
+    };
+
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    fn sample(queue_depth: usize, active_workers: usize, busy_millis: u64) -> PoolSample {
+        PoolSample {
+            queue_depth,
+            active_workers,
+            busy_time: Duration::from_millis(busy_millis),
+            interval: INTERVAL,
+        }
+    }
+
+    #[test]
+    fn test_scales_with_queue_depth() {
+        let mut autoscaler = PoolAutoscaler::new_with_config(2, 16, 8, 80, 10);
+        assert_eq!(autoscaler.target_workers(), 2);
+        // At most doubles per interval.
+        assert_eq!(autoscaler.update(sample(100, 2, 2000)), 4);
+        assert_eq!(autoscaler.update(sample(3, 4, 4000)), 7);
+        // Doesn't grow while requests are being served without queueing.
+        assert_eq!(autoscaler.update(sample(0, 7, 6000)), 7);
+        // Shrinks gradually once idle, down to the minimum.
+        assert_eq!(autoscaler.update(sample(0, 1, 1000)), 4);
+        assert_eq!(autoscaler.update(sample(0, 0, 0)), 2);
+        assert_eq!(autoscaler.update(sample(0, 0, 0)), 2);
+    }
+
+    #[test]
+    fn test_scales_up_on_short_interval() {
+        let mut autoscaler = PoolAutoscaler::new_with_config(2, 16, 8, 80, 10);
+        // A request found both workers busy a few milliseconds into the
+        // interval. There are idle cores, so the pool grows right away.
+        let sample = PoolSample {
+            queue_depth: 1,
+            active_workers: 2,
+            busy_time: Duration::from_millis(10),
+            interval: Duration::from_millis(5),
+        };
+        assert_eq!(autoscaler.update(sample), 3);
+    }
+
+    #[test]
+    fn test_stops_growing_when_cpu_bound() {
+        let mut autoscaler = PoolAutoscaler::new_with_config(4, 16, 4, 80, 10);
+        let saturated = autoscaler.saturated();
+        // Four busy workers on four cores: more threads won't help.
+        assert_eq!(autoscaler.update(sample(5, 4, 4000)), 4);
+        assert!(!saturated.load(Ordering::Relaxed));
+        assert_eq!(autoscaler.update(sample(20, 4, 4000)), 4);
+        assert!(saturated.load(Ordering::Relaxed));
+        // Workers blocked on I/O leave room to grow.
+        assert_eq!(autoscaler.update(sample(20, 4, 1000)), 8);
+        assert!(!saturated.load(Ordering::Relaxed));
+    }
+}
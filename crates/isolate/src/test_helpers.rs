@@ -95,7 +95,10 @@ use model::{
         types::FileStorageEntry,
         FileStorageId,
     },
-    scheduled_jobs::VirtualSchedulerModel,
+    scheduled_jobs::{
+        types::ScheduleOptions,
+        VirtualSchedulerModel,
+    },
     source_packages::{
         types::SourcePackage,
         upload_download::upload_package,
@@ -132,6 +135,7 @@ use udf::{
         ValidatedPathAndArgs,
     },
     ActionOutcome,
+    ExecutionRecording,
     FunctionOutcome,
     FunctionResult,
     HttpActionRequest,
@@ -235,6 +239,7 @@ pub fn test_environment_data<RT: Runtime>(rt: RT) -> anyhow::Result<EnvironmentD
     Ok(EnvironmentData {
         key_broker,
         system_env_vars,
+        env_var_overrides: BTreeMap::new(),
         file_storage,
         module_loader,
     })
@@ -310,6 +315,7 @@ impl<RT: Runtime, P: Persistence> UdfTest<RT, P> {
                 CONVEX_ORIGIN.clone() => "https://carnitas.convex.cloud".parse()?,
                 CONVEX_SITE.clone() => "https://carnitas.convex.site".parse()?
             },
+            env_var_overrides: BTreeMap::new(),
             file_storage: file_storage.clone(),
             module_loader: module_loader.clone(),
         };
@@ -367,6 +373,8 @@ impl<RT: Runtime, P: Persistence> UdfTest<RT, P> {
                     sha256,
                     package_size,
                     external_deps_package_id: None,
+                    node_version: None,
+                    dependency_layers: vec![],
                 }),
                 analyze_results,
                 None,
@@ -1075,13 +1083,43 @@ impl<RT: Runtime, P: Persistence> UdfTest<RT, P> {
         args: Vec<ConvexValue>,
         identity: Identity,
     ) -> anyhow::Result<(ActionOutcome, LogLines)> {
-        let mut tx = self.database.begin(identity.clone()).await?;
         let path = ComponentFunctionPath {
             component: ComponentPath::test_user(),
             udf_path: udf_path.parse()?,
         };
-        let canonicalized_path = path.canonicalize();
-        let args_array = ConvexArray::try_from(args)?;
+        self.run_action(
+            path.canonicalize(),
+            ConvexArray::try_from(args)?,
+            identity,
+            None,
+        )
+        .await
+    }
+
+    /// Replays a recording of an action, without running its fetches or
+    /// other async operations.
+    pub async fn replay_action(
+        &self,
+        recording: ExecutionRecording,
+        identity: Identity,
+    ) -> anyhow::Result<(ActionOutcome, LogLines)> {
+        self.run_action(
+            recording.path.clone(),
+            recording.arguments.clone(),
+            identity,
+            Some(recording),
+        )
+        .await
+    }
+
+    async fn run_action(
+        &self,
+        canonicalized_path: CanonicalizedComponentFunctionPath,
+        args_array: ConvexArray,
+        identity: Identity,
+        replay: Option<ExecutionRecording>,
+    ) -> anyhow::Result<(ActionOutcome, LogLines)> {
+        let mut tx = self.database.begin(identity.clone()).await?;
         let validated_path_or_err = ValidatedPathAndArgs::new(
             AllowedVisibility::PublicOnly,
             &mut tx,
@@ -1110,19 +1148,37 @@ impl<RT: Runtime, P: Persistence> UdfTest<RT, P> {
         let (log_line_sender, mut log_line_receiver) = mpsc::unbounded_channel();
 
         // TODO(presley): Make this also be able to use local executor.
-        let outcome = self
-            .isolate
-            .execute_action(
-                path_and_args,
-                tx,
-                Arc::new(self.clone()),
-                fetch_client,
-                log_line_sender,
-                ExecutionContext::new_for_test(),
-                self.environment_data.clone(),
-                DEV_INSTANCE_NAME.to_string(),
-            )
-            .await?;
+        let outcome = match replay {
+            Some(recording) => {
+                self.isolate
+                    .replay_action(
+                        path_and_args,
+                        tx,
+                        Arc::new(self.clone()),
+                        fetch_client,
+                        log_line_sender,
+                        ExecutionContext::new_for_test(),
+                        self.environment_data.clone(),
+                        DEV_INSTANCE_NAME.to_string(),
+                        recording,
+                    )
+                    .await?
+            },
+            None => {
+                self.isolate
+                    .execute_action(
+                        path_and_args,
+                        tx,
+                        Arc::new(self.clone()),
+                        fetch_client,
+                        log_line_sender,
+                        ExecutionContext::new_for_test(),
+                        self.environment_data.clone(),
+                        DEV_INSTANCE_NAME.to_string(),
+                    )
+                    .await?
+            },
+        };
         let mut log_lines = vec![];
         while let Some(log_line) = log_line_receiver.recv().await {
             log_lines.push(log_line);
@@ -1373,6 +1429,7 @@ impl<RT: Runtime, P: Persistence> ActionCallbacks for UdfTest<RT, P> {
         udf_args: Vec<JsonValue>,
         scheduled_ts: UnixTimestamp,
         context: ExecutionContext,
+        options: ScheduleOptions,
     ) -> anyhow::Result<DeveloperDocumentId> {
         let mut tx: database::Transaction<RT> = self.database.begin(identity).await?;
         let (scheduled_path, udf_args) = validate_schedule_args(
@@ -1387,7 +1444,7 @@ impl<RT: Runtime, P: Persistence> ActionCallbacks for UdfTest<RT, P> {
         .await?;
 
         let virtual_id = VirtualSchedulerModel::new(&mut tx, scheduling_component.into())
-            .schedule(scheduled_path, udf_args, scheduled_ts, context)
+            .schedule(scheduled_path, udf_args, scheduled_ts, context, options)
             .await?;
         self.database.commit(tx).await?;
 
@@ -1456,6 +1513,7 @@ pub async fn bogus_udf_request<RT: Runtime>(
         transaction: tx,
         journal: QueryJournal::new(),
         context: ExecutionContext::new_for_test(),
+        replay: None,
     };
     let inner = RequestType::Udf {
         request,
@@ -3,6 +3,13 @@ use std::{
         Ipv4Addr,
         SocketAddrV4,
     },
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
     time::Duration,
 };
 
@@ -17,6 +24,10 @@ use axum::{
 };
 use common::{
     assert_obj,
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    },
     http::{
         ConvexHttpService,
         NoopRouteMapper,
@@ -30,16 +41,24 @@ use http::{
 };
 use http_body_util::BodyExt;
 use itertools::Itertools;
-use keybroker::Identity;
+use keybroker::{
+    Identity,
+    DEV_INSTANCE_NAME,
+};
 use must_let::must_let;
 use runtime::{
     prod::ProdRuntime,
     testing::TestRuntime,
 };
 use serde_json::json;
+use udf::{
+    ExecutionRecording,
+    SerializedExecutionRecording,
+};
 use value::ConvexValue;
 
 use crate::{
+    environment::udf::recording::EXECUTION_RECORDER,
     test_helpers::UdfTest,
     tests::http_action::{
         http_post_request,
@@ -318,3 +337,38 @@ async fn test_fetch_timing(rt: ProdRuntime) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[convex_macro::prod_rt_test]
+async fn test_record_and_replay_fetch(rt: ProdRuntime) -> anyhow::Result<()> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let requests_ = requests.clone();
+    let router = Router::new().route(
+        "/recorded",
+        get(move || {
+            let requests = requests_.clone();
+            async move { format!("response {}", requests.fetch_add(1, Ordering::SeqCst)) }
+        }),
+    );
+    rt.spawn("test_router", serve(router, 4548));
+
+    let t = UdfTest::default(rt).await?;
+    let path = CanonicalizedComponentFunctionPath {
+        component: ComponentPath::test_user(),
+        udf_path: "fetch:fetchForRecording".parse()?,
+    };
+    let receiver = EXECUTION_RECORDER.start(DEV_INSTANCE_NAME.to_string(), path, 1)?;
+    let result = t.action("fetch:fetchForRecording", assert_obj!()).await?;
+    let mut recordings = receiver.await?;
+    assert_eq!(recordings.len(), 1);
+    let serialized: SerializedExecutionRecording = recordings.remove(0).try_into()?;
+    let recording: ExecutionRecording =
+        serde_json::from_str::<SerializedExecutionRecording>(&serde_json::to_string(&serialized)?)?
+            .try_into()?;
+
+    // The replay sees the recorded response, random numbers and time without
+    // fetching again.
+    let (outcome, _) = t.replay_action(recording, Identity::system()).await?;
+    assert_eq!(outcome.result?.unpack(), result);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use common::{
+    assert_obj,
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    },
+};
+use keybroker::DEV_INSTANCE_NAME;
+use runtime::testing::TestRuntime;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    heap_snapshot::HEAP_SNAPSHOTTER,
+    test_helpers::{
+        UdfTest,
+        UdfTestType,
+    },
+};
+
+#[convex_macro::test_runtime]
+async fn test_heap_snapshot_after_execution(rt: TestRuntime) -> anyhow::Result<()> {
+    UdfTest::run_test_with_isolate(rt, async move |t: UdfTestType| {
+        let path = CanonicalizedComponentFunctionPath {
+            component: ComponentPath::test_user(),
+            udf_path: "js:addOneInt".parse()?,
+        };
+        let receiver = HEAP_SNAPSHOTTER.start(DEV_INSTANCE_NAME.to_string(), path, 1)?;
+        t.query("js:addOneInt", assert_obj!("x" => 1)).await?;
+        let snapshots = receiver.await?;
+        assert_eq!(snapshots.len(), 1);
+
+        // Snapshots are in the JSON `.heapsnapshot` format DevTools loads.
+        let snapshot: JsonValue = serde_json::from_slice(&snapshots[0])?;
+        let node_count = snapshot["snapshot"]["node_count"]
+            .as_u64()
+            .expect("missing node count");
+        assert!(node_count > 0);
+        assert!(snapshot["nodes"].is_array());
+        assert!(snapshot["strings"]
+            .as_array()
+            .expect("missing strings")
+            .contains(&JsonValue::from("addOneInt")));
+        Ok(())
+    })
+    .await
+}
+
+#[convex_macro::test_runtime]
+async fn test_heap_snapshot_scoped_to_instance(rt: TestRuntime) -> anyhow::Result<()> {
+    UdfTest::run_test_with_isolate(rt, async move |t: UdfTestType| {
+        let path = CanonicalizedComponentFunctionPath {
+            component: ComponentPath::test_user(),
+            udf_path: "basic:readTimeMs".parse()?,
+        };
+        let mut receiver = HEAP_SNAPSHOTTER.start("alpastor".to_string(), path.clone(), 1)?;
+        t.query("basic:readTimeMs", assert_obj!()).await?;
+        // The function ran in another deployment, so it wasn't snapshotted.
+        assert!(receiver.try_recv().is_err());
+        assert!(HEAP_SNAPSHOTTER.stop("alpastor", &path).is_empty());
+        Ok(())
+    })
+    .await
+}
@@ -6,6 +6,7 @@ use std::{
 use common::{
     assert_obj,
     errors::JsError,
+    http::fetch::WebSocketMessage,
     runtime::Runtime,
     testing::{
         assert_contains,
@@ -15,6 +16,7 @@ use common::{
 };
 use futures::{
     stream,
+    SinkExt,
     StreamExt,
 };
 use headers::HeaderMap;
@@ -38,11 +40,13 @@ use serde_json::{
     Value as JsonValue,
 };
 use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use udf::{
     HttpActionRequest,
     HttpActionRequestHead,
     HttpActionResponseStreamer,
     HttpActionResult,
+    HttpActionWebSocket,
 };
 use url::Url;
 use value::ConvexValue;
@@ -64,6 +68,7 @@ pub fn http_request(path: &str) -> HttpActionRequest {
             method: Method::GET,
         },
         body: None,
+        websocket: None,
     }
 }
 
@@ -75,6 +80,7 @@ pub fn http_post_request(path: &str, body: Vec<u8>) -> HttpActionRequest {
             method: Method::POST,
         },
         body: Some(stream::once(async move { Ok(body.into()) }).boxed()),
+        websocket: None,
     }
 }
 
@@ -181,6 +187,36 @@ async fn test_http_slow(rt: TestRuntime) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[convex_macro::test_runtime]
+async fn test_http_websocket_upgrade(rt: TestRuntime) -> anyhow::Result<()> {
+    let t = http_action_udf_test(rt).await?;
+
+    let (to_action, action_stream) = mpsc::unbounded_channel();
+    let (action_sink, mut from_action) = futures::channel::mpsc::unbounded();
+    let mut request = http_request("websocket_echo");
+    request.websocket = Some(HttpActionWebSocket {
+        sink: Box::pin(action_sink.sink_map_err(anyhow::Error::from)),
+        stream: UnboundedReceiverStream::new(action_stream).boxed(),
+    });
+    to_action.send(Ok(WebSocketMessage::Text("hi".to_string())))?;
+    let client = async {
+        let echo = from_action.next().await;
+        // The action keeps running until the connection is closed.
+        to_action.send(Ok(WebSocketMessage::Close {
+            code: 1000,
+            reason: String::new(),
+        }))?;
+        anyhow::Ok(echo)
+    };
+    let (response, echo) = tokio::try_join!(
+        t.http_action("http_action", request, Identity::system()),
+        client
+    )?;
+    assert_eq!(response.status, StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(echo, Some(WebSocketMessage::Text("echo: hi".to_string())));
+    Ok(())
+}
+
 #[convex_macro::test_runtime]
 async fn test_http_echo(rt: TestRuntime) -> anyhow::Result<()> {
     let t = http_action_udf_test(rt).await?;
@@ -11,6 +11,7 @@ mod custom_errors;
 mod environment_variables;
 mod fetch;
 mod globals;
+mod heap_snapshot;
 mod http_action;
 mod id_encoding;
 mod id_strings;
@@ -20,6 +21,7 @@ mod js_builtins;
 mod logging;
 mod module_loader;
 mod query;
+mod recording;
 mod scheduler;
 mod schema;
 mod search;
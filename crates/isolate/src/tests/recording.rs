@@ -0,0 +1,41 @@
+use common::{
+    assert_obj,
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    },
+};
+use keybroker::DEV_INSTANCE_NAME;
+use runtime::testing::TestRuntime;
+
+use crate::{
+    environment::udf::recording::EXECUTION_RECORDER,
+    test_helpers::{
+        UdfTest,
+        UdfTestType,
+    },
+};
+
+#[convex_macro::test_runtime]
+async fn test_recording_scoped_to_instance(rt: TestRuntime) -> anyhow::Result<()> {
+    UdfTest::run_test_with_isolate(rt, async move |t: UdfTestType| {
+        let path = CanonicalizedComponentFunctionPath {
+            component: ComponentPath::test_user(),
+            udf_path: "basic:readTime".parse()?,
+        };
+        // Each deployment can record the same function independently.
+        let receiver = EXECUTION_RECORDER.start(DEV_INSTANCE_NAME.to_string(), path.clone(), 1)?;
+        let mut other_receiver =
+            EXECUTION_RECORDER.start("alpastor".to_string(), path.clone(), 1)?;
+        t.query("basic:readTime", assert_obj!()).await?;
+
+        let recordings = receiver.await?;
+        assert_eq!(recordings.len(), 1);
+        assert_eq!(recordings[0].path, path);
+        // The execution only counts towards the deployment it ran in.
+        assert!(other_receiver.try_recv().is_err());
+        assert!(EXECUTION_RECORDER.stop("alpastor", &path).is_empty());
+        Ok(())
+    })
+    .await
+}
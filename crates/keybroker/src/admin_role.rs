@@ -0,0 +1,81 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use errors::ErrorMetadata;
+
+/// What a team member is allowed to do with their admin key. Each role can do
+/// everything the roles before it can:
+///
+/// - `viewer`: read data, logs and configuration in the dashboard and CLI.
+/// - `deployer`: push code, schemas and indexes.
+/// - `operator`: run mutations and actions, edit data and environment
+///   variables, import and export, and cancel scheduled jobs.
+/// - `owner`: manage roles, API keys and IP access rules.
+///
+/// Until the first role is assigned every member is an owner, and keys that
+/// aren't issued to a member, like access tokens, always are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AdminRole {
+    Viewer,
+    Deployer,
+    Operator,
+    Owner,
+}
+
+impl AdminRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Deployer => "deployer",
+            AdminRole::Operator => "operator",
+            AdminRole::Owner => "owner",
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AdminRole {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let role = match s {
+            "viewer" => AdminRole::Viewer,
+            "deployer" => AdminRole::Deployer,
+            "operator" => AdminRole::Operator,
+            "owner" => AdminRole::Owner,
+            _ => anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidAdminRole",
+                format!("Invalid role {s:?}: expected `viewer`, `deployer`, `operator` or `owner`"),
+            )),
+        };
+        Ok(role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AdminRole;
+
+    #[test]
+    fn test_admin_roles() -> anyhow::Result<()> {
+        let roles = [
+            AdminRole::Viewer,
+            AdminRole::Deployer,
+            AdminRole::Operator,
+            AdminRole::Owner,
+        ];
+        for role in roles {
+            assert_eq!(role.as_str().parse::<AdminRole>()?, role);
+        }
+        assert!(roles.is_sorted());
+        assert!("admin".parse::<AdminRole>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use errors::ErrorMetadata;
+use sync_types::CanonicalizedUdfPath;
+
+/// Module of the system UDFs behind the REST API, which API keys can call
+/// without the `admin` scope since they only touch user tables.
+const REST_API_MODULE: &str = "_system/rest.js";
+
+/// A credential for scripts and services, created by an admin with
+/// `KeyBroker::issue_api_key`. Unlike admin keys, API keys can be limited in
+/// what they can do, expire, and can be revoked.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ApiKey {
+    /// ID of the key's `_api_keys` document.
+    pub id: String,
+    pub scopes: ApiKeyScopes,
+}
+
+/// What an API key is allowed to do, parsed from the list of scopes it was
+/// created with:
+///
+/// - `admin`: anything an admin key can do, including calling the admin
+///   endpoints. Can't be combined with function or table scopes.
+/// - `read_only`: run queries and read data, but not run mutations or actions.
+/// - `function:<path>` (e.g. `function:messages:send`): run this function in
+///   the root component. Keys with function scopes can't run other functions.
+/// - `table:<name>`: read and write this table. Keys with table scopes can't
+///   access other tables, including from the functions they run.
+///
+/// Keys without the `admin` scope can run functions and use the REST API.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ApiKeyScopes {
+    admin: bool,
+    read_only: bool,
+    functions: Option<BTreeSet<CanonicalizedUdfPath>>,
+    tables: Option<BTreeSet<String>>,
+}
+
+impl ApiKeyScopes {
+    pub fn parse<S: AsRef<str>>(scopes: impl IntoIterator<Item = S>) -> anyhow::Result<Self> {
+        let mut parsed = Self::default();
+        for scope in scopes {
+            let scope = scope.as_ref();
+            match scope.split_once(':') {
+                None if scope == "admin" => parsed.admin = true,
+                None if scope == "read_only" => parsed.read_only = true,
+                Some(("function", path)) => {
+                    let path: CanonicalizedUdfPath = path
+                        .parse()
+                        .with_context(|| invalid_scope(scope, "Invalid function path"))?;
+                    parsed.functions.get_or_insert_default().insert(path);
+                },
+                Some(("table", table)) => {
+                    anyhow::ensure!(
+                        !table.is_empty() && !table.starts_with('_'),
+                        invalid_scope(scope, "Only user tables can be scoped to")
+                    );
+                    parsed
+                        .tables
+                        .get_or_insert_default()
+                        .insert(table.to_string());
+                },
+                _ => anyhow::bail!(invalid_scope(
+                    scope,
+                    "Expected `admin`, `read_only`, `function:<path>` or `table:<name>`"
+                )),
+            }
+        }
+        anyhow::ensure!(
+            !parsed.admin || (parsed.functions.is_none() && parsed.tables.is_none()),
+            ErrorMetadata::bad_request(
+                "InvalidApiKeyScope",
+                "The `admin` scope can't be combined with function or table scopes",
+            )
+        );
+        Ok(parsed)
+    }
+
+    pub fn to_strings(&self) -> Vec<String> {
+        let mut scopes = vec![];
+        if self.admin {
+            scopes.push("admin".to_string());
+        }
+        if self.read_only {
+            scopes.push("read_only".to_string());
+        }
+        for path in self.functions.iter().flatten() {
+            scopes.push(format!("function:{path}"));
+        }
+        for table in self.tables.iter().flatten() {
+            scopes.push(format!("table:{table}"));
+        }
+        scopes
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn allows_function(&self, is_root_component: bool, path: &CanonicalizedUdfPath) -> bool {
+        if self.admin {
+            return true;
+        }
+        if path.is_system() {
+            return path.module().as_str() == REST_API_MODULE;
+        }
+        match &self.functions {
+            Some(functions) => is_root_component && functions.contains(path),
+            None => true,
+        }
+    }
+
+    pub fn allows_table(&self, table: &str) -> bool {
+        self.tables
+            .as_ref()
+            .is_none_or(|tables| tables.contains(table))
+    }
+}
+
+fn invalid_scope(scope: &str, reason: &str) -> ErrorMetadata {
+    ErrorMetadata::bad_request(
+        "InvalidApiKeyScope",
+        format!("Invalid API key scope {scope:?}: {reason}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiKeyScopes;
+
+    #[test]
+    fn test_api_key_scopes() -> anyhow::Result<()> {
+        let scopes =
+            ApiKeyScopes::parse(["read_only", "function:messages:list", "table:messages"])?;
+        assert!(!scopes.is_admin());
+        assert!(scopes.is_read_only());
+        assert!(scopes.allows_function(true, &"messages.js:list".parse()?));
+        assert!(!scopes.allows_function(false, &"messages.js:list".parse()?));
+        assert!(!scopes.allows_function(true, &"messages.js:send".parse()?));
+        assert!(scopes.allows_function(true, &"_system/rest.js:list".parse()?));
+        assert!(!scopes.allows_function(true, &"_system/frontend/listById.js".parse()?));
+        assert!(scopes.allows_table("messages"));
+        assert!(!scopes.allows_table("users"));
+        assert_eq!(
+            scopes.to_strings(),
+            ["read_only", "function:messages.js:list", "table:messages"]
+        );
+        assert_eq!(ApiKeyScopes::parse(scopes.to_strings())?, scopes);
+
+        let admin = ApiKeyScopes::parse(["admin"])?;
+        assert!(admin.allows_function(true, &"_system/frontend/listById.js".parse()?));
+        assert!(admin.allows_table("users"));
+
+        assert!(ApiKeyScopes::parse(["admin", "table:messages"]).is_err());
+        assert!(ApiKeyScopes::parse(["table:_storage"]).is_err());
+        assert!(ApiKeyScopes::parse(["function:"]).is_err());
+        assert!(ApiKeyScopes::parse(["superuser"]).is_err());
+        Ok(())
+    }
+}
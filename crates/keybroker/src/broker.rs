@@ -11,7 +11,10 @@ use std::{
 use anyhow::Context;
 pub use common::types::SystemKey;
 use common::{
-    components::ComponentId,
+    components::{
+        ComponentId,
+        PublicFunctionPath,
+    },
     identity::{
         IdentityCacheKey,
         InertIdentity,
@@ -33,6 +36,7 @@ use common::{
         split_admin_key,
         ActionCallbackToken,
         AdminKey,
+        EnvVarValue,
         MemberId,
         PersistenceVersion,
         TeamId,
@@ -65,6 +69,7 @@ use pb::{
             StoreFile as StoreFileProto,
         },
         AdminKey as AdminKeyProto,
+        ApiKey as ApiKeyProto,
         StorageToken as StorageTokenProto,
     },
     convex_query_journal::InstanceQueryJournal as InstanceQueryJournalProto,
@@ -88,6 +93,11 @@ use sync_types::{
 #[cfg(any(test, feature = "testing"))]
 use crate::testing::TestUserIdentity;
 use crate::{
+    admin_role::AdminRole,
+    api_key::{
+        ApiKey,
+        ApiKeyScopes,
+    },
     encryptor::Encryptor,
     metrics::{
         log_actions_token_expired,
@@ -99,9 +109,15 @@ use crate::{
 const ACTION_KEY_VERSION: u8 = 2;
 const ADMIN_KEY_VERSION: u8 = 1;
 const CURSOR_VERSION: u8 = 7;
+const ENV_SECRET_VERSION: u8 = 1;
+const EXPORT_SECRET_VERSION: u8 = 1;
 const STORE_FILE_AUTHZ_VERSION: u8 = 1;
 const QUERY_JOURNAL_VERSION: u8 = 7;
 
+const ANONYMOUS_IDENTITY_KEY_CONTEXT: &[u8] = b"anonymous-identity-tokens";
+const SAML_TOKEN_KEY_CONTEXT: &[u8] = b"saml-tokens";
+const TOKEN_EXCHANGE_KEY_CONTEXT: &[u8] = b"token-exchange-tokens";
+
 // Max delay from transaction start time -> key being issued that is tolerable.
 const MAX_TS_DELAY: Duration = Duration::from_secs(15);
 
@@ -131,9 +147,7 @@ pub enum Identity {
 impl From<Identity> for AuthenticationToken {
     fn from(i: Identity) -> Self {
         match i {
-            Identity::User(identity) => {
-                AuthenticationToken::User(identity.original_token.to_string())
-            },
+            Identity::User(identity) => AuthenticationToken::User(identity.original_token),
             Identity::ActingUser(identity, user) => {
                 AuthenticationToken::Admin(identity.key, Some(user))
             },
@@ -204,7 +218,8 @@ impl Identity {
         }
         match self {
             Identity::InstanceAdmin(admin_identity) | Identity::ActingUser(admin_identity, _) => {
-                if admin_identity.is_read_only() {
+                // Viewers and deployers can only read data.
+                if admin_identity.is_read_only() || admin_identity.role() < AdminRole::Operator {
                     anyhow::bail!(ErrorMetadata::forbidden(
                         "Unauthorized",
                         format!("You do not have permission to run {udf_type} functions.")
@@ -215,6 +230,47 @@ impl Identity {
         }
         Ok(())
     }
+
+    /// API keys can be limited to some functions. Other identities can run
+    /// any function they can see.
+    pub fn ensure_can_call_function(&self, path: &PublicFunctionPath) -> anyhow::Result<()> {
+        let Some(api_key) = self.api_key() else {
+            return Ok(());
+        };
+        let is_root_component = match path {
+            PublicFunctionPath::RootExport(_) => true,
+            PublicFunctionPath::Component(path) => path.component.is_root(),
+            PublicFunctionPath::ResolvedComponent(path) => path.component.is_root(),
+        };
+        if !api_key
+            .scopes
+            .allows_function(is_root_component, path.udf_path())
+        {
+            anyhow::bail!(ErrorMetadata::forbidden(
+                "ApiKeyScope",
+                format!(
+                    "This API key isn't allowed to run {}",
+                    String::from(path.udf_path().clone())
+                )
+            ));
+        }
+        Ok(())
+    }
+
+    /// API keys can be limited to some user tables. Other identities can
+    /// access any user table.
+    pub fn ensure_can_access_table(&self, table: &str) -> anyhow::Result<()> {
+        if let Some(api_key) = self.api_key() {
+            anyhow::ensure!(
+                api_key.scopes.allows_table(table),
+                ErrorMetadata::forbidden(
+                    "ApiKeyScope",
+                    format!("This API key isn't allowed to access the table {table}")
+                )
+            );
+        }
+        Ok(())
+    }
 }
 
 impl From<Identity> for InertIdentity {
@@ -228,7 +284,7 @@ impl From<Identity> for InertIdentity {
                 AdminIdentityPrincipal::Member(member_id) => {
                     InertIdentity::ActingUser(member_id, user.token_identifier)
                 },
-                AdminIdentityPrincipal::Team(_) => {
+                AdminIdentityPrincipal::Team(_) | AdminIdentityPrincipal::ServiceAccount(_) => {
                     panic!("Impresonating user for team access token is not supported")
                 },
             },
@@ -263,6 +319,12 @@ impl Eq for Identity {}
 impl Identity {
     pub fn cache_key(&self) -> IdentityCacheKey {
         match self.clone() {
+            // API keys can be limited to some tables, so they can't share
+            // cached results with admins.
+            Identity::InstanceAdmin(AdminIdentity {
+                api_key: Some(api_key),
+                ..
+            }) => IdentityCacheKey::ApiKey(api_key.id),
             Identity::InstanceAdmin(i) => IdentityCacheKey::InstanceAdmin(i.instance_name),
             Identity::System(_) => IdentityCacheKey::System,
             Identity::Unknown => IdentityCacheKey::Unknown,
@@ -271,7 +333,7 @@ impl Identity {
                 AdminIdentityPrincipal::Member(member_id) => {
                     IdentityCacheKey::ActingUser(member_id, user)
                 },
-                AdminIdentityPrincipal::Team(_) => {
+                AdminIdentityPrincipal::Team(_) | AdminIdentityPrincipal::ServiceAccount(_) => {
                     panic!("Impresonating user for team access token is not supported")
                 },
             },
@@ -320,6 +382,16 @@ impl Identity {
         None
     }
 
+    /// Returns the API key this identity authenticated with, if any.
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        match self {
+            Identity::InstanceAdmin(admin_identity) | Identity::ActingUser(admin_identity, _) => {
+                admin_identity.api_key.as_ref()
+            },
+            _ => None,
+        }
+    }
+
     pub fn instance_name(&self) -> Option<String> {
         if let Identity::InstanceAdmin(AdminIdentity { instance_name, .. }) = self {
             return Some(instance_name.to_string());
@@ -353,8 +425,9 @@ pub struct UserIdentity {
     pub issuer: String,
     pub expiration: SystemTime,
     pub attributes: UserIdentityAttributes,
-    // The original token this user identity was created from.
-    pub original_token: CoreIdToken,
+    // The original token this user identity was created from. It's an OpenID
+    // Connect ID token, or a JWT accepted by a custom JWT provider.
+    pub original_token: String,
 }
 
 #[cfg(any(test, feature = "testing"))]
@@ -387,7 +460,7 @@ impl From<UserIdentity> for pb::convex_identity::UserIdentity {
             issuer: Some(issuer),
             expiration: Some(expiration.into()),
             attributes: Some(attributes.into()),
-            original_token: Some(original_token.to_string()),
+            original_token: Some(original_token),
         }
     }
 }
@@ -458,7 +531,7 @@ impl UserIdentity {
             subject: subject.clone(),
             issuer: issuer.clone(),
             expiration: claims.expiration().into(),
-            original_token: token,
+            original_token: token.to_string(),
             attributes: UserIdentityAttributes {
                 token_identifier: UserIdentifier::construct(&issuer, &subject),
                 subject: Some(subject),
@@ -489,6 +562,31 @@ impl UserIdentity {
         })
     }
 
+    /// Creates the identity for a token that isn't an OpenID Connect ID token,
+    /// like a JWT accepted by a custom JWT provider. The caller must have
+    /// already verified `original_token`.
+    pub fn from_verified_token(
+        original_token: String,
+        expiration: SystemTime,
+        attributes: UserIdentityAttributes,
+    ) -> anyhow::Result<Self> {
+        let subject = attributes
+            .subject
+            .clone()
+            .context("Verified tokens must have a subject")?;
+        let issuer = attributes
+            .issuer
+            .clone()
+            .context("Verified tokens must have an issuer")?;
+        Ok(UserIdentity {
+            subject,
+            issuer,
+            expiration,
+            attributes,
+            original_token,
+        })
+    }
+
     // Decode an `Identity` serialized to protobuf *without* revalidating its
     // original token. This method assumes that the protobuf comes from a
     // trusted source, like an internal backend.
@@ -509,8 +607,7 @@ impl UserIdentity {
             .try_into()?;
         let original_token = msg
             .original_token
-            .ok_or_else(|| anyhow::anyhow!("Missing original_token"))?
-            .parse()?;
+            .ok_or_else(|| anyhow::anyhow!("Missing original_token"))?;
         Ok(Self {
             subject,
             issuer,
@@ -530,6 +627,18 @@ impl UserIdentity {
 pub enum AdminIdentityPrincipal {
     Member(MemberId),
     Team(TeamId),
+    /// ID of a `_service_accounts` document.
+    ServiceAccount(String),
+}
+
+impl fmt::Display for AdminIdentityPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminIdentityPrincipal::Member(member_id) => write!(f, "member:{member_id}"),
+            AdminIdentityPrincipal::Team(team_id) => write!(f, "team:{team_id}"),
+            AdminIdentityPrincipal::ServiceAccount(id) => write!(f, "serviceAccount:{id}"),
+        }
+    }
 }
 
 // Token indicating the possessor has authenticated as the admin for an
@@ -544,6 +653,8 @@ pub struct AdminIdentity {
     // actions. At the database level, they are allowed to read data from user and system tables
     // but not write to them.
     is_read_only: bool,
+    api_key: Option<ApiKey>,
+    role: AdminRole,
 }
 
 impl From<AdminIdentity> for pb::convex_identity::AdminIdentity {
@@ -553,6 +664,8 @@ impl From<AdminIdentity> for pb::convex_identity::AdminIdentity {
             principal,
             key,
             is_read_only,
+            api_key,
+            role,
         }: AdminIdentity,
     ) -> Self {
         Self {
@@ -564,9 +677,17 @@ impl From<AdminIdentity> for pb::convex_identity::AdminIdentity {
                 AdminIdentityPrincipal::Team(team_id) => Some(
                     pb::convex_identity::admin_identity::Principal::TeamId(team_id.0),
                 ),
+                AdminIdentityPrincipal::ServiceAccount(id) => {
+                    Some(pb::convex_identity::admin_identity::Principal::ServiceAccountId(id))
+                },
             },
             key: Some(key),
             is_read_only,
+            api_key: api_key.map(|api_key| pb::convex_identity::ApiKey {
+                id: Some(api_key.id),
+                scopes: api_key.scopes.to_strings(),
+            }),
+            role: (role != AdminRole::Owner).then(|| role.to_string()),
         }
     }
 }
@@ -583,15 +704,34 @@ impl AdminIdentity {
             Some(pb::convex_identity::admin_identity::Principal::TeamId(id)) => {
                 AdminIdentityPrincipal::Team(id.into())
             },
+            Some(pb::convex_identity::admin_identity::Principal::ServiceAccountId(id)) => {
+                AdminIdentityPrincipal::ServiceAccount(id)
+            },
             None => anyhow::bail!("Missing principal"),
         };
         let key = msg.key.ok_or_else(|| anyhow::anyhow!("Missing key"))?;
         let is_read_only: bool = msg.is_read_only;
+        let api_key = msg
+            .api_key
+            .map(|api_key| {
+                anyhow::Ok(ApiKey {
+                    id: api_key.id.context("Missing API key id")?,
+                    scopes: ApiKeyScopes::parse(api_key.scopes)?,
+                })
+            })
+            .transpose()?;
+        let role = msg
+            .role
+            .map(|role| role.parse())
+            .transpose()?
+            .unwrap_or(AdminRole::Owner);
         Ok(Self {
             instance_name,
             principal,
             key,
             is_read_only,
+            api_key,
+            role,
         })
     }
 
@@ -606,6 +746,8 @@ impl AdminIdentity {
             principal,
             key: access_token,
             is_read_only,
+            api_key: None,
+            role: AdminRole::Owner,
         }
     }
 
@@ -620,6 +762,20 @@ impl AdminIdentity {
     pub fn is_read_only(&self) -> bool {
         self.is_read_only
     }
+
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        self.api_key.as_ref()
+    }
+
+    pub fn role(&self) -> AdminRole {
+        self.role
+    }
+
+    /// Sets the member's role, which isn't part of their key, after looking it
+    /// up.
+    pub fn set_role(&mut self, role: AdminRole) {
+        self.role = role;
+    }
 }
 
 #[cfg(any(test, feature = "testing"))]
@@ -635,6 +791,8 @@ impl Arbitrary for AdminIdentity {
             principal,
             key,
             is_read_only: false,
+            api_key: None,
+            role: AdminRole::Owner,
         })
     }
 }
@@ -647,6 +805,8 @@ impl AdminIdentity {
             principal: AdminIdentityPrincipal::Member(member_id),
             key: "chocolate-charlies-cupcake".to_string(),
             is_read_only: false,
+            api_key: None,
+            role: AdminRole::Owner,
         }
     }
 
@@ -761,6 +921,8 @@ impl KeyBroker {
             issued_s: since_epoch.as_secs(),
             identity: Some(identity),
             is_read_only,
+            api_key: None,
+            expires_s: None,
         };
         format_admin_key(
             &self.instance_name,
@@ -768,6 +930,87 @@ impl KeyBroker {
         )
     }
 
+    /// Issues an API key for the `_api_keys` document `id`, on behalf of the
+    /// admin `principal`. API keys have the same format as admin keys, and
+    /// are checked with `check_admin_key`.
+    pub fn issue_api_key(
+        &self,
+        principal: &AdminIdentityPrincipal,
+        id: String,
+        scopes: &ApiKeyScopes,
+        expiration: Option<SystemTime>,
+    ) -> anyhow::Result<AdminKey> {
+        let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let identity = match principal {
+            AdminIdentityPrincipal::Member(member_id) => AdminIdentityProto::MemberId(member_id.0),
+            AdminIdentityPrincipal::Team(team_id) => AdminIdentityProto::TeamId(team_id.0),
+            AdminIdentityPrincipal::ServiceAccount(id) => {
+                AdminIdentityProto::ServiceAccountId(id.clone())
+            },
+        };
+        let expires_s = expiration
+            .map(|expiration| {
+                anyhow::Ok(expiration.duration_since(SystemTime::UNIX_EPOCH)?.as_secs())
+            })
+            .transpose()?;
+        let proto = AdminKeyProto {
+            instance_name: None,
+            issued_s: since_epoch.as_secs(),
+            identity: Some(identity),
+            is_read_only: scopes.is_read_only(),
+            api_key: Some(ApiKeyProto {
+                id: Some(id),
+                scopes: scopes.to_strings(),
+                expires_s,
+            }),
+            expires_s: None,
+        };
+        Ok(AdminKey::new(format_admin_key(
+            &self.instance_name,
+            &self.encryptor.encode_proto(ADMIN_KEY_VERSION, proto),
+        )))
+    }
+
+    /// Issues a short-lived token for the `_service_accounts` document `id`.
+    /// Tokens have the same format as admin keys, and are checked with
+    /// `check_admin_key`.
+    pub fn issue_service_account_token(
+        &self,
+        id: String,
+        expiration: SystemTime,
+    ) -> anyhow::Result<AdminKey> {
+        let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let proto = AdminKeyProto {
+            instance_name: None,
+            issued_s: since_epoch.as_secs(),
+            identity: Some(AdminIdentityProto::ServiceAccountId(id)),
+            is_read_only: false,
+            api_key: None,
+            expires_s: Some(expiration.duration_since(SystemTime::UNIX_EPOCH)?.as_secs()),
+        };
+        Ok(AdminKey::new(format_admin_key(
+            &self.instance_name,
+            &self.encryptor.encode_proto(ADMIN_KEY_VERSION, proto),
+        )))
+    }
+
+    /// Secret that anonymous identity tokens are signed with. It's derived
+    /// from the instance secret, so tokens stay valid across restarts.
+    pub fn anonymous_identity_secret(&self) -> String {
+        hex::encode(self.encryptor.derive_key(ANONYMOUS_IDENTITY_KEY_CONTEXT))
+    }
+
+    /// Secret that the tokens exchanged for SAML assertions are signed with.
+    pub fn saml_token_secret(&self) -> String {
+        hex::encode(self.encryptor.derive_key(SAML_TOKEN_KEY_CONTEXT))
+    }
+
+    /// Secret that the tokens services exchange their credentials for, to act
+    /// as a user, are signed with.
+    pub fn token_exchange_secret(&self) -> String {
+        hex::encode(self.encryptor.derive_key(TOKEN_EXCHANGE_KEY_CONTEXT))
+    }
+
     pub fn is_encrypted_admin_key(&self, key: &str) -> bool {
         let encrypted_part = split_admin_key(key).map(|(_, key)| key).unwrap_or(key);
         let admin_key: Result<AdminKeyProto, _> = self
@@ -785,6 +1028,8 @@ impl KeyBroker {
             issued_s,
             identity,
             is_read_only,
+            api_key,
+            expires_s,
         } = self
             .encryptor
             .decode_proto(ADMIN_KEY_VERSION, encrypted_part)
@@ -799,17 +1044,55 @@ impl KeyBroker {
             ));
         }
         anyhow::ensure!(issued_s != 0, "Proto missing issued_s");
+        if let Some(expires_s) = expires_s {
+            let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+            anyhow::ensure!(
+                now.as_secs() < expires_s,
+                ErrorMetadata::unauthenticated("AdminKeyExpired", "The provided key has expired")
+            );
+        }
         let identity = identity.context("Proto missing identity")?;
+        let api_key = api_key
+            .map(
+                |ApiKeyProto {
+                     id,
+                     scopes,
+                     expires_s,
+                 }| {
+                    if let Some(expires_s) = expires_s {
+                        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+                        anyhow::ensure!(
+                            now.as_secs() < expires_s,
+                            ErrorMetadata::unauthenticated(
+                                "ApiKeyExpired",
+                                "The provided API key has expired"
+                            )
+                        );
+                    }
+                    anyhow::Ok(ApiKey {
+                        id: id.context("Proto missing API key id")?,
+                        scopes: ApiKeyScopes::parse(scopes)?,
+                    })
+                },
+            )
+            .transpose()?;
 
-        Ok(match identity {
-            AdminIdentityProto::MemberId(member_id) => Identity::InstanceAdmin(AdminIdentity {
-                instance_name: self.instance_name.clone(),
-                principal: AdminIdentityPrincipal::Member(MemberId(member_id)),
-                key: key.to_string(),
-                is_read_only,
-            }),
-            AdminIdentityProto::System(()) => Identity::system(),
-        })
+        let principal = match identity {
+            AdminIdentityProto::MemberId(member_id) => {
+                AdminIdentityPrincipal::Member(MemberId(member_id))
+            },
+            AdminIdentityProto::TeamId(team_id) => AdminIdentityPrincipal::Team(TeamId(team_id)),
+            AdminIdentityProto::ServiceAccountId(id) => AdminIdentityPrincipal::ServiceAccount(id),
+            AdminIdentityProto::System(()) => return Ok(Identity::system()),
+        };
+        Ok(Identity::InstanceAdmin(AdminIdentity {
+            instance_name: self.instance_name.clone(),
+            principal,
+            key: key.to_string(),
+            is_read_only,
+            api_key,
+            role: AdminRole::Owner,
+        }))
     }
 
     pub fn check_store_file_authorization<RT: Runtime>(
@@ -1008,6 +1291,32 @@ impl KeyBroker {
         let component_id = ComponentId::deserialize_from_string(component_id.as_deref())?;
         Ok((system_time, component_id))
     }
+
+    /// Encrypts the value of a secret environment variable to store it.
+    pub fn encrypt_env_secret(&self, value: &EnvVarValue) -> String {
+        self.encryptor
+            .encode_proto(ENV_SECRET_VERSION, String::from(value.clone()))
+    }
+
+    pub fn decrypt_env_secret(&self, encrypted_value: &str) -> anyhow::Result<EnvVarValue> {
+        let value: String = self
+            .encryptor
+            .decode_proto(ENV_SECRET_VERSION, encrypted_value)
+            .context("Couldn't decrypt secret environment variable")?;
+        value.parse()
+    }
+
+    /// Encrypts a credential for an export's external destination to store
+    /// it with the export.
+    pub fn encrypt_export_secret(&self, secret: String) -> String {
+        self.encryptor.encode_proto(EXPORT_SECRET_VERSION, secret)
+    }
+
+    pub fn decrypt_export_secret(&self, encrypted_secret: &str) -> anyhow::Result<String> {
+        self.encryptor
+            .decode_proto(EXPORT_SECRET_VERSION, encrypted_secret)
+            .context("Couldn't decrypt export destination credentials")
+    }
 }
 
 #[cfg(test)]
@@ -1037,6 +1346,7 @@ mod tests {
             MemberId,
             PersistenceVersion,
             TableName,
+            UdfType,
         },
         value::DeveloperDocumentId,
     };
@@ -1055,7 +1365,11 @@ mod tests {
     };
     use crate::{
         AdminIdentity,
+        AdminIdentityPrincipal,
+        AdminRole,
+        ApiKeyScopes,
         Identity,
+        InstanceSecret,
     };
 
     #[test]
@@ -1068,6 +1382,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_env_secrets() -> anyhow::Result<()> {
+        let kb = KeyBroker::dev();
+        let value = "sk_live_123".parse()?;
+        let encrypted = kb.encrypt_env_secret(&value);
+        assert!(!encrypted.contains("sk_live_123"));
+        assert_eq!(kb.decrypt_env_secret(&encrypted)?, value);
+
+        let other_kb = KeyBroker::new("carnitas", InstanceSecret::random())?;
+        other_kb.decrypt_env_secret(&encrypted).unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_system_keys() -> anyhow::Result<()> {
         let kb = KeyBroker::dev();
@@ -1089,6 +1416,85 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_api_keys() -> anyhow::Result<()> {
+        let kb = KeyBroker::dev();
+        let principal = AdminIdentityPrincipal::Member(MemberId(0));
+        let scopes = ApiKeyScopes::parse(["read_only", "table:messages"])?;
+        let key = kb.issue_api_key(
+            &principal,
+            "api-key-id".to_string(),
+            &scopes,
+            Some(SystemTime::now() + Duration::from_secs(3600)),
+        )?;
+        let identity = kb.check_admin_key(key.as_str())?;
+        assert!(identity.is_admin());
+        let Identity::InstanceAdmin(admin_identity) = &identity else {
+            panic!("API key didn't authenticate as an admin");
+        };
+        assert!(admin_identity.is_read_only());
+        let api_key = identity.api_key().unwrap();
+        assert_eq!(api_key.id, "api-key-id");
+        assert_eq!(api_key.scopes, scopes);
+        identity.ensure_can_access_table("messages")?;
+        identity.ensure_can_access_table("users").unwrap_err();
+
+        let expired = kb.issue_api_key(
+            &principal,
+            "expired-api-key-id".to_string(),
+            &scopes,
+            Some(SystemTime::now() - Duration::from_secs(1)),
+        )?;
+        kb.check_admin_key(expired.as_str()).unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_admin_roles() -> anyhow::Result<()> {
+        let mut admin = AdminIdentity::new_for_test_only("carnitas".to_string(), MemberId(3));
+        admin.set_role(AdminRole::Deployer);
+        let admin = AdminIdentity::from_proto_unchecked(admin.into())?;
+        assert_eq!(admin.role(), AdminRole::Deployer);
+
+        let identity = Identity::InstanceAdmin(admin.clone());
+        identity.ensure_can_run_function(UdfType::Query)?;
+        identity
+            .ensure_can_run_function(UdfType::Mutation)
+            .unwrap_err();
+        identity
+            .ensure_can_run_function(UdfType::Action)
+            .unwrap_err();
+
+        let mut admin = admin;
+        admin.set_role(AdminRole::Operator);
+        Identity::InstanceAdmin(admin).ensure_can_run_function(UdfType::Mutation)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_account_tokens() -> anyhow::Result<()> {
+        let kb = KeyBroker::dev();
+        let token = kb.issue_service_account_token(
+            "service-account-id".to_string(),
+            SystemTime::now() + Duration::from_secs(60),
+        )?;
+        let Identity::InstanceAdmin(admin) = kb.check_admin_key(token.as_str())? else {
+            panic!("Service account token wasn't for an admin");
+        };
+        assert_eq!(
+            admin.principal(),
+            &AdminIdentityPrincipal::ServiceAccount("service-account-id".to_string())
+        );
+        assert!(admin.api_key().is_none());
+
+        let expired = kb.issue_service_account_token(
+            "service-account-id".to_string(),
+            SystemTime::now() - Duration::from_secs(1),
+        )?;
+        kb.check_admin_key(expired.as_str()).unwrap_err();
+        Ok(())
+    }
+
     fn old_issue_key(kb: &KeyBroker, member_id: Option<MemberId>) -> String {
         let now = SystemTime::now();
         let since_epoch = now
@@ -1104,6 +1510,8 @@ mod tests {
             issued_s: since_epoch.as_secs(),
             identity: Some(identity),
             is_read_only: false,
+            api_key: None,
+            expires_s: None,
         };
         kb.encryptor.encode_proto(ADMIN_KEY_VERSION, proto)
     }
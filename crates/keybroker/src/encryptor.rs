@@ -2,7 +2,10 @@ use std::io::Read;
 
 use byteorder::ReadBytesExt;
 use prost::Message;
-use sodiumoxide::crypto::secretbox;
+use sodiumoxide::crypto::{
+    auth::hmacsha256,
+    secretbox,
+};
 
 use crate::secret::Secret;
 
@@ -30,6 +33,12 @@ impl Encryptor {
         hex::encode(buffer)
     }
 
+    /// Derives a key for `context` from the secret, so the secret itself is
+    /// never used for anything but encryption.
+    pub fn derive_key(&self, context: &[u8]) -> [u8; 32] {
+        hmacsha256::authenticate(context, &hmacsha256::Key(self.secret.0)).0
+    }
+
     pub fn decode_proto<M: Default + Message>(
         &self,
         version: u8,
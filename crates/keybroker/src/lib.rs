@@ -2,6 +2,8 @@
 #![feature(type_alias_impl_trait)]
 #![feature(impl_trait_in_assoc_type)]
 
+mod admin_role;
+mod api_key;
 mod broker;
 mod encryptor;
 mod metrics;
@@ -12,6 +14,11 @@ pub mod testing;
 pub use sync_types::UserIdentityAttributes;
 
 pub use self::{
+    admin_role::AdminRole,
+    api_key::{
+        ApiKey,
+        ApiKeyScopes,
+    },
     broker::{
         AdminIdentity,
         AdminIdentityPrincipal,
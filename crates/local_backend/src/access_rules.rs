@@ -0,0 +1,214 @@
+//! Endpoints for managing per-table access rules, and for evaluating them
+//! against a sample identity and document before they're deployed.
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    access_rules::{
+        AccessOperation,
+        AccessRule,
+        TableAccessRules,
+    },
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+    types::TableName,
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::ConvexObject;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct AccessRulesPath {
+    table: String,
+}
+
+/// A table's rules. Operations without a rule are allowed.
+#[derive(Deserialize, Serialize)]
+pub struct AccessRulesJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    read: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    insert: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    update: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delete: Option<JsonValue>,
+}
+
+impl AccessRulesJson {
+    fn into_rules(self, table: TableName) -> anyhow::Result<TableAccessRules> {
+        let parse = |rule: Option<JsonValue>| rule.map(AccessRule::try_from).transpose();
+        Ok(TableAccessRules {
+            table,
+            read: parse(self.read)?,
+            insert: parse(self.insert)?,
+            update: parse(self.update)?,
+            delete: parse(self.delete)?,
+        })
+    }
+}
+
+impl From<TableAccessRules> for AccessRulesJson {
+    fn from(rules: TableAccessRules) -> Self {
+        Self {
+            read: rules.read.map(JsonValue::from),
+            insert: rules.insert.map(JsonValue::from),
+            update: rules.update.map(JsonValue::from),
+            delete: rules.delete.map(JsonValue::from),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TableAccessRulesJson {
+    table: String,
+    #[serde(flatten)]
+    rules: AccessRulesJson,
+}
+
+impl From<ParsedDocument<TableAccessRules>> for TableAccessRulesJson {
+    fn from(rules: ParsedDocument<TableAccessRules>) -> Self {
+        let rules = rules.into_value();
+        Self {
+            table: rules.table.to_string(),
+            rules: rules.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListAccessRulesResponse {
+    tables: Vec<TableAccessRulesJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateAccessRulesArgs {
+    table: String,
+    operation: String,
+    /// The caller's identity, as returned by `ctx.auth.getUserIdentity()`.
+    /// Omitted for unauthenticated callers.
+    identity: Option<JsonMap<String, JsonValue>>,
+    document: JsonValue,
+    /// The updated document, for updates.
+    new_document: Option<JsonValue>,
+    /// Rules to evaluate instead of the table's current rules.
+    rules: Option<AccessRulesJson>,
+}
+
+#[derive(Serialize)]
+pub struct EvaluateAccessRulesResponse {
+    allowed: bool,
+}
+
+fn parse_table_name(table: &str) -> anyhow::Result<TableName> {
+    table.parse().map_err(|e| {
+        anyhow::anyhow!(ErrorMetadata::bad_request(
+            "InvalidTableName",
+            format!("Invalid table name {table:?}: {e}"),
+        ))
+    })
+}
+
+fn parse_document(document: JsonValue) -> anyhow::Result<ConvexObject> {
+    ConvexObject::try_from(document).map_err(|e| {
+        anyhow::anyhow!(ErrorMetadata::bad_request(
+            "InvalidDocument",
+            format!("Invalid document: {e}"),
+        ))
+    })
+}
+
+pub async fn list_access_rules(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let tables = st
+        .application
+        .list_access_rules(identity)
+        .await?
+        .into_iter()
+        .map(TableAccessRulesJson::from)
+        .collect();
+    Ok(Json(ListAccessRulesResponse { tables }))
+}
+
+pub async fn set_access_rules(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(AccessRulesPath { table }): Path<AccessRulesPath>,
+    Json(rules): Json<AccessRulesJson>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let rules = rules.into_rules(parse_table_name(&table)?)?;
+    st.application.set_access_rules(identity, rules).await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_access_rules(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(AccessRulesPath { table }): Path<AccessRulesPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let table = parse_table_name(&table)?;
+    st.application.delete_access_rules(identity, &table).await?;
+    Ok(StatusCode::OK)
+}
+
+/// Evaluates a table's rules, or the rules passed in, for an operation on a
+/// document without reading or writing any data.
+pub async fn evaluate_access_rules(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(args): Json<EvaluateAccessRulesArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let table = parse_table_name(&args.table)?;
+    let operation: AccessOperation = args.operation.parse()?;
+    let rules = match args.rules {
+        Some(rules) => Some(rules.into_rules(table)?),
+        None => st
+            .application
+            .list_access_rules(identity)
+            .await?
+            .into_iter()
+            .map(ParsedDocument::into_value)
+            .find(|rules| rules.table == table),
+    };
+    let mut documents = vec![parse_document(args.document)?];
+    if let (AccessOperation::Update, Some(new_document)) = (operation, args.new_document) {
+        documents.push(parse_document(new_document)?);
+    }
+    let allowed = rules.is_none_or(|rules| {
+        documents
+            .iter()
+            .all(|document| rules.allows(operation, args.identity.as_ref(), document))
+    });
+    Ok(Json(EvaluateAccessRulesResponse { allowed }))
+}
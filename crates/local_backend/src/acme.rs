@@ -0,0 +1,601 @@
+//! Issues and renews certificates for the TLS listener with ACME (RFC 8555),
+//! from Let's Encrypt by default.
+//!
+//! Domains are validated with the `tls-alpn-01` challenge (RFC 8737), which
+//! the TLS listener answers itself, so it has to be reachable on port 443 but
+//! no other port does. The account key and issued certificates are kept in a
+//! directory, laid out like the custom domain certificates, so restarting the
+//! backend doesn't issue new ones.
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use common::runtime::Runtime;
+use http::header::{
+    CONTENT_TYPE,
+    LOCATION,
+};
+use openssl::{
+    asn1::{
+        Asn1Object,
+        Asn1OctetString,
+        Asn1Time,
+    },
+    bn::{
+        BigNum,
+        BigNumContext,
+        MsbOption,
+    },
+    ec::{
+        EcGroup,
+        EcKey,
+    },
+    ecdsa::EcdsaSig,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{
+        PKey,
+        Private,
+    },
+    sha::sha256,
+    stack::Stack,
+    x509::{
+        extension::SubjectAlternativeName,
+        X509Builder,
+        X509Extension,
+        X509NameBuilder,
+        X509ReqBuilder,
+        X509,
+    },
+};
+use rustls::sign::CertifiedKey;
+use serde::Deserialize;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use url::Url;
+
+use crate::{
+    custom_domains::{
+        certified_key_from_pem,
+        CERT_CHAIN_FILE,
+        PRIVATE_KEY_FILE,
+    },
+    tls::TlsCertificates,
+};
+
+/// Certificates are renewed once they expire within this many days. Let's
+/// Encrypt's certificates last 90 days.
+const RENEW_BEFORE_EXPIRY_DAYS: u32 = 30;
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_POLLS: usize = 60;
+const ACCOUNT_KEY_FILE: &str = "account.pem";
+/// The `acmeIdentifier` certificate extension a `tls-alpn-01` challenge
+/// response carries.
+const ACME_IDENTIFIER_OID: &str = "1.3.6.1.5.5.7.1.31";
+
+#[derive(Clone, Debug)]
+pub struct AcmeConfig {
+    pub directory_url: Url,
+    /// Where the certificate authority sends expiry notices.
+    pub contact_email: Option<String>,
+    pub state_dir: PathBuf,
+    pub domains: Vec<String>,
+}
+
+/// Keeps a certificate for each domain in `certificates`, loading the ones
+/// issued earlier from disk and renewing them before they expire.
+pub async fn run_acme_worker<RT: Runtime>(
+    rt: RT,
+    config: AcmeConfig,
+    certificates: Arc<TlsCertificates>,
+) {
+    for domain in &config.domains {
+        let domain_dir = config.state_dir.join(domain);
+        if !domain_dir.join(CERT_CHAIN_FILE).exists() {
+            continue;
+        }
+        match load_issued(&domain_dir) {
+            Ok(certified_key) => certificates.set_issued(domain, Arc::new(certified_key)),
+            Err(e) => tracing::warn!("Failed to load the certificate for {domain}: {e:#}"),
+        }
+    }
+    let mut account = None;
+    loop {
+        let wait = match renew_certificates(&rt, &config, &certificates, &mut account).await {
+            Ok(()) => CHECK_INTERVAL,
+            Err(e) => {
+                tracing::error!("Failed to renew TLS certificates: {e:#}");
+                RETRY_INTERVAL
+            },
+        };
+        rt.wait(wait).await;
+    }
+}
+
+async fn renew_certificates<RT: Runtime>(
+    rt: &RT,
+    config: &AcmeConfig,
+    certificates: &TlsCertificates,
+    account: &mut Option<AcmeAccount>,
+) -> anyhow::Result<()> {
+    for domain in &config.domains {
+        let domain_dir = config.state_dir.join(domain);
+        if !expires_soon(&domain_dir)? {
+            continue;
+        }
+        if account.is_none() {
+            *account = Some(AcmeAccount::load_or_create(config).await?);
+        }
+        let Some(account) = account.as_mut() else {
+            unreachable!()
+        };
+        tracing::info!("Requesting a TLS certificate for {domain}");
+        let (cert_pem, key_pem) = account
+            .issue(rt, domain, certificates)
+            .await
+            .with_context(|| format!("Failed to issue a certificate for {domain}"))?;
+        fs::create_dir_all(&domain_dir)?;
+        fs::write(domain_dir.join(PRIVATE_KEY_FILE), &key_pem)?;
+        fs::write(domain_dir.join(CERT_CHAIN_FILE), &cert_pem)?;
+        certificates.set_issued(
+            domain,
+            Arc::new(certified_key_from_pem(&cert_pem, &key_pem)?),
+        );
+        tracing::info!("Issued a TLS certificate for {domain}");
+    }
+    Ok(())
+}
+
+fn load_issued(domain_dir: &Path) -> anyhow::Result<CertifiedKey> {
+    certified_key_from_pem(
+        &fs::read(domain_dir.join(CERT_CHAIN_FILE))?,
+        &fs::read(domain_dir.join(PRIVATE_KEY_FILE))?,
+    )
+}
+
+/// Also true if there's no certificate yet.
+fn expires_soon(domain_dir: &Path) -> anyhow::Result<bool> {
+    let cert_path = domain_dir.join(CERT_CHAIN_FILE);
+    if !cert_path.exists() {
+        return Ok(true);
+    }
+    let cert = X509::from_pem(&fs::read(&cert_path)?)?;
+    Ok(cert.not_after() < Asn1Time::days_from_now(RENEW_BEFORE_EXPIRY_DAYS)?)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+    error: Option<JsonValue>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: String,
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    token: Option<String>,
+    error: Option<JsonValue>,
+}
+
+struct AcmeAccount {
+    client: reqwest::Client,
+    directory: Directory,
+    key: EcKey<Private>,
+    /// JWK thumbprint of the account key, for key authorizations.
+    thumbprint: String,
+    /// The account's URL, which signs requests once the account exists.
+    url: Option<String>,
+    nonce: Option<String>,
+}
+
+impl AcmeAccount {
+    /// Registering an existing key returns its account, so this doesn't need
+    /// to remember whether the account was created.
+    async fn load_or_create(config: &AcmeConfig) -> anyhow::Result<Self> {
+        let key_path = config.state_dir.join(ACCOUNT_KEY_FILE);
+        let key = if key_path.exists() {
+            EcKey::private_key_from_pem(&fs::read(&key_path)?)?
+        } else {
+            let key = EcKey::generate(&p256()?)?;
+            fs::create_dir_all(&config.state_dir)?;
+            fs::write(&key_path, key.private_key_to_pem()?)?;
+            key
+        };
+        let client = reqwest::Client::new();
+        let directory: Directory = client
+            .get(config.directory_url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let thumbprint = base64_url(sha256(&serde_json::to_vec(&jwk(&key)?)?));
+        let mut account = Self {
+            client,
+            directory,
+            key,
+            thumbprint,
+            url: None,
+            nonce: None,
+        };
+        let mut payload = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = &config.contact_email {
+            payload["contact"] = json!([format!("mailto:{email}")]);
+        }
+        let new_account = account.directory.new_account.clone();
+        let response = account.post(&new_account, Some(&payload)).await?;
+        account.url = Some(location(&response)?);
+        Ok(account)
+    }
+
+    /// Returns the PEM certificate chain and private key.
+    async fn issue<RT: Runtime>(
+        &mut self,
+        rt: &RT,
+        domain: &str,
+        certificates: &TlsCertificates,
+    ) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let new_order = self.directory.new_order.clone();
+        let payload = json!({ "identifiers": [{ "type": "dns", "value": domain }] });
+        let response = self.post(&new_order, Some(&payload)).await?;
+        let order_url = location(&response)?;
+        let order: Order = response.json().await?;
+        for authorization_url in &order.authorizations {
+            let authorization: Authorization =
+                self.post(authorization_url, None).await?.json().await?;
+            if authorization.status == "valid" {
+                continue;
+            }
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|challenge| challenge.kind == "tls-alpn-01")
+                .context("The ACME server didn't offer a tls-alpn-01 challenge")?;
+            let token = challenge
+                .token
+                .as_deref()
+                .context("tls-alpn-01 challenge is missing its token")?;
+            let key_authorization = format!("{token}.{}", self.thumbprint);
+            certificates.set_challenge(
+                domain,
+                Arc::new(challenge_certificate(domain, &key_authorization)?),
+            );
+            let result = self.validate(rt, &challenge.url, authorization_url).await;
+            certificates.remove_challenge(domain);
+            result?;
+        }
+
+        let key = PKey::from_ec_key(EcKey::generate(&p256()?)?)?;
+        let payload = json!({ "csr": base64_url(csr(domain, &key)?) });
+        self.post(&order.finalize, Some(&payload)).await?;
+        let order = self.wait_for_order(rt, &order_url).await?;
+        let certificate_url = order
+            .certificate
+            .context("Valid ACME order is missing its certificate")?;
+        let cert_pem = self.post(&certificate_url, None).await?.bytes().await?;
+        Ok((cert_pem.to_vec(), key.private_key_to_pem_pkcs8()?))
+    }
+
+    async fn validate<RT: Runtime>(
+        &mut self,
+        rt: &RT,
+        challenge_url: &str,
+        authorization_url: &str,
+    ) -> anyhow::Result<()> {
+        // An empty object tells the server the challenge is ready.
+        self.post(challenge_url, Some(&json!({}))).await?;
+        for _ in 0..MAX_POLLS {
+            rt.wait(POLL_INTERVAL).await;
+            let authorization: Authorization =
+                self.post(authorization_url, None).await?.json().await?;
+            match authorization.status.as_str() {
+                "valid" => return Ok(()),
+                "pending" => {},
+                status => {
+                    let errors: Vec<_> = authorization
+                        .challenges
+                        .into_iter()
+                        .filter_map(|challenge| challenge.error)
+                        .collect();
+                    anyhow::bail!("Authorization is {status}: {}", json!(errors));
+                },
+            }
+        }
+        anyhow::bail!("Timed out waiting for {authorization_url} to be validated")
+    }
+
+    async fn wait_for_order<RT: Runtime>(
+        &mut self,
+        rt: &RT,
+        order_url: &str,
+    ) -> anyhow::Result<Order> {
+        for _ in 0..MAX_POLLS {
+            let order: Order = self.post(order_url, None).await?.json().await?;
+            match order.status.as_str() {
+                "valid" => return Ok(order),
+                "invalid" => {
+                    anyhow::bail!("Order failed: {}", order.error.unwrap_or_default())
+                },
+                _ => rt.wait(POLL_INTERVAL).await,
+            }
+        }
+        anyhow::bail!("Timed out waiting for {order_url} to be issued")
+    }
+
+    /// Sends a signed request. A `None` payload makes a POST-as-GET request.
+    async fn post(
+        &mut self,
+        url: &str,
+        payload: Option<&JsonValue>,
+    ) -> anyhow::Result<reqwest::Response> {
+        let mut retried = false;
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => self.new_nonce().await?,
+            };
+            let response = self
+                .client
+                .post(url)
+                .header(CONTENT_TYPE, "application/jose+json")
+                .body(self.sign(url, &nonce, payload)?)
+                .send()
+                .await?;
+            self.nonce = replay_nonce(&response);
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+            let problem: JsonValue = response.json().await.unwrap_or_default();
+            // Servers can reject nonces at any time, and expect a retry with
+            // the one they sent back.
+            if problem["type"] == "urn:ietf:params:acme:error:badNonce" && !retried {
+                retried = true;
+                continue;
+            }
+            anyhow::bail!("ACME request to {url} failed with {status}: {problem}");
+        }
+    }
+
+    async fn new_nonce(&self) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .head(&self.directory.new_nonce)
+            .send()
+            .await?
+            .error_for_status()?;
+        replay_nonce(&response).context("ACME server didn't return a nonce")
+    }
+
+    /// Flattened JWS, signed with ES256.
+    fn sign(&self, url: &str, nonce: &str, payload: Option<&JsonValue>) -> anyhow::Result<String> {
+        let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+        match &self.url {
+            Some(account_url) => protected["kid"] = json!(account_url),
+            None => protected["jwk"] = jwk(&self.key)?,
+        }
+        let protected = base64_url(serde_json::to_vec(&protected)?);
+        let payload = match payload {
+            Some(payload) => base64_url(serde_json::to_vec(payload)?),
+            None => String::new(),
+        };
+        let signature = es256_sign(&self.key, format!("{protected}.{payload}").as_bytes())?;
+        Ok(json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": base64_url(signature),
+        })
+        .to_string())
+    }
+}
+
+fn p256() -> anyhow::Result<EcGroup> {
+    Ok(EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?)
+}
+
+fn base64_url(bytes: impl AsRef<[u8]>) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn location(response: &reqwest::Response) -> anyhow::Result<String> {
+    Ok(response
+        .headers()
+        .get(LOCATION)
+        .context("ACME response is missing its Location header")?
+        .to_str()?
+        .to_string())
+}
+
+fn replay_nonce(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get("replay-nonce")
+        .and_then(|nonce| nonce.to_str().ok())
+        .map(|nonce| nonce.to_string())
+}
+
+/// The members are in lexicographic order without whitespace, so the
+/// serialized JWK can be hashed into the thumbprint (RFC 7638).
+fn jwk(key: &EcKey<Private>) -> anyhow::Result<JsonValue> {
+    let mut x = BigNum::new()?;
+    let mut y = BigNum::new()?;
+    key.public_key()
+        .affine_coordinates(key.group(), &mut x, &mut y, &mut BigNumContext::new()?)?;
+    Ok(json!({
+        "crv": "P-256",
+        "kty": "EC",
+        "x": base64_url(x.to_vec_padded(32)?),
+        "y": base64_url(y.to_vec_padded(32)?),
+    }))
+}
+
+/// JWS signatures are the raw `r || s` pair rather than DER.
+fn es256_sign(key: &EcKey<Private>, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let signature = EcdsaSig::sign(&sha256(message), key)?;
+    let mut raw = signature.r().to_vec_padded(32)?;
+    raw.extend(signature.s().to_vec_padded(32)?);
+    Ok(raw)
+}
+
+fn csr(domain: &str, key: &PKey<Private>) -> anyhow::Result<Vec<u8>> {
+    let mut builder = X509ReqBuilder::new()?;
+    builder.set_pubkey(key)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, domain)?;
+    builder.set_subject_name(&name.build())?;
+    let mut extensions = Stack::new()?;
+    extensions.push(
+        SubjectAlternativeName::new()
+            .dns(domain)
+            .build(&builder.x509v3_context(None))?,
+    )?;
+    builder.add_extensions(&extensions)?;
+    builder.sign(key, MessageDigest::sha256())?;
+    Ok(builder.build().to_der()?)
+}
+
+/// Self-signed certificate for `domain` that proves control of the account
+/// key to the ACME server.
+fn challenge_certificate(domain: &str, key_authorization: &str) -> anyhow::Result<CertifiedKey> {
+    let key = PKey::from_ec_key(EcKey::generate(&p256()?)?)?;
+    let mut builder = X509Builder::new()?;
+    builder.set_version(2)?;
+    let mut serial = BigNum::new()?;
+    serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+    builder.set_serial_number(&serial.to_asn1_integer()?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, domain)?;
+    let name = name.build();
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    builder.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&*Asn1Time::days_from_now(7)?)?;
+    let san = SubjectAlternativeName::new()
+        .dns(domain)
+        .build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(san)?;
+    // The extension's value is the key authorization's SHA-256 digest as a DER
+    // octet string.
+    let mut digest = vec![0x04, 0x20];
+    digest.extend(sha256(key_authorization.as_bytes()));
+    builder.append_extension(X509Extension::new_from_der(
+        &Asn1Object::from_str(ACME_IDENTIFIER_OID)?,
+        true,
+        &Asn1OctetString::new_from_bytes(&digest)?,
+    )?)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    certified_key_from_pem(&builder.build().to_pem()?, &key.private_key_to_pem_pkcs8()?)
+}
+
+/// Hosts of `urls` that can get a certificate, leaving out IP addresses and
+/// `localhost`.
+pub fn public_domains<'a>(urls: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut domains = BTreeSet::new();
+    for url in urls {
+        let Ok(url) = Url::parse(url) else {
+            continue;
+        };
+        if let Some(url::Host::Domain(domain)) = url.host()
+            && domain != "localhost"
+        {
+            domains.insert(domain.to_ascii_lowercase());
+        }
+    }
+    domains.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{
+        bn::BigNum,
+        ec::EcKey,
+        ecdsa::EcdsaSig,
+        pkey::PKey,
+        sha::sha256,
+        x509::X509Req,
+    };
+
+    use super::{
+        challenge_certificate,
+        csr,
+        es256_sign,
+        jwk,
+        p256,
+        public_domains,
+    };
+
+    #[test]
+    fn test_es256_sign() -> anyhow::Result<()> {
+        let key = EcKey::generate(&p256()?)?;
+        let signature = es256_sign(&key, b"protected.payload")?;
+        assert_eq!(signature.len(), 64);
+        let signature = EcdsaSig::from_private_components(
+            BigNum::from_slice(&signature[..32])?,
+            BigNum::from_slice(&signature[32..])?,
+        )?;
+        assert!(signature.verify(&sha256(b"protected.payload"), &key)?);
+
+        let jwk = serde_json::to_string(&jwk(&key)?)?;
+        assert!(jwk.starts_with(r#"{"crv":"P-256","kty":"EC","x":""#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_csr() -> anyhow::Result<()> {
+        let key = PKey::from_ec_key(EcKey::generate(&p256()?)?)?;
+        let csr = X509Req::from_der(&csr("api.example.com", &key)?)?;
+        assert!(csr.verify(&key)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_challenge_certificate() -> anyhow::Result<()> {
+        let certified_key = challenge_certificate("api.example.com", "token.thumbprint")?;
+        assert_eq!(certified_key.cert.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_domains() {
+        assert_eq!(
+            public_domains([
+                "https://api.example.com",
+                "https://Hooks.Example.com:8443",
+                "http://127.0.0.1:3210",
+                "http://localhost:3211",
+            ]),
+            vec!["api.example.com", "hooks.example.com"]
+        );
+    }
+}
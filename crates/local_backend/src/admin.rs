@@ -3,16 +3,19 @@ use authentication::application_auth::ApplicationAuth;
 use common::types::MemberId;
 use errors::ErrorMetadata;
 use keybroker::{
+    AdminIdentity,
     AdminIdentityPrincipal,
+    AdminRole,
     Identity,
 };
 
+/// Checks a deploy key sent in a request body, requiring the `deployer` role.
 pub async fn must_be_admin_from_key_with_write_access(
     app_auth: &ApplicationAuth,
     instance_name: String,
     admin_key: String,
 ) -> anyhow::Result<Identity> {
-    must_be_admin_from_key_internal(app_auth, instance_name, admin_key, true).await
+    must_be_admin_from_key_internal(app_auth, instance_name, admin_key, AdminRole::Deployer).await
 }
 
 pub async fn must_be_admin_from_key(
@@ -20,38 +23,64 @@ pub async fn must_be_admin_from_key(
     instance_name: String,
     admin_key: String,
 ) -> anyhow::Result<Identity> {
-    must_be_admin_from_key_internal(app_auth, instance_name, admin_key, false).await
+    must_be_admin_from_key_internal(app_auth, instance_name, admin_key, AdminRole::Viewer).await
 }
 
 async fn must_be_admin_from_key_internal(
     app_auth: &ApplicationAuth,
     instance_name: String,
     admin_key_or_access_token: String,
-    needs_write_access: bool,
+    role: AdminRole,
 ) -> anyhow::Result<Identity> {
     let identity = app_auth
         .check_key(admin_key_or_access_token, instance_name.clone())
         .await
         .context(bad_admin_key_error(Some(instance_name)))?;
-    if needs_write_access {
-        must_be_admin_with_write_access(&identity)?;
+    // System keys can read but not deploy.
+    if role > AdminRole::Viewer || matches!(identity, Identity::InstanceAdmin(_)) {
+        must_be_admin_internal(&identity, role, false)?;
     }
     Ok(identity)
 }
 
+/// Requires the `operator` role, for operations that change data or the
+/// deployment's configuration.
 pub fn must_be_admin_with_write_access(
     identity: &Identity,
 ) -> anyhow::Result<AdminIdentityPrincipal> {
-    must_be_admin_internal(identity, true)
+    must_be_admin_internal(identity, AdminRole::Operator, false)
 }
 
 pub fn must_be_admin(identity: &Identity) -> anyhow::Result<AdminIdentityPrincipal> {
-    must_be_admin_internal(identity, false)
+    must_be_admin_internal(identity, AdminRole::Viewer, false)
+}
+
+/// Requires the `deployer` role, for pushing code and schemas.
+pub fn must_be_deployer(identity: &Identity) -> anyhow::Result<AdminIdentityPrincipal> {
+    must_be_admin_internal(identity, AdminRole::Deployer, false)
+}
+
+/// Requires the `owner` role, for managing who can access the deployment.
+pub fn must_be_owner(identity: &Identity) -> anyhow::Result<AdminIdentityPrincipal> {
+    must_be_admin_internal(identity, AdminRole::Owner, false)
+}
+
+/// Like `must_be_admin_with_write_access`, but also allows API keys without
+/// the `admin` scope, for endpoints that check the key's scopes themselves.
+pub fn must_be_admin_or_api_key_with_write_access(
+    identity: &Identity,
+) -> anyhow::Result<AdminIdentityPrincipal> {
+    must_be_admin_internal(identity, AdminRole::Operator, true)
+}
+
+pub fn must_be_admin_or_api_key(identity: &Identity) -> anyhow::Result<AdminIdentityPrincipal> {
+    must_be_admin_internal(identity, AdminRole::Viewer, true)
 }
 
 fn must_be_admin_internal(
     identity: &Identity,
-    needs_write_access: bool,
+    role: AdminRole,
+    allow_scoped_api_key: bool,
 ) -> anyhow::Result<AdminIdentityPrincipal> {
     let admin_identity = match identity {
         Identity::InstanceAdmin(admin_identity) => admin_identity,
@@ -61,29 +90,32 @@ fn must_be_admin_internal(
         },
     };
 
-    if needs_write_access && admin_identity.is_read_only() {
-        return Err(read_only_admin_key_error().into());
+    if let Some(api_key) = admin_identity.api_key()
+        && !allow_scoped_api_key
+        && !api_key.scopes.is_admin()
+    {
+        return Err(ErrorMetadata::forbidden(
+            "ApiKeyScope",
+            "This API key doesn't have the `admin` scope needed for this operation.",
+        )
+        .into());
     }
+    must_have_role(admin_identity, role)?;
     Ok(admin_identity.principal().clone())
 }
 
 pub fn must_be_admin_member_with_write_access(identity: &Identity) -> anyhow::Result<MemberId> {
-    must_be_admin_member_internal(identity, true)
+    must_be_admin_member_internal(identity, AdminRole::Operator)
 }
 
 pub fn must_be_admin_member(identity: &Identity) -> anyhow::Result<MemberId> {
-    must_be_admin_member_internal(identity, false)
+    must_be_admin_member_internal(identity, AdminRole::Viewer)
 }
 
-fn must_be_admin_member_internal(
-    identity: &Identity,
-    needs_write_access: bool,
-) -> anyhow::Result<MemberId> {
+fn must_be_admin_member_internal(identity: &Identity, role: AdminRole) -> anyhow::Result<MemberId> {
     if let Identity::InstanceAdmin(admin_identity) = identity {
         if let AdminIdentityPrincipal::Member(member_id) = admin_identity.principal() {
-            if needs_write_access && admin_identity.is_read_only() {
-                return Err(read_only_admin_key_error().into());
-            }
+            must_have_role(admin_identity, role)?;
             Ok(*member_id)
         } else {
             Err(bad_admin_key_error(identity.instance_name()).into())
@@ -93,6 +125,23 @@ fn must_be_admin_member_internal(
     }
 }
 
+fn must_have_role(admin_identity: &AdminIdentity, role: AdminRole) -> anyhow::Result<()> {
+    if role > AdminRole::Viewer && admin_identity.is_read_only() {
+        return Err(read_only_admin_key_error().into());
+    }
+    if admin_identity.role() < role {
+        return Err(ErrorMetadata::forbidden(
+            "InsufficientAdminRole",
+            format!(
+                "This operation requires the `{role}` role, but you're a `{}`.",
+                admin_identity.role()
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
 pub fn bad_admin_key_error(instance_name: Option<String>) -> ErrorMetadata {
     let msg = match instance_name {
         Some(name) => format!(
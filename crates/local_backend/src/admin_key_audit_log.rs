@@ -0,0 +1,245 @@
+//! Records the requests that are made with admin or deploy keys in
+//! `_admin_key_audit_log`, so operators can investigate who deployed, read or
+//! ran what.
+//!
+//! Keys can come in a header, a query parameter, a request body or a sync
+//! protocol message, so instead of looking for them, we record whatever key
+//! `ApplicationAuth::check_key` checks while handling the request.
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::Arc,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use authentication::admin_key_audit::{
+    with_admin_key_checks,
+    AdminKeyCheck,
+};
+use axum::{
+    extract::{
+        ConnectInfo,
+        OriginalUri,
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use common::{
+    document::ParsedDocument,
+    errors::report_error,
+    http::{
+        extract::{
+            Json,
+            Query,
+        },
+        HttpResponseError,
+    },
+    knobs::ADMIN_KEY_AUDIT_LOG_ENABLED,
+    runtime::Runtime,
+};
+use http::StatusCode;
+use model::admin_key_audit_log::types::{
+    AdminKeyUse,
+    AdminKeyUseOutcome,
+};
+use parking_lot::Mutex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::must_be_admin,
+    authentication::ExtractIdentity,
+    LocalAppState,
+    RouterState,
+};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+pub async fn admin_key_audit_middleware(
+    State(st): State<LocalAppState>,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !*ADMIN_KEY_AUDIT_LOG_ENABLED {
+        return next.run(req).await;
+    }
+    let method = req.method().to_string();
+    // Nested routers only see the rest of the path. The query string is left
+    // out, since it can hold the key.
+    let path = match req.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path().to_string(),
+        None => req.uri().path().to_string(),
+    };
+    let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+    let caller_ip = st
+        .trusted_proxies
+        .client_ip(remote_ip, req.headers())
+        .map(|ip| ip.to_string());
+    // A request may check its key more than once, e.g. to rate limit it, so
+    // only the last check is recorded.
+    let last_check = Arc::new(Mutex::new(None));
+    let on_check = {
+        let last_check = last_check.clone();
+        move |check| *last_check.lock() = Some(check)
+    };
+    let response = with_admin_key_checks(on_check, next.run(req)).await;
+    let Some(check) = last_check.lock().take() else {
+        return response;
+    };
+    let status = response.status().as_u16();
+    let key_use = admin_key_use(
+        check,
+        method,
+        path,
+        caller_ip,
+        status,
+        st.application.runtime().system_time(),
+    );
+    // Record the use in the background, so the log doesn't slow down or fail
+    // the request.
+    let application = st.application.clone();
+    st.application
+        .runtime()
+        .spawn("record_admin_key_use", async move {
+            if let Err(mut e) = application.record_admin_key_use(key_use).await {
+                report_error(&mut e.context("Failed to record admin key use")).await;
+            }
+        });
+    response
+}
+
+/// Records the keys a sync socket authenticates with while running
+/// `sync_worker`. Unlike HTTP requests, sockets authenticate after they're
+/// opened, and may authenticate again while they're open.
+pub(crate) async fn audit_sync_socket_admin_keys<T>(
+    st: &RouterState,
+    caller_ip: Option<String>,
+    sync_worker: impl Future<Output = T>,
+) -> T {
+    if !*ADMIN_KEY_AUDIT_LOG_ENABLED {
+        return sync_worker.await;
+    }
+    let api = st.api.clone();
+    let runtime = st.runtime.clone();
+    let on_check = move |check| {
+        let key_use = admin_key_use(
+            check,
+            "GET".to_string(),
+            "/api/sync".to_string(),
+            caller_ip.clone(),
+            StatusCode::SWITCHING_PROTOCOLS.as_u16(),
+            runtime.system_time(),
+        );
+        let api = api.clone();
+        runtime.spawn("record_admin_key_use", async move {
+            if let Err(mut e) = api.record_admin_key_use(key_use).await {
+                report_error(&mut e.context("Failed to record admin key use")).await;
+            }
+        });
+    };
+    with_admin_key_checks(on_check, sync_worker).await
+}
+
+fn admin_key_use(
+    check: AdminKeyCheck,
+    method: String,
+    path: String,
+    caller_ip: Option<String>,
+    status: u16,
+    used_at: SystemTime,
+) -> AdminKeyUse {
+    let (principal, api_key_id) = match check {
+        AdminKeyCheck::Valid {
+            principal,
+            api_key_id,
+        } => (Some(principal), api_key_id),
+        AdminKeyCheck::Invalid => (None, None),
+    };
+    AdminKeyUse {
+        method,
+        path,
+        caller_ip,
+        outcome: AdminKeyUseOutcome::new(principal.is_some(), status),
+        principal,
+        api_key_id,
+        status,
+        used_at,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AdminKeyAuditLogArgs {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminKeyUseJson {
+    id: String,
+    method: String,
+    path: String,
+    caller_ip: Option<String>,
+    principal: Option<String>,
+    api_key_id: Option<String>,
+    outcome: String,
+    status: u16,
+    /// Milliseconds since the Unix epoch.
+    used_at: u64,
+}
+
+impl From<ParsedDocument<AdminKeyUse>> for AdminKeyUseJson {
+    fn from(key_use: ParsedDocument<AdminKeyUse>) -> Self {
+        let id = key_use.id().developer_id.encode();
+        let key_use = key_use.into_value();
+        let used_at = key_use
+            .used_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        Self {
+            id,
+            method: key_use.method,
+            path: key_use.path,
+            caller_ip: key_use.caller_ip,
+            principal: key_use.principal,
+            api_key_id: key_use.api_key_id,
+            outcome: key_use.outcome.to_string(),
+            status: key_use.status,
+            used_at: used_at.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AdminKeyAuditLogResponse {
+    uses: Vec<AdminKeyUseJson>,
+}
+
+/// The most recent requests made with admin or deploy keys, newest first.
+pub async fn get_admin_key_audit_log(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(args): Query<AdminKeyAuditLogArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let limit = args.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let uses = st
+        .application
+        .admin_key_audit_log(identity, limit)
+        .await?
+        .into_iter()
+        .map(AdminKeyUseJson::from)
+        .collect();
+    Ok(Json(AdminKeyAuditLogResponse { uses }))
+}
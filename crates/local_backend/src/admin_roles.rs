@@ -0,0 +1,101 @@
+//! Endpoints for assigning team members roles in this deployment. See
+//! [`AdminRole`] for what each role can do.
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+    types::MemberId,
+};
+use http::StatusCode;
+use keybroker::AdminRole;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminRolePath {
+    member_id: MemberId,
+}
+
+#[derive(Deserialize)]
+pub struct SetAdminRoleArgs {
+    role: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminRoleJson {
+    member_id: MemberId,
+    role: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAdminRolesResponse {
+    roles: Vec<AdminRoleJson>,
+}
+
+pub async fn list_admin_roles(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let roles = st.application.list_admin_roles(identity).await?;
+    Ok(Json(ListAdminRolesResponse {
+        roles: roles
+            .into_iter()
+            .map(|(member_id, role)| AdminRoleJson {
+                member_id,
+                role: role.to_string(),
+            })
+            .collect(),
+    }))
+}
+
+/// Assigns a member a role, e.g. with `{"role": "viewer"}`. Assigning the
+/// first role turns off access for members without one, so it has to be an
+/// `owner`.
+pub async fn set_admin_role(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(AdminRolePath { member_id }): Path<AdminRolePath>,
+    Json(SetAdminRoleArgs { role }): Json<SetAdminRoleArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let role: AdminRole = role.parse()?;
+    st.application
+        .set_admin_role(identity, member_id, Some(role))
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn remove_admin_role(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(AdminRolePath { member_id }): Path<AdminRolePath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    st.application
+        .set_admin_role(identity, member_id, None)
+        .await?;
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,503 @@
+//! Alerts on the backend's internal metrics. Operators define rules through
+//! `/api/alert_rules`, like "the error rate is above 5% over the last 5
+//! minutes", which are kept in `_alert_rules`.
+//!
+//! The alert worker evaluates every rule each `ALERT_EVALUATION_INTERVAL`.
+//! When a rule starts or stops firing, it records the new state and notifies
+//! the rule's channels: webhooks get the notification posted as JSON, and
+//! email addresses get it through the SMTP server from `--alert-smtp-url`.
+use std::{
+    ffi::CString,
+    io,
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use anyhow::Context;
+use application::Application;
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    document::ParsedDocument,
+    errors::report_error,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+    knobs::ALERT_EVALUATION_INTERVAL,
+    runtime::Runtime,
+    types::Identity,
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use model::alert_rules::types::{
+    AlertChannel,
+    AlertMetric,
+    AlertRule,
+    AlertState,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use udf_metrics::MetricsWindow;
+use url::Url;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_admin_with_write_access,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+mod smtp;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Commit latency is measured at this percentile.
+const COMMIT_LATENCY_PERCENTILE: usize = 99;
+
+/// Sends alert emails through this server from this address.
+#[derive(Clone)]
+pub struct AlertEmailConfig {
+    pub smtp_url: Url,
+    pub from: String,
+}
+
+#[derive(Clone)]
+pub struct AlertWorker {
+    instance_name: String,
+    /// Disk usage is measured on this directory's filesystem.
+    storage_dir: PathBuf,
+    client: reqwest::Client,
+    email: Option<AlertEmailConfig>,
+}
+
+impl AlertWorker {
+    pub fn new(
+        instance_name: String,
+        storage_dir: PathBuf,
+        email: Option<AlertEmailConfig>,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            instance_name,
+            storage_dir,
+            client,
+            email,
+        })
+    }
+
+    pub async fn go<RT: Runtime>(self, application: Application<RT>) {
+        loop {
+            application.runtime().wait(*ALERT_EVALUATION_INTERVAL).await;
+            if let Err(mut e) = self.evaluate(&application).await {
+                report_error(&mut e.context("Failed to evaluate alert rules")).await;
+            }
+        }
+    }
+
+    async fn evaluate<RT: Runtime>(&self, application: &Application<RT>) -> anyhow::Result<()> {
+        let now = application.runtime().system_time();
+        for rule in application.list_alert_rules(Identity::system()).await? {
+            let id = rule.id();
+            let rule = rule.into_value();
+            let window = MetricsWindow {
+                start: now - Duration::from_secs(rule.window_secs),
+                end: now,
+                num_buckets: 1,
+            };
+            let value = self.metric_value(application, rule.metric, window).await?;
+            let firing = value > rule.threshold;
+            if firing == rule.state.firing {
+                continue;
+            }
+            // Record the new state before notifying, so channels aren't
+            // notified again if this fails.
+            application
+                .set_alert_rule_state(
+                    id,
+                    AlertState {
+                        firing,
+                        since: Some(now),
+                        value: Some(value),
+                    },
+                )
+                .await?;
+            let notification = AlertNotification {
+                deployment: self.instance_name.clone(),
+                rule_id: id.developer_id.encode(),
+                rule: rule.name.clone(),
+                metric: rule.metric.to_string(),
+                status: if firing { "firing" } else { "resolved" },
+                value,
+                threshold: rule.threshold,
+                window_secs: rule.window_secs,
+                timestamp_secs: now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs(),
+            };
+            tracing::info!(
+                "Alert rule {:?} is {}: {} is {value}",
+                notification.rule,
+                notification.status,
+                notification.metric,
+            );
+            for channel in &rule.channels {
+                if let Err(mut e) = self.notify(channel, &notification).await {
+                    report_error(&mut e.context("Failed to send alert notification")).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The metric's value over `window`. No data, like when no functions ran
+    /// in the window, counts as 0.
+    async fn metric_value<RT: Runtime>(
+        &self,
+        application: &Application<RT>,
+        metric: AlertMetric,
+        window: MetricsWindow,
+    ) -> anyhow::Result<f64> {
+        let value = match metric {
+            AlertMetric::ErrorRate => {
+                application
+                    .deployment_failure_percentage(Identity::system(), window)
+                    .await?
+            },
+            AlertMetric::SchedulerLag => application
+                .scheduled_job_lag(Identity::system(), window)
+                .await?
+                .last()
+                .and_then(|(_, lag)| *lag),
+            AlertMetric::DiskUsage => Some(disk_usage_percentage(&self.storage_dir)?),
+            AlertMetric::CommitLatency => application
+                .deployment_commit_time_percentile(
+                    Identity::system(),
+                    COMMIT_LATENCY_PERCENTILE,
+                    window,
+                )
+                .await?
+                .map(|commit_time| commit_time.as_secs_f64() * 1000.),
+        };
+        Ok(value.unwrap_or(0.))
+    }
+
+    async fn notify(
+        &self,
+        channel: &AlertChannel,
+        notification: &AlertNotification,
+    ) -> anyhow::Result<()> {
+        match channel {
+            AlertChannel::Webhook { url } => {
+                self.client
+                    .post(url.as_str())
+                    .json(notification)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            },
+            AlertChannel::Email { to } => {
+                let Some(config) = &self.email else {
+                    anyhow::bail!("Can't email {to} without --alert-smtp-url");
+                };
+                let email = smtp::Email {
+                    from: config.from.clone(),
+                    to: to.clone(),
+                    subject: notification.subject(),
+                    body: notification.body(),
+                };
+                smtp::send(&config.smtp_url, email).await?;
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Percentage of the space on `path`'s filesystem that's used, like `df`
+/// reports it: the blocks reserved for root don't count as available.
+fn disk_usage_percentage(path: &std::path::Path) -> anyhow::Result<f64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `statvfs` is plain data, so all zeroes is a valid value.
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    // SAFETY: `c_path` is a valid C string and `stat` is valid to write to.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to stat the filesystem of {}", path.display()));
+    }
+    let used = stat.f_blocks.saturating_sub(stat.f_bfree) as f64;
+    let available = stat.f_bavail as f64;
+    if used + available == 0. {
+        return Ok(0.);
+    }
+    Ok(used / (used + available) * 100.)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertNotification {
+    deployment: String,
+    rule_id: String,
+    rule: String,
+    metric: String,
+    status: &'static str,
+    value: f64,
+    threshold: f64,
+    window_secs: u64,
+    timestamp_secs: u64,
+}
+
+impl AlertNotification {
+    fn subject(&self) -> String {
+        format!(
+            "[{}] Alert {:?} is {}",
+            self.deployment, self.rule, self.status
+        )
+    }
+
+    fn body(&self) -> String {
+        let comparison = if self.status == "firing" {
+            "above"
+        } else {
+            "back at or below"
+        };
+        format!(
+            "Alert rule {:?} on {} is {}.\n\n{} is {:.2} over the last {} seconds, {comparison} \
+             the threshold of {}.",
+            self.rule,
+            self.deployment,
+            self.status,
+            self.metric,
+            self.value,
+            self.window_secs,
+            self.threshold,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AlertRulePath {
+    id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AlertChannelJson {
+    Webhook { url: String },
+    Email { to: String },
+}
+
+impl From<AlertChannel> for AlertChannelJson {
+    fn from(channel: AlertChannel) -> Self {
+        match channel {
+            AlertChannel::Webhook { url } => Self::Webhook { url },
+            AlertChannel::Email { to } => Self::Email { to },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAlertRuleArgs {
+    name: String,
+    /// `errorRate`, `schedulerLag`, `diskUsage` or `commitLatency`.
+    metric: String,
+    threshold: f64,
+    window_secs: u64,
+    channels: Vec<AlertChannelJson>,
+}
+
+impl CreateAlertRuleArgs {
+    fn into_rule(self) -> anyhow::Result<AlertRule> {
+        let invalid = |message: String| {
+            anyhow::anyhow!(ErrorMetadata::bad_request("InvalidAlertRule", message))
+        };
+        if self.name.is_empty() {
+            return Err(invalid("Alert rules need a name".to_string()));
+        }
+        let metric: AlertMetric = self.metric.parse().map_err(|_| {
+            invalid(format!(
+                "Unknown metric {:?}, expected errorRate, schedulerLag, diskUsage or commitLatency",
+                self.metric
+            ))
+        })?;
+        if !self.threshold.is_finite() || self.threshold < 0. {
+            return Err(invalid(format!(
+                "Invalid threshold {}, expected a number at least 0",
+                self.threshold
+            )));
+        }
+        if self.window_secs == 0 {
+            return Err(invalid("`windowSecs` must be at least 1".to_string()));
+        }
+        if self.channels.is_empty() {
+            return Err(invalid("Alert rules need at least one channel".to_string()));
+        }
+        let mut channels = vec![];
+        for channel in self.channels {
+            let channel = match channel {
+                AlertChannelJson::Webhook { url } => {
+                    let valid = Url::parse(&url)
+                        .is_ok_and(|url| url.scheme() == "https" || url.scheme() == "http");
+                    if !valid {
+                        return Err(invalid(format!("Invalid webhook URL {url:?}")));
+                    }
+                    AlertChannel::Webhook { url }
+                },
+                AlertChannelJson::Email { to } => {
+                    if !to.contains('@') || to.contains(['<', '>', '\r', '\n']) {
+                        return Err(invalid(format!("Invalid email address {to:?}")));
+                    }
+                    AlertChannel::Email { to }
+                },
+            };
+            channels.push(channel);
+        }
+        Ok(AlertRule {
+            name: self.name,
+            metric,
+            threshold: self.threshold,
+            window_secs: self.window_secs,
+            channels,
+            state: AlertState::ok(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleJson {
+    id: String,
+    name: String,
+    metric: String,
+    threshold: f64,
+    window_secs: u64,
+    channels: Vec<AlertChannelJson>,
+    firing: bool,
+    /// When the rule last started or stopped firing.
+    state_since_secs: Option<u64>,
+    /// The metric's value then.
+    state_value: Option<f64>,
+}
+
+impl TryFrom<ParsedDocument<AlertRule>> for AlertRuleJson {
+    type Error = anyhow::Error;
+
+    fn try_from(rule: ParsedDocument<AlertRule>) -> anyhow::Result<Self> {
+        let id = rule.id().developer_id.encode();
+        let rule = rule.into_value();
+        Ok(Self {
+            id,
+            name: rule.name,
+            metric: rule.metric.to_string(),
+            threshold: rule.threshold,
+            window_secs: rule.window_secs,
+            channels: rule.channels.into_iter().map(Into::into).collect(),
+            firing: rule.state.firing,
+            state_since_secs: rule
+                .state
+                .since
+                .map(|since| anyhow::Ok(since.duration_since(SystemTime::UNIX_EPOCH)?.as_secs()))
+                .transpose()?,
+            state_value: rule.state.value,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAlertRulesResponse {
+    rules: Vec<AlertRuleJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAlertRuleResponse {
+    id: String,
+}
+
+pub async fn list_alert_rules(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let rules = st
+        .application
+        .list_alert_rules(identity)
+        .await?
+        .into_iter()
+        .map(AlertRuleJson::try_from)
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(ListAlertRulesResponse { rules }))
+}
+
+pub async fn create_alert_rule(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(args): Json<CreateAlertRuleArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let rule = args.into_rule()?;
+    let id = st.application.create_alert_rule(identity, rule).await?;
+    Ok(Json(CreateAlertRuleResponse { id }))
+}
+
+pub async fn delete_alert_rule(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(AlertRulePath { id }): Path<AlertRulePath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    st.application.delete_alert_rule(identity, &id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AlertChannelJson,
+        CreateAlertRuleArgs,
+    };
+
+    fn args(metric: &str, channels: Vec<AlertChannelJson>) -> CreateAlertRuleArgs {
+        CreateAlertRuleArgs {
+            name: "errors".to_string(),
+            metric: metric.to_string(),
+            threshold: 5.,
+            window_secs: 300,
+            channels,
+        }
+    }
+
+    #[test]
+    fn test_create_alert_rule_args() {
+        let webhook = || AlertChannelJson::Webhook {
+            url: "https://example.com/hook".to_string(),
+        };
+        let rule = args("errorRate", vec![webhook()]).into_rule().unwrap();
+        assert!(!rule.state.firing);
+        assert!(args("cpu", vec![webhook()]).into_rule().is_err());
+        assert!(args("errorRate", vec![]).into_rule().is_err());
+        let bad_url = AlertChannelJson::Webhook {
+            url: "ftp://example.com".to_string(),
+        };
+        assert!(args("errorRate", vec![bad_url]).into_rule().is_err());
+        let bad_email = AlertChannelJson::Email {
+            to: "oncall@example.com>\r\nRCPT TO:<someone@example.com".to_string(),
+        };
+        assert!(args("diskUsage", vec![bad_email]).into_rule().is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! A minimal SMTP client for alert emails, which sends one plain-text message
+//! per connection. `smtps://` URLs connect with TLS, and `smtp://` URLs
+//! upgrade the connection with STARTTLS before authenticating, so credentials
+//! are never sent in the clear.
+use std::{
+    io::{
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    net::TcpStream,
+    time::Duration,
+};
+
+use anyhow::Context;
+use openssl::ssl::{
+    SslConnector,
+    SslMethod,
+};
+use url::Url;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends `email` through the SMTP server at `smtp_url`, authenticating with
+/// the URL's username and password if it has them.
+pub async fn send(smtp_url: &Url, email: Email) -> anyhow::Result<()> {
+    let smtp_url = smtp_url.clone();
+    tokio::task::spawn_blocking(move || send_blocking(&smtp_url, &email)).await?
+}
+
+fn send_blocking(smtp_url: &Url, email: &Email) -> anyhow::Result<()> {
+    let host = smtp_url.host_str().context("SMTP URL has no host")?;
+    let implicit_tls = match smtp_url.scheme() {
+        "smtps" => true,
+        "smtp" => false,
+        scheme => anyhow::bail!("Unsupported SMTP URL scheme {scheme:?}"),
+    };
+    let port = smtp_url
+        .port()
+        .unwrap_or(if implicit_tls { 465 } else { 587 });
+    let tcp = TcpStream::connect((host, port))
+        .with_context(|| format!("Failed to connect to {host}:{port}"))?;
+    tcp.set_read_timeout(Some(SMTP_TIMEOUT))?;
+    tcp.set_write_timeout(Some(SMTP_TIMEOUT))?;
+    let connector = SslConnector::builder(SslMethod::tls())?.build();
+    if implicit_tls {
+        let mut stream = BufReader::new(connector.connect(host, tcp)?);
+        expect_reply(&mut stream, "greeting", 220)?;
+        send_message(&mut stream, smtp_url, email)
+    } else {
+        let mut stream = BufReader::new(tcp);
+        expect_reply(&mut stream, "greeting", 220)?;
+        command(&mut stream, "EHLO localhost", 250)?;
+        command(&mut stream, "STARTTLS", 220)?;
+        let mut stream = BufReader::new(connector.connect(host, stream.into_inner())?);
+        send_message(&mut stream, smtp_url, email)
+    }
+}
+
+/// Sends the message over a connection that's already secure.
+fn send_message<S: Read + Write>(
+    stream: &mut BufReader<S>,
+    smtp_url: &Url,
+    email: &Email,
+) -> anyhow::Result<()> {
+    command(stream, "EHLO localhost", 250)?;
+    if !smtp_url.username().is_empty() {
+        let username = urlencoding::decode(smtp_url.username())?;
+        let password = urlencoding::decode(smtp_url.password().unwrap_or(""))?;
+        let credentials = base64::encode(format!("\0{username}\0{password}"));
+        command(stream, &format!("AUTH PLAIN {credentials}"), 235)?;
+    }
+    command(stream, &format!("MAIL FROM:<{}>", email.from), 250)?;
+    command(stream, &format!("RCPT TO:<{}>", email.to), 250)?;
+    command(stream, "DATA", 354)?;
+    let date = chrono::Utc::now().to_rfc2822();
+    stream
+        .get_mut()
+        .write_all(format_message(email, &date).as_bytes())?;
+    expect_reply(stream, "DATA", 250)?;
+    // The message was accepted, so a failed goodbye doesn't matter.
+    let _ = command(stream, "QUIT", 221);
+    Ok(())
+}
+
+fn command<S: Read + Write>(
+    stream: &mut BufReader<S>,
+    command: &str,
+    expected_code: u16,
+) -> anyhow::Result<()> {
+    stream
+        .get_mut()
+        .write_all(format!("{command}\r\n").as_bytes())?;
+    // Only name the command in errors, since `AUTH` has the credentials.
+    let name = command.split(' ').next().unwrap_or(command);
+    expect_reply(stream, name, expected_code)
+}
+
+fn expect_reply<S: Read>(
+    stream: &mut BufReader<S>,
+    name: &str,
+    expected_code: u16,
+) -> anyhow::Result<()> {
+    let (code, message) = read_reply(stream)?;
+    anyhow::ensure!(
+        code == expected_code,
+        "SMTP server replied to {name} with {code} {message}"
+    );
+    Ok(())
+}
+
+/// Reads a reply's code and text. Multiline replies have a `-` after the code
+/// on every line but the last.
+fn read_reply(stream: &mut impl BufRead) -> anyhow::Result<(u16, String)> {
+    let mut lines = vec![];
+    loop {
+        let mut line = String::new();
+        anyhow::ensure!(
+            stream.read_line(&mut line)? > 0,
+            "SMTP server closed the connection"
+        );
+        let line = line.trim_end();
+        let code: u16 = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .with_context(|| format!("Invalid SMTP reply {line:?}"))?;
+        lines.push(line.get(4..).unwrap_or("").to_string());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, lines.join("\n")));
+        }
+    }
+}
+
+/// The message's headers and body as sent after `DATA`, up to and including
+/// the line with a single `.` that ends it.
+fn format_message(email: &Email, date: &str) -> String {
+    // Line breaks in a header would start new headers.
+    let subject = email.subject.replace(['\r', '\n'], " ");
+    let mut message = format!(
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {subject}\r\nDate: {date}\r\nMIME-Version: \
+         1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+        email.from, email.to,
+    );
+    for line in email.body.lines() {
+        // Lines starting with `.` get another one, so they don't end the
+        // message early.
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{
+        format_message,
+        read_reply,
+        Email,
+    };
+
+    #[test]
+    fn test_read_reply() -> anyhow::Result<()> {
+        let mut reply =
+            Cursor::new("250-smtp.example.com\r\n250-AUTH PLAIN\r\n250 OK\r\n354 Go\r\n");
+        assert_eq!(
+            read_reply(&mut reply)?,
+            (250, "smtp.example.com\nAUTH PLAIN\nOK".to_string())
+        );
+        assert_eq!(read_reply(&mut reply)?, (354, "Go".to_string()));
+        assert!(read_reply(&mut reply).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_message() {
+        let email = Email {
+            from: "alerts@example.com".to_string(),
+            to: "oncall@example.com".to_string(),
+            subject: "Alert\r\nBcc: someone@example.com".to_string(),
+            body: "errorRate is 12.5\n.\nDone".to_string(),
+        };
+        assert_eq!(
+            format_message(&email, "Fri, 16 Oct 2026 12:00:00 +0000"),
+            "From: <alerts@example.com>\r\nTo: <oncall@example.com>\r\nSubject: Alert  Bcc: \
+             someone@example.com\r\nDate: Fri, 16 Oct 2026 12:00:00 +0000\r\nMIME-Version: \
+             1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\r\nerrorRate is \
+             12.5\r\n..\r\nDone\r\n.\r\n"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Endpoints that issue anonymous identities, so users can act before they
+//! sign up, and upgrade them to the identity users sign up with. They're
+//! only served when `ANONYMOUS_IDENTITIES_ENABLED` is set.
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::http::{
+    extract::Json,
+    HttpResponseError,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueAnonymousIdentityArgs {
+    /// The client's current anonymous token, to renew it with the same
+    /// subject.
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueAnonymousIdentityResponse {
+    token: String,
+    subject: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeAnonymousIdentityArgs {
+    anonymous_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeAnonymousIdentityResponse {
+    anonymous_token_identifier: String,
+}
+
+/// Issues a new anonymous identity, or renews the one of `token`. Doesn't
+/// require authentication.
+pub async fn issue_anonymous_identity(
+    State(st): State<LocalAppState>,
+    Json(IssueAnonymousIdentityArgs { token }): Json<IssueAnonymousIdentityArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let (token, subject, ttl) = st.application.issue_anonymous_identity(token).await?;
+    Ok(Json(IssueAnonymousIdentityResponse {
+        token,
+        subject,
+        expires_in: ttl.as_secs(),
+    }))
+}
+
+/// Upgrades an anonymous identity to the user authenticated by the request's
+/// `Authorization: Bearer` header.
+pub async fn upgrade_anonymous_identity(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(UpgradeAnonymousIdentityArgs { anonymous_token }): Json<UpgradeAnonymousIdentityArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let anonymous_token_identifier = st
+        .application
+        .upgrade_anonymous_identity(identity, anonymous_token)
+        .await?;
+    Ok(Json(UpgradeAnonymousIdentityResponse {
+        anonymous_token_identifier,
+    }))
+}
@@ -0,0 +1,165 @@
+//! Endpoints for managing API keys, which let scripts and services call
+//! functions and the REST API with only the scopes they need. See
+//! [`ApiKeyScopes`] for the available scopes.
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use keybroker::ApiKeyScopes;
+use model::api_keys::types::ApiKeyMetadata;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct ApiKeyPath {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyArgs {
+    name: String,
+    scopes: Vec<String>,
+    /// How long the key is valid for. Keys without one don't expire.
+    expires_in_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyJson {
+    id: String,
+    name: String,
+    scopes: Vec<String>,
+    /// Milliseconds since the epoch.
+    expires_at: Option<u64>,
+}
+
+impl ApiKeyJson {
+    fn new(id: String, metadata: ApiKeyMetadata) -> Self {
+        Self {
+            id,
+            name: metadata.name,
+            scopes: metadata.scopes.to_strings(),
+            expires_at: metadata.expiration.map(|expiration| {
+                expiration
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64
+            }),
+        }
+    }
+}
+
+impl From<ParsedDocument<ApiKeyMetadata>> for ApiKeyJson {
+    fn from(api_key: ParsedDocument<ApiKeyMetadata>) -> Self {
+        let id = api_key.id().developer_id.encode();
+        Self::new(id, api_key.into_value())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListApiKeysResponse {
+    api_keys: Vec<ApiKeyJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    api_key: ApiKeyJson,
+    /// The key itself, which is only returned when it's created.
+    key: String,
+}
+
+pub async fn list_api_keys(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let api_keys = st.application.list_api_keys(identity).await?;
+    Ok(Json(ListApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKeyJson::from).collect(),
+    }))
+}
+
+pub async fn create_api_key(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(CreateApiKeyArgs {
+        name,
+        scopes,
+        expires_in_secs,
+    }): Json<CreateApiKeyArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let scopes = ApiKeyScopes::parse(scopes)?;
+    let expiration = expires_in_secs
+        .map(|secs| {
+            SystemTime::now()
+                .checked_add(Duration::from_secs(secs))
+                .filter(|_| secs > 0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(ErrorMetadata::bad_request(
+                        "InvalidApiKeyExpiration",
+                        format!("Invalid `expiresInSecs`: {secs}"),
+                    ))
+                })
+        })
+        .transpose()?;
+    let (id, key) = st
+        .application
+        .create_api_key(identity, name.clone(), scopes.clone(), expiration)
+        .await?;
+    Ok(Json(CreateApiKeyResponse {
+        api_key: ApiKeyJson::new(
+            id,
+            ApiKeyMetadata {
+                name,
+                scopes,
+                expiration,
+            },
+        ),
+        key: key.as_string(),
+    }))
+}
+
+/// Revokes an API key, after which requests using it are rejected.
+pub async fn revoke_api_key(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(ApiKeyPath { id }): Path<ApiKeyPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    st.application.revoke_api_key(identity, &id).await?;
+    Ok(StatusCode::OK)
+}
@@ -1,3 +1,9 @@
+use std::collections::BTreeMap;
+
+use application::{
+    error_groups::ErrorGroup,
+    function_log::TableMetrics,
+};
 use axum::{
     extract::State,
     response::IntoResponse,
@@ -14,15 +20,21 @@ use common::{
         },
         HttpResponseError,
     },
+    runtime::UnixTimestamp,
     types::{
         UdfIdentifier,
         UdfType,
     },
 };
-use serde::Deserialize;
+use database::OccConflictPair;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use sync_types::UdfPath;
 
 use crate::{
+    admin::must_be_admin,
     authentication::ExtractIdentity,
     LocalAppState,
 };
@@ -163,6 +175,46 @@ pub(crate) async fn latency_percentiles(
     Ok(Json(timeseries))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExecutionProfileQueryArgs {
+    component_path: Option<String>,
+    #[serde(alias = "path")]
+    udf_path: String,
+    percentiles: Option<String>,
+    window: String,
+    udf_type: Option<String>,
+}
+
+/// Percentiles of the time a function spends reading from the database,
+/// running JavaScript, waiting on fetches and committing, keyed by phase.
+pub(crate) async fn execution_profile(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(query_args): Query<ExecutionProfileQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let udf_identifier = parse_udf_identifier(
+        query_args.udf_type,
+        query_args.component_path,
+        query_args.udf_path,
+    )?;
+    let percentiles: Vec<usize> = match query_args.percentiles {
+        Some(percentiles) => serde_json::from_str(&percentiles).map_err(anyhow::Error::new)?,
+        None => vec![50, 95, 99],
+    };
+    let window_json: serde_json::Value =
+        serde_json::from_str(&query_args.window).map_err(anyhow::Error::new)?;
+    let window = window_json.try_into()?;
+    let profile: BTreeMap<_, Vec<_>> = st
+        .application
+        .execution_profile(identity, udf_identifier, percentiles, window)
+        .await?
+        .into_iter()
+        .map(|(phase, percentiles)| (phase.as_str(), percentiles.into_iter().collect()))
+        .collect();
+    Ok(Json(profile))
+}
+
 #[derive(Deserialize)]
 pub(crate) struct TableRateQueryArgs {
     name: String,
@@ -186,6 +238,55 @@ pub(crate) async fn table_rate(
     Ok(Json(timeseries))
 }
 
+#[derive(Deserialize)]
+pub(crate) struct TableMetricsQueryArgs {
+    window: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TableMetricsJson {
+    table_name: String,
+    document_count: u64,
+    total_size: u64,
+    rows_read: u64,
+    rows_written: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl From<TableMetrics> for TableMetricsJson {
+    fn from(table: TableMetrics) -> Self {
+        Self {
+            table_name: table.table_name.to_string(),
+            document_count: table.document_count,
+            total_size: table.total_size,
+            rows_read: table.activity.rows_read,
+            rows_written: table.activity.rows_written,
+            bytes_read: table.activity.bytes_read,
+            bytes_written: table.activity.bytes_written,
+        }
+    }
+}
+
+/// Each table's size and what was read from and written to it in the window.
+pub(crate) async fn table_metrics(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(query_args): Query<TableMetricsQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let window_json: serde_json::Value =
+        serde_json::from_str(&query_args.window).map_err(anyhow::Error::new)?;
+    let window = window_json.try_into()?;
+    let tables: Vec<TableMetricsJson> = st
+        .application
+        .table_metrics(identity, window)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(tables))
+}
+
 fn parse_udf_identifier(
     udf_type: Option<String>,
     component_path: Option<String>,
@@ -234,3 +335,138 @@ pub(crate) async fn scheduled_job_lag(
     let timeseries = st.application.scheduled_job_lag(identity, window).await?;
     Ok(Json(timeseries))
 }
+
+#[derive(Deserialize)]
+pub(crate) struct ErrorGroupsQueryArgs {
+    /// Only groups seen at or after this many milliseconds since the Unix
+    /// epoch.
+    since: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorGroupJson {
+    fingerprint: String,
+    normalized_message: String,
+    latest_message: String,
+    count: u64,
+    first_seen: f64,
+    last_seen: f64,
+    functions: BTreeMap<String, u64>,
+    count_last_hour: u64,
+    spiking: bool,
+}
+
+impl From<ErrorGroup> for ErrorGroupJson {
+    fn from(group: ErrorGroup) -> Self {
+        Self {
+            fingerprint: group.fingerprint,
+            normalized_message: group.normalized_message,
+            latest_message: group.latest_message,
+            count: group.count,
+            first_seen: group.first_seen.as_secs_f64(),
+            last_seen: group.last_seen.as_secs_f64(),
+            functions: group.functions,
+            count_last_hour: group.count_last_hour,
+            spiking: group.spiking,
+        }
+    }
+}
+
+/// Function errors grouped by fingerprint, most recently seen first.
+pub(crate) async fn error_groups(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(query_args): Query<ErrorGroupsQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let since = query_args.since.map(UnixTimestamp::from_millis);
+    let groups: Vec<ErrorGroupJson> = st
+        .application
+        .error_groups(identity, since)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(groups))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OccConflictPairJson {
+    writer: Option<String>,
+    conflicting_writer: Option<String>,
+    count: u64,
+    first_seen: f64,
+    last_seen: f64,
+    indexes: BTreeMap<String, u64>,
+    latest_document_id: String,
+}
+
+impl From<OccConflictPair> for OccConflictPairJson {
+    fn from(pair: OccConflictPair) -> Self {
+        Self {
+            writer: pair.writer,
+            conflicting_writer: pair.conflicting_writer,
+            count: pair.count,
+            first_seen: pair.first_seen.as_secs_f64(),
+            last_seen: pair.last_seen.as_secs_f64(),
+            indexes: pair.indexes,
+            latest_document_id: pair.latest_document_id,
+        }
+    }
+}
+
+/// OCC conflicts by the function that failed to commit and the function whose
+/// write it conflicted with, most conflicts first.
+pub(crate) async fn occ_conflicts(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let pairs: Vec<OccConflictPairJson> = st
+        .application
+        .occ_conflicts(identity)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(pairs))
+}
+
+/// The backend's Prometheus metrics, for the public listener when it serves
+/// the admin routes. Scrapers send the admin key as `Authorization: Convex
+/// <admin key>`.
+pub(crate) async fn prometheus_metrics(
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    common::http::metrics().await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use runtime::prod::ProdRuntime;
+
+    use crate::test_helpers::setup_backend_for_test;
+
+    #[convex_macro::prod_rt_test]
+    async fn test_metrics_require_admin_key_without_admin_port(
+        rt: ProdRuntime,
+    ) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        let req = Request::builder().uri("/metrics").body(Body::empty())?;
+        backend
+            .expect_error(req, StatusCode::FORBIDDEN, "BadDeployKey")
+            .await?;
+
+        let req = Request::builder()
+            .uri("/metrics")
+            .header("Authorization", backend.admin_auth_header.0.encode())
+            .body(Body::empty())?;
+        let response = backend.send(req).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+}
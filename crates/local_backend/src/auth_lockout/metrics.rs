@@ -0,0 +1,35 @@
+use metrics::{
+    log_counter,
+    log_counter_with_labels,
+    register_convex_counter,
+    StaticMetricLabel,
+};
+
+register_convex_counter!(
+    AUTH_FAILURE_TOTAL,
+    "Count of requests that failed to authenticate"
+);
+pub fn log_auth_failure() {
+    log_counter(&AUTH_FAILURE_TOTAL, 1);
+}
+
+register_convex_counter!(
+    AUTH_LOCKOUT_TOTAL,
+    "Count of IP addresses and users locked out after failing to authenticate",
+    &["key_type"]
+);
+pub fn log_auth_lockout(key_type: &'static str) {
+    log_counter_with_labels(
+        &AUTH_LOCKOUT_TOTAL,
+        1,
+        vec![StaticMetricLabel::new("key_type", key_type)],
+    );
+}
+
+register_convex_counter!(
+    AUTH_LOCKOUT_REJECTED_TOTAL,
+    "Count of requests rejected because their IP address or user was locked out"
+);
+pub fn log_auth_lockout_rejected() {
+    log_counter(&AUTH_LOCKOUT_REJECTED_TOTAL, 1);
+}
@@ -0,0 +1,565 @@
+//! Slows down credential stuffing by locking out IP addresses and users that
+//! fail to authenticate too often.
+//!
+//! `auth_lockout_middleware` counts the API requests that fail with a 401, or
+//! with a bad deploy key, against the caller's IP address. After
+//! `--auth-lockout-failures` failures within `--auth-lockout-window-secs`, its
+//! requests are rejected with a 429 until the lockout ends.
+//!
+//! Failures are only counted against the user a JWT is for if its signature
+//! was verified, like for expired or revoked tokens, since anyone can make up
+//! an unsigned token for any user. A locked out user's requests still run,
+//! and only the ones that fail to authenticate get a 429 instead, so a valid
+//! token for the user always works.
+//!
+//! Each lockout that follows another lasts twice as long, up to `MAX_LOCKOUT`,
+//! and they're forgotten after `MAX_LOCKOUT` without failures. WebSocket
+//! clients authenticate after they've connected, so their failures aren't
+//! counted.
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+    num::NonZeroU32,
+    sync::{
+        Arc,
+        Weak,
+    },
+    time::Duration,
+};
+
+use authentication::{
+    unverified_token_identifier,
+    VERIFIED_TOKEN_ERROR_CODES,
+};
+use axum::{
+    extract::{
+        ConnectInfo,
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+    RequestPartsExt,
+};
+use common::{
+    http::{
+        HttpResponseError,
+        ResponseErrorCode,
+    },
+    runtime::Runtime,
+};
+use errors::ErrorMetadata;
+use http::{
+    header::RETRY_AFTER,
+    HeaderValue,
+    StatusCode,
+};
+use parking_lot::Mutex;
+use runtime::prod::ProdRuntime;
+use sync_types::AuthenticationToken;
+use tokio::time::Instant;
+
+use crate::{
+    authentication::ExtractAuthenticationToken,
+    LocalAppState,
+};
+
+mod metrics;
+
+use self::metrics::{
+    log_auth_failure,
+    log_auth_lockout,
+    log_auth_lockout_rejected,
+};
+
+/// The longest a lockout can last.
+const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+/// Most IP addresses to count failures for.
+const MAX_TRACKED_IPS: usize = 100_000;
+/// Most users to count failures for. They have their own budget, so users
+/// can't crowd out IP addresses.
+const MAX_TRACKED_USERS: usize = 100_000;
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthLockoutPolicy {
+    pub max_failures: NonZeroU32,
+    pub window: Duration,
+    /// How long the first lockout lasts.
+    pub lockout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum LockoutKey {
+    Ip(IpAddr),
+    /// A token identifier, `<issuer>|<subject>`.
+    User(String),
+}
+
+impl LockoutKey {
+    fn key_type(&self) -> &'static str {
+        match self {
+            Self::Ip(_) => "ip",
+            Self::User(_) => "user",
+        }
+    }
+
+    fn max_tracked(&self) -> usize {
+        match self {
+            Self::Ip(_) => MAX_TRACKED_IPS,
+            Self::User(_) => MAX_TRACKED_USERS,
+        }
+    }
+}
+
+impl fmt::Display for LockoutKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "IP address {ip}"),
+            Self::User(token_identifier) => write!(f, "user {token_identifier}"),
+        }
+    }
+}
+
+struct Failures {
+    /// Failures since `window_start`.
+    count: u32,
+    window_start: Instant,
+    last_failure: Instant,
+    /// Lockouts since the key was last forgotten.
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct LockoutState {
+    ip_failures: HashMap<LockoutKey, Failures>,
+    user_failures: HashMap<LockoutKey, Failures>,
+}
+
+impl LockoutState {
+    fn failures(&self, key: &LockoutKey) -> &HashMap<LockoutKey, Failures> {
+        match key {
+            LockoutKey::Ip(_) => &self.ip_failures,
+            LockoutKey::User(_) => &self.user_failures,
+        }
+    }
+
+    fn failures_mut(&mut self, key: &LockoutKey) -> &mut HashMap<LockoutKey, Failures> {
+        match key {
+            LockoutKey::Ip(_) => &mut self.ip_failures,
+            LockoutKey::User(_) => &mut self.user_failures,
+        }
+    }
+
+    /// How much longer the longest lockout of any of `keys` lasts.
+    fn locked_out_for(&self, keys: &[LockoutKey], now: Instant) -> Option<Duration> {
+        keys.iter()
+            .filter_map(|key| self.failures(key).get(key)?.locked_until)
+            .filter(|locked_until| *locked_until > now)
+            .map(|locked_until| locked_until - now)
+            .max()
+    }
+
+    /// Counts a failure against each of `keys`, returning the ones it locked
+    /// out and for how long.
+    fn record_failure(
+        &mut self,
+        policy: &AuthLockoutPolicy,
+        keys: Vec<LockoutKey>,
+        now: Instant,
+    ) -> Vec<(LockoutKey, Duration)> {
+        let mut locked_out = vec![];
+        for key in keys {
+            let max_tracked = key.max_tracked();
+            let tracked = self.failures_mut(&key);
+            if !tracked.contains_key(&key) && tracked.len() >= max_tracked {
+                continue;
+            }
+            let failures = tracked.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                window_start: now,
+                last_failure: now,
+                lockouts: 0,
+                locked_until: None,
+            });
+            if now - failures.window_start >= policy.window {
+                failures.count = 0;
+                failures.window_start = now;
+            }
+            failures.count += 1;
+            failures.last_failure = now;
+            if failures.count >= policy.max_failures.get() {
+                let lockout = policy
+                    .lockout
+                    .saturating_mul(1 << failures.lockouts.min(16))
+                    .min(MAX_LOCKOUT);
+                failures.count = 0;
+                failures.window_start = now;
+                failures.lockouts += 1;
+                failures.locked_until = Some(now + lockout);
+                locked_out.push((key, lockout));
+            }
+        }
+        locked_out
+    }
+
+    /// Forgets the keys that haven't failed for `MAX_LOCKOUT` and aren't
+    /// locked out.
+    fn forget_idle(&mut self, now: Instant) {
+        let is_active = |_: &LockoutKey, failures: &mut Failures| {
+            now - failures.last_failure < MAX_LOCKOUT
+                || failures
+                    .locked_until
+                    .is_some_and(|locked_until| locked_until > now)
+        };
+        self.ip_failures.retain(is_active);
+        self.user_failures.retain(is_active);
+    }
+}
+
+pub struct AuthLockouts {
+    runtime: ProdRuntime,
+    /// Lockouts are off if this is `None`.
+    policy: Option<AuthLockoutPolicy>,
+    state: Mutex<LockoutState>,
+}
+
+impl AuthLockouts {
+    pub fn new(runtime: ProdRuntime, policy: Option<AuthLockoutPolicy>) -> Arc<Self> {
+        let lockouts = Arc::new(Self {
+            runtime: runtime.clone(),
+            policy,
+            state: Mutex::new(LockoutState::default()),
+        });
+        if lockouts.policy.is_some() {
+            runtime.spawn(
+                "auth_lockout_cleanup",
+                Self::cleanup(runtime.clone(), Arc::downgrade(&lockouts)),
+            );
+        }
+        lockouts
+    }
+
+    async fn cleanup(runtime: ProdRuntime, lockouts: Weak<Self>) {
+        loop {
+            runtime.wait(CLEANUP_INTERVAL).await;
+            let Some(lockouts) = lockouts.upgrade() else {
+                return;
+            };
+            lockouts.state.lock().forget_idle(runtime.monotonic_now());
+        }
+    }
+}
+
+/// Whether the response is for a request that failed to authenticate.
+fn is_auth_failure(response: &Response) -> bool {
+    // Bad deploy keys sent in request bodies are reported as forbidden.
+    response.status() == StatusCode::UNAUTHORIZED
+        || response
+            .extensions()
+            .get::<ResponseErrorCode>()
+            .is_some_and(|ResponseErrorCode(code)| code == "BadDeployKey")
+}
+
+/// Whether the response is for a user token that failed to authenticate even
+/// though its signature was verified.
+fn is_verified_token_failure(response: &Response) -> bool {
+    response
+        .extensions()
+        .get::<ResponseErrorCode>()
+        .is_some_and(|ResponseErrorCode(code)| VERIFIED_TOKEN_ERROR_CODES.contains(&&**code))
+}
+
+fn too_many_failures(wait_time: Duration) -> Response {
+    log_auth_lockout_rejected();
+    let error = anyhow::anyhow!(ErrorMetadata::rate_limited(
+        "TooManyAuthFailures",
+        format!(
+            "Too many failed attempts to authenticate. Retry in {}s.",
+            wait_time.as_secs().max(1)
+        ),
+    ));
+    let mut response = HttpResponseError::from(error).into_response();
+    let retry_after = wait_time.as_secs_f64().ceil().max(1.0) as u64;
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+pub async fn auth_lockout_middleware(
+    State(st): State<LocalAppState>,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let lockouts = &st.auth_lockouts;
+    let Some(policy) = &lockouts.policy else {
+        return next.run(req).await;
+    };
+    let (mut parts, body) = req.into_parts();
+    let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+    // Requests without a peer address come from within the process.
+    let ip_key = st
+        .trusted_proxies
+        .client_ip(remote_ip, &parts.headers)
+        .map(LockoutKey::Ip);
+    let mut user_key = None;
+    if let Ok(ExtractAuthenticationToken(AuthenticationToken::User(token))) =
+        parts.extract::<ExtractAuthenticationToken>().await
+    {
+        user_key = unverified_token_identifier(&token).map(LockoutKey::User);
+    }
+    let now = lockouts.runtime.monotonic_now();
+    let ip_locked_out_for = lockouts.state.lock().locked_out_for(ip_key.as_slice(), now);
+    if let Some(wait_time) = ip_locked_out_for {
+        return too_many_failures(wait_time);
+    }
+    let response = next.run(Request::from_parts(parts, body)).await;
+    if !is_auth_failure(&response) {
+        return response;
+    }
+    log_auth_failure();
+    let mut keys: Vec<_> = ip_key.into_iter().collect();
+    if is_verified_token_failure(&response) {
+        keys.extend(user_key.clone());
+    }
+    let now = lockouts.runtime.monotonic_now();
+    let (locked_out, user_locked_out_for) = {
+        let mut state = lockouts.state.lock();
+        let locked_out = state.record_failure(policy, keys, now);
+        (locked_out, state.locked_out_for(user_key.as_slice(), now))
+    };
+    for (key, lockout) in locked_out {
+        log_auth_lockout(key.key_type());
+        tracing::warn!(
+            "Locked out {key} for {lockout:?} after too many failed attempts to authenticate"
+        );
+    }
+    match user_locked_out_for {
+        Some(wait_time) => too_many_failures(wait_time),
+        None => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        num::NonZeroU32,
+        time::Duration,
+    };
+
+    use application::test_helpers::ApplicationTestExt;
+    use axum::body::Body;
+    use common::{
+        auth::{
+            CustomJwtKey,
+            CustomJwtProvider,
+        },
+        types::MemberId,
+    };
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use keybroker::{
+        AdminIdentity,
+        Identity,
+    };
+    use model::token_revocations::types::RevokedTokens;
+    use runtime::prod::ProdRuntime;
+    use serde_json::json;
+    use tokio::time::Instant;
+
+    use super::{
+        AuthLockoutPolicy,
+        LockoutKey,
+        LockoutState,
+        MAX_LOCKOUT,
+        MAX_TRACKED_USERS,
+    };
+    use crate::test_helpers::setup_backend_for_test;
+
+    const ISSUER: &str = "https://auth.example.com";
+
+    #[test]
+    fn test_lockouts() -> anyhow::Result<()> {
+        let policy = AuthLockoutPolicy {
+            max_failures: NonZeroU32::new(3).unwrap(),
+            window: Duration::from_secs(60),
+            lockout: Duration::from_secs(30),
+        };
+        let ip = LockoutKey::Ip("203.0.113.7".parse()?);
+        let user = LockoutKey::User("https://issuer.example.com|alice".to_string());
+        let mut state = LockoutState::default();
+        let start = Instant::now();
+
+        // Failures outside of the window don't add up.
+        state.record_failure(&policy, vec![ip.clone()], start);
+        state.record_failure(&policy, vec![ip.clone()], start + Duration::from_secs(1));
+        let later = start + Duration::from_secs(61);
+        assert!(state
+            .record_failure(&policy, vec![ip.clone()], later)
+            .is_empty());
+        assert_eq!(state.locked_out_for(&[ip.clone()], later), None);
+
+        // The third failure in the window locks out both keys.
+        state.record_failure(&policy, vec![ip.clone(), user.clone()], later);
+        let locked_out = state.record_failure(&policy, vec![ip.clone(), user.clone()], later);
+        assert_eq!(locked_out, vec![(ip.clone(), Duration::from_secs(30))]);
+        assert_eq!(
+            state.locked_out_for(&[user.clone(), ip.clone()], later + Duration::from_secs(10)),
+            Some(Duration::from_secs(20))
+        );
+        let after_lockout = later + Duration::from_secs(30);
+        assert_eq!(state.locked_out_for(&[ip.clone()], after_lockout), None);
+
+        // The next lockout lasts twice as long.
+        for _ in 0..3 {
+            state.record_failure(&policy, vec![ip.clone()], after_lockout);
+        }
+        assert_eq!(
+            state.locked_out_for(&[ip.clone()], after_lockout),
+            Some(Duration::from_secs(60))
+        );
+
+        // Keys are forgotten once they've been idle for long enough.
+        state.forget_idle(after_lockout + MAX_LOCKOUT);
+        assert!(state.ip_failures.is_empty() && state.user_failures.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_users_dont_crowd_out_ips() -> anyhow::Result<()> {
+        let policy = AuthLockoutPolicy {
+            max_failures: NonZeroU32::new(1).unwrap(),
+            window: Duration::from_secs(60),
+            lockout: Duration::from_secs(30),
+        };
+        let mut state = LockoutState::default();
+        let now = Instant::now();
+        for i in 0..=MAX_TRACKED_USERS {
+            let user = LockoutKey::User(format!("https://issuer.example.com|user{i}"));
+            state.record_failure(&policy, vec![user], now);
+        }
+        assert_eq!(state.user_failures.len(), MAX_TRACKED_USERS);
+
+        let ip = LockoutKey::Ip("203.0.113.7".parse()?);
+        let locked_out = state.record_failure(&policy, vec![ip.clone()], now);
+        assert_eq!(locked_out, vec![(ip.clone(), Duration::from_secs(30))]);
+        assert!(state.locked_out_for(&[ip], now).is_some());
+        Ok(())
+    }
+
+    /// A query authenticated with `token`.
+    fn query(token: &str) -> anyhow::Result<Request<Body>> {
+        let body = json!({ "path": "values:intQuery", "args": {} });
+        Ok(Request::builder()
+            .uri("/api/query")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::from(serde_json::to_vec(&body)?))?)
+    }
+
+    /// The `jti` claim of a JWT.
+    fn token_id(token: &str) -> anyhow::Result<String> {
+        let payload = token.split('.').nth(1).unwrap_or_default();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?)?;
+        Ok(claims["jti"].as_str().unwrap().to_string())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_forged_tokens_dont_lock_out_users(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        let max_failures = backend
+            .st
+            .auth_lockouts
+            .policy
+            .as_ref()
+            .unwrap()
+            .max_failures
+            .get();
+        let application = &backend.st.application;
+        application.load_udf_tests_modules().await?;
+        let admin = Identity::InstanceAdmin(AdminIdentity::new_for_test_only(
+            application.instance_name(),
+            MemberId(1),
+        ));
+        application
+            .create_custom_jwt_provider(
+                admin.clone(),
+                CustomJwtProvider {
+                    issuer: ISSUER.to_string(),
+                    audience: None,
+                    algorithm: "HS256".to_string(),
+                    key: CustomJwtKey::Secret("secret".to_string()),
+                    required_claims: BTreeMap::new(),
+                    claim_mapping: BTreeMap::new(),
+                },
+            )
+            .await?;
+        let token_identifier = format!("{ISSUER}|user1");
+        let (token, _) = application
+            .exchange_token(admin.clone(), token_identifier.clone())
+            .await?;
+        let (revoked_token, _) = application
+            .exchange_token(admin.clone(), token_identifier)
+            .await?;
+
+        // Tokens with the user's claims but a bad signature don't count
+        // against the user.
+        let (unsigned, _) = token.rsplit_once('.').unwrap();
+        let forged = format!("{unsigned}.{}", "A".repeat(43));
+        for _ in 0..max_failures * 2 {
+            backend
+                .expect_error(
+                    query(&forged)?,
+                    StatusCode::UNAUTHORIZED,
+                    "InvalidCustomJwt",
+                )
+                .await?;
+        }
+        backend
+            .expect_success::<serde_json::Value>(query(&token)?)
+            .await?;
+
+        // Tokens that were signed but revoked do, and once the user is locked
+        // out their failures get a 429.
+        application
+            .revoke_tokens(admin, RevokedTokens::TokenId(token_id(&revoked_token)?))
+            .await?;
+        for _ in 1..max_failures {
+            backend
+                .expect_error(
+                    query(&revoked_token)?,
+                    StatusCode::UNAUTHORIZED,
+                    "TokenRevoked",
+                )
+                .await?;
+        }
+        backend
+            .expect_error(
+                query(&revoked_token)?,
+                StatusCode::TOO_MANY_REQUESTS,
+                "TooManyAuthFailures",
+            )
+            .await?;
+
+        // Valid tokens for the user still work while it's locked out.
+        backend
+            .expect_success::<serde_json::Value>(query(&token)?)
+            .await?;
+        Ok(())
+    }
+}
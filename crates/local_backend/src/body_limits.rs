@@ -0,0 +1,106 @@
+use axum::extract::DefaultBodyLimit;
+use common::knobs::MAX_BACKEND_PUBLIC_API_REQUEST_SIZE;
+use tower::{
+    layer::util::{
+        Identity,
+        Stack,
+    },
+    ServiceBuilder,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+use udf::HTTP_ACTION_BODY_LIMIT;
+
+/// The largest request bodies each class of route accepts, in bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimits {
+    /// Calls to functions through the public API, which mostly hold their
+    /// arguments.
+    pub function_args: usize,
+    /// File storage uploads. Unlimited if unset.
+    pub file_upload: Option<usize>,
+    /// Snapshot imports, and each part of a multipart import. Unlimited if
+    /// unset.
+    pub snapshot_import: Option<usize>,
+    pub http_actions: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            function_args: *MAX_BACKEND_PUBLIC_API_REQUEST_SIZE,
+            file_upload: None,
+            snapshot_import: None,
+            http_actions: HTTP_ACTION_BODY_LIMIT,
+        }
+    }
+}
+
+pub type BodyLimitLayer =
+    ServiceBuilder<Stack<RequestBodyLimitLayer, Stack<DefaultBodyLimit, Identity>>>;
+
+/// Limits request bodies to `max_bytes`. `DefaultBodyLimit` only applies to
+/// extractors like `Json`, so this also limits handlers that stream the body,
+/// rejecting requests whose `Content-Length` is too large up front.
+pub fn body_limit(max_bytes: usize) -> BodyLimitLayer {
+    ServiceBuilder::new()
+        .layer(DefaultBodyLimit::max(max_bytes))
+        .layer(RequestBodyLimitLayer::new(max_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{
+            Body,
+            Bytes,
+        },
+        routing::post,
+        Router,
+    };
+    use futures::TryStreamExt;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use tower::ServiceExt;
+
+    use super::body_limit;
+
+    async fn status(router: &Router, body: &'static str) -> anyhow::Result<StatusCode> {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .body(Body::from(body))?;
+        Ok(router.clone().oneshot(request).await?.status())
+    }
+
+    #[tokio::test]
+    async fn test_body_limit() -> anyhow::Result<()> {
+        let extracted = Router::new()
+            .route("/", post(|_: Bytes| async {}))
+            .layer(body_limit(4));
+        assert_eq!(status(&extracted, "1234").await?, StatusCode::OK);
+        assert_eq!(
+            status(&extracted, "12345").await?,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let streamed = Router::new()
+            .route(
+                "/",
+                post(|body: Body| async move {
+                    match body.into_data_stream().try_collect::<Vec<_>>().await {
+                        Ok(_) => StatusCode::OK,
+                        Err(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                    }
+                }),
+            )
+            .layer(body_limit(4));
+        assert_eq!(status(&streamed, "1234").await?, StatusCode::OK);
+        assert_eq!(
+            status(&streamed, "12345").await?,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        Ok(())
+    }
+}
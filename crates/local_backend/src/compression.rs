@@ -0,0 +1,121 @@
+use common::knobs::{
+    RESPONSE_COMPRESSION_ENABLED,
+    RESPONSE_COMPRESSION_MIN_BYTES,
+};
+use tower_http::compression::{
+    predicate::{
+        NotForContentType,
+        Predicate,
+        SizeAbove,
+    },
+    CompressionLayer,
+};
+
+/// Compresses responses with the best encoding the client accepts.
+///
+/// Server-Sent Events and gRPC responses are left alone since compressing
+/// them would hold back their messages, as are images, which are already
+/// compressed. Export downloads are compressed as they stream from storage,
+/// since their stored entries aren't all deflated. The sync protocol's
+/// WebSocket messages are compressed with permessage-deflate instead, see
+/// `subs::deflate`.
+pub fn compression() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(*RESPONSE_COMPRESSION_MIN_BYTES)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::IMAGES);
+    let enabled = *RESPONSE_COMPRESSION_ENABLED;
+    CompressionLayer::new()
+        .br(enabled)
+        .zstd(enabled)
+        .gzip(enabled)
+        .deflate(false)
+        .compress_when(predicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        routing::get,
+        Router,
+    };
+    use bytes::Bytes;
+    use http::{
+        header::{
+            ACCEPT_ENCODING,
+            CONTENT_ENCODING,
+            CONTENT_LENGTH,
+            CONTENT_TYPE,
+        },
+        Request,
+    };
+    use tower::ServiceExt;
+
+    use super::compression;
+
+    async fn content_encoding(path: &str, accept_encoding: &str) -> anyhow::Result<Option<String>> {
+        let large_json = format!("[{}]", vec!["\"value\""; 1000].join(","));
+        let router = Router::new()
+            .route(
+                "/large",
+                get(move || async move { ([(CONTENT_TYPE, "application/json")], large_json) }),
+            )
+            .route("/small", get(|| async { "[]" }))
+            .route(
+                "/export",
+                get(|| async {
+                    let chunks = (0..100).map(|_| {
+                        Ok::<_, std::io::Error>(Bytes::from_static(b"{\"_id\":\"value\"}\n"))
+                    });
+                    (
+                        [(CONTENT_TYPE, "application/zip"), (CONTENT_LENGTH, "1700")],
+                        Body::from_stream(futures::stream::iter(chunks)),
+                    )
+                }),
+            )
+            .route(
+                "/events",
+                get(|| async {
+                    (
+                        [(CONTENT_TYPE, "text/event-stream")],
+                        "data: x\n\n".repeat(1000),
+                    )
+                }),
+            )
+            .layer(compression());
+        let request = Request::builder()
+            .uri(path)
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())?;
+        let response = router.oneshot(request).await?;
+        Ok(response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|h| h.to_str().map(str::to_string))
+            .transpose()?)
+    }
+
+    #[tokio::test]
+    async fn test_compression() -> anyhow::Result<()> {
+        assert_eq!(
+            content_encoding("/large", "br").await?.as_deref(),
+            Some("br")
+        );
+        assert_eq!(
+            content_encoding("/large", "gzip;q=0.5, zstd")
+                .await?
+                .as_deref(),
+            Some("zstd")
+        );
+        assert_eq!(content_encoding("/large", "identity").await?, None);
+        assert_eq!(content_encoding("/small", "br").await?, None);
+        // Exports stream from storage with their length in the header.
+        assert_eq!(
+            content_encoding("/export", "gzip").await?.as_deref(),
+            Some("gzip")
+        );
+        assert_eq!(content_encoding("/events", "br").await?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use http::{
+    HeaderName,
+    HeaderValue,
+};
+
+/// Which browser origins may call the public API, and HTTP actions if
+/// `http_actions` is set, and what they may send. The default allows any
+/// origin to send credentials.
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    /// Origins like `https://example.com`, or `https://*.example.com` for any
+    /// of its subdomains. Any origin is allowed if this is empty.
+    pub allowed_origins: Vec<String>,
+    /// Request headers allowed in addition to the ones Convex clients send.
+    pub allowed_headers: Vec<HeaderName>,
+    pub allow_credentials: bool,
+    /// Whether to apply the policy to HTTP actions too. The backend then
+    /// answers their preflight requests, and its CORS headers replace any the
+    /// HTTP actions set.
+    pub http_actions: bool,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            allowed_headers: vec![],
+            allow_credentials: true,
+            http_actions: false,
+        }
+    }
+}
+
+impl CorsPolicy {
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        if self.allowed_origins.is_empty() {
+            return true;
+        }
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        self.allowed_origins
+            .iter()
+            .any(|allowed| match allowed.split_once("://*.") {
+                Some((scheme, domain)) => origin
+                    .strip_prefix(scheme)
+                    .and_then(|origin| origin.strip_prefix("://"))
+                    .and_then(|host| host.strip_suffix(domain))
+                    .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+                None => allowed.eq_ignore_ascii_case(origin),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::CorsPolicy;
+
+    #[test]
+    fn test_allows_origin() {
+        let policy = CorsPolicy {
+            allowed_origins: vec![
+                "https://example.com".to_string(),
+                "https://*.preview.example.com".to_string(),
+            ],
+            ..Default::default()
+        };
+        let allows = |origin: &'static str| policy.allows_origin(&HeaderValue::from_static(origin));
+        assert!(allows("https://example.com"));
+        assert!(allows("https://pr-12.preview.example.com"));
+        assert!(!allows("https://preview.example.com"));
+        assert!(!allows("https://evilpreview.example.com"));
+        assert!(!allows("http://example.com"));
+        assert!(!allows("https://example.com.evil.com"));
+
+        assert!(CorsPolicy::default().allows_origin(&HeaderValue::from_static("https://any.com")));
+    }
+}
@@ -0,0 +1,198 @@
+//! Serving HTTP actions from a self-hoster's own domains.
+//!
+//! Requests to the backend's port whose `Host` is one of the custom domains
+//! are routed to HTTP actions, the same as requests to the site origin, so a
+//! single listener can serve both the API and the custom domains. Each domain
+//! can have its own certificate, which the TLS listener picks by SNI.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::Request,
+};
+use common::http::{
+    HttpResponseError,
+    OriginalHttpUri,
+};
+use http::{
+    header::HOST,
+    Uri,
+};
+use rustls::sign::CertifiedKey;
+
+/// Certificate chain and private key file names in each domain's directory,
+/// matching the layout of certbot's `live` directory.
+pub(crate) const CERT_CHAIN_FILE: &str = "fullchain.pem";
+pub(crate) const PRIVATE_KEY_FILE: &str = "privkey.pem";
+
+#[derive(Clone, Debug, Default)]
+pub struct CustomDomains {
+    /// Lowercase domain -> its certificate, if it has one.
+    domains: BTreeMap<String, Option<Arc<CertifiedKey>>>,
+}
+
+impl CustomDomains {
+    /// Loads each domain's certificate from `<certs_dir>/<domain>/` if that
+    /// directory exists.
+    pub fn new(domains: Vec<String>, certs_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let mut result = BTreeMap::new();
+        for domain in domains {
+            let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+            anyhow::ensure!(
+                !domain.is_empty() && !domain.contains(['/', ':']),
+                "Invalid custom domain {domain:?}. Use a hostname like `api.example.com`."
+            );
+            let certified_key = match certs_dir.map(|dir| dir.join(&domain)) {
+                Some(domain_dir) if domain_dir.exists() => Some(Arc::new(
+                    load_certified_key(
+                        &domain_dir.join(CERT_CHAIN_FILE),
+                        &domain_dir.join(PRIVATE_KEY_FILE),
+                    )
+                    .with_context(|| format!("Failed to load the certificate for {domain}"))?,
+                )),
+                _ => None,
+            };
+            result.insert(domain, certified_key);
+        }
+        Ok(Self { domains: result })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// `host` may include a port.
+    pub fn contains(&self, host: &str) -> bool {
+        self.domains.contains_key(&normalize_host(host))
+    }
+
+    pub fn certified_key(&self, domain: &str) -> Option<Arc<CertifiedKey>> {
+        self.domains.get(&normalize_host(domain)).cloned().flatten()
+    }
+
+    pub fn domains_without_certificate(&self) -> impl Iterator<Item = &str> {
+        self.domains
+            .iter()
+            .filter(|(_, certified_key)| certified_key.is_none())
+            .map(|(domain, _)| domain.as_str())
+    }
+
+    /// Routes requests to custom domains to HTTP actions. This has to run
+    /// before routing since it changes the path.
+    pub async fn route_request(
+        self: Arc<Self>,
+        mut req: Request<Body>,
+    ) -> Result<Request<Body>, HttpResponseError> {
+        let host = req
+            .headers()
+            .get(HOST)
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| req.uri().host());
+        if !host.is_some_and(|host| self.contains(host)) {
+            return Ok(req);
+        }
+        let uri = http_action_uri(req.uri())?;
+        let original_uri = std::mem::replace(req.uri_mut(), uri);
+        req.extensions_mut().insert(OriginalHttpUri(original_uri));
+        Ok(req)
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        _ => host,
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// `/webhooks/stripe?id=1` -> `/http/webhooks/stripe?id=1`.
+fn http_action_uri(uri: &Uri) -> anyhow::Result<Uri> {
+    let mut parts = uri.clone().into_parts();
+    let path_and_query = parts.path_and_query.as_ref().map_or("/", |p| p.as_str());
+    parts.path_and_query = Some(format!("/http{path_and_query}").parse()?);
+    Ok(Uri::from_parts(parts)?)
+}
+
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> anyhow::Result<CertifiedKey> {
+    let cert_pem = fs::read(cert_path).with_context(|| format!("Failed to read {cert_path:?}"))?;
+    let key_pem = fs::read(key_path).with_context(|| format!("Failed to read {key_path:?}"))?;
+    certified_key_from_pem(&cert_pem, &key_pem)
+}
+
+/// `cert_pem` holds the certificate chain, starting with the leaf.
+pub fn certified_key_from_pem(cert_pem: &[u8], key_pem: &[u8]) -> anyhow::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut &cert_pem[..]).collect::<Result<Vec<_>, _>>()?;
+    anyhow::ensure!(!certs.is_empty(), "No certificates found");
+    let key = rustls_pemfile::private_key(&mut &key_pem[..])?.context("No private key found")?;
+    let signing_key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+    Ok(CertifiedKey::new(certs, signing_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use common::http::OriginalHttpUri;
+    use http::Request;
+
+    use super::{
+        http_action_uri,
+        CustomDomains,
+    };
+
+    #[test]
+    fn test_contains() -> anyhow::Result<()> {
+        let domains = CustomDomains::new(vec!["Hooks.Example.com.".to_string()], None)?;
+        assert!(domains.contains("hooks.example.com"));
+        assert!(domains.contains("HOOKS.example.com:8443"));
+        assert!(!domains.contains("example.com"));
+        assert!(domains.certified_key("hooks.example.com").is_none());
+
+        assert!(CustomDomains::new(vec!["https://example.com".to_string()], None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_action_uri() -> anyhow::Result<()> {
+        assert_eq!(
+            http_action_uri(&"/webhooks/stripe?id=1".parse()?)?,
+            "/http/webhooks/stripe?id=1"
+        );
+        assert_eq!(http_action_uri(&"/".parse()?)?, "/http/");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_route_request() -> anyhow::Result<()> {
+        let domains = Arc::new(CustomDomains::new(
+            vec!["hooks.example.com".to_string()],
+            None,
+        )?);
+        let request = |host: &str| {
+            Request::builder()
+                .uri("/webhooks")
+                .header("host", host)
+                .body(Body::empty())
+        };
+        let routed = domains
+            .clone()
+            .route_request(request("hooks.example.com")?)
+            .await?;
+        assert_eq!(routed.uri(), "/http/webhooks");
+        assert_eq!(
+            routed.extensions().get::<OriginalHttpUri>().unwrap().0,
+            "/webhooks"
+        );
+        let routed = domains.route_request(request("127.0.0.1:3210")?).await?;
+        assert_eq!(routed.uri(), "/webhooks");
+        Ok(())
+    }
+}
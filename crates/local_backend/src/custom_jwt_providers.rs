@@ -0,0 +1,172 @@
+//! Endpoints for managing custom JWT providers, which let users authenticate
+//! with JWTs from identity systems that aren't OpenID Connect providers.
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    auth::{
+        CustomJwtKey,
+        CustomJwtProvider,
+    },
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct CustomJwtProviderPath {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCustomJwtProviderArgs {
+    issuer: String,
+    audience: Option<String>,
+    algorithm: String,
+    /// Shared secret for the HMAC algorithms.
+    secret: Option<String>,
+    /// JSON Web Key Set for the RSA and ECDSA algorithms.
+    jwks: Option<JsonValue>,
+    #[serde(default)]
+    required_claims: BTreeMap<String, JsonValue>,
+    #[serde(default)]
+    claim_mapping: BTreeMap<String, String>,
+}
+
+/// A provider without its key, which may be a secret.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomJwtProviderJson {
+    id: String,
+    issuer: String,
+    audience: Option<String>,
+    algorithm: String,
+    key_type: &'static str,
+    required_claims: BTreeMap<String, JsonValue>,
+    claim_mapping: BTreeMap<String, String>,
+}
+
+impl TryFrom<ParsedDocument<CustomJwtProvider>> for CustomJwtProviderJson {
+    type Error = anyhow::Error;
+
+    fn try_from(provider: ParsedDocument<CustomJwtProvider>) -> anyhow::Result<Self> {
+        let id = provider.id().developer_id.encode();
+        let provider = provider.into_value();
+        Ok(Self {
+            id,
+            issuer: provider.issuer,
+            audience: provider.audience,
+            algorithm: provider.algorithm,
+            key_type: match provider.key {
+                CustomJwtKey::Secret(_) => "secret",
+                CustomJwtKey::Jwks(_) => "jwks",
+            },
+            required_claims: provider
+                .required_claims
+                .into_iter()
+                .map(|(claim, value)| Ok((claim, serde_json::from_str(&value)?)))
+                .collect::<anyhow::Result<_>>()?,
+            claim_mapping: provider.claim_mapping,
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListCustomJwtProvidersResponse {
+    providers: Vec<CustomJwtProviderJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCustomJwtProviderResponse {
+    id: String,
+}
+
+pub async fn list_custom_jwt_providers(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let providers = st
+        .application
+        .list_custom_jwt_providers(identity)
+        .await?
+        .into_iter()
+        .map(CustomJwtProviderJson::try_from)
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(ListCustomJwtProvidersResponse { providers }))
+}
+
+pub async fn create_custom_jwt_provider(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(args): Json<CreateCustomJwtProviderArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let key = match (args.secret, args.jwks) {
+        (Some(secret), None) => CustomJwtKey::Secret(secret),
+        (None, Some(jwks)) => CustomJwtKey::Jwks(jwks.to_string()),
+        _ => {
+            return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+                "InvalidCustomJwtProvider",
+                "Custom JWT providers need either a `secret` or a `jwks`",
+            ))
+            .into())
+        },
+    };
+    let provider = CustomJwtProvider {
+        issuer: args.issuer,
+        audience: args.audience,
+        algorithm: args.algorithm,
+        key,
+        required_claims: args
+            .required_claims
+            .into_iter()
+            .map(|(claim, value)| (claim, value.to_string()))
+            .collect(),
+        claim_mapping: args.claim_mapping,
+    };
+    let id = st
+        .application
+        .create_custom_jwt_provider(identity, provider)
+        .await?;
+    Ok(Json(CreateCustomJwtProviderResponse { id }))
+}
+
+pub async fn delete_custom_jwt_provider(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(CustomJwtProviderPath { id }): Path<CustomJwtProviderPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    st.application
+        .delete_custom_jwt_provider(identity, &id)
+        .await?;
+    Ok(StatusCode::OK)
+}
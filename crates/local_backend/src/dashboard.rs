@@ -10,6 +10,7 @@ use axum::{
 };
 use common::{
     components::ComponentId,
+    document::CreationTime,
     http::{
         extract::{
             Json,
@@ -37,6 +38,7 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use serde_json::Value as JsonValue;
 use value::{
     TableName,
     TableNamespace,
@@ -44,6 +46,7 @@ use value::{
 
 use crate::{
     admin::{
+        must_be_admin,
         must_be_admin_from_key,
         must_be_admin_member,
         must_be_admin_member_with_write_access,
@@ -253,3 +256,69 @@ pub async fn run_test_function(
     };
     Ok(Json(response))
 }
+
+const DEFAULT_AUDIT_LOG_LIMIT: usize = 100;
+const MAX_AUDIT_LOG_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentAuditLogArgs {
+    /// Only return events created before this creation time, to page through
+    /// older events.
+    before: Option<f64>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentAuditLogEntryJson {
+    id: String,
+    creation_time: f64,
+    summary: String,
+    actor: Option<String>,
+    member_id: Option<u64>,
+    /// The event's `action` and `actionMetadata`.
+    #[serde(flatten)]
+    event: serde_json::Map<String, JsonValue>,
+}
+
+#[derive(Serialize)]
+pub struct DeploymentAuditLogResponse {
+    events: Vec<DeploymentAuditLogEntryJson>,
+}
+
+/// The most recent administrative changes to the deployment, like pushes,
+/// environment variable changes, imports and exports, newest first.
+#[debug_handler]
+pub async fn get_deployment_audit_log(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(args): Query<DeploymentAuditLogArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let before = args.before.map(CreationTime::try_from).transpose()?;
+    let limit = args
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LOG_LIMIT)
+        .min(MAX_AUDIT_LOG_LIMIT);
+    let events = st
+        .application
+        .deployment_audit_log(identity, before, limit)
+        .await?
+        .into_iter()
+        .map(|entry| {
+            let id = entry.id().developer_id.encode();
+            let creation_time = f64::from(entry.creation_time());
+            let entry = entry.into_value();
+            Ok(DeploymentAuditLogEntryJson {
+                id,
+                creation_time,
+                summary: entry.event.summary(),
+                actor: entry.actor,
+                member_id: entry.member_id.map(u64::from),
+                event: entry.event.try_into()?,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(DeploymentAuditLogResponse { events }))
+}
@@ -0,0 +1,295 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    debug_handler,
+    extract::State,
+    response::IntoResponse,
+};
+use axum_extra::{
+    headers::ContentLength,
+    TypedHeader,
+};
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        ComponentPath,
+    },
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        ExtractClientVersion,
+        ExtractRequestId,
+        HttpResponseError,
+    },
+    runtime::Runtime,
+    types::{
+        FunctionCaller,
+        ObjectKey,
+    },
+};
+use errors::ErrorMetadata;
+use futures::{
+    select_biased,
+    FutureExt,
+};
+use isolate::{
+    cpu_profiler::CPU_PROFILER,
+    environment::udf::recording::EXECUTION_RECORDER,
+    execution_capture::ExecutionCaptures,
+    heap_snapshot::HEAP_SNAPSHOTTER,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value as JsonValue;
+use storage::StorageGetStream;
+use udf::SerializedExecutionRecording;
+
+use crate::{
+    admin::must_be_admin_with_write_access,
+    authentication::ExtractIdentity,
+    custom_headers::ContentDispositionAttachment,
+    public_api::{
+        export_value,
+        UdfResponse,
+    },
+    LocalAppState,
+};
+
+const MAX_CAPTURED_EXECUTIONS: usize = 100;
+const DEFAULT_CAPTURE_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_CAPTURE_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureExecutionsRequest {
+    pub component_path: Option<String>,
+    pub udf_path: String,
+    /// Number of upcoming executions to capture. Defaults to one.
+    pub num_executions: Option<usize>,
+    /// How long to wait for the executions to happen before returning what
+    /// was captured so far.
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileFunctionResponse {
+    /// One V8 `.cpuprofile` per profiled execution, loadable in Chrome
+    /// DevTools or speedscope.
+    pub profiles: Vec<JsonValue>,
+}
+
+/// Enables V8 CPU profiling for the next executions of a function and
+/// returns the collected profiles once they've run.
+#[debug_handler]
+pub async fn profile_function(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(req): Json<CaptureExecutionsRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let profiles = capture_executions(&st, &CPU_PROFILER, req).await?;
+    Ok(Json(ProfileFunctionResponse { profiles }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordFunctionResponse {
+    /// One recording per execution, which can be passed to
+    /// `/api/replay_function`.
+    pub recordings: Vec<SerializedExecutionRecording>,
+}
+
+/// Records the nondeterministic inputs of the next executions of a query,
+/// mutation or action so they can be replayed later. Action recordings include
+/// the responses to `fetch` and other async operations.
+#[debug_handler]
+pub async fn record_function(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(req): Json<CaptureExecutionsRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let recordings = capture_executions(&st, &EXECUTION_RECORDER, req)
+        .await?
+        .into_iter()
+        .map(SerializedExecutionRecording::try_from)
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(RecordFunctionResponse { recordings }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayFunctionRequest {
+    pub recording: SerializedExecutionRecording,
+    pub format: Option<String>,
+}
+
+/// Re-executes a recording from `/api/record_function`. Nothing is read from
+/// or written to the database, and replayed actions don't make network
+/// requests, so this is safe to use for mutations and actions.
+#[debug_handler]
+pub async fn replay_function(
+    State(st): State<LocalAppState>,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(ReplayFunctionRequest { recording, format }): Json<ReplayFunctionRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let recording = recording.try_into().context(ErrorMetadata::bad_request(
+        "InvalidRecording",
+        "The recording to replay is invalid",
+    ))?;
+    let udf_return = st
+        .application
+        .replay_function(
+            request_id,
+            identity,
+            recording,
+            FunctionCaller::Tester(client_version.clone()),
+        )
+        .await?;
+    let value_format = format.map(|f| f.parse()).transpose()?;
+    let response = match udf_return {
+        Ok(result) => UdfResponse::Success {
+            value: export_value(result.value, value_format, client_version)?,
+            log_lines: result.log_lines,
+        },
+        Err(error) => {
+            UdfResponse::error(error.error, error.log_lines, value_format, client_version)?
+        },
+    };
+    Ok(Json(response))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeapSnapshotFunctionResponse {
+    /// One snapshot per execution, which can be downloaded from
+    /// `/api/heap_snapshots/:id`.
+    pub snapshot_ids: Vec<String>,
+}
+
+/// Takes a V8 heap snapshot of the isolate running each of the next
+/// executions of a function, right after the execution finishes. Snapshots
+/// are stored in exports storage.
+#[debug_handler]
+pub async fn heap_snapshot_function(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(req): Json<CaptureExecutionsRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let snapshots = capture_executions(&st, &HEAP_SNAPSHOTTER, req).await?;
+    let mut snapshot_ids = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        let object_key = st.application.store_heap_snapshot(snapshot).await?;
+        snapshot_ids.push(String::from(object_key));
+    }
+    Ok(Json(HeapSnapshotFunctionResponse { snapshot_ids }))
+}
+
+#[derive(Deserialize)]
+pub struct HeapSnapshotPath {
+    id: String,
+}
+
+/// Downloads a heap snapshot taken by `/api/heap_snapshot_function`. Load it
+/// in the Memory tab of Chrome DevTools.
+#[debug_handler]
+pub async fn get_heap_snapshot(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(HeapSnapshotPath { id }): Path<HeapSnapshotPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    // Snapshot ids are the UUIDs exports storage assigns to uploads. Don't
+    // accept arbitrary object keys, which may contain path segments.
+    let object_key: ObjectKey = id
+        .parse::<uuid::Uuid>()
+        .ok()
+        .and_then(|id| ObjectKey::try_from(id.to_string()).ok())
+        .context(ErrorMetadata::bad_request(
+            "InvalidHeapSnapshotId",
+            format!("{id} is not a valid heap snapshot id"),
+        ))?;
+    let (
+        StorageGetStream {
+            content_length,
+            stream,
+        },
+        filename,
+    ) = st.application.get_heap_snapshot(object_key).await?;
+    Ok((
+        TypedHeader(ContentLength(content_length as u64)),
+        TypedHeader(ContentDispositionAttachment(filename)),
+        Body::from_stream(stream),
+    ))
+}
+
+/// Captures the next executions of the requested function with `captures`,
+/// returning what was captured once they've all run or the request times out.
+async fn capture_executions<T>(
+    st: &LocalAppState,
+    captures: &'static ExecutionCaptures<T>,
+    CaptureExecutionsRequest {
+        component_path,
+        udf_path,
+        num_executions,
+        timeout_ms,
+    }: CaptureExecutionsRequest,
+) -> anyhow::Result<Vec<T>> {
+    let udf_path = udf_path.parse().context(ErrorMetadata::bad_request(
+        "InvalidUdfPath",
+        "Capturing executions requires a canonicalized UdfPath",
+    ))?;
+    let path = CanonicalizedComponentFunctionPath {
+        component: ComponentPath::deserialize(component_path.as_deref())?,
+        udf_path,
+    };
+    let num_executions = num_executions.unwrap_or(1);
+    if num_executions == 0 || num_executions > MAX_CAPTURED_EXECUTIONS {
+        anyhow::bail!(ErrorMetadata::bad_request(
+            "InvalidNumExecutions",
+            format!("numExecutions must be between 1 and {MAX_CAPTURED_EXECUTIONS}"),
+        ));
+    }
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_CAPTURE_TIMEOUT)
+        .min(MAX_CAPTURE_TIMEOUT);
+
+    let instance_name = st.instance_name.clone();
+    let receiver = captures.start(instance_name.clone(), path.clone(), num_executions)?;
+    let _guard = StopCaptureOnDrop {
+        captures,
+        instance_name: instance_name.clone(),
+        path: path.clone(),
+    };
+    let captured = select_biased! {
+        captured = receiver.fuse() => captured?,
+        _ = st.application.runtime().wait(timeout).fuse() => captures.stop(&instance_name, &path),
+    };
+    Ok(captured)
+}
+
+/// Stops capturing if the request is dropped, e.g. because the client
+/// disconnected, so the function isn't captured indefinitely.
+struct StopCaptureOnDrop<T: 'static> {
+    captures: &'static ExecutionCaptures<T>,
+    instance_name: String,
+    path: CanonicalizedComponentFunctionPath,
+}
+
+impl<T> Drop for StopCaptureOnDrop<T> {
+    fn drop(&mut self) {
+        self.captures.stop(&self.instance_name, &self.path);
+    }
+}
@@ -0,0 +1,97 @@
+use axum::{
+    body::Body,
+    extract::State,
+    response::IntoResponse,
+};
+use common::http::{
+    extract::{
+        Json,
+        Path,
+    },
+    HttpResponseError,
+};
+use futures::{
+    StreamExt,
+    TryStreamExt,
+};
+use http::StatusCode;
+use model::dependency_layers::types::DependencyLayer;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::must_be_deployer,
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct DependencyLayerPath {
+    name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyLayerJson {
+    name: String,
+    sha256: String,
+    size_bytes: u64,
+}
+
+impl From<DependencyLayer> for DependencyLayerJson {
+    fn from(layer: DependencyLayer) -> Self {
+        Self {
+            name: layer.name,
+            sha256: layer.sha256.as_hex(),
+            size_bytes: layer.size_bytes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDependencyLayersResponse {
+    layers: Vec<DependencyLayerJson>,
+}
+
+pub async fn list_dependency_layers(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_deployer(&identity)?;
+    let layers = st.application.list_dependency_layers(identity).await?;
+    Ok(Json(ListDependencyLayersResponse {
+        layers: layers.into_iter().map(DependencyLayerJson::from).collect(),
+    }))
+}
+
+/// Uploads a zip of a `node_modules` directory as the layer `name`, e.g. with
+/// `curl -X PUT --data-binary @layer.zip .../api/dependency_layers/sharp`.
+pub async fn upload_dependency_layer(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(DependencyLayerPath { name }): Path<DependencyLayerPath>,
+    body: Body,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_deployer(&identity)?;
+    let body_stream = body.into_data_stream().map_err(anyhow::Error::from).boxed();
+    let layer = st
+        .application
+        .upload_dependency_layer(identity, name, body_stream)
+        .await?;
+    Ok(Json(DependencyLayerJson::from(layer)))
+}
+
+pub async fn delete_dependency_layer(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(DependencyLayerPath { name }): Path<DependencyLayerPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_deployer(&identity)?;
+    st.application
+        .delete_dependency_layer(identity, &name)
+        .await?;
+    Ok(StatusCode::OK)
+}
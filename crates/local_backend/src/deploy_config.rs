@@ -65,7 +65,7 @@ use value::ConvexObject;
 use crate::{
     admin::{
         must_be_admin_from_key,
-        must_be_admin_with_write_access,
+        must_be_deployer,
     },
     EmptyResponse,
     LocalAppState,
@@ -308,7 +308,7 @@ pub async fn push_config_handler(
         .await
         .context("bad admin key error")?;
 
-    must_be_admin_with_write_access(&identity)?;
+    must_be_deployer(&identity)?;
 
     let udf_server_version = Version::parse(&config.udf_server_version).context(
         ErrorMetadata::bad_request("InvalidVersion", "The function version is invalid"),
@@ -320,7 +320,7 @@ pub async fn push_config_handler(
         && !deps.is_empty()
     {
         let deps: Vec<_> = deps.into_iter().map(NodeDependency::from).collect();
-        Some(application.build_external_node_deps(deps).await?)
+        Some(application.build_external_node_deps(deps, None).await?)
     } else {
         None
     };
@@ -49,6 +49,7 @@ use model::{
     external_packages::types::ExternalDepsPackageId,
     modules::module_versions::SerializedAnalyzedModule,
     source_packages::types::SourcePackage,
+    warmup::types::WarmupFunction,
 };
 use serde::{
     Deserialize,
@@ -95,6 +96,11 @@ impl TryFrom<StartPushResponse> for SerializedStartPushResponse {
                 .collect::<anyhow::Result<_>>()?,
             app: value.app.try_into()?,
             schema_change: value.schema_change.try_into()?,
+            warmup_functions: value
+                .warmup_functions
+                .into_iter()
+                .map(|f| Ok(JsonValue::from(ConvexObject::try_from(f)?)))
+                .collect::<anyhow::Result<_>>()?,
         })
     }
 }
@@ -135,6 +141,11 @@ impl TryFrom<SerializedStartPushResponse> for StartPushResponse {
                 .collect::<anyhow::Result<_>>()?,
             app: value.app.try_into()?,
             schema_change: value.schema_change.try_into()?,
+            warmup_functions: value
+                .warmup_functions
+                .into_iter()
+                .map(|f| WarmupFunction::try_from(ConvexObject::try_from(f)?))
+                .collect::<anyhow::Result<_>>()?,
         })
     }
 }
@@ -157,6 +168,10 @@ pub struct SerializedStartPushResponse {
 
     // Schema changes.
     schema_change: SerializedSchemaChange,
+
+    // Validated warm-up functions, persisted in `finish_push`.
+    #[serde(default)]
+    warmup_functions: Vec<JsonValue>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -0,0 +1,289 @@
+//! Draining the backend before a rolling restart.
+//!
+//! `POST /api/drain` makes the backend turn away new HTTP requests and sync
+//! WebSocket connections with a 503, which clients and load balancers treat
+//! as safe to retry elsewhere. The scheduled job and cron executors stop
+//! starting functions too, leaving them to the next backend. Requests and
+//! scheduled functions already running are left to finish, and since
+//! mutations only respond once their writes are committed, there are no
+//! pending commits left once they have. `GET /api/drain` reports when that's
+//! the case, after which the backend can be stopped without failing any
+//! requests. Open sync connections are closed when it stops, and clients
+//! reconnect and retry their in-flight mutations idempotently.
+use std::sync::{
+    atomic::{
+        AtomicBool,
+        AtomicUsize,
+        Ordering,
+    },
+    Arc,
+};
+
+use application::scheduled_jobs::SchedulerDrain;
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use common::http::{
+    extract::Json,
+    HttpResponseError,
+};
+use errors::ErrorMetadata;
+use serde::Serialize;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_admin_with_write_access,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+/// Requests to the drain endpoint are always served and aren't counted as in
+/// flight, so orchestrators can poll it while draining.
+pub const DRAIN_PATH: &str = "/api/drain";
+
+/// The liveness check keeps passing while draining, so orchestrators don't
+/// restart the backend before it's drained. The readiness check fails like
+/// any other request.
+pub const LIVENESS_PATH: &str = "/healthz";
+
+#[derive(Debug)]
+pub struct Drain {
+    draining: AtomicBool,
+    in_flight_requests: AtomicUsize,
+    scheduler: Arc<SchedulerDrain>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub in_flight_requests: usize,
+    /// Scheduled jobs and cron jobs that are still running.
+    pub running_scheduled_functions: usize,
+    /// The backend is draining and has no requests or scheduled functions
+    /// left running.
+    pub drained: bool,
+}
+
+impl Drain {
+    pub fn new(scheduler: Arc<SchedulerDrain>) -> Arc<Self> {
+        Arc::new(Self {
+            draining: AtomicBool::new(false),
+            in_flight_requests: AtomicUsize::new(0),
+            scheduler,
+        })
+    }
+
+    pub fn start(&self) {
+        self.scheduler.start();
+        if !self.draining.swap(true, Ordering::SeqCst) {
+            tracing::info!("Draining: no longer accepting new requests");
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> DrainStatus {
+        let draining = self.is_draining();
+        let in_flight_requests = self.in_flight_requests.load(Ordering::SeqCst);
+        let running_scheduled_functions = self.scheduler.running_executions();
+        DrainStatus {
+            draining,
+            in_flight_requests,
+            running_scheduled_functions,
+            drained: draining && in_flight_requests == 0 && running_scheduled_functions == 0,
+        }
+    }
+
+    fn begin_request(&self) -> InFlightRequest<'_> {
+        self.in_flight_requests.fetch_add(1, Ordering::SeqCst);
+        InFlightRequest(self)
+    }
+}
+
+struct InFlightRequest<'a>(&'a Drain);
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight_requests.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub async fn drain_middleware(
+    State(drain): State<Arc<Drain>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.uri().path() == DRAIN_PATH || req.uri().path() == LIVENESS_PATH {
+        return next.run(req).await;
+    }
+    // Count the request before checking, so a request that gets past the check
+    // is always seen by `status`.
+    let _in_flight = drain.begin_request();
+    if drain.is_draining() {
+        let error = anyhow::anyhow!(ErrorMetadata::rejected_before_execution(
+            "Draining",
+            "This backend is shutting down and isn't accepting new requests",
+        ));
+        return HttpResponseError::from(error).into_response();
+    }
+    next.run(req).await
+}
+
+pub async fn get_drain_status(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    Ok(Json(st.drain.status()))
+}
+
+/// Starts draining. There's no way back short of restarting the backend.
+pub async fn start_drain(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    st.drain.start();
+    Ok(Json(st.drain.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use application::{
+        scheduled_jobs::SchedulerDrain,
+        test_helpers::ApplicationTestExt,
+    };
+    use axum::body::Body;
+    use axum_extra::headers::authorization::Credentials;
+    use common::runtime::Runtime;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use runtime::prod::ProdRuntime;
+    use serde_json::{
+        json,
+        Value as JsonValue,
+    };
+
+    use super::{
+        Drain,
+        DrainStatus,
+        DRAIN_PATH,
+    };
+    use crate::test_helpers::{
+        setup_backend_for_test,
+        TestLocalBackend,
+    };
+
+    #[test]
+    fn test_drain_status() {
+        let drain = Drain::new(SchedulerDrain::new());
+        let request = drain.begin_request();
+        assert_eq!(
+            drain.status(),
+            DrainStatus {
+                draining: false,
+                in_flight_requests: 1,
+                running_scheduled_functions: 0,
+                drained: false,
+            }
+        );
+        drain.start();
+        assert!(!drain.status().drained);
+        drop(request);
+        assert_eq!(
+            drain.status(),
+            DrainStatus {
+                draining: true,
+                in_flight_requests: 0,
+                running_scheduled_functions: 0,
+                drained: true,
+            }
+        );
+    }
+
+    fn sleep_action(ms: u64) -> anyhow::Result<Request<Body>> {
+        let body = json!({ "path": "action:sleep", "args": { "ms": ms } });
+        Ok(Request::builder()
+            .uri("/api/action")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_vec(&body)?))?)
+    }
+
+    fn drain_request(backend: &TestLocalBackend, method: &str) -> anyhow::Result<Request<Body>> {
+        Ok(Request::builder()
+            .uri(DRAIN_PATH)
+            .method(method)
+            .header("Authorization", backend.admin_auth_header.0.encode())
+            .body(Body::empty())?)
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_drain_waits_for_running_requests(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt.clone()).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+
+        let slow_request = backend.send(sleep_action(2000)?);
+        let drain = async {
+            while backend.st.drain.status().in_flight_requests == 0 {
+                rt.wait(Duration::from_millis(10)).await;
+            }
+            let status: JsonValue = backend
+                .expect_success(drain_request(&backend, "POST")?)
+                .await?;
+            assert_eq!(status["draining"], true);
+            assert_eq!(status["drained"], false);
+
+            // Requests that arrive while draining are turned away with a 503.
+            backend
+                .expect_error(
+                    sleep_action(0)?,
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Draining",
+                )
+                .await?;
+
+            // The slow request keeps the backend from being drained.
+            let status: JsonValue = backend
+                .expect_success(drain_request(&backend, "GET")?)
+                .await?;
+            assert_eq!(status["inFlightRequests"], 1);
+            assert_eq!(status["drained"], false);
+            anyhow::Ok(())
+        };
+        let (response, result) = futures::join!(slow_request, drain);
+        result?;
+        assert_eq!(response?.status(), StatusCode::OK);
+
+        let status: JsonValue = backend
+            .expect_success(drain_request(&backend, "GET")?)
+            .await?;
+        assert_eq!(
+            status,
+            json!({
+                "draining": true,
+                "inFlightRequests": 0,
+                "runningScheduledFunctions": 0,
+                "drained": true,
+            })
+        );
+        Ok(())
+    }
+}
@@ -3,20 +3,32 @@ use axum::{
     extract::State,
     response::IntoResponse,
 };
-use common::http::{
-    extract::Json,
-    HttpResponseError,
+use common::{
+    components::ComponentPath,
+    http::{
+        extract::Json,
+        HttpResponseError,
+    },
 };
+use errors::ErrorMetadata;
 use http::StatusCode;
 use model::environment_variables::types::{
     EnvVarName,
+    EnvVarScope,
     EnvVarValue,
     EnvironmentVariable,
+    EnvironmentVariableOverride,
+};
+use serde::{
+    Deserialize,
+    Serialize,
 };
-use serde::Deserialize;
 
 use crate::{
-    admin::must_be_admin_with_write_access,
+    admin::{
+        must_be_admin,
+        must_be_admin_with_write_access,
+    },
     authentication::ExtractIdentity,
     LocalAppState,
 };
@@ -26,21 +38,75 @@ use crate::{
 pub struct UpdateEnvVarRequest {
     name: String,
     value: Option<String>, // None → delete existing
+    // If either is set, the change applies to an override scoped to this
+    // component (root if unset) and optionally a single function in it.
+    component_path: Option<String>,
+    function_name: Option<String>,
+    // Secrets are stored encrypted and can't be read back. They apply to the
+    // whole deployment, so they can't be scoped.
+    #[serde(default)]
+    secret: bool,
 }
 
 impl UpdateEnvVarRequest {
     pub async fn into_env_var_changes(self) -> anyhow::Result<Vec<EnvVarChange>> {
-        match self {
-            UpdateEnvVarRequest {
-                name,
-                value: Some(value),
-            } => {
+        let UpdateEnvVarRequest {
+            name,
+            value,
+            component_path,
+            function_name,
+            secret,
+        } = self;
+        if secret {
+            if component_path.is_some() || function_name.is_some() {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "ScopedEnvironmentSecret",
+                    "Secrets apply to the whole deployment and can't be scoped to a component or \
+                     function",
+                ));
+            }
+            return match value {
+                Some(value) => {
+                    let env_var = validate_env_var(&name, &value)?;
+                    Ok(vec![EnvVarChange::SetSecret(env_var)])
+                },
+                None => {
+                    let name = name.parse()?;
+                    Ok(vec![EnvVarChange::UnsetSecret(name)])
+                },
+            };
+        }
+        if component_path.is_none() && function_name.is_none() {
+            return match value {
+                Some(value) => {
+                    let env_var = validate_env_var(&name, &value)?;
+                    Ok(vec![EnvVarChange::Set(env_var)])
+                },
+                None => {
+                    let name = name.parse()?;
+                    Ok(vec![EnvVarChange::Unset(name)])
+                },
+            };
+        }
+        let scope = EnvVarScope {
+            component: match component_path {
+                Some(component_path) => component_path.parse()?,
+                None => ComponentPath::root(),
+            },
+            udf_path: function_name
+                .map(|function_name| function_name.parse())
+                .transpose()?,
+        };
+        match value {
+            Some(value) => {
                 let env_var = validate_env_var(&name, &value)?;
-                Ok(vec![EnvVarChange::Set(env_var)])
+                Ok(vec![EnvVarChange::SetOverride(
+                    EnvironmentVariableOverride { scope, env_var },
+                )])
             },
-            UpdateEnvVarRequest { name, value: None } => {
+            None => {
                 let name = name.parse()?;
-                Ok(vec![EnvVarChange::Unset(name)])
+                Ok(vec![EnvVarChange::UnsetOverride(scope, name)])
             },
         }
     }
@@ -77,6 +143,58 @@ pub async fn update_environment_variables(
     Ok(StatusCode::OK)
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSecretVersionJson {
+    version: u64,
+    /// Milliseconds since the Unix epoch.
+    creation_time: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSecretJson {
+    name: String,
+    current_version: u64,
+    /// The versions kept as the secret's rotation history, oldest first.
+    versions: Vec<EnvironmentSecretVersionJson>,
+}
+
+#[derive(Serialize)]
+pub struct ListEnvironmentSecretsResponse {
+    secrets: Vec<EnvironmentSecretJson>,
+}
+
+/// Lists the deployment's secrets and their versions. Their values are
+/// write-only, so they're never returned.
+pub async fn list_environment_secrets(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let secrets = st
+        .application
+        .list_environment_secrets(identity)
+        .await?
+        .into_iter()
+        .map(|(name, versions)| {
+            let versions: Vec<_> = versions
+                .into_iter()
+                .map(|doc| EnvironmentSecretVersionJson {
+                    version: doc.version,
+                    creation_time: doc.creation_time().map(f64::from),
+                })
+                .collect();
+            EnvironmentSecretJson {
+                name: String::from(name),
+                current_version: versions.last().map_or(0, |version| version.version),
+                versions,
+            }
+        })
+        .collect();
+    Ok(Json(ListEnvironmentSecretsResponse { secrets }))
+}
+
 fn validate_env_var(name: &String, value: &String) -> anyhow::Result<EnvironmentVariable> {
     let name: EnvVarName = name.parse()?;
     let value: EnvVarValue = value.parse()?;
@@ -88,9 +206,15 @@ mod tests {
     use std::collections::BTreeMap;
 
     use axum_extra::headers::authorization::Credentials;
-    use common::types::{
-        EnvVarName,
-        EnvVarValue,
+    use common::{
+        components::{
+            CanonicalizedComponentFunctionPath,
+            ComponentPath,
+        },
+        types::{
+            EnvVarName,
+            EnvVarValue,
+        },
     };
     use http::Request;
     use keybroker::Identity;
@@ -206,4 +330,104 @@ mod tests {
         );
         Ok(())
     }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_scoped_env_var_overrides(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        update_environment_variables(
+            &backend,
+            json!([
+                {"name": "name1", "value": "value1"},
+                {"name": "name1", "value": "value1b", "functionName": "messages:send"},
+                {"name": "name2", "value": "value2", "componentPath": "waitlist"},
+            ]),
+        )
+        .await?;
+        // Overrides don't change the deployment-wide values.
+        assert_eq!(
+            list_environment_variables(&backend).await?,
+            btreemap! {
+                "name1".parse()? => "value1".parse()?,
+            }
+        );
+        let mut tx = backend.st.application.begin(Identity::system()).await?;
+        let overrides = EnvironmentVariablesModel::new(&mut tx)
+            .get_overrides_for_function(&CanonicalizedComponentFunctionPath {
+                component: ComponentPath::root(),
+                udf_path: "messages:send".parse()?,
+            })
+            .await?;
+        assert_eq!(
+            overrides,
+            btreemap! {
+                "name1".parse()? => "value1b".parse()?,
+            }
+        );
+
+        update_environment_variables(
+            &backend,
+            json!([
+                {"name": "name1", "functionName": "messages:send"},
+            ]),
+        )
+        .await?;
+        let mut tx = backend.st.application.begin(Identity::system()).await?;
+        let overrides = EnvironmentVariablesModel::new(&mut tx)
+            .get_all_overrides()
+            .await?;
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].scope.component, "waitlist".parse()?);
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_env_secrets(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        update_environment_variables(
+            &backend,
+            json!([
+                {"name": "name1", "value": "value1"},
+                {"name": "secret1", "value": "hunter2", "secret": true},
+            ]),
+        )
+        .await?;
+        update_environment_variables(
+            &backend,
+            json!([
+                {"name": "secret1", "value": "hunter3", "secret": true},
+            ]),
+        )
+        .await?;
+        // Secrets aren't listed with the environment variables.
+        assert_eq!(
+            list_environment_variables(&backend).await?,
+            btreemap! {
+                "name1".parse()? => "value1".parse()?,
+            }
+        );
+        let mut tx = backend.st.application.begin(Identity::system()).await?;
+        let secrets = EnvironmentVariablesModel::new(&mut tx)
+            .get_all_secrets()
+            .await?;
+        let versions = &secrets[&"secret1".parse()?];
+        assert_eq!(
+            versions.iter().map(|doc| doc.version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(!versions[1].encrypted_value.contains("hunter3"));
+
+        update_environment_variables(
+            &backend,
+            json!([
+                {"name": "secret1", "secret": true},
+            ]),
+        )
+        .await?;
+        let mut tx = backend.st.application.begin(Identity::system()).await?;
+        assert!(EnvironmentVariablesModel::new(&mut tx)
+            .get_all_secrets()
+            .await?
+            .is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,834 @@
+//! GraphQL API over the public queries and mutations of the root app, served
+//! at `/api/graphql` when the `GRAPHQL_API_ENABLED` knob is set.
+//!
+//! Each root field runs its function through [`ApplicationApi`] with the
+//! field's arguments, and its selection set picks fields out of the result.
+//! Introspection isn't supported, but the schema is served in the schema
+//! definition language at `/api/graphql/schema` for codegen and other tooling.
+//!
+//! [`ApplicationApi`]: application::api::ApplicationApi
+use std::collections::BTreeSet;
+
+use anyhow::Context;
+use application::api::ExecuteQueryTimestamp;
+use axum::{
+    extract::State,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use common::{
+    http::{
+        extract::{
+            Json,
+            Query,
+        },
+        ExtractClientVersion,
+        ExtractRequestId,
+        ExtractResolvedHostname,
+        HttpResponseError,
+        ResolvedHostname,
+    },
+    types::FunctionCaller,
+    version::ClientVersion,
+    RequestId,
+};
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use futures::future;
+use http::header::CONTENT_TYPE;
+use keybroker::Identity;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::export::ValueFormat;
+
+use self::{
+    parser::{
+        parse_document,
+        Directive,
+        Document,
+        Field,
+        OperationDefinition,
+        OperationType,
+        Selection,
+        Value,
+    },
+    schema::{
+        GraphQlSchema,
+        RootField,
+        TypeRef,
+    },
+};
+use crate::{
+    authentication::ExtractAuthenticationToken,
+    RouterState,
+};
+
+mod parser;
+mod schema;
+
+/// How deeply selection sets can be nested, which also stops fragments that
+/// spread themselves.
+const MAX_SELECTION_DEPTH: usize = 32;
+
+pub fn graphql_routes() -> Router<RouterState> {
+    Router::new()
+        .route("/graphql", get(graphql_get).post(graphql_post))
+        .route("/graphql/schema", get(graphql_schema_get))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlRequest {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables: Option<JsonMap<String, JsonValue>>,
+}
+
+/// A GraphQL request in a query string, where variables are JSON-encoded.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlGetRequest {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphQlResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<JsonMap<String, JsonValue>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct GraphQlError {
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    path: Vec<String>,
+    /// The `data` of a `ConvexError` thrown by the function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extensions: Option<JsonValue>,
+}
+
+pub async fn graphql_post(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Json(req): Json<GraphQlRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    let response = execute(&st, &host, request_id, identity, client_version, req, true).await?;
+    Ok(Json(response))
+}
+
+/// Runs a GraphQL query sent in the query string. Mutations must be sent with
+/// POST, so following a link can't run one.
+pub async fn graphql_get(
+    State(st): State<RouterState>,
+    Query(req): Query<GraphQlGetRequest>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let variables = req
+        .variables
+        .map(|variables| serde_json::from_str(&variables))
+        .transpose()
+        .context(ErrorMetadata::bad_request(
+            "InvalidGraphQLVariables",
+            "The `variables` parameter must be a JSON object",
+        ))?;
+    let req = GraphQlRequest {
+        query: req.query,
+        operation_name: req.operation_name,
+        variables,
+    };
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    let response = execute(&st, &host, request_id, identity, client_version, req, false).await?;
+    Ok(Json(response))
+}
+
+/// Returns the GraphQL schema in the schema definition language.
+pub async fn graphql_schema_get(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let metadata = st.api.public_api_metadata(&host, request_id).await?;
+    let sdl = GraphQlSchema::new(metadata).to_sdl();
+    Ok(([(CONTENT_TYPE, "text/plain; charset=utf-8")], sdl))
+}
+
+/// Executes a request, returning invalid requests as a response with only
+/// `errors`, as GraphQL clients expect.
+async fn execute(
+    st: &RouterState,
+    host: &ResolvedHostname,
+    request_id: RequestId,
+    identity: Identity,
+    client_version: ClientVersion,
+    req: GraphQlRequest,
+    allow_mutations: bool,
+) -> anyhow::Result<GraphQlResponse> {
+    let metadata = st.api.public_api_metadata(host, request_id.clone()).await?;
+    let schema = GraphQlSchema::new(metadata);
+    let document = match parse_document(&req.query) {
+        Ok(document) => document,
+        Err(e) => return request_error(e),
+    };
+    let planned = Executor::new(&document, &schema, &req, allow_mutations)
+        .and_then(|executor| Ok((executor.plan()?, executor)));
+    let (fields, executor) = match planned {
+        Ok(planned) => planned,
+        Err(e) => return request_error(e),
+    };
+
+    let caller = FunctionCaller::HttpApi(client_version);
+    let results = match executor.operation.operation_type {
+        OperationType::Query => {
+            // All queries execute at the same timestamp.
+            let ts = st.api.latest_timestamp(host, request_id.clone()).await?;
+            future::try_join_all(fields.iter().map(|field| async {
+                let Some(root_field) = field.root_field else {
+                    return Ok(None);
+                };
+                let query_return = st
+                    .api
+                    .execute_public_query(
+                        host,
+                        request_id.clone(),
+                        identity.clone(),
+                        root_field.path.clone(),
+                        vec![JsonValue::Object(field.arguments.clone())],
+                        caller.clone(),
+                        ExecuteQueryTimestamp::At(*ts),
+                        None,
+                    )
+                    .await?;
+                anyhow::Ok(Some(query_return.result))
+            }))
+            .await?
+        },
+        OperationType::Mutation => {
+            // Mutations execute one after the other, in order.
+            let mut results = vec![];
+            for field in &fields {
+                let Some(root_field) = field.root_field else {
+                    results.push(None);
+                    continue;
+                };
+                let result = st
+                    .api
+                    .execute_public_mutation(
+                        host,
+                        request_id.clone(),
+                        identity.clone(),
+                        root_field.path.clone(),
+                        vec![JsonValue::Object(field.arguments.clone())],
+                        caller.clone(),
+                        None,
+                    )
+                    .await?;
+                results.push(Some(
+                    result
+                        .map(|mutation_return| mutation_return.value)
+                        .map_err(|mutation_error| mutation_error.error),
+                ));
+            }
+            results
+        },
+        OperationType::Subscription => {
+            anyhow::bail!("Subscriptions should have been rejected by the executor")
+        },
+    };
+
+    let mut data = JsonMap::new();
+    let mut errors = vec![];
+    for (field, result) in fields.iter().zip(results) {
+        let value = match (field.root_field, result) {
+            (Some(root_field), Some(Ok(value))) => executor.complete(
+                value.export(ValueFormat::ConvexCleanJSON),
+                &root_field.ty,
+                &field.field.selection_set,
+            )?,
+            (_, Some(Err(error))) => {
+                errors.push(GraphQlError {
+                    message: error.to_string(),
+                    path: vec![field.field.response_key().to_string()],
+                    extensions: error.custom_data_if_any().map(|data| {
+                        serde_json::json!({ "data": data.export(ValueFormat::ConvexCleanJSON) })
+                    }),
+                });
+                JsonValue::Null
+            },
+            _ => JsonValue::String(executor.root_type_name().to_string()),
+        };
+        data.insert(field.field.response_key().to_string(), value);
+    }
+    Ok(GraphQlResponse {
+        data: Some(data),
+        errors,
+    })
+}
+
+fn request_error(e: anyhow::Error) -> anyhow::Result<GraphQlResponse> {
+    if !e.is_deterministic_user_error() {
+        return Err(e);
+    }
+    Ok(GraphQlResponse {
+        data: None,
+        errors: vec![GraphQlError {
+            message: e.user_facing_message(),
+            path: vec![],
+            extensions: None,
+        }],
+    })
+}
+
+fn invalid_request(short_msg: &'static str, msg: String) -> anyhow::Error {
+    anyhow::anyhow!(ErrorMetadata::bad_request(short_msg, msg))
+}
+
+/// A root field of the operation, with the function to run for it.
+struct PlannedField<'a> {
+    field: &'a Field,
+    /// `None` for `__typename`.
+    root_field: Option<&'a RootField>,
+    arguments: JsonMap<String, JsonValue>,
+}
+
+struct Executor<'a> {
+    document: &'a Document,
+    schema: &'a GraphQlSchema,
+    operation: &'a OperationDefinition,
+    variables: JsonMap<String, JsonValue>,
+    /// Variables declared by the operation, including ones without a value.
+    declared_variables: BTreeSet<&'a str>,
+}
+
+impl<'a> Executor<'a> {
+    fn new(
+        document: &'a Document,
+        schema: &'a GraphQlSchema,
+        req: &GraphQlRequest,
+        allow_mutations: bool,
+    ) -> anyhow::Result<Self> {
+        let operation = match &req.operation_name {
+            Some(name) => document
+                .operations
+                .iter()
+                .find(|operation| operation.name.as_ref() == Some(name))
+                .ok_or_else(|| {
+                    invalid_request("UnknownOperation", format!("Unknown operation {name}"))
+                })?,
+            None if document.operations.len() == 1 => &document.operations[0],
+            None => {
+                return Err(invalid_request(
+                    "OperationNameRequired",
+                    "`operationName` is required for documents with more than one operation"
+                        .to_string(),
+                ))
+            },
+        };
+        match operation.operation_type {
+            OperationType::Query => {},
+            OperationType::Mutation => anyhow::ensure!(
+                allow_mutations,
+                invalid_request(
+                    "MutationNotAllowed",
+                    "Mutations must be sent with a POST request".to_string()
+                )
+            ),
+            OperationType::Subscription => {
+                return Err(invalid_request(
+                    "SubscriptionsUnsupported",
+                    "GraphQL subscriptions aren't supported. Subscribe to queries with a Convex \
+                     client or the `/api/query_subscribe` endpoint instead."
+                        .to_string(),
+                ))
+            },
+        }
+        let provided = req.variables.as_ref();
+        let mut variables = JsonMap::new();
+        let mut declared_variables = BTreeSet::new();
+        for definition in &operation.variables {
+            declared_variables.insert(definition.name.as_str());
+            let provided = provided.and_then(|provided| provided.get(&definition.name));
+            let value = match (provided, &definition.default_value) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default_value)) => const_to_json(default_value),
+                (None, None) => continue,
+            };
+            variables.insert(definition.name.clone(), value);
+        }
+        Ok(Self {
+            document,
+            schema,
+            operation,
+            variables,
+            declared_variables,
+        })
+    }
+
+    /// Validates the operation's selections and returns its root fields.
+    fn plan(&self) -> anyhow::Result<Vec<PlannedField<'a>>> {
+        let root_fields = match self.operation.operation_type {
+            OperationType::Mutation => &self.schema.mutations,
+            _ => &self.schema.queries,
+        };
+        let mut planned = vec![];
+        for field in self.collect_fields(&self.operation.selection_set)? {
+            if field.name == "__typename" {
+                planned.push(PlannedField {
+                    field,
+                    root_field: None,
+                    arguments: JsonMap::new(),
+                });
+                continue;
+            }
+            if field.name.starts_with("__") {
+                return Err(invalid_request(
+                    "IntrospectionUnsupported",
+                    "GraphQL introspection isn't supported. The schema is served at \
+                     `/api/graphql/schema` instead."
+                        .to_string(),
+                ));
+            }
+            let root_field = root_fields.get(&field.name).ok_or_else(|| {
+                invalid_request(
+                    "UnknownField",
+                    format!(
+                        "Cannot query field {} on type {}",
+                        field.name,
+                        self.root_type_name()
+                    ),
+                )
+            })?;
+            self.validate(field, &root_field.ty, 0)?;
+            planned.push(PlannedField {
+                field,
+                root_field: Some(root_field),
+                arguments: self.arguments(&field.arguments)?,
+            });
+        }
+        Ok(planned)
+    }
+
+    fn root_type_name(&self) -> &'static str {
+        match self.operation.operation_type {
+            OperationType::Mutation => "Mutation",
+            _ => "Query",
+        }
+    }
+
+    /// Returns the fields to include from a selection set, expanding fragments
+    /// and applying `@skip` and `@include`.
+    fn collect_fields(&self, selection_set: &'a [Selection]) -> anyhow::Result<Vec<&'a Field>> {
+        let mut fields = vec![];
+        let mut visited_fragments = BTreeSet::new();
+        self.collect_fields_into(selection_set, &mut fields, &mut visited_fragments)?;
+        Ok(fields)
+    }
+
+    fn collect_fields_into(
+        &self,
+        selection_set: &'a [Selection],
+        fields: &mut Vec<&'a Field>,
+        visited_fragments: &mut BTreeSet<&'a str>,
+    ) -> anyhow::Result<()> {
+        for selection in selection_set {
+            match selection {
+                Selection::Field(field) => {
+                    if self.is_included(&field.directives)? {
+                        fields.push(field);
+                    }
+                },
+                Selection::FragmentSpread { name, directives } => {
+                    if !self.is_included(directives)? || !visited_fragments.insert(name) {
+                        continue;
+                    }
+                    let fragment = self.document.fragments.get(name).ok_or_else(|| {
+                        invalid_request("UnknownFragment", format!("Unknown fragment {name}"))
+                    })?;
+                    self.collect_fields_into(&fragment.selection_set, fields, visited_fragments)?;
+                },
+                Selection::InlineFragment {
+                    directives,
+                    selection_set,
+                } => {
+                    if self.is_included(directives)? {
+                        self.collect_fields_into(selection_set, fields, visited_fragments)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn is_included(&self, directives: &[Directive]) -> anyhow::Result<bool> {
+        for directive in directives {
+            let expected = match directive.name.as_str() {
+                "skip" => false,
+                "include" => true,
+                // Other directives, e.g. for client-side tooling, don't affect
+                // execution.
+                _ => continue,
+            };
+            let condition = directive
+                .arguments
+                .iter()
+                .find(|(name, _)| name == "if")
+                .map(|(_, value)| self.to_json(value))
+                .transpose()?;
+            let Some(JsonValue::Bool(condition)) = condition else {
+                return Err(invalid_request(
+                    "InvalidDirective",
+                    format!("@{} requires a Boolean `if` argument", directive.name),
+                ));
+            };
+            if condition != expected {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks that the selections on a field match its type.
+    fn validate(&self, field: &'a Field, ty: &TypeRef, depth: usize) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            depth < MAX_SELECTION_DEPTH,
+            invalid_request(
+                "SelectionTooDeep",
+                format!("Selections can be nested at most {MAX_SELECTION_DEPTH} levels deep"),
+            )
+        );
+        let type_name = ty.named_type();
+        let Some(object_type) = self.schema.types.get(type_name) else {
+            anyhow::ensure!(
+                field.selection_set.is_empty(),
+                invalid_request(
+                    "UnexpectedSelection",
+                    format!(
+                        "Field {} of type {ty} can't have a selection of subfields",
+                        field.name
+                    ),
+                )
+            );
+            return Ok(());
+        };
+        anyhow::ensure!(
+            !field.selection_set.is_empty(),
+            invalid_request(
+                "MissingSelection",
+                format!(
+                    "Field {} of type {ty} must have a selection of subfields",
+                    field.name
+                ),
+            )
+        );
+        for subfield in self.collect_fields(&field.selection_set)? {
+            anyhow::ensure!(
+                subfield.arguments.is_empty(),
+                invalid_request(
+                    "UnknownArgument",
+                    format!(
+                        "Field {} on type {type_name} has no arguments",
+                        subfield.name
+                    ),
+                )
+            );
+            if subfield.name == "__typename" {
+                continue;
+            }
+            let subfield_type = object_type.fields.get(&subfield.name).ok_or_else(|| {
+                invalid_request(
+                    "UnknownField",
+                    format!("Cannot query field {} on type {type_name}", subfield.name),
+                )
+            })?;
+            self.validate(subfield, subfield_type, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Converts a field's arguments to the argument object of its function.
+    /// Arguments set to a variable without a value are left out.
+    fn arguments(
+        &self,
+        arguments: &[(String, Value)],
+    ) -> anyhow::Result<JsonMap<String, JsonValue>> {
+        let mut result = JsonMap::new();
+        for (name, value) in arguments {
+            if let Value::Variable(variable) = value
+                && self.declared_variables.contains(variable.as_str())
+                && !self.variables.contains_key(variable)
+            {
+                continue;
+            }
+            result.insert(name.clone(), self.to_json(value)?);
+        }
+        Ok(result)
+    }
+
+    fn to_json(&self, value: &Value) -> anyhow::Result<JsonValue> {
+        let json = match value {
+            Value::Variable(name) => {
+                anyhow::ensure!(
+                    self.declared_variables.contains(name.as_str()),
+                    invalid_request(
+                        "UnknownVariable",
+                        format!("Variable ${name} is not defined by the operation"),
+                    )
+                );
+                self.variables.get(name).cloned().unwrap_or(JsonValue::Null)
+            },
+            Value::List(values) => JsonValue::Array(
+                values
+                    .iter()
+                    .map(|value| self.to_json(value))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            Value::Object(fields) => JsonValue::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), self.to_json(value)?)))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            _ => const_to_json(value),
+        };
+        Ok(json)
+    }
+
+    /// Picks the selected fields out of a value returned by a function. The
+    /// selections were already validated against `ty`.
+    fn complete(
+        &self,
+        value: JsonValue,
+        ty: &TypeRef,
+        selection_set: &'a [Selection],
+    ) -> anyhow::Result<JsonValue> {
+        let completed = match (ty, value) {
+            (TypeRef::NonNull(inner), value) => self.complete(value, inner, selection_set)?,
+            (TypeRef::List(inner), JsonValue::Array(values)) => JsonValue::Array(
+                values
+                    .into_iter()
+                    .map(|value| self.complete(value, inner, selection_set))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            (TypeRef::Named(type_name), JsonValue::Object(object)) => {
+                let Some(object_type) = self.schema.types.get(type_name) else {
+                    return Ok(JsonValue::Object(object));
+                };
+                let mut result = JsonMap::new();
+                for field in self.collect_fields(selection_set)? {
+                    let value = if field.name == "__typename" {
+                        JsonValue::String(type_name.clone())
+                    } else {
+                        let value = object.get(&field.name).cloned().unwrap_or(JsonValue::Null);
+                        self.complete(
+                            value,
+                            &object_type.fields[&field.name],
+                            &field.selection_set,
+                        )?
+                    };
+                    result.insert(field.response_key().to_string(), value);
+                }
+                JsonValue::Object(result)
+            },
+            // Scalars, and values that don't match their validator.
+            (_, value) => value,
+        };
+        Ok(completed)
+    }
+}
+
+/// Converts a value without variables to JSON. Enum values are strings.
+fn const_to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Variable(_) => JsonValue::Null,
+        Value::Int(n) => JsonValue::from(*n),
+        Value::Float(n) => JsonValue::from(*n),
+        Value::String(s) | Value::Enum(s) => JsonValue::String(s.clone()),
+        Value::Boolean(b) => JsonValue::Bool(*b),
+        Value::Null => JsonValue::Null,
+        Value::List(values) => JsonValue::Array(values.iter().map(const_to_json).collect()),
+        Value::Object(fields) => JsonValue::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), const_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use errors::ErrorMetadataAnyhowExt;
+    use serde_json::json;
+
+    use super::{
+        parser::parse_document,
+        schema::{
+            GraphQlSchema,
+            ObjectType,
+            RootField,
+            TypeRef,
+        },
+        Executor,
+        GraphQlRequest,
+    };
+
+    fn named(name: &str) -> TypeRef {
+        TypeRef::NonNull(Box::new(TypeRef::Named(name.to_string())))
+    }
+
+    fn test_schema() -> anyhow::Result<GraphQlSchema> {
+        let mut schema = GraphQlSchema::default();
+        schema.types.insert(
+            "Messages".to_string(),
+            ObjectType {
+                fields: [
+                    ("_id".to_string(), named("ID")),
+                    ("author".to_string(), named("String")),
+                    ("body".to_string(), named("String")),
+                ]
+                .into(),
+            },
+        );
+        schema.queries.insert(
+            "messages_list".to_string(),
+            RootField {
+                path: "messages:list".parse()?,
+                arguments: [("author".to_string(), TypeRef::Named("String".to_string()))].into(),
+                ty: TypeRef::NonNull(Box::new(TypeRef::List(Box::new(named("Messages"))))),
+            },
+        );
+        schema.mutations.insert(
+            "messages_send".to_string(),
+            RootField {
+                path: "messages:send".parse()?,
+                arguments: [("body".to_string(), named("String"))].into(),
+                ty: named("ID"),
+            },
+        );
+        Ok(schema)
+    }
+
+    fn request(query: &str, variables: serde_json::Value) -> GraphQlRequest {
+        GraphQlRequest {
+            query: query.to_string(),
+            operation_name: None,
+            variables: variables.as_object().cloned(),
+        }
+    }
+
+    #[test]
+    fn test_plan_and_complete() -> anyhow::Result<()> {
+        let schema = test_schema()?;
+        let req = request(
+            r#"
+            query ($author: String, $unset: String, $withBody: Boolean!) {
+                __typename
+                recent: messages_list(author: $author, tag: $unset) {
+                    id: _id
+                    ...Body @include(if: $withBody)
+                    __typename
+                }
+            }
+            fragment Body on Messages { body }
+            "#,
+            json!({ "author": "sarah", "withBody": true }),
+        );
+        let document = parse_document(&req.query)?;
+        let executor = Executor::new(&document, &schema, &req, false)?;
+        let fields = executor.plan()?;
+        assert_eq!(fields.len(), 2);
+        assert!(fields[0].root_field.is_none());
+        assert_eq!(fields[1].field.response_key(), "recent");
+        // Arguments set to variables without a value are left out.
+        assert_eq!(
+            fields[1].arguments,
+            *json!({ "author": "sarah" }).as_object().unwrap()
+        );
+
+        let root_field = fields[1].root_field.unwrap();
+        let result = executor.complete(
+            json!([{ "_id": "abc", "_creationTime": 1.0, "author": "sarah", "body": "hi" }]),
+            &root_field.ty,
+            &fields[1].field.selection_set,
+        )?;
+        assert_eq!(
+            result,
+            json!([{ "id": "abc", "body": "hi", "__typename": "Messages" }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_requests() -> anyhow::Result<()> {
+        let schema = test_schema()?;
+        for (query, allow_mutations, expected) in [
+            ("{ messages_send(body: \"hi\") }", true, "UnknownField"),
+            (
+                "mutation { messages_send(body: \"hi\") }",
+                false,
+                "MutationNotAllowed",
+            ),
+            (
+                "subscription { messages_list { _id } }",
+                true,
+                "SubscriptionsUnsupported",
+            ),
+            ("{ messages_list }", true, "MissingSelection"),
+            (
+                "{ messages_list { _id { x } } }",
+                true,
+                "UnexpectedSelection",
+            ),
+            ("{ messages_list { title } }", true, "UnknownField"),
+            (
+                "{ messages_list(author: $a) { _id } }",
+                true,
+                "UnknownVariable",
+            ),
+            ("{ messages_list { ...Missing } }", true, "UnknownFragment"),
+            (
+                "{ __schema { types { name } } }",
+                true,
+                "IntrospectionUnsupported",
+            ),
+            (
+                "query A { __typename } query B { __typename }",
+                true,
+                "OperationNameRequired",
+            ),
+        ] {
+            let req = request(query, json!({}));
+            let document = parse_document(&req.query)?;
+            let err = Executor::new(&document, &schema, &req, allow_mutations)
+                .and_then(|executor| executor.plan().map(|_| ()))
+                .unwrap_err();
+            assert_eq!(err.short_msg(), expected, "{query}");
+        }
+        Ok(())
+    }
+}
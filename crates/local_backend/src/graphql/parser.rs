@@ -0,0 +1,696 @@
+//! Parser for GraphQL executable documents, i.e. operations and fragments.
+//! Type system definitions aren't accepted, since clients can't send them.
+use std::collections::BTreeMap;
+
+use errors::ErrorMetadata;
+
+#[derive(Debug)]
+pub struct Document {
+    pub operations: Vec<OperationDefinition>,
+    pub fragments: BTreeMap<String, FragmentDefinition>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+#[derive(Debug)]
+pub struct OperationDefinition {
+    pub operation_type: OperationType,
+    pub name: Option<String>,
+    pub variables: Vec<VariableDefinition>,
+    pub selection_set: Vec<Selection>,
+}
+
+#[derive(Debug)]
+pub struct VariableDefinition {
+    pub name: String,
+    pub default_value: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct FragmentDefinition {
+    pub selection_set: Vec<Selection>,
+}
+
+#[derive(Debug)]
+pub enum Selection {
+    Field(Field),
+    FragmentSpread {
+        name: String,
+        directives: Vec<Directive>,
+    },
+    InlineFragment {
+        directives: Vec<Directive>,
+        selection_set: Vec<Selection>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<(String, Value)>,
+    pub directives: Vec<Directive>,
+    pub selection_set: Vec<Selection>,
+}
+
+impl Field {
+    /// The key of the field in the response.
+    pub fn response_key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Directive {
+    pub name: String,
+    pub arguments: Vec<(String, Value)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Variable(String),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Enum(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Punctuator(char),
+    Spread,
+    Name(String),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+fn syntax_error(line: usize, message: impl Into<String>) -> anyhow::Error {
+    anyhow::anyhow!(ErrorMetadata::bad_request(
+        "InvalidGraphQL",
+        format!("Syntax error on line {line}: {}", message.into()),
+    ))
+}
+
+/// Splits `source` into tokens along with the line each one starts on.
+fn tokenize(source: &str) -> anyhow::Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            },
+            // Commas are insignificant, like whitespace.
+            ' ' | '\t' | '\r' | ',' | '\u{feff}' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            },
+            '!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+                tokens.push((Token::Punctuator(c), line));
+                i += 1;
+            },
+            '.' => {
+                if chars.get(i..i + 3) != Some(&['.'; 3][..]) {
+                    return Err(syntax_error(line, "Expected `...`"));
+                }
+                tokens.push((Token::Spread, line));
+                i += 3;
+            },
+            '"' => {
+                let start_line = line;
+                let (value, end) = if chars.get(i..i + 3) == Some(&['"'; 3][..]) {
+                    read_block_string(&chars, i + 3, &mut line)?
+                } else {
+                    read_string(&chars, i + 1, line)?
+                };
+                tokens.push((Token::String(value), start_line));
+                i = end;
+            },
+            '-' | '0'..='9' => {
+                let start = i;
+                i += 1;
+                let mut is_float = false;
+                while i < chars.len() {
+                    match chars[i] {
+                        '0'..='9' => {},
+                        '.' | 'e' | 'E' => is_float = true,
+                        '+' | '-' if matches!(chars[i - 1], 'e' | 'E') => {},
+                        _ => break,
+                    }
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                let token = if is_float {
+                    Token::Float(
+                        number
+                            .parse()
+                            .map_err(|_| syntax_error(line, format!("Invalid number {number}")))?,
+                    )
+                } else {
+                    Token::Int(
+                        number
+                            .parse()
+                            .map_err(|_| syntax_error(line, format!("Invalid number {number}")))?,
+                    )
+                };
+                tokens.push((token, line));
+            },
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i] == '_' || chars[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push((Token::Name(chars[start..i].iter().collect()), line));
+            },
+            c => return Err(syntax_error(line, format!("Unexpected character {c:?}"))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads a string starting after its opening quote, returning it and the index
+/// after its closing quote.
+fn read_string(chars: &[char], mut i: usize, line: usize) -> anyhow::Result<(String, usize)> {
+    let mut value = String::new();
+    loop {
+        match chars.get(i) {
+            None | Some('\n') => return Err(syntax_error(line, "Unterminated string")),
+            Some('"') => return Ok((value, i + 1)),
+            Some('\\') => {
+                let escaped = match chars.get(i + 1) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let hex: String = chars
+                            .get(i + 2..i + 6)
+                            .ok_or_else(|| syntax_error(line, "Invalid unicode escape"))?
+                            .iter()
+                            .collect();
+                        i += 4;
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| syntax_error(line, "Invalid unicode escape"))?
+                    },
+                    _ => return Err(syntax_error(line, "Invalid escape sequence")),
+                };
+                value.push(escaped);
+                i += 2;
+            },
+            Some(&c) => {
+                value.push(c);
+                i += 1;
+            },
+        }
+    }
+}
+
+/// Reads a `"""` block string starting after its opening quotes, removing its
+/// common indentation and leading and trailing blank lines.
+fn read_block_string(
+    chars: &[char],
+    mut i: usize,
+    line: &mut usize,
+) -> anyhow::Result<(String, usize)> {
+    let mut raw = String::new();
+    loop {
+        if chars.get(i..i + 3) == Some(&['"'; 3][..]) {
+            break;
+        }
+        if chars.get(i..i + 4) == Some(&['\\', '"', '"', '"'][..]) {
+            raw.push_str("\"\"\"");
+            i += 4;
+            continue;
+        }
+        let Some(&c) = chars.get(i) else {
+            return Err(syntax_error(*line, "Unterminated block string"));
+        };
+        if c == '\n' {
+            *line += 1;
+        }
+        raw.push(c);
+        i += 1;
+    }
+    let lines: Vec<&str> = raw.lines().collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut lines: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(n, l)| {
+            if n == 0 {
+                l
+            } else {
+                l.get(indent..).unwrap_or("")
+            }
+        })
+        .collect();
+    while lines.first().is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    Ok((lines.join("\n"), i + 3))
+}
+
+pub fn parse_document(source: &str) -> anyhow::Result<Document> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+    let mut document = Document {
+        operations: vec![],
+        fragments: BTreeMap::new(),
+    };
+    while parser.peek().is_some() {
+        if parser.peek_name("fragment") {
+            let line = parser.line();
+            let (name, fragment) = parser.fragment_definition()?;
+            if document.fragments.insert(name.clone(), fragment).is_some() {
+                return Err(syntax_error(
+                    line,
+                    format!("Fragment {name} is defined more than once"),
+                ));
+            }
+        } else {
+            document.operations.push(parser.operation_definition()?);
+        }
+    }
+    if document.operations.is_empty() {
+        return Err(syntax_error(parser.line(), "Expected an operation"));
+    }
+    Ok(document)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_punctuator(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punctuator(c))
+    }
+
+    fn peek_name(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == name)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1)
+    }
+
+    fn next(&mut self) -> anyhow::Result<Token> {
+        let (token, _) = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| syntax_error(self.line(), "Unexpected end of document"))?
+            .clone();
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect_punctuator(&mut self, c: char) -> anyhow::Result<()> {
+        let line = self.line();
+        match self.next()? {
+            Token::Punctuator(p) if p == c => Ok(()),
+            token => Err(syntax_error(
+                line,
+                format!("Expected `{c}`, found {token:?}"),
+            )),
+        }
+    }
+
+    /// Consumes `c` if it's the next token.
+    fn skip_punctuator(&mut self, c: char) -> bool {
+        let found = self.peek_punctuator(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn name(&mut self) -> anyhow::Result<String> {
+        let line = self.line();
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            token => Err(syntax_error(
+                line,
+                format!("Expected a name, found {token:?}"),
+            )),
+        }
+    }
+
+    fn operation_definition(&mut self) -> anyhow::Result<OperationDefinition> {
+        // The `{ ... }` shorthand is an anonymous query.
+        if self.peek_punctuator('{') {
+            return Ok(OperationDefinition {
+                operation_type: OperationType::Query,
+                name: None,
+                variables: vec![],
+                selection_set: self.selection_set()?,
+            });
+        }
+        let line = self.line();
+        let operation_type = match self.name()?.as_str() {
+            "query" => OperationType::Query,
+            "mutation" => OperationType::Mutation,
+            "subscription" => OperationType::Subscription,
+            other => {
+                return Err(syntax_error(
+                    line,
+                    format!("Expected an operation, found {other}"),
+                ))
+            },
+        };
+        let name = match self.peek() {
+            Some(Token::Name(_)) => Some(self.name()?),
+            _ => None,
+        };
+        let variables = if self.peek_punctuator('(') {
+            self.variable_definitions()?
+        } else {
+            vec![]
+        };
+        // Directives on operations don't affect execution.
+        self.directives()?;
+        Ok(OperationDefinition {
+            operation_type,
+            name,
+            variables,
+            selection_set: self.selection_set()?,
+        })
+    }
+
+    fn variable_definitions(&mut self) -> anyhow::Result<Vec<VariableDefinition>> {
+        self.expect_punctuator('(')?;
+        let mut variables = vec![];
+        while !self.skip_punctuator(')') {
+            self.expect_punctuator('$')?;
+            let name = self.name()?;
+            self.expect_punctuator(':')?;
+            // Arguments are checked by the function's validator, so the declared
+            // type is only parsed.
+            self.type_reference()?;
+            let default_value = if self.skip_punctuator('=') {
+                Some(self.value(true)?)
+            } else {
+                None
+            };
+            self.directives()?;
+            variables.push(VariableDefinition {
+                name,
+                default_value,
+            });
+        }
+        Ok(variables)
+    }
+
+    fn type_reference(&mut self) -> anyhow::Result<()> {
+        if self.skip_punctuator('[') {
+            self.type_reference()?;
+            self.expect_punctuator(']')?;
+        } else {
+            self.name()?;
+        }
+        self.skip_punctuator('!');
+        Ok(())
+    }
+
+    fn fragment_definition(&mut self) -> anyhow::Result<(String, FragmentDefinition)> {
+        // `fragment`
+        self.name()?;
+        let name = self.name()?;
+        let line = self.line();
+        if self.name()? != "on" {
+            return Err(syntax_error(line, "Expected `on`"));
+        }
+        self.name()?;
+        self.directives()?;
+        Ok((
+            name,
+            FragmentDefinition {
+                selection_set: self.selection_set()?,
+            },
+        ))
+    }
+
+    fn selection_set(&mut self) -> anyhow::Result<Vec<Selection>> {
+        self.expect_punctuator('{')?;
+        let mut selections = vec![];
+        while !self.skip_punctuator('}') {
+            selections.push(self.selection()?);
+        }
+        if selections.is_empty() {
+            return Err(syntax_error(self.line(), "Selection sets can't be empty"));
+        }
+        Ok(selections)
+    }
+
+    fn selection(&mut self) -> anyhow::Result<Selection> {
+        if self.peek() == Some(&Token::Spread) {
+            self.position += 1;
+            if self.peek_name("on") || !matches!(self.peek(), Some(Token::Name(_))) {
+                if self.peek_name("on") {
+                    self.position += 1;
+                    self.name()?;
+                }
+                return Ok(Selection::InlineFragment {
+                    directives: self.directives()?,
+                    selection_set: self.selection_set()?,
+                });
+            }
+            return Ok(Selection::FragmentSpread {
+                name: self.name()?,
+                directives: self.directives()?,
+            });
+        }
+        let mut name = self.name()?;
+        let mut alias = None;
+        if self.skip_punctuator(':') {
+            alias = Some(name);
+            name = self.name()?;
+        }
+        let arguments = self.arguments(false)?;
+        let directives = self.directives()?;
+        let selection_set = if self.peek_punctuator('{') {
+            self.selection_set()?
+        } else {
+            vec![]
+        };
+        Ok(Selection::Field(Field {
+            alias,
+            name,
+            arguments,
+            directives,
+            selection_set,
+        }))
+    }
+
+    fn arguments(&mut self, is_const: bool) -> anyhow::Result<Vec<(String, Value)>> {
+        let mut arguments = vec![];
+        if self.skip_punctuator('(') {
+            while !self.skip_punctuator(')') {
+                let name = self.name()?;
+                self.expect_punctuator(':')?;
+                arguments.push((name, self.value(is_const)?));
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn directives(&mut self) -> anyhow::Result<Vec<Directive>> {
+        let mut directives = vec![];
+        while self.skip_punctuator('@') {
+            directives.push(Directive {
+                name: self.name()?,
+                arguments: self.arguments(false)?,
+            });
+        }
+        Ok(directives)
+    }
+
+    /// Parses a value. Constant values, like variable defaults, can't contain
+    /// variables.
+    fn value(&mut self, is_const: bool) -> anyhow::Result<Value> {
+        let line = self.line();
+        let value = match self.next()? {
+            Token::Punctuator('$') if !is_const => Value::Variable(self.name()?),
+            Token::Int(n) => Value::Int(n),
+            Token::Float(n) => Value::Float(n),
+            Token::String(s) => Value::String(s),
+            Token::Name(name) => match name.as_str() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                "null" => Value::Null,
+                _ => Value::Enum(name),
+            },
+            Token::Punctuator('[') => {
+                let mut values = vec![];
+                while !self.skip_punctuator(']') {
+                    values.push(self.value(is_const)?);
+                }
+                Value::List(values)
+            },
+            Token::Punctuator('{') => {
+                let mut fields = vec![];
+                while !self.skip_punctuator('}') {
+                    let name = self.name()?;
+                    self.expect_punctuator(':')?;
+                    fields.push((name, self.value(is_const)?));
+                }
+                Value::Object(fields)
+            },
+            token => {
+                return Err(syntax_error(
+                    line,
+                    format!("Expected a value, found {token:?}"),
+                ))
+            },
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use errors::ErrorMetadataAnyhowExt;
+
+    use super::{
+        parse_document,
+        OperationType,
+        Selection,
+        Value,
+    };
+
+    #[test]
+    fn test_parse_operations() -> anyhow::Result<()> {
+        let document = parse_document(
+            r#"
+            # Lists messages.
+            query List($channel: ID!, $limit: Float = 10) {
+                recent: messages_list(channel: $channel, limit: $limit) {
+                    _id
+                    ...Body
+                    ... on Messages @include(if: true) { author }
+                }
+            }
+            fragment Body on Messages { body }
+            mutation { messages_send(body: "hi\n", tags: ["a", B], meta: {n: -1.5e2}) }
+            "#,
+        )?;
+        assert_eq!(document.operations.len(), 2);
+        assert!(document.fragments.contains_key("Body"));
+
+        let query = &document.operations[0];
+        assert_eq!(query.operation_type, OperationType::Query);
+        assert_eq!(query.name.as_deref(), Some("List"));
+        assert_eq!(query.variables[1].default_value, Some(Value::Int(10)));
+        let Selection::Field(field) = &query.selection_set[0] else {
+            panic!("Expected a field");
+        };
+        assert_eq!(field.name, "messages_list");
+        assert_eq!(field.response_key(), "recent");
+        assert_eq!(
+            field.arguments[0],
+            (
+                "channel".to_string(),
+                Value::Variable("channel".to_string())
+            )
+        );
+        assert_eq!(field.selection_set.len(), 3);
+        assert!(matches!(
+            &field.selection_set[2],
+            Selection::InlineFragment { directives, .. } if directives[0].name == "include"
+        ));
+
+        let mutation = &document.operations[1];
+        assert_eq!(mutation.operation_type, OperationType::Mutation);
+        let Selection::Field(field) = &mutation.selection_set[0] else {
+            panic!("Expected a field");
+        };
+        assert_eq!(
+            field.arguments,
+            vec![
+                ("body".to_string(), Value::String("hi\n".to_string())),
+                (
+                    "tags".to_string(),
+                    Value::List(vec![
+                        Value::String("a".to_string()),
+                        Value::Enum("B".to_string())
+                    ])
+                ),
+                (
+                    "meta".to_string(),
+                    Value::Object(vec![("n".to_string(), Value::Float(-150.0))])
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_block_string() -> anyhow::Result<()> {
+        let document = parse_document("{ f(s: \"\"\"\n    first\n      second\n  \"\"\") }")?;
+        let Selection::Field(field) = &document.operations[0].selection_set[0] else {
+            panic!("Expected a field");
+        };
+        assert_eq!(
+            field.arguments[0].1,
+            Value::String("first\n  second".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in [
+            "",
+            "{",
+            "{ }",
+            "query { f(a: $b) ",
+            "{ f(s: \"unterminated) }",
+            "type Query { f: Int }",
+            "query Q($a: Int = $b) { f }",
+        ] {
+            let err = parse_document(source).unwrap_err();
+            assert_eq!(err.short_msg(), "InvalidGraphQL", "{source}");
+        }
+    }
+}
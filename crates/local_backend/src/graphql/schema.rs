@@ -0,0 +1,474 @@
+//! Builds the GraphQL schema for a deployment: schema-defined tables are
+//! object types, and public queries and mutations of the root app are fields
+//! of the `Query` and `Mutation` root types.
+use std::{
+    collections::BTreeMap,
+    fmt::{
+        self,
+        Display,
+        Write,
+    },
+};
+
+use application::api::{
+    PublicApiMetadata,
+    PublicFunctionMetadata,
+};
+use common::{
+    components::ExportPath,
+    schemas::{
+        validator::{
+            LiteralValidator,
+            ObjectValidator,
+            Validator,
+        },
+        DocumentSchema,
+    },
+    types::UdfType,
+};
+use model::modules::function_validators::{
+    ArgsValidator,
+    ReturnsValidator,
+};
+use value::TableName;
+
+/// Scalar for values without a GraphQL equivalent, like objects that aren't
+/// table documents or unions.
+pub const JSON_SCALAR: &str = "JSON";
+
+const BUILTIN_TYPES: [&str; 8] = [
+    "Query", "Mutation", "JSON", "ID", "String", "Float", "Int", "Boolean",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeRef {
+    Named(String),
+    List(Box<TypeRef>),
+    NonNull(Box<TypeRef>),
+}
+
+impl TypeRef {
+    /// The name of the type, without lists or non-null wrappers.
+    pub fn named_type(&self) -> &str {
+        match self {
+            Self::Named(name) => name,
+            Self::List(inner) | Self::NonNull(inner) => inner.named_type(),
+        }
+    }
+
+    fn named(name: &str) -> Self {
+        Self::Named(name.to_string())
+    }
+
+    fn non_null(self) -> Self {
+        match self {
+            Self::NonNull(_) => self,
+            _ => Self::NonNull(Box::new(self)),
+        }
+    }
+
+    fn nullable(self) -> Self {
+        match self {
+            Self::NonNull(inner) => *inner,
+            _ => self,
+        }
+    }
+}
+
+impl Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Named(name) => write!(f, "{name}"),
+            Self::List(inner) => write!(f, "[{inner}]"),
+            Self::NonNull(inner) => write!(f, "{inner}!"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjectType {
+    pub fields: BTreeMap<String, TypeRef>,
+}
+
+#[derive(Debug)]
+pub struct RootField {
+    pub path: ExportPath,
+    pub arguments: BTreeMap<String, TypeRef>,
+    pub ty: TypeRef,
+}
+
+#[derive(Debug, Default)]
+pub struct GraphQlSchema {
+    /// Object types for tables, by type name.
+    pub types: BTreeMap<String, ObjectType>,
+    pub queries: BTreeMap<String, RootField>,
+    pub mutations: BTreeMap<String, RootField>,
+}
+
+impl GraphQlSchema {
+    pub fn new(metadata: PublicApiMetadata) -> Self {
+        let mut schema = Self::default();
+        let mut table_types = BTreeMap::new();
+        let tables = metadata
+            .schema
+            .map(|schema| schema.tables)
+            .unwrap_or_default();
+        for table_name in tables.keys() {
+            let mut type_name = type_name(table_name);
+            if BUILTIN_TYPES.contains(&type_name.as_str()) {
+                type_name.push_str("Table");
+            }
+            // Skip tables whose names only differ in casing or underscores.
+            if table_types.values().any(|name| name == &type_name) {
+                continue;
+            }
+            table_types.insert(table_name.clone(), type_name);
+        }
+        for (table_name, table) in &tables {
+            let Some(type_name) = table_types.get(table_name) else {
+                continue;
+            };
+            let mut fields = BTreeMap::new();
+            fields.insert("_id".to_string(), TypeRef::named("ID").non_null());
+            fields.insert(
+                "_creationTime".to_string(),
+                TypeRef::named("Float").non_null(),
+            );
+            if let Some(DocumentSchema::Union(shapes)) = &table.document_type {
+                for shape in shapes {
+                    for (field_name, field) in shape.0.iter() {
+                        let mut ty = output_type(&field.validator, &table_types);
+                        // Fields missing from some shapes can be missing from a
+                        // document.
+                        if field.optional || shapes.len() > 1 {
+                            ty = ty.nullable();
+                        }
+                        let field_name = field_name.to_string();
+                        match fields.get(&field_name) {
+                            Some(existing) if existing != &ty => {
+                                fields.insert(field_name, TypeRef::named(JSON_SCALAR));
+                            },
+                            _ => {
+                                fields.insert(field_name, ty);
+                            },
+                        }
+                    }
+                }
+            }
+            schema
+                .types
+                .insert(type_name.clone(), ObjectType { fields });
+        }
+        for function in metadata.functions {
+            let root_fields = match function.udf_type {
+                UdfType::Query => &mut schema.queries,
+                UdfType::Mutation => &mut schema.mutations,
+                UdfType::Action | UdfType::HttpAction => continue,
+            };
+            let field_name = field_name(&function);
+            if root_fields.contains_key(&field_name) {
+                tracing::warn!(
+                    "Skipping {} in GraphQL schema: name collision",
+                    function.path
+                );
+                continue;
+            }
+            let arguments = match &function.args {
+                ArgsValidator::Validated(ObjectValidator(args)) => args
+                    .iter()
+                    .map(|(name, field)| {
+                        let ty = input_type(&field.validator);
+                        let ty = if field.optional { ty.nullable() } else { ty };
+                        (name.to_string(), ty)
+                    })
+                    .collect(),
+                // Arguments are passed through to the function as is.
+                ArgsValidator::Unvalidated => BTreeMap::new(),
+            };
+            let ty = match &function.returns {
+                ReturnsValidator::Validated(validator) => output_type(validator, &table_types),
+                ReturnsValidator::Unvalidated => TypeRef::named(JSON_SCALAR),
+            };
+            root_fields.insert(
+                field_name,
+                RootField {
+                    path: function.path.into(),
+                    arguments,
+                    ty,
+                },
+            );
+        }
+        schema
+    }
+
+    /// Returns the schema in the GraphQL schema definition language.
+    pub fn to_sdl(&self) -> String {
+        let mut sdl = format!("scalar {JSON_SCALAR}\n");
+        for (name, object_type) in &self.types {
+            let _ = write!(sdl, "\ntype {name} {{\n");
+            for (field_name, ty) in &object_type.fields {
+                let _ = writeln!(sdl, "  {field_name}: {ty}");
+            }
+            sdl.push_str("}\n");
+        }
+        for (name, fields) in [("Query", &self.queries), ("Mutation", &self.mutations)] {
+            if fields.is_empty() {
+                continue;
+            }
+            let _ = write!(sdl, "\ntype {name} {{\n");
+            for (field_name, field) in fields {
+                let arguments = field
+                    .arguments
+                    .iter()
+                    .map(|(name, ty)| format!("{name}: {ty}"))
+                    .collect::<Vec<_>>();
+                if arguments.is_empty() {
+                    let _ = writeln!(sdl, "  {field_name}: {}", field.ty);
+                } else {
+                    let _ = writeln!(
+                        sdl,
+                        "  {field_name}({}): {}",
+                        arguments.join(", "),
+                        field.ty
+                    );
+                }
+            }
+            sdl.push_str("}\n");
+        }
+        sdl
+    }
+}
+
+/// `user_profiles` -> `UserProfiles`
+fn type_name(table_name: &TableName) -> String {
+    table_name
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// `chat/messages:list` -> `chat_messages_list`, and a default export is named
+/// after its module.
+fn field_name(function: &PublicFunctionMetadata) -> String {
+    let path = function.path.clone().strip();
+    let module = path.module().as_str();
+    let mut name = match path.function_name() {
+        Some(function_name) => format!("{module}:{function_name}"),
+        None => module.to_string(),
+    };
+    name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// The type of a value matching `validator` in a response. Objects with an
+/// `_id` of a table with a type are documents of that table.
+fn output_type(validator: &Validator, table_types: &BTreeMap<TableName, String>) -> TypeRef {
+    let ty = match validator {
+        Validator::Object(ObjectValidator(fields)) => {
+            let table_type = fields
+                .iter()
+                .find_map(|(name, field)| match &field.validator {
+                    Validator::Id(table_name) if &**name == "_id" => table_types.get(table_name),
+                    _ => None,
+                });
+            match table_type {
+                Some(type_name) => TypeRef::named(type_name),
+                None => TypeRef::named(JSON_SCALAR),
+            }
+        },
+        Validator::Array(element) => TypeRef::List(Box::new(output_type(element, table_types))),
+        Validator::Union(options) => {
+            return union_type(options, |v| output_type(v, table_types));
+        },
+        _ => return scalar_type(validator),
+    };
+    ty.non_null()
+}
+
+/// The type of an argument matching `validator`. Objects are passed as JSON.
+fn input_type(validator: &Validator) -> TypeRef {
+    match validator {
+        Validator::Array(element) => TypeRef::List(Box::new(input_type(element))).non_null(),
+        Validator::Union(options) => union_type(options, input_type),
+        _ => scalar_type(validator),
+    }
+}
+
+/// GraphQL has no unions of scalars, so only `v.union(x, v.null())` or unions
+/// of literals of the same type have a type other than `JSON`.
+fn union_type(options: &[Validator], to_type: impl Fn(&Validator) -> TypeRef) -> TypeRef {
+    let nullable = options.iter().any(|v| matches!(v, Validator::Null));
+    let mut types = options
+        .iter()
+        .filter(|v| !matches!(v, Validator::Null))
+        .map(to_type);
+    let Some(first) = types.next() else {
+        return TypeRef::named(JSON_SCALAR);
+    };
+    if !types.all(|ty| ty == first) {
+        return TypeRef::named(JSON_SCALAR);
+    }
+    if nullable {
+        first.nullable()
+    } else {
+        first
+    }
+}
+
+fn scalar_type(validator: &Validator) -> TypeRef {
+    let name = match validator {
+        Validator::Id(_) => "ID",
+        Validator::Float64 | Validator::Literal(LiteralValidator::Float64(_)) => "Float",
+        Validator::Boolean | Validator::Literal(LiteralValidator::Boolean(_)) => "Boolean",
+        // 64-bit integers and bytes are strings in clean JSON.
+        Validator::String
+        | Validator::Int64
+        | Validator::Bytes
+        | Validator::Literal(LiteralValidator::String(_) | LiteralValidator::Int64(_)) => "String",
+        // `null` and values without a GraphQL type.
+        _ => return TypeRef::named(JSON_SCALAR),
+    };
+    TypeRef::named(name).non_null()
+}
+
+#[cfg(test)]
+mod tests {
+    use application::api::{
+        PublicApiMetadata,
+        PublicFunctionMetadata,
+    };
+    use common::{
+        db_schema,
+        object_validator,
+        schemas::{
+            validator::{
+                FieldValidator,
+                Validator,
+            },
+            DocumentSchema,
+        },
+        types::UdfType,
+    };
+    use model::modules::function_validators::{
+        ArgsValidator,
+        ReturnsValidator,
+    };
+
+    use super::GraphQlSchema;
+
+    fn function(
+        path: &str,
+        udf_type: UdfType,
+        args: ArgsValidator,
+        returns: ReturnsValidator,
+    ) -> anyhow::Result<PublicFunctionMetadata> {
+        Ok(PublicFunctionMetadata {
+            path: path.parse()?,
+            udf_type,
+            args,
+            returns,
+        })
+    }
+
+    #[test]
+    fn test_schema_sdl() -> anyhow::Result<()> {
+        let message = object_validator!(
+            "author" => FieldValidator::required_field_type(Validator::String),
+            "likes" => FieldValidator::optional_field_type(Validator::Int64),
+        );
+        let message_document = object_validator!(
+            "_id" => FieldValidator::required_field_type(Validator::Id("messages".parse()?)),
+            "_creationTime" => FieldValidator::required_field_type(Validator::Float64),
+            "author" => FieldValidator::required_field_type(Validator::String),
+        );
+        let metadata = PublicApiMetadata {
+            functions: vec![
+                function(
+                    "chat/messages:list",
+                    UdfType::Query,
+                    ArgsValidator::Validated(object_validator!(
+                        "author" => FieldValidator::optional_field_type(Validator::String),
+                    )),
+                    ReturnsValidator::Validated(Validator::Array(Box::new(Validator::Object(
+                        message_document,
+                    )))),
+                )?,
+                function(
+                    "chat/messages",
+                    UdfType::Mutation,
+                    ArgsValidator::Validated(object_validator!(
+                        "tags" => FieldValidator::required_field_type(
+                            Validator::Array(Box::new(Validator::String))
+                        ),
+                    )),
+                    ReturnsValidator::Validated(Validator::Union(vec![
+                        Validator::Id("messages".parse()?),
+                        Validator::Null,
+                    ])),
+                )?,
+                function(
+                    "chat/messages:summarize",
+                    UdfType::Action,
+                    ArgsValidator::Unvalidated,
+                    ReturnsValidator::Unvalidated,
+                )?,
+                function(
+                    "stats:count",
+                    UdfType::Query,
+                    ArgsValidator::Unvalidated,
+                    ReturnsValidator::Unvalidated,
+                )?,
+            ],
+            schema: Some(db_schema!(
+                "messages" => DocumentSchema::Union(vec![message]),
+                "user_profiles" => DocumentSchema::Any,
+            )),
+            http_routes: vec![],
+        };
+        let schema = GraphQlSchema::new(metadata);
+        assert_eq!(
+            schema.to_sdl(),
+            r#"scalar JSON
+
+type Messages {
+  _creationTime: Float!
+  _id: ID!
+  author: String!
+  likes: String
+}
+
+type UserProfiles {
+  _creationTime: Float!
+  _id: ID!
+}
+
+type Query {
+  chat_messages_list(author: String): [Messages!]!
+  stats_count: JSON
+}
+
+type Mutation {
+  chat_messages(tags: [String!]!): ID
+}
+"#
+        );
+        assert_eq!(
+            String::from(schema.queries["chat_messages_list"].path.clone()),
+            "chat/messages.js:list"
+        );
+        Ok(())
+    }
+}
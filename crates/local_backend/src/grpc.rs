@@ -0,0 +1,449 @@
+//! The public function API over gRPC, for backend-to-backend callers.
+//!
+//! It's served on the same listener as the HTTP API, so it needs HTTP/2,
+//! either over TLS or with prior knowledge. Requests are authenticated and
+//! rate limited like the HTTP API's, and arguments and results are JSON in the
+//! same formats.
+use anyhow::Context;
+use application::api::ExecuteQueryTimestamp;
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::Response as HttpResponse,
+    RequestPartsExt,
+    Router,
+};
+use common::{
+    components::ExportPath,
+    errors::report_error,
+    http::{
+        ExtractClientVersion,
+        ExtractRequestId,
+        ExtractResolvedHostname,
+        ResolvedHostname,
+    },
+    types::FunctionCaller,
+    version::ClientVersion,
+    RequestId,
+};
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use futures::{
+    stream::BoxStream,
+    StreamExt,
+};
+use keybroker::Identity;
+use pb::convex_api::{
+    convex_api_server::{
+        ConvexApi,
+        ConvexApiServer,
+    },
+    function_response::Result as FunctionResult,
+    FunctionError,
+    FunctionRequest,
+    FunctionResponse,
+};
+use serde_json::Value as JsonValue;
+use sync_types::AuthenticationToken;
+use tonic::{
+    Code,
+    Status,
+};
+use value::export::ValueFormat;
+
+use crate::{
+    authentication::ExtractAuthenticationToken,
+    idempotency::{
+        mutation_identifier,
+        ExtractIdempotencyKey,
+    },
+    parse::parse_export_path,
+    public_api::{
+        export_value,
+        parse_args_json,
+        query_subscription_updates,
+        UdfResponse,
+    },
+    RouterState,
+};
+
+pub fn grpc_routes(st: RouterState, max_message_bytes: usize) -> Router<RouterState> {
+    let service =
+        ConvexApiServer::new(ConvexApiService { st }).max_decoding_message_size(max_message_bytes);
+    Router::new()
+        .route_service("/convex_api.ConvexApi/*rest", service)
+        .layer(axum::middleware::from_fn(grpc_context_middleware))
+}
+
+/// What the HTTP API gets from its extractors, which the gRPC handlers can't
+/// use directly.
+#[derive(Clone)]
+struct GrpcRequestContext {
+    host: ResolvedHostname,
+    request_id: RequestId,
+    auth_token: AuthenticationToken,
+    client_version: ClientVersion,
+    idempotency_key: Option<String>,
+}
+
+async fn grpc_context_middleware(req: Request, next: Next) -> HttpResponse {
+    let (mut parts, body) = req.into_parts();
+    let context = async {
+        let ExtractResolvedHostname(host) = parts.extract().await?;
+        let ExtractRequestId(request_id) = parts.extract().await?;
+        let ExtractAuthenticationToken(auth_token) = parts.extract().await?;
+        let ExtractClientVersion(client_version) = parts.extract().await?;
+        let ExtractIdempotencyKey(idempotency_key) = parts.extract().await?;
+        anyhow::Ok(GrpcRequestContext {
+            host,
+            request_id,
+            auth_token,
+            client_version,
+            idempotency_key,
+        })
+    }
+    .await;
+    match context {
+        Ok(context) => {
+            parts.extensions.insert(context);
+            next.run(Request::from_parts(parts, body)).await
+        },
+        Err(e) => error_status(e).await.into_http::<Body>(),
+    }
+}
+
+struct ConvexApiService {
+    st: RouterState,
+}
+
+/// A parsed [`FunctionRequest`] from an authenticated caller.
+struct FunctionCall {
+    context: GrpcRequestContext,
+    identity: Identity,
+    export_path: ExportPath,
+    args: Vec<JsonValue>,
+    value_format: Option<ValueFormat>,
+}
+
+impl ConvexApiService {
+    async fn function_call(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> anyhow::Result<FunctionCall> {
+        let context = request
+            .extensions()
+            .get::<GrpcRequestContext>()
+            .cloned()
+            .context("Missing gRPC request context")?;
+        let request = request.into_inner();
+        let export_path = parse_export_path(&request.path)?;
+        let args = parse_args_json(request.args_json.as_deref())?;
+        let value_format = request.format.map(|f| f.parse()).transpose()?;
+        let identity = self
+            .st
+            .api
+            .authenticate(
+                &context.host,
+                context.request_id.clone(),
+                context.auth_token.clone(),
+            )
+            .await?;
+        Ok(FunctionCall {
+            context,
+            identity,
+            export_path,
+            args,
+            value_format,
+        })
+    }
+
+    async fn _query(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> anyhow::Result<UdfResponse> {
+        let FunctionCall {
+            context,
+            identity,
+            export_path,
+            args,
+            value_format,
+        } = self.function_call(request).await?;
+        let client_version = context.client_version;
+        let query_return = self
+            .st
+            .api
+            .execute_public_query(
+                &context.host,
+                context.request_id,
+                identity,
+                export_path,
+                args,
+                FunctionCaller::HttpApi(client_version.clone()),
+                ExecuteQueryTimestamp::Latest,
+                None,
+            )
+            .await?;
+        match query_return.result {
+            Ok(value) => Ok(UdfResponse::Success {
+                value: export_value(value, value_format, client_version)?,
+                log_lines: query_return.log_lines,
+            }),
+            Err(error) => {
+                UdfResponse::error(error, query_return.log_lines, value_format, client_version)
+            },
+        }
+    }
+
+    async fn _mutation(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> anyhow::Result<UdfResponse> {
+        let FunctionCall {
+            context,
+            identity,
+            export_path,
+            args,
+            value_format,
+        } = self.function_call(request).await?;
+        let client_version = context.client_version;
+        let mutation_identifier = context
+            .idempotency_key
+            .map(|key| mutation_identifier(&identity, &export_path, &key, &args));
+        let udf_result = self
+            .st
+            .api
+            .execute_public_mutation(
+                &context.host,
+                context.request_id,
+                identity,
+                export_path,
+                args,
+                FunctionCaller::HttpApi(client_version.clone()),
+                mutation_identifier,
+            )
+            .await?;
+        match udf_result {
+            Ok(write_return) => Ok(UdfResponse::Success {
+                value: export_value(write_return.value, value_format, client_version)?,
+                log_lines: write_return.log_lines,
+            }),
+            Err(write_error) => UdfResponse::error(
+                write_error.error,
+                write_error.log_lines,
+                value_format,
+                client_version,
+            ),
+        }
+    }
+
+    async fn _action(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> anyhow::Result<UdfResponse> {
+        let FunctionCall {
+            context,
+            identity,
+            export_path,
+            args,
+            value_format,
+        } = self.function_call(request).await?;
+        let client_version = context.client_version;
+        let action_result = self
+            .st
+            .api
+            .execute_public_action(
+                &context.host,
+                context.request_id,
+                identity,
+                export_path,
+                args,
+                FunctionCaller::HttpApi(client_version.clone()),
+            )
+            .await?;
+        match action_result {
+            Ok(action_return) => Ok(UdfResponse::Success {
+                value: export_value(action_return.value, value_format, client_version)?,
+                log_lines: action_return.log_lines,
+            }),
+            Err(action_error) => UdfResponse::error(
+                action_error.error,
+                action_error.log_lines,
+                value_format,
+                client_version,
+            ),
+        }
+    }
+
+    async fn _subscribe_query(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> anyhow::Result<BoxStream<'static, Result<FunctionResponse, Status>>> {
+        let FunctionCall {
+            context,
+            identity,
+            export_path,
+            args,
+            value_format,
+        } = self.function_call(request).await?;
+        let subscription_client = self.st.api.subscription_client(&context.host).await?;
+        let updates = query_subscription_updates(
+            self.st.clone(),
+            context.host,
+            context.request_id,
+            identity,
+            export_path,
+            args,
+            value_format,
+            None,
+            context.client_version,
+            subscription_client,
+        );
+        // The stream of updates ends after its first error.
+        let responses = updates.then(|update| async move {
+            match update.and_then(function_response) {
+                Ok(response) => Ok(response),
+                Err(e) => Err(error_status(e).await),
+            }
+        });
+        Ok(responses.boxed())
+    }
+}
+
+#[tonic::async_trait]
+impl ConvexApi for ConvexApiService {
+    type SubscribeQueryStream = BoxStream<'static, Result<FunctionResponse, Status>>;
+
+    async fn query(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> Result<tonic::Response<FunctionResponse>, Status> {
+        grpc_response(self._query(request).await.and_then(function_response)).await
+    }
+
+    async fn mutation(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> Result<tonic::Response<FunctionResponse>, Status> {
+        grpc_response(self._mutation(request).await.and_then(function_response)).await
+    }
+
+    async fn action(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> Result<tonic::Response<FunctionResponse>, Status> {
+        grpc_response(self._action(request).await.and_then(function_response)).await
+    }
+
+    async fn subscribe_query(
+        &self,
+        request: tonic::Request<FunctionRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeQueryStream>, Status> {
+        grpc_response(self._subscribe_query(request).await).await
+    }
+}
+
+fn function_response(response: UdfResponse) -> anyhow::Result<FunctionResponse> {
+    let (result, log_lines) = match response {
+        UdfResponse::Success { value, log_lines } => (
+            FunctionResult::ValueJson(serde_json::to_string(&value)?),
+            log_lines,
+        ),
+        UdfResponse::Error {
+            error_message,
+            error_data,
+            log_lines,
+        } => (
+            FunctionResult::Error(FunctionError {
+                message: error_message,
+                data_json: error_data
+                    .map(|data| serde_json::to_string(&data))
+                    .transpose()?,
+            }),
+            log_lines,
+        ),
+    };
+    Ok(FunctionResponse {
+        result: Some(result),
+        log_lines: log_lines.iter().cloned().collect(),
+    })
+}
+
+async fn grpc_response<T>(result: anyhow::Result<T>) -> Result<tonic::Response<T>, Status> {
+    match result {
+        Ok(response) => Ok(tonic::Response::new(response)),
+        Err(e) => Err(error_status(e).await),
+    }
+}
+
+/// Like `HttpResponseError`, only shows the caller the messages of errors
+/// with `ErrorMetadata`.
+async fn error_status(mut e: anyhow::Error) -> Status {
+    report_error(&mut e).await;
+    let code = e
+        .downcast_ref::<ErrorMetadata>()
+        .map_or(Code::Internal, |metadata| metadata.code.grpc_status_code());
+    Status::new(code, e.user_facing_message())
+}
+
+#[cfg(test)]
+mod tests {
+    use application::redaction::RedactedLogLines;
+    use errors::ErrorMetadata;
+    use pb::convex_api::{
+        function_response::Result as FunctionResult,
+        FunctionError,
+    };
+    use serde_json::json;
+    use tonic::Code;
+
+    use super::{
+        error_status,
+        function_response,
+    };
+    use crate::public_api::UdfResponse;
+
+    #[test]
+    fn test_function_response() -> anyhow::Result<()> {
+        let response = function_response(UdfResponse::Success {
+            value: json!({"count": "1"}),
+            log_lines: RedactedLogLines::empty(),
+        })?;
+        assert_eq!(
+            response.result,
+            Some(FunctionResult::ValueJson(r#"{"count":"1"}"#.to_string()))
+        );
+
+        let response = function_response(UdfResponse::Error {
+            error_message: "Uncaught ConvexError: nope".to_string(),
+            error_data: Some(json!("nope")),
+            log_lines: RedactedLogLines::empty(),
+        })?;
+        assert_eq!(
+            response.result,
+            Some(FunctionResult::Error(FunctionError {
+                message: "Uncaught ConvexError: nope".to_string(),
+                data_json: Some(r#""nope""#.to_string()),
+            }))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error_status() {
+        let status = error_status(anyhow::anyhow!(ErrorMetadata::unauthenticated(
+            "TokenExpired",
+            "Convex token identity expired",
+        )))
+        .await;
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(status.message(), "Convex token identity expired");
+
+        // Internal errors aren't shown to the caller.
+        let status = error_status(anyhow::anyhow!("database password is hunter2")).await;
+        assert_eq!(status.code(), Code::Internal);
+        assert!(!status.message().contains("hunter2"));
+    }
+}
@@ -0,0 +1,202 @@
+//! Health checks for load balancers and orchestrators.
+//!
+//! `GET /healthz` only says the backend is up and serving HTTP, which is what
+//! liveness probes want: restarting the backend won't fix a dependency that's
+//! down. `GET /healthz/ready` probes the backend's dependencies and responds
+//! with a 503 if any of them are failing, for readiness gates, along with
+//! each probe's status and latency.
+use std::{
+    collections::BTreeMap,
+    time::Instant,
+};
+
+use ::storage::{
+    StorageExt,
+    Upload,
+};
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use bytes::Bytes;
+use common::{
+    http::extract::Json,
+    knobs::HEALTH_CHECK_PROBE_TIMEOUT,
+    persistence::PersistenceGlobalKey,
+};
+use futures::Future;
+use http::StatusCode;
+use serde::Serialize;
+
+use crate::LocalAppState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    Ok,
+    Failing,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResult {
+    status: HealthStatus,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    status: HealthStatus,
+    components: BTreeMap<&'static str, ProbeResult>,
+}
+
+impl HealthResponse {
+    fn new(components: BTreeMap<&'static str, ProbeResult>) -> Self {
+        let status = if components
+            .values()
+            .all(|probe| probe.status == HealthStatus::Ok)
+        {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Failing
+        };
+        Self { status, components }
+    }
+}
+
+impl IntoResponse for HealthResponse {
+    fn into_response(self) -> axum::response::Response {
+        let status_code = match self.status {
+            HealthStatus::Ok => StatusCode::OK,
+            HealthStatus::Failing => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        (status_code, Json(self)).into_response()
+    }
+}
+
+async fn probe(
+    name: &'static str,
+    check: impl Future<Output = anyhow::Result<()>>,
+) -> (&'static str, ProbeResult) {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(*HEALTH_CHECK_PROBE_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!(
+            "Timed out after {:?}",
+            *HEALTH_CHECK_PROBE_TIMEOUT
+        )),
+    };
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.;
+    let probe_result = match result {
+        Ok(()) => ProbeResult {
+            status: HealthStatus::Ok,
+            latency_ms,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!("Health check probe {name} failed: {e:#}");
+            ProbeResult {
+                status: HealthStatus::Failing,
+                latency_ms,
+                error: Some(format!("{e:#}")),
+            }
+        },
+    };
+    (name, probe_result)
+}
+
+/// Reads from the database, past the in-memory snapshot.
+async fn check_persistence(st: &LocalAppState) -> anyhow::Result<()> {
+    st.persistence_reader
+        .get_persistence_global(PersistenceGlobalKey::MaxRepeatableTimestamp)
+        .await?;
+    Ok(())
+}
+
+/// Writes a small object to file storage, reads it back and deletes it.
+async fn check_storage(st: &LocalAppState) -> anyhow::Result<()> {
+    let storage = st.application.files_storage();
+    let contents = Bytes::from_static(b"healthz");
+    let mut upload = storage.start_upload().await?;
+    upload.write(contents.clone()).await?;
+    let key = upload.complete().await?;
+    let read = match storage.get(&key).await? {
+        Some(stream) => stream.collect_as_bytes().await,
+        None => Err(anyhow::anyhow!(
+            "Object {key:?} is missing after writing it"
+        )),
+    };
+    storage.delete_object(&key).await?;
+    anyhow::ensure!(read? == contents, "Object {key:?} changed after writing it");
+    Ok(())
+}
+
+/// Text and vector searches fail until their indexes are loaded after startup.
+async fn check_searcher(st: &LocalAppState) -> anyhow::Result<()> {
+    let snapshot = st.application.latest_snapshot()?;
+    anyhow::ensure!(
+        !snapshot.text_indexes.is_bootstrapping(),
+        "Text search indexes are still bootstrapping"
+    );
+    anyhow::ensure!(
+        !snapshot.vector_indexes.is_bootstrapping(),
+        "Vector search indexes are still bootstrapping"
+    );
+    Ok(())
+}
+
+async fn check_scheduler(st: &LocalAppState) -> anyhow::Result<()> {
+    let status = st.application.scheduled_job_executor_status();
+    if let Some(error) = status.last_error {
+        anyhow::bail!("Scheduled job executor is failing: {error}");
+    }
+    anyhow::ensure!(status.running, "Scheduled job executor hasn't started");
+    Ok(())
+}
+
+pub async fn healthz() -> impl IntoResponse {
+    HealthResponse::new(BTreeMap::new())
+}
+
+pub async fn healthz_ready(State(st): State<LocalAppState>) -> impl IntoResponse {
+    let (persistence, storage, searcher, scheduler) = futures::join!(
+        probe("persistence", check_persistence(&st)),
+        probe("storage", check_storage(&st)),
+        probe("searcher", check_searcher(&st)),
+        probe("scheduler", check_scheduler(&st)),
+    );
+    HealthResponse::new(BTreeMap::from([persistence, storage, searcher, scheduler]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use http::StatusCode;
+
+    use super::{
+        probe,
+        HealthResponse,
+        HealthStatus,
+    };
+
+    #[tokio::test]
+    async fn test_failing_probe_fails_response() -> anyhow::Result<()> {
+        let response = HealthResponse::new(BTreeMap::from([
+            probe("ok", async { Ok(()) }).await,
+            probe("broken", async { anyhow::bail!("connection refused") }).await,
+        ]));
+        assert_eq!(response.status, HealthStatus::Failing);
+        assert_eq!(
+            response.components["broken"].error.as_deref(),
+            Some("connection refused")
+        );
+        assert!(response.components["ok"].error.is_none());
+        let response = axum::response::IntoResponse::into_response(response);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        Ok(())
+    }
+}
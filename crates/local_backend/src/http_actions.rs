@@ -10,6 +10,12 @@ use axum::{
     },
     debug_handler,
     extract::{
+        ws::{
+            CloseFrame,
+            Message,
+            WebSocket,
+            WebSocketUpgrade,
+        },
         FromRequest,
         Host,
         State,
@@ -25,7 +31,9 @@ use axum::{
     RequestExt,
 };
 use common::{
+    errors::report_error,
     http::{
+        fetch::WebSocketMessage,
         ExtractRequestId,
         ExtractResolvedHostname,
         HttpResponseError,
@@ -41,24 +49,35 @@ use futures::{
         FusedStream,
     },
     FutureExt,
+    SinkExt,
     StreamExt,
     TryStreamExt,
 };
 use futures_async_stream::try_stream;
 use http::{
-    header::FORWARDED,
+    header::{
+        FORWARDED,
+        SEC_WEBSOCKET_PROTOCOL,
+    },
     HeaderMap,
     Method,
     StatusCode,
 };
 use keybroker::Identity;
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::sync::{
+    mpsc,
+    oneshot,
+};
+use tokio_stream::wrappers::{
+    ReceiverStream,
+    UnboundedReceiverStream,
+};
 use udf::{
     HttpActionRequest,
     HttpActionRequestHead,
     HttpActionResponsePart,
     HttpActionResponseStreamer,
+    HttpActionWebSocket,
 };
 use url::Url;
 
@@ -135,6 +154,7 @@ impl FromRequest<RouterState, axum::body::Body> for ExtractHttpRequestMetadata {
                     method,
                 },
                 body: None,
+                websocket: None,
             }));
         }
 
@@ -147,6 +167,7 @@ impl FromRequest<RouterState, axum::body::Body> for ExtractHttpRequestMetadata {
                 method,
             },
             body: Some(Box::pin(body.into_data_stream().map_err(|e| e.into()))),
+            websocket: None,
         }))
     }
 }
@@ -158,8 +179,14 @@ pub async fn http_any_method(
     TryExtractIdentity(identity_result): TryExtractIdentity,
     ExtractRequestId(request_id): ExtractRequestId,
     ExtractResolvedHostname(host): ExtractResolvedHostname,
-    ExtractHttpRequestMetadata(http_request_metadata): ExtractHttpRequestMetadata,
-) -> Result<impl IntoResponse, HttpResponseError> {
+    websocket_upgrade: Option<WebSocketUpgrade>,
+    ExtractHttpRequestMetadata(mut http_request_metadata): ExtractHttpRequestMetadata,
+) -> Result<Response, HttpResponseError> {
+    let relay = websocket_upgrade.map(|upgrade| {
+        let (relay, websocket) = WebSocketRelay::new(upgrade);
+        http_request_metadata.websocket = Some(websocket);
+        relay
+    });
     let mut http_response_stream = stream_http_response(
         host,
         request_id,
@@ -171,6 +198,12 @@ pub async fn http_any_method(
     let Some(HttpActionResponsePart::Head(response_head)) = head else {
         return Err(anyhow::anyhow!("Did not receive HTTP response head first").into());
     };
+    if response_head.status == StatusCode::SWITCHING_PROTOCOLS {
+        // The action accepted the WebSocket with `upgradeWebSocket`, which is
+        // only possible for upgrade requests.
+        let relay = relay.context("HTTP action switched protocols without an upgrade request")?;
+        return Ok(relay.accept(&response_head.headers, http_response_stream));
+    }
     let body = http_response_stream.map(|p| match p {
         Ok(HttpActionResponsePart::BodyChunk(bytes)) => Ok(bytes),
         Err(e) => Err(e),
@@ -183,7 +216,156 @@ pub async fn http_any_method(
         status: response_head.status,
         headers: response_head.headers,
         body: Box::pin(body),
-    })
+    }
+    .into_response())
+}
+
+/// How many messages can be waiting to be relayed in each direction. The
+/// action waits for room before sending, but the client can't be made to wait,
+/// so its connection is closed once the action falls this far behind.
+const WEBSOCKET_RELAY_BUFFER_SIZE: usize = 64;
+
+/// The close code for a client that sent messages faster than the action could
+/// receive them (1008, "policy violation").
+const WEBSOCKET_OVERFLOW_CLOSE_CODE: u16 = 1008;
+
+/// Relays messages between a client and the HTTP action that accepted its
+/// request's upgrade to a WebSocket.
+struct WebSocketRelay {
+    upgrade: WebSocketUpgrade,
+    to_action: mpsc::Sender<anyhow::Result<WebSocketMessage>>,
+    from_action: futures::channel::mpsc::Receiver<WebSocketMessage>,
+}
+
+impl WebSocketRelay {
+    fn new(upgrade: WebSocketUpgrade) -> (Self, HttpActionWebSocket) {
+        let (to_action, action_stream) = mpsc::channel(WEBSOCKET_RELAY_BUFFER_SIZE);
+        let (action_sink, from_action) =
+            futures::channel::mpsc::channel(WEBSOCKET_RELAY_BUFFER_SIZE);
+        let websocket = HttpActionWebSocket {
+            sink: Box::pin(action_sink.sink_map_err(anyhow::Error::from)),
+            stream: ReceiverStream::new(action_stream).boxed(),
+        };
+        let relay = Self {
+            upgrade,
+            to_action,
+            from_action,
+        };
+        (relay, websocket)
+    }
+
+    /// Completes the upgrade with the subprotocol the action picked, if any.
+    /// The rest of `action` is empty, but must be polled for the action to
+    /// keep running.
+    fn accept(
+        self,
+        headers: &HeaderMap,
+        action: BoxStream<'static, anyhow::Result<HttpActionResponsePart>>,
+    ) -> Response {
+        let protocol = headers
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let upgrade = match protocol {
+            Some(protocol) => self.upgrade.protocols([protocol]),
+            None => self.upgrade,
+        };
+        let (to_action, from_action) = (self.to_action, self.from_action);
+        upgrade.on_upgrade(move |socket| relay_websocket(socket, to_action, from_action, action))
+    }
+}
+
+async fn relay_websocket(
+    socket: WebSocket,
+    to_action: mpsc::Sender<anyhow::Result<WebSocketMessage>>,
+    mut from_action: futures::channel::mpsc::Receiver<WebSocketMessage>,
+    mut action: BoxStream<'static, anyhow::Result<HttpActionResponsePart>>,
+) {
+    let (mut client_sink, mut client_stream) = socket.split();
+    let run_action = async {
+        while let Some(part) = action.next().await {
+            if let Err(mut e) = part {
+                report_error(&mut e).await;
+            }
+        }
+    };
+    let (overflow_tx, overflow_rx) = oneshot::channel();
+    // Fused since the sender is dropped without sending when the client goes
+    // away, and the receiver can't be polled again after that.
+    let mut overflow_rx = overflow_rx.fuse();
+    // Owns `to_action` so the action sees the end of its stream as soon as
+    // this stops relaying.
+    let client_to_action = async move {
+        loop {
+            let message = tokio::select! {
+                message = client_stream.next() => message,
+                // The action is done, so there's no one to relay to.
+                _ = to_action.closed() => break,
+            };
+            let message = match message {
+                Some(Ok(Message::Text(text))) => Ok(WebSocketMessage::Text(text)),
+                Some(Ok(Message::Binary(bytes))) => Ok(WebSocketMessage::Binary(bytes.into())),
+                Some(Ok(Message::Close(frame))) => Ok(match frame {
+                    Some(frame) => WebSocketMessage::Close {
+                        code: frame.code,
+                        reason: frame.reason.into_owned(),
+                    },
+                    // 1005 is reserved for "no status code present".
+                    None => WebSocketMessage::Close {
+                        code: 1005,
+                        reason: String::new(),
+                    },
+                }),
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Err(e)) => Err(e.into()),
+                // The client went away. Dropping `to_action` tells the action.
+                None => break,
+            };
+            match to_action.try_send(message) {
+                Ok(()) => (),
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    let _ = overflow_tx.send(());
+                    break;
+                },
+                Err(mpsc::error::TrySendError::Closed(_)) => break,
+            }
+        }
+    };
+    // Likewise owns `from_action`, so the action's sends fail once this stops.
+    let action_to_client = async move {
+        loop {
+            let message = tokio::select! {
+                message = from_action.next() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                Ok(()) = &mut overflow_rx => {
+                    let _ = client_sink
+                        .send(Message::Close(Some(CloseFrame {
+                            code: WEBSOCKET_OVERFLOW_CLOSE_CODE,
+                            reason: "Too many unprocessed messages".into(),
+                        })))
+                        .await;
+                    break;
+                },
+            };
+            let message = match message {
+                WebSocketMessage::Text(text) => Message::Text(text),
+                WebSocketMessage::Binary(bytes) => Message::Binary(bytes.into()),
+                WebSocketMessage::Close { code, reason } => Message::Close(Some(CloseFrame {
+                    code,
+                    reason: reason.into(),
+                })),
+            };
+            if client_sink.send(message).await.is_err() {
+                break;
+            }
+        }
+        // The action finished or fell behind, so close the connection if it
+        // didn't.
+        let _ = client_sink.close().await;
+    };
+    tokio::join!(run_action, client_to_action, action_to_client);
 }
 
 #[try_stream(ok=HttpActionResponsePart, error=anyhow::Error, boxed)]
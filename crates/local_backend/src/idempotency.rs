@@ -0,0 +1,170 @@
+//! `Idempotency-Key` support for mutations called over HTTP.
+//!
+//! A mutation called with a key is recorded in `_session_requests` along with
+//! its result, atomically with its writes, the same way the sync protocol
+//! makes mutations idempotent. Retrying it with the same key replays the
+//! recorded result instead of running it again. Results are replayed for
+//! `IDEMPOTENCY_KEY_WINDOW_HOURS`, after which a key can be reused. Reusing a
+//! key within the window with different arguments fails with
+//! `IdempotencyKeyReused`. Mutations that fail aren't recorded, so retrying
+//! them runs them again.
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use common::{
+    components::ExportPath,
+    http::HttpResponseError,
+    identity::InertIdentity,
+    sha256::Sha256,
+};
+use errors::ErrorMetadata;
+use keybroker::Identity;
+use model::session_requests::types::SessionRequestIdentifier;
+use serde_json::Value as JsonValue;
+use sync_types::SessionId;
+use uuid::Uuid;
+use value::{
+    json_serialize,
+    ConvexValue,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+pub struct ExtractIdempotencyKey(pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ExtractIdempotencyKey
+where
+    S: Send + Sync,
+{
+    type Rejection = HttpResponseError;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(header) = parts.headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(Self(None));
+        };
+        let key = header
+            .to_str()
+            .ok()
+            .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+            .ok_or_else(|| {
+                anyhow::anyhow!(ErrorMetadata::bad_request(
+                    "InvalidIdempotencyKey",
+                    format!(
+                        "The Idempotency-Key header must be 1 to {MAX_IDEMPOTENCY_KEY_LENGTH} \
+                         printable ASCII characters"
+                    ),
+                ))
+            })?;
+        Ok(Self(Some(key.to_string())))
+    }
+}
+
+/// The session request that records a mutation called with `key`. It's scoped
+/// to the caller and the function, so a key can't replay another user's
+/// result.
+pub fn mutation_identifier(
+    identity: &Identity,
+    path: &ExportPath,
+    key: &str,
+    args: &[JsonValue],
+) -> SessionRequestIdentifier {
+    let identity = InertIdentity::from(identity.clone()).to_string();
+    let path = path.udf_path().to_string();
+    let mut hasher = Sha256::new();
+    for part in [identity.as_str(), path.as_str(), key] {
+        hasher.update(&(part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    SessionRequestIdentifier {
+        session_id: SessionId::new(Uuid::from_bytes(bytes)),
+        request_id: 0,
+        args_hash: Some(args_hash(args)),
+    }
+}
+
+/// Hashes the arguments after converting them to Convex values, so the order
+/// of object fields doesn't matter. Arguments that aren't valid Convex values
+/// are hashed as is, since the mutation fails without being recorded anyway.
+fn args_hash(args: &[JsonValue]) -> String {
+    let args = JsonValue::from(args.to_vec());
+    let serialized = ConvexValue::try_from(args.clone())
+        .and_then(json_serialize)
+        .unwrap_or_else(|_| args.to_string());
+    Sha256::hash(serialized.as_bytes()).as_hex()
+}
+
+#[cfg(test)]
+mod tests {
+    use keybroker::Identity;
+    use serde_json::json;
+
+    use super::mutation_identifier;
+
+    #[test]
+    fn test_mutation_identifier() {
+        let admin = Identity::system();
+        let identifier = |path: &str, key: &str| {
+            mutation_identifier(&admin, &path.parse().unwrap(), key, &[]).session_id
+        };
+        assert_eq!(
+            identifier("messages:send", "key-1"),
+            identifier("messages:send", "key-1")
+        );
+        assert_ne!(
+            identifier("messages:send", "key-1"),
+            identifier("messages:send", "key-2")
+        );
+        assert_ne!(
+            identifier("messages:send", "key-1"),
+            identifier("messages:edit", "key-1")
+        );
+        assert_ne!(
+            mutation_identifier(
+                &Identity::Unknown,
+                &"messages:send".parse().unwrap(),
+                "key-1",
+                &[],
+            )
+            .session_id,
+            identifier("messages:send", "key-1")
+        );
+    }
+
+    #[test]
+    fn test_args_hash() {
+        let admin = Identity::system();
+        let args_hash = |args: serde_json::Value| {
+            mutation_identifier(&admin, &"messages:send".parse().unwrap(), "key-1", &[args])
+                .args_hash
+                .unwrap()
+        };
+        // The arguments don't change which record the key refers to, only
+        // whether the recorded result can be replayed.
+        assert_eq!(
+            mutation_identifier(&admin, &"messages:send".parse().unwrap(), "key-1", &[]).session_id,
+            mutation_identifier(
+                &admin,
+                &"messages:send".parse().unwrap(),
+                "key-1",
+                &[json!({ "body": "hi" })],
+            )
+            .session_id,
+        );
+        assert_eq!(
+            args_hash(json!({ "body": "hi", "author": "me" })),
+            args_hash(json!({ "author": "me", "body": "hi" })),
+        );
+        assert_ne!(
+            args_hash(json!({ "body": "hi" })),
+            args_hash(json!({ "body": "bye" })),
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Hosting several deployments in one backend process.
+//!
+//! With `--instances-file`, the backend serves more deployments next to its
+//! own. Each has its own `Database`, storage directory and key broker, but
+//! they share the process's runtime, searcher and listeners, so many small
+//! apps don't each need a backend of their own. Requests under
+//! `/instance/<name>` are dispatched to that deployment's routes with the
+//! prefix stripped, so `http://127.0.0.1:3210/instance/acme` works as a
+//! deployment URL for the CLI, the dashboard and clients.
+use std::{
+    collections::{
+        btree_map::Entry,
+        BTreeMap,
+        BTreeSet,
+    },
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{
+        Request,
+        State,
+    },
+    response::{
+        IntoResponse,
+        Response,
+    },
+    routing::any,
+    Router,
+};
+use common::{
+    http::HttpResponseError,
+    shutdown::ShutdownSignal,
+};
+use errors::ErrorMetadata;
+use http::Uri;
+use keybroker::InstanceSecret;
+use parking_lot::RwLock;
+use runtime::prod::ProdRuntime;
+use serde::Deserialize;
+use tower::ServiceExt;
+
+use crate::{
+    config::LocalConfig,
+    make_app_with_shared_services,
+    persistence::connect_persistence,
+    router::{
+        public_router,
+        router,
+    },
+    LocalAppState,
+    SharedServices,
+};
+
+/// Hosted instances are served under `/instance/<name>`.
+pub const INSTANCE_PATH_PREFIX: &str = "/instance";
+
+const MAX_INSTANCE_NAME_LEN: usize = 64;
+
+/// A deployment to host, as listed in `--instances-file`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct InstanceSpec {
+    pub name: String,
+    /// The instance secret admin keys are generated from, as 64 hex digits.
+    pub secret: String,
+}
+
+impl InstanceSpec {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            is_valid_instance_name(&self.name),
+            "Invalid instance name {:?}. Use up to {MAX_INSTANCE_NAME_LEN} lowercase letters, \
+             digits and dashes, starting with a letter.",
+            self.name
+        );
+        InstanceSecret::try_from(self.secret.as_str())
+            .with_context(|| format!("Invalid secret for instance {}", self.name))?;
+        Ok(())
+    }
+}
+
+fn is_valid_instance_name(name: &str) -> bool {
+    name.len() <= MAX_INSTANCE_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Reads and validates the instances listed in `path`.
+pub fn load_instance_specs(path: &Path) -> anyhow::Result<Vec<InstanceSpec>> {
+    let contents = fs::read(path).with_context(|| format!("Failed to read {path:?}"))?;
+    parse_instance_specs(&contents).with_context(|| format!("Invalid instances file {path:?}"))
+}
+
+fn parse_instance_specs(contents: &[u8]) -> anyhow::Result<Vec<InstanceSpec>> {
+    let specs: Vec<InstanceSpec> = serde_json::from_slice(contents)?;
+    let mut names = BTreeSet::new();
+    for spec in &specs {
+        spec.validate()?;
+        anyhow::ensure!(
+            names.insert(&spec.name),
+            "Instance {} is listed more than once",
+            spec.name
+        );
+    }
+    Ok(specs)
+}
+
+struct HostedInstance {
+    st: LocalAppState,
+    router: Router,
+    public_router: Router,
+}
+
+/// The deployments hosted next to the process's own, by name.
+pub struct InstanceHost {
+    runtime: ProdRuntime,
+    config: LocalConfig,
+    shared: SharedServices,
+    zombify_rx: async_broadcast::Receiver<()>,
+    preempt_tx: ShutdownSignal,
+    instances: RwLock<BTreeMap<String, HostedInstance>>,
+}
+
+impl InstanceHost {
+    /// `config` is the process's own config, which hosted instances derive
+    /// theirs from.
+    pub fn new(
+        runtime: ProdRuntime,
+        config: LocalConfig,
+        shared: SharedServices,
+        zombify_rx: async_broadcast::Receiver<()>,
+        preempt_tx: ShutdownSignal,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            runtime,
+            config,
+            shared,
+            zombify_rx,
+            preempt_tx,
+            instances: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    /// Connects to the instance's database and starts serving it.
+    pub async fn start_instance(&self, spec: &InstanceSpec) -> anyhow::Result<LocalAppState> {
+        spec.validate()?;
+        anyhow::ensure!(
+            spec.name != self.config.name() && !self.instances.read().contains_key(&spec.name),
+            "Instance {} is already running",
+            spec.name
+        );
+        let config = self.config.for_instance(spec)?;
+        fs::create_dir_all(config.storage_dir())?;
+        let persistence = connect_persistence(
+            config.db,
+            &config.db_spec,
+            config.do_not_require_ssl,
+            &spec.name,
+            self.runtime.clone(),
+            self.preempt_tx.clone(),
+        )
+        .await?;
+        let st = make_app_with_shared_services(
+            self.runtime.clone(),
+            config,
+            &self.shared,
+            persistence,
+            self.zombify_rx.clone(),
+            self.preempt_tx.clone(),
+        )
+        .await?;
+        let hosted = HostedInstance {
+            st: st.clone(),
+            router: router(st.clone()),
+            public_router: public_router(st.clone()),
+        };
+        let already_running = match self.instances.write().entry(spec.name.clone()) {
+            Entry::Occupied(_) => true,
+            Entry::Vacant(entry) => {
+                entry.insert(hosted);
+                false
+            },
+        };
+        if already_running {
+            st.shutdown().await?;
+            anyhow::bail!("Instance {} is already running", spec.name);
+        }
+        tracing::info!("Hosting instance {} at {}", spec.name, st.origin);
+        Ok(st)
+    }
+
+    pub fn instance_names(&self) -> Vec<String> {
+        self.instances.read().keys().cloned().collect()
+    }
+
+    pub fn instance(&self, name: &str) -> Option<LocalAppState> {
+        self.instances
+            .read()
+            .get(name)
+            .map(|hosted| hosted.st.clone())
+    }
+
+    /// Stops serving the instance and shuts it down, if it's running. Its
+    /// data is kept.
+    pub async fn stop_instance(&self, name: &str) -> anyhow::Result<()> {
+        let hosted = self.instances.write().remove(name);
+        if let Some(hosted) = hosted {
+            tracing::info!("Shutting down instance {name}...");
+            hosted.st.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    /// Routes `/instance/<name>/...` to the hosted instances.
+    pub fn router(self: &Arc<Self>, include_admin_routes: bool) -> Router {
+        let st = DispatchState {
+            host: self.clone(),
+            include_admin_routes,
+        };
+        Router::new()
+            .route(&format!("{INSTANCE_PATH_PREFIX}/:instance"), any(dispatch))
+            .route(
+                &format!("{INSTANCE_PATH_PREFIX}/:instance/*rest"),
+                any(dispatch),
+            )
+            .with_state(st)
+    }
+
+    pub async fn shutdown(&self) -> anyhow::Result<()> {
+        let instances = std::mem::take(&mut *self.instances.write());
+        for (name, hosted) in instances {
+            tracing::info!("Shutting down instance {name}...");
+            hosted.st.shutdown().await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct DispatchState {
+    host: Arc<InstanceHost>,
+    include_admin_routes: bool,
+}
+
+async fn dispatch(
+    State(st): State<DispatchState>,
+    mut req: Request<Body>,
+) -> Result<Response, HttpResponseError> {
+    let (name, uri) = instance_uri(req.uri())?;
+    let router = st.host.instances.read().get(&name).map(|hosted| {
+        if st.include_admin_routes {
+            hosted.router.clone()
+        } else {
+            hosted.public_router.clone()
+        }
+    });
+    let Some(router) = router else {
+        return Err(anyhow::anyhow!(ErrorMetadata::not_found(
+            "InstanceNotFound",
+            format!("This backend doesn't host an instance named {name}"),
+        ))
+        .into());
+    };
+    *req.uri_mut() = uri;
+    Ok(router.oneshot(req).await.into_response())
+}
+
+/// `/instance/acme/api/query?a=1` -> (`acme`, `/api/query?a=1`).
+fn instance_uri(uri: &Uri) -> anyhow::Result<(String, Uri)> {
+    let rest = uri
+        .path()
+        .strip_prefix(INSTANCE_PATH_PREFIX)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .context("Not an instance path")?;
+    let (name, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse()?);
+    Ok((name.to_string(), Uri::from_parts(parts)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use keybroker::DEV_SECRET as SECRET;
+
+    use super::{
+        instance_uri,
+        parse_instance_specs,
+        InstanceSpec,
+    };
+
+    #[test]
+    fn test_instance_uri() -> anyhow::Result<()> {
+        let (name, uri) = instance_uri(&"/instance/acme/api/query?a=1".parse()?)?;
+        assert_eq!(name, "acme");
+        assert_eq!(uri, "/api/query?a=1");
+        let (name, uri) = instance_uri(&"/instance/acme".parse()?)?;
+        assert_eq!(name, "acme");
+        assert_eq!(uri, "/");
+        assert!(instance_uri(&"/api/query".parse()?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_instance_specs() -> anyhow::Result<()> {
+        let specs = parse_instance_specs(
+            format!(r#"[{{"name": "acme", "secret": "{SECRET}"}}]"#).as_bytes(),
+        )?;
+        assert_eq!(
+            specs,
+            vec![InstanceSpec {
+                name: "acme".to_string(),
+                secret: SECRET.to_string(),
+            }]
+        );
+
+        for invalid in [
+            format!(r#"[{{"name": "Acme", "secret": "{SECRET}"}}]"#),
+            format!(r#"[{{"name": "1acme", "secret": "{SECRET}"}}]"#),
+            format!(r#"[{{"name": "acme/api", "secret": "{SECRET}"}}]"#),
+            r#"[{"name": "acme", "secret": "not hex"}]"#.to_string(),
+            format!(
+                r#"[{{"name": "acme", "secret": "{SECRET}"}}, {{"name": "acme", "secret": "{SECRET}"}}]"#
+            ),
+        ] {
+            assert!(
+                parse_instance_specs(invalid.as_bytes()).is_err(),
+                "{invalid} should be invalid"
+            );
+        }
+        Ok(())
+    }
+}
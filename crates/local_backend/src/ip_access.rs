@@ -0,0 +1,229 @@
+//! IP-based access control for the admin, deploy and dashboard routes, so an
+//! internet-exposed deployment can limit who may even try an admin key.
+//!
+//! The policy starts out from `LocalConfig` and can be replaced at runtime
+//! through `/api/ip_access_policy`. Changes made there last until the backend
+//! restarts. Public routes, like function calls and HTTP actions, aren't
+//! affected.
+use std::{
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{
+        ConnectInfo,
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use common::http::{
+    extract::Json,
+    HttpResponseError,
+};
+use errors::ErrorMetadata;
+use ipnet::IpNet;
+use parking_lot::RwLock;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IpAccessPolicy {
+    /// Any address may connect if this is empty.
+    pub allow: Vec<IpNet>,
+    /// Takes precedence over `allow`.
+    pub deny: Vec<IpNet>,
+}
+
+impl IpAccessPolicy {
+    /// `allow` and `deny` hold CIDR blocks like `10.0.0.0/8`, or single
+    /// addresses.
+    pub fn parse(allow: &[String], deny: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            allow: allow
+                .iter()
+                .map(|net| parse_net(net))
+                .try_collect::<Vec<_>>()?,
+            deny: deny
+                .iter()
+                .map(|net| parse_net(net))
+                .try_collect::<Vec<_>>()?,
+        })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Requests without a peer address are only allowed if the policy allows
+    /// any address.
+    fn allows_client(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => self.allows(ip),
+            None => *self == Self::default(),
+        }
+    }
+}
+
+pub(crate) fn parse_net(net: &str) -> anyhow::Result<IpNet> {
+    let net = net.trim();
+    let parsed = match net.parse::<IpNet>() {
+        Ok(net) => Ok(net),
+        Err(_) => net.parse::<IpAddr>().map(IpNet::from),
+    };
+    parsed.context(ErrorMetadata::bad_request(
+        "InvalidIpNetwork",
+        format!("{net:?} isn't an IP address or a CIDR block like `10.0.0.0/8`"),
+    ))
+}
+
+#[derive(Debug, Default)]
+pub struct IpAccessControl {
+    policy: RwLock<IpAccessPolicy>,
+}
+
+impl IpAccessControl {
+    pub fn new(policy: IpAccessPolicy) -> Arc<Self> {
+        Arc::new(Self {
+            policy: RwLock::new(policy),
+        })
+    }
+
+    pub fn policy(&self) -> IpAccessPolicy {
+        self.policy.read().clone()
+    }
+
+    pub fn set_policy(&self, policy: IpAccessPolicy) {
+        *self.policy.write() = policy;
+    }
+
+    fn allows_client(&self, ip: Option<IpAddr>) -> bool {
+        self.policy.read().allows_client(ip)
+    }
+}
+
+pub async fn ip_access_middleware(
+    State(st): State<LocalAppState>,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+    let ip = st.trusted_proxies.client_ip(remote_ip, req.headers());
+    if !st.ip_access.allows_client(ip) {
+        let caller = match ip {
+            Some(ip) => ip.to_string(),
+            None => "an unknown address".to_string(),
+        };
+        let error = anyhow::anyhow!(ErrorMetadata::forbidden(
+            "IpNotAllowed",
+            format!("Requests from {caller} aren't allowed to this route"),
+        ));
+        return HttpResponseError::from(error).into_response();
+    }
+    next.run(req).await
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpAccessPolicyJson {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl From<IpAccessPolicy> for IpAccessPolicyJson {
+    fn from(policy: IpAccessPolicy) -> Self {
+        Self {
+            allow: policy.allow.iter().map(|net| net.to_string()).collect(),
+            deny: policy.deny.iter().map(|net| net.to_string()).collect(),
+        }
+    }
+}
+
+pub async fn get_ip_access_policy(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    Ok(Json(IpAccessPolicyJson::from(st.ip_access.policy())))
+}
+
+/// Replaces the policy. Policies that would block the caller are rejected, so
+/// an admin can't lock themselves out.
+pub async fn put_ip_access_policy(
+    State(st): State<LocalAppState>,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    ExtractIdentity(identity): ExtractIdentity,
+    headers: http::HeaderMap,
+    Json(req): Json<IpAccessPolicyJson>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let policy = IpAccessPolicy::parse(&req.allow, &req.deny)?;
+    let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+    let ip = st.trusted_proxies.client_ip(remote_ip, &headers);
+    if !policy.allows_client(ip) {
+        let address = match ip {
+            Some(ip) => format!("your address, {ip}"),
+            None => "callers without a known address, like you".to_string(),
+        };
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "IpAccessPolicyBlocksCaller",
+            format!("This policy would block requests from {address}"),
+        ))
+        .into());
+    }
+    st.ip_access.set_policy(policy.clone());
+    Ok(Json(IpAccessPolicyJson::from(policy)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpAccessPolicy;
+
+    #[test]
+    fn test_allows() -> anyhow::Result<()> {
+        let policy = IpAccessPolicy::parse(
+            &["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+            &["10.0.0.13".to_string()],
+        )?;
+        assert!(policy.allows("10.1.2.3".parse()?));
+        assert!(policy.allows("2001:db8::1".parse()?));
+        assert!(policy.allows("::ffff:10.1.2.3".parse()?));
+        assert!(!policy.allows("10.0.0.13".parse()?));
+        assert!(!policy.allows("203.0.113.7".parse()?));
+
+        let deny_only = IpAccessPolicy::parse(&[], &["203.0.113.0/24".to_string()])?;
+        assert!(deny_only.allows("198.51.100.2".parse()?));
+        assert!(!deny_only.allows("203.0.113.7".parse()?));
+
+        assert!(IpAccessPolicy::default().allows("203.0.113.7".parse()?));
+        assert!(IpAccessPolicy::default().allows_client(None));
+        assert!(!deny_only.allows_client(None));
+        assert!(IpAccessPolicy::parse(&["10.0.0.0/33".to_string()], &[]).is_err());
+        Ok(())
+    }
+}
@@ -17,23 +17,39 @@ use ::storage::{
     LocalDirStorage,
     StorageUseCase,
 };
+use alerting::AlertWorker;
+use anyhow::Context;
 use application::{
     api::ApplicationApi,
     log_visibility::RedactLogsToClient,
     Application,
     QueryCache,
 };
+use async_trait::async_trait;
+use auth_lockout::AuthLockouts;
+use body_limits::BodyLimits;
 use common::{
+    aws::AwsCredentials,
     http::{
-        fetch::ProxiedFetchClient,
+        fetch::{
+            FetchHostPolicy,
+            ProxiedFetchClient,
+        },
         RouteMapper,
     },
     knobs::{
         ACTION_USER_TIMEOUT,
         UDF_CACHE_MAX_SIZE,
     },
-    log_streaming::NoopLogSender,
-    persistence::Persistence,
+    log_lines::LogLine,
+    log_streaming::{
+        FanOutLogSender,
+        LogSender,
+    },
+    persistence::{
+        Persistence,
+        PersistenceReader,
+    },
     runtime::Runtime,
     shutdown::ShutdownSignal,
     types::{
@@ -41,9 +57,14 @@ use common::{
         ConvexSite,
     },
 };
-use config::LocalConfig;
+use config::{
+    LocalConfig,
+    NodeExecutorKind,
+};
+use cors::CorsPolicy;
+use custom_domains::CustomDomains;
 use database::Database;
-use events::usage::NoOpUsageEventLogger;
+use drain::Drain;
 use file_storage::{
     FileStorage,
     TransactionalFileStorage,
@@ -53,14 +74,42 @@ use function_runner::{
     server::InstanceStorage,
     FunctionRunner,
 };
+use ip_access::IpAccessControl;
+use log_sinks::LogSinks;
+use log_store::LogStore;
 use model::{
     initialize_application_system_tables,
     virtual_system_mapping,
 };
 use node_executor::{
-    local::LocalNodeExecutor,
+    docker::{
+        DockerNodeExecutor,
+        DockerNodeExecutorConfig,
+    },
+    lambda::{
+        LambdaNodeExecutor,
+        LambdaNodeExecutorConfig,
+    },
+    local::{
+        LocalNodeExecutor,
+        LocalNodeExecutorConfig,
+    },
+    remote::{
+        RemoteNodeExecutor,
+        RemoteNodeExecutorConfig,
+    },
+    resource_limits::NodeResourceLimits,
+    worker_pool::NodeWorkerPoolConfig,
     Actions,
+    ExecutorRequest,
+    InvokeResponse,
+    NodeExecutor,
 };
+use rate_limit::{
+    RateLimits,
+    TrustedProxies,
+};
+use request_timeouts::RequestTimeouts;
 use runtime::prod::ProdRuntime;
 use search::{
     searcher::InProcessSearcher,
@@ -68,34 +117,73 @@ use search::{
     SegmentTermMetadataFetcher,
 };
 use serde::Serialize;
+use subs::stats::SyncStats;
+use sync::ResumableSessions;
+use tokio::sync::mpsc;
+use usage::UsageRecorder;
 
+pub mod access_rules;
+pub mod acme;
 pub mod admin;
+pub mod admin_key_audit_log;
+pub mod admin_roles;
+pub mod alerting;
+pub mod anonymous_identities;
+pub mod api_keys;
 mod app_metrics;
 mod args_structs;
+pub mod auth_lockout;
 pub mod authentication;
 pub mod beacon;
+pub mod body_limits;
+pub mod compression;
 pub mod config;
+pub mod cors;
+pub mod custom_domains;
 pub mod custom_headers;
+pub mod custom_jwt_providers;
 pub mod dashboard;
+pub mod debugging;
+pub mod dependency_layers;
 pub mod deploy_config;
 pub mod deploy_config2;
+pub mod drain;
 pub mod environment_variables;
+pub mod graphql;
+pub mod grpc;
+pub mod health;
 pub mod http_actions;
+pub mod idempotency;
+pub mod instances;
+pub mod ip_access;
+pub mod log_sinks;
+pub mod log_store;
 pub mod logs;
 pub mod node_action_callbacks;
+pub mod openapi;
 pub mod parse;
 pub mod persistence;
+pub mod preview_deployments;
 pub mod proxy;
 pub mod public_api;
+pub mod rate_limit;
+pub mod request_timeouts;
+pub mod rest;
 pub mod router;
+pub mod saml;
 pub mod scheduling;
 pub mod schema;
+pub mod service_accounts;
 pub mod snapshot_export;
 pub mod snapshot_import;
 pub mod storage;
 pub mod subs;
 #[cfg(test)]
 mod test_helpers;
+pub mod tls;
+pub mod token_exchange;
+pub mod token_revocations;
+pub mod usage;
 
 pub const MAX_CONCURRENT_REQUESTS: usize = 128;
 
@@ -109,10 +197,30 @@ pub struct LocalAppState {
     pub instance_name: String,
     pub application: Application<ProdRuntime>,
     pub zombify_rx: async_broadcast::Receiver<()>,
+    pub rate_limits: Arc<RateLimits>,
+    pub auth_lockouts: Arc<AuthLockouts>,
+    pub cors_policy: CorsPolicy,
+    pub custom_domains: Arc<CustomDomains>,
+    pub body_limits: BodyLimits,
+    pub ip_access: Arc<IpAccessControl>,
+    pub trusted_proxies: Arc<TrustedProxies>,
+    pub drain: Arc<Drain>,
+    pub request_timeouts: Arc<RequestTimeouts>,
+    /// Whether the admin routes require a client certificate.
+    pub require_client_certificate: bool,
+    pub usage_recorder: UsageRecorder,
+    /// Persisted function logs, if the log store is enabled.
+    pub log_store: Option<Arc<LogStore>>,
+    /// For the health check to read from the database directly.
+    pub persistence_reader: Arc<dyn PersistenceReader>,
+    /// Shared by the sync WebSockets on every listener.
+    pub sync_stats: Arc<SyncStats>,
 }
 
 impl LocalAppState {
     pub async fn shutdown(self) -> anyhow::Result<()> {
+        // Persist the usage recorded since the last flush.
+        self.usage_recorder.flush(&self.application).await?;
         self.application.shutdown().await?;
 
         Ok(())
@@ -126,31 +234,171 @@ impl LocalAppState {
 pub struct RouterState {
     pub api: Arc<dyn ApplicationApi>,
     pub runtime: ProdRuntime,
+    pub rate_limits: Arc<RateLimits>,
+    pub trusted_proxies: Arc<TrustedProxies>,
+    pub resumable_sessions: Arc<ResumableSessions>,
+    pub sync_stats: Arc<SyncStats>,
 }
 
 #[derive(Serialize)]
 pub struct EmptyResponse {}
 
+#[cfg(target_os = "linux")]
+fn firecracker_node_executor(
+    config: &LocalConfig,
+    node_process_timeout: Duration,
+) -> anyhow::Result<Arc<dyn NodeExecutor>> {
+    use node_executor::firecracker::{
+        FirecrackerNodeExecutor,
+        FirecrackerNodeExecutorConfig,
+    };
+
+    let executor = FirecrackerNodeExecutor::new(FirecrackerNodeExecutorConfig {
+        firecracker_path: config.node_executor_firecracker_path.clone(),
+        kernel_image_path: config
+            .node_executor_firecracker_kernel
+            .clone()
+            .context("--node-executor-firecracker-kernel is required")?,
+        rootfs_path: config
+            .node_executor_firecracker_rootfs
+            .clone()
+            .context("--node-executor-firecracker-rootfs is required")?,
+        vcpu_count: config.node_executor_firecracker_vcpus,
+        mem_size_mib: config.node_executor_firecracker_memory_mib,
+        taps: config
+            .node_executor_firecracker_taps
+            .iter()
+            .map(|tap| tap.parse())
+            .collect::<anyhow::Result<_>>()?,
+        backend_address: config.node_executor_firecracker_backend_url.clone(),
+        pool_size: config.node_executor_firecracker_pool_size,
+        boot_timeout: Duration::from_secs(config.node_executor_firecracker_boot_timeout_secs),
+        node_process_timeout,
+    })?;
+    Ok(Arc::new(executor))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn firecracker_node_executor(
+    _config: &LocalConfig,
+    _node_process_timeout: Duration,
+) -> anyhow::Result<Arc<dyn NodeExecutor>> {
+    anyhow::bail!("The firecracker node executor is only supported on Linux")
+}
+
+/// Services that every instance hosted by a process can share.
+#[derive(Clone)]
+pub struct SharedServices {
+    searcher: Arc<dyn Searcher>,
+    segment_metadata_fetcher: Arc<dyn SegmentTermMetadataFetcher>,
+    /// The local and firecracker node executors own resources of the whole
+    /// process, like cgroups, worker processes and taps, so every instance
+    /// uses the one started with the process's config rather than its own.
+    node_executor: Option<Arc<dyn NodeExecutor>>,
+}
+
+impl SharedServices {
+    pub async fn new(runtime: ProdRuntime, config: &LocalConfig) -> anyhow::Result<Self> {
+        let in_process_searcher = InProcessSearcher::new(runtime).await?;
+        let node_executor: Option<Arc<dyn NodeExecutor>> = match config.node_executor {
+            NodeExecutorKind::Local => Some(Arc::new(LocalNodeExecutor::new_with_config(
+                LocalNodeExecutorConfig {
+                    node_process_timeout: node_process_timeout(),
+                    worker_pool: (config.node_executor_local_pool_size > 0).then_some(
+                        NodeWorkerPoolConfig {
+                            size: config.node_executor_local_pool_size,
+                            max_invocations: config.node_executor_local_max_invocations,
+                        },
+                    ),
+                    resource_limits: NodeResourceLimits {
+                        cgroup_parent: config.node_executor_local_cgroup.clone(),
+                        memory_limit_mb: config.node_executor_local_memory_mb,
+                        cpus: config.node_executor_local_cpus,
+                        max_open_files: config.node_executor_local_max_open_files,
+                    },
+                    package_cache_dir: config.node_executor_local_package_cache_dir.clone(),
+                    package_cache_max_entries: config.node_executor_local_package_cache_entries,
+                },
+            )?)),
+            NodeExecutorKind::Firecracker => {
+                Some(firecracker_node_executor(config, node_process_timeout())?)
+            },
+            NodeExecutorKind::Docker | NodeExecutorKind::Remote | NodeExecutorKind::Lambda => None,
+        };
+        Ok(Self {
+            searcher: Arc::new(in_process_searcher.clone()),
+            // TODO(CX-6572) Separate `SegmentMetadataFetcher` from `SearcherImpl`
+            segment_metadata_fetcher: Arc::new(in_process_searcher),
+            node_executor,
+        })
+    }
+}
+
+fn node_process_timeout() -> Duration {
+    *ACTION_USER_TIMEOUT + Duration::from_secs(5)
+}
+
+/// An instance's handle on the process's shared node executor. Instances are
+/// stopped independently, so stopping one doesn't shut the executor down.
+struct SharedNodeExecutor(Arc<dyn NodeExecutor>);
+
+#[async_trait]
+impl NodeExecutor for SharedNodeExecutor {
+    fn enable(&self) -> anyhow::Result<()> {
+        self.0.enable()
+    }
+
+    async fn invoke(
+        &self,
+        request: ExecutorRequest,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        self.0.invoke(request, log_line_sender).await
+    }
+
+    fn shutdown(&self) {}
+}
+
 pub async fn make_app(
     runtime: ProdRuntime,
     config: LocalConfig,
     persistence: Arc<dyn Persistence>,
     zombify_rx: async_broadcast::Receiver<()>,
     preempt_tx: ShutdownSignal,
+) -> anyhow::Result<LocalAppState> {
+    let shared = SharedServices::new(runtime.clone(), &config).await?;
+    make_app_with_shared_services(
+        runtime,
+        config,
+        &shared,
+        persistence,
+        zombify_rx,
+        preempt_tx,
+    )
+    .await
+}
+
+/// Like `make_app`, but for one of several instances in the same process,
+/// which reuse `shared` instead of starting their own.
+pub async fn make_app_with_shared_services(
+    runtime: ProdRuntime,
+    config: LocalConfig,
+    shared: &SharedServices,
+    persistence: Arc<dyn Persistence>,
+    zombify_rx: async_broadcast::Receiver<()>,
+    preempt_tx: ShutdownSignal,
 ) -> anyhow::Result<LocalAppState> {
     let key_broker = config.key_broker()?;
-    let in_process_searcher = InProcessSearcher::new(runtime.clone()).await?;
-    let searcher: Arc<dyn Searcher> = Arc::new(in_process_searcher.clone());
-    // TODO(CX-6572) Separate `SegmentMetadataFetcher` from `SearcherImpl`
-    let segment_metadata_fetcher: Arc<dyn SegmentTermMetadataFetcher> =
-        Arc::new(in_process_searcher);
+    let searcher = shared.searcher.clone();
+    let segment_metadata_fetcher = shared.segment_metadata_fetcher.clone();
+    let usage_recorder = UsageRecorder::new();
     let database = Database::load(
         persistence.clone(),
         runtime.clone(),
         searcher.clone(),
         preempt_tx,
         virtual_system_mapping().clone(),
-        Arc::new(NoOpUsageEventLogger),
+        Arc::new(usage_recorder.clone()),
     )
     .await?;
     initialize_application_system_tables(&database).await?;
@@ -191,8 +439,52 @@ pub async fn make_app(
         database: database.clone(),
     };
 
-    let node_process_timeout = *ACTION_USER_TIMEOUT + Duration::from_secs(5);
-    let node_executor = Arc::new(LocalNodeExecutor::new(node_process_timeout)?);
+    let node_process_timeout = node_process_timeout();
+    let node_executor: Arc<dyn NodeExecutor> = match config.node_executor {
+        NodeExecutorKind::Local | NodeExecutorKind::Firecracker => Arc::new(SharedNodeExecutor(
+            shared
+                .node_executor
+                .clone()
+                .context("The shared node executor wasn't started")?,
+        )),
+        NodeExecutorKind::Docker => Arc::new(DockerNodeExecutor::new(DockerNodeExecutorConfig {
+            image: config.node_executor_docker_image.clone(),
+            network: config.node_executor_docker_network.clone(),
+            backend_address: config.node_executor_docker_backend_url.clone(),
+            memory_limit_mb: config.node_executor_docker_memory_mb,
+            cpus: config.node_executor_docker_cpus,
+            pids_limit: config.node_executor_docker_pids_limit,
+            tmpfs_size_mb: config.node_executor_docker_tmpfs_mb,
+            packages_dir: modules_storage.path().clone(),
+            node_process_timeout,
+        })?),
+        NodeExecutorKind::Remote => Arc::new(RemoteNodeExecutor::new(RemoteNodeExecutorConfig {
+            url: config
+                .node_executor_remote_url
+                .clone()
+                .context("--node-executor-remote-url is required")?,
+            auth_token: config.node_executor_remote_auth_token.clone(),
+            backend_address: config.node_executor_remote_backend_url.clone(),
+            node_process_timeout,
+        })?),
+        NodeExecutorKind::Lambda => Arc::new(LambdaNodeExecutor::new(LambdaNodeExecutorConfig {
+            region: config
+                .node_executor_lambda_region
+                .clone()
+                .context("--node-executor-lambda-region is required")?,
+            function_prefix: config.node_executor_lambda_function_prefix.clone(),
+            role_arn: config
+                .node_executor_lambda_role_arn
+                .clone()
+                .context("--node-executor-lambda-role-arn is required")?,
+            memory_mb: config.node_executor_lambda_memory_mb,
+            credentials: AwsCredentials::from_env()
+                .context("The lambda node executor needs AWS credentials")?,
+            backend_address: config.node_executor_lambda_backend_url.clone(),
+            node_process_timeout,
+            endpoint: config.node_executor_lambda_endpoint.clone(),
+        })?),
+    };
     let actions = Actions::new(
         node_executor,
         config.convex_origin_url()?,
@@ -200,16 +492,25 @@ pub async fn make_app(
         runtime.clone(),
     );
 
+    let fetch_host_policy =
+        FetchHostPolicy::new(&config.fetch_allowed_hosts, &config.fetch_denied_hosts)?;
+    if !fetch_host_policy.is_unrestricted() {
+        tracing::warn!(
+            "The fetch host policy doesn't apply to Node.js actions, which can still contact any \
+             host"
+        );
+    }
     #[cfg(not(debug_assertions))]
-    if config.convex_http_proxy.is_none() {
+    if config.convex_http_proxy.is_none() && fetch_host_policy.is_unrestricted() {
         tracing::warn!(
-            "Running without a proxy in release mode -- UDF `fetch` requests are unrestricted!"
+            "Running without a proxy or fetch host policy in release mode -- UDF `fetch` requests \
+             are unrestricted!"
         );
     }
-    let fetch_client = Arc::new(ProxiedFetchClient::new(
-        config.convex_http_proxy.clone(),
-        config.name(),
-    ));
+    let fetch_client = Arc::new(
+        ProxiedFetchClient::new(config.convex_http_proxy.clone(), config.name())
+            .with_host_policy(fetch_host_policy),
+    );
     let function_runner: Arc<dyn FunctionRunner<ProdRuntime>> = Arc::new(
         InProcessFunctionRunner::new(
             config.name().clone(),
@@ -222,10 +523,29 @@ pub async fn make_app(
                 modules_storage: modules_storage.clone(),
             },
             database.clone(),
-            fetch_client,
+            fetch_client.clone(),
         )
         .await?,
     );
+    let mut log_senders: Vec<Arc<dyn LogSender>> = vec![];
+    let log_sinks = config.log_sinks();
+    if !log_sinks.is_empty() {
+        log_senders.push(Arc::new(LogSinks::start(
+            runtime.clone(),
+            config.name(),
+            log_sinks,
+        )?));
+    }
+    let log_store = match config.log_store() {
+        Some(log_store_config) => {
+            let log_store = Arc::new(LogStore::start(runtime.clone(), log_store_config)?);
+            log_senders.push(log_store.clone());
+            Some(log_store)
+        },
+        None => None,
+    };
+    let log_sender = Arc::new(FanOutLogSender(log_senders));
+    let persistence_reader = persistence.reader();
     let application = Application::new(
         runtime.clone(),
         database.clone(),
@@ -239,13 +559,14 @@ pub async fn make_app(
         key_broker.clone(),
         config.name(),
         function_runner,
+        fetch_client,
         config.convex_origin_url()?,
         config.convex_site_url()?,
         searcher.clone(),
         segment_metadata_fetcher.clone(),
         persistence,
         actions,
-        Arc::new(NoopLogSender),
+        log_sender,
         Arc::new(RedactLogsToClient::new(config.redact_logs_to_client)),
         Arc::new(ApplicationAuth::new(
             key_broker.clone(),
@@ -263,13 +584,38 @@ pub async fn make_app(
             beacon::start_beacon(runtime.clone(), database.clone(), config.beacon_tag.clone());
         runtime.spawn("beacon_worker", beacon_future);
     }
+    runtime.spawn(
+        "usage_recorder",
+        usage_recorder.clone().go(application.clone()),
+    );
+    let alert_worker = AlertWorker::new(
+        instance_name.clone(),
+        config.storage_dir(),
+        config.alert_email(),
+    )?;
+    runtime.spawn("alert_worker", alert_worker.go(application.clone()));
 
+    let drain = Drain::new(application.scheduler_drain());
     let app_state = LocalAppState {
         origin,
         site_origin: config.convex_site_url()?,
         instance_name,
         application,
         zombify_rx,
+        rate_limits: RateLimits::new(runtime.clone(), config.rate_limit_quotas()),
+        auth_lockouts: AuthLockouts::new(runtime.clone(), config.auth_lockout_policy()),
+        cors_policy: config.cors_policy(),
+        custom_domains: Arc::new(config.custom_domains()?),
+        body_limits: config.body_limits(),
+        ip_access: IpAccessControl::new(config.ip_access_policy()?),
+        trusted_proxies: config.trusted_proxies()?,
+        drain,
+        request_timeouts: config.request_timeouts(),
+        require_client_certificate: config.admin_client_ca.is_some(),
+        usage_recorder,
+        log_store,
+        persistence_reader,
+        sync_stats: SyncStats::new(),
     };
 
     Ok(app_state)
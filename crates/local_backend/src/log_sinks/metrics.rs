@@ -0,0 +1,31 @@
+use metrics::{
+    log_counter_with_labels,
+    register_convex_counter,
+    StaticMetricLabel,
+};
+
+register_convex_counter!(
+    LOG_SINK_EVENTS_SENT_TOTAL,
+    "Count of log events sent to a log sink",
+    &["sink"]
+);
+pub fn log_sink_events_sent(sink: &'static str, num_events: usize) {
+    log_counter_with_labels(
+        &LOG_SINK_EVENTS_SENT_TOTAL,
+        num_events as u64,
+        vec![StaticMetricLabel::new("sink", sink)],
+    );
+}
+
+register_convex_counter!(
+    LOG_SINK_EVENTS_DROPPED_TOTAL,
+    "Count of log events dropped because a log sink fell behind or kept failing",
+    &["sink"]
+);
+pub fn log_sink_events_dropped(sink: &'static str, num_events: usize) {
+    log_counter_with_labels(
+        &LOG_SINK_EVENTS_DROPPED_TOTAL,
+        num_events as u64,
+        vec![StaticMetricLabel::new("sink", sink)],
+    );
+}
@@ -0,0 +1,436 @@
+//! Streams function logs and deployment audit logs to external log platforms:
+//! Datadog, Axiom, or any HTTPS endpoint as a webhook.
+//!
+//! Each configured sink gets its own queue and worker, which batches events
+//! in the V2 log stream format, posts them, and retries failed requests with
+//! backoff. Events are dropped if a sink falls too far behind or keeps
+//! failing, so a slow platform never holds up functions.
+use std::time::Duration;
+
+use anyhow::Context;
+use common::{
+    backoff::Backoff,
+    log_streaming::{
+        LogEvent,
+        LogEventFormatVersion,
+        LogSender,
+    },
+    runtime::Runtime,
+};
+use hmac::{
+    Hmac,
+    Mac,
+};
+use http::{
+    header::{
+        AUTHORIZATION,
+        CONTENT_TYPE,
+    },
+    StatusCode,
+};
+use parking_lot::Mutex;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use url::Url;
+
+use crate::log_sinks::metrics::{
+    log_sink_events_dropped,
+    log_sink_events_sent,
+};
+
+mod metrics;
+
+/// Events queued for each sink. Events sent while it's full are dropped.
+const LOG_SINK_QUEUE_SIZE: usize = 10_000;
+
+/// Most events to post to a sink at once.
+const MAX_EVENTS_PER_BATCH: usize = 500;
+
+/// How long to wait for more events before posting a batch that isn't full.
+const BATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Attempts to post a batch before its events are dropped.
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Header with the hex HMAC-SHA256 of a webhook request's body, keyed with
+/// its secret.
+const WEBHOOK_SIGNATURE_HEADER: &str = "x-convex-signature";
+
+#[derive(Clone)]
+pub enum LogSinkConfig {
+    Datadog {
+        /// The Datadog site, like `datadoghq.com` or `datadoghq.eu`.
+        site: String,
+        api_key: String,
+        service: String,
+        tags: Vec<String>,
+    },
+    Axiom {
+        api_url: Url,
+        token: String,
+        dataset: String,
+    },
+    Webhook {
+        url: Url,
+        /// If set, requests are signed with it in `x-convex-signature`.
+        secret: Option<String>,
+    },
+}
+
+impl LogSinkConfig {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Datadog { .. } => "datadog",
+            Self::Axiom { .. } => "axiom",
+            Self::Webhook { .. } => "webhook",
+        }
+    }
+
+    fn url(&self) -> anyhow::Result<Url> {
+        let url = match self {
+            Self::Datadog { site, .. } => format!("https://http-intake.logs.{site}/api/v2/logs"),
+            Self::Axiom {
+                api_url, dataset, ..
+            } => format!(
+                "{}/v1/datasets/{}/ingest",
+                api_url.as_str().trim_end_matches('/'),
+                urlencoding::encode(dataset)
+            ),
+            Self::Webhook { url, .. } => return Ok(url.clone()),
+        };
+        url.parse()
+            .with_context(|| format!("Invalid {} log sink URL {url}", self.name()))
+    }
+
+    /// The JSON body that posts `events` to the sink.
+    fn body(
+        &self,
+        instance_name: &str,
+        events: Vec<serde_json::Map<String, JsonValue>>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let events: Vec<_> = match self {
+            Self::Datadog { service, tags, .. } => events
+                .into_iter()
+                .map(|mut event| {
+                    event.insert("ddsource".to_string(), json!("convex"));
+                    event.insert("service".to_string(), json!(service));
+                    event.insert("hostname".to_string(), json!(instance_name));
+                    if !tags.is_empty() {
+                        event.insert("ddtags".to_string(), json!(tags.join(",")));
+                    }
+                    event
+                })
+                .collect(),
+            Self::Axiom { .. } => events
+                .into_iter()
+                .map(|mut event| {
+                    // Axiom doesn't know the timestamp is the event's time
+                    // unless it's in `_time`.
+                    let time = event
+                        .get("timestamp")
+                        .and_then(JsonValue::as_i64)
+                        .and_then(chrono::DateTime::from_timestamp_millis);
+                    if let Some(time) = time {
+                        event.insert("_time".to_string(), json!(time.to_rfc3339()));
+                    }
+                    event.insert("deployment".to_string(), json!(instance_name));
+                    event
+                })
+                .collect(),
+            Self::Webhook { .. } => events,
+        };
+        Ok(serde_json::to_vec(&events)?)
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+        body: Vec<u8>,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let mut request = client
+            .post(url.clone())
+            .header(CONTENT_TYPE, "application/json");
+        match self {
+            Self::Datadog { api_key, .. } => {
+                request = request.header("DD-API-KEY", api_key);
+            },
+            Self::Axiom { token, .. } => {
+                request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+            },
+            Self::Webhook {
+                secret: Some(secret),
+                ..
+            } => {
+                request = request.header(WEBHOOK_SIGNATURE_HEADER, sign(secret, &body)?);
+            },
+            Self::Webhook { secret: None, .. } => {},
+        }
+        Ok(request.body(body))
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> anyhow::Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(body);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Sends logs to each of the configured sinks.
+pub struct LogSinks {
+    senders: Mutex<Vec<(&'static str, mpsc::Sender<LogEvent>)>>,
+}
+
+impl LogSinks {
+    pub fn start<RT: Runtime>(
+        rt: RT,
+        instance_name: String,
+        configs: Vec<LogSinkConfig>,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let mut senders = vec![];
+        for config in configs {
+            let name = config.name();
+            let (sender, receiver) = mpsc::channel(LOG_SINK_QUEUE_SIZE);
+            let worker = LogSinkWorker {
+                rt: rt.clone(),
+                client: client.clone(),
+                url: config.url()?,
+                config,
+                instance_name: instance_name.clone(),
+            };
+            tracing::info!("Streaming logs to {name} at {}", worker.url);
+            rt.spawn("log_sink_worker", worker.run(receiver));
+            senders.push((name, sender));
+        }
+        Ok(Self {
+            senders: Mutex::new(senders),
+        })
+    }
+}
+
+impl LogSender for LogSinks {
+    fn send_logs(&self, logs: Vec<LogEvent>) {
+        for (name, sender) in self.senders.lock().iter() {
+            let mut num_dropped = 0;
+            for log in &logs {
+                if sender.try_send(log.clone()).is_err() {
+                    num_dropped += 1;
+                }
+            }
+            if num_dropped > 0 {
+                log_sink_events_dropped(name, num_dropped);
+            }
+        }
+    }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        // The workers post what's already queued and stop once their senders
+        // are dropped.
+        self.senders.lock().clear();
+        Ok(())
+    }
+}
+
+struct LogSinkWorker<RT: Runtime> {
+    rt: RT,
+    client: reqwest::Client,
+    config: LogSinkConfig,
+    url: Url,
+    instance_name: String,
+}
+
+impl<RT: Runtime> LogSinkWorker<RT> {
+    async fn run(self, mut receiver: mpsc::Receiver<LogEvent>) {
+        // Check that the sink accepts our requests up front, so
+        // misconfigurations show up at startup.
+        let verification = match LogEvent::default_for_verification(&self.rt) {
+            Ok(event) => vec![event],
+            Err(e) => {
+                tracing::error!("Couldn't create verification log event: {e:#}");
+                vec![]
+            },
+        };
+        self.send_batch(verification).await;
+
+        let mut events = Vec::with_capacity(MAX_EVENTS_PER_BATCH);
+        while receiver.recv_many(&mut events, MAX_EVENTS_PER_BATCH).await > 0 {
+            let mut batch_deadline = self.rt.wait(BATCH_INTERVAL);
+            while events.len() < MAX_EVENTS_PER_BATCH {
+                let limit = MAX_EVENTS_PER_BATCH - events.len();
+                tokio::select! {
+                    num_received = receiver.recv_many(&mut events, limit) => {
+                        if num_received == 0 {
+                            break;
+                        }
+                    },
+                    _ = &mut batch_deadline => break,
+                }
+            }
+            self.send_batch(events.drain(..).collect()).await;
+        }
+    }
+
+    async fn send_batch(&self, events: Vec<LogEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let name = self.config.name();
+        let num_events = events.len();
+        let result: anyhow::Result<()> = try {
+            let events = events
+                .into_iter()
+                .map(|event| event.to_json_map(LogEventFormatVersion::V2))
+                .collect::<anyhow::Result<_>>()?;
+            let body = self.config.body(&self.instance_name, events)?;
+            self.post_with_retries(body).await?
+        };
+        match result {
+            Ok(()) => log_sink_events_sent(name, num_events),
+            Err(e) => {
+                tracing::error!("Dropped {num_events} log events for the {name} log sink: {e:#}");
+                log_sink_events_dropped(name, num_events);
+            },
+        }
+    }
+
+    async fn post_with_retries(&self, body: Vec<u8>) -> anyhow::Result<()> {
+        let name = self.config.name();
+        let mut backoff = Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF);
+        loop {
+            let result = self
+                .config
+                .request(&self.client, &self.url, body.clone())?
+                .send()
+                .await;
+            let error = match result {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    let error = anyhow::anyhow!("{name} log sink responded with {status}: {text}");
+                    if !is_retriable(status) {
+                        return Err(error);
+                    }
+                    error
+                },
+                Err(e) => e.into(),
+            };
+            if backoff.failures() + 1 >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+            let delay = backoff.fail(&mut self.rt.rng());
+            tracing::warn!(
+                "Failed to post to the {name} log sink, retrying in {delay:?}: {error:#}"
+            );
+            self.rt.wait(delay).await;
+        }
+    }
+}
+
+/// Other client errors mean the sink rejected the request itself, e.g.
+/// because the API key is wrong, so retrying won't help.
+fn is_retriable(status: StatusCode) -> bool {
+    !status.is_client_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use serde_json::{
+        json,
+        Value as JsonValue,
+    };
+
+    use super::{
+        is_retriable,
+        sign,
+        LogSinkConfig,
+    };
+
+    fn events() -> Vec<serde_json::Map<String, JsonValue>> {
+        let JsonValue::Object(event) = json!({
+            "timestamp": 1_700_000_000_000i64,
+            "topic": "console",
+            "message": "hello",
+        }) else {
+            unreachable!()
+        };
+        vec![event]
+    }
+
+    #[test]
+    fn test_datadog_body() -> anyhow::Result<()> {
+        let config = LogSinkConfig::Datadog {
+            site: "datadoghq.eu".to_string(),
+            api_key: "key".to_string(),
+            service: "convex".to_string(),
+            tags: vec!["env:prod".to_string(), "team:web".to_string()],
+        };
+        assert_eq!(
+            config.url()?.as_str(),
+            "https://http-intake.logs.datadoghq.eu/api/v2/logs"
+        );
+        let body: JsonValue = serde_json::from_slice(&config.body("carnitas", events())?)?;
+        assert_eq!(
+            body,
+            json!([{
+                "timestamp": 1_700_000_000_000i64,
+                "topic": "console",
+                "message": "hello",
+                "ddsource": "convex",
+                "service": "convex",
+                "hostname": "carnitas",
+                "ddtags": "env:prod,team:web",
+            }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_axiom_body() -> anyhow::Result<()> {
+        let config = LogSinkConfig::Axiom {
+            api_url: "https://api.axiom.co/".parse()?,
+            token: "token".to_string(),
+            dataset: "convex logs".to_string(),
+        };
+        assert_eq!(
+            config.url()?.as_str(),
+            "https://api.axiom.co/v1/datasets/convex%20logs/ingest"
+        );
+        let body: JsonValue = serde_json::from_slice(&config.body("carnitas", events())?)?;
+        assert_eq!(body[0]["_time"], "2023-11-14T22:13:20+00:00");
+        assert_eq!(body[0]["deployment"], "carnitas");
+        Ok(())
+    }
+
+    #[test]
+    fn test_webhook_signature() -> anyhow::Result<()> {
+        // From RFC 4231's second test case.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?")?,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_retriable() {
+        assert!(is_retriable(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_retriable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retriable(StatusCode::FORBIDDEN));
+        assert!(!is_retriable(StatusCode::BAD_REQUEST));
+    }
+}
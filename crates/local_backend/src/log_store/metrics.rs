@@ -0,0 +1,28 @@
+use metrics::{
+    log_counter,
+    register_convex_counter,
+};
+
+register_convex_counter!(
+    LOG_STORE_LINES_WRITTEN_TOTAL,
+    "Count of function log lines written to the log store"
+);
+pub fn log_store_lines_written(num_lines: usize) {
+    log_counter(&LOG_STORE_LINES_WRITTEN_TOTAL, num_lines as u64);
+}
+
+register_convex_counter!(
+    LOG_STORE_LINES_DROPPED_TOTAL,
+    "Count of function log lines dropped because the log store fell behind or failed to write them"
+);
+pub fn log_store_lines_dropped(num_lines: usize) {
+    log_counter(&LOG_STORE_LINES_DROPPED_TOTAL, num_lines as u64);
+}
+
+register_convex_counter!(
+    LOG_STORE_LINES_DELETED_TOTAL,
+    "Count of function log lines deleted from the log store after their retention"
+);
+pub fn log_store_lines_deleted(num_lines: usize) {
+    log_counter(&LOG_STORE_LINES_DELETED_TOTAL, num_lines as u64);
+}
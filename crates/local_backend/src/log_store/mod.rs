@@ -0,0 +1,525 @@
+//! Keeps function logs in a SQLite database next to the deployment's files, so
+//! they can be searched after they've aged out of the in-memory function log
+//! without an external log pipeline.
+//!
+//! Console lines and function completions are queued as they're logged and
+//! written in batches by a worker, and another worker deletes the lines older
+//! than the retention. Lines are dropped rather than slowing down functions if
+//! the writer falls behind. Messages are indexed with FTS5 for full-text
+//! search.
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use common::{
+    log_streaming::{
+        LogEvent,
+        LogSender,
+        StructuredLogEvent,
+    },
+    runtime::{
+        block_in_place,
+        Runtime,
+    },
+};
+use parking_lot::Mutex;
+use rusqlite::{
+    params,
+    params_from_iter,
+    types::Value as SqlValue,
+    Connection,
+};
+use tokio::sync::mpsc;
+
+use crate::log_store::metrics::{
+    log_store_lines_deleted,
+    log_store_lines_dropped,
+    log_store_lines_written,
+};
+
+mod metrics;
+
+/// Lines queued to be written. Lines logged while it's full are dropped.
+const LOG_STORE_QUEUE_SIZE: usize = 10_000;
+
+/// Most lines to write in one transaction.
+const MAX_LINES_PER_WRITE: usize = 1000;
+
+/// How often lines past the retention are deleted.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Most expired lines to delete in one transaction, so deleting doesn't hold
+/// up writes for long.
+const DELETE_BATCH_SIZE: usize = 10_000;
+
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+
+const INIT: &str = r#"
+PRAGMA journal_mode = WAL;
+CREATE TABLE IF NOT EXISTS function_logs (
+    id INTEGER PRIMARY KEY,
+    ts_ms INTEGER NOT NULL,
+    topic TEXT NOT NULL,
+    level TEXT NOT NULL,
+    component_path TEXT NOT NULL,
+    function TEXT NOT NULL,
+    udf_type TEXT NOT NULL,
+    request_id TEXT NOT NULL,
+    execution_id TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS function_logs_by_ts ON function_logs (ts_ms);
+CREATE INDEX IF NOT EXISTS function_logs_by_function ON function_logs (function, id);
+CREATE INDEX IF NOT EXISTS function_logs_by_request_id ON function_logs (request_id);
+CREATE VIRTUAL TABLE IF NOT EXISTS function_logs_fts USING fts5(
+    message,
+    content = 'function_logs',
+    content_rowid = 'id'
+);
+CREATE TRIGGER IF NOT EXISTS function_logs_insert AFTER INSERT ON function_logs BEGIN
+    INSERT INTO function_logs_fts (rowid, message) VALUES (new.id, new.message);
+END;
+CREATE TRIGGER IF NOT EXISTS function_logs_delete AFTER DELETE ON function_logs BEGIN
+    INSERT INTO function_logs_fts (function_logs_fts, rowid, message)
+        VALUES ('delete', old.id, old.message);
+END;
+"#;
+
+const INSERT_LINE: &str = r#"
+INSERT INTO function_logs
+    (ts_ms, topic, level, component_path, function, udf_type, request_id, execution_id, message)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+"#;
+
+const DELETE_EXPIRED: &str = r#"
+DELETE FROM function_logs WHERE id IN (
+    SELECT id FROM function_logs WHERE ts_ms < ? ORDER BY ts_ms LIMIT ?
+)
+"#;
+
+#[derive(Clone, Debug)]
+pub struct LogStoreConfig {
+    pub path: PathBuf,
+    pub retention: Duration,
+}
+
+/// A console line or function completion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredLogLine {
+    /// Milliseconds since the Unix epoch.
+    pub ts_ms: i64,
+    /// `console` or `function_execution`, like the log stream topics.
+    pub topic: String,
+    /// The console level, like `LOG` or `ERROR`, or for function completions,
+    /// `INFO` if the function succeeded and `ERROR` if it failed.
+    pub level: String,
+    /// Empty for the root app.
+    pub component_path: String,
+    pub function: String,
+    pub udf_type: String,
+    pub request_id: String,
+    pub execution_id: String,
+    pub message: String,
+}
+
+impl StoredLogLine {
+    fn from_event(event: LogEvent) -> anyhow::Result<Option<Self>> {
+        let ts_ms = event.timestamp.as_ms_since_epoch()? as i64;
+        let line = match event.event {
+            StructuredLogEvent::Console { source, log_line } => Self {
+                ts_ms,
+                topic: "console".to_string(),
+                level: log_line.level.to_string(),
+                component_path: source.component_path.serialize().unwrap_or_default(),
+                function: source.udf_path,
+                udf_type: source.udf_type.to_string(),
+                request_id: source.context.request_id.to_string(),
+                execution_id: source.context.execution_id.to_string(),
+                message: log_line.messages.join(" "),
+            },
+            StructuredLogEvent::FunctionExecution {
+                source,
+                error,
+                execution_time,
+                ..
+            } => {
+                let (level, message) = match error {
+                    Some(error) => ("ERROR", error.to_string()),
+                    None => (
+                        "INFO",
+                        format!("Completed in {}ms", execution_time.as_millis()),
+                    ),
+                };
+                Self {
+                    ts_ms,
+                    topic: "function_execution".to_string(),
+                    level: level.to_string(),
+                    component_path: source.component_path.serialize().unwrap_or_default(),
+                    function: source.udf_path,
+                    udf_type: source.udf_type.to_string(),
+                    request_id: source.context.request_id.to_string(),
+                    execution_id: source.context.execution_id.to_string(),
+                    message,
+                }
+            },
+            // Exceptions repeat the error of their function completion.
+            StructuredLogEvent::Exception { .. }
+            | StructuredLogEvent::DeploymentAuditLog { .. }
+            | StructuredLogEvent::Verification => return Ok(None),
+        };
+        Ok(Some(line))
+    }
+}
+
+/// Which stored lines to return. Every filter that's set must match.
+#[derive(Clone, Debug, Default)]
+pub struct LogSearch {
+    pub function: Option<String>,
+    pub component_path: Option<String>,
+    /// Any of these levels, or any level if empty.
+    pub levels: Vec<String>,
+    pub request_id: Option<String>,
+    /// Only lines logged at or after this many milliseconds since the Unix
+    /// epoch.
+    pub start_ms: Option<i64>,
+    /// Only lines logged before this many milliseconds since the Unix epoch.
+    pub end_ms: Option<i64>,
+    /// Words that must all be in the message.
+    pub text: Option<String>,
+    /// Only lines stored before the line with this ID, to page through older
+    /// lines.
+    pub before_id: Option<i64>,
+    pub limit: usize,
+}
+
+/// Stores function logs sent to it, and searches them.
+pub struct LogStore {
+    connection: Arc<Mutex<Connection>>,
+    sender: Mutex<Option<mpsc::Sender<StoredLogLine>>>,
+}
+
+impl LogStore {
+    pub fn start<RT: Runtime>(rt: RT, config: LogStoreConfig) -> anyhow::Result<Self> {
+        let connection = Connection::open(&config.path)?;
+        connection.execute_batch(INIT)?;
+        let connection = Arc::new(Mutex::new(connection));
+        let (sender, receiver) = mpsc::channel(LOG_STORE_QUEUE_SIZE);
+        rt.spawn(
+            "log_store_writer",
+            write_lines(connection.clone(), receiver),
+        );
+        rt.spawn(
+            "log_store_retention",
+            delete_expired_lines(rt.clone(), connection.clone(), config.retention),
+        );
+        tracing::info!(
+            "Storing function logs for {} days in {}",
+            config.retention.as_secs() / (24 * 60 * 60),
+            config.path.display()
+        );
+        Ok(Self {
+            connection,
+            sender: Mutex::new(Some(sender)),
+        })
+    }
+
+    /// The stored lines matching `search`, newest first, with their IDs.
+    pub async fn search(&self, search: LogSearch) -> anyhow::Result<Vec<(i64, StoredLogLine)>> {
+        let (sql, values) = search_query(&search);
+        block_in_place(|| {
+            let connection = self.connection.lock();
+            let mut statement = connection.prepare(&sql)?;
+            let rows = statement.query_map(params_from_iter(values), |row| {
+                Ok((
+                    row.get(0)?,
+                    StoredLogLine {
+                        ts_ms: row.get(1)?,
+                        topic: row.get(2)?,
+                        level: row.get(3)?,
+                        component_path: row.get(4)?,
+                        function: row.get(5)?,
+                        udf_type: row.get(6)?,
+                        request_id: row.get(7)?,
+                        execution_id: row.get(8)?,
+                        message: row.get(9)?,
+                    },
+                ))
+            })?;
+            Ok(rows.collect::<Result<_, _>>()?)
+        })
+    }
+}
+
+impl LogSender for LogStore {
+    fn send_logs(&self, logs: Vec<LogEvent>) {
+        let sender = self.sender.lock();
+        let Some(sender) = sender.as_ref() else {
+            return;
+        };
+        let mut num_dropped = 0;
+        for log in logs {
+            match StoredLogLine::from_event(log) {
+                Ok(Some(line)) => {
+                    if sender.try_send(line).is_err() {
+                        num_dropped += 1;
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    tracing::warn!("Couldn't store log line: {e:#}");
+                    num_dropped += 1;
+                },
+            }
+        }
+        if num_dropped > 0 {
+            log_store_lines_dropped(num_dropped);
+        }
+    }
+
+    fn shutdown(&self) -> anyhow::Result<()> {
+        // The writer writes what's already queued and stops once the sender
+        // is dropped.
+        self.sender.lock().take();
+        Ok(())
+    }
+}
+
+async fn write_lines(
+    connection: Arc<Mutex<Connection>>,
+    mut receiver: mpsc::Receiver<StoredLogLine>,
+) {
+    let mut lines = Vec::with_capacity(MAX_LINES_PER_WRITE);
+    while receiver.recv_many(&mut lines, MAX_LINES_PER_WRITE).await > 0 {
+        let num_lines = lines.len();
+        let result = block_in_place(|| insert_lines(&mut connection.lock(), &lines));
+        match result {
+            Ok(()) => log_store_lines_written(num_lines),
+            Err(e) => {
+                tracing::error!("Dropped {num_lines} function log lines: {e:#}");
+                log_store_lines_dropped(num_lines);
+            },
+        }
+        lines.clear();
+    }
+}
+
+fn insert_lines(connection: &mut Connection, lines: &[StoredLogLine]) -> anyhow::Result<()> {
+    let tx = connection.transaction()?;
+    {
+        let mut statement = tx.prepare_cached(INSERT_LINE)?;
+        for line in lines {
+            statement.execute(params![
+                line.ts_ms,
+                line.topic,
+                line.level,
+                line.component_path,
+                line.function,
+                line.udf_type,
+                line.request_id,
+                line.execution_id,
+                line.message,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+async fn delete_expired_lines<RT: Runtime>(
+    rt: RT,
+    connection: Arc<Mutex<Connection>>,
+    retention: Duration,
+) {
+    loop {
+        let cutoff = rt.system_time().checked_sub(retention);
+        let result = block_in_place(|| {
+            let Some(cutoff) = cutoff else {
+                return Ok(0);
+            };
+            let cutoff_ms = cutoff.duration_since(SystemTime::UNIX_EPOCH)?.as_millis() as i64;
+            let mut num_deleted = 0;
+            loop {
+                let num_deleted_in_batch = connection
+                    .lock()
+                    .execute(DELETE_EXPIRED, params![cutoff_ms, DELETE_BATCH_SIZE])?;
+                num_deleted += num_deleted_in_batch;
+                if num_deleted_in_batch < DELETE_BATCH_SIZE {
+                    return anyhow::Ok(num_deleted);
+                }
+            }
+        });
+        match result {
+            Ok(num_deleted) => log_store_lines_deleted(num_deleted),
+            Err(e) => tracing::error!("Failed to delete expired function log lines: {e:#}"),
+        }
+        rt.wait(RETENTION_INTERVAL).await;
+    }
+}
+
+/// An FTS5 query that matches messages with all of the words in `text`, which
+/// are quoted so punctuation in them is matched rather than parsed as query
+/// syntax.
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn search_query(search: &LogSearch) -> (String, Vec<SqlValue>) {
+    let mut conditions = vec![];
+    let mut values = vec![];
+    if let Some(text) = &search.text
+        && !text.trim().is_empty()
+    {
+        conditions.push(
+            "id IN (SELECT rowid FROM function_logs_fts WHERE function_logs_fts MATCH ?)"
+                .to_string(),
+        );
+        values.push(SqlValue::Text(fts_query(text)));
+    }
+    if let Some(function) = &search.function {
+        conditions.push("function = ?".to_string());
+        values.push(SqlValue::Text(function.clone()));
+    }
+    if let Some(component_path) = &search.component_path {
+        conditions.push("component_path = ?".to_string());
+        values.push(SqlValue::Text(component_path.clone()));
+    }
+    if !search.levels.is_empty() {
+        let placeholders = vec!["?"; search.levels.len()].join(", ");
+        conditions.push(format!("level IN ({placeholders})"));
+        values.extend(
+            search
+                .levels
+                .iter()
+                .map(|level| SqlValue::Text(level.to_uppercase())),
+        );
+    }
+    if let Some(request_id) = &search.request_id {
+        conditions.push("request_id = ?".to_string());
+        values.push(SqlValue::Text(request_id.clone()));
+    }
+    if let Some(start_ms) = search.start_ms {
+        conditions.push("ts_ms >= ?".to_string());
+        values.push(SqlValue::Integer(start_ms));
+    }
+    if let Some(end_ms) = search.end_ms {
+        conditions.push("ts_ms < ?".to_string());
+        values.push(SqlValue::Integer(end_ms));
+    }
+    if let Some(before_id) = search.before_id {
+        conditions.push("id < ?".to_string());
+        values.push(SqlValue::Integer(before_id));
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    values.push(SqlValue::Integer(search.limit.min(MAX_SEARCH_LIMIT) as i64));
+    let sql = format!(
+        "SELECT id, ts_ms, topic, level, component_path, function, udf_type, request_id, \
+         execution_id, message FROM function_logs {where_clause} ORDER BY id DESC LIMIT ?"
+    );
+    (sql, values)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::{
+        fts_query,
+        insert_lines,
+        search_query,
+        LogSearch,
+        StoredLogLine,
+        INIT,
+    };
+
+    fn line(function: &str, level: &str, request_id: &str, message: &str) -> StoredLogLine {
+        StoredLogLine {
+            ts_ms: 1000,
+            topic: "console".to_string(),
+            level: level.to_string(),
+            component_path: String::new(),
+            function: function.to_string(),
+            udf_type: "Mutation".to_string(),
+            request_id: request_id.to_string(),
+            execution_id: "1".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn search(connection: &Connection, search: LogSearch) -> anyhow::Result<Vec<i64>> {
+        let (sql, values) = search_query(&search);
+        let mut statement = connection.prepare(&sql)?;
+        let ids = statement
+            .query_map(rusqlite::params_from_iter(values), |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+
+    #[test]
+    fn test_search() -> anyhow::Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        connection.execute_batch(INIT)?;
+        insert_lines(
+            &mut connection,
+            &[
+                line("messages:send", "LOG", "a", "sending message to #general"),
+                line(
+                    "messages:send",
+                    "ERROR",
+                    "a",
+                    "failed to send: rate limited",
+                ),
+                line("users:get", "LOG", "b", "looking up user 42"),
+            ],
+        )?;
+        let all = LogSearch {
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(search(&connection, all.clone())?, vec![3, 2, 1]);
+        let by_function = LogSearch {
+            function: Some("messages:send".to_string()),
+            ..all.clone()
+        };
+        assert_eq!(search(&connection, by_function)?, vec![2, 1]);
+        let by_level = LogSearch {
+            levels: vec!["error".to_string()],
+            ..all.clone()
+        };
+        assert_eq!(search(&connection, by_level)?, vec![2]);
+        let by_request_id = LogSearch {
+            request_id: Some("b".to_string()),
+            ..all.clone()
+        };
+        assert_eq!(search(&connection, by_request_id)?, vec![3]);
+        let by_text = LogSearch {
+            text: Some("message #general".to_string()),
+            ..all.clone()
+        };
+        assert_eq!(search(&connection, by_text)?, vec![1]);
+        let before = LogSearch {
+            before_id: Some(3),
+            limit: 1,
+            ..all
+        };
+        assert_eq!(search(&connection, before)?, vec![2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("rate limited"), r#""rate" "limited""#);
+        assert_eq!(fts_query(r#"say "hi""#), r#""say" """hi""""#);
+    }
+}
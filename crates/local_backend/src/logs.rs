@@ -4,13 +4,22 @@ use anyhow::Context;
 use application::function_log::{
     FunctionExecution,
     FunctionExecutionPart,
+    SlowFunctionExecution,
     UdfParams,
 };
 use axum::{
-    extract::State,
+    extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
+        State,
+        WebSocketUpgrade,
+    },
     response::IntoResponse,
 };
 use common::{
+    errors::report_error,
     http::{
         extract::{
             Json,
@@ -19,11 +28,25 @@ use common::{
         ExtractClientVersion,
         HttpResponseError,
     },
-    version::ClientType,
+    log_lines::{
+        LogLevel,
+        LogLine,
+        LogLines,
+    },
+    version::{
+        ClientType,
+        ClientVersion,
+    },
+    ws::is_connection_closed_error,
     RequestId,
 };
 use errors::ErrorMetadata;
-use futures::FutureExt;
+use futures::{
+    FutureExt,
+    SinkExt,
+    StreamExt,
+};
+use keybroker::Identity;
 use serde::{
     Deserialize,
     Serialize,
@@ -31,7 +54,12 @@ use serde::{
 use serde_json::Value as JsonValue;
 
 use crate::{
+    admin::must_be_admin,
     authentication::ExtractIdentity,
+    log_store::{
+        LogSearch,
+        StoredLogLine,
+    },
     LocalAppState,
 };
 
@@ -56,6 +84,10 @@ pub enum FunctionExecutionJson {
         error: Option<String>,
         request_id: String,
         execution_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trace_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        span_id: Option<String>,
     },
     #[serde(rename_all = "camelCase")]
     Progress {
@@ -66,6 +98,10 @@ pub enum FunctionExecutionJson {
         log_lines: Vec<JsonValue>,
         request_id: String,
         execution_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trace_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        span_id: Option<String>,
     },
 }
 
@@ -149,24 +185,7 @@ pub async fn stream_function_logs(
         )),
         _ => None,
     };
-    // As of writing, this endpoint is only used by the CLI and dashboard, both of
-    // which support either unstructured `string` log lines or structured log
-    // lines.
-    let supports_structured_log_lines = match client_version.client() {
-        ClientType::CLI => true,
-        ClientType::Dashboard => true,
-        ClientType::NPM
-        | ClientType::Actions
-        | ClientType::Python
-        | ClientType::Rust
-        | ClientType::StreamingImport
-        | ClientType::AirbyteExport
-        | ClientType::FivetranImport
-        | ClientType::FivetranExport
-        | ClientType::Swift
-        | ClientType::Kotlin
-        | ClientType::Unrecognized(_) => false,
-    };
+    let supports_structured_log_lines = supports_structured_log_lines(&client_version);
     futures::select_biased! {
         entries_future_r = entries_future.fuse() => {
             let (log_entries, new_cursor) = entries_future_r?;
@@ -186,29 +205,7 @@ pub async fn stream_function_logs(
                         }
                     }
                 })
-                .map(|e| {
-                    let json = match e {
-                        FunctionExecutionPart::Completion(c) => {
-                            execution_to_json(c, supports_structured_log_lines)?
-                        },
-                        FunctionExecutionPart::Progress(c) => {
-                            FunctionExecutionJson::Progress {
-                                udf_type: c.event_source.udf_type.to_string(),
-                                component_path: c.event_source.component_path.serialize(),
-                                identifier: c.event_source.udf_path,
-                                timestamp: c.function_start_timestamp.as_secs_f64(),
-                                log_lines: c.log_lines.to_jsons(
-                                    supports_structured_log_lines,
-                                    false,
-                                )?,
-                                request_id: c.event_source.context.request_id.to_string(),
-                                execution_id: c.event_source.context.execution_id.to_string()
-                            }
-                        }
-                    };
-
-                    Ok(json)
-                })
+                .map(|e| part_to_json(e, supports_structured_log_lines))
                 .collect::<anyhow::Result<_>>()?;
             let response = StreamUdfExecutionResponse {
                 entries,
@@ -230,10 +227,384 @@ pub async fn stream_function_logs(
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TailFunctionLogsQueryArgs {
+    /// Only entries for this function, like `messages:send`.
+    function: Option<String>,
+    /// Comma-separated, like `ERROR,WARN`.
+    levels: Option<String>,
+}
+
+/// Which entries a log tail pushes. Unset filters match everything.
+struct LogTailFilter {
+    function: Option<String>,
+    /// Log lines at any of these levels, or every log line if empty. Failed
+    /// completions count as `ERROR`.
+    levels: Vec<LogLevel>,
+}
+
+impl LogTailFilter {
+    fn new(query_args: TailFunctionLogsQueryArgs) -> anyhow::Result<Self> {
+        let levels = query_args
+            .levels
+            .iter()
+            .flat_map(|levels| levels.split(','))
+            .map(str::trim)
+            .filter(|level| !level.is_empty())
+            .map(|level| {
+                level.to_uppercase().parse().map_err(|_| {
+                    anyhow::anyhow!(ErrorMetadata::bad_request(
+                        "InvalidLogLevel",
+                        format!("Unknown log level {level}"),
+                    ))
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            function: query_args.function,
+            levels,
+        })
+    }
+
+    fn filter_log_lines(&self, log_lines: LogLines) -> LogLines {
+        if self.levels.is_empty() {
+            return log_lines;
+        }
+        log_lines
+            .into_iter()
+            .filter_map(|log_line| match log_line {
+                LogLine::Structured(line) => self
+                    .levels
+                    .contains(&line.level)
+                    .then_some(LogLine::Structured(line)),
+                LogLine::SubFunction { path, log_lines } => {
+                    let log_lines = self.filter_log_lines(log_lines);
+                    (!log_lines.is_empty()).then_some(LogLine::SubFunction { path, log_lines })
+                },
+            })
+            .collect()
+    }
+
+    /// Drops the log lines that don't match, and the entry if nothing in it
+    /// does.
+    fn apply(&self, part: FunctionExecutionPart) -> Option<FunctionExecutionPart> {
+        match part {
+            FunctionExecutionPart::Completion(mut c) => {
+                let (identifier, failed) = match &c.params {
+                    UdfParams::Function { error, identifier } => {
+                        (identifier.udf_path.strip().to_string(), error.is_some())
+                    },
+                    UdfParams::Http { result, identifier } => {
+                        (identifier.to_string(), result.is_err())
+                    },
+                };
+                if self.function.as_ref().is_some_and(|f| *f != identifier) {
+                    return None;
+                }
+                c.log_lines = self.filter_log_lines(c.log_lines);
+                let matches = self.levels.is_empty()
+                    || !c.log_lines.is_empty()
+                    || (failed && self.levels.contains(&LogLevel::Error));
+                matches.then_some(FunctionExecutionPart::Completion(c))
+            },
+            FunctionExecutionPart::Progress(mut c) => {
+                if self
+                    .function
+                    .as_ref()
+                    .is_some_and(|f| *f != c.event_source.udf_path)
+                {
+                    return None;
+                }
+                c.log_lines = self.filter_log_lines(c.log_lines);
+                (self.levels.is_empty() || !c.log_lines.is_empty())
+                    .then_some(FunctionExecutionPart::Progress(c))
+            },
+        }
+    }
+}
+
+// Pushes log lines and function completions over a WebSocket as they're
+// logged, in the same JSON as `stream_function_logs`, one entry per message.
+// Only entries logged after the socket connects are sent.
+pub async fn tail_function_logs(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Query(query_args): Query<TailFunctionLogsQueryArgs>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let filter = LogTailFilter::new(query_args)?;
+    let supports_structured_log_lines = supports_structured_log_lines(&client_version);
+    Ok(ws.on_upgrade(move |socket| async move {
+        if let Err(mut e) =
+            run_log_tail(st, identity, filter, supports_structured_log_lines, socket).await
+        {
+            if !e
+                .downcast_ref::<axum::Error>()
+                .is_some_and(|e| is_connection_closed_error(e))
+            {
+                report_error(&mut e).await;
+            }
+        }
+    }))
+}
+
+async fn run_log_tail(
+    st: LocalAppState,
+    identity: Identity,
+    filter: LogTailFilter,
+    supports_structured_log_lines: bool,
+    socket: WebSocket,
+) -> anyhow::Result<()> {
+    let (mut tx, mut rx) = socket.split();
+    let mut zombify_rx = st.zombify_rx.clone();
+    let mut cursor = st.application.latest_function_log_cursor();
+    loop {
+        futures::select_biased! {
+            message = rx.next().fuse() => match message {
+                // Anything the client sends besides closing the socket is ignored.
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            },
+            _ = zombify_rx.recv().fuse() => {
+                tx.send(Message::Close(None)).await?;
+                return Ok(());
+            },
+            entries = st
+                .application
+                .stream_function_logs(identity.clone(), cursor)
+                .fuse() => {
+                let (entries, new_cursor) = entries?;
+                cursor = new_cursor;
+                for entry in entries {
+                    let Some(entry) = filter.apply(entry) else {
+                        continue;
+                    };
+                    let json = part_to_json(entry, supports_structured_log_lines)?;
+                    tx.send(Message::Text(serde_json::to_string(&json)?)).await?;
+                }
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SlowFunctionLogsQueryArgs {
+    cursor: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowFunctionExecutionJson {
+    udf_type: String,
+    component_path: Option<String>,
+    identifier: String,
+    timestamp: f64,
+    execution_time: f64,
+    indexes_read: Vec<String>,
+    documents_scanned: u64,
+    database_read_bytes: u64,
+    request_id: String,
+}
+
+impl From<SlowFunctionExecution> for SlowFunctionExecutionJson {
+    fn from(execution: SlowFunctionExecution) -> Self {
+        Self {
+            udf_type: execution.udf_type.to_string(),
+            component_path: execution.identifier.component.serialize(),
+            identifier: execution.identifier.udf_path.strip().into(),
+            timestamp: execution.unix_timestamp.as_secs_f64(),
+            execution_time: execution.execution_time.as_secs_f64(),
+            indexes_read: execution.indexes_read.into_iter().collect(),
+            documents_scanned: execution.documents_scanned,
+            database_read_bytes: execution.database_read_bytes,
+            request_id: execution.request_id.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowFunctionLogsResponse {
+    entries: Vec<SlowFunctionExecutionJson>,
+    new_cursor: f64,
+}
+
+// Lists the queries and mutations that crossed the slow function log's
+// thresholds after `cursor`, or all the ones still in memory without a cursor.
+// Unlike the streaming routes, this returns right away.
+pub async fn slow_function_logs(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(query_args): Query<SlowFunctionLogsQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let (entries, new_cursor) = st
+        .application
+        .slow_function_logs(identity, query_args.cursor.unwrap_or(0.))?;
+    Ok(Json(SlowFunctionLogsResponse {
+        entries: entries.into_iter().map(Into::into).collect(),
+        new_cursor,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFunctionLogsQueryArgs {
+    function: Option<String>,
+    component_path: Option<String>,
+    /// Comma-separated, like `ERROR,WARN`.
+    levels: Option<String>,
+    request_id: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    start: Option<i64>,
+    end: Option<i64>,
+    query: Option<String>,
+    before: Option<i64>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredLogLineJson {
+    id: i64,
+    timestamp: i64,
+    topic: String,
+    level: String,
+    component_path: Option<String>,
+    identifier: String,
+    udf_type: String,
+    request_id: String,
+    execution_id: String,
+    message: String,
+}
+
+impl StoredLogLineJson {
+    fn new(id: i64, line: StoredLogLine) -> Self {
+        Self {
+            id,
+            timestamp: line.ts_ms,
+            topic: line.topic,
+            level: line.level,
+            component_path: Some(line.component_path).filter(|path| !path.is_empty()),
+            identifier: line.function,
+            udf_type: line.udf_type,
+            request_id: line.request_id,
+            execution_id: line.execution_id,
+            message: line.message,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchFunctionLogsResponse {
+    entries: Vec<StoredLogLineJson>,
+    /// Pass as `before` to get the next page, or `None` if this was the last.
+    next_cursor: Option<i64>,
+}
+
+// Searches the function logs persisted in the log store, newest first.
+pub async fn search_function_logs(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(query_args): Query<SearchFunctionLogsQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let Some(log_store) = st.log_store else {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "FunctionLogStoreDisabled",
+            "Function logs aren't persisted because --function-log-retention-days is 0",
+        ))
+        .into());
+    };
+    let limit = query_args.limit.unwrap_or(100);
+    let search = LogSearch {
+        function: query_args.function,
+        component_path: query_args.component_path,
+        levels: query_args
+            .levels
+            .iter()
+            .flat_map(|levels| levels.split(','))
+            .map(|level| level.trim().to_string())
+            .filter(|level| !level.is_empty())
+            .collect(),
+        request_id: query_args.request_id,
+        start_ms: query_args.start,
+        end_ms: query_args.end,
+        text: query_args.query,
+        before_id: query_args.before,
+        limit,
+    };
+    let lines = log_store.search(search).await?;
+    let next_cursor = if lines.len() < limit {
+        None
+    } else {
+        lines.last().map(|(id, _)| *id)
+    };
+    Ok(Json(SearchFunctionLogsResponse {
+        entries: lines
+            .into_iter()
+            .map(|(id, line)| StoredLogLineJson::new(id, line))
+            .collect(),
+        next_cursor,
+    }))
+}
+
+// As of writing, the log streaming endpoints are only used by the CLI and
+// dashboard, both of which support either unstructured `string` log lines or
+// structured log lines.
+fn supports_structured_log_lines(client_version: &ClientVersion) -> bool {
+    match client_version.client() {
+        ClientType::CLI => true,
+        ClientType::Dashboard => true,
+        ClientType::NPM
+        | ClientType::Actions
+        | ClientType::Python
+        | ClientType::Rust
+        | ClientType::StreamingImport
+        | ClientType::AirbyteExport
+        | ClientType::FivetranImport
+        | ClientType::FivetranExport
+        | ClientType::Swift
+        | ClientType::Kotlin
+        | ClientType::Unrecognized(_) => false,
+    }
+}
+
+fn part_to_json(
+    part: FunctionExecutionPart,
+    supports_structured_log_lines: bool,
+) -> anyhow::Result<FunctionExecutionJson> {
+    let json = match part {
+        FunctionExecutionPart::Completion(c) => {
+            execution_to_json(c, supports_structured_log_lines)?
+        },
+        FunctionExecutionPart::Progress(c) => {
+            let (trace_id, span_id) = c.event_source.context.trace_ids().unzip();
+            FunctionExecutionJson::Progress {
+                udf_type: c.event_source.udf_type.to_string(),
+                component_path: c.event_source.component_path.serialize(),
+                identifier: c.event_source.udf_path,
+                timestamp: c.function_start_timestamp.as_secs_f64(),
+                log_lines: c.log_lines.to_jsons(supports_structured_log_lines, false)?,
+                request_id: c.event_source.context.request_id.to_string(),
+                execution_id: c.event_source.context.execution_id.to_string(),
+                trace_id,
+                span_id,
+            }
+        },
+    };
+    Ok(json)
+}
+
 fn execution_to_json(
     execution: FunctionExecution,
     supports_structured_log_lines: bool,
 ) -> anyhow::Result<FunctionExecutionJson> {
+    let (trace_id, span_id) = execution.context.trace_ids().unzip();
     let json = match execution.params {
         UdfParams::Function { error, identifier } => {
             let component_path = identifier.component.serialize();
@@ -252,6 +623,8 @@ fn execution_to_json(
                 error: error.map(|e| e.to_string()),
                 request_id: execution.context.request_id.to_string(),
                 execution_id: execution.context.execution_id.to_string(),
+                trace_id,
+                span_id,
             }
         },
         UdfParams::Http { result, identifier } => {
@@ -274,8 +647,151 @@ fn execution_to_json(
                 error: error.map(|e| e.to_string()),
                 request_id: execution.context.request_id.to_string(),
                 execution_id: execution.context.execution_id.to_string(),
+                trace_id,
+                span_id,
             }
         },
     };
     Ok(json)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        time::Duration,
+    };
+
+    use application::test_helpers::ApplicationTestExt;
+    use axum::body::Body;
+    use axum_extra::headers::authorization::Credentials;
+    use common::http::ConvexHttpService;
+    use futures::StreamExt;
+    use http::{
+        HeaderValue,
+        Request,
+        StatusCode,
+    };
+    use runtime::prod::ProdRuntime;
+    use serde_json::{
+        json,
+        Value as JsonValue,
+    };
+    use tokio::{
+        net::TcpStream,
+        sync::oneshot,
+    };
+    use tokio_tungstenite::{
+        connect_async,
+        MaybeTlsStream,
+        WebSocketStream,
+    };
+    use tungstenite::{
+        client::IntoClientRequest,
+        error::Error as TungsteniteError,
+        Message,
+    };
+
+    use crate::{
+        router::router,
+        test_helpers::setup_backend_for_test,
+    };
+
+    async fn connect(
+        addr: SocketAddr,
+        query: &str,
+        authorization: Option<HeaderValue>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, TungsteniteError> {
+        loop {
+            let mut request = format!("ws://{addr}/api/app_metrics/tail_function_logs?{query}")
+                .into_client_request()?;
+            if let Some(authorization) = &authorization {
+                request
+                    .headers_mut()
+                    .insert("Authorization", authorization.clone());
+            }
+            match connect_async(request).await {
+                Ok((websocket, _)) => return Ok(websocket),
+                // Can take a moment after the server spawn to connect to it.
+                Err(TungsteniteError::Io(_)) => tokio::task::yield_now().await,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn rejected_status(result: Result<impl Sized, TungsteniteError>) -> Option<StatusCode> {
+        match result {
+            Err(TungsteniteError::Http(response)) => Some(response.status()),
+            _ => None,
+        }
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_tail_function_logs(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let app = ConvexHttpService::new_for_test(router(backend.st.clone()));
+        let port = portpicker::pick_unused_port().expect("No ports free");
+        let addr = format!("127.0.0.1:{port}").parse()?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server = tokio::spawn(app.serve(addr, async move {
+            shutdown_rx.await.unwrap();
+        }));
+        let authorization = backend.admin_auth_header.0.encode();
+
+        assert_eq!(
+            rejected_status(connect(addr, "", None).await),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            rejected_status(connect(addr, "levels=LOG,BOGUS", Some(authorization.clone())).await),
+            Some(StatusCode::BAD_REQUEST)
+        );
+
+        let mut websocket = connect(
+            addr,
+            "function=logging:logDocument&levels=log",
+            Some(authorization),
+        )
+        .await?;
+        // The tail starts from the latest entry once the upgraded socket is
+        // running, which can be just after the handshake, so keep running the
+        // functions until an entry arrives. `logString` doesn't match the
+        // function filter and `values:intQuery` doesn't log anything.
+        let message = loop {
+            for (uri, path) in [
+                ("/api/query", "logging:logString"),
+                ("/api/query", "values:intQuery"),
+                ("/api/mutation", "logging:logDocument"),
+            ] {
+                let json_body = json!({"path": path, "args": {}});
+                let req = Request::builder()
+                    .uri(uri)
+                    .method("POST")
+                    .header("Content-Type", "application/json")
+                    .header("Host", "localhost")
+                    .body(Body::from(serde_json::to_vec(&json_body)?))?;
+                let _: JsonValue = backend.expect_success(req).await?;
+            }
+            if let Ok(message) =
+                tokio::time::timeout(Duration::from_secs(1), websocket.next()).await
+            {
+                break message;
+            }
+        };
+        let Some(Message::Text(text)) = message.transpose()? else {
+            anyhow::bail!("Expected a log entry");
+        };
+        let entry: JsonValue = serde_json::from_str(&text)?;
+        assert_eq!(entry["kind"], "Completion");
+        assert_eq!(entry["identifier"], "logging:logDocument");
+        let log_lines = entry["logLines"].as_array().expect("logLines is an array");
+        assert_eq!(log_lines.len(), 1);
+        assert!(log_lines[0].to_string().contains("value"));
+
+        websocket.close(None).await?;
+        shutdown_tx.send(()).unwrap();
+        server.await??;
+        Ok(())
+    }
+}
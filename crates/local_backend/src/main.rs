@@ -1,13 +1,24 @@
 #![feature(let_chains)]
 
-use std::time::Duration;
+use std::{
+    net::SocketAddr,
+    time::Duration,
+};
 
 use anyhow::anyhow;
 use clap::Parser;
 use cmd_util::env::config_service;
+#[cfg(unix)]
+use common::http::serve_unix;
 use common::{
     errors::MainError,
-    http::ConvexHttpService,
+    fastrace_helpers::set_sampling_config,
+    http::{
+        serve_http,
+        serve_https,
+        ConvexHttpService,
+    },
+    otlp::OtlpTraceExporter,
     runtime::Runtime,
     shutdown::ShutdownSignal,
     version::SERVER_VERSION_STR,
@@ -20,12 +31,25 @@ use futures::{
     FutureExt,
 };
 use local_backend::{
+    acme::run_acme_worker,
     config::LocalConfig,
-    make_app,
+    instances::{
+        load_instance_specs,
+        InstanceHost,
+    },
+    make_app_with_shared_services,
     persistence::connect_persistence,
+    preview_deployments::PreviewDeployments,
     proxy::dev_site_proxy,
-    router::router,
+    router::{
+        admin_metrics_routes,
+        preview_deployment_routes,
+        public_router,
+        router,
+    },
+    tls::TlsCertificates,
     HttpActionRouteMapper,
+    SharedServices,
     MAX_CONCURRENT_REQUESTS,
 };
 use runtime::prod::ProdRuntime;
@@ -98,6 +122,16 @@ async fn run_server(runtime: ProdRuntime, config: LocalConfig) -> anyhow::Result
 }
 
 async fn run_server_inner(runtime: ProdRuntime, config: LocalConfig) -> anyhow::Result<()> {
+    let otlp_exporter = match config.otlp_trace_export_config() {
+        Some(otlp_config) => {
+            set_sampling_config(&format!(
+                r#"{{"defaultFraction":{}}}"#,
+                config.otlp_trace_sample_fraction
+            ));
+            Some(OtlpTraceExporter::start(otlp_config)?)
+        },
+        None => None,
+    };
     // Used to receive fatal errors from the database or /preempt endpoint.
     let (preempt_tx, mut preempt_rx) = async_broadcast::broadcast(1);
     let preempt_signal = ShutdownSignal::new(preempt_tx.clone(), config.name());
@@ -112,34 +146,163 @@ async fn run_server_inner(runtime: ProdRuntime, config: LocalConfig) -> anyhow::
         preempt_signal.clone(),
     )
     .await?;
-    let st = make_app(
+    let shared = SharedServices::new(runtime.clone(), &config).await?;
+    let st = make_app_with_shared_services(
         runtime.clone(),
         config.clone(),
+        &shared,
         persistence,
         shutdown_rx.clone(),
         preempt_signal.clone(),
     )
     .await?;
-    let router = router(st.clone());
+    let instance_host = InstanceHost::new(
+        runtime.clone(),
+        config.clone(),
+        shared,
+        shutdown_rx.clone(),
+        preempt_signal.clone(),
+    );
+    if let Some(instances_file) = &config.instances_file {
+        for spec in load_instance_specs(instances_file)? {
+            instance_host.start_instance(&spec).await?;
+        }
+    }
+    let mut admin_routes = router(st.clone()).merge(instance_host.router(true));
+    if config.preview_deployments {
+        let previews = PreviewDeployments::start(
+            runtime.clone(),
+            config.clone(),
+            instance_host.clone(),
+            st.clone(),
+        )
+        .await?;
+        admin_routes = admin_routes.merge(preview_deployment_routes(st.clone(), previews));
+    }
+    // With `--admin-port`, only the admin listener serves the admin routes.
+    let admin_addr = config.admin_bind_address().map(SocketAddr::from);
+    let (router, admin_router) = match admin_addr {
+        Some(_) => (
+            public_router(st.clone()).merge(instance_host.router(false)),
+            Some(admin_routes),
+        ),
+        None => (admin_routes.merge(admin_metrics_routes(st.clone())), None),
+    };
+    // Routes are timed out individually by the router, so the service only
+    // needs to enforce the longest timeout.
+    let request_timeout = st.request_timeouts.max();
     let mut shutdown_rx_ = shutdown_rx.clone();
-    let http_service = ConvexHttpService::new(
+    let mut http_service = ConvexHttpService::new(
         router,
         "backend",
         SERVER_VERSION_STR.to_string(),
         MAX_CONCURRENT_REQUESTS,
-        Duration::from_secs(125),
+        request_timeout,
         HttpActionRouteMapper,
     );
-    let serve_http_future = http_service.serve(config.http_bind_address().into(), async move {
-        let _ = shutdown_rx_.recv().await;
-    });
+    // Prometheus metrics are served by the admin listener, or by
+    // `admin_metrics_routes` with an admin key if there isn't one.
+    http_service.set_metrics_route_enabled(false);
+    let custom_domains = st.custom_domains.clone();
+    let router = http_service
+        .into_router_with_middleware(move |req| custom_domains.clone().route_request(req));
+    let serve_http_future = if config.unix_socket_only {
+        Either::Right(future::ok::<_, anyhow::Error>(()))
+    } else {
+        let http_addr = SocketAddr::from(config.http_bind_address());
+        tracing::info!("backend listening on {http_addr}");
+        Either::Left(serve_http(
+            router
+                .clone()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+            http_addr,
+            async move {
+                let _ = shutdown_rx_.recv().await;
+            },
+        ))
+    };
+    let serve_admin_future = match (admin_addr, admin_router) {
+        (Some(admin_addr), Some(admin_router)) => {
+            let admin_service = ConvexHttpService::new(
+                admin_router,
+                "backend_admin",
+                SERVER_VERSION_STR.to_string(),
+                MAX_CONCURRENT_REQUESTS,
+                request_timeout,
+                HttpActionRouteMapper,
+            );
+            let mut shutdown_rx = shutdown_rx.clone();
+            let shutdown = async move {
+                let _ = shutdown_rx.recv().await;
+            };
+            match config.admin_tls_acceptor()? {
+                Some(tls_acceptor) => Either::Left(Either::Left(admin_service.serve_https(
+                    admin_addr,
+                    tls_acceptor,
+                    shutdown,
+                ))),
+                None => Either::Left(Either::Right(admin_service.serve(admin_addr, shutdown))),
+            }
+        },
+        _ => Either::Right(future::ok::<_, anyhow::Error>(())),
+    };
+    let serve_unix_future = match config.unix_socket.clone() {
+        #[cfg(unix)]
+        Some(path) => {
+            let mut shutdown_rx = shutdown_rx.clone();
+            let router = router.clone();
+            let mode = config.unix_socket_mode;
+            tracing::info!("backend listening on Unix socket {}", path.display());
+            Either::Left(async move {
+                serve_unix(router, &path, mode, async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            })
+        },
+        #[cfg(not(unix))]
+        Some(_) => Either::Left(future::err(anyhow::anyhow!(
+            "--unix-socket is only supported on Unix"
+        ))),
+        None => Either::Right(future::ok::<_, anyhow::Error>(())),
+    };
+    let serve_https_future = match config.tls_bind_address() {
+        Some(tls_addr) => {
+            let tls_addr = SocketAddr::from(tls_addr);
+            let certificates = TlsCertificates::new(st.custom_domains.clone());
+            if let Some(acme_config) = config.acme_config(&st.custom_domains)? {
+                runtime.spawn(
+                    "acme_worker",
+                    run_acme_worker(runtime.clone(), acme_config, certificates.clone()),
+                );
+            }
+            let mut shutdown_rx = shutdown_rx.clone();
+            tracing::info!("backend listening for HTTPS on {tls_addr}");
+            Either::Left(serve_https(
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+                tls_addr,
+                certificates.acceptor(),
+                async move {
+                    let _ = shutdown_rx.recv().await;
+                },
+            ))
+        },
+        None => Either::Right(future::ok::<_, anyhow::Error>(())),
+    };
     let proxy_future = dev_site_proxy(
         config.site_bind_address(),
         config.convex_origin_url()?,
         shutdown_rx,
     );
 
-    let serve_future = future::try_join(serve_http_future, proxy_future).fuse();
+    let serve_future = future::try_join5(
+        serve_http_future,
+        serve_admin_future,
+        serve_unix_future,
+        serve_https_future,
+        proxy_future,
+    )
+    .fuse();
     futures::pin_mut!(serve_future);
 
     let preempt_future = async move { preempt_rx.recv().await }.fuse();
@@ -173,8 +336,13 @@ async fn run_server_inner(runtime: ProdRuntime, config: LocalConfig) -> anyhow::
 
         // Next, shutdown all of our asynchronous workers.
         tracing::info!("Shutting down application...");
+        instance_host.shutdown().await?;
         st.shutdown().await?;
 
+        if let Some(otlp_exporter) = otlp_exporter {
+            otlp_exporter.shutdown().await;
+        }
+
         Ok::<_, anyhow::Error>(())
     }
     .fuse();
@@ -35,6 +35,10 @@ use common::{
         HttpResponseError,
     },
     knobs::ACTION_USER_TIMEOUT,
+    log_lines::{
+        LogLine,
+        LogLineStructured,
+    },
     runtime::UnixTimestamp,
     types::{
         FunctionCaller,
@@ -50,6 +54,10 @@ use isolate::{
     UdfArgsJson,
 };
 use keybroker::Identity;
+use model::scheduled_jobs::types::{
+    ScheduleOptions,
+    ScheduleOptionsJson,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -269,6 +277,8 @@ pub struct ScheduleJobRequest {
     udf_path: Option<String>,
     udf_args: UdfArgsJson,
     scheduled_ts: f64,
+    #[serde(default)]
+    options: ScheduleOptionsJson,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -288,6 +298,7 @@ pub async fn schedule_job(
     Json(req): Json<ScheduleJobRequest>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     let scheduled_ts = UnixTimestamp::from_secs_f64(req.scheduled_ts);
+    let options = ScheduleOptions::try_from(req.options)?;
     // User might have entered an invalid path, so this is a developer error.
     let path = st
         .application
@@ -313,6 +324,7 @@ pub async fn schedule_job(
             udf_args,
             scheduled_ts,
             context,
+            options,
         )
         .await?;
     Ok(Json(ScheduleJobResponse {
@@ -540,6 +552,39 @@ pub async fn storage_delete(
     Ok(Json(json!(null)))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLinesRequest {
+    log_stream_id: String,
+    log_lines: Vec<JsonValue>,
+}
+
+/// Receives log lines from a Node action while it's still running, so they
+/// show up in the logs before the action finishes. The log stream id is only
+/// known to the executor running the action, so there's no need to
+/// authenticate the user again on top of the callback token.
+#[debug_handler]
+pub async fn stream_log_lines(
+    State(st): State<LocalAppState>,
+    Json(req): Json<LogLinesRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let log_lines = req
+        .log_lines
+        .into_iter()
+        .map(|value| LogLineStructured::try_from(value).map(LogLine::Structured))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| {
+            anyhow::anyhow!(ErrorMetadata::bad_request(
+                "InvalidLogLine",
+                format!("Invalid log line: {e}"),
+            ))
+        })?;
+    st.application
+        .runner()
+        .stream_node_action_log_lines(&req.log_stream_id, log_lines)?;
+    Ok(Json(json!(null)))
+}
+
 pub static CONVEX_ACTIONS_CALLBACK_TOKEN: &str = "Convex-Action-Callback-Token";
 
 async fn check_actions_token(
@@ -715,6 +760,7 @@ impl<T> FromRequestParts<T> for ExtractExecutionContext {
             execution_id,
             parent_job_id.map(|id| (parent_component_id, id)),
             is_root,
+            get_encoded_span(&parts.headers)?.0,
         )))
     }
 }
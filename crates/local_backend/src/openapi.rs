@@ -0,0 +1,297 @@
+//! OpenAPI document describing the root app's public functions and HTTP
+//! actions, served at `/api/openapi.json` so clients in other languages can be
+//! generated from it.
+//!
+//! Functions are described as calls to `/api/run/{path}`, with their argument
+//! and return validators converted to JSON Schema. HTTP actions don't declare
+//! their request and response bodies, so only their methods and paths are
+//! listed, and routes matching a path prefix are left out since OpenAPI can't
+//! describe them.
+use std::collections::BTreeMap;
+
+use application::api::{
+    PublicApiMetadata,
+    PublicFunctionMetadata,
+};
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    http::{
+        extract::Json,
+        ExtractRequestId,
+        ExtractResolvedHostname,
+        HttpResponseError,
+    },
+    json_schemas,
+    schemas::validator::AddTopLevelFields,
+    types::{
+        HttpActionRoute,
+        UdfType,
+    },
+    version::SERVER_VERSION_STR,
+};
+use model::modules::function_validators::{
+    ArgsValidator,
+    ReturnsValidator,
+};
+use serde_json::{
+    json,
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::export::ValueFormat;
+
+use crate::{
+    admin::must_be_admin,
+    authentication::ExtractAuthenticationToken,
+    RouterState,
+};
+
+/// Returns the OpenAPI document. Like `npx convex function-spec`, this
+/// requires an admin key.
+pub async fn openapi_get(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin(&identity)?;
+    let metadata = st.api.public_api_metadata(&host, request_id).await?;
+    Ok(Json(openapi_spec(&metadata)))
+}
+
+pub fn openapi_spec(metadata: &PublicApiMetadata) -> JsonValue {
+    let mut paths = BTreeMap::new();
+    for function in &metadata.functions {
+        let (path, operation) = function_operation(function);
+        paths.insert(path, json!({ "post": operation }));
+    }
+    let mut http_paths: BTreeMap<String, JsonMap<String, JsonValue>> = BTreeMap::new();
+    for route in &metadata.http_routes {
+        if route.path.ends_with('*') {
+            continue;
+        }
+        http_paths
+            .entry(format!("/http{}", route.path))
+            .or_default()
+            .insert(
+                route.method.to_string().to_lowercase(),
+                http_action_operation(route),
+            );
+    }
+    for (path, operations) in http_paths {
+        paths.insert(path, JsonValue::Object(operations));
+    }
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Convex",
+            "version": *SERVER_VERSION_STR,
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "A user's identity token, or an admin key",
+                },
+            },
+        },
+    })
+}
+
+/// `chat/messages:list` -> `/api/run/chat/messages/list`.
+fn function_operation(function: &PublicFunctionMetadata) -> (String, JsonValue) {
+    let module = function.path.module().clone().strip();
+    let module = module.as_str();
+    let function_name = function.path.function_name();
+    let args = match &function.args {
+        ArgsValidator::Validated(object) => {
+            object.to_json_schema(AddTopLevelFields::False, ValueFormat::ConvexCleanJSON)
+        },
+        ArgsValidator::Unvalidated => json!({ "type": "object" }),
+    };
+    let returns = match &function.returns {
+        ReturnsValidator::Validated(validator) => {
+            validator.to_json_schema(ValueFormat::ConvexCleanJSON)
+        },
+        ReturnsValidator::Unvalidated => json_schemas::any(),
+    };
+    let kind = match function.udf_type {
+        UdfType::Query => "query",
+        UdfType::Mutation => "mutation",
+        UdfType::Action => "action",
+        UdfType::HttpAction => "httpAction",
+    };
+    let operation = json!({
+        "operationId": format!("{module}_{function_name}").replace(['/', '.', '-'], "_"),
+        "summary": format!("Runs the {kind} {module}:{function_name}"),
+        "tags": [kind],
+        "security": [{}, { "bearerAuth": [] }],
+        "requestBody": {
+            "required": true,
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "properties": { "args": args },
+                        "required": ["args"],
+                    },
+                },
+            },
+        },
+        "responses": {
+            "200": {
+                "description": "The function's result, or the error it threw",
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "oneOf": [
+                                {
+                                    "type": "object",
+                                    "properties": {
+                                        "status": { "const": "success" },
+                                        "value": returns,
+                                    },
+                                    "required": ["status", "value"],
+                                },
+                                {
+                                    "type": "object",
+                                    "properties": {
+                                        "status": { "const": "error" },
+                                        "errorMessage": { "type": "string" },
+                                        "errorData": {},
+                                    },
+                                    "required": ["status", "errorMessage"],
+                                },
+                            ],
+                        },
+                    },
+                },
+            },
+        },
+    });
+    (format!("/api/run/{module}/{function_name}"), operation)
+}
+
+fn http_action_operation(route: &HttpActionRoute) -> JsonValue {
+    let operation_id: String = format!("{}{}", route.method, route.path)
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    json!({
+        "operationId": operation_id,
+        "summary": format!("Runs the HTTP action for {} {}", route.method, route.path),
+        "tags": ["httpAction"],
+        "responses": {
+            "default": { "description": "The HTTP action's response" },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use application::api::{
+        PublicApiMetadata,
+        PublicFunctionMetadata,
+    };
+    use common::{
+        object_validator,
+        schemas::validator::{
+            FieldValidator,
+            Validator,
+        },
+        types::{
+            HttpActionRoute,
+            RoutableMethod,
+            UdfType,
+        },
+    };
+    use model::modules::function_validators::{
+        ArgsValidator,
+        ReturnsValidator,
+    };
+    use serde_json::json;
+
+    use super::openapi_spec;
+
+    #[test]
+    fn test_openapi_spec() -> anyhow::Result<()> {
+        let metadata = PublicApiMetadata {
+            functions: vec![
+                PublicFunctionMetadata {
+                    path: "chat/messages:list".parse()?,
+                    udf_type: UdfType::Query,
+                    args: ArgsValidator::Validated(object_validator!(
+                        "author" => FieldValidator::optional_field_type(Validator::String),
+                    )),
+                    returns: ReturnsValidator::Validated(Validator::Int64),
+                },
+                PublicFunctionMetadata {
+                    path: "send".parse()?,
+                    udf_type: UdfType::Mutation,
+                    args: ArgsValidator::Unvalidated,
+                    returns: ReturnsValidator::Unvalidated,
+                },
+            ],
+            schema: None,
+            http_routes: vec![
+                HttpActionRoute {
+                    method: RoutableMethod::Post,
+                    path: "/webhooks/stripe".to_string(),
+                },
+                HttpActionRoute {
+                    method: RoutableMethod::Get,
+                    path: "/webhooks/stripe".to_string(),
+                },
+                HttpActionRoute {
+                    method: RoutableMethod::Get,
+                    path: "/static/*".to_string(),
+                },
+            ],
+        };
+        let spec = openapi_spec(&metadata);
+        let paths = spec["paths"].as_object().unwrap();
+        assert_eq!(
+            paths.keys().collect::<Vec<_>>(),
+            vec![
+                "/api/run/chat/messages/list",
+                "/api/run/send/default",
+                "/http/webhooks/stripe",
+            ]
+        );
+
+        let list = &paths["/api/run/chat/messages/list"]["post"];
+        assert_eq!(list["operationId"], "chat_messages_list");
+        assert_eq!(
+            list["requestBody"]["content"]["application/json"]["schema"]["properties"]["args"],
+            json!({
+                "type": "object",
+                "properties": { "author": { "type": "string" } },
+                "additionalProperties": false,
+                "required": [],
+            })
+        );
+        assert_eq!(
+            list["responses"]["200"]["content"]["application/json"]["schema"]["oneOf"][0]
+                ["properties"]["value"],
+            json!({
+                "$description": "int64 represented as base10 string",
+                "type": "string",
+            })
+        );
+
+        let stripe = &paths["/http/webhooks/stripe"];
+        assert_eq!(stripe["get"]["operationId"], "get_webhooks_stripe");
+        assert_eq!(stripe["post"]["operationId"], "post_webhooks_stripe");
+        Ok(())
+    }
+}
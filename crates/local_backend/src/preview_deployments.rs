@@ -0,0 +1,938 @@
+//! Preview deployments for self-hosted backends.
+//!
+//! Like preview deployments in the cloud, a preview deployment is a
+//! short-lived deployment for a git branch, so each branch can be tested
+//! against its own data. Here they are instances hosted by the backend's
+//! `InstanceHost` (see `instances`), either empty or seeded from a snapshot
+//! export of a parent deployment. Seeding from another hosted deployment
+//! requires that deployment's admin key. They're deleted along with their
+//! storage once they expire. They're kept in `preview_deployments.json` under
+//! `--local-storage`, so they survive restarts.
+//!
+//! With Postgres or MySQL, an expired preview's database is left for the
+//! operator to drop, since the backend doesn't manage databases.
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fs,
+    path::PathBuf,
+    sync::Arc,
+    time::{
+        Duration,
+        UNIX_EPOCH,
+    },
+};
+
+use anyhow::Context;
+use application::snapshot_import::do_import;
+use axum::{
+    debug_handler,
+    extract::{
+        FromRef,
+        State,
+    },
+    response::IntoResponse,
+};
+use common::{
+    components::ComponentPath,
+    http::{
+        extract::Json,
+        HttpResponseError,
+    },
+    runtime::Runtime,
+    types::MemberId,
+};
+use either::Either;
+use errors::ErrorMetadata;
+use futures::StreamExt;
+use keybroker::{
+    Identity,
+    InstanceSecret,
+    KeyBroker,
+};
+use model::snapshot_imports::types::{
+    ImportFormat,
+    ImportMode,
+};
+use parking_lot::Mutex;
+use runtime::prod::ProdRuntime;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use storage::StorageGetStream;
+use value::id_v6::DeveloperDocumentId;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_admin_from_key,
+        must_be_admin_from_key_with_write_access,
+        must_be_admin_with_write_access,
+    },
+    authentication::ExtractIdentity,
+    config::LocalConfig,
+    instances::{
+        InstanceHost,
+        InstanceSpec,
+    },
+    EmptyResponse,
+    LocalAppState,
+};
+
+const REGISTRY_FILE: &str = "preview_deployments.json";
+const MAX_BRANCH_LEN: usize = 256;
+/// Preview instance names are `preview-<branch>-<suffix>`, with the branch
+/// shortened to fit in an instance name.
+const MAX_BRANCH_SLUG_LEN: usize = 40;
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewDeployment {
+    pub branch: String,
+    pub instance_name: String,
+    secret: String,
+    /// The deployment it was seeded from, if any.
+    pub parent: Option<String>,
+    pub created_at_ms: u64,
+    pub expires_at_ms: u64,
+}
+
+impl PreviewDeployment {
+    fn spec(&self) -> InstanceSpec {
+        InstanceSpec {
+            name: self.instance_name.clone(),
+            secret: self.secret.clone(),
+        }
+    }
+
+    fn admin_key(&self) -> anyhow::Result<String> {
+        let key_broker = KeyBroker::new(
+            &self.instance_name,
+            InstanceSecret::try_from(self.secret.as_str())?,
+        )?;
+        Ok(key_broker.issue_admin_key(MemberId(0)).as_str().to_string())
+    }
+}
+
+/// How to create a preview deployment.
+pub struct PreviewDeploymentRequest {
+    pub branch: String,
+    /// Seeds the preview with this completed snapshot export of `parent`.
+    pub seed_snapshot_id: Option<DeveloperDocumentId>,
+    /// Defaults to the backend's own deployment.
+    pub parent: Option<String>,
+    /// Who is creating the preview. Seeding from the backend's own deployment
+    /// reads the snapshot as them.
+    pub identity: Identity,
+    /// Required to seed from a deployment other than the backend's own.
+    pub parent_admin_key: Option<String>,
+    /// Defaults to `--preview-deployment-ttl-hours`.
+    pub ttl: Option<Duration>,
+}
+
+pub struct PreviewDeployments {
+    runtime: ProdRuntime,
+    config: LocalConfig,
+    host: Arc<InstanceHost>,
+    /// The backend's own deployment, the default parent.
+    parent: LocalAppState,
+    ttl: Duration,
+    max_count: usize,
+    registry: Mutex<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// By branch.
+    deployments: BTreeMap<String, PreviewDeployment>,
+    /// Branches whose preview is being started and seeded. They're reserved
+    /// so creates for the same branch don't race, without holding the lock
+    /// while seeding.
+    creating: BTreeSet<String>,
+}
+
+/// Releases a branch reserved in `Registry::creating` when creating its
+/// preview finishes, fails or is cancelled.
+struct Reservation<'a> {
+    registry: &'a Mutex<Registry>,
+    branch: String,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.registry.lock().creating.remove(&self.branch);
+    }
+}
+
+impl PreviewDeployments {
+    /// Restarts the unexpired preview deployments from the last run and
+    /// starts the worker that deletes expired ones. A preview that fails to
+    /// start is logged and left stopped rather than failing the backend's
+    /// startup, so it can still be deleted or expire.
+    pub async fn start(
+        runtime: ProdRuntime,
+        config: LocalConfig,
+        host: Arc<InstanceHost>,
+        parent: LocalAppState,
+    ) -> anyhow::Result<Arc<Self>> {
+        let registry_path = config.storage_dir().join(REGISTRY_FILE);
+        let deployments: Vec<PreviewDeployment> = match fs::read(&registry_path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .with_context(|| format!("Invalid preview deployments file {registry_path:?}"))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).context(format!("Failed to read {registry_path:?}")),
+        };
+        let ttl_secs = config
+            .preview_deployment_ttl_hours
+            .get()
+            .checked_mul(60 * 60)
+            .context("--preview-deployment-ttl-hours is too large")?;
+        let previews = Arc::new(Self {
+            runtime: runtime.clone(),
+            ttl: Duration::from_secs(ttl_secs),
+            max_count: config.max_preview_deployments,
+            config,
+            host,
+            parent,
+            registry: Mutex::new(Registry::default()),
+        });
+        previews.registry.lock().deployments = deployments
+            .into_iter()
+            .map(|deployment| (deployment.branch.clone(), deployment))
+            .collect();
+        previews.delete_expired().await?;
+        for deployment in previews.list() {
+            if let Err(e) = previews.host.start_instance(&deployment.spec()).await {
+                tracing::error!(
+                    "Failed to start preview deployment {} for branch {}: {e:#}",
+                    deployment.instance_name,
+                    deployment.branch
+                );
+            }
+        }
+        runtime.spawn("preview_deployment_cleanup", previews.clone().go());
+        Ok(previews)
+    }
+
+    async fn go(self: Arc<Self>) {
+        loop {
+            self.runtime.wait(CLEANUP_INTERVAL).await;
+            if let Err(e) = self.delete_expired().await {
+                tracing::error!("Failed to delete expired preview deployments: {e:#}");
+            }
+        }
+    }
+
+    /// Creates a preview deployment for the branch, or extends the expiry of
+    /// the branch's existing one.
+    pub async fn create(
+        &self,
+        request: PreviewDeploymentRequest,
+    ) -> anyhow::Result<PreviewDeployment> {
+        anyhow::ensure!(
+            !request.branch.is_empty() && request.branch.len() <= MAX_BRANCH_LEN,
+            ErrorMetadata::bad_request(
+                "InvalidPreviewBranch",
+                format!("Branch names must have 1 to {MAX_BRANCH_LEN} characters"),
+            )
+        );
+        let now_ms = self.now_ms()?;
+        let expires_at_ms = u64::try_from(request.ttl.unwrap_or(self.ttl).as_millis())
+            .ok()
+            .and_then(|ttl_ms| now_ms.checked_add(ttl_ms))
+            .context(ErrorMetadata::bad_request(
+                "InvalidPreviewTtl",
+                "The preview deployment's TTL is too long",
+            ))?;
+        let reservation = {
+            let mut registry = self.registry.lock();
+            if let Some(deployment) = registry.deployments.get_mut(&request.branch) {
+                deployment.expires_at_ms = expires_at_ms;
+                let deployment = deployment.clone();
+                self.save(&registry.deployments)?;
+                return Ok(deployment);
+            }
+            anyhow::ensure!(
+                !registry.creating.contains(&request.branch),
+                ErrorMetadata::bad_request(
+                    "PreviewDeploymentBeingCreated",
+                    format!(
+                        "The preview deployment for branch {} is still being created",
+                        request.branch
+                    ),
+                )
+            );
+            let count = registry.deployments.len() + registry.creating.len();
+            anyhow::ensure!(
+                count < self.max_count,
+                ErrorMetadata::bad_request(
+                    "TooManyPreviewDeployments",
+                    format!(
+                        "There are already {count} preview deployments. Delete one or raise \
+                         --max-preview-deployments."
+                    ),
+                )
+            );
+            registry.creating.insert(request.branch.clone());
+            Reservation {
+                registry: &self.registry,
+                branch: request.branch.clone(),
+            }
+        };
+        let parent = match request.seed_snapshot_id {
+            Some(snapshot_id) => Some((
+                self.seed_parent(
+                    request.parent.as_deref(),
+                    request.identity,
+                    request.parent_admin_key,
+                )
+                .await?,
+                snapshot_id,
+            )),
+            None => None,
+        };
+        let deployment = PreviewDeployment {
+            instance_name: format!(
+                "preview-{}-{}",
+                branch_slug(&request.branch),
+                hex::encode(rand::random::<[u8; 3]>())
+            ),
+            secret: InstanceSecret::random().to_string(),
+            branch: request.branch,
+            parent: parent
+                .as_ref()
+                .map(|((parent, _), _)| parent.instance_name.clone()),
+            created_at_ms: now_ms,
+            expires_at_ms,
+        };
+        let st = self.host.start_instance(&deployment.spec()).await?;
+        if let Some(((parent, identity), snapshot_id)) = parent {
+            let seeded = self
+                .seed(&parent, identity, snapshot_id, &st, &deployment)
+                .await;
+            if let Err(e) = seeded {
+                self.remove(&deployment).await?;
+                return Err(e);
+            }
+        }
+        tracing::info!(
+            "Created preview deployment {} for branch {}",
+            deployment.instance_name,
+            deployment.branch
+        );
+        {
+            let mut registry = self.registry.lock();
+            registry.creating.remove(&deployment.branch);
+            registry
+                .deployments
+                .insert(deployment.branch.clone(), deployment.clone());
+            self.save(&registry.deployments)?;
+        }
+        drop(reservation);
+        Ok(deployment)
+    }
+
+    /// The deployment to read the seed snapshot from, and who to read it as.
+    /// Other hosted deployments need their own admin key, since being an
+    /// admin of this deployment doesn't grant access to their data.
+    async fn seed_parent(
+        &self,
+        parent: Option<&str>,
+        identity: Identity,
+        parent_admin_key: Option<String>,
+    ) -> anyhow::Result<(LocalAppState, Identity)> {
+        let name = match parent {
+            Some(name) if name != self.parent.instance_name => name,
+            _ => return Ok((self.parent.clone(), identity)),
+        };
+        let parent = self.host.instance(name).context(ErrorMetadata::not_found(
+            "InstanceNotFound",
+            format!("This backend doesn't host an instance named {name}"),
+        ))?;
+        let admin_key = parent_admin_key.context(ErrorMetadata::bad_request(
+            "MissingParentAdminKey",
+            format!("Seeding from {name} requires its admin key in parentAdminKey"),
+        ))?;
+        let identity = must_be_admin_from_key(
+            parent.application.app_auth(),
+            parent.instance_name.clone(),
+            admin_key,
+        )
+        .await?;
+        Ok((parent, identity))
+    }
+
+    /// Imports the parent's snapshot export into a new preview.
+    async fn seed(
+        &self,
+        parent: &LocalAppState,
+        identity: Identity,
+        snapshot_id: DeveloperDocumentId,
+        st: &LocalAppState,
+        deployment: &PreviewDeployment,
+    ) -> anyhow::Result<()> {
+        let (StorageGetStream { stream, .. }, filename) = parent
+            .application
+            .get_zip_export(identity, Either::Left(snapshot_id))
+            .await?;
+        anyhow::ensure!(
+            !filename.ends_with(".age"),
+            ErrorMetadata::bad_request(
+                "EncryptedSnapshot",
+                "Preview deployments can't be seeded from encrypted snapshot exports",
+            )
+        );
+        let identity = must_be_admin_from_key_with_write_access(
+            st.application.app_auth(),
+            st.instance_name.clone(),
+            deployment.admin_key()?,
+        )
+        .await?;
+        do_import(
+            &st.application,
+            identity,
+            ImportFormat::Zip,
+            ImportMode::Replace,
+            ComponentPath::root(),
+            stream
+                .map(|chunk| chunk.map_err(anyhow::Error::from))
+                .boxed(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<PreviewDeployment> {
+        self.registry.lock().deployments.values().cloned().collect()
+    }
+
+    pub async fn delete(&self, branch: &str) -> anyhow::Result<()> {
+        let deployment = {
+            let mut registry = self.registry.lock();
+            let deployment =
+                registry
+                    .deployments
+                    .remove(branch)
+                    .context(ErrorMetadata::not_found(
+                        "PreviewDeploymentNotFound",
+                        format!("There is no preview deployment for branch {branch}"),
+                    ))?;
+            self.save(&registry.deployments)?;
+            deployment
+        };
+        self.remove(&deployment).await
+    }
+
+    /// Deletes the expired previews. Failing to delete one doesn't stop the
+    /// others from being deleted.
+    async fn delete_expired(&self) -> anyhow::Result<()> {
+        let now_ms = self.now_ms()?;
+        let expired = {
+            let mut registry = self.registry.lock();
+            let expired: Vec<_> = registry
+                .deployments
+                .values()
+                .filter(|deployment| deployment.expires_at_ms <= now_ms)
+                .cloned()
+                .collect();
+            if expired.is_empty() {
+                return Ok(());
+            }
+            for deployment in &expired {
+                registry.deployments.remove(&deployment.branch);
+            }
+            self.save(&registry.deployments)?;
+            expired
+        };
+        for deployment in expired {
+            tracing::info!(
+                "Deleting expired preview deployment {} for branch {}",
+                deployment.instance_name,
+                deployment.branch
+            );
+            if let Err(e) = self.remove(&deployment).await {
+                tracing::error!(
+                    "Failed to delete expired preview deployment {}: {e:#}",
+                    deployment.instance_name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops the preview's instance and deletes its storage.
+    async fn remove(&self, deployment: &PreviewDeployment) -> anyhow::Result<()> {
+        self.host.stop_instance(&deployment.instance_name).await?;
+        let storage_dir = self.config.for_instance(&deployment.spec())?.storage_dir();
+        if storage_dir.exists() {
+            fs::remove_dir_all(&storage_dir)
+                .with_context(|| format!("Failed to delete {storage_dir:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, deployments: &BTreeMap<String, PreviewDeployment>) -> anyhow::Result<()> {
+        let path = self.config.storage_dir().join(REGISTRY_FILE);
+        let tmp_path = PathBuf::from(format!("{}.tmp", path.display()));
+        let deployments: Vec<_> = deployments.values().collect();
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&deployments)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn now_ms(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .runtime
+            .system_time()
+            .duration_since(UNIX_EPOCH)?
+            .as_millis() as u64)
+    }
+}
+
+/// `Feature/Login_Page` -> `feature-login-page`.
+fn branch_slug(branch: &str) -> String {
+    let mut slug = String::new();
+    for c in branch.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() == MAX_BRANCH_SLUG_LEN {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "branch".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[derive(Clone)]
+pub struct PreviewDeploymentsState {
+    pub st: LocalAppState,
+    pub previews: Arc<PreviewDeployments>,
+}
+
+impl FromRef<PreviewDeploymentsState> for LocalAppState {
+    fn from_ref(state: &PreviewDeploymentsState) -> Self {
+        state.st.clone()
+    }
+}
+
+impl FromRef<PreviewDeploymentsState> for Arc<PreviewDeployments> {
+    fn from_ref(state: &PreviewDeploymentsState) -> Self {
+        state.previews.clone()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePreviewDeploymentRequest {
+    pub branch: String,
+    /// ID of a completed snapshot export of `parent` to seed the preview
+    /// with.
+    pub seed_snapshot_id: Option<String>,
+    /// Instance name of the deployment the snapshot is from. Defaults to this
+    /// deployment.
+    pub parent: Option<String>,
+    /// An admin key for `parent`, required when it isn't this deployment.
+    pub parent_admin_key: Option<String>,
+    pub ttl_hours: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewDeploymentResponse {
+    pub branch: String,
+    pub instance_name: String,
+    pub url: String,
+    pub parent: Option<String>,
+    pub created_at_ms: u64,
+    pub expires_at_ms: u64,
+    /// Only returned when creating the deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_key: Option<String>,
+}
+
+impl PreviewDeploymentResponse {
+    fn new(
+        previews: &PreviewDeployments,
+        deployment: PreviewDeployment,
+        with_admin_key: bool,
+    ) -> anyhow::Result<Self> {
+        let url = previews
+            .config
+            .for_instance(&deployment.spec())?
+            .convex_origin_url()?
+            .to_string();
+        let admin_key = with_admin_key.then(|| deployment.admin_key()).transpose()?;
+        Ok(Self {
+            url,
+            admin_key,
+            branch: deployment.branch,
+            instance_name: deployment.instance_name,
+            parent: deployment.parent,
+            created_at_ms: deployment.created_at_ms,
+            expires_at_ms: deployment.expires_at_ms,
+        })
+    }
+}
+
+#[debug_handler(state = PreviewDeploymentsState)]
+pub async fn create_preview_deployment(
+    State(previews): State<Arc<PreviewDeployments>>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(CreatePreviewDeploymentRequest {
+        branch,
+        seed_snapshot_id,
+        parent,
+        parent_admin_key,
+        ttl_hours,
+    }): Json<CreatePreviewDeploymentRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let seed_snapshot_id = seed_snapshot_id
+        .map(|id| id.parse::<DeveloperDocumentId>())
+        .transpose()
+        .context(ErrorMetadata::bad_request(
+            "BadSnapshotId",
+            "Snapshot Id did not parse to an ID.",
+        ))?;
+    let ttl = ttl_hours
+        .map(|hours| {
+            anyhow::ensure!(
+                hours > 0,
+                ErrorMetadata::bad_request("InvalidPreviewTtl", "ttlHours must be greater than 0")
+            );
+            let secs = hours
+                .checked_mul(60 * 60)
+                .context(ErrorMetadata::bad_request(
+                    "InvalidPreviewTtl",
+                    "ttlHours is too large",
+                ))?;
+            Ok(Duration::from_secs(secs))
+        })
+        .transpose()?;
+    let deployment = previews
+        .create(PreviewDeploymentRequest {
+            branch,
+            seed_snapshot_id,
+            parent,
+            identity,
+            parent_admin_key,
+            ttl,
+        })
+        .await?;
+    Ok(Json(PreviewDeploymentResponse::new(
+        &previews, deployment, true,
+    )?))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPreviewDeploymentsResponse {
+    pub preview_deployments: Vec<PreviewDeploymentResponse>,
+}
+
+#[debug_handler(state = PreviewDeploymentsState)]
+pub async fn list_preview_deployments(
+    State(previews): State<Arc<PreviewDeployments>>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let preview_deployments = previews
+        .list()
+        .into_iter()
+        .map(|deployment| PreviewDeploymentResponse::new(&previews, deployment, false))
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(ListPreviewDeploymentsResponse {
+        preview_deployments,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePreviewDeploymentRequest {
+    pub branch: String,
+}
+
+#[debug_handler(state = PreviewDeploymentsState)]
+pub async fn delete_preview_deployment(
+    State(previews): State<Arc<PreviewDeployments>>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(DeletePreviewDeploymentRequest { branch }): Json<DeletePreviewDeploymentRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    previews.delete(&branch).await?;
+    Ok(Json(EmptyResponse {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::Duration,
+    };
+
+    use clap::Parser;
+    use common::{
+        shutdown::ShutdownSignal,
+        testing::TestPersistence,
+    };
+    use errors::ErrorMetadataAnyhowExt;
+    use keybroker::{
+        Identity,
+        InstanceSecret,
+    };
+    use maplit::btreemap;
+    use runtime::prod::ProdRuntime;
+    use tempfile::TempDir;
+    use value::id_v6::DeveloperDocumentId;
+
+    use super::{
+        branch_slug,
+        PreviewDeployment,
+        PreviewDeploymentRequest,
+        PreviewDeployments,
+    };
+    use crate::{
+        config::LocalConfig,
+        instances::{
+            InstanceHost,
+            InstanceSpec,
+        },
+        make_app,
+        LocalAppState,
+        SharedServices,
+    };
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn test_config(dir: &TempDir) -> anyhow::Result<LocalConfig> {
+        let storage_dir = dir.path().to_string_lossy().into_owned();
+        let db_path = format!("{storage_dir}/convex_local_backend.sqlite3");
+        Ok(LocalConfig::try_parse_from([
+            "convex-local-backend",
+            &db_path,
+            "--local-storage",
+            &storage_dir,
+        ])?)
+    }
+
+    async fn start_parent(rt: ProdRuntime, config: &LocalConfig) -> anyhow::Result<LocalAppState> {
+        let (preempt_tx, _preempt_rx) = async_broadcast::broadcast(1);
+        let (_shutdown_tx, shutdown_rx) = async_broadcast::broadcast(1);
+        make_app(
+            rt,
+            config.clone(),
+            Arc::new(TestPersistence::new()),
+            shutdown_rx,
+            ShutdownSignal::new(preempt_tx, config.name()),
+        )
+        .await
+    }
+
+    async fn start_host(
+        rt: ProdRuntime,
+        config: &LocalConfig,
+    ) -> anyhow::Result<Arc<InstanceHost>> {
+        let (preempt_tx, _preempt_rx) = async_broadcast::broadcast(1);
+        let (_shutdown_tx, shutdown_rx) = async_broadcast::broadcast(1);
+        Ok(InstanceHost::new(
+            rt.clone(),
+            config.clone(),
+            SharedServices::new(rt, config).await?,
+            shutdown_rx,
+            ShutdownSignal::new(preempt_tx, config.name()),
+        ))
+    }
+
+    fn request(branch: &str, ttl: Duration) -> PreviewDeploymentRequest {
+        PreviewDeploymentRequest {
+            branch: branch.to_string(),
+            seed_snapshot_id: None,
+            parent: None,
+            identity: Identity::system(),
+            parent_admin_key: None,
+            ttl: Some(ttl),
+        }
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_create_extend_and_delete(rt: ProdRuntime) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent).await?;
+
+        let deployment = previews.create(request("Feature/Login", HOUR)).await?;
+        assert!(deployment
+            .instance_name
+            .starts_with("preview-feature-login-"));
+        assert!(host.instance(&deployment.instance_name).is_some());
+        let storage_dir = config.for_instance(&deployment.spec())?.storage_dir();
+        assert!(storage_dir.exists());
+
+        // Creating the branch's preview again extends its expiry.
+        let extended = previews.create(request("Feature/Login", 2 * HOUR)).await?;
+        assert_eq!(extended.instance_name, deployment.instance_name);
+        assert!(extended.expires_at_ms > deployment.expires_at_ms);
+        assert_eq!(previews.list(), vec![extended]);
+
+        previews.delete("Feature/Login").await?;
+        assert!(previews.list().is_empty());
+        assert!(host.instance(&deployment.instance_name).is_none());
+        assert!(!storage_dir.exists());
+        let err = previews.delete("Feature/Login").await.unwrap_err();
+        assert!(err.is_not_found(), "{err:?}");
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_delete_expired(rt: ProdRuntime) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent).await?;
+
+        let expired = previews.create(request("expired", Duration::ZERO)).await?;
+        let unexpired = previews.create(request("unexpired", HOUR)).await?;
+        previews.delete_expired().await?;
+        assert_eq!(previews.list(), vec![unexpired.clone()]);
+        assert!(host.instance(&expired.instance_name).is_none());
+        assert!(host.instance(&unexpired.instance_name).is_some());
+        assert!(!config.for_instance(&expired.spec())?.storage_dir().exists());
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_registry_survives_restart(rt: ProdRuntime) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent.clone())
+                .await?;
+        let deployment = previews.create(request("main", HOUR)).await?;
+        host.shutdown().await?;
+
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent).await?;
+        assert_eq!(previews.list(), vec![deployment.clone()]);
+        assert!(host.instance(&deployment.instance_name).is_some());
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_preview_that_fails_to_start_is_skipped(rt: ProdRuntime) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent.clone())
+                .await?;
+        let deployment = previews.create(request("main", HOUR)).await?;
+        let broken = PreviewDeployment {
+            branch: "broken".to_string(),
+            instance_name: "preview-broken-000000".to_string(),
+            secret: "not a secret".to_string(),
+            ..deployment.clone()
+        };
+        previews.save(&btreemap! {
+            deployment.branch.clone() => deployment.clone(),
+            broken.branch.clone() => broken.clone(),
+        })?;
+        host.shutdown().await?;
+
+        let host = start_host(rt.clone(), &config).await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent).await?;
+        assert!(host.instance(&deployment.instance_name).is_some());
+        assert!(host.instance(&broken.instance_name).is_none());
+        // The broken preview can still be deleted.
+        previews.delete("broken").await?;
+        assert_eq!(previews.list(), vec![deployment]);
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_ttl_too_long(rt: ProdRuntime) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        let previews = PreviewDeployments::start(rt.clone(), config, host, parent).await?;
+
+        let err = previews
+            .create(request("main", Duration::from_secs(u64::MAX)))
+            .await
+            .unwrap_err();
+        assert!(err.is_bad_request(), "{err:?}");
+        assert_eq!(err.short_msg(), "InvalidPreviewTtl");
+        assert!(previews.list().is_empty());
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_seeding_from_another_instance_requires_its_admin_key(
+        rt: ProdRuntime,
+    ) -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = test_config(&dir)?;
+        let parent = start_parent(rt.clone(), &config).await?;
+        let host = start_host(rt.clone(), &config).await?;
+        host.start_instance(&InstanceSpec {
+            name: "acme".to_string(),
+            secret: InstanceSecret::random().to_string(),
+        })
+        .await?;
+        let previews =
+            PreviewDeployments::start(rt.clone(), config.clone(), host.clone(), parent).await?;
+
+        let seeded_from_acme = |parent_admin_key: Option<&str>| PreviewDeploymentRequest {
+            seed_snapshot_id: Some(DeveloperDocumentId::MIN),
+            parent: Some("acme".to_string()),
+            parent_admin_key: parent_admin_key.map(str::to_string),
+            ..request("main", HOUR)
+        };
+        let err = previews.create(seeded_from_acme(None)).await.unwrap_err();
+        assert_eq!(err.short_msg(), "MissingParentAdminKey");
+        let err = previews
+            .create(seeded_from_acme(Some("not a key")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "BadDeployKey");
+        assert!(previews.list().is_empty());
+        assert_eq!(host.instance_names(), vec!["acme".to_string()]);
+
+        // The failed creates don't leave the branch reserved.
+        previews.create(request("main", HOUR)).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_branch_slug() {
+        assert_eq!(branch_slug("Feature/Login_Page"), "feature-login-page");
+        assert_eq!(branch_slug("--fix--"), "fix");
+        assert_eq!(branch_slug("日本"), "branch");
+        assert_eq!(branch_slug(&"a".repeat(100)).len(), 40);
+        assert_eq!(
+            branch_slug(&format!("{}-b", "a".repeat(39))),
+            "a".repeat(39)
+        );
+    }
+}
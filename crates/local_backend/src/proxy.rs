@@ -4,7 +4,9 @@ use std::{
 };
 
 use axum::{
+    body::Body,
     extract::{
+        ConnectInfo,
         Request,
         State,
     },
@@ -20,7 +22,32 @@ use common::{
     },
     types::ConvexOrigin,
 };
-use hyper_util::rt::TokioExecutor;
+use http::{
+    header::{
+        HOST,
+        UPGRADE,
+        X_FORWARDED_FOR,
+        X_FORWARDED_HOST,
+    },
+    StatusCode,
+};
+use hyper_tls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{
+        connect::HttpConnector,
+        Client,
+    },
+    rt::{
+        TokioExecutor,
+        TokioIo,
+    },
+};
+
+#[derive(Clone)]
+struct ProxyState {
+    origin: ConvexOrigin,
+    client: Client<HttpsConnector<HttpConnector>, Body>,
+}
 
 /// Routes HTTP actions to the main webserver
 pub async fn dev_site_proxy(
@@ -34,16 +61,63 @@ pub async fn dev_site_proxy(
     tracing::info!("Starting dev site proxy at {:?}...", SocketAddr::from(addr));
 
     async fn proxy_method(
-        State(st): State<ConvexOrigin>,
+        State(st): State<ProxyState>,
+        ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
         mut request: Request,
     ) -> Result<impl IntoResponse, HttpResponseError> {
-        let new_uri = format!("{}/http{}", st, request.uri());
+        let new_uri = format!("{}/http{}", st.origin, request.uri());
         *request.uri_mut() = new_uri.parse().map_err(anyhow::Error::new)?;
-        let resp = hyper_util::client::legacy::Client::builder(TokioExecutor::new())
-            .build_http()
+        // Address the backend by its own host so it doesn't route the request
+        // as a custom domain again. HTTP actions still see the original host.
+        if let Some(host) = request.headers_mut().remove(HOST) {
+            if !request.headers().contains_key(X_FORWARDED_HOST) {
+                request.headers_mut().insert(X_FORWARDED_HOST, host);
+            }
+        }
+        // Pass on the client's address so the backend can rate limit by it.
+        let forwarded_for = match request.headers().get(X_FORWARDED_FOR) {
+            Some(existing) => format!(
+                "{}, {}",
+                existing.to_str().map_err(anyhow::Error::new)?,
+                remote_addr.ip()
+            ),
+            None => remote_addr.ip().to_string(),
+        };
+        request.headers_mut().insert(
+            X_FORWARDED_FOR,
+            forwarded_for.parse().map_err(anyhow::Error::new)?,
+        );
+        // Take the client's side of a protocol upgrade, e.g. to a WebSocket,
+        // before the request is passed on.
+        let client_upgrade = request
+            .headers()
+            .contains_key(UPGRADE)
+            .then(|| hyper::upgrade::on(&mut request));
+        let mut resp = st
+            .client
             .request(request)
             .await
             .map_err(anyhow::Error::new)?;
+        if let Some(client_upgrade) = client_upgrade
+            && resp.status() == StatusCode::SWITCHING_PROTOCOLS
+        {
+            let origin_upgrade = hyper::upgrade::on(&mut resp);
+            tokio::spawn(async move {
+                let result = async {
+                    let (client, origin) = tokio::try_join!(client_upgrade, origin_upgrade)?;
+                    tokio::io::copy_bidirectional(
+                        &mut TokioIo::new(client),
+                        &mut TokioIo::new(origin),
+                    )
+                    .await?;
+                    anyhow::Ok(())
+                }
+                .await;
+                if let Err(e) = result {
+                    tracing::debug!("Upgraded proxy connection closed: {e:?}");
+                }
+            });
+        }
         Ok(resp)
     }
 
@@ -56,7 +130,13 @@ pub async fn dev_site_proxy(
     let router = Router::new()
         .route("/*rest", proxy_handler.clone())
         .route("/", proxy_handler)
-        .with_state(origin);
+        .with_state(ProxyState {
+            origin,
+            // Connections are pooled and speak HTTP/1.1, or HTTP/2 if the
+            // origin uses TLS and negotiates it. The origin may be another
+            // proxy in front of the backend, e.g. with `--unix-socket-only`.
+            client: Client::builder(TokioExecutor::new()).build(HttpsConnector::new()),
+        });
 
     let service = ConvexHttpService::new(
         Router::new().fallback_service(router),
@@ -73,3 +153,89 @@ pub async fn dev_site_proxy(
     proxy_server.await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        extract::ws::{
+            Message,
+            WebSocketUpgrade,
+        },
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use common::{
+        http::ConvexHttpService,
+        types::ConvexOrigin,
+    };
+    use futures::{
+        SinkExt,
+        StreamExt,
+    };
+    use tokio::sync::oneshot;
+    use tokio_tungstenite::connect_async;
+    use tungstenite::{
+        error::Error as TungsteniteError,
+        Message as ClientMessage,
+    };
+
+    use super::dev_site_proxy;
+
+    async fn echo(upgrade: WebSocketUpgrade) -> impl IntoResponse {
+        upgrade.on_upgrade(|mut socket| async move {
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                if socket
+                    .send(Message::Text(format!("echo: {text}")))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_proxy_websocket_upgrade() -> anyhow::Result<()> {
+        let origin_port = portpicker::pick_unused_port().expect("No ports free");
+        let origin_addr = format!("127.0.0.1:{origin_port}").parse()?;
+        let (origin_shutdown_tx, origin_shutdown_rx) = oneshot::channel();
+        let origin =
+            tokio::spawn(
+                ConvexHttpService::new_for_test(Router::new().route("/http/echo", get(echo)))
+                    .serve(origin_addr, async move {
+                        origin_shutdown_rx.await.unwrap();
+                    }),
+            );
+
+        let proxy_port = portpicker::pick_unused_port().expect("No ports free");
+        let (proxy_shutdown_tx, proxy_shutdown_rx) = async_broadcast::broadcast(1);
+        let proxy = tokio::spawn(dev_site_proxy(
+            Some(([127, 0, 0, 1], proxy_port)),
+            ConvexOrigin::from(format!("http://{origin_addr}")),
+            proxy_shutdown_rx,
+        ));
+
+        let mut websocket = loop {
+            match connect_async(format!("ws://127.0.0.1:{proxy_port}/echo")).await {
+                Ok((websocket, _)) => break websocket,
+                // Can take a moment after the servers spawn to connect to them.
+                Err(TungsteniteError::Io(_)) => tokio::task::yield_now().await,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        websocket.send(ClientMessage::Text("hello".into())).await?;
+        let Some(ClientMessage::Text(text)) = websocket.next().await.transpose()? else {
+            anyhow::bail!("Expected a text message");
+        };
+        assert_eq!(text, "echo: hello");
+
+        websocket.close(None).await?;
+        proxy_shutdown_tx.broadcast(()).await?;
+        proxy.await??;
+        origin_shutdown_tx.send(()).unwrap();
+        origin.await??;
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
+use std::convert::Infallible;
+
+use anyhow::Context;
 use application::{
-    api::ExecuteQueryTimestamp,
+    api::{
+        ExecuteQueryTimestamp,
+        SubscriptionClient,
+    },
     redaction::{
         RedactedJsError,
         RedactedLogLines,
@@ -7,13 +13,22 @@ use application::{
 };
 use axum::{
     extract::State,
-    response::IntoResponse,
+    response::{
+        sse::{
+            Event,
+            KeepAlive,
+            Sse,
+        },
+        IntoResponse,
+    },
 };
 use common::{
     components::{
         CanonicalizedComponentFunctionPath,
         ComponentPath,
+        ExportPath,
     },
+    errors::report_error,
     http::{
         extract::{
             Json,
@@ -24,18 +39,39 @@ use common::{
         ExtractRequestId,
         ExtractResolvedHostname,
         HttpResponseError,
+        ResolvedHostname,
     },
+    knobs::MAX_QUERY_BATCH_SIZE,
+    projection::project_documents,
+    runtime::Runtime,
     types::FunctionCaller,
     version::ClientVersion,
+    RequestId,
+};
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
+use futures::{
+    future,
+    stream::BoxStream,
+    StreamExt,
+};
+use futures_async_stream::{
+    stream,
+    try_stream,
 };
-use errors::ErrorMetadata;
 use isolate::UdfArgsJson;
+use keybroker::Identity;
 use serde::{
     Deserialize,
     Serialize,
 };
 use serde_json::Value as JsonValue;
-use sync_types::Timestamp;
+use sync_types::{
+    AuthenticationToken,
+    Timestamp,
+};
 use value::{
     export::ValueFormat,
     ConvexValue,
@@ -44,6 +80,10 @@ use value::{
 use crate::{
     args_structs::UdfPostRequestWithComponent,
     authentication::ExtractAuthenticationToken,
+    idempotency::{
+        mutation_identifier,
+        ExtractIdempotencyKey,
+    },
     parse::{
         parse_export_path,
         parse_udf_path,
@@ -58,6 +98,9 @@ pub struct UdfPostRequest {
     pub args: UdfArgsJson,
 
     pub format: Option<String>,
+    /// Only return these top-level fields of the documents in a query's
+    /// result, along with their `_id`s. Ignored for mutations and actions.
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Serialize)]
@@ -74,6 +117,7 @@ pub struct UdfPostWithTsRequest {
     pub ts: SerializedTs,
 
     pub format: Option<String>,
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -105,6 +149,8 @@ pub struct UdfArgsQuery {
     pub args: UdfArgsJson,
 
     pub format: Option<String>,
+    /// Comma-separated fields to project the query's documents to.
+    pub fields: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -325,6 +371,26 @@ pub fn export_value(
     Ok(value.export(format))
 }
 
+/// Keeps only `fields` and `_id` of the documents in a query's result, if the
+/// caller asked for a subset of fields.
+pub(crate) fn project_query_result(value: ConvexValue, fields: Option<&[String]>) -> ConvexValue {
+    match fields {
+        Some(fields) => project_documents(value, fields),
+        None => value,
+    }
+}
+
+/// Parses fields passed as a comma-separated query parameter.
+pub(crate) fn parse_fields(fields: Option<&str>) -> Option<Vec<String>> {
+    fields.map(|fields| {
+        fields
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .filter(|field| !field.is_empty())
+            .collect()
+    })
+}
+
 #[fastrace::trace(properties = { "udf_type": "query"})]
 pub async fn public_query_get(
     State(st): State<RouterState>,
@@ -359,10 +425,15 @@ pub async fn public_query_get(
         )
         .await?;
     let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
+    let fields = parse_fields(req.fields.as_deref());
     let log_lines = query_result.log_lines;
     let response = match query_result.result {
         Ok(value) => UdfResponse::Success {
-            value: export_value(value, value_format, client_version)?,
+            value: export_value(
+                project_query_result(value, fields.as_deref()),
+                value_format,
+                client_version,
+            )?,
             log_lines,
         },
         Err(error) => UdfResponse::error(error, log_lines, value_format, client_version)?,
@@ -405,7 +476,11 @@ pub async fn public_query_post(
     let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
     let response = match query_return.result {
         Ok(value) => UdfResponse::Success {
-            value: export_value(value, value_format, client_version)?,
+            value: export_value(
+                project_query_result(value, req.fields.as_deref()),
+                value_format,
+                client_version,
+            )?,
             log_lines: query_return.log_lines,
         },
         Err(error) => {
@@ -460,7 +535,11 @@ pub async fn public_query_at_ts_post(
     let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
     let response = match query_return.result {
         Ok(value) => UdfResponse::Success {
-            value: export_value(value, value_format, client_version)?,
+            value: export_value(
+                project_query_result(value, req.fields.as_deref()),
+                value_format,
+                client_version,
+            )?,
             log_lines: query_return.log_lines,
         },
         Err(error) => {
@@ -473,13 +552,21 @@ pub async fn public_query_at_ts_post(
 #[derive(Deserialize)]
 pub struct QueryBatchArgs {
     queries: Vec<UdfPostRequest>,
+    /// Defaults to the latest timestamp.
+    ts: Option<SerializedTs>,
 }
 
 #[derive(Serialize)]
 pub struct QueryBatchResponse {
     results: Vec<UdfResponse>,
+    /// The timestamp all the queries ran at, for running more queries at the
+    /// same timestamp with `/api/query_at_ts`.
+    ts: SerializedTs,
 }
 
+/// Runs a batch of queries concurrently at a single timestamp, so their
+/// results are consistent with each other. Each query succeeds or fails on its
+/// own, but the whole batch fails if any of its paths or formats is invalid.
 pub async fn public_query_batch_post(
     State(st): State<RouterState>,
     ExtractResolvedHostname(host): ExtractResolvedHostname,
@@ -488,46 +575,277 @@ pub async fn public_query_batch_post(
     ExtractClientVersion(client_version): ExtractClientVersion,
     Json(req_batch): Json<QueryBatchArgs>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
-    let mut results = vec![];
+    if req_batch.queries.len() > *MAX_QUERY_BATCH_SIZE {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "TooManyQueries",
+            format!(
+                "A batch can have at most {} queries, but it has {}",
+                *MAX_QUERY_BATCH_SIZE,
+                req_batch.queries.len()
+            ),
+        ))
+        .into());
+    }
+    let queries = req_batch
+        .queries
+        .into_iter()
+        .map(|req| {
+            let value_format: Option<ValueFormat> =
+                req.format.as_ref().map(|f| f.parse()).transpose()?;
+            let export_path = parse_export_path(&req.path)?;
+            anyhow::Ok((
+                export_path,
+                req.args.into_arg_vec(),
+                value_format,
+                req.fields,
+            ))
+        })
+        .try_collect::<Vec<_>>()?;
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
     // All queries execute at the same timestamp.
-    let ts = st.api.latest_timestamp(&host, request_id.clone()).await?;
+    let ts = match req_batch.ts {
+        Some(ts) => Timestamp::try_from(ts)?,
+        None => *st.api.latest_timestamp(&host, request_id.clone()).await?,
+    };
+    let results = future::try_join_all(queries.into_iter().map(
+        |(export_path, args, value_format, fields)| {
+            let st = &st;
+            let host = &host;
+            let request_id = request_id.clone();
+            let identity = identity.clone();
+            let client_version = client_version.clone();
+            async move {
+                let udf_return = st
+                    .api
+                    .execute_public_query(
+                        host,
+                        request_id,
+                        identity,
+                        export_path,
+                        args,
+                        FunctionCaller::HttpApi(client_version.clone()),
+                        ExecuteQueryTimestamp::At(ts),
+                        None,
+                    )
+                    .await?;
+                match udf_return.result {
+                    Ok(value) => Ok(UdfResponse::Success {
+                        value: export_value(
+                            project_query_result(value, fields.as_deref()),
+                            value_format,
+                            client_version,
+                        )?,
+                        log_lines: udf_return.log_lines,
+                    }),
+                    Err(error) => UdfResponse::error(
+                        error,
+                        udf_return.log_lines,
+                        value_format,
+                        client_version,
+                    ),
+                }
+            }
+        },
+    ))
+    .await?;
+    Ok(Json(QueryBatchResponse {
+        results,
+        ts: ts.into(),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySubscribeArgs {
+    pub path: String,
+    /// JSON-encoded arguments, since a query string can't hold nested values.
+    /// Defaults to no arguments.
+    pub args: Option<String>,
+    pub format: Option<String>,
+    /// Comma-separated fields to project the query's documents to.
+    pub fields: Option<String>,
+    /// A user's auth token, for clients like `EventSource` that can't set the
+    /// `Authorization` header.
+    pub token: Option<String>,
+}
+
+/// Subscribes to a query and streams its results as Server-Sent Events, for
+/// clients that can't use the WebSocket sync protocol. Sends an `update` event
+/// holding a [`UdfResponse`] with the initial result and whenever it changes,
+/// and an `error` event right before ending the stream if the subscription
+/// fails, e.g. because the user's token expired.
+#[fastrace::trace(properties = { "udf_type": "query"})]
+pub async fn public_query_subscribe_get(
+    State(st): State<RouterState>,
+    Query(req): Query<QuerySubscribeArgs>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let export_path = parse_export_path(&req.path)?;
+    let args = parse_args_json(req.args.as_deref())?;
+    let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
+    let fields = parse_fields(req.fields.as_deref());
+    let auth_token = match (auth_token, req.token) {
+        (AuthenticationToken::None, Some(token)) => AuthenticationToken::User(token),
+        (auth_token, _) => auth_token,
+    };
+    // Fail before starting the stream if the request can't be authenticated, so
+    // the client gets an error status instead of an `error` event.
     let identity = st
         .api
         .authenticate(&host, request_id.clone(), auth_token)
         .await?;
-    for req in req_batch.queries {
-        let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
-        let export_path = parse_export_path(&req.path)?;
-        let udf_return = st
+    let subscription_client = st.api.subscription_client(&host).await?;
+    let events = query_subscription_updates(
+        st,
+        host,
+        request_id,
+        identity,
+        export_path,
+        args,
+        value_format,
+        fields,
+        client_version,
+        subscription_client,
+    )
+    .map(|response| -> anyhow::Result<Event> {
+        Ok(Event::default().event("update").json_data(response?)?)
+    })
+    .boxed();
+    Ok(Sse::new(sse_events(events)).keep_alive(KeepAlive::default()))
+}
+
+/// Parses arguments passed as a JSON string rather than in a JSON body.
+/// Missing arguments mean no arguments.
+pub(crate) fn parse_args_json(args: Option<&str>) -> anyhow::Result<Vec<JsonValue>> {
+    let Some(args) = args else {
+        return Ok(vec![JsonValue::Object(Default::default())]);
+    };
+    let args = serde_json::from_str::<UdfArgsJson>(args).context(ErrorMetadata::bad_request(
+        "BadQueryArgs",
+        "The `args` parameter must be JSON",
+    ))?;
+    Ok(args.into_arg_vec())
+}
+
+/// Reruns the query whenever its subscription is invalidated, yielding the
+/// response whenever it changes.
+#[try_stream(ok = UdfResponse, error = anyhow::Error, boxed)]
+pub(crate) async fn query_subscription_updates(
+    st: RouterState,
+    host: ResolvedHostname,
+    request_id: RequestId,
+    identity: Identity,
+    export_path: ExportPath,
+    args: Vec<JsonValue>,
+    value_format: Option<ValueFormat>,
+    fields: Option<Vec<String>>,
+    client_version: ClientVersion,
+    subscription_client: Box<dyn SubscriptionClient>,
+) {
+    let expiration = match &identity {
+        Identity::User(user) => Some(user.expiration),
+        _ => None,
+    };
+    let mut journal = None;
+    let mut last_response = None;
+    loop {
+        let query_return = st
             .api
             .execute_public_query(
                 &host,
                 request_id.clone(),
                 identity.clone(),
-                export_path,
-                req.args.into_arg_vec(),
+                export_path.clone(),
+                args.clone(),
                 FunctionCaller::HttpApi(client_version.clone()),
-                ExecuteQueryTimestamp::At(*ts),
-                None,
+                ExecuteQueryTimestamp::Latest,
+                journal,
             )
             .await?;
-        let response = match udf_return.result {
+        let response = match query_return.result {
             Ok(value) => UdfResponse::Success {
-                value: export_value(value, value_format, client_version.clone())?,
-                log_lines: udf_return.log_lines,
+                value: export_value(
+                    project_query_result(value, fields.as_deref()),
+                    value_format,
+                    client_version.clone(),
+                )?,
+                log_lines: query_return.log_lines,
             },
             Err(error) => UdfResponse::error(
                 error,
-                udf_return.log_lines,
+                query_return.log_lines,
                 value_format,
                 client_version.clone(),
             )?,
         };
-        results.push(response);
+        let serialized = serde_json::to_string(&response)?;
+        if last_response.as_ref() != Some(&serialized) {
+            last_response = Some(serialized);
+            yield response;
+        }
+        journal = Some(query_return.journal);
+
+        let subscription = subscription_client.subscribe(query_return.token).await?;
+        let token_expired = async {
+            match expiration {
+                Some(expiration) => {
+                    let remaining = expiration
+                        .duration_since(st.runtime.system_time())
+                        .unwrap_or_default();
+                    st.runtime.wait(remaining).await
+                },
+                None => future::pending().await,
+            }
+        };
+        tokio::select! {
+            result = subscription.wait_for_invalidation() => result?,
+            _ = token_expired => {
+                anyhow::bail!(ErrorMetadata::unauthenticated(
+                    "TokenExpired",
+                    "Convex token identity expired"
+                ));
+            },
+        }
     }
-    Ok(Json(QueryBatchResponse { results }))
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscriptionErrorEvent {
+    code: String,
+    message: String,
+}
+
+/// Ends the stream with an `error` event on the first error, since an SSE
+/// response has no other way to report one after it started.
+#[stream(item = Result<Event, Infallible>, boxed)]
+async fn sse_events(mut events: BoxStream<'static, anyhow::Result<Event>>) {
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(event) => yield Ok(event),
+            Err(mut e) => {
+                report_error(&mut e).await;
+                let error = SubscriptionErrorEvent {
+                    code: e.short_msg().to_string(),
+                    message: e.user_facing_message(),
+                };
+                if let Ok(event) = Event::default().event("error").json_data(error) {
+                    yield Ok(event);
+                }
+                break;
+            },
+        }
+    }
+}
+
+/// Runs a mutation. With an `Idempotency-Key` header, retries with the same
+/// key return the first successful call's result instead of running it again.
 #[fastrace::trace(properties = { "udf_type": "mutation"})]
 pub async fn public_mutation_post(
     State(st): State<RouterState>,
@@ -535,6 +853,7 @@ pub async fn public_mutation_post(
     ExtractRequestId(request_id): ExtractRequestId,
     ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
     ExtractClientVersion(client_version): ExtractClientVersion,
+    ExtractIdempotencyKey(idempotency_key): ExtractIdempotencyKey,
     Json(req): Json<UdfPostRequest>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     let export_path = parse_export_path(&req.path)?;
@@ -546,6 +865,9 @@ pub async fn public_mutation_post(
         .api
         .authenticate(&host, request_id.clone(), auth_token)
         .await?;
+    let args = req.args.into_arg_vec();
+    let mutation_identifier =
+        idempotency_key.map(|key| mutation_identifier(&identity, &export_path, &key, &args));
     let udf_result = st
         .api
         .execute_public_mutation(
@@ -553,9 +875,9 @@ pub async fn public_mutation_post(
             request_id,
             identity,
             export_path,
-            req.args.into_arg_vec(),
+            args,
             FunctionCaller::HttpApi(client_version.clone()),
-            None,
+            mutation_identifier,
         )
         .await?;
     let value_format = req.format.as_ref().map(|f| f.parse()).transpose()?;
@@ -622,12 +944,14 @@ pub async fn public_action_post(
 
 #[cfg(test)]
 mod tests {
+    use anyhow::Context;
     use application::test_helpers::ApplicationTestExt;
     use axum::body::Body;
     use http::{
         Request,
         StatusCode,
     };
+    use http_body_util::BodyExt;
     use runtime::prod::ProdRuntime;
     use serde_json::{
         json,
@@ -786,4 +1110,171 @@ mod tests {
         )
         .await
     }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_mutation_idempotency_key(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let mutation = |args: JsonValue| -> anyhow::Result<Request<Body>> {
+            let json_body = json!({
+                "path": "values:insertObject",
+                "args": args,
+            });
+            Ok(Request::builder()
+                .uri("/api/mutation")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Host", "localhost")
+                .header("Idempotency-Key", "key-1")
+                .body(Body::from(serde_json::to_vec(&json_body)?))?)
+        };
+        let first: JsonValue = backend
+            .expect_success(mutation(json!({"obj": {"a": 1, "b": 2}}))?)
+            .await?;
+        // Retrying replays the recorded result, regardless of field order.
+        let retry: JsonValue = backend
+            .expect_success(mutation(json!({"obj": {"b": 2, "a": 1}}))?)
+            .await?;
+        assert_eq!(first["value"], retry["value"]);
+        backend
+            .expect_error(
+                mutation(json!({"obj": {"a": 2}}))?,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "IdempotencyKeyReused",
+            )
+            .await
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_query_batch(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let json_body = json!({
+            "queries": [
+                {"path": "values:intQuery", "args": {}},
+                {"path": "args_validation:stringArg", "args": {"arg": "val"}, "format": "json"},
+            ],
+        });
+        let req = Request::builder()
+            .uri("/api/query_batch")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Host", "localhost")
+            .body(Body::from(serde_json::to_vec(&json_body)?))?;
+        let result: JsonValue = backend.expect_success(req).await?;
+        assert_eq!(
+            result["results"],
+            json!([
+                {"status": "success", "value": "1"},
+                {"status": "success", "value": "val"},
+            ])
+        );
+        assert!(result["ts"].is_string());
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_query_batch_too_many_queries(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        let queries = vec![json!({"path": "values:intQuery", "args": {}}); 101];
+        let req = Request::builder()
+            .uri("/api/query_batch")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Host", "localhost")
+            .body(Body::from(serde_json::to_vec(
+                &json!({ "queries": queries }),
+            )?))?;
+        backend
+            .expect_error(req, StatusCode::BAD_REQUEST, "TooManyQueries")
+            .await
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_query_subscribe(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let req = Request::builder()
+            .uri("/api/query_subscribe?path=values:intQuery")
+            .method("GET")
+            .header("Host", "localhost")
+            .body(Body::empty())?;
+        let response = backend.send(req).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let frame = response
+            .into_body()
+            .frame()
+            .await
+            .context("Missing update event")??;
+        let event = frame.into_data().map_err(|_| anyhow::anyhow!("Not data"))?;
+        assert_eq!(
+            String::from_utf8(event.to_vec())?,
+            "event: update\ndata: {\"status\":\"success\",\"value\":\"1\"}\n\n"
+        );
+        Ok(())
+    }
+
+    /// Reads the next event from an SSE body, skipping keep-alive comments.
+    async fn next_event(body: &mut Body) -> anyhow::Result<(String, JsonValue)> {
+        loop {
+            let frame = body.frame().await.context("Missing event")??;
+            let data = frame.into_data().map_err(|_| anyhow::anyhow!("Not data"))?;
+            let event = String::from_utf8(data.to_vec())?;
+            if event.starts_with(':') {
+                continue;
+            }
+            let (name, data) = event
+                .trim_end()
+                .strip_prefix("event: ")
+                .and_then(|event| event.split_once("\ndata: "))
+                .context("Malformed event")?;
+            return Ok((name.to_string(), serde_json::from_str(data)?));
+        }
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_query_subscribe_updates(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let req = Request::builder()
+            .uri("/api/query_subscribe?path=basic:count")
+            .method("GET")
+            .header("Host", "localhost")
+            .body(Body::empty())?;
+        let response = backend.send(req).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body();
+        let (name, data) = next_event(&mut body).await?;
+        assert_eq!(name, "update");
+        assert_eq!(data["value"].as_f64(), Some(0.));
+
+        // Writing to the table the query read invalidates the subscription.
+        for count in [1., 2.] {
+            let json_body = json!({"path": "basic:insertObject", "args": {"field": "a"}});
+            let req = Request::builder()
+                .uri("/api/mutation")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Host", "localhost")
+                .body(Body::from(serde_json::to_vec(&json_body)?))?;
+            let _: JsonValue = backend.expect_success(req).await?;
+            let (name, data) = next_event(&mut body).await?;
+            assert_eq!(name, "update");
+            assert_eq!(data["value"].as_f64(), Some(count));
+        }
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_query_subscribe_bad_args(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        let req = Request::builder()
+            .uri("/api/query_subscribe?path=values:intQuery&args=%7Bnot%20json")
+            .method("GET")
+            .header("Host", "localhost")
+            .body(Body::empty())?;
+        backend
+            .expect_error(req, StatusCode::BAD_REQUEST, "BadQueryArgs")
+            .await
+    }
 }
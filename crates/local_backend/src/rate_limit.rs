@@ -0,0 +1,407 @@
+//! Token-bucket rate limiting for the public API and HTTP actions, so one
+//! misbehaving client can't take over a self-hosted deployment.
+//!
+//! Each class of route has its own limit. Clients are identified by their user
+//! identity if they're authenticated and by their IP address otherwise, and
+//! requests with an admin key aren't limited. Addresses in `X-Forwarded-For`
+//! are only used for requests from a configured trusted proxy.
+use std::{
+    collections::BTreeMap,
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+    num::NonZeroU32,
+    sync::{
+        Arc,
+        Weak,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        ConnectInfo,
+        OriginalUri,
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+    RequestPartsExt,
+};
+use common::{
+    http::{
+        ExtractRequestId,
+        ExtractResolvedHostname,
+        HttpResponseError,
+    },
+    runtime::{
+        new_keyed_rate_limiter,
+        KeyedRateLimiter,
+        Runtime,
+    },
+};
+use errors::ErrorMetadata;
+use governor::Quota;
+use http::{
+    header::RETRY_AFTER,
+    HeaderValue,
+    Method,
+};
+use ipnet::IpNet;
+use keybroker::Identity;
+use runtime::prod::ProdRuntime;
+
+use crate::{
+    authentication::ExtractAuthenticationToken,
+    ip_access::parse_net,
+    RouterState,
+};
+
+/// How often to forget clients that haven't made a request recently.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RouteClass {
+    Query,
+    Mutation,
+    Action,
+    HttpAction,
+    /// File storage uploads and downloads. These can have their own request
+    /// timeout, but aren't rate limited.
+    Storage,
+    /// Snapshot export requests and downloads. These can have their own
+    /// request timeout, but aren't rate limited.
+    Export,
+}
+
+impl RouteClass {
+    /// `/api/function` and `/api/run/*` can run any kind of function, so
+    /// they're limited like mutations.
+    pub(crate) fn for_request(method: &Method, path: &str) -> Option<Self> {
+        if method == Method::OPTIONS {
+            return None;
+        }
+        if path.starts_with("/http/") {
+            return Some(Self::HttpAction);
+        }
+        if path.starts_with("/api/storage/") {
+            return Some(Self::Storage);
+        }
+        if path.starts_with("/api/export/") {
+            return Some(Self::Export);
+        }
+        match path {
+            "/api/query" | "/api/query_at_ts" | "/api/query_batch" | "/api/query_subscribe" => {
+                Some(Self::Query)
+            },
+            "/api/mutation" | "/api/function" => Some(Self::Mutation),
+            "/api/action" => Some(Self::Action),
+            "/convex_api.ConvexApi/Query" | "/convex_api.ConvexApi/SubscribeQuery" => {
+                Some(Self::Query)
+            },
+            "/convex_api.ConvexApi/Mutation" => Some(Self::Mutation),
+            "/convex_api.ConvexApi/Action" => Some(Self::Action),
+            _ if path.starts_with("/api/run/") => Some(Self::Mutation),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum RateLimitKey {
+    User {
+        issuer: String,
+        subject: String,
+    },
+    Ip(IpAddr),
+    /// Requests without a peer address share one limit.
+    UnknownAddress,
+}
+
+pub struct RateLimits {
+    runtime: ProdRuntime,
+    limiters: BTreeMap<RouteClass, KeyedRateLimiter<RateLimitKey, ProdRuntime>>,
+}
+
+impl RateLimits {
+    pub fn new(runtime: ProdRuntime, quotas: BTreeMap<RouteClass, Quota>) -> Arc<Self> {
+        let limiters = quotas
+            .into_iter()
+            .map(|(class, quota)| (class, new_keyed_rate_limiter(runtime.clone(), quota)))
+            .collect();
+        let rate_limits = Arc::new(Self {
+            runtime: runtime.clone(),
+            limiters,
+        });
+        if !rate_limits.limiters.is_empty() {
+            runtime.spawn(
+                "rate_limit_cleanup",
+                Self::cleanup(runtime.clone(), Arc::downgrade(&rate_limits)),
+            );
+        }
+        rate_limits
+    }
+
+    /// The quota for `per_second` requests a second, allowing bursts of
+    /// `burst_seconds` seconds' worth of requests.
+    pub fn quota(per_second: NonZeroU32, burst_seconds: NonZeroU32) -> Quota {
+        Quota::per_second(per_second).allow_burst(per_second.saturating_mul(burst_seconds))
+    }
+
+    /// Returns how long to wait before retrying if the request is over its
+    /// limit.
+    fn check(&self, class: RouteClass, key: &RateLimitKey) -> Result<(), Duration> {
+        let Some(limiter) = self.limiters.get(&class) else {
+            return Ok(());
+        };
+        limiter
+            .check_key(key)
+            .map_err(|not_until| not_until.wait_time_from(self.runtime.monotonic_now().into()))
+    }
+
+    async fn cleanup(runtime: ProdRuntime, rate_limits: Weak<Self>) {
+        loop {
+            runtime.wait(CLEANUP_INTERVAL).await;
+            let Some(rate_limits) = rate_limits.upgrade() else {
+                return;
+            };
+            for limiter in rate_limits.limiters.values() {
+                limiter.retain_recent();
+            }
+        }
+    }
+}
+
+pub async fn rate_limit_middleware(
+    State(st): State<RouterState>,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, HttpResponseError> {
+    // Nested routers only see the rest of the path.
+    let path = match req.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path(),
+        None => req.uri().path(),
+    };
+    let Some(class) = RouteClass::for_request(req.method(), path) else {
+        return Ok(next.run(req).await);
+    };
+    if !st.rate_limits.limiters.contains_key(&class) {
+        return Ok(next.run(req).await);
+    }
+    let (mut parts, body) = req.into_parts();
+    let ExtractResolvedHostname(host) = parts.extract().await?;
+    let ExtractRequestId(request_id) = parts.extract().await?;
+    // Requests with an invalid token are limited by IP address, and fail
+    // later when they're authenticated again.
+    let identity = match parts.extract::<ExtractAuthenticationToken>().await {
+        Ok(ExtractAuthenticationToken(token)) => st
+            .api
+            .authenticate(&host, request_id, token)
+            .await
+            .unwrap_or(Identity::Unknown),
+        Err(_) => Identity::Unknown,
+    };
+    let key = match identity {
+        Identity::InstanceAdmin(_) | Identity::ActingUser(..) | Identity::System(_) => {
+            return Ok(next.run(Request::from_parts(parts, body)).await);
+        },
+        Identity::User(user) => RateLimitKey::User {
+            issuer: user.issuer,
+            subject: user.subject,
+        },
+        Identity::Unknown => {
+            let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+            match st.trusted_proxies.client_ip(remote_ip, &parts.headers) {
+                Some(ip) => RateLimitKey::Ip(ip),
+                None => RateLimitKey::UnknownAddress,
+            }
+        },
+    };
+    if let Err(wait_time) = st.rate_limits.check(class, &key) {
+        let error = anyhow::anyhow!(ErrorMetadata::rate_limited(
+            "RateLimited",
+            format!(
+                "Too many requests. Retry in {:.1}s.",
+                wait_time.as_secs_f64()
+            ),
+        ));
+        let mut response = HttpResponseError::from(error).into_response();
+        let retry_after = wait_time.as_secs_f64().ceil().max(1.0) as u64;
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        return Ok(response);
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// The proxies, like a load balancer or the HTTP actions proxy on the site
+/// port, whose `X-Forwarded-For` header is trusted to name the client.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    /// `proxies` hold CIDR blocks like `10.0.0.0/8`, or single addresses.
+    pub fn parse(proxies: &[String]) -> anyhow::Result<Arc<Self>> {
+        Ok(Arc::new(Self(
+            proxies
+                .iter()
+                .map(|net| parse_net(net))
+                .try_collect::<Vec<_>>()?,
+        )))
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+
+    /// The address of the client that made a request, or `None` if the
+    /// request has no peer address. Each trusted proxy appends the address it
+    /// got the request from to `X-Forwarded-For`, so the client is the last
+    /// address that isn't a trusted proxy. Addresses before it were set by
+    /// the client, so they can't be trusted.
+    pub(crate) fn client_ip(
+        &self,
+        remote_ip: Option<IpAddr>,
+        headers: &http::HeaderMap,
+    ) -> Option<IpAddr> {
+        let mut client_ip = remote_ip?;
+        let Some(forwarded_for) = headers.get("x-forwarded-for").and_then(|h| h.to_str().ok())
+        else {
+            return Some(client_ip);
+        };
+        for forwarded_ip in forwarded_for.rsplit(',') {
+            if !self.contains(client_ip) {
+                break;
+            }
+            let Ok(forwarded_ip) = forwarded_ip.trim().parse() else {
+                break;
+            };
+            client_ip = forwarded_ip;
+        }
+        Some(client_ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        net::IpAddr,
+        num::NonZeroU32,
+    };
+
+    use http::{
+        HeaderMap,
+        Method,
+    };
+    use runtime::prod::ProdRuntime;
+
+    use super::{
+        RateLimitKey,
+        RateLimits,
+        RouteClass,
+        TrustedProxies,
+    };
+
+    #[test]
+    fn test_route_class() {
+        let cases = [
+            (Method::POST, "/api/query", Some(RouteClass::Query)),
+            (Method::GET, "/api/query_subscribe", Some(RouteClass::Query)),
+            (Method::POST, "/api/mutation", Some(RouteClass::Mutation)),
+            (
+                Method::POST,
+                "/api/run/messages/send",
+                Some(RouteClass::Mutation),
+            ),
+            (Method::POST, "/api/action", Some(RouteClass::Action)),
+            (
+                Method::POST,
+                "/convex_api.ConvexApi/SubscribeQuery",
+                Some(RouteClass::Query),
+            ),
+            (Method::GET, "/http/webhooks", Some(RouteClass::HttpAction)),
+            (Method::OPTIONS, "/http/webhooks", None),
+            (
+                Method::GET,
+                "/api/storage/abc123",
+                Some(RouteClass::Storage),
+            ),
+            (
+                Method::GET,
+                "/api/export/zip/abc123",
+                Some(RouteClass::Export),
+            ),
+            (Method::GET, "/api/sync", None),
+        ];
+        for (method, path, class) in cases {
+            assert_eq!(
+                RouteClass::for_request(&method, path),
+                class,
+                "{method} {path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_client_ip() -> anyhow::Result<()> {
+        let trusted = TrustedProxies::parse(&["127.0.0.1".to_string(), "10.0.0.0/8".to_string()])?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "192.0.2.1, 203.0.113.7, 10.0.0.1".parse()?,
+        );
+        let loopback: IpAddr = "127.0.0.1".parse()?;
+        let remote: IpAddr = "198.51.100.2".parse()?;
+        assert_eq!(
+            trusted.client_ip(Some(loopback), &headers),
+            Some("203.0.113.7".parse()?)
+        );
+        // Only trusted proxies may set the header.
+        assert_eq!(trusted.client_ip(Some(remote), &headers), Some(remote));
+        assert_eq!(
+            TrustedProxies::default().client_ip(Some(loopback), &headers),
+            Some(loopback)
+        );
+        assert_eq!(
+            trusted.client_ip(Some(loopback), &HeaderMap::new()),
+            Some(loopback)
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "unknown".parse()?);
+        assert_eq!(trusted.client_ip(Some(loopback), &headers), Some(loopback));
+        assert_eq!(trusted.client_ip(None, &headers), None);
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_rate_limits(rt: ProdRuntime) -> anyhow::Result<()> {
+        let quota = RateLimits::quota(NonZeroU32::new(1).unwrap(), NonZeroU32::new(2).unwrap());
+        let rate_limits = RateLimits::new(rt, BTreeMap::from([(RouteClass::Query, quota)]));
+        let alice = RateLimitKey::User {
+            issuer: "https://issuer.example.com".to_string(),
+            subject: "alice".to_string(),
+        };
+        let bob = RateLimitKey::Ip("203.0.113.7".parse()?);
+
+        rate_limits.check(RouteClass::Query, &alice).unwrap();
+        rate_limits.check(RouteClass::Query, &alice).unwrap();
+        let wait_time = rate_limits.check(RouteClass::Query, &alice).unwrap_err();
+        assert!(!wait_time.is_zero());
+
+        // Other clients and route classes have their own limits.
+        rate_limits.check(RouteClass::Query, &bob).unwrap();
+        for _ in 0..10 {
+            rate_limits.check(RouteClass::Mutation, &alice).unwrap();
+        }
+        Ok(())
+    }
+}
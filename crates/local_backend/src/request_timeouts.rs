@@ -0,0 +1,109 @@
+//! Request timeouts for each class of route, so long downloads and short
+//! public queries don't have to share one limit.
+//!
+//! Requests are timed out by `request_timeout_middleware`, which applies the
+//! timeout for the request's `RouteClass`, or `--request-timeout-secs` for
+//! routes without one. The HTTP service's own timeout is the longest of
+//! these, so it doesn't cut off routes that are allowed to take longer.
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use http::{
+    Method,
+    StatusCode,
+};
+
+use crate::rate_limit::RouteClass;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    default: Duration,
+    by_class: BTreeMap<RouteClass, Duration>,
+}
+
+impl RequestTimeouts {
+    pub fn new(default: Duration, by_class: BTreeMap<RouteClass, Duration>) -> Arc<Self> {
+        Arc::new(Self { default, by_class })
+    }
+
+    pub fn timeout_for(&self, method: &Method, path: &str) -> Duration {
+        RouteClass::for_request(method, path)
+            .and_then(|class| self.by_class.get(&class).copied())
+            .unwrap_or(self.default)
+    }
+
+    /// The longest timeout of any route.
+    pub fn max(&self) -> Duration {
+        self.by_class
+            .values()
+            .copied()
+            .fold(self.default, Duration::max)
+    }
+}
+
+pub async fn request_timeout_middleware(
+    State(timeouts): State<Arc<RequestTimeouts>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let timeout = timeouts.timeout_for(req.method(), req.uri().path());
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => StatusCode::REQUEST_TIMEOUT.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        time::Duration,
+    };
+
+    use http::Method;
+
+    use super::RequestTimeouts;
+    use crate::rate_limit::RouteClass;
+
+    #[test]
+    fn test_timeout_for() {
+        let timeouts = RequestTimeouts::new(
+            Duration::from_secs(125),
+            BTreeMap::from([
+                (RouteClass::Query, Duration::from_secs(5)),
+                (RouteClass::Export, Duration::from_secs(3600)),
+            ]),
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/api/query"),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::GET, "/api/export/zip/abc123"),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/api/mutation"),
+            Duration::from_secs(125)
+        );
+        assert_eq!(
+            timeouts.timeout_for(&Method::POST, "/api/push_config"),
+            Duration::from_secs(125)
+        );
+        assert_eq!(timeouts.max(), Duration::from_secs(3600));
+    }
+}
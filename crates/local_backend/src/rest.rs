@@ -0,0 +1,490 @@
+//! REST API over the documents in the root app's tables, served at
+//! `/api/rest/:table` when the `REST_API_ENABLED` knob is set, for scripts and
+//! tools that can't use a Convex client.
+//!
+//! Requests read and write tables directly, skipping the authorization checks
+//! in the app's functions, so they require an admin key or an API key, whose
+//! table scopes are enforced by the database. Each request runs a system UDF
+//! in `_system/rest.ts` through [`ApplicationApi`], and documents are sent and
+//! returned in the same JSON format as function arguments and results.
+//!
+//! [`ApplicationApi`]: application::api::ApplicationApi
+use anyhow::Context;
+use application::{
+    api::ExecuteQueryTimestamp,
+    redaction::RedactedJsError,
+};
+use axum::{
+    extract::State,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use common::{
+    components::ExportPath,
+    http::{
+        extract::{
+            Json,
+            Path,
+            Query,
+        },
+        ExtractClientVersion,
+        ExtractRequestId,
+        ExtractResolvedHostname,
+        HttpResponseError,
+    },
+    types::FunctionCaller,
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    json,
+    Map as JsonMap,
+    Value as JsonValue,
+};
+use value::{
+    export::ValueFormat,
+    ConvexValue,
+    TableName,
+};
+
+use crate::{
+    admin::{
+        must_be_admin_or_api_key,
+        must_be_admin_or_api_key_with_write_access,
+    },
+    authentication::ExtractAuthenticationToken,
+    RouterState,
+};
+
+/// Number of documents in a page when `numItems` isn't given.
+const DEFAULT_NUM_ITEMS: usize = 100;
+const MAX_NUM_ITEMS: usize = 1000;
+
+pub fn rest_routes() -> Router<RouterState> {
+    Router::new()
+        .route("/rest/:table", get(list_documents).post(insert_document))
+        .route(
+            "/rest/:table/:id",
+            get(get_document)
+                .patch(patch_document)
+                .delete(delete_document),
+        )
+}
+
+#[derive(Deserialize)]
+pub struct TablePath {
+    table: String,
+}
+
+#[derive(Deserialize)]
+pub struct DocumentPath {
+    table: String,
+    id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ListOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Filters on an index are JSON objects from field names to values, like
+/// `?index=by_channel&eq={"channel":"general"}&gte={"_creationTime":0}`.
+/// Equality filters must be on a prefix of the index's fields, in order, and
+/// the range filters on the field after them.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDocumentsQuery {
+    index: Option<String>,
+    eq: Option<String>,
+    gt: Option<String>,
+    gte: Option<String>,
+    lt: Option<String>,
+    lte: Option<String>,
+    #[serde(default)]
+    order: ListOrder,
+    cursor: Option<String>,
+    num_items: Option<usize>,
+}
+
+pub async fn list_documents(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Path(TablePath { table }): Path<TablePath>,
+    Query(query): Query<ListDocumentsQuery>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin_or_api_key(&identity)?;
+    let args = list_args(&parse_table_name(&table)?, query)?;
+    let query_return = st
+        .api
+        .execute_public_query(
+            &host,
+            request_id,
+            identity,
+            rest_udf("list")?,
+            vec![args],
+            FunctionCaller::HttpApi(client_version),
+            ExecuteQueryTimestamp::Latest,
+            None,
+        )
+        .await?;
+    let page = query_return.result.map_err(rest_udf_error)?;
+    Ok(Json(page.export(ValueFormat::ConvexCleanJSON)))
+}
+
+pub async fn get_document(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Path(DocumentPath { table, id }): Path<DocumentPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin_or_api_key(&identity)?;
+    let table = parse_table_name(&table)?;
+    let query_return = st
+        .api
+        .execute_public_query(
+            &host,
+            request_id,
+            identity,
+            rest_udf("get")?,
+            vec![json!({ "table": table.to_string(), "id": id })],
+            FunctionCaller::HttpApi(client_version),
+            ExecuteQueryTimestamp::Latest,
+            None,
+        )
+        .await?;
+    let document = query_return.result.map_err(rest_udf_error)?;
+    Ok(Json(found_document(document, &table, &id)?))
+}
+
+pub async fn insert_document(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Path(TablePath { table }): Path<TablePath>,
+    Json(document): Json<JsonMap<String, JsonValue>>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin_or_api_key_with_write_access(&identity)?;
+    let table = parse_table_name(&table)?;
+    let result = st
+        .api
+        .execute_public_mutation(
+            &host,
+            request_id,
+            identity,
+            rest_udf("insert")?,
+            vec![json!({ "table": table.to_string(), "document": document })],
+            FunctionCaller::HttpApi(client_version),
+            None,
+        )
+        .await?;
+    let document = result
+        .map(|mutation_return| mutation_return.value)
+        .map_err(|mutation_error| rest_udf_error(mutation_error.error))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(document.export(ValueFormat::ConvexCleanJSON)),
+    ))
+}
+
+/// Sets the fields in the request body on a document, leaving its other
+/// fields as they are.
+pub async fn patch_document(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Path(DocumentPath { table, id }): Path<DocumentPath>,
+    Json(fields): Json<JsonMap<String, JsonValue>>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin_or_api_key_with_write_access(&identity)?;
+    let table = parse_table_name(&table)?;
+    let result = st
+        .api
+        .execute_public_mutation(
+            &host,
+            request_id,
+            identity,
+            rest_udf("patch")?,
+            vec![json!({ "table": table.to_string(), "id": id, "fields": fields })],
+            FunctionCaller::HttpApi(client_version),
+            None,
+        )
+        .await?;
+    let document = result
+        .map(|mutation_return| mutation_return.value)
+        .map_err(|mutation_error| rest_udf_error(mutation_error.error))?;
+    Ok(Json(found_document(document, &table, &id)?))
+}
+
+/// Deletes a document, returning it as it was before it was deleted.
+pub async fn delete_document(
+    State(st): State<RouterState>,
+    ExtractResolvedHostname(host): ExtractResolvedHostname,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractAuthenticationToken(auth_token): ExtractAuthenticationToken,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Path(DocumentPath { table, id }): Path<DocumentPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let identity = st
+        .api
+        .authenticate(&host, request_id.clone(), auth_token)
+        .await?;
+    must_be_admin_or_api_key_with_write_access(&identity)?;
+    let table = parse_table_name(&table)?;
+    let result = st
+        .api
+        .execute_public_mutation(
+            &host,
+            request_id,
+            identity,
+            rest_udf("remove")?,
+            vec![json!({ "table": table.to_string(), "id": id })],
+            FunctionCaller::HttpApi(client_version),
+            None,
+        )
+        .await?;
+    let document = result
+        .map(|mutation_return| mutation_return.value)
+        .map_err(|mutation_error| rest_udf_error(mutation_error.error))?;
+    Ok(Json(found_document(document, &table, &id)?))
+}
+
+fn rest_udf(name: &str) -> anyhow::Result<ExportPath> {
+    format!("_system/rest:{name}").parse()
+}
+
+/// Only user tables can be accessed over the REST API.
+fn parse_table_name(table: &str) -> anyhow::Result<TableName> {
+    let table_name: TableName = table.parse().context(ErrorMetadata::bad_request(
+        "InvalidTableName",
+        format!("Invalid table name: {table}"),
+    ))?;
+    anyhow::ensure!(
+        !table_name.is_system(),
+        ErrorMetadata::bad_request(
+            "InvalidTableName",
+            format!("System table {table} can't be accessed over the REST API"),
+        )
+    );
+    Ok(table_name)
+}
+
+fn found_document(document: ConvexValue, table: &TableName, id: &str) -> anyhow::Result<JsonValue> {
+    anyhow::ensure!(
+        document != ConvexValue::Null,
+        ErrorMetadata::not_found(
+            "DocumentNotFound",
+            format!("Document {id} not found in table {table}"),
+        )
+    );
+    Ok(document.export(ValueFormat::ConvexCleanJSON))
+}
+
+/// Errors thrown by the system UDFs, like schema validation failures, are
+/// caused by the request, so they're returned as 400s.
+fn rest_udf_error(error: RedactedJsError) -> anyhow::Error {
+    let message = error.nested_to_string();
+    let message = match error.custom_data_if_any() {
+        Some(ConvexValue::String(data)) => String::from(data),
+        _ => message,
+    };
+    ErrorMetadata::bad_request("InvalidRestRequest", message).into()
+}
+
+fn list_args(table: &TableName, query: ListDocumentsQuery) -> anyhow::Result<JsonValue> {
+    let num_items = query.num_items.unwrap_or(DEFAULT_NUM_ITEMS);
+    anyhow::ensure!(
+        (1..=MAX_NUM_ITEMS).contains(&num_items),
+        ErrorMetadata::bad_request(
+            "InvalidNumItems",
+            format!("`numItems` must be between 1 and {MAX_NUM_ITEMS}"),
+        )
+    );
+    let eq = match query.eq {
+        Some(eq) => parse_filter("eq", &eq)?
+            .into_iter()
+            .map(|(field, value)| json!({ "field": field, "value": value }))
+            .collect(),
+        None => vec![],
+    };
+    let lower = parse_bound(&[("gt", query.gt), ("gte", query.gte)])?;
+    let upper = parse_bound(&[("lt", query.lt), ("lte", query.lte)])?;
+    anyhow::ensure!(
+        query.index.is_some() || (eq.is_empty() && lower.is_none() && upper.is_none()),
+        ErrorMetadata::bad_request("MissingIndex", "Filtering documents requires an `index`",)
+    );
+    let mut args = json!({
+        "table": table.to_string(),
+        "eq": eq,
+        "order": query.order,
+        "paginationOpts": {
+            "numItems": num_items,
+            "cursor": query.cursor,
+        },
+    });
+    if let Some(index) = query.index {
+        args["index"] = index.into();
+    }
+    if let Some(lower) = lower {
+        args["lower"] = lower;
+    }
+    if let Some(upper) = upper {
+        args["upper"] = upper;
+    }
+    Ok(args)
+}
+
+fn parse_filter(param: &str, filter: &str) -> anyhow::Result<JsonMap<String, JsonValue>> {
+    serde_json::from_str(filter).context(ErrorMetadata::bad_request(
+        "InvalidFilter",
+        format!("`{param}` must be a JSON object from field names to values"),
+    ))
+}
+
+/// Parses at most one of the given range filters, each of which must be on a
+/// single field.
+fn parse_bound(params: &[(&str, Option<String>)]) -> anyhow::Result<Option<JsonValue>> {
+    let mut bound = None;
+    for (op, filter) in params {
+        let Some(filter) = filter else {
+            continue;
+        };
+        anyhow::ensure!(
+            bound.is_none(),
+            ErrorMetadata::bad_request(
+                "InvalidFilter",
+                format!(
+                    "Only one of {} can be given",
+                    params
+                        .iter()
+                        .map(|(op, _)| format!("`{op}`"))
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                ),
+            )
+        );
+        let mut fields = parse_filter(op, filter)?.into_iter();
+        let (Some((field, value)), None) = (fields.next(), fields.next()) else {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidFilter",
+                format!("`{op}` must filter on exactly one field"),
+            ));
+        };
+        bound = Some(json!({ "op": op, "field": field, "value": value }));
+    }
+    Ok(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use errors::ErrorMetadataAnyhowExt;
+    use serde_json::json;
+
+    use super::{
+        list_args,
+        parse_table_name,
+        ListDocumentsQuery,
+        MAX_NUM_ITEMS,
+    };
+
+    #[test]
+    fn test_list_args() -> anyhow::Result<()> {
+        let table = parse_table_name("messages")?;
+        let query = ListDocumentsQuery {
+            index: Some("by_channel".to_string()),
+            eq: Some(r#"{"channel":"general","author":"sarah"}"#.to_string()),
+            gte: Some(r#"{"_creationTime":10}"#.to_string()),
+            cursor: Some("abc".to_string()),
+            num_items: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            list_args(&table, query)?,
+            json!({
+                "table": "messages",
+                "index": "by_channel",
+                // Equality filters keep the order they were given in.
+                "eq": [
+                    { "field": "channel", "value": "general" },
+                    { "field": "author", "value": "sarah" },
+                ],
+                "lower": { "op": "gte", "field": "_creationTime", "value": 10 },
+                "order": "asc",
+                "paginationOpts": { "numItems": 10, "cursor": "abc" },
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_args_errors() -> anyhow::Result<()> {
+        let table = parse_table_name("messages")?;
+        let err = list_args(
+            &table,
+            ListDocumentsQuery {
+                eq: Some(r#"{"channel":"general"}"#.to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.short_msg(), "MissingIndex");
+
+        let err = list_args(
+            &table,
+            ListDocumentsQuery {
+                index: Some("by_channel".to_string()),
+                gt: Some(r#"{"a":1}"#.to_string()),
+                gte: Some(r#"{"a":1}"#.to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.short_msg(), "InvalidFilter");
+
+        let err = list_args(
+            &table,
+            ListDocumentsQuery {
+                num_items: Some(MAX_NUM_ITEMS + 1),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.short_msg(), "InvalidNumItems");
+
+        let err = parse_table_name("_scheduled_functions").unwrap_err();
+        assert_eq!(err.short_msg(), "InvalidTableName");
+        Ok(())
+    }
+}
@@ -12,8 +12,10 @@ use axum::{
         State,
     },
     routing::{
+        delete,
         get,
         post,
+        put,
     },
     Router,
 };
@@ -23,10 +25,13 @@ use common::{
         CONVEX_CLIENT_HEADER,
     },
     knobs::{
-        MAX_BACKEND_PUBLIC_API_REQUEST_SIZE,
+        ANONYMOUS_IDENTITIES_ENABLED,
+        GRAPHQL_API_ENABLED,
+        GRPC_API_ENABLED,
         MAX_BACKEND_RPC_REQUEST_SIZE,
         MAX_ECHO_BYTES,
         MAX_PUSH_BYTES,
+        REST_API_ENABLED,
     },
 };
 use http::{
@@ -44,6 +49,7 @@ use http::{
     StatusCode,
 };
 use metrics::SERVER_VERSION_STR;
+use sync::ResumableSessions;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{
@@ -52,37 +58,117 @@ use tower_http::{
     },
     decompression::RequestDecompressionLayer,
 };
-use udf::HTTP_ACTION_BODY_LIMIT;
 
 use crate::{
+    access_rules::{
+        delete_access_rules,
+        evaluate_access_rules,
+        list_access_rules,
+        set_access_rules,
+    },
+    admin_key_audit_log::{
+        admin_key_audit_middleware,
+        get_admin_key_audit_log,
+    },
+    admin_roles::{
+        list_admin_roles,
+        remove_admin_role,
+        set_admin_role,
+    },
+    alerting::{
+        create_alert_rule,
+        delete_alert_rule,
+        list_alert_rules,
+    },
+    anonymous_identities::{
+        issue_anonymous_identity,
+        upgrade_anonymous_identity,
+    },
+    api_keys::{
+        create_api_key,
+        list_api_keys,
+        revoke_api_key,
+    },
     app_metrics::{
         cache_hit_percentage,
         cache_hit_percentage_top_k,
+        error_groups,
+        execution_profile,
         failure_percentage_top_k,
         latency_percentiles,
+        occ_conflicts,
+        prometheus_metrics,
         scheduled_job_lag,
+        table_metrics,
         table_rate,
         udf_rate,
     },
+    auth_lockout::auth_lockout_middleware,
+    body_limits::{
+        body_limit,
+        BodyLimits,
+    },
+    compression::compression,
+    cors::CorsPolicy,
+    custom_jwt_providers::{
+        create_custom_jwt_provider,
+        delete_custom_jwt_provider,
+        list_custom_jwt_providers,
+    },
     dashboard::{
         delete_component,
         delete_tables,
+        get_deployment_audit_log,
         get_indexes,
         get_source_code,
         run_test_function,
         shapes2,
     },
+    debugging::{
+        get_heap_snapshot,
+        heap_snapshot_function,
+        profile_function,
+        record_function,
+        replay_function,
+    },
+    dependency_layers::{
+        delete_dependency_layer,
+        list_dependency_layers,
+        upload_dependency_layer,
+    },
     deploy_config::{
         get_config,
         get_config_hashes,
         push_config,
     },
     deploy_config2,
-    environment_variables::update_environment_variables,
+    drain::{
+        drain_middleware,
+        get_drain_status,
+        start_drain,
+    },
+    environment_variables::{
+        list_environment_secrets,
+        update_environment_variables,
+    },
+    graphql::graphql_routes,
+    grpc::grpc_routes,
+    health::{
+        healthz,
+        healthz_ready,
+    },
     http_actions::http_action_handler,
+    ip_access::{
+        get_ip_access_policy,
+        ip_access_middleware,
+        put_ip_access_policy,
+    },
     logs::{
+        search_function_logs,
+        slow_function_logs,
         stream_function_logs,
         stream_udf_execution,
+        tail_function_logs,
     },
     node_action_callbacks::{
         action_callbacks_middleware,
@@ -96,8 +182,17 @@ use crate::{
         storage_generate_upload_url,
         storage_get_metadata,
         storage_get_url,
+        stream_log_lines,
         vector_search,
     },
+    openapi::openapi_get,
+    preview_deployments::{
+        create_preview_deployment,
+        delete_preview_deployment,
+        list_preview_deployments,
+        PreviewDeployments,
+        PreviewDeploymentsState,
+    },
     public_api::{
         public_action_post,
         public_function_post,
@@ -108,18 +203,46 @@ use crate::{
         public_query_batch_post,
         public_query_get,
         public_query_post,
+        public_query_subscribe_get,
+    },
+    rate_limit::rate_limit_middleware,
+    request_timeouts::request_timeout_middleware,
+    rest::rest_routes,
+    saml::{
+        create_saml_provider,
+        delete_saml_provider,
+        list_saml_providers,
+        saml_acs,
+        saml_metadata,
     },
     scheduling::{
         cancel_all_jobs,
         cancel_job,
+        cancel_workflow,
+        delete_dead_letter,
+        list_scheduler_pauses,
+        pause_scheduler,
+        rerun_dead_letter,
+        resume_scheduler,
     },
     schema::{
         prepare_schema,
         schema_state,
     },
+    service_accounts::{
+        create_service_account,
+        delete_service_account,
+        list_service_accounts,
+        service_account_token,
+    },
     snapshot_export::{
+        delete_export_schedule,
+        get_export_schedule,
         get_zip_export,
+        request_encrypted_export,
+        request_s3_export,
         request_zip_export,
+        set_export_schedule,
     },
     snapshot_import::{
         cancel_import,
@@ -133,7 +256,21 @@ use crate::{
         storage_get,
         storage_upload,
     },
-    subs::sync,
+    subs::{
+        stats::get_sync_stats,
+        sync,
+    },
+    tls::client_certificate_middleware,
+    token_exchange::exchange_token,
+    token_revocations::{
+        delete_token_revocation,
+        list_token_revocations,
+        revoke_tokens,
+    },
+    usage::{
+        usage_by_function,
+        usage_by_table,
+    },
     LocalAppState,
     RouterState,
 };
@@ -149,18 +286,89 @@ where
     request
 }
 
+/// All of the backend's routes.
 pub fn router(st: LocalAppState) -> Router {
-    let browser_routes = Router::new()
-        // Called by the browser (and optionally authenticated by a cookie or `Authorization`
-        // header). Passes version in the URL because websockets can't do it in header.
-        .route("/:client_version/sync", get(sync));
+    routes(st, true)
+}
 
+/// The routes without the dashboard, CLI and other admin routes, for the
+/// public listeners when `--admin-port` is set.
+pub fn public_router(st: LocalAppState) -> Router {
+    routes(st, false)
+}
+
+fn admin_api_routes(st: &LocalAppState) -> Router<LocalAppState> {
     let dashboard_routes = common_dashboard_routes()
         // Scheduled jobs routes
         .route("/cancel_all_jobs", post(cancel_all_jobs))
         .route("/cancel_job", post(cancel_job))
+        .route("/rerun_dead_letter", post(rerun_dead_letter))
+        .route("/delete_dead_letter", post(delete_dead_letter))
+        .route("/cancel_workflow", post(cancel_workflow))
+        .route("/pause_scheduler", post(pause_scheduler))
+        .route("/resume_scheduler", post(resume_scheduler))
+        .route("/scheduler_pauses", get(list_scheduler_pauses))
         // Environment variable routes
         .route("/update_environment_variables", post(update_environment_variables))
+        .route("/environment_secrets", get(list_environment_secrets))
+        // Debugging routes
+        .route("/profile_function", post(profile_function))
+        .route("/record_function", post(record_function))
+        .route("/replay_function", post(replay_function))
+        .route("/heap_snapshot_function", post(heap_snapshot_function))
+        .route("/heap_snapshots/:id", get(get_heap_snapshot))
+        // API key routes
+        .route("/api_keys", get(list_api_keys).post(create_api_key))
+        .route("/api_keys/:id", delete(revoke_api_key))
+        // Service account routes
+        .route(
+            "/service_accounts",
+            get(list_service_accounts).post(create_service_account),
+        )
+        .route("/service_accounts/token", post(service_account_token))
+        .route("/service_accounts/:id", delete(delete_service_account))
+        .route("/token_exchange", post(exchange_token))
+        // Custom JWT provider routes
+        .route(
+            "/custom_jwt_providers",
+            get(list_custom_jwt_providers).post(create_custom_jwt_provider),
+        )
+        .route("/custom_jwt_providers/:id", delete(delete_custom_jwt_provider))
+        // Token revocation routes
+        .route(
+            "/token_revocations",
+            get(list_token_revocations).post(revoke_tokens),
+        )
+        .route("/token_revocations/:id", delete(delete_token_revocation))
+        // SAML provider routes
+        .route(
+            "/saml_providers",
+            get(list_saml_providers).post(create_saml_provider),
+        )
+        .route("/saml_providers/:id", delete(delete_saml_provider))
+        // Access rule routes
+        .route("/access_rules", get(list_access_rules))
+        .route("/access_rules/evaluate", post(evaluate_access_rules))
+        .route(
+            "/access_rules/:table",
+            put(set_access_rules).delete(delete_access_rules),
+        )
+        // Admin key audit log routes
+        .route("/admin_key_audit_log", get(get_admin_key_audit_log))
+        // Admin role routes
+        .route("/admin_roles", get(list_admin_roles))
+        .route(
+            "/admin_roles/:member_id",
+            put(set_admin_role).delete(remove_admin_role),
+        )
+        // Sync protocol stats
+        .route("/sync_stats", get(get_sync_stats))
+        // Alert rule routes
+        .route(
+            "/alert_rules",
+            get(list_alert_rules).post(create_alert_rule),
+        )
+        .route("/alert_rules/:id", delete(delete_alert_rule))
         // Administrative routes for the dashboard
         .layer(ServiceBuilder::new());
 
@@ -188,74 +396,223 @@ pub fn router(st: LocalAppState) -> Router {
         )
         .route("/get_config", post(get_config))
         .route("/get_config_hashes", post(get_config_hashes))
+        .route("/dependency_layers", get(list_dependency_layers))
+        .route(
+            "/dependency_layers/:name",
+            put(upload_dependency_layer).delete(delete_dependency_layer),
+        )
         .route("/schema_state/:schema_id", get(schema_state))
         .route("/stream_udf_execution", get(stream_udf_execution))
         .route("/stream_function_logs", get(stream_function_logs))
-        .merge(import_routes())
+        .route("/slow_function_logs", get(slow_function_logs))
+        .merge(import_routes(st.body_limits))
         .layer(cli_cors());
 
     let snapshot_export_routes = Router::new()
         .route("/request/zip", post(request_zip_export))
-        .route("/zip/:id", get(get_zip_export));
+        .route("/request/s3", post(request_s3_export))
+        .route("/request/encrypted", post(request_encrypted_export))
+        .route("/zip/:id", get(get_zip_export))
+        .route(
+            "/schedule",
+            get(get_export_schedule)
+                .put(set_export_schedule)
+                .delete(delete_export_schedule),
+        );
 
-    let api_routes = Router::new()
+    Router::new()
         .merge(cli_routes)
         .merge(dashboard_routes)
-        .nest(
-            "/actions",
-            action_callback_routes().layer(axum::middleware::map_request_with_state(
-                st.clone(),
-                add_extension::<LocalAppState, _>,
-            )),
+        .nest("/export", snapshot_export_routes)
+        .route(
+            "/ip_access_policy",
+            get(get_ip_access_policy).put(put_ip_access_policy),
         )
-        .nest("/export", snapshot_export_routes);
+        .route("/drain", get(get_drain_status).post(start_drain))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            admin_key_audit_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            ip_access_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.require_client_certificate,
+            client_certificate_middleware,
+        ))
+}
+
+fn routes(st: LocalAppState, include_admin_routes: bool) -> Router {
+    let browser_routes = Router::new()
+        // Called by the browser (and optionally authenticated by a cookie or `Authorization`
+        // header). Passes version in the URL because websockets can't do it in header.
+        .route("/:client_version/sync", get(sync));
+
+    let mut api_routes = Router::new().nest(
+        "/actions",
+        action_callback_routes().layer(axum::middleware::map_request_with_state(
+            st.clone(),
+            add_extension::<LocalAppState, _>,
+        )),
+    );
+    if include_admin_routes {
+        api_routes = api_routes.merge(admin_api_routes(&st));
+    }
+    if *ANONYMOUS_IDENTITIES_ENABLED {
+        api_routes = api_routes
+            .route("/auth/anonymous", post(issue_anonymous_identity))
+            .route("/auth/anonymous/upgrade", post(upgrade_anonymous_identity));
+    }
+    api_routes = api_routes
+        .route("/saml/metadata", get(saml_metadata))
+        .route("/saml/acs", post(saml_acs));
+    let auth_lockout = axum::middleware::from_fn_with_state(st.clone(), auth_lockout_middleware);
+    api_routes = api_routes.layer(auth_lockout.clone());
 
     // Endpoints migrated to use the RouterState trait instead of application.
     let migrated_api_routes = Router::new()
         .merge(browser_routes)
-        .merge(public_api_routes())
-        .nest("/storage", storage_api_routes());
-    let migrated = Router::new()
-        .nest("/api", migrated_api_routes)
-        .layer(cors())
+        .merge(public_api_routes(st.body_limits))
+        .nest("/storage", storage_api_routes(st.body_limits));
+    let router_state = RouterState {
+        api: Arc::new(st.application.clone()),
+        runtime: st.application.runtime().clone(),
+        rate_limits: st.rate_limits.clone(),
+        trusted_proxies: st.trusted_proxies.clone(),
+        resumable_sessions: ResumableSessions::new(),
+        sync_stats: st.sync_stats.clone(),
+    };
+    let rate_limit =
+        axum::middleware::from_fn_with_state(router_state.clone(), rate_limit_middleware);
+    let mut http_actions = http_action_routes(st.body_limits).layer(rate_limit.clone());
+    if st.cors_policy.http_actions {
+        http_actions = http_actions.layer(cors(&st.cors_policy));
+    }
+    let mut migrated_routes = Router::new().nest("/api", migrated_api_routes);
+    if *GRPC_API_ENABLED {
+        migrated_routes = migrated_routes.merge(grpc_routes(
+            router_state.clone(),
+            st.body_limits.function_args,
+        ));
+    }
+    let migrated = migrated_routes
+        // Rate limit and lock out inside CORS, so browsers can read the 429
+        // responses.
+        .layer(rate_limit)
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            admin_key_audit_middleware,
+        ))
+        .layer(auth_lockout)
+        .layer(cors(&st.cors_policy))
+        .layer(compression())
         // Order matters. Layers only apply to routes above them.
         // Notably, any layers added here won't apply to common routes
         // added inside `serve_http`
-        .nest("/http/", http_action_routes())
-        .with_state(RouterState {
-            api: Arc::new(st.application.clone()),
-            runtime: st.application.runtime().clone(),
-        });
+        .nest("/http/", http_actions)
+        .with_state(router_state);
 
     let version = SERVER_VERSION_STR.to_string();
+    let drain = st.drain.clone();
+    let request_timeouts = st.request_timeouts.clone();
 
     Router::new()
         .nest("/api", api_routes)
         .merge(health_check_routes(version))
-        .layer(cors())
+        // The dashboard and CLI call these routes, so they keep the default policy.
+        .layer(cors(&CorsPolicy::default()))
+        .layer(compression())
         .with_state(st)
         .merge(migrated)
+        .layer(axum::middleware::from_fn_with_state(
+            request_timeouts,
+            request_timeout_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            drain,
+            drain_middleware,
+        ))
 }
 
-pub fn public_api_routes() -> Router<RouterState> {
+/// Prometheus metrics for the public listener when there's no admin listener
+/// to serve them. Unlike on the admin listener, they need an admin key.
+pub fn admin_metrics_routes(st: LocalAppState) -> Router {
     Router::new()
+        .route("/metrics", get(prometheus_metrics))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            auth_lockout_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            admin_key_audit_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            ip_access_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.require_client_certificate,
+            client_certificate_middleware,
+        ))
+        .with_state(st)
+}
+
+/// Routes to manage preview deployments, served with the admin routes.
+pub fn preview_deployment_routes(st: LocalAppState, previews: Arc<PreviewDeployments>) -> Router {
+    let routes = Router::new()
+        .route("/create", post(create_preview_deployment))
+        .route("/list", get(list_preview_deployments))
+        .route("/delete", post(delete_preview_deployment))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            admin_key_audit_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.clone(),
+            ip_access_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            st.require_client_certificate,
+            client_certificate_middleware,
+        ))
+        .layer(cors(&CorsPolicy::default()));
+    Router::new()
+        .nest("/api/preview_deployments", routes)
+        .with_state(PreviewDeploymentsState { st, previews })
+}
+
+pub fn public_api_routes(body_limits: BodyLimits) -> Router<RouterState> {
+    let mut routes = Router::new();
+    if *GRAPHQL_API_ENABLED {
+        routes = routes.merge(graphql_routes());
+    }
+    if *REST_API_ENABLED {
+        routes = routes.merge(rest_routes());
+    }
+    routes
         .route("/sync", get(sync))
         .route("/query", get(public_query_get))
         .route("/query", post(public_query_post))
         .route("/query_at_ts", post(public_query_at_ts_post))
         .route("/query_ts", post(public_get_query_ts))
         .route("/query_batch", post(public_query_batch_post))
+        .route("/query_subscribe", get(public_query_subscribe_get))
         .route("/mutation", post(public_mutation_post))
         .route("/action", post(public_action_post))
         .route("/function", post(public_function_post))
         .route("/run/*rest", post(public_function_post_with_path))
-        .layer(DefaultBodyLimit::max(*MAX_BACKEND_PUBLIC_API_REQUEST_SIZE))
+        .route("/openapi.json", get(openapi_get))
+        .layer(body_limit(body_limits.function_args))
 }
 
-pub fn storage_api_routes() -> Router<RouterState> {
-    Router::new()
-        .route("/upload", post(storage_upload))
-        .route("/:storage_id", get(storage_get))
+pub fn storage_api_routes(body_limits: BodyLimits) -> Router<RouterState> {
+    let mut upload_routes = Router::new().route("/upload", post(storage_upload));
+    if let Some(max_bytes) = body_limits.file_upload {
+        upload_routes = upload_routes.layer(body_limit(max_bytes));
+    }
+    upload_routes.route("/:storage_id", get(storage_get))
 }
 
 // IMPORTANT NOTE: Those routes are proxied by Usher. Any changes to the router,
@@ -274,6 +631,7 @@ where
         .route("/vector_search", post(vector_search))
         .route("/cancel_job", post(cancel_developer_job))
         .route("/create_function_handle", post(create_function_handle))
+        .route("/log_lines", post(stream_log_lines))
         // file storage endpoints
         .route("/storage_generate_upload_url", post(storage_generate_upload_url))
         .route("/storage_get_url", post(storage_get_url))
@@ -284,25 +642,29 @@ where
         .layer(axum::middleware::from_fn(action_callbacks_middleware))
 }
 
-pub fn import_routes<S>() -> Router<S>
+pub fn import_routes<S>(body_limits: BodyLimits) -> Router<S>
 where
     LocalAppState: FromRef<S>,
     S: Clone + Send + Sync + 'static,
 {
-    Router::new()
+    let routes = Router::new()
         .route("/import", post(import))
         .route("/import/start_upload", post(import_start_upload))
         .route("/import/upload_part", post(import_upload_part))
         .route("/import/finish_upload", post(import_finish_upload))
         .route("/perform_import", post(perform_import))
-        .route("/cancel_import", post(cancel_import))
+        .route("/cancel_import", post(cancel_import));
+    match body_limits.snapshot_import {
+        Some(max_bytes) => routes.layer(body_limit(max_bytes)),
+        None => routes,
+    }
 }
 
-pub fn http_action_routes() -> Router<RouterState> {
+pub fn http_action_routes(body_limits: BodyLimits) -> Router<RouterState> {
     Router::new()
         .route("/*rest", http_action_handler())
         .route("/", http_action_handler())
-        .layer(DefaultBodyLimit::max(HTTP_ACTION_BODY_LIMIT))
+        .layer(body_limit(body_limits.http_actions))
 }
 
 pub fn app_metrics_routes<S>() -> Router<S>
@@ -313,6 +675,11 @@ where
     Router::new()
         .route("/stream_udf_execution", get(stream_udf_execution))
         .route("/stream_function_logs", get(stream_function_logs))
+        .route("/slow_function_logs", get(slow_function_logs))
+        .route("/search_function_logs", get(search_function_logs))
+        .route("/tail_function_logs", get(tail_function_logs))
+        .route("/error_groups", get(error_groups))
+        .route("/occ_conflicts", get(occ_conflicts))
         .route("/udf_rate", get(udf_rate))
         .route("/failure_percentage_top_k", get(failure_percentage_top_k))
         .route(
@@ -321,8 +688,12 @@ where
         )
         .route("/cache_hit_percentage", get(cache_hit_percentage))
         .route("/table_rate", get(table_rate))
+        .route("/table_metrics", get(table_metrics))
         .route("/latency_percentiles", get(latency_percentiles))
+        .route("/execution_profile", get(execution_profile))
         .route("/scheduled_job_lag", get(scheduled_job_lag))
+        .route("/usage_by_function", get(usage_by_function))
+        .route("/usage_by_table", get(usage_by_table))
 }
 
 // Routes with the same handlers for the local backend + closed source backend
@@ -337,6 +708,7 @@ where
         .route("/delete_tables", post(delete_tables))
         .route("/delete_component", post(delete_component))
         .route("/get_source_code", get(get_source_code))
+        .route("/deployment_audit_log", get(get_deployment_audit_log))
         // Metrics routes
         .nest("/app_metrics", app_metrics_routes())
 }
@@ -352,6 +724,8 @@ where
             get(|State(st): State<LocalAppState>| async move { st.instance_name.clone() }),
         )
         .route("/instance_version", get(|| async move { version }))
+        .route("/healthz", get(healthz))
+        .route("/healthz/ready", get(healthz_ready))
         .route(
             "/",
             get(|| async { "This Convex deployment is running. See https://docs.convex.dev/." }),
@@ -362,23 +736,26 @@ where
         // Limit requests to 128MiB to help mitigate DDoS attacks.
         .layer(DefaultBodyLimit::max(*MAX_ECHO_BYTES)),
         )
-        .layer(cors())
+        .layer(cors(&CorsPolicy::default()))
 }
 
-pub fn cors() -> CorsLayer {
+pub fn cors(policy: &CorsPolicy) -> CorsLayer {
+    let mut allowed_headers = vec![
+        "baggage".parse().unwrap(),
+        "sentry-trace".parse().unwrap(),
+        ACCEPT,
+        ACCEPT_LANGUAGE,
+        AUTHORIZATION,
+        CONTENT_TYPE,
+        CONVEX_CLIENT_HEADER,
+        REFERER,
+        USER_AGENT,
+    ];
+    allowed_headers.extend(policy.allowed_headers.iter().cloned());
+    let policy = policy.clone();
     CorsLayer::new()
-        .allow_headers(vec![
-           "baggage".parse().unwrap(),
-           "sentry-trace".parse().unwrap(),
-           ACCEPT,
-           ACCEPT_LANGUAGE,
-           AUTHORIZATION,
-           CONTENT_TYPE,
-           CONVEX_CLIENT_HEADER,
-           REFERER,
-           USER_AGENT,
-        ])
-        .allow_credentials(true)
+        .allow_headers(allowed_headers)
+        .allow_credentials(policy.allow_credentials)
         .allow_methods(vec![
             Method::GET,
             Method::POST,
@@ -396,10 +773,78 @@ pub fn cors() -> CorsLayer {
         // Instead respond with Access-Control-Allow-Origin set to the submitted Origin header.
         //
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Access-Control-Allow-Origin#directives
-        .allow_origin(
-            AllowOrigin::predicate(|_origin: &HeaderValue, _request_head: &request::Parts| {
-                true
-            }),
-        )
+        .allow_origin(AllowOrigin::predicate(
+            move |origin: &HeaderValue, _request_head: &request::Parts| {
+                policy.allows_origin(origin)
+            },
+        ))
         .max_age(Duration::from_secs(86400))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        Router,
+    };
+    use axum_extra::headers::authorization::Credentials;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use runtime::prod::ProdRuntime;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::{
+        public_router,
+        router,
+    };
+    use crate::test_helpers::setup_backend_for_test;
+
+    async fn status(router: Router, req: Request<Body>) -> anyhow::Result<StatusCode> {
+        Ok(router.oneshot(req).await?.status())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_public_router_excludes_admin_routes(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend.st.application.load_udf_tests_modules().await?;
+        let admin_request = || {
+            Request::builder()
+                .uri("/api/scheduler_pauses")
+                .method("GET")
+                .header("Host", "localhost")
+                .header("Authorization", backend.admin_auth_header.0.encode())
+                .body(Body::empty())
+        };
+        let query_request = || -> anyhow::Result<Request<Body>> {
+            let json_body = json!({"path": "values:intQuery", "args": {}});
+            Ok(Request::builder()
+                .uri("/api/query")
+                .method("POST")
+                .header("Content-Type", "application/json")
+                .header("Host", "localhost")
+                .body(Body::from(serde_json::to_vec(&json_body)?))?)
+        };
+
+        // With `--admin-port`, only the admin listener serves admin routes.
+        assert_eq!(
+            status(router(backend.st.clone()), admin_request()?).await?,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(public_router(backend.st.clone()), admin_request()?).await?,
+            StatusCode::NOT_FOUND
+        );
+
+        // Both serve the public API.
+        for router in [
+            router(backend.st.clone()),
+            public_router(backend.st.clone()),
+        ] {
+            assert_eq!(status(router, query_request()?).await?, StatusCode::OK);
+        }
+        Ok(())
+    }
+}
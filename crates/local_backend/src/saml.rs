@@ -0,0 +1,201 @@
+//! Endpoints for SAML 2.0 single sign-on. The deployment acts as a service
+//! provider: identity providers post signed assertions to the assertion
+//! consumer service, which exchanges them for tokens users authenticate with
+//! like tokens from a custom JWT provider.
+use std::collections::BTreeMap;
+
+use authentication::saml::service_provider_metadata;
+use axum::{
+    extract::State,
+    response::IntoResponse,
+    Form,
+};
+use common::{
+    auth::SamlProvider,
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+    runtime::Runtime,
+};
+use http::{
+    header::CONTENT_TYPE,
+    StatusCode,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct SamlProviderPath {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSamlProviderArgs {
+    entity_id: String,
+    /// The identity provider's PEM-encoded signing certificate.
+    certificate: String,
+    /// Maps identity fields (e.g. `email`) to the assertion attributes they're
+    /// read from.
+    #[serde(default)]
+    attribute_mapping: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamlProviderJson {
+    id: String,
+    entity_id: String,
+    certificate: String,
+    attribute_mapping: BTreeMap<String, String>,
+}
+
+impl From<ParsedDocument<SamlProvider>> for SamlProviderJson {
+    fn from(provider: ParsedDocument<SamlProvider>) -> Self {
+        let id = provider.id().developer_id.encode();
+        let provider = provider.into_value();
+        Self {
+            id,
+            entity_id: provider.entity_id,
+            certificate: provider.certificate,
+            attribute_mapping: provider.attribute_mapping,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListSamlProvidersResponse {
+    providers: Vec<SamlProviderJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSamlProviderResponse {
+    id: String,
+}
+
+/// The form identity providers post with the HTTP-POST binding.
+#[derive(Deserialize)]
+pub struct SamlAcsArgs {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamlAcsResponse {
+    token: String,
+    expires_in: u64,
+    relay_state: Option<String>,
+}
+
+fn service_provider_entity_id(st: &LocalAppState) -> String {
+    format!("{}/api/saml/metadata", st.origin)
+}
+
+fn assertion_consumer_service_url(st: &LocalAppState) -> String {
+    format!("{}/api/saml/acs", st.origin)
+}
+
+pub async fn list_saml_providers(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let providers = st
+        .application
+        .list_saml_providers(identity)
+        .await?
+        .into_iter()
+        .map(SamlProviderJson::from)
+        .collect();
+    Ok(Json(ListSamlProvidersResponse { providers }))
+}
+
+pub async fn create_saml_provider(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(CreateSamlProviderArgs {
+        entity_id,
+        certificate,
+        attribute_mapping,
+    }): Json<CreateSamlProviderArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let provider = SamlProvider {
+        entity_id,
+        certificate,
+        attribute_mapping,
+    };
+    let id = st
+        .application
+        .create_saml_provider(identity, provider)
+        .await?;
+    Ok(Json(CreateSamlProviderResponse { id }))
+}
+
+pub async fn delete_saml_provider(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(SamlProviderPath { id }): Path<SamlProviderPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    st.application.delete_saml_provider(identity, &id).await?;
+    Ok(StatusCode::OK)
+}
+
+/// The deployment's service provider metadata, for configuring identity
+/// providers. Doesn't require authentication.
+pub async fn saml_metadata(
+    State(st): State<LocalAppState>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let metadata = service_provider_metadata(
+        &service_provider_entity_id(&st),
+        &assertion_consumer_service_url(&st),
+    );
+    Ok(([(CONTENT_TYPE, "application/samlmetadata+xml")], metadata))
+}
+
+/// The assertion consumer service, which exchanges a signed assertion for a
+/// token. Doesn't require authentication.
+pub async fn saml_acs(
+    State(st): State<LocalAppState>,
+    Form(SamlAcsArgs {
+        saml_response,
+        relay_state,
+    }): Form<SamlAcsArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let (token, expiration) = st
+        .application
+        .complete_saml_sign_in(
+            &saml_response,
+            &service_provider_entity_id(&st),
+            &assertion_consumer_service_url(&st),
+        )
+        .await?;
+    let now = st.application.runtime().system_time();
+    Ok(Json(SamlAcsResponse {
+        token,
+        expires_in: expiration.duration_since(now).unwrap_or_default().as_secs(),
+        relay_state,
+    }))
+}
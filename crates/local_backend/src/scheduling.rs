@@ -10,25 +10,45 @@ use common::{
         ComponentId,
         ComponentPath,
     },
+    execution_context::ExecutionContext,
     http::{
         extract::Json,
+        ExtractClientVersion,
+        ExtractRequestId,
         HttpResponseError,
     },
+    types::FunctionCaller,
 };
 use errors::ErrorMetadata;
 use http::StatusCode;
-use model::scheduled_jobs::{
-    SchedulerModel,
-    SCHEDULED_JOBS_TABLE,
+use model::{
+    scheduled_jobs::{
+        dead_letters::{
+            ScheduledJobDeadLettersModel,
+            SCHEDULED_JOB_DEAD_LETTERS_TABLE,
+        },
+        pauses::SchedulerPausesModel,
+        types::ScheduledJobFilter,
+        SchedulerModel,
+        SCHEDULED_JOBS_TABLE,
+    },
+    workflows::{
+        WorkflowModel,
+        WORKFLOWS_TABLE,
+    },
 };
 use serde::{
     Deserialize,
     Serialize,
 };
+use serde_json::Value as JsonValue;
 use value::TableNamespace;
 
 use crate::{
-    admin::must_be_admin_member_with_write_access,
+    admin::{
+        must_be_admin,
+        must_be_admin_member_with_write_access,
+    },
     authentication::ExtractIdentity,
     parse::parse_document_id,
     LocalAppState,
@@ -44,6 +64,19 @@ pub struct CancelAllJobsRequest {
     /// happen if a function is scheduled from a different component.
     pub component_path: Option<String>,
     pub udf_path: Option<String>,
+    /// Only cancel jobs whose arguments object has these fields, with equal
+    /// values, in Convex's JSON format.
+    pub args_match: Option<JsonValue>,
+    /// Only cancel jobs that are due in this range, in milliseconds since the
+    /// Unix epoch. The start is inclusive and the end exclusive.
+    pub scheduled_after_ms: Option<f64>,
+    pub scheduled_before_ms: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelAllJobsResponse {
+    pub canceled: usize,
 }
 
 #[debug_handler]
@@ -54,6 +87,9 @@ pub async fn cancel_all_jobs(
         component_id,
         udf_path,
         component_path,
+        args_match,
+        scheduled_after_ms,
+        scheduled_before_ms,
     }): Json<CancelAllJobsRequest>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     must_be_admin_member_with_write_access(&identity)?;
@@ -73,11 +109,14 @@ pub async fn cancel_all_jobs(
             udf_path,
         }),
     };
-    st.application
-        .cancel_all_jobs(component_id, path, identity)
+    let filter =
+        ScheduledJobFilter::new(path, args_match, scheduled_after_ms, scheduled_before_ms)?;
+    let canceled = st
+        .application
+        .cancel_all_jobs(component_id, filter, identity)
         .await?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(CancelAllJobsResponse { canceled }))
 }
 
 #[derive(Deserialize, Serialize)]
@@ -116,3 +155,288 @@ pub async fn cancel_job(
 
     Ok(StatusCode::OK)
 }
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetterRequest {
+    pub id: String,
+    pub component_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RerunDeadLetterResponse {
+    /// The `_scheduled_jobs` id of the job that runs the dead letter again.
+    pub job_id: String,
+}
+
+#[debug_handler]
+pub async fn rerun_dead_letter(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    ExtractRequestId(request_id): ExtractRequestId,
+    ExtractClientVersion(client_version): ExtractClientVersion,
+    Json(request): Json<DeadLetterRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_member_with_write_access(&identity)?;
+    let component_id = ComponentId::deserialize_from_string(request.component_id.as_deref())?;
+    let context = ExecutionContext::new(request_id, &FunctionCaller::HttpApi(client_version));
+    let job_id = st
+        .application
+        .execute_with_audit_log_events_and_occ_retries(
+            identity.clone(),
+            "rerun_dead_letter",
+            |tx| {
+                async {
+                    let namespace = TableNamespace::from(component_id);
+                    let id = parse_document_id(
+                        &request.id,
+                        &tx.table_mapping().namespace(namespace),
+                        &SCHEDULED_JOB_DEAD_LETTERS_TABLE,
+                    )?;
+                    let job_id = ScheduledJobDeadLettersModel::new(tx, namespace)
+                        .rerun(id, context.clone())
+                        .await?
+                        .context(ErrorMetadata::not_found(
+                            "DeadLetterNotFound",
+                            format!("Dead letter {} not found", request.id),
+                        ))?;
+                    Ok((job_id, vec![]))
+                }
+                .into()
+            },
+        )
+        .await?;
+
+    Ok(Json(RerunDeadLetterResponse {
+        job_id: job_id.developer_id.encode(),
+    }))
+}
+
+#[debug_handler]
+pub async fn delete_dead_letter(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(request): Json<DeadLetterRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_member_with_write_access(&identity)?;
+    let component_id = ComponentId::deserialize_from_string(request.component_id.as_deref())?;
+    st.application
+        .execute_with_audit_log_events_and_occ_retries(
+            identity.clone(),
+            "delete_dead_letter",
+            |tx| {
+                async {
+                    let namespace = TableNamespace::from(component_id);
+                    let id = parse_document_id(
+                        &request.id,
+                        &tx.table_mapping().namespace(namespace),
+                        &SCHEDULED_JOB_DEAD_LETTERS_TABLE,
+                    )?;
+                    let mut model = ScheduledJobDeadLettersModel::new(tx, namespace);
+                    if model.get(id).await?.is_some() {
+                        model.delete(id).await?;
+                    }
+                    Ok(((), vec![]))
+                }
+                .into()
+            },
+        )
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelWorkflowRequest {
+    pub id: String,
+    pub component_id: Option<String>,
+}
+
+#[debug_handler]
+pub async fn cancel_workflow(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(request): Json<CancelWorkflowRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_member_with_write_access(&identity)?;
+    let component_id = ComponentId::deserialize_from_string(request.component_id.as_deref())?;
+    st.application
+        .execute_with_audit_log_events_and_occ_retries(identity.clone(), "cancel_workflow", |tx| {
+            async {
+                let namespace = TableNamespace::from(component_id);
+                let id = parse_document_id(
+                    &request.id,
+                    &tx.table_mapping().namespace(namespace),
+                    &WORKFLOWS_TABLE,
+                )?;
+                WorkflowModel::new(tx, namespace)
+                    .cancel(id.developer_id)
+                    .await?;
+                Ok(((), vec![]))
+            }
+            .into()
+        })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseSchedulerRequest {
+    /// component_path and udf_path are the function whose scheduled jobs and
+    /// cron jobs to pause. Omit both to pause every job.
+    pub component_path: Option<String>,
+    pub udf_path: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseSchedulerResponse {
+    /// False if the jobs were already paused.
+    pub paused: bool,
+}
+
+/// Pauses running scheduled jobs and cron jobs, either of one function or all
+/// of them, until they're resumed. Jobs that are already running finish. The
+/// pause is stored in the database, so it survives restarts.
+#[debug_handler]
+pub async fn pause_scheduler(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(PauseSchedulerRequest {
+        component_path,
+        udf_path,
+        reason,
+    }): Json<PauseSchedulerRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_member_with_write_access(&identity)?;
+    let path = parse_paused_function(component_path, udf_path)?;
+    let paused = st
+        .application
+        .execute_with_audit_log_events_and_occ_retries(identity.clone(), "pause_scheduler", |tx| {
+            async {
+                let paused = SchedulerPausesModel::new(tx)
+                    .pause(path.clone(), reason.clone())
+                    .await?;
+                Ok((paused, vec![]))
+            }
+            .into()
+        })
+        .await?;
+
+    Ok(Json(PauseSchedulerResponse { paused }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeSchedulerRequest {
+    /// The function to resume, as passed to `/pause_scheduler`. Omit both to
+    /// lift the pause of every job, which leaves pauses of single functions in
+    /// place.
+    pub component_path: Option<String>,
+    pub udf_path: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeSchedulerResponse {
+    /// False if the jobs weren't paused.
+    pub resumed: bool,
+}
+
+#[debug_handler]
+pub async fn resume_scheduler(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(ResumeSchedulerRequest {
+        component_path,
+        udf_path,
+    }): Json<ResumeSchedulerRequest>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_member_with_write_access(&identity)?;
+    let path = parse_paused_function(component_path, udf_path)?;
+    let resumed = st
+        .application
+        .execute_with_audit_log_events_and_occ_retries(identity.clone(), "resume_scheduler", |tx| {
+            async {
+                let resumed = SchedulerPausesModel::new(tx).resume(path.clone()).await?;
+                Ok((resumed, vec![]))
+            }
+            .into()
+        })
+        .await?;
+
+    Ok(Json(ResumeSchedulerResponse { resumed }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerPauseJson {
+    /// Both are null if every job is paused.
+    pub component_path: Option<String>,
+    pub udf_path: Option<String>,
+    pub reason: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub paused_at: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListSchedulerPausesResponse {
+    pub pauses: Vec<SchedulerPauseJson>,
+}
+
+#[debug_handler]
+pub async fn list_scheduler_pauses(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let mut tx = st.application.begin(identity).await?;
+    let pauses = SchedulerPausesModel::new(&mut tx)
+        .list()
+        .await?
+        .into_iter()
+        .map(|pause| {
+            let pause = pause.into_value();
+            SchedulerPauseJson {
+                component_path: pause
+                    .path
+                    .as_ref()
+                    .map(|path| String::from(path.component.clone())),
+                udf_path: pause.path.map(|path| String::from(path.udf_path)),
+                reason: pause.reason,
+                paused_at: i64::from(pause.paused_ts) / 1_000_000,
+            }
+        })
+        .collect();
+    Ok(Json(ListSchedulerPausesResponse { pauses }))
+}
+
+fn parse_paused_function(
+    component_path: Option<String>,
+    udf_path: Option<String>,
+) -> anyhow::Result<Option<CanonicalizedComponentFunctionPath>> {
+    let Some(udf_path) = udf_path else {
+        anyhow::ensure!(
+            component_path.is_none(),
+            ErrorMetadata::bad_request(
+                "InvalidUdfPath",
+                "componentPath requires a udfPath to pause or resume",
+            )
+        );
+        return Ok(None);
+    };
+    let udf_path = udf_path.parse().context(ErrorMetadata::bad_request(
+        "InvalidUdfPath",
+        "Pausing a function requires a canonicalized UdfPath",
+    ))?;
+    Ok(Some(CanonicalizedComponentFunctionPath {
+        component: ComponentPath::deserialize(component_path.as_deref())?,
+        udf_path,
+    }))
+}
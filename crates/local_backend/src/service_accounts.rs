@@ -0,0 +1,197 @@
+//! Endpoints for managing service accounts, and the token endpoint they
+//! exchange their credentials at for short-lived access tokens. Tokens are
+//! sent like admin keys, in an `Authorization: Convex <token>` header.
+use authentication::service_account::{
+    ClientCredential,
+    CLIENT_ASSERTION_TYPE_JWT_BEARER,
+};
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use keybroker::AdminRole;
+use model::service_accounts::types::{
+    ServiceAccount,
+    ServiceAccountCredential,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_owner,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct ServiceAccountPath {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateServiceAccountArgs {
+    name: String,
+    role: String,
+    /// A public JWK to verify client assertions with. Accounts without one
+    /// get a client secret.
+    public_key: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccountJson {
+    id: String,
+    name: String,
+    role: String,
+    credential_type: &'static str,
+}
+
+impl From<ParsedDocument<ServiceAccount>> for ServiceAccountJson {
+    fn from(account: ParsedDocument<ServiceAccount>) -> Self {
+        let id = account.id().developer_id.encode();
+        let account = account.into_value();
+        Self {
+            id,
+            name: account.name,
+            role: account.role.to_string(),
+            credential_type: match account.credential {
+                ServiceAccountCredential::ClientSecret { .. } => "clientSecret",
+                ServiceAccountCredential::PublicKey { .. } => "publicKey",
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListServiceAccountsResponse {
+    service_accounts: Vec<ServiceAccountJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateServiceAccountResponse {
+    id: String,
+    /// Only returned when the account is created.
+    client_secret: Option<String>,
+}
+
+/// Arguments for the OAuth client credentials grant.
+#[derive(Deserialize)]
+pub struct TokenArgs {
+    grant_type: String,
+    client_id: String,
+    client_secret: Option<String>,
+    client_assertion_type: Option<String>,
+    client_assertion: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenResponse {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: u64,
+}
+
+pub async fn list_service_accounts(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let accounts = st.application.list_service_accounts(identity).await?;
+    Ok(Json(ListServiceAccountsResponse {
+        service_accounts: accounts.into_iter().map(ServiceAccountJson::from).collect(),
+    }))
+}
+
+pub async fn create_service_account(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(CreateServiceAccountArgs {
+        name,
+        role,
+        public_key,
+    }): Json<CreateServiceAccountArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    let role: AdminRole = role.parse()?;
+    let jwk = public_key.map(|jwk| jwk.to_string());
+    let (id, client_secret) = st
+        .application
+        .create_service_account(identity, name, role, jwk)
+        .await?;
+    Ok(Json(CreateServiceAccountResponse { id, client_secret }))
+}
+
+pub async fn delete_service_account(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(ServiceAccountPath { id }): Path<ServiceAccountPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_owner(&identity)?;
+    st.application.delete_service_account(identity, &id).await?;
+    Ok(StatusCode::OK)
+}
+
+/// Exchanges a service account's client secret or client assertion for an
+/// access token. Doesn't require an admin key.
+pub async fn service_account_token(
+    State(st): State<LocalAppState>,
+    Json(args): Json<TokenArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    if args.grant_type != "client_credentials" {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "UnsupportedGrantType",
+            "Only the `client_credentials` grant type is supported",
+        ))
+        .into());
+    }
+    let credential = match (
+        args.client_secret,
+        args.client_assertion_type.as_deref(),
+        args.client_assertion,
+    ) {
+        (Some(secret), None, None) => ClientCredential::Secret(secret),
+        (None, Some(CLIENT_ASSERTION_TYPE_JWT_BEARER), Some(assertion)) => {
+            ClientCredential::Assertion(assertion)
+        },
+        _ => {
+            return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+                "InvalidClientCredentials",
+                format!(
+                    "Expected either `client_secret`, or `client_assertion` with a \
+                     `client_assertion_type` of `{CLIENT_ASSERTION_TYPE_JWT_BEARER}`"
+                ),
+            ))
+            .into())
+        },
+    };
+    let (token, ttl) = st
+        .application
+        .issue_service_account_token(&args.client_id, credential)
+        .await?;
+    Ok(Json(TokenResponse {
+        access_token: token.as_string(),
+        // Tokens are sent like admin keys, not OAuth bearer tokens.
+        token_type: "Convex",
+        expires_in: ttl.as_secs(),
+    }))
+}
@@ -1,4 +1,11 @@
-use std::time::Duration;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    str::FromStr,
+    time::Duration,
+};
 
 use anyhow::Context;
 use axum::{
@@ -18,6 +25,7 @@ use common::{
     components::ComponentId,
     http::{
         extract::{
+            Json,
             Path,
             Query,
         },
@@ -26,15 +34,34 @@ use common::{
 };
 use either::Either;
 use errors::ErrorMetadata;
-use http::StatusCode;
-use model::exports::types::{
-    ExportFormat,
-    ExportRequestor,
+use http::{
+    header::CONTENT_TYPE,
+    StatusCode,
+};
+use keybroker::Identity;
+use model::{
+    cron_jobs::types::CronSchedule,
+    exports::types::{
+        Export,
+        ExportDestination,
+        ExportEncryption,
+        ExportFilter,
+        ExportFormat,
+        ExportRequestor,
+        S3Credentials,
+    },
+};
+use serde::{
+    Deserialize,
+    Serialize,
 };
-use serde::Deserialize;
 use storage::StorageGetStream;
 use sync_types::Timestamp;
-use value::DeveloperDocumentId;
+use value::{
+    DeveloperDocumentId,
+    IdentifierFieldName,
+    TableName,
+};
 
 use crate::{
     admin::must_be_admin_with_write_access,
@@ -46,35 +73,530 @@ use crate::{
 // Export GETs are immutable. Browser can cache for a long time.
 const MAX_CACHE_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
 
+/// The format of the files in the export's zip.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RequestedExportFormat {
+    #[default]
+    Jsonl,
+    Parquet,
+    Csv,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestZipExport {
     #[serde(default)]
     pub include_storage: bool,
+    /// Whether to list each file in file storage with a signed URL to
+    /// download it, so importing the export restores the files without them
+    /// being in the zip.
+    #[serde(default)]
+    pub storage_urls: bool,
+    #[serde(default)]
+    pub format: RequestedExportFormat,
+    /// Comma separated fields to put first in each table of a CSV export.
+    pub field_order: Option<String>,
     pub component: Option<String>,
+    /// If set, the export is incremental, and only has the changes since
+    /// this export.
+    pub previous_export_id: Option<String>,
+    /// Comma separated tables to export. Other tables are left out.
+    pub include_tables: Option<String>,
+    /// Comma separated tables to leave out.
+    pub exclude_tables: Option<String>,
+    /// Comma separated paths of the components to export, like
+    /// `waitlist,waitlist/ratelimiter`. The root component's path is empty.
+    pub include_components: Option<String>,
+    /// Comma separated paths of the components to leave out.
+    pub exclude_components: Option<String>,
+    /// Comma separated `<table>.<field>` fields to drop from the exported
+    /// documents, like `users.email,users.phone`.
+    pub exclude_fields: Option<String>,
+}
+
+/// An S3 bucket to upload an export to, in addition to the deployment's
+/// exports storage. Exactly one of an access key or a role to assume must be
+/// given.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3ExportDestination {
+    /// `s3://<bucket>/<key prefix>`
+    pub s3_uri: String,
+    pub region: String,
+    /// S3 API endpoint, for S3-compatible object stores.
+    pub endpoint: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub role_arn: Option<String>,
+    pub external_id: Option<String>,
+}
+
+/// How to encrypt an export. Exactly one of the age public keys (`age1...`)
+/// to encrypt to or a passphrase must be given. The export is an age file of
+/// the zip, which decrypts with `age --decrypt`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportEncryptionArg {
+    pub public_keys: Option<Vec<String>>,
+    pub passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestS3Export {
+    #[serde(flatten)]
+    pub export: RequestZipExport,
+    pub destination: S3ExportDestination,
+    pub encryption: Option<ExportEncryptionArg>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestEncryptedExport {
+    #[serde(flatten)]
+    pub export: RequestZipExport,
+    pub encryption: ExportEncryptionArg,
 }
 
 #[fastrace::trace]
 pub async fn request_zip_export(
     State(st): State<LocalAppState>,
     ExtractIdentity(identity): ExtractIdentity,
-    Query(RequestZipExport {
+    Query(args): Query<RequestZipExport>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    request_export(&st, identity, args, None, None).await?;
+    Ok(StatusCode::OK)
+}
+
+/// Requests an export that's also uploaded to an S3 bucket. The request is a
+/// POST with a JSON body, since it has credentials.
+#[fastrace::trace]
+pub async fn request_s3_export(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(RequestS3Export {
+        export,
+        destination,
+        encryption,
+    }): Json<RequestS3Export>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let destination = export_destination(&st, destination)?;
+    let encryption = encryption
+        .map(|encryption| export_encryption(&st, encryption))
+        .transpose()?;
+    request_export(&st, identity, export, Some(destination), encryption).await?;
+    Ok(StatusCode::OK)
+}
+
+/// Requests an export that's encrypted before it's uploaded. The request is a
+/// POST with a JSON body, since it may have a passphrase.
+#[fastrace::trace]
+pub async fn request_encrypted_export(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(RequestEncryptedExport { export, encryption }): Json<RequestEncryptedExport>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let encryption = export_encryption(&st, encryption)?;
+    request_export(&st, identity, export, None, Some(encryption)).await?;
+    Ok(StatusCode::OK)
+}
+
+/// A schedule for automatic exports, like every day at 02:00 UTC. Only the
+/// latest `retain` of the schedule's exports are kept.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetExportSchedule {
+    /// A cron expression in UTC, like `0 2 * * *`.
+    pub cron: String,
+    #[serde(default)]
+    pub include_storage: bool,
+    #[serde(default)]
+    pub storage_urls: bool,
+    #[serde(default)]
+    pub format: RequestedExportFormat,
+    /// Comma separated fields to put first in each table of a CSV export.
+    pub field_order: Option<String>,
+    pub component: Option<String>,
+    pub retain: u64,
+    pub destination: Option<S3ExportDestination>,
+    pub encryption: Option<ExportEncryptionArg>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportScheduleResponse {
+    cron: String,
+    format: String,
+    retain: u64,
+    /// The S3 URI scheduled exports are also uploaded to, if any.
+    destination: Option<String>,
+    /// Whether scheduled exports are encrypted.
+    encrypted: bool,
+    next_ts: i64,
+    last_requested_ts: Option<i64>,
+    last_export: Option<ScheduledExportStatus>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledExportStatus {
+    id: String,
+    state: &'static str,
+    progress_message: Option<String>,
+}
+
+pub async fn get_export_schedule(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let (schedule, last_export) = st
+        .application
+        .get_export_schedule(identity)
+        .await?
+        .context(ErrorMetadata::not_found(
+            "ExportScheduleNotFound",
+            "The deployment has no export schedule",
+        ))?;
+    let cron = match schedule.schedule {
+        CronSchedule::Cron { cron_expr, .. } => cron_expr,
+        schedule => format!("{schedule:?}"),
+    };
+    let format = match schedule.format {
+        ExportFormat::Zip { .. } => "jsonl",
+        ExportFormat::Parquet => "parquet",
+        ExportFormat::Csv { .. } => "csv",
+    };
+    let destination = schedule.destination.map(|destination| match destination {
+        ExportDestination::S3 {
+            bucket, key_prefix, ..
+        } => format!("s3://{bucket}/{key_prefix}"),
+    });
+    let last_export = schedule
+        .last_export_id
+        .zip(last_export)
+        .map(|(id, export)| {
+            let (state, progress_message) = match export {
+                Export::Requested { .. } => ("requested", None),
+                Export::InProgress {
+                    progress_message, ..
+                } => ("in_progress", progress_message),
+                Export::Completed { .. } => ("completed", None),
+                Export::Failed { .. } => ("failed", None),
+                Export::Canceled { .. } => ("canceled", None),
+            };
+            ScheduledExportStatus {
+                id: id.encode(),
+                state,
+                progress_message,
+            }
+        });
+    Ok(Json(ExportScheduleResponse {
+        cron,
+        format: format.to_string(),
+        retain: schedule.retain,
+        destination,
+        encrypted: schedule.encryption.is_some(),
+        next_ts: schedule.next_ts.into(),
+        last_requested_ts: schedule.last_requested_ts.map(i64::from),
+        last_export,
+    }))
+}
+
+/// Sets the deployment's export schedule, replacing any existing one. The
+/// request is a PUT with a JSON body, since the destination may have
+/// credentials.
+pub async fn set_export_schedule(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(SetExportSchedule {
+        cron,
         include_storage,
+        storage_urls,
+        format,
+        field_order,
         component,
-    }): Query<RequestZipExport>,
+        retain,
+        destination,
+        encryption,
+    }): Json<SetExportSchedule>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     must_be_admin_with_write_access(&identity)?;
+    let format = export_format(include_storage, storage_urls, format, field_order)?;
     let component = ComponentId::deserialize_from_string(component.as_deref())?;
+    let destination = destination
+        .map(|destination| export_destination(&st, destination))
+        .transpose()?;
+    let encryption = encryption
+        .map(|encryption| export_encryption(&st, encryption))
+        .transpose()?;
+    st.application
+        .set_export_schedule(
+            identity,
+            CronSchedule::Cron {
+                cron_expr: cron,
+                timezone: None,
+            },
+            format,
+            component,
+            retain,
+            destination,
+            encryption,
+        )
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_export_schedule(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    st.application.delete_export_schedule(identity).await?;
+    Ok(StatusCode::OK)
+}
+
+fn export_destination(
+    st: &LocalAppState,
+    destination: S3ExportDestination,
+) -> anyhow::Result<ExportDestination> {
+    let (bucket, key_prefix) = parse_s3_uri(&destination.s3_uri)?;
+    let credentials = match destination {
+        S3ExportDestination {
+            access_key_id: Some(access_key_id),
+            secret_access_key: Some(secret_access_key),
+            role_arn: None,
+            external_id: None,
+            ..
+        } => S3Credentials::AccessKey {
+            access_key_id,
+            encrypted_secret_access_key: st
+                .application
+                .key_broker()
+                .encrypt_export_secret(secret_access_key),
+        },
+        S3ExportDestination {
+            access_key_id: None,
+            secret_access_key: None,
+            role_arn: Some(role_arn),
+            external_id,
+            ..
+        } => S3Credentials::AssumeRole {
+            role_arn,
+            external_id,
+        },
+        _ => {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidS3Credentials",
+                "Specify either accessKeyId and secretAccessKey, or roleArn",
+            ))
+        },
+    };
+    Ok(ExportDestination::S3 {
+        bucket,
+        key_prefix,
+        region: destination.region,
+        endpoint: destination.endpoint,
+        credentials,
+    })
+}
+
+fn export_encryption(
+    st: &LocalAppState,
+    encryption: ExportEncryptionArg,
+) -> anyhow::Result<ExportEncryption> {
+    let encryption = match encryption {
+        ExportEncryptionArg {
+            public_keys: Some(recipients),
+            passphrase: None,
+        } => ExportEncryption::PublicKeys { recipients },
+        ExportEncryptionArg {
+            public_keys: None,
+            passphrase: Some(passphrase),
+        } if !passphrase.is_empty() => ExportEncryption::Passphrase {
+            encrypted_passphrase: st
+                .application
+                .key_broker()
+                .encrypt_export_secret(passphrase),
+        },
+        _ => {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidExportEncryption",
+                "Specify either publicKeys or a non-empty passphrase",
+            ))
+        },
+    };
+    Ok(encryption)
+}
+
+async fn request_export(
+    st: &LocalAppState,
+    identity: Identity,
+    RequestZipExport {
+        include_storage,
+        storage_urls,
+        format,
+        field_order,
+        component,
+        previous_export_id,
+        include_tables,
+        exclude_tables,
+        include_components,
+        exclude_components,
+        exclude_fields,
+    }: RequestZipExport,
+    destination: Option<ExportDestination>,
+    encryption: Option<ExportEncryption>,
+) -> anyhow::Result<()> {
+    let format = export_format(include_storage, storage_urls, format, field_order)?;
+    let filter = export_filter(
+        include_tables,
+        exclude_tables,
+        include_components,
+        exclude_components,
+        exclude_fields,
+    )?;
+    let component = ComponentId::deserialize_from_string(component.as_deref())?;
+    let previous_export_id = previous_export_id
+        .map(|id| {
+            id.parse::<DeveloperDocumentId>()
+                .context(ErrorMetadata::bad_request(
+                    "BadSnapshotId",
+                    "previousExportId did not parse to an ID.",
+                ))
+        })
+        .transpose()?;
     st.application
         .request_export(
             identity,
-            ExportFormat::Zip { include_storage },
+            format,
             component,
             ExportRequestor::SnapshotExport,
             None,
+            previous_export_id,
+            destination,
+            filter,
+            encryption,
         )
         .await?;
-    Ok(StatusCode::OK)
+    Ok(())
+}
+
+fn export_format(
+    include_storage: bool,
+    storage_urls: bool,
+    format: RequestedExportFormat,
+    field_order: Option<String>,
+) -> anyhow::Result<ExportFormat> {
+    if field_order.is_some() && !matches!(format, RequestedExportFormat::Csv) {
+        anyhow::bail!(ErrorMetadata::bad_request(
+            "FieldOrderWithoutCsv",
+            "fieldOrder can only be set for CSV exports",
+        ));
+    }
+    if (include_storage || storage_urls) && !matches!(format, RequestedExportFormat::Jsonl) {
+        anyhow::bail!(ErrorMetadata::bad_request(
+            "TabularExportWithStorage",
+            "Parquet and CSV exports can't include file storage",
+        ));
+    }
+    let format = match format {
+        RequestedExportFormat::Jsonl => ExportFormat::Zip {
+            include_storage,
+            storage_urls,
+        },
+        RequestedExportFormat::Parquet => ExportFormat::Parquet,
+        RequestedExportFormat::Csv => ExportFormat::Csv {
+            field_order: field_order
+                .iter()
+                .flat_map(|fields| fields.split(','))
+                .map(|field| field.trim().to_string())
+                .filter(|field| !field.is_empty())
+                .collect(),
+        },
+    };
+    Ok(format)
+}
+
+fn export_filter(
+    include_tables: Option<String>,
+    exclude_tables: Option<String>,
+    include_components: Option<String>,
+    exclude_components: Option<String>,
+    exclude_fields: Option<String>,
+) -> anyhow::Result<ExportFilter> {
+    let mut excluded_fields = BTreeMap::<_, BTreeSet<_>>::new();
+    for table_field in exclude_fields.iter().flat_map(|fields| fields.split(',')) {
+        let (table, field) = table_field
+            .trim()
+            .split_once('.')
+            .filter(|(_, field)| !field.starts_with('_'))
+            .context(ErrorMetadata::bad_request(
+                "InvalidExportFilter",
+                format!(
+                    "excludeFields must have <table>.<field> fields that aren't system fields, \
+                     not {table_field}"
+                ),
+            ))?;
+        excluded_fields
+            .entry(parse_filter_item::<TableName>(table)?)
+            .or_default()
+            .insert(parse_filter_item::<IdentifierFieldName>(field)?);
+    }
+    Ok(ExportFilter {
+        include_tables: include_tables.as_deref().map(parse_filter).transpose()?,
+        exclude_tables: exclude_tables
+            .as_deref()
+            .map(parse_filter)
+            .transpose()?
+            .unwrap_or_default(),
+        include_components: include_components
+            .as_deref()
+            .map(parse_filter)
+            .transpose()?,
+        exclude_components: exclude_components
+            .as_deref()
+            .map(parse_filter)
+            .transpose()?
+            .unwrap_or_default(),
+        exclude_fields: excluded_fields,
+    })
+}
+
+/// Parses a comma separated list of tables or components.
+fn parse_filter<T: FromStr<Err = anyhow::Error> + Ord>(list: &str) -> anyhow::Result<BTreeSet<T>> {
+    list.split(',').map(parse_filter_item).collect()
+}
+
+fn parse_filter_item<T: FromStr<Err = anyhow::Error>>(item: &str) -> anyhow::Result<T> {
+    let item = item.trim();
+    item.parse().context(ErrorMetadata::bad_request(
+        "InvalidExportFilter",
+        format!("Invalid table, component, or field in export filter: {item:?}"),
+    ))
+}
+
+/// Splits an `s3://<bucket>/<key prefix>` URI into the bucket and the prefix
+/// of the export's key, which always ends in a `/` if it's not empty.
+fn parse_s3_uri(uri: &str) -> anyhow::Result<(String, String)> {
+    let (bucket, key_prefix) = uri
+        .strip_prefix("s3://")
+        .map(|rest| rest.split_once('/').unwrap_or((rest, "")))
+        .filter(|(bucket, _)| !bucket.is_empty())
+        .context(ErrorMetadata::bad_request(
+            "InvalidS3Uri",
+            format!("{uri} isn't an S3 URI like s3://<bucket>/<key prefix>"),
+        ))?;
+    let key_prefix = if key_prefix.is_empty() || key_prefix.ends_with('/') {
+        key_prefix.to_string()
+    } else {
+        format!("{key_prefix}/")
+    };
+    Ok((bucket.to_string(), key_prefix))
 }
 
 #[derive(Deserialize)]
@@ -105,8 +627,15 @@ pub async fn get_zip_export(
         filename,
     ) = st.application.get_zip_export(identity, id).await?;
     let content_length = ContentLength(content_length as u64);
+    // Encrypted exports aren't zips until they're decrypted.
+    let content_type = if filename.ends_with(".age") {
+        "application/octet-stream"
+    } else {
+        "application/zip"
+    };
     Ok((
         TypedHeader(content_length),
+        [(CONTENT_TYPE, content_type)],
         // `ContentDisposition::attachment()` is not implemented in the headers library yet!
         // so we handroll it:
         TypedHeader(ContentDispositionAttachment(filename)),
@@ -118,3 +647,71 @@ pub async fn get_zip_export(
         Body::from_stream(stream),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use common::components::ComponentPath;
+    use maplit::{
+        btreemap,
+        btreeset,
+    };
+    use model::exports::types::ExportFilter;
+
+    use super::{
+        export_filter,
+        parse_s3_uri,
+    };
+
+    #[test]
+    fn test_parse_s3_uri() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_s3_uri("s3://backups")?,
+            ("backups".to_string(), "".to_string())
+        );
+        assert_eq!(
+            parse_s3_uri("s3://backups/convex/prod")?,
+            ("backups".to_string(), "convex/prod/".to_string())
+        );
+        assert_eq!(
+            parse_s3_uri("s3://backups/convex/")?,
+            ("backups".to_string(), "convex/".to_string())
+        );
+        assert!(parse_s3_uri("s3:///convex").is_err());
+        assert!(parse_s3_uri("https://backups.s3.amazonaws.com").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_filter() -> anyhow::Result<()> {
+        assert_eq!(
+            export_filter(None, None, None, None, None)?,
+            ExportFilter::default()
+        );
+        assert_eq!(
+            export_filter(
+                Some("users, messages".to_string()),
+                Some("logs".to_string()),
+                Some(",waitlist".to_string()),
+                Some("waitlist/ratelimiter".to_string()),
+                Some("users.email,users.phone,messages.author".to_string()),
+            )?,
+            ExportFilter {
+                include_tables: Some(btreeset! { "users".parse()?, "messages".parse()? }),
+                exclude_tables: btreeset! { "logs".parse()? },
+                include_components: Some(btreeset! {
+                    ComponentPath::root(),
+                    "waitlist".parse()?,
+                }),
+                exclude_components: btreeset! { "waitlist/ratelimiter".parse()? },
+                exclude_fields: btreemap! {
+                    "users".parse()? => btreeset! { "email".parse()?, "phone".parse()? },
+                    "messages".parse()? => btreeset! { "author".parse()? },
+                },
+            }
+        );
+        assert!(export_filter(Some("users,".to_string()), None, None, None, None).is_err());
+        assert!(export_filter(None, None, None, None, Some("email".to_string())).is_err());
+        assert!(export_filter(None, None, None, None, Some("users._id".to_string())).is_err());
+        Ok(())
+    }
+}
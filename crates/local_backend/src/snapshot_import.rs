@@ -1,9 +1,14 @@
-use std::str::FromStr;
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+};
 
 use anyhow::Context;
 use application::snapshot_import::{
     self,
+    can_stream_import,
     do_import,
+    do_streamed_import,
 };
 use axum::{
     body::Body,
@@ -20,6 +25,7 @@ use common::{
         },
         HttpResponseError,
     },
+    schemas::validator::Validator,
 };
 use errors::ErrorMetadata;
 use futures::{
@@ -27,19 +33,27 @@ use futures::{
     TryStreamExt,
 };
 use model::snapshot_imports::types::{
+    ImportColumnType,
+    ImportColumnTypes,
     ImportFormat,
     ImportMode,
+    ImportValueType,
+    MongoIdMapping,
 };
 use serde::{
     Deserialize,
     Serialize,
 };
+use serde_json::Value as JsonValue;
 use storage::{
     ClientDrivenUploadPartToken,
     ClientDrivenUploadToken,
 };
 use value::{
     id_v6::DeveloperDocumentId,
+    FieldName,
+    IdentifierFieldName,
+    Namespace,
     TableName,
 };
 
@@ -57,6 +71,14 @@ pub struct ImportQueryArgs {
     format: ImportFormatArg,
     #[serde(default)]
     mode: ImportMode,
+    /// For MongoDB imports, the field to keep each document's `_id` in. The
+    /// `_id` is dropped if this isn't set.
+    mongo_id_field: Option<String>,
+    /// For CSV and Postgres imports, `infer` to infer each column's type from
+    /// its cells, or a JSON object of each column's validator, like
+    /// `{"id": {"type": "bigint"}}`. Cells that look like numbers are
+    /// float64s and others are strings if this isn't set.
+    column_types: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -82,6 +104,9 @@ enum ImportFormatArg {
     JsonLines,
     JsonArray,
     Zip,
+    MongoBson,
+    MongoArchive,
+    PgDump,
 }
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -92,6 +117,8 @@ struct ImportResponse {
 fn parse_format_arg(
     table_name: Option<String>,
     format: ImportFormatArg,
+    mongo_id_field: Option<String>,
+    column_types: Option<String>,
 ) -> anyhow::Result<ImportFormat> {
     let table_name = table_name
         .map(|table_name| {
@@ -103,6 +130,45 @@ fn parse_format_arg(
             })
         })
         .transpose()?;
+    let mongo_id_mapping = match mongo_id_field {
+        Some(_)
+            if !matches!(
+                format,
+                ImportFormatArg::MongoBson | ImportFormatArg::MongoArchive
+            ) =>
+        {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidMongoIdField",
+                "mongoIdField is only supported for MongoDB imports",
+            ));
+        },
+        Some(field) => {
+            let field = IdentifierFieldName::from_str(&field).map_err(|e| {
+                ErrorMetadata::bad_request(
+                    "InvalidMongoIdField",
+                    format!("invalid mongoIdField {field}: {e}"),
+                )
+            })?;
+            if field.is_system() {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "InvalidMongoIdField",
+                    format!("mongoIdField {field} can't start with an underscore"),
+                ));
+            }
+            MongoIdMapping::Field(field)
+        },
+        None => MongoIdMapping::Discard,
+    };
+    let column_types = match column_types {
+        Some(_) if !matches!(format, ImportFormatArg::Csv | ImportFormatArg::PgDump) => {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "InvalidColumnTypes",
+                "columnTypes is only supported for CSV and Postgres imports",
+            ));
+        },
+        Some(column_types) => parse_column_types(&column_types)?,
+        None => ImportColumnTypes::Guess,
+    };
     let inner_format = match format {
         ImportFormatArg::Zip => {
             if table_name.is_some() {
@@ -113,19 +179,107 @@ fn parse_format_arg(
             }
             ImportFormat::Zip
         },
-        ImportFormatArg::Csv => ImportFormat::Csv(table_name.context(
-            ErrorMetadata::bad_request("InvalidName", "CSV import requires table name"),
-        )?),
+        ImportFormatArg::Csv => ImportFormat::Csv(
+            table_name.context(ErrorMetadata::bad_request(
+                "InvalidName",
+                "CSV import requires table name",
+            ))?,
+            column_types,
+        ),
         ImportFormatArg::JsonArray => ImportFormat::JsonArray(table_name.context(
             ErrorMetadata::bad_request("InvalidName", "JSON import requires table name"),
         )?),
         ImportFormatArg::JsonLines => ImportFormat::JsonLines(table_name.context(
             ErrorMetadata::bad_request("InvalidName", "JSONL import requires table name"),
         )?),
+        ImportFormatArg::MongoBson => ImportFormat::MongoBson(
+            table_name.context(ErrorMetadata::bad_request(
+                "InvalidName",
+                "MongoDB BSON import requires table name",
+            ))?,
+            mongo_id_mapping,
+        ),
+        ImportFormatArg::MongoArchive => {
+            if table_name.is_some() {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "InvalidName",
+                    "MongoDB archive import cannot have table name",
+                ));
+            }
+            ImportFormat::MongoArchive(mongo_id_mapping)
+        },
+        ImportFormatArg::PgDump => ImportFormat::PgDump(
+            table_name.context(ErrorMetadata::bad_request(
+                "InvalidName",
+                "Postgres import requires table name",
+            ))?,
+            column_types,
+        ),
     };
     Ok(inner_format)
 }
 
+fn invalid_column_types(message: String) -> ErrorMetadata {
+    ErrorMetadata::bad_request("InvalidColumnTypes", message)
+}
+
+fn parse_column_types(column_types: &str) -> anyhow::Result<ImportColumnTypes> {
+    if column_types == "infer" {
+        return Ok(ImportColumnTypes::Infer);
+    }
+    let validators: BTreeMap<String, JsonValue> = serde_json::from_str(column_types)
+        .map_err(|e| invalid_column_types(format!("columnTypes isn't a JSON object: {e}")))?;
+    let columns = validators
+        .into_iter()
+        .map(|(column, validator)| {
+            let column_type = Validator::try_from(validator)
+                .and_then(import_column_type)
+                .map_err(|e| {
+                    invalid_column_types(format!("invalid type for column {column:?}: {e}"))
+                })?;
+            let column = FieldName::from_str(&column).map_err(|e| {
+                invalid_column_types(format!("{column:?} isn't a valid field name: {e}"))
+            })?;
+            anyhow::Ok((column, column_type))
+        })
+        .try_collect()?;
+    Ok(ImportColumnTypes::Explicit(columns))
+}
+
+/// Columns can be any validator for a value that has a text form, or a union
+/// of one with `v.null()` for columns with empty or NULL cells.
+fn import_column_type(validator: Validator) -> anyhow::Result<ImportColumnType> {
+    let (validator, nullable) = match validator {
+        Validator::Union(options) if options.contains(&Validator::Null) => {
+            let mut options: Vec<_> = options
+                .into_iter()
+                .filter(|option| *option != Validator::Null)
+                .collect();
+            anyhow::ensure!(
+                options.len() == 1,
+                "unions can only have one validator besides v.null()"
+            );
+            (options.remove(0), true)
+        },
+        validator => (validator, false),
+    };
+    let value_type = match validator {
+        Validator::String | Validator::Id(_) => ImportValueType::String,
+        Validator::Float64 => ImportValueType::Float64,
+        Validator::Int64 => ImportValueType::Int64,
+        Validator::Boolean => ImportValueType::Boolean,
+        Validator::Bytes => ImportValueType::Bytes,
+        Validator::Array(_) | Validator::Object(_) | Validator::Record(..) | Validator::Any => {
+            ImportValueType::Json
+        },
+        validator => anyhow::bail!("{validator} columns aren't supported"),
+    };
+    Ok(ImportColumnType {
+        value_type,
+        nullable,
+    })
+}
+
 pub async fn import(
     State(st): State<LocalAppState>,
     ExtractIdentity(identity): ExtractIdentity,
@@ -134,25 +288,41 @@ pub async fn import(
         component_path,
         format,
         mode,
+        mongo_id_field,
+        column_types,
     }): Query<ImportQueryArgs>,
     stream: Body,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     must_be_admin_with_write_access(&identity)?;
-    let format = parse_format_arg(table_name, format)?;
+    let format = parse_format_arg(table_name, format, mongo_id_field, column_types)?;
     let component_path = ComponentPath::deserialize(component_path.as_deref())?;
     let body_stream = stream
         .into_data_stream()
         .map_err(anyhow::Error::from)
         .boxed();
-    let num_written = do_import(
-        &st.application,
-        identity,
-        format,
-        mode,
-        component_path,
-        body_stream,
-    )
-    .await?;
+    // Formats that are parsed in a single pass are imported as the body
+    // arrives, and the rest are uploaded to storage first.
+    let num_written = if can_stream_import(&format) {
+        do_streamed_import(
+            &st.application,
+            identity,
+            format,
+            mode,
+            component_path,
+            body_stream,
+        )
+        .await?
+    } else {
+        do_import(
+            &st.application,
+            identity,
+            format,
+            mode,
+            component_path,
+            body_stream,
+        )
+        .await?
+    };
     Ok(Json(ImportResponse { num_written }))
 }
 
@@ -224,13 +394,15 @@ pub async fn import_finish_upload(
                 component_path,
                 format,
                 mode,
+                mongo_id_field,
+                column_types,
             },
         upload_token,
         part_tokens,
     }): Json<ImportFinishUploadArgs>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
     must_be_admin_with_write_access(&identity)?;
-    let format = parse_format_arg(table_name, format)?;
+    let format = parse_format_arg(table_name, format, mongo_id_field, column_types)?;
     let component_path = ComponentPath::deserialize(component_path.as_deref())?;
     let import_id = st
         .application
@@ -289,3 +461,46 @@ pub async fn cancel_import(
     snapshot_import::cancel_import(&st.application, identity, import_id).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+    use model::snapshot_imports::types::{
+        ImportColumnType,
+        ImportColumnTypes,
+        ImportValueType,
+    };
+
+    use super::parse_column_types;
+
+    #[test]
+    fn test_parse_column_types() -> anyhow::Result<()> {
+        assert_eq!(parse_column_types("infer")?, ImportColumnTypes::Infer);
+        let column_types = parse_column_types(
+            r#"{
+                "id": {"type": "bigint"},
+                "name": {"type": "union", "value": [{"type": "string"}, {"type": "null"}]},
+                "tags": {"type": "array", "value": {"type": "string"}}
+            }"#,
+        )?;
+        let column_type = |value_type, nullable| ImportColumnType {
+            value_type,
+            nullable,
+        };
+        assert_eq!(
+            column_types,
+            ImportColumnTypes::Explicit(btreemap! {
+                "id".parse()? => column_type(ImportValueType::Int64, false),
+                "name".parse()? => column_type(ImportValueType::String, true),
+                "tags".parse()? => column_type(ImportValueType::Json, false),
+            })
+        );
+        assert!(parse_column_types(r#"{"id": {"type": "literal", "value": 1}}"#).is_err());
+        assert!(parse_column_types(
+            r#"{"id": {"type": "union", "value": [{"type": "string"}, {"type": "number"}]}}"#
+        )
+        .is_err());
+        assert!(parse_column_types("[]").is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use std::{
     ops::Bound,
-    time::Duration,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
 };
 
 use anyhow::Context;
@@ -22,7 +26,11 @@ use axum_extra::{
         CacheControl,
         ContentLength,
         ContentType,
+        ETag,
         Header,
+        IfModifiedSince,
+        IfNoneMatch,
+        LastModified,
         Range,
     },
     typed_header::{
@@ -33,6 +41,7 @@ use axum_extra::{
 };
 use common::{
     components::ComponentId,
+    document::CreationTime,
     http::{
         extract::{
             Json,
@@ -43,7 +52,10 @@ use common::{
         ExtractResolvedHostname,
         HttpResponseError,
     },
-    sha256::DigestHeader,
+    sha256::{
+        DigestHeader,
+        Sha256Digest,
+    },
 };
 use errors::ErrorMetadata;
 use file_storage::{
@@ -136,6 +148,35 @@ pub async fn storage_upload(
     }))
 }
 
+/// Stored files never change, so their hash is a strong ETag.
+fn file_etag(sha256: &Sha256Digest) -> ETag {
+    format!("\"{}\"", sha256.as_hex())
+        .parse()
+        .expect("A hex digest is a valid ETag")
+}
+
+fn file_last_modified(creation_time: CreationTime) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs_f64(f64::from(creation_time) / 1000.)
+}
+
+/// Whether a conditional GET's cached copy of a file is still current.
+/// `If-Modified-Since` is only checked if `If-None-Match` isn't sent, as
+/// RFC 9110 requires.
+fn is_not_modified(
+    etag: &ETag,
+    last_modified: Option<SystemTime>,
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+) -> bool {
+    match (if_none_match, if_modified_since, last_modified) {
+        (Some(if_none_match), ..) => !if_none_match.precondition_passes(etag),
+        (None, Some(if_modified_since), Some(last_modified)) => {
+            !if_modified_since.is_modified(last_modified)
+        },
+        _ => false,
+    }
+}
+
 #[derive(Deserialize)]
 pub struct GetQueryParams {
     component: Option<String>,
@@ -147,6 +188,8 @@ pub async fn storage_get(
     Path(uuid): Path<String>,
     Query(GetQueryParams { component }): Query<GetQueryParams>,
     range: Result<TypedHeader<Range>, TypedHeaderRejection>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
     ExtractResolvedHostname(host): ExtractResolvedHostname,
     Host(original_host): Host,
     ExtractRequestId(request_id): ExtractRequestId,
@@ -161,6 +204,39 @@ pub async fn storage_get(
 
     // TODO(CX-3065) figure out deterministic repeatable tokens
 
+    // Check conditional GETs before fetching the file, so clients and CDNs
+    // revalidating their cached copies don't cost a read from storage.
+    let validators = st
+        .api
+        .get_file_validators(
+            &host,
+            request_id.clone(),
+            component,
+            file_storage_id.clone(),
+        )
+        .await?;
+    let etag = file_etag(&validators.sha256);
+    let last_modified = validators.creation_time.map(file_last_modified);
+    if is_not_modified(
+        &etag,
+        last_modified,
+        if_none_match.map(|h| h.0),
+        if_modified_since.map(|h| h.0),
+    ) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            TypedHeader(etag),
+            last_modified.map(|t| TypedHeader(LastModified::from(t))),
+            TypedHeader(
+                CacheControl::new()
+                    .with_private()
+                    .with_max_age(MAX_CACHE_AGE),
+            ),
+        )
+            .into_response());
+    }
+    let last_modified = last_modified.map(|t| TypedHeader(LastModified::from(t)));
+
     if let Ok(range_header) = range {
         let ranges: Vec<(Bound<u64>, Bound<u64>)> = range_header
             .satisfiable_ranges(
@@ -201,6 +277,8 @@ pub async fn storage_get(
             content_type.map(TypedHeader),
             content_range,
             TypedHeader(content_length),
+            TypedHeader(etag),
+            last_modified,
             TypedHeader(
                 CacheControl::new()
                     .with_private()
@@ -225,6 +303,8 @@ pub async fn storage_get(
         TypedHeader(DigestHeader(sha256)),
         content_type.map(TypedHeader),
         TypedHeader(content_length),
+        TypedHeader(etag),
+        last_modified,
         TypedHeader(
             CacheControl::new()
                 .with_private()
@@ -235,3 +315,69 @@ pub async fn storage_get(
     )
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        UNIX_EPOCH,
+    };
+
+    use axum_extra::headers::{
+        ETag,
+        IfModifiedSince,
+        IfNoneMatch,
+    };
+    use common::sha256::Sha256;
+
+    use super::{
+        file_etag,
+        is_not_modified,
+    };
+
+    #[test]
+    fn test_is_not_modified() {
+        let mut hasher = Sha256::new();
+        hasher.update(b"hello");
+        let etag = file_etag(&hasher.finalize());
+        let other: ETag = "\"other\"".parse().unwrap();
+        let stored = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let later = IfModifiedSince::from(stored + Duration::from_secs(60));
+        let earlier = IfModifiedSince::from(stored - Duration::from_secs(60));
+
+        assert!(!is_not_modified(&etag, Some(stored), None, None));
+        assert!(is_not_modified(
+            &etag,
+            Some(stored),
+            Some(IfNoneMatch::from(etag.clone())),
+            None
+        ));
+        assert!(is_not_modified(
+            &etag,
+            Some(stored),
+            Some(IfNoneMatch::any()),
+            None
+        ));
+        assert!(!is_not_modified(
+            &etag,
+            Some(stored),
+            Some(IfNoneMatch::from(other.clone())),
+            None
+        ));
+        assert!(is_not_modified(
+            &etag,
+            Some(stored),
+            None,
+            Some(later.clone())
+        ));
+        assert!(!is_not_modified(&etag, Some(stored), None, Some(earlier)));
+        assert!(!is_not_modified(&etag, None, None, Some(later.clone())));
+        // If-None-Match takes precedence over If-Modified-Since.
+        assert!(!is_not_modified(
+            &etag,
+            Some(stored),
+            Some(IfNoneMatch::from(other)),
+            Some(later)
+        ));
+    }
+}
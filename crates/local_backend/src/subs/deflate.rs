@@ -0,0 +1,643 @@
+//! The permessage-deflate WebSocket extension (RFC 7692) for the sync
+//! protocol.
+//!
+//! Tungstenite rejects frames with the RSV1 bit that marks compressed
+//! messages, so `PerMessageDeflate` sits between it and the connection. It
+//! inflates the client's compressed messages into plain frames before
+//! tungstenite reads them, and deflates the data frames tungstenite writes.
+//! Control frames and uncompressed messages pass through as they are.
+use std::{
+    io,
+    pin::Pin,
+    task::{
+        ready,
+        Context,
+        Poll,
+    },
+};
+
+use bytes::{
+    Buf,
+    BufMut,
+    BytesMut,
+};
+use common::knobs::RESPONSE_COMPRESSION_MIN_BYTES;
+use flate2::{
+    Compress,
+    Compression,
+    Decompress,
+    FlushCompress,
+    FlushDecompress,
+    Status,
+};
+use http::{
+    header::SEC_WEBSOCKET_EXTENSIONS,
+    HeaderMap,
+    HeaderValue,
+};
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+    ReadBuf,
+};
+
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Each compressed message ends with this, which is left off on the wire.
+const DEFLATE_TRAILER: [u8; 4] = [0, 0, 0xff, 0xff];
+
+/// Same as tungstenite's default limit on message size.
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Frames waiting to be written past which writes wait for them to go out.
+const WRITE_HIGH_WATER_MARK: usize = 64 << 10;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+/// The permessage-deflate parameters accepted from a client's offer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeflateParams {
+    /// Compress each message on its own.
+    pub server_no_context_takeover: bool,
+    /// The client compresses each message on its own.
+    pub client_no_context_takeover: bool,
+    /// The largest window the client can inflate, from 9 to 15.
+    pub server_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// Accepts the first permessage-deflate offer in the request's
+    /// `Sec-WebSocket-Extensions` headers with parameters we support.
+    pub fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(SEC_WEBSOCKET_EXTENSIONS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(Self::accept_offer)
+    }
+
+    fn accept_offer(offer: &str) -> Option<Self> {
+        let mut params = offer.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) {
+            return None;
+        }
+        let mut accepted = Self::default();
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            match (name, value) {
+                ("server_no_context_takeover", None) => accepted.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => accepted.client_no_context_takeover = true,
+                ("server_max_window_bits", Some(bits)) => {
+                    // zlib can't deflate with a window of 8 bits.
+                    let bits = bits.parse().ok().filter(|bits| (9..=15).contains(bits))?;
+                    accepted.server_max_window_bits = Some(bits);
+                },
+                // We inflate with the largest window, so any window the client uses
+                // works.
+                ("client_max_window_bits", _) => {},
+                _ => return None,
+            }
+        }
+        Some(accepted)
+    }
+
+    /// The `Sec-WebSocket-Extensions` header of the upgrade response.
+    pub fn response_header(&self) -> HeaderValue {
+        let mut extension = PERMESSAGE_DEFLATE.to_string();
+        if self.server_no_context_takeover {
+            extension.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            extension.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            extension.push_str(&format!("; server_max_window_bits={bits}"));
+        }
+        HeaderValue::from_str(&extension).expect("Extension header is always valid")
+    }
+}
+
+/// The header of a WebSocket frame.
+#[derive(Debug)]
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    header_len: usize,
+    payload_len: usize,
+}
+
+impl FrameHeader {
+    /// Parses the header of the frame at the start of `buf`, returning `None`
+    /// until the whole frame is in `buf`.
+    fn parse(buf: &[u8]) -> io::Result<Option<Self>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let (payload_len, mut header_len) = match buf[1] & 0x7f {
+            126 if buf.len() < 4 => return Ok(None),
+            126 => (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4),
+            127 if buf.len() < 10 => return Ok(None),
+            127 => (
+                u64::from_be_bytes(buf[2..10].try_into().expect("Slice is 8 bytes")),
+                10,
+            ),
+            len => (len as u64, 2),
+        };
+        let payload_len = usize::try_from(payload_len)
+            .ok()
+            .filter(|len| *len <= MAX_MESSAGE_SIZE)
+            .ok_or_else(|| invalid_data("WebSocket frame is too large"))?;
+        let mask = if buf[1] & 0x80 != 0 {
+            let Some(mask) = buf.get(header_len..header_len + 4) else {
+                return Ok(None);
+            };
+            header_len += 4;
+            Some(mask.try_into().expect("Slice is 4 bytes"))
+        } else {
+            None
+        };
+        if buf.len() < header_len + payload_len {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            fin: buf[0] & 0x80 != 0,
+            rsv1: buf[0] & 0x40 != 0,
+            opcode: buf[0] & 0x0f,
+            mask,
+            header_len,
+            payload_len,
+        }))
+    }
+
+    fn frame_len(&self) -> usize {
+        self.header_len + self.payload_len
+    }
+
+    fn is_control(&self) -> bool {
+        self.opcode & 0x08 != 0
+    }
+
+    /// The frame's payload, unmasked.
+    fn payload(&self, frame: &[u8]) -> Vec<u8> {
+        let mut payload = frame[self.header_len..self.frame_len()].to_vec();
+        if let Some(mask) = self.mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        payload
+    }
+}
+
+/// Writes a whole message as a single frame. Masked frames get a zero mask,
+/// which leaves their payload as is.
+fn write_frame(out: &mut BytesMut, opcode: u8, rsv1: bool, masked: bool, payload: &[u8]) {
+    out.put_u8(0x80 | if rsv1 { 0x40 } else { 0 } | opcode);
+    let mask_bit = if masked { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => out.put_u8(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            out.put_u8(mask_bit | 126);
+            out.put_u16(len as u16);
+        },
+        len => {
+            out.put_u8(mask_bit | 127);
+            out.put_u64(len as u64);
+        },
+    }
+    if masked {
+        out.put_slice(&[0; 4]);
+    }
+    out.put_slice(payload);
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+struct DeflateCodec {
+    params: DeflateParams,
+    compress: Compress,
+    decompress: Decompress,
+    /// The opcode and payload so far of the compressed message being read.
+    inbound: Option<(u8, Vec<u8>)>,
+    /// The opcode and payload so far of the fragmented message being written.
+    outbound: Option<(u8, Vec<u8>)>,
+}
+
+impl DeflateCodec {
+    fn new(params: DeflateParams) -> Self {
+        let window_bits = params.server_max_window_bits.unwrap_or(15);
+        Self {
+            params,
+            compress: Compress::new_with_window_bits(Compression::default(), false, window_bits),
+            decompress: Decompress::new(false),
+            inbound: None,
+            outbound: None,
+        }
+    }
+
+    /// Turns a frame from the client into frames for tungstenite.
+    fn decode_frame(&mut self, frame: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        let header = FrameHeader::parse(frame)?.expect("Frame is whole");
+        if header.is_control() {
+            out.put_slice(frame);
+            return Ok(());
+        }
+        match (header.opcode, header.rsv1, &mut self.inbound) {
+            (OPCODE_TEXT | OPCODE_BINARY, true, None) => {
+                if header.mask.is_none() {
+                    return Err(invalid_data("Frames from the client must be masked"));
+                }
+                self.inbound = Some((header.opcode, header.payload(frame)));
+            },
+            (OPCODE_CONTINUATION, false, Some((_, payload))) => {
+                if payload.len() + header.payload_len > MAX_MESSAGE_SIZE {
+                    return Err(invalid_data("WebSocket message is too large"));
+                }
+                payload.extend(header.payload(frame));
+            },
+            (_, _, Some(_)) => {
+                return Err(invalid_data(
+                    "Expected a continuation of the compressed message",
+                ))
+            },
+            // Tungstenite checks the frames we don't change.
+            (..) => {
+                out.put_slice(frame);
+                return Ok(());
+            },
+        }
+        if header.fin {
+            let (opcode, payload) = self.inbound.take().expect("Message was just started");
+            let message = self.inflate(payload)?;
+            write_frame(out, opcode, false, true, &message);
+        }
+        Ok(())
+    }
+
+    /// Turns a frame from tungstenite into frames for the client.
+    fn encode_frame(&mut self, frame: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        let header = FrameHeader::parse(frame)?.expect("Frame is whole");
+        if header.is_control() {
+            out.put_slice(frame);
+            return Ok(());
+        }
+        let (opcode, payload) = match (header.opcode, header.fin, self.outbound.take()) {
+            (OPCODE_TEXT | OPCODE_BINARY, true, None) => (header.opcode, header.payload(frame)),
+            (OPCODE_TEXT | OPCODE_BINARY, false, None) => {
+                self.outbound = Some((header.opcode, header.payload(frame)));
+                return Ok(());
+            },
+            (OPCODE_CONTINUATION, fin, Some((opcode, mut payload))) => {
+                payload.extend(header.payload(frame));
+                if !fin {
+                    self.outbound = Some((opcode, payload));
+                    return Ok(());
+                }
+                (opcode, payload)
+            },
+            _ => return Err(invalid_data("Unexpected WebSocket frame")),
+        };
+        if payload.len() < *RESPONSE_COMPRESSION_MIN_BYTES as usize {
+            write_frame(out, opcode, false, false, &payload);
+        } else {
+            let compressed = self.deflate(&payload)?;
+            write_frame(out, opcode, true, false, &compressed);
+        }
+        Ok(())
+    }
+
+    fn deflate(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(payload.len() / 2 + 64);
+        let start = self.compress.total_in();
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&payload[consumed..], &mut output, FlushCompress::Sync)
+                .map_err(io::Error::other)?;
+            let consumed = (self.compress.total_in() - start) as usize;
+            // The flush is done once the input is consumed without filling the output.
+            if consumed == payload.len() && output.len() < output.capacity() {
+                break;
+            }
+            output.reserve(output.capacity().max(64));
+        }
+        if output.ends_with(&DEFLATE_TRAILER) {
+            output.truncate(output.len() - DEFLATE_TRAILER.len());
+        }
+        if self.params.server_no_context_takeover {
+            self.compress.reset();
+        }
+        Ok(output)
+    }
+
+    fn inflate(&mut self, mut payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        payload.extend_from_slice(&DEFLATE_TRAILER);
+        let mut output = Vec::with_capacity(payload.len() * 4);
+        let start = self.decompress.total_in();
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let (total_in, total_out) = (self.decompress.total_in(), self.decompress.total_out());
+            let status = self
+                .decompress
+                .decompress_vec(&payload[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|_| invalid_data("Invalid compressed WebSocket message"))?;
+            if status == Status::StreamEnd {
+                // The client ended its deflate stream, so its next message starts a
+                // new one.
+                self.decompress.reset(false);
+                break;
+            }
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if consumed == payload.len() && output.len() < output.capacity() {
+                break;
+            }
+            if self.decompress.total_in() == total_in && self.decompress.total_out() == total_out {
+                return Err(invalid_data("Truncated compressed WebSocket message"));
+            }
+            if output.len() >= MAX_MESSAGE_SIZE {
+                return Err(invalid_data("WebSocket message is too large"));
+            }
+            output.reserve(output.capacity().max(64));
+        }
+        if self.params.client_no_context_takeover {
+            self.decompress.reset(false);
+        }
+        Ok(output)
+    }
+}
+
+/// A connection that speaks permessage-deflate, if it was negotiated, and
+/// plain WebSocket frames to tungstenite.
+pub struct PerMessageDeflate<S> {
+    inner: S,
+    /// `None` if permessage-deflate wasn't negotiated, in which case bytes
+    /// pass straight through.
+    codec: Option<DeflateCodec>,
+    /// Bytes read from the client that aren't a whole frame yet.
+    read_buf: BytesMut,
+    /// Frames for tungstenite to read.
+    decoded: BytesMut,
+    read_eof: bool,
+    /// Bytes written by tungstenite that aren't a whole frame yet.
+    write_buf: BytesMut,
+    /// Frames to write to the client.
+    encoded: BytesMut,
+}
+
+impl<S> PerMessageDeflate<S> {
+    pub fn new(inner: S, params: Option<DeflateParams>) -> Self {
+        Self {
+            inner,
+            codec: params.map(DeflateCodec::new),
+            read_buf: BytesMut::new(),
+            decoded: BytesMut::new(),
+            read_eof: false,
+            write_buf: BytesMut::new(),
+            encoded: BytesMut::new(),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> PerMessageDeflate<S> {
+    fn poll_write_encoded(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.encoded.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.encoded))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.encoded.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PerMessageDeflate<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(codec) = &mut this.codec else {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        };
+        loop {
+            if !this.decoded.is_empty() {
+                let n = this.decoded.len().min(buf.remaining());
+                buf.put_slice(&this.decoded.split_to(n));
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(header) = FrameHeader::parse(&this.read_buf)? {
+                let frame = this.read_buf.split_to(header.frame_len());
+                codec.decode_frame(&frame, &mut this.decoded)?;
+                continue;
+            }
+            if this.read_eof {
+                // Let tungstenite see what's left of a truncated frame.
+                if this.read_buf.is_empty() {
+                    return Poll::Ready(Ok(()));
+                }
+                this.decoded = this.read_buf.split();
+                continue;
+            }
+            let mut chunk = [0; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+            if chunk_buf.filled().is_empty() {
+                this.read_eof = true;
+            } else {
+                this.read_buf.put_slice(chunk_buf.filled());
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PerMessageDeflate<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.codec.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        if this.encoded.len() >= WRITE_HIGH_WATER_MARK {
+            ready!(this.poll_write_encoded(cx))?;
+        }
+        this.write_buf.put_slice(buf);
+        let codec = this.codec.as_mut().expect("Checked above");
+        while let Some(header) = FrameHeader::parse(&this.write_buf)? {
+            let frame = this.write_buf.split_to(header.frame_len());
+            codec.encode_frame(&frame, &mut this.encoded)?;
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_encoded(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_encoded(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use flate2::{
+        Compress,
+        Compression,
+        Decompress,
+        FlushCompress,
+        FlushDecompress,
+    };
+    use futures::{
+        SinkExt,
+        StreamExt,
+    };
+    use http::{
+        header::SEC_WEBSOCKET_EXTENSIONS,
+        HeaderMap,
+    };
+    use tokio::io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    };
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::{
+        protocol::Role,
+        Message,
+    };
+
+    use super::{
+        write_frame,
+        DeflateParams,
+        FrameHeader,
+        PerMessageDeflate,
+        DEFLATE_TRAILER,
+        OPCODE_TEXT,
+    };
+
+    fn negotiate(offers: &[&str]) -> Option<DeflateParams> {
+        let mut headers = HeaderMap::new();
+        for offer in offers {
+            headers.append(SEC_WEBSOCKET_EXTENSIONS, offer.parse().unwrap());
+        }
+        DeflateParams::negotiate(&headers)
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(&[]), None);
+        assert_eq!(negotiate(&["x-webkit-deflate-frame"]), None);
+        assert_eq!(
+            negotiate(&["permessage-deflate; client_max_window_bits"]),
+            Some(DeflateParams::default())
+        );
+        // Offers we can't accept are skipped in favor of later ones.
+        let params = negotiate(&["permessage-deflate; server_max_window_bits=8, \
+                                  permessage-deflate; server_max_window_bits=10; \
+                                  server_no_context_takeover"])
+        .unwrap();
+        assert_eq!(
+            params,
+            DeflateParams {
+                server_no_context_takeover: true,
+                client_no_context_takeover: false,
+                server_max_window_bits: Some(10),
+            }
+        );
+        assert_eq!(
+            params.response_header(),
+            "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+        );
+        assert_eq!(negotiate(&["permessage-deflate; unknown_param"]), None);
+    }
+
+    /// Reads a frame the server wrote, returning its RSV1 bit and payload.
+    async fn read_frame(client: &mut tokio::io::DuplexStream) -> anyhow::Result<(bool, Vec<u8>)> {
+        let mut buf = BytesMut::new();
+        loop {
+            if let Some(header) = FrameHeader::parse(&buf)? {
+                assert!(header.fin);
+                assert_eq!(header.opcode, OPCODE_TEXT);
+                return Ok((header.rsv1, header.payload(&buf)));
+            }
+            let mut chunk = [0; 8192];
+            let n = client.read(&mut chunk).await?;
+            anyhow::ensure!(n > 0, "Server closed the connection");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compressed_messages() -> anyhow::Result<()> {
+        let (mut client, server) = tokio::io::duplex(1 << 20);
+        let server = PerMessageDeflate::new(server, Some(DeflateParams::default()));
+        let mut server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let mut client_compress = Compress::new(Compression::default(), false);
+        let mut client_decompress = Decompress::new(false);
+
+        for i in 0..2 {
+            // A compressed message from the client, split across two frames.
+            let text = format!("{{\"type\":\"ModifyQuerySet\",\"i\":{i}}}").repeat(100);
+            let mut compressed = Vec::with_capacity(text.len() + 64);
+            client_compress.compress_vec(text.as_bytes(), &mut compressed, FlushCompress::Sync)?;
+            assert!(compressed.ends_with(&DEFLATE_TRAILER));
+            compressed.truncate(compressed.len() - DEFLATE_TRAILER.len());
+            let (first, second) = compressed.split_at(compressed.len() / 2);
+            let mask = [1, 2, 3, 4];
+            for (opcode, fin, rsv1, part) in
+                [(OPCODE_TEXT, false, true, first), (0, true, false, second)]
+            {
+                let mut frame = BytesMut::new();
+                frame.extend_from_slice(&[
+                    if fin { 0x80 } else { 0 } | if rsv1 { 0x40 } else { 0 } | opcode,
+                    0x80 | 126,
+                ]);
+                frame.extend_from_slice(&(part.len() as u16).to_be_bytes());
+                frame.extend_from_slice(&mask);
+                frame.extend(part.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+                client.write_all(&frame).await?;
+            }
+            assert_eq!(server.next().await.unwrap()?, Message::Text(text));
+
+            // Large messages from the server are compressed, and small ones aren't.
+            let text = format!("{{\"type\":\"Transition\",\"i\":{i}}}").repeat(100);
+            server.send(Message::Text(text.clone())).await?;
+            let (rsv1, mut payload) = read_frame(&mut client).await?;
+            assert!(rsv1);
+            assert!(payload.len() < text.len());
+            payload.extend_from_slice(&DEFLATE_TRAILER);
+            let mut inflated = Vec::with_capacity(text.len() + 64);
+            client_decompress.decompress_vec(&payload, &mut inflated, FlushDecompress::Sync)?;
+            assert_eq!(inflated, text.as_bytes());
+
+            server.send(Message::Text("{}".to_string())).await?;
+            assert_eq!(read_frame(&mut client).await?, (false, b"{}".to_vec()));
+        }
+
+        // Control frames pass through.
+        let mut ping = BytesMut::new();
+        write_frame(&mut ping, 0x9, false, true, b"");
+        client.write_all(&ping).await?;
+        assert_eq!(server.next().await.unwrap()?, Message::Ping(vec![]));
+        Ok(())
+    }
+}
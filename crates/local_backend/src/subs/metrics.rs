@@ -72,6 +72,7 @@ pub fn log_websocket_message_out(message: &ServerMessage, delay: Duration) {
         ServerMessage::AuthError { .. } => "AuthError",
         ServerMessage::FatalError { .. } => "FatalError",
         ServerMessage::Ping { .. } => "Ping",
+        ServerMessage::SessionResumeToken { .. } => "SessionResumeToken",
     };
     let labels = vec![StaticMetricLabel::new("endpoint", endpoint)];
     log_distribution_with_labels(
@@ -130,3 +131,35 @@ pub fn log_debug_sync_protocol_websockets_total(tag: &'static str, delta: i8) {
         .with_label_values(&[tag])
         .add(delta as f64)
 }
+
+register_convex_gauge!(
+    SYNC_SUBSCRIPTIONS_TOTAL,
+    "Number of queries subscribed to over sync WebSockets"
+);
+pub fn log_sync_subscriptions_total(delta: f64) {
+    SYNC_SUBSCRIPTIONS_TOTAL.add(delta)
+}
+
+register_convex_counter!(
+    SYNC_QUERY_INVALIDATIONS_TOTAL,
+    "Count of subscribed query results pushed again because something they read changed"
+);
+pub fn log_sync_query_invalidations(count: u64) {
+    log_counter(&SYNC_QUERY_INVALIDATIONS_TOTAL, count)
+}
+
+register_convex_counter!(
+    SYNC_BYTES_PUSHED_TOTAL,
+    "Bytes of messages sent over sync WebSockets"
+);
+pub fn log_sync_bytes_pushed(bytes: u64) {
+    log_counter(&SYNC_BYTES_PUSHED_TOTAL, bytes)
+}
+
+register_convex_counter!(
+    SYNC_RECONNECTS_TOTAL,
+    "Count of sync WebSockets from clients reconnecting to an existing session"
+);
+pub fn log_sync_reconnect() {
+    log_counter(&SYNC_RECONNECTS_TOTAL, 1)
+}
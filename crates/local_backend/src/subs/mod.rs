@@ -1,6 +1,10 @@
-use std::time::{
-    Duration,
-    Instant,
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 use ::errors::{
@@ -10,12 +14,7 @@ use ::errors::{
 use anyhow::Context as _;
 use axum::{
     extract::{
-        ws::{
-            CloseFrame,
-            Message,
-            WebSocket,
-            WebSocketUpgrade,
-        },
+        ConnectInfo,
         State,
     },
     response::IntoResponse,
@@ -42,12 +41,13 @@ use futures::{
     SinkExt,
     StreamExt,
 };
+use http::HeaderMap;
 use parking_lot::Mutex;
-use runtime::prod::ProdRuntime;
 use sentry::SentryFutureExt;
 use serde_json::Value as JsonValue;
 use sync::{
     worker::measurable_unbounded_channel,
+    ResumableSessions,
     ServerMessage,
     SyncWorker,
     SyncWorkerConfig,
@@ -57,8 +57,14 @@ use sync_types::{
     SessionId,
 };
 use tokio::sync::mpsc;
+use tungstenite::Message;
 
+use crate::admin_key_audit_log::audit_sync_socket_admin_keys;
+
+mod deflate;
 mod metrics;
+pub mod stats;
+mod upgrade;
 
 use metrics::{
     log_debug_sync_protocol_websockets_total,
@@ -75,6 +81,8 @@ use metrics::{
     websocket_upgrade_timer,
 };
 
+use self::upgrade::SyncSocket;
+pub use self::upgrade::SyncUpgrade;
 use crate::RouterState;
 
 /// How often heartbeat pings are sent.
@@ -132,12 +140,14 @@ impl Drop for DebugSyncSocketDropToken {
 async fn run_sync_socket(
     st: RouterState,
     host: ResolvedHostname,
+    caller_ip: Option<String>,
     config: SyncWorkerConfig,
-    socket: WebSocket,
+    socket: SyncSocket,
     sentry_scope: sentry::Scope,
     on_connect: Box<dyn FnOnce(SessionId) + Send>,
 ) {
     let _drop_token = SyncSocketDropToken::new();
+    let connection_stats = st.sync_stats.connect(st.runtime.unix_timestamp());
 
     let (mut tx, mut rx) = socket.split();
 
@@ -170,6 +180,7 @@ async fn run_sync_socket(
                             ))
                         })?;
                     log_websocket_message_in();
+                    connection_stats.record_client_message(&body, st.runtime.unix_timestamp());
                     if client_tx.send((body, st.runtime.monotonic_now())).is_err() {
                         break;
                     }
@@ -219,7 +230,10 @@ async fn run_sync_socket(
                     };
                     let delay = st.runtime.monotonic_now() - send_time;
                     log_websocket_message_out(&message, delay);
+                    let now = st.runtime.unix_timestamp();
+                    connection_stats.record_server_message(&message, now);
                     let serialized = serde_json::to_string(&JsonValue::from(message))?;
+                    connection_stats.record_bytes_pushed(serialized.len(), now);
                     if tx.send(Message::Text(serialized)).await.is_err() {
                         break 'top;
                     }
@@ -240,7 +254,7 @@ async fn run_sync_socket(
             server_tx,
             on_connect,
         );
-        let r = sync_worker.go().await;
+        let r = audit_sync_socket_admin_keys(&st, caller_ip, sync_worker.go()).await;
         identity_version = Some(sync_worker.identity_version());
         // Explicit drop for emphasis: dropping triggers send_messages to complete.
         drop(sync_worker);
@@ -296,12 +310,7 @@ async fn run_sync_socket(
             if let Some(label) = err.metric_server_error_label() {
                 log_websocket_server_error(label);
             }
-            // Convert from tungstenite::Message to axum::Message
-            let close_frame = err.close_frame().map(|cf| CloseFrame {
-                code: cf.code.into(),
-                reason: cf.reason,
-            });
-            Some(Message::Close(close_frame))
+            Some(Message::Close(err.close_frame()))
         },
     };
     // Similarly, only do a best effort send of the close message.
@@ -329,29 +338,34 @@ async fn run_sync_socket(
     log_websocket_closed();
 }
 
-fn new_sync_worker_config(client_version: ClientVersion) -> anyhow::Result<SyncWorkerConfig> {
-    Ok(SyncWorkerConfig { client_version })
+fn new_sync_worker_config(
+    client_version: ClientVersion,
+    resumable_sessions: Arc<ResumableSessions>,
+) -> anyhow::Result<SyncWorkerConfig> {
+    Ok(SyncWorkerConfig {
+        client_version,
+        resumable_sessions: Some(resumable_sessions),
+    })
 }
 
 pub async fn sync_handler(
     st: RouterState,
     host: ResolvedHostname,
+    caller_ip: Option<String>,
     client_version: ClientVersion,
-    ws: WebSocketUpgrade,
+    ws: SyncUpgrade,
     on_connect: Box<dyn FnOnce(SessionId) + Send>,
 ) -> Result<impl IntoResponse, HttpResponseError> {
-    let config = new_sync_worker_config(client_version)?;
+    let config = new_sync_worker_config(client_version, st.resumable_sessions.clone())?;
     // Make a copy of the Sentry scope, which contains the request metadata.
     let sentry_scope = sentry::configure_scope(move |s| s.clone());
 
     let upgrade_timer = websocket_upgrade_timer();
     let hub = sentry::Hub::current();
-    Ok(ws.on_upgrade(move |ws: WebSocket| {
+    let runtime = st.runtime.clone();
+    Ok(ws.on_upgrade(&runtime, move |ws: SyncSocket| {
         upgrade_timer.finish();
-        let monitor = ProdRuntime::task_monitor("sync_socket");
-        monitor.instrument(
-            run_sync_socket(st, host, config, ws, sentry_scope, on_connect).bind_hub(hub),
-        )
+        run_sync_socket(st, host, caller_ip, config, ws, sentry_scope, on_connect).bind_hub(hub)
     }))
 }
 
@@ -359,9 +373,24 @@ pub async fn sync(
     State(st): State<RouterState>,
     ExtractResolvedHostname(host): ExtractResolvedHostname,
     ExtractClientVersion(client_version): ExtractClientVersion,
-    ws: WebSocketUpgrade,
+    remote_addr: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    ws: SyncUpgrade,
 ) -> Result<impl IntoResponse, HttpResponseError> {
-    sync_handler(st, host, client_version, ws, Box::new(|_session_id| ())).await
+    let remote_ip = remote_addr.map(|ConnectInfo(addr)| addr.ip());
+    let caller_ip = st
+        .trusted_proxies
+        .client_ip(remote_ip, &headers)
+        .map(|ip| ip.to_string());
+    sync_handler(
+        st,
+        host,
+        caller_ip,
+        client_version,
+        ws,
+        Box::new(|_session_id| ()),
+    )
+    .await
 }
 
 #[cfg(test)]
@@ -0,0 +1,337 @@
+//! Subscription and traffic stats for the sync WebSockets, so operators can
+//! see which connections hold many subscriptions or get invalidated often.
+//!
+//! Each connection counts the queries it's subscribed to, how often their
+//! results are pushed again because something they read changed, and the
+//! bytes sent to it. `GET /api/sync_stats` sums these up across connections,
+//! along with rates over the last minute and how often clients reconnect.
+use std::{
+    collections::{
+        BTreeMap,
+        HashSet,
+        VecDeque,
+    },
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    http::{
+        extract::Json,
+        HttpResponseError,
+    },
+    runtime::{
+        Runtime,
+        UnixTimestamp,
+    },
+};
+use parking_lot::Mutex;
+use serde::Serialize;
+use sync::ServerMessage;
+use sync_types::{
+    ClientMessage,
+    QueryId,
+    QuerySetModification,
+    StateModification,
+};
+
+use super::metrics::{
+    log_sync_bytes_pushed,
+    log_sync_query_invalidations,
+    log_sync_reconnect,
+    log_sync_subscriptions_total,
+};
+use crate::{
+    admin::must_be_admin,
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+/// Rates are over this many seconds.
+const RATE_WINDOW_SECS: u64 = 60;
+/// How many connections `/api/sync_stats` lists, most subscriptions first.
+const TOP_CONNECTIONS: usize = 10;
+
+/// Counts per second over the last `RATE_WINDOW_SECS`.
+#[derive(Default)]
+struct RecentCounter {
+    buckets: VecDeque<(u64, u64)>,
+}
+
+impl RecentCounter {
+    fn add(&mut self, now: UnixTimestamp, count: u64) {
+        let second = now.as_secs();
+        match self.buckets.back_mut() {
+            Some((last_second, total)) if *last_second >= second => *total += count,
+            _ => self.buckets.push_back((second, count)),
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(first_second, _)| first_second + RATE_WINDOW_SECS <= second)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn per_second(&self, now: UnixTimestamp) -> f64 {
+        let second = now.as_secs();
+        let total: u64 = self
+            .buckets
+            .iter()
+            .filter(|(bucket, _)| bucket + RATE_WINDOW_SECS > second)
+            .map(|(_, count)| count)
+            .sum();
+        total as f64 / RATE_WINDOW_SECS as f64
+    }
+}
+
+#[derive(Default)]
+struct Rates {
+    invalidations: RecentCounter,
+    bytes_pushed: RecentCounter,
+    reconnects: RecentCounter,
+}
+
+#[derive(Default)]
+struct ConnectionState {
+    session_id: Option<String>,
+    /// How many times the client says it connected before in this session.
+    connection_count: u32,
+    /// The queries the client has subscribed to.
+    queries: HashSet<QueryId>,
+    /// The subscribed queries that have been sent a result, so later results
+    /// are from invalidations.
+    queries_with_results: HashSet<QueryId>,
+    invalidations: u64,
+    bytes_pushed: u64,
+}
+
+/// Stats for every open sync WebSocket.
+#[derive(Default)]
+pub struct SyncStats {
+    next_connection_id: AtomicU64,
+    connections: Mutex<BTreeMap<u64, (UnixTimestamp, Arc<Mutex<ConnectionState>>)>>,
+    rates: Mutex<Rates>,
+    total_invalidations: AtomicU64,
+    total_bytes_pushed: AtomicU64,
+    total_reconnects: AtomicU64,
+}
+
+impl SyncStats {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Starts tracking a connection until the returned handle is dropped.
+    pub fn connect(self: &Arc<Self>, now: UnixTimestamp) -> SyncConnectionStats {
+        let id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let state = Arc::new(Mutex::new(ConnectionState::default()));
+        self.connections.lock().insert(id, (now, state.clone()));
+        SyncConnectionStats {
+            stats: self.clone(),
+            id,
+            state,
+        }
+    }
+
+    pub fn summary(&self, now: UnixTimestamp) -> SyncStatsSummary {
+        let mut connections: Vec<_> = self
+            .connections
+            .lock()
+            .iter()
+            .map(|(id, (connected_at, state))| {
+                let state = state.lock();
+                ConnectionSummary {
+                    connection_id: *id,
+                    session_id: state.session_id.clone(),
+                    connected_at: connected_at.as_secs_f64(),
+                    connection_count: state.connection_count,
+                    subscriptions: state.queries.len(),
+                    invalidations: state.invalidations,
+                    bytes_pushed: state.bytes_pushed,
+                }
+            })
+            .collect();
+        let num_connections = connections.len();
+        let subscriptions = connections.iter().map(|c| c.subscriptions).sum();
+        connections.sort_by(|a, b| b.subscriptions.cmp(&a.subscriptions));
+        connections.truncate(TOP_CONNECTIONS);
+        let rates = self.rates.lock();
+        SyncStatsSummary {
+            connections: num_connections,
+            subscriptions,
+            invalidations_per_second: rates.invalidations.per_second(now),
+            bytes_pushed_per_second: rates.bytes_pushed.per_second(now),
+            reconnects_per_second: rates.reconnects.per_second(now),
+            total_invalidations: self.total_invalidations.load(Ordering::Relaxed),
+            total_bytes_pushed: self.total_bytes_pushed.load(Ordering::Relaxed),
+            total_reconnects: self.total_reconnects.load(Ordering::Relaxed),
+            top_connections: connections,
+        }
+    }
+}
+
+/// Records the traffic of one sync WebSocket.
+pub struct SyncConnectionStats {
+    stats: Arc<SyncStats>,
+    id: u64,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl SyncConnectionStats {
+    pub fn record_client_message(&self, message: &ClientMessage, now: UnixTimestamp) {
+        let mut state = self.state.lock();
+        match message {
+            ClientMessage::Connect {
+                session_id,
+                connection_count,
+                ..
+            } => {
+                state.session_id = Some(session_id.to_string());
+                state.connection_count = *connection_count;
+                if *connection_count > 0 {
+                    log_sync_reconnect();
+                    self.stats.total_reconnects.fetch_add(1, Ordering::Relaxed);
+                    self.stats.rates.lock().reconnects.add(now, 1);
+                }
+            },
+            ClientMessage::ModifyQuerySet { modifications, .. } => {
+                let before = state.queries.len();
+                for modification in modifications {
+                    match modification {
+                        QuerySetModification::Add(query) => {
+                            state.queries.insert(query.query_id);
+                        },
+                        QuerySetModification::Remove { query_id } => {
+                            state.queries.remove(query_id);
+                            state.queries_with_results.remove(query_id);
+                        },
+                    }
+                }
+                log_sync_subscriptions_total(state.queries.len() as f64 - before as f64);
+            },
+            _ => {},
+        }
+    }
+
+    /// Counts the query results in `message` that were pushed again after
+    /// an invalidation.
+    pub fn record_server_message(&self, message: &ServerMessage, now: UnixTimestamp) {
+        let ServerMessage::Transition { modifications, .. } = message else {
+            return;
+        };
+        let mut invalidations = 0;
+        {
+            let mut state = self.state.lock();
+            for modification in modifications {
+                match modification {
+                    StateModification::QueryUpdated { query_id, .. }
+                    | StateModification::QueryFailed { query_id, .. } => {
+                        if !state.queries_with_results.insert(*query_id) {
+                            invalidations += 1;
+                        }
+                    },
+                    StateModification::QueryRemoved { query_id } => {
+                        state.queries_with_results.remove(query_id);
+                    },
+                }
+            }
+            state.invalidations += invalidations;
+        }
+        if invalidations == 0 {
+            return;
+        }
+        log_sync_query_invalidations(invalidations);
+        self.stats
+            .total_invalidations
+            .fetch_add(invalidations, Ordering::Relaxed);
+        self.stats
+            .rates
+            .lock()
+            .invalidations
+            .add(now, invalidations);
+    }
+
+    pub fn record_bytes_pushed(&self, bytes: usize, now: UnixTimestamp) {
+        let bytes = bytes as u64;
+        self.state.lock().bytes_pushed += bytes;
+        log_sync_bytes_pushed(bytes);
+        self.stats
+            .total_bytes_pushed
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.stats.rates.lock().bytes_pushed.add(now, bytes);
+    }
+}
+
+impl Drop for SyncConnectionStats {
+    fn drop(&mut self) {
+        self.stats.connections.lock().remove(&self.id);
+        log_sync_subscriptions_total(-(self.state.lock().queries.len() as f64));
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionSummary {
+    connection_id: u64,
+    session_id: Option<String>,
+    connected_at: f64,
+    connection_count: u32,
+    subscriptions: usize,
+    invalidations: u64,
+    bytes_pushed: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatsSummary {
+    connections: usize,
+    subscriptions: usize,
+    invalidations_per_second: f64,
+    bytes_pushed_per_second: f64,
+    reconnects_per_second: f64,
+    total_invalidations: u64,
+    total_bytes_pushed: u64,
+    total_reconnects: u64,
+    top_connections: Vec<ConnectionSummary>,
+}
+
+pub async fn get_sync_stats(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let now = st.application.runtime().unix_timestamp();
+    Ok(Json(st.sync_stats.summary(now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use common::runtime::UnixTimestamp;
+
+    use super::RecentCounter;
+
+    #[test]
+    fn test_recent_counter() {
+        let mut counter = RecentCounter::default();
+        counter.add(UnixTimestamp::from_millis(1_000), 30);
+        counter.add(UnixTimestamp::from_millis(1_500), 30);
+        counter.add(UnixTimestamp::from_millis(30_000), 60);
+        assert_eq!(counter.per_second(UnixTimestamp::from_millis(30_000)), 2.0);
+        // The first second is out of the window.
+        assert_eq!(counter.per_second(UnixTimestamp::from_millis(61_000)), 1.0);
+        counter.add(UnixTimestamp::from_millis(120_000), 6);
+        assert_eq!(counter.buckets.len(), 1);
+        assert_eq!(counter.per_second(UnixTimestamp::from_millis(120_000)), 0.1);
+    }
+}
@@ -0,0 +1,155 @@
+//! The WebSocket handshake for the sync protocol.
+//!
+//! Axum's `WebSocketUpgrade` can't negotiate extensions, so `SyncUpgrade`
+//! does the handshake itself and runs the socket over `PerMessageDeflate`.
+use ::errors::ErrorMetadata;
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::FromRequestParts,
+    response::Response,
+};
+use common::{
+    http::HttpResponseError,
+    knobs::RESPONSE_COMPRESSION_ENABLED,
+    runtime::Runtime,
+};
+use futures::Future;
+use http::{
+    header::{
+        CONNECTION,
+        SEC_WEBSOCKET_ACCEPT,
+        SEC_WEBSOCKET_EXTENSIONS,
+        SEC_WEBSOCKET_KEY,
+        SEC_WEBSOCKET_VERSION,
+        UPGRADE,
+    },
+    request::Parts,
+    HeaderMap,
+    HeaderValue,
+    Method,
+    StatusCode,
+};
+use hyper::upgrade::OnUpgrade;
+use hyper_util::rt::TokioIo;
+use runtime::prod::ProdRuntime;
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::{
+    handshake::derive_accept_key,
+    protocol::Role,
+};
+
+use super::deflate::{
+    DeflateParams,
+    PerMessageDeflate,
+};
+
+pub type SyncSocket = WebSocketStream<PerMessageDeflate<TokioIo<hyper::upgrade::Upgraded>>>;
+
+/// A request to upgrade to a sync protocol WebSocket.
+pub struct SyncUpgrade {
+    on_upgrade: OnUpgrade,
+    accept_key: HeaderValue,
+    deflate: Option<DeflateParams>,
+}
+
+fn header_contains(headers: &HeaderMap, name: http::HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+fn invalid_upgrade(msg: &'static str) -> HttpResponseError {
+    anyhow::anyhow!(ErrorMetadata::bad_request("InvalidWebSocketUpgrade", msg)).into()
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SyncUpgrade
+where
+    S: Send + Sync,
+{
+    type Rejection = HttpResponseError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if parts.method != Method::GET {
+            return Err(invalid_upgrade("WebSocket upgrades must use GET"));
+        }
+        if !header_contains(&parts.headers, CONNECTION, "upgrade")
+            || !header_contains(&parts.headers, UPGRADE, "websocket")
+        {
+            return Err(invalid_upgrade("Expected a WebSocket upgrade request"));
+        }
+        if parts
+            .headers
+            .get(SEC_WEBSOCKET_VERSION)
+            .map(|v| v.as_bytes())
+            != Some(b"13")
+        {
+            return Err(invalid_upgrade("Unsupported WebSocket version"));
+        }
+        let Some(key) = parts.headers.get(SEC_WEBSOCKET_KEY) else {
+            return Err(invalid_upgrade("Missing Sec-WebSocket-Key header"));
+        };
+        let accept_key = HeaderValue::from_str(&derive_accept_key(key.as_bytes()))
+            .expect("Accept key is base64");
+        let deflate = if *RESPONSE_COMPRESSION_ENABLED {
+            DeflateParams::negotiate(&parts.headers)
+        } else {
+            None
+        };
+        let on_upgrade = parts
+            .extensions
+            .remove::<OnUpgrade>()
+            .ok_or_else(|| invalid_upgrade("Connection can't be upgraded"))?;
+        Ok(Self {
+            on_upgrade,
+            accept_key,
+            deflate,
+        })
+    }
+}
+
+impl SyncUpgrade {
+    /// Responds to the upgrade request and runs `callback` on the socket once
+    /// the connection is upgraded.
+    pub fn on_upgrade<Fut>(
+        self,
+        runtime: &ProdRuntime,
+        callback: impl FnOnce(SyncSocket) -> Fut + Send + 'static,
+    ) -> Response
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let Self {
+            on_upgrade,
+            accept_key,
+            deflate,
+        } = self;
+        runtime.spawn("sync_socket", async move {
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    tracing::warn!("Failed to upgrade sync socket: {e}");
+                    return;
+                },
+            };
+            let io = PerMessageDeflate::new(TokioIo::new(upgraded), deflate);
+            let socket = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+            callback(socket).await;
+        });
+        let mut response = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(CONNECTION, "upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_ACCEPT, accept_key);
+        if let Some(deflate) = deflate {
+            response = response.header(SEC_WEBSOCKET_EXTENSIONS, deflate.response_header());
+        }
+        response
+            .body(Body::empty())
+            .expect("Upgrade response is valid")
+    }
+}
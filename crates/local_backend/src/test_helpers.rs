@@ -29,7 +29,10 @@ use tower::ServiceExt;
 use crate::{
     config::LocalConfig,
     make_app,
-    router::router,
+    router::{
+        admin_metrics_routes,
+        router,
+    },
     LocalAppState,
     MAX_CONCURRENT_REQUESTS,
 };
@@ -53,8 +56,9 @@ pub async fn setup_backend_for_test(runtime: ProdRuntime) -> anyhow::Result<Test
         ShutdownSignal::new(preempt_tx, config.name()),
     )
     .await?;
-    let router = router(st.clone());
-    let app = ConvexHttpService::new(
+    // Serve the routes like the public listener does without `--admin-port`.
+    let router = router(st.clone()).merge(admin_metrics_routes(st.clone()));
+    let mut app = ConvexHttpService::new(
         router,
         "backend_test",
         SERVER_VERSION_STR.to_string(),
@@ -62,6 +66,7 @@ pub async fn setup_backend_for_test(runtime: ProdRuntime) -> anyhow::Result<Test
         Duration::from_secs(125),
         NoopRouteMapper,
     );
+    app.set_metrics_route_enabled(false);
     let admin_auth_header = config
         .key_broker()?
         .issue_admin_key(MemberId(2))
@@ -74,6 +79,16 @@ pub async fn setup_backend_for_test(runtime: ProdRuntime) -> anyhow::Result<Test
 }
 
 impl TestLocalBackend {
+    /// Sends a request and returns the response as is, e.g. to read a
+    /// streamed body.
+    pub async fn send(
+        &self,
+        req: Request<axum::body::Body>,
+    ) -> anyhow::Result<http::Response<axum::body::Body>> {
+        tracing::info!("Sending req {req:?}");
+        Ok(self.app.router().clone().oneshot(req).await?)
+    }
+
     pub async fn expect_success<T: DeserializeOwned>(
         &self,
         req: Request<axum::body::Body>,
@@ -0,0 +1,215 @@
+//! Certificates for the backend's TLS listener, picked by SNI.
+//!
+//! Custom domains with their own certificate use it. Other domains use
+//! certificates issued by ACME (see [`crate::acme`]), which are swapped out
+//! here as they're renewed without restarting the listener.
+//!
+//! `--admin-port` can also serve HTTPS, with a fixed certificate, and require
+//! client certificates on its admin routes.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use common::http::{
+    ClientCertificate,
+    HttpResponseError,
+    ACME_TLS_ALPN_PROTOCOL,
+};
+use errors::ErrorMetadata;
+use parking_lot::RwLock;
+use rustls::{
+    server::{
+        ClientHello,
+        ResolvesServerCert,
+        WebPkiClientVerifier,
+    },
+    sign::CertifiedKey,
+    RootCertStore,
+    ServerConfig,
+};
+use tokio_rustls::TlsAcceptor;
+
+use crate::custom_domains::CustomDomains;
+
+#[derive(Debug)]
+pub struct TlsCertificates {
+    custom_domains: Arc<CustomDomains>,
+    /// Lowercase domain -> its certificate from ACME.
+    issued: RwLock<BTreeMap<String, Arc<CertifiedKey>>>,
+    /// Lowercase domain -> self-signed certificate answering its in-progress
+    /// `tls-alpn-01` challenge.
+    challenges: RwLock<BTreeMap<String, Arc<CertifiedKey>>>,
+}
+
+impl TlsCertificates {
+    pub fn new(custom_domains: Arc<CustomDomains>) -> Arc<Self> {
+        Arc::new(Self {
+            custom_domains,
+            issued: RwLock::new(BTreeMap::new()),
+            challenges: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn set_issued(&self, domain: &str, certified_key: Arc<CertifiedKey>) {
+        self.issued
+            .write()
+            .insert(domain.to_ascii_lowercase(), certified_key);
+    }
+
+    pub fn set_challenge(&self, domain: &str, certified_key: Arc<CertifiedKey>) {
+        self.challenges
+            .write()
+            .insert(domain.to_ascii_lowercase(), certified_key);
+    }
+
+    pub fn remove_challenge(&self, domain: &str) {
+        self.challenges.write().remove(&domain.to_ascii_lowercase());
+    }
+
+    pub fn acceptor(self: Arc<Self>) -> TlsAcceptor {
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(self);
+        config.alpn_protocols = vec![
+            b"h2".to_vec(),
+            b"http/1.1".to_vec(),
+            ACME_TLS_ALPN_PROTOCOL.to_vec(),
+        ];
+        TlsAcceptor::from(Arc::new(config))
+    }
+}
+
+impl ResolvesServerCert for TlsCertificates {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let domain = client_hello.server_name()?.to_ascii_lowercase();
+        let is_acme_challenge = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN_PROTOCOL));
+        if is_acme_challenge {
+            return self.challenges.read().get(&domain).cloned();
+        }
+        self.custom_domains
+            .certified_key(&domain)
+            .or_else(|| self.issued.read().get(&domain).cloned())
+    }
+}
+
+/// Terminates TLS for `--admin-port` with the certificate chain at
+/// `cert_path`. With `client_ca_path`, clients may present a certificate signed
+/// by one of the CAs in it.
+pub fn admin_acceptor(
+    cert_path: &Path,
+    key_path: &Path,
+    client_ca_path: Option<&Path>,
+) -> anyhow::Result<TlsAcceptor> {
+    let cert_pem = fs::read(cert_path).with_context(|| format!("Failed to read {cert_path:?}"))?;
+    let key_pem = fs::read(key_path).with_context(|| format!("Failed to read {key_path:?}"))?;
+    let certs = rustls_pemfile::certs(&mut &cert_pem[..]).collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut &key_pem[..])?.context("No private key found")?;
+    let builder = ServerConfig::builder();
+    let builder = match client_ca_path {
+        Some(client_ca_path) => {
+            let ca_pem = fs::read(client_ca_path)
+                .with_context(|| format!("Failed to read {client_ca_path:?}"))?;
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut &ca_pem[..]) {
+                roots.add(cert?)?;
+            }
+            // Connections without a certificate still complete the handshake,
+            // so the router can reject their admin requests with an error
+            // saying why, and the public routes keep working without one.
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .allow_unauthenticated()
+                .build()?;
+            builder.with_client_cert_verifier(verifier)
+        },
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Rejects requests on connections that didn't present a verified client
+/// certificate, if `required`.
+pub async fn client_certificate_middleware(
+    State(required): State<bool>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if required && req.extensions().get::<ClientCertificate>().is_none() {
+        let error = anyhow::anyhow!(ErrorMetadata::forbidden(
+            "ClientCertificateRequired",
+            "This route requires a client certificate signed by the admin client CA",
+        ));
+        return HttpResponseError::from(error).into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        routing::get,
+        Router,
+    };
+    use common::http::{
+        ClientCertificate,
+        HttpError,
+    };
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use tokio_rustls::rustls::pki_types::CertificateDer;
+    use tower::ServiceExt;
+
+    use super::client_certificate_middleware;
+
+    async fn status(required: bool, with_certificate: bool) -> anyhow::Result<StatusCode> {
+        let router = Router::new()
+            .route("/api/check_admin_key", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                required,
+                client_certificate_middleware,
+            ));
+        let mut req = Request::builder()
+            .uri("/api/check_admin_key")
+            .body(Body::empty())?;
+        if with_certificate {
+            req.extensions_mut()
+                .insert(ClientCertificate(CertificateDer::from(vec![0x30, 0x00])));
+        }
+        let response = router.oneshot(req).await?;
+        if response.status() != StatusCode::OK {
+            let error = HttpError::from_response(response).await?;
+            assert_eq!(error.error_code(), "ClientCertificateRequired");
+            return Ok(error.status_code());
+        }
+        Ok(response.status())
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_middleware() -> anyhow::Result<()> {
+        assert_eq!(status(true, false).await?, StatusCode::FORBIDDEN);
+        assert_eq!(status(true, true).await?, StatusCode::OK);
+        assert_eq!(status(false, false).await?, StatusCode::OK);
+        Ok(())
+    }
+}
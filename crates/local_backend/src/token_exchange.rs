@@ -0,0 +1,208 @@
+//! The token exchange endpoint, where services authenticated with a service
+//! account token or admin key get short-lived tokens to act as a user, like
+//! to render a page or run a background job for them. Requests follow RFC
+//! 8693, with the user's token identifier as the subject token.
+use async_trait::async_trait;
+use authentication::token_exchange::{
+    GRANT_TYPE_TOKEN_EXCHANGE,
+    TOKEN_TYPE_JWT,
+    TOKEN_TYPE_TOKEN_IDENTIFIER,
+};
+use axum::{
+    extract::{
+        FromRequest,
+        Request,
+        State,
+    },
+    response::IntoResponse,
+    Form,
+};
+use common::http::{
+    extract::Json,
+    HttpResponseError,
+};
+use errors::ErrorMetadata;
+use http::header::CONTENT_TYPE;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    admin::must_be_admin_with_write_access,
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+/// Arguments for the token exchange grant.
+#[derive(Deserialize)]
+pub struct TokenExchangeArgs {
+    grant_type: String,
+    subject_token: String,
+    subject_token_type: String,
+    requested_token_type: Option<String>,
+}
+
+/// RFC 8693 requests are form encoded, but JSON bodies are accepted too.
+#[async_trait]
+impl<S> FromRequest<S> for TokenExchangeArgs
+where
+    S: Send + Sync,
+{
+    type Rejection = HttpResponseError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_form = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| {
+                content_type.starts_with("application/x-www-form-urlencoded")
+            });
+        if !is_form {
+            let Json(args) = Json::<Self>::from_request(req, state).await?;
+            return Ok(args);
+        }
+        let Form(args) = Form::<Self>::from_request(req, state).await.map_err(|e| {
+            anyhow::anyhow!(ErrorMetadata::bad_request(
+                "BadTokenExchangeRequest",
+                e.body_text()
+            ))
+        })?;
+        Ok(args)
+    }
+}
+
+#[derive(Serialize)]
+pub struct TokenExchangeResponse {
+    access_token: String,
+    issued_token_type: &'static str,
+    token_type: &'static str,
+    expires_in: u64,
+}
+
+/// Exchanges the token identifier of a user for a token to act as them. The
+/// exchange is recorded in the deployment audit log.
+pub async fn exchange_token(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    args: TokenExchangeArgs,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    if args.grant_type != GRANT_TYPE_TOKEN_EXCHANGE {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "UnsupportedGrantType",
+            format!("Only the `{GRANT_TYPE_TOKEN_EXCHANGE}` grant type is supported"),
+        ))
+        .into());
+    }
+    if args.subject_token_type != TOKEN_TYPE_TOKEN_IDENTIFIER {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "UnsupportedSubjectTokenType",
+            format!("The `subject_token_type` must be `{TOKEN_TYPE_TOKEN_IDENTIFIER}`"),
+        ))
+        .into());
+    }
+    if args
+        .requested_token_type
+        .is_some_and(|token_type| token_type != TOKEN_TYPE_JWT)
+    {
+        return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+            "UnsupportedRequestedTokenType",
+            format!("Only tokens of type `{TOKEN_TYPE_JWT}` can be requested"),
+        ))
+        .into());
+    }
+    let (token, ttl) = st
+        .application
+        .exchange_token(identity, args.subject_token)
+        .await?;
+    Ok(Json(TokenExchangeResponse {
+        access_token: token,
+        issued_token_type: TOKEN_TYPE_JWT,
+        // Exchanged tokens are sent like any other user's token.
+        token_type: "Bearer",
+        expires_in: ttl.as_secs(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use authentication::token_exchange::{
+        GRANT_TYPE_TOKEN_EXCHANGE,
+        TOKEN_TYPE_TOKEN_IDENTIFIER,
+    };
+    use axum_extra::headers::authorization::Credentials;
+    use http::{
+        Request,
+        StatusCode,
+    };
+    use runtime::prod::ProdRuntime;
+    use serde_json::json;
+
+    use crate::test_helpers::{
+        setup_backend_for_test,
+        TestLocalBackend,
+    };
+
+    const UNKNOWN_USER: &str = "https://unknown.example.com|user1";
+
+    fn form_request(
+        backend: &TestLocalBackend,
+        admin: bool,
+    ) -> anyhow::Result<Request<axum::body::Body>> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", GRANT_TYPE_TOKEN_EXCHANGE)
+            .append_pair("subject_token", UNKNOWN_USER)
+            .append_pair("subject_token_type", TOKEN_TYPE_TOKEN_IDENTIFIER)
+            .finish();
+        let mut req = Request::builder()
+            .uri("/api/token_exchange")
+            .method("POST")
+            .header("Content-Type", "application/x-www-form-urlencoded");
+        if admin {
+            req = req.header("Authorization", backend.admin_auth_header.0.encode());
+        }
+        Ok(req.body(body.into())?)
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_token_exchange_requires_admin(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend
+            .expect_error(
+                form_request(&backend, false)?,
+                StatusCode::FORBIDDEN,
+                "BadDeployKey",
+            )
+            .await
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_token_exchange_unknown_issuer(rt: ProdRuntime) -> anyhow::Result<()> {
+        let backend = setup_backend_for_test(rt).await?;
+        backend
+            .expect_error(
+                form_request(&backend, true)?,
+                StatusCode::BAD_REQUEST,
+                "UnknownSubjectIssuer",
+            )
+            .await?;
+
+        // The same request as JSON.
+        let body = json!({
+            "grant_type": GRANT_TYPE_TOKEN_EXCHANGE,
+            "subject_token": UNKNOWN_USER,
+            "subject_token_type": TOKEN_TYPE_TOKEN_IDENTIFIER,
+        });
+        let req = Request::builder()
+            .uri("/api/token_exchange")
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Authorization", backend.admin_auth_header.0.encode())
+            .body(serde_json::to_vec(&body)?.into())?;
+        backend
+            .expect_error(req, StatusCode::BAD_REQUEST, "UnknownSubjectIssuer")
+            .await
+    }
+}
@@ -0,0 +1,145 @@
+//! Endpoints for revoking user tokens before they expire, by their `jti`
+//! claim or all of a user's tokens at once.
+use std::time::SystemTime;
+
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    document::ParsedDocument,
+    http::{
+        extract::{
+            Json,
+            Path,
+        },
+        HttpResponseError,
+    },
+};
+use errors::ErrorMetadata;
+use http::StatusCode;
+use model::token_revocations::types::{
+    RevokedTokens,
+    TokenRevocation,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sync_types::UserIdentifier;
+
+use crate::{
+    admin::{
+        must_be_admin,
+        must_be_admin_with_write_access,
+    },
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+#[derive(Deserialize)]
+pub struct TokenRevocationPath {
+    id: String,
+}
+
+/// Either `tokenId`, or the `issuer` and `subject` of a user.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokeTokensArgs {
+    token_id: Option<String>,
+    issuer: Option<String>,
+    subject: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenRevocationJson {
+    id: String,
+    token_id: Option<String>,
+    token_identifier: Option<String>,
+    revoked_at_secs: u64,
+}
+
+impl TryFrom<ParsedDocument<TokenRevocation>> for TokenRevocationJson {
+    type Error = anyhow::Error;
+
+    fn try_from(revocation: ParsedDocument<TokenRevocation>) -> anyhow::Result<Self> {
+        let id = revocation.id().developer_id.encode();
+        let revocation = revocation.into_value();
+        let (token_id, token_identifier) = match revocation.revoked {
+            RevokedTokens::TokenId(token_id) => (Some(token_id), None),
+            RevokedTokens::User(token_identifier) => (None, Some(token_identifier)),
+        };
+        Ok(Self {
+            id,
+            token_id,
+            token_identifier,
+            revoked_at_secs: revocation
+                .revoked_at
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTokenRevocationsResponse {
+    revocations: Vec<TokenRevocationJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokeTokensResponse {
+    id: String,
+}
+
+pub async fn list_token_revocations(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin(&identity)?;
+    let revocations = st
+        .application
+        .list_token_revocations(identity)
+        .await?
+        .into_iter()
+        .map(TokenRevocationJson::try_from)
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Json(ListTokenRevocationsResponse { revocations }))
+}
+
+pub async fn revoke_tokens(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Json(args): Json<RevokeTokensArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    let revoked = match (args.token_id, args.issuer, args.subject) {
+        (Some(token_id), None, None) => RevokedTokens::TokenId(token_id),
+        (None, Some(issuer), Some(subject)) => {
+            RevokedTokens::User(UserIdentifier::construct(&issuer, &subject).0)
+        },
+        _ => {
+            return Err(anyhow::anyhow!(ErrorMetadata::bad_request(
+                "InvalidTokenRevocation",
+                "Expected either `tokenId`, or a user's `issuer` and `subject`",
+            ))
+            .into())
+        },
+    };
+    let id = st.application.revoke_tokens(identity, revoked).await?;
+    Ok(Json(RevokeTokensResponse { id }))
+}
+
+pub async fn delete_token_revocation(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Path(TokenRevocationPath { id }): Path<TokenRevocationPath>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    must_be_admin_with_write_access(&identity)?;
+    st.application
+        .delete_token_revocation(identity, &id)
+        .await?;
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,461 @@
+//! Meters the resources each function uses. Usage events are added up in
+//! memory and flushed to `_usage_records` as hourly totals per function and
+//! table, which the dashboard reads back for chargeback and capacity planning.
+use std::{
+    collections::BTreeMap,
+    mem,
+    sync::Arc,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use application::Application;
+use async_trait::async_trait;
+use axum::{
+    extract::State,
+    response::IntoResponse,
+};
+use common::{
+    errors::report_error,
+    http::{
+        extract::{
+            Json,
+            Query,
+        },
+        HttpResponseError,
+    },
+    knobs::USAGE_RECORD_RETENTION,
+    runtime::Runtime,
+};
+use events::usage::{
+    UsageEvent,
+    UsageEventLogger,
+};
+use model::usage_records::types::{
+    UsageCounts,
+    UsageKey,
+    UsageRecord,
+};
+use parking_lot::Mutex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    authentication::ExtractIdentity,
+    LocalAppState,
+};
+
+/// How often the usage added up in memory is written to `_usage_records`.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Most expired usage records to delete in one transaction.
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// Adds up usage events until they're flushed to `_usage_records`.
+#[derive(Clone, Debug, Default)]
+pub struct UsageRecorder {
+    pending: Arc<Mutex<BTreeMap<UsageKey, UsageCounts>>>,
+}
+
+impl UsageRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flushes the recorded usage every `FLUSH_INTERVAL` and deletes the
+    /// records older than `USAGE_RECORD_RETENTION`.
+    pub async fn go<RT: Runtime>(self, application: Application<RT>) {
+        loop {
+            application.runtime().wait(FLUSH_INTERVAL).await;
+            if let Err(mut e) = self.flush(&application).await {
+                report_error(&mut e.context("Failed to flush usage records")).await;
+            }
+        }
+    }
+
+    /// Writes the usage recorded since the last flush to `_usage_records`,
+    /// attributing it to the current hour.
+    pub async fn flush<RT: Runtime>(&self, application: &Application<RT>) -> anyhow::Result<()> {
+        let now = application.runtime().system_time();
+        let usage = mem::take(&mut *self.pending.lock());
+        if !usage.is_empty() {
+            if let Err(e) = application
+                .record_usage(hour_start(now)?, usage.clone())
+                .await
+            {
+                // Keep the usage to try again on the next flush.
+                let mut pending = self.pending.lock();
+                for (key, counts) in usage {
+                    pending.entry(key).or_default().add(&counts);
+                }
+                return Err(e);
+            }
+        }
+        let cutoff = now - *USAGE_RECORD_RETENTION;
+        while application
+            .delete_usage_records_before(cutoff, DELETE_BATCH_SIZE)
+            .await?
+            == DELETE_BATCH_SIZE
+        {}
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UsageEventLogger for UsageRecorder {
+    fn record(&self, events: Vec<UsageEvent>) {
+        let mut pending = self.pending.lock();
+        for event in events {
+            if let Some((key, counts)) = event_usage(event) {
+                pending.entry(key).or_default().add(&counts);
+            }
+        }
+    }
+
+    async fn record_async(&self, events: Vec<UsageEvent>) {
+        self.record(events)
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// What `event` is attributed to and the usage it adds. The `Current*`
+/// storage snapshots aren't metered.
+fn event_usage(event: UsageEvent) -> Option<(UsageKey, UsageCounts)> {
+    let function_key = |component_path, udf_id| UsageKey {
+        component_path,
+        udf_id: Some(udf_id),
+        table_name: None,
+    };
+    let usage = match event {
+        UsageEvent::FunctionCall { fields } => {
+            if !fields.is_tracked {
+                return None;
+            }
+            (
+                function_key(fields.component_path, fields.udf_id),
+                UsageCounts {
+                    function_calls: 1,
+                    action_compute_mb_ms: fields
+                        .memory_megabytes
+                        .saturating_mul(fields.duration_millis),
+                    ..Default::default()
+                },
+            )
+        },
+        UsageEvent::FunctionStorageCalls {
+            component_path,
+            udf_id,
+            count,
+            ..
+        } => (
+            function_key(component_path, udf_id),
+            UsageCounts {
+                storage_calls: count,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::FunctionStorageBandwidth {
+            component_path,
+            udf_id,
+            ingress,
+            egress,
+            ..
+        } => (
+            function_key(component_path, udf_id),
+            UsageCounts {
+                storage_ingress_bytes: ingress,
+                storage_egress_bytes: egress,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::StorageCall { component_path, .. } => (
+            UsageKey {
+                component_path,
+                udf_id: None,
+                table_name: None,
+            },
+            UsageCounts {
+                storage_calls: 1,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::StorageBandwidth {
+            component_path,
+            ingress,
+            egress,
+            ..
+        } => (
+            UsageKey {
+                component_path,
+                udf_id: None,
+                table_name: None,
+            },
+            UsageCounts {
+                storage_ingress_bytes: ingress,
+                storage_egress_bytes: egress,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::DatabaseBandwidth {
+            component_path,
+            udf_id,
+            table_name,
+            ingress,
+            egress,
+            egress_rows,
+            ..
+        } => (
+            UsageKey {
+                component_path,
+                udf_id: Some(udf_id),
+                table_name: Some(table_name),
+            },
+            UsageCounts {
+                database_ingress_bytes: ingress,
+                database_egress_bytes: egress,
+                database_egress_rows: egress_rows,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::VectorBandwidth {
+            component_path,
+            udf_id,
+            table_name,
+            ingress,
+            egress,
+            ..
+        } => (
+            UsageKey {
+                component_path,
+                udf_id: Some(udf_id),
+                table_name: Some(table_name),
+            },
+            UsageCounts {
+                vector_ingress_bytes: ingress,
+                vector_egress_bytes: egress,
+                ..Default::default()
+            },
+        ),
+        UsageEvent::CurrentVectorStorage { .. }
+        | UsageEvent::CurrentDatabaseStorage { .. }
+        | UsageEvent::CurrentFileStorage { .. }
+        | UsageEvent::CurrentDocumentCounts { .. } => return None,
+    };
+    Some(usage)
+}
+
+fn hour_start(ts: SystemTime) -> anyhow::Result<SystemTime> {
+    let since_epoch = ts.duration_since(SystemTime::UNIX_EPOCH)?;
+    let hours = since_epoch.as_secs() / HOUR.as_secs();
+    Ok(SystemTime::UNIX_EPOCH + HOUR * hours as u32)
+}
+
+/// Adds up `records` by `key`.
+fn sum_by<K: Ord>(
+    records: Vec<UsageRecord>,
+    key: impl Fn(UsageKey) -> Option<K>,
+) -> BTreeMap<K, UsageCounts> {
+    let mut totals: BTreeMap<K, UsageCounts> = BTreeMap::new();
+    for record in records {
+        if let Some(key) = key(record.key) {
+            totals.entry(key).or_default().add(&record.counts);
+        }
+    }
+    totals
+}
+
+#[derive(Deserialize)]
+pub(crate) struct UsageQueryArgs {
+    /// Milliseconds since the Unix epoch. Usage is recorded per hour, so the
+    /// usage of the hours starting in `[start, end)` is returned.
+    start: u64,
+    end: u64,
+}
+
+impl UsageQueryArgs {
+    fn range(&self) -> (SystemTime, SystemTime) {
+        (
+            SystemTime::UNIX_EPOCH + Duration::from_millis(self.start),
+            SystemTime::UNIX_EPOCH + Duration::from_millis(self.end),
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FunctionUsageJson {
+    component_path: Option<String>,
+    /// `None` for the usage outside of functions, like snapshot imports.
+    function: Option<String>,
+    function_calls: u64,
+    database_ingress_bytes: u64,
+    database_egress_bytes: u64,
+    database_egress_rows: u64,
+    storage_calls: u64,
+    storage_ingress_bytes: u64,
+    storage_egress_bytes: u64,
+    /// Action compute in GB-hours: memory in GB times run time in hours.
+    action_compute_gb_hours: f64,
+    vector_ingress_bytes: u64,
+    vector_egress_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TableUsageJson {
+    component_path: Option<String>,
+    table_name: String,
+    database_ingress_bytes: u64,
+    database_egress_bytes: u64,
+    database_egress_rows: u64,
+    vector_ingress_bytes: u64,
+    vector_egress_bytes: u64,
+}
+
+/// Each function's usage over the requested hours.
+pub(crate) async fn usage_by_function(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(args): Query<UsageQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let (start, end) = args.range();
+    let records = st.application.usage_records(identity, start, end).await?;
+    let usage: Vec<_> = sum_by(records, |key| Some((key.component_path, key.udf_id)))
+        .into_iter()
+        .map(|((component_path, function), counts)| FunctionUsageJson {
+            component_path,
+            function,
+            function_calls: counts.function_calls,
+            database_ingress_bytes: counts.database_ingress_bytes,
+            database_egress_bytes: counts.database_egress_bytes,
+            database_egress_rows: counts.database_egress_rows,
+            storage_calls: counts.storage_calls,
+            storage_ingress_bytes: counts.storage_ingress_bytes,
+            storage_egress_bytes: counts.storage_egress_bytes,
+            action_compute_gb_hours: counts.action_compute_mb_ms as f64
+                / 1024.
+                / HOUR.as_millis() as f64,
+            vector_ingress_bytes: counts.vector_ingress_bytes,
+            vector_egress_bytes: counts.vector_egress_bytes,
+        })
+        .collect();
+    Ok(Json(usage))
+}
+
+/// Each table's database and vector index bandwidth over the requested hours.
+pub(crate) async fn usage_by_table(
+    State(st): State<LocalAppState>,
+    ExtractIdentity(identity): ExtractIdentity,
+    Query(args): Query<UsageQueryArgs>,
+) -> Result<impl IntoResponse, HttpResponseError> {
+    let (start, end) = args.range();
+    let records = st.application.usage_records(identity, start, end).await?;
+    let usage: Vec<_> = sum_by(records, |key| Some((key.component_path, key.table_name?)))
+        .into_iter()
+        .map(|((component_path, table_name), counts)| TableUsageJson {
+            component_path,
+            table_name,
+            database_ingress_bytes: counts.database_ingress_bytes,
+            database_egress_bytes: counts.database_egress_bytes,
+            database_egress_rows: counts.database_egress_rows,
+            vector_ingress_bytes: counts.vector_ingress_bytes,
+            vector_egress_bytes: counts.vector_egress_bytes,
+        })
+        .collect();
+    Ok(Json(usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use events::usage::{
+        UsageEvent,
+        UsageEventLogger,
+    };
+    use model::usage_records::types::{
+        UsageCounts,
+        UsageKey,
+        UsageRecord,
+    };
+
+    use super::{
+        hour_start,
+        sum_by,
+        UsageRecorder,
+    };
+
+    fn database_bandwidth(udf_id: &str, table_name: &str, egress: u64) -> UsageEvent {
+        UsageEvent::DatabaseBandwidth {
+            id: "1".to_string(),
+            request_id: "2".to_string(),
+            component_path: None,
+            udf_id: udf_id.to_string(),
+            table_name: table_name.to_string(),
+            ingress: 0,
+            egress,
+            egress_rows: 1,
+        }
+    }
+
+    #[test]
+    fn test_record_usage() {
+        let recorder = UsageRecorder::new();
+        recorder.record(vec![
+            database_bandwidth("messages:list", "messages", 100),
+            database_bandwidth("messages:list", "messages", 50),
+            database_bandwidth("messages:list", "users", 10),
+            UsageEvent::CurrentVectorStorage { tables: vec![] },
+        ]);
+        let pending = recorder.pending.lock().clone();
+        let key = |table_name: &str| UsageKey {
+            component_path: None,
+            udf_id: Some("messages:list".to_string()),
+            table_name: Some(table_name.to_string()),
+        };
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[&key("messages")].database_egress_bytes, 150);
+        assert_eq!(pending[&key("messages")].database_egress_rows, 2);
+        assert_eq!(pending[&key("users")].database_egress_bytes, 10);
+
+        let records = pending
+            .into_iter()
+            .map(|(key, counts)| UsageRecord {
+                hour_start: SystemTime::UNIX_EPOCH,
+                key,
+                counts,
+            })
+            .collect();
+        let by_function = sum_by(records, |key| key.udf_id);
+        assert_eq!(
+            by_function["messages:list"],
+            UsageCounts {
+                database_egress_bytes: 160,
+                database_egress_rows: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_hour_start() -> anyhow::Result<()> {
+        let ts = SystemTime::UNIX_EPOCH + Duration::from_secs(3 * 3600 + 59 * 60);
+        assert_eq!(
+            hour_start(ts)?,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(3 * 3600)
+        );
+        Ok(())
+    }
+}
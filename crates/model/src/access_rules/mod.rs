@@ -0,0 +1,107 @@
+//! The access rules users configure per table. The database enforces them
+//! (see `common::access_rules`); this model manages them.
+use common::{
+    access_rules::{
+        TableAccessRules,
+        ACCESS_RULES_TABLE,
+    },
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    SystemIndex,
+    SystemTable,
+};
+
+pub struct AccessRulesTable;
+impl SystemTable for AccessRulesTable {
+    fn table_name(&self) -> &'static TableName {
+        &ACCESS_RULES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<TableAccessRules>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct AccessRulesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> AccessRulesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<TableAccessRules>>> {
+        let query = Query::full_table_scan(ACCESS_RULES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut rules = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            rules.push(doc.try_into()?);
+        }
+        Ok(rules)
+    }
+
+    pub async fn get(
+        &mut self,
+        table: &TableName,
+    ) -> anyhow::Result<Option<ParsedDocument<TableAccessRules>>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|rules| &rules.table == table))
+    }
+
+    /// Sets the rules for `rules.table`, replacing any it had.
+    pub async fn set(&mut self, rules: TableAccessRules) -> anyhow::Result<ResolvedDocumentId> {
+        match self.get(&rules.table).await? {
+            Some(existing) => {
+                let id = existing.id();
+                SystemMetadataModel::new_global(self.tx)
+                    .replace(id, rules.try_into()?)
+                    .await?;
+                Ok(id)
+            },
+            None => {
+                SystemMetadataModel::new_global(self.tx)
+                    .insert(&ACCESS_RULES_TABLE, rules.try_into()?)
+                    .await
+            },
+        }
+    }
+
+    /// Deletes the rules for `table`, returning whether it had any.
+    pub async fn delete(&mut self, table: &TableName) -> anyhow::Result<bool> {
+        let Some(rules) = self.get(table).await? else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(rules.id())
+            .await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,87 @@
+//! An append-only log of the requests made with admin and deploy keys, for
+//! investigating who deployed or read what.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    admin_key_audit_log::types::AdminKeyUse,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static ADMIN_KEY_AUDIT_LOG_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_admin_key_audit_log"
+        .parse()
+        .expect("Invalid built-in admin key audit log table")
+});
+
+pub struct AdminKeyAuditLogTable;
+impl SystemTable for AdminKeyAuditLogTable {
+    fn table_name(&self) -> &'static TableName {
+        &ADMIN_KEY_AUDIT_LOG_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<AdminKeyUse>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct AdminKeyAuditLogModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> AdminKeyAuditLogModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn insert(&mut self, key_use: AdminKeyUse) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&ADMIN_KEY_AUDIT_LOG_TABLE, key_use.try_into()?)
+            .await
+    }
+
+    /// The most recent `limit` uses, newest first.
+    pub async fn list_recent(
+        &mut self,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ParsedDocument<AdminKeyUse>>> {
+        let query = Query::full_table_scan(ADMIN_KEY_AUDIT_LOG_TABLE.clone(), Order::Desc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut key_uses = vec![];
+        while key_uses.len() < limit {
+            let Some(doc) = query_stream.next(self.tx, None).await? else {
+                break;
+            };
+            key_uses.push(doc.try_into()?);
+        }
+        Ok(key_uses)
+    }
+}
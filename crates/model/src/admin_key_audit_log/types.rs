@@ -0,0 +1,145 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// A request made with an admin or deploy key.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct AdminKeyUse {
+    pub method: String,
+    pub path: String,
+    /// The caller's address, if the request came over the network.
+    pub caller_ip: Option<String>,
+    /// Who the key belongs to, like `member:123`, `team:45` or
+    /// `serviceAccount:<id>`. `None` if the key was rejected.
+    pub principal: Option<String>,
+    /// ID of the key's `_api_keys` document, for API keys.
+    pub api_key_id: Option<String>,
+    pub outcome: AdminKeyUseOutcome,
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "0..1000u16"))]
+    pub status: u16,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(
+            strategy = "(0..1u64 << 40).prop_map(|millis| SystemTime::UNIX_EPOCH + \
+                        Duration::from_millis(millis))"
+        )
+    )]
+    pub used_at: SystemTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum AdminKeyUseOutcome {
+    /// The request succeeded.
+    Succeeded,
+    /// The key was valid, but the request failed, including because the key
+    /// isn't allowed to make it.
+    Failed,
+    /// The key wasn't a valid key for this deployment.
+    InvalidKey,
+}
+
+impl AdminKeyUseOutcome {
+    /// The outcome of a request that got an HTTP `status` response. Sync
+    /// sockets get `101 Switching Protocols`.
+    pub fn new(key_is_valid: bool, status: u16) -> Self {
+        if !key_is_valid {
+            Self::InvalidKey
+        } else if (100..400).contains(&status) {
+            Self::Succeeded
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+impl fmt::Display for AdminKeyUseOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self {
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::InvalidKey => "invalidKey",
+        };
+        write!(f, "{outcome}")
+    }
+}
+
+impl FromStr for AdminKeyUseOutcome {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            "invalidKey" => Ok(Self::InvalidKey),
+            _ => anyhow::bail!("Invalid admin key use outcome {s:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAdminKeyUse {
+    method: String,
+    path: String,
+    caller_ip: Option<String>,
+    principal: Option<String>,
+    api_key_id: Option<String>,
+    outcome: String,
+    status: i64,
+    used_at_ms: i64,
+}
+
+impl TryFrom<AdminKeyUse> for SerializedAdminKeyUse {
+    type Error = anyhow::Error;
+
+    fn try_from(key_use: AdminKeyUse) -> anyhow::Result<Self> {
+        Ok(Self {
+            method: key_use.method,
+            path: key_use.path,
+            caller_ip: key_use.caller_ip,
+            principal: key_use.principal,
+            api_key_id: key_use.api_key_id,
+            outcome: key_use.outcome.to_string(),
+            status: key_use.status.into(),
+            used_at_ms: key_use
+                .used_at
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_millis()
+                .try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedAdminKeyUse> for AdminKeyUse {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedAdminKeyUse) -> anyhow::Result<Self> {
+        Ok(Self {
+            method: value.method,
+            path: value.path,
+            caller_ip: value.caller_ip,
+            principal: value.principal,
+            api_key_id: value.api_key_id,
+            outcome: value.outcome.parse()?,
+            status: value.status.try_into()?,
+            used_at: SystemTime::UNIX_EPOCH + Duration::from_millis(value.used_at_ms.try_into()?),
+        })
+    }
+}
+
+codegen_convex_serialization!(AdminKeyUse, SerializedAdminKeyUse);
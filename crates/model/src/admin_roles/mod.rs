@@ -0,0 +1,136 @@
+//! Roles of the team members that use this deployment. While the table is
+//! empty every member is an owner, and once it isn't, members without a role
+//! can't use their admin keys.
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+};
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+    types::MemberId,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use errors::ErrorMetadata;
+use keybroker::AdminRole;
+use value::{
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    admin_roles::types::AdminRoleAssignment,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static ADMIN_ROLES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_admin_roles"
+        .parse()
+        .expect("Invalid built-in admin roles table")
+});
+
+pub struct AdminRolesTable;
+impl SystemTable for AdminRolesTable {
+    fn table_name(&self) -> &'static TableName {
+        &ADMIN_ROLES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<AdminRoleAssignment>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct AdminRolesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> AdminRolesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<AdminRoleAssignment>>> {
+        let query = Query::full_table_scan(ADMIN_ROLES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut assignments = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            assignments.push(doc.try_into()?);
+        }
+        Ok(assignments)
+    }
+
+    pub async fn roles(&mut self) -> anyhow::Result<BTreeMap<MemberId, AdminRole>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .map(|assignment| (assignment.member_id, assignment.role))
+            .collect())
+    }
+
+    /// Sets or removes the role of `member_id`, returning the roles of all
+    /// members afterwards. Fails if no owner would be left.
+    pub async fn set_role(
+        &mut self,
+        member_id: MemberId,
+        role: Option<AdminRole>,
+    ) -> anyhow::Result<BTreeMap<MemberId, AdminRole>> {
+        let existing = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|assignment| assignment.member_id == member_id);
+        match (existing, role) {
+            (Some(existing), Some(role)) => {
+                SystemMetadataModel::new_global(self.tx)
+                    .replace(
+                        existing.id(),
+                        AdminRoleAssignment { member_id, role }.try_into()?,
+                    )
+                    .await?;
+            },
+            (Some(existing), None) => {
+                SystemMetadataModel::new_global(self.tx)
+                    .delete(existing.id())
+                    .await?;
+            },
+            (None, Some(role)) => {
+                SystemMetadataModel::new_global(self.tx)
+                    .insert(
+                        &ADMIN_ROLES_TABLE,
+                        AdminRoleAssignment { member_id, role }.try_into()?,
+                    )
+                    .await?;
+            },
+            (None, None) => {},
+        }
+        let roles = self.roles().await?;
+        anyhow::ensure!(
+            roles.is_empty() || roles.values().any(|role| *role == AdminRole::Owner),
+            ErrorMetadata::bad_request(
+                "NoAdminOwner",
+                "At least one member must be an owner while roles are assigned",
+            )
+        );
+        Ok(roles)
+    }
+}
@@ -0,0 +1,66 @@
+use common::types::MemberId;
+use keybroker::AdminRole;
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// The role of a team member in this deployment.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct AdminRoleAssignment {
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "(0..i64::MAX as u64).prop_map(MemberId)")
+    )]
+    pub member_id: MemberId,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "admin_role_strategy()")
+    )]
+    pub role: AdminRole,
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn admin_role_strategy() -> impl Strategy<Value = AdminRole> {
+    prop_oneof![
+        Just(AdminRole::Viewer),
+        Just(AdminRole::Deployer),
+        Just(AdminRole::Operator),
+        Just(AdminRole::Owner),
+    ]
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAdminRoleAssignment {
+    member_id: i64,
+    role: String,
+}
+
+impl TryFrom<AdminRoleAssignment> for SerializedAdminRoleAssignment {
+    type Error = anyhow::Error;
+
+    fn try_from(assignment: AdminRoleAssignment) -> anyhow::Result<Self> {
+        Ok(Self {
+            member_id: assignment.member_id.0.try_into()?,
+            role: assignment.role.to_string(),
+        })
+    }
+}
+
+impl TryFrom<SerializedAdminRoleAssignment> for AdminRoleAssignment {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedAdminRoleAssignment) -> anyhow::Result<Self> {
+        Ok(Self {
+            member_id: MemberId(value.member_id.try_into()?),
+            role: value.role.parse()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(AdminRoleAssignment, SerializedAdminRoleAssignment);
@@ -0,0 +1,195 @@
+//! Alert rules over the backend's internal metrics. The alert worker
+//! evaluates every rule periodically and notifies the rule's channels when it
+//! starts or stops firing.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    alert_rules::types::{
+        AlertRule,
+        AlertState,
+    },
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static ALERT_RULES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_alert_rules"
+        .parse()
+        .expect("Invalid built-in alert rules table")
+});
+
+pub struct AlertRulesTable;
+impl SystemTable for AlertRulesTable {
+    fn table_name(&self) -> &'static TableName {
+        &ALERT_RULES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<AlertRule>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct AlertRulesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> AlertRulesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<AlertRule>>> {
+        let query = Query::full_table_scan(ALERT_RULES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut rules = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            rules.push(doc.try_into()?);
+        }
+        Ok(rules)
+    }
+
+    /// Adds a rule, which must have a name no other rule has.
+    pub async fn insert(&mut self, rule: AlertRule) -> anyhow::Result<ResolvedDocumentId> {
+        anyhow::ensure!(
+            !self
+                .list()
+                .await?
+                .iter()
+                .any(|existing| existing.name == rule.name),
+            "An alert rule named {:?} already exists",
+            rule.name
+        );
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&ALERT_RULES_TABLE, rule.try_into()?)
+            .await
+    }
+
+    /// Records whether the rule is firing, after the alert worker evaluated
+    /// it.
+    pub async fn set_state(
+        &mut self,
+        id: ResolvedDocumentId,
+        state: AlertState,
+    ) -> anyhow::Result<()> {
+        let Some(rule) = self.list().await?.into_iter().find(|rule| rule.id() == id) else {
+            // The rule was deleted while it was being evaluated.
+            return Ok(());
+        };
+        let rule = AlertRule {
+            state,
+            ..rule.into_value()
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .replace(id, rule.try_into()?)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the rule with the given id, returning whether it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(rule) = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|rule| rule.id().developer_id.encode() == id)
+        else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(rule.id())
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use database::test_helpers::DbFixtures;
+    use runtime::testing::TestRuntime;
+
+    use crate::{
+        alert_rules::{
+            types::{
+                AlertChannel,
+                AlertMetric,
+                AlertRule,
+                AlertState,
+            },
+            AlertRulesModel,
+        },
+        test_helpers::DbFixturesWithModel,
+    };
+
+    #[convex_macro::test_runtime]
+    async fn test_alert_rules(rt: TestRuntime) -> anyhow::Result<()> {
+        let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+        let mut tx = db.begin_system().await?;
+        let mut model = AlertRulesModel::new(&mut tx);
+        let rule = AlertRule {
+            name: "errors".to_string(),
+            metric: AlertMetric::ErrorRate,
+            threshold: 5.0,
+            window_secs: 300,
+            channels: vec![
+                AlertChannel::Webhook {
+                    url: "https://example.com/hook".to_string(),
+                },
+                AlertChannel::Email {
+                    to: "oncall@example.com".to_string(),
+                },
+            ],
+            state: AlertState::ok(),
+        };
+        let id = model.insert(rule.clone()).await?;
+        // Names are unique.
+        assert!(model.insert(rule.clone()).await.is_err());
+
+        let state = AlertState {
+            firing: true,
+            since: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            value: Some(12.5),
+        };
+        model.set_state(id, state.clone()).await?;
+        let rules = model.list().await?;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].clone().into_value(), AlertRule { state, ..rule });
+
+        assert!(model.delete(&id.developer_id.encode()).await?);
+        assert!(model.list().await?.is_empty());
+        assert!(!model.delete(&id.developer_id.encode()).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// A rule that notifies operators when one of the backend's metrics goes
+/// above a threshold, and again when it recovers.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: AlertMetric,
+    /// The rule fires while the metric is above this, in the metric's unit.
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "0.0..1e9f64"))]
+    pub threshold: f64,
+    /// The metric is measured over this many of the latest seconds.
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "1..86400u64"))]
+    pub window_secs: u64,
+    pub channels: Vec<AlertChannel>,
+    pub state: AlertState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum AlertMetric {
+    /// Percentage of function calls that failed.
+    ErrorRate,
+    /// Seconds the oldest overdue scheduled job has been waiting to run.
+    SchedulerLag,
+    /// Percentage of the storage directory's disk that's used.
+    DiskUsage,
+    /// 99th percentile of how long mutations take to commit, in milliseconds.
+    CommitLatency,
+}
+
+impl AlertMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ErrorRate => "errorRate",
+            Self::SchedulerLag => "schedulerLag",
+            Self::DiskUsage => "diskUsage",
+            Self::CommitLatency => "commitLatency",
+        }
+    }
+}
+
+impl fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AlertMetric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let metric = match s {
+            "errorRate" => Self::ErrorRate,
+            "schedulerLag" => Self::SchedulerLag,
+            "diskUsage" => Self::DiskUsage,
+            "commitLatency" => Self::CommitLatency,
+            _ => anyhow::bail!("Invalid alert metric: {s}"),
+        };
+        Ok(metric)
+    }
+}
+
+/// Where to send a rule's notifications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum AlertChannel {
+    /// Posts the notification to the URL as JSON.
+    Webhook { url: String },
+    /// Emails the notification to the address over the backend's SMTP server.
+    Email { to: String },
+}
+
+/// Whether the rule fired the last time it was evaluated, and since when.
+/// It's only written when it changes, so it doesn't churn the table.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct AlertState {
+    pub firing: bool,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of((0..1u64 << 40).prop_map(|secs| \
+                             SystemTime::UNIX_EPOCH + Duration::from_secs(secs)))")
+    )]
+    pub since: Option<SystemTime>,
+    /// The metric's value when the state changed.
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of(0.0..1e9f64)")
+    )]
+    pub value: Option<f64>,
+}
+
+impl AlertState {
+    pub fn ok() -> Self {
+        Self {
+            firing: false,
+            since: None,
+            value: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAlertRule {
+    name: String,
+    metric: String,
+    threshold: f64,
+    window_secs: i64,
+    channels: Vec<SerializedAlertChannel>,
+    firing: bool,
+    state_since_secs: Option<i64>,
+    state_value: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SerializedAlertChannel {
+    Webhook { url: String },
+    Email { to: String },
+}
+
+impl TryFrom<AlertRule> for SerializedAlertRule {
+    type Error = anyhow::Error;
+
+    fn try_from(rule: AlertRule) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: rule.name,
+            metric: rule.metric.to_string(),
+            threshold: rule.threshold,
+            window_secs: rule.window_secs.try_into()?,
+            channels: rule
+                .channels
+                .into_iter()
+                .map(|channel| match channel {
+                    AlertChannel::Webhook { url } => SerializedAlertChannel::Webhook { url },
+                    AlertChannel::Email { to } => SerializedAlertChannel::Email { to },
+                })
+                .collect(),
+            firing: rule.state.firing,
+            state_since_secs: rule
+                .state
+                .since
+                .map(|since| {
+                    anyhow::Ok(
+                        since
+                            .duration_since(SystemTime::UNIX_EPOCH)?
+                            .as_secs()
+                            .try_into()?,
+                    )
+                })
+                .transpose()?,
+            state_value: rule.state.value,
+        })
+    }
+}
+
+impl TryFrom<SerializedAlertRule> for AlertRule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedAlertRule) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: value.name,
+            metric: value.metric.parse()?,
+            threshold: value.threshold,
+            window_secs: value.window_secs.try_into()?,
+            channels: value
+                .channels
+                .into_iter()
+                .map(|channel| match channel {
+                    SerializedAlertChannel::Webhook { url } => AlertChannel::Webhook { url },
+                    SerializedAlertChannel::Email { to } => AlertChannel::Email { to },
+                })
+                .collect(),
+            state: AlertState {
+                firing: value.firing,
+                since: value
+                    .state_since_secs
+                    .map(|secs| {
+                        anyhow::Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs.try_into()?))
+                    })
+                    .transpose()?,
+                value: value.state_value,
+            },
+        })
+    }
+}
+
+codegen_convex_serialization!(AlertRule, SerializedAlertRule);
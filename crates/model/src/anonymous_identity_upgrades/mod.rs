@@ -0,0 +1,84 @@
+//! Anonymous identities that were upgraded to the identity their user signed
+//! up with. The application keeps them in memory so upgraded identities can
+//! be annotated on every request.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    anonymous_identity_upgrades::types::AnonymousIdentityUpgrade,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static ANONYMOUS_IDENTITY_UPGRADES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_anonymous_identity_upgrades"
+        .parse()
+        .expect("Invalid built-in anonymous identity upgrades table")
+});
+
+pub struct AnonymousIdentityUpgradesTable;
+impl SystemTable for AnonymousIdentityUpgradesTable {
+    fn table_name(&self) -> &'static TableName {
+        &ANONYMOUS_IDENTITY_UPGRADES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<AnonymousIdentityUpgrade>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct AnonymousIdentityUpgradesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> AnonymousIdentityUpgradesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<AnonymousIdentityUpgrade>>> {
+        let query = Query::full_table_scan(ANONYMOUS_IDENTITY_UPGRADES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut upgrades = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            upgrades.push(doc.try_into()?);
+        }
+        Ok(upgrades)
+    }
+
+    pub async fn insert(
+        &mut self,
+        upgrade: AnonymousIdentityUpgrade,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&ANONYMOUS_IDENTITY_UPGRADES_TABLE, upgrade.try_into()?)
+            .await
+    }
+}
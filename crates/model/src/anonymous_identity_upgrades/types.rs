@@ -0,0 +1,42 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// Links an anonymous identity to the identity its user signed up with.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct AnonymousIdentityUpgrade {
+    /// Token identifier of the anonymous identity, `<issuer>|<subject>`.
+    pub anonymous_token_identifier: String,
+    /// Token identifier of the identity it was upgraded to.
+    pub token_identifier: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAnonymousIdentityUpgrade {
+    anonymous_token_identifier: String,
+    token_identifier: String,
+}
+
+impl From<AnonymousIdentityUpgrade> for SerializedAnonymousIdentityUpgrade {
+    fn from(upgrade: AnonymousIdentityUpgrade) -> Self {
+        Self {
+            anonymous_token_identifier: upgrade.anonymous_token_identifier,
+            token_identifier: upgrade.token_identifier,
+        }
+    }
+}
+
+impl From<SerializedAnonymousIdentityUpgrade> for AnonymousIdentityUpgrade {
+    fn from(value: SerializedAnonymousIdentityUpgrade) -> Self {
+        Self {
+            anonymous_token_identifier: value.anonymous_token_identifier,
+            token_identifier: value.token_identifier,
+        }
+    }
+}
+
+codegen_convex_serialization!(AnonymousIdentityUpgrade, SerializedAnonymousIdentityUpgrade);
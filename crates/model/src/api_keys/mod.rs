@@ -0,0 +1,96 @@
+//! API keys that haven't been revoked. Keys are issued by the `KeyBroker` and
+//! carry their own scopes and expiration, but only authenticate while their
+//! document here exists, so deleting it revokes the key.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    api_keys::types::ApiKeyMetadata,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static API_KEYS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_api_keys"
+        .parse()
+        .expect("Invalid built-in API keys table")
+});
+
+pub struct ApiKeysTable;
+impl SystemTable for ApiKeysTable {
+    fn table_name(&self) -> &'static TableName {
+        &API_KEYS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<ApiKeyMetadata>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct ApiKeysModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> ApiKeysModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<ApiKeyMetadata>>> {
+        let query = Query::full_table_scan(API_KEYS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut api_keys = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            api_keys.push(doc.try_into()?);
+        }
+        Ok(api_keys)
+    }
+
+    pub async fn insert(&mut self, metadata: ApiKeyMetadata) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&API_KEYS_TABLE, metadata.try_into()?)
+            .await
+    }
+
+    /// Revokes the key with the given id, returning it if it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<Option<ApiKeyMetadata>> {
+        let Some(api_key) = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|api_key| api_key.id().developer_id.encode() == id)
+        else {
+            return Ok(None);
+        };
+        let (id, metadata) = api_key.into_id_and_value();
+        SystemMetadataModel::new_global(self.tx).delete(id).await?;
+        Ok(Some(metadata))
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+use keybroker::ApiKeyScopes;
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// An API key that hasn't been revoked. Only what the key is for is stored,
+/// since the key itself is encrypted with the instance secret and can't be
+/// shown again after it's created.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ApiKeyMetadata {
+    pub name: String,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "api_key_scopes_strategy()")
+    )]
+    pub scopes: ApiKeyScopes,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of((0..1u64 << 40).prop_map(|secs| \
+                             SystemTime::UNIX_EPOCH + Duration::from_secs(secs)))")
+    )]
+    pub expiration: Option<SystemTime>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+fn api_key_scopes_strategy() -> impl Strategy<Value = ApiKeyScopes> {
+    prop_oneof![
+        Just(vec!["admin"]),
+        Just(vec!["read_only"]),
+        Just(vec!["read_only", "table:messages"]),
+        Just(vec![
+            "function:messages:send",
+            "table:messages",
+            "table:users"
+        ]),
+    ]
+    .prop_map(|scopes| ApiKeyScopes::parse(scopes).expect("Invalid API key scopes"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedApiKeyMetadata {
+    name: String,
+    scopes: Vec<String>,
+    expiration_secs: Option<i64>,
+}
+
+impl TryFrom<ApiKeyMetadata> for SerializedApiKeyMetadata {
+    type Error = anyhow::Error;
+
+    fn try_from(metadata: ApiKeyMetadata) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: metadata.name,
+            scopes: metadata.scopes.to_strings(),
+            expiration_secs: metadata
+                .expiration
+                .map(|expiration| {
+                    anyhow::Ok(
+                        expiration
+                            .duration_since(SystemTime::UNIX_EPOCH)?
+                            .as_secs()
+                            .try_into()?,
+                    )
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedApiKeyMetadata> for ApiKeyMetadata {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedApiKeyMetadata) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: value.name,
+            scopes: ApiKeyScopes::parse(value.scopes)?,
+            expiration: value
+                .expiration_secs
+                .map(|secs| {
+                    anyhow::Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs.try_into()?))
+                })
+                .transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(ApiKeyMetadata, SerializedApiKeyMetadata);
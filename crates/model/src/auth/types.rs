@@ -35,9 +35,29 @@ impl TryFrom<ConvexObject> for AuthInfoPersisted {
             Some(ConvexValue::String(s)) => IssuerUrl::new(s.into())?,
             _ => anyhow::bail!("Missing or invalid domain field for AuthInfo"),
         };
+        let claim_mapping = match fields.remove("claimMapping") {
+            Some(ConvexValue::Array(mappings)) => mappings
+                .into_iter()
+                .map(|mapping| {
+                    let ConvexValue::Object(mapping) = mapping else {
+                        anyhow::bail!("Invalid claim mapping {mapping:?} for AuthInfo");
+                    };
+                    let mut mapping: BTreeMap<_, _> = mapping.into();
+                    match (mapping.remove("field"), mapping.remove("claim")) {
+                        (Some(ConvexValue::String(field)), Some(ConvexValue::String(claim))) => {
+                            Ok((field.into(), claim.into()))
+                        },
+                        _ => anyhow::bail!("Invalid claim mapping for AuthInfo"),
+                    }
+                })
+                .collect::<anyhow::Result<_>>()?,
+            None => BTreeMap::new(),
+            _ => anyhow::bail!("Invalid claimMapping field for AuthInfo"),
+        };
         Ok(Self(AuthInfo {
             application_id,
             domain,
+            claim_mapping,
         }))
     }
 }
@@ -46,10 +66,25 @@ impl TryFrom<AuthInfoPersisted> for ConvexObject {
     type Error = anyhow::Error;
 
     fn try_from(info: AuthInfoPersisted) -> Result<Self, Self::Error> {
-        obj!(
-            "applicationID" => info.0.application_id,
-            "domain" => info.0.domain.to_string(),
-        )
+        let AuthInfo {
+            application_id,
+            domain,
+            claim_mapping,
+        } = info.0;
+        let object = obj!(
+            "applicationID" => application_id,
+            "domain" => domain.to_string(),
+        )?;
+        // Providers without a claim mapping are stored as they were before
+        // mappings existed.
+        if claim_mapping.is_empty() {
+            return Ok(object);
+        }
+        let mappings = claim_mapping
+            .into_iter()
+            .map(|(field, claim)| obj!("field" => field, "claim" => claim).map(ConvexValue::Object))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        object.shallow_merge(obj!("claimMapping" => ConvexValue::Array(mappings.try_into()?))?)
     }
 }
 
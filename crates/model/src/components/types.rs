@@ -10,7 +10,10 @@ use common::{
     },
     components::ComponentDefinitionPath,
     schemas::DatabaseSchema,
-    types::NodeDependency,
+    types::{
+        NodeDependency,
+        NodeVersion,
+    },
 };
 use semver::Version;
 use serde::{
@@ -31,6 +34,7 @@ use crate::{
         SerializedAnalyzedModule,
     },
     udf_config::types::UdfConfig,
+    warmup::types::WarmupFunction,
 };
 
 #[derive(Debug)]
@@ -42,6 +46,15 @@ pub struct ProjectConfig {
 
     // TODO(CX-6483): Add support for components to declare their own external dependencies.
     pub node_dependencies: Vec<NodeDependency>,
+
+    // Node.js version for "use node" actions, if the project picked one.
+    pub node_version: Option<NodeVersion>,
+
+    // Names of prebuilt dependency layers for "use node" actions, in order of precedence.
+    pub dependency_layers: Vec<String>,
+
+    // Queries to run after the push, validated in `start_push`.
+    pub warmup: Vec<WarmupFunction>,
 }
 
 #[derive(Debug)]
@@ -86,6 +86,8 @@ async fn test_config(rt: TestRuntime) -> anyhow::Result<()> {
                 sha256,
                 external_deps_package_id: None,
                 package_size,
+                node_version: None,
+                dependency_layers: vec![],
             }),
             btreemap! {
                 p1 => AnalyzedModule {
@@ -172,6 +174,8 @@ async fn test_config_large_modules(rt: TestRuntime) -> anyhow::Result<()> {
                 sha256,
                 external_deps_package_id: None,
                 package_size,
+                node_version: None,
+                dependency_layers: vec![],
             }),
             analyzed_result,
             None,
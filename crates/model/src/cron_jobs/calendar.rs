@@ -0,0 +1,357 @@
+//! Calendar expressions for schedules that cron syntax can't express, like
+//! "last weekday of month at 17:00" or "2nd tuesday of month at 9:30".
+//!
+//! An expression picks one day of every month and a time of day:
+//!
+//! ```text
+//! <ordinal> <day> of month at <hh>:<mm>
+//! ```
+//!
+//! - `<ordinal>` counts from the start of the month, like `first` or `3rd`, or
+//!   from its end, like `last` or `2nd last`.
+//! - `<day>` is `day`, a day of the week like `tuesday`, or `weekday` (also
+//!   `business day`) for Monday to Friday.
+//!
+//! Months without a matching day, like months with only four Tuesdays for
+//! `5th tuesday`, are skipped.
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use chrono::{
+    Datelike,
+    NaiveDate,
+    NaiveDateTime,
+    NaiveTime,
+    Weekday,
+};
+
+use super::types::CronValidationError;
+
+/// How many months to look ahead for the next matching day. Every valid
+/// expression matches at least once a year.
+const MAX_MONTHS_AHEAD: u32 = 2 * 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CalendarExpression {
+    ordinal: Ordinal,
+    day: DayKind,
+    time: NaiveTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ordinal {
+    /// The nth matching day of the month, starting at 1.
+    FromStart(u32),
+    /// The nth matching day from the end of the month, where 1 is the last.
+    FromEnd(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DayKind {
+    Day,
+    DayOfWeek(Weekday),
+    Weekday,
+}
+
+impl DayKind {
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            Self::Day => true,
+            Self::DayOfWeek(weekday) => date.weekday() == *weekday,
+            Self::Weekday => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+        }
+    }
+
+    /// The most days of this kind a month can have.
+    fn max_per_month(&self) -> u32 {
+        match self {
+            Self::Day => 31,
+            Self::DayOfWeek(_) => 5,
+            Self::Weekday => 23,
+        }
+    }
+}
+
+impl CalendarExpression {
+    /// The first time after `prev` that the expression matches, in the same
+    /// wall-clock time as `prev`.
+    pub fn next_after(&self, prev: NaiveDateTime) -> anyhow::Result<NaiveDateTime> {
+        let (mut year, mut month) = (prev.year(), prev.month());
+        for _ in 0..=MAX_MONTHS_AHEAD {
+            if let Some(date) = self.day_in_month(year, month) {
+                let next = date.and_time(self.time);
+                if next > prev {
+                    return Ok(next);
+                }
+            }
+            (year, month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+        }
+        anyhow::bail!("Could not compute next timestamp for calendar expression")
+    }
+
+    fn day_in_month(&self, year: i32, month: u32) -> Option<NaiveDate> {
+        let days: Vec<_> = (1..=31)
+            .map_while(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .filter(|date| self.day.matches(*date))
+            .collect();
+        let index = match self.ordinal {
+            Ordinal::FromStart(n) => n as usize - 1,
+            Ordinal::FromEnd(n) => days.len().checked_sub(n as usize)?,
+        };
+        days.get(index).copied()
+    }
+}
+
+impl FromStr for CalendarExpression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let invalid = |reason: &str| {
+            anyhow::anyhow!(CronValidationError::InvalidCalendarExpression(
+                s.to_string(),
+                reason.to_string()
+            ))
+        };
+        let lowercase = s.to_lowercase();
+        let words: Vec<_> = lowercase.split_whitespace().collect();
+        let [day_words @ .., "of", "month", "at", time] = &words[..] else {
+            return Err(invalid(
+                "expected an expression like \"last weekday of month at 17:00\"",
+            ));
+        };
+        let time = parse_time(time).ok_or_else(|| invalid("expected a time like 17:00"))?;
+        let (ordinal, day_words) = match day_words {
+            ["last", rest @ ..] => (Ordinal::FromEnd(1), rest),
+            [n, "last", rest @ ..] => (
+                Ordinal::FromEnd(parse_ordinal(n).ok_or_else(|| invalid("invalid ordinal"))?),
+                rest,
+            ),
+            [n, rest @ ..] => (
+                Ordinal::FromStart(parse_ordinal(n).ok_or_else(|| invalid("invalid ordinal"))?),
+                rest,
+            ),
+            [] => return Err(invalid("expected a day like \"2nd tuesday\"")),
+        };
+        let day = match day_words {
+            ["day"] => DayKind::Day,
+            ["weekday"] | ["business", "day"] => DayKind::Weekday,
+            [day_of_week] => DayKind::DayOfWeek(
+                day_of_week
+                    .parse()
+                    .map_err(|_| invalid("expected a day like \"tuesday\" or \"weekday\""))?,
+            ),
+            _ => return Err(invalid("expected a day like \"tuesday\" or \"weekday\"")),
+        };
+        let (Ordinal::FromStart(n) | Ordinal::FromEnd(n)) = ordinal;
+        if n > day.max_per_month() {
+            return Err(invalid(&format!(
+                "a month has at most {} of these days",
+                day.max_per_month()
+            )));
+        }
+        Ok(Self { ordinal, day, time })
+    }
+}
+
+impl fmt::Display for CalendarExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ordinal {
+            Ordinal::FromStart(n) => write!(f, "{} ", ordinal_str(n))?,
+            Ordinal::FromEnd(1) => write!(f, "last ")?,
+            Ordinal::FromEnd(n) => write!(f, "{} last ", ordinal_str(n))?,
+        }
+        match self.day {
+            DayKind::Day => write!(f, "day")?,
+            DayKind::DayOfWeek(weekday) => write!(f, "{}", weekday_str(weekday))?,
+            DayKind::Weekday => write!(f, "weekday")?,
+        }
+        write!(f, " of month at {}", self.time.format("%H:%M"))
+    }
+}
+
+/// Parses ordinals like `first` or `2nd`.
+fn parse_ordinal(s: &str) -> Option<u32> {
+    let n = match s {
+        "first" => 1,
+        "second" => 2,
+        "third" => 3,
+        "fourth" => 4,
+        "fifth" => 5,
+        _ => {
+            let digits = ["st", "nd", "rd", "th"]
+                .into_iter()
+                .find_map(|suffix| s.strip_suffix(suffix))?;
+            if !digits.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            digits.parse().ok()?
+        },
+    };
+    (n > 0).then_some(n)
+}
+
+fn ordinal_str(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+fn weekday_str(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// Parses times of day like `9:30` or `17:00`.
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    let (hour, minute) = s.split_once(':')?;
+    if hour.is_empty()
+        || hour.len() > 2
+        || minute.len() != 2
+        || !hour
+            .chars()
+            .chain(minute.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    NaiveTime::from_hms_opt(hour.parse().ok()?, minute.parse().ok()?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{
+        NaiveDate,
+        NaiveDateTime,
+    };
+
+    use super::CalendarExpression;
+
+    fn datetime(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn next_after(expr: &str, prev: NaiveDateTime) -> NaiveDateTime {
+        expr.parse::<CalendarExpression>()
+            .unwrap()
+            .next_after(prev)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_calendar_next_after() {
+        // Mar 31 2023 is a Friday.
+        assert_eq!(
+            next_after("last weekday of month at 17:00", datetime(2023, 3, 1, 0, 0)),
+            datetime(2023, 3, 31, 17, 0)
+        );
+        // Apr 30 2023 is a Sunday.
+        assert_eq!(
+            next_after(
+                "last weekday of month at 17:00",
+                datetime(2023, 3, 31, 17, 0)
+            ),
+            datetime(2023, 4, 28, 17, 0)
+        );
+        assert_eq!(
+            next_after("2nd Tuesday of month at 9:30", datetime(2023, 3, 14, 9, 29)),
+            datetime(2023, 3, 14, 9, 30)
+        );
+        assert_eq!(
+            next_after("2nd tuesday of month at 9:30", datetime(2023, 3, 14, 9, 30)),
+            datetime(2023, 4, 11, 9, 30)
+        );
+        // Apr 3 2023 is the first business day of April.
+        assert_eq!(
+            next_after(
+                "1st business day of month at 08:00",
+                datetime(2023, 3, 2, 0, 0)
+            ),
+            datetime(2023, 4, 3, 8, 0)
+        );
+        assert_eq!(
+            next_after(
+                "3rd last business day of month at 08:00",
+                datetime(2023, 3, 1, 0, 0)
+            ),
+            datetime(2023, 3, 29, 8, 0)
+        );
+        assert_eq!(
+            next_after("last day of month at 23:59", datetime(2024, 2, 1, 0, 0)),
+            datetime(2024, 2, 29, 23, 59)
+        );
+        // Months without a 5th Monday or a 31st day are skipped.
+        assert_eq!(
+            next_after(
+                "fifth monday of month at 12:00",
+                datetime(2023, 1, 31, 0, 0)
+            ),
+            datetime(2023, 5, 29, 12, 0)
+        );
+        assert_eq!(
+            next_after("31st day of month at 12:00", datetime(2023, 3, 31, 12, 0)),
+            datetime(2023, 5, 31, 12, 0)
+        );
+    }
+
+    #[test]
+    fn test_calendar_parse() {
+        for (expr, normalized) in [
+            (
+                "Last  Weekday of month at 9:05",
+                "last weekday of month at 09:05",
+            ),
+            (
+                "second last friday of month at 17:00",
+                "2nd last friday of month at 17:00",
+            ),
+            (
+                "11th business day of month at 00:00",
+                "11th weekday of month at 00:00",
+            ),
+        ] {
+            assert_eq!(
+                expr.parse::<CalendarExpression>().unwrap().to_string(),
+                normalized
+            );
+        }
+        for expr in [
+            "",
+            "last weekday at 17:00",
+            "last weekday of month",
+            "last weekday of month at 24:00",
+            "last weekday of month at 9:5",
+            "0th day of month at 12:00",
+            "32nd day of month at 12:00",
+            "6th tuesday of month at 12:00",
+            "24th weekday of month at 12:00",
+            "last tuesdays of month at 12:00",
+            "last of month at 12:00",
+        ] {
+            assert!(
+                expr.parse::<CalendarExpression>().is_err(),
+                "{expr:?} should be invalid"
+            );
+        }
+    }
+}
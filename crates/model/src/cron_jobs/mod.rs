@@ -36,7 +36,10 @@ use value::{
 use crate::{
     config::types::CronDiff,
     cron_jobs::{
-        next_ts::compute_next_ts,
+        next_ts::{
+            compute_jitter,
+            compute_next_ts,
+        },
         types::{
             CronIdentifier,
             CronJob,
@@ -52,6 +55,7 @@ use crate::{
     SystemTable,
 };
 
+pub mod calendar;
 pub mod next_ts;
 pub mod types;
 
@@ -197,12 +201,15 @@ impl<'a, RT: Runtime> CronModel<'a, RT> {
         cron_spec: CronSpec,
     ) -> anyhow::Result<()> {
         let now = self.runtime().generate_timestamp()?;
+        let scheduled_ts = compute_next_ts(&cron_spec, None, now)?;
+        let jitter = compute_jitter(&cron_spec, scheduled_ts, &mut self.runtime().rng())?;
         let cron = CronJob {
             name,
-            next_ts: compute_next_ts(&cron_spec, None, now)?,
+            next_ts: scheduled_ts.add(jitter)?,
             cron_spec,
             state: CronJobState::Pending,
             prev_ts: None,
+            jitter,
         };
         SystemMetadataModel::new(self.tx, self.component.into())
             .insert(&CRON_JOBS_TABLE, cron.try_into()?)
@@ -216,9 +223,17 @@ impl<'a, RT: Runtime> CronModel<'a, RT> {
         new_cron_spec: CronSpec,
     ) -> anyhow::Result<()> {
         let (job_id, mut cron_job) = cron_job.into_id_and_value();
-        if new_cron_spec.cron_schedule != cron_job.cron_spec.cron_schedule {
-            let now = self.runtime().generate_timestamp()?;
-            cron_job.next_ts = compute_next_ts(&new_cron_spec, cron_job.prev_ts, now)?;
+        let schedule_changed = new_cron_spec.cron_schedule != cron_job.cron_spec.cron_schedule;
+        if schedule_changed || new_cron_spec.dispatch.jitter != cron_job.cron_spec.dispatch.jitter {
+            let scheduled_ts = if schedule_changed {
+                let now = self.runtime().generate_timestamp()?;
+                compute_next_ts(&new_cron_spec, cron_job.prev_ts, now)?
+            } else {
+                cron_job.scheduled_ts()?
+            };
+            cron_job.jitter =
+                compute_jitter(&new_cron_spec, scheduled_ts, &mut self.runtime().rng())?;
+            cron_job.next_ts = scheduled_ts.add(cron_job.jitter)?;
         }
         cron_job.cron_spec = new_cron_spec;
         self.update_job_state(job_id, cron_job).await?;
@@ -2,23 +2,67 @@ use std::time::Duration;
 
 use anyhow::Context;
 use chrono::{
+    DateTime,
+    LocalResult,
+    NaiveDateTime,
+    TimeDelta,
     TimeZone,
     Utc,
 };
+use chrono_tz::Tz;
+use rand::Rng;
 use saffron::Cron;
 use sync_types::Timestamp;
 
-use super::types::{
-    CronSchedule,
-    CronSpec,
+use super::{
+    calendar::CalendarExpression,
+    types::{
+        parse_timezone,
+        CronSchedule,
+        CronSpec,
+    },
 };
 
+/// Longest that clocks go forward at once. Some places have skipped a whole
+/// day when moving across the date line.
+const MAX_TIMEZONE_GAP_MINUTES: i64 = 24 * 60;
+
 pub fn compute_next_ts(
     cron_spec: &CronSpec,
     prev_ts: Option<Timestamp>,
     now: Timestamp,
 ) -> anyhow::Result<Timestamp> {
-    let cron: Cron = match cron_spec.cron_schedule.clone() {
+    compute_next_ts_for_schedule(&cron_spec.cron_schedule, prev_ts, now)
+}
+
+/// Picks how long to delay the run due at `next_ts`: a random duration up to
+/// the cron's jitter, but shorter than the time until the run after it, so
+/// jitter never makes the cron skip runs.
+pub fn compute_jitter(
+    cron_spec: &CronSpec,
+    next_ts: Timestamp,
+    rng: &mut impl Rng,
+) -> anyhow::Result<Duration> {
+    let Some(jitter) = cron_spec.dispatch.jitter else {
+        return Ok(Duration::ZERO);
+    };
+    let following_ts = compute_next_ts(cron_spec, Some(next_ts), next_ts)?;
+    let max_jitter_ms = jitter.min(following_ts - next_ts).as_millis() as u64;
+    if max_jitter_ms == 0 {
+        return Ok(Duration::ZERO);
+    }
+    Ok(Duration::from_millis(rng.gen_range(0..max_jitter_ms)))
+}
+
+/// The next time `cron_schedule` is due after `prev_ts`, or after `now` if it
+/// hasn't run before.
+pub fn compute_next_ts_for_schedule(
+    cron_schedule: &CronSchedule,
+    prev_ts: Option<Timestamp>,
+    now: Timestamp,
+) -> anyhow::Result<Timestamp> {
+    let mut timezone = None;
+    let cron: Cron = match cron_schedule.clone() {
         CronSchedule::Interval { seconds } => {
             let next_ts = match prev_ts {
                 Some(prev_ts) => prev_ts.add(Duration::from_secs(seconds as u64))?,
@@ -49,16 +93,58 @@ pub fn compute_next_ts(
         } => format!("{minute_utc} {hour_utc} {day} * *")
             .parse()
             .context("Monthly Schedule: Cron parsing from Saffron failed")?,
-        CronSchedule::Cron { cron_expr } => cron_expr
-            .parse()
-            .context("Cron Schedule: Cron parsing from Saffron failed")?,
+        CronSchedule::Cron {
+            cron_expr,
+            timezone: cron_timezone,
+        } => {
+            timezone = cron_timezone.as_deref().map(parse_timezone).transpose()?;
+            cron_expr
+                .parse()
+                .context("Cron Schedule: Cron parsing from Saffron failed")?
+        },
+        CronSchedule::Calendar { expr, timezone } => {
+            let timezone = timezone.as_deref().map(parse_timezone).transpose()?;
+            return next_ts_after(
+                &Recurrence::Calendar(expr.parse()?),
+                timezone,
+                prev_ts.unwrap_or(now),
+            );
+        },
     };
-    let prev_ts = prev_ts.unwrap_or(now);
+    next_ts_after(&Recurrence::Cron(cron), timezone, prev_ts.unwrap_or(now))
+}
+
+/// A schedule that's evaluated on wall-clock times, which are in UTC unless
+/// the schedule has a timezone.
+enum Recurrence {
+    Cron(Cron),
+    Calendar(CalendarExpression),
+}
+
+impl Recurrence {
+    fn next_after(&self, local: NaiveDateTime) -> anyhow::Result<NaiveDateTime> {
+        match self {
+            // Saffron only evaluates expressions in UTC, so evaluate it on the
+            // wall-clock time as if it were UTC.
+            Self::Cron(cron) => Ok(cron
+                .next_after(local.and_utc())
+                .context("Could not compute next timestamp for cron")?
+                .naive_utc()),
+            Self::Calendar(expr) => expr.next_after(local),
+        }
+    }
+}
+
+fn next_ts_after(
+    recurrence: &Recurrence,
+    timezone: Option<Tz>,
+    prev_ts: Timestamp,
+) -> anyhow::Result<Timestamp> {
     let prev_ts_nanos: i64 = prev_ts.into();
     let prev_ts_utc = Utc.timestamp_nanos(prev_ts_nanos);
-    let next_ts_utc = match cron.next_after(prev_ts_utc) {
-        Some(next_ts_utc) => next_ts_utc,
-        None => return Err(anyhow::anyhow!("Could not compute next timestamp for cron")),
+    let next_ts_utc = match timezone {
+        Some(timezone) => next_after_in_timezone(recurrence, timezone, prev_ts_utc)?,
+        None => recurrence.next_after(prev_ts_utc.naive_utc())?.and_utc(),
     };
     let next_ts_nanos = next_ts_utc
         .timestamp_nanos_opt()
@@ -67,9 +153,52 @@ pub fn compute_next_ts(
     Ok(next_ts)
 }
 
+/// The next time after `prev` that `recurrence` matches the wall-clock time
+/// in `timezone`, so a job at 9:00 stays at 9:00 when daylight saving time
+/// starts or ends.
+///
+/// Wall-clock times that are skipped when clocks go forward run when the gap
+/// ends, and times that happen twice when clocks go back run once.
+fn next_after_in_timezone(
+    recurrence: &Recurrence,
+    timezone: Tz,
+    prev: DateTime<Utc>,
+) -> anyhow::Result<DateTime<Utc>> {
+    let mut local = prev.with_timezone(&timezone).naive_local();
+    loop {
+        let next_local = recurrence.next_after(local)?;
+        let next = match timezone.from_local_datetime(&next_local) {
+            LocalResult::Single(next) => Some(next),
+            LocalResult::Ambiguous(earliest, latest) => [earliest, latest]
+                .into_iter()
+                .find(|next| next.with_timezone(&Utc) > prev),
+            LocalResult::None => end_of_gap(timezone, next_local),
+        };
+        if let Some(next) = next {
+            let next = next.with_timezone(&Utc);
+            if next > prev {
+                return Ok(next);
+            }
+        }
+        local = next_local;
+    }
+}
+
+/// The first time after `local`, which clocks skipped when they went forward.
+fn end_of_gap(timezone: Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    (1..=MAX_TIMEZONE_GAP_MINUTES).find_map(|minutes| {
+        timezone
+            .from_local_datetime(&(local + TimeDelta::minutes(minutes)))
+            .earliest()
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{
+        str::FromStr,
+        time::Duration,
+    };
 
     use sync_types::{
         Timestamp,
@@ -78,8 +207,12 @@ mod tests {
     use value::ConvexArray;
 
     use crate::cron_jobs::{
-        next_ts::compute_next_ts,
+        next_ts::{
+            compute_jitter,
+            compute_next_ts,
+        },
         types::{
+            CronDispatchOptions,
             CronSchedule,
             CronSpec,
         },
@@ -92,6 +225,7 @@ mod tests {
             udf_path: UdfPath::from_str("test").unwrap().canonicalize(),
             udf_args: ConvexArray::try_from(vec![]).unwrap(),
             cron_schedule: CronSchedule::Interval { seconds: 60 },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Mar 01 2023 08:35:00 UTC
@@ -114,6 +248,7 @@ mod tests {
             udf_path: UdfPath::from_str("test").unwrap().canonicalize(),
             udf_args: ConvexArray::try_from(vec![]).unwrap(),
             cron_schedule: CronSchedule::Hourly { minute_utc: 5 },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Mar 01 2023 08:35:00 UTC
@@ -141,6 +276,7 @@ mod tests {
                 hour_utc: 8,
                 minute_utc: 30,
             },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Feb 28 2023 08:35:00 UTC
@@ -169,6 +305,7 @@ mod tests {
                 hour_utc: 12,
                 minute_utc: 30,
             },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Feb 28 2023 08:35:00 UTC
@@ -197,6 +334,7 @@ mod tests {
                 hour_utc: 12,
                 minute_utc: 30,
             },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Feb 28 2023 08:35:00 UTC
@@ -225,7 +363,9 @@ mod tests {
             udf_args: ConvexArray::try_from(vec![]).unwrap(),
             cron_schedule: CronSchedule::Cron {
                 cron_expr: "0 12 * * 1,5".to_string(),
+                timezone: None,
             },
+            dispatch: CronDispatchOptions::default(),
         };
 
         // Feb 28 2023 08:35:00 UTC
@@ -248,11 +388,131 @@ mod tests {
             udf_args: ConvexArray::try_from(vec![]).unwrap(),
             cron_schedule: CronSchedule::Cron {
                 cron_expr: "0 12 * * 7".to_string(),
+                timezone: None,
             },
+            dispatch: CronDispatchOptions::default(),
         };
         result = compute_next_ts(&cron_spec, prev_ts, now);
         assert!(result.is_err());
         assert!(format!("{:?}", result.unwrap_err())
             .contains("Cron Schedule: Cron parsing from Saffron failed"));
     }
+
+    fn cron_in_berlin(cron_expr: &str) -> CronSpec {
+        CronSpec {
+            udf_path: UdfPath::from_str("test").unwrap().canonicalize(),
+            udf_args: ConvexArray::try_from(vec![]).unwrap(),
+            cron_schedule: CronSchedule::Cron {
+                cron_expr: cron_expr.to_string(),
+                timezone: Some("Europe/Berlin".to_string()),
+            },
+            dispatch: CronDispatchOptions::default(),
+        }
+    }
+
+    fn ts(secs: i64) -> Timestamp {
+        Timestamp::try_from(i64::pow(10, 9) * secs).unwrap()
+    }
+
+    #[test]
+    fn test_compute_jitter() -> anyhow::Result<()> {
+        let mut rng = rand::thread_rng();
+        let mut cron_spec = CronSpec {
+            udf_path: UdfPath::from_str("test").unwrap().canonicalize(),
+            udf_args: ConvexArray::try_from(vec![]).unwrap(),
+            cron_schedule: CronSchedule::Hourly { minute_utc: 0 },
+            dispatch: CronDispatchOptions::default(),
+        };
+        let next_ts = ts(1677661200);
+        assert_eq!(
+            compute_jitter(&cron_spec, next_ts, &mut rng)?,
+            Duration::ZERO
+        );
+
+        cron_spec.dispatch.jitter = Some(Duration::from_secs(5 * 60));
+        for _ in 0..100 {
+            assert!(compute_jitter(&cron_spec, next_ts, &mut rng)? < Duration::from_secs(5 * 60));
+        }
+
+        // Jitter longer than an hour is capped so runs stay in order.
+        cron_spec.dispatch.jitter = Some(Duration::from_secs(3 * 60 * 60));
+        for _ in 0..100 {
+            assert!(compute_jitter(&cron_spec, next_ts, &mut rng)? < Duration::from_secs(60 * 60));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_next_ts_cron_timezone() -> anyhow::Result<()> {
+        // Every day at 9:00 in Berlin stays at 9:00 when clocks go forward on
+        // Mar 26 2023.
+        let cron_spec = cron_in_berlin("0 9 * * *");
+        // Mar 25 2023 09:00 CET
+        let prev_ts = ts(1679731200);
+        // Mar 26 2023 09:00 CEST
+        assert_eq!(
+            compute_next_ts(&cron_spec, Some(prev_ts), prev_ts)?,
+            ts(1679814000)
+        );
+
+        // 2:30 doesn't happen on Mar 26 2023, so it runs when the clocks
+        // reach 3:00 CEST instead.
+        let cron_spec = cron_in_berlin("30 2 * * *");
+        // Mar 25 2023 02:30 CET
+        let prev_ts = ts(1679707800);
+        assert_eq!(
+            compute_next_ts(&cron_spec, Some(prev_ts), prev_ts)?,
+            ts(1679792400)
+        );
+
+        // 2:30 happens twice on Oct 29 2023, but it only runs the first time.
+        // Oct 28 2023 02:30 CEST
+        let prev_ts = ts(1698453000);
+        // Oct 29 2023 02:30 CEST
+        let next_ts = compute_next_ts(&cron_spec, Some(prev_ts), prev_ts)?;
+        assert_eq!(next_ts, ts(1698539400));
+        // Oct 30 2023 02:30 CET
+        assert_eq!(
+            compute_next_ts(&cron_spec, Some(next_ts), next_ts)?,
+            ts(1698629400)
+        );
+
+        let cron_spec = CronSpec {
+            cron_schedule: CronSchedule::Cron {
+                cron_expr: "0 9 * * *".to_string(),
+                timezone: Some("Europe/Atlantis".to_string()),
+            },
+            ..cron_spec
+        };
+        assert!(compute_next_ts(&cron_spec, None, prev_ts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_next_ts_calendar() -> anyhow::Result<()> {
+        let cron_spec = CronSpec {
+            udf_path: UdfPath::from_str("test").unwrap().canonicalize(),
+            udf_args: ConvexArray::try_from(vec![]).unwrap(),
+            cron_schedule: CronSchedule::Calendar {
+                expr: "last weekday of month at 17:00".to_string(),
+                timezone: None,
+            },
+            dispatch: CronDispatchOptions::default(),
+        };
+        // Mar 01 2023 00:00 UTC
+        let now = ts(1677628800);
+        // Mar 31 2023 17:00 UTC
+        assert_eq!(compute_next_ts(&cron_spec, None, now)?, ts(1680282000));
+
+        // Mar 31 2023 17:00 CEST
+        let cron_spec = CronSpec {
+            cron_schedule: CronSchedule::Calendar {
+                expr: "last weekday of month at 17:00".to_string(),
+                timezone: Some("Europe/Berlin".to_string()),
+            },
+            ..cron_spec
+        };
+        assert_eq!(compute_next_ts(&cron_spec, None, now)?, ts(1680274800));
+        Ok(())
+    }
 }
@@ -4,16 +4,20 @@ use std::{
     mem,
     ops::Deref,
     str::FromStr,
+    time::Duration,
 };
 
 use anyhow::{
     bail,
     Context,
 };
+use chrono_tz::Tz;
 use common::{
     log_lines::RawLogLines,
     types::Timestamp,
 };
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
 use saffron::Cron;
 use serde::{
     Deserialize,
@@ -35,6 +39,8 @@ use value::{
     ConvexValue,
 };
 
+use super::calendar::CalendarExpression;
+
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum CronValidationError {
     #[error("Invalid JSON")]
@@ -43,8 +49,19 @@ pub enum CronValidationError {
     SecondsMinutesHours,
     #[error("Interval must be an integer greater than 0")]
     InvalidIntervalValue,
+    #[error("Invalid timezone {0:?}: expected an IANA timezone like \"Europe/Berlin\"")]
+    InvalidTimezone(String),
+    #[error("Invalid calendar expression {0:?}: {1}")]
+    InvalidCalendarExpression(String, String),
+    #[error("Jitter must be an integer number of milliseconds from 0 to {MAX_CRON_JITTER_MS}")]
+    InvalidJitter,
+    #[error("maxDispatchPerSecond must be an integer greater than 0")]
+    InvalidMaxDispatchRate,
 }
 
+/// The longest a cron's runs can be delayed by jitter, a day.
+pub const MAX_CRON_JITTER_MS: i64 = 24 * 60 * 60 * 1000;
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub struct CronJob {
@@ -58,6 +75,20 @@ pub struct CronJob {
     pub state: CronJobState,
     pub prev_ts: Option<Timestamp>,
     pub next_ts: Timestamp,
+    // How long `next_ts` was delayed past the schedule's time by the cron's
+    // jitter.
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "(0..MAX_CRON_JITTER_MS as u64).prop_map(Duration::from_millis)")
+    )]
+    pub jitter: Duration,
+}
+
+impl CronJob {
+    /// When the next run is due according to the schedule, before jitter.
+    pub fn scheduled_ts(&self) -> anyhow::Result<Timestamp> {
+        self.next_ts.sub(self.jitter)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,6 +99,8 @@ struct SerializedCronJob {
     state: CronJobState,
     prev_ts: Option<i64>,
     next_ts: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jitter_ms: Option<i64>,
 }
 
 impl TryFrom<CronJob> for SerializedCronJob {
@@ -80,6 +113,9 @@ impl TryFrom<CronJob> for SerializedCronJob {
             state: job.state,
             prev_ts: job.prev_ts.map(|ts| ts.into()),
             next_ts: job.next_ts.into(),
+            jitter_ms: (!job.jitter.is_zero())
+                .then(|| job.jitter.as_millis().try_into())
+                .transpose()?,
         })
     }
 }
@@ -94,6 +130,7 @@ impl TryFrom<SerializedCronJob> for CronJob {
             state: value.state,
             prev_ts: value.prev_ts.map(|ts| ts.try_into()).transpose()?,
             next_ts: value.next_ts.try_into()?,
+            jitter: Duration::from_millis(value.jitter_ms.unwrap_or(0).try_into()?),
         })
     }
 }
@@ -172,6 +209,53 @@ pub struct CronSpec {
     )]
     pub udf_args: ConvexArray,
     pub cron_schedule: CronSchedule,
+    pub dispatch: CronDispatchOptions,
+}
+
+/// Options that spread out a cron's work, so crons that fan out to many
+/// scheduled functions don't start them all at the same moment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct CronDispatchOptions {
+    /// Each run is delayed by a random duration up to this long.
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of((0..MAX_CRON_JITTER_MS as \
+                             u64).prop_map(Duration::from_millis))")
+    )]
+    pub jitter: Option<Duration>,
+    /// The most functions a run's mutation can schedule to start each second.
+    /// Functions past the limit start later, in the order they're due.
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of(1..10_000u32)")
+    )]
+    pub max_dispatch_per_second: Option<u32>,
+}
+
+impl CronDispatchOptions {
+    fn new(jitter_ms: Option<i64>, max_dispatch_per_second: Option<i64>) -> anyhow::Result<Self> {
+        let jitter = jitter_ms
+            .map(|ms| {
+                if !(0..=MAX_CRON_JITTER_MS).contains(&ms) {
+                    anyhow::bail!(CronValidationError::InvalidJitter);
+                }
+                Ok(Duration::from_millis(ms as u64))
+            })
+            .transpose()?;
+        let max_dispatch_per_second = max_dispatch_per_second
+            .map(|rate| {
+                u32::try_from(rate)
+                    .ok()
+                    .filter(|rate| *rate > 0)
+                    .ok_or_else(|| anyhow::anyhow!(CronValidationError::InvalidMaxDispatchRate))
+            })
+            .transpose()?;
+        Ok(Self {
+            jitter,
+            max_dispatch_per_second,
+        })
+    }
 }
 
 impl HeapSize for CronSpec {
@@ -187,6 +271,10 @@ pub struct SerializedCronSpec {
     #[serde(with = "serde_bytes")]
     udf_args: Option<Vec<u8>>,
     cron_schedule: SerializedCronSchedule,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jitter_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_dispatch_per_second: Option<i64>,
 }
 
 impl TryFrom<CronSpec> for SerializedCronSpec {
@@ -201,6 +289,12 @@ impl TryFrom<CronSpec> for SerializedCronSpec {
             udf_path: String::from(spec.udf_path),
             udf_args: Some(udf_args_bytes),
             cron_schedule: spec.cron_schedule.try_into()?,
+            jitter_ms: spec
+                .dispatch
+                .jitter
+                .map(|jitter| jitter.as_millis().try_into())
+                .transpose()?,
+            max_dispatch_per_second: spec.dispatch.max_dispatch_per_second.map(i64::from),
         })
     }
 }
@@ -218,10 +312,12 @@ impl TryFrom<SerializedCronSpec> for CronSpec {
             None => ConvexArray::try_from(vec![])?,
         };
         let cron_schedule = value.cron_schedule.try_into()?;
+        let dispatch = CronDispatchOptions::new(value.jitter_ms, value.max_dispatch_per_second)?;
         Ok(Self {
             udf_path,
             udf_args,
             cron_schedule,
+            dispatch,
         })
     }
 }
@@ -294,7 +390,15 @@ impl TryFrom<JsonValue> for CronSpec {
             },
             #[serde(rename_all = "camelCase")]
             #[serde(rename = "cron")]
-            Cron { cron: String },
+            Cron {
+                cron: String,
+                timezone: Option<String>,
+            },
+            #[serde(rename = "calendar")]
+            Calendar {
+                calendar: String,
+                timezone: Option<String>,
+            },
         }
 
         // The JavaScript object produced by crons.export() uses different names:
@@ -305,6 +409,15 @@ impl TryFrom<JsonValue> for CronSpec {
             name: String,
             args: JsonValue,
             schedule: ScheduleJson,
+            #[serde(default)]
+            dispatch: Option<DispatchJson>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DispatchJson {
+            jitter_ms: Option<i64>,
+            max_dispatch_per_second: Option<i64>,
         }
         let j: CronSpecJson = serde_json::from_value(value.clone())
             .with_context(|| CronValidationError::InvalidJson)?;
@@ -430,18 +543,42 @@ impl TryFrom<JsonValue> for CronSpec {
                     minute_utc,
                 }
             },
-            ScheduleJson::Cron { cron } => {
+            ScheduleJson::Cron { cron, timezone } => {
                 cron.parse::<saffron::Cron>()?;
-                CronSchedule::Cron { cron_expr: cron }
+                if let Some(timezone) = &timezone {
+                    parse_timezone(timezone)?;
+                }
+                CronSchedule::Cron {
+                    cron_expr: cron,
+                    timezone,
+                }
+            },
+            ScheduleJson::Calendar { calendar, timezone } => {
+                calendar.parse::<CalendarExpression>()?;
+                if let Some(timezone) = &timezone {
+                    parse_timezone(timezone)?;
+                }
+                CronSchedule::Calendar {
+                    expr: calendar,
+                    timezone,
+                }
             },
         };
 
+        let dispatch = match j.dispatch {
+            Some(dispatch) => {
+                CronDispatchOptions::new(dispatch.jitter_ms, dispatch.max_dispatch_per_second)?
+            },
+            None => CronDispatchOptions::default(),
+        };
+
         let udf_path: UdfPath = j.name.parse()?;
         let udf_path_canonicalized = udf_path.canonicalize();
         Ok(Self {
             udf_path: udf_path_canonicalized,
             udf_args: ConvexArray::try_from(j.args)?,
             cron_schedule: schedule,
+            dispatch,
         })
     }
 }
@@ -481,6 +618,15 @@ pub enum CronSchedule {
     },
     Cron {
         cron_expr: String,
+        /// IANA timezone the expression is evaluated in, like `Europe/Berlin`.
+        /// It's evaluated in UTC if it's `None`.
+        timezone: Option<String>,
+    },
+    /// A `CalendarExpression`, like "last weekday of month at 17:00".
+    Calendar {
+        expr: String,
+        /// Like `Cron`'s timezone.
+        timezone: Option<String>,
     },
 }
 
@@ -491,14 +637,18 @@ impl HeapSize for CronSchedule {
             CronSchedule::Hourly { .. } => mem::size_of::<i64>(),
             CronSchedule::Daily { .. } => 2 * mem::size_of::<i64>(),
             CronSchedule::Monthly { .. } | CronSchedule::Weekly { .. } => 3 * mem::size_of::<i64>(),
-            CronSchedule::Cron { cron_expr } => cron_expr.heap_size(),
+            CronSchedule::Cron {
+                cron_expr,
+                timezone,
+            } => cron_expr.heap_size() + timezone.heap_size(),
+            CronSchedule::Calendar { expr, timezone } => expr.heap_size() + timezone.heap_size(),
         }
     }
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
-enum SerializedCronSchedule {
+pub(crate) enum SerializedCronSchedule {
     Interval {
         seconds: i64,
     },
@@ -530,6 +680,13 @@ enum SerializedCronSchedule {
     #[serde(rename_all = "camelCase")]
     Cron {
         cron_expr: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+    Calendar {
+        expr: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
     },
 }
 
@@ -565,7 +722,14 @@ impl TryFrom<CronSchedule> for SerializedCronSchedule {
                 hour_utc,
                 minute_utc,
             }),
-            CronSchedule::Cron { cron_expr } => Ok(Self::Cron { cron_expr }),
+            CronSchedule::Cron {
+                cron_expr,
+                timezone,
+            } => Ok(Self::Cron {
+                cron_expr,
+                timezone,
+            }),
+            CronSchedule::Calendar { expr, timezone } => Ok(Self::Calendar { expr, timezone }),
         }
     }
 }
@@ -604,7 +768,16 @@ impl TryFrom<SerializedCronSchedule> for CronSchedule {
                 hour_utc,
                 minute_utc,
             }),
-            SerializedCronSchedule::Cron { cron_expr } => Ok(CronSchedule::Cron { cron_expr }),
+            SerializedCronSchedule::Cron {
+                cron_expr,
+                timezone,
+            } => Ok(CronSchedule::Cron {
+                cron_expr,
+                timezone,
+            }),
+            SerializedCronSchedule::Calendar { expr, timezone } => {
+                Ok(CronSchedule::Calendar { expr, timezone })
+            },
         }
     }
 }
@@ -644,6 +817,11 @@ pub enum CronScheduleProductAnalysis {
     },
     Cron {
         cron_expr: String,
+        timezone: Option<String>,
+    },
+    Calendar {
+        expr: String,
+        timezone: Option<String>,
     },
 }
 
@@ -677,7 +855,14 @@ impl From<CronSchedule> for CronScheduleProductAnalysis {
                 hour_utc,
                 minute_utc,
             },
-            CronSchedule::Cron { cron_expr } => Self::Cron { cron_expr },
+            CronSchedule::Cron {
+                cron_expr,
+                timezone,
+            } => Self::Cron {
+                cron_expr,
+                timezone,
+            },
+            CronSchedule::Calendar { expr, timezone } => Self::Calendar { expr, timezone },
         }
     }
 }
@@ -714,14 +899,36 @@ impl CronSchedule {
             } => format!("{minute_utc} {hour_utc} {day} * *")
                 .parse()
                 .context("Monthly Schedule: Cron parsing from Saffron failed")?,
-            CronSchedule::Cron { cron_expr } => cron_expr
-                .parse()
-                .context("Cron Schedule: Cron parsing from Saffron failed")?,
+            CronSchedule::Cron {
+                cron_expr,
+                timezone,
+            } => {
+                if let Some(timezone) = timezone {
+                    parse_timezone(&timezone)?;
+                }
+                cron_expr
+                    .parse()
+                    .context("Cron Schedule: Cron parsing from Saffron failed")?
+            },
+            CronSchedule::Calendar { expr, timezone } => {
+                if let Some(timezone) = timezone {
+                    parse_timezone(&timezone)?;
+                }
+                expr.parse::<CalendarExpression>()?;
+                return Ok(());
+            },
         };
         Ok(())
     }
 }
 
+/// Parses an IANA timezone like `Europe/Berlin`.
+pub fn parse_timezone(timezone: &str) -> anyhow::Result<Tz> {
+    timezone
+        .parse()
+        .map_err(|_| CronValidationError::InvalidTimezone(timezone.to_string()).into())
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub struct CronJobLog {
@@ -1026,8 +1233,11 @@ impl TryFrom<ConvexObject> for CronJobLogLines {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use cmd_util::env::env_config;
     use proptest::prelude::*;
+    use serde_json::json;
     use sync_types::testing::assert_roundtrips;
     use value::{
         assert_obj,
@@ -1036,11 +1246,15 @@ mod tests {
     };
 
     use crate::cron_jobs::types::{
+        CronDispatchOptions,
         CronJob,
         CronJobLog,
         CronJobLogLines,
         CronJobResult,
         CronJobStatus,
+        CronSchedule,
+        CronSpec,
+        CronValidationError,
     };
 
     proptest! {
@@ -1086,4 +1300,73 @@ mod tests {
         );
         assert_roundtrips::<_, CronJob>(cron_job_obj);
     }
+    #[test]
+    fn test_cron_spec_dispatch_options() -> anyhow::Result<()> {
+        let spec_json = |dispatch: serde_json::Value| {
+            json!({
+                "name": "digests.js:scheduleAll",
+                "args": [{}],
+                "schedule": {"type": "daily", "hourUTC": 9, "minuteUTC": 0},
+                "dispatch": dispatch,
+            })
+        };
+        let spec = CronSpec::try_from(spec_json(json!({
+            "jitterMs": 300000,
+            "maxDispatchPerSecond": 50,
+        })))?;
+        assert_eq!(
+            spec.dispatch,
+            CronDispatchOptions {
+                jitter: Some(Duration::from_secs(300)),
+                max_dispatch_per_second: Some(50),
+            }
+        );
+        let spec = CronSpec::try_from(spec_json(json!({})))?;
+        assert_eq!(spec.dispatch, CronDispatchOptions::default());
+
+        assert!(CronSpec::try_from(spec_json(json!({"jitterMs": -1}))).is_err());
+        assert!(CronSpec::try_from(spec_json(json!({"jitterMs": 86400001}))).is_err());
+        assert!(CronSpec::try_from(spec_json(json!({"maxDispatchPerSecond": 0}))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cron_spec_calendar() -> anyhow::Result<()> {
+        let spec_json = |schedule: serde_json::Value| {
+            json!({
+                "name": "reports.js:monthly",
+                "args": [{}],
+                "schedule": schedule,
+            })
+        };
+        let spec = CronSpec::try_from(spec_json(json!({
+            "type": "calendar",
+            "calendar": "last weekday of month at 17:00",
+            "timezone": "Europe/Berlin",
+        })))?;
+        assert_eq!(
+            spec.cron_schedule,
+            CronSchedule::Calendar {
+                expr: "last weekday of month at 17:00".to_string(),
+                timezone: Some("Europe/Berlin".to_string()),
+            }
+        );
+
+        // Invalid expressions are rejected when the crons are pushed.
+        let err = CronSpec::try_from(spec_json(json!({
+            "type": "calendar",
+            "calendar": "6th tuesday of month at 9:00",
+        })))
+        .unwrap_err();
+        assert!(err
+            .downcast_ref::<CronValidationError>()
+            .is_some_and(|e| matches!(e, CronValidationError::InvalidCalendarExpression(..))));
+        assert!(CronSpec::try_from(spec_json(json!({
+            "type": "calendar",
+            "calendar": "last weekday of month at 17:00",
+            "timezone": "Europe/Atlantis",
+        })))
+        .is_err());
+        Ok(())
+    }
 }
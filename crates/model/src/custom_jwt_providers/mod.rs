@@ -0,0 +1,104 @@
+//! The custom JWT providers users can authenticate with, in addition to the
+//! OpenID Connect providers in `auth.config.js`.
+use std::sync::LazyLock;
+
+use common::{
+    auth::CustomJwtProvider,
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    custom_jwt_providers::types::CustomJwtProviderPersisted,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static CUSTOM_JWT_PROVIDERS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_custom_jwt_providers"
+        .parse()
+        .expect("Invalid built-in custom JWT providers table")
+});
+
+pub struct CustomJwtProvidersTable;
+impl SystemTable for CustomJwtProvidersTable {
+    fn table_name(&self) -> &'static TableName {
+        &CUSTOM_JWT_PROVIDERS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<CustomJwtProviderPersisted>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct CustomJwtProvidersModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> CustomJwtProvidersModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<CustomJwtProvider>>> {
+        let query = Query::full_table_scan(CUSTOM_JWT_PROVIDERS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut providers = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let provider: ParsedDocument<CustomJwtProviderPersisted> = doc.try_into()?;
+            providers.push(provider.map(|p| Ok(p.0))?);
+        }
+        Ok(providers)
+    }
+
+    pub async fn insert(
+        &mut self,
+        provider: CustomJwtProvider,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(
+                &CUSTOM_JWT_PROVIDERS_TABLE,
+                CustomJwtProviderPersisted(provider).try_into()?,
+            )
+            .await
+    }
+
+    /// Deletes the provider with the given id, returning whether it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(provider) = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|provider| provider.id().developer_id.encode() == id)
+        else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(provider.id())
+            .await?;
+        Ok(true)
+    }
+}
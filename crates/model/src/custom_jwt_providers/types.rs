@@ -0,0 +1,101 @@
+use common::auth::{
+    CustomJwtKey,
+    CustomJwtProvider,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// Persisted version of [`CustomJwtProvider`], which is defined in `common`
+/// for the authentication crate.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct CustomJwtProviderPersisted(pub CustomJwtProvider);
+
+/// Claim names are arbitrary strings, so the claim maps are stored as lists
+/// rather than objects.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedCustomJwtProvider {
+    issuer: String,
+    audience: Option<String>,
+    algorithm: String,
+    key: SerializedCustomJwtKey,
+    required_claims: Vec<SerializedRequiredClaim>,
+    claim_mapping: Vec<SerializedClaimMapping>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SerializedCustomJwtKey {
+    Secret { secret: String },
+    Jwks { jwks: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedRequiredClaim {
+    claim: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedClaimMapping {
+    field: String,
+    claim: String,
+}
+
+impl From<CustomJwtProviderPersisted> for SerializedCustomJwtProvider {
+    fn from(CustomJwtProviderPersisted(provider): CustomJwtProviderPersisted) -> Self {
+        Self {
+            issuer: provider.issuer,
+            audience: provider.audience,
+            algorithm: provider.algorithm,
+            key: match provider.key {
+                CustomJwtKey::Secret(secret) => SerializedCustomJwtKey::Secret { secret },
+                CustomJwtKey::Jwks(jwks) => SerializedCustomJwtKey::Jwks { jwks },
+            },
+            required_claims: provider
+                .required_claims
+                .into_iter()
+                .map(|(claim, value)| SerializedRequiredClaim { claim, value })
+                .collect(),
+            claim_mapping: provider
+                .claim_mapping
+                .into_iter()
+                .map(|(field, claim)| SerializedClaimMapping { field, claim })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<SerializedCustomJwtProvider> for CustomJwtProviderPersisted {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedCustomJwtProvider) -> anyhow::Result<Self> {
+        Ok(Self(CustomJwtProvider {
+            issuer: value.issuer,
+            audience: value.audience,
+            algorithm: value.algorithm,
+            key: match value.key {
+                SerializedCustomJwtKey::Secret { secret } => CustomJwtKey::Secret(secret),
+                SerializedCustomJwtKey::Jwks { jwks } => CustomJwtKey::Jwks(jwks),
+            },
+            required_claims: value
+                .required_claims
+                .into_iter()
+                .map(|SerializedRequiredClaim { claim, value }| (claim, value))
+                .collect(),
+            claim_mapping: value
+                .claim_mapping
+                .into_iter()
+                .map(|SerializedClaimMapping { field, claim }| (field, claim))
+                .collect(),
+        }))
+    }
+}
+
+codegen_convex_serialization!(CustomJwtProviderPersisted, SerializedCustomJwtProvider);
@@ -0,0 +1,159 @@
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        IndexRange,
+        IndexRangeExpression,
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+    types::IndexName,
+};
+use database::{
+    defaults::system_index,
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use errors::ErrorMetadata;
+use value::{
+    ConvexValue,
+    FieldPath,
+    TableName,
+    TableNamespace,
+};
+
+use self::types::DependencyLayer;
+use crate::{
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static DEPENDENCY_LAYERS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_dependency_layers"
+        .parse()
+        .expect("Invalid built-in dependency layers table")
+});
+
+pub static DEPENDENCY_LAYERS_INDEX_BY_NAME: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&DEPENDENCY_LAYERS_TABLE, "by_name"));
+static NAME_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "name".parse().expect("invalid name field"));
+
+pub struct DependencyLayersTable;
+impl SystemTable for DependencyLayersTable {
+    fn table_name(&self) -> &'static TableName {
+        &DEPENDENCY_LAYERS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![SystemIndex {
+            name: DEPENDENCY_LAYERS_INDEX_BY_NAME.clone(),
+            fields: vec![NAME_FIELD.clone()].try_into().unwrap(),
+        }]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<DependencyLayer>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct DependencyLayersModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> DependencyLayersModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn get(
+        &mut self,
+        name: &str,
+    ) -> anyhow::Result<Option<ParsedDocument<DependencyLayer>>> {
+        let range = vec![IndexRangeExpression::Eq(
+            NAME_FIELD.clone(),
+            ConvexValue::try_from(name.to_string())?.into(),
+        )];
+        let query = Query::index_range(IndexRange {
+            index_name: DEPENDENCY_LAYERS_INDEX_BY_NAME.clone(),
+            range,
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        query_stream
+            .expect_at_most_one(self.tx)
+            .await?
+            .map(|doc| doc.try_into())
+            .transpose()
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<DependencyLayer>> {
+        let query = Query::index_range(IndexRange {
+            index_name: DEPENDENCY_LAYERS_INDEX_BY_NAME.clone(),
+            range: vec![],
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut layers = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let layer: ParsedDocument<DependencyLayer> = doc.try_into()?;
+            layers.push(layer.into_value());
+        }
+        Ok(layers)
+    }
+
+    /// Adds a layer or replaces the layer with the same name. Source packages
+    /// keep their own copy of the layers they were pushed with, so replacing
+    /// a layer only affects later pushes.
+    pub async fn upsert(&mut self, layer: DependencyLayer) -> anyhow::Result<()> {
+        types::validate_dependency_layer_name(&layer.name)?;
+        match self.get(&layer.name).await? {
+            Some(existing) => {
+                SystemMetadataModel::new_global(self.tx)
+                    .replace(existing.id(), layer.try_into()?)
+                    .await?;
+            },
+            None => {
+                SystemMetadataModel::new_global(self.tx)
+                    .insert(&DEPENDENCY_LAYERS_TABLE, layer.try_into()?)
+                    .await?;
+            },
+        }
+        Ok(())
+    }
+
+    pub async fn delete(&mut self, name: &str) -> anyhow::Result<Option<DependencyLayer>> {
+        let Some(existing) = self.get(name).await? else {
+            return Ok(None);
+        };
+        let (id, layer) = existing.into_id_and_value();
+        SystemMetadataModel::new_global(self.tx).delete(id).await?;
+        Ok(Some(layer))
+    }
+
+    /// Looks up the layers a push asked for, in order.
+    pub async fn resolve(&mut self, names: &[String]) -> anyhow::Result<Vec<DependencyLayer>> {
+        let mut layers = Vec::with_capacity(names.len());
+        for name in names {
+            let Some(layer) = self.get(name).await? else {
+                anyhow::bail!(ErrorMetadata::bad_request(
+                    "DependencyLayerNotFound",
+                    format!(
+                        "Dependency layer {name:?} doesn't exist. Upload it before pushing \
+                         functions that use it."
+                    ),
+                ));
+            };
+            layers.push(layer.into_value());
+        }
+        Ok(layers)
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use common::types::ObjectKey;
+use errors::ErrorMetadata;
+use value::{
+    obj,
+    sha256::Sha256Digest,
+    ConvexObject,
+    ConvexValue,
+};
+
+const MAX_DEPENDENCY_LAYER_NAME_LEN: usize = 64;
+
+/// A prebuilt `node_modules` archive, e.g. for packages with native binaries
+/// like `sharp` or Prisma's engines that can't be bundled or installed with
+/// `build_deps`. Layers are uploaded through the admin API, attached to pushes
+/// by name, and extracted next to the pushed modules by the node executor.
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyLayer {
+    pub name: String,
+    pub storage_key: ObjectKey,
+    pub sha256: Sha256Digest,
+    /// Size of the zipped archive.
+    pub size_bytes: u64,
+}
+
+pub fn validate_dependency_layer_name(name: &str) -> anyhow::Result<()> {
+    let is_valid = !name.is_empty()
+        && name.len() <= MAX_DEPENDENCY_LAYER_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    anyhow::ensure!(
+        is_valid,
+        ErrorMetadata::bad_request(
+            "InvalidDependencyLayerName",
+            format!(
+                "Invalid dependency layer name {name:?}. Names must be at most \
+                 {MAX_DEPENDENCY_LAYER_NAME_LEN} characters of letters, digits, '-', '_', and '.'."
+            ),
+        )
+    );
+    Ok(())
+}
+
+impl TryFrom<DependencyLayer> for ConvexObject {
+    type Error = anyhow::Error;
+
+    fn try_from(value: DependencyLayer) -> Result<Self, Self::Error> {
+        let storage_key: String = value.storage_key.into();
+        obj!(
+            "name" => value.name,
+            "storageKey" => storage_key,
+            "sha256" => value.sha256,
+            "sizeBytes" => ConvexValue::Int64(value.size_bytes as i64),
+        )
+    }
+}
+
+impl TryFrom<ConvexObject> for DependencyLayer {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ConvexObject) -> Result<Self, Self::Error> {
+        let mut fields = BTreeMap::from(value);
+
+        let name = match fields.remove("name") {
+            Some(ConvexValue::String(name)) => name.into(),
+            _ => anyhow::bail!("Missing or invalid 'name' in {fields:?}"),
+        };
+        let storage_key = match fields.remove("storageKey") {
+            Some(ConvexValue::String(key)) => String::from(key).try_into()?,
+            _ => anyhow::bail!("Missing or invalid 'storageKey' in {fields:?}"),
+        };
+        let sha256 = match fields.remove("sha256") {
+            Some(sha256) => sha256.try_into()?,
+            _ => anyhow::bail!("Missing or invalid 'sha256' in {fields:?}"),
+        };
+        let size_bytes = match fields.remove("sizeBytes") {
+            Some(ConvexValue::Int64(size)) => size as u64,
+            _ => anyhow::bail!("Missing or invalid 'sizeBytes' in {fields:?}"),
+        };
+        Ok(Self {
+            name,
+            storage_key,
+            sha256,
+            size_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cmd_util::env::env_config;
+    use common::testing::assert_roundtrips;
+    use proptest::prelude::*;
+    use value::ConvexObject;
+
+    use super::{
+        validate_dependency_layer_name,
+        DependencyLayer,
+    };
+
+    proptest! {
+        #![proptest_config(
+            ProptestConfig { cases: 256 * env_config("CONVEX_PROPTEST_MULTIPLIER", 1), failure_persistence: None, ..ProptestConfig::default() }
+        )]
+        #[test]
+        fn test_dependency_layer_roundtrip(v in any::<DependencyLayer>()) {
+            assert_roundtrips::<DependencyLayer, ConvexObject>(v);
+        }
+    }
+
+    #[test]
+    fn test_validate_dependency_layer_name() {
+        assert!(validate_dependency_layer_name("sharp-0.33_linux-x64").is_ok());
+        assert!(validate_dependency_layer_name("").is_err());
+        assert!(validate_dependency_layer_name("../sharp").is_err());
+        assert!(validate_dependency_layer_name(&"a".repeat(65)).is_err());
+    }
+}
@@ -2,16 +2,23 @@ use std::sync::LazyLock;
 
 use common::{
     document::{
+        CreationTime,
         ParsedDocument,
         ResolvedDocument,
+        CREATION_TIME_FIELD_PATH,
     },
     obj,
     query::{
+        IndexRange,
+        IndexRangeExpression,
         Order,
         Query,
     },
     runtime::Runtime,
-    types::MemberId,
+    types::{
+        IndexName,
+        MemberId,
+    },
 };
 use database::{
     unauthorized_error,
@@ -20,6 +27,10 @@ use database::{
     Transaction,
 };
 use futures_async_stream::try_stream;
+use keybroker::{
+    AdminIdentityPrincipal,
+    Identity,
+};
 use value::{
     ConvexObject,
     FieldPath,
@@ -30,7 +41,10 @@ use value::{
 
 pub mod types;
 
-use types::DeploymentAuditLogEvent;
+use types::{
+    DeploymentAuditLogEntry,
+    DeploymentAuditLogEvent,
+};
 
 use crate::{
     SystemIndex,
@@ -46,6 +60,9 @@ pub static DEPLOYMENT_AUDIT_LOG_TABLE: LazyLock<TableName> = LazyLock::new(|| {
 pub static ACTION_FIELD: LazyLock<FieldPath> =
     LazyLock::new(|| "action".parse().expect("invalid action field"));
 
+/// Administrative changes to the deployment, like pushes, environment variable
+/// changes, imports and exports. Events are only ever inserted, never updated
+/// or deleted.
 pub struct DeploymentAuditLogsTable;
 impl SystemTable for DeploymentAuditLogsTable {
     fn table_name(&self) -> &'static TableName {
@@ -89,6 +106,15 @@ impl<'a, RT: Runtime> DeploymentAuditLogModel<'a, RT> {
             anyhow::bail!(unauthorized_error("insert_deployment_audit_log_event"));
         }
         let member_id = member_id_override.or_else(|| self.tx.identity().member_id());
+        let actor = match (member_id_override, self.tx.identity()) {
+            (Some(member_id), _) => Some(AdminIdentityPrincipal::Member(member_id).to_string()),
+            (None, Identity::InstanceAdmin(admin_identity))
+            | (None, Identity::ActingUser(admin_identity, _)) => {
+                Some(admin_identity.principal().to_string())
+            },
+            (None, Identity::System(_)) => Some("system".to_string()),
+            (None, Identity::User(_) | Identity::Unknown) => None,
+        };
         let member_id_value = member_id
             .map(|member_id| {
                 let member_id_u64: u64 = member_id.into();
@@ -102,8 +128,14 @@ impl<'a, RT: Runtime> DeploymentAuditLogModel<'a, RT> {
                 Some(member_id) => event_object.shallow_merge(obj!("member_id" => member_id)?)?,
                 None => event_object.shallow_merge(obj!("member_id" => null)?)?,
             };
+            let event_object_with_actor = match &actor {
+                Some(actor) => {
+                    event_object_with_member_id.shallow_merge(obj!("actor" => actor.clone())?)?
+                },
+                None => event_object_with_member_id,
+            };
             let id = SystemMetadataModel::new_global(self.tx)
-                .insert_metadata(&DEPLOYMENT_AUDIT_LOG_TABLE, event_object_with_member_id)
+                .insert_metadata(&DEPLOYMENT_AUDIT_LOG_TABLE, event_object_with_actor)
                 .await?;
             deployment_audit_log_ids.push(id);
         }
@@ -119,6 +151,35 @@ impl<'a, RT: Runtime> DeploymentAuditLogModel<'a, RT> {
         Ok(ids[0])
     }
 
+    /// The most recent `limit` events created before `before`, newest first.
+    pub async fn list_recent(
+        &mut self,
+        before: Option<CreationTime>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ParsedDocument<DeploymentAuditLogEntry>>> {
+        if !(self.tx.identity().is_system() || self.tx.identity().is_admin()) {
+            anyhow::bail!(unauthorized_error("list_deployment_audit_log"));
+        }
+        let range = before
+            .map(|before| {
+                IndexRangeExpression::Lt(CREATION_TIME_FIELD_PATH.clone(), f64::from(before).into())
+            })
+            .into_iter()
+            .collect();
+        let index_query = Query::index_range(IndexRange {
+            index_name: IndexName::by_creation_time(DEPLOYMENT_AUDIT_LOG_TABLE.clone()),
+            range,
+            order: Order::Desc,
+        })
+        .limit(limit);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, index_query)?;
+        let mut entries = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            entries.push(doc.try_into()?);
+        }
+        Ok(entries)
+    }
+
     #[try_stream(boxed, ok = ParsedDocument<DeploymentAuditLogEvent>, error = anyhow::Error)]
     pub async fn list(&mut self) {
         let value_query = Query::full_table_scan(DEPLOYMENT_AUDIT_LOG_TABLE.clone(), Order::Asc);
@@ -129,3 +190,66 @@ impl<'a, RT: Runtime> DeploymentAuditLogModel<'a, RT> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::types::MemberId;
+    use database::test_helpers::DbFixtures;
+    use keybroker::{
+        AdminIdentity,
+        Identity,
+    };
+    use runtime::testing::TestRuntime;
+
+    use super::{
+        types::DeploymentAuditLogEvent,
+        DeploymentAuditLogModel,
+    };
+
+    #[convex_macro::test_runtime]
+    async fn test_list_recent_with_actors(rt: TestRuntime) -> anyhow::Result<()> {
+        let database = DbFixtures::new_with_model(&rt).await?.db;
+        let mut tx = database.begin(Identity::system()).await?;
+        DeploymentAuditLogModel::new(&mut tx)
+            .insert(vec![DeploymentAuditLogEvent::ClearTables])
+            .await?;
+        database.commit(tx).await?;
+
+        let admin = Identity::InstanceAdmin(AdminIdentity::new_for_test_only(
+            "carnitas".to_string(),
+            MemberId(7),
+        ));
+        let mut tx = database.begin(admin.clone()).await?;
+        DeploymentAuditLogModel::new(&mut tx)
+            .insert(vec![DeploymentAuditLogEvent::CreateEnvironmentVariable {
+                name: "API_KEY".parse()?,
+            }])
+            .await?;
+        database.commit(tx).await?;
+
+        let mut tx = database.begin(admin).await?;
+        let entries = DeploymentAuditLogModel::new(&mut tx)
+            .list_recent(None, 10)
+            .await?;
+        assert_eq!(entries.len(), 2);
+        // Newest first.
+        assert_eq!(entries[0].actor.as_deref(), Some("member:7"));
+        assert_eq!(entries[0].member_id, Some(MemberId(7)));
+        assert_eq!(entries[0].event.action(), "create_environment_variable");
+        assert_eq!(entries[1].actor.as_deref(), Some("system"));
+        assert_eq!(entries[1].member_id, None);
+
+        let older = DeploymentAuditLogModel::new(&mut tx)
+            .list_recent(Some(entries[0].creation_time()), 10)
+            .await?;
+        assert_eq!(older.len(), 1);
+        assert_eq!(older[0].event.action(), "clear_tables");
+
+        let mut tx = database.begin(Identity::Unknown).await?;
+        assert!(DeploymentAuditLogModel::new(&mut tx)
+            .list_recent(None, 10)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
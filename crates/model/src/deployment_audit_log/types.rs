@@ -21,9 +21,13 @@ use common::{
         GenericIndexName,
         IndexDiff,
         IndexName,
+        MemberId,
     },
 };
-use database::LegacyIndexDiff;
+use database::{
+    LegacyIndexDiff,
+    SchemaDiff,
+};
 #[cfg(any(test, feature = "testing"))]
 use proptest::prelude::*;
 use serde::{
@@ -34,6 +38,7 @@ use serde_json::Value as JsonValue;
 use value::{
     codegen_convex_serialization,
     obj,
+    remove_boolean,
     remove_int64,
     remove_nullable_string,
     remove_object,
@@ -43,6 +48,7 @@ use value::{
     val,
     ConvexObject,
     ConvexValue,
+    FieldName,
     TableName,
 };
 
@@ -51,10 +57,18 @@ use crate::{
     backend_state::types::BackendState,
     components::config::{
         ComponentDiff,
+        ComponentDiffType,
         SerializedComponentDiff,
     },
     config::types::ConfigDiff,
-    environment_variables::types::EnvVarName,
+    environment_variables::types::{
+        EnvVarName,
+        EnvVarScope,
+    },
+    exports::types::{
+        ExportFormat,
+        ExportRequestor,
+    },
     snapshot_imports::types::{
         ImportFormat,
         ImportMode,
@@ -125,6 +139,18 @@ pub enum DeploymentAuditLogEvent {
         previous_name: EnvVarName,
         name: EnvVarName,
     },
+    CreateEnvironmentVariableOverride {
+        name: EnvVarName,
+        scope: EnvVarScope,
+    },
+    UpdateEnvironmentVariableOverride {
+        name: EnvVarName,
+        scope: EnvVarScope,
+    },
+    DeleteEnvironmentVariableOverride {
+        name: EnvVarName,
+        scope: EnvVarScope,
+    },
     PushConfig {
         config_diff: ConfigDiff,
     },
@@ -161,6 +187,19 @@ pub enum DeploymentAuditLogEvent {
         table_names_deleted: BTreeMap<ComponentPath, Vec<TableName>>,
         table_count_deleted: u64,
     },
+    /// A service exchanged its credentials for a token to act as a user.
+    ExchangeToken {
+        /// The token identifier of the user it acts as.
+        token_identifier: String,
+        /// The admin or service account that asked for the token.
+        actor: String,
+    },
+    RequestExport {
+        format: ExportFormat,
+        requestor: ExportRequestor,
+        /// Whether only the changes since an earlier export are exported.
+        incremental: bool,
+    },
 }
 
 impl From<LegacyIndexDiff> for DeploymentAuditLogEvent {
@@ -204,6 +243,15 @@ impl DeploymentAuditLogEvent {
             DeploymentAuditLogEvent::ReplaceEnvironmentVariable { .. } => {
                 "replace_environment_variable"
             },
+            DeploymentAuditLogEvent::CreateEnvironmentVariableOverride { .. } => {
+                "create_environment_variable_override"
+            },
+            DeploymentAuditLogEvent::UpdateEnvironmentVariableOverride { .. } => {
+                "update_environment_variable_override"
+            },
+            DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride { .. } => {
+                "delete_environment_variable_override"
+            },
             DeploymentAuditLogEvent::PushConfig { .. } => "push_config",
             DeploymentAuditLogEvent::PushConfigWithComponents { .. } => {
                 "push_config_with_components"
@@ -212,6 +260,144 @@ impl DeploymentAuditLogEvent {
             DeploymentAuditLogEvent::ChangeDeploymentState { .. } => "change_deployment_state",
             DeploymentAuditLogEvent::SnapshotImport { .. } => "snapshot_import",
             DeploymentAuditLogEvent::ClearTables => "clear_tables",
+            DeploymentAuditLogEvent::ExchangeToken { .. } => "exchange_token",
+            DeploymentAuditLogEvent::RequestExport { .. } => "request_export",
+        }
+    }
+
+    /// A one-line description of what changed, for people reading the log.
+    pub fn summary(&self) -> String {
+        match self {
+            DeploymentAuditLogEvent::CreateEnvironmentVariable { name } => {
+                format!("Created environment variable {name}")
+            },
+            DeploymentAuditLogEvent::UpdateEnvironmentVariable { name } => {
+                format!("Updated environment variable {name}")
+            },
+            DeploymentAuditLogEvent::DeleteEnvironmentVariable { name } => {
+                format!("Deleted environment variable {name}")
+            },
+            DeploymentAuditLogEvent::ReplaceEnvironmentVariable {
+                previous_name,
+                name,
+            } => format!("Replaced environment variable {previous_name} with {name}"),
+            DeploymentAuditLogEvent::CreateEnvironmentVariableOverride { name, scope } => {
+                format!(
+                    "Created environment variable {name} for {}",
+                    describe_scope(scope)
+                )
+            },
+            DeploymentAuditLogEvent::UpdateEnvironmentVariableOverride { name, scope } => {
+                format!(
+                    "Updated environment variable {name} for {}",
+                    describe_scope(scope)
+                )
+            },
+            DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride { name, scope } => {
+                format!(
+                    "Deleted environment variable {name} for {}",
+                    describe_scope(scope)
+                )
+            },
+            DeploymentAuditLogEvent::PushConfig { config_diff } => {
+                let mut changes = Changes::default();
+                changes
+                    .count("auth provider", "added", &config_diff.auth_diff.added)
+                    .count("auth provider", "removed", &config_diff.auth_diff.removed)
+                    .count("function", "added", &config_diff.module_diff.added)
+                    .count("function", "removed", &config_diff.module_diff.removed)
+                    .count("cron", "added", &config_diff.cron_diff.added)
+                    .count("cron", "updated", &config_diff.cron_diff.updated)
+                    .count("cron", "deleted", &config_diff.cron_diff.deleted)
+                    .count("index", "added", &config_diff.index_diff.added)
+                    .count("index", "removed", &config_diff.index_diff.dropped)
+                    .schema(&config_diff.schema_diff);
+                changes.summary("Pushed")
+            },
+            DeploymentAuditLogEvent::PushConfigWithComponents { diffs } => {
+                let mut changes = Changes::default();
+                changes
+                    .count("auth provider", "added", &diffs.auth_diff.added)
+                    .count("auth provider", "removed", &diffs.auth_diff.removed);
+                for (path, diff) in &diffs.component_diffs {
+                    let component = if path.is_root() {
+                        "the app".to_string()
+                    } else {
+                        format!("component {path}")
+                    };
+                    let mut component_changes = Changes::default();
+                    component_changes
+                        .count("function", "added", &diff.module_diff.added)
+                        .count("function", "removed", &diff.module_diff.removed)
+                        .count("cron", "added", &diff.cron_diff.added)
+                        .count("cron", "updated", &diff.cron_diff.updated)
+                        .count("cron", "deleted", &diff.cron_diff.deleted)
+                        .count("index", "added", &diff.index_diff.added_indexes)
+                        .count("index", "removed", &diff.index_diff.removed_indexes)
+                        .schema(&diff.schema_diff);
+                    let change = match diff.diff_type {
+                        ComponentDiffType::Create => format!("mounted {component}"),
+                        ComponentDiffType::Unmount => format!("unmounted {component}"),
+                        ComponentDiffType::Remount => format!("remounted {component}"),
+                        ComponentDiffType::Modify if component_changes.0.is_empty() => continue,
+                        ComponentDiffType::Modify => component,
+                    };
+                    if component_changes.0.is_empty() {
+                        changes.0.push(change);
+                    } else {
+                        changes.0.push(component_changes.summary(&change));
+                    }
+                }
+                changes.summary("Pushed")
+            },
+            DeploymentAuditLogEvent::BuildIndexes {
+                added_indexes,
+                removed_indexes,
+            } => {
+                let mut changes = Changes::default();
+                changes.count("index", "added", added_indexes);
+                changes.count("index", "removed", removed_indexes);
+                changes.summary("Built indexes")
+            },
+            DeploymentAuditLogEvent::ChangeDeploymentState {
+                old_state,
+                new_state,
+            } => format!("Changed the deployment state from {old_state} to {new_state}"),
+            DeploymentAuditLogEvent::ClearTables => "Cleared tables".to_string(),
+            DeploymentAuditLogEvent::SnapshotImport {
+                table_count,
+                import_mode,
+                table_count_deleted,
+                ..
+            } => format!(
+                "Imported {table_count} {} ({import_mode}), deleting {table_count_deleted} {}",
+                plural("table", *table_count as usize),
+                plural("table", *table_count_deleted as usize),
+            ),
+            DeploymentAuditLogEvent::ExchangeToken {
+                token_identifier,
+                actor,
+            } => format!("{actor} exchanged a token to act as {token_identifier}"),
+            DeploymentAuditLogEvent::RequestExport {
+                format,
+                requestor,
+                incremental,
+            } => {
+                let format = match format {
+                    ExportFormat::Zip {
+                        include_storage: true,
+                        ..
+                    } => "zip export with file storage",
+                    ExportFormat::Zip { .. } => "zip export",
+                    ExportFormat::Parquet => "Parquet export",
+                    ExportFormat::Csv { .. } => "CSV export",
+                };
+                if *incremental {
+                    format!("Requested an incremental {format} ({requestor})")
+                } else {
+                    format!("Requested a {format} ({requestor})")
+                }
+            },
         }
     }
 
@@ -228,6 +414,20 @@ impl DeploymentAuditLogEvent {
             } => {
                 obj!("variable_name" => name.to_string(), "previous_variable_name" => previous_name.to_string())
             },
+            DeploymentAuditLogEvent::CreateEnvironmentVariableOverride { name, scope }
+            | DeploymentAuditLogEvent::UpdateEnvironmentVariableOverride { name, scope }
+            | DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride { name, scope } => {
+                let component: ConvexValue = scope.component.serialize().try_into()?;
+                let function: ConvexValue = scope
+                    .udf_path
+                    .map(|udf_path| udf_path.to_string())
+                    .try_into()?;
+                obj!(
+                    "variable_name" => name.to_string(),
+                    "component" => component,
+                    "function" => function,
+                )
+            },
             DeploymentAuditLogEvent::PushConfig { config_diff } => {
                 ConvexObject::try_from(config_diff)
             },
@@ -329,6 +529,23 @@ impl DeploymentAuditLogEvent {
                 )
             },
             DeploymentAuditLogEvent::ClearTables => obj!(),
+            DeploymentAuditLogEvent::ExchangeToken {
+                token_identifier,
+                actor,
+            } => {
+                obj!("token_identifier" => token_identifier, "actor" => actor)
+            },
+            DeploymentAuditLogEvent::RequestExport {
+                format,
+                requestor,
+                incremental,
+            } => {
+                obj!(
+                    "format" => ConvexObject::try_from(format)?,
+                    "requestor" => requestor.to_string(),
+                    "incremental" => incremental,
+                )
+            },
         }
     }
 
@@ -350,6 +567,48 @@ impl DeploymentAuditLogEvent {
     }
 }
 
+fn plural(noun: &str, count: usize) -> String {
+    if count == 1 {
+        noun.to_string()
+    } else if noun.ends_with('x') {
+        format!("{noun}es")
+    } else {
+        format!("{noun}s")
+    }
+}
+
+/// The changes made by a push, like "2 functions added".
+#[derive(Default)]
+struct Changes(Vec<String>);
+
+impl Changes {
+    fn count<T>(&mut self, noun: &str, verb: &str, items: &[T]) -> &mut Self {
+        if !items.is_empty() {
+            let count = items.len();
+            let noun = plural(noun, count);
+            self.0.push(format!("{count} {noun} {verb}"));
+        }
+        self
+    }
+
+    fn schema(&mut self, schema_diff: &Option<SchemaDiff>) -> &mut Self {
+        if let Some(schema_diff) = schema_diff
+            && schema_diff.previous_schema != schema_diff.next_schema
+        {
+            self.0.push("schema changed".to_string());
+        }
+        self
+    }
+
+    fn summary(&self, prefix: &str) -> String {
+        if self.0.is_empty() {
+            format!("{prefix} with no changes")
+        } else {
+            format!("{prefix}: {}", self.0.join(", "))
+        }
+    }
+}
+
 impl TryFrom<DeploymentAuditLogEvent> for ConvexObject {
     type Error = anyhow::Error;
 
@@ -358,6 +617,34 @@ impl TryFrom<DeploymentAuditLogEvent> for ConvexObject {
     }
 }
 
+/// Where an environment variable override applies, e.g. "function
+/// messages.js:send in component waitlist".
+fn describe_scope(scope: &EnvVarScope) -> String {
+    let component = if scope.component.is_root() {
+        None
+    } else {
+        Some(format!("component {}", scope.component))
+    };
+    match (&scope.udf_path, component) {
+        (Some(udf_path), Some(component)) => format!("function {udf_path} in {component}"),
+        (Some(udf_path), None) => format!("function {udf_path}"),
+        (None, Some(component)) => component,
+        (None, None) => "the app".to_string(),
+    }
+}
+
+fn remove_scope(fields: &mut BTreeMap<FieldName, ConvexValue>) -> anyhow::Result<EnvVarScope> {
+    let component =
+        ComponentPath::deserialize(remove_nullable_string(fields, "component")?.as_deref())?;
+    let udf_path = remove_nullable_string(fields, "function")?
+        .map(|udf_path| udf_path.parse())
+        .transpose()?;
+    Ok(EnvVarScope {
+        component,
+        udf_path,
+    })
+}
+
 fn value_to_index_metadata(
     value: ConvexValue,
 ) -> anyhow::Result<(IndexName, DeveloperIndexConfig)> {
@@ -391,6 +678,24 @@ impl TryFrom<ConvexObject> for DeploymentAuditLogEvent {
                 previous_name: remove_string(&mut fields, "previous_variable_name")?.parse()?,
                 name: remove_string(&mut fields, "variable_name")?.parse()?,
             },
+            "create_environment_variable_override" => {
+                DeploymentAuditLogEvent::CreateEnvironmentVariableOverride {
+                    name: remove_string(&mut fields, "variable_name")?.parse()?,
+                    scope: remove_scope(&mut fields)?,
+                }
+            },
+            "update_environment_variable_override" => {
+                DeploymentAuditLogEvent::UpdateEnvironmentVariableOverride {
+                    name: remove_string(&mut fields, "variable_name")?.parse()?,
+                    scope: remove_scope(&mut fields)?,
+                }
+            },
+            "delete_environment_variable_override" => {
+                DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride {
+                    name: remove_string(&mut fields, "variable_name")?.parse()?,
+                    scope: remove_scope(&mut fields)?,
+                }
+            },
             "push_config" => DeploymentAuditLogEvent::PushConfig {
                 config_diff: ConvexObject::try_from(fields)?.try_into()?,
             },
@@ -416,6 +721,15 @@ impl TryFrom<ConvexObject> for DeploymentAuditLogEvent {
                 new_state: remove_string(&mut fields, "new_state")?.parse()?,
             },
             "clear_tables" => DeploymentAuditLogEvent::ClearTables,
+            "exchange_token" => DeploymentAuditLogEvent::ExchangeToken {
+                token_identifier: remove_string(&mut fields, "token_identifier")?,
+                actor: remove_string(&mut fields, "actor")?,
+            },
+            "request_export" => DeploymentAuditLogEvent::RequestExport {
+                format: remove_object(&mut fields, "format")?,
+                requestor: remove_string(&mut fields, "requestor")?.parse()?,
+                incremental: remove_boolean(&mut fields, "incremental")?,
+            },
             "snapshot_import" => {
                 let table_names: BTreeMap<_, _> = remove_vec(&mut fields, "table_names")?
                     .into_iter()
@@ -466,6 +780,37 @@ impl TryFrom<ConvexObject> for DeploymentAuditLogEvent {
     }
 }
 
+/// An event in `_deployment_audit_log` along with who caused it.
+#[derive(Debug, Clone)]
+pub struct DeploymentAuditLogEntry {
+    pub event: DeploymentAuditLogEvent,
+    pub member_id: Option<MemberId>,
+    /// The principal that caused the event, like `member:7`,
+    /// `serviceAccount:<id>` or `system`. Missing from events recorded before
+    /// actors were.
+    pub actor: Option<String>,
+}
+
+impl TryFrom<ConvexObject> for DeploymentAuditLogEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(obj: ConvexObject) -> anyhow::Result<Self> {
+        let mut fields = BTreeMap::from(obj);
+        let member_id = match fields.remove("member_id") {
+            Some(ConvexValue::Int64(member_id)) => Some(MemberId(u64::try_from(member_id)?)),
+            Some(ConvexValue::Null) | None => None,
+            v => anyhow::bail!("expected int or null for member_id, got {v:?}"),
+        };
+        let actor = remove_nullable_string(&mut fields, "actor")?;
+        let event = ConvexObject::try_from(fields)?.try_into()?;
+        Ok(Self {
+            event,
+            member_id,
+            actor,
+        })
+    }
+}
+
 impl TryFrom<DeploymentAuditLogEvent> for serde_json::Map<String, JsonValue> {
     type Error = anyhow::Error;
 
@@ -635,6 +980,19 @@ mod tests {
     use value::ConvexObject;
 
     use super::DeploymentAuditLogEvent;
+    use crate::{
+        config::types::{
+            ConfigDiff,
+            ConfigIndexDiff,
+            CronDiff,
+            ModuleDiff,
+        },
+        environment_variables::types::EnvVarScope,
+        exports::types::{
+            ExportFormat,
+            ExportRequestor,
+        },
+    };
 
     proptest! {
         #![proptest_config(
@@ -651,6 +1009,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_summary() -> anyhow::Result<()> {
+        let push = DeploymentAuditLogEvent::PushConfig {
+            config_diff: ConfigDiff {
+                auth_diff: Default::default(),
+                udf_server_version_diff: None,
+                module_diff: ModuleDiff {
+                    added: vec![
+                        "messages.js:list".to_string(),
+                        "messages.js:send".to_string(),
+                    ],
+                    removed: vec![],
+                },
+                cron_diff: CronDiff::default(),
+                index_diff: ConfigIndexDiff {
+                    added: vec!["messages.by_author".to_string()],
+                    dropped: vec![],
+                },
+                schema_diff: None,
+            },
+        };
+        assert_eq!(push.summary(), "Pushed: 2 functions added, 1 index added");
+        let export = DeploymentAuditLogEvent::RequestExport {
+            format: ExportFormat::Parquet,
+            requestor: ExportRequestor::ScheduledExport,
+            incremental: false,
+        };
+        assert_eq!(
+            export.summary(),
+            "Requested a Parquet export (scheduledExport)"
+        );
+        let rename = DeploymentAuditLogEvent::ReplaceEnvironmentVariable {
+            previous_name: "OLD_KEY".parse()?,
+            name: "NEW_KEY".parse()?,
+        };
+        assert_eq!(
+            rename.summary(),
+            "Replaced environment variable OLD_KEY with NEW_KEY"
+        );
+        let function_override = DeploymentAuditLogEvent::CreateEnvironmentVariableOverride {
+            name: "API_KEY".parse()?,
+            scope: EnvVarScope {
+                component: "waitlist".parse()?,
+                udf_path: Some("messages:send".parse()?),
+            },
+        };
+        assert_eq!(
+            function_override.summary(),
+            "Created environment variable API_KEY for function messages.js:send in component \
+             waitlist"
+        );
+        let component_override = DeploymentAuditLogEvent::DeleteEnvironmentVariableOverride {
+            name: "API_KEY".parse()?,
+            scope: EnvVarScope {
+                component: "waitlist".parse()?,
+                udf_path: None,
+            },
+        };
+        assert_eq!(
+            component_override.summary(),
+            "Deleted environment variable API_KEY for component waitlist"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_serialization_of_audit_log_event() -> anyhow::Result<()> {
         let event = DeploymentAuditLogEvent::to_log_event(
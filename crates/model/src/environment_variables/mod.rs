@@ -9,11 +9,14 @@ use std::{
 
 use anyhow::Context;
 use common::{
+    components::CanonicalizedComponentFunctionPath,
     document::{
         ParsedDocument,
         ResolvedDocument,
+        CREATION_TIME_FIELD_PATH,
     },
     interval::Interval,
+    knobs::ENV_SECRET_MAX_VERSIONS,
     query::{
         IndexRange,
         IndexRangeExpression,
@@ -47,8 +50,11 @@ use crate::{
     deployment_audit_log::types::DeploymentAuditLogEvent,
     environment_variables::types::{
         EnvVarName,
+        EnvVarScope,
         EnvVarValue,
+        EnvironmentSecretVersion,
         EnvironmentVariable,
+        EnvironmentVariableOverride,
         PersistedEnvironmentVariable,
     },
     SystemIndex,
@@ -68,6 +74,30 @@ pub static ENVIRONMENT_VARIABLES_INDEX_BY_NAME: LazyLock<IndexName> =
 static NAME_FIELD: LazyLock<FieldPath> =
     LazyLock::new(|| "name".parse().expect("invalid name field"));
 
+pub static ENVIRONMENT_VARIABLE_OVERRIDES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_environment_variable_overrides"
+        .parse()
+        .expect("Invalid built-in environment variable overrides table")
+});
+
+pub static ENVIRONMENT_VARIABLE_OVERRIDES_INDEX_BY_SCOPE: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&ENVIRONMENT_VARIABLE_OVERRIDES_TABLE, "by_scope"));
+static COMPONENT_PATH_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "componentPath".parse().expect("invalid componentPath field"));
+static UDF_PATH_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "udfPath".parse().expect("invalid udfPath field"));
+
+pub static ENVIRONMENT_SECRETS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_environment_secrets"
+        .parse()
+        .expect("Invalid built-in environment secrets table")
+});
+
+pub static ENVIRONMENT_SECRETS_INDEX_BY_NAME: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&ENVIRONMENT_SECRETS_TABLE, "by_name"));
+static VERSION_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "version".parse().expect("invalid version field"));
+
 pub struct EnvironmentVariablesTable;
 impl SystemTable for EnvironmentVariablesTable {
     fn table_name(&self) -> &'static TableName {
@@ -86,6 +116,50 @@ impl SystemTable for EnvironmentVariablesTable {
     }
 }
 
+pub struct EnvironmentVariableOverridesTable;
+impl SystemTable for EnvironmentVariableOverridesTable {
+    fn table_name(&self) -> &'static TableName {
+        &ENVIRONMENT_VARIABLE_OVERRIDES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![SystemIndex {
+            name: ENVIRONMENT_VARIABLE_OVERRIDES_INDEX_BY_SCOPE.clone(),
+            fields: vec![
+                COMPONENT_PATH_FIELD.clone(),
+                UDF_PATH_FIELD.clone(),
+                CREATION_TIME_FIELD_PATH.clone(),
+            ]
+            .try_into()
+            .unwrap(),
+        }]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<EnvironmentVariableOverride>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct EnvironmentSecretsTable;
+impl SystemTable for EnvironmentSecretsTable {
+    fn table_name(&self) -> &'static TableName {
+        &ENVIRONMENT_SECRETS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![SystemIndex {
+            name: ENVIRONMENT_SECRETS_INDEX_BY_NAME.clone(),
+            fields: vec![NAME_FIELD.clone(), VERSION_FIELD.clone()]
+                .try_into()
+                .unwrap(),
+        }]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<EnvironmentSecretVersion>::try_from(document).map(|_| ())
+    }
+}
+
 pub struct EnvironmentVariablesModel<'a, RT: Runtime> {
     tx: &'a mut Transaction<RT>,
 }
@@ -177,6 +251,9 @@ impl<'a, RT: Runtime> EnvironmentVariablesModel<'a, RT> {
         if forbidden_names.contains(env_var.name()) {
             anyhow::bail!(env_var_name_forbidden(env_var.name()));
         }
+        if !self.get_secret_versions(env_var.name()).await?.is_empty() {
+            anyhow::bail!(env_var_name_not_unique(Some(env_var.name())));
+        }
         SystemMetadataModel::new_global(self.tx)
             .insert(
                 &ENVIRONMENT_VARIABLES_TABLE,
@@ -234,6 +311,13 @@ impl<'a, RT: Runtime> EnvironmentVariablesModel<'a, RT> {
             {
                 anyhow::bail!(env_var_name_not_unique(Some(&new_env_var_name)));
             }
+            if !self
+                .get_secret_versions(&new_env_var_name)
+                .await?
+                .is_empty()
+            {
+                anyhow::bail!(env_var_name_not_unique(Some(&new_env_var_name)));
+            }
 
             SystemMetadataModel::new_global(self.tx)
                 .replace(
@@ -270,6 +354,222 @@ impl<'a, RT: Runtime> EnvironmentVariablesModel<'a, RT> {
 
         Ok(audit_events)
     }
+
+    /// Returns the versions of the secret `name`, oldest first.
+    pub async fn get_secret_versions(
+        &mut self,
+        name: &EnvVarName,
+    ) -> anyhow::Result<Vec<ParsedDocument<EnvironmentSecretVersion>>> {
+        let range = vec![IndexRangeExpression::Eq(
+            NAME_FIELD.clone(),
+            ConvexValue::try_from(String::from(name.clone()))?.into(),
+        )];
+        self.query_secrets(range).await
+    }
+
+    /// Returns the versions of every secret, oldest first.
+    pub async fn get_all_secrets(
+        &mut self,
+    ) -> anyhow::Result<BTreeMap<EnvVarName, Vec<ParsedDocument<EnvironmentSecretVersion>>>> {
+        let mut secrets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for doc in self.query_secrets(vec![]).await? {
+            secrets.entry(doc.name.clone()).or_default().push(doc);
+        }
+        Ok(secrets)
+    }
+
+    /// Returns the encrypted value of the current version of every secret.
+    #[fastrace::trace]
+    pub async fn get_current_secrets(&mut self) -> anyhow::Result<BTreeMap<EnvVarName, String>> {
+        Ok(self
+            .get_all_secrets()
+            .await?
+            .into_iter()
+            .filter_map(|(name, versions)| {
+                let current = versions.into_iter().last()?;
+                Some((name, current.into_value().encrypted_value))
+            })
+            .collect())
+    }
+
+    /// Adds a version of the secret `name`, deleting the oldest versions past
+    /// `ENV_SECRET_MAX_VERSIONS`. Returns the new version's number, which
+    /// starts at 1.
+    pub async fn set_secret(
+        &mut self,
+        name: EnvVarName,
+        encrypted_value: String,
+        forbidden_names: &HashSet<EnvVarName>,
+    ) -> anyhow::Result<u64> {
+        if forbidden_names.contains(&name) {
+            anyhow::bail!(env_var_name_forbidden(&name));
+        }
+        if self.get(&name).await?.is_some() {
+            anyhow::bail!(env_var_name_not_unique(Some(&name)));
+        }
+        let versions = self.get_secret_versions(&name).await?;
+        let version = versions.last().map_or(1, |doc| doc.version + 1);
+        let num_to_delete = (versions.len() + 1).saturating_sub(*ENV_SECRET_MAX_VERSIONS);
+        for doc in versions.into_iter().take(num_to_delete) {
+            SystemMetadataModel::new_global(self.tx)
+                .delete(doc.id())
+                .await?;
+        }
+        SystemMetadataModel::new_global(self.tx)
+            .insert(
+                &ENVIRONMENT_SECRETS_TABLE,
+                EnvironmentSecretVersion {
+                    name,
+                    version,
+                    encrypted_value,
+                }
+                .try_into()?,
+            )
+            .await?;
+        Ok(version)
+    }
+
+    /// Deletes every version of the secret `name`. Returns whether it existed.
+    pub async fn delete_secret(&mut self, name: &EnvVarName) -> anyhow::Result<bool> {
+        let versions = self.get_secret_versions(name).await?;
+        let existed = !versions.is_empty();
+        for doc in versions {
+            SystemMetadataModel::new_global(self.tx)
+                .delete(doc.id())
+                .await?;
+        }
+        Ok(existed)
+    }
+
+    async fn query_secrets(
+        &mut self,
+        range: Vec<IndexRangeExpression>,
+    ) -> anyhow::Result<Vec<ParsedDocument<EnvironmentSecretVersion>>> {
+        let query = Query::index_range(IndexRange {
+            index_name: ENVIRONMENT_SECRETS_INDEX_BY_NAME.clone(),
+            range,
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut docs = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            docs.push(doc.try_into()?);
+        }
+        Ok(docs)
+    }
+
+    /// Returns the overrides that apply to `path`, with overrides scoped to
+    /// the function taking precedence over ones scoped to its component.
+    #[fastrace::trace]
+    pub async fn get_overrides_for_function(
+        &mut self,
+        path: &CanonicalizedComponentFunctionPath,
+    ) -> anyhow::Result<BTreeMap<EnvVarName, EnvVarValue>> {
+        let mut overrides = BTreeMap::new();
+        let scopes = [
+            EnvVarScope {
+                component: path.component.clone(),
+                udf_path: None,
+            },
+            EnvVarScope {
+                component: path.component.clone(),
+                udf_path: Some(path.udf_path.clone()),
+            },
+        ];
+        for scope in scopes {
+            for doc in self.get_overrides_in_scope(&scope).await? {
+                let EnvironmentVariable { name, value } = doc.into_value().env_var;
+                overrides.insert(name, value);
+            }
+        }
+        Ok(overrides)
+    }
+
+    pub async fn get_all_overrides(&mut self) -> anyhow::Result<Vec<EnvironmentVariableOverride>> {
+        let query =
+            Query::full_table_scan(ENVIRONMENT_VARIABLE_OVERRIDES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut overrides = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let env_var_override: ParsedDocument<EnvironmentVariableOverride> = doc.try_into()?;
+            overrides.push(env_var_override.into_value());
+        }
+        Ok(overrides)
+    }
+
+    /// Sets an override, replacing any existing override with the same name
+    /// and scope. Returns whether an existing override was replaced.
+    pub async fn set_override(
+        &mut self,
+        env_var_override: EnvironmentVariableOverride,
+        forbidden_names: &HashSet<EnvVarName>,
+    ) -> anyhow::Result<bool> {
+        let name = env_var_override.env_var.name();
+        if forbidden_names.contains(name) {
+            anyhow::bail!(env_var_name_forbidden(name));
+        }
+        let existed = self
+            .delete_override(&env_var_override.scope, name)
+            .await?
+            .is_some();
+        SystemMetadataModel::new_global(self.tx)
+            .insert(
+                &ENVIRONMENT_VARIABLE_OVERRIDES_TABLE,
+                env_var_override.try_into()?,
+            )
+            .await?;
+        Ok(existed)
+    }
+
+    pub async fn delete_override(
+        &mut self,
+        scope: &EnvVarScope,
+        name: &EnvVarName,
+    ) -> anyhow::Result<Option<EnvironmentVariable>> {
+        let Some(doc) = self
+            .get_overrides_in_scope(scope)
+            .await?
+            .into_iter()
+            .find(|doc| doc.env_var.name() == name)
+        else {
+            return Ok(None);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(doc.id())
+            .await?;
+        Ok(Some(doc.into_value().env_var))
+    }
+
+    async fn get_overrides_in_scope(
+        &mut self,
+        scope: &EnvVarScope,
+    ) -> anyhow::Result<Vec<ParsedDocument<EnvironmentVariableOverride>>> {
+        let range = vec![
+            IndexRangeExpression::Eq(
+                COMPONENT_PATH_FIELD.clone(),
+                ConvexValue::try_from(String::from(scope.component.clone()))?.into(),
+            ),
+            IndexRangeExpression::Eq(
+                UDF_PATH_FIELD.clone(),
+                match &scope.udf_path {
+                    Some(udf_path) => ConvexValue::try_from(udf_path.to_string())?,
+                    None => ConvexValue::Null,
+                }
+                .into(),
+            ),
+        ];
+        let query = Query::index_range(IndexRange {
+            index_name: ENVIRONMENT_VARIABLE_OVERRIDES_INDEX_BY_SCOPE.clone(),
+            range,
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut docs = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            docs.push(doc.try_into()?);
+        }
+        Ok(docs)
+    }
 }
 
 fn value_query_from_env_var(env_var: &EnvVarName) -> anyhow::Result<Query> {
@@ -291,17 +591,27 @@ mod tests {
         HashSet,
     };
 
-    use common::types::{
-        EnvVarName,
-        EnvVarValue,
-        EnvironmentVariable,
+    use common::{
+        components::CanonicalizedComponentFunctionPath,
+        knobs::ENV_SECRET_MAX_VERSIONS,
+        types::{
+            EnvVarName,
+            EnvVarValue,
+            EnvironmentVariable,
+        },
     };
     use database::test_helpers::DbFixtures;
     use maplit::btreemap;
     use runtime::testing::TestRuntime;
 
     use crate::{
-        environment_variables::EnvironmentVariablesModel,
+        environment_variables::{
+            types::{
+                EnvVarScope,
+                EnvironmentVariableOverride,
+            },
+            EnvironmentVariablesModel,
+        },
         test_helpers::DbFixturesWithModel,
     };
 
@@ -380,4 +690,140 @@ mod tests {
 
         Ok(())
     }
+
+    #[convex_macro::test_runtime]
+    async fn test_overrides_precedence(rt: TestRuntime) -> anyhow::Result<()> {
+        let database = DbFixtures::new_with_model(&rt).await?.db;
+        let mut tx = database.begin_system().await?;
+        let mut env_model = EnvironmentVariablesModel::new(&mut tx);
+        let component_scope = EnvVarScope {
+            component: "waitlist".parse()?,
+            udf_path: None,
+        };
+        let function_scope = EnvVarScope {
+            component: "waitlist".parse()?,
+            udf_path: Some("slack:post".parse()?),
+        };
+        for (scope, name, value) in [
+            (&component_scope, "API_KEY", "component"),
+            (&component_scope, "REGION", "us"),
+            (&function_scope, "API_KEY", "function"),
+        ] {
+            env_model
+                .set_override(
+                    EnvironmentVariableOverride {
+                        scope: scope.clone(),
+                        env_var: EnvironmentVariable::new(name.parse()?, value.parse()?),
+                    },
+                    &HashSet::new(),
+                )
+                .await?;
+        }
+
+        let path = |udf_path: &str| -> anyhow::Result<_> {
+            Ok(CanonicalizedComponentFunctionPath {
+                component: "waitlist".parse()?,
+                udf_path: udf_path.parse()?,
+            })
+        };
+        assert_eq!(
+            env_model
+                .get_overrides_for_function(&path("slack:post")?)
+                .await?,
+            btreemap! {
+                "API_KEY".parse()? => "function".parse()?,
+                "REGION".parse()? => "us".parse()?,
+            }
+        );
+        assert_eq!(
+            env_model
+                .get_overrides_for_function(&path("slack:list")?)
+                .await?,
+            btreemap! {
+                "API_KEY".parse()? => "component".parse()?,
+                "REGION".parse()? => "us".parse()?,
+            }
+        );
+
+        // Setting an override again replaces it.
+        assert!(
+            env_model
+                .set_override(
+                    EnvironmentVariableOverride {
+                        scope: function_scope.clone(),
+                        env_var: EnvironmentVariable::new(
+                            "API_KEY".parse()?,
+                            "function2".parse()?
+                        ),
+                    },
+                    &HashSet::new(),
+                )
+                .await?
+        );
+        assert_eq!(env_model.get_all_overrides().await?.len(), 3);
+        assert!(env_model
+            .delete_override(&function_scope, &"API_KEY".parse()?)
+            .await?
+            .is_some());
+        assert_eq!(
+            env_model
+                .get_overrides_for_function(&path("slack:post")?)
+                .await?
+                .get(&"API_KEY".parse()?),
+            Some(&"component".parse()?)
+        );
+        Ok(())
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_secret_versions(rt: TestRuntime) -> anyhow::Result<()> {
+        let database = DbFixtures::new_with_model(&rt).await?.db;
+        let mut tx = database.begin_system().await?;
+        let mut env_model = EnvironmentVariablesModel::new(&mut tx);
+        let name: EnvVarName = "STRIPE_KEY".parse()?;
+        for i in 1..=(*ENV_SECRET_MAX_VERSIONS as u64 + 2) {
+            let version = env_model
+                .set_secret(name.clone(), format!("encrypted-{i}"), &HashSet::new())
+                .await?;
+            assert_eq!(version, i);
+        }
+
+        // Only the latest versions are kept.
+        let versions: Vec<_> = env_model
+            .get_secret_versions(&name)
+            .await?
+            .into_iter()
+            .map(|doc| doc.version)
+            .collect();
+        assert_eq!(
+            versions,
+            (3..=(*ENV_SECRET_MAX_VERSIONS as u64 + 2)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            env_model.get_current_secrets().await?,
+            btreemap! {
+                name.clone() => format!("encrypted-{}", *ENV_SECRET_MAX_VERSIONS + 2),
+            }
+        );
+
+        // Secrets and environment variables can't share names.
+        let env_var = EnvironmentVariable::new(name.clone(), "plain".parse()?);
+        assert!(env_model.create(env_var, &HashSet::new()).await.is_err());
+        let other: EnvVarName = "REGION".parse()?;
+        env_model
+            .create(
+                EnvironmentVariable::new(other.clone(), "us".parse()?),
+                &HashSet::new(),
+            )
+            .await?;
+        assert!(env_model
+            .set_secret(other, "encrypted".to_string(), &HashSet::new())
+            .await
+            .is_err());
+
+        assert!(env_model.delete_secret(&name).await?);
+        assert!(env_model.get_all_secrets().await?.is_empty());
+        assert!(!env_model.delete_secret(&name).await?);
+        Ok(())
+    }
 }
@@ -1,10 +1,12 @@
 use std::collections::BTreeMap;
 
+use common::components::ComponentPath;
 pub use common::types::{
     EnvVarName,
     EnvVarValue,
     EnvironmentVariable,
 };
+use sync_types::CanonicalizedUdfPath;
 use value::{
     obj,
     ConvexObject,
@@ -47,6 +49,142 @@ impl TryFrom<ConvexObject> for PersistedEnvironmentVariable {
     }
 }
 
+/// Where an environment variable override applies.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct EnvVarScope {
+    pub component: ComponentPath,
+    /// If set, the override only applies to this function within `component`.
+    /// Otherwise it applies to every function in `component`.
+    pub udf_path: Option<CanonicalizedUdfPath>,
+}
+
+/// An environment variable that takes precedence over the deployment-wide
+/// value for functions within its scope.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct EnvironmentVariableOverride {
+    pub scope: EnvVarScope,
+    pub env_var: EnvironmentVariable,
+}
+
+impl TryFrom<EnvironmentVariableOverride> for ConvexObject {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        EnvironmentVariableOverride {
+            scope: EnvVarScope {
+                component,
+                udf_path,
+            },
+            env_var: EnvironmentVariable { name, value },
+        }: EnvironmentVariableOverride,
+    ) -> anyhow::Result<ConvexObject> {
+        obj!(
+            "name" => String::from(name),
+            "value" => String::from(value),
+            "componentPath" => String::from(component),
+            "udfPath" => match udf_path {
+                Some(udf_path) => ConvexValue::try_from(udf_path.to_string())?,
+                None => ConvexValue::Null,
+            },
+        )
+    }
+}
+
+impl TryFrom<ConvexObject> for EnvironmentVariableOverride {
+    type Error = anyhow::Error;
+
+    fn try_from(obj: ConvexObject) -> anyhow::Result<EnvironmentVariableOverride> {
+        let mut fields = BTreeMap::from(obj);
+        let name: String = match fields.remove("name") {
+            Some(ConvexValue::String(s)) => s.into(),
+            v => anyhow::bail!("Invalid name field for EnvironmentVariableOverride: {v:?}"),
+        };
+        let value: String = match fields.remove("value") {
+            Some(ConvexValue::String(s)) => s.into(),
+            v => anyhow::bail!("Invalid value field for EnvironmentVariableOverride: {v:?}"),
+        };
+        let component: ComponentPath = match fields.remove("componentPath") {
+            Some(ConvexValue::String(s)) => String::from(s).parse()?,
+            v => {
+                anyhow::bail!("Invalid componentPath field for EnvironmentVariableOverride: {v:?}")
+            },
+        };
+        let udf_path = match fields.remove("udfPath") {
+            Some(ConvexValue::String(s)) => Some(String::from(s).parse()?),
+            Some(ConvexValue::Null) => None,
+            v => anyhow::bail!("Invalid udfPath field for EnvironmentVariableOverride: {v:?}"),
+        };
+        Ok(Self {
+            scope: EnvVarScope {
+                component,
+                udf_path,
+            },
+            env_var: EnvironmentVariable {
+                name: name.parse()?,
+                value: value.parse()?,
+            },
+        })
+    }
+}
+
+/// One version of a secret environment variable. Its value is encrypted with
+/// the deployment's `KeyBroker`, so it's only readable where functions run.
+/// Setting a secret adds a version, and older versions are kept as its
+/// rotation history.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct EnvironmentSecretVersion {
+    pub name: EnvVarName,
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "1..1000u64"))]
+    pub version: u64,
+    pub encrypted_value: String,
+}
+
+impl TryFrom<EnvironmentSecretVersion> for ConvexObject {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        EnvironmentSecretVersion {
+            name,
+            version,
+            encrypted_value,
+        }: EnvironmentSecretVersion,
+    ) -> anyhow::Result<ConvexObject> {
+        obj!(
+            "name" => String::from(name),
+            "version" => ConvexValue::Int64(version.try_into()?),
+            "encryptedValue" => encrypted_value,
+        )
+    }
+}
+
+impl TryFrom<ConvexObject> for EnvironmentSecretVersion {
+    type Error = anyhow::Error;
+
+    fn try_from(obj: ConvexObject) -> anyhow::Result<EnvironmentSecretVersion> {
+        let mut fields = BTreeMap::from(obj);
+        let name: String = match fields.remove("name") {
+            Some(ConvexValue::String(s)) => s.into(),
+            v => anyhow::bail!("Invalid name field for EnvironmentSecretVersion: {v:?}"),
+        };
+        let version = match fields.remove("version") {
+            Some(ConvexValue::Int64(version)) => version.try_into()?,
+            v => anyhow::bail!("Invalid version field for EnvironmentSecretVersion: {v:?}"),
+        };
+        let encrypted_value = match fields.remove("encryptedValue") {
+            Some(ConvexValue::String(s)) => s.into(),
+            v => anyhow::bail!("Invalid encryptedValue field for EnvironmentSecretVersion: {v:?}"),
+        };
+        Ok(Self {
+            name: name.parse()?,
+            version,
+            encrypted_value,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -57,7 +195,11 @@ mod tests {
         ConvexObject,
     };
 
-    use super::PersistedEnvironmentVariable;
+    use super::{
+        EnvironmentSecretVersion,
+        EnvironmentVariableOverride,
+        PersistedEnvironmentVariable,
+    };
 
     proptest! {
         #![proptest_config(
@@ -67,5 +209,15 @@ mod tests {
         fn test_env_var_to_object_roundtrip(e in any::<PersistedEnvironmentVariable>()) {
             assert_roundtrips::<PersistedEnvironmentVariable, ConvexObject>(e);
         }
+
+        #[test]
+        fn test_env_var_override_to_object_roundtrip(e in any::<EnvironmentVariableOverride>()) {
+            assert_roundtrips::<EnvironmentVariableOverride, ConvexObject>(e);
+        }
+
+        #[test]
+        fn test_env_secret_version_to_object_roundtrip(e in any::<EnvironmentSecretVersion>()) {
+            assert_roundtrips::<EnvironmentSecretVersion, ConvexObject>(e);
+        }
     }
 }
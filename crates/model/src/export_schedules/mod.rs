@@ -0,0 +1,161 @@
+//! The deployment's schedule for automatic exports. There's at most one
+//! schedule, and the export schedule worker requests its exports when they're
+//! due.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    export_schedules::types::ExportSchedule,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static EXPORT_SCHEDULES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_export_schedules"
+        .parse()
+        .expect("Invalid built-in export schedules table")
+});
+
+pub struct ExportSchedulesTable;
+impl SystemTable for ExportSchedulesTable {
+    fn table_name(&self) -> &'static TableName {
+        &EXPORT_SCHEDULES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<ExportSchedule>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct ExportSchedulesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> ExportSchedulesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn get(&mut self) -> anyhow::Result<Option<ParsedDocument<ExportSchedule>>> {
+        let query = Query::full_table_scan(EXPORT_SCHEDULES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        query_stream
+            .expect_at_most_one(self.tx)
+            .await?
+            .map(|doc| doc.try_into())
+            .transpose()
+    }
+
+    /// Sets the deployment's schedule, replacing any existing one.
+    pub async fn set(&mut self, schedule: ExportSchedule) -> anyhow::Result<()> {
+        match self.get().await? {
+            Some(existing) => {
+                SystemMetadataModel::new_global(self.tx)
+                    .replace(existing.id(), schedule.try_into()?)
+                    .await?;
+            },
+            None => {
+                SystemMetadataModel::new_global(self.tx)
+                    .insert(&EXPORT_SCHEDULES_TABLE, schedule.try_into()?)
+                    .await?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Deletes the deployment's schedule, returning whether there was one.
+    pub async fn delete(&mut self) -> anyhow::Result<bool> {
+        let Some(existing) = self.get().await? else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(existing.id())
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::components::ComponentId;
+    use database::test_helpers::DbFixtures;
+    use runtime::testing::TestRuntime;
+    use sync_types::Timestamp;
+
+    use crate::{
+        cron_jobs::types::CronSchedule,
+        export_schedules::{
+            types::ExportSchedule,
+            ExportSchedulesModel,
+        },
+        exports::types::ExportFormat,
+        test_helpers::DbFixturesWithModel,
+    };
+
+    #[convex_macro::test_runtime]
+    async fn test_export_schedule(rt: TestRuntime) -> anyhow::Result<()> {
+        let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+        let mut tx = db.begin_system().await?;
+        let mut model = ExportSchedulesModel::new(&mut tx);
+        assert!(model.get().await?.is_none());
+
+        let schedule = ExportSchedule {
+            schedule: CronSchedule::Cron {
+                cron_expr: "0 2 * * *".to_string(),
+                timezone: None,
+            },
+            format: ExportFormat::Zip {
+                include_storage: false,
+                storage_urls: false,
+            },
+            component: ComponentId::Root,
+            retain: 7,
+            destination: None,
+            encryption: None,
+            next_ts: Timestamp::must(1000),
+            last_requested_ts: None,
+            last_export_id: None,
+        };
+        model.set(schedule.clone()).await?;
+        assert_eq!(model.get().await?.unwrap().into_value(), schedule);
+
+        // Setting the schedule again replaces it.
+        let schedule = ExportSchedule {
+            retain: 3,
+            ..schedule
+        };
+        model.set(schedule.clone()).await?;
+        assert_eq!(model.get().await?.unwrap().into_value(), schedule);
+
+        assert!(model.delete().await?);
+        assert!(model.get().await?.is_none());
+        assert!(!model.delete().await?);
+        Ok(())
+    }
+}
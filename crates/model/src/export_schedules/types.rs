@@ -0,0 +1,103 @@
+use common::components::ComponentId;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sync_types::Timestamp;
+use value::{
+    codegen_convex_serialization,
+    DeveloperDocumentId,
+};
+
+use crate::{
+    cron_jobs::types::{
+        CronSchedule,
+        SerializedCronSchedule,
+    },
+    exports::types::{
+        ExportDestination,
+        ExportEncryption,
+        ExportFormat,
+        SerializedExportDestination,
+        SerializedExportEncryption,
+        SerializedExportFormat,
+    },
+};
+
+/// A schedule for exporting the deployment automatically, like every day at
+/// 02:00 UTC. The schedule's exports are requested like any other export, and
+/// only the latest `retain` of them are kept.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ExportSchedule {
+    pub schedule: CronSchedule,
+    pub format: ExportFormat,
+    pub component: ComponentId,
+    /// How many of the schedule's completed exports to keep. Older ones are
+    /// deleted.
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "1..1000u64"))]
+    pub retain: u64,
+    pub destination: Option<ExportDestination>,
+    pub encryption: Option<ExportEncryption>,
+    /// When the next export is due.
+    pub next_ts: Timestamp,
+    /// When the schedule last requested an export, and that export's ID.
+    pub last_requested_ts: Option<Timestamp>,
+    pub last_export_id: Option<DeveloperDocumentId>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedExportSchedule {
+    schedule: SerializedCronSchedule,
+    format: SerializedExportFormat,
+    component: Option<String>,
+    retain: i64,
+    destination: Option<SerializedExportDestination>,
+    #[serde(default)]
+    encryption: Option<SerializedExportEncryption>,
+    next_ts: i64,
+    last_requested_ts: Option<i64>,
+    last_export_id: Option<String>,
+}
+
+impl TryFrom<ExportSchedule> for SerializedExportSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ExportSchedule) -> anyhow::Result<Self> {
+        Ok(Self {
+            schedule: value.schedule.try_into()?,
+            format: value.format.into(),
+            component: value.component.serialize_to_string(),
+            retain: value.retain.try_into()?,
+            destination: value.destination.map(SerializedExportDestination::from),
+            encryption: value.encryption.map(SerializedExportEncryption::from),
+            next_ts: value.next_ts.into(),
+            last_requested_ts: value.last_requested_ts.map(i64::from),
+            last_export_id: value.last_export_id.map(|id| id.encode()),
+        })
+    }
+}
+
+impl TryFrom<SerializedExportSchedule> for ExportSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedExportSchedule) -> anyhow::Result<Self> {
+        Ok(Self {
+            schedule: value.schedule.try_into()?,
+            format: value.format.into(),
+            component: ComponentId::deserialize_from_string(value.component.as_deref())?,
+            retain: value.retain.try_into()?,
+            destination: value.destination.map(ExportDestination::from),
+            encryption: value.encryption.map(ExportEncryption::from),
+            next_ts: value.next_ts.try_into()?,
+            last_requested_ts: value
+                .last_requested_ts
+                .map(Timestamp::try_from)
+                .transpose()?,
+            last_export_id: value.last_export_id.map(|id| id.parse()).transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(ExportSchedule, SerializedExportSchedule);
@@ -33,8 +33,12 @@ use database::{
 };
 use sync_types::Timestamp;
 use types::{
+    ExportDestination,
+    ExportEncryption,
+    ExportFilter,
     ExportFormat,
     ExportRequestor,
+    IncrementalExport,
 };
 use value::{
     ConvexValue,
@@ -122,6 +126,10 @@ impl<'a, RT: Runtime> ExportsModel<'a, RT> {
         component: ComponentId,
         requestor: ExportRequestor,
         expiration_ts_ns: Option<u64>,
+        incremental: Option<IncrementalExport>,
+        destination: Option<ExportDestination>,
+        filter: ExportFilter,
+        encryption: Option<ExportEncryption>,
     ) -> anyhow::Result<ResolvedDocumentId> {
         let default_expiration_ts =
             u64::from(*self.tx.begin_timestamp()) + DEFAULT_EXPORT_RETENTION;
@@ -130,7 +138,17 @@ impl<'a, RT: Runtime> ExportsModel<'a, RT> {
         SystemMetadataModel::new_global(self.tx)
             .insert(
                 &EXPORTS_TABLE,
-                Export::requested(format, component, requestor, expiration_ts_ns).try_into()?,
+                Export::requested(
+                    format,
+                    component,
+                    requestor,
+                    expiration_ts_ns,
+                    incremental,
+                    destination,
+                    filter,
+                    encryption,
+                )
+                .try_into()?,
             )
             .await
     }
@@ -183,6 +201,50 @@ impl<'a, RT: Runtime> ExportsModel<'a, RT> {
         Ok(result)
     }
 
+    /// Deletes the completed exports from `requestor` other than the latest
+    /// `retain`, returning their object keys so the objects can be deleted
+    /// from storage.
+    pub async fn prune_completed(
+        &mut self,
+        requestor: ExportRequestor,
+        retain: u64,
+    ) -> anyhow::Result<Vec<ObjectKey>> {
+        let index_range = IndexRange {
+            index_name: EXPORTS_BY_REQUESTOR.clone(),
+            range: vec![IndexRangeExpression::Eq(
+                EXPORTS_REQUESTOR_FIELD.clone(),
+                ConvexValue::try_from(requestor.to_string())?.into(),
+            )],
+            order: Order::Asc,
+        };
+        let completed_filter = Expression::Eq(
+            Expression::Field(EXPORTS_STATE_FIELD.clone()).into(),
+            Expression::Literal(maybe_val!("completed")).into(),
+        );
+        let query = Query::index_range(index_range).filter(completed_filter);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut completed = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let (id, export) = ParsedDocument::<Export>::try_from(doc)?.into_id_and_value();
+            if let Export::Completed {
+                start_ts,
+                zip_object_key,
+                ..
+            } = export
+            {
+                completed.push((start_ts, id, zip_object_key));
+            }
+        }
+        // Newest first.
+        completed.sort_by(|a, b| b.0.cmp(&a.0));
+        let mut to_delete = vec![];
+        for (_, id, zip_object_key) in completed.into_iter().skip(retain as usize) {
+            SystemMetadataModel::new_global(self.tx).delete(id).await?;
+            to_delete.push(zip_object_key);
+        }
+        Ok(to_delete)
+    }
+
     pub async fn latest_requested(&mut self) -> anyhow::Result<Option<ParsedDocument<Export>>> {
         self.export_in_state("requested").await
     }
@@ -323,30 +385,46 @@ impl<'a, RT: Runtime> ExportsModel<'a, RT> {
 mod tests {
     use std::{
         assert_matches::assert_matches,
+        collections::BTreeSet,
         time::Duration,
     };
 
     use anyhow::Context;
     use cmd_util::env::env_config;
     use common::{
-        components::ComponentId,
+        components::{
+            ComponentId,
+            ComponentPath,
+        },
         types::ObjectKey,
     };
     use database::test_helpers::DbFixtures;
+    use maplit::{
+        btreemap,
+        btreeset,
+    };
     use proptest::prelude::*;
     use runtime::testing::{
         TestDriver,
         TestRuntime,
     };
     use sync_types::Timestamp;
-    use value::ConvexObject;
+    use value::{
+        ConvexObject,
+        DeveloperDocumentId,
+    };
 
     use crate::{
         exports::{
             types::{
                 Export,
+                ExportDestination,
+                ExportEncryption,
+                ExportFilter,
                 ExportFormat,
                 ExportRequestor,
+                IncrementalExport,
+                S3Credentials,
             },
             ExportsModel,
         },
@@ -370,10 +448,15 @@ mod tests {
         let requested_export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::SnapshotExport,
             4321,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         );
         check_roundtrip(&requested_export);
 
@@ -400,6 +483,141 @@ mod tests {
         let export = in_progress_export.canceled(Timestamp::must(1235))?;
         check_roundtrip(&export);
 
+        // Incremental
+        let export = Export::requested(
+            ExportFormat::Zip {
+                include_storage: false,
+                storage_urls: false,
+            },
+            ComponentId::test_user(),
+            ExportRequestor::CloudBackup,
+            4321,
+            Some(IncrementalExport {
+                base_export_id: DeveloperDocumentId::MIN,
+                previous_export_id: DeveloperDocumentId::MIN,
+                since_ts: Timestamp::must(1000),
+            }),
+            None,
+            ExportFilter::default(),
+            None,
+        )
+        .in_progress(ts)?
+        .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
+        check_roundtrip(&export);
+
+        // External destination
+        let export = Export::requested(
+            ExportFormat::Zip {
+                include_storage: false,
+                storage_urls: false,
+            },
+            ComponentId::test_user(),
+            ExportRequestor::SnapshotExport,
+            4321,
+            None,
+            Some(ExportDestination::S3 {
+                bucket: "backups".to_string(),
+                key_prefix: "convex/".to_string(),
+                region: "us-east-1".to_string(),
+                endpoint: None,
+                credentials: S3Credentials::AssumeRole {
+                    role_arn: "arn:aws:iam::123456789012:role/backups".to_string(),
+                    external_id: None,
+                },
+            }),
+            ExportFilter::default(),
+            None,
+        )
+        .in_progress(ts)?
+        .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
+        check_roundtrip(&export);
+
+        // Filtered
+        let export = Export::requested(
+            ExportFormat::Csv {
+                field_order: vec![],
+            },
+            ComponentId::test_user(),
+            ExportRequestor::SnapshotExport,
+            4321,
+            None,
+            None,
+            ExportFilter {
+                include_tables: Some(btreeset! {"users".parse()?, "messages".parse()?}),
+                exclude_tables: BTreeSet::new(),
+                include_components: None,
+                exclude_components: btreeset! {"waitlist".parse()?},
+                exclude_fields: btreemap! {
+                    "users".parse()? => btreeset! {"email".parse()?, "phone".parse()?},
+                },
+            },
+            None,
+        );
+        check_roundtrip(&export);
+
+        // Encrypted
+        for encryption in [
+            ExportEncryption::PublicKeys {
+                recipients: vec![
+                    "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p".to_string(),
+                ],
+            },
+            ExportEncryption::Passphrase {
+                encrypted_passphrase: "encrypted".to_string(),
+            },
+        ] {
+            let export = Export::requested(
+                ExportFormat::Zip {
+                    include_storage: true,
+                    storage_urls: false,
+                },
+                ComponentId::test_user(),
+                ExportRequestor::ScheduledExport,
+                4321,
+                None,
+                None,
+                ExportFilter::default(),
+                Some(encryption),
+            )
+            .in_progress(ts)?
+            .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
+            check_roundtrip(&export);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_filter() -> anyhow::Result<()> {
+        let filter = ExportFilter {
+            include_tables: None,
+            exclude_tables: btreeset! {"audit".parse()?},
+            include_components: None,
+            exclude_components: btreeset! {"waitlist".parse()?},
+            exclude_fields: btreemap! {
+                "users".parse()? => btreeset! {"email".parse()?},
+                "messages".parse()? => btreeset! {},
+            },
+        };
+        let root = ComponentPath::root();
+        let waitlist: ComponentPath = "waitlist".parse()?;
+        assert!(filter.includes_table(&root, &"users".parse()?));
+        assert!(!filter.includes_table(&root, &"audit".parse()?));
+        assert!(!filter.includes_table(&waitlist, &"users".parse()?));
+        assert!(filter.excluded_fields(&"users".parse()?).is_some());
+        assert!(filter.excluded_fields(&"messages".parse()?).is_none());
+
+        let filter = ExportFilter {
+            include_tables: Some(btreeset! {"users".parse()?}),
+            include_components: Some(btreeset! {root.clone()}),
+            ..ExportFilter::default()
+        };
+        assert!(filter.includes_table(&root, &"users".parse()?));
+        assert!(!filter.includes_table(&root, &"messages".parse()?));
+        assert!(!filter.includes_component(&waitlist));
+
+        let filter = ExportFilter::default();
+        assert!(filter.includes_table(&waitlist, &"audit".parse()?));
         Ok(())
     }
 
@@ -412,6 +630,10 @@ mod tests {
             component in any::<ComponentId>(),
             requestor in any::<ExportRequestor>(),
             expiration_ts in any::<u64>(),
+            incremental in any::<Option<IncrementalExport>>(),
+            destination in any::<Option<ExportDestination>>(),
+            filter in any::<ExportFilter>(),
+            encryption in any::<Option<ExportEncryption>>(),
         ) {
             let td = TestDriver::new();
             let rt = td.rt();
@@ -421,6 +643,10 @@ mod tests {
                 component,
                 requestor,
                 expiration_ts,
+                incremental,
+                destination,
+                filter,
+                encryption,
             )).unwrap();
         }
     }
@@ -431,12 +657,25 @@ mod tests {
         component: ComponentId,
         requestor: ExportRequestor,
         expiration_ts: u64,
+        incremental: Option<IncrementalExport>,
+        destination: Option<ExportDestination>,
+        filter: ExportFilter,
+        encryption: Option<ExportEncryption>,
     ) -> anyhow::Result<()> {
         let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
         let mut tx = db.begin_system().await?;
         let mut exports_model = ExportsModel::new(&mut tx);
         let snapshot_id = exports_model
-            .insert_requested(format, component, requestor, Some(expiration_ts))
+            .insert_requested(
+                format.clone(),
+                component,
+                requestor,
+                Some(expiration_ts),
+                incremental,
+                destination.clone(),
+                filter.clone(),
+                encryption.clone(),
+            )
             .await?;
         let items: Vec<_> = exports_model
             .list()
@@ -449,6 +688,10 @@ mod tests {
             component,
             requestor,
             expiration_ts,
+            incremental,
+            destination,
+            filter,
+            encryption,
         };
         assert_eq!(items, vec![expected.clone()]);
         assert_eq!(
@@ -484,10 +727,15 @@ mod tests {
             .insert_export(Export::requested(
                 ExportFormat::Zip {
                     include_storage: false,
+                    storage_urls: false,
                 },
                 ComponentId::test_user(),
                 ExportRequestor::CloudBackup,
                 ts_u64 + 1000,
+                None,
+                None,
+                ExportFilter::default(),
+                None,
             ))
             .await?;
         let backups = exports_model.list_unexpired_cloud_backups().await?;
@@ -497,10 +745,15 @@ mod tests {
         let export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::SnapshotExport,
             ts_u64 + 1000,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         )
         .in_progress(ts)?
         .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
@@ -512,10 +765,15 @@ mod tests {
         let export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::CloudBackup,
             ts_u64 - 1000,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         )
         .in_progress(ts)?
         .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
@@ -527,10 +785,15 @@ mod tests {
         let export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::CloudBackup,
             ts_u64 + 1000,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         )
         .in_progress(ts)?
         .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
@@ -553,10 +816,15 @@ mod tests {
         let export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::SnapshotExport,
             ts_u64 + 1000,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         )
         .in_progress(ts)?
         .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
@@ -590,10 +858,15 @@ mod tests {
         let export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::CloudBackup,
             ts_u64,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         )
         .in_progress(ts)?
         .completed(ts, ts, ObjectKey::try_from("asdf")?)?;
@@ -626,6 +899,75 @@ mod tests {
         Ok(())
     }
 
+    #[convex_macro::test_runtime]
+    async fn test_prune_completed(rt: TestRuntime) -> anyhow::Result<()> {
+        let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+        let mut tx = db.begin_system().await?;
+        let ts = *tx.begin_timestamp();
+        let ts_u64: u64 = ts.into();
+        let mut exports_model = ExportsModel::new(&mut tx);
+
+        for (i, requestor) in [
+            ExportRequestor::ScheduledExport,
+            ExportRequestor::ScheduledExport,
+            ExportRequestor::SnapshotExport,
+            ExportRequestor::ScheduledExport,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let start_ts = ts.add(Duration::from_secs(i as u64))?;
+            let export = Export::requested(
+                ExportFormat::Zip {
+                    include_storage: false,
+                    storage_urls: false,
+                },
+                ComponentId::test_user(),
+                requestor,
+                ts_u64 + 1000,
+                None,
+                None,
+                ExportFilter::default(),
+                None,
+            )
+            .in_progress(start_ts)?
+            .completed(
+                start_ts,
+                start_ts,
+                ObjectKey::try_from(format!("export{i}"))?,
+            )?;
+            exports_model.insert_export(export).await?;
+        }
+        // An in-progress scheduled export isn't pruned.
+        exports_model
+            .insert_export(Export::requested(
+                ExportFormat::Zip {
+                    include_storage: false,
+                    storage_urls: false,
+                },
+                ComponentId::test_user(),
+                ExportRequestor::ScheduledExport,
+                ts_u64 + 1000,
+                None,
+                None,
+                ExportFilter::default(),
+                None,
+            ))
+            .await?;
+
+        let pruned = exports_model
+            .prune_completed(ExportRequestor::ScheduledExport, 2)
+            .await?;
+        assert_eq!(pruned, vec![ObjectKey::try_from("export0")?]);
+        assert_eq!(exports_model.list().await?.len(), 4);
+
+        let pruned = exports_model
+            .prune_completed(ExportRequestor::ScheduledExport, 2)
+            .await?;
+        assert!(pruned.is_empty());
+        Ok(())
+    }
+
     #[convex_macro::test_runtime]
     async fn test_cancel(rt: TestRuntime) -> anyhow::Result<()> {
         let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
@@ -633,10 +975,15 @@ mod tests {
         let initial_export = Export::requested(
             ExportFormat::Zip {
                 include_storage: false,
+                storage_urls: false,
             },
             ComponentId::test_user(),
             ExportRequestor::CloudBackup,
             u64::MAX,
+            None,
+            None,
+            ExportFilter::default(),
+            None,
         );
 
         // Should be able to cancel a `Requested` or `InProgress` export
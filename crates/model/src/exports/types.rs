@@ -1,10 +1,19 @@
-use std::fmt::{
-    self,
-    Display,
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt::{
+        self,
+        Display,
+    },
 };
 
 use common::{
-    components::ComponentId,
+    components::{
+        ComponentId,
+        ComponentPath,
+    },
     types::ObjectKey,
 };
 use serde::{
@@ -12,7 +21,12 @@ use serde::{
     Serialize,
 };
 use sync_types::Timestamp;
-use value::codegen_convex_serialization;
+use value::{
+    codegen_convex_serialization,
+    DeveloperDocumentId,
+    IdentifierFieldName,
+    TableName,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
@@ -34,6 +48,14 @@ pub enum Export {
         format: ExportFormat,
         component: ComponentId,
         requestor: ExportRequestor,
+        /// Set if the export only has the changes since an earlier export.
+        incremental: Option<IncrementalExport>,
+        /// Set if the export is also uploaded to an external destination.
+        destination: Option<ExportDestination>,
+        /// The tables, components, and fields the export leaves out.
+        filter: ExportFilter,
+        /// Set if the export is encrypted before it's uploaded.
+        encryption: Option<ExportEncryption>,
         /// Expiration timestamp in nanos
         expiration_ts: u64,
     },
@@ -44,6 +66,14 @@ pub enum Export {
         format: ExportFormat,
         component: ComponentId,
         requestor: ExportRequestor,
+        /// Set if the export only has the changes since an earlier export.
+        incremental: Option<IncrementalExport>,
+        /// Set if the export is also uploaded to an external destination.
+        destination: Option<ExportDestination>,
+        /// The tables, components, and fields the export leaves out.
+        filter: ExportFilter,
+        /// Set if the export is encrypted before it's uploaded.
+        encryption: Option<ExportEncryption>,
         /// Expiration timestamp in nanos
         expiration_ts: u64,
         progress_message: Option<String>,
@@ -61,6 +91,14 @@ pub enum Export {
         format: ExportFormat,
         component: ComponentId,
         requestor: ExportRequestor,
+        /// Set if the export only has the changes since an earlier export.
+        incremental: Option<IncrementalExport>,
+        /// Set if the export is also uploaded to an external destination.
+        destination: Option<ExportDestination>,
+        /// The tables, components, and fields the export leaves out.
+        filter: ExportFilter,
+        /// Set if the export is encrypted before it's uploaded.
+        encryption: Option<ExportEncryption>,
     },
     Failed {
         /// Timestamp for the failed (final) attempt at Export.
@@ -70,6 +108,14 @@ pub enum Export {
         format: ExportFormat,
         component: ComponentId,
         requestor: ExportRequestor,
+        /// Set if the export only has the changes since an earlier export.
+        incremental: Option<IncrementalExport>,
+        /// Set if the export is also uploaded to an external destination.
+        destination: Option<ExportDestination>,
+        /// The tables, components, and fields the export leaves out.
+        filter: ExportFilter,
+        /// Set if the export is encrypted before it's uploaded.
+        encryption: Option<ExportEncryption>,
     },
     Canceled {
         /// When the Export first started, if at all
@@ -79,6 +125,14 @@ pub enum Export {
         format: ExportFormat,
         component: ComponentId,
         requestor: ExportRequestor,
+        /// Set if the export only has the changes since an earlier export.
+        incremental: Option<IncrementalExport>,
+        /// Set if the export is also uploaded to an external destination.
+        destination: Option<ExportDestination>,
+        /// The tables, components, and fields the export leaves out.
+        filter: ExportFilter,
+        /// Set if the export is encrypted before it's uploaded.
+        encryption: Option<ExportEncryption>,
     },
 }
 
@@ -90,6 +144,14 @@ enum SerializedExport {
         format: SerializedExportFormat,
         component: Option<String>,
         requestor: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        incremental: Option<SerializedIncrementalExport>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<SerializedExportDestination>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<SerializedExportFilter>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<SerializedExportEncryption>,
         expiration_ts: i64,
     },
     InProgress {
@@ -97,6 +159,14 @@ enum SerializedExport {
         format: SerializedExportFormat,
         component: Option<String>,
         requestor: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        incremental: Option<SerializedIncrementalExport>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<SerializedExportDestination>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<SerializedExportFilter>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<SerializedExportEncryption>,
         expiration_ts: i64,
         progress_message: Option<String>,
     },
@@ -108,6 +178,14 @@ enum SerializedExport {
         format: SerializedExportFormat,
         component: Option<String>,
         requestor: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        incremental: Option<SerializedIncrementalExport>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<SerializedExportDestination>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<SerializedExportFilter>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<SerializedExportEncryption>,
     },
     Failed {
         start_ts: u64,
@@ -115,6 +193,14 @@ enum SerializedExport {
         format: SerializedExportFormat,
         component: Option<String>,
         requestor: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        incremental: Option<SerializedIncrementalExport>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<SerializedExportDestination>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<SerializedExportFilter>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<SerializedExportEncryption>,
     },
     #[serde(alias = "cancelled")]
     Canceled {
@@ -124,6 +210,14 @@ enum SerializedExport {
         format: SerializedExportFormat,
         component: Option<String>,
         requestor: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        incremental: Option<SerializedIncrementalExport>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<SerializedExportDestination>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<SerializedExportFilter>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encryption: Option<SerializedExportEncryption>,
     },
 }
 
@@ -137,11 +231,19 @@ impl TryFrom<Export> for SerializedExport {
                 component,
                 requestor,
                 expiration_ts,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => SerializedExport::Requested {
                 format: format.into(),
                 component: component.serialize_to_string(),
                 requestor: requestor.to_string(),
                 expiration_ts: expiration_ts as i64,
+                incremental: incremental.map(SerializedIncrementalExport::from),
+                destination: destination.map(SerializedExportDestination::from),
+                filter: filter.serialize(),
+                encryption: encryption.map(SerializedExportEncryption::from),
             },
             Export::InProgress {
                 start_ts,
@@ -150,6 +252,10 @@ impl TryFrom<Export> for SerializedExport {
                 expiration_ts,
                 requestor,
                 progress_message,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => SerializedExport::InProgress {
                 start_ts: start_ts.into(),
                 format: format.into(),
@@ -157,6 +263,10 @@ impl TryFrom<Export> for SerializedExport {
                 requestor: requestor.to_string(),
                 expiration_ts: expiration_ts as i64,
                 progress_message,
+                incremental: incremental.map(SerializedIncrementalExport::from),
+                destination: destination.map(SerializedExportDestination::from),
+                filter: filter.serialize(),
+                encryption: encryption.map(SerializedExportEncryption::from),
             },
             Export::Completed {
                 start_ts,
@@ -166,6 +276,10 @@ impl TryFrom<Export> for SerializedExport {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => SerializedExport::Completed {
                 start_ts: start_ts.into(),
                 complete_ts: complete_ts.into(),
@@ -174,6 +288,10 @@ impl TryFrom<Export> for SerializedExport {
                 format: format.into(),
                 component: component.serialize_to_string(),
                 requestor: requestor.to_string(),
+                incremental: incremental.map(SerializedIncrementalExport::from),
+                destination: destination.map(SerializedExportDestination::from),
+                filter: filter.serialize(),
+                encryption: encryption.map(SerializedExportEncryption::from),
             },
             Export::Failed {
                 start_ts,
@@ -181,12 +299,20 @@ impl TryFrom<Export> for SerializedExport {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => SerializedExport::Failed {
                 start_ts: start_ts.into(),
                 failed_ts: failed_ts.into(),
                 format: format.into(),
                 component: component.serialize_to_string(),
                 requestor: requestor.to_string(),
+                incremental: incremental.map(SerializedIncrementalExport::from),
+                destination: destination.map(SerializedExportDestination::from),
+                filter: filter.serialize(),
+                encryption: encryption.map(SerializedExportEncryption::from),
             },
             Export::Canceled {
                 start_ts,
@@ -194,12 +320,20 @@ impl TryFrom<Export> for SerializedExport {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => SerializedExport::Canceled {
                 start_ts: start_ts.map(From::from),
                 canceled_ts: canceled_ts.into(),
                 format: format.into(),
                 component: component.serialize_to_string(),
                 requestor: requestor.to_string(),
+                incremental: incremental.map(SerializedIncrementalExport::from),
+                destination: destination.map(SerializedExportDestination::from),
+                filter: filter.serialize(),
+                encryption: encryption.map(SerializedExportEncryption::from),
             },
         })
     }
@@ -215,11 +349,22 @@ impl TryFrom<SerializedExport> for Export {
                 component,
                 requestor,
                 expiration_ts,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Export::Requested {
                 format: format.into(),
                 component: ComponentId::deserialize_from_string(component.as_deref())?,
                 requestor: requestor.parse()?,
                 expiration_ts: expiration_ts as u64,
+                incremental: incremental.map(IncrementalExport::try_from).transpose()?,
+                destination: destination.map(ExportDestination::from),
+                filter: filter
+                    .map(ExportFilter::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                encryption: encryption.map(ExportEncryption::from),
             },
             SerializedExport::InProgress {
                 start_ts,
@@ -228,6 +373,10 @@ impl TryFrom<SerializedExport> for Export {
                 expiration_ts,
                 requestor,
                 progress_message,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Export::InProgress {
                 start_ts: start_ts.try_into()?,
                 format: format.into(),
@@ -235,6 +384,13 @@ impl TryFrom<SerializedExport> for Export {
                 requestor: requestor.parse()?,
                 expiration_ts: expiration_ts as u64,
                 progress_message,
+                incremental: incremental.map(IncrementalExport::try_from).transpose()?,
+                destination: destination.map(ExportDestination::from),
+                filter: filter
+                    .map(ExportFilter::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                encryption: encryption.map(ExportEncryption::from),
             },
             SerializedExport::Completed {
                 start_ts,
@@ -244,6 +400,10 @@ impl TryFrom<SerializedExport> for Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Export::Completed {
                 start_ts: start_ts.try_into()?,
                 complete_ts: complete_ts.try_into()?,
@@ -252,6 +412,13 @@ impl TryFrom<SerializedExport> for Export {
                 format: format.into(),
                 component: ComponentId::deserialize_from_string(component.as_deref())?,
                 requestor: requestor.parse()?,
+                incremental: incremental.map(IncrementalExport::try_from).transpose()?,
+                destination: destination.map(ExportDestination::from),
+                filter: filter
+                    .map(ExportFilter::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                encryption: encryption.map(ExportEncryption::from),
             },
             SerializedExport::Failed {
                 start_ts,
@@ -259,12 +426,23 @@ impl TryFrom<SerializedExport> for Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Export::Failed {
                 start_ts: start_ts.try_into()?,
                 failed_ts: failed_ts.try_into()?,
                 format: format.into(),
                 component: ComponentId::deserialize_from_string(component.as_deref())?,
                 requestor: requestor.parse()?,
+                incremental: incremental.map(IncrementalExport::try_from).transpose()?,
+                destination: destination.map(ExportDestination::from),
+                filter: filter
+                    .map(ExportFilter::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                encryption: encryption.map(ExportEncryption::from),
             },
             SerializedExport::Canceled {
                 start_ts,
@@ -272,12 +450,23 @@ impl TryFrom<SerializedExport> for Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Export::Canceled {
                 start_ts: start_ts.map(Timestamp::try_from).transpose()?,
                 canceled_ts: canceled_ts.try_into()?,
                 format: format.into(),
                 component: ComponentId::deserialize_from_string(component.as_deref())?,
                 requestor: requestor.parse()?,
+                incremental: incremental.map(IncrementalExport::try_from).transpose()?,
+                destination: destination.map(ExportDestination::from),
+                filter: filter
+                    .map(ExportFilter::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                encryption: encryption.map(ExportEncryption::from),
             },
         })
     }
@@ -292,7 +481,7 @@ impl Export {
             | Export::InProgress { format, .. }
             | Export::Completed { format, .. }
             | Export::Failed { format, .. }
-            | Export::Canceled { format, .. } => *format,
+            | Export::Canceled { format, .. } => format.clone(),
         }
     }
 
@@ -315,34 +504,118 @@ impl Export {
             | Export::Canceled { requestor, .. } => *requestor,
         }
     }
+
+    pub fn incremental(&self) -> Option<IncrementalExport> {
+        match self {
+            Export::Requested { incremental, .. }
+            | Export::InProgress { incremental, .. }
+            | Export::Completed { incremental, .. }
+            | Export::Failed { incremental, .. }
+            | Export::Canceled { incremental, .. } => *incremental,
+        }
+    }
+
+    pub fn destination(&self) -> Option<ExportDestination> {
+        match self {
+            Export::Requested { destination, .. }
+            | Export::InProgress { destination, .. }
+            | Export::Completed { destination, .. }
+            | Export::Failed { destination, .. }
+            | Export::Canceled { destination, .. } => destination.clone(),
+        }
+    }
+
+    pub fn filter(&self) -> ExportFilter {
+        match self {
+            Export::Requested { filter, .. }
+            | Export::InProgress { filter, .. }
+            | Export::Completed { filter, .. }
+            | Export::Failed { filter, .. }
+            | Export::Canceled { filter, .. } => filter.clone(),
+        }
+    }
+
+    pub fn encryption(&self) -> Option<ExportEncryption> {
+        match self {
+            Export::Requested { encryption, .. }
+            | Export::InProgress { encryption, .. }
+            | Export::Completed { encryption, .. }
+            | Export::Failed { encryption, .. }
+            | Export::Canceled { encryption, .. } => encryption.clone(),
+        }
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub enum ExportFormat {
     /// zip file containing a CleanJsonl for each table, and sidecar type info.
-    Zip { include_storage: bool },
+    Zip {
+        /// Whether the files in file storage are in the zip.
+        include_storage: bool,
+        /// Whether the zip has `_storage/documents.jsonl` with a signed URL to
+        /// download each file, so the export can be restored with its files
+        /// without them being in the zip.
+        storage_urls: bool,
+    },
+    /// zip file containing a Parquet file for each table, with columns from
+    /// the table's inferred schema.
+    Parquet,
+    /// zip file containing a CSV file for each table, with a header naming
+    /// each column's field and type.
+    Csv {
+        /// Fields to put first in each table's columns, in order. The rest
+        /// follow the system fields and then the table's other fields.
+        field_order: Vec<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(tag = "format")]
 #[serde(rename_all = "snake_case")]
-enum SerializedExportFormat {
-    Zip { include_storage: bool },
+pub(crate) enum SerializedExportFormat {
+    Zip {
+        include_storage: bool,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        storage_urls: bool,
+    },
+    Parquet,
+    Csv {
+        #[serde(default)]
+        field_order: Vec<String>,
+    },
 }
 
 impl From<ExportFormat> for SerializedExportFormat {
     fn from(value: ExportFormat) -> Self {
-        let ExportFormat::Zip { include_storage } = value;
-        SerializedExportFormat::Zip { include_storage }
+        match value {
+            ExportFormat::Zip {
+                include_storage,
+                storage_urls,
+            } => SerializedExportFormat::Zip {
+                include_storage,
+                storage_urls,
+            },
+            ExportFormat::Parquet => SerializedExportFormat::Parquet,
+            ExportFormat::Csv { field_order } => SerializedExportFormat::Csv { field_order },
+        }
     }
 }
 
 impl From<SerializedExportFormat> for ExportFormat {
     fn from(value: SerializedExportFormat) -> Self {
-        let SerializedExportFormat::Zip { include_storage } = value;
-        ExportFormat::Zip { include_storage }
+        match value {
+            SerializedExportFormat::Zip {
+                include_storage,
+                storage_urls,
+            } => ExportFormat::Zip {
+                include_storage,
+                storage_urls,
+            },
+            SerializedExportFormat::Parquet => ExportFormat::Parquet,
+            SerializedExportFormat::Csv { field_order } => ExportFormat::Csv { field_order },
+        }
     }
 }
 
@@ -356,6 +629,8 @@ pub enum ExportRequestor {
     SnapshotExport,
     /// The team-level cloud backup feature
     CloudBackup,
+    /// The deployment's export schedule
+    ScheduledExport,
 }
 
 impl ExportRequestor {
@@ -363,6 +638,375 @@ impl ExportRequestor {
         match self {
             Self::SnapshotExport => "snapshot_export",
             Self::CloudBackup => "cloud_backup",
+            Self::ScheduledExport => "scheduled_export",
+        }
+    }
+}
+
+/// An export with only the documents that changed since an earlier export,
+/// which restores to the state at its snapshot when applied on top of the
+/// chain of exports it starts from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct IncrementalExport {
+    /// The full export the chain of incremental exports starts from.
+    pub base_export_id: DeveloperDocumentId,
+    /// The export this one has the changes since, which is either the base or
+    /// an earlier incremental export.
+    pub previous_export_id: DeveloperDocumentId,
+    /// The snapshot timestamp of the previous export.
+    pub since_ts: Timestamp,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedIncrementalExport {
+    base_export_id: String,
+    previous_export_id: String,
+    since_ts: u64,
+}
+
+impl From<IncrementalExport> for SerializedIncrementalExport {
+    fn from(value: IncrementalExport) -> Self {
+        Self {
+            base_export_id: value.base_export_id.encode(),
+            previous_export_id: value.previous_export_id.encode(),
+            since_ts: value.since_ts.into(),
+        }
+    }
+}
+
+impl TryFrom<SerializedIncrementalExport> for IncrementalExport {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedIncrementalExport) -> anyhow::Result<Self> {
+        Ok(Self {
+            base_export_id: value.base_export_id.parse()?,
+            previous_export_id: value.previous_export_id.parse()?,
+            since_ts: value.since_ts.try_into()?,
+        })
+    }
+}
+
+/// The parts of the deployment an export leaves out, so a partial dataset can
+/// be shared with another environment. The default filter leaves out nothing.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ExportFilter {
+    /// If set, only these tables are exported.
+    pub include_tables: Option<BTreeSet<TableName>>,
+    pub exclude_tables: BTreeSet<TableName>,
+    /// If set, only the tables in these components are exported.
+    pub include_components: Option<BTreeSet<ComponentPath>>,
+    pub exclude_components: BTreeSet<ComponentPath>,
+    /// Fields dropped from each table's documents, like columns with PII.
+    pub exclude_fields: BTreeMap<TableName, BTreeSet<IdentifierFieldName>>,
+}
+
+impl ExportFilter {
+    /// Whether the export has the table `table_name` in the component at
+    /// `component_path`.
+    pub fn includes_table(&self, component_path: &ComponentPath, table_name: &TableName) -> bool {
+        self.includes_component(component_path)
+            && self
+                .include_tables
+                .as_ref()
+                .is_none_or(|tables| tables.contains(table_name))
+            && !self.exclude_tables.contains(table_name)
+    }
+
+    pub fn includes_component(&self, component_path: &ComponentPath) -> bool {
+        self.include_components
+            .as_ref()
+            .is_none_or(|components| components.contains(component_path))
+            && !self.exclude_components.contains(component_path)
+    }
+
+    /// The fields dropped from the documents in `table_name`, if any are.
+    pub fn excluded_fields(
+        &self,
+        table_name: &TableName,
+    ) -> Option<&BTreeSet<IdentifierFieldName>> {
+        self.exclude_fields
+            .get(table_name)
+            .filter(|fields| !fields.is_empty())
+    }
+
+    fn serialize(self) -> Option<SerializedExportFilter> {
+        if self == Self::default() {
+            return None;
+        }
+        Some(SerializedExportFilter {
+            include_tables: self
+                .include_tables
+                .map(|tables| tables.into_iter().map(String::from).collect()),
+            exclude_tables: self.exclude_tables.into_iter().map(String::from).collect(),
+            include_components: self
+                .include_components
+                .map(|components| components.into_iter().map(String::from).collect()),
+            exclude_components: self
+                .exclude_components
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            exclude_fields: self
+                .exclude_fields
+                .into_iter()
+                .map(|(table, fields)| SerializedExcludedFields {
+                    table: table.into(),
+                    fields: fields.into_iter().map(String::from).collect(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedExportFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    include_tables: Option<Vec<String>>,
+    #[serde(default)]
+    exclude_tables: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    include_components: Option<Vec<String>>,
+    #[serde(default)]
+    exclude_components: Vec<String>,
+    #[serde(default)]
+    exclude_fields: Vec<SerializedExcludedFields>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedExcludedFields {
+    table: String,
+    fields: Vec<String>,
+}
+
+impl TryFrom<SerializedExportFilter> for ExportFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedExportFilter) -> anyhow::Result<Self> {
+        Ok(Self {
+            include_tables: value
+                .include_tables
+                .map(|tables| {
+                    tables
+                        .iter()
+                        .map(|table| table.parse())
+                        .collect::<anyhow::Result<_>>()
+                })
+                .transpose()?,
+            exclude_tables: value
+                .exclude_tables
+                .iter()
+                .map(|table| table.parse())
+                .collect::<anyhow::Result<_>>()?,
+            include_components: value
+                .include_components
+                .map(|components| {
+                    components
+                        .iter()
+                        .map(|component| component.parse())
+                        .collect::<anyhow::Result<_>>()
+                })
+                .transpose()?,
+            exclude_components: value
+                .exclude_components
+                .iter()
+                .map(|component| component.parse())
+                .collect::<anyhow::Result<_>>()?,
+            exclude_fields: value
+                .exclude_fields
+                .into_iter()
+                .map(|SerializedExcludedFields { table, fields }| {
+                    anyhow::Ok((
+                        table.parse()?,
+                        fields
+                            .iter()
+                            .map(|field| field.parse())
+                            .collect::<anyhow::Result<_>>()?,
+                    ))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+/// An external destination an export is uploaded to, in addition to the
+/// exports storage, so backups land off the backend's host.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum ExportDestination {
+    S3 {
+        bucket: String,
+        /// Prefix of the export's key in the bucket.
+        key_prefix: String,
+        region: String,
+        /// S3 API endpoint, if not the region's default, like for
+        /// S3-compatible object stores.
+        endpoint: Option<String>,
+        credentials: S3Credentials,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum S3Credentials {
+    /// An access key. The secret is encrypted with the deployment's
+    /// `KeyBroker`.
+    AccessKey {
+        access_key_id: String,
+        encrypted_secret_access_key: String,
+    },
+    /// A role the backend assumes with its own AWS credentials.
+    AssumeRole {
+        role_arn: String,
+        external_id: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SerializedExportDestination {
+    S3 {
+        bucket: String,
+        key_prefix: String,
+        region: String,
+        endpoint: Option<String>,
+        credentials: SerializedS3Credentials,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SerializedS3Credentials {
+    AccessKey {
+        access_key_id: String,
+        encrypted_secret_access_key: String,
+    },
+    AssumeRole {
+        role_arn: String,
+        external_id: Option<String>,
+    },
+}
+
+impl From<ExportDestination> for SerializedExportDestination {
+    fn from(value: ExportDestination) -> Self {
+        match value {
+            ExportDestination::S3 {
+                bucket,
+                key_prefix,
+                region,
+                endpoint,
+                credentials,
+            } => SerializedExportDestination::S3 {
+                bucket,
+                key_prefix,
+                region,
+                endpoint,
+                credentials: match credentials {
+                    S3Credentials::AccessKey {
+                        access_key_id,
+                        encrypted_secret_access_key,
+                    } => SerializedS3Credentials::AccessKey {
+                        access_key_id,
+                        encrypted_secret_access_key,
+                    },
+                    S3Credentials::AssumeRole {
+                        role_arn,
+                        external_id,
+                    } => SerializedS3Credentials::AssumeRole {
+                        role_arn,
+                        external_id,
+                    },
+                },
+            },
+        }
+    }
+}
+
+impl From<SerializedExportDestination> for ExportDestination {
+    fn from(value: SerializedExportDestination) -> Self {
+        match value {
+            SerializedExportDestination::S3 {
+                bucket,
+                key_prefix,
+                region,
+                endpoint,
+                credentials,
+            } => ExportDestination::S3 {
+                bucket,
+                key_prefix,
+                region,
+                endpoint,
+                credentials: match credentials {
+                    SerializedS3Credentials::AccessKey {
+                        access_key_id,
+                        encrypted_secret_access_key,
+                    } => S3Credentials::AccessKey {
+                        access_key_id,
+                        encrypted_secret_access_key,
+                    },
+                    SerializedS3Credentials::AssumeRole {
+                        role_arn,
+                        external_id,
+                    } => S3Credentials::AssumeRole {
+                        role_arn,
+                        external_id,
+                    },
+                },
+            },
+        }
+    }
+}
+
+/// How an export is encrypted before it's uploaded, so only the holders of its
+/// key can read it. Encrypted exports are [age](https://age-encryption.org)
+/// files of the export's zip.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum ExportEncryption {
+    /// age X25519 public keys, like `age1...`. Any of their private keys
+    /// decrypts the export.
+    PublicKeys { recipients: Vec<String> },
+    /// A passphrase, encrypted with the deployment's `KeyBroker`.
+    Passphrase { encrypted_passphrase: String },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SerializedExportEncryption {
+    PublicKeys { recipients: Vec<String> },
+    Passphrase { encrypted_passphrase: String },
+}
+
+impl From<ExportEncryption> for SerializedExportEncryption {
+    fn from(value: ExportEncryption) -> Self {
+        match value {
+            ExportEncryption::PublicKeys { recipients } => {
+                SerializedExportEncryption::PublicKeys { recipients }
+            },
+            ExportEncryption::Passphrase {
+                encrypted_passphrase,
+            } => SerializedExportEncryption::Passphrase {
+                encrypted_passphrase,
+            },
+        }
+    }
+}
+
+impl From<SerializedExportEncryption> for ExportEncryption {
+    fn from(value: SerializedExportEncryption) -> Self {
+        match value {
+            SerializedExportEncryption::PublicKeys { recipients } => {
+                ExportEncryption::PublicKeys { recipients }
+            },
+            SerializedExportEncryption::Passphrase {
+                encrypted_passphrase,
+            } => ExportEncryption::Passphrase {
+                encrypted_passphrase,
+            },
         }
     }
 }
@@ -373,12 +1017,20 @@ impl Export {
         component: ComponentId,
         requestor: ExportRequestor,
         expiration_ts: u64,
+        incremental: Option<IncrementalExport>,
+        destination: Option<ExportDestination>,
+        filter: ExportFilter,
+        encryption: Option<ExportEncryption>,
     ) -> Self {
         Self::Requested {
             format,
             component,
             requestor,
             expiration_ts,
+            incremental,
+            destination,
+            filter,
+            encryption,
         }
     }
 
@@ -389,6 +1041,10 @@ impl Export {
                 component,
                 requestor,
                 expiration_ts,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Ok(Self::InProgress {
                 start_ts: ts,
                 format,
@@ -396,6 +1052,10 @@ impl Export {
                 requestor,
                 expiration_ts,
                 progress_message: None,
+                incremental,
+                destination,
+                filter,
+                encryption,
             }),
             Self::Completed { .. }
             | Self::InProgress { .. }
@@ -415,6 +1075,10 @@ impl Export {
                 expiration_ts,
                 start_ts,
                 progress_message: _,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => Ok(Self::InProgress {
                 start_ts,
                 format,
@@ -422,6 +1086,10 @@ impl Export {
                 requestor,
                 expiration_ts,
                 progress_message: Some(msg),
+                incremental,
+                destination,
+                filter,
+                encryption,
             }),
             Self::Completed { .. }
             | Self::Requested { .. }
@@ -446,6 +1114,10 @@ impl Export {
                 expiration_ts,
                 start_ts: _, // replace start_ts with the actual database TS
                 progress_message: _,
+                incremental,
+                destination,
+                filter,
+                encryption,
             } => {
                 anyhow::ensure!(snapshot_ts <= complete_ts);
                 Ok(Self::Completed {
@@ -456,12 +1128,20 @@ impl Export {
                     format,
                     component,
                     requestor,
+                    incremental,
+                    destination,
+                    filter,
+                    encryption,
                 })
             },
             Self::Requested {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
                 expiration_ts: _,
             }
             | Self::Completed {
@@ -472,6 +1152,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             }
             | Self::Failed {
                 start_ts: _,
@@ -479,6 +1163,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             }
             | Self::Canceled {
                 start_ts: _,
@@ -486,6 +1174,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             } => Err(anyhow::anyhow!(
                 "Can only complete an export that is in_progress"
             )),
@@ -498,6 +1190,10 @@ impl Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
                 ..
             } => {
                 anyhow::ensure!(snapshot_ts <= failed_ts);
@@ -507,12 +1203,20 @@ impl Export {
                     format,
                     component,
                     requestor,
+                    incremental,
+                    destination,
+                    filter,
+                    encryption,
                 })
             },
             Self::Requested {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
                 expiration_ts: _,
             }
             | Self::Completed {
@@ -523,6 +1227,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             }
             | Self::Failed {
                 start_ts: _,
@@ -530,6 +1238,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             }
             | Self::Canceled {
                 start_ts: _,
@@ -537,6 +1249,10 @@ impl Export {
                 format: _,
                 component: _,
                 requestor: _,
+                incremental: _,
+                destination: _,
+                filter: _,
+                encryption: _,
             } => Err(anyhow::anyhow!(
                 "Can only fail an export that is in_progress"
             )),
@@ -550,6 +1266,10 @@ impl Export {
                 component,
                 requestor,
                 start_ts,
+                incremental,
+                destination,
+                filter,
+                encryption,
                 ..
             } => Ok(Self::Canceled {
                 start_ts: Some(start_ts),
@@ -557,11 +1277,19 @@ impl Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             }),
             Self::Requested {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
                 ..
             } => Ok(Self::Canceled {
                 start_ts: None,
@@ -569,6 +1297,10 @@ impl Export {
                 format,
                 component,
                 requestor,
+                incremental,
+                destination,
+                filter,
+                encryption,
             }),
             Self::Completed { .. } | Self::Failed { .. } | Self::Canceled { .. } => Err(
                 anyhow::anyhow!("Can only cancel an export that hasn't completed or failed"),
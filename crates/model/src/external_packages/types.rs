@@ -2,6 +2,7 @@ use std::collections::BTreeMap;
 
 use common::types::{
     NodeDependency,
+    NodeVersion,
     ObjectKey,
 };
 use value::{
@@ -21,6 +22,10 @@ pub struct ExternalDepsPackage {
     pub sha256: Sha256Digest,
     pub deps: Vec<NodeDependency>,
     pub package_size: PackageSize,
+    /// Node.js version the dependencies were installed with, since native
+    /// modules are built against a specific version. `None` for packages
+    /// built before deployments could pick a version, which used Node.js 18.
+    pub node_version: Option<NodeVersion>,
 }
 
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
@@ -82,11 +87,17 @@ impl TryFrom<ConvexObject> for ExternalDepsPackage {
             None => PackageSize::default(),
             _ => anyhow::bail!("Invalid 'packageSize' for ExternalDepsPackage in {fields:?}"),
         };
+        let node_version = match fields.remove("nodeVersion") {
+            Some(ConvexValue::Null) | None => None,
+            Some(ConvexValue::String(s)) => Some(s.parse()?),
+            _ => anyhow::bail!("Invalid 'nodeVersion' for ExternalDepsPackage in {fields:?}"),
+        };
         Ok(Self {
             storage_key,
             sha256,
             deps,
             package_size,
+            node_version,
         })
     }
 }
@@ -107,6 +118,11 @@ impl TryFrom<ExternalDepsPackage> for ConvexObject {
                     .try_into()?
             ),
             "packageSize" => ConvexValue::Object(value.package_size.try_into()?),
+            "nodeVersion" => value
+                .node_version
+                .map(|v| ConvexValue::try_from(v.to_string()))
+                .transpose()?
+                .unwrap_or(ConvexValue::Null),
         )
     }
 }
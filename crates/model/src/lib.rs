@@ -95,6 +95,7 @@ use scheduled_jobs::{
     SCHEDULED_JOBS_INDEX,
     SCHEDULED_JOBS_INDEX_BY_COMPLETED_TS,
     SCHEDULED_JOBS_INDEX_BY_UDF_PATH,
+    SCHEDULED_JOBS_TABLE,
 };
 use session_requests::SESSION_REQUESTS_INDEX;
 use strum::IntoEnumIterator;
@@ -106,44 +107,87 @@ use value::{
 };
 
 use crate::{
+    access_rules::AccessRulesTable,
+    admin_key_audit_log::AdminKeyAuditLogTable,
+    admin_roles::AdminRolesTable,
+    alert_rules::AlertRulesTable,
+    anonymous_identity_upgrades::AnonymousIdentityUpgradesTable,
+    api_keys::ApiKeysTable,
     auth::AuthTable,
     backend_state::BackendStateModel,
     cron_jobs::{
         CronJobLogsTable,
         CronJobsTable,
     },
+    custom_jwt_providers::CustomJwtProvidersTable,
+    dependency_layers::DependencyLayersTable,
     deployment_audit_log::DeploymentAuditLogsTable,
-    environment_variables::EnvironmentVariablesTable,
+    environment_variables::{
+        EnvironmentSecretsTable,
+        EnvironmentVariableOverridesTable,
+        EnvironmentVariablesTable,
+    },
+    export_schedules::ExportSchedulesTable,
     exports::ExportsTable,
     external_packages::ExternalPackagesTable,
     file_storage::FileStorageTable,
     modules::ModulesTable,
-    scheduled_jobs::ScheduledJobsTable,
+    saml_assertions::SamlAssertionsTable,
+    saml_providers::SamlProvidersTable,
+    scheduled_jobs::{
+        dead_letters::ScheduledJobDeadLettersTable,
+        pauses::SchedulerPausesTable,
+        ScheduledJobsTable,
+    },
+    service_accounts::ServiceAccountsTable,
     session_requests::SessionRequestsTable,
     snapshot_imports::SnapshotImportsTable,
     source_packages::SourcePackagesTable,
+    token_revocations::TokenRevocationsTable,
     udf_config::UdfConfigTable,
+    usage_records::UsageRecordsTable,
+    warmup::WarmupFunctionsTable,
+    workflows::{
+        WorkflowStepsTable,
+        WorkflowsTable,
+    },
 };
 
+pub mod access_rules;
+pub mod admin_key_audit_log;
+pub mod admin_roles;
+pub mod alert_rules;
+pub mod anonymous_identity_upgrades;
+pub mod api_keys;
 pub mod auth;
 pub mod backend_state;
 pub mod components;
 pub mod config;
 pub mod cron_jobs;
+pub mod custom_jwt_providers;
 pub mod database_globals;
+pub mod dependency_layers;
 pub mod deployment_audit_log;
 pub mod environment_variables;
+pub mod export_schedules;
 pub mod exports;
 pub mod external_packages;
 pub mod file_storage;
 mod metrics;
 pub mod migrations;
 pub mod modules;
+pub mod saml_assertions;
+pub mod saml_providers;
 pub mod scheduled_jobs;
+pub mod service_accounts;
 pub mod session_requests;
 pub mod snapshot_imports;
 pub mod source_packages;
+pub mod token_revocations;
 pub mod udf_config;
+pub mod usage_records;
+pub mod warmup;
+pub mod workflows;
 
 #[cfg(any(test, feature = "testing"))]
 pub mod test_helpers;
@@ -177,9 +221,30 @@ enum DefaultTableNumber {
     ComponentDefinitionsTable = 31,
     ComponentsTable = 32,
     FunctionHandlesTable = 33,
+    EnvironmentVariableOverridesTable = 34,
+    WarmupFunctions = 35,
+    DependencyLayers = 36,
+    ApiKeys = 37,
+    AdminRoles = 38,
+    ServiceAccounts = 39,
+    CustomJwtProviders = 40,
+    TokenRevocations = 41,
+    AnonymousIdentityUpgrades = 42,
+    SamlProviders = 43,
+    AccessRules = 44,
+    AdminKeyAuditLog = 45,
+    EnvironmentSecrets = 46,
+    ExportSchedules = 47,
+    UsageRecords = 48,
+    AlertRules = 49,
+    ScheduledJobDeadLetters = 50,
+    Workflows = 51,
+    WorkflowSteps = 52,
+    SchedulerPauses = 53,
+    SamlAssertions = 54,
     // Keep this number and your user name up to date. The number makes it easy to know
     // what to use next. The username on the same line detects merge conflicts
-    // Next Number - 34 - sujayakar
+    // Next Number - 55 - stonega
 }
 
 impl From<DefaultTableNumber> for TableNumber {
@@ -216,6 +281,29 @@ impl From<DefaultTableNumber> for &'static dyn SystemTable {
             DefaultTableNumber::ComponentDefinitionsTable => &ComponentDefinitionsTable,
             DefaultTableNumber::ComponentsTable => &ComponentsTable,
             DefaultTableNumber::FunctionHandlesTable => &FunctionHandlesTable,
+            DefaultTableNumber::EnvironmentVariableOverridesTable => {
+                &EnvironmentVariableOverridesTable
+            },
+            DefaultTableNumber::WarmupFunctions => &WarmupFunctionsTable,
+            DefaultTableNumber::DependencyLayers => &DependencyLayersTable,
+            DefaultTableNumber::ApiKeys => &ApiKeysTable,
+            DefaultTableNumber::AdminRoles => &AdminRolesTable,
+            DefaultTableNumber::ServiceAccounts => &ServiceAccountsTable,
+            DefaultTableNumber::CustomJwtProviders => &CustomJwtProvidersTable,
+            DefaultTableNumber::TokenRevocations => &TokenRevocationsTable,
+            DefaultTableNumber::AnonymousIdentityUpgrades => &AnonymousIdentityUpgradesTable,
+            DefaultTableNumber::SamlProviders => &SamlProvidersTable,
+            DefaultTableNumber::AccessRules => &AccessRulesTable,
+            DefaultTableNumber::AdminKeyAuditLog => &AdminKeyAuditLogTable,
+            DefaultTableNumber::EnvironmentSecrets => &EnvironmentSecretsTable,
+            DefaultTableNumber::ExportSchedules => &ExportSchedulesTable,
+            DefaultTableNumber::UsageRecords => &UsageRecordsTable,
+            DefaultTableNumber::AlertRules => &AlertRulesTable,
+            DefaultTableNumber::ScheduledJobDeadLetters => &ScheduledJobDeadLettersTable,
+            DefaultTableNumber::Workflows => &WorkflowsTable,
+            DefaultTableNumber::WorkflowSteps => &WorkflowStepsTable,
+            DefaultTableNumber::SchedulerPauses => &SchedulerPausesTable,
+            DefaultTableNumber::SamlAssertions => &SamlAssertionsTable,
         }
     }
 }
@@ -288,6 +376,24 @@ pub async fn initialize_application_system_tables<RT: Runtime>(
             }
         }
     }
+    // Components created before the dead letter and workflow tables existed
+    // don't have them yet, and their `_scheduled_jobs` tables may be missing
+    // indexes added since. Every component has a `_scheduled_jobs` table.
+    let added_component_tables: [&dyn SystemTable; 4] = [
+        &ScheduledJobsTable,
+        &ScheduledJobDeadLettersTable,
+        &WorkflowsTable,
+        &WorkflowStepsTable,
+    ];
+    for namespace in tx
+        .table_mapping()
+        .namespaces_for_name(&SCHEDULED_JOBS_TABLE)
+    {
+        for table in added_component_tables {
+            initialize_application_system_table(&mut tx, table, namespace, &DEFAULT_TABLE_NUMBERS)
+                .await?;
+        }
+    }
     database
         .commit_with_write_source(tx, "init_app_system_tables")
         .await?;
@@ -400,6 +506,7 @@ pub fn app_system_tables() -> Vec<&'static dyn SystemTable> {
         &DatabaseGlobalsTable,
         &DeploymentAuditLogsTable,
         &EnvironmentVariablesTable,
+        &EnvironmentVariableOverridesTable,
         &AuthTable,
         &ExternalPackagesTable,
         &SessionRequestsTable,
@@ -407,6 +514,23 @@ pub fn app_system_tables() -> Vec<&'static dyn SystemTable> {
         &ExportsTable,
         &SnapshotImportsTable,
         &FunctionHandlesTable,
+        &WarmupFunctionsTable,
+        &DependencyLayersTable,
+        &ApiKeysTable,
+        &AdminRolesTable,
+        &ServiceAccountsTable,
+        &CustomJwtProvidersTable,
+        &TokenRevocationsTable,
+        &AnonymousIdentityUpgradesTable,
+        &SamlProvidersTable,
+        &AccessRulesTable,
+        &AdminKeyAuditLogTable,
+        &EnvironmentSecretsTable,
+        &ExportSchedulesTable,
+        &UsageRecordsTable,
+        &AlertRulesTable,
+        &SchedulerPausesTable,
+        &SamlAssertionsTable,
     ];
     system_tables.extend(component_system_tables());
     system_tables
@@ -418,6 +542,9 @@ pub fn component_system_tables() -> Vec<&'static dyn SystemTable> {
     vec![
         &FileStorageTable,
         &ScheduledJobsTable,
+        &ScheduledJobDeadLettersTable,
+        &WorkflowsTable,
+        &WorkflowStepsTable,
         &CronJobsTable,
         &CronJobLogsTable,
         &ModulesTable,
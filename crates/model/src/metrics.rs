@@ -10,3 +10,11 @@ register_convex_counter!(
 pub fn log_migration_worker_failed() {
     log_counter(&MIGRATION_WORKER_FAILED_TOTAL, 1)
 }
+
+register_convex_counter!(
+    SCHEDULED_JOB_REJECTED_TOTAL,
+    "Number of functions not scheduled because they had too many outstanding runs"
+);
+pub fn log_scheduled_job_rejected() {
+    log_counter(&SCHEDULED_JOB_REJECTED_TOTAL, 1)
+}
@@ -77,7 +77,7 @@ impl fmt::Display for MigrationCompletionCriterion {
 // migrations unless explicitly dropping support.
 // Add a user name next to the version when you make a change to highlight merge
 // conflicts.
-pub const DATABASE_VERSION: DatabaseVersion = 115; // nipunn
+pub const DATABASE_VERSION: DatabaseVersion = 117; // stonega
 
 pub struct MigrationWorker<RT: Runtime> {
     rt: RT,
@@ -372,6 +372,21 @@ impl<RT: Runtime> MigrationWorker<RT> {
                 }
                 MigrationCompletionCriterion::MigrationComplete(to_version)
             },
+            116 => {
+                // Empty migration corresponding to _scheduled_jobs.by_dedupe_key
+                // creation
+                MigrationCompletionCriterion::LogLine(
+                    "Finished backfill of system index _scheduled_jobs.by_dedupe_key".into(),
+                )
+            },
+            117 => {
+                // Empty migration corresponding to
+                // _scheduled_jobs.by_priority_and_next_ts creation
+                MigrationCompletionCriterion::LogLine(
+                    "Finished backfill of system index _scheduled_jobs.by_priority_and_next_ts"
+                        .into(),
+                )
+            },
             // NOTE: Make sure to increase DATABASE_VERSION when adding new migrations.
             _ => anyhow::bail!("Version did not define a migration! {}", to_version),
         };
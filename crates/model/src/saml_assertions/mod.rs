@@ -0,0 +1,154 @@
+//! The SAML assertions that were exchanged for tokens, so each can only be
+//! exchanged once. They're stored in a global table so replays are caught
+//! across restarts, and are deleted once the assertions expire.
+use std::{
+    sync::LazyLock,
+    time::SystemTime,
+};
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        IndexRange,
+        IndexRangeExpression,
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+    types::IndexName,
+};
+use database::{
+    defaults::system_index,
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ConvexValue,
+    FieldPath,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    saml_assertions::types::UsedSamlAssertion,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static SAML_ASSERTIONS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_saml_assertions"
+        .parse()
+        .expect("Invalid built-in SAML assertions table")
+});
+
+pub static SAML_ASSERTIONS_INDEX_BY_ASSERTION_ID: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&SAML_ASSERTIONS_TABLE, "by_assertion_id"));
+pub static SAML_ASSERTIONS_INDEX_BY_EXPIRATION: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&SAML_ASSERTIONS_TABLE, "by_expiration"));
+static ISSUER_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "issuer".parse().expect("invalid issuer field"));
+static ASSERTION_ID_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "assertionId".parse().expect("invalid assertionId field"));
+static EXPIRATION_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "expirationMs".parse().expect("invalid expirationMs field"));
+
+pub struct SamlAssertionsTable;
+impl SystemTable for SamlAssertionsTable {
+    fn table_name(&self) -> &'static TableName {
+        &SAML_ASSERTIONS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![
+            SystemIndex {
+                name: SAML_ASSERTIONS_INDEX_BY_ASSERTION_ID.clone(),
+                fields: vec![ISSUER_FIELD.clone(), ASSERTION_ID_FIELD.clone()]
+                    .try_into()
+                    .unwrap(),
+            },
+            SystemIndex {
+                name: SAML_ASSERTIONS_INDEX_BY_EXPIRATION.clone(),
+                fields: vec![EXPIRATION_FIELD.clone()].try_into().unwrap(),
+            },
+        ]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<UsedSamlAssertion>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct SamlAssertionsModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> SamlAssertionsModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    /// Records that `assertion` was exchanged for a token. Returns false if it
+    /// already was.
+    pub async fn record_use(&mut self, assertion: UsedSamlAssertion) -> anyhow::Result<bool> {
+        let query = Query::index_range(IndexRange {
+            index_name: SAML_ASSERTIONS_INDEX_BY_ASSERTION_ID.clone(),
+            range: vec![
+                IndexRangeExpression::Eq(
+                    ISSUER_FIELD.clone(),
+                    ConvexValue::try_from(assertion.issuer.clone())?.into(),
+                ),
+                IndexRangeExpression::Eq(
+                    ASSERTION_ID_FIELD.clone(),
+                    ConvexValue::try_from(assertion.assertion_id.clone())?.into(),
+                ),
+            ],
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        if query_stream.next(self.tx, None).await?.is_some() {
+            return Ok(false);
+        }
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&SAML_ASSERTIONS_TABLE, assertion.try_into()?)
+            .await?;
+        Ok(true)
+    }
+
+    /// Deletes up to `limit` of the assertions that expired before `now`,
+    /// returning how many were deleted.
+    pub async fn delete_expired(&mut self, now: SystemTime, limit: usize) -> anyhow::Result<usize> {
+        let query = Query::index_range(IndexRange {
+            index_name: SAML_ASSERTIONS_INDEX_BY_EXPIRATION.clone(),
+            range: vec![IndexRangeExpression::Lt(
+                EXPIRATION_FIELD.clone(),
+                expiration_value(now)?,
+            )],
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut expired = vec![];
+        while expired.len() < limit
+            && let Some(doc) = query_stream.next(self.tx, None).await?
+        {
+            expired.push(doc.id());
+        }
+        for id in &expired {
+            SystemMetadataModel::new_global(self.tx).delete(*id).await?;
+        }
+        Ok(expired.len())
+    }
+}
+
+fn expiration_value(expiration: SystemTime) -> anyhow::Result<ConvexValue> {
+    let millis: i64 = expiration
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis()
+        .try_into()?;
+    Ok(ConvexValue::Int64(millis))
+}
@@ -0,0 +1,69 @@
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// A SAML assertion that was exchanged for a token. It's kept until the
+/// assertion expires so it can't be exchanged again, even by another backend
+/// or after a restart.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct UsedSamlAssertion {
+    /// The identity provider's entity ID.
+    pub issuer: String,
+    /// The assertion's `ID`, which is only unique per issuer.
+    pub assertion_id: String,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "(0..1u64 << 40).prop_map(|ms| SystemTime::UNIX_EPOCH + \
+                             Duration::from_millis(ms))")
+    )]
+    pub expiration: SystemTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedUsedSamlAssertion {
+    issuer: String,
+    assertion_id: String,
+    expiration_ms: i64,
+}
+
+impl TryFrom<UsedSamlAssertion> for SerializedUsedSamlAssertion {
+    type Error = anyhow::Error;
+
+    fn try_from(assertion: UsedSamlAssertion) -> anyhow::Result<Self> {
+        Ok(Self {
+            issuer: assertion.issuer,
+            assertion_id: assertion.assertion_id,
+            expiration_ms: assertion
+                .expiration
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_millis()
+                .try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedUsedSamlAssertion> for UsedSamlAssertion {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedUsedSamlAssertion) -> anyhow::Result<Self> {
+        Ok(Self {
+            issuer: value.issuer,
+            assertion_id: value.assertion_id,
+            expiration: SystemTime::UNIX_EPOCH
+                + Duration::from_millis(value.expiration_ms.try_into()?),
+        })
+    }
+}
+
+codegen_convex_serialization!(UsedSamlAssertion, SerializedUsedSamlAssertion);
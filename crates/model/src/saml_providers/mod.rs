@@ -0,0 +1,101 @@
+//! The SAML 2.0 identity providers users can sign in with, for enterprises
+//! that can't use OpenID Connect.
+use std::sync::LazyLock;
+
+use common::{
+    auth::SamlProvider,
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    saml_providers::types::SamlProviderPersisted,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static SAML_PROVIDERS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_saml_providers"
+        .parse()
+        .expect("Invalid built-in SAML providers table")
+});
+
+pub struct SamlProvidersTable;
+impl SystemTable for SamlProvidersTable {
+    fn table_name(&self) -> &'static TableName {
+        &SAML_PROVIDERS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<SamlProviderPersisted>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct SamlProvidersModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> SamlProvidersModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<SamlProvider>>> {
+        let query = Query::full_table_scan(SAML_PROVIDERS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut providers = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let provider: ParsedDocument<SamlProviderPersisted> = doc.try_into()?;
+            providers.push(provider.map(|p| Ok(p.0))?);
+        }
+        Ok(providers)
+    }
+
+    pub async fn insert(&mut self, provider: SamlProvider) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(
+                &SAML_PROVIDERS_TABLE,
+                SamlProviderPersisted(provider).try_into()?,
+            )
+            .await
+    }
+
+    /// Deletes the provider with the given id, returning whether it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(provider) = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|provider| provider.id().developer_id.encode() == id)
+        else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(provider.id())
+            .await?;
+        Ok(true)
+    }
+}
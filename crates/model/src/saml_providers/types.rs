@@ -0,0 +1,59 @@
+use common::auth::SamlProvider;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// Persisted version of [`SamlProvider`], which is defined in `common` for
+/// the authentication crate.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct SamlProviderPersisted(pub SamlProvider);
+
+/// Attribute names are arbitrary strings, so the mapping is stored as a list
+/// rather than an object.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedSamlProvider {
+    entity_id: String,
+    certificate: String,
+    attribute_mapping: Vec<SerializedAttributeMapping>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAttributeMapping {
+    field: String,
+    attribute: String,
+}
+
+impl From<SamlProviderPersisted> for SerializedSamlProvider {
+    fn from(SamlProviderPersisted(provider): SamlProviderPersisted) -> Self {
+        Self {
+            entity_id: provider.entity_id,
+            certificate: provider.certificate,
+            attribute_mapping: provider
+                .attribute_mapping
+                .into_iter()
+                .map(|(field, attribute)| SerializedAttributeMapping { field, attribute })
+                .collect(),
+        }
+    }
+}
+
+impl From<SerializedSamlProvider> for SamlProviderPersisted {
+    fn from(value: SerializedSamlProvider) -> Self {
+        Self(SamlProvider {
+            entity_id: value.entity_id,
+            certificate: value.certificate,
+            attribute_mapping: value
+                .attribute_mapping
+                .into_iter()
+                .map(|SerializedAttributeMapping { field, attribute }| (field, attribute))
+                .collect(),
+        })
+    }
+}
+
+codegen_convex_serialization!(SamlProviderPersisted, SerializedSamlProvider);
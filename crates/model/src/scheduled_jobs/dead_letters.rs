@@ -0,0 +1,171 @@
+//! Scheduled jobs with a retry policy that failed on every attempt, kept
+//! after the job itself is garbage collected so they can be inspected and run
+//! again.
+use std::{
+    sync::LazyLock,
+    time::Duration,
+};
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    execution_context::ExecutionContext,
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+    types::Timestamp,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    scheduled_jobs::{
+        types::ScheduledJobDeadLetter,
+        SchedulerModel,
+    },
+    SystemIndex,
+    SystemTable,
+};
+
+pub static SCHEDULED_JOB_DEAD_LETTERS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_scheduled_job_dead_letters"
+        .parse()
+        .expect("_scheduled_job_dead_letters is not a valid system table name")
+});
+
+pub struct ScheduledJobDeadLettersTable;
+impl SystemTable for ScheduledJobDeadLettersTable {
+    fn table_name(&self) -> &'static TableName {
+        &SCHEDULED_JOB_DEAD_LETTERS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<ScheduledJobDeadLetter>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct ScheduledJobDeadLettersModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+    namespace: TableNamespace,
+}
+
+impl<'a, RT: Runtime> ScheduledJobDeadLettersModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>, namespace: TableNamespace) -> Self {
+        Self { tx, namespace }
+    }
+
+    pub async fn insert(
+        &mut self,
+        dead_letter: ScheduledJobDeadLetter,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .insert_metadata(&SCHEDULED_JOB_DEAD_LETTERS_TABLE, dead_letter.try_into()?)
+            .await
+    }
+
+    pub async fn get(
+        &mut self,
+        id: ResolvedDocumentId,
+    ) -> anyhow::Result<Option<ParsedDocument<ScheduledJobDeadLetter>>> {
+        self.check_table(id)?;
+        self.tx.get(id).await?.map(TryFrom::try_from).transpose()
+    }
+
+    /// Lists the dead letters, oldest first.
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<ScheduledJobDeadLetter>>> {
+        let query = Query::full_table_scan(SCHEDULED_JOB_DEAD_LETTERS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, query)?;
+        let mut dead_letters = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            dead_letters.push(doc.try_into()?);
+        }
+        Ok(dead_letters)
+    }
+
+    pub async fn delete(&mut self, id: ResolvedDocumentId) -> anyhow::Result<()> {
+        self.check_table(id)?;
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .delete(id)
+            .await?;
+        Ok(())
+    }
+
+    /// Schedules the dead letter's job to run again now, with the options it
+    /// was originally scheduled with, and deletes the dead letter. Returns the
+    /// new job's id, or `None` if the dead letter doesn't exist.
+    pub async fn rerun(
+        &mut self,
+        id: ResolvedDocumentId,
+        context: ExecutionContext,
+    ) -> anyhow::Result<Option<ResolvedDocumentId>> {
+        let Some(dead_letter) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let now = self.tx.runtime().unix_timestamp();
+        let job_id = SchedulerModel::new(self.tx, self.namespace)
+            .schedule(
+                dead_letter.path.clone(),
+                dead_letter.udf_args()?,
+                now,
+                context,
+                dead_letter.options(),
+            )
+            .await?;
+        self.delete(id).await?;
+        Ok(Some(job_id))
+    }
+
+    /// Deletes up to `limit` dead letters that failed more than `retention`
+    /// before `now`, oldest first. Returns how many were deleted and, if the
+    /// limit wasn't hit, how long until the next one expires.
+    pub async fn delete_expired(
+        &mut self,
+        now: Timestamp,
+        retention: Duration,
+        limit: usize,
+    ) -> anyhow::Result<(usize, Option<Duration>)> {
+        let query = Query::full_table_scan(SCHEDULED_JOB_DEAD_LETTERS_TABLE.clone(), Order::Asc)
+            .limit(limit);
+        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, query)?;
+        let mut expired = vec![];
+        let mut next_expiry = None;
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let dead_letter: ParsedDocument<ScheduledJobDeadLetter> = doc.try_into()?;
+            let expires_ts = dead_letter.failed_ts.add(retention)?;
+            if expires_ts > now {
+                next_expiry = Some(expires_ts - now);
+                break;
+            }
+            expired.push(dead_letter.id());
+        }
+        for id in &expired {
+            self.delete(*id).await?;
+        }
+        Ok((expired.len(), next_expiry))
+    }
+
+    fn check_table(&mut self, id: ResolvedDocumentId) -> anyhow::Result<()> {
+        anyhow::ensure!(self
+            .tx
+            .table_mapping()
+            .namespace(self.namespace)
+            .tablet_matches_name(id.tablet_id, &SCHEDULED_JOB_DEAD_LETTERS_TABLE));
+        Ok(())
+    }
+}
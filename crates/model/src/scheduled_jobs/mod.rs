@@ -4,21 +4,27 @@ use std::{
         Arc,
         LazyLock,
     },
+    time::Duration,
 };
 
+use anyhow::Context;
 use common::{
     components::CanonicalizedComponentFunctionPath,
     document::{
         ParsedDocument,
         ResolvedDocument,
+        CREATION_TIME_FIELD_PATH,
     },
     execution_context::ExecutionContext,
     knobs::{
+        SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION,
         TRANSACTION_MAX_NUM_SCHEDULED,
         TRANSACTION_MAX_SCHEDULED_TOTAL_ARGUMENT_SIZE_BYTES,
     },
     maybe_val,
     query::{
+        Cursor,
+        CursorPosition,
         Expression,
         IndexRange,
         IndexRangeExpression,
@@ -37,7 +43,12 @@ use common::{
 };
 use database::{
     defaults::system_index,
+    query::{
+        PaginationOptions,
+        TableFilter,
+    },
     unauthorized_error,
+    IndexModel,
     ResolvedQuery,
     SystemMetadataModel,
     Transaction,
@@ -57,18 +68,28 @@ use value::{
 };
 
 use self::{
+    dead_letters::ScheduledJobDeadLettersModel,
     types::{
+        DuplicateJobBehavior,
+        ScheduleOptions,
         ScheduledJob,
         ScheduledJobAttempts,
+        ScheduledJobDeadLetter,
+        ScheduledJobFilter,
+        ScheduledJobPriority,
         ScheduledJobState,
     },
     virtual_table::ScheduledJobsDocMapper,
 };
 use crate::{
+    metrics::log_scheduled_job_rejected,
+    workflows::WorkflowModel,
     SystemIndex,
     SystemTable,
 };
 
+pub mod dead_letters;
+pub mod pauses;
 pub mod types;
 pub mod virtual_table;
 
@@ -100,6 +121,10 @@ pub static SCHEDULED_JOBS_INDEX_BY_UDF_PATH: LazyLock<IndexName> =
     LazyLock::new(|| system_index(&SCHEDULED_JOBS_TABLE, "by_udf_path_and_next_event_ts"));
 pub static SCHEDULED_JOBS_INDEX_BY_COMPLETED_TS: LazyLock<IndexName> =
     LazyLock::new(|| system_index(&SCHEDULED_JOBS_TABLE, "by_completed_ts"));
+pub static SCHEDULED_JOBS_INDEX_BY_PRIORITY: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&SCHEDULED_JOBS_TABLE, "by_priority_and_next_ts"));
+pub static SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&SCHEDULED_JOBS_TABLE, "by_dedupe_key_and_next_ts"));
 pub static NEXT_TS_FIELD: LazyLock<FieldPath> =
     LazyLock::new(|| "nextTs".parse().expect("invalid nextTs field"));
 pub static COMPLETED_TS_FIELD: LazyLock<FieldPath> =
@@ -108,6 +133,10 @@ static UDF_PATH_FIELD: LazyLock<FieldPath> =
     LazyLock::new(|| "udfPath".parse().expect("invalid udfPath field"));
 static COMPONENT_PATH_FIELD: LazyLock<FieldPath> =
     LazyLock::new(|| "component".parse().expect("invalid component field"));
+pub static PRIORITY_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "priority".parse().expect("invalid priority field"));
+static DEDUPE_KEY_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "dedupeKey".parse().expect("invalid dedupeKey field"));
 
 pub struct ScheduledJobsTable;
 impl SystemTable for ScheduledJobsTable {
@@ -127,6 +156,26 @@ impl SystemTable for ScheduledJobsTable {
                 name: SCHEDULED_JOBS_INDEX.clone(),
                 fields: vec![NEXT_TS_FIELD.clone()].try_into().unwrap(),
             },
+            // By priority and next ts. Used to find the highest priority jobs that are ready
+            // to run when the scheduler is behind.
+            SystemIndex {
+                name: SCHEDULED_JOBS_INDEX_BY_PRIORITY.clone(),
+                fields: vec![
+                    PRIORITY_FIELD.clone(),
+                    NEXT_TS_FIELD.clone(),
+                    CREATION_TIME_FIELD_PATH.clone(),
+                ]
+                .try_into()
+                .unwrap(),
+            },
+            // By dedupe key and next ts. Used to find the pending job a newly scheduled job
+            // with the same dedupe key replaces.
+            SystemIndex {
+                name: SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY.clone(),
+                fields: vec![DEDUPE_KEY_FIELD.clone(), NEXT_TS_FIELD.clone()]
+                    .try_into()
+                    .unwrap(),
+            },
             // By udf path and next ts. Used by the dashboard to group scheduled jobs by udf
             // function.
             SystemIndex {
@@ -162,6 +211,105 @@ impl SystemTable for ScheduledJobsTable {
     }
 }
 
+/// Queries the jobs that are due to run by `now`, oldest first. With a
+/// priority, only that priority's jobs are queried, using the
+/// `by_priority_and_next_ts` index.
+pub fn ready_jobs_query(
+    priority: Option<ScheduledJobPriority>,
+    now: Timestamp,
+) -> anyhow::Result<Query> {
+    let next_ts_range = [
+        IndexRangeExpression::Gt(NEXT_TS_FIELD.clone(), ConvexValue::Null),
+        IndexRangeExpression::Lte(NEXT_TS_FIELD.clone(), ConvexValue::from(i64::from(now))),
+    ];
+    let query = match priority {
+        Some(priority) => {
+            // Normal priority jobs don't have the field, see `SerializedScheduledJob`.
+            let priority_value = match priority {
+                ScheduledJobPriority::Normal => maybe_val!(undefined),
+                priority => ConvexValue::try_from(priority.to_string())?.into(),
+            };
+            let mut range = vec![IndexRangeExpression::Eq(
+                PRIORITY_FIELD.clone(),
+                priority_value,
+            )];
+            range.extend(next_ts_range);
+            Query::index_range(IndexRange {
+                index_name: SCHEDULED_JOBS_INDEX_BY_PRIORITY.clone(),
+                range,
+                order: Order::Asc,
+            })
+        },
+        None => Query::index_range(IndexRange {
+            index_name: SCHEDULED_JOBS_INDEX.clone(),
+            range: next_ts_range.into(),
+            order: Order::Asc,
+        }),
+    };
+    Ok(query)
+}
+
+/// Queries the pending and in progress jobs that match the filter's function
+/// and scheduled times, soonest first. Arguments aren't filtered.
+fn filtered_jobs_query(filter: &ScheduledJobFilter) -> anyhow::Result<Query> {
+    let mut next_ts_range = vec![match filter.scheduled_after {
+        Some(after) => {
+            IndexRangeExpression::Gte(NEXT_TS_FIELD.clone(), ConvexValue::from(i64::from(after)))
+        },
+        None => IndexRangeExpression::Gt(NEXT_TS_FIELD.clone(), value::ConvexValue::Null),
+    }];
+    if let Some(before) = filter.scheduled_before {
+        next_ts_range.push(IndexRangeExpression::Lt(
+            NEXT_TS_FIELD.clone(),
+            ConvexValue::from(i64::from(before)),
+        ));
+    }
+    let query = match &filter.path {
+        Some(path) => {
+            let udf_path = &path.udf_path;
+            let component_path = &path.component;
+            let mut component_path_filter = Expression::Eq(
+                Expression::Field(COMPONENT_PATH_FIELD.clone()).into(),
+                Expression::Literal(maybe_val!(String::from(component_path.clone()))).into(),
+            );
+            if component_path.is_root() {
+                component_path_filter = Expression::Or(vec![
+                    component_path_filter,
+                    Expression::Eq(
+                        Expression::Field(COMPONENT_PATH_FIELD.clone()).into(),
+                        Expression::Literal(maybe_val!(undefined)).into(),
+                    ),
+                ]);
+            }
+            let mut range = vec![IndexRangeExpression::Eq(
+                UDF_PATH_FIELD.clone(),
+                ConvexValue::try_from(udf_path.to_string())?.into(),
+            )];
+            range.extend(next_ts_range);
+            Query::index_range(IndexRange {
+                index_name: SCHEDULED_JOBS_INDEX_BY_UDF_PATH.clone(),
+                range,
+                order: Order::Asc,
+            })
+            .filter(component_path_filter)
+        },
+        None => Query::index_range(IndexRange {
+            index_name: SCHEDULED_JOBS_INDEX.clone(),
+            range: next_ts_range,
+            order: Order::Asc,
+        }),
+    };
+    Ok(query)
+}
+
+/// The result of a `SchedulerModel::cancel_all` batch.
+pub struct CancelJobsPage {
+    pub canceled: usize,
+    /// Where the next batch continues scanning, or `None` if every job that
+    /// matches the filter was scanned.
+    pub cursor: Option<Cursor>,
+}
+
 // Maintains state for scheduling asynchronous functions (scheduled jobs).
 pub struct SchedulerModel<'a, RT: Runtime> {
     tx: &'a mut Transaction<RT>,
@@ -202,12 +350,42 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
         Ok(())
     }
 
+    /// Fails if the function is capped by
+    /// `SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION` and already has that many
+    /// pending or in progress runs in this component.
+    async fn check_outstanding_limit(
+        &mut self,
+        path: &CanonicalizedComponentFunctionPath,
+    ) -> anyhow::Result<()> {
+        let Some(limit) = SCHEDULED_JOB_MAX_OUTSTANDING_PER_FUNCTION.limit_for(&path.udf_path)
+        else {
+            return Ok(());
+        };
+        let filter = ScheduledJobFilter {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        if self.count_outstanding(&filter, limit).await? >= limit {
+            log_scheduled_job_rejected();
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "TooManyScheduledFunctionsOutstanding",
+                format!(
+                    "Too many scheduled runs of {} haven't finished yet (limit: {limit}). Wait \
+                     for them to run or cancel some before scheduling more.",
+                    path.udf_path
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     pub async fn schedule(
         &mut self,
         path: CanonicalizedComponentFunctionPath,
         args: ConvexArray,
         ts: UnixTimestamp,
         context: ExecutionContext,
+        options: ScheduleOptions,
     ) -> anyhow::Result<ResolvedDocumentId> {
         if path.udf_path.is_system()
             && !(self.tx.identity().is_admin() || self.tx.identity().is_system())
@@ -215,7 +393,17 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
             anyhow::bail!(unauthorized_error("schedule"))
         }
 
+        if let Some(dedupe_key) = &options.dedupe_key
+            && let Some(duplicate_id) = self.find_pending_duplicate(&path, dedupe_key).await?
+        {
+            match options.on_duplicate {
+                DuplicateJobBehavior::Keep => return Ok(duplicate_id),
+                DuplicateJobBehavior::Replace => self.cancel(duplicate_id).await?,
+            }
+        }
+
         self.check_scheduling_limits(&args)?;
+        self.check_outstanding_limit(&path).await?;
 
         let now: Timestamp = self.tx.runtime().generate_timestamp()?;
         let original_scheduled_ts: Timestamp = ts.as_system_time().try_into()?;
@@ -230,6 +418,8 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
             None,
             original_scheduled_ts,
             ScheduledJobAttempts::default(),
+            context.traceparent.clone(),
+            options.clone(),
         )?;
         let job = if let Some((parent_component_id, parent_scheduled_job)) =
             context.parent_scheduled_job
@@ -269,6 +459,8 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
                             Some(*scheduled_ts),
                             *scheduled_ts,
                             ScheduledJobAttempts::default(),
+                            context.traceparent,
+                            options,
                         )?
                     },
                 }
@@ -285,6 +477,47 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
         Ok(id)
     }
 
+    /// Finds the pending job of the function at `path` that was scheduled
+    /// with `dedupe_key`. Jobs that already started don't count.
+    ///
+    /// Fails while the dedupe key index is still backfilling, since pending
+    /// jobs scheduled before it was added can't be found yet.
+    async fn find_pending_duplicate(
+        &mut self,
+        path: &CanonicalizedComponentFunctionPath,
+        dedupe_key: &str,
+    ) -> anyhow::Result<Option<ResolvedDocumentId>> {
+        if IndexModel::new(self.tx)
+            .enabled_index_metadata(self.namespace, &SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY)?
+            .is_none()
+        {
+            anyhow::bail!(ErrorMetadata::bad_request(
+                "DedupeKeyUnavailable",
+                "Scheduling with a dedupe key isn't available yet on this deployment. Try again \
+                 later or schedule without a dedupe key.",
+            ));
+        }
+        let query = Query::index_range(IndexRange {
+            index_name: SCHEDULED_JOBS_INDEX_BY_DEDUPE_KEY.clone(),
+            range: vec![
+                IndexRangeExpression::Eq(
+                    DEDUPE_KEY_FIELD.clone(),
+                    ConvexValue::try_from(dedupe_key.to_string())?.into(),
+                ),
+                IndexRangeExpression::Gt(NEXT_TS_FIELD.clone(), ConvexValue::Null),
+            ],
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, query)?;
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            let job: ParsedDocument<ScheduledJob> = doc.try_into()?;
+            if job.path == *path && job.state == ScheduledJobState::Pending {
+                return Ok(Some(job.id()));
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn replace(
         &mut self,
         id: ResolvedDocumentId,
@@ -305,6 +538,18 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
         &mut self,
         id: ResolvedDocumentId,
         state: ScheduledJobState,
+    ) -> anyhow::Result<()> {
+        self.complete_with_result(id, state, None).await
+    }
+
+    /// Like `complete`, with the value the job's function returned as Convex
+    /// JSON if it succeeded. Jobs that run a workflow step record it as the
+    /// step's result.
+    pub async fn complete_with_result(
+        &mut self,
+        id: ResolvedDocumentId,
+        state: ScheduledJobState,
+        result: Option<String>,
     ) -> anyhow::Result<()> {
         match state {
             ScheduledJobState::InProgress | ScheduledJobState::Pending => {
@@ -334,7 +579,8 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
         }
 
         let mut job: ScheduledJob = job.into_value();
-        job.state = state;
+        let workflow_step = job.workflow_step;
+        job.state = state.clone();
         // Remove next_ts and set completed_ts so the scheduler knows that the
         // job has already been processed
         job.next_ts = None;
@@ -343,6 +589,69 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
             .replace(id, job.try_into()?)
             .await?;
 
+        if let Some(step_id) = workflow_step {
+            let outcome = match state {
+                ScheduledJobState::Success => Ok(result.unwrap_or_else(|| "null".to_string())),
+                ScheduledJobState::Failed(error) => Err(error),
+                ScheduledJobState::Canceled => Err("Canceled".to_string()),
+                ScheduledJobState::Pending | ScheduledJobState::InProgress => {
+                    anyhow::bail!("invalid state for completing a workflow step")
+                },
+            };
+            WorkflowModel::new(self.tx, self.namespace)
+                .complete_step(step_id, outcome)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Fails a job with an error from the function it ran. If the job's retry
+    /// policy has attempts left, the job goes back to pending and runs again
+    /// after a backoff. Otherwise it's completed as failed, and jobs with a
+    /// retry policy are also copied to the dead letter table.
+    pub async fn fail(&mut self, id: ResolvedDocumentId, error: String) -> anyhow::Result<()> {
+        let Some(job) = self.tx.get(id).await? else {
+            anyhow::bail!("scheduled job not found")
+        };
+        let job: ParsedDocument<ScheduledJob> = job.try_into()?;
+        match job.state {
+            ScheduledJobState::Pending | ScheduledJobState::InProgress => {},
+            // Same as in `complete`, failing a canceled job is a no-op.
+            ScheduledJobState::Canceled => return Ok(()),
+            ScheduledJobState::Failed(_) | ScheduledJobState::Success => {
+                anyhow::bail!(
+                    "Scheduled job cannot be failed because it is in state {:?}",
+                    job.state
+                )
+            },
+        }
+        let mut job: ScheduledJob = job.into_value();
+        job.attempts.user_errors += 1;
+        let Some(retry_policy) = job.retry_policy else {
+            return self.complete(id, ScheduledJobState::Failed(error)).await;
+        };
+        if job.attempts.user_errors < retry_policy.max_attempts {
+            let delay = retry_policy.backoff(job.attempts.user_errors);
+            job.state = ScheduledJobState::Pending;
+            job.next_ts = Some(self.tx.runtime().generate_timestamp()?.add(delay)?);
+            return self.replace(id, job).await;
+        }
+        let failed_ts = *self.tx.begin_timestamp();
+        let dead_letter =
+            ScheduledJobDeadLetter::new(id.developer_id, job.clone(), error.clone(), failed_ts);
+        let workflow_step = job.workflow_step;
+        job.state = ScheduledJobState::Failed(error.clone());
+        job.next_ts = None;
+        job.completed_ts = Some(failed_ts);
+        self.replace(id, job).await?;
+        ScheduledJobDeadLettersModel::new(self.tx, self.namespace)
+            .insert(dead_letter)
+            .await?;
+        if let Some(step_id) = workflow_step {
+            WorkflowModel::new(self.tx, self.namespace)
+                .complete_step(step_id, Err(error))
+                .await?;
+        }
         Ok(())
     }
 
@@ -374,67 +683,103 @@ impl<'a, RT: Runtime> SchedulerModel<'a, RT> {
         Ok(())
     }
 
-    // Cancel up to `limit` jobs for the UDF and return how many were canceled.
-    // Note: the caller will assume all have been canceled if Result < `limit`.
+    /// Scans up to `limit` pending jobs that match the filter, starting at
+    /// `cursor`, and cancels the ones whose arguments match too.
     pub async fn cancel_all(
         &mut self,
-        path: Option<CanonicalizedComponentFunctionPath>,
+        filter: &ScheduledJobFilter,
+        cursor: Option<Cursor>,
         limit: usize,
-    ) -> anyhow::Result<usize> {
-        let index_query = match path {
-            Some(path) => {
-                let udf_path = path.udf_path;
-                let component_path = path.component;
-                let mut component_path_filter = Expression::Eq(
-                    Expression::Field(COMPONENT_PATH_FIELD.clone()).into(),
-                    Expression::Literal(maybe_val!(String::from(component_path.clone()))).into(),
-                );
-                if component_path.is_root() {
-                    component_path_filter = Expression::Or(vec![
-                        component_path_filter,
-                        Expression::Eq(
-                            Expression::Field(COMPONENT_PATH_FIELD.clone()).into(),
-                            Expression::Literal(maybe_val!(undefined)).into(),
-                        ),
-                    ]);
-                }
-                let range = vec![
-                    IndexRangeExpression::Eq(
-                        UDF_PATH_FIELD.clone(),
-                        ConvexValue::try_from(udf_path.to_string())?.into(),
-                    ),
-                    IndexRangeExpression::Gt(NEXT_TS_FIELD.clone(), value::ConvexValue::Null),
-                ];
-                Query::index_range(IndexRange {
-                    index_name: SCHEDULED_JOBS_INDEX_BY_UDF_PATH.clone(),
-                    range,
-                    order: Order::Asc,
-                })
-                .filter(component_path_filter)
+    ) -> anyhow::Result<CancelJobsPage> {
+        let index_query = filtered_jobs_query(filter)?;
+        let mut query_stream = ResolvedQuery::new_bounded(
+            self.tx,
+            self.namespace,
+            index_query,
+            PaginationOptions::ManualPagination {
+                start_cursor: cursor,
+                maximum_rows_read: None,
+                maximum_bytes_read: None,
             },
-            None => {
-                let range = vec![IndexRangeExpression::Gt(
-                    NEXT_TS_FIELD.clone(),
-                    value::ConvexValue::Null,
-                )];
-                Query::index_range(IndexRange {
-                    index_name: SCHEDULED_JOBS_INDEX.clone(),
-                    range,
-                    order: Order::Asc,
-                })
-            },
-        };
-        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, index_query)?;
-        let mut count = 0;
-        while count < limit
+            None,
+            TableFilter::IncludePrivateSystemTables,
+        )?;
+        let mut scanned = 0;
+        let mut canceled = 0;
+        while scanned < limit
             && let Some(doc) = query_stream.next(self.tx, None).await?
         {
-            self.cancel(doc.id()).await?;
+            scanned += 1;
+            let job: ParsedDocument<ScheduledJob> = doc.try_into()?;
+            if filter.matches_args(&job)? {
+                self.cancel(job.id()).await?;
+                canceled += 1;
+            }
+        }
+        let cursor = query_stream
+            .cursor()
+            .context("Missing cursor after scanning scheduled jobs")?;
+        let is_done = scanned < limit || cursor.position == CursorPosition::End;
+        Ok(CancelJobsPage {
+            canceled,
+            cursor: (!is_done).then_some(cursor),
+        })
+    }
+
+    /// Counts the pending and in progress jobs that match the filter's
+    /// function and scheduled times, reading at most `limit` of them.
+    pub async fn count_outstanding(
+        &mut self,
+        filter: &ScheduledJobFilter,
+        limit: usize,
+    ) -> anyhow::Result<usize> {
+        let query = filtered_jobs_query(filter)?.limit(limit);
+        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, query)?;
+        let mut count = 0;
+        while query_stream.next(self.tx, None).await?.is_some() {
             count += 1;
         }
         Ok(count)
     }
 
+    /// Delays the pending jobs scheduled earlier in this transaction, in the
+    /// order they're due, so that at most `max_per_second` of them start each
+    /// second. Crons use this to smooth out the functions they fan out to.
+    pub async fn space_out_new_jobs(&mut self, max_per_second: u32) -> anyhow::Result<()> {
+        let table_mapping = self.tx.table_mapping().namespace(self.namespace);
+        let new_job_ids: Vec<_> = self
+            .tx
+            .writes()
+            .as_flat()?
+            .generated_ids()
+            .into_iter()
+            .filter(|id| table_mapping.tablet_matches_name(id.tablet_id, &SCHEDULED_JOBS_TABLE))
+            .collect();
+        let mut new_jobs = vec![];
+        for id in new_job_ids {
+            let Some(job) = self.tx.get(id).await? else {
+                continue;
+            };
+            let job: ParsedDocument<ScheduledJob> = job.try_into()?;
+            if let (ScheduledJobState::Pending, Some(next_ts)) = (&job.state, job.next_ts) {
+                new_jobs.push((next_ts, id, job.into_value()));
+            }
+        }
+        new_jobs.sort_by_key(|(next_ts, ..)| *next_ts);
+
+        let interval = Duration::from_secs(1) / max_per_second;
+        let mut next_slot = self.tx.runtime().generate_timestamp()?;
+        for (next_ts, id, mut job) in new_jobs {
+            let start_ts = next_ts.max(next_slot);
+            if start_ts != next_ts {
+                job.next_ts = Some(start_ts);
+                self.replace(id, job).await?;
+            }
+            next_slot = start_ts.add(interval)?;
+        }
+        Ok(())
+    }
+
     pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<ScheduledJob>>> {
         let scheduled_query = Query::full_table_scan(SCHEDULED_JOBS_TABLE.clone(), Order::Asc);
         let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, scheduled_query)?;
@@ -481,9 +826,10 @@ impl<'a, RT: Runtime> VirtualSchedulerModel<'a, RT> {
         args: ConvexArray,
         ts: UnixTimestamp,
         context: ExecutionContext,
+        options: ScheduleOptions,
     ) -> anyhow::Result<DeveloperDocumentId> {
         let system_id = SchedulerModel::new(self.tx, self.namespace)
-            .schedule(path, args, ts, context)
+            .schedule(path, args, ts, context, options)
             .await?;
         self.tx
             .virtual_system_mapping()
@@ -0,0 +1,155 @@
+//! Pauses of scheduled job and cron job execution, either for every function
+//! or for single functions. They're stored in a global table so they apply to
+//! every component and survive restarts.
+use std::{
+    collections::BTreeSet,
+    sync::LazyLock,
+};
+
+use common::{
+    components::CanonicalizedComponentFunctionPath,
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    scheduled_jobs::types::SchedulerPause,
+    SystemIndex,
+    SystemTable,
+};
+
+pub static SCHEDULER_PAUSES_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_scheduler_pauses"
+        .parse()
+        .expect("_scheduler_pauses is not a valid system table name")
+});
+
+pub struct SchedulerPausesTable;
+impl SystemTable for SchedulerPausesTable {
+    fn table_name(&self) -> &'static TableName {
+        &SCHEDULER_PAUSES_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<SchedulerPause>::try_from(document).map(|_| ())
+    }
+}
+
+/// The pauses in effect, for the executors to check jobs against.
+#[derive(Clone, Debug, Default)]
+pub struct SchedulerPauses {
+    all: bool,
+    functions: BTreeSet<CanonicalizedComponentFunctionPath>,
+}
+
+impl SchedulerPauses {
+    /// Whether every job is paused.
+    pub fn is_all_paused(&self) -> bool {
+        self.all
+    }
+
+    pub fn is_paused(&self, path: &CanonicalizedComponentFunctionPath) -> bool {
+        self.all || self.functions.contains(path)
+    }
+}
+
+pub struct SchedulerPausesModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> SchedulerPausesModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<SchedulerPause>>> {
+        let query = Query::full_table_scan(SCHEDULER_PAUSES_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut pauses = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            pauses.push(doc.try_into()?);
+        }
+        Ok(pauses)
+    }
+
+    pub async fn load(&mut self) -> anyhow::Result<SchedulerPauses> {
+        let mut pauses = SchedulerPauses::default();
+        for pause in self.list().await? {
+            match pause.into_value().path {
+                Some(path) => {
+                    pauses.functions.insert(path);
+                },
+                None => pauses.all = true,
+            }
+        }
+        Ok(pauses)
+    }
+
+    /// Pauses the jobs of the function at `path`, or every job if `path` is
+    /// `None`. Returns false if they were already paused.
+    pub async fn pause(
+        &mut self,
+        path: Option<CanonicalizedComponentFunctionPath>,
+        reason: Option<String>,
+    ) -> anyhow::Result<bool> {
+        if self.find(path.as_ref()).await?.is_some() {
+            return Ok(false);
+        }
+        let pause = SchedulerPause {
+            path,
+            reason,
+            paused_ts: self.tx.runtime().generate_timestamp()?,
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&SCHEDULER_PAUSES_TABLE, pause.try_into()?)
+            .await?;
+        Ok(true)
+    }
+
+    /// Lifts the pause of the function at `path`, or the pause of every job if
+    /// `path` is `None`. Lifting the pause of every job doesn't lift the
+    /// pauses of single functions. Returns false if there was no such pause.
+    pub async fn resume(
+        &mut self,
+        path: Option<CanonicalizedComponentFunctionPath>,
+    ) -> anyhow::Result<bool> {
+        let Some(pause) = self.find(path.as_ref()).await? else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(pause.id())
+            .await?;
+        Ok(true)
+    }
+
+    async fn find(
+        &mut self,
+        path: Option<&CanonicalizedComponentFunctionPath>,
+    ) -> anyhow::Result<Option<ParsedDocument<SchedulerPause>>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|pause| pause.path.as_ref() == path))
+    }
+}
@@ -1,10 +1,18 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::Duration,
+};
+
 use common::{
     components::{
         CanonicalizedComponentFunctionPath,
         ComponentPath,
     },
+    runtime::UnixTimestamp,
     types::Timestamp,
 };
+use errors::ErrorMetadata;
 #[cfg(any(test, feature = "testing"))]
 use proptest::prelude::*;
 use serde::{
@@ -15,6 +23,7 @@ use serde_bytes::ByteBuf;
 use serde_json::Value as JsonValue;
 use value::{
     codegen_convex_serialization,
+    id_v6::DeveloperDocumentId,
     ConvexArray,
 };
 
@@ -48,6 +57,335 @@ pub struct ScheduledJob {
     pub original_scheduled_ts: Timestamp,
 
     pub attempts: ScheduledJobAttempts,
+
+    /// The W3C `traceparent` of the function that scheduled this job, so the
+    /// job's execution continues its trace.
+    pub traceparent: Option<String>,
+
+    /// Ready jobs with a higher priority run before ones with a lower
+    /// priority, regardless of which was scheduled first.
+    pub priority: ScheduledJobPriority,
+    /// The named queue this job belongs to, if any. Queues can have their
+    /// own concurrency limit via `SCHEDULED_JOB_QUEUE_CONCURRENCY`.
+    pub queue: Option<String>,
+    /// How to retry the job when it fails with an error. Jobs without a policy
+    /// run at most once.
+    pub retry_policy: Option<ScheduledJobRetryPolicy>,
+    /// The `_workflow_steps` document of the workflow step this job runs, if
+    /// any, which gets the job's result when it completes.
+    pub workflow_step: Option<DeveloperDocumentId>,
+    /// Scheduling the same function with the same key while this job is
+    /// pending replaces it or is skipped, see `ScheduleOptions::on_duplicate`.
+    pub dedupe_key: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum ScheduledJobPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl ScheduledJobPriority {
+    /// From highest to lowest.
+    pub const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Low];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::High => "high",
+            Self::Normal => "normal",
+            Self::Low => "low",
+        }
+    }
+}
+
+impl fmt::Display for ScheduledJobPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ScheduledJobPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let priority = match s {
+            "high" => Self::High,
+            "normal" => Self::Normal,
+            "low" => Self::Low,
+            _ => anyhow::bail!("Invalid scheduled job priority: {s}"),
+        };
+        Ok(priority)
+    }
+}
+
+/// How many times to run a job that fails with an error, and how long to
+/// wait between attempts. Only errors thrown by the function count as
+/// attempts: system errors are always retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ScheduledJobRetryPolicy {
+    /// Includes the first attempt.
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "1..=20u32"))]
+    pub max_attempts: u32,
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "0..1u64 << 40"))]
+    pub initial_backoff_ms: u64,
+    #[cfg_attr(any(test, feature = "testing"), proptest(strategy = "0..1u64 << 40"))]
+    pub max_backoff_ms: u64,
+}
+
+impl ScheduledJobRetryPolicy {
+    /// The delay before the next attempt after `failures` failed ones. It
+    /// starts at `initial_backoff_ms` and doubles after every failure, up to
+    /// `max_backoff_ms`.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let multiplier = 2u64.saturating_pow(failures.saturating_sub(1));
+        let backoff_ms = self
+            .initial_backoff_ms
+            .saturating_mul(multiplier)
+            .min(self.max_backoff_ms);
+        Duration::from_millis(backoff_ms)
+    }
+}
+
+/// How a job should be run, as passed to `ctx.scheduler.withOptions`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduleOptions {
+    pub priority: ScheduledJobPriority,
+    pub queue: Option<String>,
+    pub retry: Option<ScheduledJobRetryPolicy>,
+    /// Only set by workflows, for the jobs that run their steps.
+    pub workflow_step: Option<DeveloperDocumentId>,
+    pub dedupe_key: Option<String>,
+    /// What to do when a pending job of the same function already has the
+    /// same `dedupe_key`.
+    pub on_duplicate: DuplicateJobBehavior,
+}
+
+/// What scheduling a job does when a pending job of the same function in the
+/// same component has the same dedupe key. Jobs that are already running
+/// don't count.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateJobBehavior {
+    /// Cancel the pending job and schedule the new one, e.g. to debounce.
+    #[default]
+    Replace,
+    /// Keep the pending job and don't schedule the new one.
+    Keep,
+}
+
+impl FromStr for DuplicateJobBehavior {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let behavior = match s {
+            "replace" => Self::Replace,
+            "keep" => Self::Keep,
+            _ => anyhow::bail!("Invalid duplicate job behavior: {s}"),
+        };
+        Ok(behavior)
+    }
+}
+
+const MAX_QUEUE_NAME_LENGTH: usize = 64;
+const MAX_DEDUPE_KEY_LENGTH: usize = 256;
+const MAX_RETRY_ATTEMPTS: u32 = 20;
+const DEFAULT_RETRY_INITIAL_BACKOFF_MS: u64 = 1000;
+const DEFAULT_RETRY_MAX_BACKOFF_MS: u64 = 60 * 60 * 1000;
+const MAX_RETRY_BACKOFF_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleOptionsJson {
+    pub priority: Option<String>,
+    pub queue: Option<String>,
+    pub retry: Option<RetryPolicyJson>,
+    pub dedupe_key: Option<String>,
+    pub on_duplicate: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicyJson {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+}
+
+impl TryFrom<RetryPolicyJson> for ScheduledJobRetryPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: RetryPolicyJson) -> anyhow::Result<Self> {
+        let invalid_retry =
+            |message: String| ErrorMetadata::bad_request("InvalidScheduleRetry", message);
+        anyhow::ensure!(
+            (1..=MAX_RETRY_ATTEMPTS).contains(&value.max_attempts),
+            invalid_retry(format!(
+                "maxAttempts must be between 1 and {MAX_RETRY_ATTEMPTS}, got {}.",
+                value.max_attempts
+            ))
+        );
+        let initial_backoff_ms = value
+            .initial_backoff_ms
+            .unwrap_or(DEFAULT_RETRY_INITIAL_BACKOFF_MS);
+        let max_backoff_ms = value
+            .max_backoff_ms
+            .unwrap_or(DEFAULT_RETRY_MAX_BACKOFF_MS.max(initial_backoff_ms));
+        anyhow::ensure!(
+            initial_backoff_ms <= max_backoff_ms && max_backoff_ms <= MAX_RETRY_BACKOFF_MS,
+            invalid_retry(format!(
+                "initialBackoffMs ({initial_backoff_ms}) must be at most maxBackoffMs \
+                 ({max_backoff_ms}), which must be at most a day."
+            ))
+        );
+        Ok(Self {
+            max_attempts: value.max_attempts,
+            initial_backoff_ms,
+            max_backoff_ms,
+        })
+    }
+}
+
+impl TryFrom<ScheduleOptionsJson> for ScheduleOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ScheduleOptionsJson) -> anyhow::Result<Self> {
+        let priority = match value.priority {
+            Some(priority) => priority.parse().map_err(|_| {
+                ErrorMetadata::bad_request(
+                    "InvalidSchedulePriority",
+                    format!(
+                        "Invalid priority {priority:?}. Expected \"high\", \"normal\" or \"low\"."
+                    ),
+                )
+            })?,
+            None => ScheduledJobPriority::Normal,
+        };
+        if let Some(queue) = &value.queue {
+            anyhow::ensure!(
+                !queue.is_empty() && queue.len() <= MAX_QUEUE_NAME_LENGTH,
+                ErrorMetadata::bad_request(
+                    "InvalidScheduleQueue",
+                    format!(
+                        "Queue names must be between 1 and {MAX_QUEUE_NAME_LENGTH} characters \
+                         long, got {queue:?}."
+                    ),
+                )
+            );
+        }
+        if let Some(dedupe_key) = &value.dedupe_key {
+            anyhow::ensure!(
+                !dedupe_key.is_empty() && dedupe_key.len() <= MAX_DEDUPE_KEY_LENGTH,
+                ErrorMetadata::bad_request(
+                    "InvalidScheduleDedupeKey",
+                    format!(
+                        "Dedupe keys must be between 1 and {MAX_DEDUPE_KEY_LENGTH} characters \
+                         long, got {dedupe_key:?}."
+                    ),
+                )
+            );
+        }
+        let on_duplicate = match value.on_duplicate {
+            Some(on_duplicate) => {
+                anyhow::ensure!(
+                    value.dedupe_key.is_some(),
+                    ErrorMetadata::bad_request(
+                        "InvalidScheduleOnDuplicate",
+                        "onDuplicate requires a dedupeKey.",
+                    )
+                );
+                on_duplicate.parse().map_err(|_| {
+                    ErrorMetadata::bad_request(
+                        "InvalidScheduleOnDuplicate",
+                        format!(
+                            "Invalid onDuplicate {on_duplicate:?}. Expected \"replace\" or \
+                             \"keep\"."
+                        ),
+                    )
+                })?
+            },
+            None => DuplicateJobBehavior::Replace,
+        };
+        Ok(Self {
+            priority,
+            queue: value.queue,
+            retry: value
+                .retry
+                .map(ScheduledJobRetryPolicy::try_from)
+                .transpose()?,
+            workflow_step: None,
+            dedupe_key: value.dedupe_key,
+            on_duplicate,
+        })
+    }
+}
+
+/// Selects the pending scheduled jobs to cancel in bulk, e.g. with
+/// `ctx.scheduler.cancelAll` or from the dashboard. Jobs must match every
+/// condition that's set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduledJobFilter {
+    pub path: Option<CanonicalizedComponentFunctionPath>,
+    /// Fields the job's arguments must have, with equal values, in Convex's
+    /// JSON format.
+    pub args_match: Option<serde_json::Map<String, JsonValue>>,
+    /// Only jobs that are due at or after this time.
+    pub scheduled_after: Option<Timestamp>,
+    /// Only jobs that are due before this time.
+    pub scheduled_before: Option<Timestamp>,
+}
+
+impl ScheduledJobFilter {
+    /// Builds a filter from the conditions passed to the API, with times in
+    /// milliseconds since the Unix epoch.
+    pub fn new(
+        path: Option<CanonicalizedComponentFunctionPath>,
+        args_match: Option<JsonValue>,
+        scheduled_after_ms: Option<f64>,
+        scheduled_before_ms: Option<f64>,
+    ) -> anyhow::Result<Self> {
+        let invalid_filter = |message: &str| {
+            ErrorMetadata::bad_request("InvalidScheduledJobFilter", message.to_string())
+        };
+        let args_match = match args_match {
+            Some(JsonValue::Object(fields)) => Some(fields),
+            Some(_) => anyhow::bail!(invalid_filter("argsMatch must be an object")),
+            None => None,
+        };
+        let to_timestamp = |ms: f64| -> anyhow::Result<Timestamp> {
+            // The latest time a JavaScript `Date` can represent.
+            anyhow::ensure!(
+                (0.0..=8.64e15).contains(&ms),
+                invalid_filter("Scheduled times must be valid dates")
+            );
+            UnixTimestamp::from_secs_f64(ms / 1000.0)
+                .as_system_time()
+                .try_into()
+        };
+        Ok(Self {
+            path,
+            args_match,
+            scheduled_after: scheduled_after_ms.map(to_timestamp).transpose()?,
+            scheduled_before: scheduled_before_ms.map(to_timestamp).transpose()?,
+        })
+    }
+
+    /// Whether the job's arguments have the fields in `args_match`. The other
+    /// conditions are checked with index ranges when querying for jobs.
+    pub fn matches_args(&self, job: &ScheduledJob) -> anyhow::Result<bool> {
+        let Some(args_match) = &self.args_match else {
+            return Ok(true);
+        };
+        let args_json: JsonValue = serde_json::from_slice(&job.udf_args_bytes)?;
+        let Some(JsonValue::Object(args)) = args_json.get(0) else {
+            return Ok(args_match.is_empty());
+        };
+        Ok(args_match
+            .iter()
+            .all(|(field, value)| args.get(field) == Some(value)))
+    }
 }
 
 fn args_to_bytes(args: ConvexArray) -> anyhow::Result<ByteBuf> {
@@ -65,6 +403,8 @@ impl ScheduledJob {
         completed_ts: Option<Timestamp>,
         original_scheduled_ts: Timestamp,
         attempts: ScheduledJobAttempts,
+        traceparent: Option<String>,
+        options: ScheduleOptions,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             path,
@@ -74,6 +414,12 @@ impl ScheduledJob {
             completed_ts,
             original_scheduled_ts,
             attempts,
+            traceparent,
+            priority: options.priority,
+            queue: options.queue,
+            retry_policy: options.retry,
+            workflow_step: options.workflow_step,
+            dedupe_key: options.dedupe_key,
         })
     }
 
@@ -82,6 +428,18 @@ impl ScheduledJob {
         let args = args_json.try_into()?;
         Ok(args)
     }
+
+    /// The options the job was scheduled with, e.g. to schedule it again.
+    pub fn options(&self) -> ScheduleOptions {
+        ScheduleOptions {
+            priority: self.priority,
+            queue: self.queue.clone(),
+            retry: self.retry_policy,
+            workflow_step: self.workflow_step,
+            dedupe_key: self.dedupe_key.clone(),
+            on_duplicate: DuplicateJobBehavior::default(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -97,6 +455,52 @@ struct SerializedScheduledJob {
     completed_ts: Option<i64>,
     original_scheduled_ts: Option<i64>,
     attempts: Option<ScheduledJobAttempts>,
+    traceparent: Option<String>,
+    // Only set for priorities other than normal, so normal jobs and jobs from
+    // before priorities existed share a key in the `by_priority_and_next_ts`
+    // index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry: Option<SerializedRetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workflow_step: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dedupe_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedRetryPolicy {
+    max_attempts: i64,
+    initial_backoff_ms: i64,
+    max_backoff_ms: i64,
+}
+
+impl TryFrom<ScheduledJobRetryPolicy> for SerializedRetryPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(policy: ScheduledJobRetryPolicy) -> anyhow::Result<Self> {
+        Ok(Self {
+            max_attempts: policy.max_attempts.into(),
+            initial_backoff_ms: policy.initial_backoff_ms.try_into()?,
+            max_backoff_ms: policy.max_backoff_ms.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedRetryPolicy> for ScheduledJobRetryPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedRetryPolicy) -> anyhow::Result<Self> {
+        Ok(Self {
+            max_attempts: value.max_attempts.try_into()?,
+            initial_backoff_ms: value.initial_backoff_ms.try_into()?,
+            max_backoff_ms: value.max_backoff_ms.try_into()?,
+        })
+    }
 }
 
 impl TryFrom<ScheduledJob> for SerializedScheduledJob {
@@ -112,6 +516,13 @@ impl TryFrom<ScheduledJob> for SerializedScheduledJob {
             completed_ts: job.completed_ts.map(|ts| ts.into()),
             original_scheduled_ts: Some(job.original_scheduled_ts.into()),
             attempts: Some(job.attempts),
+            traceparent: job.traceparent,
+            priority: (job.priority != ScheduledJobPriority::Normal)
+                .then(|| job.priority.to_string()),
+            queue: job.queue,
+            retry: job.retry_policy.map(TryFrom::try_from).transpose()?,
+            workflow_step: job.workflow_step.map(|id| id.encode()),
+            dedupe_key: job.dedupe_key,
         })
     }
 }
@@ -154,6 +565,19 @@ impl TryFrom<SerializedScheduledJob> for ScheduledJob {
             completed_ts,
             original_scheduled_ts,
             attempts: value.attempts.unwrap_or_default(),
+            traceparent: value.traceparent,
+            priority: value
+                .priority
+                .map(|p| p.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            queue: value.queue,
+            retry_policy: value.retry.map(TryFrom::try_from).transpose()?,
+            workflow_step: value
+                .workflow_step
+                .map(|id| DeveloperDocumentId::decode(&id))
+                .transpose()?,
+            dedupe_key: value.dedupe_key,
         })
     }
 }
@@ -164,9 +588,15 @@ impl TryFrom<SerializedScheduledJob> for ScheduledJob {
 pub struct ScheduledJobAttempts {
     pub system_errors: u32,
     pub occ_errors: u32,
+    /// Attempts that failed with an error from the function, which are only
+    /// retried with a `ScheduledJobRetryPolicy`.
+    #[serde(default)]
+    pub user_errors: u32,
 }
 
 impl ScheduledJobAttempts {
+    /// Counts the system errors the scheduler retries on its own, which don't
+    /// include `user_errors`.
     pub fn count_failures(&self) -> u32 {
         self.system_errors + self.occ_errors
     }
@@ -236,6 +666,209 @@ impl TryFrom<SerializedScheduledJobState> for ScheduledJobState {
 
 codegen_convex_serialization!(ScheduledJob, SerializedScheduledJob);
 
+/// A job that failed on its last attempt, kept in the dead letter table with
+/// its arguments and error so it can be inspected and run again.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ScheduledJobDeadLetter {
+    /// The job's `_scheduled_jobs` document, which is garbage collected
+    /// before the dead letter is.
+    pub job_id: DeveloperDocumentId,
+    pub path: CanonicalizedComponentFunctionPath,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(
+            strategy = "proptest::arbitrary::any_with::<ConvexArray>((0..4).into()).\
+                        prop_map(args_to_bytes).prop_filter_map(\"invalid json\", |b| b.ok())"
+        )
+    )]
+    pub udf_args_bytes: ByteBuf,
+    pub error: String,
+    /// How many times the job ran, including the last one.
+    pub attempts: u32,
+    pub original_scheduled_ts: Timestamp,
+    pub failed_ts: Timestamp,
+
+    pub priority: ScheduledJobPriority,
+    pub queue: Option<String>,
+    pub retry_policy: Option<ScheduledJobRetryPolicy>,
+}
+
+impl ScheduledJobDeadLetter {
+    pub fn new(
+        job_id: DeveloperDocumentId,
+        job: ScheduledJob,
+        error: String,
+        failed_ts: Timestamp,
+    ) -> Self {
+        Self {
+            job_id,
+            path: job.path,
+            udf_args_bytes: job.udf_args_bytes,
+            error,
+            attempts: job.attempts.user_errors,
+            original_scheduled_ts: job.original_scheduled_ts,
+            failed_ts,
+            priority: job.priority,
+            queue: job.queue,
+            retry_policy: job.retry_policy,
+        }
+    }
+
+    pub fn udf_args(&self) -> anyhow::Result<ConvexArray> {
+        let args_json: JsonValue = serde_json::from_slice(&self.udf_args_bytes)?;
+        let args = args_json.try_into()?;
+        Ok(args)
+    }
+
+    pub fn options(&self) -> ScheduleOptions {
+        ScheduleOptions {
+            priority: self.priority,
+            queue: self.queue.clone(),
+            retry: self.retry_policy,
+            // The workflow step already failed with the job.
+            workflow_step: None,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedScheduledJobDeadLetter {
+    job_id: String,
+    component: String,
+    udf_path: String,
+    udf_args: ByteBuf,
+    error: String,
+    attempts: i64,
+    original_scheduled_ts: i64,
+    failed_ts: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry: Option<SerializedRetryPolicy>,
+}
+
+impl TryFrom<ScheduledJobDeadLetter> for SerializedScheduledJobDeadLetter {
+    type Error = anyhow::Error;
+
+    fn try_from(dead_letter: ScheduledJobDeadLetter) -> anyhow::Result<Self> {
+        Ok(Self {
+            job_id: dead_letter.job_id.encode(),
+            component: String::from(dead_letter.path.component),
+            udf_path: String::from(dead_letter.path.udf_path),
+            udf_args: dead_letter.udf_args_bytes,
+            error: dead_letter.error,
+            attempts: dead_letter.attempts.into(),
+            original_scheduled_ts: dead_letter.original_scheduled_ts.into(),
+            failed_ts: dead_letter.failed_ts.into(),
+            priority: (dead_letter.priority != ScheduledJobPriority::Normal)
+                .then(|| dead_letter.priority.to_string()),
+            queue: dead_letter.queue,
+            retry: dead_letter
+                .retry_policy
+                .map(TryFrom::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedScheduledJobDeadLetter> for ScheduledJobDeadLetter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedScheduledJobDeadLetter) -> anyhow::Result<Self> {
+        Ok(Self {
+            job_id: DeveloperDocumentId::decode(&value.job_id)?,
+            path: CanonicalizedComponentFunctionPath {
+                component: value.component.parse()?,
+                udf_path: value.udf_path.parse()?,
+            },
+            udf_args_bytes: value.udf_args,
+            error: value.error,
+            attempts: value.attempts.try_into()?,
+            original_scheduled_ts: value.original_scheduled_ts.try_into()?,
+            failed_ts: value.failed_ts.try_into()?,
+            priority: value
+                .priority
+                .map(|p| p.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            queue: value.queue,
+            retry_policy: value.retry.map(TryFrom::try_from).transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(ScheduledJobDeadLetter, SerializedScheduledJobDeadLetter);
+
+/// A pause of scheduled job and cron job execution, set from the admin API
+/// during incidents. Jobs of paused functions stay pending, and cron jobs of
+/// paused functions are skipped, until the pause is lifted. Jobs that are
+/// already running when the pause is set run to completion.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct SchedulerPause {
+    /// The function whose jobs are paused, or `None` to pause all jobs.
+    pub path: Option<CanonicalizedComponentFunctionPath>,
+    /// Why the jobs were paused, for whoever lifts the pause.
+    pub reason: Option<String>,
+    pub paused_ts: Timestamp,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedSchedulerPause {
+    component: Option<String>,
+    udf_path: Option<String>,
+    reason: Option<String>,
+    paused_ts: i64,
+}
+
+impl TryFrom<SchedulerPause> for SerializedSchedulerPause {
+    type Error = anyhow::Error;
+
+    fn try_from(pause: SchedulerPause) -> anyhow::Result<Self> {
+        let (component, udf_path) = match pause.path {
+            Some(path) => (
+                Some(String::from(path.component)),
+                Some(String::from(path.udf_path)),
+            ),
+            None => (None, None),
+        };
+        Ok(Self {
+            component,
+            udf_path,
+            reason: pause.reason,
+            paused_ts: pause.paused_ts.into(),
+        })
+    }
+}
+
+impl TryFrom<SerializedSchedulerPause> for SchedulerPause {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedSchedulerPause) -> anyhow::Result<Self> {
+        let path = match (value.component, value.udf_path) {
+            (Some(component), Some(udf_path)) => Some(CanonicalizedComponentFunctionPath {
+                component: component.parse()?,
+                udf_path: udf_path.parse()?,
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("Scheduler pause has only one of component and udfPath"),
+        };
+        Ok(Self {
+            path,
+            reason: value.reason,
+            paused_ts: value.paused_ts.try_into()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(SchedulerPause, SerializedSchedulerPause);
+
 mod state {
     use value::codegen_convex_serialization;
 
@@ -0,0 +1,102 @@
+//! Service accounts that can exchange their credential for access tokens.
+//! Deleting an account stops its tokens from working.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    service_accounts::types::ServiceAccount,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static SERVICE_ACCOUNTS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_service_accounts"
+        .parse()
+        .expect("Invalid built-in service accounts table")
+});
+
+pub struct ServiceAccountsTable;
+impl SystemTable for ServiceAccountsTable {
+    fn table_name(&self) -> &'static TableName {
+        &SERVICE_ACCOUNTS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<ServiceAccount>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct ServiceAccountsModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> ServiceAccountsModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<ServiceAccount>>> {
+        let query = Query::full_table_scan(SERVICE_ACCOUNTS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut accounts = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            accounts.push(doc.try_into()?);
+        }
+        Ok(accounts)
+    }
+
+    pub async fn get(
+        &mut self,
+        id: &str,
+    ) -> anyhow::Result<Option<ParsedDocument<ServiceAccount>>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|account| account.id().developer_id.encode() == id))
+    }
+
+    pub async fn insert(&mut self, account: ServiceAccount) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&SERVICE_ACCOUNTS_TABLE, account.try_into()?)
+            .await
+    }
+
+    /// Deletes the account with the given id, returning whether it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(account) = self.get(id).await? else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(account.id())
+            .await?;
+        Ok(true)
+    }
+}
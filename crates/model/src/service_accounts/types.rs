@@ -0,0 +1,90 @@
+use keybroker::AdminRole;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::{
+    codegen_convex_serialization,
+    sha256::Sha256Digest,
+};
+
+/// A non-human client, like a CI job, that exchanges its credential for
+/// short-lived access tokens instead of holding a long-lived admin key.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ServiceAccount {
+    pub name: String,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "crate::admin_roles::types::admin_role_strategy()")
+    )]
+    pub role: AdminRole,
+    pub credential: ServiceAccountCredential,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum ServiceAccountCredential {
+    /// A secret generated when the account is created. Only its hash is kept.
+    ClientSecret { sha256: Sha256Digest },
+    /// The public half of a key pair as a JWK. The account proves it holds the
+    /// private key by signing a client assertion with it.
+    PublicKey { jwk: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedServiceAccount {
+    name: String,
+    role: String,
+    credential: SerializedServiceAccountCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SerializedServiceAccountCredential {
+    ClientSecret { sha256: String },
+    PublicKey { jwk: String },
+}
+
+impl From<ServiceAccount> for SerializedServiceAccount {
+    fn from(account: ServiceAccount) -> Self {
+        Self {
+            name: account.name,
+            role: account.role.to_string(),
+            credential: match account.credential {
+                ServiceAccountCredential::ClientSecret { sha256 } => {
+                    SerializedServiceAccountCredential::ClientSecret {
+                        sha256: sha256.as_base64(),
+                    }
+                },
+                ServiceAccountCredential::PublicKey { jwk } => {
+                    SerializedServiceAccountCredential::PublicKey { jwk }
+                },
+            },
+        }
+    }
+}
+
+impl TryFrom<SerializedServiceAccount> for ServiceAccount {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedServiceAccount) -> anyhow::Result<Self> {
+        Ok(Self {
+            name: value.name,
+            role: value.role.parse()?,
+            credential: match value.credential {
+                SerializedServiceAccountCredential::ClientSecret { sha256 } => {
+                    ServiceAccountCredential::ClientSecret {
+                        sha256: Sha256Digest::from_base64(&sha256)?,
+                    }
+                },
+                SerializedServiceAccountCredential::PublicKey { jwk } => {
+                    ServiceAccountCredential::PublicKey { jwk }
+                },
+            },
+        })
+    }
+}
+
+codegen_convex_serialization!(ServiceAccount, SerializedServiceAccount);
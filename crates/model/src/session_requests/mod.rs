@@ -29,6 +29,7 @@ use sync_types::Timestamp;
 use value::{
     ConvexValue,
     FieldPath,
+    ResolvedDocumentId,
     TableName,
     TableNamespace,
 };
@@ -37,7 +38,6 @@ pub mod types;
 
 use types::{
     SessionRequestIdentifier,
-    SessionRequestOutcome,
     SessionRequestRecord,
 };
 
@@ -96,7 +96,7 @@ impl<'a, RT: Runtime> SessionRequestModel<'a, RT> {
         &mut self,
         request_identifier: &SessionRequestIdentifier,
         identity: Identity,
-    ) -> anyhow::Result<Option<(Timestamp, SessionRequestOutcome)>> {
+    ) -> anyhow::Result<Option<(Timestamp, ParsedDocument<SessionRequestRecord>)>> {
         // We only expect this function to be called by the framework as part
         // of a mutation UDF. We require passing in a system identity to confirm
         // that the caller isn't letting a user call this directly.
@@ -145,9 +145,7 @@ impl<'a, RT: Runtime> SessionRequestModel<'a, RT> {
             (doc.try_into()?, ts)
         };
 
-        let outcome = doc.into_value().outcome;
-
-        Ok(Some((ts, outcome)))
+        Ok(Some((ts, doc)))
     }
 
     pub async fn record_session_request(
@@ -168,4 +166,18 @@ impl<'a, RT: Runtime> SessionRequestModel<'a, RT> {
             .await?;
         Ok(())
     }
+
+    /// Deletes a record whose result should no longer be replayed, so the
+    /// request can run and be recorded again.
+    pub async fn delete_session_request(
+        &mut self,
+        id: ResolvedDocumentId,
+        identity: Identity,
+    ) -> anyhow::Result<()> {
+        if !identity.is_system() {
+            anyhow::bail!(unauthorized_error("delete_session_request"))
+        }
+        SystemMetadataModel::new_global(self.tx).delete(id).await?;
+        Ok(())
+    }
 }
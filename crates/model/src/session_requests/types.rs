@@ -29,6 +29,11 @@ use value::ConvexObject;
 pub struct SessionRequestIdentifier {
     pub session_id: SessionId,
     pub request_id: SessionRequestSeqNumber,
+    /// For mutations called over HTTP with an `Idempotency-Key`, a hash of
+    /// the mutation's arguments. Replaying a recorded result requires the
+    /// arguments to match, and the result is only replayed for
+    /// `IDEMPOTENCY_KEY_WINDOW`.
+    pub args_hash: Option<String>,
 }
 
 /// Information for a single session request
@@ -46,18 +51,25 @@ pub struct SessionRequestRecord {
     /// Non-permission-granting representation of the identity input to the
     /// mutation.
     pub identity: InertIdentity,
+
+    /// See `SessionRequestIdentifier::args_hash`.
+    pub args_hash: Option<String>,
 }
 
 impl TryFrom<SessionRequestRecord> for ConvexObject {
     type Error = anyhow::Error;
 
     fn try_from(request: SessionRequestRecord) -> anyhow::Result<Self> {
-        obj!(
+        let mut object = obj!(
             "sessionId" => request.session_id.to_string(),
             "requestId" => (request.request_id as i64),
             "outcome" =>  ConvexValue::Object(request.outcome.try_into()?),
             "identity" => request.identity.to_string(),
-        )
+        )?;
+        if let Some(args_hash) = request.args_hash {
+            object = object.shallow_merge(obj!("argsHash" => args_hash)?)?;
+        }
+        Ok(object)
     }
 }
 
@@ -83,12 +95,18 @@ impl TryFrom<ConvexObject> for SessionRequestRecord {
             Some(ConvexValue::String(s)) => s.to_string().parse()?,
             v => anyhow::bail!("Invalid identity field for SessionRequest: {:?}", v),
         };
+        let args_hash = match fields.remove("argsHash") {
+            Some(ConvexValue::String(s)) => Some(s.to_string()),
+            None => None,
+            v => anyhow::bail!("Invalid argsHash field for SessionRequest: {:?}", v),
+        };
 
         Ok(SessionRequestRecord {
             session_id,
             request_id,
             outcome,
             identity,
+            args_hash,
         })
     }
 }
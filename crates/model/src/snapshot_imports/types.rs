@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use common::{
     components::ComponentPath,
     types::{
@@ -14,6 +16,8 @@ use serde::{
 use sync_types::Timestamp;
 use value::{
     codegen_convex_serialization,
+    FieldName,
+    IdentifierFieldName,
     TabletId,
 };
 
@@ -101,30 +105,179 @@ codegen_convex_serialization!(SnapshotImport, SerializedSnapshotImport);
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub enum ImportFormat {
-    Csv(TableName),
+    Csv(TableName, ImportColumnTypes),
     JsonLines(TableName),
     JsonArray(TableName),
     Zip,
+    /// A collection's `.bson` file from `mongodump`, optionally gzipped.
+    MongoBson(TableName, MongoIdMapping),
+    /// A `mongodump --archive` of a database, optionally gzipped. Each
+    /// collection is imported into the table with the same name.
+    MongoArchive(MongoIdMapping),
+    /// The rows a `pg_dump` plain-text dump has for a table, in its
+    /// `COPY ... FROM stdin` block.
+    PgDump(TableName, ImportColumnTypes),
+}
+
+/// How to convert the text cells of a CSV file or Postgres dump to values.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum ImportColumnTypes {
+    /// Cells that are numbers are imported as float64s, and other cells as
+    /// strings.
+    Guess,
+    /// The type of each column, supplied by the user. Every column must have
+    /// one.
+    Explicit(BTreeMap<FieldName, ImportColumnType>),
+    /// Infer the type of each column from all of its cells before importing,
+    /// so a column's values all have the same type.
+    Infer,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct ImportColumnType {
+    pub value_type: ImportValueType,
+    /// Whether empty cells, or `\N` in Postgres dumps, are imported as null.
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, strum::EnumString, strum::Display)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+#[strum(serialize_all = "camelCase")]
+pub enum ImportValueType {
+    String,
+    Float64,
+    Int64,
+    /// `true`, `false`, or Postgres's `t` and `f`.
+    Boolean,
+    /// Base64, or hex with a `\x` prefix like Postgres's `bytea` output.
+    Bytes,
+    /// JSON, like a Postgres `json` column. Numbers are imported as float64s.
+    Json,
+}
+
+impl ImportColumnTypes {
+    fn serialize(self) -> Option<SerializedImportColumnTypes> {
+        match self {
+            ImportColumnTypes::Guess => None,
+            ImportColumnTypes::Explicit(columns) => Some(SerializedImportColumnTypes::Explicit {
+                columns: columns
+                    .into_iter()
+                    .map(|(name, column_type)| SerializedImportColumn {
+                        name: name.to_string(),
+                        value_type: column_type.value_type.to_string(),
+                        nullable: column_type.nullable,
+                    })
+                    .collect(),
+            }),
+            ImportColumnTypes::Infer => Some(SerializedImportColumnTypes::Infer),
+        }
+    }
+
+    fn deserialize(column_types: Option<SerializedImportColumnTypes>) -> anyhow::Result<Self> {
+        Ok(match column_types {
+            None => ImportColumnTypes::Guess,
+            Some(SerializedImportColumnTypes::Explicit { columns }) => ImportColumnTypes::Explicit(
+                columns
+                    .into_iter()
+                    .map(|column| {
+                        let column_type = ImportColumnType {
+                            value_type: column.value_type.parse()?,
+                            nullable: column.nullable,
+                        };
+                        anyhow::Ok((column.name.parse()?, column_type))
+                    })
+                    .try_collect()?,
+            ),
+            Some(SerializedImportColumnTypes::Infer) => ImportColumnTypes::Infer,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode")]
+pub enum SerializedImportColumnTypes {
+    #[serde(rename = "explicit")]
+    Explicit {
+        columns: Vec<SerializedImportColumn>,
+    },
+    #[serde(rename = "infer")]
+    Infer,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SerializedImportColumn {
+    name: String,
+    #[serde(rename = "type")]
+    value_type: String,
+    nullable: bool,
+}
+
+/// What to do with the `_id`s of imported MongoDB documents, since Convex
+/// assigns its own document IDs.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum MongoIdMapping {
+    /// Drop the `_id`s.
+    Discard,
+    /// Keep each `_id` in this field, like `mongoId`. ObjectIds are kept as
+    /// their hex strings, like the ObjectIds in other fields.
+    Field(IdentifierFieldName),
+}
+
+impl MongoIdMapping {
+    fn serialize(self) -> Option<String> {
+        match self {
+            MongoIdMapping::Discard => None,
+            MongoIdMapping::Field(field) => Some(field.into()),
+        }
+    }
+
+    fn deserialize(id_field: Option<String>) -> anyhow::Result<Self> {
+        Ok(match id_field {
+            None => MongoIdMapping::Discard,
+            Some(field) => MongoIdMapping::Field(field.parse()?),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "format")]
 pub enum SerializedImportFormat {
     #[serde(rename = "csv")]
-    Csv { table: String },
+    Csv {
+        table: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        column_types: Option<SerializedImportColumnTypes>,
+    },
     #[serde(rename = "jsonl")]
     JsonLines { table: String },
     #[serde(rename = "json_array")]
     JsonArray { table: String },
     #[serde(rename = "zip")]
     Zip,
+    #[serde(rename = "mongo_bson")]
+    MongoBson {
+        table: String,
+        id_field: Option<String>,
+    },
+    #[serde(rename = "mongo_archive")]
+    MongoArchive { id_field: Option<String> },
+    #[serde(rename = "pg_dump")]
+    PgDump {
+        table: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        column_types: Option<SerializedImportColumnTypes>,
+    },
 }
 
 impl From<ImportFormat> for SerializedImportFormat {
     fn from(format: ImportFormat) -> SerializedImportFormat {
         match format {
-            ImportFormat::Csv(table) => SerializedImportFormat::Csv {
+            ImportFormat::Csv(table, column_types) => SerializedImportFormat::Csv {
                 table: table.to_string(),
+                column_types: column_types.serialize(),
             },
             ImportFormat::JsonLines(table) => SerializedImportFormat::JsonLines {
                 table: table.to_string(),
@@ -133,6 +286,17 @@ impl From<ImportFormat> for SerializedImportFormat {
                 table: table.to_string(),
             },
             ImportFormat::Zip => SerializedImportFormat::Zip,
+            ImportFormat::MongoBson(table, id_mapping) => SerializedImportFormat::MongoBson {
+                table: table.to_string(),
+                id_field: id_mapping.serialize(),
+            },
+            ImportFormat::MongoArchive(id_mapping) => SerializedImportFormat::MongoArchive {
+                id_field: id_mapping.serialize(),
+            },
+            ImportFormat::PgDump(table, column_types) => SerializedImportFormat::PgDump {
+                table: table.to_string(),
+                column_types: column_types.serialize(),
+            },
         }
     }
 }
@@ -142,7 +306,13 @@ impl TryFrom<SerializedImportFormat> for ImportFormat {
 
     fn try_from(format: SerializedImportFormat) -> anyhow::Result<ImportFormat> {
         match format {
-            SerializedImportFormat::Csv { table } => Ok(ImportFormat::Csv(table.parse()?)),
+            SerializedImportFormat::Csv {
+                table,
+                column_types,
+            } => Ok(ImportFormat::Csv(
+                table.parse()?,
+                ImportColumnTypes::deserialize(column_types)?,
+            )),
             SerializedImportFormat::JsonLines { table } => {
                 Ok(ImportFormat::JsonLines(table.parse()?))
             },
@@ -150,6 +320,20 @@ impl TryFrom<SerializedImportFormat> for ImportFormat {
                 Ok(ImportFormat::JsonArray(table.parse()?))
             },
             SerializedImportFormat::Zip => Ok(ImportFormat::Zip),
+            SerializedImportFormat::MongoBson { table, id_field } => Ok(ImportFormat::MongoBson(
+                table.parse()?,
+                MongoIdMapping::deserialize(id_field)?,
+            )),
+            SerializedImportFormat::MongoArchive { id_field } => Ok(ImportFormat::MongoArchive(
+                MongoIdMapping::deserialize(id_field)?,
+            )),
+            SerializedImportFormat::PgDump {
+                table,
+                column_types,
+            } => Ok(ImportFormat::PgDump(
+                table.parse()?,
+                ImportColumnTypes::deserialize(column_types)?,
+            )),
         }
     }
 }
@@ -5,7 +5,10 @@ use std::{
 
 use common::{
     obj,
-    types::ObjectKey,
+    types::{
+        NodeVersion,
+        ObjectKey,
+    },
 };
 use errors::ErrorMetadata;
 use humansize::{
@@ -20,7 +23,10 @@ use value::{
     ConvexValue,
 };
 
-use crate::external_packages::types::ExternalDepsPackageId;
+use crate::{
+    dependency_layers::types::DependencyLayer,
+    external_packages::types::ExternalDepsPackageId,
+};
 
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -29,6 +35,12 @@ pub struct SourcePackage {
     pub sha256: Sha256Digest,
     pub external_deps_package_id: Option<ExternalDepsPackageId>,
     pub package_size: PackageSize,
+    /// Node.js version the package's "use node" actions run on, if the push
+    /// picked one.
+    pub node_version: Option<NodeVersion>,
+    /// Prebuilt dependency layers the package's "use node" actions run with,
+    /// as they were when the package was pushed.
+    pub dependency_layers: Vec<DependencyLayer>,
 }
 
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
@@ -174,6 +186,8 @@ impl TryFrom<SourcePackage> for ConvexObject {
             sha256,
             external_deps_package_id,
             package_size,
+            node_version,
+            dependency_layers,
         }: SourcePackage,
     ) -> Result<Self, Self::Error> {
         let storage_key: String = storage_key.into();
@@ -185,6 +199,17 @@ impl TryFrom<SourcePackage> for ConvexObject {
                 .transpose()?
                 .unwrap_or(ConvexValue::Null),
             "packageSize" => ConvexValue::Object(package_size.try_into()?),
+            "nodeVersion" => node_version
+                .map(|v| ConvexValue::try_from(v.to_string()))
+                .transpose()?
+                .unwrap_or(ConvexValue::Null),
+            "dependencyLayers" => ConvexValue::Array(
+                dependency_layers
+                    .into_iter()
+                    .map(|layer| ConvexObject::try_from(layer).map(ConvexValue::Object))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .try_into()?
+            ),
         )
     }
 }
@@ -213,11 +238,29 @@ impl TryFrom<ConvexObject> for SourcePackage {
             None => PackageSize::default(),
             _ => anyhow::bail!("Invalid 'packageSize' in {object_fields:?}"),
         };
+        let node_version = match object_fields.remove("nodeVersion") {
+            Some(ConvexValue::Null) | None => None,
+            Some(ConvexValue::String(s)) => Some(s.parse()?),
+            _ => anyhow::bail!("Invalid 'nodeVersion' in {object_fields:?}"),
+        };
+        let dependency_layers = match object_fields.remove("dependencyLayers") {
+            Some(ConvexValue::Array(layers)) => layers
+                .into_iter()
+                .map(|layer| match layer {
+                    ConvexValue::Object(o) => o.try_into(),
+                    _ => anyhow::bail!("Invalid dependency layer {layer:?}"),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            None => vec![],
+            _ => anyhow::bail!("Invalid 'dependencyLayers' in {object_fields:?}"),
+        };
         Ok(Self {
             storage_key,
             sha256,
             external_deps_package_id: external_package_id,
             package_size,
+            node_version,
+            dependency_layers,
         })
     }
 }
@@ -0,0 +1,99 @@
+//! Revoked user tokens, which are rejected before they expire. The
+//! application keeps them in memory so they can be checked on every request.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    token_revocations::types::TokenRevocation,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static TOKEN_REVOCATIONS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_token_revocations"
+        .parse()
+        .expect("Invalid built-in token revocations table")
+});
+
+pub struct TokenRevocationsTable;
+impl SystemTable for TokenRevocationsTable {
+    fn table_name(&self) -> &'static TableName {
+        &TOKEN_REVOCATIONS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<TokenRevocation>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct TokenRevocationsModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> TokenRevocationsModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<TokenRevocation>>> {
+        let query = Query::full_table_scan(TOKEN_REVOCATIONS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut revocations = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            revocations.push(doc.try_into()?);
+        }
+        Ok(revocations)
+    }
+
+    pub async fn insert(
+        &mut self,
+        revocation: TokenRevocation,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        SystemMetadataModel::new_global(self.tx)
+            .insert(&TOKEN_REVOCATIONS_TABLE, revocation.try_into()?)
+            .await
+    }
+
+    /// Deletes the revocation with the given id, returning whether it existed.
+    pub async fn delete(&mut self, id: &str) -> anyhow::Result<bool> {
+        let Some(revocation) = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|revocation| revocation.id().developer_id.encode() == id)
+        else {
+            return Ok(false);
+        };
+        SystemMetadataModel::new_global(self.tx)
+            .delete(revocation.id())
+            .await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct TokenRevocation {
+    pub revoked: RevokedTokens,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "(0..1u64 << 40).prop_map(|secs| SystemTime::UNIX_EPOCH + \
+                             Duration::from_secs(secs))")
+    )]
+    pub revoked_at: SystemTime,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum RevokedTokens {
+    /// The token with this `jti` claim.
+    TokenId(String),
+    /// A user's tokens issued before the revocation, by their token
+    /// identifier, `<issuer>|<subject>`.
+    User(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedTokenRevocation {
+    revoked: SerializedRevokedTokens,
+    revoked_at_secs: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SerializedRevokedTokens {
+    #[serde(rename_all = "camelCase")]
+    TokenId { token_id: String },
+    #[serde(rename_all = "camelCase")]
+    User { token_identifier: String },
+}
+
+impl TryFrom<TokenRevocation> for SerializedTokenRevocation {
+    type Error = anyhow::Error;
+
+    fn try_from(revocation: TokenRevocation) -> anyhow::Result<Self> {
+        Ok(Self {
+            revoked: match revocation.revoked {
+                RevokedTokens::TokenId(token_id) => SerializedRevokedTokens::TokenId { token_id },
+                RevokedTokens::User(token_identifier) => {
+                    SerializedRevokedTokens::User { token_identifier }
+                },
+            },
+            revoked_at_secs: revocation
+                .revoked_at
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs()
+                .try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedTokenRevocation> for TokenRevocation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedTokenRevocation) -> anyhow::Result<Self> {
+        Ok(Self {
+            revoked: match value.revoked {
+                SerializedRevokedTokens::TokenId { token_id } => RevokedTokens::TokenId(token_id),
+                SerializedRevokedTokens::User { token_identifier } => {
+                    RevokedTokens::User(token_identifier)
+                },
+            },
+            revoked_at: SystemTime::UNIX_EPOCH
+                + Duration::from_secs(value.revoked_at_secs.try_into()?),
+        })
+    }
+}
+
+codegen_convex_serialization!(TokenRevocation, SerializedTokenRevocation);
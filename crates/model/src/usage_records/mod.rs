@@ -0,0 +1,204 @@
+//! Hourly totals of the resources each function used, broken down by table
+//! for database and vector index bandwidth, for chargeback and capacity
+//! planning.
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+    time::SystemTime,
+};
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        IndexRange,
+        IndexRangeExpression,
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+    types::IndexName,
+};
+use database::{
+    defaults::system_index,
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    ConvexValue,
+    FieldPath,
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    usage_records::types::{
+        UsageCounts,
+        UsageKey,
+        UsageRecord,
+    },
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static USAGE_RECORDS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_usage_records"
+        .parse()
+        .expect("Invalid built-in usage records table")
+});
+
+pub static USAGE_RECORDS_INDEX_BY_HOUR_START: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&USAGE_RECORDS_TABLE, "by_hour_start"));
+static HOUR_START_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "hourStartMs".parse().expect("invalid hourStartMs field"));
+
+pub struct UsageRecordsTable;
+impl SystemTable for UsageRecordsTable {
+    fn table_name(&self) -> &'static TableName {
+        &USAGE_RECORDS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![SystemIndex {
+            name: USAGE_RECORDS_INDEX_BY_HOUR_START.clone(),
+            fields: vec![HOUR_START_FIELD.clone()].try_into().unwrap(),
+        }]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<UsageRecord>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct UsageRecordsModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> UsageRecordsModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    /// Adds `usage` to the usage already recorded in the hour starting at
+    /// `hour_start`.
+    pub async fn add(
+        &mut self,
+        hour_start: SystemTime,
+        usage: BTreeMap<UsageKey, UsageCounts>,
+    ) -> anyhow::Result<()> {
+        let mut existing: BTreeMap<_, _> = self
+            .list_range(
+                IndexRangeExpression::Eq(
+                    HOUR_START_FIELD.clone(),
+                    hour_start_value(hour_start)?.into(),
+                ),
+                None,
+                usize::MAX,
+            )
+            .await?
+            .into_iter()
+            .map(|record| (record.key.clone(), record))
+            .collect();
+        for (key, counts) in usage {
+            match existing.remove(&key) {
+                Some(record) => {
+                    let (id, mut record) = record.into_id_and_value();
+                    record.counts.add(&counts);
+                    SystemMetadataModel::new_global(self.tx)
+                        .replace(id, record.try_into()?)
+                        .await?;
+                },
+                None => {
+                    let record = UsageRecord {
+                        hour_start,
+                        key,
+                        counts,
+                    };
+                    SystemMetadataModel::new_global(self.tx)
+                        .insert(&USAGE_RECORDS_TABLE, record.try_into()?)
+                        .await?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// The usage recorded in the hours starting in `[start, end)`, oldest
+    /// first.
+    pub async fn list(
+        &mut self,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> anyhow::Result<Vec<UsageRecord>> {
+        let records = self
+            .list_range(
+                IndexRangeExpression::Gte(HOUR_START_FIELD.clone(), hour_start_value(start)?),
+                Some(IndexRangeExpression::Lt(
+                    HOUR_START_FIELD.clone(),
+                    hour_start_value(end)?,
+                )),
+                usize::MAX,
+            )
+            .await?;
+        Ok(records
+            .into_iter()
+            .map(|record| record.into_value())
+            .collect())
+    }
+
+    /// Deletes up to `limit` records of the hours before `cutoff`, returning
+    /// how many were deleted.
+    pub async fn delete_before(
+        &mut self,
+        cutoff: SystemTime,
+        limit: usize,
+    ) -> anyhow::Result<usize> {
+        let records = self
+            .list_range(
+                IndexRangeExpression::Lt(HOUR_START_FIELD.clone(), hour_start_value(cutoff)?),
+                None,
+                limit,
+            )
+            .await?;
+        for record in &records {
+            SystemMetadataModel::new_global(self.tx)
+                .delete(record.id())
+                .await?;
+        }
+        Ok(records.len())
+    }
+
+    async fn list_range(
+        &mut self,
+        start: IndexRangeExpression,
+        end: Option<IndexRangeExpression>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ParsedDocument<UsageRecord>>> {
+        let query = Query::index_range(IndexRange {
+            index_name: USAGE_RECORDS_INDEX_BY_HOUR_START.clone(),
+            range: [start].into_iter().chain(end).collect(),
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut records = vec![];
+        while records.len() < limit
+            && let Some(doc) = query_stream.next(self.tx, None).await?
+        {
+            records.push(doc.try_into()?);
+        }
+        Ok(records)
+    }
+}
+
+fn hour_start_value(hour_start: SystemTime) -> anyhow::Result<ConvexValue> {
+    let millis: i64 = hour_start
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis()
+        .try_into()?;
+    Ok(ConvexValue::Int64(millis))
+}
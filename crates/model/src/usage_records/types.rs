@@ -0,0 +1,188 @@
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use value::codegen_convex_serialization;
+
+/// The usage attributed to a function, or a function's use of a table, in one
+/// hour.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct UsageRecord {
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(
+            strategy = "(0..1u64 << 30).prop_map(|hours| SystemTime::UNIX_EPOCH + \
+                        Duration::from_secs(hours * 3600))"
+        )
+    )]
+    pub hour_start: SystemTime,
+    pub key: UsageKey,
+    pub counts: UsageCounts,
+}
+
+/// What usage is attributed to.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct UsageKey {
+    pub component_path: Option<String>,
+    /// The function that used the resources, or `None` for usage outside of
+    /// functions, like snapshot imports and exports.
+    pub udf_id: Option<String>,
+    /// The table read or written, for database and vector index bandwidth.
+    pub table_name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsageCounts {
+    pub function_calls: u64,
+    pub database_ingress_bytes: u64,
+    pub database_egress_bytes: u64,
+    pub database_egress_rows: u64,
+    pub storage_calls: u64,
+    pub storage_ingress_bytes: u64,
+    pub storage_egress_bytes: u64,
+    /// The memory in megabytes times the duration in milliseconds of action
+    /// runs, summed.
+    pub action_compute_mb_ms: u64,
+    pub vector_ingress_bytes: u64,
+    pub vector_egress_bytes: u64,
+}
+
+impl UsageCounts {
+    pub fn add(&mut self, other: &UsageCounts) {
+        self.function_calls = self.function_calls.saturating_add(other.function_calls);
+        self.database_ingress_bytes = self
+            .database_ingress_bytes
+            .saturating_add(other.database_ingress_bytes);
+        self.database_egress_bytes = self
+            .database_egress_bytes
+            .saturating_add(other.database_egress_bytes);
+        self.database_egress_rows = self
+            .database_egress_rows
+            .saturating_add(other.database_egress_rows);
+        self.storage_calls = self.storage_calls.saturating_add(other.storage_calls);
+        self.storage_ingress_bytes = self
+            .storage_ingress_bytes
+            .saturating_add(other.storage_ingress_bytes);
+        self.storage_egress_bytes = self
+            .storage_egress_bytes
+            .saturating_add(other.storage_egress_bytes);
+        self.action_compute_mb_ms = self
+            .action_compute_mb_ms
+            .saturating_add(other.action_compute_mb_ms);
+        self.vector_ingress_bytes = self
+            .vector_ingress_bytes
+            .saturating_add(other.vector_ingress_bytes);
+        self.vector_egress_bytes = self
+            .vector_egress_bytes
+            .saturating_add(other.vector_egress_bytes);
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Arbitrary for UsageCounts {
+    type Parameters = ();
+
+    type Strategy = impl Strategy<Value = UsageCounts>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        // Counts are stored as int64s.
+        prop::array::uniform10(0..=i64::MAX as u64).prop_map(|counts| UsageCounts {
+            function_calls: counts[0],
+            database_ingress_bytes: counts[1],
+            database_egress_bytes: counts[2],
+            database_egress_rows: counts[3],
+            storage_calls: counts[4],
+            storage_ingress_bytes: counts[5],
+            storage_egress_bytes: counts[6],
+            action_compute_mb_ms: counts[7],
+            vector_ingress_bytes: counts[8],
+            vector_egress_bytes: counts[9],
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedUsageRecord {
+    hour_start_ms: i64,
+    component_path: Option<String>,
+    udf_id: Option<String>,
+    table_name: Option<String>,
+    function_calls: i64,
+    database_ingress_bytes: i64,
+    database_egress_bytes: i64,
+    database_egress_rows: i64,
+    storage_calls: i64,
+    storage_ingress_bytes: i64,
+    storage_egress_bytes: i64,
+    action_compute_mb_ms: i64,
+    vector_ingress_bytes: i64,
+    vector_egress_bytes: i64,
+}
+
+impl TryFrom<UsageRecord> for SerializedUsageRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(record: UsageRecord) -> anyhow::Result<Self> {
+        let counts = record.counts;
+        Ok(Self {
+            hour_start_ms: record
+                .hour_start
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_millis()
+                .try_into()?,
+            component_path: record.key.component_path,
+            udf_id: record.key.udf_id,
+            table_name: record.key.table_name,
+            function_calls: counts.function_calls.try_into()?,
+            database_ingress_bytes: counts.database_ingress_bytes.try_into()?,
+            database_egress_bytes: counts.database_egress_bytes.try_into()?,
+            database_egress_rows: counts.database_egress_rows.try_into()?,
+            storage_calls: counts.storage_calls.try_into()?,
+            storage_ingress_bytes: counts.storage_ingress_bytes.try_into()?,
+            storage_egress_bytes: counts.storage_egress_bytes.try_into()?,
+            action_compute_mb_ms: counts.action_compute_mb_ms.try_into()?,
+            vector_ingress_bytes: counts.vector_ingress_bytes.try_into()?,
+            vector_egress_bytes: counts.vector_egress_bytes.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedUsageRecord> for UsageRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedUsageRecord) -> anyhow::Result<Self> {
+        Ok(Self {
+            hour_start: SystemTime::UNIX_EPOCH
+                + Duration::from_millis(value.hour_start_ms.try_into()?),
+            key: UsageKey {
+                component_path: value.component_path,
+                udf_id: value.udf_id,
+                table_name: value.table_name,
+            },
+            counts: UsageCounts {
+                function_calls: value.function_calls.try_into()?,
+                database_ingress_bytes: value.database_ingress_bytes.try_into()?,
+                database_egress_bytes: value.database_egress_bytes.try_into()?,
+                database_egress_rows: value.database_egress_rows.try_into()?,
+                storage_calls: value.storage_calls.try_into()?,
+                storage_ingress_bytes: value.storage_ingress_bytes.try_into()?,
+                storage_egress_bytes: value.storage_egress_bytes.try_into()?,
+                action_compute_mb_ms: value.action_compute_mb_ms.try_into()?,
+                vector_ingress_bytes: value.vector_ingress_bytes.try_into()?,
+                vector_egress_bytes: value.vector_egress_bytes.try_into()?,
+            },
+        })
+    }
+}
+
+codegen_convex_serialization!(UsageRecord, SerializedUsageRecord);
@@ -0,0 +1,91 @@
+//! Functions to run after each push (and optionally on an interval) so that
+//! isolates, module caches and the query cache are warm before real traffic
+//! arrives. The list is rewritten on every push from the `warmup` section of
+//! the push config.
+use std::sync::LazyLock;
+
+use common::{
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+    },
+    query::{
+        Order,
+        Query,
+    },
+    runtime::Runtime,
+};
+use database::{
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use value::{
+    TableName,
+    TableNamespace,
+};
+
+use crate::{
+    warmup::types::WarmupFunction,
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static WARMUP_FUNCTIONS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_warmup_functions"
+        .parse()
+        .expect("Invalid built-in warmup functions table")
+});
+
+pub struct WarmupFunctionsTable;
+impl SystemTable for WarmupFunctionsTable {
+    fn table_name(&self) -> &'static TableName {
+        &WARMUP_FUNCTIONS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<WarmupFunction>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct WarmupModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+}
+
+impl<'a, RT: Runtime> WarmupModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>) -> Self {
+        Self { tx }
+    }
+
+    pub async fn list(&mut self) -> anyhow::Result<Vec<ParsedDocument<WarmupFunction>>> {
+        let query = Query::full_table_scan(WARMUP_FUNCTIONS_TABLE.clone(), Order::Asc);
+        let mut query_stream = ResolvedQuery::new(self.tx, TableNamespace::Global, query)?;
+        let mut functions = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            functions.push(doc.try_into()?);
+        }
+        Ok(functions)
+    }
+
+    /// Replaces the configured warm-up functions. The new functions always get
+    /// new document ids, which is how the warm-up executor notices a push.
+    pub async fn replace(&mut self, functions: Vec<WarmupFunction>) -> anyhow::Result<()> {
+        for doc in self.list().await? {
+            SystemMetadataModel::new_global(self.tx)
+                .delete(doc.id())
+                .await?;
+        }
+        for function in functions {
+            SystemMetadataModel::new_global(self.tx)
+                .insert(&WARMUP_FUNCTIONS_TABLE, function.try_into()?)
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use common::components::{
+    CanonicalizedComponentFunctionPath,
+    ComponentPath,
+};
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_bytes::ByteBuf;
+use serde_json::Value as JsonValue;
+use value::{
+    codegen_convex_serialization,
+    ConvexArray,
+};
+
+/// A query that is run after each push, and optionally on an interval, so the
+/// first request after a deploy doesn't pay for cold isolates and an empty
+/// query cache.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct WarmupFunction {
+    pub path: CanonicalizedComponentFunctionPath,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(
+            strategy = "proptest::arbitrary::any_with::<ConvexArray>((0..4).into()).\
+                        prop_map(args_to_bytes).prop_filter_map(\"invalid json\", |b| b.ok())"
+        )
+    )]
+    pub udf_args_bytes: ByteBuf,
+    /// How often to rerun the function. If `None`, it only runs after pushes.
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(strategy = "proptest::option::of((1..86400u64).prop_map(Duration::from_secs))")
+    )]
+    pub interval: Option<Duration>,
+}
+
+fn args_to_bytes(args: ConvexArray) -> anyhow::Result<ByteBuf> {
+    let args_json = JsonValue::from(args);
+    let args_bytes = serde_json::to_vec(&args_json)?;
+    Ok(ByteBuf::from(args_bytes))
+}
+
+impl WarmupFunction {
+    pub fn new(
+        path: CanonicalizedComponentFunctionPath,
+        udf_args: ConvexArray,
+        interval: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            path,
+            udf_args_bytes: args_to_bytes(udf_args)?,
+            interval,
+        })
+    }
+
+    pub fn udf_args(&self) -> anyhow::Result<ConvexArray> {
+        let args_json: JsonValue = serde_json::from_slice(&self.udf_args_bytes)?;
+        let args = args_json.try_into()?;
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedWarmupFunction {
+    component: String,
+    udf_path: String,
+    // Serialize the udf arguments as binary since we restrict what
+    // field names can be used in a `Document`'s top-level object.
+    udf_args: ByteBuf,
+    interval_secs: Option<i64>,
+}
+
+impl TryFrom<WarmupFunction> for SerializedWarmupFunction {
+    type Error = anyhow::Error;
+
+    fn try_from(function: WarmupFunction) -> anyhow::Result<Self> {
+        Ok(Self {
+            component: String::from(function.path.component),
+            udf_path: String::from(function.path.udf_path),
+            udf_args: function.udf_args_bytes,
+            interval_secs: function
+                .interval
+                .map(|interval| interval.as_secs().try_into())
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<SerializedWarmupFunction> for WarmupFunction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedWarmupFunction) -> anyhow::Result<Self> {
+        let component: ComponentPath = value.component.parse()?;
+        Ok(Self {
+            path: CanonicalizedComponentFunctionPath {
+                component,
+                udf_path: value.udf_path.parse()?,
+            },
+            udf_args_bytes: value.udf_args,
+            interval: value
+                .interval_secs
+                .map(|secs| anyhow::Ok(Duration::from_secs(secs.try_into()?)))
+                .transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(WarmupFunction, SerializedWarmupFunction);
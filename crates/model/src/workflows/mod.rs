@@ -0,0 +1,733 @@
+//! Durable workflows, which compose queries, mutations, actions and sleeps
+//! into a sequence of steps that survives restarts and failures.
+//!
+//! A workflow is defined by a mutation that replays the workflow's journal of
+//! completed steps and then either starts the next step or finishes the
+//! workflow. Mutation and action steps run as scheduled jobs, which record
+//! their results in the journal and schedule the workflow's mutation to run
+//! again. Queries run inside the workflow's mutation and sleeps schedule it to
+//! run again later.
+use std::{
+    sync::LazyLock,
+    time::Duration,
+};
+
+use anyhow::Context;
+use common::{
+    components::CanonicalizedComponentFunctionPath,
+    document::{
+        ParsedDocument,
+        ResolvedDocument,
+        CREATION_TIME_FIELD_PATH,
+    },
+    execution_context::{
+        ExecutionContext,
+        ExecutionId,
+    },
+    query::{
+        IndexRange,
+        IndexRangeExpression,
+        Order,
+        Query,
+    },
+    runtime::{
+        Runtime,
+        UnixTimestamp,
+    },
+    types::{
+        IndexName,
+        Timestamp,
+    },
+    RequestId,
+};
+use database::{
+    defaults::system_index,
+    ResolvedQuery,
+    SystemMetadataModel,
+    Transaction,
+};
+use errors::ErrorMetadata;
+use value::{
+    id_v6::DeveloperDocumentId,
+    obj,
+    ConvexArray,
+    ConvexObject,
+    ConvexValue,
+    FieldPath,
+    ResolvedDocumentId,
+    TableName,
+    TableNamespace,
+};
+
+use self::types::{
+    Workflow,
+    WorkflowState,
+    WorkflowStep,
+    WorkflowStepKind,
+    WorkflowStepState,
+};
+use crate::{
+    scheduled_jobs::{
+        types::{
+            ScheduleOptions,
+            ScheduledJobRetryPolicy,
+        },
+        SchedulerModel,
+        SCHEDULED_JOBS_TABLE,
+    },
+    SystemIndex,
+    SystemTable,
+};
+
+pub mod types;
+
+pub static WORKFLOWS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_workflows"
+        .parse()
+        .expect("_workflows is not a valid system table name")
+});
+
+pub static WORKFLOW_STEPS_TABLE: LazyLock<TableName> = LazyLock::new(|| {
+    "_workflow_steps"
+        .parse()
+        .expect("_workflow_steps is not a valid system table name")
+});
+
+pub static WORKFLOW_STEPS_INDEX_BY_WORKFLOW_ID: LazyLock<IndexName> =
+    LazyLock::new(|| system_index(&WORKFLOW_STEPS_TABLE, "by_workflow_id_and_step_number"));
+static WORKFLOW_ID_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "workflowId".parse().expect("invalid workflowId field"));
+static STEP_NUMBER_FIELD: LazyLock<FieldPath> =
+    LazyLock::new(|| "stepNumber".parse().expect("invalid stepNumber field"));
+
+/// The longest a workflow can sleep for in a single step.
+pub const MAX_WORKFLOW_SLEEP: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+pub struct WorkflowsTable;
+impl SystemTable for WorkflowsTable {
+    fn table_name(&self) -> &'static TableName {
+        &WORKFLOWS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<Workflow>::try_from(document).map(|_| ())
+    }
+}
+
+pub struct WorkflowStepsTable;
+impl SystemTable for WorkflowStepsTable {
+    fn table_name(&self) -> &'static TableName {
+        &WORKFLOW_STEPS_TABLE
+    }
+
+    fn indexes(&self) -> Vec<SystemIndex> {
+        vec![
+            // Used to load a workflow's journal in order.
+            SystemIndex {
+                name: WORKFLOW_STEPS_INDEX_BY_WORKFLOW_ID.clone(),
+                fields: vec![
+                    WORKFLOW_ID_FIELD.clone(),
+                    STEP_NUMBER_FIELD.clone(),
+                    CREATION_TIME_FIELD_PATH.clone(),
+                ]
+                .try_into()
+                .unwrap(),
+            },
+        ]
+    }
+
+    fn validate_document(&self, document: ResolvedDocument) -> anyhow::Result<()> {
+        ParsedDocument::<WorkflowStep>::try_from(document).map(|_| ())
+    }
+}
+
+/// A workflow with its steps, in the order they were started.
+pub struct WorkflowJournal {
+    pub workflow: ParsedDocument<Workflow>,
+    pub steps: Vec<ParsedDocument<WorkflowStep>>,
+}
+
+pub struct WorkflowModel<'a, RT: Runtime> {
+    tx: &'a mut Transaction<RT>,
+    namespace: TableNamespace,
+}
+
+impl<'a, RT: Runtime> WorkflowModel<'a, RT> {
+    pub fn new(tx: &'a mut Transaction<RT>, namespace: TableNamespace) -> Self {
+        Self { tx, namespace }
+    }
+
+    /// Starts a workflow defined by the mutation at `path`, which must be in
+    /// this model's component, and schedules its first run.
+    pub async fn start(
+        &mut self,
+        path: CanonicalizedComponentFunctionPath,
+        args: ConvexObject,
+        context: ExecutionContext,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        let start_ts = self.tx.runtime().generate_timestamp()?;
+        let workflow = Workflow::new(path.clone(), args, start_ts)?;
+        let id = SystemMetadataModel::new(self.tx, self.namespace)
+            .insert_metadata(&WORKFLOWS_TABLE, workflow.try_into()?)
+            .await?;
+        let now = self.tx.runtime().unix_timestamp();
+        self.schedule_run(id.developer_id, path, now, context)
+            .await?;
+        Ok(id)
+    }
+
+    pub async fn get(
+        &mut self,
+        id: DeveloperDocumentId,
+    ) -> anyhow::Result<Option<ParsedDocument<Workflow>>> {
+        let id = self.resolve(id, &WORKFLOWS_TABLE)?;
+        self.tx.get(id).await?.map(TryFrom::try_from).transpose()
+    }
+
+    /// Loads the workflow and its steps, or `None` if it doesn't exist.
+    pub async fn load(
+        &mut self,
+        id: DeveloperDocumentId,
+    ) -> anyhow::Result<Option<WorkflowJournal>> {
+        let Some(workflow) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let index_query = Query::index_range(IndexRange {
+            index_name: WORKFLOW_STEPS_INDEX_BY_WORKFLOW_ID.clone(),
+            range: vec![IndexRangeExpression::Eq(
+                WORKFLOW_ID_FIELD.clone(),
+                ConvexValue::try_from(id.encode())?.into(),
+            )],
+            order: Order::Asc,
+        });
+        let mut query_stream = ResolvedQuery::new(self.tx, self.namespace, index_query)?;
+        let mut steps = vec![];
+        while let Some(doc) = query_stream.next(self.tx, None).await? {
+            steps.push(doc.try_into()?);
+        }
+        Ok(Some(WorkflowJournal { workflow, steps }))
+    }
+
+    /// Loads the workflow for its mutation to run, first finishing its last
+    /// step if it's a sleep that's over.
+    pub async fn resume(
+        &mut self,
+        id: DeveloperDocumentId,
+    ) -> anyhow::Result<Option<WorkflowJournal>> {
+        let Some(mut journal) = self.load(id).await? else {
+            return Ok(None);
+        };
+        let now = self.tx.runtime().generate_timestamp()?;
+        if let Some(step) = journal.steps.last_mut() {
+            if step.kind == WorkflowStepKind::Sleep
+                && step.state == WorkflowStepState::InProgress
+                && step.wake_ts.is_some_and(|wake_ts| wake_ts <= now)
+            {
+                step.state = WorkflowStepState::Succeeded {
+                    result: "null".to_string(),
+                };
+                step.completed_ts = Some(now);
+                SystemMetadataModel::new(self.tx, self.namespace)
+                    .replace(step.id(), (**step).clone().try_into()?)
+                    .await?;
+            }
+        }
+        Ok(Some(journal))
+    }
+
+    /// Journals a query the workflow's mutation ran with its result or error.
+    pub async fn record_query_step(
+        &mut self,
+        workflow_id: DeveloperDocumentId,
+        step_number: u32,
+        path: CanonicalizedComponentFunctionPath,
+        outcome: Result<String, String>,
+    ) -> anyhow::Result<()> {
+        let state = match outcome {
+            Ok(result) => WorkflowStepState::Succeeded { result },
+            Err(error) => WorkflowStepState::Failed { error },
+        };
+        self.insert_step(
+            workflow_id,
+            step_number,
+            WorkflowStepKind::Query,
+            Some(path),
+            state,
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Starts a mutation or action step by scheduling a job to run it now.
+    /// The job records its result in the step and runs the workflow again
+    /// when it completes.
+    pub async fn start_job_step(
+        &mut self,
+        workflow_id: DeveloperDocumentId,
+        step_number: u32,
+        kind: WorkflowStepKind,
+        path: CanonicalizedComponentFunctionPath,
+        args: ConvexArray,
+        retry: Option<ScheduledJobRetryPolicy>,
+        context: ExecutionContext,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        anyhow::ensure!(
+            matches!(kind, WorkflowStepKind::Mutation | WorkflowStepKind::Action),
+            "Only mutation and action steps run as scheduled jobs"
+        );
+        let (step_id, mut step) = self
+            .insert_step(
+                workflow_id,
+                step_number,
+                kind,
+                Some(path.clone()),
+                WorkflowStepState::InProgress,
+                None,
+            )
+            .await?;
+        let options = ScheduleOptions {
+            retry,
+            workflow_step: Some(step_id.developer_id),
+            ..Default::default()
+        };
+        let now = self.tx.runtime().unix_timestamp();
+        let job_id = SchedulerModel::new(self.tx, self.namespace)
+            .schedule(path, args, now, context, options)
+            .await?;
+        step.scheduled_job_id = Some(job_id.developer_id);
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .replace(step_id, step.try_into()?)
+            .await?;
+        Ok(step_id)
+    }
+
+    /// Starts a sleep step, scheduling the workflow to run again once it's
+    /// over.
+    pub async fn sleep(
+        &mut self,
+        workflow_id: DeveloperDocumentId,
+        step_number: u32,
+        duration: Duration,
+        context: ExecutionContext,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        let wake = self.tx.runtime().unix_timestamp() + duration;
+        let (step_id, mut step) = self
+            .insert_step(
+                workflow_id,
+                step_number,
+                WorkflowStepKind::Sleep,
+                None,
+                WorkflowStepState::InProgress,
+                Some(wake.as_system_time().try_into()?),
+            )
+            .await?;
+        let workflow = self
+            .get(workflow_id)
+            .await?
+            .context("Workflow disappeared")?;
+        let job_id = self
+            .schedule_run(workflow_id, workflow.path.clone(), wake, context)
+            .await?;
+        step.scheduled_job_id = Some(job_id.developer_id);
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .replace(step_id, step.try_into()?)
+            .await?;
+        Ok(step_id)
+    }
+
+    /// Records the result of an in progress step and, if the workflow is
+    /// still running, schedules it to run again to start its next step. Does
+    /// nothing if the step already completed.
+    pub async fn complete_step(
+        &mut self,
+        step_id: DeveloperDocumentId,
+        outcome: Result<String, String>,
+    ) -> anyhow::Result<()> {
+        let step_id = self.resolve(step_id, &WORKFLOW_STEPS_TABLE)?;
+        let Some(step) = self.tx.get(step_id).await? else {
+            return Ok(());
+        };
+        let step: ParsedDocument<WorkflowStep> = step.try_into()?;
+        if step.state != WorkflowStepState::InProgress {
+            return Ok(());
+        }
+        let mut step = step.into_value();
+        step.state = match outcome {
+            Ok(result) => WorkflowStepState::Succeeded { result },
+            Err(error) => WorkflowStepState::Failed { error },
+        };
+        step.completed_ts = Some(*self.tx.begin_timestamp());
+        let workflow_id = step.workflow_id;
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .replace(step_id, step.try_into()?)
+            .await?;
+
+        let Some(workflow) = self.get(workflow_id).await? else {
+            return Ok(());
+        };
+        if workflow.state == WorkflowState::Running {
+            // Steps complete in scheduled jobs and from the dashboard, so
+            // there's no request to continue.
+            let context = ExecutionContext::new_from_parts(
+                RequestId::new(),
+                ExecutionId::new(),
+                None,
+                true,
+                None,
+            );
+            let now = self.tx.runtime().unix_timestamp();
+            self.schedule_run(workflow_id, workflow.path.clone(), now, context)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Finishes a running workflow with the value it returned or the error
+    /// it threw.
+    pub async fn finish(
+        &mut self,
+        id: DeveloperDocumentId,
+        outcome: Result<String, String>,
+    ) -> anyhow::Result<()> {
+        let workflow = self.get(id).await?.context(workflow_not_found(id))?;
+        anyhow::ensure!(
+            workflow.state == WorkflowState::Running,
+            "Workflow {id} already finished"
+        );
+        let resolved_id = workflow.id();
+        let mut workflow = workflow.into_value();
+        workflow.state = match outcome {
+            Ok(result) => WorkflowState::Completed { result },
+            Err(error) => WorkflowState::Failed { error },
+        };
+        workflow.completed_ts = Some(*self.tx.begin_timestamp());
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .replace(resolved_id, workflow.try_into()?)
+            .await?;
+        Ok(())
+    }
+
+    /// Cancels a running workflow along with the job of its in progress
+    /// step, if any. Does nothing if the workflow already finished.
+    pub async fn cancel(&mut self, id: DeveloperDocumentId) -> anyhow::Result<()> {
+        let Some(journal) = self.load(id).await? else {
+            anyhow::bail!(workflow_not_found(id));
+        };
+        if journal.workflow.state != WorkflowState::Running {
+            return Ok(());
+        }
+        let resolved_id = journal.workflow.id();
+        let mut workflow = journal.workflow.into_value();
+        workflow.state = WorkflowState::Canceled;
+        workflow.completed_ts = Some(*self.tx.begin_timestamp());
+        SystemMetadataModel::new(self.tx, self.namespace)
+            .replace(resolved_id, workflow.try_into()?)
+            .await?;
+
+        let Some(step) = journal.steps.last() else {
+            return Ok(());
+        };
+        if step.state != WorkflowStepState::InProgress {
+            return Ok(());
+        }
+        if let Some(job_id) = step.scheduled_job_id {
+            let job_id = self.resolve(job_id, &SCHEDULED_JOBS_TABLE)?;
+            SchedulerModel::new(self.tx, self.namespace)
+                .cancel(job_id)
+                .await?;
+        }
+        // Canceling a mutation or action step's job already completed it, but
+        // sleeps' jobs run the workflow itself.
+        self.complete_step(step.id().developer_id, Err("Canceled".to_string()))
+            .await
+    }
+
+    /// Adds the next step to a running workflow's journal. Steps run one at a
+    /// time, so this fails if `step_number` isn't the number of steps the
+    /// workflow has already started, which happens if the workflow's
+    /// mutation doesn't start the same steps every time it runs.
+    async fn insert_step(
+        &mut self,
+        workflow_id: DeveloperDocumentId,
+        step_number: u32,
+        kind: WorkflowStepKind,
+        path: Option<CanonicalizedComponentFunctionPath>,
+        state: WorkflowStepState,
+        wake_ts: Option<Timestamp>,
+    ) -> anyhow::Result<(ResolvedDocumentId, WorkflowStep)> {
+        let journal = self
+            .load(workflow_id)
+            .await?
+            .context(workflow_not_found(workflow_id))?;
+        anyhow::ensure!(
+            journal.workflow.state == WorkflowState::Running,
+            ErrorMetadata::bad_request(
+                "WorkflowNotRunning",
+                format!("Workflow {workflow_id} is no longer running"),
+            )
+        );
+        anyhow::ensure!(
+            step_number as usize == journal.steps.len()
+                && journal
+                    .steps
+                    .last()
+                    .is_none_or(|step| step.state != WorkflowStepState::InProgress),
+            ErrorMetadata::bad_request(
+                "NondeterministicWorkflow",
+                format!(
+                    "Workflow {workflow_id} tried to start step {step_number} after {} steps. \
+                     Workflows must start the same steps, one at a time, every time they run.",
+                    journal.steps.len()
+                ),
+            )
+        );
+        let start_ts = self.tx.runtime().generate_timestamp()?;
+        let completed_ts = (state != WorkflowStepState::InProgress).then_some(start_ts);
+        let step = WorkflowStep {
+            workflow_id,
+            step_number,
+            kind,
+            path,
+            state,
+            scheduled_job_id: None,
+            wake_ts,
+            start_ts,
+            completed_ts,
+        };
+        let step_id = SystemMetadataModel::new(self.tx, self.namespace)
+            .insert_metadata(&WORKFLOW_STEPS_TABLE, step.clone().try_into()?)
+            .await?;
+        Ok((step_id, step))
+    }
+
+    /// Schedules the workflow's mutation to run at `ts` to make progress.
+    async fn schedule_run(
+        &mut self,
+        workflow_id: DeveloperDocumentId,
+        path: CanonicalizedComponentFunctionPath,
+        ts: UnixTimestamp,
+        context: ExecutionContext,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        let args = ConvexArray::try_from(vec![ConvexValue::Object(obj!(
+            "workflowId" => workflow_id.encode(),
+        )?)])?;
+        SchedulerModel::new(self.tx, self.namespace)
+            .schedule(path, args, ts, context, ScheduleOptions::default())
+            .await
+    }
+
+    fn resolve(
+        &mut self,
+        id: DeveloperDocumentId,
+        table_name: &TableName,
+    ) -> anyhow::Result<ResolvedDocumentId> {
+        let table_mapping = self.tx.table_mapping().namespace(self.namespace);
+        let invalid_id = || {
+            ErrorMetadata::bad_request(
+                "InvalidWorkflowId",
+                format!("{id} is not an ID in the {table_name} table"),
+            )
+        };
+        let id = id
+            .to_resolved(table_mapping.number_to_tablet())
+            .map_err(|_| invalid_id())?;
+        anyhow::ensure!(
+            table_mapping.tablet_matches_name(id.tablet_id, table_name),
+            invalid_id()
+        );
+        Ok(id)
+    }
+}
+
+fn workflow_not_found(id: DeveloperDocumentId) -> ErrorMetadata {
+    ErrorMetadata::not_found("WorkflowNotFound", format!("Workflow {id} not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        time::Duration,
+    };
+
+    use common::{
+        components::{
+            CanonicalizedComponentFunctionPath,
+            ComponentPath,
+        },
+        execution_context::ExecutionContext,
+    };
+    use database::test_helpers::DbFixtures;
+    use errors::ErrorMetadataAnyhowExt;
+    use runtime::testing::TestRuntime;
+    use sync_types::CanonicalizedUdfPath;
+    use value::{
+        assert_obj,
+        ConvexArray,
+        TableNamespace,
+    };
+
+    use crate::{
+        test_helpers::DbFixturesWithModel,
+        workflows::{
+            types::{
+                WorkflowState,
+                WorkflowStepKind,
+                WorkflowStepState,
+            },
+            WorkflowModel,
+        },
+    };
+
+    fn path(udf_path: &str) -> anyhow::Result<CanonicalizedComponentFunctionPath> {
+        Ok(CanonicalizedComponentFunctionPath {
+            component: ComponentPath::test_user(),
+            udf_path: CanonicalizedUdfPath::from_str(udf_path)?,
+        })
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_workflow_steps(rt: TestRuntime) -> anyhow::Result<()> {
+        let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+        let mut tx = db.begin_system().await?;
+        let mut model = WorkflowModel::new(&mut tx, TableNamespace::test_user());
+        let id = model
+            .start(
+                path("workflows:onboard")?,
+                assert_obj!("userId" => "alice"),
+                ExecutionContext::new_for_test(),
+            )
+            .await?
+            .developer_id;
+        let journal = model.resume(id).await?.unwrap();
+        assert_eq!(journal.workflow.state, WorkflowState::Running);
+        assert_eq!(journal.workflow.args()?, assert_obj!("userId" => "alice"));
+        assert!(journal.steps.is_empty());
+
+        model
+            .record_query_step(id, 0, path("users:get")?, Ok("1".to_string()))
+            .await?;
+        // Steps must be started in order.
+        let err = model
+            .start_job_step(
+                id,
+                2,
+                WorkflowStepKind::Mutation,
+                path("users:update")?,
+                ConvexArray::empty(),
+                None,
+                ExecutionContext::new_for_test(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "NondeterministicWorkflow");
+        let step_id = model
+            .start_job_step(
+                id,
+                1,
+                WorkflowStepKind::Mutation,
+                path("users:update")?,
+                ConvexArray::empty(),
+                None,
+                ExecutionContext::new_for_test(),
+            )
+            .await?;
+        // Only one step can be in progress at a time.
+        let err = model
+            .sleep(
+                id,
+                2,
+                Duration::from_secs(1),
+                ExecutionContext::new_for_test(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "NondeterministicWorkflow");
+
+        let journal = model.load(id).await?.unwrap();
+        assert_eq!(journal.steps.len(), 2);
+        assert_eq!(
+            journal.steps[0].state,
+            WorkflowStepState::Succeeded {
+                result: "1".to_string()
+            }
+        );
+        assert_eq!(journal.steps[1].state, WorkflowStepState::InProgress);
+        assert!(journal.steps[1].scheduled_job_id.is_some());
+
+        // Completing a step is idempotent.
+        model
+            .complete_step(step_id.developer_id, Ok("\"done\"".to_string()))
+            .await?;
+        model
+            .complete_step(step_id.developer_id, Err("late".to_string()))
+            .await?;
+        let journal = model.load(id).await?.unwrap();
+        assert_eq!(
+            journal.steps[1].state,
+            WorkflowStepState::Succeeded {
+                result: "\"done\"".to_string()
+            }
+        );
+
+        model.finish(id, Ok("null".to_string())).await?;
+        let err = model
+            .record_query_step(id, 2, path("users:get")?, Ok("1".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "WorkflowNotRunning");
+        let workflow = model.get(id).await?.unwrap();
+        assert_eq!(
+            workflow.state,
+            WorkflowState::Completed {
+                result: "null".to_string()
+            }
+        );
+        assert!(workflow.completed_ts.is_some());
+        Ok(())
+    }
+
+    #[convex_macro::test_runtime]
+    async fn test_cancel_sleeping_workflow(rt: TestRuntime) -> anyhow::Result<()> {
+        let DbFixtures { db, .. } = DbFixtures::new_with_model(&rt).await?;
+        let mut tx = db.begin_system().await?;
+        let mut model = WorkflowModel::new(&mut tx, TableNamespace::test_user());
+        let id = model
+            .start(
+                path("workflows:remind")?,
+                assert_obj!(),
+                ExecutionContext::new_for_test(),
+            )
+            .await?
+            .developer_id;
+        model
+            .sleep(
+                id,
+                0,
+                Duration::from_secs(60 * 60),
+                ExecutionContext::new_for_test(),
+            )
+            .await?;
+        // The sleep isn't over yet, so resuming doesn't complete it.
+        let journal = model.resume(id).await?.unwrap();
+        assert_eq!(journal.steps[0].state, WorkflowStepState::InProgress);
+
+        model.cancel(id).await?;
+        let journal = model.load(id).await?.unwrap();
+        assert_eq!(journal.workflow.state, WorkflowState::Canceled);
+        assert_eq!(
+            journal.steps[0].state,
+            WorkflowStepState::Failed {
+                error: "Canceled".to_string()
+            }
+        );
+        // Canceling again does nothing.
+        model.cancel(id).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use common::{
+    components::CanonicalizedComponentFunctionPath,
+    types::Timestamp,
+};
+#[cfg(any(test, feature = "testing"))]
+use proptest::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_bytes::ByteBuf;
+use serde_json::Value as JsonValue;
+use value::{
+    codegen_convex_serialization,
+    id_v6::DeveloperDocumentId,
+    ConvexObject,
+};
+
+/// A run of a workflow. The workflow's definition is a mutation that replays
+/// the journal of its completed steps and either starts the next step or
+/// finishes the workflow. It runs again whenever a step completes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct Workflow {
+    /// The mutation that defines the workflow. It lives in the same component
+    /// as the `_workflows` table the workflow is in.
+    pub path: CanonicalizedComponentFunctionPath,
+    #[cfg_attr(
+        any(test, feature = "testing"),
+        proptest(
+            strategy = "any_with::<ConvexObject>(((0..4).into(), Default::default(), \
+                        Default::default())).prop_map(args_to_bytes).prop_filter_map(\"invalid \
+                        json\", |b| b.ok())"
+        )
+    )]
+    pub args_bytes: ByteBuf,
+    pub state: WorkflowState,
+    pub start_ts: Timestamp,
+    /// Only set once the workflow is no longer running.
+    pub completed_ts: Option<Timestamp>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum WorkflowState {
+    Running,
+    /// The workflow returned a value, serialized as Convex JSON.
+    Completed {
+        result: String,
+    },
+    /// The workflow threw an error, e.g. because a step failed on its last
+    /// attempt and the workflow didn't catch it.
+    Failed {
+        error: String,
+    },
+    Canceled,
+}
+
+fn args_to_bytes(args: ConvexObject) -> anyhow::Result<ByteBuf> {
+    let args_json = JsonValue::from(args);
+    Ok(ByteBuf::from(serde_json::to_vec(&args_json)?))
+}
+
+impl Workflow {
+    pub fn new(
+        path: CanonicalizedComponentFunctionPath,
+        args: ConvexObject,
+        start_ts: Timestamp,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            path,
+            args_bytes: args_to_bytes(args)?,
+            state: WorkflowState::Running,
+            start_ts,
+            completed_ts: None,
+        })
+    }
+
+    pub fn args(&self) -> anyhow::Result<ConvexObject> {
+        let args_json: JsonValue = serde_json::from_slice(&self.args_bytes)?;
+        args_json.try_into()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedWorkflow {
+    component: String,
+    udf_path: String,
+    // Serialized as binary for the same reason as scheduled jobs' arguments:
+    // the arguments can have field names that aren't allowed in documents.
+    args: ByteBuf,
+    state: SerializedWorkflowState,
+    start_ts: i64,
+    completed_ts: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum SerializedWorkflowState {
+    Running,
+    Completed { result: String },
+    Failed { error: String },
+    Canceled,
+}
+
+impl From<WorkflowState> for SerializedWorkflowState {
+    fn from(state: WorkflowState) -> Self {
+        match state {
+            WorkflowState::Running => Self::Running,
+            WorkflowState::Completed { result } => Self::Completed { result },
+            WorkflowState::Failed { error } => Self::Failed { error },
+            WorkflowState::Canceled => Self::Canceled,
+        }
+    }
+}
+
+impl From<SerializedWorkflowState> for WorkflowState {
+    fn from(value: SerializedWorkflowState) -> Self {
+        match value {
+            SerializedWorkflowState::Running => Self::Running,
+            SerializedWorkflowState::Completed { result } => Self::Completed { result },
+            SerializedWorkflowState::Failed { error } => Self::Failed { error },
+            SerializedWorkflowState::Canceled => Self::Canceled,
+        }
+    }
+}
+
+impl TryFrom<Workflow> for SerializedWorkflow {
+    type Error = anyhow::Error;
+
+    fn try_from(workflow: Workflow) -> anyhow::Result<Self> {
+        Ok(Self {
+            component: String::from(workflow.path.component),
+            udf_path: String::from(workflow.path.udf_path),
+            args: workflow.args_bytes,
+            state: workflow.state.into(),
+            start_ts: workflow.start_ts.into(),
+            completed_ts: workflow.completed_ts.map(|ts| ts.into()),
+        })
+    }
+}
+
+impl TryFrom<SerializedWorkflow> for Workflow {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedWorkflow) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: CanonicalizedComponentFunctionPath {
+                component: value.component.parse()?,
+                udf_path: value.udf_path.parse()?,
+            },
+            args_bytes: value.args,
+            state: value.state.into(),
+            start_ts: value.start_ts.try_into()?,
+            completed_ts: value.completed_ts.map(|ts| ts.try_into()).transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(Workflow, SerializedWorkflow);
+
+/// An entry in a workflow's journal. Steps are numbered from zero in the
+/// order the workflow started them, and only the last one can be in
+/// progress.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub struct WorkflowStep {
+    pub workflow_id: DeveloperDocumentId,
+    pub step_number: u32,
+    pub kind: WorkflowStepKind,
+    /// The function the step runs. Not set for sleeps.
+    pub path: Option<CanonicalizedComponentFunctionPath>,
+    pub state: WorkflowStepState,
+    /// The scheduled job that runs a mutation or action step, or that runs
+    /// the workflow again when a sleep is over.
+    pub scheduled_job_id: Option<DeveloperDocumentId>,
+    /// When a sleep is over.
+    pub wake_ts: Option<Timestamp>,
+    pub start_ts: Timestamp,
+    pub completed_ts: Option<Timestamp>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum WorkflowStepKind {
+    /// Runs in the same transaction as the workflow, so it's journaled as
+    /// soon as it's started.
+    Query,
+    /// Runs exactly once as a scheduled job, which records its result in the
+    /// same transaction.
+    Mutation,
+    /// Runs as a scheduled job, at most once per attempt.
+    Action,
+    Sleep,
+}
+
+impl WorkflowStepKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Mutation => "mutation",
+            Self::Action => "action",
+            Self::Sleep => "sleep",
+        }
+    }
+}
+
+impl fmt::Display for WorkflowStepKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WorkflowStepKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let kind = match s {
+            "query" => Self::Query,
+            "mutation" => Self::Mutation,
+            "action" => Self::Action,
+            "sleep" => Self::Sleep,
+            _ => anyhow::bail!("Invalid workflow step kind: {s}"),
+        };
+        Ok(kind)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
+pub enum WorkflowStepState {
+    InProgress,
+    /// The value the step's function returned, serialized as Convex JSON.
+    Succeeded {
+        result: String,
+    },
+    /// The step's function threw an error on its last attempt, or its job was
+    /// canceled.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedWorkflowStep {
+    workflow_id: String,
+    step_number: i64,
+    kind: String,
+    component: Option<String>,
+    udf_path: Option<String>,
+    state: SerializedWorkflowStepState,
+    scheduled_job_id: Option<String>,
+    wake_ts: Option<i64>,
+    start_ts: i64,
+    completed_ts: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+enum SerializedWorkflowStepState {
+    InProgress,
+    Succeeded { result: String },
+    Failed { error: String },
+}
+
+impl From<WorkflowStepState> for SerializedWorkflowStepState {
+    fn from(state: WorkflowStepState) -> Self {
+        match state {
+            WorkflowStepState::InProgress => Self::InProgress,
+            WorkflowStepState::Succeeded { result } => Self::Succeeded { result },
+            WorkflowStepState::Failed { error } => Self::Failed { error },
+        }
+    }
+}
+
+impl From<SerializedWorkflowStepState> for WorkflowStepState {
+    fn from(value: SerializedWorkflowStepState) -> Self {
+        match value {
+            SerializedWorkflowStepState::InProgress => Self::InProgress,
+            SerializedWorkflowStepState::Succeeded { result } => Self::Succeeded { result },
+            SerializedWorkflowStepState::Failed { error } => Self::Failed { error },
+        }
+    }
+}
+
+impl TryFrom<WorkflowStep> for SerializedWorkflowStep {
+    type Error = anyhow::Error;
+
+    fn try_from(step: WorkflowStep) -> anyhow::Result<Self> {
+        let (component, udf_path) = match step.path {
+            Some(path) => (
+                Some(String::from(path.component)),
+                Some(String::from(path.udf_path)),
+            ),
+            None => (None, None),
+        };
+        Ok(Self {
+            workflow_id: step.workflow_id.encode(),
+            step_number: step.step_number.into(),
+            kind: step.kind.to_string(),
+            component,
+            udf_path,
+            state: step.state.into(),
+            scheduled_job_id: step.scheduled_job_id.map(|id| id.encode()),
+            wake_ts: step.wake_ts.map(|ts| ts.into()),
+            start_ts: step.start_ts.into(),
+            completed_ts: step.completed_ts.map(|ts| ts.into()),
+        })
+    }
+}
+
+impl TryFrom<SerializedWorkflowStep> for WorkflowStep {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SerializedWorkflowStep) -> anyhow::Result<Self> {
+        let path = match (value.component, value.udf_path) {
+            (Some(component), Some(udf_path)) => Some(CanonicalizedComponentFunctionPath {
+                component: component.parse()?,
+                udf_path: udf_path.parse()?,
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("Workflow step has only one of component and udfPath"),
+        };
+        Ok(Self {
+            workflow_id: DeveloperDocumentId::decode(&value.workflow_id)?,
+            step_number: value.step_number.try_into()?,
+            kind: value.kind.parse()?,
+            path,
+            state: value.state.into(),
+            scheduled_job_id: value
+                .scheduled_job_id
+                .map(|id| DeveloperDocumentId::decode(&id))
+                .transpose()?,
+            wake_ts: value.wake_ts.map(|ts| ts.try_into()).transpose()?,
+            start_ts: value.start_ts.try_into()?,
+            completed_ts: value.completed_ts.map(|ts| ts.try_into()).transpose()?,
+        })
+    }
+}
+
+codegen_convex_serialization!(WorkflowStep, SerializedWorkflowStep);
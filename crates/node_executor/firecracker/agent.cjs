@@ -0,0 +1,38 @@
+// Guest agent for the Firecracker node executor. Copy this to
+// /convex/agent.cjs in the VM rootfs and start it with
+// `socat VSOCK-LISTEN:52 EXEC:"node /convex/agent.cjs"`.
+//
+// The backend sends one line of JSON: `files` maps absolute paths to
+// base64-encoded contents to write before running, and `args` are the
+// arguments for `node`. The executor's stdout is relayed as is, followed by a
+// final `{"firecrackerAgentExit":<code>}` line.
+"use strict";
+
+const { spawn } = require("child_process");
+const fs = require("fs");
+const os = require("os");
+const path = require("path");
+const readline = require("readline");
+
+const input = readline.createInterface({ input: process.stdin });
+input.once("line", (line) => {
+  input.close();
+  const { files, args } = JSON.parse(line);
+  for (const [filePath, contents] of Object.entries(files)) {
+    fs.mkdirSync(path.dirname(filePath), { recursive: true });
+    fs.writeFileSync(filePath, Buffer.from(contents, "base64"));
+  }
+  const child = spawn(process.execPath, args, {
+    stdio: ["ignore", "pipe", "inherit"],
+  });
+  child.stdout.pipe(process.stdout, { end: false });
+  child.on("close", (code, signal) => {
+    // Mirror the shell convention for processes killed by a signal, e.g. 137
+    // for the OOM killer's SIGKILL.
+    const exitCode = code ?? 128 + (os.constants.signals[signal] ?? 0);
+    process.stdout.write(
+      JSON.stringify({ firecrackerAgentExit: exitCode }) + "\n",
+      () => process.exit(0),
+    );
+  });
+});
@@ -0,0 +1,301 @@
+use std::{
+    path::PathBuf,
+    process::Command as StdCommand,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use common::{
+    log_lines::LogLine,
+    types::{
+        ConvexOrigin,
+        NodeVersion,
+    },
+};
+use serde_json::Value as JsonValue;
+use tempfile::TempDir;
+use tokio::{
+    process::Command as TokioCommand,
+    sync::mpsc,
+};
+
+use crate::{
+    executor::{
+        error_response_json,
+        ExecutorRequest,
+        InvokeResponse,
+        NodeExecutor,
+        EXECUTE_TIMEOUT_RESPONSE_JSON,
+    },
+    local::{
+        run_node_process,
+        write_executor_source,
+        NodeProcessResult,
+    },
+};
+
+/// Where the executor script is mounted inside the container.
+const CONTAINER_SOURCE_DIR: &str = "/convex";
+
+const NODE_VERSION_PLACEHOLDER: &str = "{node_version}";
+
+/// Exit code of a container killed by the kernel OOM killer.
+const OOM_KILLED_EXIT_CODE: i32 = 137;
+
+static NEXT_CONTAINER_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug)]
+pub struct DockerNodeExecutorConfig {
+    /// Image to run actions in. It must have `node` on its `PATH`. Any
+    /// `{node_version}` is replaced with the major version of Node.js the
+    /// deployment picked, e.g. `node:{node_version}-slim`.
+    pub image: String,
+    /// Docker network for the container, e.g. `bridge`, `host`, or a
+    /// user-defined network with its own egress rules. Actions call back into
+    /// the backend over HTTP, so the network must be able to reach it.
+    pub network: String,
+    /// Address the container uses to reach the backend, if it's different
+    /// from the backend's origin (e.g. `http://host.docker.internal:3210`).
+    pub backend_address: Option<ConvexOrigin>,
+    pub memory_limit_mb: u64,
+    pub cpus: f64,
+    pub pids_limit: u32,
+    /// Size of the writable `/tmp` where packages are unpacked.
+    pub tmpfs_size_mb: u64,
+    /// Local directory holding source packages. It's mounted at the same path
+    /// so the package URIs in requests resolve inside the container.
+    pub packages_dir: PathBuf,
+    pub node_process_timeout: Duration,
+}
+
+/// Runs each Node action in a fresh Docker container with a read-only root
+/// filesystem, no capabilities, and memory, CPU, and process limits, rather
+/// than as a process with the backend's privileges.
+pub struct DockerNodeExecutor {
+    _source_dir: TempDir,
+    source_dir_path: PathBuf,
+    config: DockerNodeExecutorConfig,
+}
+
+impl DockerNodeExecutor {
+    pub fn new(config: DockerNodeExecutorConfig) -> anyhow::Result<Self> {
+        let (source_dir, _) = write_executor_source()?;
+        let source_dir_path = source_dir.path().to_path_buf();
+        tracing::info!(
+            "Using docker node executor with image {}, network {}",
+            config.image,
+            config.network,
+        );
+        Ok(Self {
+            _source_dir: source_dir,
+            source_dir_path,
+            config,
+        })
+    }
+
+    fn docker_args(
+        &self,
+        container_name: &str,
+        node_version: NodeVersion,
+        packages_writable: bool,
+    ) -> Vec<String> {
+        let config = &self.config;
+        let packages_dir = config.packages_dir.to_string_lossy();
+        let mut args = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            format!("--name={container_name}"),
+            "--read-only".to_string(),
+            format!("--tmpfs=/tmp:rw,size={}m", config.tmpfs_size_mb),
+            format!("--network={}", config.network),
+            format!("--memory={}m", config.memory_limit_mb),
+            // Disallow swap so the memory limit is a hard limit.
+            format!("--memory-swap={}m", config.memory_limit_mb),
+            format!("--cpus={}", config.cpus),
+            format!("--pids-limit={}", config.pids_limit),
+            "--cap-drop=ALL".to_string(),
+            "--security-opt=no-new-privileges".to_string(),
+            "--user=1000:1000".to_string(),
+            format!(
+                "--volume={}:{CONTAINER_SOURCE_DIR}:ro",
+                self.source_dir_path.to_string_lossy()
+            ),
+        ];
+        // Custom host mappings aren't allowed with the `host` and `none` networks.
+        if config.network != "host" && config.network != "none" {
+            args.push("--add-host=host.docker.internal:host-gateway".to_string());
+        }
+        if packages_writable {
+            args.push(format!("--volume={packages_dir}:{packages_dir}"));
+        } else {
+            args.push(format!("--volume={packages_dir}:{packages_dir}:ro"));
+        }
+        args.push(
+            config
+                .image
+                .replace(NODE_VERSION_PLACEHOLDER, &node_version.to_string()),
+        );
+        args.push("node".to_string());
+        args.push(format!("{CONTAINER_SOURCE_DIR}/local.cjs"));
+        args
+    }
+}
+
+/// Force-removes the container if the invocation is dropped before it exits,
+/// since killing the `docker` client doesn't stop the container.
+struct ContainerGuard {
+    name: String,
+    armed: bool,
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        if let Err(e) = StdCommand::new("docker")
+            .args(["rm", "--force", &self.name])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        {
+            tracing::error!("Failed to remove container {}: {e}", self.name);
+        }
+    }
+}
+
+#[async_trait]
+impl NodeExecutor for DockerNodeExecutor {
+    fn enable(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn invoke(
+        &self,
+        mut request: ExecutorRequest,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        if let ExecutorRequest::Execute {
+            ref mut backend_address,
+            ..
+        } = request
+            && let Some(address) = &self.config.backend_address
+        {
+            *backend_address = address.clone();
+        }
+        let node_version = request.node_version();
+        // Only building dependencies uploads a package.
+        let packages_writable = matches!(request, ExecutorRequest::BuildDeps(_));
+        let request = serde_json::to_string(&JsonValue::try_from(request)?)?;
+
+        let container_name = format!(
+            "convex-node-{}-{}",
+            std::process::id(),
+            NEXT_CONTAINER_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut guard = ContainerGuard {
+            name: container_name.clone(),
+            armed: true,
+        };
+        let mut cmd = TokioCommand::new("docker");
+        cmd.args(self.docker_args(&container_name, node_version, packages_writable))
+            .arg("--request")
+            .arg(request)
+            .kill_on_drop(true);
+        let result =
+            run_node_process(&mut cmd, &log_line_sender, self.config.node_process_timeout).await?;
+        // `--rm` cleans up containers that exit on their own.
+        guard.armed = matches!(result, NodeProcessResult::TimedOut);
+        let response = match result {
+            NodeProcessResult::Response(response) => response,
+            NodeProcessResult::TimedOut => EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+            NodeProcessResult::Failed(status) if status.code() == Some(OOM_KILLED_EXIT_CODE) => {
+                error_response_json(&format!(
+                    "Node action ran out of memory (limit: {} MB)",
+                    self.config.memory_limit_mb
+                ))
+            },
+            NodeProcessResult::Failed(status) => {
+                anyhow::bail!(
+                    "Docker container {container_name} did not exit successfully: {status}"
+                )
+            },
+        };
+        Ok(InvokeResponse {
+            response,
+            memory_used_in_mb: self.config.memory_limit_mb,
+            aws_request_id: None,
+        })
+    }
+
+    fn shutdown(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::types::NodeVersion;
+
+    use super::{
+        DockerNodeExecutor,
+        DockerNodeExecutorConfig,
+    };
+
+    fn test_executor() -> anyhow::Result<DockerNodeExecutor> {
+        DockerNodeExecutor::new(DockerNodeExecutorConfig {
+            image: "node:{node_version}-slim".to_string(),
+            network: "none".to_string(),
+            backend_address: None,
+            memory_limit_mb: 256,
+            cpus: 0.5,
+            pids_limit: 64,
+            tmpfs_size_mb: 128,
+            packages_dir: "/var/convex/modules".into(),
+            node_process_timeout: Duration::from_secs(5),
+        })
+    }
+
+    #[test]
+    fn test_docker_args_constrain_container() -> anyhow::Result<()> {
+        let executor = test_executor()?;
+        let args = executor.docker_args("convex-node-test", NodeVersion::V18, false);
+        for expected in [
+            "--read-only",
+            "--network=none",
+            "--memory=256m",
+            "--memory-swap=256m",
+            "--cpus=0.5",
+            "--pids-limit=64",
+            "--cap-drop=ALL",
+            "--volume=/var/convex/modules:/var/convex/modules:ro",
+        ] {
+            assert!(args.iter().any(|a| a == expected), "missing {expected}");
+        }
+        let image_index = args.iter().position(|a| a == "node:18-slim").unwrap();
+        assert_eq!(&args[image_index + 1..], ["node", "/convex/local.cjs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_docker_args_node_version_image() -> anyhow::Result<()> {
+        let executor = test_executor()?;
+        let args = executor.docker_args("convex-node-test", NodeVersion::V22, false);
+        assert!(args.iter().any(|a| a == "node:22-slim"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_docker_args_build_deps_can_upload() -> anyhow::Result<()> {
+        let executor = test_executor()?;
+        let args = executor.docker_args("convex-node-test", NodeVersion::V18, true);
+        assert!(args
+            .iter()
+            .any(|a| a == "--volume=/var/convex/modules:/var/convex/modules"));
+        Ok(())
+    }
+}
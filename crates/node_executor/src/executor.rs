@@ -17,7 +17,11 @@ use common::{
         JsError,
     },
     execution_context::ExecutionContext,
-    knobs::NODE_ANALYZE_MAX_RETRIES,
+    function_timeouts::FunctionTimeouts,
+    knobs::{
+        FUNCTION_USER_TIMEOUTS,
+        NODE_ANALYZE_MAX_RETRIES,
+    },
     log_lines::{
         LogLine,
         LogLineStructured,
@@ -28,11 +32,15 @@ use common::{
         ActionCallbackToken,
         ConvexOrigin,
         NodeDependency,
+        NodeVersion,
         ObjectKey,
         UdfType,
     },
 };
-use errors::ErrorMetadataAnyhowExt;
+use errors::{
+    ErrorMetadata,
+    ErrorMetadataAnyhowExt,
+};
 use http::Uri;
 use isolate::{
     deserialize_udf_custom_error,
@@ -65,6 +73,7 @@ use model::{
         SourcePackageId,
     },
 };
+use parking_lot::Mutex;
 use serde::Deserialize;
 use serde_json::{
     json,
@@ -141,7 +150,26 @@ pub struct Actions<RT: Runtime> {
     executor: Arc<dyn NodeExecutor>,
     convex_origin: ConvexOrigin,
     user_timeout: Duration,
+    /// Overrides of `user_timeout` for individual functions.
+    function_timeouts: FunctionTimeouts,
     runtime: RT,
+    /// Log line senders of in-flight executions, keyed by the log stream id
+    /// passed to the executor. Executors post log lines to the
+    /// `/api/actions/log_lines` callback while the action is running, so
+    /// they show up in the logs before the action finishes.
+    log_streams: Arc<Mutex<BTreeMap<String, mpsc::UnboundedSender<LogLine>>>>,
+}
+
+/// Removes a log stream from [`Actions`] once its execution finishes.
+struct LogStreamGuard {
+    log_streams: Arc<Mutex<BTreeMap<String, mpsc::UnboundedSender<LogLine>>>>,
+    log_stream_id: String,
+}
+
+impl Drop for LogStreamGuard {
+    fn drop(&mut self) {
+        self.log_streams.lock().remove(&self.log_stream_id);
+    }
 }
 
 fn construct_js_error(
@@ -200,10 +228,17 @@ impl<RT: Runtime> Actions<RT> {
             executor,
             convex_origin,
             user_timeout,
+            function_timeouts: FUNCTION_USER_TIMEOUTS.clone(),
             runtime,
+            log_streams: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
+    #[cfg(any(test, feature = "testing"))]
+    pub fn set_function_timeouts(&mut self, function_timeouts: FunctionTimeouts) {
+        self.function_timeouts = function_timeouts;
+    }
+
     pub fn enable(&self) -> anyhow::Result<()> {
         self.executor.enable()
     }
@@ -212,6 +247,27 @@ impl<RT: Runtime> Actions<RT> {
         self.executor.shutdown()
     }
 
+    /// Forwards log lines streamed by the executor to the execution that
+    /// registered `log_stream_id`.
+    pub fn stream_log_lines(
+        &self,
+        log_stream_id: &str,
+        log_lines: Vec<LogLine>,
+    ) -> anyhow::Result<()> {
+        let log_streams = self.log_streams.lock();
+        let Some(log_line_sender) = log_streams.get(log_stream_id) else {
+            anyhow::bail!(ErrorMetadata::not_found(
+                "LogStreamNotFound",
+                "The action this log stream belongs to is no longer running",
+            ));
+        };
+        for log_line in log_lines {
+            // The receiver is only dropped once the execution is done.
+            let _ = log_line_sender.send(log_line);
+        }
+        Ok(())
+    }
+
     #[rustfmt::skip]
     pub async fn execute(
         &self,
@@ -222,14 +278,27 @@ impl<RT: Runtime> Actions<RT> {
             + Send,
     ) -> anyhow::Result<NodeActionOutcome> {
         let path = request.path_and_args.path().clone();
+        let user_timeout = self
+            .function_timeouts
+            .user_timeout_for(&path.clone().for_logging())
+            .unwrap_or(self.user_timeout);
         let timer = node_executor("execute");
+        let log_stream_id = self.runtime.new_uuid_v4().to_string();
+        self.log_streams
+            .lock()
+            .insert(log_stream_id.clone(), log_line_sender.clone());
+        let _log_stream_guard = LogStreamGuard {
+            log_streams: self.log_streams.clone(),
+            log_stream_id: log_stream_id.clone(),
+        };
         let request = ExecutorRequest::Execute {
             request,
             backend_address: self.convex_origin.clone(),
             // Use the user facing timeout here, which should be less than the
             // total Node timeout. This allows us to preempt early and give
             // better error message and logs in the common case.
-            timeout: self.user_timeout,
+            timeout: user_timeout,
+            log_stream_id: Some(log_stream_id),
         };
         let InvokeResponse {
             response,
@@ -525,11 +594,25 @@ pub enum ExecutorRequest {
         request: ExecuteRequest,
         backend_address: ConvexOrigin,
         timeout: Duration,
+        /// If set, the executor streams log lines through action callbacks
+        /// instead of returning them with the response.
+        log_stream_id: Option<String>,
     },
     Analyze(AnalyzeRequest),
     BuildDeps(BuildDepsRequest),
 }
 
+impl ExecutorRequest {
+    /// Version of Node.js the request must run on.
+    pub fn node_version(&self) -> NodeVersion {
+        match self {
+            ExecutorRequest::Execute { request, .. } => request.source_package.node_version,
+            ExecutorRequest::Analyze(request) => request.source_package.node_version,
+            ExecutorRequest::BuildDeps(request) => request.node_version,
+        }
+    }
+}
+
 impl TryFrom<ExecutorRequest> for JsonValue {
     type Error = anyhow::Error;
 
@@ -539,6 +622,7 @@ impl TryFrom<ExecutorRequest> for JsonValue {
                 request: r,
                 backend_address,
                 timeout,
+                log_stream_id,
             } => {
                 let environment_variables: Vec<JsonValue> = r
                     .environment_variables
@@ -573,6 +657,7 @@ impl TryFrom<ExecutorRequest> for JsonValue {
                     "npmVersion": npm_version.map(|v| v.to_string()),
                     "executionContext": JsonValue::from(r.context),
                     "encodedParentTrace": JsonValue::from(r.encoded_parent_trace),
+                    "logStreamId": log_stream_id,
                 })
             },
             ExecutorRequest::Analyze(r) => {
@@ -599,6 +684,7 @@ impl TryFrom<ExecutorRequest> for JsonValue {
                     "type": "build_deps",
                     "uploadUrl": JsonValue::from(r.upload_url.to_string()),
                     "deps": JsonValue::Array(deps),
+                    "nodeVersion": r.node_version.to_string(),
                 })
             },
         };
@@ -612,6 +698,13 @@ pub struct SourcePackage {
 
     // Info of external package if external dependencies were specified.
     pub external_deps: Option<Package>,
+
+    // Node.js version the package was pushed for, which its external
+    // dependencies were built with.
+    pub node_version: NodeVersion,
+
+    // Prebuilt `node_modules` archives to run with, in order of precedence.
+    pub layers: Vec<Package>,
 }
 
 impl From<SourcePackage> for JsonValue {
@@ -621,6 +714,7 @@ impl From<SourcePackage> for JsonValue {
             .external_deps
             .map(JsonValue::from)
             .unwrap_or(JsonValue::Null);
+        let layers: Vec<JsonValue> = value.layers.into_iter().map(JsonValue::from).collect();
 
         json!({
             "uri": value.bundled_source.uri.to_string(),
@@ -628,6 +722,8 @@ impl From<SourcePackage> for JsonValue {
             "sha256": base64::encode_urlsafe(&*value.bundled_source.sha256),
             "bundled_source": source_package,
             "external_deps": external_package,
+            "node_version": value.node_version.to_string(),
+            "layers": layers,
         })
     }
 }
@@ -851,6 +947,7 @@ pub struct AnalyzedNodeFunction {
 pub struct BuildDepsRequest {
     pub deps: Vec<NodeDependency>,
     pub upload_url: Uri,
+    pub node_version: NodeVersion,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -0,0 +1,580 @@
+//! Runs Node actions in Firecracker microVMs, for multi-tenant setups where
+//! user code shouldn't share a kernel with the backend.
+//!
+//! Each VM boots from a prebuilt, read-only rootfs that has Node.js 18 and
+//! `socat` installed, mounts a tmpfs on `/tmp`, and runs
+//! `socat VSOCK-LISTEN:52 EXEC:"node /convex/agent.cjs"` on startup, with
+//! `agent.cjs` copied from this crate's `firecracker/` directory. The backend
+//! connects over vsock, sends the executor script, any locally stored source
+//! packages, and the request, and then reads the executor's output as if it
+//! were a local process.
+//!
+//! VMs are single-use. A background task keeps `pool_size` of them booted and
+//! connected so invocations don't wait on boot.
+use std::{
+    collections::BTreeMap,
+    net::Ipv4Addr,
+    path::PathBuf,
+    process::{
+        ExitStatus,
+        Stdio,
+    },
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use common::{
+    backoff::Backoff,
+    log_lines::LogLine,
+    runtime::tokio_spawn,
+    types::{
+        ConvexOrigin,
+        NodeVersion,
+    },
+};
+use errors::ErrorMetadata;
+use futures::{
+    stream,
+    StreamExt,
+};
+use isolate::bundled_js::node_executor_file;
+use parking_lot::Mutex;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use tempfile::TempDir;
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        AsyncWriteExt,
+        BufReader,
+    },
+    net::UnixStream,
+    process::{
+        Child,
+        Command as TokioCommand,
+    },
+    sync::{
+        mpsc,
+        OwnedSemaphorePermit,
+        Semaphore,
+    },
+    task::JoinHandle,
+};
+use tokio_process_stream::Item;
+use value::base64;
+
+use crate::{
+    executor::{
+        ExecutorRequest,
+        InvokeResponse,
+        NodeExecutor,
+        EXECUTE_TIMEOUT_RESPONSE_JSON,
+    },
+    local::{
+        collect_node_output,
+        NodeProcessResult,
+    },
+    source_package::add_request_package_files,
+};
+
+/// vsock port the guest agent listens on.
+const AGENT_PORT: u32 = 52;
+/// Where the executor script is written inside the guest.
+const GUEST_EXECUTOR_PATH: &str = "/tmp/convex/local.cjs";
+/// Prefix of the final line the agent writes with the executor's exit code.
+const AGENT_EXIT_PREFIX: &str = "{\"firecrackerAgentExit\":";
+
+const POOL_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const POOL_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A tap device on the host for a VM's `eth0`, with the static address the
+/// guest kernel configures at boot.
+#[derive(Clone, Debug)]
+pub struct FirecrackerTap {
+    pub host_dev_name: String,
+    pub guest_ip: Ipv4Addr,
+    pub gateway_ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+}
+
+/// Parses `<host dev name>:<guest ip>:<gateway ip>:<netmask>`, e.g.
+/// `tap0:172.16.0.2:172.16.0.1:255.255.255.252`.
+impl FromStr for FirecrackerTap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let parts: Vec<_> = s.split(':').collect();
+        let [host_dev_name, guest_ip, gateway_ip, netmask] = parts[..] else {
+            anyhow::bail!(
+                "Invalid tap device {s:?}, expected <host dev name>:<guest ip>:<gateway \
+                 ip>:<netmask>"
+            );
+        };
+        Ok(Self {
+            host_dev_name: host_dev_name.to_string(),
+            guest_ip: guest_ip.parse()?,
+            gateway_ip: gateway_ip.parse()?,
+            netmask: netmask.parse()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FirecrackerNodeExecutorConfig {
+    pub firecracker_path: PathBuf,
+    pub kernel_image_path: PathBuf,
+    pub rootfs_path: PathBuf,
+    pub vcpu_count: u8,
+    pub mem_size_mib: u64,
+    /// Each running VM leases one of these, so they also bound the number of
+    /// VMs. If empty, VMs have no network and can't call back into the
+    /// backend.
+    pub taps: Vec<FirecrackerTap>,
+    /// Address VMs use to reach the backend, if it's different from the
+    /// backend's origin.
+    pub backend_address: Option<ConvexOrigin>,
+    /// Number of booted VMs to keep ready.
+    pub pool_size: usize,
+    pub boot_timeout: Duration,
+    pub node_process_timeout: Duration,
+}
+
+pub struct FirecrackerNodeExecutor {
+    inner: Arc<Inner>,
+    ready_vms: tokio::sync::Mutex<mpsc::Receiver<MicroVm>>,
+    pool_handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+struct Inner {
+    config: FirecrackerNodeExecutorConfig,
+    /// Source and source map of the executor script, sent to each VM.
+    executor_files: BTreeMap<String, Vec<u8>>,
+    taps: Option<TapPool>,
+}
+
+impl FirecrackerNodeExecutor {
+    pub fn new(config: FirecrackerNodeExecutorConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(config.pool_size > 0, "Firecracker VM pool can't be empty");
+        let (source, source_map) =
+            node_executor_file("local.cjs").expect("local.cjs not generated!");
+        let source_map = source_map.context("Missing local.cjs.map")?;
+        let executor_files = BTreeMap::from([
+            (GUEST_EXECUTOR_PATH.to_string(), source.as_bytes().to_vec()),
+            (
+                format!("{GUEST_EXECUTOR_PATH}.map"),
+                source_map.as_bytes().to_vec(),
+            ),
+        ]);
+        let taps = (!config.taps.is_empty()).then(|| TapPool::new(config.taps.clone()));
+        tracing::info!(
+            "Using firecracker node executor with rootfs {:?}, pool size {}",
+            config.rootfs_path,
+            config.pool_size
+        );
+        let inner = Arc::new(Inner {
+            config,
+            executor_files,
+            taps,
+        });
+        let (ready_tx, ready_rx) = mpsc::channel(inner.config.pool_size);
+        let pool_handle = tokio_spawn("firecracker_vm_pool", inner.clone().fill_pool(ready_tx));
+        Ok(Self {
+            inner,
+            ready_vms: tokio::sync::Mutex::new(ready_rx),
+            pool_handle: Mutex::new(Some(pool_handle)),
+        })
+    }
+
+    async fn take_vm(&self) -> anyhow::Result<MicroVm> {
+        // Allow for a VM that's partway through booting when we start waiting.
+        let wait = self.inner.config.boot_timeout * 2;
+        let vm = tokio::time::timeout(wait, async { self.ready_vms.lock().await.recv().await })
+            .await
+            .with_context(|| format!("No Firecracker VM became ready within {wait:?}"))?
+            .context("Firecracker VM pool shut down")?;
+        Ok(vm)
+    }
+}
+
+impl Inner {
+    async fn fill_pool(self: Arc<Self>, ready_tx: mpsc::Sender<MicroVm>) {
+        let mut backoff = Backoff::new(POOL_INITIAL_BACKOFF, POOL_MAX_BACKOFF);
+        loop {
+            // Reserve a slot first so we don't boot VMs nobody will use.
+            let Ok(permit) = ready_tx.reserve().await else {
+                return;
+            };
+            match MicroVm::boot(&self).await {
+                Ok(vm) => {
+                    backoff.reset();
+                    permit.send(vm);
+                },
+                Err(e) => {
+                    drop(permit);
+                    let delay = backoff.fail(&mut rand::thread_rng());
+                    tracing::error!("Failed to boot Firecracker VM, retrying in {delay:?}: {e:#}");
+                    tokio::time::sleep(delay).await;
+                },
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NodeExecutor for FirecrackerNodeExecutor {
+    fn enable(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn invoke(
+        &self,
+        mut request: ExecutorRequest,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        if let ExecutorRequest::Execute {
+            ref mut backend_address,
+            ..
+        } = request
+            && let Some(address) = &self.inner.config.backend_address
+        {
+            *backend_address = address.clone();
+        }
+        anyhow::ensure!(
+            request.node_version() == NodeVersion::default(),
+            ErrorMetadata::bad_request(
+                "UnsupportedNodeVersion",
+                format!(
+                    "This deployment runs Node.js actions in Firecracker VMs, which only support \
+                     Node.js {}",
+                    NodeVersion::default()
+                ),
+            )
+        );
+        let mut files = self.inner.executor_files.clone();
+        add_request_package_files(&request, &mut files).await?;
+        let request = serde_json::to_string(&JsonValue::try_from(request)?)?;
+
+        let vm = self.take_vm().await?;
+        let result = vm
+            .run(
+                files,
+                request,
+                &log_line_sender,
+                self.inner.config.node_process_timeout,
+            )
+            .await?;
+        let response = match result {
+            NodeProcessResult::Response(response) => response,
+            NodeProcessResult::TimedOut => EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+            NodeProcessResult::Failed(status) => {
+                anyhow::bail!("Node executor in Firecracker VM did not exit successfully: {status}")
+            },
+        };
+        Ok(InvokeResponse {
+            response,
+            memory_used_in_mb: self.inner.config.mem_size_mib,
+            aws_request_id: None,
+        })
+    }
+
+    fn shutdown(&self) {
+        if let Some(handle) = self.pool_handle.lock().take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Drop for FirecrackerNodeExecutor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Hands out tap devices so no two running VMs share one.
+struct TapPool {
+    free: Arc<Mutex<Vec<FirecrackerTap>>>,
+    semaphore: Arc<Semaphore>,
+}
+
+struct TapLease {
+    tap: FirecrackerTap,
+    free: Arc<Mutex<Vec<FirecrackerTap>>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TapPool {
+    fn new(taps: Vec<FirecrackerTap>) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(taps.len())),
+            free: Arc::new(Mutex::new(taps)),
+        }
+    }
+
+    async fn lease(&self) -> anyhow::Result<TapLease> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        let tap = self
+            .free
+            .lock()
+            .pop()
+            .context("Tap pool is empty despite holding a permit")?;
+        Ok(TapLease {
+            tap,
+            free: self.free.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+impl Drop for TapLease {
+    fn drop(&mut self) {
+        self.free.lock().push(self.tap.clone());
+    }
+}
+
+/// A booted VM with a connection to its agent. Dropping it kills the VM.
+struct MicroVm {
+    _dir: TempDir,
+    _process: Child,
+    _tap: Option<TapLease>,
+    stream: UnixStream,
+}
+
+impl MicroVm {
+    async fn boot(inner: &Inner) -> anyhow::Result<Self> {
+        let config = &inner.config;
+        let tap = match &inner.taps {
+            Some(taps) => Some(taps.lease().await?),
+            None => None,
+        };
+        let dir = TempDir::new()?;
+        let vsock_path = dir.path().join("vsock.sock");
+        let config_path = dir.path().join("config.json");
+        let vm_config = vm_config(config, tap.as_ref().map(|lease| &lease.tap), &vsock_path);
+        tokio::fs::write(&config_path, serde_json::to_vec(&vm_config)?).await?;
+
+        let process = TokioCommand::new(&config.firecracker_path)
+            .arg("--no-api")
+            .arg("--config-file")
+            .arg(&config_path)
+            // The guest's serial console.
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start firecracker")?;
+
+        let stream = tokio::time::timeout(config.boot_timeout, async {
+            loop {
+                match connect_to_agent(&vsock_path).await {
+                    Ok(stream) => break stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await
+        .context("Timed out waiting for Firecracker VM to boot")?;
+        Ok(Self {
+            _dir: dir,
+            _process: process,
+            _tap: tap,
+            stream,
+        })
+    }
+
+    async fn run(
+        self,
+        files: BTreeMap<String, Vec<u8>>,
+        request: String,
+        log_line_sender: &mpsc::UnboundedSender<LogLine>,
+        timeout: Duration,
+    ) -> anyhow::Result<NodeProcessResult> {
+        let Self {
+            _dir,
+            _process,
+            _tap,
+            stream,
+        } = self;
+        let (reader, mut writer) = stream.into_split();
+        let files: BTreeMap<String, String> = files
+            .into_iter()
+            .map(|(path, contents)| (path, base64::encode_urlsafe(&contents)))
+            .collect();
+        let header = json!({
+            "files": files,
+            "args": [GUEST_EXECUTOR_PATH, "--request", request],
+        });
+        let mut header = serde_json::to_vec(&header)?;
+        header.push(b'\n');
+        writer.write_all(&header).await?;
+        writer.flush().await?;
+
+        let lines = tokio_stream::wrappers::LinesStream::new(BufReader::new(reader).lines());
+        let output = lines
+            .map(|line| match line {
+                Ok(line) => match line.strip_prefix(AGENT_EXIT_PREFIX) {
+                    Some(rest) => Item::Done(parse_exit_code(rest)),
+                    None => Item::Stdout(line),
+                },
+                Err(e) => Item::Done(Err(e)),
+            })
+            // If the VM dies without the agent reporting an exit code, treat it
+            // as a failure rather than waiting for the timeout.
+            .chain(stream::once(async {
+                Item::Done(Err(std::io::Error::other(
+                    "Firecracker VM closed the connection",
+                )))
+            }));
+        collect_node_output(output, log_line_sender, timeout).await
+    }
+}
+
+fn parse_exit_code(rest: &str) -> std::io::Result<ExitStatus> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let code: i32 = rest
+        .trim_end_matches('}')
+        .trim()
+        .parse()
+        .map_err(std::io::Error::other)?;
+    // `from_raw` takes a wait status, which has the exit code in the second
+    // byte.
+    Ok(ExitStatus::from_raw((code & 0xff) << 8))
+}
+
+async fn connect_to_agent(vsock_path: &std::path::Path) -> anyhow::Result<UnixStream> {
+    let mut stream = UnixStream::connect(vsock_path).await?;
+    stream
+        .write_all(format!("CONNECT {AGENT_PORT}\n").as_bytes())
+        .await?;
+    // Firecracker answers the handshake with `OK <host port>\n`. Read it a byte
+    // at a time so we don't consume any of the agent's output.
+    let mut response = vec![];
+    loop {
+        let mut byte = [0u8; 1];
+        let n = tokio::io::AsyncReadExt::read(&mut stream, &mut byte).await?;
+        anyhow::ensure!(n == 1, "vsock closed during handshake");
+        if byte[0] == b'\n' {
+            break;
+        }
+        response.push(byte[0]);
+    }
+    anyhow::ensure!(
+        response.starts_with(b"OK "),
+        "Unexpected vsock handshake response: {}",
+        String::from_utf8_lossy(&response)
+    );
+    Ok(stream)
+}
+
+fn vm_config(
+    config: &FirecrackerNodeExecutorConfig,
+    tap: Option<&FirecrackerTap>,
+    vsock_path: &std::path::Path,
+) -> JsonValue {
+    let mut boot_args = "console=ttyS0 reboot=k panic=1 pci=off".to_string();
+    let mut network_interfaces = vec![];
+    if let Some(tap) = tap {
+        boot_args.push_str(&format!(
+            " ip={}::{}:{}::eth0:off",
+            tap.guest_ip, tap.gateway_ip, tap.netmask
+        ));
+        network_interfaces.push(json!({
+            "iface_id": "eth0",
+            "host_dev_name": tap.host_dev_name,
+        }));
+    }
+    json!({
+        "boot-source": {
+            "kernel_image_path": config.kernel_image_path,
+            "boot_args": boot_args,
+        },
+        "drives": [{
+            "drive_id": "rootfs",
+            "path_on_host": config.rootfs_path,
+            "is_root_device": true,
+            "is_read_only": true,
+        }],
+        "machine-config": {
+            "vcpu_count": config.vcpu_count,
+            "mem_size_mib": config.mem_size_mib,
+        },
+        "network-interfaces": network_interfaces,
+        "vsock": {
+            "guest_cid": 3,
+            "uds_path": vsock_path,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::process::ExitStatusExt,
+        time::Duration,
+    };
+
+    use super::{
+        parse_exit_code,
+        vm_config,
+        FirecrackerNodeExecutorConfig,
+        FirecrackerTap,
+    };
+
+    #[test]
+    fn test_parse_exit_code() -> anyhow::Result<()> {
+        assert!(parse_exit_code("0}")?.success());
+        assert_eq!(parse_exit_code(" 1 }")?.code(), Some(1));
+        assert_eq!(parse_exit_code("137}")?.into_raw(), 137 << 8);
+        assert!(parse_exit_code("oops}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tap() -> anyhow::Result<()> {
+        let tap: FirecrackerTap = "tap0:172.16.0.2:172.16.0.1:255.255.255.252".parse()?;
+        assert_eq!(tap.host_dev_name, "tap0");
+        assert_eq!(tap.guest_ip.to_string(), "172.16.0.2");
+        assert!("tap0:172.16.0.2".parse::<FirecrackerTap>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vm_config_network() {
+        let config = FirecrackerNodeExecutorConfig {
+            firecracker_path: "/usr/bin/firecracker".into(),
+            kernel_image_path: "/var/convex/vmlinux".into(),
+            rootfs_path: "/var/convex/rootfs.ext4".into(),
+            vcpu_count: 1,
+            mem_size_mib: 512,
+            taps: vec![],
+            backend_address: None,
+            pool_size: 1,
+            boot_timeout: Duration::from_secs(5),
+            node_process_timeout: Duration::from_secs(5),
+        };
+        let tap = FirecrackerTap {
+            host_dev_name: "tap0".to_string(),
+            guest_ip: "172.16.0.2".parse().unwrap(),
+            gateway_ip: "172.16.0.1".parse().unwrap(),
+            netmask: "255.255.255.252".parse().unwrap(),
+        };
+        let without_network = vm_config(&config, None, "/tmp/vsock.sock".as_ref());
+        assert_eq!(without_network["network-interfaces"], serde_json::json!([]));
+        assert_eq!(without_network["drives"][0]["is_read_only"], true);
+
+        let with_network = vm_config(&config, Some(&tap), "/tmp/vsock.sock".as_ref());
+        assert_eq!(
+            with_network["network-interfaces"][0]["host_dev_name"],
+            "tap0"
+        );
+        assert!(with_network["boot-source"]["boot_args"]
+            .as_str()
+            .unwrap()
+            .ends_with("ip=172.16.0.2::172.16.0.1:255.255.255.252::eth0:off"));
+    }
+}
@@ -0,0 +1,436 @@
+//! Runs Node actions as AWS Lambda functions, so bursty action traffic is
+//! served by serverless capacity instead of the backend's host.
+//!
+//! The executor manages one function per Node.js version, named
+//! `<function_prefix>-node<major>`, that runs the `aws_lambda.cjs` bundle.
+//! Functions are packaged and created, or updated if they run an older
+//! bundle, when Node actions are pushed or the first time they're needed,
+//! and each request is a synchronous invocation with the same request the
+//! local executor passes to `local.cjs` as its payload. The payload the
+//! function returns is the newline-delimited JSON `local.cjs` prints.
+//!
+//! Requests go straight to the Lambda API, signed with AWS Signature Version
+//! 4. Lambda can't read the backend's local storage, so modules storage must
+//! be reachable over HTTP (e.g. S3), and actions must be able to reach the
+//! backend at `backend_address` to call back into it.
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use async_zip_0_0_9::{
+    write::ZipFileWriter,
+    Compression,
+    ZipEntryBuilder,
+    ZipEntryBuilderExt,
+};
+use chrono::Utc;
+use common::{
+    aws::{
+        sign_request,
+        AwsCredentials,
+    },
+    log_lines::LogLine,
+    runtime::tokio_spawn,
+    types::{
+        ConvexOrigin,
+        NodeVersion,
+    },
+};
+use isolate::bundled_js::{
+    node_executor_file,
+    node_executor_files_sha256,
+};
+use reqwest::{
+    Method,
+    StatusCode,
+    Url,
+};
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use tokio::sync::{
+    mpsc,
+    OnceCell,
+};
+
+use crate::{
+    executor::{
+        parse_streamed_response,
+        ExecutorRequest,
+        InvokeResponse,
+        NodeExecutor,
+        ResponsePart,
+        EXECUTE_TIMEOUT_RESPONSE_JSON,
+    },
+    source_package::ensure_no_local_packages,
+};
+
+const LAMBDA_API_VERSION: &str = "2015-03-31";
+
+/// Lambda's maximum function timeout.
+const MAX_LAMBDA_TIMEOUT: Duration = Duration::from_secs(900);
+
+const DEPLOY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEPLOY_MAX_POLLS: usize = 120;
+
+#[derive(Clone, Debug)]
+pub struct LambdaNodeExecutorConfig {
+    pub region: String,
+    /// Prefix of the names of the functions the executor manages.
+    pub function_prefix: String,
+    /// Execution role of the functions. Actions only need it to write logs.
+    pub role_arn: String,
+    pub memory_mb: u64,
+    pub credentials: AwsCredentials,
+    /// Address the functions use to reach the backend, if it's different
+    /// from the backend's origin.
+    pub backend_address: Option<ConvexOrigin>,
+    pub node_process_timeout: Duration,
+    /// Lambda API endpoint, if not the region's default.
+    pub endpoint: Option<Url>,
+}
+
+pub struct LambdaNodeExecutor {
+    functions: Arc<LambdaFunctions>,
+}
+
+impl LambdaNodeExecutor {
+    pub fn new(config: LambdaNodeExecutorConfig) -> anyhow::Result<Self> {
+        let endpoint = match &config.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => format!("https://lambda.{}.amazonaws.com/", config.region).parse()?,
+        };
+        tracing::info!(
+            "Using lambda node executor with functions {}-node* in {}",
+            config.function_prefix,
+            config.region,
+        );
+        Ok(Self {
+            functions: Arc::new(LambdaFunctions {
+                client: reqwest::Client::new(),
+                endpoint,
+                config,
+                deployed: NodeVersion::ALL
+                    .into_iter()
+                    .map(|version| (version, OnceCell::new()))
+                    .collect(),
+            }),
+        })
+    }
+}
+
+#[async_trait]
+impl NodeExecutor for LambdaNodeExecutor {
+    fn enable(&self) -> anyhow::Result<()> {
+        // Node actions are being pushed, so get the function ready before
+        // they're first run.
+        let functions = self.functions.clone();
+        tokio_spawn("lambda_node_executor_deploy", async move {
+            if let Err(e) = functions.ensure_deployed(NodeVersion::default()).await {
+                tracing::error!("Failed to deploy lambda node executor: {e:#}");
+            }
+        });
+        Ok(())
+    }
+
+    async fn invoke(
+        &self,
+        mut request: ExecutorRequest,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        let config = &self.functions.config;
+        if let ExecutorRequest::Execute {
+            ref mut backend_address,
+            ..
+        } = request
+            && let Some(address) = &config.backend_address
+        {
+            *backend_address = address.clone();
+        }
+        ensure_no_local_packages(&request)?;
+        let node_version = request.node_version();
+        self.functions.ensure_deployed(node_version).await?;
+        let payload = serde_json::to_vec(&JsonValue::try_from(request)?)?;
+        let result = tokio::time::timeout(
+            config.node_process_timeout,
+            self.functions
+                .invoke(node_version, payload, &log_line_sender),
+        )
+        .await;
+        match result {
+            Ok(result) => result,
+            Err(_) => Ok(InvokeResponse {
+                response: EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+                memory_used_in_mb: config.memory_mb,
+                aws_request_id: None,
+            }),
+        }
+    }
+
+    fn shutdown(&self) {}
+}
+
+struct LambdaFunctions {
+    client: reqwest::Client,
+    endpoint: Url,
+    config: LambdaNodeExecutorConfig,
+    /// Set once the function for a version runs the current bundle.
+    deployed: BTreeMap<NodeVersion, OnceCell<()>>,
+}
+
+impl LambdaFunctions {
+    fn function_name(&self, version: NodeVersion) -> String {
+        format!("{}-node{}", self.config.function_prefix, version.major())
+    }
+
+    /// Identifies the bundle a function runs, so outdated functions can be
+    /// updated.
+    fn description() -> String {
+        format!(
+            "Convex node executor {}",
+            node_executor_files_sha256().as_hex()
+        )
+    }
+
+    fn function_config(&self, version: NodeVersion) -> JsonValue {
+        json!({
+            "Runtime": format!("nodejs{}.x", version.major()),
+            "Role": self.config.role_arn,
+            "Handler": "aws_lambda.handler",
+            "MemorySize": self.config.memory_mb,
+            "Timeout": self.config.node_process_timeout.min(MAX_LAMBDA_TIMEOUT).as_secs(),
+            "Description": Self::description(),
+        })
+    }
+
+    async fn ensure_deployed(&self, version: NodeVersion) -> anyhow::Result<()> {
+        self.deployed[&version]
+            .get_or_try_init(|| self.deploy(version))
+            .await?;
+        Ok(())
+    }
+
+    async fn deploy(&self, version: NodeVersion) -> anyhow::Result<()> {
+        let name = self.function_name(version);
+        match self.get_function(&name).await? {
+            None => {
+                tracing::info!("Creating lambda function {name}");
+                let mut body = self.function_config(version);
+                body["FunctionName"] = name.clone().into();
+                body["Code"] = json!({ "ZipFile": base64::encode(package_executor().await?) });
+                self.send_json(Method::POST, "functions", &body).await?;
+            },
+            Some(function) if function["Configuration"]["Description"] != Self::description() => {
+                tracing::info!("Updating lambda function {name}");
+                self.send_json(
+                    Method::PUT,
+                    &format!("functions/{name}/code"),
+                    &json!({ "ZipFile": base64::encode(package_executor().await?) }),
+                )
+                .await?;
+                // Configuration can't be updated while the code update is in
+                // progress.
+                self.wait_until_ready(&name).await?;
+                self.send_json(
+                    Method::PUT,
+                    &format!("functions/{name}/configuration"),
+                    &self.function_config(version),
+                )
+                .await?;
+            },
+            Some(_) => {},
+        }
+        self.wait_until_ready(&name).await
+    }
+
+    async fn get_function(&self, name: &str) -> anyhow::Result<Option<JsonValue>> {
+        let response = self
+            .send(Method::GET, &format!("functions/{name}"), vec![], &[])
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(check_response(response).await?.json().await?))
+    }
+
+    /// Waits until the function is done being created or updated.
+    async fn wait_until_ready(&self, name: &str) -> anyhow::Result<()> {
+        for _ in 0..DEPLOY_MAX_POLLS {
+            let function = self
+                .get_function(name)
+                .await?
+                .with_context(|| format!("Lambda function {name} disappeared"))?;
+            let configuration = &function["Configuration"];
+            let state = configuration["State"].as_str();
+            let update_status = configuration["LastUpdateStatus"].as_str();
+            if state == Some("Failed") || update_status == Some("Failed") {
+                anyhow::bail!(
+                    "Failed to deploy lambda function {name}: {} {}",
+                    configuration["StateReason"],
+                    configuration["LastUpdateStatusReason"],
+                );
+            }
+            if state == Some("Active") && update_status != Some("InProgress") {
+                return Ok(());
+            }
+            tokio::time::sleep(DEPLOY_POLL_INTERVAL).await;
+        }
+        anyhow::bail!("Timed out waiting for lambda function {name} to deploy")
+    }
+
+    async fn invoke(
+        &self,
+        version: NodeVersion,
+        payload: Vec<u8>,
+        log_line_sender: &mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        let name = self.function_name(version);
+        let response = self
+            .send(
+                Method::POST,
+                &format!("functions/{name}/invocations"),
+                payload,
+                &[("x-amz-invocation-type", "RequestResponse")],
+            )
+            .await?;
+        let response = check_response(response).await?;
+        let aws_request_id = response
+            .headers()
+            .get("x-amzn-requestid")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let function_error = response
+            .headers()
+            .get("x-amz-function-error")
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+        let body = response.text().await?;
+        let memory_used_in_mb = self.config.memory_mb;
+
+        if let Some(function_error) = function_error {
+            // The function itself failed, e.g. by running out of memory or
+            // time, rather than the action throwing.
+            let error: JsonValue = serde_json::from_str(&body).unwrap_or_default();
+            let message = error["errorMessage"].as_str().unwrap_or(&body);
+            if message.contains("Task timed out") {
+                return Ok(InvokeResponse {
+                    response: EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+                    memory_used_in_mb,
+                    aws_request_id,
+                });
+            }
+            anyhow::bail!(
+                "Lambda function {name} failed ({function_error}, request {aws_request_id:?}): \
+                 {message}"
+            );
+        }
+
+        let mut result_values = vec![];
+        for part in parse_streamed_response(&body)? {
+            match part {
+                ResponsePart::LogLine(log_line) => log_line_sender.send(log_line)?,
+                ResponsePart::Result(result) => result_values.push(result),
+            }
+        }
+        anyhow::ensure!(
+            result_values.len() <= 1,
+            "Received more than one result from lambda response"
+        );
+        let response = result_values
+            .pop()
+            .context("Received no result from lambda response")?;
+        Ok(InvokeResponse {
+            response,
+            memory_used_in_mb,
+            aws_request_id,
+        })
+    }
+
+    async fn send_json(
+        &self,
+        method: Method,
+        path: &str,
+        body: &JsonValue,
+    ) -> anyhow::Result<JsonValue> {
+        let response = self
+            .send(
+                method,
+                path,
+                serde_json::to_vec(body)?,
+                &[("content-type", "application/json")],
+            )
+            .await?;
+        Ok(check_response(response).await?.json().await?)
+    }
+
+    /// Sends a signed request to `path` under the Lambda API.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Vec<u8>,
+        headers: &[(&str, &str)],
+    ) -> anyhow::Result<reqwest::Response> {
+        let url = self
+            .endpoint
+            .join(&format!("{LAMBDA_API_VERSION}/{path}"))?;
+        let mut headers: BTreeMap<String, String> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        sign_request(
+            &self.config.credentials,
+            &self.config.region,
+            "lambda",
+            &method,
+            &url,
+            &mut headers,
+            &body,
+            Utc::now(),
+        )?;
+        let mut builder = self.client.request(method, url.clone()).body(body);
+        for (name, value) in &headers {
+            // reqwest sets the host header itself.
+            if name != "host" {
+                builder = builder.header(name, value);
+            }
+        }
+        builder
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach the Lambda API at {url}"))
+    }
+}
+
+async fn check_response(response: reqwest::Response) -> anyhow::Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("Lambda API returned {status}: {body}");
+    }
+    Ok(response)
+}
+
+/// Zips the `aws_lambda.cjs` bundle for uploading as function code.
+async fn package_executor() -> anyhow::Result<Vec<u8>> {
+    let (source, source_map) =
+        node_executor_file("aws_lambda.cjs").context("aws_lambda.cjs not generated!")?;
+    let mut buf = vec![];
+    let mut writer = ZipFileWriter::new(&mut buf);
+    let builder = ZipEntryBuilder::new("aws_lambda.cjs".to_string(), Compression::Deflate)
+        .unix_permissions(0o644);
+    writer.write_entry_whole(builder, source.as_bytes()).await?;
+    if let Some(source_map) = source_map {
+        let builder = ZipEntryBuilder::new("aws_lambda.cjs.map".to_string(), Compression::Deflate)
+            .unix_permissions(0o644);
+        writer
+            .write_entry_whole(builder, source_map.as_bytes())
+            .await?;
+    }
+    writer.close().await?;
+    Ok(buf)
+}
@@ -6,10 +6,17 @@
 #![feature(stmt_expr_attributes)]
 #![feature(try_blocks)]
 
+pub mod docker;
 mod executor;
+#[cfg(target_os = "linux")]
+pub mod firecracker;
+pub mod lambda;
 pub mod local;
 mod metrics;
+pub mod remote;
+pub mod resource_limits;
 pub mod source_package;
+pub mod worker_pool;
 
 pub use crate::executor::{
     error_response_json,
@@ -1,19 +1,32 @@
 use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    ffi::OsString,
     fs,
+    iter,
     path::PathBuf,
+    process::ExitStatus,
+    sync::Arc,
     time::Duration,
 };
 
 use anyhow::Context;
 use async_trait::async_trait;
-use common::log_lines::LogLine;
+use common::{
+    log_lines::LogLine,
+    types::NodeVersion,
+};
 use errors::ErrorMetadata;
 use futures::{
     select_biased,
     FutureExt,
+    Stream,
     StreamExt,
 };
 use isolate::bundled_js::node_executor_file;
+use parking_lot::Mutex;
 use serde_json::Value as JsonValue;
 use tempfile::TempDir;
 use tokio::{
@@ -25,80 +38,252 @@ use tokio_process_stream::{
     ProcessLineStream,
 };
 
-use crate::executor::{
-    parse_streamed_response,
-    ExecutorRequest,
-    InvokeResponse,
-    NodeExecutor,
-    ResponsePart,
-    EXECUTE_TIMEOUT_RESPONSE_JSON,
+use crate::{
+    executor::{
+        error_response_json,
+        parse_streamed_response,
+        ExecutorRequest,
+        InvokeResponse,
+        NodeExecutor,
+        ResponsePart,
+        EXECUTE_TIMEOUT_RESPONSE_JSON,
+    },
+    resource_limits::{
+        NodeResourceLimits,
+        NodeSandbox,
+    },
+    worker_pool::{
+        NodeWorkerPool,
+        NodeWorkerPoolConfig,
+    },
 };
 
-/// Always use node version specified in .nvmrc for lambda execution, even if
-/// we're using older version for CLI.
+/// Always use node version specified in .nvmrc for the default runtime, even
+/// if we're using older version for CLI.
 const NODE_VERSION: &str = include_str!("../../../.nvmrc");
 
+#[derive(Clone, Debug)]
+pub struct LocalNodeExecutorConfig {
+    pub node_process_timeout: Duration,
+    /// Reuse a pool of warm Node.js processes if set, instead of starting one
+    /// per invocation.
+    pub worker_pool: Option<NodeWorkerPoolConfig>,
+    pub resource_limits: NodeResourceLimits,
+    /// Directory Node.js processes share unpacked source packages and
+    /// node_modules trees in, keyed by package hash, so they're downloaded
+    /// and unpacked once per deploy rather than once per process. Defaults to
+    /// a temp dir that's removed along with the executor.
+    pub package_cache_dir: Option<PathBuf>,
+    /// Number of source packages, and separately of node_modules trees, to
+    /// keep in the package cache.
+    pub package_cache_max_entries: usize,
+}
+
+impl LocalNodeExecutorConfig {
+    pub fn new(node_process_timeout: Duration) -> Self {
+        Self {
+            node_process_timeout,
+            worker_pool: None,
+            resource_limits: NodeResourceLimits::default(),
+            package_cache_dir: None,
+            package_cache_max_entries: 16,
+        }
+    }
+}
+
 pub struct LocalNodeExecutor {
     _source_dir: TempDir,
+    _package_cache_dir: Option<TempDir>,
     source_path: PathBuf,
-    node_path: String,
+    /// Arguments to start `local.cjs` with, before the request or `--worker`.
+    executor_args: Vec<OsString>,
+    node_paths: BTreeMap<NodeVersion, String>,
+    /// Versions that `check_version` found installed.
+    checked_versions: Mutex<BTreeSet<NodeVersion>>,
     node_process_timeout: Duration,
+    sandbox: Arc<NodeSandbox>,
+    worker_pool: Option<Arc<NodeWorkerPool>>,
 }
 
 impl LocalNodeExecutor {
+    /// Runs each invocation in a new Node.js process.
     pub fn new(node_process_timeout: Duration) -> anyhow::Result<Self> {
-        // Write the source of local.cjs to a temp file.
-        let source_dir = TempDir::new()?;
-        let (source, source_map) =
-            node_executor_file("local.cjs").expect("local.cjs not generated!");
-        let source_map = source_map.context("Missing local.cjs.map")?;
-        let source_path = source_dir.path().join("local.cjs");
-        let source_map_path = source_dir.path().join("local.cjs.map");
-        fs::write(&source_path, source.as_bytes())?;
-        fs::write(source_map_path, source_map.as_bytes())?;
+        Self::new_with_config(LocalNodeExecutorConfig::new(node_process_timeout))
+    }
+
+    /// Runs invocations on a pool of reused Node.js worker processes. Must be
+    /// called within a tokio runtime, since it starts warming up the pool.
+    pub fn new_with_worker_pool(
+        node_process_timeout: Duration,
+        pool_config: NodeWorkerPoolConfig,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_config(LocalNodeExecutorConfig {
+            worker_pool: Some(pool_config),
+            ..LocalNodeExecutorConfig::new(node_process_timeout)
+        })
+    }
+
+    /// Must be called within a tokio runtime if `config.worker_pool` is set.
+    pub fn new_with_config(config: LocalNodeExecutorConfig) -> anyhow::Result<Self> {
+        let (source_dir, source_path) = write_executor_source()?;
         tracing::info!(
             "Using local node executor. Source: {}",
             source_path.to_str().expect("Path is not UTF-8 string?"),
         );
-        let node_version = NODE_VERSION.trim();
-
-        // Look for node in a few places.
-        let possible_path = home::home_dir()
-            .unwrap()
-            .join(".nvm")
-            .join(format!("versions/node/v{node_version}/bin/node"));
-        let node_path = if possible_path.exists() {
-            possible_path.to_string_lossy().to_string()
-        } else {
-            "node".to_string()
+        let (package_cache_temp_dir, package_cache_dir) = match config.package_cache_dir {
+            Some(dir) => {
+                fs::create_dir_all(&dir)?;
+                (None, dir)
+            },
+            None => {
+                let temp_dir = TempDir::new()?;
+                let dir = temp_dir.path().to_path_buf();
+                (Some(temp_dir), dir)
+            },
         };
+        let executor_args = vec![
+            source_path.clone().into_os_string(),
+            "--cache-dir".into(),
+            package_cache_dir.into_os_string(),
+            "--cache-max-entries".into(),
+            config.package_cache_max_entries.to_string().into(),
+        ];
+        let node_paths = NodeVersion::ALL
+            .into_iter()
+            .map(|version| (version, find_node(version)))
+            .collect::<BTreeMap<_, _>>();
+        let sandbox = Arc::new(NodeSandbox::new(config.resource_limits)?);
+        let worker_pool = config.worker_pool.map(|pool_config| {
+            tracing::info!(
+                "Keeping {} warm node workers, each reused for up to {} invocations",
+                pool_config.size,
+                pool_config.max_invocations
+            );
+            // Workers run each invocation's modules in a fresh `vm` context.
+            let worker_args = iter::once("--experimental-vm-modules".into())
+                .chain(executor_args.iter().cloned())
+                .collect();
+            NodeWorkerPool::new(
+                pool_config,
+                worker_args,
+                node_paths.clone(),
+                sandbox.clone(),
+            )
+        });
 
         Ok(Self {
             _source_dir: source_dir,
+            _package_cache_dir: package_cache_temp_dir,
             source_path,
-            node_path,
-            node_process_timeout,
+            executor_args,
+            node_paths,
+            checked_versions: Mutex::new(BTreeSet::new()),
+            node_process_timeout: config.node_process_timeout,
+            sandbox,
+            worker_pool,
         })
     }
 
-    async fn check_version(&self) -> anyhow::Result<()> {
-        let cmd = TokioCommand::new(&self.node_path)
+    fn node_path(&self, version: NodeVersion) -> &str {
+        &self.node_paths[&version]
+    }
+
+    async fn check_version(&self, version: NodeVersion) -> anyhow::Result<()> {
+        if self.checked_versions.lock().contains(&version) {
+            return Ok(());
+        }
+        let cmd = TokioCommand::new(self.node_path(version))
             .arg("--version")
             .output()
             .await?;
-        let version = String::from_utf8_lossy(&cmd.stdout);
+        let installed = String::from_utf8_lossy(&cmd.stdout);
 
-        if !version.starts_with("v18.") {
+        if !installed.starts_with(&format!("v{}.", version.major())) {
             anyhow::bail!(ErrorMetadata::bad_request(
                 "DeploymentNotConfiguredForNodeActions",
-                "Deployment is not configured to deploy \"use node\" actions. \
-                 Node.js v18 is not installed. \
-                 Install Node.js 18 with nvm (https://github.com/nvm-sh/nvm) \
-                 to deploy Node.js actions."
+                format!(
+                    "Deployment is not configured to deploy \"use node\" actions. Node.js \
+                     v{version} is not installed. Install Node.js {version} with nvm \
+                     (https://github.com/nvm-sh/nvm) to deploy Node.js actions."
+                )
             ))
         }
+        self.checked_versions.lock().insert(version);
         Ok(())
     }
+
+    async fn run_on_worker(
+        &self,
+        worker_pool: &Arc<NodeWorkerPool>,
+        node_version: NodeVersion,
+        request: &str,
+        log_line_sender: &mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<JsonValue> {
+        let mut worker = worker_pool.take(node_version).await?;
+        let result = worker
+            .run(request, log_line_sender, self.node_process_timeout)
+            .await;
+        let oom_killed = worker.oom_killed();
+        // Workers that failed, timed out, or exited are replaced.
+        if matches!(result, Ok(NodeProcessResult::Response(_))) {
+            worker_pool.put(node_version, worker);
+        } else {
+            worker_pool.discard(node_version, worker);
+        }
+        self.response(result?, oom_killed)
+    }
+
+    fn response(&self, result: NodeProcessResult, oom_killed: bool) -> anyhow::Result<JsonValue> {
+        let response = match result {
+            NodeProcessResult::Response(response) => response,
+            NodeProcessResult::TimedOut => EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+            NodeProcessResult::Failed(_) => {
+                // Going over the memory limit is the action's fault, so
+                // report it to the user instead of as a system error.
+                if oom_killed && let Some(memory_limit_mb) = self.sandbox.memory_limit_mb() {
+                    error_response_json(&format!(
+                        "Node action ran out of memory (limit: {memory_limit_mb} MB)"
+                    ))
+                } else {
+                    anyhow::bail!("Local process did not exit successfully")
+                }
+            },
+        };
+        Ok(response)
+    }
+}
+
+/// Looks for a node binary of the given major version in a few places.
+fn find_node(version: NodeVersion) -> String {
+    let nvm_versions = home::home_dir().unwrap().join(".nvm").join("versions/node");
+    // Use the exact version in .nvmrc for the default runtime.
+    if version == NodeVersion::default() {
+        let possible_path = nvm_versions.join(format!("v{}/bin/node", NODE_VERSION.trim()));
+        if possible_path.exists() {
+            return possible_path.to_string_lossy().to_string();
+        }
+    }
+    // Otherwise use the newest installed release of the major version.
+    let prefix = format!("v{}.", version.major());
+    let newest = fs::read_dir(&nvm_versions)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let release: Vec<u32> = name
+                .strip_prefix(&prefix)?
+                .split('.')
+                .map(|part| part.parse().ok())
+                .collect::<Option<_>>()?;
+            let node_path = entry.path().join("bin/node");
+            node_path.exists().then_some((release, node_path))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+    match newest {
+        Some((_, node_path)) => node_path.to_string_lossy().to_string(),
+        None => "node".to_string(),
+    }
 }
 
 #[async_trait]
@@ -112,59 +297,33 @@ impl NodeExecutor for LocalNodeExecutor {
         request: ExecutorRequest,
         log_line_sender: mpsc::UnboundedSender<LogLine>,
     ) -> anyhow::Result<InvokeResponse> {
+        let node_version = request.node_version();
         let request = JsonValue::try_from(request)?;
-        self.check_version().await?;
+        self.check_version(node_version).await?;
         let request = serde_json::to_string(&request)?;
-        tracing::info!(
-            "{} {} --request='{}'",
-            &self.node_path,
-            self.source_path.to_str().expect("Must be utf-8"),
-            &request,
-        );
-        let mut _cmd = TokioCommand::new(&self.node_path);
-        let cmd = _cmd
-            .arg(&self.source_path)
-            .arg("--request")
-            .arg(request)
-            .kill_on_drop(true);
-        let mut result_values = vec![];
-        let mut err_lines = vec![];
-
-        let mut procstream = ProcessLineStream::try_from(cmd)?.fuse();
-
-        let response = loop {
-            select_biased! {
-                item = procstream.select_next_some() => {
-                    match item {
-                        Item::Stdout(line) => {
-                            let parts = parse_streamed_response(&line)?;
-                            for part in parts {
-                                match part {
-                                    ResponsePart::LogLine(log_line) => {
-                                        log_line_sender.send(log_line)?;
-                                    },
-                                    ResponsePart::Result(result) => result_values.push(result)
-                                }
-                            }
-                        },
-                        Item::Done(status) => {
-                            if !status?.success() {
-                                for line in err_lines {
-                                    tracing::error!("{line}");
-                                }
-                                anyhow::bail!("Local process did not exit successfully");
-                            }
-                            anyhow::ensure!(result_values.len() <= 1, "Received more than one result from lambda response");
-                            let value = result_values.pop().ok_or_else(|| anyhow::anyhow!("Received no result from lambda response"))?;
-                            break value;
-                        }
-                        Item::Stderr(line) => err_lines.push(line),
-                    }
-                },
-                _ = tokio::time::sleep(self.node_process_timeout).fuse() => {
-                    break EXECUTE_TIMEOUT_RESPONSE_JSON.clone();
-                },
-            }
+        let response = match &self.worker_pool {
+            Some(worker_pool) => {
+                self.run_on_worker(worker_pool, node_version, &request, &log_line_sender)
+                    .await?
+            },
+            None => {
+                let node_path = self.node_path(node_version);
+                tracing::info!(
+                    "{} {} --request='{}'",
+                    node_path,
+                    self.source_path.to_str().expect("Must be utf-8"),
+                    &request,
+                );
+                let (mut cmd, cgroup) = self.sandbox.command(node_path)?;
+                cmd.args(&self.executor_args)
+                    .arg("--request")
+                    .arg(request)
+                    .kill_on_drop(true);
+                let result =
+                    run_node_process(&mut cmd, &log_line_sender, self.node_process_timeout).await?;
+                let oom_killed = cgroup.as_ref().is_some_and(|cgroup| cgroup.oom_killed());
+                self.response(result, oom_killed)?
+            },
         };
         Ok(InvokeResponse {
             response,
@@ -174,7 +333,92 @@ impl NodeExecutor for LocalNodeExecutor {
         })
     }
 
-    fn shutdown(&self) {}
+    fn shutdown(&self) {
+        if let Some(worker_pool) = &self.worker_pool {
+            worker_pool.shutdown();
+        }
+    }
+}
+
+/// Writes the source of local.cjs to a temp dir, returning the dir and the
+/// path of the script within it.
+pub(crate) fn write_executor_source() -> anyhow::Result<(TempDir, PathBuf)> {
+    let source_dir = TempDir::new()?;
+    let (source, source_map) = node_executor_file("local.cjs").expect("local.cjs not generated!");
+    let source_map = source_map.context("Missing local.cjs.map")?;
+    let source_path = source_dir.path().join("local.cjs");
+    let source_map_path = source_dir.path().join("local.cjs.map");
+    fs::write(&source_path, source.as_bytes())?;
+    fs::write(source_map_path, source_map.as_bytes())?;
+    Ok((source_dir, source_path))
+}
+
+/// How a node executor process ended.
+pub(crate) enum NodeProcessResult {
+    Response(JsonValue),
+    TimedOut,
+    Failed(ExitStatus),
+}
+
+/// Runs a node executor process, forwarding its log lines to
+/// `log_line_sender` and returning the single result it prints to stdout.
+pub(crate) async fn run_node_process(
+    cmd: &mut TokioCommand,
+    log_line_sender: &mpsc::UnboundedSender<LogLine>,
+    timeout: Duration,
+) -> anyhow::Result<NodeProcessResult> {
+    let procstream = ProcessLineStream::try_from(cmd)?;
+    collect_node_output(procstream, log_line_sender, timeout).await
+}
+
+/// Consumes the output of a node executor, which may be running somewhere
+/// other than a local process, until it exits or `timeout` elapses.
+pub(crate) async fn collect_node_output(
+    output: impl Stream<Item = Item<String>>,
+    log_line_sender: &mpsc::UnboundedSender<LogLine>,
+    timeout: Duration,
+) -> anyhow::Result<NodeProcessResult> {
+    let mut result_values = vec![];
+    let mut err_lines = vec![];
+
+    let mut procstream = std::pin::pin!(output.fuse());
+
+    let result = loop {
+        select_biased! {
+            item = procstream.select_next_some() => {
+                match item {
+                    Item::Stdout(line) => {
+                        let parts = parse_streamed_response(&line)?;
+                        for part in parts {
+                            match part {
+                                ResponsePart::LogLine(log_line) => {
+                                    log_line_sender.send(log_line)?;
+                                },
+                                ResponsePart::Result(result) => result_values.push(result)
+                            }
+                        }
+                    },
+                    Item::Done(status) => {
+                        let status = status?;
+                        if !status.success() {
+                            for line in err_lines {
+                                tracing::error!("{line}");
+                            }
+                            break NodeProcessResult::Failed(status);
+                        }
+                        anyhow::ensure!(result_values.len() <= 1, "Received more than one result from lambda response");
+                        let value = result_values.pop().ok_or_else(|| anyhow::anyhow!("Received no result from lambda response"))?;
+                        break NodeProcessResult::Response(value);
+                    }
+                    Item::Stderr(line) => err_lines.push(line),
+                }
+            },
+            _ = tokio::time::sleep(timeout).fuse() => {
+                break NodeProcessResult::TimedOut;
+            },
+        }
+    };
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -189,6 +433,7 @@ mod tests {
         time::Duration,
     };
 
+    use async_trait::async_trait;
     use cmd_util::env::config_test;
     use common::{
         assert_obj,
@@ -196,11 +441,17 @@ mod tests {
         fastrace_helpers::EncodedSpan,
         log_lines::{
             run_function_and_collect_log_lines,
+            LogLevel,
+            LogLine,
             LogLines,
         },
-        runtime::Runtime,
+        runtime::{
+            Runtime,
+            UnixTimestamp,
+        },
         types::{
             ModuleEnvironment,
+            NodeVersion,
             UdfType,
         },
         value::ConvexValue,
@@ -240,7 +491,17 @@ mod tests {
         CanonicalizedModulePath,
         ModulePath,
     };
-    use tokio::sync::mpsc;
+    use tokio::{
+        io::{
+            AsyncReadExt,
+            AsyncWriteExt,
+        },
+        net::TcpListener,
+        sync::{
+            mpsc,
+            Notify,
+        },
+    };
     use udf::validation::ValidatedPathAndArgs;
     use value::{
         array,
@@ -249,12 +510,19 @@ mod tests {
         ConvexObject,
     };
 
-    use super::LocalNodeExecutor;
+    use super::{
+        LocalNodeExecutor,
+        LocalNodeExecutorConfig,
+    };
     use crate::{
         executor::{
+            ExecutorRequest,
+            InvokeResponse,
             NodeActionOutcome,
+            NodeExecutor,
             Package,
         },
+        worker_pool::NodeWorkerPoolConfig,
         Actions,
         AnalyzeRequest,
         ExecuteRequest,
@@ -285,6 +553,8 @@ mod tests {
         Ok(SourcePackage {
             bundled_source: Package { uri, key, sha256 },
             external_deps: None,
+            node_version: NodeVersion::default(),
+            layers: vec![],
         })
     }
 
@@ -370,6 +640,168 @@ mod tests {
         Ok(())
     }
 
+    // The fetch host policy is only enforced by the fetch client of Convex's
+    // runtime. Node actions make requests directly from the executor, so they
+    // can reach hosts the policy would deny, like the backend's own network.
+    #[convex_macro::prod_rt_test]
+    async fn test_fetch_host_policy_does_not_apply(rt: ProdRuntime) -> anyhow::Result<()> {
+        let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
+        let actions = create_actions(rt);
+        let source_package = upload_modules(storage.clone(), TEST_SOURCE.clone()).await?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await?;
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                )
+                .await?;
+            anyhow::Ok(())
+        });
+
+        let args = create_args(assert_obj!("url" => url))?;
+        let path_and_args = ValidatedPathAndArgs::new_for_tests(
+            "node_actions.js:fetchText".parse()?,
+            args,
+            VERSION.clone(),
+        );
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(path_and_args, source_package),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert_eq!(response.result?, ConvexValue::try_from("hello")?);
+        server.await??;
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_worker_pool(rt: ProdRuntime) -> anyhow::Result<()> {
+        let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
+        let executor = LocalNodeExecutor::new_with_worker_pool(
+            TEST_NODE_PROCESS_TIMEOUT,
+            NodeWorkerPoolConfig {
+                size: 1,
+                max_invocations: 10,
+            },
+        )?;
+        let actions = Actions::new(
+            Arc::new(executor),
+            TEST_BACKEND_ADDRESS.into(),
+            TEST_USER_TIMEOUT,
+            rt,
+        );
+        let source_package = upload_modules(storage.clone(), TEST_SOURCE.clone()).await?;
+        let add_numbers = || -> anyhow::Result<ValidatedPathAndArgs> {
+            let numbers: ConvexArray = array![1f64.into(), 7f64.into()]?;
+            let args = create_args(assert_obj!("numbers" => ConvexValue::Array(numbers)))?;
+            Ok(ValidatedPathAndArgs::new_for_tests(
+                "node_actions.js:addNumbers".parse()?,
+                args,
+                VERSION.clone(),
+            ))
+        };
+
+        // Run on the same worker twice.
+        for _ in 0..2 {
+            let (response, _log_lines) = execute(
+                &actions,
+                execute_request(add_numbers()?, source_package.clone()),
+                empty_source_maps_callback(),
+            )
+            .await?;
+            assert_eq!(response.result?, ConvexValue::from(8.));
+        }
+
+        // A worker that times out is replaced.
+        let path_and_args = ValidatedPathAndArgs::new_for_tests(
+            "node_actions.js:workHardForAnHour".parse()?,
+            array![],
+            VERSION.clone(),
+        );
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(path_and_args, source_package.clone()),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert!(response.result.is_err());
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(add_numbers()?, source_package),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert_eq!(response.result?, ConvexValue::from(8.));
+        Ok(())
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_package_cache(rt: ProdRuntime) -> anyhow::Result<()> {
+        let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
+        let cache_dir = tempfile::TempDir::new()?;
+        let executor = LocalNodeExecutor::new_with_config(LocalNodeExecutorConfig {
+            package_cache_dir: Some(cache_dir.path().to_path_buf()),
+            ..LocalNodeExecutorConfig::new(TEST_NODE_PROCESS_TIMEOUT)
+        })?;
+        let actions = Actions::new(
+            Arc::new(executor),
+            TEST_BACKEND_ADDRESS.into(),
+            TEST_USER_TIMEOUT,
+            rt,
+        );
+        let source_package = upload_modules(storage.clone(), TEST_SOURCE.clone()).await?;
+        let add_numbers = || -> anyhow::Result<ValidatedPathAndArgs> {
+            let numbers: ConvexArray = array![1f64.into(), 7f64.into()]?;
+            let args = create_args(assert_obj!("numbers" => ConvexValue::Array(numbers)))?;
+            Ok(ValidatedPathAndArgs::new_for_tests(
+                "node_actions.js:addNumbers".parse()?,
+                args,
+                VERSION.clone(),
+            ))
+        };
+        let cached_sources = || -> anyhow::Result<Vec<_>> {
+            std::fs::read_dir(cache_dir.path().join("source"))?
+                .map(|entry| Ok(entry?.file_name()))
+                .collect()
+        };
+
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(add_numbers()?, source_package.clone()),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert_eq!(response.result?, ConvexValue::from(8.));
+        let cached = cached_sources()?;
+        assert_eq!(cached.len(), 1);
+        assert!(cache_dir
+            .path()
+            .join("source")
+            .join(&cached[0])
+            .join("metadata.json")
+            .exists());
+
+        // A new process runs the package from the cache without downloading it.
+        storage
+            .delete_object(&source_package.bundled_source.key)
+            .await?;
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(add_numbers()?, source_package),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert_eq!(response.result?, ConvexValue::from(8.));
+        assert_eq!(cached_sources()?, cached);
+        Ok(())
+    }
+
     #[convex_macro::prod_rt_test]
     async fn test_log_lines(rt: ProdRuntime) -> anyhow::Result<()> {
         let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
@@ -398,6 +830,102 @@ mod tests {
         Ok(())
     }
 
+    /// Hands the log stream id of each execution to the test and waits for
+    /// the test to let the execution finish.
+    struct LogStreamingExecutor {
+        log_stream_ids: mpsc::UnboundedSender<String>,
+        finish: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl NodeExecutor for LogStreamingExecutor {
+        fn enable(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn invoke(
+            &self,
+            request: ExecutorRequest,
+            _log_line_sender: mpsc::UnboundedSender<LogLine>,
+        ) -> anyhow::Result<InvokeResponse> {
+            let ExecutorRequest::Execute {
+                log_stream_id: Some(log_stream_id),
+                ..
+            } = request
+            else {
+                anyhow::bail!("Expected an execute request with a log stream");
+            };
+            self.log_stream_ids.send(log_stream_id)?;
+            self.finish.notified().await;
+            anyhow::bail!("Execution finished");
+        }
+
+        fn shutdown(&self) {}
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_stream_log_lines(rt: ProdRuntime) -> anyhow::Result<()> {
+        let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
+        let (log_stream_id_sender, mut log_stream_ids) = mpsc::unbounded_channel();
+        let finish = Arc::new(Notify::new());
+        let actions = Actions::new(
+            Arc::new(LogStreamingExecutor {
+                log_stream_ids: log_stream_id_sender,
+                finish: finish.clone(),
+            }),
+            TEST_BACKEND_ADDRESS.into(),
+            TEST_USER_TIMEOUT,
+            rt,
+        );
+        let source_package = upload_modules(storage.clone(), TEST_SOURCE.clone()).await?;
+        let path_and_args = ValidatedPathAndArgs::new_for_tests(
+            "node_actions.js:logHelloWorldAndReturn7".parse()?,
+            array![],
+            VERSION.clone(),
+        );
+        let (log_line_sender, mut log_line_receiver) = mpsc::unbounded_channel();
+        let execution = tokio::spawn({
+            let actions = actions.clone();
+            async move {
+                actions
+                    .execute(
+                        execute_request(path_and_args, source_package),
+                        log_line_sender,
+                        empty_source_maps_callback(),
+                    )
+                    .await
+            }
+        });
+        let log_stream_id = log_stream_ids.recv().await.expect("execution didn't start");
+
+        // Lines streamed while the action runs reach its log line receiver.
+        let log_line = |message: &str| {
+            LogLine::new_developer_log_line(
+                LogLevel::Info,
+                vec![format!("'{message}'")],
+                UnixTimestamp::from_millis(0),
+            )
+        };
+        actions.stream_log_lines(&log_stream_id, vec![log_line("Hello"), log_line("World!")])?;
+        for expected in ["[INFO] 'Hello'", "[INFO] 'World!'"] {
+            let log_line = log_line_receiver.recv().await.expect("missing log line");
+            assert_eq!(log_line.to_pretty_string_test_only(), expected);
+        }
+        let err = actions
+            .stream_log_lines("not-a-log-stream", vec![log_line("lost")])
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "LogStreamNotFound");
+
+        // The stream is closed once the execution finishes.
+        finish.notify_one();
+        assert!(execution.await?.is_err());
+        let err = actions
+            .stream_log_lines(&log_stream_id, vec![log_line("late")])
+            .unwrap_err();
+        assert_eq!(err.short_msg(), "LogStreamNotFound");
+        Ok(())
+    }
+
     #[convex_macro::prod_rt_test]
     async fn test_auth_syscall(rt: ProdRuntime) -> anyhow::Result<()> {
         let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
@@ -688,6 +1216,30 @@ mod tests {
         Ok(())
     }
 
+    #[convex_macro::prod_rt_test]
+    async fn test_function_user_timeout(rt: ProdRuntime) -> anyhow::Result<()> {
+        let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
+        let mut actions = create_actions(rt);
+        actions.set_function_timeouts("node_actions:sleepAnHour=1".parse()?);
+        let source_package = upload_modules(storage.clone(), TEST_SOURCE.clone()).await?;
+        let path_and_args = ValidatedPathAndArgs::new_for_tests(
+            "node_actions.js:sleepAnHour".parse()?,
+            array![],
+            VERSION.clone(),
+        );
+        let (response, _log_lines) = execute(
+            &actions,
+            execute_request(path_and_args, source_package),
+            empty_source_maps_callback(),
+        )
+        .await?;
+        assert_eq!(
+            &response.result.unwrap_err().message[..],
+            "Action `sleepAnHour` execution timed out (maximum duration 1s)"
+        );
+        Ok(())
+    }
+
     #[convex_macro::prod_rt_test]
     async fn test_partial_escape_sequence_result(rt: ProdRuntime) -> anyhow::Result<()> {
         let storage = Arc::new(LocalDirStorage::new(rt.clone())?);
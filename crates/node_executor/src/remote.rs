@@ -0,0 +1,269 @@
+//! Runs Node actions on a remote executor fleet over HTTP, so their CPU and
+//! memory can scale independently of the backend.
+//!
+//! Each invocation is a `POST <url>/invoke` with a JSON body:
+//!
+//! ```json
+//! { "request": <executor request>, "files": { "<path>": "<base64url>" } }
+//! ```
+//!
+//! `request` is the same request the local executor passes to `local.cjs`.
+//! Packages in remote storage are referenced by their signed URLs, but
+//! packages in the backend's local storage aren't reachable from the fleet,
+//! so they're sent in `files` and must be written to the same paths before
+//! running the request. The response body is the newline-delimited JSON that
+//! `local.cjs` prints: log lines as they're produced and then one result. The
+//! fleet may report the memory it gave the action in the
+//! `Convex-Memory-Used-Mb` response header.
+use std::{
+    collections::BTreeMap,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use common::{
+    log_lines::LogLine,
+    types::ConvexOrigin,
+};
+use futures::StreamExt;
+use reqwest::Url;
+use serde_json::{
+    json,
+    Value as JsonValue,
+};
+use tokio::sync::mpsc;
+use value::base64;
+
+use crate::{
+    executor::{
+        parse_streamed_response,
+        ExecutorRequest,
+        InvokeResponse,
+        NodeExecutor,
+        ResponsePart,
+        EXECUTE_TIMEOUT_RESPONSE_JSON,
+    },
+    source_package::add_request_package_files,
+};
+
+const MEMORY_USED_HEADER: &str = "convex-memory-used-mb";
+
+/// Reported when the fleet doesn't say, matching the local executor.
+const DEFAULT_MEMORY_USED_MB: u64 = 512;
+
+#[derive(Clone, Debug)]
+pub struct RemoteNodeExecutorConfig {
+    /// Base URL of the executor fleet.
+    pub url: Url,
+    /// Sent as a bearer token so the fleet can reject other callers.
+    pub auth_token: Option<String>,
+    /// Address the fleet uses to reach the backend, if it's different from
+    /// the backend's origin.
+    pub backend_address: Option<ConvexOrigin>,
+    pub node_process_timeout: Duration,
+}
+
+pub struct RemoteNodeExecutor {
+    client: reqwest::Client,
+    invoke_url: Url,
+    config: RemoteNodeExecutorConfig,
+}
+
+impl RemoteNodeExecutor {
+    pub fn new(config: RemoteNodeExecutorConfig) -> anyhow::Result<Self> {
+        let mut base_url = config.url.clone();
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        let invoke_url = base_url.join("invoke")?;
+        tracing::info!("Using remote node executor at {}", config.url);
+        Ok(Self {
+            client: reqwest::Client::new(),
+            invoke_url,
+            config,
+        })
+    }
+
+    async fn send(
+        &self,
+        body: &JsonValue,
+        log_line_sender: &mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        let mut builder = self.client.post(self.invoke_url.clone()).json(body);
+        if let Some(token) = &self.config.auth_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await.with_context(|| {
+            format!(
+                "Failed to reach remote node executor at {}",
+                self.invoke_url
+            )
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Remote node executor returned {status}: {body}");
+        }
+        let memory_used_in_mb = response
+            .headers()
+            .get(MEMORY_USED_HEADER)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .unwrap_or(DEFAULT_MEMORY_USED_MB);
+
+        let mut result_values = vec![];
+        let mut handle_line = |line: &str| -> anyhow::Result<()> {
+            for part in parse_streamed_response(line)? {
+                match part {
+                    ResponsePart::LogLine(log_line) => log_line_sender.send(log_line)?,
+                    ResponsePart::Result(result) => result_values.push(result),
+                }
+            }
+            Ok(())
+        };
+        let mut lines = LineBuffer::default();
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.context("Failed to read remote node executor response")?;
+            for line in lines.push(&chunk)? {
+                handle_line(&line)?;
+            }
+        }
+        if let Some(line) = lines.finish()? {
+            handle_line(&line)?;
+        }
+
+        anyhow::ensure!(
+            result_values.len() <= 1,
+            "Received more than one result from remote node executor"
+        );
+        let response = result_values
+            .pop()
+            .context("Received no result from remote node executor")?;
+        Ok(InvokeResponse {
+            response,
+            memory_used_in_mb,
+            aws_request_id: None,
+        })
+    }
+}
+
+#[async_trait]
+impl NodeExecutor for RemoteNodeExecutor {
+    fn enable(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn invoke(
+        &self,
+        mut request: ExecutorRequest,
+        log_line_sender: mpsc::UnboundedSender<LogLine>,
+    ) -> anyhow::Result<InvokeResponse> {
+        if let ExecutorRequest::Execute {
+            ref mut backend_address,
+            ..
+        } = request
+            && let Some(address) = &self.config.backend_address
+        {
+            *backend_address = address.clone();
+        }
+        let mut files = BTreeMap::new();
+        add_request_package_files(&request, &mut files).await?;
+        let files: BTreeMap<String, String> = files
+            .into_iter()
+            .map(|(path, contents)| (path, base64::encode_urlsafe(&contents)))
+            .collect();
+        let body = json!({
+            "request": JsonValue::try_from(request)?,
+            "files": files,
+        });
+        match tokio::time::timeout(
+            self.config.node_process_timeout,
+            self.send(&body, &log_line_sender),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Ok(InvokeResponse {
+                response: EXECUTE_TIMEOUT_RESPONSE_JSON.clone(),
+                memory_used_in_mb: DEFAULT_MEMORY_USED_MB,
+                aws_request_id: None,
+            }),
+        }
+    }
+
+    fn shutdown(&self) {}
+}
+
+/// Splits a response body that arrives in arbitrary chunks into lines.
+#[derive(Default)]
+struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> anyhow::Result<Vec<String>> {
+        self.buf.extend_from_slice(chunk);
+        let mut lines = vec![];
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            lines.push(String::from_utf8(line)?);
+        }
+        Ok(lines)
+    }
+
+    fn finish(self) -> anyhow::Result<Option<String>> {
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(self.buf)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        LineBuffer,
+        RemoteNodeExecutor,
+        RemoteNodeExecutorConfig,
+    };
+
+    #[test]
+    fn test_line_buffer_splits_chunks() -> anyhow::Result<()> {
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(b"{\"kind\":")?.is_empty());
+        assert_eq!(
+            lines.push(b"\"LogLine\"}\n{\"a\"")?,
+            vec!["{\"kind\":\"LogLine\"}\n"]
+        );
+        assert_eq!(lines.push(b":1}\n\n")?, vec!["{\"a\":1}\n", "\n"]);
+        assert_eq!(lines.finish()?, None);
+
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(b"{}")?.is_empty());
+        assert_eq!(lines.finish()?.as_deref(), Some("{}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invoke_url() -> anyhow::Result<()> {
+        for url in [
+            "https://executors.internal/fleet",
+            "https://executors.internal/fleet/",
+        ] {
+            let executor = RemoteNodeExecutor::new(RemoteNodeExecutorConfig {
+                url: url.parse()?,
+                auth_token: None,
+                backend_address: None,
+                node_process_timeout: Duration::from_secs(5),
+            })?;
+            assert_eq!(
+                executor.invoke_url.as_str(),
+                "https://executors.internal/fleet/invoke"
+            );
+        }
+        Ok(())
+    }
+}
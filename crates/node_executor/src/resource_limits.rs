@@ -0,0 +1,296 @@
+//! Resource limits for Node.js processes started by the local node executor.
+//!
+//! Memory and CPU limits are enforced by running each process in its own
+//! cgroup v2, created under a parent cgroup that's delegated to the backend,
+//! and the open file limit with `ulimit -n`. The kernel kills every process in
+//! a cgroup that goes over its memory limit, which is reported to the user as
+//! the action running out of memory, instead of the whole host running out.
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use parking_lot::Mutex;
+use tokio::process::Command as TokioCommand;
+
+/// Length of the period `cpu.max` quotas are measured over.
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Prefix of the cgroups the executor creates under the parent cgroup.
+const CGROUP_PREFIX: &str = "convex-node-";
+
+static NEXT_CGROUP_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, Default)]
+pub struct NodeResourceLimits {
+    /// Parent cgroup v2 directory to create a cgroup per process in, e.g.
+    /// `/sys/fs/cgroup/convex-node`. The backend must be allowed to create
+    /// cgroups in it and must not run in it itself. Required for memory and
+    /// CPU limits.
+    pub cgroup_parent: Option<PathBuf>,
+    pub memory_limit_mb: Option<u64>,
+    /// Number of CPUs each process may use, which can be fractional.
+    pub cpus: Option<f64>,
+    pub max_open_files: Option<u64>,
+}
+
+/// Starts Node.js processes with [`NodeResourceLimits`] applied.
+pub(crate) struct NodeSandbox {
+    limits: NodeResourceLimits,
+    /// Cgroups that couldn't be removed right away because their processes
+    /// were still exiting.
+    leftover_cgroups: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl NodeSandbox {
+    pub(crate) fn new(limits: NodeResourceLimits) -> anyhow::Result<Self> {
+        match &limits.cgroup_parent {
+            Some(parent) => {
+                anyhow::ensure!(
+                    parent.join("cgroup.controllers").exists(),
+                    "{} is not a cgroup v2 directory",
+                    parent.display()
+                );
+                let mut controllers = vec![];
+                if limits.memory_limit_mb.is_some() {
+                    controllers.push("+memory");
+                }
+                if limits.cpus.is_some() {
+                    controllers.push("+cpu");
+                }
+                if !controllers.is_empty() {
+                    fs::write(parent.join("cgroup.subtree_control"), controllers.join(" "))
+                        .with_context(|| {
+                            format!(
+                                "Failed to enable {} controllers in {}",
+                                controllers.join(" "),
+                                parent.display()
+                            )
+                        })?;
+                }
+                remove_stale_cgroups(parent);
+                tracing::info!(
+                    "Limiting local Node.js processes with cgroups in {}",
+                    parent.display()
+                );
+            },
+            None => anyhow::ensure!(
+                limits.memory_limit_mb.is_none() && limits.cpus.is_none(),
+                "Memory and CPU limits for local Node.js processes require a cgroup v2 directory"
+            ),
+        }
+        Ok(Self {
+            limits,
+            leftover_cgroups: Arc::new(Mutex::new(vec![])),
+        })
+    }
+
+    pub(crate) fn memory_limit_mb(&self) -> Option<u64> {
+        self.limits.memory_limit_mb
+    }
+
+    /// Returns a command that runs `node_path` with the limits applied, along
+    /// with the cgroup it runs in, which kills any processes left in it when
+    /// dropped.
+    pub(crate) fn command(
+        &self,
+        node_path: &str,
+    ) -> anyhow::Result<(TokioCommand, Option<NodeCgroup>)> {
+        self.leftover_cgroups
+            .lock()
+            .retain(|path| fs::remove_dir(path).is_err());
+        let cgroup = self
+            .limits
+            .cgroup_parent
+            .as_deref()
+            .map(|parent| NodeCgroup::create(parent, &self.limits, self.leftover_cgroups.clone()))
+            .transpose()?;
+        if cgroup.is_none() && self.limits.max_open_files.is_none() {
+            return Ok((TokioCommand::new(node_path), None));
+        }
+        // Apply the limits in a shell that then execs node, so they're in place
+        // before node runs any code.
+        let mut script = vec![];
+        if cgroup.is_some() {
+            script.push("echo $$ > \"$1/cgroup.procs\"".to_string());
+        }
+        if let Some(max_open_files) = self.limits.max_open_files {
+            script.push(format!("ulimit -n {max_open_files}"));
+        }
+        script.push("shift".to_string());
+        script.push("exec \"$@\"".to_string());
+        let mut cmd = TokioCommand::new("/bin/sh");
+        cmd.arg("-c")
+            .arg(script.join(" && "))
+            .arg("sh")
+            .arg(
+                cgroup
+                    .as_ref()
+                    .map(|c| c.path.as_os_str())
+                    .unwrap_or_default(),
+            )
+            .arg(node_path);
+        Ok((cmd, cgroup))
+    }
+}
+
+/// A cgroup holding a single Node.js process and anything it starts.
+pub(crate) struct NodeCgroup {
+    path: PathBuf,
+    leftover_cgroups: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl NodeCgroup {
+    fn create(
+        parent: &Path,
+        limits: &NodeResourceLimits,
+        leftover_cgroups: Arc<Mutex<Vec<PathBuf>>>,
+    ) -> anyhow::Result<Self> {
+        let path = parent.join(format!(
+            "{CGROUP_PREFIX}{}-{}",
+            std::process::id(),
+            NEXT_CGROUP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&path)
+            .with_context(|| format!("Failed to create cgroup {}", path.display()))?;
+        let cgroup = Self {
+            path,
+            leftover_cgroups,
+        };
+        if let Some(memory_limit_mb) = limits.memory_limit_mb {
+            cgroup.write("memory.max", &(memory_limit_mb * 1024 * 1024).to_string())?;
+            // Disallow swap so the memory limit is a hard limit. The file is
+            // missing if swap accounting is disabled, in which case there's
+            // nothing to disallow.
+            if cgroup.path.join("memory.swap.max").exists() {
+                cgroup.write("memory.swap.max", "0")?;
+            }
+            // Kill the whole cgroup rather than a single process in it, so
+            // node doesn't keep running with a killed child.
+            cgroup.write("memory.oom.group", "1")?;
+        }
+        if let Some(cpus) = limits.cpus {
+            let quota = ((cpus * CPU_PERIOD_US as f64).round() as u64).max(1000);
+            cgroup.write("cpu.max", &format!("{quota} {CPU_PERIOD_US}"))?;
+        }
+        Ok(cgroup)
+    }
+
+    fn write(&self, file: &str, value: &str) -> anyhow::Result<()> {
+        fs::write(self.path.join(file), value)
+            .with_context(|| format!("Failed to write {value} to {}/{file}", self.path.display()))
+    }
+
+    /// Whether the kernel killed processes in the cgroup for going over its
+    /// memory limit.
+    pub(crate) fn oom_killed(&self) -> bool {
+        fs::read_to_string(self.path.join("memory.events"))
+            .map(|events| oom_kill_count(&events) > 0)
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for NodeCgroup {
+    fn drop(&mut self) {
+        // Kill anything the process left running, e.g. if it timed out.
+        let _ = fs::write(self.path.join("cgroup.kill"), "1");
+        if fs::remove_dir(&self.path).is_err() {
+            self.leftover_cgroups.lock().push(self.path.clone());
+        }
+    }
+}
+
+/// The process that created a cgroup named by [`NodeCgroup::create`].
+fn cgroup_owner_pid(name: &str) -> Option<u32> {
+    let (pid, _) = name.strip_prefix(CGROUP_PREFIX)?.split_once('-')?;
+    pid.parse().ok()
+}
+
+fn oom_kill_count(memory_events: &str) -> u64 {
+    memory_events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Removes cgroups left behind by backend processes that are no longer
+/// running. Cgroups of this process and of other running backends that share
+/// the parent are still in use.
+fn remove_stale_cgroups(parent: &Path) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = cgroup_owner_pid(&entry.file_name().to_string_lossy()) else {
+            continue;
+        };
+        if pid == std::process::id() || Path::new(&format!("/proc/{pid}")).exists() {
+            continue;
+        }
+        let path = entry.path();
+        let _ = fs::write(path.join("cgroup.kill"), "1");
+        if let Err(e) = fs::remove_dir(&path) {
+            tracing::warn!("Failed to remove stale cgroup {}: {e}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cgroup_owner_pid,
+        oom_kill_count,
+        NodeResourceLimits,
+        NodeSandbox,
+    };
+
+    #[test]
+    fn test_oom_kill_count() {
+        let events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\noom_group_kill 1\n";
+        assert_eq!(oom_kill_count(events), 1);
+        assert_eq!(oom_kill_count("low 0\nmax 0\noom 0\noom_kill 0\n"), 0);
+        assert_eq!(oom_kill_count(""), 0);
+    }
+
+    #[test]
+    fn test_cgroup_owner_pid() {
+        assert_eq!(cgroup_owner_pid("convex-node-1234-7"), Some(1234));
+        assert_eq!(cgroup_owner_pid("convex-node-1234"), None);
+        assert_eq!(cgroup_owner_pid("other-1234-7"), None);
+    }
+
+    #[test]
+    fn test_limits_require_cgroup() {
+        assert!(NodeSandbox::new(NodeResourceLimits {
+            memory_limit_mb: Some(512),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_max_open_files() -> anyhow::Result<()> {
+        let sandbox = NodeSandbox::new(NodeResourceLimits {
+            max_open_files: Some(64),
+            ..Default::default()
+        })?;
+        let (mut cmd, cgroup) = sandbox.command("/bin/sh")?;
+        assert!(cgroup.is_none());
+        let output = cmd.arg("-c").arg("ulimit -n").output().await?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout)?.trim(), "64");
+        Ok(())
+    }
+}
@@ -1,3 +1,86 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use http::Uri;
+
+use crate::{
+    ExecutorRequest,
+    Package,
+    SourcePackage,
+};
+
+/// Returns the path of a `file://` URI from local storage.
+fn local_file_path(uri: &Uri) -> Option<&str> {
+    (uri.scheme_str() == Some("file")).then(|| uri.path())
+}
+
+/// Reads the packages of `source_package` that are in local storage into
+/// `files`, keyed by path, for executors that can't read the backend's
+/// filesystem. They write them to the same paths before running the request.
+async fn add_package_files(
+    source_package: &SourcePackage,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> anyhow::Result<()> {
+    for package in packages(source_package) {
+        if let Some(path) = local_file_path(&package.uri) {
+            let contents = tokio::fs::read(path)
+                .await
+                .with_context(|| format!("Failed to read source package {path}"))?;
+            files.insert(path.to_string(), contents);
+        }
+    }
+    Ok(())
+}
+
+fn packages(source_package: &SourcePackage) -> impl Iterator<Item = &Package> {
+    std::iter::once(&source_package.bundled_source)
+        .chain(source_package.external_deps.as_ref())
+        .chain(&source_package.layers)
+}
+
+/// Fails if `request` reads or writes packages in local storage, for
+/// executors that can only reach storage over HTTP.
+pub(crate) fn ensure_no_local_packages(request: &ExecutorRequest) -> anyhow::Result<()> {
+    let is_local =
+        match request {
+            ExecutorRequest::Execute { request, .. } => packages(&request.source_package)
+                .any(|package| local_file_path(&package.uri).is_some()),
+            ExecutorRequest::Analyze(request) => packages(&request.source_package)
+                .any(|package| local_file_path(&package.uri).is_some()),
+            ExecutorRequest::BuildDeps(request) => local_file_path(&request.upload_url).is_some(),
+        };
+    anyhow::ensure!(
+        !is_local,
+        "Running Node actions outside of the backend's host requires storage reachable over HTTP"
+    );
+    Ok(())
+}
+
+/// Adds the packages in local storage that `request` reads to `files`.
+pub(crate) async fn add_request_package_files(
+    request: &ExecutorRequest,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> anyhow::Result<()> {
+    match request {
+        ExecutorRequest::Execute { request, .. } => {
+            add_package_files(&request.source_package, files).await
+        },
+        ExecutorRequest::Analyze(request) => {
+            add_package_files(&request.source_package, files).await
+        },
+        ExecutorRequest::BuildDeps(request) => {
+            // There's no way to send the built package back, so it has to be
+            // uploaded directly.
+            anyhow::ensure!(
+                local_file_path(&request.upload_url).is_none(),
+                "Building dependencies outside of the backend's host requires storage reachable \
+                 over HTTP"
+            );
+            Ok(())
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
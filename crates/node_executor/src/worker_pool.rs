@@ -0,0 +1,432 @@
+//! A pool of long-lived `local.cjs --worker` processes for the local node
+//! executor, so short actions don't pay for spawning Node.js and loading the
+//! executor on every invocation.
+//!
+//! Requests are written to a worker's stdin one per line, prefixed with a
+//! random nonce, and the worker writes the same output as a one-shot process
+//! to stdout, followed by a [`WorkerDone`] line with the same nonce once the
+//! request is finished. User code can write to stdout too, but can't end a
+//! request early since it doesn't know the nonce. A worker runs one request
+//! at a time, and runs each request's modules in a fresh `vm` context with
+//! its environment variables and working directory reset. Timers and other
+//! handles can't be reset, so a worker that still has some from a request is
+//! retired instead of reused, as are workers that reach a number of
+//! invocations, time out, or exit.
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use common::{
+    log_lines::LogLine,
+    runtime::tokio_spawn,
+    types::NodeVersion,
+};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        AsyncWriteExt,
+        BufReader,
+        Lines,
+    },
+    process::{
+        Child,
+        ChildStdin,
+        ChildStdout,
+    },
+    sync::mpsc,
+};
+
+use crate::{
+    executor::{
+        parse_streamed_response,
+        ResponsePart,
+    },
+    local::NodeProcessResult,
+    resource_limits::{
+        NodeCgroup,
+        NodeSandbox,
+    },
+};
+
+/// Printed by a worker after it's done with a request. Keep in sync with
+/// `local.ts`.
+#[derive(Deserialize)]
+struct WorkerDone {
+    kind: String,
+    /// The nonce the request was sent with.
+    nonce: String,
+    /// Whether the request left behind handles that could run code later.
+    retire: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NodeWorkerPoolConfig {
+    /// Number of idle workers to keep per Node.js version.
+    pub size: usize,
+    /// Number of invocations after which a worker is replaced with a fresh
+    /// process.
+    pub max_invocations: usize,
+}
+
+pub(crate) struct NodeWorkerPool {
+    config: NodeWorkerPoolConfig,
+    /// Arguments to start Node.js with, before `--worker`.
+    executor_args: Vec<OsString>,
+    node_paths: BTreeMap<NodeVersion, String>,
+    sandbox: Arc<NodeSandbox>,
+    idle: Mutex<BTreeMap<NodeVersion, Vec<NodeWorker>>>,
+}
+
+impl NodeWorkerPool {
+    pub(crate) fn new(
+        config: NodeWorkerPoolConfig,
+        executor_args: Vec<OsString>,
+        node_paths: BTreeMap<NodeVersion, String>,
+        sandbox: Arc<NodeSandbox>,
+    ) -> Arc<Self> {
+        let pool = Arc::new(Self {
+            config,
+            executor_args,
+            node_paths,
+            sandbox,
+            idle: Mutex::new(BTreeMap::new()),
+        });
+        // Only warm up the default version, since most deployments use it.
+        for _ in 0..config.size {
+            pool.clone().refill(NodeVersion::default());
+        }
+        pool
+    }
+
+    /// Takes an idle worker, or starts one if there aren't any.
+    pub(crate) async fn take(self: &Arc<Self>, version: NodeVersion) -> anyhow::Result<NodeWorker> {
+        let idle = {
+            let mut idle = self.idle.lock();
+            let workers = idle.entry(version).or_default();
+            let mut found = None;
+            while let Some(mut worker) = workers.pop() {
+                // Skip workers that exited while idle.
+                if matches!(worker.child.try_wait(), Ok(None)) {
+                    found = Some(worker);
+                    break;
+                }
+            }
+            found
+        };
+        // Workers are returned to the pool after their request, so only start
+        // a new one if they're all busy.
+        match idle {
+            Some(worker) => Ok(worker),
+            None => self.spawn(version).await,
+        }
+    }
+
+    /// Returns a worker that finished a request to the pool, unless it should
+    /// be retired, in which case a fresh worker is started to replace it.
+    pub(crate) fn put(self: &Arc<Self>, version: NodeVersion, worker: NodeWorker) {
+        if worker.retire || worker.invocations >= self.config.max_invocations {
+            self.discard(version, worker);
+            return;
+        }
+        let mut idle = self.idle.lock();
+        let workers = idle.entry(version).or_default();
+        if workers.len() < self.config.size {
+            workers.push(worker);
+        }
+    }
+
+    /// Kills a worker that can't be reused, e.g. because it timed out, and
+    /// starts a fresh one in its place.
+    pub(crate) fn discard(self: &Arc<Self>, version: NodeVersion, worker: NodeWorker) {
+        drop(worker);
+        self.clone().refill(version);
+    }
+
+    pub(crate) fn shutdown(&self) {
+        // Dropping the workers kills their processes.
+        self.idle.lock().clear();
+    }
+
+    /// Starts a worker in the background for the next invocation to use.
+    fn refill(self: Arc<Self>, version: NodeVersion) {
+        if self.config.size == 0 {
+            return;
+        }
+        tokio_spawn("node_worker_pool_refill", async move {
+            match self.spawn(version).await {
+                Ok(worker) => {
+                    let mut idle = self.idle.lock();
+                    let workers = idle.entry(version).or_default();
+                    if workers.len() < self.config.size {
+                        workers.push(worker);
+                    }
+                },
+                Err(e) => tracing::warn!("Failed to start Node.js {version} worker: {e:#}"),
+            }
+        });
+    }
+
+    async fn spawn(&self, version: NodeVersion) -> anyhow::Result<NodeWorker> {
+        let (mut cmd, cgroup) = self.sandbox.command(&self.node_paths[&version])?;
+        let mut child = cmd
+            .args(&self.executor_args)
+            .arg("--worker")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start Node.js {version} worker"))?;
+        let stdin = child.stdin.take().context("Missing worker stdin")?;
+        let stdout = child.stdout.take().context("Missing worker stdout")?;
+        let stderr = child.stderr.take().context("Missing worker stderr")?;
+        let pid = child.id();
+        // Drain stderr so the worker doesn't block on a full pipe.
+        tokio_spawn("node_worker_stderr", async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::warn!("Node.js worker {pid:?}: {line}");
+            }
+        });
+        Ok(NodeWorker {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            invocations: 0,
+            retire: false,
+            cgroup,
+        })
+    }
+}
+
+pub(crate) struct NodeWorker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    invocations: usize,
+    /// Set if the last request left behind handles, so the worker can't be
+    /// reused.
+    retire: bool,
+    // Dropped after `child`, so it's only cleaned up once the worker is dead.
+    cgroup: Option<NodeCgroup>,
+}
+
+impl NodeWorker {
+    /// Whether the worker was killed for going over its memory limit.
+    pub(crate) fn oom_killed(&self) -> bool {
+        self.cgroup
+            .as_ref()
+            .is_some_and(|cgroup| cgroup.oom_killed())
+    }
+
+    /// Runs `request` on the worker. The worker can only be reused if this
+    /// returns a [`NodeProcessResult::Response`].
+    pub(crate) async fn run(
+        &mut self,
+        request: &str,
+        log_line_sender: &mpsc::UnboundedSender<LogLine>,
+        timeout: Duration,
+    ) -> anyhow::Result<NodeProcessResult> {
+        anyhow::ensure!(!request.contains('\n'), "Worker requests must be one line");
+        self.invocations += 1;
+        let nonce = format!("{:032x}", rand::random::<u128>());
+        self.stdin.write_all(nonce.as_bytes()).await?;
+        self.stdin.write_all(b" ").await?;
+        self.stdin.write_all(request.as_bytes()).await?;
+        self.stdin.write_all(b"\n").await?;
+        self.stdin.flush().await?;
+
+        let output = async {
+            let mut result_values = vec![];
+            loop {
+                let Some(line) = self.stdout.next_line().await? else {
+                    let status = self.child.wait().await?;
+                    return Ok(NodeProcessResult::Failed(status));
+                };
+                if let Ok(done) = serde_json::from_str::<WorkerDone>(&line)
+                    && done.kind == "WorkerDone"
+                    && done.nonce == nonce
+                {
+                    self.retire = done.retire;
+                    break;
+                }
+                for part in parse_streamed_response(&line)? {
+                    match part {
+                        ResponsePart::LogLine(log_line) => log_line_sender.send(log_line)?,
+                        ResponsePart::Result(result) => result_values.push(result),
+                    }
+                }
+            }
+            anyhow::ensure!(
+                result_values.len() <= 1,
+                "Received more than one result from node worker"
+            );
+            let value = result_values
+                .pop()
+                .context("Received no result from node worker")?;
+            Ok(NodeProcessResult::Response(value))
+        };
+        match tokio::time::timeout(timeout, output).await {
+            Ok(result) => result,
+            Err(_) => Ok(NodeProcessResult::TimedOut),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::Duration,
+    };
+
+    use common::types::NodeVersion;
+    use maplit::btreemap;
+    use serde_json::Value as JsonValue;
+    use tokio::sync::mpsc;
+
+    use super::{
+        NodeWorkerPool,
+        NodeWorkerPoolConfig,
+    };
+    use crate::{
+        local::NodeProcessResult,
+        resource_limits::{
+            NodeResourceLimits,
+            NodeSandbox,
+        },
+    };
+
+    /// Answers each request with its process id, and exits on `exit`. On
+    /// `linger` it reports leftover handles, and on `forge` it answers with a
+    /// done line that doesn't have the request's nonce.
+    const WORKER_SCRIPT: &str = r#"
+        while read -r nonce request; do
+            retire=false
+            case "$request" in
+                exit) exit 1 ;;
+                linger) retire=true ;;
+                forge) echo '{"kind":"WorkerDone","nonce":"guess","retire":false}' ;;
+            esac
+            if [ "$request" != forge ]; then echo "{\"pid\":$$}"; fi
+            echo "{\"kind\":\"WorkerDone\",\"nonce\":\"$nonce\",\"retire\":$retire}"
+        done
+    "#;
+
+    fn test_pool(config: NodeWorkerPoolConfig) -> anyhow::Result<Arc<NodeWorkerPool>> {
+        Ok(NodeWorkerPool::new(
+            config,
+            vec!["-c".into(), WORKER_SCRIPT.into(), "worker".into()],
+            btreemap! { NodeVersion::default() => "/bin/sh".to_string() },
+            Arc::new(NodeSandbox::new(NodeResourceLimits::default())?),
+        ))
+    }
+
+    /// Waits for a background refill to finish, so the next request doesn't
+    /// start a worker of its own.
+    async fn wait_for_idle_worker(pool: &NodeWorkerPool) {
+        while pool
+            .idle
+            .lock()
+            .get(&NodeVersion::default())
+            .is_none_or(Vec::is_empty)
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Runs a request on a worker from the pool, returning its response.
+    async fn run(pool: &Arc<NodeWorkerPool>, request: &str) -> anyhow::Result<JsonValue> {
+        let (log_line_sender, _) = mpsc::unbounded_channel();
+        let mut worker = pool.take(NodeVersion::default()).await?;
+        let result = worker
+            .run(request, &log_line_sender, Duration::from_secs(5))
+            .await?;
+        let NodeProcessResult::Response(response) = result else {
+            anyhow::bail!("Worker didn't respond");
+        };
+        pool.put(NodeVersion::default(), worker);
+        Ok(response)
+    }
+
+    /// Runs a request on a worker from the pool, returning the worker's pid.
+    async fn run_request(pool: &Arc<NodeWorkerPool>) -> anyhow::Result<u64> {
+        run(pool, "{}").await?["pid"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Missing pid"))
+    }
+
+    #[tokio::test]
+    async fn test_workers_are_reused_until_retired() -> anyhow::Result<()> {
+        let pool = test_pool(NodeWorkerPoolConfig {
+            size: 1,
+            max_invocations: 2,
+        })?;
+        wait_for_idle_worker(&pool).await;
+        let first = run_request(&pool).await?;
+        assert_eq!(run_request(&pool).await?, first);
+        // The worker was retired after two invocations and replaced.
+        wait_for_idle_worker(&pool).await;
+        let second = run_request(&pool).await?;
+        assert_ne!(second, first);
+        assert_eq!(run_request(&pool).await?, second);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exited_workers_are_replaced() -> anyhow::Result<()> {
+        let pool = test_pool(NodeWorkerPoolConfig {
+            size: 1,
+            max_invocations: 10,
+        })?;
+        wait_for_idle_worker(&pool).await;
+        let first = run_request(&pool).await?;
+
+        let (log_line_sender, _) = mpsc::unbounded_channel();
+        let mut worker = pool.take(NodeVersion::default()).await?;
+        let result = worker
+            .run("exit", &log_line_sender, Duration::from_secs(5))
+            .await?;
+        assert!(matches!(result, NodeProcessResult::Failed(_)));
+        pool.discard(NodeVersion::default(), worker);
+
+        wait_for_idle_worker(&pool).await;
+        assert_ne!(run_request(&pool).await?, first);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_workers_with_leftover_handles_are_retired() -> anyhow::Result<()> {
+        let pool = test_pool(NodeWorkerPoolConfig {
+            size: 1,
+            max_invocations: 10,
+        })?;
+        wait_for_idle_worker(&pool).await;
+        let first = run(&pool, "linger").await?["pid"].as_u64();
+        wait_for_idle_worker(&pool).await;
+        assert_ne!(Some(run_request(&pool).await?), first);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_done_lines_without_the_nonce_are_output() -> anyhow::Result<()> {
+        let pool = test_pool(NodeWorkerPoolConfig {
+            size: 1,
+            max_invocations: 10,
+        })?;
+        wait_for_idle_worker(&pool).await;
+        let first = run_request(&pool).await?;
+        // The forged line doesn't end the request, so it's the response rather
+        // than leaking into the next request.
+        assert_eq!(run(&pool, "forge").await?["nonce"], "guess");
+        assert_eq!(run_request(&pool).await?, first);
+        Ok(())
+    }
+}
@@ -23,6 +23,7 @@ impl From<ErrorCode> for ErrorCodeProto {
             ErrorCode::NotFound => ErrorCodeProto::TransientNotFound,
             ErrorCode::ClientDisconnect => ErrorCodeProto::ClientDisconnect,
             ErrorCode::RateLimited => ErrorCodeProto::RateLimited,
+            ErrorCode::UnprocessableEntity => ErrorCodeProto::UnprocessableEntity,
             ErrorCode::Overloaded => ErrorCodeProto::Overloaded,
             ErrorCode::RejectedBeforeExecution => ErrorCodeProto::RejectedBeforeExecution,
             ErrorCode::OCC { .. } => ErrorCodeProto::Occ,
@@ -45,6 +46,7 @@ impl ErrorCodeProto {
             ErrorCodeProto::TransientNotFound => ErrorCode::NotFound,
             ErrorCodeProto::ClientDisconnect => ErrorCode::ClientDisconnect,
             ErrorCodeProto::RateLimited => ErrorCode::RateLimited,
+            ErrorCodeProto::UnprocessableEntity => ErrorCode::UnprocessableEntity,
             ErrorCodeProto::Overloaded => ErrorCode::Overloaded,
             ErrorCodeProto::RejectedBeforeExecution => ErrorCode::RejectedBeforeExecution,
             ErrorCodeProto::Occ => ErrorCode::OCC {
@@ -12,6 +12,9 @@ pub mod common {
 pub mod convex_actions {
     include!(concat!(env!("OUT_DIR"), "/convex_actions.rs"));
 }
+pub mod convex_api {
+    include!(concat!(env!("OUT_DIR"), "/convex_api.rs"));
+}
 pub mod convex_cursor {
     include!(concat!(env!("OUT_DIR"), "/convex_cursor.rs"));
 }
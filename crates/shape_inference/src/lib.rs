@@ -32,7 +32,10 @@ pub mod pretty;
 mod tests;
 
 use std::{
-    collections::BTreeMap,
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
     sync::Arc,
 };
 
@@ -223,6 +226,27 @@ impl<C: ShapeConfig> StructuralShape<C> {
         let counted = CountedShape::shape_of(value);
         Self::from(&counted)
     }
+
+    /// An object shape with the given required fields, or `None` if it has
+    /// more than [`ShapeConfig::MAX_OBJECT_FIELDS`].
+    pub fn object(fields: BTreeMap<IdentifierFieldName, Self>) -> Option<Self> {
+        if fields.len() > C::MAX_OBJECT_FIELDS {
+            return None;
+        }
+        let fields = fields
+            .into_iter()
+            .map(|(field_name, value_shape)| {
+                let field = ObjectField {
+                    value_shape,
+                    optional: false,
+                };
+                (field_name, field)
+            })
+            .collect();
+        Some(Self::new(ShapeEnum::Object(ObjectShape::<C, ()>::new(
+            fields,
+        ))))
+    }
 }
 
 impl<C: ShapeConfig> CountedShape<C> {
@@ -523,6 +547,33 @@ impl<C: ShapeConfig> CountedShape<C> {
         Some(Self::new(new_variant, new_num_values))
     }
 
+    /// The shape of this shape's values after removing `fields` from each of
+    /// its top-level objects. Shapes that don't name their fields, like
+    /// records, are returned as is.
+    pub fn without_fields(&self, fields: &BTreeSet<IdentifierFieldName>) -> Self {
+        match &*self.variant {
+            ShapeEnum::Object(object_shape) => {
+                let remaining = object_shape
+                    .fields()
+                    .iter()
+                    .filter(|(field_name, _)| !fields.contains(*field_name))
+                    .map(|(field_name, field)| (field_name.clone(), field.clone()))
+                    .collect();
+                Self::new(
+                    ShapeEnum::Object(ObjectShape::<C, u64>::new(remaining)),
+                    self.num_values,
+                )
+            },
+            ShapeEnum::Union(union_shape) => union_shape
+                .iter()
+                .fold(UnionBuilder::new(), |builder, variant| {
+                    builder.push(variant.without_fields(fields))
+                })
+                .build(),
+            _ => self.clone(),
+        }
+    }
+
     pub fn num_values(&self) -> &u64 {
         &self.num_values
     }
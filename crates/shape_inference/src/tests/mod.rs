@@ -1,7 +1,9 @@
 use cmd_util::env::env_config;
+use maplit::btreeset;
 use proptest::prelude::*;
 use serde_json::Value as JsonValue;
 use value::{
+    assert_obj,
     testing::assert_roundtrips,
     ConvexValue,
 };
@@ -254,3 +256,18 @@ proptest! {
         assert_roundtrips::<CountedShape<TestConfig>, JsonValue>(t)
     }
 }
+
+#[test]
+fn test_without_fields() -> anyhow::Result<()> {
+    let shape = CountedShape::<TestConfig>::empty()
+        .insert(&assert_obj!("a" => 1, "b" => "secret"))
+        .insert(&assert_obj!("a" => 2))
+        .insert(&assert_obj!("b" => 3));
+    let expected = CountedShape::<TestConfig>::empty()
+        .insert(&assert_obj!("a" => 1))
+        .insert(&assert_obj!("a" => 2))
+        .insert(&assert_obj!());
+    assert_eq!(shape.without_fields(&btreeset! {"b".parse()?}), expected);
+    assert_eq!(shape.without_fields(&btreeset! {"c".parse()?}), shape);
+    Ok(())
+}
@@ -346,6 +346,18 @@ impl BufferedUpload {
         }
     }
 
+    /// Caps the size of the parts the stream is split into, which bounds how
+    /// much of it is held in memory while it's uploaded. The cap must be at
+    /// least the storage's minimum part size, and since the number of parts
+    /// is limited, it also limits the size of the object.
+    pub fn limit_part_size(&mut self, max_part_size: usize) {
+        self.max_intermediate_part_size = cmp::min(self.max_intermediate_part_size, max_part_size);
+        self.target_intermediate_part_size = cmp::min(
+            self.target_intermediate_part_size,
+            self.max_intermediate_part_size,
+        );
+    }
+
     fn update_buffer_and_get_next(&mut self, data: Bytes) -> Option<Bytes> {
         Self::_update_buffer_and_get_next(
             &mut self.buffer,
@@ -1129,7 +1141,14 @@ impl Display for StorageUseCase {
 mod buffered_upload_tests {
     use std::{
         pin::Pin,
-        sync::Arc,
+        sync::{
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+            Arc,
+        },
+        time::Duration,
     };
 
     use async_trait::async_trait;
@@ -1156,6 +1175,7 @@ mod buffered_upload_tests {
         ChannelWriter,
         Upload,
         UploadExt,
+        MAXIMUM_PARALLEL_UPLOADS,
     };
 
     struct NoopUpload {
@@ -1218,6 +1238,55 @@ mod buffered_upload_tests {
         Ok(())
     }
 
+    /// An upload whose parts never finish uploading.
+    struct StalledUpload;
+
+    #[async_trait]
+    impl Upload for StalledUpload {
+        async fn write(&mut self, _data: Bytes) -> anyhow::Result<()> {
+            futures::future::pending().await
+        }
+
+        async fn try_write_parallel<'a>(
+            &'a mut self,
+            _stream: &mut Pin<Box<dyn Stream<Item = anyhow::Result<Bytes>> + Send + 'a>>,
+        ) -> anyhow::Result<()> {
+            futures::future::pending().await
+        }
+
+        async fn abort(self: Box<Self>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn complete(self: Box<Self>) -> anyhow::Result<common::types::ObjectKey> {
+            anyhow::bail!("Stalled uploads never complete")
+        }
+    }
+
+    #[convex_macro::prod_rt_test]
+    async fn test_buffered_upload_limit_part_size(_rt: ProdRuntime) -> anyhow::Result<()> {
+        let mut upload = BufferedUpload::new(StalledUpload, 10, 1000);
+        upload.limit_part_size(20);
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let pulled_ = pulled.clone();
+        let mut stream = futures::stream::repeat_with(move || {
+            pulled_.fetch_add(7, Ordering::SeqCst);
+            Ok(Bytes::from_static(b"abcdefg"))
+        })
+        .boxed();
+        // Only the parts queued for upload and the next one are held in memory,
+        // however much of the stream there is.
+        let result = tokio::time::timeout(
+            Duration::from_millis(100),
+            upload.try_write_parallel(&mut stream),
+        )
+        .await;
+        assert!(result.is_err());
+        let max_buffered = (MAXIMUM_PARALLEL_UPLOADS / 2 + 2) * 20 + 7;
+        assert!(pulled.load(Ordering::SeqCst) <= max_buffered);
+        Ok(())
+    }
+
     #[convex_macro::test_runtime]
     async fn test_buffered_upload_max_size(_rt: TestRuntime) -> anyhow::Result<()> {
         let (sender, receiver) = mpsc::channel::<Bytes>(1);
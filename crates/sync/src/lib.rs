@@ -3,9 +3,11 @@
 #![feature(try_blocks)]
 
 mod metrics;
+pub mod resume;
 mod state;
 pub mod worker;
 
+pub use resume::ResumableSessions;
 pub use worker::{
     SyncWorker,
     SyncWorkerConfig,
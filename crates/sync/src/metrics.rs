@@ -105,6 +105,19 @@ pub fn log_connect(last_close_reason: String, connection_count: u32) {
     log_distribution(&SYNC_RECONNECT_PREV_CONNECTIONS, connection_count.into());
 }
 
+register_convex_counter!(
+    SYNC_SESSION_RESUME_TOTAL,
+    "Number of attempts to resume a sync session",
+    &["resumed"]
+);
+pub fn log_session_resume(resumed: bool) {
+    let labels = vec![StaticMetricLabel::new(
+        "resumed",
+        if resumed { "true" } else { "false" },
+    )];
+    log_counter_with_labels(&SYNC_SESSION_RESUME_TOTAL, 1, labels);
+}
+
 register_convex_histogram!(
     SYNC_LINEARIZABILITY_DELAY_SECONDS,
     "How far behind the current backend is behind what the client has observed",
@@ -0,0 +1,101 @@
+//! Sessions kept after their WebSocket disconnects, so a client that
+//! reconnects soon after can resume where it left off instead of rerunning
+//! all of its queries.
+//!
+//! Each connection that asks for it is sent a single-use token. When the
+//! connection ends, its `SyncState`, including its subscriptions, is parked
+//! under that token for `SYNC_SESSION_RESUME_WINDOW`. A client that presents
+//! the token along with the last version it received gets the state back, and
+//! its next transition only holds the queries whose results have changed
+//! since.
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use common::knobs::{
+    SYNC_MAX_RESUMABLE_SESSIONS,
+    SYNC_SESSION_RESUME_WINDOW,
+};
+use parking_lot::Mutex;
+use sync_types::{
+    SessionId,
+    StateVersion,
+};
+use tokio::time::Instant;
+
+use crate::state::SyncState;
+
+struct ParkedSession {
+    parked_at: Instant,
+    state: SyncState,
+}
+
+#[derive(Default)]
+pub struct ResumableSessions {
+    sessions: Mutex<BTreeMap<String, ParkedSession>>,
+}
+
+impl fmt::Debug for ResumableSessions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumableSessions")
+            .field("len", &self.sessions.lock().len())
+            .finish()
+    }
+}
+
+impl ResumableSessions {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Keeps `state` for the client holding `token`, unless it has updates
+    /// from the client that didn't make it into a transition.
+    pub(crate) fn park(&self, token: String, state: SyncState, now: Instant) {
+        let Some(state) = state.park() else {
+            return;
+        };
+        let mut sessions = self.sessions.lock();
+        sessions.retain(|_, session| now - session.parked_at < *SYNC_SESSION_RESUME_WINDOW);
+        while sessions.len() >= *SYNC_MAX_RESUMABLE_SESSIONS {
+            let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.parked_at)
+                .map(|(token, _)| token.clone())
+            else {
+                break;
+            };
+            sessions.remove(&oldest);
+        }
+        sessions.insert(
+            token,
+            ParkedSession {
+                parked_at: now,
+                state,
+            },
+        );
+    }
+
+    /// Takes the session parked under `token` if it belongs to `session_id`
+    /// and the client has received everything up to its current version.
+    /// Tokens can only be used once.
+    pub(crate) fn resume(
+        &self,
+        token: &str,
+        session_id: SessionId,
+        version: StateVersion,
+        now: Instant,
+        current_time: SystemTime,
+    ) -> Option<SyncState> {
+        let session = self.sessions.lock().remove(token)?;
+        let state = session.state;
+        let resumable = now - session.parked_at < *SYNC_SESSION_RESUME_WINDOW
+            && state.session_id() == Some(session_id)
+            && state.current_version() == version
+            // Resuming with an expired identity would fail the next transition.
+            && state.identity(current_time).is_ok();
+        resumable.then_some(state)
+    }
+}
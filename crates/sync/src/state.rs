@@ -470,6 +470,33 @@ impl SyncState {
     pub fn num_queries(&self) -> usize {
         self.queries.len() + self.in_progress_queries.len()
     }
+
+    /// Prepares the state to be resumed by a later connection, or returns
+    /// `None` if the client's version of the state may differ from ours, which
+    /// is the case if we've received updates from it that haven't finished
+    /// transitioning.
+    pub fn park(mut self) -> Option<Self> {
+        let settled = self.session_id.is_some()
+            && self.in_progress_queries.is_empty()
+            && self.pending_query_updates.is_empty()
+            && self.pending_identity.is_none()
+            && self.received_client_version.query_set == self.current_version.query_set
+            && self.received_client_version.identity == self.current_version.identity;
+        if !settled {
+            return None;
+        }
+        // The invalidation futures are tied to the old connection's worker.
+        // Leave the subscriptions so the next transition can refresh them
+        // instead of rerunning their queries.
+        for query in self.queries.values_mut() {
+            if let Some(handle) = query.invalidation_future.take() {
+                handle.abort();
+            }
+        }
+        self.invalidation_futures = FuturesUnordered::new();
+        self.refill_needed = true;
+        Some(self)
+    }
 }
 
 fn hash_result(
@@ -63,6 +63,7 @@ use sync_types::{
     Query,
     QueryId,
     QuerySetModification,
+    ResumeFrom,
     StateModification,
     UserIdentityAttributes,
 };
@@ -73,6 +74,7 @@ use crate::{
         measurable_unbounded_channel,
         SingleFlightReceiver,
     },
+    ResumableSessions,
     ServerMessage,
     SyncWorker,
     SyncWorkerConfig,
@@ -135,6 +137,42 @@ impl<RT: Runtime> SyncTest<RT> {
         &self,
         config: SyncWorkerConfig,
         max_observed_timestamp: Option<Timestamp>,
+    ) -> anyhow::Result<TestSyncWorker<RT>> {
+        let connect = ClientMessage::Connect {
+            session_id: SessionId::nil(),
+            connection_count: 0,
+            last_close_reason: "InitialConnect".to_string(),
+            max_observed_timestamp,
+            resumable: false,
+            resume_from: None,
+        };
+        self.new_worker_with_connect(config, connect)
+    }
+
+    fn new_resumable_worker(
+        &self,
+        resumable_sessions: Arc<ResumableSessions>,
+        resume_from: Option<ResumeFrom>,
+    ) -> anyhow::Result<TestSyncWorker<RT>> {
+        let config = SyncWorkerConfig {
+            resumable_sessions: Some(resumable_sessions),
+            ..SyncWorkerConfig::default()
+        };
+        let connect = ClientMessage::Connect {
+            session_id: SessionId::nil(),
+            connection_count: 1,
+            last_close_reason: "InitialConnect".to_string(),
+            max_observed_timestamp: None,
+            resumable: true,
+            resume_from,
+        };
+        self.new_worker_with_connect(config, connect)
+    }
+
+    fn new_worker_with_connect(
+        &self,
+        config: SyncWorkerConfig,
+        connect: ClientMessage,
     ) -> anyhow::Result<TestSyncWorker<RT>> {
         let worker_failed = Arc::new(Mutex::new(None));
         let (client_tx, client_rx) = mpsc::unbounded_channel();
@@ -166,15 +204,7 @@ impl<RT: Runtime> SyncTest<RT> {
         };
         let worker_handle = self.rt.spawn("sync_test", future);
 
-        client_tx.send((
-            ClientMessage::Connect {
-                session_id: SessionId::nil(),
-                connection_count: 0,
-                last_close_reason: "InitialConnect".to_string(),
-                max_observed_timestamp,
-            },
-            self.rt.monotonic_now(),
-        ))?;
+        client_tx.send((connect, self.rt.monotonic_now()))?;
 
         Ok(TestSyncWorker {
             rt: self.rt.clone(),
@@ -290,6 +320,7 @@ async fn test_basic_account(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![assert_obj!("name" => name1.clone()).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 0,
@@ -343,6 +374,7 @@ async fn test_basic_account(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![assert_obj!("name" => name2.clone()).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 1,
@@ -434,6 +466,7 @@ async fn test_remove_in_progress_query(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![assert_obj!("name" => name1.clone()).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 0,
@@ -472,6 +505,7 @@ async fn test_query_failure(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![assert_obj!("i" => ConvexValue::from(0.0)).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let query2 = Query {
         query_id: QueryId::new(1),
@@ -479,6 +513,7 @@ async fn test_query_failure(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![assert_obj!("i" => ConvexValue::from(3.0)).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 0,
@@ -513,6 +548,7 @@ async fn test_query_failure(rt: TestRuntime) -> anyhow::Result<()> {
         args: vec![],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: end_version.query_set,
@@ -737,6 +773,7 @@ async fn test_value_deduplication_success(rt: TestRuntime) -> anyhow::Result<()>
         args: vec![assert_obj!("throwError" => ConvexValue::from(false)).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 0,
@@ -794,6 +831,7 @@ async fn test_value_deduplication_failure(rt: TestRuntime) -> anyhow::Result<()>
         args: vec![assert_obj!("throwError" => ConvexValue::from(true)).into()],
         journal: None,
         component_path: None,
+        fields: None,
     };
     let msg = ClientMessage::ModifyQuerySet {
         base_version: 0,
@@ -908,3 +946,81 @@ async fn test_max_observed_timestamp(rt: TestRuntime) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[convex_macro::test_runtime]
+async fn test_resume_session(rt: TestRuntime) -> anyhow::Result<()> {
+    let test = SyncTest::new(rt).await?;
+    let resumable_sessions = ResumableSessions::new();
+
+    let mut sync_worker = test.new_resumable_worker(resumable_sessions.clone(), None)?;
+    must_let!(let ServerMessage::SessionResumeToken {
+        token,
+        resumed: false,
+    } = sync_worker.receive().await?);
+    for (request_id, name) in [(0, "orinoco"), (1, "tizoncito")] {
+        sync_worker
+            .mutation(
+                "sync:initialize",
+                assert_obj!("name" => name, "balance" => 100.0),
+                request_id,
+            )
+            .await?;
+        must_let!(let ServerMessage::Transition { .. } = sync_worker.receive().await?);
+    }
+    let queries = ["orinoco", "tizoncito"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            Ok(QuerySetModification::Add(Query {
+                query_id: QueryId::new(i as u32),
+                udf_path: "sync:accountBalance".parse()?,
+                args: vec![assert_obj!("name" => name).into()],
+                journal: None,
+                component_path: None,
+                fields: None,
+            }))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    sync_worker.send(ClientMessage::ModifyQuerySet {
+        base_version: 0,
+        new_version: 1,
+        modifications: queries,
+    })?;
+    must_let!(let ServerMessage::Transition { end_version, modifications, .. } = sync_worker.receive().await?);
+    assert_eq!(modifications.len(), 2);
+    sync_worker.shutdown().await?;
+
+    // Change one of the balances while the client is disconnected.
+    let mut other_worker = test.new_worker()?;
+    other_worker
+        .mutation(
+            "sync:deposit",
+            assert_obj!("name" => "orinoco", "balance" => 50.0),
+            0,
+        )
+        .await?;
+    other_worker.shutdown().await?;
+
+    // The resumed session only sends the query that changed.
+    let resume_from = ResumeFrom {
+        token,
+        version: end_version,
+    };
+    let mut sync_worker =
+        test.new_resumable_worker(resumable_sessions.clone(), Some(resume_from.clone()))?;
+    must_let!(let ServerMessage::SessionResumeToken { resumed: true, .. } = sync_worker.receive().await?);
+    must_let!(let ServerMessage::Transition { start_version, end_version, modifications } = sync_worker.receive().await?);
+    assert_eq!(start_version, resume_from.version);
+    assert_eq!(end_version.query_set, 1);
+    assert_eq!(modifications.len(), 1);
+    must_let!(let StateModification::QueryUpdated { query_id, value, .. } = &modifications[0]);
+    assert_eq!(*query_id, QueryId::new(0));
+    assert_eq!(value, &ConvexValue::from(150.0));
+    sync_worker.shutdown().await?;
+
+    // Tokens can only be used once.
+    let mut sync_worker = test.new_resumable_worker(resumable_sessions, Some(resume_from))?;
+    must_let!(let ServerMessage::SessionResumeToken { resumed: false, .. } = sync_worker.receive().await?);
+    sync_worker.shutdown().await?;
+    Ok(())
+}
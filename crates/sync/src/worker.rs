@@ -1,5 +1,6 @@
 use std::{
     collections::BTreeMap,
+    mem,
     sync::{
         atomic::{
             AtomicUsize,
@@ -36,6 +37,7 @@ use common::{
     fastrace_helpers::get_sampled_span,
     http::ResolvedHostname,
     knobs::SYNC_MAX_SEND_TRANSITION_COUNT,
+    projection::project_documents,
     runtime::{
         Runtime,
         WithTimeout,
@@ -74,6 +76,7 @@ use sync_types::{
     IdentityVersion,
     QueryId,
     QuerySetModification,
+    ResumeFrom,
     SerializedQueryJournal,
     SessionId,
     StateModification,
@@ -97,6 +100,7 @@ use crate::{
         mutation_queue_timer,
         TypedClientEvent,
     },
+    resume::ResumableSessions,
     state::SyncState,
     ServerMessage,
 };
@@ -114,12 +118,16 @@ const SYNC_WORKER_PROCESS_TIMEOUT: Duration = Duration::from_secs(60);
 #[derive(Clone, Debug)]
 pub struct SyncWorkerConfig {
     pub client_version: ClientVersion,
+    /// Where sessions are kept for resumption. Clients can't resume sessions
+    /// if unset.
+    pub resumable_sessions: Option<Arc<ResumableSessions>>,
 }
 
 impl Default for SyncWorkerConfig {
     fn default() -> Self {
         Self {
             client_version: ClientVersion::unknown(),
+            resumable_sessions: None,
         }
     }
 }
@@ -236,6 +244,9 @@ pub struct SyncWorker<RT: Runtime> {
     update_scheduled: bool,
 
     on_connect: Option<(StatusTimer, Box<dyn FnOnce(SessionId) + Send>)>,
+
+    // The token this connection's client can resume its session with.
+    resume_token: Option<String>,
 }
 
 enum QueryResult {
@@ -281,6 +292,7 @@ impl<RT: Runtime> SyncWorker<RT> {
             transition_future: None,
             update_scheduled: false,
             on_connect: Some((connect_timer(), on_connect)),
+            resume_token: None,
         }
     }
 
@@ -398,6 +410,13 @@ impl<RT: Runtime> SyncWorker<RT> {
         Ok(())
     }
 
+    /// The session's identity, unless its token has expired or been revoked.
+    fn identity(&self) -> anyhow::Result<Identity> {
+        let identity = self.state.identity(self.rt.system_time())?;
+        self.api.check_token_revocation(&identity)?;
+        Ok(identity)
+    }
+
     pub fn identity_version(&self) -> IdentityVersion {
         self.state.current_version().identity
     }
@@ -427,11 +446,16 @@ impl<RT: Runtime> SyncWorker<RT> {
                 last_close_reason,
                 max_observed_timestamp,
                 connection_count,
+                resumable,
+                resume_from,
             } => {
                 if let Some((timer, on_connect)) = self.on_connect.take() {
                     timer.finish();
                     on_connect(session_id);
                 }
+                if resumable {
+                    self.resume_session(session_id, resume_from);
+                }
                 self.state.set_session_id(session_id);
                 if let Some(max_observed_timestamp) = max_observed_timestamp {
                     let latest_timestamp = *self
@@ -471,11 +495,12 @@ impl<RT: Runtime> SyncWorker<RT> {
                 args,
                 component_path,
             } => {
-                let identity = self.state.identity(self.rt.system_time())?;
+                let identity = self.identity()?;
                 let mutation_identifier =
                     self.state.session_id().map(|id| SessionRequestIdentifier {
                         session_id: id,
                         request_id,
+                        args_hash: None,
                     });
                 let server_request_id = match self.state.session_id() {
                     Some(id) => RequestId::new_for_ws_session(id, request_id),
@@ -572,7 +597,7 @@ impl<RT: Runtime> SyncWorker<RT> {
                 args,
                 component_path,
             } => {
-                let identity = self.state.identity(self.rt.system_time())?;
+                let identity = self.identity()?;
 
                 let api = self.api.clone();
                 let host = self.host.clone();
@@ -674,6 +699,39 @@ impl<RT: Runtime> SyncWorker<RT> {
         Ok(())
     }
 
+    /// Picks up the session parked under `resume_from`'s token if there is one,
+    /// and sends the client a token to resume this connection's session with.
+    fn resume_session(&mut self, session_id: SessionId, resume_from: Option<ResumeFrom>) {
+        let Some(sessions) = self.config.resumable_sessions.clone() else {
+            return;
+        };
+        let mut resumed = false;
+        if let Some(ResumeFrom { token, version }) = resume_from {
+            let state = sessions.resume(
+                &token,
+                session_id,
+                version,
+                self.rt.monotonic_now(),
+                self.rt.system_time(),
+            );
+            if let Some(state) = state {
+                self.state = state;
+                // Refresh the subscriptions up to now, sending the client only
+                // the queries that changed while it was away.
+                self.schedule_update();
+                resumed = true;
+            }
+            metrics::log_session_resume(resumed);
+        }
+        let token = self.rt.new_uuid_v4().simple().to_string();
+        self.resume_token = Some(token.clone());
+        // If the client is gone, the main loop will exit once it notices.
+        _ = self.tx.send((
+            ServerMessage::SessionResumeToken { token, resumed },
+            self.rt.monotonic_now(),
+        ));
+    }
+
     fn begin_update_queries(
         &mut self,
         new_ts: Timestamp,
@@ -708,7 +766,7 @@ impl<RT: Runtime> SyncWorker<RT> {
             self.state.insert_identity(new_identity);
             identity_version = new_identity_version;
         }
-        let identity = self.state.identity(self.rt.system_time())?;
+        let identity = self.identity()?;
 
         // Step 1: Decide on a new target (query set version, identity version, ts) for
         // the system.
@@ -808,7 +866,15 @@ impl<RT: Runtime> SyncWorker<RT> {
                         let subscription = subscriptions_client.subscribe(udf_return.token).await?;
                         (
                             QueryResult::Rerun {
-                                result: udf_return.result,
+                                // Projecting before the result is hashed means
+                                // changes to fields the client didn't ask for
+                                // don't cause transitions.
+                                result: match &query.fields {
+                                    Some(fields) => udf_return
+                                        .result
+                                        .map(|value| project_documents(value, fields)),
+                                    None => udf_return.result,
+                                },
                                 log_lines: udf_return.log_lines,
                                 journal: udf_return.journal,
                             },
@@ -895,3 +961,14 @@ impl<RT: Runtime> SyncWorker<RT> {
         Ok(transition)
     }
 }
+
+impl<RT: Runtime> Drop for SyncWorker<RT> {
+    fn drop(&mut self) {
+        if let Some(sessions) = &self.config.resumable_sessions
+            && let Some(token) = self.resume_token.take()
+        {
+            let state = mem::replace(&mut self.state, SyncState::new());
+            sessions.park(token, state, self.rt.monotonic_now());
+        }
+    }
+}
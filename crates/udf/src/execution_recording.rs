@@ -0,0 +1,444 @@
+use bytes::Bytes;
+use common::{
+    components::{
+        CanonicalizedComponentFunctionPath,
+        SerializedComponentFunctionPath,
+    },
+    runtime::UnixTimestamp,
+    types::UdfType,
+};
+use errors::ErrorMetadata;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value as JsonValue;
+use value::ConvexArray;
+
+/// Everything a function observed from outside its JavaScript execution: its
+/// arguments, the RNG seed and time it was given, and the result of every
+/// syscall it made. Since UDFs are otherwise deterministic, replaying these
+/// inputs re-executes the function exactly, without access to the database
+/// state it originally read.
+///
+/// Actions also read the clock and run async operations like `fetch` that
+/// complete in a nondeterministic order, so their recordings list these in
+/// `action_events` instead of `syscalls`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecutionRecording {
+    pub udf_type: UdfType,
+    pub path: CanonicalizedComponentFunctionPath,
+    pub arguments: ConvexArray,
+    pub rng_seed: [u8; 32],
+    pub unix_timestamp: UnixTimestamp,
+    /// Syscalls in the order their results were returned to JavaScript.
+    pub syscalls: Vec<RecordedSyscall>,
+    /// For actions, everything the action observed in the order it happened.
+    pub action_events: Vec<RecordedActionEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedSyscall {
+    pub name: String,
+    pub args: JsonValue,
+    /// Only deterministic developer errors are recorded. Any other error
+    /// fails the execution, so it doesn't produce a recording.
+    pub result: Result<JsonValue, ErrorMetadata>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedActionEvent {
+    /// A read of the current time, e.g. from `Date.now()`.
+    UnixTimestamp(UnixTimestamp),
+    /// An async syscall or op (`fetch`, `runQuery`, ...) completing, with the
+    /// value that was passed to JavaScript.
+    TaskDone {
+        task_id: usize,
+        name: String,
+        result: Result<JsonValue, RecordedTaskError>,
+    },
+    /// The next chunk of a response body, or `None` once it's done. Only
+    /// deterministic developer errors are recorded.
+    StreamExtend {
+        stream_id: String,
+        chunk: Result<Option<Bytes>, ErrorMetadata>,
+    },
+}
+
+/// An error thrown into JavaScript by a failed async operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedTaskError {
+    pub message: String,
+    /// The `data` of a `ConvexError`.
+    pub data: Option<JsonValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedExecutionRecording {
+    udf_type: String,
+    path: SerializedComponentFunctionPath,
+    arguments: JsonValue,
+    rng_seed: String,
+    unix_timestamp_nanos: String,
+    syscalls: Vec<SerializedRecordedSyscall>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    action_events: Vec<SerializedRecordedActionEvent>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedRecordedSyscall {
+    name: String,
+    args: JsonValue,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    result: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<SerializedSyscallError>,
+}
+
+/// Deserializes a field that's present as `Some`, even if it's `null`, so a
+/// syscall that returned `null` isn't mistaken for one without a result.
+fn deserialize_present<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<JsonValue>, D::Error> {
+    JsonValue::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum SerializedRecordedActionEvent {
+    #[serde(rename_all = "camelCase")]
+    UnixTimestamp { unix_timestamp_nanos: String },
+    #[serde(rename_all = "camelCase")]
+    TaskDone {
+        task_id: usize,
+        name: String,
+        #[serde(
+            default,
+            deserialize_with = "deserialize_present",
+            skip_serializing_if = "Option::is_none"
+        )]
+        result: Option<JsonValue>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<SerializedTaskError>,
+    },
+    #[serde(rename_all = "camelCase")]
+    StreamExtend {
+        stream_id: String,
+        /// Hex-encoded chunk, absent once the stream is done.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        chunk: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<SerializedSyscallError>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedTaskError {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<JsonValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedSyscallError {
+    short_msg: String,
+    msg: String,
+}
+
+impl TryFrom<ExecutionRecording> for SerializedExecutionRecording {
+    type Error = anyhow::Error;
+
+    fn try_from(recording: ExecutionRecording) -> anyhow::Result<Self> {
+        Ok(Self {
+            udf_type: recording.udf_type.to_string(),
+            path: recording.path.try_into()?,
+            arguments: recording.arguments.into(),
+            rng_seed: hex::encode(recording.rng_seed),
+            unix_timestamp_nanos: recording.unix_timestamp.as_nanos().to_string(),
+            syscalls: recording
+                .syscalls
+                .into_iter()
+                .map(|syscall| {
+                    let (result, error) = match syscall.result {
+                        Ok(result) => (Some(result), None),
+                        Err(e) => (
+                            None,
+                            Some(SerializedSyscallError {
+                                short_msg: e.short_msg.to_string(),
+                                msg: e.msg.to_string(),
+                            }),
+                        ),
+                    };
+                    SerializedRecordedSyscall {
+                        name: syscall.name,
+                        args: syscall.args,
+                        result,
+                        error,
+                    }
+                })
+                .collect(),
+            action_events: recording
+                .action_events
+                .into_iter()
+                .map(SerializedRecordedActionEvent::from)
+                .collect(),
+        })
+    }
+}
+
+impl TryFrom<SerializedExecutionRecording> for ExecutionRecording {
+    type Error = anyhow::Error;
+
+    fn try_from(recording: SerializedExecutionRecording) -> anyhow::Result<Self> {
+        let rng_seed = hex::decode(&recording.rng_seed)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid rng seed in recording"))?;
+        Ok(Self {
+            udf_type: recording.udf_type.parse()?,
+            path: recording.path.try_into()?,
+            arguments: recording.arguments.try_into()?,
+            rng_seed,
+            unix_timestamp: UnixTimestamp::from_nanos(recording.unix_timestamp_nanos.parse()?),
+            syscalls: recording
+                .syscalls
+                .into_iter()
+                .map(|syscall| {
+                    let result = match (syscall.result, syscall.error) {
+                        (Some(result), None) => Ok(result),
+                        (None, Some(SerializedSyscallError { short_msg, msg })) => {
+                            Err(ErrorMetadata::bad_request(short_msg, msg))
+                        },
+                        _ => anyhow::bail!(
+                            "Recorded syscall {} must have exactly one of result or error",
+                            syscall.name
+                        ),
+                    };
+                    Ok(RecordedSyscall {
+                        name: syscall.name,
+                        args: syscall.args,
+                        result,
+                    })
+                })
+                .try_collect()?,
+            action_events: recording
+                .action_events
+                .into_iter()
+                .map(RecordedActionEvent::try_from)
+                .try_collect()?,
+        })
+    }
+}
+
+impl From<RecordedActionEvent> for SerializedRecordedActionEvent {
+    fn from(event: RecordedActionEvent) -> Self {
+        match event {
+            RecordedActionEvent::UnixTimestamp(ts) => Self::UnixTimestamp {
+                unix_timestamp_nanos: ts.as_nanos().to_string(),
+            },
+            RecordedActionEvent::TaskDone {
+                task_id,
+                name,
+                result,
+            } => {
+                let (result, error) = match result {
+                    Ok(result) => (Some(result), None),
+                    Err(RecordedTaskError { message, data }) => {
+                        (None, Some(SerializedTaskError { message, data }))
+                    },
+                };
+                Self::TaskDone {
+                    task_id,
+                    name,
+                    result,
+                    error,
+                }
+            },
+            RecordedActionEvent::StreamExtend { stream_id, chunk } => {
+                let (chunk, error) = match chunk {
+                    Ok(chunk) => (chunk.map(hex::encode), None),
+                    Err(e) => (
+                        None,
+                        Some(SerializedSyscallError {
+                            short_msg: e.short_msg.to_string(),
+                            msg: e.msg.to_string(),
+                        }),
+                    ),
+                };
+                Self::StreamExtend {
+                    stream_id,
+                    chunk,
+                    error,
+                }
+            },
+        }
+    }
+}
+
+impl TryFrom<SerializedRecordedActionEvent> for RecordedActionEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: SerializedRecordedActionEvent) -> anyhow::Result<Self> {
+        let event = match event {
+            SerializedRecordedActionEvent::UnixTimestamp {
+                unix_timestamp_nanos,
+            } => Self::UnixTimestamp(UnixTimestamp::from_nanos(unix_timestamp_nanos.parse()?)),
+            SerializedRecordedActionEvent::TaskDone {
+                task_id,
+                name,
+                result,
+                error,
+            } => {
+                let result = match (result, error) {
+                    (Some(result), None) => Ok(result),
+                    (None, Some(SerializedTaskError { message, data })) => {
+                        Err(RecordedTaskError { message, data })
+                    },
+                    _ => anyhow::bail!(
+                        "Recorded task {name} must have exactly one of result or error"
+                    ),
+                };
+                Self::TaskDone {
+                    task_id,
+                    name,
+                    result,
+                }
+            },
+            SerializedRecordedActionEvent::StreamExtend {
+                stream_id,
+                chunk,
+                error,
+            } => {
+                let chunk = match (chunk, error) {
+                    (chunk, None) => Ok(chunk.map(hex::decode).transpose()?.map(Bytes::from)),
+                    (None, Some(SerializedSyscallError { short_msg, msg })) => {
+                        Err(ErrorMetadata::bad_request(short_msg, msg))
+                    },
+                    (Some(_), Some(_)) => anyhow::bail!(
+                        "Recorded chunk of stream {stream_id} can't have both data and an error"
+                    ),
+                };
+                Self::StreamExtend { stream_id, chunk }
+            },
+        };
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use common::{
+        components::{
+            CanonicalizedComponentFunctionPath,
+            ComponentPath,
+        },
+        runtime::UnixTimestamp,
+        types::UdfType,
+    };
+    use errors::ErrorMetadata;
+    use serde_json::json;
+    use value::{
+        assert_val,
+        ConvexArray,
+    };
+
+    use super::{
+        ExecutionRecording,
+        RecordedActionEvent,
+        RecordedSyscall,
+        RecordedTaskError,
+        SerializedExecutionRecording,
+    };
+
+    #[test]
+    fn test_recording_roundtrips() -> anyhow::Result<()> {
+        let recording = ExecutionRecording {
+            udf_type: UdfType::Mutation,
+            path: CanonicalizedComponentFunctionPath {
+                component: ComponentPath::root(),
+                udf_path: "messages:send".parse()?,
+            },
+            arguments: ConvexArray::try_from(vec![assert_val!({"body" => "hi"})])?,
+            rng_seed: [7; 32],
+            unix_timestamp: UnixTimestamp::from_nanos(1_700_000_000_123_456_789),
+            syscalls: vec![
+                RecordedSyscall {
+                    name: "1.0/queryStreamNext".to_string(),
+                    args: json!({ "queryId": 0 }),
+                    result: Ok(json!({ "value": null, "done": true })),
+                },
+                RecordedSyscall {
+                    name: "1.0/get".to_string(),
+                    args: json!({ "id": "abc" }),
+                    result: Ok(json!(null)),
+                },
+                RecordedSyscall {
+                    name: "1.0/insert".to_string(),
+                    args: json!({ "table": "messages", "value": {} }),
+                    result: Err(ErrorMetadata::bad_request("InvalidTable", "no")),
+                },
+            ],
+            action_events: vec![],
+        };
+        let serialized: SerializedExecutionRecording = recording.clone().try_into()?;
+        let serialized: SerializedExecutionRecording =
+            serde_json::from_str(&serde_json::to_string(&serialized)?)?;
+        assert_eq!(ExecutionRecording::try_from(serialized)?, recording);
+        Ok(())
+    }
+
+    #[test]
+    fn test_action_recording_roundtrips() -> anyhow::Result<()> {
+        let stream_id = "0b9fc8ef-1f43-4b0f-a6a4-2c1e0d5bd7b3".to_string();
+        let recording = ExecutionRecording {
+            udf_type: UdfType::Action,
+            path: CanonicalizedComponentFunctionPath {
+                component: ComponentPath::root(),
+                udf_path: "messages:fetchWeather".parse()?,
+            },
+            arguments: ConvexArray::try_from(vec![])?,
+            rng_seed: [3; 32],
+            unix_timestamp: UnixTimestamp::from_nanos(1_700_000_000_000_000_000),
+            syscalls: vec![],
+            action_events: vec![
+                RecordedActionEvent::UnixTimestamp(UnixTimestamp::from_nanos(
+                    1_700_000_000_500_000_000,
+                )),
+                RecordedActionEvent::TaskDone {
+                    task_id: 0,
+                    name: "fetch".to_string(),
+                    result: Ok(json!({ "streamId": stream_id, "status": 200 })),
+                },
+                RecordedActionEvent::StreamExtend {
+                    stream_id: stream_id.clone(),
+                    chunk: Ok(Some(Bytes::from_static(b"sunny"))),
+                },
+                RecordedActionEvent::StreamExtend {
+                    stream_id,
+                    chunk: Ok(None),
+                },
+                RecordedActionEvent::TaskDone {
+                    task_id: 1,
+                    name: "runMutation".to_string(),
+                    result: Err(RecordedTaskError {
+                        message: "Uncaught ConvexError: taken".to_string(),
+                        data: Some(json!("taken")),
+                    }),
+                },
+            ],
+        };
+        let serialized: SerializedExecutionRecording = recording.clone().try_into()?;
+        let serialized: SerializedExecutionRecording =
+            serde_json::from_str(&serde_json::to_string(&serialized)?)?;
+        assert_eq!(ExecutionRecording::try_from(serialized)?, recording);
+        Ok(())
+    }
+}
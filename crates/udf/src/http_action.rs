@@ -2,7 +2,13 @@ use core::fmt;
 
 use bytes::Bytes;
 use common::{
-    http::normalize_header_map,
+    http::{
+        fetch::{
+            WebSocketMessage,
+            WebSocketSink,
+        },
+        normalize_header_map,
+    },
     types::{
         HttpActionRoute,
         RoutableMethod,
@@ -32,16 +38,31 @@ pub const HTTP_ACTION_BODY_LIMIT: usize = 20 << 20;
 pub struct HttpActionRequest {
     pub head: HttpActionRequestHead,
     pub body: Option<BoxStream<'static, anyhow::Result<bytes::Bytes>>>,
+    /// Set if the client asked to upgrade the request to a WebSocket.
+    pub websocket: Option<HttpActionWebSocket>,
 }
 
 impl fmt::Debug for HttpActionRequest {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HttpActionRequest")
             .field("head", &self.head)
+            .field("websocket", &self.websocket.is_some())
             .finish()
     }
 }
 
+/// The action's end of a WebSocket that a client asked to upgrade an HTTP
+/// action's request to. The action accepts it by responding with `101
+/// Switching Protocols`, and the connection then lasts until either side
+/// closes it or the action finishes. If the action responds with anything
+/// else, the connection is never opened.
+pub struct HttpActionWebSocket {
+    /// Messages to the client.
+    pub sink: WebSocketSink,
+    /// Messages from the client. The stream ends when the connection drops.
+    pub stream: BoxStream<'static, anyhow::Result<WebSocketMessage>>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HttpActionRequestHead {
     pub headers: HeaderMap,
@@ -124,7 +145,8 @@ impl proptest::arbitrary::Arbitrary for HttpActionRequest {
                         method: method.to_string().parse()?,
                         url,
                     },
-                    body: body.map(|body| stream::once(async move { Ok(body.into())}).boxed())
+                    body: body.map(|body| stream::once(async move { Ok(body.into())}).boxed()),
+                    websocket: None,
 
                 })
             }
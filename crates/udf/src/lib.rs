@@ -4,6 +4,7 @@
 mod action_outcome;
 mod client;
 pub mod environment;
+mod execution_recording;
 mod function_outcome;
 pub mod helpers;
 mod http_action;
@@ -24,6 +25,13 @@ pub use crate::{
         EvaluateAppDefinitionsResult,
         FunctionResult,
     },
+    execution_recording::{
+        ExecutionRecording,
+        RecordedActionEvent,
+        RecordedSyscall,
+        RecordedTaskError,
+        SerializedExecutionRecording,
+    },
     function_outcome::FunctionOutcome,
     http_action::{
         HttpActionRequest,
@@ -31,9 +39,13 @@ pub use crate::{
         HttpActionResponseHead,
         HttpActionResponsePart,
         HttpActionResponseStreamer,
+        HttpActionWebSocket,
         HTTP_ACTION_BODY_LIMIT,
     },
     syscall_stats::SyscallStats,
-    syscall_trace::SyscallTrace,
+    syscall_trace::{
+        SyscallTrace,
+        FETCH_SYSCALL_NAME,
+    },
     udf_outcome::UdfOutcome,
 };
@@ -18,6 +18,9 @@ use value::heap_size::{
 
 use crate::SyscallStats;
 
+/// Actions' fetches are traced alongside their async syscalls under this name.
+pub const FETCH_SYSCALL_NAME: &str = "fetch";
+
 #[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
 pub struct SyscallTrace {
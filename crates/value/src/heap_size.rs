@@ -914,6 +914,9 @@ impl<V: HeapSize> HeapSize for ServerMessage<V> {
             } => error_message.heap_size() + base_version.heap_size(),
             ServerMessage::FatalError { error_message } => error_message.heap_size(),
             ServerMessage::Ping => 0,
+            ServerMessage::SessionResumeToken { token, resumed } => {
+                token.heap_size() + resumed.heap_size()
+            },
         }
     }
 }
@@ -18,7 +18,7 @@ use crate::ConvexValue;
 
 #[must_use]
 #[cfg_attr(any(test, feature = "testing"), derive(proptest_derive::Arbitrary))]
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Hash)]
 pub struct Sha256Digest([u8; 32]);
 
 impl Sha256Digest {
@@ -287,6 +287,13 @@ async function startComponentsPushAndCodegen(
     appDefinition,
     componentDefinitions,
     nodeDependencies: appImplementation.externalNodeDependencies,
+    ...(projectConfig.node.nodeVersion
+      ? { nodeVersion: projectConfig.node.nodeVersion }
+      : {}),
+    ...(projectConfig.node.dependencyLayers
+      ? { dependencyLayers: projectConfig.node.dependencyLayers }
+      : {}),
+    ...(projectConfig.warmup ? { warmup: projectConfig.warmup } : {}),
   };
   if (options.writePushRequest) {
     const pushRequestPath = path.resolve(options.writePushRequest);
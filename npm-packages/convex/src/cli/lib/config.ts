@@ -58,6 +58,11 @@ export interface ProjectConfig {
   functions: string;
   node: {
     externalPackages: string[];
+    // Major version of Node.js for "use node" actions. Defaults to 18.
+    nodeVersion?: NodeVersion;
+    // Names of prebuilt node_modules layers uploaded to the deployment, for
+    // packages the bundler can't handle. Earlier layers take precedence.
+    dependencyLayers?: string[];
   };
   generateCommonJSApi: boolean;
   // deprecated
@@ -68,6 +73,21 @@ export interface ProjectConfig {
   prodUrl?: string;
   // deprecated
   authInfo?: AuthInfo[];
+  warmup?: WarmupFunction[];
+}
+
+export const NODE_VERSIONS = ["18", "20", "22"] as const;
+export type NodeVersion = (typeof NODE_VERSIONS)[number];
+
+/**
+ * A query to run after each deploy (and optionally every `intervalSeconds`)
+ * so the first real request doesn't hit cold caches.
+ */
+export interface WarmupFunction {
+  function: string;
+  componentPath?: string;
+  args?: Record<string, any>;
+  intervalSeconds?: number;
 }
 
 export interface Config {
@@ -102,6 +122,22 @@ function isAuthInfos(object: any): object is AuthInfo[] {
   return Array.isArray(object) && object.every((item: any) => isAuthInfo(item));
 }
 
+function isWarmupFunction(object: any): object is WarmupFunction {
+  return (
+    typeof object === "object" &&
+    object !== null &&
+    typeof object.function === "string" &&
+    (object.componentPath === undefined ||
+      typeof object.componentPath === "string") &&
+    (object.args === undefined ||
+      (typeof object.args === "object" &&
+        object.args !== null &&
+        !Array.isArray(object.args))) &&
+    (object.intervalSeconds === undefined ||
+      (Number.isInteger(object.intervalSeconds) && object.intervalSeconds > 0))
+  );
+}
+
 /** Error parsing ProjectConfig representation. */
 class ParseError extends Error {}
 
@@ -134,6 +170,30 @@ export async function parseProjectConfig(
         "Expected `node.externalPackages` in `convex.json` to be an array of strings",
     });
   }
+  if (
+    obj.node.nodeVersion !== undefined &&
+    !NODE_VERSIONS.includes(obj.node.nodeVersion)
+  ) {
+    return await ctx.crash({
+      exitCode: 1,
+      errorType: "invalid filesystem data",
+      printedMessage: `Expected \`node.nodeVersion\` in \`convex.json\` to be one of ${NODE_VERSIONS.map((v) => `"${v}"`).join(", ")}`,
+    });
+  }
+  if (
+    obj.node.dependencyLayers !== undefined &&
+    (!Array.isArray(obj.node.dependencyLayers) ||
+      !obj.node.dependencyLayers.every(
+        (item: any) => typeof item === "string",
+      ))
+  ) {
+    return await ctx.crash({
+      exitCode: 1,
+      errorType: "invalid filesystem data",
+      printedMessage:
+        "Expected `node.dependencyLayers` in `convex.json` to be an array of strings",
+    });
+  }
   if (typeof obj.generateCommonJSApi === "undefined") {
     obj.generateCommonJSApi = false;
   } else if (typeof obj.generateCommonJSApi !== "boolean") {
@@ -167,6 +227,20 @@ export async function parseProjectConfig(
     }
   }
 
+  if (obj.warmup !== undefined) {
+    if (
+      !Array.isArray(obj.warmup) ||
+      !obj.warmup.every((item: any) => isWarmupFunction(item))
+    ) {
+      return await ctx.crash({
+        exitCode: 1,
+        errorType: "invalid filesystem data",
+        printedMessage:
+          "Expected `warmup` in `convex.json` to be an array of `{ function, componentPath?, args?, intervalSeconds? }`",
+      });
+    }
+  }
+
   return obj;
 }
 
@@ -20,6 +20,21 @@ export const startPushRequest = looseObject({
   componentDefinitions: z.array(componentDefinitionConfig),
 
   nodeDependencies: z.array(nodeDependency),
+
+  nodeVersion: z.optional(z.string()),
+
+  dependencyLayers: z.optional(z.array(z.string())),
+
+  warmup: z.optional(
+    z.array(
+      looseObject({
+        function: z.string(),
+        componentPath: z.optional(z.string()),
+        args: z.optional(z.record(z.string(), z.any())),
+        intervalSeconds: z.optional(z.number()),
+      }),
+    ),
+  ),
 });
 export type StartPushRequest = z.infer<typeof startPushRequest>;
 
@@ -6,6 +6,12 @@ import { SchedulableFunctionReference } from "./scheduler.js";
 type CronSchedule = {
   type: "cron";
   cron: string;
+  timezone?: string;
+};
+type CalendarSchedule = {
+  type: "calendar";
+  calendar: string;
+  timezone?: string;
 };
 /** @public */
 export type IntervalSchedule =
@@ -136,6 +142,7 @@ export type Weekly = {
 /** @public */
 export type Schedule =
   | CronSchedule
+  | CalendarSchedule
   | IntervalSchedule
   | HourlySchedule
   | DailySchedule
@@ -153,8 +160,31 @@ export interface CronJob {
   name: string;
   args: JSONValue;
   schedule: Schedule;
+  dispatch?: CronDispatchOptions;
 }
 
+/**
+ * Options that spread out a cron's work, so crons that fan out to many
+ * scheduled functions don't start them all at the same moment.
+ *
+ * @public
+ */
+export type CronDispatchOptions = {
+  /**
+   * Delay each run by a random duration of up to this many milliseconds,
+   * at most a day. Runs are never delayed past the next scheduled run.
+   */
+  jitterMs?: number;
+  /**
+   * The most functions a run can schedule to start each second. Functions
+   * the cron's mutation schedules past this rate start later, in the order
+   * they're due.
+   *
+   * Only applies to crons that run mutations.
+   */
+  maxDispatchPerSecond?: number;
+};
+
 /**
  * Create a CronJobs object to schedule recurring tasks.
  *
@@ -186,6 +216,36 @@ export const cronJobs = () => new Crons();
  */
 type CronString = string;
 
+/**
+ * @public
+ *
+ * A cron string evaluated in an IANA timezone like `"America/New_York"`
+ * instead of UTC.
+ */
+export type CronWithTimezone = {
+  cron: CronString;
+  timezone: string;
+};
+
+/**
+ * @public
+ *
+ * A calendar expression like `"last weekday of month at 17:00"`, see
+ * {@link Crons.calendar}.
+ */
+type CalendarString = string;
+
+/**
+ * @public
+ *
+ * A calendar expression evaluated in an IANA timezone like
+ * `"America/New_York"` instead of UTC.
+ */
+export type CalendarWithTimezone = {
+  calendar: CalendarString;
+  timezone: string;
+};
+
 function validateIntervalNumber(n: number) {
   if (!Number.isInteger(n) || n <= 0) {
     throw new Error("Interval must be an integer greater than 0");
@@ -224,6 +284,34 @@ function validatedCronString(s: string) {
   return s;
 }
 
+function validatedCalendarString(s: string) {
+  if (typeof s !== "string" || !s.includes(" of month at ")) {
+    throw new Error(
+      'Calendar expressions must look like "last weekday of month at 17:00"',
+    );
+  }
+  return s;
+}
+
+function validatedDispatchOptions(options: CronDispatchOptions) {
+  const { jitterMs, maxDispatchPerSecond } = options;
+  if (
+    jitterMs !== undefined &&
+    (!Number.isInteger(jitterMs) || jitterMs < 0 || jitterMs > 86_400_000)
+  ) {
+    throw new Error(
+      "jitterMs must be an integer number of milliseconds from 0 to 86400000 (a day)",
+    );
+  }
+  if (
+    maxDispatchPerSecond !== undefined &&
+    (!Number.isInteger(maxDispatchPerSecond) || maxDispatchPerSecond <= 0)
+  ) {
+    throw new Error("maxDispatchPerSecond must be an integer greater than 0");
+  }
+  return { jitterMs, maxDispatchPerSecond };
+}
+
 function validatedCronIdentifier(s: string) {
   if (!s.match(/^[ -~]*$/)) {
     throw new Error(
@@ -267,6 +355,34 @@ export class Crons {
     };
   }
 
+  /**
+   * Spread out the work of a cron job registered on this object, e.g. one
+   * that schedules a function for every user.
+   *
+   * ```js
+   * crons.daily(
+   *   "send digests",
+   *   { hourUTC: 9, minuteUTC: 0 },
+   *   internal.digests.scheduleAll,
+   * );
+   * crons.setDispatchOptions("send digests", {
+   *   jitterMs: 5 * 60 * 1000,
+   *   maxDispatchPerSecond: 50,
+   * });
+   * ```
+   *
+   * @param cronIdentifier - The name the cron job was registered with.
+   * @param options - How to spread out its work. See
+   * {@link CronDispatchOptions}.
+   */
+  setDispatchOptions(cronIdentifier: string, options: CronDispatchOptions) {
+    const cron = this.crons[cronIdentifier];
+    if (cron === undefined) {
+      throw new Error(`Unknown cron identifier: ${cronIdentifier}`);
+    }
+    cron.dispatch = validatedDispatchOptions(options);
+  }
+
   /**
    * Schedule a mutation or action to run at some interval.
    *
@@ -471,22 +587,82 @@ export class Crons {
    * "* * * * *"
    * ```
    *
+   * The cron string is evaluated in UTC unless it's passed with an IANA
+   * timezone like `{ cron: "0 9 * * 1-5", timezone: "Europe/Berlin" }`, which
+   * follows the timezone's daylight saving changes.
+   *
    * @param cronIdentifier - A unique name for this scheduled job.
-   * @param cron - Cron string like `"15 7 * * *"` (Every day at 7:15 UTC)
+   * @param cron - Cron string like `"15 7 * * *"` (Every day at 7:15 UTC), or
+   * a {@link CronWithTimezone}.
    * @param functionReference - A {@link FunctionReference} for the function
    * to schedule.
    * @param args - The arguments to the function.
    */
   cron<FuncRef extends SchedulableFunctionReference>(
     cronIdentifier: string,
-    cron: CronString,
+    cron: CronString | CronWithTimezone,
+    functionReference: FuncRef,
+    ...args: OptionalRestArgs<FuncRef>
+  ) {
+    const c =
+      typeof cron === "string"
+        ? validatedCronString(cron)
+        : validatedCronString(cron.cron);
+    const timezone = typeof cron === "string" ? undefined : cron.timezone;
+    this.schedule(
+      cronIdentifier,
+      timezone === undefined
+        ? { cron: c, type: "cron" }
+        : { cron: c, timezone, type: "cron" },
+      functionReference,
+      ...args,
+    );
+  }
+
+  /**
+   * Schedule a mutation or action to run once a month on a day that cron
+   * strings can't express, like the last weekday of the month.
+   *
+   * Calendar expressions have the form
+   * `"<ordinal> <day> of month at <hh>:<mm>"`:
+   * - The ordinal counts from the start of the month, like `"first"` or
+   *   `"3rd"`, or from its end, like `"last"` or `"2nd last"`.
+   * - The day is `"day"`, a day of the week like `"tuesday"`, or `"weekday"`
+   *   (also `"business day"`) for Monday to Friday.
+   *
+   * For example `"last weekday of month at 17:00"`,
+   * `"2nd tuesday of month at 9:30"` or
+   * `"3rd business day of month at 08:00"`. Months without a matching day,
+   * like months with only four Tuesdays for `"5th tuesday of month"`, are
+   * skipped. Invalid expressions are rejected when the crons are pushed.
+   *
+   * Like {@link Crons.cron}, the expression is evaluated in UTC unless it's
+   * passed with an IANA timezone.
+   *
+   * @param cronIdentifier - A unique name for this scheduled job.
+   * @param calendar - A calendar expression, or a
+   * {@link CalendarWithTimezone}.
+   * @param functionReference - A {@link FunctionReference} for the function
+   * to schedule.
+   * @param args - The arguments to the function.
+   */
+  calendar<FuncRef extends SchedulableFunctionReference>(
+    cronIdentifier: string,
+    calendar: CalendarString | CalendarWithTimezone,
     functionReference: FuncRef,
     ...args: OptionalRestArgs<FuncRef>
   ) {
-    const c = validatedCronString(cron);
+    const c =
+      typeof calendar === "string"
+        ? validatedCalendarString(calendar)
+        : validatedCalendarString(calendar.calendar);
+    const timezone =
+      typeof calendar === "string" ? undefined : calendar.timezone;
     this.schedule(
       cronIdentifier,
-      { cron: c, type: "cron" },
+      timezone === undefined
+        ? { calendar: c, type: "calendar" }
+        : { calendar: c, timezone, type: "calendar" },
       functionReference,
       ...args,
     );
@@ -2,19 +2,29 @@ import { convexToJson, Value } from "../../values/index.js";
 import { version } from "../../index.js";
 import { performAsyncSyscall } from "./syscall.js";
 import { parseArgs } from "../../common/index.js";
-import { SchedulableFunctionReference, Scheduler } from "../scheduler.js";
+import {
+  CancelAllResult,
+  RetryPolicy,
+  SchedulableFunctionReference,
+  ScheduledFunctionFilter,
+  ScheduleOptions,
+  Scheduler,
+} from "../scheduler.js";
 import { Id } from "../../values/value.js";
 import { validateArg } from "./validate.js";
 import { getFunctionAddress } from "../components/paths.js";
 
-export function setupMutationScheduler(): Scheduler {
+export function setupMutationScheduler(options?: ScheduleOptions): Scheduler {
   return {
     runAfter: async (
       delayMs: number,
       functionReference: SchedulableFunctionReference,
       args?: Record<string, Value>,
     ) => {
-      const syscallArgs = runAfterSyscallArgs(delayMs, functionReference, args);
+      const syscallArgs = {
+        ...runAfterSyscallArgs(delayMs, functionReference, args),
+        ...optionsSyscallArgs(options),
+      };
       return await performAsyncSyscall("1.0/schedule", syscallArgs);
     },
     runAt: async (
@@ -22,11 +32,10 @@ export function setupMutationScheduler(): Scheduler {
       functionReference: SchedulableFunctionReference,
       args?: Record<string, Value>,
     ) => {
-      const syscallArgs = runAtSyscallArgs(
-        ms_since_epoch_or_date,
-        functionReference,
-        args,
-      );
+      const syscallArgs = {
+        ...runAtSyscallArgs(ms_since_epoch_or_date, functionReference, args),
+        ...optionsSyscallArgs(options),
+      };
       return await performAsyncSyscall("1.0/schedule", syscallArgs);
     },
     cancel: async (id: Id<"_scheduled_functions">) => {
@@ -34,10 +43,25 @@ export function setupMutationScheduler(): Scheduler {
       const args = { id: convexToJson(id) };
       await performAsyncSyscall("1.0/cancel_job", args);
     },
+    cancelAll: async (
+      filter?: ScheduledFunctionFilter,
+      cursor?: string | null,
+    ): Promise<CancelAllResult> => {
+      const syscallArgs = {
+        ...cancelAllSyscallArgs(filter ?? {}),
+        cursor: cursor ?? undefined,
+      };
+      return await performAsyncSyscall("1.0/cancel_jobs", syscallArgs);
+    },
+    withOptions: (newOptions: ScheduleOptions) =>
+      setupMutationScheduler(validatedOptions(newOptions)),
   };
 }
 
-export function setupActionScheduler(requestId: string): Scheduler {
+export function setupActionScheduler(
+  requestId: string,
+  options?: ScheduleOptions,
+): Scheduler {
   return {
     runAfter: async (
       delayMs: number,
@@ -47,6 +71,7 @@ export function setupActionScheduler(requestId: string): Scheduler {
       const syscallArgs = {
         requestId,
         ...runAfterSyscallArgs(delayMs, functionReference, args),
+        ...optionsSyscallArgs(options),
       };
       return await performAsyncSyscall("1.0/actions/schedule", syscallArgs);
     },
@@ -58,6 +83,7 @@ export function setupActionScheduler(requestId: string): Scheduler {
       const syscallArgs = {
         requestId,
         ...runAtSyscallArgs(ms_since_epoch_or_date, functionReference, args),
+        ...optionsSyscallArgs(options),
       };
       return await performAsyncSyscall("1.0/actions/schedule", syscallArgs);
     },
@@ -66,9 +92,102 @@ export function setupActionScheduler(requestId: string): Scheduler {
       const syscallArgs = { id: convexToJson(id) };
       return await performAsyncSyscall("1.0/actions/cancel_job", syscallArgs);
     },
+    cancelAll: async () => {
+      throw new Error(
+        "`cancelAll` is only available in mutations. Call it from a mutation with `ctx.runMutation`.",
+      );
+    },
+    withOptions: (newOptions: ScheduleOptions) =>
+      setupActionScheduler(requestId, validatedOptions(newOptions)),
+  };
+}
+
+function validatedOptions(options: ScheduleOptions): ScheduleOptions {
+  const { priority, queue, retry, dedupeKey, onDuplicate } = options;
+  if (
+    priority !== undefined &&
+    priority !== "high" &&
+    priority !== "normal" &&
+    priority !== "low"
+  ) {
+    throw new Error('`priority` must be "high", "normal" or "low"');
+  }
+  if (queue !== undefined && typeof queue !== "string") {
+    throw new Error("`queue` must be a string");
+  }
+  if (dedupeKey !== undefined && typeof dedupeKey !== "string") {
+    throw new Error("`dedupeKey` must be a string");
+  }
+  if (
+    onDuplicate !== undefined &&
+    onDuplicate !== "replace" &&
+    onDuplicate !== "keep"
+  ) {
+    throw new Error('`onDuplicate` must be "replace" or "keep"');
+  }
+  if (onDuplicate !== undefined && dedupeKey === undefined) {
+    throw new Error("`onDuplicate` requires a `dedupeKey`");
+  }
+  const validated = { priority, queue, dedupeKey, onDuplicate };
+  if (retry !== undefined) {
+    return { ...validated, retry: validatedRetryPolicy(retry) };
+  }
+  return validated;
+}
+
+export function validatedRetryPolicy(retry: RetryPolicy): RetryPolicy {
+  const { maxAttempts, initialBackoffMs, maxBackoffMs } = retry;
+  if (!Number.isInteger(maxAttempts) || maxAttempts < 1) {
+    throw new Error("`retry.maxAttempts` must be a positive integer");
+  }
+  for (const [name, value] of [
+    ["initialBackoffMs", initialBackoffMs],
+    ["maxBackoffMs", maxBackoffMs],
+  ] as const) {
+    if (value !== undefined && (!Number.isInteger(value) || value < 0)) {
+      throw new Error(`\`retry.${name}\` must be a non-negative integer`);
+    }
+  }
+  return { maxAttempts, initialBackoffMs, maxBackoffMs };
+}
+
+function cancelAllSyscallArgs(filter: ScheduledFunctionFilter) {
+  const { function: functionReference, argsMatch } = filter;
+  const address =
+    functionReference === undefined
+      ? {}
+      : getFunctionAddress(functionReference);
+  if (
+    argsMatch !== undefined &&
+    (typeof argsMatch !== "object" ||
+      argsMatch === null ||
+      Array.isArray(argsMatch))
+  ) {
+    throw new Error("`argsMatch` must be an object");
+  }
+  return {
+    ...address,
+    argsMatch: argsMatch === undefined ? undefined : convexToJson(argsMatch),
+    scheduledAfterMs: timeArg(filter.scheduledAfter, "scheduledAfter"),
+    scheduledBeforeMs: timeArg(filter.scheduledBefore, "scheduledBefore"),
   };
 }
 
+function timeArg(time: number | Date | undefined, name: string) {
+  if (time === undefined) {
+    return undefined;
+  }
+  const ms = time instanceof Date ? time.valueOf() : time;
+  if (typeof ms !== "number" || !isFinite(ms)) {
+    throw new Error(`\`${name}\` must be a Date or a finite number`);
+  }
+  return ms;
+}
+
+function optionsSyscallArgs(options?: ScheduleOptions) {
+  return options === undefined ? {} : { options };
+}
+
 function runAfterSyscallArgs(
   delayMs: number,
   functionReference: SchedulableFunctionReference,
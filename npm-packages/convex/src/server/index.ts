@@ -128,9 +128,34 @@ export type {
 } from "./registration.js";
 export * from "./search_filter_builder.js";
 export * from "./storage.js";
-export type { Scheduler, SchedulableFunctionReference } from "./scheduler.js";
+export type {
+  Scheduler,
+  SchedulableFunctionReference,
+  ScheduleOptions,
+  RetryPolicy,
+  ScheduledFunctionFilter,
+  CancelAllResult,
+} from "./scheduler.js";
 export { cronJobs } from "./cron.js";
-export type { CronJob, Crons } from "./cron.js";
+export type {
+  CalendarWithTimezone,
+  CronDispatchOptions,
+  CronJob,
+  Crons,
+  CronWithTimezone,
+} from "./cron.js";
+export {
+  workflow,
+  startWorkflow,
+  getWorkflow,
+  cancelWorkflow,
+} from "./workflow.js";
+export type {
+  WorkflowStep,
+  WorkflowStepOptions,
+  WorkflowStatus,
+  WorkflowStepStatus,
+} from "./workflow.js";
 export type {
   SystemFields,
   IdField,
@@ -146,6 +171,9 @@ export type {
   RouteSpecWithPath,
   RouteSpecWithPathPrefix,
 } from "./router.js";
+export { upgradeWebSocket } from "./websocket.js";
+export { verifyWebhook, WebhookVerificationError } from "./webhooks.js";
+export type { WebhookScheme } from "./webhooks.js";
 export {
   anyApi,
   getFunctionName,
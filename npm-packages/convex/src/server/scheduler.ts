@@ -1,5 +1,5 @@
 import { FunctionReference, OptionalRestArgs } from "../server/api.js";
-import { Id } from "../values/value.js";
+import { Id, Value } from "../values/value.js";
 
 /**
  * A {@link FunctionReference} that can be scheduled to run in the future.
@@ -13,6 +13,127 @@ export type SchedulableFunctionReference = FunctionReference<
   "public" | "internal"
 >;
 
+/**
+ * Options for how scheduled functions run, passed to
+ * {@link Scheduler.withOptions}.
+ *
+ * @public
+ */
+export type ScheduleOptions = {
+  /**
+   * When the scheduler is behind, functions that are due with a higher
+   * priority run before ones with a lower priority, even if those were due
+   * earlier. Defaults to `"normal"`.
+   */
+  priority?: "high" | "normal" | "low";
+  /**
+   * A named queue to run the function in. The deployment can limit how many
+   * functions from each queue run at once, so a backlog in one queue doesn't
+   * hold up functions in other queues.
+   */
+  queue?: string;
+  /**
+   * How to retry the function when it throws an error. By default, a function
+   * that throws isn't run again.
+   *
+   * Functions that throw on every attempt are kept in the deployment's dead
+   * letters, where you can inspect their arguments and error and run them
+   * again from the dashboard.
+   */
+  retry?: RetryPolicy;
+  /**
+   * A key that identifies the scheduled function, like `digest:${userId}`.
+   * Scheduling the same function with the same key while an earlier run with
+   * that key is still pending does what `onDuplicate` says, instead of adding
+   * another run. Runs that already started aren't affected.
+   *
+   * At most 256 characters.
+   */
+  dedupeKey?: string;
+  /**
+   * What to do when a pending run of the same function has the same
+   * `dedupeKey`:
+   * - `"replace"` cancels the pending run and schedules the new one, which
+   *   debounces the function. This is the default.
+   * - `"keep"` keeps the pending run and returns its ID instead of scheduling
+   *   the new one.
+   */
+  onDuplicate?: "replace" | "keep";
+};
+
+/**
+ * How many times to run a scheduled function that throws an error, and how
+ * long to wait between attempts. The wait doubles after every failed attempt.
+ *
+ * Note that retried actions can run more than once, so they should be safe to
+ * run again after a partial failure.
+ *
+ * @public
+ */
+export type RetryPolicy = {
+  /**
+   * How many times to run the function, including the first attempt. Between
+   * 1 and 20.
+   */
+  maxAttempts: number;
+  /**
+   * How long to wait before the second attempt, in milliseconds. Defaults to
+   * 1 second.
+   */
+  initialBackoffMs?: number;
+  /**
+   * The longest to wait between attempts, in milliseconds. At most a day.
+   * Defaults to 1 hour.
+   */
+  maxBackoffMs?: number;
+};
+
+/**
+ * Selects pending scheduled functions for {@link Scheduler.cancelAll}. A
+ * scheduled function must match every field that's set.
+ *
+ * @public
+ */
+export type ScheduledFunctionFilter = {
+  /**
+   * Only functions scheduled to call this function.
+   */
+  function?: SchedulableFunctionReference;
+  /**
+   * Only functions whose arguments have these fields, with equal values.
+   */
+  argsMatch?: Record<string, Value>;
+  /**
+   * Only functions that are due to run at or after this time.
+   */
+  scheduledAfter?: number | Date;
+  /**
+   * Only functions that are due to run before this time.
+   */
+  scheduledBefore?: number | Date;
+};
+
+/**
+ * The result of a {@link Scheduler.cancelAll} batch.
+ *
+ * @public
+ */
+export type CancelAllResult = {
+  /**
+   * How many scheduled functions this batch canceled.
+   */
+  canceled: number;
+  /**
+   * Whether every scheduled function that matches the filter was canceled.
+   */
+  isDone: boolean;
+  /**
+   * Pass this to the next call to cancel the next batch, or `null` if there
+   * are no more.
+   */
+  continueCursor: string | null;
+};
+
 /**
  * An interface to schedule Convex functions.
  *
@@ -22,6 +143,9 @@ export type SchedulableFunctionReference = FunctionReference<
  * function. Actions execute at most once - they are not retried and might fail
  * due to transient errors.
  *
+ * Functions scheduled with a {@link RetryPolicy} via
+ * {@link Scheduler.withOptions} are also run again when they throw an error.
+ *
  * Consider using an {@link internalMutation} or {@link internalAction} to enforce that
  * these functions cannot be called directly from a Convex client.
  *
@@ -69,4 +193,31 @@ export interface Scheduler {
    * @param id
    */
   cancel(id: Id<"_scheduled_functions">): Promise<void>;
+
+  /**
+   * Cancels a batch of the pending scheduled functions that match the filter,
+   * like `ctx.scheduler.cancelAll({ function: internal.emails.send })`.
+   *
+   * Each call scans up to a limited number of scheduled functions, so cancel
+   * large backlogs by calling it again with the returned `continueCursor`
+   * (e.g. from a mutation that reschedules itself) until `isDone` is true.
+   *
+   * Only available in mutations.
+   *
+   * @param filter - Which scheduled functions to cancel. By default, all of
+   * them.
+   * @param cursor - The `continueCursor` of the previous batch.
+   */
+  cancelAll(
+    filter?: ScheduledFunctionFilter,
+    cursor?: string | null,
+  ): Promise<CancelAllResult>;
+
+  /**
+   * Returns a scheduler that schedules functions with the given options, like
+   * `ctx.scheduler.withOptions({ priority: "low", queue: "backfill" })`.
+   *
+   * @param options - The {@link ScheduleOptions} for the scheduled functions.
+   */
+  withOptions(options: ScheduleOptions): Scheduler;
 }
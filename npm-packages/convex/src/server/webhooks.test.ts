@@ -0,0 +1,140 @@
+import { expect, test } from "vitest";
+import { verifyWebhook, WebhookVerificationError } from "./webhooks.js";
+
+const body = '{"type":"payment_intent.succeeded","amount":1000}';
+
+async function hmac(secret: Uint8Array, data: string): Promise<Uint8Array> {
+  const key = await crypto.subtle.importKey(
+    "raw",
+    secret,
+    { name: "HMAC", hash: "SHA-256" },
+    false,
+    ["sign"],
+  );
+  const signature = await crypto.subtle.sign(
+    "HMAC",
+    key,
+    new TextEncoder().encode(data),
+  );
+  return new Uint8Array(signature);
+}
+
+function hex(bytes: Uint8Array): string {
+  return Array.from(bytes, (b) => b.toString(16).padStart(2, "0")).join("");
+}
+
+function base64(bytes: Uint8Array): string {
+  return btoa(String.fromCharCode(...bytes));
+}
+
+function request(headers: Record<string, string>): Request {
+  return new Request("https://example.com/webhook", {
+    method: "POST",
+    headers,
+    body,
+  });
+}
+
+const now = () => Math.floor(Date.now() / 1000).toString();
+
+test("stripe", async () => {
+  const secret = "whsec_test";
+  const t = now();
+  const v1 = hex(await hmac(new TextEncoder().encode(secret), `${t}.${body}`));
+  const options = { scheme: "stripe", secret } as const;
+
+  expect(
+    await verifyWebhook(
+      request({ "Stripe-Signature": `t=${t},v1=${"00".repeat(32)},v1=${v1}` }),
+      options,
+    ),
+  ).toEqual(body);
+  await expect(
+    verifyWebhook(request({ "Stripe-Signature": `t=${t},v1=${v1}` }), {
+      ...options,
+      secret: "whsec_other",
+    }),
+  ).rejects.toThrow(WebhookVerificationError);
+  await expect(
+    verifyWebhook(
+      request({ "Stripe-Signature": `t=${Number(t) - 600},v1=${v1}` }),
+      options,
+    ),
+  ).rejects.toThrow("tolerance");
+  await expect(verifyWebhook(request({}), options)).rejects.toThrow(
+    "Missing Stripe-Signature header",
+  );
+});
+
+test("github", async () => {
+  const secret = "It's a Secret to Everybody";
+  const signature = hex(await hmac(new TextEncoder().encode(secret), body));
+  const options = { scheme: "github", secret } as const;
+
+  expect(
+    await verifyWebhook(
+      request({ "X-Hub-Signature-256": `sha256=${signature}` }),
+      options,
+    ),
+  ).toEqual(body);
+  await expect(
+    verifyWebhook(
+      request({ "X-Hub-Signature-256": `sha256=${signature.slice(2)}` }),
+      options,
+    ),
+  ).rejects.toThrow(WebhookVerificationError);
+  await expect(
+    verifyWebhook(request({ "X-Hub-Signature": `sha1=${signature}` }), options),
+  ).rejects.toThrow("Missing X-Hub-Signature-256 header");
+});
+
+test("svix", async () => {
+  const secretBytes = new TextEncoder().encode("svix signing secret");
+  const secret = `whsec_${base64(secretBytes)}`;
+  const id = "msg_p5jXN8AQM9LWM0D4loKWxJek";
+  const timestamp = now();
+  const signature = base64(
+    await hmac(secretBytes, `${id}.${timestamp}.${body}`),
+  );
+  const options = { scheme: "svix", secret } as const;
+
+  expect(
+    await verifyWebhook(
+      request({
+        "svix-id": id,
+        "svix-timestamp": timestamp,
+        "svix-signature": `v1,bm90IGl0 v1,${signature}`,
+      }),
+      options,
+    ),
+  ).toEqual(body);
+  // Standard Webhooks uses the same scheme with different headers.
+  expect(
+    await verifyWebhook(
+      request({
+        "webhook-id": id,
+        "webhook-timestamp": timestamp,
+        "webhook-signature": `v1,${signature}`,
+      }),
+      options,
+    ),
+  ).toEqual(body);
+  await expect(
+    verifyWebhook(
+      request({
+        "svix-id": "msg_other",
+        "svix-timestamp": timestamp,
+        "svix-signature": `v1,${signature}`,
+      }),
+      options,
+    ),
+  ).rejects.toThrow(WebhookVerificationError);
+});
+
+test("body already read", async () => {
+  const req = request({});
+  await req.text();
+  await expect(
+    verifyWebhook(req, { scheme: "github", secret: "secret" }),
+  ).rejects.toThrow("already been read");
+});
@@ -0,0 +1,252 @@
+/**
+ * The webhook signature schemes {@link verifyWebhook} understands.
+ *
+ * - `"stripe"`: the `Stripe-Signature` header, signed with the endpoint's
+ *   `whsec_...` signing secret.
+ * - `"github"`: the `X-Hub-Signature-256` header, signed with the webhook's
+ *   secret.
+ * - `"svix"`: the `svix-*` (or Standard Webhooks `webhook-*`) headers, signed
+ *   with the endpoint's `whsec_...` secret. Used by Clerk, Resend and others.
+ *
+ * @public
+ */
+export type WebhookScheme = "stripe" | "github" | "svix";
+
+/**
+ * Thrown by {@link verifyWebhook} when a request isn't signed correctly.
+ *
+ * @public
+ */
+export class WebhookVerificationError extends Error {
+  name = "WebhookVerificationError";
+}
+
+/**
+ * How far a signed timestamp may be from now before a request is treated as
+ * a replay, by default.
+ */
+const DEFAULT_TOLERANCE_SECONDS = 5 * 60;
+
+/**
+ * Verify that an HTTP action's request is a webhook signed with `secret`,
+ * and return its body.
+ *
+ * Signatures are computed over the exact bytes that were sent, so the body
+ * must not be parsed and re-serialized before it's checked. This reads the
+ * raw body itself, which means the request's body can't have been read
+ * already, and returns it as a string once the signature checks out. Parse
+ * that instead of calling `request.json()`. Signatures are compared in
+ * constant time.
+ *
+ * ```js
+ * export const stripeWebhook = httpAction(async (ctx, request) => {
+ *   const body = await verifyWebhook(request, {
+ *     scheme: "stripe",
+ *     secret: process.env.STRIPE_WEBHOOK_SECRET!,
+ *   });
+ *   const event = JSON.parse(body);
+ *   ...
+ * });
+ * ```
+ *
+ * @param request - The HTTP action's request.
+ * @param options - `toleranceSeconds` is how old a signed timestamp may be,
+ * 5 minutes by default. GitHub's signatures don't include a timestamp.
+ * @returns The request's body.
+ * @throws {@link WebhookVerificationError} if the request isn't signed
+ * correctly.
+ *
+ * @public
+ */
+export async function verifyWebhook(
+  request: Request,
+  options: {
+    scheme: WebhookScheme;
+    secret: string;
+    toleranceSeconds?: number;
+  },
+): Promise<string> {
+  if (request.bodyUsed) {
+    throw new Error(
+      "verifyWebhook must read the request's raw body, but it has already been read",
+    );
+  }
+  const body = new Uint8Array(await request.arrayBuffer());
+  const tolerance = options.toleranceSeconds ?? DEFAULT_TOLERANCE_SECONDS;
+  switch (options.scheme) {
+    case "stripe":
+      await verifyStripe(request.headers, body, options.secret, tolerance);
+      break;
+    case "github":
+      await verifyGithub(request.headers, body, options.secret);
+      break;
+    case "svix":
+      await verifySvix(request.headers, body, options.secret, tolerance);
+      break;
+    default:
+      throw new Error(`Unknown webhook scheme: ${String(options.scheme)}`);
+  }
+  return new TextDecoder().decode(body);
+}
+
+async function verifyStripe(
+  headers: Headers,
+  body: Uint8Array,
+  secret: string,
+  tolerance: number,
+) {
+  const header = requireHeader(headers, "Stripe-Signature");
+  let timestamp: string | undefined;
+  const signatures: string[] = [];
+  for (const item of header.split(",")) {
+    const [key, value] = splitOnce(item.trim(), "=");
+    if (key === "t") {
+      timestamp = value;
+    } else if (key === "v1") {
+      signatures.push(value);
+    }
+  }
+  if (timestamp === undefined || signatures.length === 0) {
+    throw new WebhookVerificationError(
+      "Stripe-Signature header is missing its timestamp or v1 signature",
+    );
+  }
+  checkTimestamp(timestamp, tolerance);
+  const key = await hmacKey(new TextEncoder().encode(secret));
+  const signed = concat(new TextEncoder().encode(`${timestamp}.`), body);
+  await verifyAny(key, signed, signatures.map(decodeHex));
+}
+
+async function verifyGithub(
+  headers: Headers,
+  body: Uint8Array,
+  secret: string,
+) {
+  const header = requireHeader(headers, "X-Hub-Signature-256");
+  const [algorithm, signature] = splitOnce(header.trim(), "=");
+  if (algorithm !== "sha256") {
+    throw new WebhookVerificationError(
+      "X-Hub-Signature-256 header isn't a sha256 signature",
+    );
+  }
+  const key = await hmacKey(new TextEncoder().encode(secret));
+  await verifyAny(key, body, [decodeHex(signature)]);
+}
+
+async function verifySvix(
+  headers: Headers,
+  body: Uint8Array,
+  secret: string,
+  tolerance: number,
+) {
+  const prefix = headers.has("svix-id") ? "svix" : "webhook";
+  const id = requireHeader(headers, `${prefix}-id`);
+  const timestamp = requireHeader(headers, `${prefix}-timestamp`);
+  const header = requireHeader(headers, `${prefix}-signature`);
+  checkTimestamp(timestamp, tolerance);
+  const signatures: Array<Uint8Array | null> = [];
+  for (const item of header.split(" ")) {
+    const [version, signature] = splitOnce(item, ",");
+    if (version === "v1") {
+      signatures.push(decodeBase64(signature));
+    }
+  }
+  const secretBytes = decodeBase64(secret.replace(/^whsec_/, ""));
+  if (secretBytes === null) {
+    throw new Error("Svix webhook secrets must be base64, like `whsec_...`");
+  }
+  const key = await hmacKey(secretBytes);
+  const signed = concat(new TextEncoder().encode(`${id}.${timestamp}.`), body);
+  await verifyAny(key, signed, signatures);
+}
+
+function requireHeader(headers: Headers, name: string): string {
+  const value = headers.get(name);
+  if (value === null) {
+    throw new WebhookVerificationError(`Missing ${name} header`);
+  }
+  return value;
+}
+
+function checkTimestamp(timestamp: string, tolerance: number) {
+  const seconds = Number(timestamp);
+  if (!/^\d+$/.test(timestamp) || !Number.isSafeInteger(seconds)) {
+    throw new WebhookVerificationError(
+      `Invalid webhook timestamp: ${timestamp}`,
+    );
+  }
+  if (Math.abs(Date.now() / 1000 - seconds) > tolerance) {
+    throw new WebhookVerificationError(
+      "Webhook timestamp is outside the tolerance window",
+    );
+  }
+}
+
+function hmacKey(secret: Uint8Array): Promise<CryptoKey> {
+  return crypto.subtle.importKey(
+    "raw",
+    secret,
+    { name: "HMAC", hash: "SHA-256" },
+    false,
+    ["verify"],
+  );
+}
+
+/**
+ * `crypto.subtle.verify` compares HMACs in constant time. Malformed
+ * signatures are `null` and never match.
+ */
+async function verifyAny(
+  key: CryptoKey,
+  data: Uint8Array,
+  signatures: Array<Uint8Array | null>,
+) {
+  for (const signature of signatures) {
+    if (
+      signature !== null &&
+      (await crypto.subtle.verify("HMAC", key, signature, data))
+    ) {
+      return;
+    }
+  }
+  throw new WebhookVerificationError("No matching webhook signature");
+}
+
+function splitOnce(s: string, separator: string): [string, string] {
+  const index = s.indexOf(separator);
+  if (index === -1) {
+    return [s, ""];
+  }
+  return [s.slice(0, index), s.slice(index + separator.length)];
+}
+
+function concat(a: Uint8Array, b: Uint8Array): Uint8Array {
+  const result = new Uint8Array(a.length + b.length);
+  result.set(a);
+  result.set(b, a.length);
+  return result;
+}
+
+function decodeHex(hex: string): Uint8Array | null {
+  if (hex.length % 2 !== 0 || !/^[0-9a-fA-F]*$/.test(hex)) {
+    return null;
+  }
+  const bytes = new Uint8Array(hex.length / 2);
+  for (let i = 0; i < bytes.length; i++) {
+    bytes[i] = parseInt(hex.slice(2 * i, 2 * i + 2), 16);
+  }
+  return bytes;
+}
+
+function decodeBase64(base64: string): Uint8Array | null {
+  try {
+    const binary = atob(base64);
+    const bytes = new Uint8Array(binary.length);
+    for (let i = 0; i < binary.length; i++) {
+      bytes[i] = binary.charCodeAt(i);
+    }
+    return bytes;
+  } catch {
+    return null;
+  }
+}
@@ -0,0 +1,34 @@
+import { performJsSyscall } from "./impl/syscall.js";
+
+/**
+ * Accept a WebSocket connection in an HTTP action.
+ *
+ * The request must be a WebSocket upgrade request (a `GET` with an
+ * `Upgrade: websocket` header). The action must return the returned
+ * `response`, and keeps running until the connection is closed by either
+ * side or the action times out.
+ *
+ * ```js
+ * export const stream = httpAction(async (ctx, request) => {
+ *   const { socket, response } = upgradeWebSocket(request);
+ *   socket.onmessage = (event) => socket.send(event.data);
+ *   return response;
+ * });
+ * ```
+ *
+ * @param request - The HTTP action's request.
+ * @param options - `protocol` is the subprotocol to accept, which must be one
+ * the client offered.
+ * @returns The action's end of the connection and the response to return.
+ *
+ * @public
+ */
+export function upgradeWebSocket(
+  request: Request,
+  options?: { protocol?: string },
+): { socket: WebSocket; response: Response } {
+  return performJsSyscall("upgradeWebSocket", {
+    request,
+    protocol: options?.protocol,
+  });
+}
@@ -0,0 +1,419 @@
+import {
+  FunctionReference,
+  FunctionReturnType,
+  OptionalRestArgs,
+} from "./api.js";
+import { parseArgs } from "../common/index.js";
+import {
+  convexToJson,
+  jsonToConvex,
+  JSONValue,
+  Value,
+} from "../values/index.js";
+import { v } from "../values/validator.js";
+import { getFunctionAddress } from "./components/paths.js";
+import { internalMutationGeneric } from "./impl/registration_impl.js";
+import { validatedRetryPolicy } from "./impl/scheduler_impl.js";
+import { performAsyncSyscall } from "./impl/syscall.js";
+import { RegisteredMutation } from "./registration.js";
+import { RetryPolicy } from "./scheduler.js";
+
+/**
+ * Options for a mutation or action step of a workflow.
+ *
+ * @public
+ */
+export type WorkflowStepOptions = {
+  /**
+   * How to retry the step's function when it throws an error. By default, a
+   * step that throws isn't run again and the error is thrown from
+   * `step.runMutation` or `step.runAction`.
+   */
+  retry?: RetryPolicy;
+};
+
+/**
+ * The steps a workflow can take, passed to the handler of a {@link workflow}.
+ *
+ * Every step is journaled as soon as it completes, so a workflow that's
+ * interrupted picks up where it left off: steps that already completed aren't
+ * run again, and return the result or throw the error they did the first
+ * time.
+ *
+ * Steps run one at a time, so each one must be awaited before starting the
+ * next.
+ *
+ * @public
+ */
+export interface WorkflowStep {
+  /**
+   * The id of the running workflow, as returned by {@link startWorkflow}.
+   */
+  workflowId: string;
+
+  /**
+   * Run a query and journal its result.
+   */
+  runQuery<Query extends FunctionReference<"query", "public" | "internal">>(
+    query: Query,
+    ...args: OptionalRestArgs<Query>
+  ): Promise<FunctionReturnType<Query>>;
+
+  /**
+   * Run a mutation in its own transaction, exactly once.
+   */
+  runMutation<
+    Mutation extends FunctionReference<"mutation", "public" | "internal">,
+  >(
+    mutation: Mutation,
+    args: Mutation["_args"],
+    options?: WorkflowStepOptions,
+  ): Promise<FunctionReturnType<Mutation>>;
+
+  /**
+   * Run an action. Like scheduled actions, an action step runs at most once
+   * per attempt, so it should be safe to run again when it's retried.
+   */
+  runAction<Action extends FunctionReference<"action", "public" | "internal">>(
+    action: Action,
+    args: Action["_args"],
+    options?: WorkflowStepOptions,
+  ): Promise<FunctionReturnType<Action>>;
+
+  /**
+   * Wait for `ms` milliseconds, up to a year, without running anything.
+   */
+  sleep(ms: number): Promise<void>;
+}
+
+/**
+ * A workflow's state and journal, as returned by {@link getWorkflow}.
+ *
+ * @public
+ */
+export type WorkflowStatus = {
+  id: string;
+  /**
+   * The name of the mutation that defines the workflow.
+   */
+  name: string;
+  args: Record<string, Value>;
+  state:
+    | { kind: "running" }
+    | { kind: "completed"; result: Value }
+    | { kind: "failed"; error: string }
+    | { kind: "canceled" };
+  steps: WorkflowStepStatus[];
+  /**
+   * Milliseconds since the Unix epoch.
+   */
+  startedAt: number;
+  completedAt: number | null;
+};
+
+/**
+ * A step in a workflow's journal.
+ *
+ * @public
+ */
+export type WorkflowStepStatus = {
+  kind: "query" | "mutation" | "action" | "sleep";
+  /**
+   * The name of the function the step ran, or `null` for sleeps.
+   */
+  name: string | null;
+  state:
+    | { kind: "inProgress" }
+    | { kind: "succeeded"; result: Value }
+    | { kind: "failed"; error: string };
+  startedAt: number;
+  completedAt: number | null;
+};
+
+type WorkflowJournalJson = Omit<WorkflowStatus, "args" | "state" | "steps"> & {
+  args: JSONValue;
+  state:
+    | { kind: "running" | "canceled" }
+    | { kind: "completed"; result: JSONValue }
+    | { kind: "failed"; error: string };
+  steps: (Omit<WorkflowStepStatus, "state"> & {
+    state:
+      | { kind: "inProgress" }
+      | { kind: "succeeded"; result: JSONValue }
+      | { kind: "failed"; error: string };
+  })[];
+};
+
+/**
+ * Define a durable workflow, which composes queries, mutations, actions and
+ * sleeps into steps that survive restarts and failures. This replaces chains
+ * of scheduled functions that schedule each other.
+ *
+ * ```js
+ * export const onboard = workflow({
+ *   handler: async (step, { userId }) => {
+ *     const user = await step.runQuery(internal.users.get, { userId });
+ *     await step.runAction(
+ *       internal.emails.sendWelcome,
+ *       { email: user.email },
+ *       { retry: { maxAttempts: 5 } },
+ *     );
+ *     await step.sleep(24 * 60 * 60 * 1000);
+ *     await step.runMutation(internal.users.markOnboarded, { userId });
+ *   },
+ * });
+ * ```
+ *
+ * Start the workflow from a mutation with {@link startWorkflow}. The
+ * workflow is an internal mutation that runs again after every step
+ * completes, replaying the steps that already completed from the workflow's
+ * journal, so the handler must start the same steps in the same order every
+ * time it runs. Use steps for anything that reads or writes data, or isn't
+ * deterministic.
+ *
+ * The workflow fails if the handler throws, for example because a step
+ * failed on its last attempt. Errors are replayed as an `Error` with the
+ * original error's message.
+ *
+ * @param definition - The workflow's handler, which gets the workflow's steps
+ * and the arguments the workflow was started with.
+ * @returns The workflow's internal mutation. Export it from a module in your
+ * `convex/` folder so it can be started and run.
+ *
+ * @public
+ */
+export function workflow<
+  Args extends Record<string, Value>,
+  Returns,
+>(definition: {
+  handler: (step: WorkflowStep, args: Args) => Promise<Returns>;
+}): RegisteredMutation<"internal", { workflowId: string }, Promise<null>> {
+  return internalMutationGeneric({
+    args: { workflowId: v.string() },
+    handler: async (ctx, { workflowId }) => {
+      const journal: WorkflowJournalJson | null = await performAsyncSyscall(
+        "1.0/workflow/resume",
+        { workflowId },
+      );
+      if (journal === null || journal.state.kind !== "running") {
+        return null;
+      }
+      if (journal.steps.at(-1)?.state.kind === "inProgress") {
+        // The step runs the workflow again when it completes.
+        return null;
+      }
+
+      // Steps that start something new resolve `suspended`, and never
+      // return, since the workflow can't continue until the step completes.
+      let resolveSuspended: () => void;
+      const suspended = new Promise<typeof SUSPENDED>((resolve) => {
+        resolveSuspended = () => resolve(SUSPENDED);
+      });
+      const suspend = () => {
+        resolveSuspended();
+        return new Promise<never>(() => {});
+      };
+      let nextStepNumber = 0;
+      const nextStep = (kind: WorkflowStepStatus["kind"]) => {
+        const stepNumber = nextStepNumber++;
+        const journaled = journal.steps[stepNumber];
+        if (journaled !== undefined && journaled.kind !== kind) {
+          throw new Error(
+            `Workflow step ${stepNumber} is a ${kind} step, but it was a ` +
+              `${journaled.kind} step when the workflow ran before. ` +
+              "Workflows must start the same steps every time they run.",
+          );
+        }
+        return { stepNumber, journaled };
+      };
+      const replay = (
+        journaled: WorkflowJournalJson["steps"][number],
+      ): Promise<any> => {
+        switch (journaled.state.kind) {
+          case "inProgress":
+            return suspend();
+          case "succeeded":
+            return Promise.resolve(jsonToConvex(journaled.state.result));
+          case "failed":
+            return Promise.reject(new Error(journaled.state.error));
+        }
+      };
+      const startJobStep = async (
+        kind: "mutation" | "action",
+        functionReference: any,
+        args: any,
+        options?: WorkflowStepOptions,
+      ) => {
+        const { stepNumber, journaled } = nextStep(kind);
+        if (journaled !== undefined) {
+          return replay(journaled);
+        }
+        await performAsyncSyscall("1.0/workflow/step", {
+          workflowId,
+          stepNumber,
+          kind,
+          ...getFunctionAddress(functionReference),
+          args: convexToJson(parseArgs(args)),
+          retry:
+            options?.retry === undefined
+              ? undefined
+              : validatedRetryPolicy(options.retry),
+        });
+        return suspend();
+      };
+
+      const step: WorkflowStep = {
+        workflowId,
+        runQuery: async (query: any, args?: any) => {
+          const { stepNumber, journaled } = nextStep("query");
+          if (journaled !== undefined) {
+            return replay(journaled);
+          }
+          const recordQuery = (outcome: object) =>
+            performAsyncSyscall("1.0/workflow/step", {
+              workflowId,
+              stepNumber,
+              kind: "query",
+              ...getFunctionAddress(query),
+              ...outcome,
+            });
+          let result;
+          try {
+            result = await ctx.runQuery(query, parseArgs(args));
+          } catch (e: any) {
+            await recordQuery({ error: errorMessage(e) });
+            throw e;
+          }
+          await recordQuery({
+            result: convexToJson(result === undefined ? null : result),
+          });
+          return result;
+        },
+        runMutation: (
+          mutation: any,
+          args: any,
+          options?: WorkflowStepOptions,
+        ) =>
+          startJobStep("mutation", mutation, args, options),
+        runAction: (action: any, args: any, options?: WorkflowStepOptions) =>
+          startJobStep("action", action, args, options),
+        sleep: async (ms: number) => {
+          if (typeof ms !== "number" || !isFinite(ms) || ms < 0) {
+            throw new Error("`ms` must be a non-negative finite number");
+          }
+          const { stepNumber, journaled } = nextStep("sleep");
+          if (journaled !== undefined) {
+            await replay(journaled);
+            return;
+          }
+          await performAsyncSyscall("1.0/workflow/step", {
+            workflowId,
+            stepNumber,
+            kind: "sleep",
+            delayMs: ms,
+          });
+          return suspend();
+        },
+      };
+
+      const args = jsonToConvex(journal.args) as Args;
+      let outcome;
+      try {
+        const result = await Promise.race([
+          definition.handler(step, args),
+          suspended,
+        ]);
+        if (result === SUSPENDED) {
+          return null;
+        }
+        outcome = {
+          result: convexToJson(result === undefined ? null : (result as Value)),
+        };
+      } catch (e: any) {
+        outcome = { error: errorMessage(e) };
+      }
+      await performAsyncSyscall("1.0/workflow/finish", {
+        workflowId,
+        ...outcome,
+      });
+      return null;
+    },
+  }) as RegisteredMutation<"internal", { workflowId: string }, Promise<null>>;
+}
+
+const SUSPENDED = Symbol("suspended");
+
+function errorMessage(e: any): string {
+  return e instanceof Error ? e.message : String(e);
+}
+
+/**
+ * Start a workflow defined with {@link workflow}. Only available in
+ * mutations, and the workflow must be defined in the same component.
+ *
+ * The workflow starts running once the mutation commits, and nothing is
+ * started if the mutation fails.
+ *
+ * @param workflowReference - A reference to the workflow, e.g.
+ * `internal.onboarding.onboard`.
+ * @param args - The arguments to pass to the workflow's handler.
+ * @returns The workflow's id, to pass to {@link getWorkflow} and
+ * {@link cancelWorkflow}.
+ *
+ * @public
+ */
+export async function startWorkflow(
+  workflowReference: FunctionReference<"mutation", "internal">,
+  args?: Record<string, Value>,
+): Promise<string> {
+  return await performAsyncSyscall("1.0/workflow/start", {
+    ...getFunctionAddress(workflowReference),
+    args: convexToJson(parseArgs(args)),
+  });
+}
+
+/**
+ * Get a workflow's state and the steps it has taken so far, or `null` if
+ * there's no workflow with that id. Available in queries and mutations.
+ *
+ * @public
+ */
+export async function getWorkflow(
+  workflowId: string,
+): Promise<WorkflowStatus | null> {
+  const journal: WorkflowJournalJson | null = await performAsyncSyscall(
+    "1.0/workflow/get",
+    { workflowId },
+  );
+  if (journal === null) {
+    return null;
+  }
+  const state = journal.state;
+  return {
+    ...journal,
+    args: jsonToConvex(journal.args) as Record<string, Value>,
+    state:
+      state.kind === "completed"
+        ? { kind: "completed", result: jsonToConvex(state.result) }
+        : state,
+    steps: journal.steps.map((step) => ({
+      ...step,
+      state:
+        step.state.kind === "succeeded"
+          ? { kind: "succeeded", result: jsonToConvex(step.state.result) }
+          : step.state,
+    })),
+  } as WorkflowStatus;
+}
+
+/**
+ * Cancel a running workflow. The step it's running, if any, is canceled too,
+ * and the workflow doesn't start any more steps. Only available in mutations.
+ *
+ * Does nothing if the workflow already finished.
+ *
+ * @public
+ */
+export async function cancelWorkflow(workflowId: string): Promise<void> {
+  await performAsyncSyscall("1.0/workflow/cancel", { workflowId });
+}
@@ -55,12 +55,17 @@ function Schedule({
   if (schedule.type === "interval") {
     const duration = formatDuration({ seconds: Number(schedule.seconds) });
     formattedSchedule = `Every ${duration}`;
+  } else if (schedule.type === "calendar") {
+    formattedSchedule = `${schedule.expr.charAt(0).toUpperCase()}${schedule.expr.slice(1)} ${schedule.timezone ?? "UTC"}`;
   } else if (wasmCron) {
     const [cron, description] = wasmCron.parseAndDescribe(
       scheduleAsCron(schedule),
     );
     cron.free();
-    formattedSchedule = prettierSaffron(description);
+    formattedSchedule = prettierSaffron(
+      description,
+      schedule.type === "cron" ? schedule.timezone : undefined,
+    );
   }
 
   const tip = <pre className="text-left">{literal}</pre>;
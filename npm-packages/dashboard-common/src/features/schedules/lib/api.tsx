@@ -1,4 +1,5 @@
 import { useContext } from "react";
+import { convexToJson, Value } from "convex/values";
 import Link from "next/link";
 import { useAdminKey, useDeploymentUrl } from "@common/lib/deploymentApi";
 import { useNents } from "@common/lib/useNents";
@@ -6,15 +7,27 @@ import { toast } from "@common/lib/utils";
 import { DeploymentInfoContext } from "@common/lib/deploymentContext";
 import { displayName } from "@common/lib/functions/generateFileTree";
 
-export function useCancelAllJobs(): (udfPath?: string) => Promise<void> {
+export type CancelJobsFilter = {
+  argsMatch?: Record<string, Value>;
+  scheduledAfterMs?: number;
+  scheduledBeforeMs?: number;
+};
+
+export function useCancelAllJobs(): (
+  udfPath?: string,
+  filter?: CancelJobsFilter,
+) => Promise<void> {
   const deploymentUrl = useDeploymentUrl();
   const adminKey = useAdminKey();
   const { selectedNent } = useNents();
   const { deploymentsURI, reportHttpError } = useContext(DeploymentInfoContext);
 
-  return async (udfPath?: string) => {
+  return async (udfPath?: string, filter?: CancelJobsFilter) => {
     const body = JSON.stringify({
       udfPath,
+      argsMatch: filter?.argsMatch && convexToJson(filter.argsMatch),
+      scheduledAfterMs: filter?.scheduledAfterMs,
+      scheduledBeforeMs: filter?.scheduledBeforeMs,
       componentPath: selectedNent?.path ?? undefined,
       componentId: selectedNent?.id ?? undefined,
     });
@@ -49,11 +62,13 @@ export function useCancelAllJobs(): (udfPath?: string) => Promise<void> {
       }
       throw err;
     } else {
+      const { canceled }: { canceled: number } = await res.json();
+      const runs = `${canceled} scheduled run${canceled === 1 ? "" : "s"}`;
       toast(
         "success",
         udfPath
-          ? `Canceled all scheduled runs for ${displayName(udfPath, selectedNent?.path ?? null)}.`
-          : "Canceled all scheduled runs.",
+          ? `Canceled ${runs} for ${displayName(udfPath, selectedNent?.path ?? null)}.`
+          : `Canceled ${runs}.`,
       );
     }
   };
@@ -86,3 +101,59 @@ export function useCancelJob(): (
     }
   };
 }
+
+export function useRerunDeadLetter(): (
+  id: string,
+  componentId: string | null,
+) => Promise<void> {
+  const deploymentUrl = useDeploymentUrl();
+  const adminKey = useAdminKey();
+  const { reportHttpError } = useContext(DeploymentInfoContext);
+
+  return async (id: string, componentId: string | null) => {
+    const body = JSON.stringify({ id, componentId });
+    const res = await fetch(`${deploymentUrl}/api/rerun_dead_letter`, {
+      method: "POST",
+      headers: {
+        Authorization: `Convex ${adminKey}`,
+        "Content-Type": "application/json",
+      },
+      body,
+    });
+    if (res.status !== 200) {
+      const err = await res.json();
+      reportHttpError("POST", res.url, err);
+      toast("error", err.message);
+    } else {
+      toast("success", "Failed function scheduled to run again.");
+    }
+  };
+}
+
+export function useDeleteDeadLetter(): (
+  id: string,
+  componentId: string | null,
+) => Promise<void> {
+  const deploymentUrl = useDeploymentUrl();
+  const adminKey = useAdminKey();
+  const { reportHttpError } = useContext(DeploymentInfoContext);
+
+  return async (id: string, componentId: string | null) => {
+    const body = JSON.stringify({ id, componentId });
+    const res = await fetch(`${deploymentUrl}/api/delete_dead_letter`, {
+      method: "POST",
+      headers: {
+        Authorization: `Convex ${adminKey}`,
+        "Content-Type": "application/json",
+      },
+      body,
+    });
+    if (res.status !== 200) {
+      const err = await res.json();
+      reportHttpError("POST", res.url, err);
+      toast("error", err.message);
+    } else {
+      toast("success", "Failed function deleted.");
+    }
+  };
+}
@@ -35,8 +35,10 @@ export function scheduleAsCron(
 /**
  * Add a reminder that this is UTC
  */
-export function prettierSaffron(s: string) {
-  return s.replaceAll("AM", "AM UTC").replaceAll("PM", "PM UTC");
+export function prettierSaffron(s: string, timezone = "UTC") {
+  return s
+    .replaceAll("AM", `AM ${timezone}`)
+    .replaceAll("PM", `PM ${timezone}`);
 }
 
 export function scheduleLiteral(s: CronSchedule): string {
@@ -62,6 +64,8 @@ export function scheduleLiteral(s: CronSchedule): string {
   minuteUTC: ${s.minuteUTC}
 })`
             : s.type === "cron"
-              ? `${s.cronExpr}`
-              : `Unknown Cron Schedule`;
+              ? `${s.cronExpr}${s.timezone ? ` (${s.timezone})` : ""}`
+              : s.type === "calendar"
+                ? `${s.expr}${s.timezone ? ` (${s.timezone})` : ""}`
+                : `Unknown Cron Schedule`;
 }
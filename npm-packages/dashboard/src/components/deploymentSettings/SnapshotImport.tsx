@@ -208,6 +208,12 @@ function snapshotImportFormatToText(
       return "JSON";
     case "zip":
       return "ZIP";
+    case "mongo_bson":
+      return "MongoDB BSON";
+    case "mongo_archive":
+      return "MongoDB archive";
+    case "pg_dump":
+      return "Postgres dump";
     default: {
       // eslint-disable-next-line @typescript-eslint/no-unused-vars
       const _: never = format;
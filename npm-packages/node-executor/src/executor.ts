@@ -28,6 +28,8 @@ import { SourcePackage, maybeDownloadAndLinkPackages } from "./source_package";
 import { buildDeps, BuildDepsRequest } from "./build_deps";
 import { ConvexError, JSONValue } from "convex/values";
 import { logDebug, logDurationMs } from "./log";
+import { LogLineData, LogLineStream } from "./log_stream";
+import { currentInvocationContext, runAsExecutor } from "./invocation_context";
 
 // When we bundle commonJS modules as ESM with esbuild, the bundled code might still use
 // `require`, exports, module, __dirname or __filename despite being in ESM.
@@ -81,7 +83,16 @@ export async function invoke(
   responseStream: Writable,
 ) {
   const start = performance.now();
-  setupConsole(responseStream);
+  const logStream =
+    request.type === "execute" && request.logStreamId
+      ? new LogLineStream(
+          request.backendAddress,
+          request.backendCallbackToken,
+          request.logStreamId,
+          (line) => writeLogLine(responseStream, line),
+        )
+      : null;
+  setupConsole(responseStream, logStream);
   numInvocations += 1;
   logDebug(`Environment numInvocations=${numInvocations}`);
   let result;
@@ -96,6 +107,9 @@ export async function invoke(
   }
 
   logDurationMs("Total invocation time", start);
+  // Log lines have to reach the backend before the result does, since the
+  // backend stops accepting them once the action is done.
+  await logStream?.flush();
   responseStream.write(JSON.stringify(result));
 }
 
@@ -119,6 +133,8 @@ export type ExecuteRequest = {
   npmVersion: string | null;
   executionContext: ExecutionContext;
   encodedParentTrace: string | null;
+  // Set if log lines should be posted to the backend as they're logged.
+  logStreamId?: string | null;
 };
 
 export type ExecutionContext = {
@@ -245,9 +261,11 @@ export async function executeInner(
   const envHash = setEnvironmentVariables(environmentVariables);
 
   setupGlobals(`${modulesDir}/${relPath}`);
-  const module = await import(
-    path.join(modulesDir, `${relPath}?envHash=${envHash}`)
-  );
+  // Workers import the module into a fresh context for each invocation.
+  const invocationContext = currentInvocationContext();
+  const module = invocationContext
+    ? await invocationContext.importModule(path.join(modulesDir, relPath))
+    : await import(path.join(modulesDir, `${relPath}?envHash=${envHash}`));
   const importTimeMs = logDurationMs("importTimeMs", start);
 
   const userFunction = module[name];
@@ -275,7 +293,9 @@ export async function executeInner(
 
     globalSyscalls = syscalls;
     udfReturn = await Promise.race<string | symbol>([
-      invoke(lambdaExecuteId, args),
+      invocationContext
+        ? invocationContext.run(() => invoke(lambdaExecuteId, args))
+        : invoke(lambdaExecuteId, args),
       timeout,
     ]).finally(() => {
       // Always clear the timeout after the promise is settled.
@@ -526,19 +546,22 @@ let globalSyscalls: Syscalls | null = null;
     if (!globalSyscalls) {
       throw new Error(`Cannot invoke syscall during module imports`);
     }
-    return globalSyscalls.syscall(op, jsonArgs);
+    const syscalls = globalSyscalls;
+    return runAsExecutor(() => syscalls.syscall(op, jsonArgs));
   },
   asyncSyscall: (op: string, jsonArgs: string) => {
     if (!globalSyscalls) {
       throw new Error(`Cannot invoke syscall during module imports`);
     }
-    return globalSyscalls.asyncSyscall(op, jsonArgs);
+    const syscalls = globalSyscalls;
+    return runAsExecutor(() => syscalls.asyncSyscall(op, jsonArgs));
   },
   jsSyscall: (op: string, args: Record<string, any>) => {
     if (!globalSyscalls) {
       throw new Error(`Cannot invoke syscall during module imports`);
     }
-    return globalSyscalls.asyncJsSyscall(op, args);
+    const syscalls = globalSyscalls;
+    return runAsExecutor(() => syscalls.asyncJsSyscall(op, args));
   },
 };
 
@@ -568,7 +591,16 @@ function defaultConsoleState(): ConsoleState {
   };
 }
 
-export function setupConsole(responseStream: Writable) {
+function writeLogLine(responseStream: Writable, line: LogLineData) {
+  responseStream.write(JSON.stringify({ kind: "LogLine", data: line }) + "\n");
+}
+
+// If `logStream` is set, log lines are posted to the backend while the
+// function runs instead of being written to `responseStream`.
+export function setupConsole(
+  responseStream: Writable,
+  logStream: LogLineStream | null = null,
+) {
   // TODO(presley): For some reason capturing stdout and stderr doesn't work in
   // AWS Lambda. Not sure if it is async issue or AWS does something weird where
   // they patch node:console Console object. For now we will will throw away the
@@ -629,17 +661,18 @@ export function setupConsole(responseStream: Writable) {
       messages = ["Log overflow (maximum 256). Remaining log lines omitted."];
       globalConsoleState.logLimitHit = true;
     }
-    responseStream.write(
-      JSON.stringify({
-        kind: "LogLine",
-        data: {
-          messages,
-          isTruncated: false,
-          timestamp: Date.now(),
-          level,
-        },
-      }) + "\n",
-    );
+    const line = {
+      messages,
+      isTruncated: false,
+      timestamp: Date.now(),
+      level,
+    };
+    if (logStream !== null) {
+      const stream = logStream;
+      runAsExecutor(() => stream.push(line));
+    } else {
+      writeLogLine(responseStream, line);
+    }
     globalConsoleState.totalSentLineLength += totalMessageLength;
     globalConsoleState.sentLines += 1;
   }
@@ -0,0 +1,222 @@
+import { AsyncLocalStorage, createHook } from "node:async_hooks";
+import fs from "node:fs";
+import { builtinModules, createRequire } from "node:module";
+import { fileURLToPath, pathToFileURL } from "node:url";
+import vm from "node:vm";
+
+// In worker mode, each invocation runs the user's modules in a fresh `vm`
+// context, so globals and module state from one invocation aren't visible to
+// the next. Timers, sockets and other handles can't be scoped to a context,
+// so they're tracked instead, and a worker with any left over after an
+// invocation is retired rather than reused.
+//
+// Requires Node.js to be started with `--experimental-vm-modules`.
+
+// Globals the executor sets up for each invocation, which the context reads
+// from the worker's global scope.
+const EXECUTOR_GLOBALS = [
+  "console",
+  "Convex",
+  "require",
+  "exports",
+  "module",
+  "__dirname",
+  "__filename",
+];
+
+// The source of user modules, shared across invocations since module files
+// are never modified after they're unpacked.
+const moduleSources: Map<string, string> = new Map();
+
+const invocationStorage = new AsyncLocalStorage<InvocationContext>();
+// The invocation that created each live resource other than a promise.
+const resourceOwners: Map<number, InvocationContext> = new Map();
+let hookEnabled = false;
+
+function enableResourceTracking() {
+  if (hookEnabled) {
+    return;
+  }
+  createHook({
+    init(asyncId, type) {
+      if (type === "PROMISE") {
+        return;
+      }
+      const invocation = invocationStorage.getStore();
+      if (invocation !== undefined) {
+        resourceOwners.set(asyncId, invocation);
+        invocation.resources.add(asyncId);
+      }
+    },
+    destroy(asyncId) {
+      resourceOwners.get(asyncId)?.resources.delete(asyncId);
+      resourceOwners.delete(asyncId);
+    },
+  }).enable();
+  hookEnabled = true;
+}
+
+let current: InvocationContext | null = null;
+
+export function currentInvocationContext(): InvocationContext | null {
+  return current;
+}
+
+export function setInvocationContext(invocation: InvocationContext | null) {
+  current = invocation;
+}
+
+// Runs executor code called from user code, like syscalls and console
+// methods, so the resources it creates aren't attributed to the invocation.
+export function runAsExecutor<T>(f: () => T): T {
+  return invocationStorage.exit(f);
+}
+
+export class InvocationContext {
+  // Async ids of the resources created by this invocation that are still
+  // alive.
+  resources: Set<number> = new Set();
+  private context: vm.Context;
+  private modules: Map<string, vm.Module> = new Map();
+
+  constructor() {
+    enableResourceTracking();
+    this.context = vm.createContext({});
+    const contextGlobal = vm.runInContext("globalThis", this.context);
+    for (const name of Object.getOwnPropertyNames(globalThis)) {
+      if (
+        name in contextGlobal ||
+        name === "global" ||
+        EXECUTOR_GLOBALS.includes(name)
+      ) {
+        continue;
+      }
+      try {
+        contextGlobal[name] = (globalThis as any)[name];
+      } catch {
+        // Skip globals that can't be read outside of their getter's object.
+      }
+    }
+    contextGlobal.global = contextGlobal;
+    for (const name of EXECUTOR_GLOBALS) {
+      Object.defineProperty(contextGlobal, name, {
+        get: () => (globalThis as any)[name],
+        // Assignments from user code stay in the context.
+        set: (value) =>
+          Object.defineProperty(contextGlobal, name, {
+            value,
+            writable: true,
+            configurable: true,
+          }),
+        configurable: true,
+      });
+    }
+    // Errors thrown by user code are created by the context's `Error`, so
+    // format their stack traces with the executor's source mapping.
+    Object.defineProperty(contextGlobal.Error, "prepareStackTrace", {
+      get: () => Error.prepareStackTrace,
+      configurable: true,
+    });
+  }
+
+  // Imports a user module into the context, returning its namespace.
+  async importModule(filePath: string): Promise<any> {
+    return await this.run(async () => {
+      const url = pathToFileURL(filePath).href;
+      const module = await this.evaluate(this.sourceTextModule(url));
+      return module.namespace;
+    });
+  }
+
+  // Runs user code, attributing the resources it creates to this invocation.
+  run<T>(f: () => T): T {
+    return invocationStorage.run(this, f);
+  }
+
+  // Returns whether the invocation left behind resources that could still
+  // run code, in which case the worker shouldn't be reused.
+  async finish(): Promise<boolean> {
+    // Let callbacks that were already scheduled run and close their handles.
+    await new Promise((resolve) => setImmediate(resolve));
+    await new Promise((resolve) => setImmediate(resolve));
+    return this.resources.size > 0;
+  }
+
+  private async evaluate(module: vm.Module): Promise<vm.Module> {
+    if (module.status === "unlinked") {
+      await module.link((specifier, referencingModule) =>
+        this.resolve(specifier, referencingModule.identifier),
+      );
+    }
+    if (module.status === "linked") {
+      await module.evaluate();
+    }
+    return module;
+  }
+
+  private async resolve(
+    specifier: string,
+    referrer: string,
+  ): Promise<vm.Module> {
+    if (
+      specifier.startsWith("./") ||
+      specifier.startsWith("../") ||
+      specifier.startsWith("/") ||
+      specifier.startsWith("file:")
+    ) {
+      return this.sourceTextModule(new URL(specifier, referrer).href);
+    }
+    // Packages and Node.js builtins are loaded once per worker like the rest
+    // of the executor, and exposed to the context as they are.
+    const resolved =
+      specifier.startsWith("node:") || builtinModules.includes(specifier)
+        ? specifier
+        : pathToFileURL(
+            createRequire(fileURLToPath(referrer)).resolve(specifier),
+          ).href;
+    let module = this.modules.get(resolved);
+    if (module === undefined) {
+      const namespace = await import(resolved);
+      const exportNames = Object.keys(namespace);
+      module = new vm.SyntheticModule(
+        exportNames,
+        function () {
+          for (const name of exportNames) {
+            this.setExport(name, namespace[name]);
+          }
+        },
+        { identifier: resolved, context: this.context },
+      );
+      this.modules.set(resolved, module);
+    }
+    return module;
+  }
+
+  private sourceTextModule(url: string): vm.Module {
+    let module = this.modules.get(url);
+    if (module !== undefined) {
+      return module;
+    }
+    let source = moduleSources.get(url);
+    if (source === undefined) {
+      source = fs.readFileSync(fileURLToPath(url), "utf8");
+      moduleSources.set(url, source);
+    }
+    const sourceTextModule = new vm.SourceTextModule(source, {
+      identifier: url,
+      context: this.context,
+      initializeImportMeta: (meta) => {
+        meta.url = url;
+      },
+      importModuleDynamically: (async (
+        specifier: string,
+        referrer: vm.Module,
+      ) =>
+        await this.evaluate(
+          await this.resolve(specifier, referrer.identifier),
+        )) as any,
+    });
+    this.modules.set(url, sourceTextModule);
+    return sourceTextModule;
+  }
+}
@@ -2,14 +2,23 @@ import { Command } from "commander";
 import { invoke } from "./executor";
 import { v4 as uuidv4 } from "uuid";
 import { log, setDebugLogging } from "./log";
+import { setPackageCache } from "./source_package";
+import { InvocationContext, setInvocationContext } from "./invocation_context";
 import os from "node:os";
 import crypto from "crypto";
 import fs from "node:fs";
+import readline from "node:readline";
 import { Writable } from "node:stream";
 
-async function main(request_str: string, debug: boolean) {
+// Printed after each request in worker mode, with the nonce the request was
+// sent with, so output from user code can't be mistaken for it. Keep in sync
+// with worker_pool.rs.
+function workerDoneLine(nonce: string, retire: boolean) {
+  return JSON.stringify({ kind: "WorkerDone", nonce, retire });
+}
+
+function parseRequest(request_str: string) {
   let request;
-  setDebugLogging(debug);
   try {
     request = JSON.parse(request_str);
   } catch (err: any) {
@@ -18,14 +27,20 @@ async function main(request_str: string, debug: boolean) {
     );
   }
   request.requestId = uuidv4();
+  return request;
+}
 
-  // Monkey-patch os.tmpdir to avoid filesystem write races
+// Monkey-patch os.tmpdir to avoid filesystem write races
+function useFreshTempdir(): string {
   const prevTempdir = os.tmpdir();
   const seed = crypto.randomBytes(20).toString("hex");
   const tempdir = `${prevTempdir}/${seed}`;
   fs.mkdirSync(tempdir);
   os.tmpdir = () => tempdir;
+  return tempdir;
+}
 
+async function invokeAndLog(request: any) {
   const responseStream = new Writable({
     write: (chunk, _encoding, callback) => {
       log(chunk.toString());
@@ -34,20 +49,92 @@ async function main(request_str: string, debug: boolean) {
   });
   await invoke(request, responseStream);
   responseStream.end();
+}
+
+async function main(request_str: string, debug: boolean) {
+  setDebugLogging(debug);
+  const request = parseRequest(request_str);
+  const tempdir = useFreshTempdir();
+
+  await invokeAndLog(request);
 
   fs.rmSync(tempdir, { recursive: true });
   // Don't wait for dangling promises. This matches AWS Lambda behavior.
   process.exit(0);
 }
 
+// Runs requests read from stdin, one per line and each prefixed with a nonce,
+// so the backend can reuse this process across invocations. Downloaded
+// packages are cached in the worker's temp dir like in a warm AWS Lambda.
+// Each invocation's user code runs in its own context, and the backend
+// retires the worker if an invocation leaves behind timers or other handles
+// that could run code during the next one.
+async function worker(debug: boolean) {
+  setDebugLogging(debug);
+  const tempdir = useFreshTempdir();
+  const baseEnv = { ...process.env };
+  const baseCwd = process.cwd();
+  // A promise left behind by an earlier invocation shouldn't take down the
+  // worker while it's running a later one.
+  process.on("unhandledRejection", (reason) => {
+    process.stderr.write(`Unhandled rejection: ${String(reason)}\n`);
+  });
+  process.on("exit", () => {
+    fs.rmSync(tempdir, { recursive: true, force: true });
+  });
+
+  const lines = readline.createInterface({ input: process.stdin });
+  for await (const line of lines) {
+    if (line.trim() === "") {
+      continue;
+    }
+    const separator = line.indexOf(" ");
+    const nonce = line.slice(0, separator);
+    process.env = { ...baseEnv };
+    process.chdir(baseCwd);
+    const invocationContext = new InvocationContext();
+    setInvocationContext(invocationContext);
+    try {
+      await invokeAndLog(parseRequest(line.slice(separator + 1)));
+    } catch (e: any) {
+      // The backend reports a missing result as a system error.
+      process.stderr.write(`Failed to run request: ${e?.stack ?? e}\n`);
+    } finally {
+      setInvocationContext(null);
+    }
+    const retire = await invocationContext.finish();
+    log(workerDoneLine(nonce, retire));
+  }
+  process.exit(0);
+}
+
 const program = new Command();
 program
   .name("node-executor")
   .description("node-executor executes a actions locally")
   .usage("command url [options]")
   .option("--debug", "print debug output", false)
-  .requiredOption("--request <json>", "json request serialized as string")
+  .option("--request <json>", "json request serialized as string")
+  .option("--worker", "run requests read from stdin, one per line", false)
+  .option(
+    "--cache-dir <dir>",
+    "directory to share unpacked packages with other processes in",
+  )
+  .option(
+    "--cache-max-entries <n>",
+    "number of packages of each kind to keep in the cache",
+    "16",
+  )
   .action(async (options) => {
-    await main(options.request, options.debug);
+    if (options.cacheDir !== undefined) {
+      setPackageCache(options.cacheDir, parseInt(options.cacheMaxEntries));
+    }
+    if (options.worker) {
+      await worker(options.debug);
+    } else if (options.request !== undefined) {
+      await main(options.request, options.debug);
+    } else {
+      program.error("error: one of --request or --worker is required");
+    }
   });
 program.parseAsync(process.argv);
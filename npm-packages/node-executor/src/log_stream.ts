@@ -0,0 +1,78 @@
+import { logDebug } from "./log";
+
+// How long to buffer log lines before posting them to the backend.
+const FLUSH_INTERVAL_MS = 100;
+
+export type LogLineData = {
+  messages: string[];
+  isTruncated: boolean;
+  timestamp: number;
+  level: string;
+};
+
+// Posts the log lines of a running action to the backend in batches, so they
+// show up in the logs before the action finishes. Lines that can't be posted
+// are passed to `fallback`, which returns them with the response instead.
+export class LogLineStream {
+  private pending: LogLineData[] = [];
+  private timer: NodeJS.Timeout | null = null;
+  // Posts are chained so the backend receives lines in order.
+  private inFlight: Promise<void> = Promise.resolve();
+
+  constructor(
+    private backendAddress: string,
+    private backendCallbackToken: string,
+    private logStreamId: string,
+    private fallback: (line: LogLineData) => void,
+  ) {}
+
+  push(line: LogLineData) {
+    this.pending.push(line);
+    if (this.timer === null) {
+      this.timer = setTimeout(() => {
+        this.timer = null;
+        this.send();
+      }, FLUSH_INTERVAL_MS);
+    }
+  }
+
+  // Posts all buffered lines and waits until the backend has received them.
+  // Must be called before writing the response.
+  async flush() {
+    if (this.timer !== null) {
+      clearTimeout(this.timer);
+      this.timer = null;
+    }
+    this.send();
+    await this.inFlight;
+  }
+
+  private send() {
+    if (this.pending.length === 0) {
+      return;
+    }
+    const logLines = this.pending;
+    this.pending = [];
+    this.inFlight = this.inFlight.then(() => this.post(logLines));
+  }
+
+  private async post(logLines: LogLineData[]) {
+    try {
+      const url = new URL("/api/actions/log_lines", this.backendAddress);
+      const response = await fetch(url, {
+        body: JSON.stringify({ logStreamId: this.logStreamId, logLines }),
+        method: "POST",
+        headers: {
+          "Content-Type": "application/json",
+          "Convex-Action-Callback-Token": this.backendCallbackToken,
+        },
+      });
+      if (!response.ok) {
+        throw new Error(`${response.status} ${await response.text()}`);
+      }
+    } catch (e: any) {
+      logDebug(`Failed to stream log lines: ${e.message}`);
+      logLines.forEach(this.fallback);
+    }
+  }
+}
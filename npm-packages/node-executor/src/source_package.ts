@@ -7,7 +7,7 @@ import AdmZip from "adm-zip";
 import concat from "concat-stream";
 
 import fetch from "node-fetch";
-import { createHash } from "node:crypto";
+import { createHash, randomUUID } from "node:crypto";
 import { logDebug, logDurationMs } from "./log";
 import { performance } from "node:perf_hooks";
 
@@ -19,6 +19,9 @@ export type SourcePackage = {
 
   bundled_source: Package;
   external_deps?: Package | null;
+  // Prebuilt node_modules archives, in order of precedence over each other
+  // and external_deps.
+  layers?: Package[];
 };
 
 export type Package = {
@@ -29,6 +32,20 @@ export type Package = {
 
 type ModuleEnvironment = "node" | "isolate";
 
+// Directory shared by executor processes to cache unpacked packages in, keyed
+// by their hashes, so they're only downloaded and unpacked once per deploy.
+// If unset, packages are unpacked into the temp dir and only reused by this
+// process.
+let packageCache: { dir: string; maxEntries: number } | null = null;
+
+// Cache entries used this recently aren't evicted, since another process may
+// still be running code from them.
+const PACKAGE_CACHE_EVICTION_GRACE_MS = 60 * 60 * 1000;
+
+export function setPackageCache(dir: string, maxEntries: number) {
+  packageCache = { dir, maxEntries };
+}
+
 type MetadataJson = {
   modulePaths: string[];
   moduleEnvironments: Map<string, ModuleEnvironment>;
@@ -89,19 +106,38 @@ export async function maybeDownloadAndLinkPackages(
   // If we've previously downloaded and cached this source package, we've already linked the necessary
   // external modules and so there is no more work left to do, so return.
   const local = availableSourcePackages.get(sourcePackage.key);
-  if (local !== undefined) {
+  if (
+    local !== undefined &&
+    (!local.cached || (await touchCached(local.dir)))
+  ) {
     return local;
   }
+  if (packageCache !== null) {
+    const cached = await downloadCachedSourcePackage(sourcePackage);
+    availableSourcePackages.set(sourcePackage.key, cached);
+    return cached;
+  }
 
+  const layers = sourcePackage.layers ?? [];
+  // Keep the other packages this source package needs when making room for
+  // one of them.
+  const keep = new Set(layers.map((layer) => layer.key));
+  if (sourcePackage.external_deps) {
+    keep.add(sourcePackage.external_deps.key);
+  }
   const sourcePackagePromise = downloadSourcePackage(
     sourcePackage.bundled_source,
   );
   const externalPackagePromise = sourcePackage.external_deps
-    ? maybeDownloadExternalPackage(sourcePackage.external_deps)
+    ? maybeDownloadExternalPackage(sourcePackage.external_deps, keep)
     : null;
-  const [localPackage, externalPackage] = await Promise.all([
+  const layerPackagesPromise = Promise.all(
+    layers.map((layer) => maybeDownloadExternalPackage(layer, keep)),
+  );
+  const [localPackage, externalPackage, layerPackages] = await Promise.all([
     sourcePackagePromise,
     externalPackagePromise,
+    layerPackagesPromise,
   ]);
 
   // Do symlinking of external package into local source package node_modules folder.
@@ -115,21 +151,196 @@ export async function maybeDownloadAndLinkPackages(
   // symlink since we can be sure that the local package was not previously downloaded and cached, otherwise
   // this function would have returned earlier. Thus, the local package directory has been freshly downloaded
   // and so no node_modules folder can exist already.
-  if (externalPackage) {
+  const packages = externalPackage
+    ? [...layerPackages, externalPackage]
+    : layerPackages;
+  await linkNodeModules(
+    packages.map((pkg) => pkg.dir),
+    localPackage.dir,
+    layerPackages.length > 0,
+  );
+
+  // Save result for next time
+  availableSourcePackages.set(sourcePackage.key, localPackage);
+
+  return localPackage;
+}
+
+// Links the node_modules of each of `depDirs` into `dir`. A single
+// directory's node_modules is linked as a whole unless `merge` is set.
+async function linkNodeModules(depDirs: string[], dir: string, merge: boolean) {
+  if (depDirs.length > 1 || (depDirs.length === 1 && merge)) {
+    await linkMergedNodeModules(
+      depDirs.map((depDir) => `${depDir}/node_modules`),
+      `${dir}/node_modules`,
+    );
+  } else if (depDirs.length === 1) {
     logDebug(
-      `Attempting symlink from ${externalPackage.dir}/node_modules to ${localPackage.dir}/node_modules`,
+      `Attempting symlink from ${depDirs[0]}/node_modules to ${dir}/node_modules`,
     );
     await fs.promises.symlink(
-      `${externalPackage.dir}/node_modules`,
-      `${localPackage.dir}/node_modules`,
+      `${depDirs[0]}/node_modules`,
+      `${dir}/node_modules`,
       "dir",
     );
   }
+}
 
-  // Save result for next time
-  availableSourcePackages.set(sourcePackage.key, localPackage);
+// Like `maybeDownloadAndLinkPackages`, but unpacks packages into the package
+// cache, where other executor processes can reuse them.
+async function downloadCachedSourcePackage(
+  sourcePackage: SourcePackage,
+): Promise<LocalSourcePackage> {
+  const start = performance.now();
+  const layers = sourcePackage.layers ?? [];
+  const depPackages = sourcePackage.external_deps
+    ? [...layers, sourcePackage.external_deps]
+    : layers;
+  // The linked node_modules are part of the cached source package, so its key
+  // covers its dependencies too.
+  const key = createHash("sha256")
+    .update(
+      JSON.stringify([
+        sourcePackage.bundled_source.sha256,
+        ...depPackages.map((pkg) => pkg.sha256),
+      ]),
+    )
+    .digest("base64url");
+  const dir = await cachedDir("source", key, async (stagingDir) => {
+    const depDirsPromise = Promise.all(
+      depPackages.map((pkg) =>
+        cachedDir("external_deps", pkg.sha256, async (depStagingDir) => {
+          const stream = await download(pkg.uri);
+          await processExternalPackageStream(depStagingDir, pkg, stream);
+        }),
+      ),
+    );
+    const sourcePromise = (async () => {
+      const stream = await download(sourcePackage.bundled_source.uri);
+      await processSourcePackageStream(
+        stagingDir,
+        sourcePackage.bundled_source,
+        stream,
+      );
+    })();
+    const [depDirs] = await Promise.all([depDirsPromise, sourcePromise]);
+    await linkNodeModules(depDirs, stagingDir, layers.length > 0);
+  });
+  const metadata = parseMetadataFile(
+    await fs.promises.readFile(`${dir}/metadata.json`, { encoding: "utf-8" }),
+  );
+  logDurationMs("cachedSourcePackageTime", start);
+  return {
+    dir,
+    modules: modulesFromMetadataJson(metadata),
+    dynamicallyDownloaded: false,
+    cached: true,
+  };
+}
 
-  return localPackage;
+// Returns the `kind/key` directory of the package cache, populating it with
+// `populate` first if it's missing. Entries are populated in a staging
+// directory and then renamed into place, so other processes never see a
+// partially populated entry.
+async function cachedDir(
+  kind: "source" | "external_deps",
+  key: string,
+  populate: (stagingDir: string) => Promise<void>,
+): Promise<string> {
+  const cache = packageCache!;
+  const dir = path.join(cache.dir, kind, key);
+  if (await touchCached(dir)) {
+    logDebug(`Using cached ${kind} package ${key}`);
+    return dir;
+  }
+  const stagingDir = path.join(cache.dir, "staging", randomUUID());
+  await createFreshDir(stagingDir);
+  try {
+    await populate(stagingDir);
+    await fs.promises.mkdir(path.dirname(dir), { recursive: true });
+    await fs.promises.rename(stagingDir, dir);
+  } catch (e: any) {
+    await fs.promises.rm(stagingDir, { recursive: true, force: true });
+    // Another process populated the entry first.
+    if (fs.existsSync(dir)) {
+      return dir;
+    }
+    throw e;
+  }
+  await evictCachedDirs(kind);
+  return dir;
+}
+
+// Marks a cache entry as used, returning false if it doesn't exist.
+async function touchCached(dir: string): Promise<boolean> {
+  const now = new Date();
+  try {
+    await fs.promises.utimes(dir, now, now);
+    return true;
+  } catch (e: any) {
+    return false;
+  }
+}
+
+// Removes the least recently used entries of `kind` beyond the cache's limit.
+async function evictCachedDirs(kind: "source" | "external_deps") {
+  const cache = packageCache!;
+  const kindDir = path.join(cache.dir, kind);
+  const entries = [];
+  for (const name of await fs.promises.readdir(kindDir)) {
+    const dir = path.join(kindDir, name);
+    const stat = await fs.promises.stat(dir).catch(() => null);
+    if (stat !== null) {
+      entries.push({ dir, mtimeMs: stat.mtimeMs });
+    }
+  }
+  entries.sort((a, b) => b.mtimeMs - a.mtimeMs);
+  for (const entry of entries.slice(cache.maxEntries)) {
+    if (Date.now() - entry.mtimeMs < PACKAGE_CACHE_EVICTION_GRACE_MS) {
+      continue;
+    }
+    logDebug(`Evicting cached package ${entry.dir}`);
+    await fs.promises.rm(entry.dir, { recursive: true, force: true });
+  }
+}
+
+// Builds a node_modules directory at `target` that links to the packages in
+// each of `sources`, taking a package from the first source that has it.
+// Scope directories (`@scope/`) and `.bin` are merged across sources.
+async function linkMergedNodeModules(sources: string[], target: string) {
+  const start = performance.now();
+  const mergeDir = async (sourceDirs: string[], targetDir: string) => {
+    await fs.promises.mkdir(targetDir, { recursive: true });
+    const merged = new Map<string, string[]>();
+    for (const sourceDir of sourceDirs) {
+      const entries = await fs.promises.readdir(sourceDir, {
+        withFileTypes: true,
+      });
+      for (const entry of entries) {
+        const entryPath = path.join(sourceDir, entry.name);
+        if (
+          entry.isDirectory() &&
+          (entry.name.startsWith("@") || entry.name === ".bin")
+        ) {
+          merged.set(entry.name, [
+            ...(merged.get(entry.name) ?? []),
+            entryPath,
+          ]);
+          continue;
+        }
+        const linkPath = path.join(targetDir, entry.name);
+        if (merged.has(entry.name) || fs.existsSync(linkPath)) {
+          continue;
+        }
+        await fs.promises.symlink(entryPath, linkPath);
+      }
+    }
+    for (const [name, dirs] of merged) {
+      await mergeDir(dirs, path.join(targetDir, name));
+    }
+  };
+  await mergeDir(sources.filter((source) => fs.existsSync(source)), target);
+  logDurationMs("linkDependencyLayersTime", start);
 }
 
 // Downloads sourcePackage and unzips it into `source/${sourcePackage.key}/modules`
@@ -167,6 +378,7 @@ async function downloadSourcePackage(
 // Downloads externalPackage and unzips it into `externals/${externalPackage.key}/node_modules`.
 async function maybeDownloadExternalPackage(
   externalPackage: Package,
+  keep: Set<string>,
 ): Promise<ExternalDepsPackage> {
   const start = performance.now();
   const externalDeps =
@@ -176,7 +388,7 @@ async function maybeDownloadExternalPackage(
     logDebug("External Package not available locally");
 
     // Cleanup other external dependency packages to not run out of disk space
-    await cleanupExternalPackages();
+    await cleanupExternalPackages(keep);
     logDurationMs("cleanupExternalPackages", start);
 
     // Create directory and do download in parallel
@@ -304,6 +516,8 @@ type LocalSourcePackage = {
   dir: string;
   modules: Set<CanonicalizedModulePath>;
   dynamicallyDownloaded: boolean;
+  // Whether `dir` is in the package cache, where it may be evicted.
+  cached?: boolean;
 };
 
 type ExternalDepsPackage = {
@@ -458,12 +672,13 @@ export async function cleanupSourcePackages() {
   }
 }
 
-// Delete all external dependency packages. This doesn't distinguish between
-// prebuilt and non-prebuilt packages, like cleanupSourcePackages, since there
-// is no prebuild of external packages yet.
-export async function cleanupExternalPackages() {
+// Delete all dynamically downloaded external dependency packages and
+// dependency layers, except those in `keep`.
+export async function cleanupExternalPackages(
+  keep: Set<string> = new Set(),
+) {
   for (const [key, pkg] of availableExternalPackages) {
-    if (pkg.dynamicallyDownloaded) {
+    if (pkg.dynamicallyDownloaded && !keep.has(key)) {
       availableExternalPackages.delete(key);
       await fs.promises.rm(pkg.dir, { recursive: true, force: true });
     }
@@ -46,6 +46,21 @@ const scheduleSchema = z.object({
   functionHandle: z.optional(z.string()),
   ts: z.number(),
   args: z.any(),
+  options: z.optional(
+    z.object({
+      priority: z.optional(z.string()),
+      queue: z.optional(z.string()),
+      retry: z.optional(
+        z.object({
+          maxAttempts: z.number(),
+          initialBackoffMs: z.optional(z.number()),
+          maxBackoffMs: z.optional(z.number()),
+        }),
+      ),
+      dedupeKey: z.optional(z.string()),
+      onDuplicate: z.optional(z.string()),
+    }),
+  ),
   version: z.string(),
 });
 
@@ -530,6 +545,7 @@ export class SyscallsImpl {
         udfPath: scheduleArgs.name,
         udfArgs: scheduleArgs.args,
         scheduledTs: scheduleArgs.ts,
+        options: scheduleArgs.options,
       },
       path: "/api/actions/schedule_job",
       operationName,
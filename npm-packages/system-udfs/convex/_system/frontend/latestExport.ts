@@ -22,6 +22,29 @@ export const latestCloudExport = queryPrivateSystem({
   },
 });
 
+export const latestScheduledExport = queryPrivateSystem({
+  args: {},
+  handler: async function ({ db }): Promise<Export | null> {
+    return await db
+      .query("_exports")
+      .withIndex("by_requestor", (q) => q.eq("requestor", "scheduledExport"))
+      .order("desc")
+      .first();
+  },
+});
+
+export const exportSchedule = queryPrivateSystem({
+  args: {},
+  handler: async function ({ db }) {
+    const schedule = await db.query("_export_schedules").first();
+    if (schedule === null) {
+      return null;
+    }
+    const { destination, ...rest } = schedule;
+    return { ...rest, hasDestination: destination !== null };
+  },
+});
+
 export const canExportFileStorage = queryGeneric({
   args: {},
   handler: async (ctx) => {
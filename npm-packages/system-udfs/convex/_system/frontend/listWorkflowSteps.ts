@@ -0,0 +1,21 @@
+import { Doc } from "../../_generated/dataModel";
+import { queryPrivateSystem } from "../secretSystemTables";
+import { v } from "convex/values";
+
+export default queryPrivateSystem({
+  args: {
+    componentId: v.optional(v.union(v.string(), v.null())),
+    workflowId: v.string(),
+  },
+  handler: async function (
+    { db },
+    { workflowId },
+  ): Promise<Doc<"_workflow_steps">[]> {
+    return await db
+      .query("_workflow_steps")
+      .withIndex("by_workflow_id_and_step_number", (q) =>
+        q.eq("workflowId", workflowId),
+      )
+      .collect();
+  },
+});
@@ -0,0 +1,26 @@
+import { Doc } from "../../_generated/dataModel";
+import { PaginationResult, paginationOptsValidator } from "convex/server";
+import { queryPrivateSystem } from "../secretSystemTables";
+import { v } from "convex/values";
+import { maximumBytesRead, maximumRowsRead } from "../paginationLimits";
+
+export default queryPrivateSystem({
+  args: {
+    componentId: v.optional(v.union(v.string(), v.null())),
+    paginationOpts: paginationOptsValidator,
+  },
+  handler: async function (
+    { db },
+    { paginationOpts },
+  ): Promise<PaginationResult<Doc<"_workflows">>> {
+    // Most recently started first.
+    return await db
+      .query("_workflows")
+      .order("desc")
+      .paginate({
+        ...paginationOpts,
+        maximumBytesRead,
+        maximumRowsRead,
+      });
+  },
+});
@@ -0,0 +1,111 @@
+import { GenericDocument, paginationOptsValidator } from "convex/server";
+import { ConvexError, GenericId, Value, v } from "convex/values";
+import { maximumBytesRead, maximumRowsRead } from "./paginationLimits";
+import { mutationGeneric, queryGeneric } from "./server";
+
+// These UDFs back the REST API at `/api/rest/:table`. They only ever touch
+// user tables in the root component, and return `null` if a document ID
+// doesn't belong to the requested table so the API can respond with a 404.
+
+const lowerBound = v.object({
+  op: v.union(v.literal("gt"), v.literal("gte")),
+  field: v.string(),
+  value: v.any(),
+});
+
+const upperBound = v.object({
+  op: v.union(v.literal("lt"), v.literal("lte")),
+  field: v.string(),
+  value: v.any(),
+});
+
+export const list = queryGeneric({
+  args: {
+    table: v.string(),
+    index: v.optional(v.string()),
+    // Equality filters on a prefix of the index's fields, in index order.
+    eq: v.array(v.object({ field: v.string(), value: v.any() })),
+    lower: v.optional(lowerBound),
+    upper: v.optional(upperBound),
+    order: v.union(v.literal("asc"), v.literal("desc")),
+    paginationOpts: paginationOptsValidator,
+  },
+  handler: async ({ db }, args) => {
+    let query;
+    if (args.index === undefined) {
+      if (args.eq.length > 0 || args.lower || args.upper) {
+        throw new ConvexError("Filtering requires an index.");
+      }
+      query = db.query(args.table);
+    } else {
+      query = db.query(args.table).withIndex(args.index, (q: any) => {
+        for (const { field, value } of args.eq) {
+          q = q.eq(field, value);
+        }
+        if (args.lower) {
+          q = q[args.lower.op](args.lower.field, args.lower.value);
+        }
+        if (args.upper) {
+          q = q[args.upper.op](args.upper.field, args.upper.value);
+        }
+        return q;
+      });
+    }
+    const { page, continueCursor, isDone } = await query
+      .order(args.order)
+      .paginate({ ...args.paginationOpts, maximumRowsRead, maximumBytesRead });
+    return { page, continueCursor, isDone };
+  },
+});
+
+export const get = queryGeneric({
+  args: { table: v.string(), id: v.string() },
+  handler: async ({ db }, args) => {
+    const id = db.normalizeId(args.table, args.id);
+    return id === null ? null : await db.get(id);
+  },
+});
+
+export const insert = mutationGeneric({
+  args: { table: v.string(), document: v.any() },
+  handler: async ({ db }, args) => {
+    let id: GenericId<string>;
+    try {
+      id = await db.insert(args.table, args.document as GenericDocument);
+    } catch (e: any) {
+      // Rewrapping this error because it could be a schema validation error.
+      throw new ConvexError(e.message);
+    }
+    return await db.get(id);
+  },
+});
+
+export const patch = mutationGeneric({
+  args: { table: v.string(), id: v.string(), fields: v.any() },
+  handler: async ({ db }, args) => {
+    const id = db.normalizeId(args.table, args.id);
+    if (id === null || (await db.get(id)) === null) {
+      return null;
+    }
+    try {
+      await db.patch(id, args.fields as Record<string, Value>);
+    } catch (e: any) {
+      // Rewrapping this error because it could be a schema validation error.
+      throw new ConvexError(e.message);
+    }
+    return await db.get(id);
+  },
+});
+
+export const remove = mutationGeneric({
+  args: { table: v.string(), id: v.string() },
+  handler: async ({ db }, args) => {
+    const id = db.normalizeId(args.table, args.id);
+    const document = id === null ? null : await db.get(id);
+    if (id === null || document === null) {
+      return null;
+    }
+    await db.delete(id);
+    return document;
+  },
+});
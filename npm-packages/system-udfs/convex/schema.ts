@@ -89,6 +89,12 @@ const CronSchedule = v.union(
   v.object({
     type: v.literal("cron"),
     cronExpr: v.string(),
+    timezone: v.optional(v.string()),
+  }),
+  v.object({
+    type: v.literal("calendar"),
+    expr: v.string(),
+    timezone: v.optional(v.string()),
   }),
 );
 
@@ -96,6 +102,8 @@ const analyzedCronSpec = v.object({
   udfPath: v.string(),
   udfArgs: v.bytes(),
   cronSchedule: CronSchedule,
+  jitterMs: v.optional(v.int64()),
+  maxDispatchPerSecond: v.optional(v.int64()),
 });
 
 const mappedModule = v.object({
@@ -135,6 +143,21 @@ const analyzedModule = v.object({
 const exportRequestor = v.union(
   v.literal("snapshotExport"),
   v.literal("cloudBackup"),
+  v.literal("scheduledExport"),
+);
+
+const exportFormat = v.union(
+  v.object({
+    format: v.literal("zip"),
+    include_storage: v.boolean(),
+  }),
+  v.object({
+    format: v.literal("parquet"),
+  }),
+  v.object({
+    format: v.literal("csv"),
+    field_order: v.array(v.string()),
+  }),
 );
 
 export const completedExport = v.object({
@@ -168,6 +191,12 @@ const cronJobStatus = v.union(
   }),
 );
 
+const retryPolicy = v.object({
+  maxAttempts: v.int64(),
+  initialBackoffMs: v.int64(),
+  maxBackoffMs: v.int64(),
+});
+
 // Log sinks
 export const sinkState = v.union(
   v.object({
@@ -302,6 +331,53 @@ export default defineSchema({
       // TODO: add canceled
     ),
   ).index("by_requestor", ["requestor"]),
+  _export_schedules: defineTable({
+    schedule: CronSchedule,
+    format: exportFormat,
+    component: v.union(v.string(), v.null()),
+    retain: v.int64(),
+    // Has the destination's credentials, so it's not sent to the dashboard.
+    destination: v.union(v.any(), v.null()),
+    nextTs: v.int64(),
+    lastRequestedTs: v.union(v.int64(), v.null()),
+    lastExportId: v.union(v.string(), v.null()),
+  }),
+  _usage_records: defineTable({
+    hourStartMs: v.int64(),
+    componentPath: v.union(v.string(), v.null()),
+    udfId: v.union(v.string(), v.null()),
+    tableName: v.union(v.string(), v.null()),
+    functionCalls: v.int64(),
+    databaseIngressBytes: v.int64(),
+    databaseEgressBytes: v.int64(),
+    databaseEgressRows: v.int64(),
+    storageCalls: v.int64(),
+    storageIngressBytes: v.int64(),
+    storageEgressBytes: v.int64(),
+    actionComputeMbMs: v.int64(),
+    vectorIngressBytes: v.int64(),
+    vectorEgressBytes: v.int64(),
+  }).index("by_hour_start", ["hourStartMs"]),
+  _alert_rules: defineTable({
+    name: v.string(),
+    metric: v.union(
+      v.literal("errorRate"),
+      v.literal("schedulerLag"),
+      v.literal("diskUsage"),
+      v.literal("commitLatency"),
+    ),
+    threshold: v.float64(),
+    windowSecs: v.int64(),
+    channels: v.array(
+      v.union(
+        v.object({ type: v.literal("webhook"), url: v.string() }),
+        v.object({ type: v.literal("email"), to: v.string() }),
+      ),
+    ),
+    firing: v.boolean(),
+    stateSinceSecs: v.union(v.int64(), v.null()),
+    stateValue: v.union(v.float64(), v.null()),
+  }),
   _deployment_audit_log: deploymentAuditLogTable,
   _scheduled_jobs: defineTable({
     nextTs: v.union(v.int64(), v.null()),
@@ -312,9 +388,76 @@ export default defineSchema({
     ),
     udfArgs: v.bytes(),
     component: v.optional(v.string()),
+    priority: v.optional(v.union(v.literal("high"), v.literal("low"))),
+    queue: v.optional(v.string()),
+    retry: v.optional(retryPolicy),
+    workflowStep: v.optional(v.string()),
+    dedupeKey: v.optional(v.string()),
   })
     .index("by_udf_path_and_next_event_ts", ["udfPath", "nextTs"])
-    .index("by_next_ts", ["nextTs"]),
+    .index("by_next_ts", ["nextTs"])
+    .index("by_priority_and_next_ts", ["priority", "nextTs"])
+    .index("by_dedupe_key_and_next_ts", ["dedupeKey", "nextTs"]),
+  _scheduled_job_dead_letters: defineTable({
+    jobId: v.string(),
+    component: v.string(),
+    udfPath: v.string(),
+    udfArgs: v.bytes(),
+    error: v.string(),
+    attempts: v.int64(),
+    originalScheduledTs: v.int64(),
+    failedTs: v.int64(),
+    priority: v.optional(v.union(v.literal("high"), v.literal("low"))),
+    queue: v.optional(v.string()),
+    retry: v.optional(retryPolicy),
+  }),
+  _workflows: defineTable({
+    component: v.string(),
+    udfPath: v.string(),
+    args: v.bytes(),
+    state: v.union(
+      v.object({ type: v.literal("running") }),
+      v.object({ type: v.literal("completed"), result: v.string() }),
+      v.object({ type: v.literal("failed"), error: v.string() }),
+      v.object({ type: v.literal("canceled") }),
+    ),
+    startTs: v.int64(),
+    completedTs: v.union(v.int64(), v.null()),
+  }),
+  _workflow_steps: defineTable({
+    workflowId: v.string(),
+    stepNumber: v.int64(),
+    kind: v.union(
+      v.literal("query"),
+      v.literal("mutation"),
+      v.literal("action"),
+      v.literal("sleep"),
+    ),
+    component: v.union(v.string(), v.null()),
+    udfPath: v.union(v.string(), v.null()),
+    state: v.union(
+      v.object({ type: v.literal("inProgress") }),
+      v.object({ type: v.literal("succeeded"), result: v.string() }),
+      v.object({ type: v.literal("failed"), error: v.string() }),
+    ),
+    scheduledJobId: v.union(v.string(), v.null()),
+    wakeTs: v.union(v.int64(), v.null()),
+    startTs: v.int64(),
+    completedTs: v.union(v.int64(), v.null()),
+  }).index("by_workflow_id_and_step_number", ["workflowId", "stepNumber"]),
+  _scheduler_pauses: defineTable({
+    component: v.union(v.string(), v.null()),
+    udfPath: v.union(v.string(), v.null()),
+    reason: v.union(v.string(), v.null()),
+    pausedTs: v.int64(),
+  }),
+  _saml_assertions: defineTable({
+    issuer: v.string(),
+    assertionId: v.string(),
+    expirationMs: v.int64(),
+  })
+    .index("by_assertion_id", ["issuer", "assertionId"])
+    .index("by_expiration", ["expirationMs"]),
   _cron_jobs: defineTable({
     name: v.string(),
     cronSpec: analyzedCronSpec,
@@ -324,6 +467,7 @@ export default defineSchema({
     ),
     nextTs: v.int64(),
     prevTs: v.union(v.int64(), v.null()),
+    jitterMs: v.optional(v.int64()),
   })
     .index("by_next_ts", ["nextTs"])
     .index("by_name", ["name"]),
@@ -9,6 +9,7 @@ import {
 const createEnvironmentVariable = v.object({
   action: v.literal("create_environment_variable"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     variable_name: v.string(),
   }),
@@ -17,6 +18,7 @@ const createEnvironmentVariable = v.object({
 const deleteEnvironmentVariable = v.object({
   action: v.literal("delete_environment_variable"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     variable_name: v.string(),
   }),
@@ -25,6 +27,7 @@ const deleteEnvironmentVariable = v.object({
 const updateEnvironmentVariable = v.object({
   action: v.literal("update_environment_variable"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     variable_name: v.string(),
   }),
@@ -33,6 +36,7 @@ const updateEnvironmentVariable = v.object({
 const replaceEnvironmentVariable = v.object({
   action: v.literal("replace_environment_variable"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     previous_variable_name: v.string(),
     variable_name: v.string(),
@@ -66,6 +70,7 @@ const indexConfigs = v.array(v.union(databaseIndex, searchIndex, vectorIndex));
 export const buildIndexes = v.object({
   action: v.literal("build_indexes"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     added_indexes: indexConfigs,
     removed_indexes: indexConfigs,
@@ -115,6 +120,7 @@ export const schemaDiffType = v.optional(
 export const pushConfig = v.object({
   action: v.literal("push_config"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     auth: authDiff,
     server_version: serverVersion,
@@ -143,6 +149,7 @@ export const componentDiff = v.object({
 export const pushConfigWithComponents = v.object({
   action: v.literal("push_config_with_components"),
   member_id: v.int64(),
+  actor: v.optional(v.string()),
   metadata: v.object({
     auth_diff: v.optional(authDiff),
     component_diffs: v.array(
@@ -163,6 +170,7 @@ export const deploymentState = v.union(
 export const changeDeploymentState = v.object({
   action: v.literal("change_deployment_state"),
   member_id: v.union(v.int64(), v.null()),
+  actor: v.optional(v.string()),
   metadata: v.object({
     old_state: deploymentState,
     new_state: deploymentState,
@@ -172,12 +180,14 @@ export const changeDeploymentState = v.object({
 export const clearTables = v.object({
   action: v.literal("clear_tables"),
   member_id: v.union(v.int64(), v.null()),
+  actor: v.optional(v.string()),
   metadata: v.object({}),
 });
 
 export const snapshotImport = v.object({
   action: v.literal("snapshot_import"),
   member_id: v.union(v.int64(), v.null()),
+  actor: v.optional(v.string()),
   metadata: v.object({
     table_names: v.array(
       v.object({
@@ -199,6 +209,41 @@ export const snapshotImport = v.object({
   }),
 });
 
+export const exchangeToken = v.object({
+  action: v.literal("exchange_token"),
+  member_id: v.union(v.int64(), v.null()),
+  actor: v.optional(v.string()),
+  metadata: v.object({
+    token_identifier: v.string(),
+    actor: v.string(),
+  }),
+});
+
+const exportFormat = v.union(
+  v.object({
+    format: v.literal("zip"),
+    include_storage: v.boolean(),
+    storage_urls: v.optional(v.boolean()),
+  }),
+  v.object({ format: v.literal("parquet") }),
+  v.object({ format: v.literal("csv"), field_order: v.array(v.string()) }),
+);
+
+export const requestExport = v.object({
+  action: v.literal("request_export"),
+  member_id: v.union(v.int64(), v.null()),
+  actor: v.optional(v.string()),
+  metadata: v.object({
+    format: exportFormat,
+    requestor: v.union(
+      v.literal("snapshotExport"),
+      v.literal("cloudBackup"),
+      v.literal("scheduledExport"),
+    ),
+    incremental: v.boolean(),
+  }),
+});
+
 const deploymentAuditLogTable = defineTable(
   v.union(
     createEnvironmentVariable,
@@ -211,6 +256,8 @@ const deploymentAuditLogTable = defineTable(
     changeDeploymentState,
     clearTables,
     snapshotImport,
+    exchangeToken,
+    requestExport,
   ),
 );
 
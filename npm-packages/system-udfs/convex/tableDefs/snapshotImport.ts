@@ -1,18 +1,56 @@
 import { defineTable } from "convex/server";
 import { v } from "convex/values";
 
-export const snapshotImportFormat = v.union(
+const importColumnTypes = v.union(
   v.object({
-    format: v.union(
-      v.literal("csv"),
-      v.literal("jsonl"),
-      v.literal("json_array"),
+    mode: v.literal("explicit"),
+    columns: v.array(
+      v.object({
+        name: v.string(),
+        type: v.union(
+          v.literal("string"),
+          v.literal("float64"),
+          v.literal("int64"),
+          v.literal("boolean"),
+          v.literal("bytes"),
+          v.literal("json"),
+        ),
+        nullable: v.boolean(),
+      }),
     ),
+  }),
+  v.object({
+    mode: v.literal("infer"),
+  }),
+);
+
+export const snapshotImportFormat = v.union(
+  v.object({
+    format: v.literal("csv"),
+    table: v.string(),
+    column_types: v.optional(importColumnTypes),
+  }),
+  v.object({
+    format: v.union(v.literal("jsonl"), v.literal("json_array")),
     table: v.string(),
   }),
   v.object({
     format: v.literal("zip"),
   }),
+  v.object({
+    format: v.literal("mongo_bson"),
+    table: v.string(),
+    id_field: v.union(v.string(), v.null()),
+  }),
+  v.object({
+    format: v.literal("mongo_archive"),
+    id_field: v.union(v.string(), v.null()),
+  }),
+  v.object({
+    format: v.literal("pg_dump"),
+    table: v.string(),
+    column_types: v.optional(importColumnTypes),
+  }),
 );
 
 export const snapshotImportMode = v.union(
@@ -18,7 +18,9 @@ import {
   normalizeAlgorithmGetKeyLength,
   normalizeAlgorithmImportKey,
   normalizeAlgorithmSign,
+  normalizeAlgorithmUnwrapKey,
   normalizeAlgorithmVerify,
+  normalizeAlgorithmWrapKey,
 } from "./crypto/normalize_algorithm.js";
 import {
   KEY_STORE,
@@ -26,6 +28,7 @@ import {
   _handle,
   _type,
   _algorithm,
+  _extractable,
   _usages,
 } from "./crypto/crypto_key.js";
 import * as ImportKey from "./crypto/import_key.js";
@@ -419,7 +422,7 @@ class SubtleCrypto {
       | HkdfParams
       | Pbkdf2Params,
     baseKey: CryptoKey,
-    length: number,
+    length: number | null,
   ): Promise<ArrayBuffer> {
     const prefix = "Failed to execute 'deriveBits' on 'SubtleCrypto'";
     requiredArguments(arguments.length, 3, prefix);
@@ -645,12 +648,130 @@ class SubtleCrypto {
     throw new TypeError(`Unknown algorithm name ${normalizedAlgorithm.name}`);
   }
 
-  async wrapKey() {
-    throwNotImplementedMethodError("wrapKey", "SubtleCrypto");
+  async wrapKey(
+    format: "jwk" | "pkcs8" | "raw" | "spki",
+    key: CryptoKey,
+    wrappingKey: CryptoKey,
+    wrapAlgorithm: AlgorithmIdentifier,
+  ): Promise<ArrayBuffer> {
+    const prefix = "Failed to execute 'wrapKey' on 'SubtleCrypto'";
+    requiredArguments(arguments.length, 4, prefix);
+
+    // 2-3.
+    const normalizedAlgorithm = normalizeAlgorithmWrapKey(wrapAlgorithm);
+
+    // 8.
+    if (normalizedAlgorithm.name !== wrappingKey[_algorithm].name) {
+      throw new DOMException(
+        "Wrapping algorithm doesn't match key algorithm.",
+        "InvalidAccessError",
+      );
+    }
+
+    // 9.
+    if (!wrappingKey[_usages].includes("wrapKey")) {
+      throw new DOMException(
+        "Key does not support the 'wrapKey' operation.",
+        "InvalidAccessError",
+      );
+    }
+
+    // 11.
+    if (key[_extractable] === false) {
+      throw new DOMException("Key is not extractable", "InvalidAccessError");
+    }
+
+    // 12.
+    const exportedKey = await this.exportKey(format, key);
+
+    // 13.
+    const bytes =
+      format === "jwk"
+        ? new TextEncoder().encode(JSON.stringify(exportedKey))
+        : new Uint8Array(exportedKey as ArrayBuffer);
+
+    // 14-15.
+    const keyData = KEY_STORE.get(wrappingKey[_handle]);
+    const wrapped = performOp(
+      "crypto/wrapKey",
+      { key: keyData, algorithm: normalizedAlgorithm.name },
+      bytes,
+    );
+    return wrapped.buffer;
   }
 
-  async unwrapKey() {
-    throwNotImplementedMethodError("unwrapKey", "SubtleCrypto");
+  async unwrapKey(
+    format: "jwk" | "pkcs8" | "raw" | "spki",
+    wrappedKey: BufferSource,
+    unwrappingKey: CryptoKey,
+    unwrapAlgorithm: AlgorithmIdentifier,
+    unwrappedKeyAlgorithm: any,
+    extractable: boolean,
+    keyUsages: KeyUsage[],
+  ): Promise<CryptoKey> {
+    const prefix = "Failed to execute 'unwrapKey' on 'SubtleCrypto'";
+    requiredArguments(arguments.length, 7, prefix);
+
+    // 2.
+    const wrappedKeyCopy = copyBuffer(wrappedKey);
+
+    // 3-4.
+    const normalizedAlgorithm = normalizeAlgorithmUnwrapKey(unwrapAlgorithm);
+
+    // 5-6.
+    const normalizedKeyAlgorithm = normalizeAlgorithmImportKey(
+      unwrappedKeyAlgorithm,
+    );
+
+    // 11.
+    if (normalizedAlgorithm.name !== unwrappingKey[_algorithm].name) {
+      throw new DOMException(
+        "Unwrapping algorithm doesn't match key algorithm.",
+        "InvalidAccessError",
+      );
+    }
+
+    // 12.
+    if (!unwrappingKey[_usages].includes("unwrapKey")) {
+      throw new DOMException(
+        "Key does not support the 'unwrapKey' operation.",
+        "InvalidAccessError",
+      );
+    }
+
+    // 13.
+    const keyData = KEY_STORE.get(unwrappingKey[_handle]);
+    const unwrapped: Uint8Array = performOp(
+      "crypto/unwrapKey",
+      { key: keyData, algorithm: normalizedAlgorithm.name },
+      wrappedKeyCopy,
+    );
+
+    // 14.
+    const bytes =
+      format === "jwk"
+        ? JSON.parse(new TextDecoder().decode(unwrapped))
+        : unwrapped.buffer;
+
+    // 15.
+    const result = await this.importKey(
+      format,
+      bytes,
+      // @ts-expect-error TODO: figure out why these types don't match up
+      normalizedKeyAlgorithm,
+      extractable,
+      keyUsages,
+    );
+
+    // 16.
+    if (
+      ["private", "secret"].includes(result[_type]) &&
+      keyUsages.length === 0
+    ) {
+      throw new SyntaxError("Invalid key usages");
+    }
+    // 17-19.
+    return result;
   }
 
   async generateKey() {
@@ -57,6 +57,9 @@ export interface RequestInit {
 }
 
 const _contentLength = Symbol("[[contentLength]]");
+// Set on an HTTP action's request if the client asked to upgrade it to a
+// WebSocket, until the action accepts it with `upgradeWebSocket`.
+export const _websocketId = Symbol("[[websocketId]]");
 
 export class Request {
   private readonly _headers: Headers;
@@ -65,6 +68,7 @@ export class Request {
   private _bodyStream: ReadableStream | null;
   private _bodyUsed = false;
   [_contentLength]: number | null;
+  [_websocketId]: string | null = null;
 
   constructor(input: string | URL | Request, options?: RequestInit) {
     if (input === undefined) {
@@ -343,6 +347,7 @@ export const requestFromConvexJson = ({
     body: stream,
     method: convexJson.method,
   });
+  request[_websocketId] = convexJson.websocketId ?? null;
   return request;
 };
 
@@ -307,6 +307,17 @@ export const convexJsonFromResponse = ({ response }: { response: any }) => {
   };
 };
 
+// The constructor rejects 1xx statuses, so this is the only way to create the
+// response that accepts a WebSocket upgrade.
+export const switchingProtocolsResponse = (
+  body: ReadableStream,
+  headers: Headers,
+) => {
+  const response = new Response(body, { headers });
+  (response as any)._status = 101;
+  return response;
+};
+
 export const responseFromConvexObject = (convexObject: Record<string, any>) => {
   const body = convexObject.streamId
     ? extractStream(convexObject.streamId)
@@ -0,0 +1,419 @@
+import { Event, EventTarget } from "./02_event.js";
+import { ReadableStream } from "./06_streams.js";
+import { Blob } from "./09_file.js";
+import { Request, _websocketId } from "./23_request.js";
+import { switchingProtocolsResponse } from "./23_response.js";
+import { requiredArguments } from "./helpers.js";
+import { performAsyncOp } from "./syscall.js";
+
+// https://websockets.spec.whatwg.org/#the-websocket-interface
+const CONNECTING = 0;
+const OPEN = 1;
+const CLOSING = 2;
+const CLOSED = 3;
+
+// Close code for connections that dropped without a close message.
+const ABNORMAL_CLOSURE = 1006;
+
+type WebSocketMessage =
+  | { type: "text"; data: string }
+  | { type: "binary"; data: Uint8Array }
+  | { type: "close"; code: number; reason: string };
+
+/**
+ * Options for the non-standard `new WebSocket(url, options)` form, which
+ * allows setting headers on the opening handshake (e.g. `Authorization`).
+ */
+interface WebSocketOptions {
+  protocols?: string | string[];
+  headers?: HeadersInit;
+}
+
+type AcceptedSocket = {
+  socketId: string;
+  protocol: string;
+  onClosed: () => void;
+};
+
+// Set while `upgradeWebSocket` constructs the action's end of an upgraded
+// HTTP action request.
+let acceptingSocket: AcceptedSocket | null = null;
+
+class MessageEvent extends Event {
+  readonly data: any;
+  readonly origin: string;
+
+  constructor(
+    type: string,
+    init?: EventInit & { data?: any; origin?: string },
+  ) {
+    super(type, init);
+    this.data = init?.data ?? null;
+    this.origin = init?.origin ?? "";
+  }
+}
+
+class CloseEvent extends Event {
+  readonly code: number;
+  readonly reason: string;
+  readonly wasClean: boolean;
+
+  constructor(
+    type: string,
+    init?: EventInit & { code?: number; reason?: string; wasClean?: boolean },
+  ) {
+    super(type, init);
+    this.code = init?.code ?? 0;
+    this.reason = init?.reason ?? "";
+    this.wasClean = init?.wasClean ?? false;
+  }
+}
+
+export class WebSocket extends EventTarget {
+  static readonly CONNECTING = CONNECTING;
+  static readonly OPEN = OPEN;
+  static readonly CLOSING = CLOSING;
+  static readonly CLOSED = CLOSED;
+
+  onopen: ((event: Event) => any) | null = null;
+  onmessage: ((event: MessageEvent) => any) | null = null;
+  onerror: ((event: Event) => any) | null = null;
+  onclose: ((event: CloseEvent) => any) | null = null;
+
+  private _url: string;
+  private _readyState = CONNECTING;
+  private _protocol = "";
+  private _binaryType: "blob" | "arraybuffer" = "blob";
+  private _bufferedAmount = 0;
+  private _socketId: string | null = null;
+  // Sends are chained so messages go out in the order they were sent.
+  private _sendQueue: Promise<void> = Promise.resolve();
+  private _closeRequest: { code: number; reason: string } | null = null;
+  private _onClosed: (() => void) | null = null;
+
+  constructor(
+    url: string | URL,
+    protocols?: string | string[] | WebSocketOptions,
+  ) {
+    super();
+    requiredArguments(arguments.length, 1, "Failed to construct 'WebSocket'");
+    let parsed: URL;
+    try {
+      parsed = new URL(url);
+    } catch {
+      throw new DOMException(`Invalid URL '${url}'`, "SyntaxError");
+    }
+    if (parsed.protocol === "http:") {
+      parsed.protocol = "ws:";
+    } else if (parsed.protocol === "https:") {
+      parsed.protocol = "wss:";
+    }
+    if (parsed.protocol !== "ws:" && parsed.protocol !== "wss:") {
+      throw new DOMException(
+        `The URL's scheme must be either 'ws' or 'wss'. '${parsed.protocol}' is not allowed.`,
+        "SyntaxError",
+      );
+    }
+    if (parsed.hash !== "") {
+      throw new DOMException(
+        "The URL contains a fragment identifier, which is not allowed.",
+        "SyntaxError",
+      );
+    }
+    this._url = parsed.href;
+    if (acceptingSocket !== null) {
+      this._accept(acceptingSocket);
+      return;
+    }
+
+    const options: WebSocketOptions =
+      typeof protocols === "object" && !Array.isArray(protocols)
+        ? protocols
+        : { protocols };
+    const protocolList =
+      options.protocols === undefined
+        ? []
+        : typeof options.protocols === "string"
+          ? [options.protocols]
+          : [...options.protocols];
+    if (new Set(protocolList).size !== protocolList.length) {
+      throw new DOMException(
+        "Duplicate subprotocols are not allowed.",
+        "SyntaxError",
+      );
+    }
+    const headerPairs = [...new Headers(options.headers).entries()];
+
+    void this._run(protocolList, headerPairs);
+  }
+
+  get url() {
+    return this._url;
+  }
+
+  get readyState() {
+    return this._readyState;
+  }
+
+  get protocol() {
+    return this._protocol;
+  }
+
+  get extensions() {
+    return "";
+  }
+
+  get bufferedAmount() {
+    return this._bufferedAmount;
+  }
+
+  get binaryType() {
+    return this._binaryType;
+  }
+
+  set binaryType(value: "blob" | "arraybuffer") {
+    if (value === "blob" || value === "arraybuffer") {
+      this._binaryType = value;
+    }
+  }
+
+  send(data: string | ArrayBufferLike | ArrayBufferView | Blob) {
+    requiredArguments(
+      arguments.length,
+      1,
+      "Failed to execute 'send' on 'WebSocket'",
+    );
+    if (this._readyState === CONNECTING) {
+      throw new DOMException(
+        "Failed to execute 'send' on 'WebSocket': Still in CONNECTING state.",
+        "InvalidStateError",
+      );
+    }
+    if (this._readyState !== OPEN) {
+      return;
+    }
+    let payload: string | Uint8Array;
+    let size: number;
+    if (typeof data === "string") {
+      payload = data;
+      size = new TextEncoder().encode(data).byteLength;
+    } else if (data instanceof Blob) {
+      payload = new Uint8Array(data.arrayBuffer());
+      size = data.size;
+    } else if (ArrayBuffer.isView(data)) {
+      payload = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+      size = data.byteLength;
+    } else {
+      payload = new Uint8Array(data);
+      size = data.byteLength;
+    }
+    this._bufferedAmount += size;
+    const socketId = this._socketId;
+    this._sendQueue = this._sendQueue.then(async () => {
+      try {
+        await performAsyncOp("websocket/send", socketId, payload);
+      } finally {
+        this._bufferedAmount -= size;
+      }
+    });
+    // Failed sends surface as an error event from the receive loop when the
+    // connection drops, so don't leave an unhandled rejection here.
+    this._sendQueue = this._sendQueue.catch(() => {});
+  }
+
+  close(code?: number, reason?: string) {
+    if (
+      code !== undefined &&
+      code !== 1000 &&
+      !(code >= 3000 && code <= 4999)
+    ) {
+      throw new DOMException(
+        `The close code must be either 1000, or between 3000 and 4999. ${code} is neither.`,
+        "InvalidAccessError",
+      );
+    }
+    reason = reason ?? "";
+    if (new TextEncoder().encode(reason).byteLength > 123) {
+      throw new DOMException(
+        "The close reason must not be greater than 123 UTF-8 bytes.",
+        "SyntaxError",
+      );
+    }
+    if (this._readyState === CLOSING || this._readyState === CLOSED) {
+      return;
+    }
+    const wasConnecting = this._readyState === CONNECTING;
+    this._readyState = CLOSING;
+    this._closeRequest = { code: code ?? 1000, reason };
+    if (!wasConnecting) {
+      this._sendClose();
+    }
+    // Otherwise, the close is sent once the connection is established.
+  }
+
+  private _sendClose() {
+    const socketId = this._socketId;
+    const { code, reason } = this._closeRequest!;
+    this._sendQueue = this._sendQueue
+      .then(() => performAsyncOp("websocket/close", socketId, code, reason))
+      .catch(() => {});
+  }
+
+  private async _run(protocols: string[], headerPairs: [string, string][]) {
+    try {
+      const { socketId, protocol } = await performAsyncOp("websocket/connect", {
+        url: this._url,
+        protocols,
+        headerPairs,
+      });
+      this._socketId = socketId;
+      this._protocol = protocol ?? "";
+    } catch (e: any) {
+      this._fail(e);
+      return;
+    }
+    if (this._readyState === CLOSING) {
+      // `close()` was called while connecting.
+      this._sendClose();
+    } else {
+      this._readyState = OPEN;
+      this._dispatch(new Event("open"));
+    }
+    await this._receive();
+  }
+
+  private _accept({ socketId, protocol, onClosed }: AcceptedSocket) {
+    this._socketId = socketId;
+    this._protocol = protocol;
+    this._onClosed = onClosed;
+    this._readyState = OPEN;
+    void (async () => {
+      // Open once the action has had a chance to add listeners.
+      await Promise.resolve();
+      if (this._readyState === OPEN) {
+        this._dispatch(new Event("open"));
+      }
+      await this._receive();
+    })();
+  }
+
+  private async _receive() {
+    const origin = new URL(this._url).origin;
+    for (;;) {
+      let message: WebSocketMessage;
+      try {
+        message = await performAsyncOp("websocket/receive", this._socketId);
+      } catch (e: any) {
+        this._fail(e);
+        return;
+      }
+      if (message.type === "close") {
+        this._readyState = CLOSED;
+        this._onClosed?.();
+        this._dispatch(
+          new CloseEvent("close", {
+            code: message.code,
+            reason: message.reason,
+            wasClean: message.code !== ABNORMAL_CLOSURE,
+          }),
+        );
+        return;
+      }
+      let data: any = message.data;
+      if (message.type === "binary") {
+        data =
+          this._binaryType === "arraybuffer"
+            ? message.data.buffer.slice(
+                message.data.byteOffset,
+                message.data.byteOffset + message.data.byteLength,
+              )
+            : new Blob([message.data]);
+      }
+      this._dispatch(new MessageEvent("message", { data, origin }));
+    }
+  }
+
+  private _fail(error: any) {
+    this._readyState = CLOSED;
+    this._onClosed?.();
+    console.error(`WebSocket connection to '${this._url}' failed:`, error);
+    this._dispatch(new Event("error"));
+    this._dispatch(
+      new CloseEvent("close", { code: ABNORMAL_CLOSURE, wasClean: false }),
+    );
+  }
+
+  private _dispatch(event: Event) {
+    const handler = (this as any)[`on${event.type}`];
+    if (typeof handler === "function") {
+      try {
+        handler.call(this, event);
+      } catch (e) {
+        console.error(e);
+      }
+    }
+    this.dispatchEvent(event);
+  }
+}
+
+/**
+ * Accepts the WebSocket a client asked to upgrade an HTTP action's request to.
+ * The action must respond with the returned response, and keeps running until
+ * the connection is closed.
+ */
+export const upgradeWebSocket = ({
+  request,
+  protocol,
+}: {
+  request: Request;
+  protocol?: string;
+}) => {
+  if (!(request instanceof Request) || request[_websocketId] === null) {
+    throw new TypeError(
+      "Failed to execute 'upgradeWebSocket': The request isn't a WebSocket upgrade request.",
+    );
+  }
+  const offered = (request.headers.get("sec-websocket-protocol") ?? "")
+    .split(",")
+    .map((p) => p.trim());
+  if (protocol !== undefined && !offered.includes(protocol)) {
+    throw new DOMException(
+      `The client didn't offer the subprotocol '${protocol}'.`,
+      "SyntaxError",
+    );
+  }
+  const socketId = request[_websocketId];
+  // A request can only be upgraded once.
+  request[_websocketId] = null;
+
+  // The response body stays open while the connection is, which keeps the
+  // action running.
+  let closeBody = () => {};
+  const body = new ReadableStream({
+    start(controller) {
+      closeBody = () => controller.close();
+    },
+  });
+  let socket: WebSocket;
+  acceptingSocket = {
+    socketId,
+    protocol: protocol ?? "",
+    onClosed: () => closeBody(),
+  };
+  try {
+    socket = new WebSocket(request.url);
+  } finally {
+    acceptingSocket = null;
+  }
+  const headers = new Headers();
+  if (protocol !== undefined) {
+    headers.set("sec-websocket-protocol", protocol);
+  }
+  return { socket, response: switchingProtocolsResponse(body, headers) };
+};
+
+export const setupWebSocket = (global: any) => {
+  global.WebSocket = WebSocket;
+  global.MessageEvent = MessageEvent;
+  global.CloseEvent = CloseEvent;
+};
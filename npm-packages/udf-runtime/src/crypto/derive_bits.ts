@@ -3,7 +3,13 @@
 
 import * as z from "zod";
 import { deriveBits as deriveBitsDef } from "./normalize_algorithm";
-import { CryptoKey, _handle, KEY_STORE } from "./crypto_key";
+import {
+  CryptoKey,
+  _algorithm,
+  _handle,
+  _type,
+  KEY_STORE,
+} from "./crypto_key";
 import { copyBuffer } from "./helpers";
 import { performOp } from "../syscall.js";
 import { throwNotImplementedMethodError } from "../helpers";
@@ -11,7 +17,7 @@ import { throwNotImplementedMethodError } from "../helpers";
 export async function deriveBits(
   normalizedAlgorithm: z.infer<typeof deriveBitsDef>,
   baseKey: CryptoKey,
-  length: number,
+  length: number | null,
 ) {
   switch (normalizedAlgorithm.name) {
     case "PBKDF2": {
@@ -25,6 +31,19 @@ export async function deriveBits(
         throw new DOMException("Invalid length", "OperationError");
       }
 
+      // `iterations` is an `[EnforceRange] unsigned long`.
+      const iterations = normalizedAlgorithm.iterations;
+      if (
+        !Number.isFinite(iterations) ||
+        Math.trunc(iterations) < 0 ||
+        Math.trunc(iterations) > 0xffffffff
+      ) {
+        throw new TypeError(
+          "iterations is outside the range of an unsigned long",
+        );
+      }
+      normalizedAlgorithm.iterations = Math.trunc(iterations);
+
       if (normalizedAlgorithm.iterations === 0) {
         throw new DOMException("iterations must not be zero", "OperationError");
       }
@@ -48,7 +67,51 @@ export async function deriveBits(
 
       return buf.buffer;
     }
-    case "ECDH":
+    case "ECDH": {
+      // 1.
+      if (baseKey[_type] !== "private") {
+        throw new DOMException("Invalid key type", "InvalidAccessError");
+      }
+      // 2.
+      const publicKey = normalizedAlgorithm.public;
+      // 3.
+      if (publicKey[_type] !== "public") {
+        throw new DOMException("Invalid key type", "InvalidAccessError");
+      }
+      // 4.
+      if (publicKey[_algorithm].name !== baseKey[_algorithm].name) {
+        throw new DOMException("Algorithm mismatch", "InvalidAccessError");
+      }
+      // 5.
+      const namedCurve = baseKey[_algorithm].namedCurve;
+      if (publicKey[_algorithm].namedCurve !== namedCurve) {
+        throw new DOMException("namedCurve mismatch", "InvalidAccessError");
+      }
+      // 6.
+      if (namedCurve !== "P-256" && namedCurve !== "P-384") {
+        throw new DOMException("Not implemented", "NotSupportedError");
+      }
+      const buf: Uint8Array = performOp("crypto/deriveBits", {
+        key: KEY_STORE.get(baseKey[_handle]),
+        publicKey: KEY_STORE.get(publicKey[_handle]),
+        algorithm: "ECDH",
+        namedCurve,
+        length: length ?? 0,
+      });
+      // 7-8.
+      if (length === null || length === undefined) {
+        return buf.buffer;
+      }
+      if (buf.byteLength * 8 < length) {
+        throw new DOMException("Invalid length", "OperationError");
+      }
+      const result = buf.slice(0, Math.ceil(length / 8));
+      // Zero out any bits past `length` in the last byte.
+      if (length % 8 !== 0) {
+        result[result.length - 1] &= 0xff << (8 - (length % 8));
+      }
+      return result.buffer;
+    }
     case "HKDF":
       return throwNotImplementedMethodError(
         `deriveBits with algorithm ${normalizedAlgorithm.name}`,
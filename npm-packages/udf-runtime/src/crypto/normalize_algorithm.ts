@@ -157,6 +157,11 @@ export const deriveBits = z.union([
   algorithmNameLiteralWithParams("X25519", ecdhKeyDeriveParams),
 ]);
 
+// Wrapping with algorithms that are only supported for `encrypt` (e.g. AES-GCM)
+// isn't supported since `encrypt` isn't implemented.
+const wrapKey = algorithmNameLiteralWithoutParams("AES-KW");
+const unwrapKey = wrapKey;
+
 export const normalizeAlgorithmSign = (
   input: unknown,
 ): z.infer<typeof sign> => {
@@ -201,6 +206,28 @@ export const normalizeAlgorithmDeriveBits = (
   }
 };
 
+export const normalizeAlgorithmWrapKey = (
+  input: unknown,
+): z.infer<typeof wrapKey> => {
+  const result = wrapKey.safeParse(input);
+  if (!result.success) {
+    throw new Error("Unrecognized algorithm");
+  } else {
+    return result.data;
+  }
+};
+
+export const normalizeAlgorithmUnwrapKey = (
+  input: unknown,
+): z.infer<typeof unwrapKey> => {
+  const result = unwrapKey.safeParse(input);
+  if (!result.success) {
+    throw new Error("Unrecognized algorithm");
+  } else {
+    return result.data;
+  }
+};
+
 export const normalizeAlgorithmGetKeyLength = (
   input: unknown,
 ): z.infer<typeof getKeyLength> => {
@@ -13,6 +13,7 @@ import { setupFormData } from "./21_formdata.js";
 import { requestFromConvexJson, setupRequest } from "./23_request.js";
 import { convexJsonFromResponse, setupResponse } from "./23_response.js";
 import { setupFetch } from "./26_fetch.js";
+import { setupWebSocket, upgradeWebSocket } from "./27_websocket.js";
 import { setupSourceMapping } from "./errors.js";
 import { throwUncatchableDeveloperError } from "./helpers.js";
 import { getBlob, getResponse, storeBlob, storeRequest } from "./storage.js";
@@ -51,6 +52,7 @@ export function setup(global: any) {
   setupRequest(global);
   setupResponse(global);
   setupFetch(global);
+  setupWebSocket(global);
 
   global.Convex.jsSyscall = (op: string, args: Record<string, any>) => {
     switch (op) {
@@ -58,6 +60,8 @@ export function setup(global: any) {
         return requestFromConvexJson(args as any);
       case "convexJsonFromResponse":
         return convexJsonFromResponse(args as any);
+      case "upgradeWebSocket":
+        return upgradeWebSocket(args as any);
       case "storage/storeBlob":
         return storeBlob(args as any);
       case "storage/getBlob":
@@ -1727,6 +1727,15 @@ export const fetchBlockedOnTimeouts = action(async () => {
   );
 });
 
+export const fetchForRecording = action(async () => {
+  const response = await fetch("http://localhost:4548/recorded");
+  return {
+    body: await response.text(),
+    random: Math.random(),
+    now: Date.now(),
+  };
+});
+
 export const danglingFetch = action(() => {
   // eslint-disable-next-line @typescript-eslint/no-floating-promises
   fetch("http://localhost:4546/echo_server");
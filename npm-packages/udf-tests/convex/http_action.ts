@@ -1,4 +1,4 @@
-import { httpRouter } from "convex/server";
+import { httpRouter, upgradeWebSocket } from "convex/server";
 import { imported } from "./http_no_default";
 import { api } from "./_generated/api";
 import { httpAction, query } from "./_generated/server";
@@ -108,6 +108,13 @@ const slowResponse = httpAction(async (_ctx, _request) => {
   return new Response("slow");
 });
 
+// Echoes messages until the client closes the connection.
+const websocketEcho = httpAction(async (_ctx, request) => {
+  const { socket, response } = upgradeWebSocket(request);
+  socket.onmessage = (event) => socket.send(`echo: ${event.data}`);
+  return response;
+});
+
 const http = httpRouter();
 http.route({
   method: "POST",
@@ -164,6 +171,11 @@ http.route({
   path: "/slow",
   handler: slowResponse,
 });
+http.route({
+  method: "GET",
+  path: "/websocket_echo",
+  handler: websocketEcho,
+});
 http.route({
   method: "GET",
   path: "/errorInRun",
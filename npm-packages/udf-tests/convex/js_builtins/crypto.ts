@@ -1023,7 +1023,7 @@ async function testImportRsaJwk() {
   }
 }
 
-const _jwtECKeys = {
+const jwtECKeys = {
   "256": {
     size: 256,
     algo: "ES256",
@@ -1135,62 +1135,74 @@ const _jwtECKeys = {
 //   }
 // }
 
-// async function testImportEcDhJwk() {
-//   const subtle = crypto.subtle;
-//   assert(subtle);
+async function testImportEcDhJwk() {
+  const subtle = crypto.subtle;
+  assert(subtle);
 
-//   for (const [_key, jwkData] of Object.entries(jwtECKeys)) {
-//     const { size, publicJWK, privateJWK } = jwkData;
+  for (const [_key, jwkData] of Object.entries(jwtECKeys)) {
+    const { size, publicJWK, privateJWK } = jwkData;
 
-//     // 1. Test import EcDsa
-//     const privateKeyECDH = await subtle.importKey(
-//       "jwk",
-//       {
-//         ...privateJWK,
-//         ext: true,
-//         key_ops: ["deriveBits"],
-//       },
-//       { name: "ECDH", namedCurve: privateJWK.crv },
-//       true,
-//       ["deriveBits"],
-//     );
+    // 1. Test import EcDsa
+    const privateKeyECDH = await subtle.importKey(
+      "jwk",
+      {
+        ...privateJWK,
+        ext: true,
+        key_ops: ["deriveBits"],
+      },
+      { name: "ECDH", namedCurve: privateJWK.crv },
+      true,
+      ["deriveBits"],
+    );
 
-//     // const expPrivateKeyJWK = await subtle.exportKey(
-//     //   "jwk",
-//     //   privateKeyECDH,
-//     // );
-//     // assert(equalJwk(privateJWK, expPrivateKeyJWK as JWK));
+    // const expPrivateKeyJWK = await subtle.exportKey(
+    //   "jwk",
+    //   privateKeyECDH,
+    // );
+    // assert(equalJwk(privateJWK, expPrivateKeyJWK as JWK));
 
-//     const publicKeyECDH = await subtle.importKey(
-//       "jwk",
-//       {
-//         ...publicJWK,
-//         ext: true,
-//         key_ops: [],
-//       },
-//       { name: "ECDH", namedCurve: publicJWK.crv },
-//       true,
-//       [],
-//     );
-//     // const expPublicKeyJWK = await subtle.exportKey(
-//     //   "jwk",
-//     //   publicKeyECDH,
-//     // );
-//     // assert(equalJwk(publicJWK, expPublicKeyJWK as JWK));
+    const publicKeyECDH = await subtle.importKey(
+      "jwk",
+      {
+        ...publicJWK,
+        ext: true,
+        key_ops: [],
+      },
+      { name: "ECDH", namedCurve: publicJWK.crv },
+      true,
+      [],
+    );
+    // const expPublicKeyJWK = await subtle.exportKey(
+    //   "jwk",
+    //   publicKeyECDH,
+    // );
+    // assert(equalJwk(publicJWK, expPublicKeyJWK as JWK));
 
-//     const derivedKey = await subtle.deriveBits(
-//       {
-//         name: "ECDH",
-//         public: publicKeyECDH,
-//       },
-//       privateKeyECDH,
-//       size,
-//     );
+    const derivedKey = await subtle.deriveBits(
+      {
+        name: "ECDH",
+        public: publicKeyECDH,
+      },
+      privateKeyECDH,
+      size,
+    );
 
-//     assert(derivedKey instanceof ArrayBuffer);
-//     assert.strictEqual(derivedKey.byteLength, size / 8);
-//   }
-// }
+    assert(derivedKey instanceof ArrayBuffer);
+    assert.strictEqual(derivedKey.byteLength, size / 8);
+
+    const truncated = await subtle.deriveBits(
+      {
+        name: "ECDH",
+        public: publicKeyECDH,
+      },
+      privateKeyECDH,
+      100,
+    );
+    const expected = new Uint8Array(derivedKey.slice(0, 13));
+    expected[12] &= 0xf0;
+    assert.deepEqual(new Uint8Array(truncated), expected);
+  }
+}
 
 const _ecTestKeys = [
   {
@@ -1605,6 +1617,67 @@ async function testSecretJwkBase64Url() {
 //   assertEquals(new Uint8Array(hmacKeyBytes), new Uint8Array(unwrappedKeyBytes));
 // }
 
+function hexToBytes(hex: string) {
+  return new Uint8Array(hex.match(/../g)!.map((byte) => parseInt(byte, 16)));
+}
+
+// Test vector from RFC 3394 section 4.1.
+async function testAesKwWrapKey() {
+  const kek = await crypto.subtle.importKey(
+    "raw",
+    hexToBytes("000102030405060708090A0B0C0D0E0F"),
+    "AES-KW",
+    false,
+    ["wrapKey", "unwrapKey"],
+  );
+  const hmacKey = await crypto.subtle.importKey(
+    "raw",
+    hexToBytes("00112233445566778899AABBCCDDEEFF"),
+    { name: "HMAC", hash: "SHA-256" },
+    true,
+    ["sign"],
+  );
+
+  const wrappedKey = await crypto.subtle.wrapKey("raw", hmacKey, kek, {
+    name: "AES-KW",
+  });
+  assert(wrappedKey instanceof ArrayBuffer);
+  assert.deepEqual(
+    new Uint8Array(wrappedKey),
+    hexToBytes("1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5"),
+  );
+
+  const unwrappedKey = await crypto.subtle.unwrapKey(
+    "raw",
+    wrappedKey,
+    kek,
+    "AES-KW",
+    { name: "HMAC", hash: "SHA-256" },
+    true,
+    ["sign"],
+  );
+  assert(unwrappedKey instanceof CryptoKey);
+  assert.strictEqual((unwrappedKey.algorithm as HmacKeyAlgorithm).length, 128);
+  assert.deepEqual(
+    new Uint8Array(await crypto.subtle.exportKey("raw", unwrappedKey)),
+    new Uint8Array(await crypto.subtle.exportKey("raw", hmacKey)),
+  );
+
+  const tampered = new Uint8Array(wrappedKey);
+  tampered[0] ^= 1;
+  await expect(
+    crypto.subtle.unwrapKey(
+      "raw",
+      tampered,
+      kek,
+      "AES-KW",
+      { name: "HMAC", hash: "SHA-256" },
+      true,
+      ["sign"],
+    ),
+  ).to.be.rejectedWith(/integrity check failed/);
+}
+
 // https://github.com/denoland/deno/issues/13534
 async function testAesGcmTagLength() {
   const _key = await crypto.subtle.importKey(
@@ -1871,6 +1944,57 @@ async function testDeriveBitsPBKDF2() {
   );
 }
 
+// Test vector from RFC 6070.
+async function testDeriveBitsPBKDF2Vector() {
+  const key = await crypto.subtle.importKey(
+    "raw",
+    new TextEncoder().encode("password"),
+    "PBKDF2",
+    false,
+    ["deriveBits"],
+  );
+  const bits = await crypto.subtle.deriveBits(
+    {
+      name: "PBKDF2",
+      hash: "SHA-1",
+      salt: new TextEncoder().encode("salt"),
+      iterations: 4096,
+    },
+    key,
+    20 * 8,
+  );
+  assert.deepEqual(
+    new Uint8Array(bits),
+    hexToBytes("4B007901B765489ABEAD49D926F721D065A429C1"),
+  );
+
+  // Counts recommended for password hashing should work too.
+  const slowBits = await crypto.subtle.deriveBits(
+    {
+      name: "PBKDF2",
+      hash: "SHA-256",
+      salt: new TextEncoder().encode("salt"),
+      iterations: 600_000,
+    },
+    key,
+    32 * 8,
+  );
+  assert.strictEqual(slowBits.byteLength, 32);
+
+  await expect(
+    crypto.subtle.deriveBits(
+      {
+        name: "PBKDF2",
+        hash: "SHA-256",
+        salt: new TextEncoder().encode("salt"),
+        iterations: 2 ** 32,
+      },
+      key,
+      32 * 8,
+    ),
+  ).to.be.rejectedWith(/outside the range of an unsigned long/);
+}
+
 async function testDeriveKeyPBKDF2() {
   // Test deriveKey
   const rawKey = crypto.getRandomValues(new Uint8Array(16));
@@ -1961,11 +2085,12 @@ export const test = query({
       testImportRsaJwk,
       // importing EC keys requires SecureRandom
       // testImportExportEcDsaJwk,
-      // estImportEcDhJwk,
+      testImportEcDhJwk,
       // testImportEcSpkiPkcs8,
       testAesGcmEncrypt,
       testSecretJwkBase64Url,
       // testAESWrapKey,
+      testAesKwWrapKey,
       testAesGcmTagLength,
       // ecPrivateKeyMaterialExportSpki,
       importJwkWithUse,
@@ -1981,6 +2106,7 @@ export const test = query({
       testHMACSignAlternativeSyntax,
       testInvalidAlgorithm,
       testDeriveBitsPBKDF2,
+      testDeriveBitsPBKDF2Vector,
       testDeriveKeyPBKDF2,
       testDigest,
     });
@@ -75,6 +75,13 @@ export const getTestEnvVar = actionGeneric(async () => {
   return process.env.TEST_NAME;
 });
 
+export const fetchText = actionGeneric(
+  async (_, { url }: { url: string }) => {
+    const response = await fetch(url);
+    return await response.text();
+  },
+);
+
 export const deadlock = actionGeneric(async () => {
   // Deadlock the UDF
   return await new Promise(() =>